@@ -1,16 +1,54 @@
+mod chart_recorder;
 pub mod editor;
 mod main_output;
+mod preferences_window;
 mod title_bar;
+use super::app_settings::AppSettings;
 use super::args::Args;
 use super::launcher_comm;
+use super::usage_stats::UsageSession;
+use chart_recorder::ChartRecorderArea;
 use editor::{EditorArea, NodeGraphState};
-use engine::engine_outpost::{EngineOutpostHandle, EventFilter, EventKind};
+use engine::cpu_backend::ExecutionBackend;
+use engine::engine_outpost::{
+    EngineCommand, EngineEventReceiver, EngineOutpostEvent, EngineOutpostHandle, EventFilter,
+    EventKind,
+};
+use engine::export::{self, ConsistencyReport};
 use main_output::MainOutputArea;
+use preferences_window::PreferencesWindow;
+use std::fs;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use title_bar::Command;
+use util::channels::message_channel;
+use util::diagnostics::DiagnosticsReport;
+use util::local_data;
 use util::local_data::project::{Project, ProjectId};
+use util::shutdown::ShutdownCoordinator;
 use util::ui::popup_window;
 
+/// Subsystem names used with the [ShutdownCoordinator] on exit.
+const ENGINE_SUBSYSTEM: &str = "engine";
+const PROJECT_SUBSYSTEM: &str = "project";
+
+/// How long the engine gets to stop its thread (and the media workers it owns).
+const ENGINE_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long closing (unlocking) the project gets.
+const PROJECT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often unsaved changes are written to the project's recovery journal.
+const RECOVERY_JOURNAL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many frames File > Record Execution Trace records.
+const TRACE_FRAMES: usize = 10;
+
+/// How many seconds of the output File > Check Preview/Export Consistency
+/// renders, and how many frames of it are compared.
+const CONSISTENCY_CHECK_SECS: f64 = 5.0;
+const CONSISTENCY_CHECK_SAMPLES: u64 = 10;
+
 /// This is the main area of the app.
 /// Anything you add to this please make sure it is contained within an _area file
 /// The app struct should handle as little logic as possible, and should just be responsible for rendering the different areas of the app and passing data between them
@@ -18,20 +56,51 @@ pub struct AppArea {
     title_bar: title_bar::TitleBarArea,
     editor_area: EditorArea,
     main_output: MainOutputArea,
+    chart_recorder: ChartRecorderArea,
+    /// Settings that belong to this machine instead of the project.
+    app_settings: AppSettings,
+    /// This session's usage, kept for the user's own stats.
+    usage: UsageSession,
+    preferences: PreferencesWindow,
     engine_handle: Option<EngineOutpostHandle>,
     show_exit_confirmation: bool,
     /// Flag to indicate we're exiting, prevents re-checking for changes
     is_exiting: bool,
+    /// Whether close requests are ignored for now (see
+    /// [Self::set_close_deferred]).
+    close_deferred: bool,
     startup_maximized_requested: bool,
+    /// The backend chosen for this session (see [Args::cpu_backend]).
+    execution_backend: ExecutionBackend,
+    /// Whether this session was started in safe mode (see [Args::safe_mode]).
+    safe_mode: bool,
+    /// Where the last diagnostics report or execution trace went, shown until
+    /// dismissed.
+    diagnostics_notice: Option<String>,
+    /// Tells us when an execution trace has been saved.
+    trace_events: Option<EngineEventReceiver>,
+    /// The result of the running consistency check, and where its report goes.
+    pending_consistency_check: Option<(
+        message_channel::Inbox<Result<ConsistencyReport, String>>,
+        PathBuf,
+    )>,
+    /// When unsaved changes were last written to the recovery journal.
+    last_recovery_journal: Instant,
 }
 
 impl AppArea {
-    pub fn new(cc: &eframe::CreationContext<'_>, args: Args) -> Self {
+    /// Set up the fonts and image loaders every [AppArea] needs. This only has
+    /// to be done once, since every window shares the same context.
+    pub fn setup_context(ctx: &egui::Context) {
         let mut fonts = egui::FontDefinitions::default();
         egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
-        cc.egui_ctx.set_fonts(fonts);
+        ctx.set_fonts(fonts);
+        // Needed for node example images in the help panel.
+        egui_extras::install_image_loaders(ctx);
+    }
 
-        let mut editor_area = EditorArea::new();
+    pub fn new(args: Args) -> Self {
+        let mut editor_area = EditorArea::new(!args.safe_mode);
 
         // Load project if specified in args (launcher passes ProjectId as string)
         if !args.open_project.is_empty() {
@@ -50,14 +119,36 @@ impl AppArea {
             }
         }
 
+        let mut main_output = MainOutputArea::new();
+        if args.safe_mode {
+            // Nothing runs until playback is started, so a graph that crashes
+            // the GPU can be fixed first.
+            main_output.pause();
+        }
+
         Self {
             title_bar: title_bar::TitleBarArea::new(),
             editor_area,
-            main_output: MainOutputArea::new(),
+            main_output,
+            chart_recorder: ChartRecorderArea::new(),
+            app_settings: AppSettings::load(),
+            usage: UsageSession::start(),
+            preferences: PreferencesWindow::new(),
             engine_handle: None,
             show_exit_confirmation: false,
             is_exiting: false,
+            close_deferred: false,
             startup_maximized_requested: false,
+            execution_backend: if args.cpu_backend {
+                ExecutionBackend::Cpu
+            } else {
+                ExecutionBackend::Gpu
+            },
+            safe_mode: args.safe_mode,
+            diagnostics_notice: None,
+            trace_events: None,
+            pending_consistency_check: None,
+            last_recovery_journal: Instant::now(),
         }
     }
 
@@ -84,7 +175,7 @@ impl AppArea {
 
     /// This is for things that are not in the app area but still need things in the app area.
     /// Like the save button needing access to the editor area to trigger saves.
-    fn process_pending_commands(&mut self) {
+    fn process_pending_commands(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
         let commands = self.title_bar.toolbar_mut().drain_pending();
 
         for command in commands {
@@ -93,10 +184,231 @@ impl AppArea {
                     util::debug_log_info!("Saving project");
                     self.editor_area.save_state();
                 }
+                Command::OpenProjectSettings => {
+                    self.editor_area.open_project_settings();
+                }
+                Command::OpenPreferences => {
+                    self.preferences.open();
+                }
+                Command::OpenFindReplace => {
+                    self.editor_area.open_find_replace();
+                }
+                Command::OpenExport => {
+                    self.editor_area.open_export();
+                }
+                Command::OpenScenes => {
+                    self.editor_area.open_scenes();
+                }
+                Command::OpenChartRecorder => {
+                    self.chart_recorder.open();
+                }
+                Command::OpenGraphStats => {
+                    self.editor_area.open_graph_stats();
+                }
+                Command::ExportGraphImage => {
+                    self.editor_area.open_graph_image_export();
+                }
+                Command::ToggleExecutionOrder => {
+                    self.editor_area.toggle_execution_order();
+                }
+                Command::CopyDiagnostics => {
+                    self.copy_diagnostics(ctx, frame.wgpu_render_state());
+                }
+                Command::RecordTrace => {
+                    self.record_trace();
+                }
+                Command::CheckConsistency => {
+                    self.check_consistency();
+                }
+                Command::OpenHelp => {
+                    self.editor_area.open_help();
+                }
+                Command::OpenUsageStats => {
+                    self.editor_area.open_usage_stats();
+                }
             }
         }
     }
 
+    /// Copy a [DiagnosticsReport] for this session to the clipboard and save it
+    /// to a file, so it can be attached to a bug report.
+    fn copy_diagnostics(
+        &mut self,
+        ctx: &egui::Context,
+        render_state: Option<&egui_wgpu::RenderState>,
+    ) {
+        let mut report = DiagnosticsReport::new();
+        report
+            .add("Session", "Safe Mode", self.safe_mode)
+            .add(
+                "Session",
+                "Execution Backend",
+                format!("{:?}", self.execution_backend),
+            )
+            .add(
+                "Session",
+                "Project Open",
+                self.editor_area
+                    .editor_state_context_mut()
+                    .has_open_project(),
+            );
+        match render_state {
+            Some(render_state) => engine::diagnostics::add_gpu_info(
+                &mut report,
+                &render_state.adapter,
+                &render_state.device,
+            ),
+            None => {
+                report.add("GPU", "Name", "(not available)");
+            }
+        }
+        engine::diagnostics::add_node_library_info(&mut report, self.editor_area.node_library());
+        media::diagnostics::add_media_info(&mut report);
+
+        ctx.copy_text(report.to_text());
+        self.diagnostics_notice = Some(match report.save() {
+            Ok(path) => format!(
+                "Diagnostics were copied to the clipboard and saved to:\n{}",
+                path.display()
+            ),
+            Err(e) => {
+                util::debug_log_error!("Failed to save diagnostics: {e}");
+                "Diagnostics were copied to the clipboard, but couldn't be saved to a file."
+                    .to_string()
+            }
+        });
+    }
+
+    /// Ask the engine to record the next [TRACE_FRAMES] frames to a trace file
+    /// in the diagnostics folder. [Self::check_trace_events] says where it went.
+    fn record_trace(&mut self) {
+        let Some(handle) = &self.engine_handle else {
+            self.diagnostics_notice = Some("The engine hasn't started yet.".to_string());
+            return;
+        };
+
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let path = local_data::diagnostics_path().join(format!("trace_{seconds}.json"));
+        if handle
+            .send_command(EngineCommand::RecordTrace {
+                frames: TRACE_FRAMES,
+                path,
+            })
+            .is_err()
+        {
+            util::debug_log_error!("Failed to ask the engine for an execution trace");
+        }
+    }
+
+    fn check_trace_events(&mut self) {
+        let Some(trace_events) = &self.trace_events else {
+            return;
+        };
+        for event in trace_events.drain() {
+            if let EngineOutpostEvent::TraceSaved(path) = event {
+                self.diagnostics_notice = Some(format!(
+                    "An execution trace of {TRACE_FRAMES} frames was saved to:\n{}",
+                    path.display()
+                ));
+            }
+        }
+    }
+
+    /// Render the first [CONSISTENCY_CHECK_SECS] of the output like the preview
+    /// and like an export on a thread of its own, and save what differs to the
+    /// diagnostics folder. [Self::check_consistency_result] says what it found.
+    fn check_consistency(&mut self) {
+        if self.pending_consistency_check.is_some() {
+            self.diagnostics_notice = Some("A consistency check is already running.".to_string());
+            return;
+        }
+        let Some((check, node_library)) = self
+            .editor_area
+            .consistency_check(CONSISTENCY_CHECK_SECS, CONSISTENCY_CHECK_SAMPLES)
+        else {
+            self.diagnostics_notice = Some("The graph has no output to check.".to_string());
+            return;
+        };
+
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let path = local_data::diagnostics_path().join(format!("consistency_{seconds}.json"));
+        let (inbox, outbox) = message_channel::new();
+        self.pending_consistency_check = Some((inbox, path.clone()));
+        std::thread::spawn(move || {
+            let result = export::check_consistency_headless(&check, &node_library, |_| true)
+                .map_err(|e| e.to_string())
+                .and_then(|report| {
+                    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+                    fs::write(&path, json).map_err(|e| e.to_string())?;
+                    Ok(report)
+                });
+            let _ = outbox.send(result);
+        });
+    }
+
+    fn check_consistency_result(&mut self) {
+        let Some((inbox, path)) = &self.pending_consistency_check else {
+            return;
+        };
+        let result = match inbox.check_non_blocking() {
+            Ok(Some(result)) => result,
+            Ok(None) => return,
+            Err(_) => Err("The check stopped unexpectedly.".to_string()),
+        };
+        self.diagnostics_notice = Some(match result {
+            Ok(report) => format!("{report}\n\nThe report was saved to:\n{}", path.display()),
+            Err(e) => {
+                util::debug_log_error!("Consistency check failed: {e}");
+                format!("The consistency check failed: {e}")
+            }
+        });
+        self.pending_consistency_check = None;
+    }
+
+    /// The project open in this window, if there is one.
+    pub fn project_id(&mut self) -> Option<ProjectId> {
+        self.editor_area
+            .editor_state_context_mut()
+            .project_id()
+            .cloned()
+    }
+
+    /// Whether the window's close was cancelled to ask about unsaved changes.
+    pub fn is_confirming_exit(&self) -> bool {
+        self.show_exit_confirmation
+    }
+
+    /// Ignore requests to close the window (without asking about unsaved
+    /// changes) while `deferred`, e.g. so other windows can be closed first.
+    /// Whoever defers the close is expected to cancel it.
+    pub fn set_close_deferred(&mut self, deferred: bool) {
+        self.close_deferred = deferred;
+    }
+
+    /// A stop signal (e.g. `SIGINT`) skips the unsaved changes dialog. Changes
+    /// are saved and the window is closed, which runs the normal shutdown.
+    fn handle_stop_signal(&mut self, ctx: &egui::Context, stop_requested: bool) {
+        if self.is_exiting || !stop_requested {
+            return;
+        }
+
+        util::debug_log_info!("Stop signal received, exiting");
+        if self
+            .editor_area
+            .editor_state_context_mut()
+            .has_unsaved_changes()
+        {
+            self.editor_area.save_state();
+        }
+        self.is_exiting = true;
+        self.show_exit_confirmation = false;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
     fn handle_exit(&mut self, ctx: &egui::Context) {
         if !self.is_exiting {
             // essentially, if there are unsaved changes, we want to show a confirmation dialog.
@@ -123,12 +435,13 @@ impl AppArea {
             }
         }
     }
-}
 
-impl eframe::App for AppArea {
-    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+    /// Show this window. `stop_requested` is set when a stop signal was
+    /// received (see [Self::handle_stop_signal]).
+    pub fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame, stop_requested: bool) {
         self.request_startup_maximized(ctx);
-        self.process_pending_commands();
+        self.handle_stop_signal(ctx, stop_requested);
+        self.process_pending_commands(ctx, frame);
 
         // Spawn engine and wire up per-area senders/receivers once render_state is available
         if self.engine_handle.is_none()
@@ -144,15 +457,34 @@ impl eframe::App for AppArea {
                 );
                 util::debug_log_info!("Engine spawned, setting up subscriptions");
 
+                if self.execution_backend != ExecutionBackend::default() {
+                    _ = handle
+                        .send_command(EngineCommand::SetExecutionBackend(self.execution_backend));
+                }
+                _ = handle.send_command(EngineCommand::LoadPipelineCache(
+                    render_state.adapter.get_info(),
+                ));
+
                 // Subscribe main output to a filtered event stream and provide it with a command sender
                 let output_rx = handle.subscribe(EventFilter::Only(vec![
                     EventKind::FrameReady,
                     EventKind::StreamState,
                     EventKind::FpsChanged,
                     EventKind::InfoResponse,
+                    EventKind::WorkerStalled,
+                    EventKind::WorkerPanicked,
+                    EventKind::StreamRecovered,
+                    EventKind::PlaybackPosition,
+                    EventKind::LinkStatus,
+                    EventKind::ShadersCompiling,
+                    EventKind::CachedFrames,
                 ]));
                 let output_tx = handle.command_sender();
-                self.main_output.init_engine(output_tx, output_rx);
+                self.main_output
+                    .init_engine(output_tx, handle.playback_client(), output_rx);
+                self.chart_recorder.init_engine(handle.clone());
+                self.trace_events =
+                    Some(handle.subscribe(EventFilter::Only(vec![EventKind::TraceSaved])));
 
                 util::debug_log_info!("Engine handle stored");
                 self.engine_handle = Some(handle);
@@ -160,7 +492,7 @@ impl eframe::App for AppArea {
         }
 
         // Check if the user is trying to close the window
-        if ctx.input(|i| i.viewport().close_requested()) {
+        if ctx.input(|i| i.viewport().close_requested()) && !self.close_deferred {
             // Only check for unsaved changes if we're not already exiting
             self.handle_exit(ctx);
         }
@@ -190,16 +522,54 @@ impl eframe::App for AppArea {
             });
         }
 
+        self.check_trace_events();
+        self.check_consistency_result();
+        if !self.is_exiting && self.last_recovery_journal.elapsed() >= RECOVERY_JOURNAL_INTERVAL {
+            self.editor_area.write_recovery_journal();
+            self.last_recovery_journal = Instant::now();
+        }
+        if let Some(notice) = &self.diagnostics_notice {
+            let mut dismissed = false;
+            popup_window(ctx, "Diagnostics", |ui| {
+                ui.label(notice);
+                ui.add_space(10.0);
+                dismissed = ui.button("OK").clicked();
+            });
+            if dismissed {
+                self.diagnostics_notice = None;
+            }
+        }
+
         self.show_top_bar(ctx);
+        let onboarding = self.app_settings.onboarding.clone();
+        let export = self.app_settings.export.clone();
+        let node_finder = self.app_settings.node_finder.clone();
         self.editor_area.show(
             ctx,
             frame,
             self.main_output.preview_selected_node_enabled(),
             self.main_output.has_frame(),
             self.main_output.playback_enabled(),
+            &mut self.app_settings.onboarding,
+            &mut self.app_settings.export,
+            &mut self.app_settings.node_finder,
+            &mut self.usage,
         );
+        if self.app_settings.onboarding != onboarding
+            || self.app_settings.export != export
+            || self.app_settings.node_finder != node_finder
+        {
+            self.app_settings.save();
+        }
+        self.usage.tick();
         if let Some(render_state) = frame.wgpu_render_state() {
-            self.main_output.show(ctx, render_state);
+            self.main_output
+                .show(ctx, render_state, &self.app_settings.preview);
+        }
+        self.chart_recorder
+            .show(ctx, &self.editor_area.engine_node_names());
+        if self.preferences.show(ctx, &mut self.app_settings) {
+            self.app_settings.save();
         }
 
         // Doing this instead of the recommended frame rate of the video
@@ -214,11 +584,11 @@ impl eframe::App for AppArea {
         }
     }
 
-    fn persist_egui_memory(&self) -> bool {
-        true
-    }
+    /// Stop the engine and close the project, saving unsaved changes unless
+    /// the user chose to discard them.
+    pub fn shut_down(&mut self) {
+        self.usage.save();
 
-    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         // auto save on unexpected exits
         if !self.is_exiting {
             let has_unsaved_changes = self
@@ -230,11 +600,31 @@ impl eframe::App for AppArea {
             }
         }
 
-        self.editor_area
-            .editor_state_context_mut()
-            .close_project()
-            .unwrap_or_else(|e| {
-                util::debug_log_error!("Failed to close project on exit: {}", e);
+        // The engine has to stop before the project is closed so nothing is
+        // still reading project files when they get unlocked. If the engine
+        // doesn't stop in time, the project is skipped and stays locked until
+        // the process exits.
+        let mut coordinator = ShutdownCoordinator::new();
+
+        if let Some(handle) = self.engine_handle.take() {
+            coordinator.register(ENGINE_SUBSYSTEM, ENGINE_SHUTDOWN_TIMEOUT, move || {
+                handle.shutdown();
+            });
+        }
+
+        if let Some(project) = self.editor_area.editor_state_context_mut().take_project() {
+            coordinator.register(PROJECT_SUBSYSTEM, PROJECT_SHUTDOWN_TIMEOUT, move || {
+                if let Err(e) = project.close() {
+                    util::debug_log_error!("Failed to close project on exit: {}", e);
+                }
             });
+        }
+
+        coordinator.order(ENGINE_SUBSYSTEM, PROJECT_SUBSYSTEM);
+
+        let report = coordinator.shutdown();
+        if !report.all_completed() {
+            util::debug_log_warning!("Shutdown didn't complete cleanly: {:?}", report.outcomes());
+        }
     }
 }
@@ -0,0 +1,5 @@
+mod chart_recorder_area;
+mod chart_series;
+mod data_recording;
+
+pub use chart_recorder_area::ChartRecorderArea;
@@ -0,0 +1,478 @@
+use super::chart_series::{self, ChartSeries};
+use super::data_recording::{DataRecording, PushOutcome};
+use crate::components::{LevelMeter, MeterChannel};
+use engine::engine_outpost::{
+    AnalysisKey, AnalysisReceiver, AnalysisRecordReceiver, EngineOutpostHandle,
+};
+use engine::node_graph::EngineNodeId;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// The fastest the chart takes published analysis values (about once per UI
+/// frame).
+const ANALYSIS_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How much history is kept, no matter how small the visible window is.
+const MAX_HISTORY_SECS: f64 = 120.0;
+const DEFAULT_WINDOW_SECS: f64 = 10.0;
+const PLOT_MIN_HEIGHT: f32 = 160.0;
+const METER_HEIGHT: f32 = 140.0;
+
+/// The outputs a node needs to publish to be shown as a stereo level meter
+/// (like the Audio Meter node does).
+const METER_OUTPUTS: [&str; 4] = ["Left RMS", "Left Peak", "Right RMS", "Right Peak"];
+const METER_CLIPPING_OUTPUT: &str = "Clipping";
+const PLOT_BACKGROUND: egui::Color32 = egui::Color32::from_rgb(14, 17, 19);
+const GRID_COLOR: egui::Color32 = egui::Color32::from_rgb(38, 46, 50);
+const SERIES_COLORS: [egui::Color32; 6] = [
+    egui::Color32::from_rgb(98, 200, 160),
+    egui::Color32::from_rgb(240, 170, 80),
+    egui::Color32::from_rgb(120, 160, 240),
+    egui::Color32::from_rgb(230, 100, 120),
+    egui::Color32::from_rgb(190, 140, 230),
+    egui::Color32::from_rgb(220, 220, 110),
+];
+
+/// A scrolling line chart of the values nodes publish on the engine's analysis
+/// bus (outputs flagged with `publish`), like a chart recorder.
+pub struct ChartRecorderArea {
+    engine: Option<EngineOutpostHandle>,
+    receiver: Option<AnalysisReceiver>,
+    series: Vec<ChartSeries>,
+    /// Time zero for every series.
+    start: Instant,
+    open: bool,
+    /// While paused the chart is frozen and new values are dropped.
+    paused_at: Option<f64>,
+    autoscale: bool,
+    manual_range: (f32, f32),
+    window_secs: f64,
+    /// Every frame's values while recording for export.
+    recording: Option<ActiveRecording>,
+    /// Stop recording once playback loops back to where it started, so
+    /// exactly one pass over the loop region is recorded.
+    stop_at_loop_end: bool,
+    /// Whether each level meter's clip light is on.
+    clip_lights: HashMap<EngineNodeId, bool>,
+}
+
+struct ActiveRecording {
+    receiver: AnalysisRecordReceiver,
+    data: DataRecording,
+}
+
+impl ChartRecorderArea {
+    pub fn new() -> Self {
+        Self {
+            engine: None,
+            receiver: None,
+            series: Vec::new(),
+            start: Instant::now(),
+            open: false,
+            paused_at: None,
+            autoscale: true,
+            manual_range: (0.0, 1.0),
+            window_secs: DEFAULT_WINDOW_SECS,
+            recording: None,
+            stop_at_loop_end: true,
+            clip_lights: HashMap::new(),
+        }
+    }
+
+    pub fn init_engine(&mut self, engine: EngineOutpostHandle) {
+        self.receiver = Some(engine.subscribe_analysis(ANALYSIS_INTERVAL));
+        self.engine = Some(engine);
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// `node_names` labels each series by the node it came from.
+    pub fn show(&mut self, ctx: &egui::Context, node_names: &HashMap<EngineNodeId, String>) {
+        self.drain_samples();
+        self.drain_recording(node_names);
+
+        let mut open = self.open;
+        egui::Window::new("Chart Recorder")
+            .open(&mut open)
+            .default_size(egui::vec2(520.0, 320.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                self.show_controls(ui, node_names);
+                self.show_recording_controls(ui, node_names);
+                ui.separator();
+                self.show_legend(ui, node_names);
+                ui.horizontal_top(|ui| {
+                    self.show_meters(ui, node_names);
+                    self.show_plot(ui);
+                });
+            });
+        self.open = open;
+    }
+
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    /// The right edge of the chart in seconds.
+    fn chart_end(&self) -> f64 {
+        self.paused_at.unwrap_or_else(|| self.now())
+    }
+
+    fn drain_samples(&mut self) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+
+        let samples = match receiver.take() {
+            Ok(samples) => samples,
+            Err(_) => {
+                // The engine is gone.
+                self.receiver = None;
+                return;
+            }
+        };
+
+        if self.paused_at.is_some() {
+            return;
+        }
+
+        for sample in samples {
+            let seconds = sample
+                .published_at
+                .saturating_duration_since(self.start)
+                .as_secs_f64();
+
+            let idx = match self.series.iter().position(|s| s.key == sample.key) {
+                Some(idx) => idx,
+                None => {
+                    self.series.push(ChartSeries::new(sample.key));
+                    self.series.len() - 1
+                }
+            };
+            self.series[idx].push(seconds, sample.value.as_f32());
+        }
+
+        let oldest = self.now() - MAX_HISTORY_SECS;
+        for series in &mut self.series {
+            series.prune_before(oldest);
+        }
+    }
+
+    fn drain_recording(&mut self, node_names: &HashMap<EngineNodeId, String>) {
+        let Some(recording) = &mut self.recording else {
+            return;
+        };
+
+        let frames = match recording.receiver.check_all() {
+            Ok(frames) => frames.unwrap_or_default(),
+            Err(_) => {
+                // The engine is gone, save what was recorded.
+                self.finish_recording(node_names);
+                return;
+            }
+        };
+
+        for frame in frames {
+            if recording.data.push(frame) == PushOutcome::Wrapped && self.stop_at_loop_end {
+                self.finish_recording(node_names);
+                return;
+            }
+        }
+    }
+
+    fn start_recording(&mut self) {
+        let Some(engine) = &self.engine else {
+            return;
+        };
+        self.recording = Some(ActiveRecording {
+            receiver: engine.record_analysis(),
+            data: DataRecording::new(),
+        });
+    }
+
+    /// Stop recording and ask where to export the recorded values.
+    fn finish_recording(&mut self, node_names: &HashMap<EngineNodeId, String>) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+        if recording.data.is_empty() {
+            return;
+        }
+
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("JSON", &["json"])
+            .set_file_name("analysis.csv")
+            .save_file()
+        else {
+            return;
+        };
+
+        let label = |key: &AnalysisKey| series_label(key, node_names);
+        let contents = if is_json_path(&path) {
+            recording.data.to_json(label)
+        } else {
+            recording.data.to_csv(label)
+        };
+        if let Err(e) = std::fs::write(&path, contents) {
+            util::debug_log_error!("Failed to export recording to {}: {}", path.display(), e);
+        }
+    }
+
+    fn show_recording_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        node_names: &HashMap<EngineNodeId, String>,
+    ) {
+        ui.horizontal_wrapped(|ui| {
+            match &self.recording {
+                Some(recording) => {
+                    let frames = recording.data.len();
+                    if ui.button("Stop and Export").clicked() {
+                        self.finish_recording(node_names);
+                    }
+                    ui.label(format!("Recording ({frames} frames)"));
+                }
+                None => {
+                    let button = ui.add_enabled(self.engine.is_some(), egui::Button::new("Record"));
+                    if button
+                        .on_hover_text(
+                            "Record every frame's published values to export as CSV or JSON",
+                        )
+                        .clicked()
+                    {
+                        self.start_recording();
+                    }
+                }
+            }
+
+            ui.checkbox(&mut self.stop_at_loop_end, "Stop when playback loops")
+                .on_hover_text(
+                    "Stop recording once playback jumps back to where it started, e.g. \
+                     after one pass over the loop region",
+                );
+        });
+    }
+
+    fn show_controls(&mut self, ui: &mut egui::Ui, node_names: &HashMap<EngineNodeId, String>) {
+        ui.horizontal_wrapped(|ui| {
+            let pause_label = if self.paused_at.is_some() {
+                "Resume"
+            } else {
+                "Pause"
+            };
+            if ui.button(pause_label).clicked() {
+                self.paused_at = match self.paused_at {
+                    Some(_) => None,
+                    None => Some(self.now()),
+                };
+            }
+
+            if ui.button("Clear").clicked() {
+                for series in &mut self.series {
+                    series.clear();
+                }
+            }
+
+            ui.label("Window:");
+            ui.add(
+                egui::DragValue::new(&mut self.window_secs)
+                    .range(1.0..=MAX_HISTORY_SECS)
+                    .speed(0.25)
+                    .suffix(" s"),
+            );
+
+            ui.checkbox(&mut self.autoscale, "Autoscale");
+            if !self.autoscale {
+                let (min, max) = &mut self.manual_range;
+                ui.add(egui::DragValue::new(min).speed(0.01).prefix("min "));
+                ui.add(egui::DragValue::new(max).speed(0.01).prefix("max "));
+            }
+
+            if ui.button("Export CSV").clicked() {
+                self.export_csv(node_names);
+            }
+        });
+    }
+
+    fn show_legend(&mut self, ui: &mut egui::Ui, node_names: &HashMap<EngineNodeId, String>) {
+        if self.series.is_empty() {
+            ui.label(
+                egui::RichText::new(
+                    "No published values yet. Outputs flagged with \"publish\" show up here.",
+                )
+                .weak(),
+            );
+            return;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            for (idx, series) in self.series.iter_mut().enumerate() {
+                let label = series_label(&series.key, node_names);
+                let text = match series.latest() {
+                    Some(value) => format!("{label}: {value:.3}"),
+                    None => label,
+                };
+                let color = SERIES_COLORS[idx % SERIES_COLORS.len()];
+                ui.checkbox(&mut series.visible, egui::RichText::new(text).color(color));
+            }
+        });
+    }
+
+    /// Show a level meter for every node publishing [METER_OUTPUTS].
+    fn show_meters(&mut self, ui: &mut egui::Ui, node_names: &HashMap<EngineNodeId, String>) {
+        let mut meter_nodes: Vec<EngineNodeId> = Vec::new();
+        for series in &self.series {
+            if series.key.output == METER_OUTPUTS[1] && !meter_nodes.contains(&series.key.node_id) {
+                meter_nodes.push(series.key.node_id);
+            }
+        }
+        self.clip_lights
+            .retain(|node_id, _| meter_nodes.contains(node_id));
+
+        for node_id in meter_nodes {
+            let latest = |output: &str| {
+                self.series
+                    .iter()
+                    .find(|series| series.key.node_id == node_id && series.key.output == output)
+                    .and_then(ChartSeries::latest)
+                    .unwrap_or(0.0)
+            };
+            let [left_rms, left_peak, right_rms, right_peak] = METER_OUTPUTS.map(latest);
+            let channels = [
+                MeterChannel {
+                    rms: left_rms,
+                    peak: left_peak,
+                },
+                MeterChannel {
+                    rms: right_rms,
+                    peak: right_peak,
+                },
+            ];
+            let clipping = latest(METER_CLIPPING_OUTPUT) > 0.5;
+
+            let clip_light = self.clip_lights.entry(node_id).or_default();
+            *clip_light |= clipping;
+
+            ui.vertical(|ui| {
+                ui.add(LevelMeter::new(&channels, clip_light).height(METER_HEIGHT));
+                let name = node_names.get(&node_id).map_or("Meter", String::as_str);
+                ui.label(egui::RichText::new(name).small().weak());
+            });
+        }
+    }
+
+    fn show_plot(&self, ui: &mut egui::Ui) {
+        let size = egui::vec2(
+            ui.available_width(),
+            ui.available_height().max(PLOT_MIN_HEIGHT),
+        );
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 4.0, PLOT_BACKGROUND);
+
+        let end = self.chart_end();
+        let start = end - self.window_secs;
+
+        let (min, max) = if self.autoscale {
+            chart_series::value_range(&self.series, start).unwrap_or((0.0, 1.0))
+        } else {
+            self.manual_range
+        };
+        let (min, max) = padded_range(min, max);
+
+        for i in 0..=4 {
+            let y = egui::lerp(rect.bottom()..=rect.top(), i as f32 / 4.0);
+            painter.hline(rect.x_range(), y, egui::Stroke::new(1.0, GRID_COLOR));
+        }
+
+        let to_screen = |(seconds, value): (f64, f32)| {
+            let x = ((seconds - start) / self.window_secs) as f32;
+            let y = (value - min) / (max - min);
+            egui::pos2(
+                egui::lerp(rect.left()..=rect.right(), x),
+                egui::lerp(rect.bottom()..=rect.top(), y.clamp(0.0, 1.0)),
+            )
+        };
+
+        for (idx, series) in self.series.iter().enumerate() {
+            if !series.visible {
+                continue;
+            }
+
+            let points: Vec<egui::Pos2> = series
+                .points_since(start)
+                .take_while(|&(seconds, _)| seconds <= end)
+                .map(to_screen)
+                .collect();
+            if points.len() >= 2 {
+                let color = SERIES_COLORS[idx % SERIES_COLORS.len()];
+                painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+            }
+        }
+
+        let label_font = egui::FontId::monospace(11.0);
+        let label_color = ui.visuals().weak_text_color();
+        painter.text(
+            rect.left_top() + egui::vec2(4.0, 2.0),
+            egui::Align2::LEFT_TOP,
+            format!("{max:.3}"),
+            label_font.clone(),
+            label_color,
+        );
+        painter.text(
+            rect.left_bottom() + egui::vec2(4.0, -2.0),
+            egui::Align2::LEFT_BOTTOM,
+            format!("{min:.3}"),
+            label_font.clone(),
+            label_color,
+        );
+        painter.text(
+            rect.right_bottom() + egui::vec2(-4.0, -2.0),
+            egui::Align2::RIGHT_BOTTOM,
+            format!("-{:.0} s", self.window_secs),
+            label_font,
+            label_color,
+        );
+    }
+
+    fn export_csv(&self, node_names: &HashMap<EngineNodeId, String>) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("chart.csv")
+            .save_file()
+        else {
+            return;
+        };
+
+        let start = self.chart_end() - self.window_secs;
+        let csv = chart_series::to_csv(&self.series, start, |key| series_label(key, node_names));
+        if let Err(e) = std::fs::write(&path, csv) {
+            util::debug_log_error!("Failed to export chart to {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn series_label(key: &AnalysisKey, node_names: &HashMap<EngineNodeId, String>) -> String {
+    match node_names.get(&key.node_id) {
+        Some(node_name) => format!("{node_name} / {}", key.output),
+        None => key.output.clone(),
+    }
+}
+
+fn is_json_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+/// Add a little room above and below the values so lines don't hug the edges.
+fn padded_range(min: f32, max: f32) -> (f32, f32) {
+    if max - min <= f32::EPSILON {
+        return (min - 0.5, max + 0.5);
+    }
+    let padding = (max - min) * 0.05;
+    (min - padding, max + padding)
+}
@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use engine::engine_outpost::AnalysisKey;
+
+/// The recorded history of one published value.
+pub struct ChartSeries {
+    pub key: AnalysisKey,
+    pub visible: bool,
+    /// `(seconds since the recorder started, value)`, oldest first.
+    points: VecDeque<(f64, f32)>,
+}
+
+impl ChartSeries {
+    pub fn new(key: AnalysisKey) -> Self {
+        Self {
+            key,
+            visible: true,
+            points: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, seconds: f64, value: f32) {
+        self.points.push_back((seconds, value));
+    }
+
+    /// Forget every point from before `seconds`.
+    pub fn prune_before(&mut self, seconds: f64) {
+        while self
+            .points
+            .front()
+            .is_some_and(|&(point_seconds, _)| point_seconds < seconds)
+        {
+            self.points.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// The points from `start` seconds onward.
+    pub fn points_since(&self, start: f64) -> impl Iterator<Item = (f64, f32)> + '_ {
+        let first = self.points.partition_point(|&(seconds, _)| seconds < start);
+        self.points.range(first..).copied()
+    }
+
+    pub fn latest(&self) -> Option<f32> {
+        self.points.back().map(|&(_, value)| value)
+    }
+}
+
+/// The smallest and largest value of every visible series from `start` seconds
+/// onward, or [None] if there are no points.
+pub fn value_range<'a>(
+    series: impl IntoIterator<Item = &'a ChartSeries>,
+    start: f64,
+) -> Option<(f32, f32)> {
+    series
+        .into_iter()
+        .filter(|series| series.visible)
+        .flat_map(|series| series.points_since(start))
+        .map(|(_, value)| value)
+        .filter(|value| value.is_finite())
+        .fold(None, |range, value| match range {
+            None => Some((value, value)),
+            Some((min, max)) => Some((min.min(value), max.max(value))),
+        })
+}
+
+/// Format every visible series from `start` seconds onward as CSV, one row per
+/// point. `label` names each series.
+pub fn to_csv<'a>(
+    series: impl IntoIterator<Item = &'a ChartSeries>,
+    start: f64,
+    label: impl Fn(&AnalysisKey) -> String,
+) -> String {
+    let mut csv = String::from("seconds,series,value\n");
+    for series in series.into_iter().filter(|series| series.visible) {
+        let label = csv_field(&label(&series.key));
+        for (seconds, value) in series.points_since(start) {
+            _ = writeln!(csv, "{seconds:.4},{label},{value}");
+        }
+    }
+    csv
+}
+
+/// Quote a CSV field if it needs it.
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::node_graph::EngineNodeId;
+
+    fn series_with(points: &[(f64, f32)]) -> ChartSeries {
+        let mut series = ChartSeries::new(AnalysisKey {
+            node_id: EngineNodeId::default(),
+            output: "Output".to_string(),
+        });
+        for &(seconds, value) in points {
+            series.push(seconds, value);
+        }
+        series
+    }
+
+    // --- prune_before() ---
+
+    #[test]
+    fn test_prune_before() {
+        let mut series = series_with(&[(0.0, 1.0), (1.0, 2.0), (2.0, 3.0)]);
+        series.prune_before(1.0);
+        assert_eq!(
+            series.points_since(0.0).collect::<Vec<_>>(),
+            [(1.0, 2.0), (2.0, 3.0)]
+        );
+    }
+
+    // --- value_range() ---
+
+    #[test]
+    fn test_value_range_skips_hidden_and_old_points() {
+        let a = series_with(&[(0.0, -10.0), (1.0, 2.0), (2.0, 5.0)]);
+        let mut b = series_with(&[(1.0, 100.0)]);
+        b.visible = false;
+        assert_eq!(value_range([&a, &b], 1.0), Some((2.0, 5.0)));
+        assert_eq!(value_range([&a], 3.0), None);
+    }
+
+    // --- to_csv() ---
+
+    #[test]
+    fn test_to_csv_quotes_labels() {
+        let series = series_with(&[(0.5, 1.0)]);
+        let csv = to_csv([&series], 0.0, |_| "Envelope, \"A\"".to_string());
+        assert_eq!(
+            csv,
+            "seconds,series,value\n0.5000,\"Envelope, \"\"A\"\"\",1\n"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use engine::engine_outpost::{AnalysisFrame, AnalysisKey, AnalysisValue};
+use media::fps::Fps;
+use serde_json::{Map, Value, json};
+use util::timecode::{self, FrameRate, Timecode};
+
+use super::chart_series::csv_field;
+
+/// One row of a [DataRecording].
+struct RecordedRow {
+    /// The output's frame index, or the row's index if there's no video
+    /// source to take a frame index from.
+    frame: usize,
+    fps: Option<Fps>,
+    values: Vec<(AnalysisKey, AnalysisValue)>,
+}
+
+/// Every published value over a range of frames, to be exported as CSV or JSON
+/// and analyzed alongside the rendered video.
+#[derive(Default)]
+pub struct DataRecording {
+    rows: Vec<RecordedRow>,
+    /// Every recorded key, in the order they first appeared.
+    keys: Vec<AnalysisKey>,
+}
+
+/// What happened to a frame passed to [DataRecording::push].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Added,
+    /// The frame was recorded already (e.g. values changed while paused), so
+    /// its row was replaced.
+    Replaced,
+    /// Playback jumped back to before the first recorded frame (e.g. the loop
+    /// region started over). The frame wasn't recorded.
+    Wrapped,
+}
+
+impl DataRecording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn push(&mut self, frame: AnalysisFrame) -> PushOutcome {
+        let (index, fps) = match frame.position {
+            Some((index, fps)) => (index, Some(fps)),
+            None => (self.rows.len(), None),
+        };
+
+        let outcome = match (self.rows.first(), self.rows.last()) {
+            (Some(_), Some(last)) if fps.is_some() && index == last.frame => PushOutcome::Replaced,
+            (Some(first), _) if fps.is_some() && index <= first.frame => {
+                return PushOutcome::Wrapped;
+            }
+            _ => PushOutcome::Added,
+        };
+
+        for (key, _) in &frame.values {
+            if !self.keys.contains(key) {
+                self.keys.push(key.clone());
+            }
+        }
+
+        let row = RecordedRow {
+            frame: index,
+            fps,
+            values: frame.values,
+        };
+        if outcome == PushOutcome::Replaced {
+            self.rows.pop();
+        }
+        self.rows.push(row);
+        outcome
+    }
+
+    /// Format as CSV with one row per frame and one column per published
+    /// value. Series values are separated by spaces. `label` names each
+    /// column.
+    pub fn to_csv(&self, label: impl Fn(&AnalysisKey) -> String) -> String {
+        let mut csv = String::from("frame,timecode,seconds");
+        for key in &self.keys {
+            _ = write!(csv, ",{}", csv_field(&label(key)));
+        }
+        csv.push('\n');
+
+        for row in &self.rows {
+            _ = write!(csv, "{},", row.frame);
+            if let Some((timecode, seconds)) = row_time(row) {
+                _ = write!(csv, "{timecode},{seconds:.6}");
+            } else {
+                csv.push(',');
+            }
+
+            let values: HashMap<_, _> = row.values.iter().map(|(k, v)| (k, v)).collect();
+            for key in &self.keys {
+                csv.push(',');
+                if let Some(value) = values.get(key) {
+                    csv.push_str(&csv_value(value));
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Format as pretty printed JSON: an object with a `frames` array, where
+    /// each frame has its `frame` index, `timecode`, `seconds`, and a `values`
+    /// object keyed by `label`.
+    pub fn to_json(&self, label: impl Fn(&AnalysisKey) -> String) -> String {
+        let frames: Vec<Value> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let time = row_time(row);
+                let values: Map<String, Value> = row
+                    .values
+                    .iter()
+                    .map(|(key, value)| (label(key), json_value(value)))
+                    .collect();
+                json!({
+                    "frame": row.frame,
+                    "timecode": time.map(|(timecode, _)| timecode.to_string()),
+                    "seconds": time.map(|(_, seconds)| seconds),
+                    "values": values,
+                })
+            })
+            .collect();
+
+        let fps = self
+            .rows
+            .iter()
+            .find_map(|row| row.fps)
+            .map(|fps| format!("{}/{}", fps.num(), fps.den()));
+
+        serde_json::to_string_pretty(&json!({
+            "fps": fps,
+            "series": self.keys.iter().map(&label).collect::<Vec<_>>(),
+            "frames": frames,
+        }))
+        .expect("JSON values always serialize")
+    }
+}
+
+/// The timecode and seconds of a row, or [None] if it has no FPS.
+fn row_time(row: &RecordedRow) -> Option<(Timecode, f64)> {
+    let rate = row
+        .fps
+        .and_then(|fps| FrameRate::new(fps.num(), fps.den()))?;
+    let frame = row.frame as u64;
+    Some((
+        Timecode::from_frame(frame, rate, true),
+        timecode::frame_to_time(frame, rate).as_secs_f64(),
+    ))
+}
+
+fn csv_value(value: &AnalysisValue) -> String {
+    match value {
+        AnalysisValue::Series(values) => values
+            .iter()
+            .map(f32::to_string)
+            .collect::<Vec<_>>()
+            .join(" "),
+        value => value.as_f32().to_string(),
+    }
+}
+
+fn json_value(value: &AnalysisValue) -> Value {
+    match value {
+        AnalysisValue::Bool(b) => json!(b),
+        AnalysisValue::Int(i) => json!(i),
+        AnalysisValue::Float(f) => json!(f),
+        AnalysisValue::Series(values) => json!(values),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::node_graph::EngineNodeId;
+
+    fn key(output: &str) -> AnalysisKey {
+        AnalysisKey {
+            node_id: EngineNodeId::default(),
+            output: output.to_string(),
+        }
+    }
+
+    fn frame(index: usize, values: &[(&str, f32)]) -> AnalysisFrame {
+        AnalysisFrame {
+            position: Some((index, Fps::try_from(30).unwrap())),
+            values: values
+                .iter()
+                .map(|&(output, value)| (key(output), AnalysisValue::Float(value)))
+                .collect(),
+        }
+    }
+
+    // --- push() ---
+
+    #[test]
+    fn test_push_replaces_and_wraps() {
+        let mut recording = DataRecording::new();
+        assert_eq!(recording.push(frame(10, &[("A", 1.0)])), PushOutcome::Added);
+        assert_eq!(recording.push(frame(11, &[("A", 2.0)])), PushOutcome::Added);
+        assert_eq!(
+            recording.push(frame(11, &[("A", 3.0)])),
+            PushOutcome::Replaced
+        );
+        assert_eq!(
+            recording.push(frame(10, &[("A", 4.0)])),
+            PushOutcome::Wrapped
+        );
+        assert_eq!(recording.len(), 2);
+    }
+
+    // --- to_csv() ---
+
+    #[test]
+    fn test_to_csv() {
+        let mut recording = DataRecording::new();
+        recording.push(frame(30, &[("A", 0.5)]));
+        recording.push(frame(31, &[("A", 1.0), ("B, C", 2.0)]));
+
+        let csv = recording.to_csv(|key| key.output.clone());
+        assert_eq!(
+            csv,
+            "frame,timecode,seconds,A,\"B, C\"\n\
+             30,00:00:01:00,1.000000,0.5,\n\
+             31,00:00:01:01,1.033333,1,2\n"
+        );
+    }
+}
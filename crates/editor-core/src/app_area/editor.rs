@@ -1,7 +1,20 @@
 mod editor_area;
 mod editor_state_context;
+mod execution_order_overlay;
+mod export_dialog;
+mod find_replace_dialog;
+mod graph_image_dialog;
+mod graph_stats_panel;
+mod graph_tutorial;
+mod help_browser;
 mod node_graph;
+mod scene_panel;
 mod snarl_style;
+mod usage_stats_panel;
 
 pub use editor_area::EditorArea;
-pub use node_graph::NodeGraphState;
+pub use export_dialog::{export_fps, frame_count};
+pub use node_graph::{
+    GraphSyncResult, NodeGraphState, OutputSettings, apply_global_overrides, normalize_node_inputs,
+    sync_graph,
+};
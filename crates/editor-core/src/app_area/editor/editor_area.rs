@@ -1,18 +1,63 @@
 use super::editor_state_context::EditorStateContext;
+use super::execution_order_overlay::ExecutionOrderOverlay;
+use super::export_dialog::{ExportDialog, export_fps, frame_count};
+use super::find_replace_dialog::FindReplaceDialog;
+use super::graph_image_dialog::GraphImageDialog;
+use super::graph_stats_panel::{DEFAULT_RESOLUTION, GraphStatsPanel};
+use super::graph_tutorial::{Gesture, GraphTutorial};
+use super::help_browser::HelpBrowser;
 use super::node_graph::{
-    GraphSyncResult, InputWidgetState, NodeGraphState, NodeGraphViewer, sync_graph,
+    GraphSyncResult, InputWidgetState, InteractionHints, NodeGraphState, NodeGraphViewer,
+    NodeIdMap, OutputSettings, ProjectGlobal, is_valid_global_name, recovery_summary,
+    show_help_contents, sync_graph, unused_global_name,
 };
+use super::scene_panel::ScenePanel;
 use super::snarl_style;
+use super::usage_stats_panel::UsageStatsPanel;
+use crate::app_settings::{ExportSettings, NodeFinderSettings, OnboardingSettings};
+use crate::usage_stats::UsageSession;
 
 use eframe;
 use egui;
 use egui_wgpu::wgpu;
-use engine::engine_outpost::{EngineCommand, EngineCommandSender};
+use engine::engine_outpost::{
+    EngineCommand, EngineCommandSender, EngineEventReceiver, EngineOutpostEvent, EventFilter,
+    EventKind, PendingPlayback, PlaybackClient, PlaybackRequest,
+};
+use engine::export::{self, AlphaMode, ConsistencyCheck, ExportJob};
+use engine::graph_executor::{GraphExecutor, OutputFormat};
 use engine::node::NodeLibrary;
 use engine::node_graph::{EngineNodeId, InputValue, NodeGraph};
-use std::collections::VecDeque;
+use media::fps::Fps;
+use media::fps::consts::{COMMON_FRAME_RATES, common_frame_rate_name};
+use media::frame::Dimensions;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
-use util::ui::ErrorPopup;
+use std::time::{Duration, Instant};
+use util::channels::message_channel;
+use util::ui::{ErrorPopup, popup_window};
+
+/// How often the engine is sent the graph while an input is being dragged.
+const PREVIEW_SYNC_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A graph recovered from a session that crashed, waiting for the user to
+/// restore or discard it.
+struct PendingRecovery {
+    node_graph: NodeGraphState,
+    /// What restoring it would change (see [recovery_summary]).
+    summary: Vec<String>,
+}
+
+/// Resolutions offered in the project settings window.
+const RESOLUTION_PRESETS: &[(&str, (u32, u32))] = &[
+    ("720p", (1280, 720)),
+    ("1080p", (1920, 1080)),
+    ("1440p", (2560, 1440)),
+    ("4K UHD", (3840, 2160)),
+    ("Square 1080", (1080, 1080)),
+    ("Vertical 1080p", (1080, 1920)),
+];
 
 pub struct EditorArea {
     local_node_graph: NodeGraphState,
@@ -29,12 +74,62 @@ pub struct EditorArea {
     snarl_view_generation: u64,
     apply_saved_graph_zoom_once: bool,
     last_synced_topology_hash: Option<u64>,
+    /// When the graph was last sent to the engine.
+    last_graph_sync: Option<Instant>,
     last_graph_errors: Vec<String>,
+    /// Whether the node help panel is open.
+    help_panel_open: bool,
+    /// The definition of the node the help panel is showing. Follows the
+    /// selection while the panel is open.
+    help_definition_name: Option<String>,
+    /// Whether the current graph was last estimated to be too expensive, so
+    /// the warning is only shown when that changes.
+    cost_warning_shown: bool,
+    /// A warning that the graph likely can't keep up with the project's
+    /// output settings, shown over the graph until it's dismissed.
+    cost_notice: Option<String>,
+    /// Whether the project settings window is open.
+    project_settings_open: bool,
+    /// The output format last sent to the engine.
+    last_sent_output_format: Option<OutputFormat>,
+    /// Whether Ableton Link was last enabled or disabled in the engine.
+    last_sent_link_enabled: Option<bool>,
+    /// Nodes the user asked to always evaluate, even when they're dead
+    /// branches. Not saved with the project.
+    forced_nodes: HashSet<egui_snarl::NodeId>,
+    /// The engine IDs of the forced nodes last sent to the engine.
+    last_sent_forced_nodes: Option<Vec<EngineNodeId>>,
+    find_replace: FindReplaceDialog,
+    export_dialog: ExportDialog,
+    scene_panel: ScenePanel,
+    graph_stats: GraphStatsPanel,
+    graph_image: GraphImageDialog,
+    help_browser: HelpBrowser,
+    usage_stats: UsageStatsPanel,
+    execution_order: ExecutionOrderOverlay,
+    interaction_hints: InteractionHints,
+    graph_tutorial: GraphTutorial,
+    pending_recovery: Option<PendingRecovery>,
+    /// Where to save the output of a node whose menu asked for it, once the
+    /// user picks a file.
+    pending_node_output_dialog: Option<(EngineNodeId, message_channel::Inbox<Option<PathBuf>>)>,
+    /// Tells us when a node's output has been saved.
+    engine_events: Option<EngineEventReceiver>,
+    playback_client: Option<PlaybackClient>,
+    /// Play/pause requests the engine hasn't answered yet.
+    pending_playback: PendingPlayback,
 }
 
 impl EditorArea {
-    pub fn new() -> Self {
-        let node_library = match NodeLibrary::load_all() {
+    /// Create the editor area. Nodes from the users nodes folder are only
+    /// loaded if `include_user_nodes` is set (it isn't in safe mode).
+    pub fn new(include_user_nodes: bool) -> Self {
+        let node_library = if include_user_nodes {
+            NodeLibrary::load_all()
+        } else {
+            NodeLibrary::load_built_in()
+        };
+        let node_library = match node_library {
             Ok(lib) => Arc::new(lib),
             Err(err) => {
                 util::debug_log_error!("Failed to load node library: {:?}", err);
@@ -57,7 +152,32 @@ impl EditorArea {
             snarl_view_generation: 0,
             apply_saved_graph_zoom_once: true,
             last_synced_topology_hash: None,
+            last_graph_sync: None,
             last_graph_errors: Vec::new(),
+            help_panel_open: false,
+            help_definition_name: None,
+            cost_warning_shown: false,
+            cost_notice: None,
+            project_settings_open: false,
+            last_sent_output_format: None,
+            last_sent_link_enabled: None,
+            forced_nodes: HashSet::new(),
+            last_sent_forced_nodes: None,
+            find_replace: FindReplaceDialog::new(),
+            export_dialog: ExportDialog::new(),
+            scene_panel: ScenePanel::new(),
+            graph_stats: GraphStatsPanel::new(),
+            graph_image: GraphImageDialog::new(),
+            help_browser: HelpBrowser::new(),
+            usage_stats: UsageStatsPanel::new(),
+            execution_order: ExecutionOrderOverlay::new(),
+            interaction_hints: InteractionHints::new(),
+            graph_tutorial: GraphTutorial::new(),
+            pending_recovery: None,
+            pending_node_output_dialog: None,
+            engine_events: None,
+            playback_client: None,
+            pending_playback: PendingPlayback::new(),
         }
     }
 
@@ -68,6 +188,24 @@ impl EditorArea {
             .unwrap_or(&mut self.local_node_graph)
     }
 
+    /// The name of every node that's in the engine graph, by engine node ID.
+    pub fn engine_node_names(&self) -> HashMap<EngineNodeId, String> {
+        let graph = self
+            .editor_state_context
+            .node_graph()
+            .unwrap_or(&self.local_node_graph);
+        graph
+            .engine_ids()
+            .iter()
+            .map(|(snarl_id, engine_id)| (engine_id, graph.snarl[snarl_id].definition_name.clone()))
+            .collect()
+    }
+
+    /// The nodes the editor was started with.
+    pub fn node_library(&self) -> &NodeLibrary {
+        &self.node_library
+    }
+
     /// Access to the editor state context for project operations
     pub fn editor_state_context_mut(&mut self) -> &mut EditorStateContext {
         &mut self.editor_state_context
@@ -87,6 +225,13 @@ impl EditorArea {
     ) -> engine::engine_outpost::EngineOutpostHandle {
         let handle = engine::spawn(device, queue, self.node_library.clone(), format);
         self.engine_tx = Some(handle.command_sender());
+        self.playback_client = Some(handle.playback_client());
+        self.engine_events = Some(handle.subscribe(EventFilter::Only(vec![
+            EventKind::NodeOutputSaved,
+            EventKind::GraphEditFailed,
+        ])));
+        self.graph_stats.init_engine(&handle);
+        self.execution_order.init_engine(&handle);
         handle
     }
 
@@ -107,20 +252,97 @@ impl EditorArea {
         }
 
         self.editor_state_context.set_project(project);
+
+        if let Some(mut recovered) = self.editor_state_context.recovered_node_graph() {
+            super::node_graph::normalize_node_inputs(&mut recovered, &self.node_library);
+            let summary = self
+                .editor_state_context
+                .node_graph()
+                .map(|saved| recovery_summary(saved, &recovered))
+                .unwrap_or_default();
+            util::debug_log_info!("Found unsaved changes from a crashed session");
+            self.pending_recovery = Some(PendingRecovery {
+                node_graph: recovered,
+                summary,
+            });
+        }
+    }
+
+    /// Write unsaved changes to the open project's recovery journal, so they
+    /// can be restored after a crash. Waits until any recovered changes have
+    /// been restored or discarded, so they aren't overwritten.
+    pub fn write_recovery_journal(&mut self) {
+        if self.pending_recovery.is_some() {
+            return;
+        }
+        if let Err(e) = self.editor_state_context.write_journal() {
+            util::debug_log_error!("{e}");
+        }
+    }
+
+    /// Offer to restore the changes a crashed session didn't save.
+    fn show_recovery_prompt(&mut self, ctx: &egui::Context) {
+        let Some(recovery) = &self.pending_recovery else {
+            return;
+        };
+
+        let mut restore = None;
+        popup_window(ctx, "Recover Unsaved Changes", |ui| {
+            ui.label(
+                "The editor closed unexpectedly with unsaved changes to this project. \
+                Restore them?",
+            );
+            ui.add_space(6.0);
+            if recovery.summary.is_empty() {
+                ui.label(egui::RichText::new("No graph changes (only minor details).").weak());
+            }
+            for line in &recovery.summary {
+                ui.label(format!("• {line}"));
+            }
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                if ui.button("Restore").clicked() {
+                    restore = Some(true);
+                }
+                if ui.button("Discard").clicked() {
+                    restore = Some(false);
+                }
+            });
+        });
+
+        let Some(restore) = restore else {
+            return;
+        };
+        let recovery = self.pending_recovery.take().expect("checked above");
+        if restore {
+            util::debug_log_info!("Restoring unsaved changes from a crashed session");
+            self.editor_state_context
+                .restore_node_graph(recovery.node_graph);
+            // Show the restored view instead of the current one.
+            self.snarl_view_generation = self.snarl_view_generation.wrapping_add(1);
+            self.apply_saved_graph_zoom_once = true;
+        } else {
+            self.editor_state_context.discard_journal();
+        }
     }
 
     fn set_playback_enabled(&mut self, enabled: bool) {
+        for error in self.pending_playback.take_errors() {
+            self.error_popup_queue
+                .push_back(format!("Playback couldn't be changed: {error}"));
+        }
+
         if self.playback_enabled != enabled
-            && let Some(tx) = self.engine_tx.clone()
+            && let Some(client) = &self.playback_client
         {
-            let command = if enabled {
-                EngineCommand::PlayStreams
+            let request = if enabled {
+                PlaybackRequest::Play
             } else {
-                EngineCommand::PauseStreams
+                PlaybackRequest::Pause
             };
 
-            if let Err(err) = tx.send(command) {
-                util::debug_log_warning!("Failed to queue playback command: {err}");
+            if let Err(err) = self.pending_playback.send(client, request) {
+                util::debug_log_warning!("Failed to queue playback request: {err}");
             }
         }
         self.playback_enabled = enabled;
@@ -128,7 +350,9 @@ impl EditorArea {
 }
 
 impl EditorArea {
-    /// Render the entire editor area
+    /// Render the entire editor area. Nodes added and exports finished are
+    /// counted in `usage`.
+    #[allow(clippy::too_many_arguments)]
     pub fn show(
         &mut self,
         ctx: &egui::Context,
@@ -136,13 +360,32 @@ impl EditorArea {
         preview_selected_node_enabled: bool,
         output_has_frame: bool,
         playback_enabled: bool,
+        onboarding: &mut OnboardingSettings,
+        export_settings: &mut ExportSettings,
+        node_finder: &mut NodeFinderSettings,
+        usage: &mut UsageSession,
     ) {
         // Apply playback controls handed down from AppArea
         self.set_playback_enabled(playback_enabled);
 
         // Render graph UI, then update preview/output from current selection.
-        let selected_nodes = self.show_node_graph(ctx);
+        let selected_nodes = self.show_node_graph(ctx, onboarding, node_finder, usage);
         let selected_snarl_node = self.update_output_selection(&selected_nodes);
+        self.show_help_panel(ctx, selected_snarl_node);
+        self.show_project_settings(ctx);
+        self.show_find_replace(ctx);
+        self.show_export(ctx, export_settings);
+        if let Some(frames) = self.export_dialog.take_finished_export() {
+            usage.record_export(frames);
+        }
+        self.show_scenes(ctx, &selected_nodes);
+        self.show_graph_stats(ctx);
+        self.show_graph_image(ctx);
+        self.help_browser.show(ctx, &self.node_library);
+        self.usage_stats.show(ctx, usage, &self.node_library);
+        self.sync_output_format();
+        self.sync_link_enabled();
+        self.sync_forced_nodes();
         self.update_output_from_graph(
             frame,
             selected_snarl_node,
@@ -150,6 +393,9 @@ impl EditorArea {
             output_has_frame,
         );
 
+        self.check_node_output_dialog(ctx);
+        self.check_engine_events();
+        self.show_recovery_prompt(ctx);
         self.show_any_error_popups(ctx);
     }
 
@@ -169,20 +415,40 @@ impl EditorArea {
         }
     }
 
-    fn show_node_graph(&mut self, ctx: &egui::Context) -> Vec<egui_snarl::NodeId> {
+    fn show_node_graph(
+        &mut self,
+        ctx: &egui::Context,
+        onboarding: &mut OnboardingSettings,
+        node_finder: &mut NodeFinderSettings,
+        usage: &mut UsageSession,
+    ) -> Vec<egui_snarl::NodeId> {
         let mut selected_nodes = Vec::new();
+        let mut added_nodes = Vec::new();
         let mut pending_errors = Vec::new();
+        let mut help_requested = None;
+        let mut save_output_requested = None;
         let mut input_widget_state = std::mem::take(&mut self.input_widget_state);
+        let pin_spots = self.interaction_hints.begin_frame();
+        let mut graph_response = None;
+        let mut gestures = Vec::new();
+        let mut node_rects = HashMap::new();
 
         // First, render the UI
-        egui::CentralPanel::default()
+        let panel_response = egui::CentralPanel::default()
             .frame(egui::Frame::new().fill(egui::Color32::from_rgb(16, 20, 22)))
             .show(ctx, |ui| {
-                let mut viewer =
-                    NodeGraphViewer::new(self.node_library.clone(), &mut input_widget_state);
+                let mut viewer = NodeGraphViewer::new(
+                    self.node_library.clone(),
+                    &mut input_widget_state,
+                    pin_spots,
+                );
+                viewer.set_forced_nodes(std::mem::take(&mut self.forced_nodes));
+                viewer.set_node_order(node_finder.sort_by_use, &usage.stats().node_uses);
 
+                let graph_id = egui::Id::new(("node_graph", self.snarl_view_generation));
+                viewer.set_graph_id(graph_id);
                 let snarl_widget = egui_snarl::ui::SnarlWidget::new()
-                    .id(egui::Id::new(("node_graph", self.snarl_view_generation)))
+                    .id(graph_id)
                     .style(snarl_style::snarl_style());
 
                 let apply_saved_graph_zoom_once = self.apply_saved_graph_zoom_once;
@@ -190,12 +456,30 @@ impl EditorArea {
                 {
                     let node_graph = self.active_node_graph_mut();
                     node_graph.ensure_output_sink();
+                    viewer.set_globals(node_graph.globals.clone());
                     viewer.set_initial_graph_view(
                         node_graph.graph_view,
                         node_graph.legacy_graph_view_zoom,
                         apply_saved_graph_zoom_once,
                     );
-                    snarl_widget.show(&mut node_graph.snarl, &mut viewer, ui);
+                    let response = snarl_widget.show(&mut node_graph.snarl, &mut viewer, ui);
+                    viewer.splice_dropped_node(&mut node_graph.snarl);
+                    for change in viewer.take_input_changes() {
+                        node_graph.input_history.push(change);
+                    }
+                    if response.dragged() && response.drag_delta() != egui::Vec2::ZERO {
+                        gestures.push(Gesture::Pan);
+                    }
+                    if let (Some(before), Some(after)) =
+                        (node_graph.graph_view, viewer.latest_graph_view())
+                        && before.scaling != after.scaling
+                    {
+                        gestures.push(Gesture::Zoom);
+                    }
+                    if viewer.take_connected() {
+                        gestures.push(Gesture::Connect);
+                    }
+                    graph_response = Some(response);
                     node_graph.graph_view = viewer.latest_graph_view();
                     node_graph.legacy_graph_view_zoom = None;
 
@@ -213,11 +497,55 @@ impl EditorArea {
                     self.editor_state_context.mark_edited();
                 }
 
+                let snarl = &self.active_node_graph_mut().snarl;
+                node_rects = viewer
+                    .take_node_rects()
+                    .into_iter()
+                    .filter_map(|(snarl_id, rect)| {
+                        Some((snarl.get_node(snarl_id)?.engine_node_id?, rect))
+                    })
+                    .collect();
+
                 selected_nodes = snarl_widget.get_selected_nodes(ui);
                 pending_errors = viewer.take_pending_errors();
+                help_requested = viewer.take_help_requested();
+                save_output_requested = viewer.take_save_output_requested();
+                self.forced_nodes = viewer.take_forced_nodes();
+                added_nodes = viewer.take_added_nodes();
+                node_finder.sort_by_use = viewer.sort_by_use();
             });
+        for definition_name in added_nodes {
+            usage.record_node_added(&definition_name);
+        }
 
+        if let Some(graph_response) = &graph_response {
+            self.interaction_hints.update(ctx, graph_response);
+        }
+        self.execution_order
+            .show(ctx, &node_rects, panel_response.response.rect);
+        for gesture in gestures {
+            self.graph_tutorial.observe(gesture);
+        }
+        self.graph_tutorial
+            .show(ctx, panel_response.response.rect, onboarding);
+        self.show_cost_notice(ctx, panel_response.response.rect);
+
+        if let Some(definition_name) = help_requested {
+            self.help_definition_name = Some(definition_name);
+            self.help_panel_open = true;
+        }
+        if let Some((engine_node_id, title)) = save_output_requested {
+            self.open_node_output_dialog(engine_node_id, title);
+        }
+
+        {
+            let node_graph = self.active_node_graph_mut();
+            if let Some(change) = input_widget_state.finish_abandoned_edit(ctx, &node_graph.snarl) {
+                node_graph.input_history.push(change);
+            }
+        }
         self.input_widget_state = input_widget_state;
+        self.undo_redo_input_changes(ctx);
 
         for error in pending_errors {
             self.error_popup_queue.push_back(error);
@@ -226,9 +554,20 @@ impl EditorArea {
         // Sync to engine only when graph TOPOLOGY has changed (not when moving nodes).
         let has_project = self.editor_state_context.has_open_project();
 
+        // While an input is being dragged, the engine is only sent its preview
+        // value every so often.
+        let throttled = self.input_widget_state.is_editing()
+            && self
+                .last_graph_sync
+                .is_some_and(|last| last.elapsed() < PREVIEW_SYNC_INTERVAL);
+        if throttled {
+            ctx.request_repaint_after(PREVIEW_SYNC_INTERVAL);
+        }
+
         let current_topology_hash = self.active_node_graph_mut().compute_topology_hash();
-        if self.last_synced_topology_hash != current_topology_hash {
+        if !throttled && self.last_synced_topology_hash != current_topology_hash {
             self.last_synced_topology_hash = current_topology_hash;
+            self.last_graph_sync = Some(Instant::now());
             let node_library = self.node_library.clone();
             let warnings =
                 super::node_graph::validate_midi_ports(self.active_node_graph_mut(), &node_library);
@@ -257,6 +596,36 @@ impl EditorArea {
         selected_nodes
     }
 
+    /// Undo (Ctrl+Z) or redo (Ctrl+Shift+Z) changes to node inputs, unless a
+    /// text field has the keyboard (it undoes its own edits).
+    fn undo_redo_input_changes(&mut self, ctx: &egui::Context) {
+        const UNDO: egui::KeyboardShortcut =
+            egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        const REDO: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+            egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+            egui::Key::Z,
+        );
+
+        if ctx.wants_keyboard_input() || self.input_widget_state.is_editing() {
+            return;
+        }
+        // Redo first, since its shortcut includes undo's.
+        let redo = ctx.input_mut(|i| i.consume_shortcut(&REDO));
+        let undo = !redo && ctx.input_mut(|i| i.consume_shortcut(&UNDO));
+
+        let node_graph = self.active_node_graph_mut();
+        let changed = if redo {
+            node_graph.input_history.redo(&mut node_graph.snarl)
+        } else if undo {
+            node_graph.input_history.undo(&mut node_graph.snarl)
+        } else {
+            false
+        };
+        if changed {
+            self.editor_state_context.mark_edited();
+        }
+    }
+
     fn push_graph_to_engine(&mut self) {
         let Some(tx) = self.engine_tx.clone() else {
             return;
@@ -269,25 +638,32 @@ impl EditorArea {
             GraphSyncResult::Valid {
                 graph,
                 output_node,
-                snarl_to_engine,
+                node_ids,
             } => {
                 self.last_graph_errors.clear();
-                // Write stable engine IDs back so preview-selection lookups stay valid.
-                let node_graph = self.active_node_graph_mut();
-                for (&snarl_id, &engine_id) in &snarl_to_engine {
-                    node_graph.snarl[snarl_id].engine_node_id = Some(engine_id);
+                // Write engine IDs back so preview-selection lookups stay valid.
+                self.active_node_graph_mut().set_engine_ids(node_ids);
+                // Only send what changed, so the engine doesn't start over
+                // with a new graph every time an input is edited.
+                let edits = self.engine_graph.diff(&graph);
+                self.engine_graph = graph;
+                self.check_graph_cost();
+                if !edits.is_empty() {
+                    let _ = tx.send(EngineCommand::EditGraph(edits));
                 }
-                self.engine_graph = graph.clone();
-                let _ = tx.send(EngineCommand::UpdateGraph(graph));
                 let _ = tx.send(EngineCommand::SetOutputNode(Some(output_node)));
             }
             GraphSyncResult::NoOutput => {
                 self.last_graph_errors.clear();
+                self.active_node_graph_mut()
+                    .set_engine_ids(NodeIdMap::new());
                 self.engine_graph = NodeGraph::default();
                 let _ = tx.send(EngineCommand::UpdateGraph(NodeGraph::default()));
                 let _ = tx.send(EngineCommand::SetOutputNode(None));
             }
             GraphSyncResult::Invalid(errors) => {
+                self.active_node_graph_mut()
+                    .set_engine_ids(NodeIdMap::new());
                 self.engine_graph = NodeGraph::default();
                 let _ = tx.send(EngineCommand::UpdateGraph(NodeGraph::default()));
                 let _ = tx.send(EngineCommand::SetOutputNode(None));
@@ -301,6 +677,468 @@ impl EditorArea {
         }
     }
 
+    /// Estimates the cost of the current engine graph and shows a notice if it
+    /// likely can't run at the project's output resolution and frame rate
+    /// (1080p and 30 fps when the project doesn't set them).
+    fn check_graph_cost(&mut self) {
+        let report = match GraphExecutor::estimate(&self.engine_graph, &self.node_library) {
+            Ok(report) => report,
+            Err(err) => {
+                util::debug_log_warning!("Failed to estimate graph cost: {err}");
+                return;
+            }
+        };
+
+        let output_settings = self.active_node_graph_mut().output_settings;
+        let (width, height) = output_settings.resolution.unwrap_or(DEFAULT_RESOLUTION);
+        let fps = export_fps(output_settings).as_float();
+        let too_expensive = !report.likely_sustains(fps, width, height);
+        if !too_expensive {
+            self.cost_notice = None;
+        } else if !self.cost_warning_shown {
+            let frame_time = report.estimated_frame_time(width, height);
+            let heaviest = report
+                .most_expensive()
+                .first()
+                .map(|node| format!(" The most expensive node is \"{}\".", node.definition_name))
+                .unwrap_or_default();
+            self.cost_notice = Some(format!(
+                "This graph likely can't run at {} fps at {width}x{height} \
+                (about {:.1} ms per frame, {} passes).{heaviest}",
+                (fps * 100.0).round() / 100.0,
+                frame_time.as_secs_f64() * 1000.0,
+                report.total_passes(),
+            ));
+        }
+        self.cost_warning_shown = too_expensive;
+    }
+
+    /// Shows the notice from [Self::check_graph_cost] in the top right corner
+    /// of the graph, if there is one.
+    fn show_cost_notice(&mut self, ctx: &egui::Context, graph_rect: egui::Rect) {
+        let Some(notice) = &self.cost_notice else {
+            return;
+        };
+
+        let mut dismissed = false;
+        egui::Area::new(egui::Id::new("graph_cost_notice"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::RIGHT_TOP)
+            .fixed_pos(graph_rect.right_top() + egui::vec2(-12.0, 12.0))
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(egui::Color32::from_rgb(24, 29, 31))
+                    .stroke(egui::Stroke::new(1.0, ui.visuals().warn_fg_color))
+                    .corner_radius(6.0)
+                    .inner_margin(egui::Margin::same(10))
+                    .show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.label(notice);
+                        ui.add_space(4.0);
+                        dismissed = ui.button("Dismiss").clicked();
+                    });
+            });
+        if dismissed {
+            self.cost_notice = None;
+        }
+    }
+
+    /// Shows documentation for the selected node (or the node whose help was
+    /// last requested) from its node definition.
+    fn show_help_panel(&mut self, ctx: &egui::Context, selected: Option<egui_snarl::NodeId>) {
+        if !self.help_panel_open {
+            return;
+        }
+
+        if let Some(selected) = selected {
+            let definition_name = self.active_node_graph_mut().snarl[selected]
+                .definition_name
+                .clone();
+            self.help_definition_name = Some(definition_name);
+        }
+
+        let definition = self
+            .help_definition_name
+            .as_ref()
+            .and_then(|name| self.node_library.get_definition(name));
+
+        egui::Window::new("Node Help")
+            .open(&mut self.help_panel_open)
+            .default_width(340.0)
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| match definition {
+                    Some(definition) => show_help_contents(ui, definition),
+                    None => {
+                        ui.label(egui::RichText::new("Select a node to see its help.").weak());
+                    }
+                });
+            });
+    }
+
+    pub fn open_project_settings(&mut self) {
+        self.project_settings_open = true;
+    }
+
+    /// Shows the project-level output resolution and frame rate, and whether
+    /// the project syncs its tempo over Ableton Link.
+    fn show_project_settings(&mut self, ctx: &egui::Context) {
+        if !self.project_settings_open {
+            return;
+        }
+
+        let mut settings = self.active_node_graph_mut().output_settings;
+        let mut link_enabled = self.active_node_graph_mut().link_enabled;
+        let mut globals = self.active_node_graph_mut().globals.clone();
+        let mut global_renames = Vec::new();
+        let mut open = self.project_settings_open;
+
+        egui::Window::new("Project Settings")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("project_settings_grid")
+                    .num_columns(2)
+                    .spacing([12.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label("Resolution");
+                        show_resolution_setting(ui, &mut settings.resolution);
+                        ui.end_row();
+
+                        ui.label("Frame rate");
+                        show_fps_setting(ui, &mut settings.fps);
+                        ui.end_row();
+
+                        ui.label("Ableton Link (experimental)");
+                        ui.checkbox(&mut link_enabled, "Sync tempo with the network")
+                            .on_hover_text(
+                                "Share the Tempo node's BPM and beat with DAWs and \
+                                other apps on the local network. Experimental: it \
+                                hasn't been tested with other Link apps yet, so \
+                                they may not see each other.",
+                            );
+                        ui.end_row();
+                    });
+
+                ui.add_space(4.0);
+                ui.label(
+                    egui::RichText::new(
+                        "Image and video sources are conformed to the output \
+                        resolution following their Fit Mode, and played at the \
+                        output frame rate.",
+                    )
+                    .weak(),
+                );
+
+                ui.separator();
+                show_globals_setting(ui, &mut globals, &mut global_renames);
+            });
+
+        self.project_settings_open = open;
+
+        let node_graph = self.active_node_graph_mut();
+        let globals_changed = node_graph.globals != globals;
+        for (from, to) in global_renames {
+            node_graph.rename_global(&from, &to);
+        }
+        let output_settings_changed = node_graph.output_settings != settings;
+        if output_settings_changed || node_graph.link_enabled != link_enabled || globals_changed {
+            node_graph.output_settings = settings;
+            node_graph.link_enabled = link_enabled;
+            node_graph.globals = globals;
+            self.editor_state_context.mark_edited();
+        }
+        if output_settings_changed {
+            self.check_graph_cost();
+        }
+    }
+
+    pub fn open_find_replace(&mut self) {
+        self.find_replace.open();
+    }
+
+    /// Shows the find and replace window. Replacements change input values,
+    /// so they're picked up by the next topology sync like any other edit.
+    fn show_find_replace(&mut self, ctx: &egui::Context) {
+        let node_graph = self
+            .editor_state_context
+            .node_graph_mut()
+            .unwrap_or(&mut self.local_node_graph);
+        if self
+            .find_replace
+            .show(ctx, &mut node_graph.snarl, &self.node_library)
+        {
+            self.editor_state_context.mark_edited();
+        }
+    }
+
+    pub fn open_export(&mut self) {
+        self.export_dialog.open();
+    }
+
+    /// Shows the export window. Exports render a copy of the engine graph as
+    /// it is when they start, so editing while one runs doesn't affect it.
+    fn show_export(&mut self, ctx: &egui::Context, export_settings: &mut ExportSettings) {
+        let node_graph = self.active_node_graph_mut();
+        let output_settings = node_graph.output_settings;
+        let render_passes = node_graph.render_passes();
+        self.export_dialog.show(
+            ctx,
+            &self.engine_graph,
+            self.output_source_engine_node,
+            output_settings,
+            render_passes,
+            &self.node_library,
+            &mut export_settings.presets,
+        );
+    }
+
+    /// A check of the first `duration_secs` of the graph's output, comparing
+    /// `samples` frames of it rendered like the preview and like an export,
+    /// and the nodes to run it with. Returns [None] if the graph has no output.
+    pub fn consistency_check(
+        &mut self,
+        duration_secs: f64,
+        samples: u64,
+    ) -> Option<(ConsistencyCheck, Arc<NodeLibrary>)> {
+        let output_node = self.output_source_engine_node?;
+        let output_settings = self.active_node_graph_mut().output_settings;
+        let resolution = output_settings
+            .resolution
+            .and_then(|(width, height)| Dimensions::new(width, height));
+        let fps = export_fps(output_settings);
+        let frame_count = frame_count(duration_secs, fps);
+        let check = ConsistencyCheck {
+            job: ExportJob {
+                graph: self.engine_graph.clone(),
+                output_node_id: output_node,
+                resolution,
+                fps,
+                frame_count,
+                alpha: AlphaMode::default(),
+                passes: Vec::new(),
+                watermark: None,
+                segment_frames: None,
+            },
+            preview_format: OutputFormat {
+                resolution,
+                fps: output_settings
+                    .fps
+                    .and_then(|(num, den)| Fps::from_frac(num, den).ok()),
+            },
+            samples: export::sample_frames(frame_count, samples),
+        };
+        Some((check, self.node_library.clone()))
+    }
+
+    /// Ask where to save the output of `engine_node_id` (titled `title`) as a
+    /// PNG. The engine saves it once a file is picked.
+    fn open_node_output_dialog(&mut self, engine_node_id: EngineNodeId, title: String) {
+        if self.pending_node_output_dialog.is_some() {
+            return;
+        }
+        let (inbox, outbox) = message_channel::new();
+        self.pending_node_output_dialog = Some((engine_node_id, inbox));
+        std::thread::spawn(move || {
+            let _ = outbox.send(
+                rfd::FileDialog::new()
+                    .add_filter("PNG", &["png"])
+                    .set_file_name(format!("{title}.png"))
+                    .save_file(),
+            );
+        });
+    }
+
+    fn check_node_output_dialog(&mut self, ctx: &egui::Context) {
+        let Some((engine_node_id, inbox)) = &self.pending_node_output_dialog else {
+            return;
+        };
+        match inbox.check_non_blocking() {
+            Ok(Some(Some(path))) => {
+                let command = EngineCommand::SaveNodeOutput {
+                    node_id: *engine_node_id,
+                    path,
+                };
+                if let Some(tx) = &self.engine_tx
+                    && let Err(err) = tx.send(command)
+                {
+                    util::debug_log_warning!("Failed to queue saving a node's output: {err}");
+                }
+                self.pending_node_output_dialog = None;
+            }
+            Ok(Some(None)) | Err(_) => {
+                self.pending_node_output_dialog = None;
+            }
+            Ok(None) => ctx.request_repaint(),
+        }
+    }
+
+    fn check_engine_events(&mut self) {
+        let Some(events) = &self.engine_events else {
+            return;
+        };
+        for event in events.drain() {
+            match event {
+                EngineOutpostEvent::NodeOutputSaved(Ok(path)) => {
+                    util::debug_log_info!("Saved a node's output to {}", path.display());
+                }
+                EngineOutpostEvent::NodeOutputSaved(Err(e)) => {
+                    self.error_popup_queue
+                        .push_back(format!("Failed to save the node's output: {e}"));
+                }
+                EngineOutpostEvent::GraphEditFailed(e) => {
+                    // The engine kept its old graph, so start it over with
+                    // ours.
+                    util::debug_log_warning!("The engine couldn't edit its graph: {e}");
+                    if let Some(tx) = &self.engine_tx {
+                        _ = tx.send(EngineCommand::UpdateGraph(self.engine_graph.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn open_graph_stats(&mut self) {
+        self.graph_stats.open();
+    }
+
+    /// Turn the execution order overlay on the node graph on or off.
+    pub fn toggle_execution_order(&mut self) {
+        self.execution_order.toggle(self.engine_tx.as_ref());
+    }
+
+    fn show_graph_stats(&mut self, ctx: &egui::Context) {
+        let resolution = self.active_node_graph_mut().output_settings.resolution;
+        self.graph_stats.show(
+            ctx,
+            self.engine_tx.as_ref(),
+            &self.engine_graph,
+            self.output_source_engine_node,
+            &self.node_library,
+            resolution,
+        );
+    }
+
+    pub fn open_usage_stats(&mut self) {
+        self.usage_stats.open();
+    }
+
+    pub fn open_help(&mut self) {
+        self.help_browser.open();
+    }
+
+    pub fn open_graph_image_export(&mut self) {
+        self.graph_image.open();
+    }
+
+    fn show_graph_image(&mut self, ctx: &egui::Context) {
+        let node_graph = self
+            .editor_state_context
+            .node_graph_mut()
+            .unwrap_or(&mut self.local_node_graph);
+        self.graph_image
+            .show(ctx, &node_graph.snarl, &self.node_library);
+    }
+
+    pub fn open_scenes(&mut self) {
+        self.scene_panel.open();
+    }
+
+    /// Shows the scene panel and handles its triggers and fades. Recalled
+    /// scenes change input values, which reach the engine with the next
+    /// topology sync.
+    fn show_scenes(&mut self, ctx: &egui::Context, selected_nodes: &[egui_snarl::NodeId]) {
+        let node_graph = self
+            .editor_state_context
+            .node_graph_mut()
+            .unwrap_or(&mut self.local_node_graph);
+        if self.scene_panel.show(
+            ctx,
+            &mut node_graph.snarl,
+            &mut node_graph.scenes,
+            selected_nodes,
+        ) {
+            self.editor_state_context.mark_edited();
+        }
+    }
+
+    /// Sends the project output settings to the engine whenever they change
+    /// (including when a different project is loaded).
+    fn sync_output_format(&mut self) {
+        let Some(tx) = self.engine_tx.clone() else {
+            return;
+        };
+
+        let settings = self.active_node_graph_mut().output_settings;
+        let output_format = OutputFormat {
+            resolution: settings
+                .resolution
+                .and_then(|(width, height)| Dimensions::new(width, height)),
+            fps: settings
+                .fps
+                .and_then(|(num, den)| Fps::from_frac(num, den).ok()),
+        };
+
+        if self.last_sent_output_format == Some(output_format) {
+            return;
+        }
+
+        if let Err(err) = tx.send(EngineCommand::SetOutputFormat(output_format)) {
+            util::debug_log_warning!("Failed to queue output format: {err}");
+            return;
+        }
+        self.last_sent_output_format = Some(output_format);
+    }
+
+    /// Joins or leaves the Ableton Link session whenever the project's setting
+    /// changes (including when a different project is loaded).
+    fn sync_link_enabled(&mut self) {
+        let Some(tx) = self.engine_tx.clone() else {
+            return;
+        };
+
+        let link_enabled = self.active_node_graph_mut().link_enabled;
+        if self.last_sent_link_enabled == Some(link_enabled) {
+            return;
+        }
+
+        if let Err(err) = tx.send(EngineCommand::SetLinkEnabled(link_enabled)) {
+            util::debug_log_warning!("Failed to queue Link setting: {err}");
+            return;
+        }
+        self.last_sent_link_enabled = Some(link_enabled);
+    }
+
+    /// Tells the engine which nodes to always evaluate whenever they (or
+    /// their engine IDs) change. Forced nodes that were deleted are dropped.
+    fn sync_forced_nodes(&mut self) {
+        let Some(tx) = self.engine_tx.clone() else {
+            return;
+        };
+
+        let mut forced_nodes = std::mem::take(&mut self.forced_nodes);
+        let snarl = &self.active_node_graph_mut().snarl;
+        forced_nodes.retain(|node_id| snarl.get_node(*node_id).is_some());
+        let mut engine_node_ids: Vec<EngineNodeId> = forced_nodes
+            .iter()
+            .filter_map(|node_id| snarl[*node_id].engine_node_id)
+            .collect();
+        engine_node_ids.sort();
+        self.forced_nodes = forced_nodes;
+
+        if self.last_sent_forced_nodes.as_ref() == Some(&engine_node_ids) {
+            return;
+        }
+
+        if let Err(err) = tx.send(EngineCommand::SetForcedNodes(engine_node_ids.clone())) {
+            util::debug_log_warning!("Failed to queue forced nodes: {err}");
+            return;
+        }
+        self.last_sent_forced_nodes = Some(engine_node_ids);
+    }
+
     fn update_output_selection(
         &mut self,
         selected_nodes: &[egui_snarl::NodeId],
@@ -411,3 +1249,171 @@ impl ErrorPopup<String> for EditorArea {
         &mut self.error_popup_queue
     }
 }
+
+fn show_resolution_setting(ui: &mut egui::Ui, resolution: &mut Option<(u32, u32)>) {
+    ui.vertical(|ui| {
+        let selected_text = match *resolution {
+            None => "Follow sources".to_string(),
+            Some((width, height)) => RESOLUTION_PRESETS
+                .iter()
+                .find(|(_, preset)| *preset == (width, height))
+                .map(|(name, _)| format!("{name} ({width}x{height})"))
+                .unwrap_or_else(|| format!("Custom ({width}x{height})")),
+        };
+
+        egui::ComboBox::from_id_salt("project_resolution")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(resolution, None, "Follow sources");
+                for &(name, (width, height)) in RESOLUTION_PRESETS {
+                    ui.selectable_value(
+                        resolution,
+                        Some((width, height)),
+                        format!("{name} ({width}x{height})"),
+                    );
+                }
+            });
+
+        if let Some((width, height)) = resolution {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(width).range(1..=16384).suffix(" px"));
+                ui.label("x");
+                ui.add(egui::DragValue::new(height).range(1..=16384).suffix(" px"));
+            });
+        }
+    });
+}
+
+/// Edits the project's globals. Renamed globals are added to `renames` (as
+/// their old and new names) so the inputs bound to them can follow.
+fn show_globals_setting(
+    ui: &mut egui::Ui,
+    globals: &mut Vec<ProjectGlobal>,
+    renames: &mut Vec<(String, String)>,
+) {
+    ui.label(egui::RichText::new("Globals").strong());
+    ui.label(
+        egui::RichText::new(
+            "Values inputs can be bound to (right-click an input's name), and \
+            that text inputs can mention as $name.",
+        )
+        .weak(),
+    );
+
+    let names: Vec<String> = globals.iter().map(|global| global.name.clone()).collect();
+    let mut removed = None;
+    egui::Grid::new("project_globals_grid")
+        .num_columns(4)
+        .spacing([8.0, 4.0])
+        .show(ui, |ui| {
+            for (i, global) in globals.iter_mut().enumerate() {
+                let mut name = global.name.clone();
+                let taken = names.iter().filter(|other| **other == name).count() > 1;
+                let valid = is_valid_global_name(&name) && !taken;
+                ui.horizontal(|ui| {
+                    ui.label("$");
+                    let mut text_edit = egui::TextEdit::singleline(&mut name).desired_width(120.0);
+                    if !valid {
+                        text_edit = text_edit.text_color(egui::Color32::from_rgb(220, 90, 90));
+                    }
+                    let response = ui.add(text_edit);
+                    if !valid {
+                        response.on_hover_text(if taken {
+                            "Another global has this name."
+                        } else {
+                            "Names can only have letters, digits, and underscores, and can't \
+                            start with a digit."
+                        });
+                    }
+                });
+                if name != global.name {
+                    renames.push((global.name.clone(), name.clone()));
+                    global.name = name;
+                }
+
+                let kind_name = ProjectGlobal::KINDS
+                    .iter()
+                    .find(|(_, value)| {
+                        std::mem::discriminant(value) == std::mem::discriminant(&global.value)
+                    })
+                    .map_or("", |(kind_name, _)| *kind_name);
+                egui::ComboBox::from_id_salt(("project_global_kind", i))
+                    .selected_text(kind_name)
+                    .show_ui(ui, |ui| {
+                        for (option_name, default) in ProjectGlobal::KINDS {
+                            if ui
+                                .selectable_label(option_name == kind_name, option_name)
+                                .clicked()
+                                && option_name != kind_name
+                            {
+                                global.value = default;
+                            }
+                        }
+                    });
+
+                match &mut global.value {
+                    InputValue::Float(value) => {
+                        ui.add(egui::DragValue::new(value).speed(0.1));
+                    }
+                    InputValue::Int(value) => {
+                        ui.add(egui::DragValue::new(value));
+                    }
+                    InputValue::Bool(value) => {
+                        ui.checkbox(value, "");
+                    }
+                    InputValue::Text(text) => {
+                        ui.add(egui::TextEdit::singleline(text).desired_width(120.0));
+                    }
+                    InputValue::Pixel { r, g, b, a } => {
+                        let mut rgba = [*r, *g, *b, *a];
+                        ui.color_edit_button_rgba_unmultiplied(&mut rgba);
+                        [*r, *g, *b, *a] = rgba;
+                    }
+                    _ => {
+                        ui.label("-");
+                    }
+                }
+
+                if ui.button("Delete").clicked() {
+                    removed = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+
+    if let Some(i) = removed {
+        globals.remove(i);
+    }
+    if ui.button("Add Global").clicked() {
+        globals.push(ProjectGlobal {
+            name: unused_global_name(globals),
+            value: InputValue::Float(0.0),
+        });
+    }
+}
+
+fn show_fps_setting(ui: &mut egui::Ui, fps: &mut Option<(u32, u32)>) {
+    let fps_label = |fps: Fps| {
+        common_frame_rate_name(fps)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{:.3}", fps.as_float()))
+    };
+
+    let selected_text = match fps.and_then(|(num, den)| Fps::from_frac(num, den).ok()) {
+        Some(fps) => format!("{} fps", fps_label(fps)),
+        None => "Follow sources".to_string(),
+    };
+
+    egui::ComboBox::from_id_salt("project_fps")
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(fps, None, "Follow sources");
+            for &common in COMMON_FRAME_RATES {
+                ui.selectable_value(
+                    fps,
+                    Some(common.as_frac()),
+                    format!("{} fps", fps_label(common)),
+                );
+            }
+        });
+}
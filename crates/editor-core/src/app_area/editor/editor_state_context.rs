@@ -2,7 +2,7 @@ use super::node_graph::NodeGraphState;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::time::SystemTime;
-use util::local_data::project::OpenProject;
+use util::local_data::project::{OpenProject, ProjectHeader, ProjectId};
 
 pub struct EditorStateContext {
     last_edit: Option<SystemTime>,
@@ -59,6 +59,12 @@ impl EditorStateContext {
         self.open_project.is_some()
     }
 
+    pub fn project_id(&self) -> Option<&ProjectId> {
+        self.open_project
+            .as_ref()
+            .map(|project| project.cached_info().id())
+    }
+
     pub fn mark_edited(&mut self) {
         self.last_edit = Some(SystemTime::now());
     }
@@ -92,6 +98,45 @@ impl EditorStateContext {
         }
     }
 
+    /// Write unsaved changes to the project's recovery journal (see
+    /// [OpenProject::write_journal]).
+    pub fn write_journal(&mut self) -> Result<(), String> {
+        let Some(ref mut project) = self.open_project else {
+            return Ok(());
+        };
+        project
+            .write_journal()
+            .map_err(|e| format!("Failed to write recovery journal: {}", e))
+    }
+
+    /// The graph left in the recovery journal by a session that crashed, if
+    /// it has changes that weren't saved (see [OpenProject::recovered_data]).
+    pub fn recovered_node_graph(&self) -> Option<NodeGraphState> {
+        let project = self.open_project.as_ref()?;
+        project
+            .recovered_data()
+            .inspect_err(|e| util::debug_log_error!("Failed to read recovery journal: {e}"))
+            .ok()
+            .flatten()
+    }
+
+    /// Replace the project's graph with a recovered one, as unsaved changes.
+    pub fn restore_node_graph(&mut self, node_graph: NodeGraphState) {
+        if let Some(project) = &mut self.open_project {
+            *project.data_mut() = node_graph;
+            self.mark_edited();
+        }
+    }
+
+    /// Remove the project's recovery journal without restoring it.
+    pub fn discard_journal(&mut self) {
+        if let Some(project) = &mut self.open_project
+            && let Err(e) = project.discard_journal()
+        {
+            util::debug_log_error!("Failed to discard recovery journal: {e}");
+        }
+    }
+
     /// Returns Ok(true) if data was written, Ok(false) if no changes.
     pub fn save(&mut self) -> Result<bool, String> {
         let Some(ref mut project) = self.open_project else {
@@ -110,6 +155,12 @@ impl EditorStateContext {
         Ok(result)
     }
 
+    /// Take the open project out of the context without closing it, e.g. so
+    /// it can be closed on another thread during shutdown.
+    pub fn take_project(&mut self) -> Option<OpenProject<NodeGraphState>> {
+        self.open_project.take()
+    }
+
     pub fn close_project(&mut self) -> Result<(), String> {
         if let Some(project) = self.open_project.take() {
             project
@@ -0,0 +1,140 @@
+use engine::engine_outpost::{
+    EngineCommand, EngineCommandSender, EngineEventReceiver, EngineOutpostEvent,
+    EngineOutpostHandle, EventFilter, EventKind,
+};
+use engine::node_graph::EngineNodeId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long each node stays highlighted as the overlay steps through the
+/// order.
+const STEP_DURATION: Duration = Duration::from_millis(400);
+
+const BADGE_RADIUS: f32 = 11.0;
+const BADGE_COLOR: egui::Color32 = egui::Color32::from_rgb(46, 58, 64);
+const HIGHLIGHT_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 180, 60);
+
+/// Numbers the nodes in the node graph in the order the engine ran them last
+/// frame, stepping a highlight through them in that order, so it's clear what
+/// runs before what. Nodes that didn't run aren't numbered.
+///
+/// The order comes from the engine's execution trace, which is only recorded
+/// while the overlay is on.
+pub struct ExecutionOrderOverlay {
+    enabled: bool,
+    /// Where the engine's execution orders come in.
+    events: Option<EngineEventReceiver>,
+    /// The nodes the last execution ran, in order.
+    order: Vec<EngineNodeId>,
+    /// When the overlay was turned on, which the highlight steps from.
+    enabled_at: Instant,
+}
+
+impl ExecutionOrderOverlay {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            events: None,
+            order: Vec::new(),
+            enabled_at: Instant::now(),
+        }
+    }
+
+    pub fn init_engine(&mut self, handle: &EngineOutpostHandle) {
+        self.events = Some(handle.subscribe(EventFilter::Only(vec![EventKind::ExecutionOrder])));
+    }
+
+    /// Turn the overlay on or off, and the engine's reporting along with it.
+    pub fn toggle(&mut self, engine_tx: Option<&EngineCommandSender>) {
+        self.enabled = !self.enabled;
+        self.enabled_at = Instant::now();
+        self.order.clear();
+
+        if let Some(tx) = engine_tx
+            && let Err(err) = tx.send(EngineCommand::SetExecutionOrderReporting(self.enabled))
+        {
+            util::debug_log_warning!("Failed to toggle execution order reporting: {err}");
+        }
+    }
+
+    /// Draw the overlay over the node graph, if it's on. `node_rects` are
+    /// where each node was drawn (in screen space), and nothing is drawn
+    /// outside `clip_rect`.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        node_rects: &HashMap<EngineNodeId, egui::Rect>,
+        clip_rect: egui::Rect,
+    ) {
+        // Orders are taken in while the overlay is off too, so they don't pile
+        // up.
+        let order = self.take_order();
+        if !self.enabled {
+            return;
+        }
+        if let Some(order) = order {
+            self.order = order;
+        }
+        ctx.request_repaint_after(STEP_DURATION);
+        if self.order.is_empty() {
+            return;
+        }
+
+        let step = (self.enabled_at.elapsed().as_millis() / STEP_DURATION.as_millis()) as usize
+            % self.order.len();
+        let painter = ctx
+            .layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("execution_order_overlay"),
+            ))
+            .with_clip_rect(clip_rect);
+
+        for (index, node_id) in self.order.iter().enumerate() {
+            let Some(rect) = node_rects.get(node_id) else {
+                continue;
+            };
+            let highlighted = index == step;
+            if highlighted {
+                painter.rect_stroke(
+                    *rect,
+                    6.0,
+                    egui::Stroke::new(2.0, HIGHLIGHT_COLOR),
+                    egui::StrokeKind::Outside,
+                );
+            }
+
+            let center = rect.left_top();
+            let (fill, text_color) = if highlighted {
+                (HIGHLIGHT_COLOR, egui::Color32::BLACK)
+            } else {
+                (BADGE_COLOR, egui::Color32::WHITE)
+            };
+            painter.circle(
+                center,
+                BADGE_RADIUS,
+                fill,
+                egui::Stroke::new(1.0, egui::Color32::from_black_alpha(160)),
+            );
+            painter.text(
+                center,
+                egui::Align2::CENTER_CENTER,
+                (index + 1).to_string(),
+                egui::FontId::proportional(12.0),
+                text_color,
+            );
+        }
+    }
+
+    /// The latest execution order the engine sent, if it has.
+    fn take_order(&mut self) -> Option<Vec<EngineNodeId>> {
+        let events = self.events.as_ref()?;
+        events
+            .drain()
+            .into_iter()
+            .filter_map(|event| match event {
+                EngineOutpostEvent::ExecutionOrder(order) => Some(order),
+                _ => None,
+            })
+            .last()
+    }
+}
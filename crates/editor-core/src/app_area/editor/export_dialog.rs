@@ -0,0 +1,1484 @@
+use super::node_graph::OutputSettings;
+use crate::export_presets::{self, ExportPreset};
+use engine::export::{
+    self, AlphaMode, AudioSource, EncodingOptions, EncodingSpeed, ExportJob, ExportSummary,
+    FrameSink, HardwareEncoder, ImageSequenceFormat, ImageSequenceSettings, RateControl,
+    RenderPass, VideoCodec, VideoContainer, VideoSettings, Watermark, WatermarkMark,
+    WatermarkPosition,
+};
+use engine::node::NodeLibrary;
+use engine::node_graph::{EngineNodeId, NodeGraph};
+use media::fps::Fps;
+use media::fps::consts::FPS_30;
+use media::frame::Dimensions;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use util::channels::message_channel;
+
+/// How often the window repaints while an export runs, to show its progress.
+const PROGRESS_REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The port render farm workers connect to by default.
+const DEFAULT_FARM_PORT: u16 = 47800;
+
+enum ExportProgress {
+    /// How many frames have been written.
+    Frames(u64),
+    /// How many render farm workers are connected.
+    Workers(usize),
+    /// Something about the export the user should know once it's done.
+    Note(String),
+    /// The video is being decoded back to check it.
+    Verifying,
+    /// What a video export wrote, sent before it finishes.
+    Summary(ExportSummary),
+    /// The export stopped, having written this many frames.
+    Finished(Result<u64, String>),
+}
+
+/// What an export is written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportTarget {
+    ImageSequence,
+    Video,
+}
+
+impl ExportTarget {
+    const ALL: [Self; 2] = [Self::ImageSequence, Self::Video];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::ImageSequence => "Image sequence",
+            Self::Video => "Video",
+        }
+    }
+}
+
+/// The settings of the sink an export is written with, sent to its thread.
+enum SinkSettings {
+    ImageSequence(ImageSequenceSettings),
+    Video(VideoSettings),
+    /// A video rendered by render farm workers connecting to `port`, in
+    /// chunks of `chunk_frames` frames.
+    Farm {
+        settings: VideoSettings,
+        port: u16,
+        chunk_frames: u64,
+    },
+}
+
+/// The watermark settings being edited (see [Watermark]).
+struct WatermarkForm {
+    enabled: bool,
+    use_text: bool,
+    image_path: String,
+    text: String,
+    text_size: f32,
+    text_color: [f32; 4],
+    position: WatermarkPosition,
+    opacity: f32,
+    margin: f32,
+    scale: f32,
+}
+
+impl WatermarkForm {
+    fn new() -> Self {
+        Self {
+            enabled: false,
+            use_text: false,
+            image_path: String::new(),
+            text: String::new(),
+            text_size: 32.0,
+            text_color: [1.0; 4],
+            position: WatermarkPosition::default(),
+            opacity: 0.5,
+            margin: 24.0,
+            scale: 1.0,
+        }
+    }
+
+    /// Whether there's a mark to draw, if the watermark is enabled.
+    fn is_ready(&self) -> bool {
+        !self.enabled || self.watermark().is_some()
+    }
+
+    /// The watermark to export with, or [None] if it's disabled or has
+    /// nothing to draw.
+    fn watermark(&self) -> Option<Watermark> {
+        if !self.enabled {
+            return None;
+        }
+        let mark = if self.use_text {
+            if self.text.trim().is_empty() {
+                return None;
+            }
+            WatermarkMark::Text {
+                text: self.text.clone(),
+                size: self.text_size,
+                color: self.text_color,
+            }
+        } else {
+            if self.image_path.trim().is_empty() {
+                return None;
+            }
+            WatermarkMark::Image(PathBuf::from(self.image_path.trim()))
+        };
+        Some(Watermark {
+            mark,
+            position: self.position,
+            opacity: self.opacity,
+            margin: self.margin,
+            scale: self.scale,
+        })
+    }
+}
+
+/// The encoding settings being edited (see [EncodingOptions]).
+struct EncodingForm {
+    two_pass: bool,
+    quality: u8,
+    /// The two-pass bit rate, in kilobits per second.
+    bit_rate_kbps: u64,
+    keyframes: bool,
+    keyframe_interval: u32,
+    speed: EncodingSpeed,
+}
+
+impl EncodingForm {
+    fn new() -> Self {
+        Self {
+            two_pass: false,
+            quality: VideoCodec::H264.default_quality().unwrap_or_default(),
+            bit_rate_kbps: 8000,
+            keyframes: false,
+            keyframe_interval: 60,
+            speed: EncodingSpeed::default(),
+        }
+    }
+
+    /// The options to encode `codec` with, leaving out the settings it
+    /// doesn't have.
+    fn options(&self, codec: VideoCodec, two_pass: bool, hardware: bool) -> EncodingOptions {
+        let Some(range) = codec.quality_range() else {
+            return EncodingOptions {
+                hardware,
+                ..Default::default()
+            };
+        };
+        EncodingOptions {
+            rate_control: Some(if two_pass {
+                RateControl::TwoPass {
+                    bit_rate: self.bit_rate_kbps * 1000,
+                }
+            } else {
+                RateControl::Quality(self.quality.clamp(*range.start(), *range.end()))
+            }),
+            keyframe_interval: self.keyframes.then_some(self.keyframe_interval),
+            speed: Some(self.speed),
+            hardware,
+        }
+    }
+}
+
+/// The audio settings being edited (see [AudioSource]).
+struct AudioForm {
+    enabled: bool,
+    normalize: bool,
+    /// The loudness to normalize to, in LUFS.
+    loudness_target: f64,
+    /// Whether each source is mixed in, and its gain in decibels. Sources
+    /// that aren't here yet are, at 0 dB.
+    sources: HashMap<PathBuf, (bool, f32)>,
+}
+
+impl AudioForm {
+    fn new() -> Self {
+        Self {
+            enabled: true,
+            normalize: false,
+            loudness_target: export::DEFAULT_LOUDNESS_TARGET,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// The sources out of `paths` that are mixed in.
+    fn sources(&self, paths: &[PathBuf]) -> Vec<AudioSource> {
+        paths
+            .iter()
+            .filter_map(|path| {
+                let (include, gain_db) = self.sources.get(path).copied().unwrap_or((true, 0.0));
+                include.then(|| AudioSource {
+                    path: path.clone(),
+                    gain: 10f32.powf(gain_db / 20.0),
+                })
+            })
+            .collect()
+    }
+}
+
+/// An export running on a thread of its own.
+struct RunningExport {
+    progress: message_channel::Inbox<ExportProgress>,
+    cancel: Arc<AtomicBool>,
+    frame_count: u64,
+    /// How many times the frames are rendered, which is 2 for a two-pass
+    /// video.
+    encoding_passes: u64,
+    written: u64,
+    /// The port and connected worker count of a render farm export.
+    farm: Option<(u16, usize)>,
+    verifying: bool,
+    notes: Vec<String>,
+    summary: Option<ExportSummary>,
+}
+
+/// A window for rendering the project's output to an image sequence or a
+/// video (see [engine::export]), optionally with a watermark burned in.
+/// Exports render on their own GPU device, so the editor and live output keep
+/// running meanwhile. Videos can also be rendered by render farm workers (see
+/// [export::render_distributed]), and their settings picked from or saved as
+/// [ExportPreset]s.
+pub struct ExportDialog {
+    open: bool,
+    target: ExportTarget,
+    directory: String,
+    pattern: String,
+    start_number: u64,
+    format: ImageSequenceFormat,
+    video_path: String,
+    container: VideoContainer,
+    codec: VideoCodec,
+    encoding: EncodingForm,
+    audio: AudioForm,
+    /// The resolution and frame rate of the last [ExportPreset] picked, which
+    /// videos are exported at instead of the project's.
+    resolution_override: Option<(u32, u32)>,
+    fps_override: Option<(u32, u32)>,
+    /// The name to save the settings as a preset under.
+    preset_name: String,
+    hardware: bool,
+    /// Whether this machine's hardware encoders have been checked (see
+    /// [export::hardware_encoders]), which happens in the background.
+    hardware_checked: bool,
+    pending_hardware_check: Option<message_channel::Inbox<()>>,
+    alpha: AlphaMode,
+    watermark: WatermarkForm,
+    duration_secs: f64,
+    segmented: bool,
+    segment_frames: u64,
+    farm: bool,
+    farm_port: u16,
+    /// Whether videos are decoded back to check them once they're written.
+    verify: bool,
+    running: Option<RunningExport>,
+    status: Option<String>,
+    /// The summary of the last video exported, shown under the status.
+    summary: Option<ExportSummary>,
+    pending_folder_dialog: Option<message_channel::Inbox<Option<PathBuf>>>,
+    pending_video_dialog: Option<message_channel::Inbox<Option<PathBuf>>>,
+    pending_watermark_dialog: Option<message_channel::Inbox<Option<PathBuf>>>,
+    /// How many frames the last export wrote, until it's taken.
+    finished_export: Option<u64>,
+}
+
+impl ExportDialog {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            target: ExportTarget::ImageSequence,
+            directory: String::new(),
+            pattern: "frame_####".to_string(),
+            start_number: 1,
+            format: ImageSequenceFormat::Png,
+            video_path: String::new(),
+            container: VideoContainer::Mp4,
+            codec: VideoCodec::H264,
+            encoding: EncodingForm::new(),
+            audio: AudioForm::new(),
+            resolution_override: None,
+            fps_override: None,
+            preset_name: String::new(),
+            hardware: false,
+            hardware_checked: false,
+            pending_hardware_check: None,
+            alpha: AlphaMode::default(),
+            watermark: WatermarkForm::new(),
+            duration_secs: 10.0,
+            segmented: false,
+            segment_frames: 500,
+            farm: false,
+            farm_port: DEFAULT_FARM_PORT,
+            verify: false,
+            running: None,
+            status: None,
+            summary: None,
+            pending_folder_dialog: None,
+            pending_video_dialog: None,
+            pending_watermark_dialog: None,
+            finished_export: None,
+        }
+    }
+
+    /// How many frames an export that just finished wrote.
+    pub fn take_finished_export(&mut self) -> Option<u64> {
+        self.finished_export.take()
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        if !self.hardware_checked && self.pending_hardware_check.is_none() {
+            let (inbox, outbox) = message_channel::new();
+            self.pending_hardware_check = Some(inbox);
+            std::thread::spawn(move || {
+                for codec in VideoCodec::ALL {
+                    export::hardware_encoders(codec);
+                }
+                let _ = outbox.send(());
+            });
+        }
+    }
+
+    /// Show the window if it's open. `graph` and `output_node` are what's
+    /// exported, at the project's `output_settings`, along with
+    /// `render_passes`.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        graph: &NodeGraph,
+        output_node: Option<EngineNodeId>,
+        output_settings: OutputSettings,
+        render_passes: Vec<RenderPass>,
+        node_library: &Arc<NodeLibrary>,
+        user_presets: &mut Vec<ExportPreset>,
+    ) {
+        self.check_progress(ctx);
+        if !self.open {
+            return;
+        }
+        self.check_folder_dialog(ctx);
+        self.check_video_dialog(ctx);
+        self.check_watermark_dialog(ctx);
+        self.check_hardware_check(ctx);
+
+        let output_settings = self.output_settings(output_settings);
+        let fps = export_fps(output_settings);
+        let audio_paths = export::audio_source_paths(graph, node_library);
+        let mut open = self.open;
+        egui::Window::new("Export")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.add_enabled_ui(self.running.is_none(), |ui| {
+                    self.show_settings(ui, fps, &audio_paths, user_presets);
+                });
+                if !render_passes.is_empty() {
+                    let names: Vec<&str> = render_passes
+                        .iter()
+                        .map(|pass| pass.name.as_str())
+                        .collect();
+                    let hover_text = match self.target {
+                        ExportTarget::ImageSequence => {
+                            "Each pass is written to a folder named after it"
+                        }
+                        ExportTarget::Video => {
+                            "Each pass is written to a video with its name added to the file's"
+                        }
+                    };
+                    ui.label(format!("Render passes: {}", names.join(", ")))
+                        .on_hover_text(hover_text);
+                }
+                ui.separator();
+
+                match &self.running {
+                    Some(running) => {
+                        let frame_count = running.frame_count.max(1);
+                        let fraction =
+                            running.written as f32 / (frame_count * running.encoding_passes) as f32;
+                        let mut text = if running.encoding_passes > 1 {
+                            let pass =
+                                (running.written / frame_count + 1).min(running.encoding_passes);
+                            format!(
+                                "Pass {pass} of {}: frame {} of {}",
+                                running.encoding_passes,
+                                running.written - (pass - 1) * frame_count,
+                                running.frame_count
+                            )
+                        } else {
+                            format!("Frame {} of {}", running.written, running.frame_count)
+                        };
+                        if let Some((port, workers)) = running.farm {
+                            text += &format!(", {workers} worker(s) connected on port {port}");
+                        }
+                        if running.verifying {
+                            text = "Verifying the video…".to_string();
+                        }
+                        ui.add(egui::ProgressBar::new(fraction).text(text));
+                        if ui.button("Cancel").clicked() {
+                            running.cancel.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    None => {
+                        let destination_missing = match self.target {
+                            ExportTarget::ImageSequence => self.directory.trim().is_empty(),
+                            ExportTarget::Video => self.video_path.trim().is_empty(),
+                        };
+                        let blocker = if output_node.is_none() {
+                            Some("The graph has no output to export.")
+                        } else if destination_missing {
+                            Some(match self.target {
+                                ExportTarget::ImageSequence => "Choose a folder to export to.",
+                                ExportTarget::Video => "Choose a file to export to.",
+                            })
+                        } else if !self.watermark.is_ready() {
+                            Some("Choose an image or enter text for the watermark.")
+                        } else {
+                            None
+                        };
+                        let mut response =
+                            ui.add_enabled(blocker.is_none(), egui::Button::new("Export"));
+                        if let Some(blocker) = blocker {
+                            response = response.on_disabled_hover_text(blocker);
+                        }
+                        if response.clicked()
+                            && let Some(output_node) = output_node
+                        {
+                            self.start(
+                                graph,
+                                output_node,
+                                output_settings,
+                                fps,
+                                render_passes.clone(),
+                                node_library,
+                            );
+                        }
+                    }
+                }
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+                if let Some(summary) = &self.summary {
+                    egui::CollapsingHeader::new("Summary")
+                        .id_salt("export_summary")
+                        .show(ui, |ui| {
+                            ui.label(summary.to_string());
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "Saved to {}",
+                                    self.video_settings().summary_path().display()
+                                ))
+                                .weak(),
+                            );
+                        });
+                }
+            });
+        self.open = open;
+    }
+
+    fn show_settings(
+        &mut self,
+        ui: &mut egui::Ui,
+        fps: Fps,
+        audio_paths: &[PathBuf],
+        user_presets: &mut Vec<ExportPreset>,
+    ) {
+        egui::Grid::new("export_settings_grid")
+            .num_columns(2)
+            .spacing([12.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("Export as");
+                egui::ComboBox::from_id_salt("export_target")
+                    .selected_text(self.target.name())
+                    .show_ui(ui, |ui| {
+                        for target in ExportTarget::ALL {
+                            ui.selectable_value(&mut self.target, target, target.name());
+                        }
+                    });
+                ui.end_row();
+
+                match self.target {
+                    ExportTarget::ImageSequence => self.show_sequence_settings(ui),
+                    ExportTarget::Video => {
+                        self.show_preset_settings(ui, user_presets);
+                        self.show_video_settings(ui);
+                        self.show_audio_settings(ui, audio_paths);
+                    }
+                }
+
+                ui.label("Alpha");
+                ui.add_enabled_ui(self.alpha_supported(), |ui| {
+                    egui::ComboBox::from_id_salt("export_alpha")
+                        .selected_text(self.alpha().name())
+                        .show_ui(ui, |ui| {
+                            for alpha in AlphaMode::ALL {
+                                ui.selectable_value(&mut self.alpha, alpha, alpha.name());
+                            }
+                        });
+                })
+                .response
+                .on_disabled_hover_text(format!(
+                    "{} video can't have an alpha channel",
+                    self.codec.name()
+                ));
+                ui.end_row();
+
+                self.show_watermark_settings(ui);
+
+                ui.label("Duration");
+                ui.add(
+                    egui::DragValue::new(&mut self.duration_secs)
+                        .range(0.0..=3600.0)
+                        .speed(0.1)
+                        .suffix(" s"),
+                );
+                ui.end_row();
+
+                if self.is_farm() {
+                    ui.label("Chunks");
+                    ui.add(
+                        egui::DragValue::new(&mut self.segment_frames)
+                            .range(1..=100_000)
+                            .suffix(" frames"),
+                    )
+                    .on_hover_text("How many frames each worker renders at a time");
+                } else {
+                    ui.label("Segments");
+                    let two_pass = self.is_two_pass();
+                    ui.horizontal(|ui| {
+                        ui.add_enabled_ui(!two_pass, |ui| {
+                            ui.checkbox(&mut self.segmented, "Write in segments of")
+                                .on_hover_text(
+                                    "If the export fails, the segments it finished are kept, \
+                                     and exporting again with the same settings picks up after \
+                                     them.",
+                                );
+                        })
+                        .response
+                        .on_disabled_hover_text("Two-pass encodes can't be written in segments");
+                        ui.add_enabled(
+                            self.segmented && !two_pass,
+                            egui::DragValue::new(&mut self.segment_frames)
+                                .range(1..=100_000)
+                                .suffix(" frames"),
+                        );
+                    });
+                }
+                ui.end_row();
+
+                if self.target == ExportTarget::Video {
+                    ui.label("Verify");
+                    ui.add_enabled_ui(!self.farm, |ui| {
+                        ui.checkbox(&mut self.verify, "Decode the video afterwards")
+                            .on_hover_text(
+                                "Checks that every frame of the finished video decodes, which \
+                                 takes a while for long videos",
+                            );
+                    })
+                    .response
+                    .on_disabled_hover_text("Render farm exports can't be verified yet");
+                    ui.end_row();
+                }
+            });
+
+        let destination = match self.target {
+            ExportTarget::ImageSequence => {
+                format!("starting with {}", self.sequence_settings().file_name(0))
+            }
+            ExportTarget::Video => format!(
+                "to {}",
+                self.video_settings()
+                    .file_path()
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            ),
+        };
+        ui.label(
+            egui::RichText::new(format!(
+                "{} frames at {:.3} FPS, {destination}",
+                frame_count(self.duration_secs, fps),
+                fps.as_float(),
+            ))
+            .weak(),
+        );
+    }
+
+    fn show_sequence_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label("Folder");
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.directory);
+            if ui.button("Browse…").clicked() && self.pending_folder_dialog.is_none() {
+                let (inbox, outbox) = message_channel::new();
+                self.pending_folder_dialog = Some(inbox);
+                std::thread::spawn(move || {
+                    let _ = outbox.send(rfd::FileDialog::new().pick_folder());
+                });
+            }
+        });
+        ui.end_row();
+
+        ui.label("File names");
+        ui.text_edit_singleline(&mut self.pattern).on_hover_text(
+            "Each run of # is replaced with the frame number, padded to that many digits",
+        );
+        ui.end_row();
+
+        ui.label("First number");
+        ui.add(egui::DragValue::new(&mut self.start_number));
+        ui.end_row();
+
+        ui.label("Format");
+        egui::ComboBox::from_id_salt("export_format")
+            .selected_text(self.format.name())
+            .show_ui(ui, |ui| {
+                for format in ImageSequenceFormat::ALL {
+                    ui.selectable_value(&mut self.format, format, format.name());
+                }
+            });
+        ui.end_row();
+    }
+
+    fn show_preset_settings(&mut self, ui: &mut egui::Ui, user_presets: &mut Vec<ExportPreset>) {
+        let presets: Vec<ExportPreset> = export_presets::built_in()
+            .into_iter()
+            .chain(user_presets.iter().cloned())
+            .collect();
+        let current = presets
+            .iter()
+            .find(|preset| self.preset(preset.name.clone()) == **preset)
+            .map(|preset| preset.name.clone());
+
+        ui.label("Preset");
+        ui.horizontal(|ui| {
+            let mut chosen = None;
+            egui::ComboBox::from_id_salt("export_preset")
+                .selected_text(current.as_deref().unwrap_or("Custom"))
+                .show_ui(ui, |ui| {
+                    for preset in &presets {
+                        let selected = current.as_ref() == Some(&preset.name);
+                        if ui.selectable_label(selected, &preset.name).clicked() {
+                            chosen = Some(preset.clone());
+                        }
+                    }
+                });
+            if let Some(preset) = chosen {
+                self.apply_preset(&preset);
+            }
+            if let Some(name) = &current
+                && user_presets.iter().any(|preset| &preset.name == name)
+                && ui.button("Delete").clicked()
+            {
+                user_presets.retain(|preset| &preset.name != name);
+            }
+        });
+        ui.end_row();
+
+        ui.label("");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.preset_name)
+                    .hint_text("Preset name")
+                    .desired_width(140.0),
+            );
+            let name = self.preset_name.trim().to_string();
+            let blocker = if name.is_empty() {
+                Some("Name the preset first")
+            } else if export_presets::built_in()
+                .iter()
+                .any(|preset| preset.name == name)
+            {
+                Some("A built-in preset has this name")
+            } else {
+                None
+            };
+            let mut response = ui
+                .add_enabled(blocker.is_none(), egui::Button::new("Save as preset"))
+                .on_hover_text("Replaces your preset with this name, if you have one");
+            if let Some(blocker) = blocker {
+                response = response.on_disabled_hover_text(blocker);
+            }
+            if response.clicked() {
+                user_presets.retain(|preset| preset.name != name);
+                user_presets.push(self.preset(name));
+                self.preset_name.clear();
+            }
+        });
+        ui.end_row();
+
+        if self.resolution_override.is_some() || self.fps_override.is_some() {
+            let mut output = Vec::new();
+            if let Some((width, height)) = self.resolution_override {
+                output.push(format!("{width}×{height}"));
+            }
+            if let Some((num, den)) = self.fps_override {
+                output.push(format!("{:.3} FPS", num as f64 / den.max(1) as f64));
+            }
+            ui.label("Output");
+            ui.horizontal(|ui| {
+                ui.label(format!("{}, from the preset", output.join(" at ")));
+                if ui.button("Use the project's").clicked() {
+                    self.resolution_override = None;
+                    self.fps_override = None;
+                }
+            });
+            ui.end_row();
+        }
+    }
+
+    fn show_video_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label("File");
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.video_path);
+            if ui.button("Browse…").clicked() && self.pending_video_dialog.is_none() {
+                let (inbox, outbox) = message_channel::new();
+                self.pending_video_dialog = Some(inbox);
+                let container = self.container;
+                std::thread::spawn(move || {
+                    let _ = outbox.send(
+                        rfd::FileDialog::new()
+                            .add_filter(container.name(), &[container.extension()])
+                            .save_file(),
+                    );
+                });
+            }
+        });
+        ui.end_row();
+
+        ui.label("Container");
+        let previous_codec = self.codec;
+        let previous_container = self.container;
+        egui::ComboBox::from_id_salt("export_container")
+            .selected_text(self.container.name())
+            .show_ui(ui, |ui| {
+                for container in VideoContainer::ALL {
+                    ui.selectable_value(&mut self.container, container, container.name());
+                }
+            });
+        if self.container != previous_container && !self.container.supports(self.codec) {
+            self.codec = self.container.default_codec();
+        }
+        ui.end_row();
+
+        ui.label("Codec");
+        egui::ComboBox::from_id_salt("export_codec")
+            .selected_text(self.codec.name())
+            .show_ui(ui, |ui| {
+                for codec in VideoCodec::ALL {
+                    if self.container.supports(codec) {
+                        ui.selectable_value(&mut self.codec, codec, codec.name());
+                    }
+                }
+            });
+        if self.codec != previous_codec
+            && let Some(quality) = self.codec.default_quality()
+        {
+            self.encoding.quality = quality;
+        }
+        ui.end_row();
+
+        self.show_encoding_settings(ui);
+
+        ui.label("Encoder");
+        let two_pass = self.is_two_pass();
+        ui.horizontal(|ui| {
+            let supported = HardwareEncoder::ALL
+                .into_iter()
+                .any(|hardware| hardware.supports(self.codec));
+            ui.add_enabled_ui(supported && !self.farm && !two_pass, |ui| {
+                ui.checkbox(&mut self.hardware, "Use the GPU's encoder")
+                    .on_hover_text(
+                        "Much faster than encoding on the CPU, for somewhat bigger files. \
+                         Falls back to the CPU when the GPU has no encoder for the codec \
+                         or the video has an alpha channel.",
+                    );
+            })
+            .response
+            .on_disabled_hover_text(if self.farm {
+                "Render farm workers always encode on the CPU, so their chunks match"
+            } else if two_pass {
+                "Two-pass encodes are made on the CPU"
+            } else {
+                "No GPU encoder supports this codec"
+            });
+            if supported && self.hardware && !self.farm && !two_pass {
+                let found = if !self.hardware_checked {
+                    "checking…".to_string()
+                } else {
+                    match export::hardware_encoders(self.codec).first() {
+                        Some(hardware) => hardware.name().to_string(),
+                        None => "none found, the CPU is used".to_string(),
+                    }
+                };
+                ui.label(egui::RichText::new(format!("({found})")).weak());
+            }
+        });
+        ui.end_row();
+
+        ui.label("Render farm");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.farm, "Distribute to workers on port")
+                .on_hover_text(format!(
+                    "Run `--render-worker <this computer's address>:{}` on other computers to \
+                     have them render chunks of the video. They need the project's files at \
+                     the same paths, and the same version and nodes as this editor.",
+                    self.farm_port
+                ));
+            ui.add_enabled(
+                self.farm,
+                egui::DragValue::new(&mut self.farm_port).range(1024..=u16::MAX),
+            );
+        });
+        ui.end_row();
+    }
+
+    fn show_encoding_settings(&mut self, ui: &mut egui::Ui) {
+        let Some(range) = self.codec.quality_range() else {
+            ui.label("Rate control");
+            ui.label(
+                egui::RichText::new(format!("Set by the {} profile", self.codec.name())).weak(),
+            );
+            ui.end_row();
+            return;
+        };
+        let two_pass = self.is_two_pass();
+        let form = &mut self.encoding;
+
+        ui.label("Rate control");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut form.two_pass, false, "Quality")
+                .on_hover_text("Keep the quality steady and let the file be as big as it needs");
+            ui.add_enabled_ui(!self.farm, |ui| {
+                ui.selectable_value(&mut form.two_pass, true, "Two-pass bit rate")
+                    .on_hover_text(
+                        "Render the video twice to spread a bit rate where it's needed most, \
+                         for a predictable file size",
+                    );
+            })
+            .response
+            .on_disabled_hover_text("Render farm chunks can't be encoded in two passes");
+        });
+        ui.end_row();
+
+        if two_pass {
+            ui.label("Bit rate");
+            ui.add(
+                egui::DragValue::new(&mut form.bit_rate_kbps)
+                    .range(100..=500_000)
+                    .speed(50)
+                    .suffix(" kbit/s"),
+            );
+            ui.end_row();
+
+            ui.label("Target size");
+            let mut size_mb = form.bit_rate_kbps as f64 * self.duration_secs / 8000.0;
+            let size = ui
+                .add(
+                    egui::DragValue::new(&mut size_mb)
+                        .range(0.1..=100_000.0)
+                        .max_decimals(1)
+                        .suffix(" MB"),
+                )
+                .on_hover_text("Sets the bit rate that makes the video about this big");
+            if size.changed() {
+                let bit_rate =
+                    export::bit_rate_for_file_size((size_mb * 1e6) as u64, self.duration_secs);
+                form.bit_rate_kbps = (bit_rate / 1000).max(1);
+            }
+            ui.end_row();
+        } else {
+            let default_quality = self.codec.default_quality().unwrap_or_default();
+            ui.label("Quality");
+            ui.add(egui::Slider::new(&mut form.quality, range).text("CRF"))
+                .on_hover_text(format!(
+                    "Lower is better and makes bigger files. {default_quality} is a good start."
+                ));
+            ui.end_row();
+        }
+
+        ui.label("Keyframes");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut form.keyframes, "Every")
+                .on_hover_text("More keyframes make seeking faster, for bigger files");
+            ui.add_enabled(
+                form.keyframes,
+                egui::DragValue::new(&mut form.keyframe_interval)
+                    .range(1..=10_000)
+                    .suffix(" frames"),
+            );
+        });
+        ui.end_row();
+
+        ui.label("Speed");
+        egui::ComboBox::from_id_salt("export_encoding_speed")
+            .selected_text(form.speed.name())
+            .show_ui(ui, |ui| {
+                for speed in EncodingSpeed::ALL {
+                    ui.selectable_value(&mut form.speed, speed, speed.name());
+                }
+            })
+            .response
+            .on_hover_text("Slower encodes make smaller files at the same quality");
+        ui.end_row();
+    }
+
+    fn show_audio_settings(&mut self, ui: &mut egui::Ui, paths: &[PathBuf]) {
+        let blocker = if self.is_farm() {
+            Some("Render farm chunks can't have audio")
+        } else if self.is_segmented() {
+            Some("Videos written in segments can't have audio")
+        } else {
+            None
+        };
+        ui.label("Audio");
+        ui.add_enabled_ui(blocker.is_none(), |ui| {
+            ui.checkbox(&mut self.audio.enabled, "Include the sources' sound")
+                .on_hover_text(
+                    "The sound of the files the graph's Video Source and Audio Meter nodes \
+                     play. One source is copied as it is, and more are mixed together.",
+                );
+        })
+        .response
+        .on_disabled_hover_text(blocker.unwrap_or_default());
+        ui.end_row();
+        if !self.audio.enabled || blocker.is_some() {
+            return;
+        }
+
+        ui.label("");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.audio.normalize, "Normalize loudness to")
+                .on_hover_text(
+                    "Measure the sound before encoding and turn it up or down to this \
+                     loudness, so platforms don't change its volume. -14 LUFS suits most \
+                     streaming sites.",
+                );
+            ui.add_enabled(
+                self.audio.normalize,
+                egui::DragValue::new(&mut self.audio.loudness_target)
+                    .range(-40.0..=-5.0)
+                    .speed(0.1)
+                    .suffix(" LUFS"),
+            );
+        });
+        ui.end_row();
+
+        if paths.is_empty() {
+            ui.label("");
+            ui.label(egui::RichText::new("The graph plays no files").weak());
+            ui.end_row();
+        }
+        for path in paths {
+            let (include, gain_db) = self
+                .audio
+                .sources
+                .entry(path.clone())
+                .or_insert((true, 0.0));
+            ui.label("");
+            ui.horizontal(|ui| {
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                ui.checkbox(include, name.to_string_lossy())
+                    .on_hover_text(path.display().to_string());
+                ui.add_enabled(
+                    *include,
+                    egui::DragValue::new(gain_db)
+                        .range(-60.0..=12.0)
+                        .speed(0.1)
+                        .suffix(" dB"),
+                )
+                .on_hover_text("Anything but 0 dB mixes the sound again");
+            });
+            ui.end_row();
+        }
+    }
+
+    fn show_watermark_settings(&mut self, ui: &mut egui::Ui) {
+        let form = &mut self.watermark;
+        ui.label("Watermark");
+        ui.checkbox(&mut form.enabled, "Burn in")
+            .on_hover_text("Draw an image or text over every exported frame");
+        ui.end_row();
+        if !form.enabled {
+            return;
+        }
+
+        ui.label("Mark");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut form.use_text, false, "Image");
+            ui.selectable_value(&mut form.use_text, true, "Text");
+        });
+        ui.end_row();
+
+        if form.use_text {
+            ui.label("Text");
+            ui.text_edit_singleline(&mut form.text);
+            ui.end_row();
+
+            ui.label("Text size");
+            ui.add(
+                egui::DragValue::new(&mut form.text_size)
+                    .range(4.0..=512.0)
+                    .suffix(" px"),
+            );
+            ui.end_row();
+
+            ui.label("Text color");
+            ui.color_edit_button_rgba_unmultiplied(&mut form.text_color);
+            ui.end_row();
+        } else {
+            ui.label("Image");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut form.image_path);
+                if ui.button("Browse…").clicked() && self.pending_watermark_dialog.is_none() {
+                    let (inbox, outbox) = message_channel::new();
+                    self.pending_watermark_dialog = Some(inbox);
+                    std::thread::spawn(move || {
+                        let _ = outbox.send(
+                            rfd::FileDialog::new()
+                                .add_filter("Images", &["png", "jpg", "jpeg", "webp", "bmp"])
+                                .pick_file(),
+                        );
+                    });
+                }
+            });
+            ui.end_row();
+        }
+
+        ui.label("Position");
+        egui::ComboBox::from_id_salt("export_watermark_position")
+            .selected_text(form.position.name())
+            .show_ui(ui, |ui| {
+                for position in WatermarkPosition::ALL {
+                    ui.selectable_value(&mut form.position, position, position.name());
+                }
+            });
+        ui.end_row();
+
+        ui.label("Opacity");
+        ui.add(egui::Slider::new(&mut form.opacity, 0.0..=1.0));
+        ui.end_row();
+
+        ui.label("Margin");
+        ui.add(
+            egui::DragValue::new(&mut form.margin)
+                .range(0.0..=500.0)
+                .suffix(" px"),
+        );
+        ui.end_row();
+
+        ui.label("Scale");
+        ui.add(
+            egui::DragValue::new(&mut form.scale)
+                .range(0.01..=10.0)
+                .speed(0.01),
+        );
+        ui.end_row();
+    }
+
+    /// Whether the export is rendered by render farm workers.
+    fn is_farm(&self) -> bool {
+        self.target == ExportTarget::Video && self.farm
+    }
+
+    /// Whether the export is written in segments.
+    fn is_segmented(&self) -> bool {
+        self.segmented && !self.is_farm() && !self.is_two_pass()
+    }
+
+    /// Whether the export is a video encoded in two passes.
+    fn is_two_pass(&self) -> bool {
+        self.target == ExportTarget::Video
+            && self.encoding.two_pass
+            && !self.farm
+            && self.codec.quality_range().is_some()
+    }
+
+    /// Whether the chosen target can keep an alpha channel.
+    fn alpha_supported(&self) -> bool {
+        match self.target {
+            ExportTarget::ImageSequence => true,
+            ExportTarget::Video => self.codec.supports_alpha(),
+        }
+    }
+
+    /// The alpha mode to export with, which is opaque when the target can't
+    /// keep an alpha channel (so the choice is remembered for when it can).
+    fn alpha(&self) -> AlphaMode {
+        if self.alpha_supported() {
+            self.alpha
+        } else {
+            AlphaMode::Opaque
+        }
+    }
+
+    fn sequence_settings(&self) -> ImageSequenceSettings {
+        ImageSequenceSettings {
+            directory: PathBuf::from(self.directory.trim()),
+            pattern: self.pattern.clone(),
+            start_number: self.start_number,
+            format: self.format,
+        }
+    }
+
+    /// The project's output settings, with a picked preset's resolution and
+    /// frame rate in place of its own when exporting a video.
+    fn output_settings(&self, project: OutputSettings) -> OutputSettings {
+        if self.target != ExportTarget::Video {
+            return project;
+        }
+        OutputSettings {
+            resolution: self.resolution_override.or(project.resolution),
+            fps: self.fps_override.or(project.fps),
+        }
+    }
+
+    /// The video settings saved as a preset named `name`.
+    fn preset(&self, name: String) -> ExportPreset {
+        ExportPreset {
+            loudness_target: self.video_settings().loudness_target,
+            ..ExportPreset::new(
+                name,
+                self.resolution_override,
+                self.fps_override,
+                self.container,
+                self.codec,
+                &self.video_settings().encoding,
+                self.audio.enabled,
+            )
+        }
+    }
+
+    /// Switch to `preset`'s settings, or say why it can't be used.
+    fn apply_preset(&mut self, preset: &ExportPreset) {
+        let settings =
+            || Ok::<_, String>((preset.container()?, preset.codec()?, preset.encoding()?));
+        let (container, codec, encoding) = match settings() {
+            Ok(settings) => settings,
+            Err(e) => {
+                self.status = Some(format!("The \"{}\" preset can't be used: {e}", preset.name));
+                return;
+            }
+        };
+
+        self.container = container;
+        self.codec = codec;
+        self.resolution_override = preset.resolution;
+        self.fps_override = preset.fps;
+        self.audio.enabled = preset.audio;
+        self.audio.normalize = preset.loudness_target.is_some();
+        if let Some(target) = preset.loudness_target {
+            self.audio.loudness_target = target;
+        }
+        let form = &mut self.encoding;
+        form.two_pass = encoding.is_two_pass();
+        match encoding.rate_control {
+            Some(RateControl::Quality(quality)) => form.quality = quality,
+            Some(RateControl::TwoPass { bit_rate }) => {
+                form.bit_rate_kbps = (bit_rate / 1000).max(1)
+            }
+            None => {}
+        }
+        form.keyframes = encoding.keyframe_interval.is_some();
+        if let Some(keyframe_interval) = encoding.keyframe_interval {
+            form.keyframe_interval = keyframe_interval;
+        }
+        form.speed = encoding.speed.unwrap_or_default();
+        if form.two_pass {
+            self.farm = false;
+        }
+    }
+
+    fn video_settings(&self) -> VideoSettings {
+        VideoSettings {
+            path: PathBuf::from(self.video_path.trim()),
+            container: self.container,
+            codec: self.codec,
+            encoding: self.encoding.options(
+                self.codec,
+                self.is_two_pass(),
+                self.hardware && !self.farm && !self.is_two_pass(),
+            ),
+            audio: Vec::new(),
+            loudness_target: (self.audio.enabled && self.audio.normalize)
+                .then_some(self.audio.loudness_target),
+        }
+    }
+
+    /// Where the chosen target is exported to, for showing the user.
+    fn destination(&self) -> String {
+        match self.target {
+            ExportTarget::ImageSequence => self.directory.trim().to_string(),
+            ExportTarget::Video => self.video_settings().file_path().display().to_string(),
+        }
+    }
+
+    fn start(
+        &mut self,
+        graph: &NodeGraph,
+        output_node: EngineNodeId,
+        output_settings: OutputSettings,
+        fps: Fps,
+        passes: Vec<RenderPass>,
+        node_library: &Arc<NodeLibrary>,
+    ) {
+        let job = ExportJob {
+            graph: graph.clone(),
+            output_node_id: output_node,
+            resolution: output_settings
+                .resolution
+                .and_then(|(width, height)| Dimensions::new(width, height)),
+            fps,
+            frame_count: frame_count(self.duration_secs, fps),
+            alpha: self.alpha(),
+            passes,
+            watermark: self.watermark.watermark(),
+            segment_frames: self.is_segmented().then_some(self.segment_frames),
+        };
+        let sink_settings = match self.target {
+            ExportTarget::ImageSequence => SinkSettings::ImageSequence(self.sequence_settings()),
+            ExportTarget::Video if self.farm => SinkSettings::Farm {
+                settings: self.video_settings(),
+                port: self.farm_port,
+                chunk_frames: self.segment_frames,
+            },
+            ExportTarget::Video => SinkSettings::Video(VideoSettings {
+                audio: if self.audio.enabled && !self.is_segmented() {
+                    self.audio
+                        .sources(&export::audio_source_paths(graph, node_library))
+                } else {
+                    Vec::new()
+                },
+                ..self.video_settings()
+            }),
+        };
+        let node_library = node_library.clone();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (inbox, outbox) = message_channel::new();
+
+        self.running = Some(RunningExport {
+            progress: inbox,
+            cancel: cancel.clone(),
+            frame_count: job.frame_count,
+            encoding_passes: if self.is_two_pass() { 2 } else { 1 },
+            written: 0,
+            farm: self.is_farm().then_some((self.farm_port, 0)),
+            verifying: false,
+            notes: Vec::new(),
+            summary: None,
+        });
+        self.status = None;
+        self.summary = None;
+        let verify = self.verify;
+
+        std::thread::spawn(move || {
+            let render = |sink: &mut dyn FrameSink| {
+                export::render_headless(&job, &node_library, sink, |written| {
+                    let _ = outbox.send(ExportProgress::Frames(written));
+                    !cancel.load(Ordering::Relaxed)
+                })
+            };
+            let result = match sink_settings {
+                SinkSettings::ImageSequence(settings) => {
+                    export::ImageSequenceSink::new(settings).and_then(|mut sink| render(&mut sink))
+                }
+                SinkSettings::Video(settings) => {
+                    let mut sink = export::VideoSink::new(settings);
+                    let mut result = render(&mut sink);
+                    if verify && result.is_ok() && sink.summary().is_some() {
+                        let _ = outbox.send(ExportProgress::Verifying);
+                        if let Err(error) = sink.verify() {
+                            result = Err(error);
+                        }
+                    }
+                    if let Some(reason) = sink.software_fallback() {
+                        let _ = outbox.send(ExportProgress::Note(format!(
+                            "It was encoded on the CPU: {reason}."
+                        )));
+                    }
+                    if let Some(loudness) = sink.loudness() {
+                        let _ = outbox.send(ExportProgress::Note(format!("{loudness}.")));
+                    }
+                    if let Some(summary) = sink.summary() {
+                        if summary
+                            .verification
+                            .as_ref()
+                            .is_some_and(|verification| !verification.passed())
+                        {
+                            let _ = outbox.send(ExportProgress::Note(
+                                "The video failed verification, see the summary.".to_string(),
+                            ));
+                        }
+                        let _ = outbox.send(ExportProgress::Summary(summary.clone()));
+                    }
+                    result
+                }
+                SinkSettings::Farm {
+                    settings,
+                    port,
+                    chunk_frames,
+                } => match TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)) {
+                    Ok(listener) => export::render_distributed(
+                        &job,
+                        &node_library,
+                        settings,
+                        chunk_frames,
+                        listener,
+                        |progress| {
+                            let _ = outbox.send(ExportProgress::Frames(progress.frames));
+                            let _ = outbox.send(ExportProgress::Workers(progress.workers));
+                            !cancel.load(Ordering::Relaxed)
+                        },
+                    ),
+                    Err(e) => {
+                        let _ = outbox.send(ExportProgress::Finished(Err(format!(
+                            "Couldn't listen for workers on port {port}: {e}"
+                        ))));
+                        return;
+                    }
+                },
+            };
+            let _ = outbox.send(ExportProgress::Finished(
+                result.map_err(|error| error.to_string()),
+            ));
+        });
+    }
+
+    fn check_progress(&mut self, ctx: &egui::Context) {
+        let Some(running) = &mut self.running else {
+            return;
+        };
+
+        let finished = match running.progress.check_non_blocking_all() {
+            Ok(Some(progress)) => progress.into_iter().find_map(|progress| match progress {
+                ExportProgress::Frames(written) => {
+                    running.written = written;
+                    None
+                }
+                ExportProgress::Workers(workers) => {
+                    if let Some((_, connected)) = &mut running.farm {
+                        *connected = workers;
+                    }
+                    None
+                }
+                ExportProgress::Note(note) => {
+                    running.notes.push(note);
+                    None
+                }
+                ExportProgress::Verifying => {
+                    running.verifying = true;
+                    None
+                }
+                ExportProgress::Summary(summary) => {
+                    running.summary = Some(summary);
+                    None
+                }
+                ExportProgress::Finished(result) => Some(result),
+            }),
+            Ok(None) => None,
+            Err(_) => Some(Err("The export stopped unexpectedly.".to_string())),
+        };
+
+        match finished {
+            Some(Ok(written)) => {
+                let notes = std::mem::take(&mut running.notes);
+                let summary = running.summary.take();
+                let mut status = format!("Exported {written} frames to {}", self.destination());
+                if !notes.is_empty() {
+                    status += &format!(". {}", notes.join(" "));
+                }
+                self.status = Some(status);
+                self.summary = summary;
+                self.running = None;
+                self.finished_export = Some(written);
+            }
+            Some(Err(error)) => {
+                util::debug_log_error!("Export failed: {error}");
+                self.status = Some(format!("Export failed: {error}"));
+                self.running = None;
+            }
+            None => ctx.request_repaint_after(PROGRESS_REPAINT_INTERVAL),
+        }
+    }
+
+    fn check_folder_dialog(&mut self, ctx: &egui::Context) {
+        let Some(inbox) = &self.pending_folder_dialog else {
+            return;
+        };
+        match inbox.check_non_blocking() {
+            Ok(Some(Some(path))) => {
+                self.directory = path.display().to_string();
+                self.pending_folder_dialog = None;
+            }
+            Ok(Some(None)) | Err(_) => {
+                self.pending_folder_dialog = None;
+            }
+            Ok(None) => {
+                // Keep repainting while the dialog is open so the pick shows up
+                // right away.
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    fn check_video_dialog(&mut self, ctx: &egui::Context) {
+        let Some(inbox) = &self.pending_video_dialog else {
+            return;
+        };
+        match inbox.check_non_blocking() {
+            Ok(Some(Some(path))) => {
+                self.video_path = path.display().to_string();
+                self.pending_video_dialog = None;
+            }
+            Ok(Some(None)) | Err(_) => {
+                self.pending_video_dialog = None;
+            }
+            Ok(None) => ctx.request_repaint(),
+        }
+    }
+
+    fn check_hardware_check(&mut self, ctx: &egui::Context) {
+        let Some(inbox) = &self.pending_hardware_check else {
+            return;
+        };
+        match inbox.check_non_blocking() {
+            Ok(Some(())) | Err(_) => {
+                self.hardware_checked = true;
+                self.pending_hardware_check = None;
+            }
+            Ok(None) => ctx.request_repaint_after(PROGRESS_REPAINT_INTERVAL),
+        }
+    }
+
+    fn check_watermark_dialog(&mut self, ctx: &egui::Context) {
+        let Some(inbox) = &self.pending_watermark_dialog else {
+            return;
+        };
+        match inbox.check_non_blocking() {
+            Ok(Some(Some(path))) => {
+                self.watermark.image_path = path.display().to_string();
+                self.pending_watermark_dialog = None;
+            }
+            Ok(Some(None)) | Err(_) => {
+                self.pending_watermark_dialog = None;
+            }
+            Ok(None) => ctx.request_repaint(),
+        }
+    }
+}
+
+/// The project's frame rate, or 30 FPS if it doesn't set one (like the engine).
+pub fn export_fps(output_settings: OutputSettings) -> Fps {
+    output_settings
+        .fps
+        .and_then(|(num, den)| Fps::from_frac(num, den).ok())
+        .unwrap_or(FPS_30)
+}
+
+/// How many frames `duration_secs` lasts at `fps`, and always at least one.
+pub fn frame_count(duration_secs: f64, fps: Fps) -> u64 {
+    ((duration_secs * fps.as_float()).round() as u64).max(1)
+}
@@ -0,0 +1,341 @@
+use super::node_graph::{
+    InputMatch, NodeData, ReplaceEdit, find_file_references, find_inputs, parse_value_like,
+    replace_inputs, value_text,
+};
+use egui_snarl::Snarl;
+use engine::node::NodeLibrary;
+use engine::node_graph::InputValue;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use util::channels::message_channel;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FindMode {
+    /// File inputs set to one path.
+    File,
+    /// Inputs matched by node, input name, and value.
+    Parameter,
+}
+
+/// A window for finding inputs across the project's graph and replacing them
+/// in one go (see [find_inputs]). Matches are listed before anything is
+/// replaced, and the last replacement can be undone.
+pub struct FindReplaceDialog {
+    open: bool,
+    mode: FindMode,
+    /// The path searched for in [FindMode::File].
+    find_path: String,
+    /// Case-insensitive filters for [FindMode::Parameter]. Empty filters match
+    /// everything.
+    node_filter: String,
+    input_filter: String,
+    value_filter: String,
+    replacement: String,
+    matches: Vec<InputMatch>,
+    /// Whether each of `matches` is replaced.
+    included: Vec<bool>,
+    last_edit: Option<ReplaceEdit>,
+    status: Option<String>,
+    pending_file_dialog: Option<message_channel::Inbox<Option<PathBuf>>>,
+}
+
+impl FindReplaceDialog {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            mode: FindMode::File,
+            find_path: String::new(),
+            node_filter: String::new(),
+            input_filter: String::new(),
+            value_filter: String::new(),
+            replacement: String::new(),
+            matches: Vec::new(),
+            included: Vec::new(),
+            last_edit: None,
+            status: None,
+            pending_file_dialog: None,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Show the window if it's open. Returns whether `snarl` was changed.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        snarl: &mut Snarl<NodeData>,
+        node_library: &NodeLibrary,
+    ) -> bool {
+        if !self.open {
+            return false;
+        }
+
+        self.check_file_dialog(ctx);
+
+        let mut changed = false;
+        let mut open = self.open;
+        egui::Window::new("Find and Replace")
+            .open(&mut open)
+            .default_size(egui::vec2(480.0, 360.0))
+            .resizable(true)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if self.show_query(ui, snarl) {
+                    self.find(snarl, node_library);
+                }
+                ui.separator();
+                self.show_matches(ui, snarl, node_library);
+                ui.separator();
+                changed = self.show_actions(ui, snarl);
+            });
+        self.open = open;
+
+        changed
+    }
+
+    /// Returns whether Find was clicked.
+    fn show_query(&mut self, ui: &mut egui::Ui, snarl: &Snarl<NodeData>) -> bool {
+        ui.horizontal(|ui| {
+            let previous_mode = self.mode;
+            ui.selectable_value(&mut self.mode, FindMode::File, "Files");
+            ui.selectable_value(&mut self.mode, FindMode::Parameter, "Parameters");
+            if self.mode != previous_mode {
+                self.clear_matches();
+            }
+        });
+        ui.add_space(4.0);
+
+        egui::Grid::new("find_replace_query")
+            .num_columns(2)
+            .spacing([12.0, 6.0])
+            .show(ui, |ui| {
+                match self.mode {
+                    FindMode::File => {
+                        ui.label("Find file");
+                        self.show_file_picker(ui, snarl);
+                        ui.end_row();
+                    }
+                    FindMode::Parameter => {
+                        ui.label("Node");
+                        ui.text_edit_singleline(&mut self.node_filter)
+                            .on_hover_text("Part of the node's name. Leave empty for any node.");
+                        ui.end_row();
+
+                        ui.label("Input");
+                        ui.text_edit_singleline(&mut self.input_filter)
+                            .on_hover_text("Part of the input's name. Leave empty for any input.");
+                        ui.end_row();
+
+                        ui.label("Value");
+                        ui.text_edit_singleline(&mut self.value_filter)
+                            .on_hover_text(
+                                "Part of the input's current value. Leave empty for any value.",
+                            );
+                        ui.end_row();
+                    }
+                }
+
+                ui.label("Replace with");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.replacement);
+                    if self.mode == FindMode::File
+                        && ui.button("Browse…").clicked()
+                        && self.pending_file_dialog.is_none()
+                    {
+                        let (inbox, outbox) = message_channel::new();
+                        self.pending_file_dialog = Some(inbox);
+                        std::thread::spawn(move || {
+                            let _ = outbox.send(rfd::FileDialog::new().pick_file());
+                        });
+                    }
+                });
+                ui.end_row();
+            });
+
+        ui.add_space(4.0);
+        ui.button("Find").clicked()
+    }
+
+    /// A combo box of every file the graph uses, since the path searched for
+    /// is always one of them.
+    fn show_file_picker(&mut self, ui: &mut egui::Ui, snarl: &Snarl<NodeData>) {
+        let paths: BTreeSet<PathBuf> =
+            find_inputs(snarl, |_, _, value| matches!(value, InputValue::File(_)))
+                .into_iter()
+                .filter_map(|found| match found.value {
+                    InputValue::File(path) => Some(path),
+                    _ => None,
+                })
+                .collect();
+
+        let selected_text = if self.find_path.is_empty() {
+            "Choose a file…".to_string()
+        } else {
+            self.find_path.clone()
+        };
+        egui::ComboBox::from_id_salt("find_replace_file")
+            .selected_text(selected_text)
+            .width(320.0)
+            .show_ui(ui, |ui| {
+                if paths.is_empty() {
+                    ui.label("No files are used in this project.");
+                }
+                for path in paths {
+                    let text = path.display().to_string();
+                    if ui.selectable_label(self.find_path == text, &text).clicked() {
+                        self.find_path = text;
+                        self.clear_matches();
+                    }
+                }
+            });
+    }
+
+    fn find(&mut self, snarl: &Snarl<NodeData>, node_library: &NodeLibrary) {
+        self.matches = match self.mode {
+            FindMode::File => find_file_references(snarl, Path::new(&self.find_path)),
+            FindMode::Parameter => {
+                let node_filter = self.node_filter.to_lowercase();
+                let input_filter = self.input_filter.to_lowercase();
+                let value_filter = self.value_filter.to_lowercase();
+                find_inputs(snarl, |node, input_name, value| {
+                    // Only values that can be typed in can be replaced.
+                    let Some(text) = value_text(value) else {
+                        return false;
+                    };
+                    node_display_name(node, node_library)
+                        .to_lowercase()
+                        .contains(&node_filter)
+                        && input_name.to_lowercase().contains(&input_filter)
+                        && text.to_lowercase().contains(&value_filter)
+                })
+            }
+        };
+        self.included = vec![true; self.matches.len()];
+        self.status = Some(match self.matches.len() {
+            1 => "1 input found.".to_string(),
+            count => format!("{count} inputs found."),
+        });
+    }
+
+    /// A preview of every match: where it is, and what it'll change to.
+    fn show_matches(
+        &mut self,
+        ui: &mut egui::Ui,
+        snarl: &Snarl<NodeData>,
+        node_library: &NodeLibrary,
+    ) {
+        if self.matches.is_empty() {
+            ui.label(egui::RichText::new("No matches.").weak());
+            return;
+        }
+
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for (found, included) in self.matches.iter().zip(&mut self.included) {
+                    let node_name = snarl
+                        .get_node(found.node)
+                        .map(|node| node_display_name(node, node_library))
+                        .unwrap_or_else(|| "Deleted node".to_string());
+                    let old = value_text(&found.value).unwrap_or_default();
+                    let new = match parse_value_like(&found.value, &self.replacement) {
+                        Some(value) => value_text(&value).unwrap_or_default(),
+                        None => "(not a valid value)".to_string(),
+                    };
+
+                    ui.checkbox(
+                        included,
+                        format!("{node_name} › {}: {old} → {new}", found.input_name),
+                    );
+                }
+            });
+    }
+
+    /// The replace and undo buttons. Returns whether `snarl` was changed.
+    fn show_actions(&mut self, ui: &mut egui::Ui, snarl: &mut Snarl<NodeData>) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let any_included = self.included.iter().any(|included| *included);
+            if ui
+                .add_enabled(any_included, egui::Button::new("Replace"))
+                .clicked()
+            {
+                let matches: Vec<InputMatch> = self
+                    .matches
+                    .iter()
+                    .zip(&self.included)
+                    .filter(|(_, included)| **included)
+                    .map(|(found, _)| found.clone())
+                    .collect();
+                let replacement = self.replacement.clone();
+                let edit = replace_inputs(snarl, &matches, |value| {
+                    parse_value_like(value, &replacement)
+                });
+                self.status = Some(match edit.changes.len() {
+                    1 => "Replaced 1 input.".to_string(),
+                    count => format!("Replaced {count} inputs."),
+                });
+                changed = !edit.changes.is_empty();
+                if changed {
+                    self.last_edit = Some(edit);
+                }
+                self.clear_matches();
+            }
+
+            if ui
+                .add_enabled(self.last_edit.is_some(), egui::Button::new("Undo Replace"))
+                .clicked()
+                && let Some(edit) = self.last_edit.take()
+            {
+                let restored = edit.undo(snarl);
+                self.status = Some(match restored {
+                    1 => "Restored 1 input.".to_string(),
+                    count => format!("Restored {count} inputs."),
+                });
+                changed = restored > 0;
+                self.clear_matches();
+            }
+        });
+
+        if let Some(status) = &self.status {
+            ui.label(egui::RichText::new(status).weak());
+        }
+        changed
+    }
+
+    fn clear_matches(&mut self) {
+        self.matches.clear();
+        self.included.clear();
+    }
+
+    fn check_file_dialog(&mut self, ctx: &egui::Context) {
+        let Some(inbox) = &self.pending_file_dialog else {
+            return;
+        };
+        match inbox.check_non_blocking() {
+            Ok(Some(Some(path))) => {
+                self.replacement = path.display().to_string();
+                self.pending_file_dialog = None;
+            }
+            Ok(Some(None)) | Err(_) => {
+                self.pending_file_dialog = None;
+            }
+            Ok(None) => {
+                // Keep repainting while the dialog is open so the pick shows up
+                // right away.
+                ctx.request_repaint();
+            }
+        }
+    }
+}
+
+/// The name `node` is shown with in the graph.
+fn node_display_name(node: &NodeData, node_library: &NodeLibrary) -> String {
+    node_library
+        .get_definition(&node.definition_name)
+        .map(|definition| definition.node.name.clone())
+        .unwrap_or_else(|| node.definition_name.clone())
+}
@@ -0,0 +1,141 @@
+use super::node_graph::{GraphImage, NodeData};
+use egui_snarl::Snarl;
+use engine::node::NodeLibrary;
+use std::path::PathBuf;
+use util::channels::message_channel;
+
+/// A window for saving a picture of the node graph as a PNG or an SVG (see
+/// [GraphImage]), at a scale of its size on the canvas.
+pub struct GraphImageDialog {
+    open: bool,
+    scale: f32,
+    status: Option<String>,
+    pending_file_dialog: Option<message_channel::Inbox<Option<PathBuf>>>,
+    /// The picture being saved on a thread of its own.
+    pending_save: Option<message_channel::Inbox<Result<PathBuf, String>>>,
+}
+
+impl GraphImageDialog {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            scale: 2.0,
+            status: None,
+            pending_file_dialog: None,
+            pending_save: None,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Show the window if it's open. The picture is of `snarl` as it is when
+    /// a file is picked.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        snarl: &Snarl<NodeData>,
+        node_library: &NodeLibrary,
+    ) {
+        self.check_file_dialog(ctx, snarl, node_library);
+        self.check_save(ctx);
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Export Graph Image")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Scale");
+                    ui.add(
+                        egui::DragValue::new(&mut self.scale)
+                            .range(0.25..=8.0)
+                            .speed(0.05)
+                            .suffix("x"),
+                    )
+                    .on_hover_text("The size of the image compared to the graph on the canvas.");
+                });
+
+                let busy = self.pending_file_dialog.is_some() || self.pending_save.is_some();
+                if ui
+                    .add_enabled(!busy, egui::Button::new("Export…"))
+                    .clicked()
+                {
+                    self.open_file_dialog();
+                }
+
+                if let Some(status) = &self.status {
+                    ui.label(egui::RichText::new(status).weak());
+                }
+            });
+        self.open = open;
+    }
+
+    fn open_file_dialog(&mut self) {
+        let (inbox, outbox) = message_channel::new();
+        self.pending_file_dialog = Some(inbox);
+        std::thread::spawn(move || {
+            let _ = outbox.send(
+                rfd::FileDialog::new()
+                    .add_filter("PNG", &["png"])
+                    .add_filter("SVG", &["svg"])
+                    .set_file_name("graph.png")
+                    .save_file(),
+            );
+        });
+    }
+
+    fn check_file_dialog(
+        &mut self,
+        ctx: &egui::Context,
+        snarl: &Snarl<NodeData>,
+        node_library: &NodeLibrary,
+    ) {
+        let Some(inbox) = &self.pending_file_dialog else {
+            return;
+        };
+        match inbox.check_non_blocking() {
+            Ok(Some(Some(path))) => {
+                self.pending_file_dialog = None;
+                self.status = Some("Exporting…".to_string());
+
+                let image = GraphImage::new(snarl, node_library);
+                let scale = self.scale;
+                let (inbox, outbox) = message_channel::new();
+                self.pending_save = Some(inbox);
+                std::thread::spawn(move || {
+                    let _ = outbox.send(image.save(&path, scale).map(|()| path));
+                });
+            }
+            Ok(Some(None)) | Err(_) => {
+                self.pending_file_dialog = None;
+            }
+            Ok(None) => ctx.request_repaint(),
+        }
+    }
+
+    fn check_save(&mut self, ctx: &egui::Context) {
+        let Some(inbox) = &self.pending_save else {
+            return;
+        };
+        match inbox.check_non_blocking() {
+            Ok(Some(result)) => {
+                self.status = Some(match result {
+                    Ok(path) => format!("Saved {}", path.display()),
+                    Err(e) => e,
+                });
+                self.pending_save = None;
+            }
+            Err(_) => {
+                self.status = Some("The export stopped unexpectedly".to_string());
+                self.pending_save = None;
+            }
+            Ok(None) => ctx.request_repaint(),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use engine::engine_outpost::message::{InfoRequest, InfoResponse};
+use engine::engine_outpost::{
+    EngineCommand, EngineCommandSender, EngineEventReceiver, EngineOutpostEvent,
+    EngineOutpostHandle, EventFilter, EventKind,
+};
+use engine::graph_executor::GraphExecutor;
+use engine::node::NodeLibrary;
+use engine::node_graph::{EngineNodeId, NodeGraph};
+use media::playback_stream::{BufferingPolicy, BufferingStats};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+/// How often node timings and buffering stats are asked for while the panel
+/// is open.
+const TIMINGS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The buffering policies that can be picked, with their labels.
+const BUFFERING_POLICIES: [(&str, BufferingPolicy); 4] = [
+    ("Adaptive", BufferingPolicy::Adaptive),
+    (
+        "Fixed (4 frames)",
+        BufferingPolicy::Fixed(NonZeroUsize::new(4).unwrap()),
+    ),
+    (
+        "Fixed (16 frames)",
+        BufferingPolicy::Fixed(NonZeroUsize::new(16).unwrap()),
+    ),
+    (
+        "Lookahead (500 ms)",
+        BufferingPolicy::Lookahead(Duration::from_millis(500)),
+    ),
+];
+
+/// The resolution texture memory and graph cost are estimated at when the
+/// project doesn't set one.
+pub const DEFAULT_RESOLUTION: (u32, u32) = (1920, 1080);
+
+/// A window summarizing the graph: how many nodes of each category it has,
+/// how much texture memory they use, its longest chain, nodes that don't
+/// reach the output, how long each node took last frame, and how far ahead
+/// video sources are decoding.
+pub struct GraphStatsPanel {
+    open: bool,
+    /// Where the engine's answers to timing and buffering requests come in.
+    events: Option<EngineEventReceiver>,
+    last_request: Option<Instant>,
+    timings: HashMap<EngineNodeId, Duration>,
+    buffering: HashMap<EngineNodeId, BufferingStats>,
+    buffering_policy: BufferingPolicy,
+}
+
+impl GraphStatsPanel {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            events: None,
+            last_request: None,
+            timings: HashMap::new(),
+            buffering: HashMap::new(),
+            buffering_policy: BufferingPolicy::default(),
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn init_engine(&mut self, handle: &EngineOutpostHandle) {
+        self.events = Some(handle.subscribe(EventFilter::Only(vec![EventKind::InfoResponse])));
+    }
+
+    /// Show the window if it's open. `graph` is the engine graph, with
+    /// `output` as its output, at the project's `resolution`.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        engine_tx: Option<&EngineCommandSender>,
+        graph: &NodeGraph,
+        output: Option<EngineNodeId>,
+        library: &NodeLibrary,
+        resolution: Option<(u32, u32)>,
+    ) {
+        // Answers are taken in while the window is closed too, so they don't
+        // pile up.
+        self.take_responses();
+        if !self.open {
+            return;
+        }
+        self.request_timings(ctx, engine_tx);
+
+        let mut open = self.open;
+        egui::Window::new("Graph Statistics")
+            .open(&mut open)
+            .default_size(egui::vec2(360.0, 420.0))
+            .resizable(true)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let stats = match GraphExecutor::stats(graph, library, output) {
+                    Ok(stats) => stats,
+                    Err(err) => {
+                        ui.label(format!("The graph can't be summarized: {err}"));
+                        return;
+                    }
+                };
+                let name = |node_id: &EngineNodeId| {
+                    graph
+                        .get_instance(*node_id)
+                        .map_or("Unknown", |instance| instance.definition_name.as_str())
+                };
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("graph_stats")
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label("Nodes");
+                            ui.label(stats.node_count().to_string());
+                            ui.end_row();
+                            for (category, count) in &stats.nodes_by_category {
+                                ui.label(format!("    {category}"));
+                                ui.label(count.to_string());
+                                ui.end_row();
+                            }
+
+                            let (width, height) = resolution.unwrap_or(DEFAULT_RESOLUTION);
+                            ui.label("Texture memory");
+                            ui.label(format!(
+                                "{:.1} MB at {width}x{height}",
+                                stats.cost.texture_memory_bytes(width, height) as f64 / 1_000_000.0
+                            ));
+                            ui.end_row();
+
+                            ui.label("Longest chain");
+                            ui.label(format!("{} node(s)", stats.longest_chain));
+                            ui.end_row();
+                        });
+
+                    ui.add_space(8.0);
+                    ui.strong("Dead branches");
+                    if stats.dead_nodes.is_empty() {
+                        ui.label("Every node contributes to the output.");
+                    } else {
+                        ui.label(format!(
+                            "{} node(s) don't contribute to the output:",
+                            stats.dead_nodes.len()
+                        ));
+                        for node_id in &stats.dead_nodes {
+                            ui.label(format!("    {}", name(node_id)));
+                        }
+                    }
+
+                    ui.add_space(8.0);
+                    ui.strong("Video buffering");
+                    self.show_buffering(ui, engine_tx, &name);
+
+                    ui.add_space(8.0);
+                    ui.strong("Last frame");
+                    if self.timings.is_empty() {
+                        ui.label("No nodes have run yet.");
+                        return;
+                    }
+                    let mut timings: Vec<_> = self.timings.iter().collect();
+                    timings.sort_by(|a, b| b.1.cmp(a.1));
+                    egui::Grid::new("graph_stats_timings")
+                        .num_columns(2)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (node_id, duration) in timings {
+                                ui.label(name(node_id));
+                                ui.label(format!("{:.2} ms", duration.as_secs_f64() * 1000.0));
+                                ui.end_row();
+                            }
+                            ui.strong("Total");
+                            ui.strong(format!(
+                                "{:.2} ms",
+                                self.timings.values().sum::<Duration>().as_secs_f64() * 1000.0
+                            ));
+                            ui.end_row();
+                        });
+                });
+            });
+        self.open = open;
+    }
+
+    /// The buffering policy picker and how full each video source's decode
+    /// buffer is.
+    fn show_buffering<'a>(
+        &mut self,
+        ui: &mut egui::Ui,
+        engine_tx: Option<&EngineCommandSender>,
+        name: &dyn Fn(&EngineNodeId) -> &'a str,
+    ) {
+        let selected = BUFFERING_POLICIES
+            .iter()
+            .find(|(_, policy)| *policy == self.buffering_policy)
+            .map_or("Custom", |(label, _)| label);
+        let mut picked = None;
+        egui::ComboBox::from_label("Policy")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for (label, policy) in BUFFERING_POLICIES {
+                    if ui
+                        .selectable_label(policy == self.buffering_policy, label)
+                        .clicked()
+                    {
+                        picked = Some(policy);
+                    }
+                }
+            });
+        if let Some(policy) = picked
+            && policy != self.buffering_policy
+        {
+            self.buffering_policy = policy;
+            if let Some(tx) = engine_tx
+                && let Err(err) = tx.send(EngineCommand::SetBufferingPolicy(policy))
+            {
+                util::debug_log_warning!("Failed to set the buffering policy: {err}");
+            }
+        }
+
+        if self.buffering.is_empty() {
+            ui.label("No video sources are playing.");
+            return;
+        }
+        let mut buffering: Vec<_> = self.buffering.iter().collect();
+        buffering.sort_by_key(|(node_id, _)| name(node_id));
+        egui::Grid::new("graph_stats_buffering")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for (node_id, stats) in buffering {
+                    ui.label(name(node_id));
+                    ui.add(
+                        egui::ProgressBar::new(stats.fill_level())
+                            .desired_width(100.0)
+                            .text(format!("{}/{}", stats.buffered, stats.target)),
+                    );
+                    ui.label(format!("{} underrun(s)", stats.underruns));
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Take in the latest node timings and buffering stats the engine
+    /// answered with, if it has.
+    fn take_responses(&mut self) {
+        let Some(events) = self.events.as_ref() else {
+            return;
+        };
+        for event in events.drain() {
+            match event {
+                EngineOutpostEvent::InfoResponse(InfoResponse::NodeTimings(timings)) => {
+                    self.timings = timings;
+                }
+                EngineOutpostEvent::InfoResponse(InfoResponse::BufferingStats(buffering)) => {
+                    self.buffering = buffering;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Ask the engine for node timings and buffering stats every
+    /// [TIMINGS_INTERVAL].
+    fn request_timings(&mut self, ctx: &egui::Context, engine_tx: Option<&EngineCommandSender>) {
+        if self
+            .last_request
+            .is_none_or(|last_request| last_request.elapsed() >= TIMINGS_INTERVAL)
+            && let Some(tx) = engine_tx
+        {
+            for request in [InfoRequest::NodeTimings, InfoRequest::BufferingStats] {
+                if let Err(err) = tx.send(EngineCommand::RequestInfo(request)) {
+                    util::debug_log_warning!("Failed to ask for graph stats: {err}");
+                }
+            }
+            self.last_request = Some(Instant::now());
+        }
+        ctx.request_repaint_after(TIMINGS_INTERVAL);
+    }
+}
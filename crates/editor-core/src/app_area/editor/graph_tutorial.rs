@@ -0,0 +1,142 @@
+use crate::app_settings::OnboardingSettings;
+use egui_phosphor::regular;
+
+/// A gesture the [GraphTutorial] teaches.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    Pan,
+    Zoom,
+    Connect,
+}
+
+/// The tutorial's steps, in order: the gesture, its icon, and how to do it.
+const STEPS: [(Gesture, &str, &str); 3] = [
+    (
+        Gesture::Pan,
+        regular::HAND_GRABBING,
+        "Drag the empty canvas to pan around",
+    ),
+    (
+        Gesture::Zoom,
+        regular::MAGNIFYING_GLASS_PLUS,
+        "Scroll or pinch to zoom in and out",
+    ),
+    (
+        Gesture::Connect,
+        regular::PLUGS_CONNECTED,
+        "Drag from an output pin to an input pin to connect two nodes",
+    ),
+];
+
+const ACCENT: egui::Color32 = egui::Color32::from_rgb(80, 160, 220);
+
+/// A first-run tutorial shown over the node graph. Each step is checked off
+/// once the user actually does its gesture, and the tutorial stays dismissed
+/// (in [OnboardingSettings]) once it's finished or skipped.
+pub struct GraphTutorial {
+    done: Vec<Gesture>,
+}
+
+impl GraphTutorial {
+    pub fn new() -> Self {
+        Self { done: Vec::new() }
+    }
+
+    /// Check off `gesture`'s step.
+    pub fn observe(&mut self, gesture: Gesture) {
+        if !self.done.contains(&gesture) {
+            self.done.push(gesture);
+        }
+    }
+
+    /// Show the tutorial at the bottom of `graph_rect`, unless it was
+    /// dismissed.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        graph_rect: egui::Rect,
+        onboarding: &mut OnboardingSettings,
+    ) {
+        if onboarding.graph_tutorial_dismissed {
+            return;
+        }
+
+        let current = STEPS
+            .iter()
+            .map(|(gesture, ..)| *gesture)
+            .find(|gesture| !self.done.contains(gesture));
+
+        egui::Area::new(egui::Id::new("graph_tutorial"))
+            .order(egui::Order::Foreground)
+            .pivot(egui::Align2::CENTER_BOTTOM)
+            .fixed_pos(egui::pos2(
+                graph_rect.center().x,
+                graph_rect.bottom() - 24.0,
+            ))
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(egui::Color32::from_rgb(24, 29, 31))
+                    .stroke(egui::Stroke::new(1.0, ACCENT))
+                    .corner_radius(6.0)
+                    .inner_margin(egui::Margin::same(12))
+                    .show(ui, |ui| {
+                        ui.set_max_width(380.0);
+                        ui.heading("Getting around the node graph");
+                        ui.add_space(6.0);
+
+                        for (gesture, icon, text) in STEPS {
+                            show_step(
+                                ui,
+                                icon,
+                                text,
+                                self.done.contains(&gesture),
+                                current == Some(gesture),
+                            );
+                        }
+
+                        ui.add_space(6.0);
+                        ui.horizontal(|ui| {
+                            if current.is_none() {
+                                ui.label("You're all set!");
+                                if ui.button("Done").clicked() {
+                                    onboarding.graph_tutorial_dismissed = true;
+                                }
+                            } else if ui.button("Skip tutorial").clicked() {
+                                onboarding.graph_tutorial_dismissed = true;
+                            }
+                        });
+                    });
+            });
+    }
+}
+
+/// One step's row. The current step is highlighted and done steps are
+/// checked off.
+fn show_step(ui: &mut egui::Ui, icon: &str, text: &str, done: bool, current: bool) {
+    let (icon, color) = if done {
+        (
+            regular::CHECK_CIRCLE,
+            egui::Color32::from_rgb(110, 200, 120),
+        )
+    } else if current {
+        (icon, ACCENT)
+    } else {
+        (icon, egui::Color32::GRAY)
+    };
+
+    egui::Frame::new()
+        .fill(if current {
+            egui::Color32::from_rgb(30, 44, 54)
+        } else {
+            egui::Color32::TRANSPARENT
+        })
+        .corner_radius(4.0)
+        .inner_margin(egui::Margin::symmetric(6, 4))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(icon).size(18.0).color(color));
+                let text = egui::RichText::new(text);
+                ui.label(if done { text.weak() } else { text.strong() });
+            });
+        });
+}
@@ -0,0 +1,408 @@
+use super::node_graph::show_help_contents;
+use engine::node::{NodeDefinition, NodeLibrary};
+
+/// The how-to topics in `help/`, each markdown titled by its first heading.
+const TOPICS: [&str; 5] = [
+    include_str!("../../../help/getting_started.md"),
+    include_str!("../../../help/editing_inputs.md"),
+    include_str!("../../../help/playback.md"),
+    include_str!("../../../help/scenes.md"),
+    include_str!("../../../help/exporting.md"),
+];
+
+/// The editor's keyboard shortcuts, and what they do.
+const SHORTCUTS: [(&str, &str); 6] = [
+    ("F1", "Open help"),
+    ("Ctrl+Z", "Undo an input change"),
+    ("Ctrl+Shift+Z", "Redo an input change"),
+    ("Escape", "Cancel dragging an input, or leave fullscreen"),
+    (",", "Step back a frame"),
+    (".", "Step forward a frame"),
+];
+
+const OPEN_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F1);
+
+#[derive(Debug, Clone, PartialEq)]
+enum HelpPage {
+    /// A how-to topic, by its index in [TOPICS].
+    Topic(usize),
+    Shortcuts,
+    /// A node's documentation, by its definition name.
+    Node(String),
+}
+
+impl HelpPage {
+    /// The heading results for this kind of page are listed under.
+    fn section(&self) -> &'static str {
+        match self {
+            Self::Topic(_) | Self::Shortcuts => "Guides",
+            Self::Node(_) => "Nodes",
+        }
+    }
+}
+
+/// A page of help and the text it's found by.
+struct HelpEntry {
+    page: HelpPage,
+    title: String,
+    /// Everything on the page, lowercase.
+    text: String,
+}
+
+/// A window for searching everything there is help for: each node's
+/// documentation (see [show_help_contents]), the keyboard shortcuts, and the
+/// how-to topics in `help/`. Opened with F1.
+pub struct HelpBrowser {
+    open: bool,
+    query: String,
+    /// Every page, rebuilt when the window opens so it has the current
+    /// nodes.
+    entries: Vec<HelpEntry>,
+    stale: bool,
+    selected: Option<HelpPage>,
+}
+
+impl HelpBrowser {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            entries: Vec::new(),
+            stale: true,
+            selected: Some(HelpPage::Topic(0)),
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.stale = true;
+    }
+
+    /// Show the window if it's open, or open it if F1 was pressed.
+    pub fn show(&mut self, ctx: &egui::Context, node_library: &NodeLibrary) {
+        if ctx.input_mut(|i| i.consume_shortcut(&OPEN_SHORTCUT)) {
+            self.open();
+        }
+        if !self.open {
+            return;
+        }
+        if self.stale {
+            self.entries = help_entries(node_library);
+            self.stale = false;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Help")
+            .open(&mut open)
+            .default_size(egui::vec2(640.0, 440.0))
+            .resizable(true)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::SidePanel::left("help_browser_results")
+                    .resizable(true)
+                    .default_width(200.0)
+                    .show_inside(ui, |ui| self.show_results(ui));
+                egui::CentralPanel::default().show_inside(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .id_salt("help_browser_page")
+                        .show(ui, |ui| self.show_page(ui, node_library));
+                });
+            });
+        self.open = open;
+    }
+
+    fn show_results(&mut self, ui: &mut egui::Ui) {
+        ui.add(
+            egui::TextEdit::singleline(&mut self.query)
+                .hint_text("Search help")
+                .desired_width(f32::INFINITY),
+        );
+        ui.separator();
+
+        let results = search(&self.entries, &self.query);
+        if results.is_empty() {
+            ui.label(egui::RichText::new("Nothing matches.").weak());
+            return;
+        }
+
+        egui::ScrollArea::vertical()
+            .id_salt("help_browser_results")
+            .show(ui, |ui| {
+                let mut section = None;
+                for index in results {
+                    let entry = &self.entries[index];
+                    if section != Some(entry.page.section()) {
+                        section = Some(entry.page.section());
+                        ui.add_space(4.0);
+                        ui.label(egui::RichText::new(entry.page.section()).weak().small());
+                    }
+                    let selected = self.selected.as_ref() == Some(&entry.page);
+                    if ui.selectable_label(selected, &entry.title).clicked() {
+                        self.selected = Some(entry.page.clone());
+                    }
+                }
+            });
+    }
+
+    fn show_page(&self, ui: &mut egui::Ui, node_library: &NodeLibrary) {
+        match &self.selected {
+            Some(HelpPage::Topic(index)) => show_markdown(ui, TOPICS[*index]),
+            Some(HelpPage::Shortcuts) => show_shortcuts(ui),
+            Some(HelpPage::Node(name)) => match node_library.get_definition(name) {
+                Some(definition) => show_help_contents(ui, definition),
+                None => {
+                    ui.label(egui::RichText::new("This node isn't in the library anymore.").weak());
+                }
+            },
+            None => {
+                ui.label(egui::RichText::new("Pick a page to read it.").weak());
+            }
+        }
+    }
+}
+
+/// Every page of help: the topics and shortcuts, then `node_library`'s nodes
+/// by name.
+fn help_entries(node_library: &NodeLibrary) -> Vec<HelpEntry> {
+    let mut entries: Vec<HelpEntry> = TOPICS
+        .iter()
+        .enumerate()
+        .map(|(index, topic)| HelpEntry {
+            page: HelpPage::Topic(index),
+            title: topic_title(topic).to_string(),
+            text: topic.to_lowercase(),
+        })
+        .collect();
+
+    entries.push(HelpEntry {
+        page: HelpPage::Shortcuts,
+        title: "Keyboard Shortcuts".to_string(),
+        text: SHORTCUTS
+            .iter()
+            .map(|(keys, action)| format!("{keys} {action}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .to_lowercase(),
+    });
+
+    let mut nodes: Vec<HelpEntry> = node_library
+        .definitions()
+        .iter()
+        .map(|(name, definition)| HelpEntry {
+            page: HelpPage::Node(name.clone()),
+            title: definition.node.name.clone(),
+            text: node_text(definition),
+        })
+        .collect();
+    nodes.sort_by(|a, b| a.title.cmp(&b.title));
+    entries.extend(nodes);
+
+    entries
+}
+
+/// Everything a node's help page says, lowercase.
+fn node_text(definition: &NodeDefinition) -> String {
+    let node = &definition.node;
+    let mut parts = vec![
+        node.name.as_str(),
+        node.category.as_str(),
+        node.short_description.as_str(),
+        node.long_description.as_str(),
+    ];
+    parts.extend(node.subcategories.iter().map(String::as_str));
+    parts.extend(node.search_keywords.iter().map(String::as_str));
+    for input in &node.inputs {
+        parts.extend([input.name.as_str(), input.help.as_str()]);
+    }
+    for output in &node.outputs {
+        parts.extend([output.name.as_str(), output.help.as_str()]);
+    }
+    parts.join("\n").to_lowercase()
+}
+
+/// The indices of the `entries` with every word of `query` in them, those with
+/// more of the words in their title first. An empty query matches everything.
+fn search(entries: &[HelpEntry], query: &str) -> Vec<usize> {
+    let query = query.to_lowercase();
+    let words: Vec<&str> = query.split_whitespace().collect();
+
+    let mut results: Vec<(usize, usize)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let title = entry.title.to_lowercase();
+            let mut title_matches = 0;
+            for word in &words {
+                if title.contains(word) {
+                    title_matches += 1;
+                } else if !entry.text.contains(word) {
+                    return None;
+                }
+            }
+            Some((index, title_matches))
+        })
+        .collect();
+    // Stable, so entries that match as well keep their order.
+    results.sort_by(|(_, a), (_, b)| b.cmp(a));
+    results.into_iter().map(|(index, _)| index).collect()
+}
+
+/// A topic's title: its first heading, or its first line if it has none.
+fn topic_title(topic: &str) -> &str {
+    topic
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .or_else(|| topic.lines().next())
+        .unwrap_or_default()
+        .trim()
+}
+
+#[derive(Debug, PartialEq)]
+enum MarkdownBlock {
+    Heading(String),
+    Subheading(String),
+    Bullet(String),
+    Paragraph(String),
+}
+
+/// Split the markdown the topics are written in into blocks: headings, list
+/// items, and paragraphs with their lines joined.
+fn markdown_blocks(markdown: &str) -> Vec<MarkdownBlock> {
+    let mut blocks = Vec::new();
+    // Whether the line before was part of a paragraph or list item, which a
+    // plain line continues.
+    let mut in_block = false;
+    for line in markdown.lines().map(str::trim) {
+        if line.is_empty() {
+            in_block = false;
+            continue;
+        }
+        let continues = in_block && !line.starts_with('#') && !line.starts_with("- ");
+        in_block = true;
+        match blocks.last_mut() {
+            Some(MarkdownBlock::Paragraph(text) | MarkdownBlock::Bullet(text)) if continues => {
+                text.push(' ');
+                text.push_str(line);
+                continue;
+            }
+            _ => {}
+        }
+        blocks.push(if let Some(heading) = line.strip_prefix("# ") {
+            MarkdownBlock::Heading(heading.to_string())
+        } else if let Some(heading) = line.strip_prefix("## ") {
+            MarkdownBlock::Subheading(heading.to_string())
+        } else if let Some(item) = line.strip_prefix("- ") {
+            MarkdownBlock::Bullet(item.to_string())
+        } else {
+            MarkdownBlock::Paragraph(line.to_string())
+        });
+    }
+    blocks
+}
+
+fn show_markdown(ui: &mut egui::Ui, markdown: &str) {
+    for block in markdown_blocks(markdown) {
+        match block {
+            MarkdownBlock::Heading(text) => {
+                ui.heading(text);
+                ui.separator();
+            }
+            MarkdownBlock::Subheading(text) => {
+                ui.add_space(8.0);
+                ui.label(egui::RichText::new(text).strong());
+            }
+            MarkdownBlock::Bullet(text) => {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("•");
+                    ui.label(text);
+                });
+            }
+            MarkdownBlock::Paragraph(text) => {
+                ui.label(text);
+                ui.add_space(4.0);
+            }
+        }
+    }
+}
+
+fn show_shortcuts(ui: &mut egui::Ui) {
+    ui.heading("Keyboard Shortcuts");
+    ui.separator();
+    egui::Grid::new("help_browser_shortcuts")
+        .num_columns(2)
+        .spacing([16.0, 6.0])
+        .striped(true)
+        .show(ui, |ui| {
+            for (keys, action) in SHORTCUTS {
+                ui.label(egui::RichText::new(keys).monospace());
+                ui.label(action);
+                ui.end_row();
+            }
+        });
+    ui.add_space(8.0);
+    ui.label(
+        egui::RichText::new("Shortcuts don't apply while typing in a text field.")
+            .weak()
+            .small(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, text: &str) -> HelpEntry {
+        HelpEntry {
+            page: HelpPage::Shortcuts,
+            title: title.to_string(),
+            text: text.to_lowercase(),
+        }
+    }
+
+    // --- search() ---
+
+    #[test]
+    fn search_needs_every_word_and_ranks_titles_first() {
+        let entries = [
+            entry("Blur", "softens a frame"),
+            entry("Exporting", "render a video with a blur"),
+            entry("Scenes", "recall inputs"),
+        ];
+
+        assert_eq!(search(&entries, ""), [0, 1, 2]);
+        assert_eq!(search(&entries, "BLUR"), [0, 1]);
+        assert_eq!(search(&entries, "blur video"), [1]);
+        assert!(search(&entries, "nothing").is_empty());
+    }
+
+    // --- markdown_blocks() ---
+
+    #[test]
+    fn markdown_blocks_joins_wrapped_lines() {
+        let markdown =
+            "# Title\n\nOne\nparagraph.\n\nAnother.\n\n## Steps\n\n- First\n  item\n- Second\n";
+
+        assert_eq!(
+            markdown_blocks(markdown),
+            [
+                MarkdownBlock::Heading("Title".to_string()),
+                MarkdownBlock::Paragraph("One paragraph.".to_string()),
+                MarkdownBlock::Paragraph("Another.".to_string()),
+                MarkdownBlock::Subheading("Steps".to_string()),
+                MarkdownBlock::Bullet("First item".to_string()),
+                MarkdownBlock::Bullet("Second".to_string()),
+            ]
+        );
+    }
+
+    // --- topic_title() ---
+
+    #[test]
+    fn every_topic_has_a_title() {
+        for topic in TOPICS {
+            assert!(!topic_title(topic).is_empty());
+            assert!(!topic_title(topic).starts_with('#'));
+        }
+    }
+}
@@ -1,13 +1,36 @@
 //! Node graph editor UI and synchronization with engine graph
 //! This module defines the state and UI for the node graph editor, as well as the logic to sync
 //! the snarl graph to the engine graph. It also includes validation logic for node connections and input values.
+//! The edits the viewer makes to the graph (connecting, splicing, deleting nodes) live in
+//! `graph_model`, apart from how the graph is drawn.
 mod colors;
+mod find_replace;
+mod globals;
+mod graph_image;
+mod graph_model;
 mod graph_sync;
 mod input_widgets;
+mod interaction_hints;
+mod node_help;
+mod recovery;
+mod scenes;
 mod validation;
 
+pub use find_replace::{
+    InputMatch, ReplaceEdit, find_file_references, find_inputs, parse_value_like, replace_inputs,
+    value_text,
+};
+pub use globals::{
+    ProjectGlobal, apply_global_overrides, is_valid_global_name, unused_global_name,
+};
+pub use graph_image::GraphImage;
+pub use graph_model::{InputChange, InputHistory, NodeIdMap};
 pub use graph_sync::{GraphSyncResult, sync_graph};
 pub use input_widgets::InputWidgetState;
+pub use interaction_hints::{InteractionHints, PinSpots};
+pub use node_help::show_help_contents;
+pub use recovery::recovery_summary;
+pub use scenes::{Scene, SceneFade, SceneValue};
 pub use validation::normalize_node_inputs;
 pub use validation::validate_midi_ports;
 pub use validation::validate_output_source;
@@ -15,22 +38,31 @@ pub use validation::validate_output_source;
 use egui;
 use egui::emath::TSTransform;
 use egui_snarl::ui::{PinInfo, SnarlViewer};
-use egui_snarl::{InPin, NodeId as SnarlNodeId, OutPin, Snarl};
-use engine::node::engine_node::{BuiltInHandler, NodeExecutionPlan, NodeOutputKind};
-use engine::node::{NodeInputKind, NodeLibrary, input_kind_to_output_kind};
-use engine::node_graph::{EngineNodeId, InputValue};
-use media::midi::streams::list_ports;
+use egui_snarl::{InPin, InPinId, NodeId as SnarlNodeId, OutPin, OutPinId, Snarl};
+use engine::export::RenderPass;
+use engine::node::engine_node::NodeOutputKind;
+use engine::node::{NodeInputKind, NodeLibrary};
+use engine::node_graph::{EngineNodeId, InputMapping, InputSmoothing, InputValue};
+use interaction_hints::{PinId, PinKind, TrackedPin};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 const VIRTUAL_OUTPUT_SINK_NAME: &str = "__virtual_output_sink__";
 
-fn are_pin_kinds_compatible(output_kind: NodeOutputKind, input_kind: &NodeInputKind) -> bool {
-    let expected_output_kind = input_kind_to_output_kind(input_kind);
-    output_kind == expected_output_kind
-        // Numeric widening: allow Int outputs to feed Float inputs.
-        || matches!((output_kind, input_kind), (NodeOutputKind::Int, NodeInputKind::Float { .. }))
+/// Whether the wire from `from` to `to` passes through `rect`. Snarl draws
+/// wires as curves that leave and enter pins horizontally, which this follows
+/// closely enough for a node-sized target.
+fn wire_crosses(from: egui::Pos2, to: egui::Pos2, rect: egui::Rect) -> bool {
+    const SAMPLES: usize = 24;
+    let bend = egui::vec2(((to.x - from.x).abs() / 2.0).max(40.0), 0.0);
+    let curve = egui::epaint::CubicBezierShape::from_points_stroke(
+        [from, from + bend, to - bend, to],
+        false,
+        egui::Color32::TRANSPARENT,
+        egui::Stroke::NONE,
+    );
+    (0..=SAMPLES).any(|i| rect.contains(curve.sample(i as f32 / SAMPLES as f32)))
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
@@ -53,14 +85,49 @@ impl GraphViewState {
     }
 }
 
+/// Project-level output resolution and frame rate. Sources are conformed to
+/// these by the engine; [None] fields follow the sources instead.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct OutputSettings {
+    /// Output width and height in pixels.
+    pub resolution: Option<(u32, u32)>,
+    /// Output frame rate as a `(numerator, denominator)` fraction.
+    pub fps: Option<(u32, u32)>,
+}
+
 /// Data associated with each node in the snarl graph, including its definition and configured input values
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct NodeData {
+    /// The node's ID, saved with the project so the node keeps it across
+    /// reloads. It's also the node's ID in the engine graph.
+    #[serde(default)]
+    pub id: EngineNodeId,
+
     pub definition_name: String,
     /// Configured input values for this node
     pub input_values: HashMap<String, InputValue>,
 
-    /// Engine node ID if this node is currently in the engine graph
+    /// Smoothing for connected Float inputs, by input name
+    #[serde(default)]
+    pub input_smoothing: HashMap<String, InputSmoothing>,
+
+    /// Value mappings for connected Float inputs, by input name
+    #[serde(default)]
+    pub input_mappings: HashMap<String, InputMapping>,
+
+    /// Frame outputs exported as render passes, by output name, with the
+    /// names of their passes
+    #[serde(default)]
+    pub render_passes: HashMap<String, String>,
+
+    /// Inputs bound to project globals (see [ProjectGlobal]), by input name,
+    /// with the names of their globals. A bound input takes its global's
+    /// value in place of its own.
+    #[serde(default)]
+    pub input_globals: HashMap<String, String>,
+
+    /// Engine node ID ([Self::id]) if this node is currently in the engine
+    /// graph
     #[serde(skip)]
     pub engine_node_id: Option<EngineNodeId>,
 }
@@ -72,6 +139,24 @@ pub struct NodeGraphState {
     #[serde(default)]
     pub graph_view: Option<GraphViewState>,
     pub legacy_graph_view_zoom: Option<f32>,
+    #[serde(default)]
+    pub output_settings: OutputSettings,
+    /// Snapshots of input values that can be recalled during a performance
+    #[serde(default)]
+    pub scenes: Vec<Scene>,
+    /// Whether the tempo clock syncs with other apps over Ableton Link
+    #[serde(default)]
+    pub link_enabled: bool,
+    /// Named values shared by the whole project, which inputs can be bound to
+    #[serde(default)]
+    pub globals: Vec<ProjectGlobal>,
+    /// Which nodes are in the engine graph, as of the last sync (see
+    /// [Self::set_engine_ids]).
+    #[serde(skip)]
+    engine_ids: NodeIdMap,
+    /// Changes made to node inputs this session, so they can be undone.
+    #[serde(skip)]
+    pub input_history: InputHistory,
 }
 
 /// Needed to impl this since [`Snarl<T>`] doesn't implement PartialEq.
@@ -97,6 +182,12 @@ impl NodeGraphState {
             snarl: Snarl::new(),
             graph_view: None,
             legacy_graph_view_zoom: None,
+            output_settings: OutputSettings::default(),
+            scenes: Vec::new(),
+            link_enabled: false,
+            globals: Vec::new(),
+            engine_ids: NodeIdMap::new(),
+            input_history: InputHistory::new(),
         };
 
         state.ensure_output_sink();
@@ -127,6 +218,36 @@ impl NodeGraphState {
                 key.hash(&mut hasher);
                 format!("{:?}", value).hash(&mut hasher); // Hash the Debug representation
             }
+
+            let mut smoothing_entries: Vec<_> = node.input_smoothing.iter().collect();
+            smoothing_entries.sort_by_key(|(k, _)| k.as_str());
+            for (key, smoothing) in smoothing_entries {
+                key.hash(&mut hasher);
+                format!("{:?}", smoothing).hash(&mut hasher);
+            }
+
+            let mut mapping_entries: Vec<_> = node.input_mappings.iter().collect();
+            mapping_entries.sort_by_key(|(k, _)| k.as_str());
+            for (key, mapping) in mapping_entries {
+                key.hash(&mut hasher);
+                format!("{:?}", mapping).hash(&mut hasher);
+            }
+
+            // Render pass nodes are synced even when they don't lead to the
+            // output, so marking one changes the engine graph (renaming doesn't).
+            let mut render_pass_outputs: Vec<_> = node.render_passes.keys().collect();
+            render_pass_outputs.sort();
+            render_pass_outputs.hash(&mut hasher);
+
+            let mut global_entries: Vec<_> = node.input_globals.iter().collect();
+            global_entries.sort();
+            global_entries.hash(&mut hasher);
+        }
+
+        // Bound and text inputs take their values from the globals
+        for global in &self.globals {
+            global.name.hash(&mut hasher);
+            format!("{:?}", global.value).hash(&mut hasher);
         }
 
         // Hash all wires (connections)
@@ -142,6 +263,24 @@ impl NodeGraphState {
         Some(hasher.finish())
     }
 
+    /// Rename the global named `from` to `to`, keeping the inputs bound to it
+    /// bound.
+    pub fn rename_global(&mut self, from: &str, to: &str) {
+        let Some(global) = self.globals.iter_mut().find(|global| global.name == from) else {
+            return;
+        };
+        global.name = to.to_string();
+
+        let node_ids: Vec<SnarlNodeId> = self.snarl.node_ids().map(|(id, _)| id).collect();
+        for node_id in node_ids {
+            for global_name in self.snarl[node_id].input_globals.values_mut() {
+                if global_name == from {
+                    *global_name = to.to_string();
+                }
+            }
+        }
+    }
+
     pub fn ensure_output_sink(&mut self) {
         let has_sink = self
             .snarl
@@ -155,8 +294,13 @@ impl NodeGraphState {
         self.snarl.insert_node(
             egui::pos2(880.0, 220.0),
             NodeData {
+                id: EngineNodeId::default(),
                 definition_name: VIRTUAL_OUTPUT_SINK_NAME.to_string(),
                 input_values: HashMap::new(),
+                input_smoothing: HashMap::new(),
+                input_mappings: HashMap::new(),
+                render_passes: HashMap::new(),
+                input_globals: HashMap::new(),
                 engine_node_id: None,
             },
         );
@@ -175,6 +319,40 @@ impl NodeGraphState {
             .wires()
             .find_map(|(from, to)| (to.node == sink).then_some(from.node))
     }
+
+    /// Record which nodes are in the engine graph after a sync, setting each
+    /// node's [NodeData::engine_node_id] (and clearing it for nodes that
+    /// aren't in the graph anymore).
+    pub fn set_engine_ids(&mut self, engine_ids: NodeIdMap) {
+        let node_ids: Vec<SnarlNodeId> = self.snarl.node_ids().map(|(id, _)| id).collect();
+        for node_id in node_ids {
+            self.snarl[node_id].engine_node_id = engine_ids.engine_id(node_id);
+        }
+        self.engine_ids = engine_ids;
+    }
+
+    /// Which nodes are in the engine graph, as of the last sync.
+    pub fn engine_ids(&self) -> &NodeIdMap {
+        &self.engine_ids
+    }
+
+    /// The render passes marked on nodes that are in the engine graph, by
+    /// name.
+    pub fn render_passes(&self) -> Vec<RenderPass> {
+        let mut passes = Vec::new();
+        for (_, node) in self.snarl.node_ids() {
+            let Some(node_id) = node.engine_node_id else {
+                continue;
+            };
+            passes.extend(node.render_passes.iter().map(|(output, name)| RenderPass {
+                name: name.trim().to_string(),
+                node_id,
+                output: output.clone(),
+            }));
+        }
+        passes.sort_by(|a, b| a.name.cmp(&b.name));
+        passes
+    }
 }
 
 impl Default for NodeGraphState {
@@ -192,12 +370,40 @@ pub struct NodeGraphViewer<'a> {
     apply_initial_graph_view: bool,
     latest_graph_view: Option<GraphViewState>,
     reset_view_requested: bool,
+    help_requested: Option<String>,
+    /// A node whose output should be saved as a PNG, and its title.
+    save_output_requested: Option<(EngineNodeId, String)>,
+    /// Nodes that are evaluated even when they don't lead to the output.
+    forced_nodes: HashSet<SnarlNodeId>,
+    /// Where pins are recorded as they're drawn (see [InteractionHints]).
+    pin_spots: PinSpots,
+    /// Whether a connection was made this frame.
+    connected: bool,
+    /// The ID the graph's [SnarlWidget](egui_snarl::ui::SnarlWidget) was
+    /// given, which the IDs snarl gives nodes are made from.
+    graph_id: egui::Id,
+    /// A node that was dropped after being dragged this frame, and where it
+    /// ended up (in screen space).
+    dropped_node: Option<(SnarlNodeId, egui::Rect)>,
+    /// Where each node was drawn (in screen space) this frame.
+    node_rects: HashMap<SnarlNodeId, egui::Rect>,
+    /// Changes made to inputs this frame, a whole drag at a time.
+    input_changes: Vec<InputChange>,
+    /// Whether the add node menu lists the nodes added most first, by how
+    /// many times each was added (by definition name).
+    sort_by_use: bool,
+    node_uses: Option<&'a HashMap<String, u64>>,
+    /// The definition names of nodes added from the menu this frame.
+    added_nodes: Vec<String>,
+    /// The project's globals, which inputs can be bound to.
+    globals: Vec<ProjectGlobal>,
 }
 
 impl<'a> NodeGraphViewer<'a> {
     pub fn new(
         node_library: Arc<NodeLibrary>,
         input_widget_state: &'a mut input_widgets::InputWidgetState,
+        pin_spots: PinSpots,
     ) -> Self {
         Self {
             node_library,
@@ -208,9 +414,52 @@ impl<'a> NodeGraphViewer<'a> {
             apply_initial_graph_view: false,
             latest_graph_view: None,
             reset_view_requested: false,
+            help_requested: None,
+            save_output_requested: None,
+            forced_nodes: HashSet::new(),
+            pin_spots,
+            connected: false,
+            graph_id: egui::Id::NULL,
+            dropped_node: None,
+            node_rects: HashMap::new(),
+            input_changes: Vec::new(),
+            sort_by_use: false,
+            globals: Vec::new(),
+            node_uses: None,
+            added_nodes: Vec::new(),
         }
     }
 
+    /// Set how the add node menu is sorted: alphabetically, or with the nodes
+    /// in `node_uses` added most first if `sort_by_use` is set.
+    pub fn set_node_order(&mut self, sort_by_use: bool, node_uses: &'a HashMap<String, u64>) {
+        self.sort_by_use = sort_by_use;
+        self.node_uses = Some(node_uses);
+    }
+
+    /// Set the project globals inputs can be bound to.
+    pub fn set_globals(&mut self, globals: Vec<ProjectGlobal>) {
+        self.globals = globals;
+    }
+
+    /// Whether the add node menu sorts by use, after it was toggled from the
+    /// menu.
+    pub fn sort_by_use(&self) -> bool {
+        self.sort_by_use
+    }
+
+    /// The definition names of nodes added from the menu this frame.
+    pub fn take_added_nodes(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.added_nodes)
+    }
+
+    /// Set the ID the graph's [SnarlWidget](egui_snarl::ui::SnarlWidget) was
+    /// given, so nodes dropped onto wires can be spliced into them (see
+    /// [Self::splice_dropped_node]).
+    pub fn set_graph_id(&mut self, graph_id: egui::Id) {
+        self.graph_id = graph_id;
+    }
+
     pub fn set_initial_graph_view(
         &mut self,
         view: Option<GraphViewState>,
@@ -230,6 +479,93 @@ impl<'a> NodeGraphViewer<'a> {
         std::mem::take(&mut self.reset_view_requested)
     }
 
+    /// The definition name of a node whose help was requested from its menu.
+    pub fn take_help_requested(&mut self) -> Option<String> {
+        self.help_requested.take()
+    }
+
+    /// The engine ID and title of a node whose output was asked to be saved
+    /// from its menu.
+    pub fn take_save_output_requested(&mut self) -> Option<(EngineNodeId, String)> {
+        self.save_output_requested.take()
+    }
+
+    pub fn set_forced_nodes(&mut self, forced_nodes: HashSet<SnarlNodeId>) {
+        self.forced_nodes = forced_nodes;
+    }
+
+    /// The forced nodes, after any were toggled from their menus.
+    pub fn take_forced_nodes(&mut self) -> HashSet<SnarlNodeId> {
+        std::mem::take(&mut self.forced_nodes)
+    }
+
+    /// Where each node was drawn (in screen space) this frame.
+    pub fn take_node_rects(&mut self) -> HashMap<SnarlNodeId, egui::Rect> {
+        std::mem::take(&mut self.node_rects)
+    }
+
+    /// The changes made to inputs since the last call, for undoing.
+    pub fn take_input_changes(&mut self) -> Vec<InputChange> {
+        std::mem::take(&mut self.input_changes)
+    }
+
+    /// Keep track of a change made to `input` of `node_id` by its widget,
+    /// which was set to `before` when the widget was shown. Changes made by
+    /// dragging are previewed until the drag ends (or are put back if Esc is
+    /// pressed), then recorded as one change.
+    fn track_input_change(
+        &mut self,
+        ui: &egui::Ui,
+        snarl: &mut Snarl<NodeData>,
+        node_id: SnarlNodeId,
+        input: &str,
+        before: Option<InputValue>,
+        response: Option<egui::Response>,
+    ) {
+        let state = &mut *self.input_widget_state;
+        if response
+            .as_ref()
+            .is_some_and(|response| response.drag_started())
+        {
+            state.input_edit = Some(graph_model::InputEdit::begin(node_id, input, before));
+        } else if !state
+            .input_edit
+            .as_ref()
+            .is_some_and(|edit| edit.is_for(node_id, input))
+        {
+            let after = snarl[node_id].input_values.get(input).cloned();
+            if after != before {
+                self.input_changes.push(InputChange {
+                    node_id,
+                    input: input.to_string(),
+                    before,
+                    after,
+                });
+            }
+            return;
+        }
+
+        let Some(edit) = state.input_edit.as_mut() else {
+            return;
+        };
+        if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            edit.cancel(snarl);
+        } else if let Some(value) = snarl[node_id].input_values.get(input).cloned() {
+            edit.preview(snarl, value);
+        }
+
+        if !response.is_some_and(|response| response.dragged())
+            && let Some(change) = state.input_edit.take().and_then(|edit| edit.commit(snarl))
+        {
+            self.input_changes.push(change);
+        }
+    }
+
+    /// Whether a connection was made since the last call.
+    pub fn take_connected(&mut self) -> bool {
+        std::mem::take(&mut self.connected)
+    }
+
     pub fn take_pending_errors(&mut self) -> Vec<String> {
         std::mem::take(&mut self.pending_errors)
     }
@@ -238,27 +574,50 @@ impl<'a> NodeGraphViewer<'a> {
         self.pending_errors.push(msg.into());
     }
 
-    /// Simple DFS to check if connecting would create a cycle in the graph
-    fn would_create_cycle(snarl: &Snarl<NodeData>, from: SnarlNodeId, to: SnarlNodeId) -> bool {
-        let mut stack = vec![to];
-        let mut visited = std::collections::HashSet::new();
+    fn track_pin(&self, info: PinInfo, pin: PinId, kind: Option<PinKind>) -> TrackedPin {
+        TrackedPin::new(info, pin, kind, &self.pin_spots)
+    }
 
-        while let Some(node) = stack.pop() {
-            if !visited.insert(node) {
-                continue;
-            }
-            if node == from {
-                return true;
-            }
+    /// A wire that passes through `rect` (in screen space) and doesn't touch
+    /// `node`.
+    fn wire_under(
+        &self,
+        snarl: &Snarl<NodeData>,
+        node: SnarlNodeId,
+        rect: egui::Rect,
+    ) -> Option<(OutPinId, InPinId)> {
+        snarl
+            .wires()
+            .filter(|(from, to)| from.node != node && to.node != node)
+            .find(|&(from, to)| {
+                let from_pos = self.pin_spots.center(PinId::Output(from));
+                let to_pos = self.pin_spots.center(PinId::Input(to));
+                from_pos
+                    .zip(to_pos)
+                    .is_some_and(|(from_pos, to_pos)| wire_crosses(from_pos, to_pos, rect))
+            })
+    }
 
-            for (wire_from, wire_to) in snarl.wires() {
-                if wire_from.node == node {
-                    stack.push(wire_to.node);
-                }
-            }
+    /// If a node without any wires was dropped onto a wire this frame, insert
+    /// it into the wire, connecting the first of its inputs and outputs that
+    /// fit. Call after the graph is shown, once every pin has been drawn.
+    pub fn splice_dropped_node(&mut self, snarl: &mut Snarl<NodeData>) {
+        let Some((node, rect)) = self.dropped_node.take() else {
+            return;
+        };
+        if snarl
+            .wires()
+            .any(|(from, to)| from.node == node || to.node == node)
+        {
+            return;
+        }
+        let Some((from, to)) = self.wire_under(snarl, node, rect) else {
+            return;
+        };
+        match graph_model::splice(snarl, &self.node_library, node, from, to) {
+            Ok(spliced) => self.connected |= spliced,
+            Err(message) => self.push_error(message),
         }
-
-        false
     }
 }
 
@@ -276,6 +635,27 @@ impl SnarlViewer<NodeData> for NodeGraphViewer<'_> {
         self.latest_graph_view = Some(GraphViewState::from_transform(*to_global));
     }
 
+    fn final_node_rect(
+        &mut self,
+        node: SnarlNodeId,
+        rect: egui::Rect,
+        ui: &mut egui::Ui,
+        _snarl: &mut Snarl<NodeData>,
+    ) {
+        // Like pins, nodes are drawn in graph space.
+        let screen_rect = ui
+            .ctx()
+            .layer_transform_to_global(ui.layer_id())
+            .map_or(rect, |to_global| to_global * rect);
+        self.node_rects.insert(node, screen_rect);
+
+        // Snarl drags nodes by their frame, which it gives this ID.
+        let frame_id = self.graph_id.with(("snarl-node", node)).with("frame");
+        if ui.ctx().drag_stopped_id() == Some(frame_id) {
+            self.dropped_node = Some((node, screen_rect));
+        }
+    }
+
     fn title(&mut self, node: &NodeData) -> String {
         if node.definition_name == VIRTUAL_OUTPUT_SINK_NAME {
             return "Output".to_string();
@@ -318,14 +698,21 @@ impl SnarlViewer<NodeData> for NodeGraphViewer<'_> {
         let node_name = snarl[pin.id.node].definition_name.clone();
         if node_name == VIRTUAL_OUTPUT_SINK_NAME {
             ui.label("Output");
-            return PinInfo::circle().with_fill(colors::input_kind_color(&NodeInputKind::Frame));
+            return self.track_pin(
+                PinInfo::circle().with_fill(colors::input_kind_color(&NodeInputKind::Frame)),
+                PinId::Input(pin.id),
+                Some(PinKind::Input(NodeInputKind::Frame)),
+            );
         }
 
         if let Some(def) = self.node_library.get_definition(&node_name)
             && let Some(input_def) = def.node.inputs.get(pin.id.input)
         {
             let mut missing_file_error = None;
-            ui.label(&input_def.name);
+            let mut label = ui.label(&input_def.name);
+            if let Some(hover_text) = node_help::input_hover_text(input_def) {
+                label = label.on_hover_text(hover_text);
+            }
 
             // If the definition is file check to make sure the file exists
             if let engine::node::NodeInputKind::File { .. } = input_def.kind
@@ -348,32 +735,71 @@ impl SnarlViewer<NodeData> for NodeGraphViewer<'_> {
             // Show input configuration UI if no connection
             if pin.remotes.is_empty() {
                 let node_data = &mut snarl[pin.id.node];
-                input_widgets::show_input_widget(
-                    ui,
-                    &mut node_data.input_values,
+                input_widgets::show_global_menu(
+                    &label,
+                    &mut node_data.input_globals,
                     input_def,
-                    &node_name,
-                    &self.node_library,
-                    pin.id.node,
-                    self.input_widget_state,
+                    &self.globals,
                 );
+                if let Some(global_name) = node_data.input_globals.get(&input_def.name) {
+                    input_widgets::show_bound_input(ui, global_name, &self.globals);
+                } else {
+                    let before = node_data.input_values.get(&input_def.name).cloned();
+                    let response = input_widgets::show_input_widget(
+                        ui,
+                        &mut node_data.input_values,
+                        input_def,
+                        &node_name,
+                        &self.node_library,
+                        pin.id.node,
+                        self.input_widget_state,
+                    );
+                    self.track_input_change(
+                        ui,
+                        snarl,
+                        pin.id.node,
+                        &input_def.name,
+                        before,
+                        response,
+                    );
+                }
             } else if let Some(remote) = pin.remotes.first() {
                 // Show connected value
                 let remote_node = &snarl[remote.node];
                 ui.label(format!("Connected to {}", remote_node.definition_name));
+                if matches!(input_def.kind, NodeInputKind::Float { .. }) {
+                    let node_data = &mut snarl[pin.id.node];
+                    ui.horizontal(|ui| {
+                        input_widgets::show_mapping_menu(
+                            ui,
+                            &mut node_data.input_mappings,
+                            &input_def.name,
+                        );
+                        input_widgets::show_smoothing_menu(
+                            ui,
+                            &mut node_data.input_smoothing,
+                            &input_def.name,
+                        );
+                    });
+                }
             }
 
             let color = colors::input_kind_color(&input_def.kind);
+            let kind = PinKind::Input(input_def.kind.clone());
 
             if let Some(error) = missing_file_error {
                 self.push_error(error);
             }
 
-            return PinInfo::circle().with_fill(color);
+            return self.track_pin(
+                PinInfo::circle().with_fill(color),
+                PinId::Input(pin.id),
+                Some(kind),
+            );
         }
 
         ui.label("input");
-        PinInfo::circle()
+        self.track_pin(PinInfo::circle(), PinId::Input(pin.id), None)
     }
 
     fn show_output(
@@ -385,19 +811,26 @@ impl SnarlViewer<NodeData> for NodeGraphViewer<'_> {
         let node_name = &snarl[pin.id.node].definition_name;
         if node_name == VIRTUAL_OUTPUT_SINK_NAME {
             ui.label("output");
-            return PinInfo::circle();
+            return self.track_pin(PinInfo::circle(), PinId::Output(pin.id), None);
         }
 
         if let Some(def) = self.node_library.get_definition(node_name)
             && let Some(output_def) = def.node.outputs.get(pin.id.output)
         {
-            ui.label(&output_def.name);
+            let label = ui.label(&output_def.name);
+            if let Some(hover_text) = node_help::output_hover_text(output_def) {
+                label.on_hover_text(hover_text);
+            }
             let color = colors::output_kind_color(&output_def.kind);
-            return PinInfo::circle().with_fill(color);
+            return self.track_pin(
+                PinInfo::circle().with_fill(color),
+                PinId::Output(pin.id),
+                Some(PinKind::Output(output_def.kind)),
+            );
         }
 
         ui.label("output");
-        PinInfo::circle()
+        self.track_pin(PinInfo::circle(), PinId::Output(pin.id), None)
     }
 
     fn has_graph_menu(&mut self, _pos: egui::Pos2, _snarl: &mut Snarl<NodeData>) -> bool {
@@ -406,6 +839,8 @@ impl SnarlViewer<NodeData> for NodeGraphViewer<'_> {
 
     fn show_graph_menu(&mut self, pos: egui::Pos2, ui: &mut egui::Ui, snarl: &mut Snarl<NodeData>) {
         ui.label("Add Node");
+        ui.checkbox(&mut self.sort_by_use, "Most used first")
+            .on_hover_text("List the nodes you add most first. Only counted on this machine.");
         ui.separator();
 
         // This should be moved somewhere else that makese sense I was just playing around with it.
@@ -422,26 +857,22 @@ impl SnarlViewer<NodeData> for NodeGraphViewer<'_> {
             .show(ui, |ui| {
                 let mut definitions: Vec<_> = self.node_library.definitions().iter().collect();
                 definitions.sort_by(|(_, a), (_, b)| a.node.name.cmp(&b.node.name));
+                if self.sort_by_use
+                    && let Some(node_uses) = self.node_uses
+                {
+                    // Stable, so nodes added as often stay alphabetical.
+                    definitions.sort_by_key(|(definition_name, _)| {
+                        std::cmp::Reverse(node_uses.get(*definition_name).copied().unwrap_or(0))
+                    });
+                }
 
                 for (definition_name, definition) in definitions {
-                    if ui.button(&definition.node.name).clicked() {
-                        let input_values = definition
-                            .node
-                            .inputs
-                            .iter()
-                            .filter_map(|input_def| {
-                                let value = validation::default_input_value(input_def)?;
-                                Some((input_def.name.clone(), value))
-                            })
-                            .collect();
-                        snarl.insert_node(
-                            pos,
-                            NodeData {
-                                definition_name: definition_name.clone(),
-                                input_values,
-                                engine_node_id: None,
-                            },
-                        );
+                    let button = ui
+                        .button(&definition.node.name)
+                        .on_hover_ui(|ui| node_help::definition_tooltip(ui, definition));
+                    if button.clicked() {
+                        snarl.insert_node(pos, graph_model::new_node(definition_name, definition));
+                        self.added_nodes.push(definition_name.clone());
                         ui.close();
                     }
                 }
@@ -465,110 +896,92 @@ impl SnarlViewer<NodeData> for NodeGraphViewer<'_> {
             return;
         }
 
-        if ui.button("Delete Node").clicked() {
-            snarl.remove_node(node_id);
+        if ui.button("Help").clicked() {
+            self.help_requested = Some(snarl[node_id].definition_name.clone());
             ui.close();
         }
-    }
-
-    fn connect(&mut self, from: &OutPin, to: &InPin, snarl: &mut Snarl<NodeData>) {
-        // Validate connection types
-        let from_node = &snarl[from.id.node];
-        let to_node = &snarl[to.id.node];
-
-        if from.id.node == to.id.node {
-            // Prevent self-connection
-            self.push_error("A node cannot be connected to itself.");
-            return;
-        }
 
-        if Self::would_create_cycle(snarl, from.id.node, to.id.node) {
-            self.push_error("Connecting these nodes would create a cycle.");
-            return;
+        let frame_outputs: Vec<String> = self
+            .node_library
+            .get_definition(&snarl[node_id].definition_name)
+            .map(|definition| {
+                definition
+                    .node
+                    .outputs
+                    .iter()
+                    .filter(|output| matches!(output.kind, NodeOutputKind::Frame))
+                    .map(|output| output.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !frame_outputs.is_empty() {
+            let title = self.title(&snarl[node_id]);
+            if let Some(engine_node_id) = snarl[node_id].engine_node_id
+                && ui.button("Save Node Output as PNG…").clicked()
+            {
+                self.save_output_requested = Some((engine_node_id, title.clone()));
+                ui.close();
+            }
+            let mut forced = self.forced_nodes.contains(&node_id);
+            if ui
+                .checkbox(&mut forced, "Always Evaluate")
+                .on_hover_text("Keep running this node even when it doesn't lead to the output")
+                .changed()
+            {
+                if forced {
+                    self.forced_nodes.insert(node_id);
+                } else {
+                    self.forced_nodes.remove(&node_id);
+                }
+            }
+            ui.menu_button("Render Passes", |ui| {
+                let node = &mut snarl[node_id];
+                for output in &frame_outputs {
+                    let mut exported = node.render_passes.contains_key(output);
+                    if ui
+                        .checkbox(&mut exported, format!("Export {output}"))
+                        .changed()
+                    {
+                        if exported {
+                            let name = if frame_outputs.len() == 1 {
+                                title.clone()
+                            } else {
+                                format!("{title} {output}")
+                            };
+                            node.render_passes.insert(output.clone(), name);
+                        } else {
+                            node.render_passes.remove(output);
+                        }
+                    }
+                    if let Some(name) = node.render_passes.get_mut(output) {
+                        ui.horizontal(|ui| {
+                            ui.label("Pass name");
+                            ui.text_edit_singleline(name);
+                        });
+                    }
+                }
+            });
         }
 
-        let Some(from_def) = self.node_library.get_definition(&from_node.definition_name) else {
-            return;
-        };
-
-        let sink_target = to_node.definition_name == VIRTUAL_OUTPUT_SINK_NAME;
-        let to_def = if sink_target {
-            None
-        } else {
-            self.node_library.get_definition(&to_node.definition_name)
-        };
-
-        if !sink_target && to_def.is_none() {
-            return;
+        if ui.button("Delete Node").clicked() {
+            snarl.remove_node(node_id);
+            ui.close();
         }
-
-        if sink_target
-            && let Err(message) = validate_output_source(snarl, from.id.node, &self.node_library)
+        if ui
+            .button("Delete and Reconnect")
+            .on_hover_text("Delete the node, connecting what went into it to what it went into")
+            .clicked()
         {
-            self.push_error(message);
-            return;
-        }
-
-        let Some(from_output) = from_def.node.outputs.get(from.id.output) else {
-            return;
-        };
-
-        let to_input_kind = if sink_target {
-            if to.id.input != 0 {
-                return;
-            }
-            NodeInputKind::Frame
-        } else {
-            let to_def = to_def.expect("checked above");
-            let Some(to_input) = to_def.node.inputs.get(to.id.input) else {
-                return;
-            };
-            to_input.kind.clone()
-        };
-
-        let to_input_name = if sink_target {
-            "Output".to_string()
-        } else {
-            let to_def = to_def.expect("checked above");
-            let Some(to_input) = to_def.node.inputs.get(to.id.input) else {
-                return;
-            };
-            to_input.name.clone()
-        };
-
-        // Check if types are compatible
-        if !are_pin_kinds_compatible(from_output.kind, &to_input_kind) {
-            self.push_error(format!(
-                "Cannot connect '{}' to '{}': incompatible pin types.",
-                from_output.name, to_input_name
-            ));
-            return;
+            graph_model::remove_and_heal(snarl, &self.node_library, node_id);
+            ui.close();
         }
+    }
 
-        if matches!(
-            from_def.node.executor,
-            NodeExecutionPlan::BuiltIn(BuiltInHandler::MidiSource)
-        ) {
-            let has_midi_ports = match list_ports() {
-                Ok(ports) => ports.count() > 0,
-                Err(_) => false,
-            };
-
-            if !has_midi_ports {
-                self.push_error(
-                    "Cannot connect MIDI node: no MIDI input port is selected or available.",
-                );
-                return;
-            }
+    fn connect(&mut self, from: &OutPin, to: &InPin, snarl: &mut Snarl<NodeData>) {
+        match graph_model::connect(snarl, &self.node_library, from.id, to.id) {
+            Ok(connected) => self.connected |= connected,
+            Err(message) => self.push_error(message),
         }
-
-        // Enforce one incoming connection per input pin.
-        // Snarl allows multiple wires per input by default, so we replace
-        // existing input wires before connecting the new source.
-        snarl.drop_inputs(to.id);
-
-        // Types match - create the connection
-        snarl.connect(from.id, to.id);
     }
 
     fn drop_inputs(&mut self, pin: &InPin, snarl: &mut Snarl<NodeData>) {
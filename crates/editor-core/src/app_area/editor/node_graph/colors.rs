@@ -18,5 +18,6 @@ pub fn output_kind_color(kind: &NodeOutputKind) -> egui::Color32 {
         NodeOutputKind::Dimensions => egui::Color32::from_rgb(200, 100, 200),
         NodeOutputKind::Pixel => egui::Color32::from_rgb(150, 150, 150),
         NodeOutputKind::Text => egui::Color32::from_rgb(255, 165, 0),
+        NodeOutputKind::FloatArray => egui::Color32::from_rgb(60, 60, 160),
     }
 }
@@ -0,0 +1,259 @@
+//! Finding node inputs across a graph and replacing them in one batch (e.g.
+//! swapping every use of one video for another), with a record of each batch
+//! so it can be undone.
+
+use super::{NodeData, VIRTUAL_OUTPUT_SINK_NAME};
+use egui_snarl::{NodeId as SnarlNodeId, Snarl};
+use engine::node_graph::InputValue;
+use std::path::{Path, PathBuf};
+
+/// An input that matched a search, with the value it had when it was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputMatch {
+    pub node: SnarlNodeId,
+    pub input_name: String,
+    pub value: InputValue,
+}
+
+/// Every configured input in `snarl` that `predicate` accepts, given the node,
+/// the input's name, and its value. Ordered by node, then input name.
+/// Connections aren't configured values, so they're never matched.
+pub fn find_inputs(
+    snarl: &Snarl<NodeData>,
+    mut predicate: impl FnMut(&NodeData, &str, &InputValue) -> bool,
+) -> Vec<InputMatch> {
+    let mut matches = Vec::new();
+    for (node_id, node) in snarl.node_ids() {
+        if node.definition_name == VIRTUAL_OUTPUT_SINK_NAME {
+            continue;
+        }
+
+        for (input_name, value) in &node.input_values {
+            if matches!(value, InputValue::Connection { .. }) {
+                continue;
+            }
+            if predicate(node, input_name, value) {
+                matches.push(InputMatch {
+                    node: node_id,
+                    input_name: input_name.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+    }
+
+    matches.sort_by(|a, b| (a.node, &a.input_name).cmp(&(b.node, &b.input_name)));
+    matches
+}
+
+/// Every File input in `snarl` set to `path`.
+pub fn find_file_references(snarl: &Snarl<NodeData>, path: &Path) -> Vec<InputMatch> {
+    find_inputs(
+        snarl,
+        |_, _, value| matches!(value, InputValue::File(file) if file == path),
+    )
+}
+
+/// How `value` is shown and searched as text, or [None] for values that
+/// can't be (like pixels and enum choices).
+pub fn value_text(value: &InputValue) -> Option<String> {
+    match value {
+        InputValue::Bool(value) => Some(value.to_string()),
+        InputValue::Int(value) => Some(value.to_string()),
+        InputValue::Float(value) => Some(value.to_string()),
+        InputValue::Text(text) => Some(text.clone()),
+        InputValue::File(path) => Some(path.display().to_string()),
+        InputValue::FloatArray(values) => Some(float_array_text(values)),
+        _ => None,
+    }
+}
+
+/// Parse `text` as a value of the same kind as `like`, or [None] if it isn't
+/// one (or `like` isn't a kind [value_text] handles).
+pub fn parse_value_like(like: &InputValue, text: &str) -> Option<InputValue> {
+    match like {
+        InputValue::Bool(_) => text.trim().parse().ok().map(InputValue::Bool),
+        InputValue::Int(_) => text.trim().parse().ok().map(InputValue::Int),
+        InputValue::Float(_) => text.trim().parse().ok().map(InputValue::Float),
+        InputValue::Text(_) => Some(InputValue::Text(text.to_string())),
+        InputValue::File(_) => Some(InputValue::File(PathBuf::from(text.trim()))),
+        InputValue::FloatArray(_) => parse_float_array(text).map(InputValue::FloatArray),
+        _ => None,
+    }
+}
+
+/// A float array as comma separated numbers (e.g. "0.5, 1, 2").
+pub fn float_array_text(values: &[f32]) -> String {
+    values
+        .iter()
+        .map(f32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parse comma separated numbers (see [float_array_text]), or [None] if any
+/// of them isn't a number. Empty entries are skipped.
+pub fn parse_float_array(text: &str) -> Option<Vec<f32>> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().ok())
+        .collect()
+}
+
+/// One input changed by [replace_inputs].
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputChange {
+    pub node: SnarlNodeId,
+    pub input_name: String,
+    pub old: InputValue,
+    pub new: InputValue,
+}
+
+/// The inputs changed by one [replace_inputs] call.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplaceEdit {
+    pub changes: Vec<InputChange>,
+}
+
+impl ReplaceEdit {
+    /// Put back the values the inputs had before the replacement. Inputs that
+    /// have been changed again since (or whose nodes were deleted) are left
+    /// alone. Returns how many inputs were restored.
+    pub fn undo(&self, snarl: &mut Snarl<NodeData>) -> usize {
+        let mut restored = 0;
+        for change in &self.changes {
+            let Some(node) = snarl.get_node_mut(change.node) else {
+                continue;
+            };
+            let Some(value) = node.input_values.get_mut(&change.input_name) else {
+                continue;
+            };
+            if *value == change.new {
+                *value = change.old.clone();
+                restored += 1;
+            }
+        }
+        restored
+    }
+}
+
+/// Replace each of `matches` with what `new_value` gives for its current
+/// value. Matches whose input has changed since it was found, and those
+/// `new_value` gives [None] or the same value for, are skipped.
+pub fn replace_inputs(
+    snarl: &mut Snarl<NodeData>,
+    matches: &[InputMatch],
+    mut new_value: impl FnMut(&InputValue) -> Option<InputValue>,
+) -> ReplaceEdit {
+    let mut edit = ReplaceEdit::default();
+    for found in matches {
+        let Some(node) = snarl.get_node_mut(found.node) else {
+            continue;
+        };
+        let Some(value) = node.input_values.get_mut(&found.input_name) else {
+            continue;
+        };
+        if *value != found.value {
+            continue;
+        }
+        let Some(new) = new_value(value) else {
+            continue;
+        };
+        if new == *value {
+            continue;
+        }
+
+        edit.changes.push(InputChange {
+            node: found.node,
+            input_name: found.input_name.clone(),
+            old: std::mem::replace(value, new.clone()),
+            new,
+        });
+    }
+    edit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::node_graph::EngineNodeId;
+
+    use std::collections::HashMap;
+
+    fn node(definition_name: &str, inputs: &[(&str, InputValue)]) -> NodeData {
+        NodeData {
+            id: EngineNodeId::default(),
+            definition_name: definition_name.to_string(),
+            input_values: inputs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect::<HashMap<_, _>>(),
+            input_smoothing: HashMap::new(),
+            input_mappings: HashMap::new(),
+            render_passes: HashMap::new(),
+            input_globals: HashMap::new(),
+            engine_node_id: None,
+        }
+    }
+
+    // --- parse_float_array() ---
+
+    #[test]
+    fn parse_float_array_reads_float_array_text() {
+        let values = vec![0.5, 1.0, -2.25];
+
+        assert_eq!(parse_float_array(&float_array_text(&values)), Some(values));
+        assert_eq!(parse_float_array(" 1, 2, "), Some(vec![1.0, 2.0]));
+        assert_eq!(parse_float_array(""), Some(vec![]));
+        assert_eq!(parse_float_array("1, two"), None);
+    }
+
+    // --- replace_inputs() ---
+
+    #[test]
+    fn test_replace_inputs() {
+        let old_video = InputValue::File(PathBuf::from("cells.mp4"));
+        let new_video = InputValue::File(PathBuf::from("cells_2.mp4"));
+
+        let mut snarl = Snarl::new();
+        let first = snarl.insert_node(
+            egui::pos2(0.0, 0.0),
+            node("video", &[("Path", old_video.clone())]),
+        );
+        let second = snarl.insert_node(
+            egui::pos2(0.0, 0.0),
+            node(
+                "video",
+                &[("Path", InputValue::File(PathBuf::from("other.mp4")))],
+            ),
+        );
+        let third = snarl.insert_node(
+            egui::pos2(0.0, 0.0),
+            node("video", &[("Path", old_video.clone())]),
+        );
+
+        let matches = find_file_references(&snarl, Path::new("cells.mp4"));
+        let nodes: Vec<_> = matches.iter().map(|found| found.node).collect();
+        assert_eq!(nodes, [first, third]);
+
+        let edit = replace_inputs(&mut snarl, &matches, |_| Some(new_video.clone()));
+        assert_eq!(edit.changes.len(), 2);
+        assert_eq!(snarl[first].input_values["Path"], new_video);
+        assert_eq!(snarl[third].input_values["Path"], new_video);
+        assert!(find_file_references(&snarl, Path::new("cells.mp4")).is_empty());
+
+        // Undoing leaves inputs changed since then alone.
+        let changed_again = InputValue::File(PathBuf::from("cells_3.mp4"));
+        snarl[third]
+            .input_values
+            .insert("Path".to_string(), changed_again.clone());
+        assert_eq!(edit.undo(&mut snarl), 1);
+        assert_eq!(snarl[first].input_values["Path"], old_video);
+        assert_eq!(
+            snarl[second].input_values["Path"],
+            InputValue::File(PathBuf::from("other.mp4"))
+        );
+        assert_eq!(snarl[third].input_values["Path"], changed_again);
+    }
+}
@@ -0,0 +1,257 @@
+//! Project globals: named values (e.g. `$bpm`, `$primary_color`,
+//! `$subject_name`) set once for the whole project. Node inputs can be bound
+//! to a global, taking its value, and text inputs can mention globals by name
+//! to have their values written in (`$$` writes a plain `$`).
+
+use super::find_replace::{parse_value_like, value_text};
+use engine::node::NodeInputKind;
+use engine::node_graph::InputValue;
+use serde::{Deserialize, Serialize};
+
+/// A named value that's the same everywhere in the project.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProjectGlobal {
+    /// The name, without the leading `$`.
+    pub name: String,
+    /// A [InputValue::Bool], [InputValue::Int], [InputValue::Float],
+    /// [InputValue::Text], or [InputValue::Pixel].
+    pub value: InputValue,
+}
+
+impl ProjectGlobal {
+    /// The kinds of value a global can have, with their names, for picking
+    /// one.
+    pub const KINDS: [(&'static str, InputValue); 5] = [
+        ("Number", InputValue::Float(0.0)),
+        ("Whole Number", InputValue::Int(0)),
+        ("Toggle", InputValue::Bool(false)),
+        ("Text", InputValue::Text(String::new())),
+        (
+            "Color",
+            InputValue::Pixel {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+                a: 1.0,
+            },
+        ),
+    ];
+}
+
+/// Whether `name` can name a global: letters, digits, and underscores, not
+/// starting with a digit.
+pub fn is_valid_global_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A name for a new global that none of `globals` has.
+pub fn unused_global_name(globals: &[ProjectGlobal]) -> String {
+    (1..)
+        .map(|i| format!("global_{i}"))
+        .find(|name| globals.iter().all(|global| &global.name != name))
+        .expect("there's always an unused name")
+}
+
+/// The global named `name`, if there is one.
+pub fn find<'a>(globals: &'a [ProjectGlobal], name: &str) -> Option<&'a ProjectGlobal> {
+    globals.iter().find(|global| global.name == name)
+}
+
+/// A global's value as text, as it's written into text inputs. Colors are
+/// written as hex (e.g. "#ff8000").
+pub fn global_text(value: &InputValue) -> Option<String> {
+    match value {
+        InputValue::Pixel { r, g, b, a } => {
+            let byte = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+            let mut text = format!("#{:02x}{:02x}{:02x}", byte(*r), byte(*g), byte(*b));
+            if *a < 1.0 {
+                text.push_str(&format!("{:02x}", byte(*a)));
+            }
+            Some(text)
+        }
+        value => value_text(value),
+    }
+}
+
+/// Parse `text` as a value of the same kind as `like`. Colors are hex, with
+/// or without alpha (e.g. "#ff8000" or "ff800080").
+pub fn parse_global_value(like: &InputValue, text: &str) -> Option<InputValue> {
+    if !matches!(like, InputValue::Pixel { .. }) {
+        return parse_value_like(like, text);
+    }
+
+    let hex = text.trim().trim_start_matches('#');
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            .map(|byte| byte as f32 / 255.0)
+    };
+    Some(InputValue::Pixel {
+        r: channel(0)?,
+        g: channel(2)?,
+        b: channel(4)?,
+        a: if hex.len() == 8 { channel(6)? } else { 1.0 },
+    })
+}
+
+/// A global's value as a value for an input of `kind`, or [None] if it can't
+/// be one (e.g. a color for a number input). Numbers are rounded or clamped
+/// to fit the input.
+pub fn bound_value(value: &InputValue, kind: &NodeInputKind) -> Option<InputValue> {
+    match (kind, value) {
+        (NodeInputKind::Bool { .. }, InputValue::Bool(_))
+        | (NodeInputKind::Pixel { .. }, InputValue::Pixel { .. }) => Some(value.clone()),
+        (NodeInputKind::Int { min, max, .. }, InputValue::Int(_) | InputValue::Float(_)) => {
+            let value = match value {
+                InputValue::Int(value) => *value,
+                InputValue::Float(value) => value.round() as i32,
+                _ => unreachable!(),
+            };
+            Some(InputValue::Int(
+                value.clamp(min.unwrap_or(i32::MIN), max.unwrap_or(i32::MAX)),
+            ))
+        }
+        (NodeInputKind::Float { min, max, .. }, InputValue::Int(_) | InputValue::Float(_)) => {
+            let value = match value {
+                InputValue::Int(value) => *value as f32,
+                InputValue::Float(value) => *value,
+                _ => unreachable!(),
+            };
+            Some(InputValue::Float(value.clamp(
+                min.unwrap_or(f32::NEG_INFINITY),
+                max.unwrap_or(f32::INFINITY),
+            )))
+        }
+        (NodeInputKind::Text { .. }, value) => global_text(value).map(InputValue::Text),
+        _ => None,
+    }
+}
+
+/// `text` with each `$name` of one of `globals` replaced by its value (see
+/// [global_text]) and each `$$` by `$`. Names that aren't globals are left as
+/// they are.
+pub fn substitute(text: &str, globals: &[ProjectGlobal]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+            continue;
+        }
+
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        match find(globals, &rest[..name_len]).and_then(|global| global_text(&global.value)) {
+            Some(value) => {
+                result.push_str(&value);
+                rest = &rest[name_len..];
+            }
+            None => result.push('$'),
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Set globals from `NAME=VALUE` overrides (e.g. from the command line),
+/// keeping each global's kind.
+pub fn apply_global_overrides(
+    globals: &mut [ProjectGlobal],
+    overrides: &[String],
+) -> Result<(), String> {
+    for text in overrides {
+        let Some((name, value)) = text.split_once('=') else {
+            return Err(format!("Expected NAME=VALUE for a global, got '{text}'."));
+        };
+        let name = name.trim().trim_start_matches('$');
+        let Some(global) = globals.iter_mut().find(|global| global.name == name) else {
+            return Err(format!("The project has no global named '{name}'."));
+        };
+        global.value = parse_global_value(&global.value, value)
+            .ok_or_else(|| format!("'{value}' isn't a valid value for global '{name}'."))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn globals() -> Vec<ProjectGlobal> {
+        vec![
+            ProjectGlobal {
+                name: "bpm".to_string(),
+                value: InputValue::Float(120.0),
+            },
+            ProjectGlobal {
+                name: "subject_name".to_string(),
+                value: InputValue::Text("Ada".to_string()),
+            },
+            ProjectGlobal {
+                name: "primary_color".to_string(),
+                value: InputValue::Pixel {
+                    r: 1.0,
+                    g: 0.5,
+                    b: 0.0,
+                    a: 1.0,
+                },
+            },
+        ]
+    }
+
+    // --- substitute() ---
+
+    #[test]
+    fn substitute_replaces_known_names_only() {
+        assert_eq!(
+            substitute(
+                "$subject_name at $bpm bpm in $primary_color, $$5 $unknown $",
+                &globals()
+            ),
+            "Ada at 120 bpm in #ff8000, $5 $unknown $"
+        );
+    }
+
+    // --- bound_value() ---
+
+    #[test]
+    fn bound_value_fits_the_input() {
+        let int_kind: NodeInputKind =
+            serde_json::from_str(r#"{"Int": {"min": 0, "max": 100}}"#).unwrap();
+        assert_eq!(
+            bound_value(&InputValue::Float(120.4), &int_kind),
+            Some(InputValue::Int(100))
+        );
+        assert_eq!(
+            bound_value(&globals()[2].value, &int_kind),
+            None,
+            "a color can't be a number"
+        );
+    }
+
+    // --- apply_global_overrides() ---
+
+    #[test]
+    fn apply_overrides_keeps_each_kind() {
+        let mut globals = globals();
+        apply_global_overrides(
+            &mut globals,
+            &["bpm=90".to_string(), "$primary_color=#00ff0080".to_string()],
+        )
+        .unwrap();
+        assert_eq!(globals[0].value, InputValue::Float(90.0));
+        assert_eq!(global_text(&globals[2].value).unwrap(), "#00ff0080");
+
+        assert!(apply_global_overrides(&mut globals, &["bpm=fast".to_string()]).is_err());
+        assert!(apply_global_overrides(&mut globals, &["tempo=90".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,616 @@
+//! Exports [GraphImage], a picture of the node graph laid out from where its
+//! nodes are placed, which can be saved as an SVG or a PNG for documentation.
+//! It's drawn from the graph itself rather than captured from the screen, so
+//! it doesn't depend on how the canvas is scrolled or zoomed.
+
+use super::{NodeData, VIRTUAL_OUTPUT_SINK_NAME, colors};
+use egui::epaint::{ClippedPrimitive, ColorImage, CubicBezierShape, Primitive};
+use egui::{Color32, Pos2, Rect, TextureId};
+use egui_snarl::Snarl;
+use engine::node::NodeInputKind;
+use engine::node::NodeLibrary;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+const FONT_SIZE: f32 = 13.0;
+/// Roughly how wide a character is at [FONT_SIZE], used to size nodes the
+/// same way in every format.
+const CHAR_WIDTH: f32 = 7.0;
+const HEADER_HEIGHT: f32 = 24.0;
+const ROW_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 10.0;
+/// The space between the input and output names on a row.
+const COLUMN_GAP: f32 = 24.0;
+const MIN_NODE_WIDTH: f32 = 100.0;
+const PIN_RADIUS: f32 = 4.0;
+const WIRE_WIDTH: f32 = 2.0;
+const CORNER_RADIUS: f32 = 4.0;
+/// The empty space around the nodes.
+const MARGIN: f32 = 32.0;
+/// The largest PNG side, in pixels.
+const MAX_IMAGE_SIDE: f32 = 16384.0;
+
+const BACKGROUND_COLOR: Color32 = Color32::from_gray(27);
+const NODE_COLOR: Color32 = Color32::from_gray(40);
+const HEADER_COLOR: Color32 = Color32::from_gray(55);
+const BORDER_COLOR: Color32 = Color32::from_gray(70);
+const TEXT_COLOR: Color32 = Color32::from_gray(220);
+
+struct Pin {
+    name: String,
+    color: Color32,
+    pos: Pos2,
+}
+
+struct NodeBox {
+    title: String,
+    rect: Rect,
+    inputs: Vec<Pin>,
+    outputs: Vec<Pin>,
+}
+
+struct Wire {
+    from: Pos2,
+    to: Pos2,
+    color: Color32,
+}
+
+/// The nodes, pins and wires of a node graph, laid out in graph coordinates.
+pub struct GraphImage {
+    nodes: Vec<NodeBox>,
+    wires: Vec<Wire>,
+    /// The area the picture covers, including its margin.
+    bounds: Rect,
+}
+
+impl GraphImage {
+    /// Lay out the nodes of `snarl` where they're placed, named and with pins
+    /// as they're defined in `library`. Collapsed nodes are drawn as just
+    /// their header.
+    pub fn new(snarl: &Snarl<NodeData>, library: &NodeLibrary) -> Self {
+        let mut nodes = Vec::new();
+        let mut node_indices = HashMap::new();
+        for (node_id, node) in snarl.node_ids() {
+            let Some(info) = snarl.get_node_info(node_id) else {
+                continue;
+            };
+            node_indices.insert(node_id, nodes.len());
+            nodes.push(layout_node(node, info.pos, info.open, library));
+        }
+
+        let wires = snarl
+            .wires()
+            .filter_map(|(out_pin, in_pin)| {
+                let from = nodes[*node_indices.get(&out_pin.node)?]
+                    .outputs
+                    .get(out_pin.output)?;
+                let to = nodes[*node_indices.get(&in_pin.node)?]
+                    .inputs
+                    .get(in_pin.input)?;
+                Some(Wire {
+                    from: from.pos,
+                    to: to.pos,
+                    color: from.color,
+                })
+            })
+            .collect();
+
+        let bounds = nodes
+            .iter()
+            .map(|node| node.rect)
+            .reduce(|a, b| a.union(b))
+            .unwrap_or(Rect::ZERO)
+            .expand(MARGIN);
+
+        Self {
+            nodes,
+            wires,
+            bounds,
+        }
+    }
+
+    /// Save the picture to `path` at `scale` times its size on the canvas, as
+    /// an SVG or a PNG depending on the path's extension.
+    pub fn save(&self, path: &Path, scale: f32) -> Result<(), String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("svg") => std::fs::write(path, self.to_svg(scale))
+                .map_err(|e| format!("Couldn't write {}: {e}", path.display())),
+            Some("png") => self
+                .to_png(scale)?
+                .save(path)
+                .map_err(|e| format!("Couldn't write {}: {e}", path.display())),
+            _ => Err(format!(
+                "Can't export {}: use a .png or .svg file",
+                path.display()
+            )),
+        }
+    }
+
+    /// The picture as an SVG document, sized at `scale` times its size on the
+    /// canvas.
+    pub fn to_svg(&self, scale: f32) -> String {
+        let origin = self.bounds.min;
+        let size = self.bounds.size();
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="{FONT_SIZE}">"#,
+            (size.x * scale).round(),
+            (size.y * scale).round(),
+            size.x,
+            size.y,
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            hex(BACKGROUND_COLOR)
+        );
+
+        for wire in &self.wires {
+            let [from, control_a, control_b, to] =
+                wire_points(wire.from, wire.to).map(|point| point - origin);
+            let _ = writeln!(
+                svg,
+                r#"<path d="M {} {} C {} {} {} {} {} {}" fill="none" stroke="{}" stroke-width="{WIRE_WIDTH}"/>"#,
+                from.x,
+                from.y,
+                control_a.x,
+                control_a.y,
+                control_b.x,
+                control_b.y,
+                to.x,
+                to.y,
+                hex(wire.color),
+            );
+        }
+
+        for node in &self.nodes {
+            let rect = node.rect.translate(-origin.to_vec2());
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{CORNER_RADIUS}" fill="{}" stroke="{}"/>"#,
+                rect.min.x,
+                rect.min.y,
+                rect.width(),
+                rect.height(),
+                hex(NODE_COLOR),
+                hex(BORDER_COLOR),
+            );
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{HEADER_HEIGHT}" rx="{CORNER_RADIUS}" fill="{}" stroke="{}"/>"#,
+                rect.min.x,
+                rect.min.y,
+                rect.width(),
+                hex(HEADER_COLOR),
+                hex(BORDER_COLOR),
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" dominant-baseline="central" fill="{}">{}</text>"#,
+                rect.min.x + PADDING,
+                rect.min.y + HEADER_HEIGHT / 2.0,
+                hex(TEXT_COLOR),
+                escape(&node.title),
+            );
+
+            for (pins, anchor, offset) in [
+                (&node.inputs, "start", PADDING),
+                (&node.outputs, "end", -PADDING),
+            ] {
+                for pin in pins {
+                    let pos = pin.pos - origin;
+                    let _ = writeln!(
+                        svg,
+                        r#"<circle cx="{}" cy="{}" r="{PIN_RADIUS}" fill="{}"/>"#,
+                        pos.x,
+                        pos.y,
+                        hex(pin.color),
+                    );
+                    if !pin.name.is_empty() {
+                        let _ = writeln!(
+                            svg,
+                            r#"<text x="{}" y="{}" dominant-baseline="central" text-anchor="{anchor}" fill="{}">{}</text>"#,
+                            pos.x + offset,
+                            pos.y,
+                            hex(TEXT_COLOR),
+                            escape(&pin.name),
+                        );
+                    }
+                }
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// The picture rendered at `scale` times its size on the canvas. It's
+    /// painted and tessellated by egui off screen, then rasterized here.
+    pub fn to_png(&self, scale: f32) -> Result<image::RgbaImage, String> {
+        let size = self.bounds.size() * scale;
+        if !(size.x >= 1.0 && size.y >= 1.0) || size.max_elem() > MAX_IMAGE_SIDE {
+            return Err(format!(
+                "A {:.0}x{:.0} image is too big to export; try a smaller scale",
+                size.x, size.y
+            ));
+        }
+
+        let ctx = egui::Context::default();
+        let mut raw_input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, self.bounds.size())),
+            ..Default::default()
+        };
+        raw_input
+            .viewports
+            .entry(egui::ViewportId::ROOT)
+            .or_default()
+            .native_pixels_per_point = Some(scale);
+        let output = ctx.run(raw_input, |ctx| {
+            self.paint(&ctx.layer_painter(egui::LayerId::background()))
+        });
+
+        let mut textures: HashMap<TextureId, ColorImage> = HashMap::new();
+        for (id, delta) in output.textures_delta.set {
+            let egui::ImageData::Color(patch) = delta.image;
+            match (delta.pos, textures.get_mut(&id)) {
+                (Some([x, y]), Some(texture)) => {
+                    for row in 0..patch.height() {
+                        let start = (y + row) * texture.width() + x;
+                        texture.pixels[start..start + patch.width()].copy_from_slice(
+                            &patch.pixels[row * patch.width()..(row + 1) * patch.width()],
+                        );
+                    }
+                }
+                _ => {
+                    textures.insert(id, (*patch).clone());
+                }
+            }
+        }
+
+        let primitives = ctx.tessellate(output.shapes, output.pixels_per_point);
+        let mut image = image::RgbaImage::from_pixel(
+            size.x.round() as u32,
+            size.y.round() as u32,
+            image::Rgba(BACKGROUND_COLOR.to_array()),
+        );
+        rasterize(&primitives, &textures, output.pixels_per_point, &mut image);
+        Ok(image)
+    }
+
+    /// Paint the picture with `painter`, with the top left of its bounds at
+    /// the top left of the screen.
+    fn paint(&self, painter: &egui::Painter) {
+        let offset = Pos2::ZERO - self.bounds.min;
+        let font = egui::FontId::proportional(FONT_SIZE);
+
+        for wire in &self.wires {
+            painter.add(CubicBezierShape::from_points_stroke(
+                wire_points(wire.from, wire.to).map(|point| point + offset),
+                false,
+                Color32::TRANSPARENT,
+                egui::Stroke::new(WIRE_WIDTH, wire.color),
+            ));
+        }
+
+        for node in &self.nodes {
+            let rect = node.rect.translate(offset);
+            let stroke = egui::Stroke::new(1.0, BORDER_COLOR);
+            painter.rect(
+                rect,
+                CORNER_RADIUS,
+                NODE_COLOR,
+                stroke,
+                egui::StrokeKind::Inside,
+            );
+            painter.rect(
+                Rect::from_min_size(rect.min, egui::vec2(rect.width(), HEADER_HEIGHT)),
+                CORNER_RADIUS,
+                HEADER_COLOR,
+                stroke,
+                egui::StrokeKind::Inside,
+            );
+            painter.text(
+                rect.min + egui::vec2(PADDING, HEADER_HEIGHT / 2.0),
+                egui::Align2::LEFT_CENTER,
+                &node.title,
+                font.clone(),
+                TEXT_COLOR,
+            );
+
+            for (pins, align, text_offset) in [
+                (&node.inputs, egui::Align2::LEFT_CENTER, PADDING),
+                (&node.outputs, egui::Align2::RIGHT_CENTER, -PADDING),
+            ] {
+                for pin in pins {
+                    let pos = pin.pos + offset;
+                    painter.circle_filled(pos, PIN_RADIUS, pin.color);
+                    painter.text(
+                        pos + egui::vec2(text_offset, 0.0),
+                        align,
+                        &pin.name,
+                        font.clone(),
+                        TEXT_COLOR,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Lay out `node` with its top left at `pos`.
+fn layout_node(node: &NodeData, pos: Pos2, open: bool, library: &NodeLibrary) -> NodeBox {
+    let (title, inputs, outputs) = if node.definition_name == VIRTUAL_OUTPUT_SINK_NAME {
+        (
+            "Output".to_string(),
+            vec![(
+                "Output".to_string(),
+                colors::input_kind_color(&NodeInputKind::Frame),
+            )],
+            Vec::new(),
+        )
+    } else if let Some(def) = library.get_definition(&node.definition_name) {
+        (
+            def.node.name.clone(),
+            def.node
+                .inputs
+                .iter()
+                .map(|input| (input.name.clone(), colors::input_kind_color(&input.kind)))
+                .collect(),
+            def.node
+                .outputs
+                .iter()
+                .map(|output| (output.name.clone(), colors::output_kind_color(&output.kind)))
+                .collect(),
+        )
+    } else {
+        (node.definition_name.clone(), Vec::new(), Vec::new())
+    };
+
+    let widest = |pins: &[(String, Color32)]| {
+        pins.iter()
+            .map(|(name, _)| text_width(name))
+            .fold(0.0, f32::max)
+    };
+    let width = (text_width(&title) + PADDING * 2.0)
+        .max(widest(&inputs) + widest(&outputs) + COLUMN_GAP + PADDING * 2.0)
+        .max(MIN_NODE_WIDTH);
+    let rows = if open {
+        inputs.len().max(outputs.len())
+    } else {
+        0
+    };
+    let rect = Rect::from_min_size(
+        pos,
+        egui::vec2(width, HEADER_HEIGHT + rows as f32 * ROW_HEIGHT),
+    );
+
+    // Collapsed nodes keep their pins, on the header, so their wires still
+    // have somewhere to go.
+    let pins = |pins: Vec<(String, Color32)>, x: f32| {
+        pins.into_iter()
+            .enumerate()
+            .map(|(row, (name, color))| {
+                if open {
+                    Pin {
+                        name,
+                        color,
+                        pos: egui::pos2(
+                            x,
+                            rect.min.y + HEADER_HEIGHT + (row as f32 + 0.5) * ROW_HEIGHT,
+                        ),
+                    }
+                } else {
+                    Pin {
+                        name: String::new(),
+                        color,
+                        pos: egui::pos2(x, rect.min.y + HEADER_HEIGHT / 2.0),
+                    }
+                }
+            })
+            .collect()
+    };
+
+    NodeBox {
+        title,
+        inputs: pins(inputs, rect.min.x),
+        outputs: pins(outputs, rect.max.x),
+        rect,
+    }
+}
+
+fn text_width(text: &str) -> f32 {
+    text.chars().count() as f32 * CHAR_WIDTH
+}
+
+/// The points of the curve a wire from `from` to `to` is drawn as, leaving and
+/// entering its pins horizontally like the canvas's wires do.
+fn wire_points(from: Pos2, to: Pos2) -> [Pos2; 4] {
+    let bend = egui::vec2(((to.x - from.x).abs() / 2.0).max(40.0), 0.0);
+    [from, from + bend, to - bend, to]
+}
+
+fn hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// Escape `text` for use as SVG text content.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Draw egui's tessellated `primitives` into `image`, sampling the `textures`
+/// they reference. Colors are blended as egui does, in premultiplied gamma
+/// space.
+fn rasterize(
+    primitives: &[ClippedPrimitive],
+    textures: &HashMap<TextureId, ColorImage>,
+    pixels_per_point: f32,
+    image: &mut image::RgbaImage,
+) {
+    let edge = |a: Pos2, b: Pos2, p: Pos2| (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+
+    for ClippedPrimitive {
+        clip_rect,
+        primitive,
+    } in primitives
+    {
+        let Primitive::Mesh(mesh) = primitive else {
+            continue;
+        };
+        let texture = textures.get(&mesh.texture_id);
+        let clip = Rect::from_min_max(
+            (clip_rect.min.to_vec2() * pixels_per_point).to_pos2(),
+            (clip_rect.max.to_vec2() * pixels_per_point).to_pos2(),
+        )
+        .intersect(Rect::from_min_size(
+            Pos2::ZERO,
+            egui::vec2(image.width() as f32, image.height() as f32),
+        ));
+        if !clip.is_positive() {
+            continue;
+        }
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let vertices = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+            let [a, b, c] =
+                vertices.map(|vertex| (vertex.pos.to_vec2() * pixels_per_point).to_pos2());
+            let area = edge(a, b, c);
+            if area.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let bounds = Rect::from_points(&[a, b, c]).intersect(clip);
+            if !bounds.is_positive() {
+                continue;
+            }
+            for y in bounds.min.y.floor() as u32..bounds.max.y.ceil() as u32 {
+                for x in bounds.min.x.floor() as u32..bounds.max.x.ceil() as u32 {
+                    let point = egui::pos2(x as f32 + 0.5, y as f32 + 0.5);
+                    let weights = [
+                        edge(b, c, point) / area,
+                        edge(c, a, point) / area,
+                        edge(a, b, point) / area,
+                    ];
+                    if weights.iter().any(|&weight| weight < 0.0) {
+                        continue;
+                    }
+
+                    let mut color = [0.0f32; 4];
+                    let mut uv = egui::Vec2::ZERO;
+                    for (vertex, weight) in vertices.iter().zip(weights) {
+                        for (channel, value) in color.iter_mut().zip(vertex.color.to_array()) {
+                            *channel += value as f32 * weight;
+                        }
+                        uv += vertex.uv.to_vec2() * weight;
+                    }
+                    if let Some(texture) = texture {
+                        let tx =
+                            ((uv.x * texture.width() as f32) as usize).min(texture.width() - 1);
+                        let ty =
+                            ((uv.y * texture.height() as f32) as usize).min(texture.height() - 1);
+                        let texel = texture.pixels[ty * texture.width() + tx].to_array();
+                        for (channel, value) in color.iter_mut().zip(texel) {
+                            *channel *= value as f32 / 255.0;
+                        }
+                    }
+
+                    let pixel = image.get_pixel_mut(x, y);
+                    let coverage = 1.0 - color[3] / 255.0;
+                    for (channel, value) in pixel.0.iter_mut().zip(color) {
+                        *channel = (value + *channel as f32 * coverage).round().min(255.0) as u8;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui_snarl::{InPinId, OutPinId};
+    use engine::node_graph::EngineNodeId;
+
+    fn node(definition_name: &str) -> NodeData {
+        NodeData {
+            id: EngineNodeId::default(),
+            definition_name: definition_name.to_string(),
+            input_values: HashMap::new(),
+            input_smoothing: HashMap::new(),
+            input_mappings: HashMap::new(),
+            render_passes: HashMap::new(),
+            input_globals: HashMap::new(),
+            engine_node_id: None,
+        }
+    }
+
+    // --- GraphImage::new() ---
+
+    #[test]
+    fn new_bounds_every_node_with_a_margin() {
+        let library = NodeLibrary::default();
+        let mut snarl = Snarl::new();
+        snarl.insert_node(egui::pos2(0.0, 0.0), node("a"));
+        snarl.insert_node(egui::pos2(300.0, 200.0), node(VIRTUAL_OUTPUT_SINK_NAME));
+
+        let image = GraphImage::new(&snarl, &library);
+
+        assert_eq!(image.nodes.len(), 2);
+        assert_eq!(image.bounds.min, egui::pos2(-MARGIN, -MARGIN));
+        assert_eq!(
+            image.bounds.max,
+            egui::pos2(
+                300.0 + MIN_NODE_WIDTH + MARGIN,
+                200.0 + HEADER_HEIGHT + ROW_HEIGHT + MARGIN
+            )
+        );
+    }
+
+    #[test]
+    fn new_skips_wires_to_pins_that_dont_exist() {
+        let library = NodeLibrary::default();
+        let mut snarl = Snarl::new();
+        let unknown = snarl.insert_node(egui::pos2(0.0, 0.0), node("a"));
+        let sink = snarl.insert_node(egui::pos2(300.0, 0.0), node(VIRTUAL_OUTPUT_SINK_NAME));
+        snarl.connect(
+            OutPinId {
+                node: unknown,
+                output: 0,
+            },
+            InPinId {
+                node: sink,
+                input: 0,
+            },
+        );
+
+        assert!(GraphImage::new(&snarl, &library).wires.is_empty());
+    }
+
+    // --- GraphImage::to_svg() ---
+
+    #[test]
+    fn to_svg_scales_and_escapes() {
+        let library = NodeLibrary::default();
+        let mut snarl = Snarl::new();
+        snarl.insert_node(egui::pos2(0.0, 0.0), node("a<b>&c"));
+
+        let image = GraphImage::new(&snarl, &library);
+        let svg = image.to_svg(2.0);
+        let size = image.bounds.size() * 2.0;
+
+        assert!(svg.starts_with(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}""#,
+            size.x, size.y
+        )));
+        assert!(svg.contains(">a&lt;b&gt;&amp;c</text>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,715 @@
+//! The edits the node graph editor makes to a graph (adding nodes, connecting
+//! pins, splicing nodes into wires, deleting nodes) and the rules they follow,
+//! kept apart from how the graph is drawn. [NodeGraphViewer](super::NodeGraphViewer)
+//! only turns what the user did into calls to these, so anything else that
+//! changes the graph (e.g. find and replace, scenes, recovery) can make the
+//! same edits the same way.
+//!
+//! Edits that can't be made return why as a message for the user.
+//!
+//! Every node has an ID that's saved with the project and used as its ID in
+//! the engine graph, so parameter edits, timings and diagnostics reported by
+//! the engine find the same node after a reload. [NodeIdMap] records which
+//! nodes are in the engine graph.
+//!
+//! Changes to an input that take more than a frame to make (e.g. dragging a
+//! slider) are made as an [InputEdit], so they can be cancelled partway and
+//! are undone in one step (see [InputHistory]).
+
+use super::validation::{self, validate_output_source};
+use super::{NodeData, VIRTUAL_OUTPUT_SINK_NAME};
+use egui_snarl::{InPinId, NodeId as SnarlNodeId, OutPinId, Snarl};
+use engine::node::engine_node::{BuiltInHandler, EngineNode, NodeExecutionPlan, NodeOutputKind};
+use engine::node::{NodeDefinition, NodeInputKind, NodeLibrary, input_kind_to_output_kind};
+use engine::node_graph::{EngineNodeId, InputValue};
+use media::midi::streams::list_ports;
+use std::collections::{HashMap, HashSet};
+
+/// Which engine node each editor node became when the graph was last synced
+/// to the engine. No two editor nodes map to the same engine node.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeIdMap {
+    engine_ids: HashMap<SnarlNodeId, EngineNodeId>,
+}
+
+impl NodeIdMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `snarl_id` is `engine_id` in the engine graph, replacing
+    /// anything either was mapped to before.
+    pub fn insert(&mut self, snarl_id: SnarlNodeId, engine_id: EngineNodeId) {
+        self.engine_ids.retain(|_, id| *id != engine_id);
+        self.engine_ids.insert(snarl_id, engine_id);
+    }
+
+    /// The engine node `snarl_id` became, if it's in the engine graph.
+    pub fn engine_id(&self, snarl_id: SnarlNodeId) -> Option<EngineNodeId> {
+        self.engine_ids.get(&snarl_id).copied()
+    }
+
+    /// Every `(editor node, engine node)` pair, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (SnarlNodeId, EngineNodeId)> + '_ {
+        self.engine_ids
+            .iter()
+            .map(|(&snarl_id, &engine_id)| (snarl_id, engine_id))
+    }
+}
+
+/// Give every node that shares its ID with another (e.g. from a hand edited
+/// project file) a new one. The node with the lowest snarl ID keeps it.
+pub fn ensure_unique_ids(snarl: &mut Snarl<NodeData>) {
+    let mut node_ids: Vec<SnarlNodeId> = snarl.node_ids().map(|(id, _)| id).collect();
+    node_ids.sort();
+
+    let mut seen = HashSet::new();
+    for node_id in node_ids {
+        let node = &mut snarl[node_id];
+        if !seen.insert(node.id) {
+            node.id = EngineNodeId::default();
+            seen.insert(node.id);
+        }
+    }
+}
+
+/// A change to one node input that's being made over several frames (e.g. by
+/// dragging a slider). Until it's committed, the input holds a preview value
+/// that can be thrown away with [Self::cancel].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputEdit {
+    node_id: SnarlNodeId,
+    input: String,
+    before: Option<InputValue>,
+    cancelled: bool,
+}
+
+impl InputEdit {
+    /// Start editing `input` of `node_id`, which was set to `before` (or unset)
+    /// before the edit began.
+    pub fn begin(node_id: SnarlNodeId, input: &str, before: Option<InputValue>) -> Self {
+        Self {
+            node_id,
+            input: input.to_string(),
+            before,
+            cancelled: false,
+        }
+    }
+
+    /// Whether this is an edit of `input` of `node_id`.
+    pub fn is_for(&self, node_id: SnarlNodeId, input: &str) -> bool {
+        self.node_id == node_id && self.input == input
+    }
+
+    /// Show `value` in the input until the edit is committed or cancelled.
+    /// Once the edit's been cancelled, the input keeps its value from before
+    /// the edit instead.
+    pub fn preview(&self, snarl: &mut Snarl<NodeData>, value: InputValue) {
+        let value = if self.cancelled {
+            self.before.clone()
+        } else {
+            Some(value)
+        };
+        set_input(snarl, self.node_id, &self.input, value);
+    }
+
+    /// Put the input back how it was before the edit, and keep it that way
+    /// until the edit is over (see [Self::preview]).
+    pub fn cancel(&mut self, snarl: &mut Snarl<NodeData>) {
+        self.cancelled = true;
+        set_input(snarl, self.node_id, &self.input, self.before.clone());
+    }
+
+    /// Finish the edit, keeping the input's current value. Returns the change
+    /// that was made, if the value changed at all.
+    pub fn commit(self, snarl: &Snarl<NodeData>) -> Option<InputChange> {
+        let after = snarl
+            .get_node(self.node_id)?
+            .input_values
+            .get(&self.input)
+            .cloned();
+        (!self.cancelled && after != self.before).then_some(InputChange {
+            node_id: self.node_id,
+            input: self.input,
+            before: self.before,
+            after,
+        })
+    }
+}
+
+/// A change that was made to one node input, which can be undone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputChange {
+    pub node_id: SnarlNodeId,
+    pub input: String,
+    pub before: Option<InputValue>,
+    pub after: Option<InputValue>,
+}
+
+impl InputChange {
+    /// Put the input back how it was before the change. Returns whether the
+    /// node still exists.
+    pub fn undo(&self, snarl: &mut Snarl<NodeData>) -> bool {
+        set_input(snarl, self.node_id, &self.input, self.before.clone())
+    }
+
+    /// Make the change again. Returns whether the node still exists.
+    pub fn redo(&self, snarl: &mut Snarl<NodeData>) -> bool {
+        set_input(snarl, self.node_id, &self.input, self.after.clone())
+    }
+}
+
+/// Changes made to node inputs, most recent last, so they can be undone and
+/// redone.
+#[derive(Debug, Clone, Default)]
+pub struct InputHistory {
+    undo: Vec<InputChange>,
+    redo: Vec<InputChange>,
+}
+
+impl InputHistory {
+    /// How many changes are kept to be undone.
+    pub const MAX_LEN: usize = 100;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a change that was just made. Changes that were undone can't be
+    /// redone after this.
+    pub fn push(&mut self, change: InputChange) {
+        if self.undo.len() == Self::MAX_LEN {
+            self.undo.remove(0);
+        }
+        self.undo.push(change);
+        self.redo.clear();
+    }
+
+    /// Undo the most recent change whose node still exists. Returns whether
+    /// anything was undone.
+    pub fn undo(&mut self, snarl: &mut Snarl<NodeData>) -> bool {
+        while let Some(change) = self.undo.pop() {
+            if change.undo(snarl) {
+                self.redo.push(change);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Redo the most recently undone change whose node still exists. Returns
+    /// whether anything was redone.
+    pub fn redo(&mut self, snarl: &mut Snarl<NodeData>) -> bool {
+        while let Some(change) = self.redo.pop() {
+            if change.redo(snarl) {
+                self.undo.push(change);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Set (or unset, with [None]) `input` of `node_id`. Returns whether the node
+/// exists.
+fn set_input(
+    snarl: &mut Snarl<NodeData>,
+    node_id: SnarlNodeId,
+    input: &str,
+    value: Option<InputValue>,
+) -> bool {
+    let Some(node) = snarl.get_node_mut(node_id) else {
+        return false;
+    };
+    match value {
+        Some(value) => node.input_values.insert(input.to_string(), value),
+        None => node.input_values.remove(input),
+    };
+    true
+}
+
+/// Whether an output of kind `output_kind` can be connected to an input of
+/// kind `input_kind`.
+pub fn are_pin_kinds_compatible(output_kind: NodeOutputKind, input_kind: &NodeInputKind) -> bool {
+    let expected_output_kind = input_kind_to_output_kind(input_kind);
+    output_kind == expected_output_kind
+        // Numeric widening: allow Int outputs to feed Float inputs.
+        || matches!((output_kind, input_kind), (NodeOutputKind::Int, NodeInputKind::Float { .. }))
+}
+
+/// A node for the definition `definition_name`, with every input that has a
+/// default set to it.
+pub fn new_node(definition_name: &str, definition: &NodeDefinition) -> NodeData {
+    let input_values = definition
+        .node
+        .inputs
+        .iter()
+        .filter_map(|input_def| {
+            let value = validation::default_input_value(input_def)?;
+            Some((input_def.name.clone(), value))
+        })
+        .collect();
+
+    NodeData {
+        id: EngineNodeId::default(),
+        definition_name: definition_name.to_string(),
+        input_values,
+        input_smoothing: HashMap::new(),
+        input_mappings: HashMap::new(),
+        render_passes: HashMap::new(),
+        input_globals: HashMap::new(),
+        engine_node_id: None,
+    }
+}
+
+/// The kind of value the output `pin` carries.
+pub fn output_kind(
+    snarl: &Snarl<NodeData>,
+    node_library: &NodeLibrary,
+    pin: OutPinId,
+) -> Option<NodeOutputKind> {
+    let definition = node_library.get_definition(&snarl.get_node(pin.node)?.definition_name)?;
+    Some(definition.node.outputs.get(pin.output)?.kind)
+}
+
+/// The kind of value the input `pin` takes.
+pub fn input_kind(
+    snarl: &Snarl<NodeData>,
+    node_library: &NodeLibrary,
+    pin: InPinId,
+) -> Option<NodeInputKind> {
+    let node = snarl.get_node(pin.node)?;
+    if node.definition_name == VIRTUAL_OUTPUT_SINK_NAME {
+        return (pin.input == 0).then_some(NodeInputKind::Frame);
+    }
+    let definition = node_library.get_definition(&node.definition_name)?;
+    Some(definition.node.inputs.get(pin.input)?.kind.clone())
+}
+
+/// Whether connecting `from` to `to` would create a cycle.
+pub fn would_create_cycle(snarl: &Snarl<NodeData>, from: SnarlNodeId, to: SnarlNodeId) -> bool {
+    let mut stack = vec![to];
+    let mut visited = HashSet::new();
+
+    while let Some(node) = stack.pop() {
+        if !visited.insert(node) {
+            continue;
+        }
+        if node == from {
+            return true;
+        }
+
+        for (wire_from, wire_to) in snarl.wires() {
+            if wire_from.node == node {
+                stack.push(wire_to.node);
+            }
+        }
+    }
+
+    false
+}
+
+/// Connect `from` to `to`, replacing whatever was connected to `to` (an input
+/// only ever has one wire). Returns whether they were connected, which they
+/// aren't if either pin doesn't exist.
+pub fn connect(
+    snarl: &mut Snarl<NodeData>,
+    node_library: &NodeLibrary,
+    from: OutPinId,
+    to: InPinId,
+) -> Result<bool, String> {
+    if from.node == to.node {
+        return Err("A node cannot be connected to itself.".to_string());
+    }
+
+    if would_create_cycle(snarl, from.node, to.node) {
+        return Err("Connecting these nodes would create a cycle.".to_string());
+    }
+
+    let Some(from_def) = node_library.get_definition(&snarl[from.node].definition_name) else {
+        return Ok(false);
+    };
+
+    let to_node = &snarl[to.node];
+    let to_def = if to_node.definition_name == VIRTUAL_OUTPUT_SINK_NAME {
+        validate_output_source(snarl, from.node, node_library)?;
+        None
+    } else {
+        let Some(to_def) = node_library.get_definition(&to_node.definition_name) else {
+            return Ok(false);
+        };
+        Some(to_def)
+    };
+
+    let Some(from_output) = from_def.node.outputs.get(from.output) else {
+        return Ok(false);
+    };
+
+    let (to_input_name, to_input_kind) = match to_def {
+        None if to.input == 0 => ("Output".to_string(), NodeInputKind::Frame),
+        None => return Ok(false),
+        Some(to_def) => {
+            let Some(to_input) = to_def.node.inputs.get(to.input) else {
+                return Ok(false);
+            };
+            (to_input.name.clone(), to_input.kind.clone())
+        }
+    };
+
+    if !are_pin_kinds_compatible(from_output.kind, &to_input_kind) {
+        return Err(format!(
+            "Cannot connect '{}' to '{}': incompatible pin types.",
+            from_output.name, to_input_name
+        ));
+    }
+
+    if matches!(
+        from_def.node.executor,
+        NodeExecutionPlan::BuiltIn(BuiltInHandler::MidiSource)
+    ) {
+        let has_midi_ports = match list_ports() {
+            Ok(ports) => ports.count() > 0,
+            Err(_) => false,
+        };
+
+        if !has_midi_ports {
+            return Err(
+                "Cannot connect MIDI node: no MIDI input port is selected or available."
+                    .to_string(),
+            );
+        }
+    }
+
+    // Snarl allows multiple wires per input by default, so existing input
+    // wires are replaced before connecting the new source.
+    snarl.drop_inputs(to);
+    snarl.connect(from, to);
+    Ok(true)
+}
+
+/// Insert `node` into the wire from `from` to `to`, connecting the first of
+/// its inputs and outputs that fit. Returns whether it was inserted, which it
+/// isn't if `node` already has wires or doesn't exist.
+pub fn splice(
+    snarl: &mut Snarl<NodeData>,
+    node_library: &NodeLibrary,
+    node: SnarlNodeId,
+    from: OutPinId,
+    to: InPinId,
+) -> Result<bool, String> {
+    if snarl
+        .wires()
+        .any(|(from, to)| from.node == node || to.node == node)
+    {
+        return Ok(false);
+    }
+    let Some(definition) = snarl
+        .get_node(node)
+        .and_then(|data| node_library.get_definition(&data.definition_name))
+    else {
+        return Ok(false);
+    };
+    let (Some(from_kind), Some(to_kind)) = (
+        output_kind(snarl, node_library, from),
+        input_kind(snarl, node_library, to),
+    ) else {
+        return Ok(false);
+    };
+
+    let input = definition
+        .node
+        .inputs
+        .iter()
+        .position(|input| are_pin_kinds_compatible(from_kind, &input.kind));
+    let output = definition
+        .node
+        .outputs
+        .iter()
+        .position(|output| are_pin_kinds_compatible(output.kind, &to_kind));
+    let (Some(input), Some(output)) = (input, output) else {
+        return Err(format!(
+            "'{}' has no input and output that fit that connection.",
+            definition.node.name
+        ));
+    };
+
+    let node_input = InPinId { node, input };
+    let node_output = OutPinId { node, output };
+    snarl.disconnect(from, to);
+    snarl.connect(from, node_input);
+    snarl.connect(node_output, to);
+
+    if snarl[to.node].definition_name == VIRTUAL_OUTPUT_SINK_NAME
+        && let Err(message) = validate_output_source(snarl, node, node_library)
+    {
+        snarl.disconnect(node_output, to);
+        snarl.disconnect(from, node_input);
+        snarl.connect(from, to);
+        return Err(message);
+    }
+
+    Ok(true)
+}
+
+/// Delete `node`, connecting whatever fed each of its inputs to whatever the
+/// matching output fed (see [passthrough_pins]).
+pub fn remove_and_heal(snarl: &mut Snarl<NodeData>, node_library: &NodeLibrary, node: SnarlNodeId) {
+    let passthroughs = node_library
+        .get_definition(&snarl[node].definition_name)
+        .map(|definition| passthrough_pins(&definition.node))
+        .unwrap_or_default();
+
+    let mut healed = Vec::new();
+    for (input, output) in passthroughs {
+        let Some(&upstream) = snarl.in_pin(InPinId { node, input }).remotes.first() else {
+            continue;
+        };
+        let Some(upstream_kind) = output_kind(snarl, node_library, upstream) else {
+            continue;
+        };
+        for (from, to) in snarl.wires() {
+            if from != (OutPinId { node, output }) || to.node == upstream.node {
+                continue;
+            }
+            if input_kind(snarl, node_library, to)
+                .is_some_and(|kind| are_pin_kinds_compatible(upstream_kind, &kind))
+            {
+                healed.push((upstream, to));
+            }
+        }
+    }
+
+    snarl.remove_node(node);
+    for (from, to) in healed {
+        snarl.connect(from, to);
+        if snarl[to.node].definition_name == VIRTUAL_OUTPUT_SINK_NAME
+            && validate_output_source(snarl, from.node, node_library).is_err()
+        {
+            snarl.disconnect(from, to);
+        }
+    }
+}
+
+/// Pairs of `(input, output)` indices of `node` where what comes in at the
+/// input goes out at the output, so deleting the node can connect one to the
+/// other. Each output is paired with the first unpaired input of its kind.
+fn passthrough_pins(node: &EngineNode) -> Vec<(usize, usize)> {
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    for (output_index, output) in node.outputs.iter().enumerate() {
+        let input_index = (0..node.inputs.len()).find(|&index| {
+            input_kind_to_output_kind(&node.inputs[index].kind) == output.kind
+                && !pairs.iter().any(|&(paired, _)| paired == index)
+        });
+        if let Some(input_index) = input_index {
+            pairs.push((input_index, output_index));
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(definition_name: &str) -> NodeData {
+        NodeData {
+            id: EngineNodeId::default(),
+            definition_name: definition_name.to_string(),
+            input_values: HashMap::new(),
+            input_smoothing: HashMap::new(),
+            input_mappings: HashMap::new(),
+            render_passes: HashMap::new(),
+            input_globals: HashMap::new(),
+            engine_node_id: None,
+        }
+    }
+
+    // --- NodeIdMap::insert() ---
+
+    #[test]
+    fn test_node_id_map_insert() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(egui::pos2(0.0, 0.0), node("a"));
+        let b = snarl.insert_node(egui::pos2(0.0, 0.0), node("b"));
+        let (first, second) = (EngineNodeId::default(), EngineNodeId::default());
+
+        let mut ids = NodeIdMap::new();
+        ids.insert(a, first);
+        assert_eq!(ids.engine_id(a), Some(first));
+
+        // Remapping either side forgets the old pair.
+        ids.insert(a, second);
+        assert_eq!(ids.iter().collect::<Vec<_>>(), vec![(a, second)]);
+        ids.insert(b, second);
+        assert_eq!(ids.engine_id(a), None);
+        assert_eq!(ids.iter().collect::<Vec<_>>(), vec![(b, second)]);
+    }
+
+    // --- ensure_unique_ids() ---
+
+    #[test]
+    fn test_ensure_unique_ids() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(egui::pos2(0.0, 0.0), node("a"));
+        let mut copy = snarl[a].clone();
+        copy.definition_name = "b".to_string();
+        let b = snarl.insert_node(egui::pos2(0.0, 0.0), copy);
+        let id = snarl[a].id;
+
+        ensure_unique_ids(&mut snarl);
+        assert_eq!(snarl[a].id, id);
+        assert_ne!(snarl[b].id, id);
+    }
+
+    // --- InputEdit ---
+
+    #[test]
+    fn test_input_edit_commit() {
+        let mut snarl = Snarl::new();
+        let id = snarl.insert_node(egui::pos2(0.0, 0.0), node("Blur"));
+        let before = Some(InputValue::Float(1.0));
+        snarl[id]
+            .input_values
+            .insert("Radius".to_string(), InputValue::Float(1.0));
+
+        let edit = InputEdit::begin(id, "Radius", before.clone());
+        edit.preview(&mut snarl, InputValue::Float(2.0));
+        edit.preview(&mut snarl, InputValue::Float(3.0));
+        assert_eq!(
+            edit.commit(&snarl),
+            Some(InputChange {
+                node_id: id,
+                input: "Radius".to_string(),
+                before,
+                after: Some(InputValue::Float(3.0)),
+            })
+        );
+
+        // Edits that end where they started aren't changes.
+        let edit = InputEdit::begin(id, "Radius", Some(InputValue::Float(3.0)));
+        edit.preview(&mut snarl, InputValue::Float(3.0));
+        assert_eq!(edit.commit(&snarl), None);
+    }
+
+    #[test]
+    fn test_input_edit_cancel() {
+        let mut snarl = Snarl::new();
+        let id = snarl.insert_node(egui::pos2(0.0, 0.0), node("Blur"));
+
+        let mut edit = InputEdit::begin(id, "Radius", None);
+        edit.preview(&mut snarl, InputValue::Float(2.0));
+        edit.cancel(&mut snarl);
+        assert_eq!(snarl[id].input_values.get("Radius"), None);
+
+        // Previews after cancelling are ignored, and nothing is committed.
+        edit.preview(&mut snarl, InputValue::Float(4.0));
+        assert_eq!(snarl[id].input_values.get("Radius"), None);
+        assert_eq!(edit.commit(&snarl), None);
+    }
+
+    // --- InputHistory ---
+
+    #[test]
+    fn test_input_history_undo_redo() {
+        let mut snarl = Snarl::new();
+        let id = snarl.insert_node(egui::pos2(0.0, 0.0), node("Blur"));
+        let removed = snarl.insert_node(egui::pos2(0.0, 0.0), node("Blur"));
+        let change = |node_id, before: Option<f32>, after: Option<f32>| InputChange {
+            node_id,
+            input: "Radius".to_string(),
+            before: before.map(InputValue::Float),
+            after: after.map(InputValue::Float),
+        };
+
+        let mut history = InputHistory::new();
+        history.push(change(id, None, Some(1.0)));
+        history.push(change(id, Some(1.0), Some(2.0)));
+        history.push(change(removed, None, Some(5.0)));
+        snarl.remove_node(removed);
+        snarl[id]
+            .input_values
+            .insert("Radius".to_string(), InputValue::Float(2.0));
+
+        // Changes to nodes that are gone are skipped.
+        assert!(history.undo(&mut snarl));
+        assert_eq!(
+            snarl[id].input_values.get("Radius"),
+            Some(&InputValue::Float(1.0))
+        );
+        assert!(history.undo(&mut snarl));
+        assert_eq!(snarl[id].input_values.get("Radius"), None);
+        assert!(!history.undo(&mut snarl));
+
+        assert!(history.redo(&mut snarl));
+        assert_eq!(
+            snarl[id].input_values.get("Radius"),
+            Some(&InputValue::Float(1.0))
+        );
+
+        // New changes replace what could be redone.
+        history.push(change(id, Some(1.0), Some(7.0)));
+        assert!(!history.redo(&mut snarl));
+    }
+
+    // --- would_create_cycle() ---
+
+    #[test]
+    fn test_would_create_cycle() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(egui::pos2(0.0, 0.0), node("a"));
+        let b = snarl.insert_node(egui::pos2(0.0, 0.0), node("b"));
+        let c = snarl.insert_node(egui::pos2(0.0, 0.0), node("c"));
+        snarl.connect(
+            OutPinId { node: a, output: 0 },
+            InPinId { node: b, input: 0 },
+        );
+        snarl.connect(
+            OutPinId { node: b, output: 0 },
+            InPinId { node: c, input: 0 },
+        );
+
+        assert!(would_create_cycle(&snarl, c, a));
+        assert!(would_create_cycle(&snarl, b, a));
+        assert!(!would_create_cycle(&snarl, a, c));
+    }
+
+    // --- connect() ---
+
+    #[test]
+    fn connect_refuses_self_connections_and_cycles() {
+        let library = NodeLibrary::default();
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(egui::pos2(0.0, 0.0), node("a"));
+        let b = snarl.insert_node(egui::pos2(0.0, 0.0), node("b"));
+        snarl.connect(
+            OutPinId { node: a, output: 0 },
+            InPinId { node: b, input: 0 },
+        );
+
+        let to_self = connect(
+            &mut snarl,
+            &library,
+            OutPinId { node: a, output: 0 },
+            InPinId { node: a, input: 0 },
+        );
+        assert!(to_self.is_err());
+
+        let back = connect(
+            &mut snarl,
+            &library,
+            OutPinId { node: b, output: 0 },
+            InPinId { node: a, input: 0 },
+        );
+        assert!(back.is_err());
+
+        // Nodes that aren't in the library are left alone.
+        let unknown = connect(
+            &mut snarl,
+            &library,
+            OutPinId { node: a, output: 1 },
+            InPinId { node: b, input: 1 },
+        );
+        assert_eq!(unknown, Ok(false));
+        assert_eq!(snarl.wires().count(), 1);
+    }
+}
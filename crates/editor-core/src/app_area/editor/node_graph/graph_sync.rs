@@ -3,7 +3,7 @@ use engine::node::{NodeInputKind, NodeLibrary};
 use engine::node_graph::{EngineNodeId, InputValue, NodeGraph};
 use std::collections::{HashMap, HashSet};
 
-use super::NodeGraphState;
+use super::{NodeGraphState, NodeIdMap, globals};
 
 pub const VIRTUAL_OUTPUT_SINK_NAME: &str = "__virtual_output_sink__";
 
@@ -13,7 +13,8 @@ pub enum GraphSyncResult {
     Valid {
         graph: NodeGraph,
         output_node: EngineNodeId,
-        snarl_to_engine: HashMap<SnarlNodeId, EngineNodeId>,
+        /// Which engine node each editor node in the graph became.
+        node_ids: NodeIdMap,
     },
     /// No output sink is wired engine should pause.
     NoOutput,
@@ -22,8 +23,9 @@ pub enum GraphSyncResult {
     Invalid(Vec<String>),
 }
 
-/// Walk the graph upstream from the output sink, validate every node,
-/// and build the engine graph in a single pass.
+/// Walk the graph upstream from the output sink and from every node with a
+/// render pass, validate every node, and build the engine graph in a single
+/// pass.
 ///
 /// This is the only function that should touch graph->engine translation.
 /// Call it whenever the editor graph changes, then act on the result.
@@ -40,6 +42,13 @@ pub fn sync_graph(state: &NodeGraphState, library: &NodeLibrary) -> GraphSyncRes
     let mut ordered: Vec<SnarlNodeId> = Vec::new(); // BFS order, upstream-first
     let mut queue = std::collections::VecDeque::new();
     queue.push_back(output_source_snarl_id);
+    queue.extend(
+        state
+            .snarl
+            .node_ids()
+            .filter(|(_, node)| !node.render_passes.is_empty())
+            .map(|(id, _)| id),
+    );
 
     // Build a reverse-adjacency map once so we don't re-scan wires per node.
     // Maps: to_node -> [(from_node, from_output_index, to_input_index)]
@@ -80,6 +89,24 @@ pub fn sync_graph(state: &NodeGraphState, library: &NodeLibrary) -> GraphSyncRes
             let is_wired = wired_inputs.contains(&idx);
             let has_value = node.input_values.contains_key(&input_def.name);
 
+            if !is_wired && let Some(global_name) = node.input_globals.get(&input_def.name) {
+                match globals::find(&state.globals, global_name) {
+                    None => errors.push(format!(
+                        "'{}' input '{}' is bound to ${global_name}, which doesn't exist.",
+                        definition.node.name, input_def.name
+                    )),
+                    Some(global)
+                        if globals::bound_value(&global.value, &input_def.kind).is_none() =>
+                    {
+                        errors.push(format!(
+                            "'{}' input '{}' can't take its value from ${global_name}.",
+                            definition.node.name, input_def.name
+                        ))
+                    }
+                    Some(_) => {}
+                }
+            }
+
             let satisfied = is_wired || has_value || has_default(&input_def.kind);
 
             if !satisfied {
@@ -106,29 +133,34 @@ pub fn sync_graph(state: &NodeGraphState, library: &NodeLibrary) -> GraphSyncRes
 
     // Build the engine graph from the validated, collected nodes.
     let mut engine_graph = NodeGraph::new();
-    let mut snarl_to_engine: HashMap<SnarlNodeId, EngineNodeId> = HashMap::new();
+    let mut node_ids = NodeIdMap::new();
 
     for &snarl_id in &ordered {
         let node = &state.snarl[snarl_id];
-        let engine_id = node
-            .engine_node_id
-            .map(|id| engine_graph.add_instance_with_id(id, node.definition_name.clone()))
-            .unwrap_or_else(|| engine_graph.add_instance(node.definition_name.clone()));
-        snarl_to_engine.insert(snarl_id, engine_id);
+        let engine_id = engine_graph.add_instance_with_id(node.id, node.definition_name.clone());
+        node_ids.insert(snarl_id, engine_id);
 
         let Some(definition) = library.get_definition(&node.definition_name) else {
             continue;
         };
 
         for input_def in &definition.node.inputs {
-            if let Some(value) = node.input_values.get(&input_def.name) {
-                if !matches!(value, InputValue::Connection { .. }) {
-                    let _ = engine_graph.set_input_value(
-                        engine_id,
-                        input_def.name.clone(),
-                        value.clone(),
-                    );
-                }
+            let bound = node
+                .input_globals
+                .get(&input_def.name)
+                .and_then(|global_name| globals::find(&state.globals, global_name))
+                .and_then(|global| globals::bound_value(&global.value, &input_def.kind));
+            if let Some(value) = bound {
+                let _ = engine_graph.set_input_value(engine_id, input_def.name.clone(), value);
+            } else if let Some(value) = node.input_values.get(&input_def.name) {
+                let value = match value {
+                    InputValue::Connection { .. } => continue,
+                    InputValue::Text(text) if !state.globals.is_empty() => {
+                        InputValue::Text(globals::substitute(text, &state.globals))
+                    }
+                    value => value.clone(),
+                };
+                let _ = engine_graph.set_input_value(engine_id, input_def.name.clone(), value);
             } else if let Some(default) = super::validation::default_input_value(input_def) {
                 let _ = engine_graph.set_input_value(engine_id, input_def.name.clone(), default);
             }
@@ -147,8 +179,8 @@ pub fn sync_graph(state: &NodeGraphState, library: &NodeLibrary) -> GraphSyncRes
         }
 
         let (Some(from_engine), Some(to_engine)) = (
-            snarl_to_engine.get(&wire_from.node).copied(),
-            snarl_to_engine.get(&wire_to.node).copied(),
+            node_ids.engine_id(wire_from.node),
+            node_ids.engine_id(wire_to.node),
         ) else {
             continue;
         };
@@ -172,16 +204,23 @@ pub fn sync_graph(state: &NodeGraphState, library: &NodeLibrary) -> GraphSyncRes
             to_engine,
             input_def.name.clone(),
         );
+        if let Some(mapping) = to_node.input_mappings.get(&input_def.name) {
+            let _ =
+                engine_graph.set_input_mapping(to_engine, &input_def.name, Some(mapping.clone()));
+        }
+        if let Some(smoothing) = to_node.input_smoothing.get(&input_def.name) {
+            let _ = engine_graph.set_input_smoothing(to_engine, &input_def.name, Some(*smoothing));
+        }
     }
 
-    let Some(&output_engine_id) = snarl_to_engine.get(&output_source_snarl_id) else {
+    let Some(output_engine_id) = node_ids.engine_id(output_source_snarl_id) else {
         return GraphSyncResult::NoOutput;
     };
 
     GraphSyncResult::Valid {
         graph: engine_graph,
         output_node: output_engine_id,
-        snarl_to_engine,
+        node_ids,
     }
 }
 
@@ -1,28 +1,62 @@
 use egui::{self, Ui};
-use egui_snarl::NodeId as SnarlNodeId;
+use egui_snarl::{NodeId as SnarlNodeId, Snarl};
 use engine::node::engine_node::NodeInput;
 use engine::node::{NodeInputKind, NodeLibrary};
-use engine::node_graph::InputValue;
+use engine::node_graph::{InputMapping, InputSmoothing, InputValue, MappingCurve, SmoothingMode};
+use engine::tone_curve::ToneCurve;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
 use media::midi::streams::list_ports;
 use util::channels::message_channel;
 
+use super::NodeData;
+use super::find_replace::{float_array_text, parse_float_array};
+use super::globals::{self as project_globals, ProjectGlobal};
+use super::graph_model::{InputChange, InputEdit};
+use crate::components::CurveEditor;
+
 /// Node names used to drive file picker filters.
 const VIDEO_NODE_NAME: &str = "Video";
 const IMAGE_NODE_NAME: &str = "Image";
+const AUDIO_METER_NODE_NAME: &str = "Audio Meter";
+const SPRITE_SHEET_NODE_NAME: &str = "Sprite Sheet";
+const TIME_REMAP_NODE_NAME: &str = "Time Remap";
+const STABILIZE_NODE_NAME: &str = "Stabilize";
+const FACE_DETECT_NODE_NAME: &str = "Face Detect";
+const DEPTH_ESTIMATE_NODE_NAME: &str = "Depth Estimate";
 
 pub struct InputWidgetState {
     pending_file_dialogs: HashMap<String, message_channel::Inbox<Option<PathBuf>>>,
+    /// The input being dragged, if any.
+    pub(super) input_edit: Option<InputEdit>,
 }
 
 impl InputWidgetState {
     pub fn new() -> Self {
         Self {
             pending_file_dialogs: HashMap::new(),
+            input_edit: None,
         }
     }
+
+    /// Whether an input is being dragged, so its value is still a preview.
+    pub fn is_editing(&self) -> bool {
+        self.input_edit.is_some()
+    }
+
+    /// Finish a drag that's over without its widget noticing (e.g. because
+    /// its node was scrolled out of view), once nothing is held down.
+    pub fn finish_abandoned_edit(
+        &mut self,
+        ctx: &egui::Context,
+        snarl: &Snarl<NodeData>,
+    ) -> Option<InputChange> {
+        if ctx.input(|i| i.pointer.any_down()) {
+            return None;
+        }
+        self.input_edit.take()?.commit(snarl)
+    }
 }
 
 impl Default for InputWidgetState {
@@ -34,6 +68,10 @@ impl Default for InputWidgetState {
 enum FileFilter {
     Video,
     Image,
+    /// Audio files or videos with sound.
+    Audio,
+    /// Neural network models.
+    Model,
     Any,
 }
 
@@ -41,8 +79,202 @@ fn file_dialog_key(node_id: SnarlNodeId, input_name: &str) -> String {
     format!("{:?}:{}", node_id, input_name)
 }
 
+/// A menu for mapping a connected Float input's value (see [InputMapping]).
+pub fn show_mapping_menu(
+    ui: &mut Ui,
+    input_mappings: &mut HashMap<String, InputMapping>,
+    input_name: &str,
+) {
+    let label = if input_mappings.contains_key(input_name) {
+        "Mapped"
+    } else {
+        "Mapping"
+    };
+    ui.menu_button(label, |ui| {
+        let mut enabled = input_mappings.contains_key(input_name);
+        if ui
+            .checkbox(&mut enabled, "Map values")
+            .on_hover_text("Rescale and reshape the connected value before it's used.")
+            .changed()
+        {
+            if enabled {
+                input_mappings.insert(input_name.to_string(), InputMapping::default());
+            } else {
+                input_mappings.remove(input_name);
+            }
+        }
+
+        let Some(mapping) = input_mappings.get_mut(input_name) else {
+            return;
+        };
+        egui::Grid::new("input_mapping_ranges")
+            .num_columns(3)
+            .show(ui, |ui| {
+                ui.label("From");
+                ui.add(egui::DragValue::new(&mut mapping.input_range.0).speed(0.01));
+                ui.add(egui::DragValue::new(&mut mapping.input_range.1).speed(0.01));
+                ui.end_row();
+
+                ui.label("To");
+                ui.add(egui::DragValue::new(&mut mapping.output_range.0).speed(0.01));
+                ui.add(egui::DragValue::new(&mut mapping.output_range.1).speed(0.01));
+                ui.end_row();
+            });
+
+        let custom = matches!(mapping.curve, MappingCurve::Custom(_));
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut mapping.curve, MappingCurve::Linear, "Linear");
+            ui.selectable_value(&mut mapping.curve, MappingCurve::Exponential, "Exp");
+            ui.selectable_value(&mut mapping.curve, MappingCurve::Logarithmic, "Log");
+            if ui.selectable_label(custom, "Custom").clicked() && !custom {
+                mapping.curve = MappingCurve::Custom(ToneCurve::identity().points().to_vec());
+            }
+        });
+        if let MappingCurve::Custom(points) = &mut mapping.curve {
+            let mut curve = ToneCurve::new(points.iter().copied()).unwrap_or_default();
+            if ui.add(CurveEditor::new(&mut curve)).changed() {
+                *points = curve.points().to_vec();
+            }
+        }
+
+        ui.checkbox(&mut mapping.invert, "Invert");
+        ui.checkbox(&mut mapping.soft_takeover, "Soft takeover")
+            .on_hover_text("Hold the value until the control is moved to it, so it doesn't jump.");
+    });
+}
+
+/// A context menu on `label`, an input's label, for binding the input to one
+/// of `globals` (see [ProjectGlobal]). Only globals whose values fit the input
+/// are listed.
+pub fn show_global_menu(
+    label: &egui::Response,
+    input_globals: &mut HashMap<String, String>,
+    input: &NodeInput,
+    globals: &[ProjectGlobal],
+) {
+    if matches!(
+        input.kind,
+        NodeInputKind::Frame | NodeInputKind::MidiPacket | NodeInputKind::File { .. }
+    ) {
+        return;
+    }
+
+    label.context_menu(|ui| {
+        let bound = input_globals.get(&input.name).cloned();
+        let mut fitting = globals
+            .iter()
+            .filter(|global| project_globals::bound_value(&global.value, &input.kind).is_some())
+            .peekable();
+        if fitting.peek().is_none() {
+            ui.label(
+                egui::RichText::new(
+                    "No project globals fit this input.\nAdd them in Project Settings.",
+                )
+                .weak(),
+            );
+        }
+        for global in fitting {
+            let selected = bound.as_deref() == Some(global.name.as_str());
+            if ui
+                .selectable_label(selected, format!("Bind to ${}", global.name))
+                .clicked()
+            {
+                input_globals.insert(input.name.clone(), global.name.clone());
+                ui.close();
+            }
+        }
+
+        if bound.is_some() {
+            ui.separator();
+            if ui.button("Unbind").clicked() {
+                input_globals.remove(&input.name);
+                ui.close();
+            }
+        }
+    });
+}
+
+/// Shows an input bound to the global named `global_name` in place of its
+/// widget, or that the global is missing.
+pub fn show_bound_input(ui: &mut Ui, global_name: &str, globals: &[ProjectGlobal]) {
+    match project_globals::find(globals, global_name) {
+        Some(global) => {
+            let value = project_globals::global_text(&global.value).unwrap_or_default();
+            ui.label(egui::RichText::new(format!("${global_name}")).strong())
+                .on_hover_text(format!(
+                    "Takes the project global's value ({value}). Right-click the input's name to unbind it."
+                ));
+        }
+        None => {
+            ui.colored_label(
+                egui::Color32::from_rgb(220, 90, 90),
+                format!("${global_name} (missing)"),
+            )
+            .on_hover_text("The global was removed. Right-click the input's name to unbind it.");
+        }
+    }
+}
+
+/// A menu for smoothing a connected Float input (see [InputSmoothing]).
+pub fn show_smoothing_menu(
+    ui: &mut Ui,
+    input_smoothing: &mut HashMap<String, InputSmoothing>,
+    input_name: &str,
+) {
+    let label = if input_smoothing.contains_key(input_name) {
+        "Smoothed"
+    } else {
+        "Smoothing"
+    };
+    ui.menu_button(label, |ui| {
+        let mut enabled = input_smoothing.contains_key(input_name);
+        if ui
+            .checkbox(&mut enabled, "Smooth changes")
+            .on_hover_text("Ease toward new values instead of jumping to them.")
+            .changed()
+        {
+            if enabled {
+                input_smoothing.insert(input_name.to_string(), InputSmoothing::default());
+            } else {
+                input_smoothing.remove(input_name);
+            }
+        }
+
+        let Some(smoothing) = input_smoothing.get_mut(input_name) else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.selectable_value(
+                &mut smoothing.mode,
+                SmoothingMode::Exponential,
+                "Exponential",
+            )
+            .on_hover_text("Slows down as it gets close to the new value.");
+            ui.selectable_value(&mut smoothing.mode, SmoothingMode::SlewLimit, "Slew Limit")
+                .on_hover_text("Moves at a constant rate, taking the set time per 1.0 of change.");
+        });
+        ui.add(
+            egui::DragValue::new(&mut smoothing.attack_secs)
+                .range(0.0..=10.0)
+                .speed(0.01)
+                .prefix("Attack: ")
+                .suffix(" s"),
+        );
+        ui.add(
+            egui::DragValue::new(&mut smoothing.release_secs)
+                .range(0.0..=10.0)
+                .speed(0.01)
+                .prefix("Release: ")
+                .suffix(" s"),
+        );
+    });
+}
+
 /// Renders the appropriate input widget based on the NodeInputKind
 /// Declutters the node_graph
+///
+/// Returns the widget's response for inputs that can be changed by dragging
+/// (see [InputEdit]).
 pub fn show_input_widget(
     ui: &mut Ui,
     input_values: &mut HashMap<String, InputValue>,
@@ -51,7 +283,7 @@ pub fn show_input_widget(
     node_library: &NodeLibrary,
     node_id: SnarlNodeId,
     state: &mut InputWidgetState,
-) {
+) -> Option<egui::Response> {
     match &input_def.kind {
         NodeInputKind::File { .. } => {
             show_file_input(
@@ -70,12 +302,27 @@ pub fn show_input_widget(
         NodeInputKind::Int {
             default, min, max, ..
         } => {
-            show_int_input(ui, input_values, input_def, node_name, *default, *min, *max);
+            return Some(show_int_input(
+                ui,
+                input_values,
+                input_def,
+                node_name,
+                *default,
+                *min,
+                *max,
+            ));
         }
         NodeInputKind::Float {
             default, min, max, ..
         } => {
-            show_float_input(ui, input_values, input_def, *default, *min, *max);
+            return Some(show_float_input(
+                ui,
+                input_values,
+                input_def,
+                *default,
+                *min,
+                *max,
+            ));
         }
         NodeInputKind::Text { default, .. } => {
             show_text_input(ui, input_values, input_def, default);
@@ -99,7 +346,14 @@ pub fn show_input_widget(
         NodeInputKind::PortSelection => {
             show_port_selection_input(ui, input_values, input_def);
         }
+        NodeInputKind::Curve => {
+            show_curve_input(ui, input_values, input_def);
+        }
+        NodeInputKind::FloatArray { default } => {
+            show_float_array_input(ui, input_values, input_def, node_id, default);
+        }
     }
+    None
 }
 
 fn show_port_selection_input(
@@ -173,8 +427,10 @@ fn show_file_input(
     if ui.button(display_text).clicked() && !state.pending_file_dialogs.contains_key(&key) {
         let filter = if let Some(def) = node_library.get_definition(node_name) {
             match def.node.name.as_str() {
-                VIDEO_NODE_NAME => FileFilter::Video,
-                IMAGE_NODE_NAME => FileFilter::Image,
+                VIDEO_NODE_NAME | TIME_REMAP_NODE_NAME | STABILIZE_NODE_NAME => FileFilter::Video,
+                IMAGE_NODE_NAME | SPRITE_SHEET_NODE_NAME => FileFilter::Image,
+                AUDIO_METER_NODE_NAME => FileFilter::Audio,
+                FACE_DETECT_NODE_NAME | DEPTH_ESTIMATE_NODE_NAME => FileFilter::Model,
                 _ => FileFilter::Any,
             }
         } else {
@@ -204,6 +460,18 @@ fn show_file_input(
                         ],
                     );
                 }
+                FileFilter::Audio => {
+                    dialog = dialog.add_filter(
+                        "Audio and Video Files",
+                        &[
+                            "wav", "mp3", "flac", "ogg", "opus", "m4a", "aac", "aif", "aiff",
+                            "mp4", "mov", "mkv", "webm",
+                        ],
+                    );
+                }
+                FileFilter::Model => {
+                    dialog = dialog.add_filter("ONNX Models", &["onnx"]);
+                }
                 FileFilter::Any => {}
             }
 
@@ -239,21 +507,20 @@ fn show_int_input(
     default: i32,
     min: Option<i32>,
     max: Option<i32>,
-) {
+) -> egui::Response {
     let mut value = if let Some(InputValue::Int(v)) = input_values.get(&input_def.name) {
         *v
     } else {
         default
     };
 
-    let changed = if let (Some(min_val), Some(max_val)) = (min, max) {
+    let response = if let (Some(min_val), Some(max_val)) = (min, max) {
         ui.add(egui::Slider::new(&mut value, min_val..=max_val))
-            .changed()
     } else {
-        ui.add(egui::DragValue::new(&mut value)).changed()
+        ui.add(egui::DragValue::new(&mut value))
     };
 
-    if changed {
+    if response.changed() {
         input_values.insert(input_def.name.clone(), InputValue::Int(value));
     }
 
@@ -264,6 +531,8 @@ fn show_int_input(
             ui.small(format!("{} ({})", key.as_str(), key_value));
         }
     }
+
+    response
 }
 
 fn show_float_input(
@@ -273,24 +542,24 @@ fn show_float_input(
     default: f32,
     min: Option<f32>,
     max: Option<f32>,
-) {
+) -> egui::Response {
     let mut value = if let Some(InputValue::Float(v)) = input_values.get(&input_def.name) {
         *v
     } else {
         default
     };
 
-    let changed = if let (Some(min_val), Some(max_val)) = (min, max) {
+    let response = if let (Some(min_val), Some(max_val)) = (min, max) {
         ui.add(egui::Slider::new(&mut value, min_val..=max_val))
-            .changed()
     } else {
         ui.add(egui::DragValue::new(&mut value).speed(0.1))
-            .changed()
     };
 
-    if changed {
+    if response.changed() {
         input_values.insert(input_def.name.clone(), InputValue::Float(value));
     }
+
+    response
 }
 
 fn show_text_input(
@@ -310,6 +579,42 @@ fn show_text_input(
     }
 }
 
+/// Comma separated numbers. The text is kept as typed while it's being
+/// edited, so a half-typed list (e.g. "1, 2,") isn't reformatted under the
+/// cursor.
+fn show_float_array_input(
+    ui: &mut Ui,
+    input_values: &mut HashMap<String, InputValue>,
+    input_def: &NodeInput,
+    node_id: SnarlNodeId,
+    default: &[f32],
+) {
+    let id = ui.id().with(("float_array", node_id, &input_def.name));
+    let mut text =
+        ui.data(|data| data.get_temp::<String>(id))
+            .unwrap_or_else(|| match input_values.get(&input_def.name) {
+                Some(InputValue::FloatArray(values)) => float_array_text(values),
+                _ => float_array_text(default),
+            });
+
+    let response = ui.text_edit_singleline(&mut text);
+    let parsed = parse_float_array(&text);
+    if response.changed()
+        && let Some(values) = parsed.clone()
+    {
+        input_values.insert(input_def.name.clone(), InputValue::FloatArray(values));
+    }
+
+    if response.has_focus() {
+        ui.data_mut(|data| data.insert_temp(id, text));
+    } else {
+        ui.data_mut(|data| data.remove::<String>(id));
+    }
+    if parsed.is_none() {
+        response.on_hover_text("Enter numbers separated by commas");
+    }
+}
+
 /// We don't really use this yet but it's here.
 fn show_dimensions_input(
     ui: &mut Ui,
@@ -374,6 +679,40 @@ fn show_pixel_input(
     }
 }
 
+fn show_curve_input(
+    ui: &mut Ui,
+    input_values: &mut HashMap<String, InputValue>,
+    input_def: &NodeInput,
+) {
+    // Text that doesn't parse (e.g. hand-edited project files) shows as the
+    // identity curve until it's edited.
+    let mut curve = match input_values.get(&input_def.name) {
+        Some(InputValue::Text(value)) => value.parse().unwrap_or_default(),
+        _ => ToneCurve::identity(),
+    };
+
+    // Tint per-channel curves so they're easy to tell apart.
+    let color = match input_def.name.as_str() {
+        name if name.starts_with("Red") => egui::Color32::from_rgb(230, 90, 90),
+        name if name.starts_with("Green") => egui::Color32::from_rgb(100, 210, 110),
+        name if name.starts_with("Blue") => egui::Color32::from_rgb(100, 150, 240),
+        _ => egui::Color32::from_gray(220),
+    };
+
+    ui.push_id(&input_def.name, |ui| {
+        ui.vertical(|ui| {
+            let mut changed = ui.add(CurveEditor::new(&mut curve).color(color)).changed();
+            if !curve.is_identity() && ui.small_button("Reset").clicked() {
+                curve = ToneCurve::identity();
+                changed = true;
+            }
+            if changed {
+                input_values.insert(input_def.name.clone(), InputValue::Text(curve.to_string()));
+            }
+        });
+    });
+}
+
 fn show_enum_input(
     ui: &mut Ui,
     input_values: &mut HashMap<String, InputValue>,
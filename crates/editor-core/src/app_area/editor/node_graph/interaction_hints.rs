@@ -0,0 +1,290 @@
+//! Cursors and inline hints for the node graph: a grab cursor for panning,
+//! a crosshair while connecting pins, and a not-allowed cursor (with the
+//! reason next to it) over pins a wire can't connect to.
+//!
+//! Snarl doesn't say where it put pins or whether a wire is being dragged, so
+//! pins are drawn with [TrackedPin], which records where each one ended up.
+//! That's also how wires are found under nodes dropped onto them (see
+//! [PinSpots::center]).
+
+use super::graph_model::are_pin_kinds_compatible;
+use egui_snarl::ui::{PinInfo, PinWireInfo, SnarlPin, SnarlStyle};
+use egui_snarl::{InPinId, NodeId as SnarlNodeId, OutPinId};
+use engine::node::engine_node::NodeOutputKind;
+use engine::node::{NodeInputKind, input_kind_to_output_kind};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// What a pin carries, and which side of its node it's on.
+#[derive(Clone, Debug)]
+pub enum PinKind {
+    Input(NodeInputKind),
+    Output(NodeOutputKind),
+}
+
+impl PinKind {
+    fn value_kind(&self) -> NodeOutputKind {
+        match self {
+            Self::Input(kind) => input_kind_to_output_kind(kind),
+            Self::Output(kind) => *kind,
+        }
+    }
+}
+
+/// Which pin a [TrackedPin] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinId {
+    Input(InPinId),
+    Output(OutPinId),
+}
+
+impl PinId {
+    fn node(self) -> SnarlNodeId {
+        match self {
+            Self::Input(pin) => pin.node,
+            Self::Output(pin) => pin.node,
+        }
+    }
+}
+
+/// Where a pin was drawn (in screen space) this frame.
+#[derive(Clone, Debug)]
+struct PinSpot {
+    pin: PinId,
+    kind: Option<PinKind>,
+    rect: egui::Rect,
+}
+
+impl PinSpot {
+    /// Why a wire dragged from this pin can't connect to `other`, if it can't.
+    fn connect_error(&self, other: &PinSpot) -> Option<String> {
+        let (Some(kind), Some(other_kind)) = (&self.kind, &other.kind) else {
+            return None;
+        };
+        if self.pin.node() == other.pin.node() {
+            return Some("A node can't connect to itself".to_string());
+        }
+
+        let (output, input) = match (kind, other_kind) {
+            (PinKind::Output(output), PinKind::Input(input))
+            | (PinKind::Input(input), PinKind::Output(output)) => (*output, input),
+            (PinKind::Input(_), PinKind::Input(_)) => {
+                return Some("Inputs connect to outputs".to_string());
+            }
+            (PinKind::Output(_), PinKind::Output(_)) => {
+                return Some("Outputs connect to inputs".to_string());
+            }
+        };
+
+        (!are_pin_kinds_compatible(output, input)).then(|| {
+            format!(
+                "{:?} can't connect to {:?}",
+                kind.value_kind(),
+                other_kind.value_kind()
+            )
+        })
+    }
+}
+
+/// The pins drawn this frame, shared by every [TrackedPin] and
+/// [InteractionHints].
+#[derive(Clone, Default)]
+pub struct PinSpots(Rc<RefCell<Vec<PinSpot>>>);
+
+impl PinSpots {
+    /// Where `pin` was drawn (in screen space), if it's been drawn this frame.
+    pub fn center(&self, pin: PinId) -> Option<egui::Pos2> {
+        self.0
+            .borrow()
+            .iter()
+            .find(|spot| spot.pin == pin)
+            .map(|spot| spot.rect.center())
+    }
+}
+
+/// A [PinInfo] that records where it's drawn in [PinSpots].
+pub struct TrackedPin {
+    info: PinInfo,
+    pin: PinId,
+    kind: Option<PinKind>,
+    spots: PinSpots,
+}
+
+impl TrackedPin {
+    pub fn new(info: PinInfo, pin: PinId, kind: Option<PinKind>, spots: &PinSpots) -> Self {
+        Self {
+            info,
+            pin,
+            kind,
+            spots: spots.clone(),
+        }
+    }
+}
+
+impl SnarlPin for TrackedPin {
+    fn draw(
+        self,
+        snarl_style: &SnarlStyle,
+        style: &egui::Style,
+        rect: egui::Rect,
+        painter: &egui::Painter,
+    ) -> PinWireInfo {
+        // Pins are drawn in graph space, pointer positions are in screen space.
+        let screen_rect = painter
+            .ctx()
+            .layer_transform_to_global(painter.layer_id())
+            .map_or(rect, |to_global| to_global * rect);
+        self.spots.0.borrow_mut().push(PinSpot {
+            pin: self.pin,
+            kind: self.kind,
+            rect: screen_rect,
+        });
+
+        self.info.draw(snarl_style, style, rect, painter)
+    }
+}
+
+/// Picks the cursor (and hint) for the node graph each frame.
+pub struct InteractionHints {
+    spots: PinSpots,
+    /// The pin a wire is being dragged from.
+    dragging_from: Option<PinSpot>,
+}
+
+impl InteractionHints {
+    pub fn new() -> Self {
+        Self {
+            spots: PinSpots::default(),
+            dragging_from: None,
+        }
+    }
+
+    /// Forget last frame's pins. Call before the graph is drawn, and draw
+    /// pins with the [PinSpots] this returns.
+    pub fn begin_frame(&mut self) -> PinSpots {
+        self.spots.0.borrow_mut().clear();
+        self.spots.clone()
+    }
+
+    /// Set the cursor from what the pointer is doing. `graph_response` is
+    /// what [egui_snarl::ui::SnarlWidget::show] returned.
+    pub fn update(&mut self, ctx: &egui::Context, graph_response: &egui::Response) {
+        let (pointer, pressed, down, press_origin, dragging, modifiers) = ctx.input(|i| {
+            (
+                i.pointer.hover_pos(),
+                i.pointer.primary_pressed(),
+                i.pointer.primary_down(),
+                i.pointer.press_origin(),
+                i.pointer.is_decidedly_dragging(),
+                i.modifiers,
+            )
+        });
+        let spots = self.spots.0.borrow();
+        let spot_at = |pos: egui::Pos2| spots.iter().find(|spot| spot.rect.contains(pos));
+
+        if !down {
+            self.dragging_from = None;
+        } else if pressed {
+            // Command-dragging an input moves its wires instead, so there's no
+            // one pin to check against.
+            self.dragging_from = press_origin
+                .filter(|_| !modifiers.command)
+                .and_then(spot_at)
+                .cloned();
+        }
+
+        let hovered_spot = pointer.and_then(spot_at);
+        if let Some(from) = &self.dragging_from {
+            let error = hovered_spot
+                .filter(|to| to.rect != from.rect)
+                .and_then(|to| from.connect_error(to));
+            if error.is_some() {
+                ctx.set_cursor_icon(egui::CursorIcon::NotAllowed);
+            } else {
+                ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
+            }
+
+            let hint = match (error, hovered_spot) {
+                (Some(error), _) => Some(error),
+                (None, None) if dragging => Some("Release on a pin to connect".to_string()),
+                _ => None,
+            };
+            if let (Some(hint), Some(pointer)) = (hint, pointer) {
+                show_hint(ctx, pointer, &hint);
+            }
+        } else if graph_response.dragged() && !modifiers.shift {
+            ctx.set_cursor_icon(egui::CursorIcon::Grabbing);
+        } else if hovered_spot.is_some() {
+            ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
+        } else if graph_response.hovered() && !modifiers.shift {
+            ctx.set_cursor_icon(egui::CursorIcon::Grab);
+        }
+    }
+}
+
+/// Draw `text` next to the pointer, above everything else.
+fn show_hint(ctx: &egui::Context, pointer: egui::Pos2, text: &str) {
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Tooltip,
+        egui::Id::new("node_graph_hint"),
+    ));
+    let galley = painter.layout_no_wrap(
+        text.to_string(),
+        egui::FontId::proportional(12.0),
+        egui::Color32::from_rgb(220, 226, 228),
+    );
+    let rect =
+        egui::Rect::from_min_size(pointer + egui::vec2(18.0, 18.0), galley.size()).expand(4.0);
+    painter.rect_filled(rect, 4.0, egui::Color32::from_black_alpha(210));
+    painter.galley(
+        rect.min + egui::vec2(4.0, 4.0),
+        galley,
+        egui::Color32::WHITE,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spot(node: usize, kind: PinKind) -> PinSpot {
+        let node = SnarlNodeId(node);
+        PinSpot {
+            pin: match kind {
+                PinKind::Input(_) => PinId::Input(InPinId { node, input: 0 }),
+                PinKind::Output(_) => PinId::Output(OutPinId { node, output: 0 }),
+            },
+            kind: Some(kind),
+            rect: egui::Rect::NOTHING,
+        }
+    }
+
+    // --- PinSpot::connect_error() ---
+
+    #[test]
+    fn connect_error_allows_matching_pins_either_way() {
+        let output = spot(0, PinKind::Output(NodeOutputKind::Frame));
+        let input = spot(1, PinKind::Input(NodeInputKind::Frame));
+
+        assert_eq!(output.connect_error(&input), None);
+        assert_eq!(input.connect_error(&output), None);
+    }
+
+    #[test]
+    fn connect_error_explains_invalid_pins() {
+        let output = spot(0, PinKind::Output(NodeOutputKind::Text));
+
+        assert_eq!(
+            output.connect_error(&spot(1, PinKind::Input(NodeInputKind::Frame))),
+            Some("Text can't connect to Frame".to_string())
+        );
+        assert_eq!(
+            output.connect_error(&spot(1, PinKind::Output(NodeOutputKind::Text))),
+            Some("Outputs connect to inputs".to_string())
+        );
+        assert_eq!(
+            output.connect_error(&spot(0, PinKind::Input(NodeInputKind::Frame))),
+            Some("A node can't connect to itself".to_string())
+        );
+    }
+}
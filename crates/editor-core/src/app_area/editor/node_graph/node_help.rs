@@ -0,0 +1,106 @@
+//! Node documentation UI. Everything shown here comes from the node's
+//! [NodeDefinition] (its node.json), so user nodes get the same help as
+//! built-in ones.
+
+use engine::node::NodeDefinition;
+use engine::node::engine_node::{NodeInput, NodeOutput};
+
+const EXAMPLE_IMAGE_MAX_WIDTH: f32 = 240.0;
+
+/// Hover tooltip for a node in the node finder.
+pub fn definition_tooltip(ui: &mut egui::Ui, definition: &NodeDefinition) {
+    let node = &definition.node;
+
+    ui.set_max_width(320.0);
+    ui.label(egui::RichText::new(&node.name).strong());
+    if !node.category.is_empty() {
+        ui.label(egui::RichText::new(&node.category).weak().small());
+    }
+
+    if !node.short_description.is_empty() {
+        ui.label(&node.short_description);
+    }
+
+    if let Some(image) = definition.example_image_paths.first() {
+        ui.add_space(4.0);
+        show_example_image(ui, image);
+    }
+}
+
+/// Hover text for an input pin, or [None] if the input has no help text.
+pub fn input_hover_text(input: &NodeInput) -> Option<String> {
+    pin_hover_text(&input.help, input.kind.name())
+}
+
+/// Hover text for an output pin, or [None] if the output has no help text.
+pub fn output_hover_text(output: &NodeOutput) -> Option<String> {
+    pin_hover_text(&output.help, output.kind.name())
+}
+
+/// Contents of the help panel for the selected node.
+pub fn show_help_contents(ui: &mut egui::Ui, definition: &NodeDefinition) {
+    let node = &definition.node;
+
+    ui.heading(&node.name);
+    if !node.category.is_empty() {
+        ui.label(egui::RichText::new(&node.category).weak());
+    }
+    ui.separator();
+
+    if !node.short_description.is_empty() {
+        ui.label(egui::RichText::new(&node.short_description).strong());
+    }
+    if !node.long_description.is_empty() {
+        ui.label(&node.long_description);
+    }
+
+    if !node.inputs.is_empty() {
+        ui.add_space(8.0);
+        ui.label(egui::RichText::new("Inputs").strong());
+        for input in &node.inputs {
+            port_row(ui, &input.name, &input.help);
+        }
+    }
+
+    if !node.outputs.is_empty() {
+        ui.add_space(8.0);
+        ui.label(egui::RichText::new("Outputs").strong());
+        for output in &node.outputs {
+            port_row(ui, &output.name, &output.help);
+        }
+    }
+
+    let images = &definition.example_image_paths;
+    if !images.is_empty() {
+        ui.add_space(8.0);
+        ui.label(egui::RichText::new("Examples").strong());
+        for image in images {
+            show_example_image(ui, image);
+        }
+    }
+}
+
+fn port_row(ui: &mut egui::Ui, name: &str, help: &str) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label(egui::RichText::new(name).monospace());
+        if !help.is_empty() {
+            ui.label(egui::RichText::new(help).weak());
+        }
+    });
+}
+
+fn pin_hover_text(help: &str, kind: &str) -> Option<String> {
+    if help.is_empty() {
+        return None;
+    }
+
+    Some(format!("{help}\n\nType: {kind}"))
+}
+
+fn show_example_image(ui: &mut egui::Ui, path: &std::path::Path) {
+    ui.add(
+        egui::Image::new(format!("file://{}", path.display()))
+            .max_width(EXAMPLE_IMAGE_MAX_WIDTH)
+            .corner_radius(4.0),
+    );
+}
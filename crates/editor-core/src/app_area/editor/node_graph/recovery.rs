@@ -0,0 +1,180 @@
+//! Summaries of what restoring a graph recovered after a crash would change
+//! (see [OpenProject::recovered_data](util::local_data::project::OpenProject::recovered_data)).
+
+use super::{NodeData, NodeGraphState, VIRTUAL_OUTPUT_SINK_NAME};
+use egui_snarl::{NodeId as SnarlNodeId, Snarl};
+use std::collections::{BTreeSet, HashSet};
+
+/// At most this many changed inputs are listed by name.
+const MAX_LISTED_INPUTS: usize = 8;
+
+/// Describe what's different in `recovered` compared to `saved`, one line per
+/// kind of change. Empty if nothing is.
+pub fn recovery_summary(saved: &NodeGraphState, recovered: &NodeGraphState) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let added = nodes_missing_from(&recovered.snarl, &saved.snarl);
+    if !added.is_empty() {
+        lines.push(format!("Nodes added: {}", added.join(", ")));
+    }
+    let removed = nodes_missing_from(&saved.snarl, &recovered.snarl);
+    if !removed.is_empty() {
+        lines.push(format!("Nodes removed: {}", removed.join(", ")));
+    }
+
+    let mut changed_inputs = Vec::new();
+    let mut layout_changed = saved.graph_view != recovered.graph_view;
+    for (id, pos, node) in saved.snarl.nodes_pos_ids() {
+        let Some(recovered_node) = matching_node(&recovered.snarl, id, node) else {
+            continue;
+        };
+        layout_changed |= recovered
+            .snarl
+            .get_node_info(id)
+            .is_some_and(|info| info.pos != pos);
+        changed_inputs.extend(
+            changed_input_names(node, recovered_node)
+                .map(|input| format!("{} ({input})", node.definition_name)),
+        );
+    }
+    if !changed_inputs.is_empty() {
+        let listed = &changed_inputs[..changed_inputs.len().min(MAX_LISTED_INPUTS)];
+        let mut line = format!("Inputs changed: {}", listed.join(", "));
+        if changed_inputs.len() > MAX_LISTED_INPUTS {
+            line += &format!(" and {} more", changed_inputs.len() - MAX_LISTED_INPUTS);
+        }
+        lines.push(line);
+    }
+
+    let saved_wires: HashSet<_> = saved.snarl.wires().collect();
+    let recovered_wires: HashSet<_> = recovered.snarl.wires().collect();
+    let changed_wires = saved_wires.symmetric_difference(&recovered_wires).count();
+    if changed_wires > 0 {
+        lines.push(match changed_wires {
+            1 => "1 connection changed".to_string(),
+            _ => format!("{changed_wires} connections changed"),
+        });
+    }
+
+    if saved.scenes != recovered.scenes {
+        lines.push("Scenes changed".to_string());
+    }
+    if saved.output_settings != recovered.output_settings
+        || saved.link_enabled != recovered.link_enabled
+    {
+        lines.push("Project settings changed".to_string());
+    }
+    if layout_changed {
+        lines.push("Node layout or view changed".to_string());
+    }
+
+    lines
+}
+
+/// The node at `id` in `snarl`, if it's the same kind of node as `node`.
+fn matching_node<'a>(
+    snarl: &'a Snarl<NodeData>,
+    id: SnarlNodeId,
+    node: &NodeData,
+) -> Option<&'a NodeData> {
+    snarl
+        .get_node(id)
+        .filter(|other| other.definition_name == node.definition_name)
+}
+
+/// The names of nodes in `snarl` that `other` doesn't have.
+fn nodes_missing_from<'a>(snarl: &'a Snarl<NodeData>, other: &Snarl<NodeData>) -> Vec<&'a str> {
+    snarl
+        .node_ids()
+        .filter(|(id, node)| {
+            node.definition_name != VIRTUAL_OUTPUT_SINK_NAME
+                && matching_node(other, *id, node).is_none()
+        })
+        .map(|(_, node)| node.definition_name.as_str())
+        .collect()
+}
+
+/// The inputs whose value, smoothing, or mapping differ between `a` and `b`.
+fn changed_input_names<'a>(a: &'a NodeData, b: &'a NodeData) -> impl Iterator<Item = &'a str> {
+    let names: BTreeSet<&str> = [a, b]
+        .into_iter()
+        .flat_map(|node| {
+            node.input_values
+                .keys()
+                .chain(node.input_smoothing.keys())
+                .chain(node.input_mappings.keys())
+        })
+        .map(String::as_str)
+        .collect();
+
+    names.into_iter().filter(|name| {
+        a.input_values.get(*name) != b.input_values.get(*name)
+            || a.input_smoothing.get(*name) != b.input_smoothing.get(*name)
+            || a.input_mappings.get(*name) != b.input_mappings.get(*name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::node_graph::EngineNodeId;
+
+    use engine::node_graph::InputValue;
+    use std::collections::HashMap;
+
+    fn node(name: &str, inputs: &[(&str, InputValue)]) -> NodeData {
+        NodeData {
+            id: EngineNodeId::default(),
+            definition_name: name.to_string(),
+            input_values: inputs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect::<HashMap<_, _>>(),
+            input_smoothing: HashMap::new(),
+            input_mappings: HashMap::new(),
+            render_passes: HashMap::new(),
+            input_globals: HashMap::new(),
+            engine_node_id: None,
+        }
+    }
+
+    // --- recovery_summary() ---
+
+    #[test]
+    fn recovery_summary_is_empty_without_changes() {
+        let mut saved = NodeGraphState::new();
+        saved
+            .snarl
+            .insert_node(egui::pos2(0.0, 0.0), node("blur", &[]));
+
+        assert!(recovery_summary(&saved, &saved.clone()).is_empty());
+    }
+
+    #[test]
+    fn recovery_summary_lists_nodes_and_inputs() {
+        let mut saved = NodeGraphState::new();
+        let brightness = saved.snarl.insert_node(
+            egui::pos2(0.0, 0.0),
+            node("brightness", &[("Amount", InputValue::Float(0.5))]),
+        );
+        saved
+            .snarl
+            .insert_node(egui::pos2(0.0, 0.0), node("blur", &[]));
+
+        let mut recovered = saved.clone();
+        recovered.snarl[brightness]
+            .input_values
+            .insert("Amount".to_string(), InputValue::Float(0.8));
+        recovered
+            .snarl
+            .insert_node(egui::pos2(0.0, 0.0), node("tint", &[]));
+
+        assert_eq!(
+            recovery_summary(&saved, &recovered),
+            vec![
+                "Nodes added: tint".to_string(),
+                "Inputs changed: brightness (Amount)".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Scenes (cues): named snapshots of node input values that can be recalled
+//! all at once, or crossfaded to over a few seconds during a performance.
+
+use super::{NodeData, VIRTUAL_OUTPUT_SINK_NAME};
+use egui_snarl::{NodeId as SnarlNodeId, Snarl};
+use engine::node_graph::InputValue;
+use serde::{Deserialize, Serialize};
+
+/// One input's value in a [Scene].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneValue {
+    pub node: SnarlNodeId,
+    pub input_name: String,
+    pub value: InputValue,
+}
+
+/// A named snapshot of input values, with the triggers that recall it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub name: String,
+    pub values: Vec<SceneValue>,
+    /// The keyboard key that recalls the scene, by [egui::Key::name].
+    #[serde(default)]
+    pub key: Option<String>,
+    /// The MIDI note that recalls the scene.
+    #[serde(default)]
+    pub midi_note: Option<u8>,
+}
+
+impl Scene {
+    /// Capture the configured inputs of `nodes`, or of every node if [None].
+    /// Connections aren't configured values, so they're never captured.
+    pub fn capture(
+        name: impl Into<String>,
+        snarl: &Snarl<NodeData>,
+        nodes: Option<&[SnarlNodeId]>,
+    ) -> Self {
+        let mut values = Vec::new();
+        for (node_id, node) in snarl.node_ids() {
+            if node.definition_name == VIRTUAL_OUTPUT_SINK_NAME
+                || nodes.is_some_and(|nodes| !nodes.contains(&node_id))
+            {
+                continue;
+            }
+
+            for (input_name, value) in &node.input_values {
+                if matches!(value, InputValue::Connection { .. }) {
+                    continue;
+                }
+                values.push(SceneValue {
+                    node: node_id,
+                    input_name: input_name.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+
+        values.sort_by(|a, b| (a.node, &a.input_name).cmp(&(b.node, &b.input_name)));
+        Self {
+            name: name.into(),
+            values,
+            key: None,
+            midi_note: None,
+        }
+    }
+
+    /// Capture the current values of the inputs already in the scene, keeping
+    /// its name and triggers.
+    pub fn update(&mut self, snarl: &Snarl<NodeData>) {
+        for scene_value in &mut self.values {
+            if let Some(value) = configured_value(snarl, scene_value.node, &scene_value.input_name)
+            {
+                scene_value.value = value.clone();
+            }
+        }
+    }
+
+    /// Set every input in the scene. Inputs of deleted nodes and inputs that
+    /// have since been connected are skipped. Returns whether anything changed.
+    pub fn recall(&self, snarl: &mut Snarl<NodeData>) -> bool {
+        let mut changed = false;
+        for scene_value in &self.values {
+            changed |= set_value(
+                snarl,
+                scene_value.node,
+                &scene_value.input_name,
+                &scene_value.value,
+            );
+        }
+        changed
+    }
+}
+
+/// A crossfade from the values inputs had when it started to a [Scene]'s.
+#[derive(Clone, Debug)]
+pub struct SceneFade {
+    steps: Vec<FadeStep>,
+    duration_secs: f32,
+    elapsed_secs: f32,
+}
+
+#[derive(Clone, Debug)]
+struct FadeStep {
+    node: SnarlNodeId,
+    input_name: String,
+    from: InputValue,
+    to: InputValue,
+}
+
+impl SceneFade {
+    /// Start fading to `scene` over `duration_secs`.
+    pub fn new(scene: &Scene, snarl: &Snarl<NodeData>, duration_secs: f32) -> Self {
+        let steps = scene
+            .values
+            .iter()
+            .filter_map(|scene_value| {
+                let from = configured_value(snarl, scene_value.node, &scene_value.input_name)?;
+                Some(FadeStep {
+                    node: scene_value.node,
+                    input_name: scene_value.input_name.clone(),
+                    from: from.clone(),
+                    to: scene_value.value.clone(),
+                })
+            })
+            .collect();
+
+        Self {
+            steps,
+            duration_secs: duration_secs.max(0.0),
+            elapsed_secs: 0.0,
+        }
+    }
+
+    /// How far along the fade is, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        if self.duration_secs <= 0.0 {
+            1.0
+        } else {
+            (self.elapsed_secs / self.duration_secs).clamp(0.0, 1.0)
+        }
+    }
+
+    /// Move the fade `secs` forward, setting every input to its value in
+    /// between. Returns whether the fade is finished.
+    pub fn advance(&mut self, snarl: &mut Snarl<NodeData>, secs: f32) -> bool {
+        self.elapsed_secs += secs.max(0.0);
+        let t = self.progress();
+        for step in &self.steps {
+            set_value(
+                snarl,
+                step.node,
+                &step.input_name,
+                &blend(&step.from, &step.to, t),
+            );
+        }
+        t >= 1.0
+    }
+}
+
+/// The configured value of `input_name` on `node`, or [None] if the node is
+/// gone or the input is connected.
+fn configured_value<'a>(
+    snarl: &'a Snarl<NodeData>,
+    node: SnarlNodeId,
+    input_name: &str,
+) -> Option<&'a InputValue> {
+    match snarl.get_node(node)?.input_values.get(input_name)? {
+        InputValue::Connection { .. } => None,
+        value => Some(value),
+    }
+}
+
+/// Set `input_name` on `node` to `value` unless the node is gone or the input
+/// is connected. Returns whether the value changed.
+fn set_value(
+    snarl: &mut Snarl<NodeData>,
+    node: SnarlNodeId,
+    input_name: &str,
+    value: &InputValue,
+) -> bool {
+    let Some(current) = snarl
+        .get_node_mut(node)
+        .and_then(|node| node.input_values.get_mut(input_name))
+    else {
+        return false;
+    };
+    if matches!(current, InputValue::Connection { .. }) || current == value {
+        return false;
+    }
+    *current = value.clone();
+    true
+}
+
+/// The value `t` (0 to 1) of the way from `from` to `to`. Numbers and colors
+/// are interpolated; anything else switches halfway through.
+fn blend(from: &InputValue, to: &InputValue, t: f32) -> InputValue {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    match (from, to) {
+        (InputValue::Float(a), InputValue::Float(b)) => InputValue::Float(lerp(*a, *b)),
+        (InputValue::Int(a), InputValue::Int(b)) => {
+            InputValue::Int(lerp(*a as f32, *b as f32).round() as i32)
+        }
+        (
+            InputValue::Dimensions {
+                width: from_width,
+                height: from_height,
+            },
+            InputValue::Dimensions { width, height },
+        ) => InputValue::Dimensions {
+            width: lerp(*from_width as f32, *width as f32).round() as u32,
+            height: lerp(*from_height as f32, *height as f32).round() as u32,
+        },
+        (
+            InputValue::Pixel {
+                r: from_r,
+                g: from_g,
+                b: from_b,
+                a: from_a,
+            },
+            InputValue::Pixel { r, g, b, a },
+        ) => InputValue::Pixel {
+            r: lerp(*from_r, *r),
+            g: lerp(*from_g, *g),
+            b: lerp(*from_b, *b),
+            a: lerp(*from_a, *a),
+        },
+        _ if t < 0.5 => from.clone(),
+        _ => to.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use engine::node_graph::EngineNodeId;
+    use std::collections::HashMap;
+
+    fn node(inputs: &[(&str, InputValue)]) -> NodeData {
+        NodeData {
+            id: EngineNodeId::default(),
+            definition_name: "brightness".to_string(),
+            input_values: inputs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect::<HashMap<_, _>>(),
+            input_smoothing: HashMap::new(),
+            input_mappings: HashMap::new(),
+            render_passes: HashMap::new(),
+            input_globals: HashMap::new(),
+            engine_node_id: None,
+        }
+    }
+
+    fn connection() -> InputValue {
+        InputValue::Connection {
+            from_node: EngineNodeId::default(),
+            output_name: "Output".to_string(),
+        }
+    }
+
+    // --- Scene::recall() ---
+
+    #[test]
+    fn test_capture_and_recall() {
+        let mut snarl = Snarl::new();
+        let first = snarl.insert_node(
+            egui::pos2(0.0, 0.0),
+            node(&[("Amount", InputValue::Float(0.25)), ("Input", connection())]),
+        );
+        let second = snarl.insert_node(
+            egui::pos2(0.0, 0.0),
+            node(&[("Invert", InputValue::Bool(false))]),
+        );
+
+        let all = Scene::capture("All", &snarl, None);
+        assert_eq!(all.values.len(), 2);
+        let only_first = Scene::capture("First", &snarl, Some(&[first]));
+        assert_eq!(only_first.values.len(), 1);
+
+        snarl[first]
+            .input_values
+            .insert("Amount".to_string(), InputValue::Float(0.75));
+        snarl[second]
+            .input_values
+            .insert("Invert".to_string(), connection());
+
+        // The connected input is left alone.
+        assert!(all.recall(&mut snarl));
+        assert_eq!(snarl[first].input_values["Amount"], InputValue::Float(0.25));
+        assert_eq!(snarl[second].input_values["Invert"], connection());
+        assert!(!all.recall(&mut snarl));
+    }
+
+    // --- SceneFade::advance() ---
+
+    #[test]
+    fn test_fade() {
+        let mut snarl = Snarl::new();
+        let id = snarl.insert_node(
+            egui::pos2(0.0, 0.0),
+            node(&[
+                ("Amount", InputValue::Float(0.0)),
+                ("Mode", InputValue::Enum(0)),
+            ]),
+        );
+        let mut scene = Scene::capture("Scene", &snarl, None);
+        scene.values[0].value = InputValue::Float(1.0);
+        scene.values[1].value = InputValue::Enum(2);
+
+        let mut fade = SceneFade::new(&scene, &snarl, 2.0);
+        assert!(!fade.advance(&mut snarl, 0.5));
+        assert_eq!(snarl[id].input_values["Amount"], InputValue::Float(0.25));
+        assert_eq!(snarl[id].input_values["Mode"], InputValue::Enum(0));
+
+        assert!(!fade.advance(&mut snarl, 0.5));
+        assert_eq!(snarl[id].input_values["Mode"], InputValue::Enum(2));
+
+        assert!(fade.advance(&mut snarl, 5.0));
+        assert_eq!(snarl[id].input_values["Amount"], InputValue::Float(1.0));
+    }
+}
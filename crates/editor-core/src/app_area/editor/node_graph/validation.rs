@@ -1,15 +1,20 @@
+use super::graph_model::ensure_unique_ids;
 use super::{NodeData, NodeGraphState, VIRTUAL_OUTPUT_SINK_NAME};
 use egui_snarl::{NodeId as SnarlNodeId, Snarl};
 use engine::node::engine_node::{BuiltInHandler, NodeExecutionPlan};
 use engine::node::{NodeInput, NodeInputKind, NodeLibrary};
 use engine::node_graph::InputValue;
+use engine::tone_curve::ToneCurve;
 use media::midi::streams::list_ports;
 use std::collections::HashSet;
 
 /// Normalize all node inputs to match their current schema definitions.
 /// Call on project load to populate missing inputs (schema additions) with
-/// defaults and drop orphaned inputs (schema removals).
+/// defaults and drop orphaned inputs (schema removals). Nodes that share an
+/// ID are given new ones too (see [ensure_unique_ids]).
 pub fn normalize_node_inputs(state: &mut NodeGraphState, node_library: &NodeLibrary) {
+    ensure_unique_ids(&mut state.snarl);
+
     let all_node_ids: Vec<SnarlNodeId> = state.snarl.node_ids().map(|(id, _)| id).collect();
 
     for node_id in all_node_ids {
@@ -255,5 +260,7 @@ pub(super) fn default_input_value(input_def: &NodeInput) -> Option<InputValue> {
         NodeInputKind::File { default, .. } => default.clone().map(InputValue::File),
         NodeInputKind::Frame | NodeInputKind::MidiPacket => None,
         NodeInputKind::PortSelection => Some(InputValue::Text(String::new())),
+        NodeInputKind::Curve => Some(InputValue::Text(ToneCurve::identity().to_string())),
+        NodeInputKind::FloatArray { default } => Some(InputValue::FloatArray(default.clone())),
     }
 }
@@ -0,0 +1,442 @@
+use super::node_graph::{NodeData, Scene, SceneFade};
+use egui_snarl::{NodeId as SnarlNodeId, Snarl};
+use media::fps::consts::FPS_60;
+use media::midi::MidiPacket;
+use media::midi::streams::{LiveMidiStream, list_ports};
+use media::playback_stream::PlaybackStream;
+use std::time::Instant;
+
+/// A trigger that's waiting for the next key press or MIDI note, for the
+/// scene at this index.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Learning {
+    Key(usize),
+    MidiNote(usize),
+}
+
+/// A window listing the project's [Scene]s (cues), where they're captured,
+/// recalled, and given keyboard and MIDI triggers.
+///
+/// Triggers work while the window is closed, so the panel has to be shown
+/// every frame.
+pub struct ScenePanel {
+    open: bool,
+    new_scene_name: String,
+    /// How long recalling a scene fades for. Zero switches instantly.
+    fade_secs: f32,
+    fade: Option<SceneFade>,
+    last_frame: Option<Instant>,
+    learning: Option<Learning>,
+    midi_port_name: Option<String>,
+    midi_stream: Option<LiveMidiStream>,
+    /// The last packet from `midi_stream`, so only new notes trigger scenes.
+    last_midi_packet: MidiPacket,
+    status: Option<String>,
+}
+
+impl ScenePanel {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            new_scene_name: String::new(),
+            fade_secs: 0.0,
+            fade: None,
+            last_frame: None,
+            learning: None,
+            midi_port_name: None,
+            midi_stream: None,
+            last_midi_packet: MidiPacket::new(),
+            status: None,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Handle scene triggers, advance any running fade, and show the window if
+    /// it's open. `selected` is the nodes selected in the graph. Returns
+    /// whether `snarl` or `scenes` was changed.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        snarl: &mut Snarl<NodeData>,
+        scenes: &mut Vec<Scene>,
+        selected: &[SnarlNodeId],
+    ) -> bool {
+        let mut changed = self.check_triggers(ctx, snarl, scenes);
+        changed |= self.advance_fade(ctx, snarl);
+
+        if !self.open {
+            return changed;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Scenes")
+            .open(&mut open)
+            .default_size(egui::vec2(420.0, 320.0))
+            .resizable(true)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                changed |= self.show_capture(ui, snarl, scenes, selected);
+                ui.separator();
+                changed |= self.show_scenes(ui, snarl, scenes);
+                ui.separator();
+                self.show_settings(ui);
+
+                if let Some(status) = &self.status {
+                    ui.label(egui::RichText::new(status).weak());
+                }
+            });
+        self.open = open;
+        if !self.open {
+            self.learning = None;
+        }
+
+        changed
+    }
+
+    /// Recall `scene`, fading if a fade time is set.
+    fn recall(&mut self, snarl: &mut Snarl<NodeData>, scene: &Scene) -> bool {
+        self.status = Some(format!("Recalled \"{}\".", scene.name));
+        if self.fade_secs > 0.0 {
+            self.fade = Some(SceneFade::new(scene, snarl, self.fade_secs));
+            self.last_frame = None;
+            false
+        } else {
+            self.fade = None;
+            scene.recall(snarl)
+        }
+    }
+
+    fn advance_fade(&mut self, ctx: &egui::Context, snarl: &mut Snarl<NodeData>) -> bool {
+        let Some(fade) = &mut self.fade else {
+            return false;
+        };
+
+        let now = Instant::now();
+        let secs = self
+            .last_frame
+            .map(|last_frame| now.duration_since(last_frame).as_secs_f32())
+            .unwrap_or(0.0);
+        self.last_frame = Some(now);
+
+        if fade.advance(snarl, secs) {
+            self.fade = None;
+        } else {
+            ctx.request_repaint();
+        }
+        true
+    }
+
+    /// Recall scenes whose key was pressed or whose MIDI note was played, or
+    /// assign the trigger that's being learned.
+    fn check_triggers(
+        &mut self,
+        ctx: &egui::Context,
+        snarl: &mut Snarl<NodeData>,
+        scenes: &mut [Scene],
+    ) -> bool {
+        let mut triggered = Vec::new();
+
+        if !ctx.wants_keyboard_input() {
+            let pressed: Vec<egui::Key> = ctx.input(|i| {
+                i.events
+                    .iter()
+                    .filter_map(|event| match event {
+                        egui::Event::Key {
+                            key,
+                            pressed: true,
+                            repeat: false,
+                            ..
+                        } => Some(*key),
+                        _ => None,
+                    })
+                    .collect()
+            });
+            for key in pressed {
+                if let Some(Learning::Key(index)) = self.learning {
+                    self.learning = None;
+                    if let Some(scene) = scenes.get_mut(index) {
+                        scene.key = (key != egui::Key::Escape).then(|| key.name().to_string());
+                    }
+                    return true;
+                }
+                triggered.extend(
+                    (0..scenes.len())
+                        .filter(|&index| scenes[index].key.as_deref() == Some(key.name())),
+                );
+            }
+        }
+
+        for note in self.new_midi_notes(ctx) {
+            if let Some(Learning::MidiNote(index)) = self.learning {
+                self.learning = None;
+                if let Some(scene) = scenes.get_mut(index) {
+                    scene.midi_note = Some(note);
+                }
+                return true;
+            }
+            triggered
+                .extend((0..scenes.len()).filter(|&index| scenes[index].midi_note == Some(note)));
+        }
+
+        let mut changed = false;
+        for index in triggered {
+            let scene = scenes[index].clone();
+            changed |= self.recall(snarl, &scene);
+        }
+        changed
+    }
+
+    /// Notes that started playing on the MIDI port since the last frame.
+    fn new_midi_notes(&mut self, ctx: &egui::Context) -> Vec<u8> {
+        let Some(stream) = &mut self.midi_stream else {
+            return Vec::new();
+        };
+
+        let packet = match stream.fetch() {
+            Ok(packet) => packet,
+            Err(err) => {
+                self.status = Some(format!("MIDI input stopped: {err}"));
+                self.midi_stream = None;
+                self.midi_port_name = None;
+                return Vec::new();
+            }
+        };
+        let notes = packet
+            .on_key_velocities()
+            .filter(|(key, _)| self.last_midi_packet.is_key_off(*key))
+            .map(|(key, _)| key.as_u8())
+            .collect();
+        self.last_midi_packet = packet;
+
+        // Keep polling so notes trigger scenes without waiting for input.
+        ctx.request_repaint();
+        notes
+    }
+
+    /// The new scene's name and the capture buttons. Returns whether `scenes`
+    /// was changed.
+    fn show_capture(
+        &mut self,
+        ui: &mut egui::Ui,
+        snarl: &Snarl<NodeData>,
+        scenes: &mut Vec<Scene>,
+        selected: &[SnarlNodeId],
+    ) -> bool {
+        let mut captured = None;
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_scene_name)
+                    .hint_text(format!("Scene {}", scenes.len() + 1))
+                    .desired_width(160.0),
+            );
+            if ui.button("Capture All").clicked() {
+                captured = Some(Scene::capture(self.take_new_name(scenes), snarl, None));
+            }
+            if ui
+                .add_enabled(!selected.is_empty(), egui::Button::new("Capture Selected"))
+                .on_hover_text("Only capture the inputs of the selected nodes.")
+                .clicked()
+            {
+                captured = Some(Scene::capture(
+                    self.take_new_name(scenes),
+                    snarl,
+                    Some(selected),
+                ));
+            }
+        });
+
+        let Some(scene) = captured else {
+            return false;
+        };
+        self.status = Some(format!(
+            "Captured {} inputs as \"{}\".",
+            scene.values.len(),
+            scene.name
+        ));
+        scenes.push(scene);
+        true
+    }
+
+    fn take_new_name(&mut self, scenes: &[Scene]) -> String {
+        let name = std::mem::take(&mut self.new_scene_name);
+        if name.trim().is_empty() {
+            format!("Scene {}", scenes.len() + 1)
+        } else {
+            name
+        }
+    }
+
+    /// Every scene with its buttons. Returns whether `snarl` or `scenes` was
+    /// changed.
+    fn show_scenes(
+        &mut self,
+        ui: &mut egui::Ui,
+        snarl: &mut Snarl<NodeData>,
+        scenes: &mut Vec<Scene>,
+    ) -> bool {
+        if scenes.is_empty() {
+            ui.label(egui::RichText::new("No scenes yet.").weak());
+            return false;
+        }
+
+        let mut changed = false;
+        let mut removed = None;
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                egui::Grid::new("scene_list")
+                    .num_columns(5)
+                    .spacing([8.0, 4.0])
+                    .show(ui, |ui| {
+                        for (index, scene) in scenes.iter_mut().enumerate() {
+                            changed |= ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut scene.name)
+                                        .desired_width(140.0),
+                                )
+                                .changed();
+
+                            if ui
+                                .button("Recall")
+                                .on_hover_text(format!("{} inputs", scene.values.len()))
+                                .clicked()
+                            {
+                                let scene = scene.clone();
+                                changed |= self.recall(snarl, &scene);
+                            }
+
+                            let key_text = match (self.learning, &scene.key) {
+                                (Some(Learning::Key(learning)), _) if learning == index => {
+                                    "Press a key…".to_string()
+                                }
+                                (_, Some(key)) => format!("Key: {key}"),
+                                (_, None) => "Set Key".to_string(),
+                            };
+                            if ui
+                                .button(key_text)
+                                .on_hover_text("Press Escape to clear the key.")
+                                .clicked()
+                            {
+                                self.learning = Some(Learning::Key(index));
+                            }
+
+                            let note_text = match (self.learning, scene.midi_note) {
+                                (Some(Learning::MidiNote(learning)), _) if learning == index => {
+                                    "Play a note…".to_string()
+                                }
+                                (_, Some(note)) => format!("Note: {note}"),
+                                (_, None) => "Set Note".to_string(),
+                            };
+                            let note_button = ui.add_enabled(
+                                self.midi_stream.is_some(),
+                                egui::Button::new(note_text),
+                            );
+                            if note_button
+                                .on_disabled_hover_text("Choose a MIDI input below first.")
+                                .clicked()
+                            {
+                                self.learning = Some(Learning::MidiNote(index));
+                            }
+
+                            ui.menu_button("…", |ui| {
+                                if ui.button("Update from Graph").clicked() {
+                                    scene.update(snarl);
+                                    changed = true;
+                                    ui.close();
+                                }
+                                if ui.button("Clear Triggers").clicked() {
+                                    scene.key = None;
+                                    scene.midi_note = None;
+                                    changed = true;
+                                    ui.close();
+                                }
+                                if ui.button("Delete").clicked() {
+                                    removed = Some(index);
+                                    ui.close();
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(index) = removed {
+            scenes.remove(index);
+            self.learning = None;
+            changed = true;
+        }
+        changed
+    }
+
+    /// The fade time and MIDI input.
+    fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Fade time");
+            ui.add(
+                egui::DragValue::new(&mut self.fade_secs)
+                    .range(0.0..=60.0)
+                    .speed(0.05)
+                    .suffix(" s"),
+            )
+            .on_hover_text("How long recalling a scene crossfades for. 0 switches instantly.");
+            if let Some(fade) = &self.fade {
+                ui.add(egui::ProgressBar::new(fade.progress()).desired_width(80.0));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("MIDI input");
+            let selected_text = self.midi_port_name.as_deref().unwrap_or("None");
+            egui::ComboBox::from_id_salt("scene_midi_port")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    if ui
+                        .selectable_label(self.midi_port_name.is_none(), "None")
+                        .clicked()
+                    {
+                        self.set_midi_port(None);
+                    }
+                    let ports = match list_ports() {
+                        Ok(ports) => ports.collect::<Vec<_>>(),
+                        Err(err) => {
+                            ui.label(format!("Couldn't list MIDI inputs: {err}"));
+                            Vec::new()
+                        }
+                    };
+                    for port in ports {
+                        let selected = self.midi_port_name.as_deref() == Some(port.port_name());
+                        if ui.selectable_label(selected, port.port_name()).clicked() {
+                            self.set_midi_port(Some(port));
+                        }
+                    }
+                });
+        });
+    }
+
+    fn set_midi_port(&mut self, port: Option<media::midi::streams::Port>) {
+        self.midi_stream = None;
+        self.midi_port_name = None;
+        self.last_midi_packet = MidiPacket::new();
+        if matches!(self.learning, Some(Learning::MidiNote(_))) {
+            self.learning = None;
+        }
+
+        let Some(port) = port else {
+            return;
+        };
+        let name = port.port_name().to_string();
+        match LiveMidiStream::new(port, FPS_60, false) {
+            Ok(stream) => {
+                self.midi_stream = Some(stream);
+                self.midi_port_name = Some(name);
+            }
+            Err(err) => {
+                self.status = Some(format!("Couldn't open \"{name}\": {err}"));
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::usage_stats::UsageSession;
+use engine::node::NodeLibrary;
+use std::time::Duration;
+
+/// How many of the most used nodes are listed.
+const TOP_NODES: usize = 10;
+
+/// A window showing the user's usage stats (see [UsageSession]): their most
+/// used nodes, how long their sessions last, and how much they've exported.
+pub struct UsageStatsPanel {
+    open: bool,
+}
+
+impl UsageStatsPanel {
+    pub fn new() -> Self {
+        Self { open: false }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Show the window if it's open. Nodes are named as `node_library` names
+    /// them.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        usage: &mut UsageSession,
+        node_library: &NodeLibrary,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Your Stats")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(
+                        "These are only kept on this computer and are never sent anywhere.",
+                    )
+                    .weak(),
+                );
+                ui.separator();
+
+                let stats = usage.stats();
+                egui::Grid::new("usage_stats")
+                    .num_columns(2)
+                    .spacing([16.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label("Sessions");
+                        ui.label(stats.sessions.to_string());
+                        ui.end_row();
+
+                        ui.label("Average session");
+                        ui.label(
+                            stats
+                                .average_session()
+                                .map(duration_text)
+                                .unwrap_or_else(|| "-".to_string()),
+                        );
+                        ui.end_row();
+
+                        ui.label("Total time");
+                        ui.label(duration_text(stats.session_time));
+                        ui.end_row();
+
+                        ui.label("Exports");
+                        ui.label(format!(
+                            "{} ({} frames)",
+                            stats.exports, stats.exported_frames
+                        ));
+                        ui.end_row();
+                    });
+
+                ui.add_space(8.0);
+                ui.label(egui::RichText::new("Most used nodes").strong());
+                let nodes = stats.most_used_nodes(TOP_NODES);
+                if nodes.is_empty() {
+                    ui.label(egui::RichText::new("No nodes added yet.").weak());
+                }
+                for (definition_name, uses) in nodes {
+                    let name = node_library
+                        .get_definition(definition_name)
+                        .map_or(definition_name, |definition| &definition.node.name);
+                    ui.horizontal(|ui| {
+                        ui.label(name);
+                        ui.label(egui::RichText::new(format!("×{uses}")).weak());
+                    });
+                }
+
+                ui.separator();
+                if ui.button("Clear Stats").clicked() {
+                    usage.clear();
+                }
+            });
+        self.open = open;
+    }
+}
+
+/// `duration` in hours and minutes, or minutes and seconds if it's under an
+/// hour.
+fn duration_text(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+    } else {
+        format!("{}m {}s", secs / 60, secs % 60)
+    }
+}
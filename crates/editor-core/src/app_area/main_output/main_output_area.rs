@@ -1,10 +1,20 @@
 use super::output_controls::OutputControls;
 use super::output_window::OutputWindow;
-use engine::engine_outpost::{EngineCommandSender, EngineEventReceiver};
+use crate::app_settings::{PreviewSettings, PreviewWindow, ProfileChoice};
+use crate::display_profile;
+use engine::engine_outpost::{EngineCommandSender, EngineEventReceiver, PlaybackClient};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How often the monitor's color profile is looked up again (the window may
+/// have moved to another monitor).
+const MONITOR_PROFILE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct MainOutputArea {
     controls: OutputControls,
     output_window: OutputWindow,
+    /// The monitor's color profile, and when it was last looked up.
+    monitor_profile: Option<(Option<PathBuf>, Instant)>,
 }
 
 impl MainOutputArea {
@@ -12,11 +22,22 @@ impl MainOutputArea {
         Self {
             controls: OutputControls::new(),
             output_window: OutputWindow::new(),
+            monitor_profile: None,
         }
     }
 
-    pub fn init_engine(&mut self, tx: EngineCommandSender, rx: EngineEventReceiver) {
-        self.output_window.init_engine(tx, rx);
+    /// Pause playback.
+    pub fn pause(&mut self) {
+        self.controls.pause();
+    }
+
+    pub fn init_engine(
+        &mut self,
+        tx: EngineCommandSender,
+        playback_client: PlaybackClient,
+        rx: EngineEventReceiver,
+    ) {
+        self.output_window.init_engine(tx, playback_client, rx);
     }
 
     pub fn playback_enabled(&self) -> bool {
@@ -31,7 +52,37 @@ impl MainOutputArea {
         self.output_window.has_frame()
     }
 
-    pub fn show(&mut self, ctx: &egui::Context, render_state: &egui_wgpu::RenderState) {
+    /// The profile file `window` is shown through, if any.
+    fn profile_path(
+        &mut self,
+        preview: &PreviewSettings,
+        window: PreviewWindow,
+    ) -> Option<PathBuf> {
+        match preview.profile_for(window) {
+            ProfileChoice::None => None,
+            ProfileChoice::File(path) => Some(path.clone()),
+            ProfileChoice::Monitor => {
+                let stale = self
+                    .monitor_profile
+                    .as_ref()
+                    .is_none_or(|(_, checked)| checked.elapsed() >= MONITOR_PROFILE_CHECK_INTERVAL);
+                if stale {
+                    self.monitor_profile =
+                        Some((display_profile::monitor_profile_path(), Instant::now()));
+                }
+                self.monitor_profile
+                    .as_ref()
+                    .and_then(|(path, _)| path.clone())
+            }
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        render_state: &egui_wgpu::RenderState,
+        preview: &PreviewSettings,
+    ) {
         // Poll engine events first — OutputWindow owns its receiver
         self.output_window.drain_engine_events(render_state);
 
@@ -39,6 +90,15 @@ impl MainOutputArea {
             *self.controls.fullscreen_enabled_mut() = false;
         }
 
+        let window = if self.controls.fullscreen_enabled() {
+            PreviewWindow::Fullscreen
+        } else {
+            PreviewWindow::OutputPanel
+        };
+        let profile_path = self.profile_path(preview, window);
+        self.output_window
+            .set_display_profile(render_state, profile_path);
+
         if self.controls.fullscreen_enabled() {
             egui::Area::new(egui::Id::new("fullscreen_output"))
                 .fixed_pos(egui::pos2(0.0, 0.0))
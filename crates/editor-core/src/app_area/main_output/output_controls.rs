@@ -1,10 +1,23 @@
+use std::ops::RangeInclusive;
+
+use engine::node::handler::LoopMode;
+use media::fps::PreviewRate;
+use media::fps::consts::{FPS_15, FPS_24, FPS_30};
+
 pub struct OutputControls {
     playback_enabled: bool,
     show_info: bool,
     preview_selected_node: bool,
     manual_fps_enabled: bool,
     manual_fps_value: f32,
+    /// How often the preview is rendered, independent of the output's FPS.
+    preview_rate: PreviewRate,
     fullscreen_enabled: bool,
+    /// Whether only the region drawn on the preview is rendered.
+    region_render_enabled: bool,
+    loop_mode: LoopMode,
+    loop_in: Option<usize>,
+    loop_out: Option<usize>,
 }
 
 impl OutputControls {
@@ -15,7 +28,12 @@ impl OutputControls {
             preview_selected_node: false,
             manual_fps_enabled: false,
             manual_fps_value: 30.0,
+            preview_rate: PreviewRate::default(),
             fullscreen_enabled: false,
+            region_render_enabled: false,
+            loop_mode: LoopMode::default(),
+            loop_in: None,
+            loop_out: None,
         }
     }
 
@@ -23,6 +41,11 @@ impl OutputControls {
         self.playback_enabled
     }
 
+    /// Pause playback (e.g. when stepping frame by frame).
+    pub fn pause(&mut self) {
+        self.playback_enabled = false;
+    }
+
     pub fn show_info(&self) -> bool {
         self.show_info
     }
@@ -39,6 +62,10 @@ impl OutputControls {
         &mut self.fullscreen_enabled
     }
 
+    pub fn region_render_enabled(&self) -> bool {
+        self.region_render_enabled
+    }
+
     pub fn manual_fps_enabled(&self) -> bool {
         self.manual_fps_enabled
     }
@@ -47,6 +74,49 @@ impl OutputControls {
         self.manual_fps_value
     }
 
+    pub fn preview_rate(&self) -> PreviewRate {
+        self.preview_rate
+    }
+
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    /// The in/out frames to loop, or [None] if neither is set. A missing in or
+    /// out point means the start or end of the video.
+    pub fn loop_region(&self) -> Option<RangeInclusive<usize>> {
+        match (self.loop_in, self.loop_out) {
+            (None, None) => None,
+            (loop_in, loop_out) => {
+                let loop_in = loop_in.unwrap_or(0);
+                // The engine clamps the end to the video's last frame.
+                let loop_out = loop_out.unwrap_or(usize::MAX).max(loop_in);
+                Some(loop_in..=loop_out)
+            }
+        }
+    }
+
+    /// Set the in point, moving the out point if it's before it.
+    pub fn set_loop_in(&mut self, frame: usize) {
+        self.loop_in = Some(frame);
+        if self.loop_out.is_some_and(|loop_out| loop_out < frame) {
+            self.loop_out = Some(frame);
+        }
+    }
+
+    /// Set the out point, moving the in point if it's after it.
+    pub fn set_loop_out(&mut self, frame: usize) {
+        self.loop_out = Some(frame);
+        if self.loop_in.is_some_and(|loop_in| loop_in > frame) {
+            self.loop_in = Some(frame);
+        }
+    }
+
+    pub fn clear_loop_region(&mut self) {
+        self.loop_in = None;
+        self.loop_out = None;
+    }
+
     pub fn show(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             let play_pause_label = if self.playback_enabled {
@@ -57,11 +127,27 @@ impl OutputControls {
             if ui.button(play_pause_label).clicked() {
                 self.playback_enabled = !self.playback_enabled;
             }
+            egui::ComboBox::from_id_salt("output_loop_mode")
+                .selected_text(loop_mode_label(self.loop_mode))
+                .width(96.0)
+                .show_ui(ui, |ui| {
+                    for mode in [LoopMode::Loop, LoopMode::PingPong, LoopMode::PlayOnce] {
+                        ui.selectable_value(&mut self.loop_mode, mode, loop_mode_label(mode));
+                    }
+                })
+                .response
+                .on_hover_text("What video sources do when they reach the out point");
             ui.separator();
             ui.checkbox(&mut self.show_info, "Info");
             ui.separator();
             ui.checkbox(&mut self.preview_selected_node, "Preview Selected Node");
             ui.separator();
+            ui.checkbox(&mut self.region_render_enabled, "Region")
+                .on_hover_text(
+                    "Only render a rectangle of the output. Drag on the preview to draw it, \
+                     double-click to clear it.",
+                );
+            ui.separator();
             ui.checkbox(&mut self.manual_fps_enabled, "Manual FPS");
 
             let fps_widget = egui::DragValue::new(&mut self.manual_fps_value)
@@ -70,6 +156,26 @@ impl OutputControls {
                 .suffix(" fps");
             ui.add_enabled(self.manual_fps_enabled, fps_widget);
 
+            egui::ComboBox::from_id_salt("output_preview_rate")
+                .selected_text(preview_rate_label(self.preview_rate))
+                .width(96.0)
+                .show_ui(ui, |ui| {
+                    for rate in [
+                        PreviewRate::Target,
+                        PreviewRate::Limited(FPS_30),
+                        PreviewRate::Limited(FPS_24),
+                        PreviewRate::Limited(FPS_15),
+                        PreviewRate::Unlimited,
+                    ] {
+                        ui.selectable_value(&mut self.preview_rate, rate, preview_rate_label(rate));
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "How often the preview is rendered. Lower rates skip frames without slowing \
+                     playback down; Unlimited renders as fast as it can (for benchmarking).",
+                );
+
             ui.separator();
             // TODO Using a phosphor icon
             if ui.button("⛶ Fullscreen").clicked() {
@@ -79,6 +185,22 @@ impl OutputControls {
     }
 }
 
+fn loop_mode_label(loop_mode: LoopMode) -> &'static str {
+    match loop_mode {
+        LoopMode::Loop => "Loop",
+        LoopMode::PingPong => "Ping-Pong",
+        LoopMode::PlayOnce => "Play Once",
+    }
+}
+
+fn preview_rate_label(preview_rate: PreviewRate) -> String {
+    match preview_rate {
+        PreviewRate::Target => "Full Rate".to_string(),
+        PreviewRate::Limited(fps) => format!("{} fps", fps.as_float().round()),
+        PreviewRate::Unlimited => "Unlimited".to_string(),
+    }
+}
+
 impl Default for OutputControls {
     fn default() -> Self {
         Self::new()
@@ -1,15 +1,34 @@
 use super::output_controls::OutputControls;
 use crate::components::FrameDisplay;
+use crate::display_profile::DisplayProfile;
 use engine::engine_outpost::EngineOutpostEvent;
 use engine::engine_outpost::message::EngineCommand;
-use engine::engine_outpost::{EngineCommandSender, EngineEventReceiver};
-use engine::graph_executor::NodeValue;
-use media::fps::Fps;
+use engine::engine_outpost::{
+    EngineCommandSender, EngineEventReceiver, PendingPlayback, PlaybackClient, PlaybackRequest,
+};
+use engine::graph_executor::{NodeValue, RenderRegion};
+use engine::node::handler::LoopMode;
+use media::fps::{Fps, PreviewRate};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use util::timecode::{self, FrameRate, Timecode};
+
+/// How long a message about a worker recovering from an error (or a panic) is
+/// shown.
+const NOTICE_DURATION: Duration = Duration::from_secs(5);
+
+/// How long the user has to leave the app alone before the engine starts
+/// rendering frames in the background.
+const BACKGROUND_RENDER_IDLE_TIME: Duration = Duration::from_secs(3);
 
 /// Main output window for displaying frames with native FPS tracking
 pub struct OutputWindow {
     engine_tx: Option<EngineCommandSender>,
     engine_rx: Option<EngineEventReceiver>,
+    playback_client: Option<PlaybackClient>,
+    /// Seeks the engine hasn't answered yet.
+    pending_playback: PendingPlayback,
     current_output: Option<NodeValue>,
     playback_fps: Option<Fps>,
     last_texture_view_ptr: Option<usize>,
@@ -17,10 +36,47 @@ pub struct OutputWindow {
     frame_width: u32,
     frame_height: u32,
     frame_display: FrameDisplay,
+    /// The color profile file the preview is shown through, if any.
+    display_profile_path: Option<PathBuf>,
+    /// Why that profile couldn't be used, if it couldn't.
+    display_profile_error: Option<String>,
     /// Tracks whether a stream is explicitly loading (not just "no frame available")
     is_stream_loading: bool,
     /// The last manual FPS value sent to the engine, or None if auto mode is active.
     last_sent_manual_fps: Option<Fps>,
+    /// The preview rate last sent to the engine.
+    last_sent_preview_rate: PreviewRate,
+    /// The name of a subsystem the engine's watchdog reported as stalled.
+    /// Cleared once frames start arriving again.
+    stalled_subsystem: Option<String>,
+    /// A message about the last error (or panic) a worker recovered from (or
+    /// failed to), and when it was reported.
+    worker_notice: Option<(String, Instant)>,
+    /// The frame index (and FPS) of the output's video source, if it has one.
+    playback_position: Option<(usize, Fps)>,
+    /// How many other apps are in the Ableton Link session, if Link is on.
+    link_peers: Option<usize>,
+    /// How many nodes are showing placeholders while their shaders compile.
+    compiling_nodes: usize,
+    /// The loop mode and region last sent to the engine.
+    last_sent_loop: (LoopMode, Option<RangeInclusive<usize>>),
+    /// The region of the output drawn on the preview, rendered when region
+    /// rendering is on.
+    render_region: Option<RenderRegion>,
+    /// Where a new region started being drawn (as a fraction of the frame).
+    region_drag_start: Option<egui::Vec2>,
+    /// The render region last sent to the engine.
+    last_sent_render_region: Option<RenderRegion>,
+    /// The output's clip and the runs of frames in it the engine has cached.
+    cached_frames: (RangeInclusive<usize>, Vec<RangeInclusive<usize>>),
+    /// When the user last did anything (pressed a key, moved the mouse...).
+    last_interaction: Instant,
+    /// Whether the engine was last told to render in the background.
+    background_rendering: bool,
+    /// The contents of the "go to" timecode field.
+    goto_input: String,
+    /// Why the last "go to" input couldn't be parsed, if it couldn't.
+    goto_error: Option<String>,
 }
 
 impl OutputWindow {
@@ -28,6 +84,8 @@ impl OutputWindow {
         Self {
             engine_tx: None,
             engine_rx: None,
+            playback_client: None,
+            pending_playback: PendingPlayback::new(),
             current_output: None,
             playback_fps: None,
             last_texture_view_ptr: None,
@@ -35,13 +93,36 @@ impl OutputWindow {
             frame_width: 0,
             frame_height: 0,
             frame_display: FrameDisplay::new(),
+            display_profile_path: None,
+            display_profile_error: None,
             is_stream_loading: false,
             last_sent_manual_fps: None,
+            last_sent_preview_rate: PreviewRate::default(),
+            stalled_subsystem: None,
+            worker_notice: None,
+            playback_position: None,
+            link_peers: None,
+            compiling_nodes: 0,
+            last_sent_loop: (LoopMode::default(), None),
+            render_region: None,
+            region_drag_start: None,
+            last_sent_render_region: None,
+            cached_frames: (0..=0, Vec::new()),
+            last_interaction: Instant::now(),
+            background_rendering: false,
+            goto_input: String::new(),
+            goto_error: None,
         }
     }
 
-    pub fn init_engine(&mut self, tx: EngineCommandSender, rx: EngineEventReceiver) {
+    pub fn init_engine(
+        &mut self,
+        tx: EngineCommandSender,
+        playback_client: PlaybackClient,
+        rx: EngineEventReceiver,
+    ) {
         self.engine_tx = Some(tx);
+        self.playback_client = Some(playback_client);
         self.engine_rx = Some(rx);
     }
 
@@ -82,9 +163,12 @@ impl OutputWindow {
                     engine::engine_outpost::message::InfoResponse::Error(msg) => {
                         util::debug_log_warning!("Engine InfoResponse error: {msg}");
                     }
+                    engine::engine_outpost::message::InfoResponse::NodeTimings(_)
+                    | engine::engine_outpost::message::InfoResponse::BufferingStats(_) => {}
                 },
                 EngineOutpostEvent::FrameReady(frame) => {
                     self.is_stream_loading = false;
+                    self.stalled_subsystem = None;
                     let output = NodeValue::Frame(frame);
                     self.current_output = Some(output.clone());
                     self.set_output_frame(render_state, &output);
@@ -92,10 +176,85 @@ impl OutputWindow {
                 EngineOutpostEvent::ExecutionError(_) => {
                     self.is_stream_loading = false;
                 }
+                EngineOutpostEvent::WorkerStalled(subsystem) => {
+                    util::debug_log_warning!("Engine watchdog reported a stall: {subsystem}");
+                    self.stalled_subsystem = Some(subsystem);
+                }
+                EngineOutpostEvent::PlaybackPosition { frame, fps } => {
+                    self.playback_position = Some((frame, fps));
+                }
+                EngineOutpostEvent::WorkerPanicked {
+                    subsystem, message, ..
+                } => {
+                    let message = format!("{subsystem} crashed: {message}");
+                    self.worker_notice = Some((message, Instant::now()));
+                }
+                EngineOutpostEvent::StreamRecovered {
+                    frame,
+                    error,
+                    recovered,
+                    ..
+                } => {
+                    let message = if recovered {
+                        format!("Recovered from a decode error at frame {frame}: {error}")
+                    } else {
+                        format!(
+                            "A video couldn't recover from a decode error at frame {frame}: {error}"
+                        )
+                    };
+                    util::debug_log_warning!("{message}");
+                    self.worker_notice = Some((message, Instant::now()));
+                }
+                EngineOutpostEvent::TraceSaved(_)
+                | EngineOutpostEvent::NodeOutputSaved(_)
+                | EngineOutpostEvent::ExecutionOrder(_)
+                | EngineOutpostEvent::GraphEditFailed(_) => {}
+                EngineOutpostEvent::LinkStatus(peers) => {
+                    self.link_peers = peers;
+                }
+                EngineOutpostEvent::ShadersCompiling(nodes) => {
+                    self.compiling_nodes = nodes.len();
+                }
+                EngineOutpostEvent::CachedFrames { clip, cached } => {
+                    self.cached_frames = (clip, cached);
+                }
             }
         }
     }
 
+    /// Show the preview through the color profile at `path` ([None] to show
+    /// frames as they are). Only does anything when the path changes.
+    pub fn set_display_profile(
+        &mut self,
+        render_state: &egui_wgpu::RenderState,
+        path: Option<PathBuf>,
+    ) {
+        if path == self.display_profile_path {
+            return;
+        }
+
+        let profile = match path.as_deref().map(DisplayProfile::load).transpose() {
+            Ok(profile) => {
+                self.display_profile_error = None;
+                profile
+            }
+            Err(e) => {
+                util::debug_log_warning!("Failed to load color profile: {e}");
+                self.display_profile_error = Some(e);
+                None
+            }
+        };
+        self.display_profile_path = path;
+
+        self.frame_display
+            .set_profile(render_state, profile.as_ref());
+        self.last_texture_view_ptr = None;
+        self.last_renderer_ptr = None;
+        if let Some(output) = self.current_output.clone() {
+            self.set_output_frame(render_state, &output);
+        }
+    }
+
     /// Update the displayed frame from output value
     pub fn set_output_frame(&mut self, render_state: &egui_wgpu::RenderState, output: &NodeValue) {
         match output {
@@ -182,6 +341,331 @@ impl OutputWindow {
             let _ = tx.send(EngineCommand::ClearManualFps);
             self.last_sent_manual_fps = None;
         }
+
+        let preview_rate = controls.preview_rate();
+        if preview_rate != self.last_sent_preview_rate {
+            let _ = tx.send(EngineCommand::SetPreviewRate(preview_rate));
+            self.last_sent_preview_rate = preview_rate;
+        }
+    }
+
+    fn sync_loop_to_engine(&mut self, controls: &OutputControls) {
+        let Some(tx) = &self.engine_tx else {
+            return;
+        };
+
+        let (loop_mode, loop_region) = (controls.loop_mode(), controls.loop_region());
+        if loop_mode != self.last_sent_loop.0 {
+            let _ = tx.send(EngineCommand::SetLoopMode(loop_mode));
+        }
+        if loop_region != self.last_sent_loop.1 {
+            let _ = tx.send(EngineCommand::SetLoopRegion(loop_region.clone()));
+        }
+        self.last_sent_loop = (loop_mode, loop_region);
+    }
+
+    fn sync_render_region_to_engine(&mut self, controls: &OutputControls) {
+        let Some(tx) = &self.engine_tx else {
+            return;
+        };
+
+        let region = self
+            .render_region
+            .filter(|_| controls.region_render_enabled());
+        if region != self.last_sent_render_region {
+            let _ = tx.send(EngineCommand::SetRenderRegion(region));
+            self.last_sent_render_region = region;
+        }
+    }
+
+    /// Have the engine render frames in the background once the user has been
+    /// idle for a while, and stop as soon as they do anything.
+    fn sync_background_rendering(&mut self, ctx: &egui::Context) {
+        let Some(tx) = &self.engine_tx else {
+            return;
+        };
+
+        if ctx.input(|i| !i.events.is_empty() || i.pointer.any_down()) {
+            self.last_interaction = Instant::now();
+        }
+        let idle_for = self.last_interaction.elapsed();
+        let idle = idle_for >= BACKGROUND_RENDER_IDLE_TIME;
+        if idle != self.background_rendering {
+            let _ = tx.send(EngineCommand::SetBackgroundRendering(idle));
+            self.background_rendering = idle;
+        }
+        if !idle {
+            // Nothing else may repaint by the time the user counts as idle.
+            ctx.request_repaint_after(BACKGROUND_RENDER_IDLE_TIME - idle_for);
+        }
+    }
+
+    /// A thin bar spanning the output's clip, green where frames are cached,
+    /// with a tick at the playhead. Hidden while nothing is cached.
+    fn show_cached_frames(&self, ui: &mut egui::Ui) {
+        let (clip, cached) = &self.cached_frames;
+        if cached.is_empty() {
+            return;
+        }
+
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 4.0), egui::Sense::hover());
+        let frames = (clip.end() - clip.start() + 1) as f32;
+        let x = |frame: usize| {
+            rect.left() + rect.width() * frame.saturating_sub(*clip.start()) as f32 / frames
+        };
+
+        let painter = ui.painter();
+        painter.rect_filled(rect, 0.0, egui::Color32::from_rgb(38, 47, 51));
+        for range in cached {
+            painter.rect_filled(
+                egui::Rect::from_x_y_ranges(x(*range.start())..=x(range.end() + 1), rect.y_range()),
+                0.0,
+                egui::Color32::from_rgb(110, 200, 120),
+            );
+        }
+        if let Some((frame, _)) = self.playback_position {
+            painter.vline(
+                x(frame),
+                rect.y_range(),
+                egui::Stroke::new(1.0, egui::Color32::WHITE),
+            );
+        }
+
+        let count: usize = cached
+            .iter()
+            .map(|range| range.end() - range.start() + 1)
+            .sum();
+        response.on_hover_text(format!("{count} frame(s) cached while idle"));
+    }
+
+    /// Draw the render region over the frame (shown in `rect`), dimming the
+    /// rest. Dragging draws a new region and double-clicking clears it.
+    fn edit_render_region(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let response = ui.interact(
+            rect,
+            ui.id().with("render_region"),
+            egui::Sense::click_and_drag(),
+        );
+        let to_fraction = |pos: egui::Pos2| {
+            ((pos - rect.min) / rect.size()).clamp(egui::Vec2::ZERO, egui::Vec2::splat(1.0))
+        };
+
+        if response.double_clicked() {
+            self.render_region = None;
+            self.region_drag_start = None;
+        } else if response.drag_started() {
+            self.region_drag_start = response.interact_pointer_pos().map(to_fraction);
+        }
+
+        let mut shown = self.render_region;
+        if let Some(start) = self.region_drag_start
+            && let Some(pos) = ui.input(|i| i.pointer.latest_pos())
+        {
+            let end = to_fraction(pos);
+            let min = start.min(end);
+            let size = (start - end).abs();
+            let region = RenderRegion {
+                x: min.x,
+                y: min.y,
+                width: size.x,
+                height: size.y,
+            };
+            shown = Some(region);
+            if !response.dragged() {
+                self.region_drag_start = None;
+                // Tiny regions are more likely slips than meant.
+                if size.x > 0.01 && size.y > 0.01 {
+                    self.render_region = Some(region);
+                }
+            }
+        }
+
+        let Some(region) = shown else {
+            return;
+        };
+        let region_rect = egui::Rect::from_min_size(
+            rect.min + egui::vec2(region.x, region.y) * rect.size(),
+            egui::vec2(region.width, region.height) * rect.size(),
+        );
+        let painter = ui.painter_at(rect);
+        let dim = egui::Color32::from_black_alpha(150);
+        for outside in [
+            egui::Rect::from_x_y_ranges(rect.x_range(), rect.top()..=region_rect.top()),
+            egui::Rect::from_x_y_ranges(rect.x_range(), region_rect.bottom()..=rect.bottom()),
+            egui::Rect::from_x_y_ranges(rect.left()..=region_rect.left(), region_rect.y_range()),
+            egui::Rect::from_x_y_ranges(region_rect.right()..=rect.right(), region_rect.y_range()),
+        ] {
+            painter.rect_filled(outside, 0.0, dim);
+        }
+        painter.rect_stroke(
+            region_rect,
+            0.0,
+            egui::Stroke::new(1.5, egui::Color32::from_rgb(80, 160, 220)),
+            egui::StrokeKind::Outside,
+        );
+    }
+
+    /// Step the output by `delta` frames with the `,`/`.` keys or the step
+    /// buttons. Stepping pauses playback.
+    fn handle_frame_stepping(&mut self, ui: &mut egui::Ui, controls: &mut OutputControls) {
+        let mut delta = 0;
+        let mut seek_target = None;
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("◀ Frame")
+                .on_hover_text("Previous frame (,)")
+                .clicked()
+            {
+                delta -= 1;
+            }
+            if ui
+                .button("Frame ▶")
+                .on_hover_text("Next frame (.)")
+                .clicked()
+            {
+                delta += 1;
+            }
+
+            if let Some((frame, fps)) = self.playback_position
+                && let Some(rate) = FrameRate::new(fps.num(), fps.den())
+            {
+                ui.separator();
+                ui.label(format!("Frame {frame}"));
+                ui.separator();
+                let current = Timecode::from_frame(frame as u64, rate, true);
+                ui.label(egui::RichText::new(current.to_string()).monospace())
+                    .on_hover_text(timecode::format_frame_clock(frame as u64, rate));
+                ui.separator();
+
+                if let Some(target) = self.show_goto_field(ui, rate) {
+                    seek_target = Some(target as usize);
+                }
+                ui.separator();
+
+                if ui
+                    .button("[ In")
+                    .on_hover_text("Set loop in point")
+                    .clicked()
+                {
+                    controls.set_loop_in(frame);
+                }
+                if ui
+                    .button("Out ]")
+                    .on_hover_text("Set loop out point")
+                    .clicked()
+                {
+                    controls.set_loop_out(frame);
+                }
+                if let Some(region) = controls.loop_region() {
+                    let out = if *region.end() == usize::MAX {
+                        "end".to_string()
+                    } else {
+                        region.end().to_string()
+                    };
+                    ui.label(format!("{}-{out}", region.start()));
+                    if ui
+                        .small_button("✕")
+                        .on_hover_text("Clear loop region")
+                        .clicked()
+                    {
+                        controls.clear_loop_region();
+                    }
+                }
+            }
+        });
+
+        if !ui.ctx().wants_keyboard_input() {
+            ui.ctx().input(|i| {
+                if i.key_pressed(egui::Key::Comma) {
+                    delta -= 1;
+                }
+                if i.key_pressed(egui::Key::Period) {
+                    delta += 1;
+                }
+            });
+        }
+
+        if let Some(frame) = seek_target {
+            controls.pause();
+            if let Some(client) = &self.playback_client
+                && let Err(err) = self
+                    .pending_playback
+                    .send(client, PlaybackRequest::Seek { frame })
+            {
+                util::debug_log_warning!("Failed to queue seek: {err}");
+            }
+        }
+
+        if delta == 0 {
+            return;
+        }
+
+        controls.pause();
+        if let Some(tx) = &self.engine_tx
+            && let Err(err) = tx.send(EngineCommand::StepFrames(delta))
+        {
+            util::debug_log_warning!("Failed to queue frame step: {err}");
+        }
+    }
+
+    /// Shows how many peers are in the Ableton Link session while Link is on.
+    fn show_link_status(&self, ui: &mut egui::Ui) {
+        let Some(peers) = self.link_peers else {
+            return;
+        };
+
+        let text = match peers {
+            0 => "Link: no peers".to_string(),
+            1 => "Link: 1 peer".to_string(),
+            _ => format!("Link: {peers} peers"),
+        };
+        let color = if peers > 0 {
+            egui::Color32::from_rgb(110, 200, 120)
+        } else {
+            egui::Color32::GRAY
+        };
+        ui.separator();
+        ui.label(egui::RichText::new(text).color(color))
+            .on_hover_text("Ableton Link is on (see Project Settings)");
+    }
+
+    /// A text field for jumping to a user-entered timecode, clock time, or
+    /// frame index. Returns the target frame when one is submitted.
+    fn show_goto_field(&mut self, ui: &mut egui::Ui, rate: FrameRate) -> Option<u64> {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.goto_input)
+                .hint_text("Go to...")
+                .desired_width(96.0),
+        );
+        let response = match &self.goto_error {
+            Some(err) => response.on_hover_text(
+                egui::RichText::new(err).color(egui::Color32::from_rgb(230, 120, 90)),
+            ),
+            None => response.on_hover_text("HH:MM:SS:FF, HH:MM:SS.mmm, or a frame number"),
+        };
+
+        if response.changed() {
+            self.goto_error = None;
+        }
+        if !(response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+            return None;
+        }
+        if self.goto_input.trim().is_empty() {
+            return None;
+        }
+
+        match timecode::parse(&self.goto_input, rate) {
+            Ok(frame) => {
+                self.goto_input.clear();
+                Some(frame)
+            }
+            Err(err) => {
+                self.goto_error = Some(err.to_string());
+                None
+            }
+        }
     }
 
     /// Render the output window to a UI
@@ -194,10 +678,69 @@ impl OutputWindow {
                 ui.vertical(|ui| {
                     ui.horizontal(|ui| {
                         controls.show(ui);
+                        self.show_link_status(ui);
                     });
                     self.sync_fps_to_engine(controls);
+                    self.sync_loop_to_engine(controls);
+                    self.sync_render_region_to_engine(controls);
+                    self.sync_background_rendering(ui.ctx());
+                    self.handle_frame_stepping(ui, controls);
+                    self.show_cached_frames(ui);
                     ui.separator();
 
+                    if let Some(subsystem) = &self.stalled_subsystem {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{subsystem} is not responding. Playback may be stuck."
+                            ))
+                            .color(egui::Color32::from_rgb(230, 120, 90)),
+                        );
+                        ui.separator();
+                    }
+
+                    if let Some(error) = self.pending_playback.take_errors().pop() {
+                        let message = format!("Couldn't seek: {error}");
+                        self.worker_notice = Some((message, Instant::now()));
+                    }
+                    if self
+                        .worker_notice
+                        .as_ref()
+                        .is_some_and(|(_, reported)| reported.elapsed() >= NOTICE_DURATION)
+                    {
+                        self.worker_notice = None;
+                    }
+                    if let Some((message, _)) = &self.worker_notice {
+                        ui.label(
+                            egui::RichText::new(message)
+                                .color(egui::Color32::from_rgb(230, 120, 90)),
+                        );
+                        ui.separator();
+                    }
+
+                    if self.compiling_nodes > 0 {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::Spinner::new().size(12.0));
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "Compiling shaders for {} node(s)…",
+                                    self.compiling_nodes
+                                ))
+                                .color(egui::Color32::from_rgb(130, 155, 170)),
+                            );
+                        });
+                        ui.separator();
+                    }
+
+                    if let Some(err) = &self.display_profile_error {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "The color profile couldn't be used: {err}"
+                            ))
+                            .color(egui::Color32::from_rgb(230, 120, 90)),
+                        );
+                        ui.separator();
+                    }
+
                     if self.is_stream_loading {
                         let available = ui.available_size();
                         ui.allocate_ui(available, |ui| {
@@ -233,7 +776,11 @@ impl OutputWindow {
                         // Allocate all remaining vertical space for the frame
                         let available = ui.available_size();
                         ui.allocate_ui(available, |ui| {
-                            self.frame_display.render_content(ui);
+                            if let Some(rect) = self.frame_display.render_content(ui)
+                                && controls.region_render_enabled()
+                            {
+                                self.edit_render_region(ui, rect);
+                            }
                         });
                     } else {
                         ui.centered_and_justified(|ui| {
@@ -0,0 +1,156 @@
+//! The Preferences window, for app-wide settings (see [AppSettings]).
+
+use crate::app_settings::{AppSettings, PreviewWindow, ProfileChoice};
+use crate::display_profile;
+
+pub struct PreferencesWindow {
+    open: bool,
+}
+
+impl PreferencesWindow {
+    pub fn new() -> Self {
+        Self { open: false }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Show the window if it's open. Returns whether `settings` changed.
+    pub fn show(&mut self, ctx: &egui::Context, settings: &mut AppSettings) -> bool {
+        if !self.open {
+            return false;
+        }
+
+        let before = settings.clone();
+        let mut open = self.open;
+
+        egui::Window::new("Preferences")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.heading("Preview Color Profile");
+                ui.label(
+                    egui::RichText::new(
+                        "Shows the preview through your monitor's color profile. \
+                        This never changes what's rendered or exported.",
+                    )
+                    .weak(),
+                );
+                ui.add_space(4.0);
+
+                let preview = &mut settings.preview;
+                egui::Grid::new("preferences_profile_grid")
+                    .num_columns(2)
+                    .spacing([12.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label("Default");
+                        show_profile_choice(
+                            ui,
+                            "preferences_default_profile",
+                            &mut preview.profile,
+                        );
+                        ui.end_row();
+
+                        for (window, label) in [
+                            (PreviewWindow::OutputPanel, "Output panel"),
+                            (PreviewWindow::Fullscreen, "Fullscreen output"),
+                        ] {
+                            ui.label(label);
+                            show_profile_override(ui, label, preview.profile_override_mut(window));
+                            ui.end_row();
+                        }
+                    });
+
+                ui.add_space(4.0);
+                let monitor_text = match display_profile::monitor_profile_path() {
+                    Some(path) => format!("Monitor profile: {}", path.display()),
+                    None => "The OS doesn't report a profile for this monitor. \
+                        Choose the profile's file instead."
+                        .to_string(),
+                };
+                ui.label(egui::RichText::new(monitor_text).weak());
+
+                ui.separator();
+                ui.heading("Help");
+                let onboarding = &mut settings.onboarding;
+                if ui
+                    .add_enabled(
+                        onboarding.graph_tutorial_dismissed,
+                        egui::Button::new("Show the node graph tutorial again"),
+                    )
+                    .clicked()
+                {
+                    onboarding.graph_tutorial_dismissed = false;
+                }
+            });
+
+        self.open = open;
+        *settings != before
+    }
+}
+
+/// A combo box choosing a [ProfileChoice], plus a button to pick the file for
+/// [ProfileChoice::File].
+fn show_profile_choice(ui: &mut egui::Ui, id: &str, choice: &mut ProfileChoice) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(profile_choice_name(choice))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(choice, ProfileChoice::None, "None (sRGB)");
+                ui.selectable_value(choice, ProfileChoice::Monitor, "Monitor profile");
+                if ui
+                    .selectable_label(matches!(choice, ProfileChoice::File(_)), "File…")
+                    .clicked()
+                    && let Some(path) = pick_profile_file()
+                {
+                    *choice = ProfileChoice::File(path);
+                }
+            });
+
+        if let ProfileChoice::File(path) = choice
+            && ui
+                .small_button("Browse…")
+                .on_hover_text(path.display().to_string())
+                .clicked()
+            && let Some(new_path) = pick_profile_file()
+        {
+            *path = new_path;
+        }
+    });
+}
+
+/// [show_profile_choice] with an extra "Use default" choice for [None].
+fn show_profile_override(ui: &mut egui::Ui, id: &str, choice: &mut Option<ProfileChoice>) {
+    ui.horizontal(|ui| {
+        let mut use_default = choice.is_none();
+        if ui.checkbox(&mut use_default, "Use default").changed() {
+            *choice = if use_default {
+                None
+            } else {
+                Some(ProfileChoice::None)
+            };
+        }
+        if let Some(choice) = choice {
+            show_profile_choice(ui, id, choice);
+        }
+    });
+}
+
+fn profile_choice_name(choice: &ProfileChoice) -> String {
+    match choice {
+        ProfileChoice::None => "None (sRGB)".to_string(),
+        ProfileChoice::Monitor => "Monitor profile".to_string(),
+        ProfileChoice::File(path) => path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+    }
+}
+
+fn pick_profile_file() -> Option<std::path::PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("ICC profiles", &["icc", "icm"])
+        .pick_file()
+}
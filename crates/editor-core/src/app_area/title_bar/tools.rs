@@ -1,6 +1,20 @@
+pub mod chart_recorder_button;
+pub mod check_consistency_button;
 pub mod command;
+pub mod copy_diagnostics_button;
+pub mod execution_order_button;
+pub mod export_button;
+pub mod export_graph_image_button;
+pub mod find_replace_button;
+pub mod graph_stats_button;
+pub mod help_button;
+pub mod preferences_button;
+pub mod project_settings_button;
+pub mod record_trace_button;
 pub mod save_button;
+pub mod scenes_button;
 pub mod toolbar_button;
+pub mod usage_stats_button;
 
 mod toolbar;
 
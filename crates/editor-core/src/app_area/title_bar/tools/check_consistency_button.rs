@@ -0,0 +1,15 @@
+use super::command::Command;
+use crate::app_area::title_bar::tools::toolbar_button::ToolBarButton;
+use egui::Context;
+
+pub struct CheckConsistencyButton;
+
+impl ToolBarButton for CheckConsistencyButton {
+    fn label(&self) -> &str {
+        "Check Preview/Export Consistency"
+    }
+
+    fn on_click(&mut self, _ctx: &Context) -> Option<Command> {
+        Command::CheckConsistency.into()
+    }
+}
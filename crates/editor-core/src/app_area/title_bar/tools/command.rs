@@ -1,3 +1,17 @@
 pub enum Command {
     SaveProject,
+    OpenProjectSettings,
+    OpenPreferences,
+    OpenFindReplace,
+    OpenExport,
+    OpenScenes,
+    OpenChartRecorder,
+    OpenGraphStats,
+    ExportGraphImage,
+    ToggleExecutionOrder,
+    CopyDiagnostics,
+    RecordTrace,
+    CheckConsistency,
+    OpenHelp,
+    OpenUsageStats,
 }
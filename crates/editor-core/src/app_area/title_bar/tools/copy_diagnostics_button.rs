@@ -0,0 +1,15 @@
+use super::command::Command;
+use crate::app_area::title_bar::tools::toolbar_button::ToolBarButton;
+use egui::Context;
+
+pub struct CopyDiagnosticsButton;
+
+impl ToolBarButton for CopyDiagnosticsButton {
+    fn label(&self) -> &str {
+        "Copy Diagnostics"
+    }
+
+    fn on_click(&mut self, _ctx: &Context) -> Option<Command> {
+        Command::CopyDiagnostics.into()
+    }
+}
@@ -0,0 +1,15 @@
+use super::command::Command;
+use crate::app_area::title_bar::tools::toolbar_button::ToolBarButton;
+use egui::Context;
+
+pub struct ExecutionOrderButton;
+
+impl ToolBarButton for ExecutionOrderButton {
+    fn label(&self) -> &str {
+        "Show/Hide Execution Order"
+    }
+
+    fn on_click(&mut self, _ctx: &Context) -> Option<Command> {
+        Command::ToggleExecutionOrder.into()
+    }
+}
@@ -0,0 +1,15 @@
+use super::command::Command;
+use crate::app_area::title_bar::tools::toolbar_button::ToolBarButton;
+use egui::Context;
+
+pub struct ExportGraphImageButton;
+
+impl ToolBarButton for ExportGraphImageButton {
+    fn label(&self) -> &str {
+        "Export Graph Image"
+    }
+
+    fn on_click(&mut self, _ctx: &Context) -> Option<Command> {
+        Command::ExportGraphImage.into()
+    }
+}
@@ -0,0 +1,15 @@
+use super::command::Command;
+use crate::app_area::title_bar::tools::toolbar_button::ToolBarButton;
+use egui::Context;
+
+pub struct FindReplaceButton;
+
+impl ToolBarButton for FindReplaceButton {
+    fn label(&self) -> &str {
+        "Find and Replace"
+    }
+
+    fn on_click(&mut self, _ctx: &Context) -> Option<Command> {
+        Command::OpenFindReplace.into()
+    }
+}
@@ -0,0 +1,15 @@
+use super::command::Command;
+use crate::app_area::title_bar::tools::toolbar_button::ToolBarButton;
+use egui::Context;
+
+pub struct HelpButton;
+
+impl ToolBarButton for HelpButton {
+    fn label(&self) -> &str {
+        "Help (F1)"
+    }
+
+    fn on_click(&mut self, _ctx: &Context) -> Option<Command> {
+        Command::OpenHelp.into()
+    }
+}
@@ -0,0 +1,15 @@
+use super::command::Command;
+use crate::app_area::title_bar::tools::toolbar_button::ToolBarButton;
+use egui::Context;
+
+pub struct PreferencesButton;
+
+impl ToolBarButton for PreferencesButton {
+    fn label(&self) -> &str {
+        "Preferences"
+    }
+
+    fn on_click(&mut self, _ctx: &Context) -> Option<Command> {
+        Command::OpenPreferences.into()
+    }
+}
@@ -0,0 +1,15 @@
+use super::command::Command;
+use crate::app_area::title_bar::tools::toolbar_button::ToolBarButton;
+use egui::Context;
+
+pub struct ProjectSettingsButton;
+
+impl ToolBarButton for ProjectSettingsButton {
+    fn label(&self) -> &str {
+        "Project Settings"
+    }
+
+    fn on_click(&mut self, _ctx: &Context) -> Option<Command> {
+        Command::OpenProjectSettings.into()
+    }
+}
@@ -0,0 +1,15 @@
+use super::command::Command;
+use crate::app_area::title_bar::tools::toolbar_button::ToolBarButton;
+use egui::Context;
+
+pub struct RecordTraceButton;
+
+impl ToolBarButton for RecordTraceButton {
+    fn label(&self) -> &str {
+        "Record Execution Trace"
+    }
+
+    fn on_click(&mut self, _ctx: &Context) -> Option<Command> {
+        Command::RecordTrace.into()
+    }
+}
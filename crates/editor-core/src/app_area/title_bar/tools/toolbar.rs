@@ -1,6 +1,20 @@
+use super::chart_recorder_button::ChartRecorderButton;
+use super::check_consistency_button::CheckConsistencyButton;
 use super::command::Command;
+use super::copy_diagnostics_button::CopyDiagnosticsButton;
+use super::execution_order_button::ExecutionOrderButton;
+use super::export_button::ExportButton;
+use super::export_graph_image_button::ExportGraphImageButton;
+use super::find_replace_button::FindReplaceButton;
+use super::graph_stats_button::GraphStatsButton;
+use super::help_button::HelpButton;
+use super::preferences_button::PreferencesButton;
+use super::project_settings_button::ProjectSettingsButton;
+use super::record_trace_button::RecordTraceButton;
 use super::save_button::SaveButton;
+use super::scenes_button::ScenesButton;
 use super::toolbar_button::ToolBarButton;
+use super::usage_stats_button::UsageStatsButton;
 
 pub struct ToolBar {
     file_buttons: Vec<Box<dyn ToolBarButton>>,
@@ -10,7 +24,23 @@ pub struct ToolBar {
 impl ToolBar {
     pub fn new() -> Self {
         Self {
-            file_buttons: vec![Box::new(SaveButton)],
+            file_buttons: vec![
+                Box::new(SaveButton),
+                Box::new(ProjectSettingsButton),
+                Box::new(PreferencesButton),
+                Box::new(FindReplaceButton),
+                Box::new(ExportButton),
+                Box::new(ScenesButton),
+                Box::new(ChartRecorderButton),
+                Box::new(GraphStatsButton),
+                Box::new(ExportGraphImageButton),
+                Box::new(ExecutionOrderButton),
+                Box::new(CopyDiagnosticsButton),
+                Box::new(RecordTraceButton),
+                Box::new(CheckConsistencyButton),
+                Box::new(UsageStatsButton),
+                Box::new(HelpButton),
+            ],
             pending: Vec::new(),
         }
     }
@@ -0,0 +1,123 @@
+//! App-wide settings: preferences that belong to the user's machine instead of
+//! to a project (e.g. which color profile their monitor has). They're saved in
+//! the user's local data (see [local_data::settings_file_path]).
+
+use crate::export_presets::ExportPreset;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use util::local_data;
+use util::saved_file::{self, SavedFile, SavedFileError};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppSettings {
+    #[serde(default)]
+    pub preview: PreviewSettings,
+    #[serde(default)]
+    pub onboarding: OnboardingSettings,
+    #[serde(default)]
+    pub export: ExportSettings,
+    #[serde(default)]
+    pub node_finder: NodeFinderSettings,
+}
+
+impl AppSettings {
+    /// Read the saved settings, or the defaults if there aren't any (or they
+    /// can't be read).
+    pub fn load() -> Self {
+        match Self::read_from_file_path_default(local_data::settings_file_path()) {
+            Ok((settings, _)) => settings,
+            Err(e) => {
+                util::debug_log_error!("Failed to read app settings (using defaults): {e}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Save the settings, logging any error.
+    pub fn save(&self) {
+        let result = saved_file::open_file_with_create_info(local_data::settings_file_path())
+            .map_err(SavedFileError::from)
+            .and_then(|(file, _)| self.save_to_file(&file));
+        if let Err(e) = result {
+            util::debug_log_error!("Failed to save app settings: {e}");
+        }
+    }
+}
+
+/// What first-run help the user has already seen.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OnboardingSettings {
+    /// Whether the node graph tutorial was finished or skipped.
+    #[serde(default)]
+    pub graph_tutorial_dismissed: bool,
+}
+
+/// Export settings that outlive a project.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportSettings {
+    /// The presets the user made, besides the
+    /// [built-in ones](crate::export_presets::built_in).
+    #[serde(default)]
+    pub presets: Vec<ExportPreset>,
+}
+
+/// How the add node menu lists nodes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeFinderSettings {
+    /// Whether the nodes the user adds most are listed first (see
+    /// [UsageStats::node_uses](crate::usage_stats::UsageStats::node_uses)),
+    /// instead of alphabetically.
+    #[serde(default)]
+    pub sort_by_use: bool,
+}
+
+/// Which color profile the output preview is shown through. Profiles only
+/// change the preview, never what's rendered or exported.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProfileChoice {
+    /// Show frames as they are (assumes an sRGB monitor).
+    #[default]
+    None,
+    /// The profile the OS has set for the monitor the window is on.
+    Monitor,
+    /// An ICC profile file.
+    File(PathBuf),
+}
+
+/// The places the output preview is shown, which can each override the
+/// default [ProfileChoice].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreviewWindow {
+    OutputPanel,
+    Fullscreen,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PreviewSettings {
+    /// The profile every preview window uses unless it overrides it.
+    #[serde(default)]
+    pub profile: ProfileChoice,
+    #[serde(default)]
+    pub output_panel_profile: Option<ProfileChoice>,
+    #[serde(default)]
+    pub fullscreen_profile: Option<ProfileChoice>,
+}
+
+impl PreviewSettings {
+    /// The profile override for `window` ([None] to use the default).
+    pub fn profile_override_mut(&mut self, window: PreviewWindow) -> &mut Option<ProfileChoice> {
+        match window {
+            PreviewWindow::OutputPanel => &mut self.output_panel_profile,
+            PreviewWindow::Fullscreen => &mut self.fullscreen_profile,
+        }
+    }
+
+    /// The profile `window` is shown through.
+    pub fn profile_for(&self, window: PreviewWindow) -> &ProfileChoice {
+        let profile_override = match window {
+            PreviewWindow::OutputPanel => &self.output_panel_profile,
+            PreviewWindow::Fullscreen => &self.fullscreen_profile,
+        };
+        profile_override.as_ref().unwrap_or(&self.profile)
+    }
+}
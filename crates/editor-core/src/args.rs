@@ -14,6 +14,17 @@ pub struct Args {
     #[arg(long, allow_hyphen_values = true, default_value = "")]
     pub open_project: String,
 
+    /// Run supported nodes on the CPU instead of the GPU (for machines with
+    /// very weak GPUs). Other nodes still run on the GPU.
+    #[arg(long)]
+    pub cpu_backend: bool,
+
+    /// Start in safe mode, for when the GPU or its driver crashes the editor:
+    /// a software (or other low power) adapter is used, nodes from the users
+    /// nodes folder aren't loaded, and playback starts paused.
+    #[arg(long)]
+    pub safe_mode: bool,
+
     #[cfg(debug_assertions)]
     /// Disable debug logging. This option only exists if `debug_assertions` are
     /// enabled.
@@ -30,6 +41,38 @@ pub struct Args {
     /// want to print to a file.
     #[arg(long, value_name = "OUTPUT_FILE")]
     pub version: Option<Option<PathBuf>>,
+
+    /// Render export chunks for the render farm coordinator at this address
+    /// (`host:port`) instead of opening the editor. Exits once the coordinator
+    /// has no chunks left.
+    #[arg(long, value_name = "ADDRESS")]
+    pub render_worker: Option<String>,
+
+    /// Render the project given with `--open-project` to this video file
+    /// instead of opening the editor, then exit.
+    #[arg(long, value_name = "OUTPUT_FILE", requires = "open_project")]
+    pub render: Option<PathBuf>,
+
+    /// The export preset to render with, e.g. `youtube-1080p` (see
+    /// `--render`). Without one, the file's extension picks the container.
+    #[arg(long, value_name = "NAME", requires = "render")]
+    pub preset: Option<String>,
+
+    /// How many seconds of video to render (see `--render`).
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub duration: u32,
+
+    /// Decode the rendered video back to check that it isn't corrupt (see
+    /// `--render`). Exits with an error if it is.
+    #[arg(long, requires = "render")]
+    pub verify: bool,
+
+    /// Set one of the project's globals for this render, e.g.
+    /// `--global subject_name=Ada` or `--global primary_color=#ff8000` (see
+    /// `--render`). Can be given more than once to render variants of a
+    /// project without editing it.
+    #[arg(long = "global", value_name = "NAME=VALUE", requires = "render")]
+    pub globals: Vec<String>,
 }
 
 impl Default for Args {
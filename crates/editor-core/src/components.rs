@@ -1,3 +1,9 @@
+mod curve_editor;
 mod frame_display;
+mod level_meter;
+mod profile_renderer;
 
+pub use curve_editor::CurveEditor;
 pub use frame_display::FrameDisplay;
+pub use level_meter::{LevelMeter, MeterChannel};
+pub use profile_renderer::ProfileRenderer;
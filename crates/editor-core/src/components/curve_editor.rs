@@ -0,0 +1,177 @@
+use egui::{Color32, Pos2, Response, Sense, Stroke, Ui, Widget, pos2, vec2};
+use engine::tone_curve::ToneCurve;
+
+/// How close (in points) the pointer must be to a control point to grab it.
+const GRAB_RADIUS: f32 = 8.0;
+const POINT_RADIUS: f32 = 3.5;
+/// The closest two control points can be dragged together, as a fraction of
+/// the editor's width.
+const MIN_POINT_GAP: f32 = 0.01;
+/// How many line segments the curve is drawn with.
+const CURVE_SEGMENTS: usize = 64;
+
+const BACKGROUND: Color32 = Color32::from_rgb(14, 17, 19);
+const GRID_COLOR: Color32 = Color32::from_rgb(38, 46, 50);
+
+/// An editor for a [ToneCurve]: drag control points to move them, click an
+/// empty spot to add one, and right click a point to remove it. The ends can
+/// be moved but not removed, and a curve always keeps at least two points.
+///
+/// The response is marked as changed whenever the curve is edited.
+pub struct CurveEditor<'a> {
+    curve: &'a mut ToneCurve,
+    color: Color32,
+    size: f32,
+}
+
+impl<'a> CurveEditor<'a> {
+    pub fn new(curve: &'a mut ToneCurve) -> Self {
+        Self {
+            curve,
+            color: Color32::from_gray(220),
+            size: 120.0,
+        }
+    }
+
+    /// The color the curve and its points are drawn in.
+    pub fn color(mut self, color: Color32) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// The width and height of the editor.
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+}
+
+impl Widget for CurveEditor<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, mut response) =
+            ui.allocate_exact_size(vec2(self.size, self.size), Sense::click_and_drag());
+        let to_screen = |[x, y]: [f32; 2]| {
+            pos2(
+                egui::lerp(rect.left()..=rect.right(), x),
+                egui::lerp(rect.bottom()..=rect.top(), y),
+            )
+        };
+        let from_screen = |pos: Pos2| {
+            [
+                ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0),
+                ((rect.bottom() - pos.y) / rect.height()).clamp(0.0, 1.0),
+            ]
+        };
+        let nearest_point = |points: &[[f32; 2]], pos: Pos2| {
+            points
+                .iter()
+                .enumerate()
+                .map(|(i, point)| (i, to_screen(*point).distance(pos)))
+                .filter(|(_, distance)| *distance <= GRAB_RADIUS)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)
+        };
+
+        let mut points = self.curve.points().to_vec();
+        let mut edited = false;
+        let drag_id = response.id.with("dragged_point");
+
+        if response.drag_started()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            let grabbed = nearest_point(&points, pos);
+            ui.data_mut(|data| data.insert_temp(drag_id, grabbed));
+        }
+        if response.dragged()
+            && let Some(index) = ui
+                .data(|data| data.get_temp::<Option<usize>>(drag_id))
+                .flatten()
+            && let Some(pos) = response.interact_pointer_pos()
+            && index < points.len()
+        {
+            // Keep points in order so dragging one never changes which is which.
+            let [mut x, y] = from_screen(pos);
+            let low = index
+                .checked_sub(1)
+                .map_or(0.0, |before| points[before][0] + MIN_POINT_GAP);
+            let high = points
+                .get(index + 1)
+                .map_or(1.0, |after| after[0] - MIN_POINT_GAP);
+            x = x.clamp(low, high.max(low));
+            points[index] = [round(x), round(y)];
+            edited = true;
+        }
+        if response.drag_stopped() {
+            ui.data_mut(|data| data.remove::<Option<usize>>(drag_id));
+        }
+
+        if response.clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+            && nearest_point(&points, pos).is_none()
+        {
+            let [x, y] = from_screen(pos);
+            let too_close = points
+                .iter()
+                .any(|point| (point[0] - x).abs() < MIN_POINT_GAP);
+            if !too_close {
+                points.push([round(x), round(y)]);
+                edited = true;
+            }
+        }
+        if response.secondary_clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+            && let Some(index) = nearest_point(&points, pos)
+            && index != 0
+            && index + 1 != points.len()
+        {
+            points.remove(index);
+            edited = true;
+        }
+
+        if edited && let Ok(curve) = ToneCurve::new(points) {
+            *self.curve = curve;
+            response.mark_changed();
+        }
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, BACKGROUND);
+        for quarter in 1..4 {
+            let t = quarter as f32 / 4.0;
+            let grid = Stroke::new(1.0, GRID_COLOR);
+            painter.hline(
+                rect.x_range(),
+                egui::lerp(rect.top()..=rect.bottom(), t),
+                grid,
+            );
+            painter.vline(
+                egui::lerp(rect.left()..=rect.right(), t),
+                rect.y_range(),
+                grid,
+            );
+        }
+        painter.line_segment(
+            [rect.left_bottom(), rect.right_top()],
+            Stroke::new(1.0, GRID_COLOR),
+        );
+
+        let line: Vec<Pos2> = (0..=CURVE_SEGMENTS)
+            .map(|i| {
+                let x = i as f32 / CURVE_SEGMENTS as f32;
+                to_screen([x, self.curve.evaluate(x)])
+            })
+            .collect();
+        painter.line(line, Stroke::new(1.5, self.color));
+        for point in self.curve.points() {
+            painter.circle_filled(to_screen(*point), POINT_RADIUS, self.color);
+        }
+
+        response.on_hover_text(
+            "Drag points to move them, click to add one, and right click to remove one",
+        )
+    }
+}
+
+/// Round a coordinate so saved curves don't carry float noise.
+fn round(value: f32) -> f32 {
+    (value * 1000.0).round() / 1000.0
+}
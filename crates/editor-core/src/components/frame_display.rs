@@ -1,7 +1,9 @@
 use std::collections::VecDeque;
 
 use eframe::wgpu;
-use egui::load::SizedTexture;
+
+use super::ProfileRenderer;
+use crate::display_profile::DisplayProfile;
 
 const MAX_TEXTURE_CACHE_SIZE: usize = 3;
 
@@ -12,6 +14,8 @@ pub struct FrameDisplay {
     last_frame_key: Option<usize>,
     last_renderer_ptr: Option<usize>,
     texture_cache: VecDeque<(usize, egui::TextureId)>,
+    /// Frames are drawn through this before being shown, if it's set.
+    profile: Option<ProfileRenderer>,
 }
 
 impl FrameDisplay {
@@ -22,9 +26,29 @@ impl FrameDisplay {
             last_frame_key: None,
             last_renderer_ptr: None,
             texture_cache: VecDeque::new(),
+            profile: None,
         }
     }
 
+    /// Show frames through a monitor color profile ([None] to show them as
+    /// they are). Clears the current texture, so the frame needs to be set
+    /// again.
+    pub fn set_profile(
+        &mut self,
+        render_state: &egui_wgpu::RenderState,
+        profile: Option<&DisplayProfile>,
+    ) {
+        self.profile = profile.map(|profile| {
+            ProfileRenderer::new(
+                &render_state.device,
+                &render_state.queue,
+                render_state.target_format,
+                profile,
+            )
+        });
+        self.clear(Some(render_state));
+    }
+
     /// Update the texture if the frame has changed
     pub fn set_wgpu_texture_if_changed(
         &mut self,
@@ -55,9 +79,17 @@ impl FrameDisplay {
         {
             *cached_id
         } else {
+            let profiled_view = self.profile.as_ref().map(|profile| {
+                profile.apply(
+                    &render_state.device,
+                    &render_state.queue,
+                    texture_view,
+                    [size[0] as u32, size[1] as u32],
+                )
+            });
             let new_id = render_state.renderer.write().register_native_texture(
                 &render_state.device,
-                texture_view,
+                profiled_view.as_ref().unwrap_or(texture_view),
                 wgpu::FilterMode::Linear,
             );
             self.texture_cache.push_back((frame_key, new_id));
@@ -109,24 +141,45 @@ impl FrameDisplay {
     }
 
     /// Render just the texture content
-    pub fn render_content(&self, ui: &mut egui::Ui) {
+    ///
+    /// The frame is fit in physical pixels, not points, and lands on whole
+    /// pixels. Otherwise, with a scale factor that isn't 1 (e.g. 150%), a frame
+    /// that fits 1:1 is still stretched between pixels and looks blurry.
+    ///
+    /// Returns where the frame was drawn, if there was one to draw.
+    pub fn render_content(&self, ui: &mut egui::Ui) -> Option<egui::Rect> {
         if let Some(texture_id) = self.texture_id {
+            let pixels_per_point = ui.ctx().pixels_per_point();
             let original_size =
                 egui::vec2(self.texture_size[0] as f32, self.texture_size[1] as f32);
 
             // Use the space egui has actually allocated, not a fixed config value
             let available = ui.available_size();
-            let scale = (available.x / original_size.x).min(available.y / original_size.y);
-
-            let display_size = original_size * scale;
-
-            ui.centered_and_justified(|ui| {
-                ui.image(SizedTexture::new(texture_id, display_size));
-            });
+            let (rect, _) = ui.allocate_exact_size(available, egui::Sense::hover());
+
+            let available_pixels = available * pixels_per_point;
+            let scale =
+                (available_pixels.x / original_size.x).min(available_pixels.y / original_size.y);
+            let display_pixels = (original_size * scale).floor();
+            let min_pixels =
+                (rect.center().to_vec2() * pixels_per_point - display_pixels / 2.0).round();
+
+            let display_rect = egui::Rect::from_min_size(
+                (min_pixels / pixels_per_point).to_pos2(),
+                display_pixels / pixels_per_point,
+            );
+            ui.painter().image(
+                texture_id,
+                display_rect,
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+            Some(display_rect)
         } else {
             ui.centered_and_justified(|ui| {
                 ui.label(egui::RichText::new("No frame data").weak());
             });
+            None
         }
     }
 }
@@ -0,0 +1,135 @@
+use egui::{Color32, Rect, Response, Sense, Stroke, Ui, Widget, pos2, vec2};
+use media::audio::ChannelLevel;
+
+/// The quietest level shown, in dBFS.
+const MIN_DB: f32 = -60.0;
+const BAR_WIDTH: f32 = 10.0;
+const BAR_SPACING: f32 = 3.0;
+const CLIP_INDICATOR_HEIGHT: f32 = 8.0;
+const SCALE_MARKS_DB: [f32; 4] = [-6.0, -12.0, -24.0, -48.0];
+
+const BACKGROUND: Color32 = Color32::from_rgb(14, 17, 19);
+const SCALE_COLOR: Color32 = Color32::from_rgb(38, 46, 50);
+const LOW_COLOR: Color32 = Color32::from_rgb(98, 200, 160);
+const HIGH_COLOR: Color32 = Color32::from_rgb(240, 200, 80);
+const CLIP_COLOR: Color32 = Color32::from_rgb(230, 70, 70);
+const CLIP_OFF_COLOR: Color32 = Color32::from_rgb(60, 30, 30);
+
+/// The level above which bars are drawn in [HIGH_COLOR].
+const HIGH_DB: f32 = -12.0;
+
+/// The levels shown by one bar of a [LevelMeter]. Levels are linear (`1.0` is
+/// full scale).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeterChannel {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+/// A vertical audio level meter with one bar per channel, a dBFS scale, and a
+/// clip indicator. The bars show the RMS level with a line at the peak level.
+///
+/// The clip indicator stays lit until it's clicked, so short clips aren't
+/// missed.
+pub struct LevelMeter<'a> {
+    channels: &'a [MeterChannel],
+    clipped: &'a mut bool,
+    height: f32,
+}
+
+impl<'a> LevelMeter<'a> {
+    pub fn new(channels: &'a [MeterChannel], clipped: &'a mut bool) -> Self {
+        Self {
+            channels,
+            clipped,
+            height: 120.0,
+        }
+    }
+
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+}
+
+impl Widget for LevelMeter<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let count = self.channels.len().max(1) as f32;
+        let width = count * BAR_WIDTH + (count - 1.0) * BAR_SPACING;
+        let (rect, response) = ui.allocate_exact_size(vec2(width, self.height), Sense::click());
+
+        if response.clicked() {
+            *self.clipped = false;
+        }
+
+        let painter = ui.painter_at(rect);
+        let clip_rect = Rect::from_min_size(rect.min, vec2(width, CLIP_INDICATOR_HEIGHT));
+        let clip_color = if *self.clipped {
+            CLIP_COLOR
+        } else {
+            CLIP_OFF_COLOR
+        };
+        painter.rect_filled(clip_rect, 2.0, clip_color);
+
+        let meter_rect = Rect::from_min_max(
+            pos2(rect.left(), clip_rect.bottom() + BAR_SPACING),
+            rect.max,
+        );
+        let level_y =
+            |level: f32| egui::lerp(meter_rect.bottom()..=meter_rect.top(), db_fraction(level));
+
+        for (i, channel) in self.channels.iter().enumerate() {
+            let left = meter_rect.left() + i as f32 * (BAR_WIDTH + BAR_SPACING);
+            let bar_rect = Rect::from_min_max(
+                pos2(left, meter_rect.top()),
+                pos2(left + BAR_WIDTH, meter_rect.bottom()),
+            );
+            painter.rect_filled(bar_rect, 1.0, BACKGROUND);
+
+            let rms_rect = Rect::from_min_max(pos2(left, level_y(channel.rms)), bar_rect.max);
+            let color = if ChannelLevel::to_dbfs(channel.rms) >= HIGH_DB {
+                HIGH_COLOR
+            } else {
+                LOW_COLOR
+            };
+            painter.rect_filled(rms_rect, 1.0, color);
+
+            if channel.peak > 0.0 {
+                let peak_color = if channel.peak >= 1.0 {
+                    CLIP_COLOR
+                } else {
+                    color
+                };
+                painter.hline(
+                    bar_rect.x_range(),
+                    level_y(channel.peak),
+                    Stroke::new(2.0, peak_color),
+                );
+            }
+        }
+
+        for db in SCALE_MARKS_DB {
+            let y = egui::lerp(
+                meter_rect.bottom()..=meter_rect.top(),
+                (db - MIN_DB) / -MIN_DB,
+            );
+            painter.hline(meter_rect.x_range(), y, Stroke::new(1.0, SCALE_COLOR));
+        }
+
+        let peak_db = self
+            .channels
+            .iter()
+            .map(|channel| ChannelLevel::to_dbfs(channel.peak))
+            .fold(f32::NEG_INFINITY, f32::max);
+        response.on_hover_text(if peak_db > MIN_DB {
+            format!("Peak {peak_db:.1} dBFS (click to reset the clip light)")
+        } else {
+            "Silent (click to reset the clip light)".to_string()
+        })
+    }
+}
+
+/// How far up the meter `level` is drawn.
+fn db_fraction(level: f32) -> f32 {
+    ((ChannelLevel::to_dbfs(level) - MIN_DB) / -MIN_DB).clamp(0.0, 1.0)
+}
@@ -0,0 +1,463 @@
+//! Monitor color profiles (ICC) for the output preview.
+//!
+//! Frames are sRGB. A [DisplayProfile] describes how a monitor actually shows
+//! colors, and [DisplayProfile::bake_lut] bakes a 3D lookup table that turns
+//! sRGB colors into the values that show them correctly on that monitor. Only
+//! matrix/TRC profiles (what almost every monitor profile is) are supported.
+//!
+//! Profiles only change what the preview looks like, never what's rendered or
+//! exported.
+
+use std::path::{Path, PathBuf};
+
+use raw_window_handle::HasWindowHandle;
+
+/// How many entries each side of a baked lookup table has.
+pub const LUT_SIZE: usize = 33;
+
+/// Linear sRGB to CIE XYZ, adapted to the D50 white point profiles use.
+const SRGB_TO_XYZ_D50: [[f64; 3]; 3] = [
+    [0.4360747, 0.3850649, 0.1430804],
+    [0.2225045, 0.7168786, 0.0606169],
+    [0.0139322, 0.0971045, 0.7141733],
+];
+
+/// A monitor's color profile: its primaries and the tone curve of each
+/// channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayProfile {
+    /// Linear display RGB to CIE XYZ (D50). The columns are the primaries.
+    to_xyz: [[f64; 3]; 3],
+    curves: [ToneCurve; 3],
+}
+
+impl DisplayProfile {
+    /// Read an ICC profile file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Failed to read '{}': {e}", path.display()))?;
+        Self::parse(&bytes)
+    }
+
+    /// Parse the bytes of an ICC profile.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.get(36..40) != Some(b"acsp") {
+            return Err("Not an ICC profile".to_string());
+        }
+        if bytes.get(16..20) != Some(b"RGB ") {
+            return Err("Only RGB profiles are supported".to_string());
+        }
+        if bytes.get(20..24) != Some(b"XYZ ") {
+            return Err("Only profiles with an XYZ connection space are supported".to_string());
+        }
+
+        let tags = read_tag_table(bytes).ok_or("The profile's tag table is cut short")?;
+        let tag = |signature: &[u8; 4]| {
+            tags.iter()
+                .find(|(tag, _)| tag == signature)
+                .map(|(_, data)| *data)
+                .ok_or_else(|| {
+                    format!(
+                        "The profile has no '{}' tag (only matrix/TRC profiles are supported)",
+                        String::from_utf8_lossy(signature)
+                    )
+                })
+        };
+
+        let mut to_xyz = [[0.0; 3]; 3];
+        for (column, signature) in [b"rXYZ", b"gXYZ", b"bXYZ"].into_iter().enumerate() {
+            let xyz = read_xyz(tag(signature)?).ok_or("A primary of the profile is invalid")?;
+            for row in 0..3 {
+                to_xyz[row][column] = xyz[row];
+            }
+        }
+        if invert(&to_xyz).is_none() {
+            return Err("The profile's primaries are invalid".to_string());
+        }
+
+        let curves = [
+            ToneCurve::parse(tag(b"rTRC")?)?,
+            ToneCurve::parse(tag(b"gTRC")?)?,
+            ToneCurve::parse(tag(b"bTRC")?)?,
+        ];
+
+        Ok(Self { to_xyz, curves })
+    }
+
+    /// Bake the sRGB to display lookup table: [LUT_SIZE]³ RGBA8 entries, with
+    /// red changing fastest and blue slowest (the layout of a 3D texture).
+    pub fn bake_lut(&self) -> Vec<u8> {
+        let from_xyz = invert(&self.to_xyz).expect("the primaries were checked when parsing");
+        let srgb_to_display = multiply(&from_xyz, &SRGB_TO_XYZ_D50);
+
+        let mut lut = Vec::with_capacity(LUT_SIZE * LUT_SIZE * LUT_SIZE * 4);
+        for b in 0..LUT_SIZE {
+            for g in 0..LUT_SIZE {
+                for r in 0..LUT_SIZE {
+                    let linear =
+                        [r, g, b].map(|i| srgb_to_linear(i as f64 / (LUT_SIZE - 1) as f64));
+                    for (channel, row) in srgb_to_display.iter().enumerate() {
+                        let value = row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2];
+                        let encoded = self.curves[channel].invert(value.clamp(0.0, 1.0));
+                        lut.push((encoded * 255.0).round() as u8);
+                    }
+                    lut.push(u8::MAX);
+                }
+            }
+        }
+        lut
+    }
+}
+
+/// How a profile turns a channel's value (0 to 1) into light (0 to 1).
+#[derive(Debug, Clone, PartialEq)]
+enum ToneCurve {
+    Gamma(f64),
+    /// Evenly spaced samples, linearly interpolated.
+    Table(Vec<f64>),
+    /// An ICC parametric curve: its function type and up to 7 parameters
+    /// (`g`, `a`, `b`, `c`, `d`, `e`, `f`).
+    Parametric(u16, [f64; 7]),
+}
+
+impl ToneCurve {
+    /// Parse a `curv` or `para` tag.
+    fn parse(data: &[u8]) -> Result<Self, String> {
+        match data.get(..4) {
+            Some(b"curv") => {
+                let count = read_u32(data, 8).ok_or("A tone curve is cut short")? as usize;
+                match count {
+                    0 => Ok(ToneCurve::Gamma(1.0)),
+                    1 => {
+                        let gamma = read_u16(data, 12).ok_or("A tone curve is cut short")?;
+                        Ok(ToneCurve::Gamma(gamma as f64 / 256.0))
+                    }
+                    _ => (0..count)
+                        .map(|i| read_u16(data, 12 + i * 2).map(|v| v as f64 / u16::MAX as f64))
+                        .collect::<Option<Vec<_>>>()
+                        .map(ToneCurve::Table)
+                        .ok_or_else(|| "A tone curve is cut short".to_string()),
+                }
+            }
+            Some(b"para") => {
+                let function = read_u16(data, 8).ok_or("A tone curve is cut short")?;
+                let param_count = match function {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return Err(format!("Unknown parametric curve type {function}")),
+                };
+                let mut params = [0.0; 7];
+                for (i, param) in params.iter_mut().enumerate().take(param_count) {
+                    *param =
+                        read_s15_fixed16(data, 12 + i * 4).ok_or("A tone curve is cut short")?;
+                }
+                Ok(ToneCurve::Parametric(function, params))
+            }
+            _ => Err("Only 'curv' and 'para' tone curves are supported".to_string()),
+        }
+    }
+
+    fn eval(&self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            ToneCurve::Gamma(gamma) => x.powf(*gamma),
+            ToneCurve::Table(samples) => {
+                let position = x * (samples.len() - 1) as f64;
+                let i = (position.floor() as usize).min(samples.len() - 2);
+                let t = position - i as f64;
+                samples[i] + (samples[i + 1] - samples[i]) * t
+            }
+            ToneCurve::Parametric(function, [g, a, b, c, d, e, f]) => {
+                let power = |x: f64| (a * x + b).max(0.0).powf(*g);
+                match function {
+                    0 => x.powf(*g),
+                    1 if x >= -b / a => power(x),
+                    1 => 0.0,
+                    2 if x >= -b / a => power(x) + c,
+                    2 => *c,
+                    3 if x >= *d => power(x),
+                    3 => c * x,
+                    _ if x >= *d => power(x) + e,
+                    _ => c * x + f,
+                }
+            }
+        }
+    }
+
+    /// The value that gives out `y` light. Curves are assumed to only ever go
+    /// up.
+    fn invert(&self, y: f64) -> f64 {
+        let (mut low, mut high) = (0.0, 1.0);
+        if y <= self.eval(low) {
+            return low;
+        }
+        if y >= self.eval(high) {
+            return high;
+        }
+        for _ in 0..32 {
+            let middle = (low + high) / 2.0;
+            if self.eval(middle) < y {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        (low + high) / 2.0
+    }
+}
+
+/// The path of the color profile of the monitor the editor's window is on, if
+/// the OS has one set (only looked up on Windows).
+pub fn monitor_profile_path() -> Option<PathBuf> {
+    monitor_profile_path_impl()
+}
+
+/// Remember the editor's window, so [monitor_profile_path] can find which
+/// monitor it's on.
+pub fn remember_window<W: HasWindowHandle>(window: &W) {
+    remember_window_impl(window);
+}
+
+#[cfg(target_os = "windows")]
+static WINDOW: std::sync::atomic::AtomicIsize = std::sync::atomic::AtomicIsize::new(0);
+
+#[cfg(target_os = "windows")]
+fn remember_window_impl<W: HasWindowHandle>(window: &W) {
+    use raw_window_handle::RawWindowHandle;
+    use std::sync::atomic::Ordering;
+
+    if let Ok(handle) = window.window_handle()
+        && let RawWindowHandle::Win32(win32_handle) = handle.as_raw()
+    {
+        WINDOW.store(win32_handle.hwnd.get(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn monitor_profile_path_impl() -> Option<PathBuf> {
+    use std::os::windows::ffi::OsStringExt;
+    use std::sync::atomic::Ordering;
+    use windows_sys::Win32::Foundation::HWND;
+    use windows_sys::Win32::Graphics::Gdi::{
+        CreateDCW, DeleteDC, GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO,
+        MONITORINFOEXW, MonitorFromWindow,
+    };
+    use windows_sys::Win32::UI::ColorSystem::GetICMProfileW;
+
+    let hwnd = WINDOW.load(Ordering::Relaxed) as HWND;
+    if hwnd.is_null() {
+        return None;
+    }
+
+    // SAFETY: The window handle came from eframe and lives as long as the app.
+    // Buffers are sized as the calls expect, and the DC is always deleted.
+    unsafe {
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        let mut info: MONITORINFOEXW = std::mem::zeroed();
+        info.monitorInfo.cbSize = size_of::<MONITORINFOEXW>() as u32;
+        if GetMonitorInfoW(monitor, (&raw mut info).cast::<MONITORINFO>()) == 0 {
+            return None;
+        }
+
+        let hdc = CreateDCW(
+            std::ptr::null(),
+            info.szDevice.as_ptr(),
+            std::ptr::null(),
+            std::ptr::null(),
+        );
+        if hdc.is_null() {
+            return None;
+        }
+
+        let mut path = [0u16; 260];
+        let mut len = path.len() as u32;
+        let found = GetICMProfileW(hdc, &mut len, path.as_mut_ptr()) != 0;
+        DeleteDC(hdc);
+        if !found {
+            return None;
+        }
+
+        let end = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+        Some(PathBuf::from(std::ffi::OsString::from_wide(&path[..end])))
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+#[inline(always)]
+fn remember_window_impl<W: HasWindowHandle>(_window: &W) {
+    // Monitor profiles are only looked up on Windows.
+}
+
+#[cfg(not(target_os = "windows"))]
+fn monitor_profile_path_impl() -> Option<PathBuf> {
+    None
+}
+
+/// The `(signature, data)` of every tag in the profile.
+fn read_tag_table(bytes: &[u8]) -> Option<Vec<([u8; 4], &[u8])>> {
+    let count = read_u32(bytes, 128)? as usize;
+    (0..count)
+        .map(|i| {
+            let entry = 132 + i * 12;
+            let signature = bytes.get(entry..entry + 4)?.try_into().ok()?;
+            let offset = read_u32(bytes, entry + 4)? as usize;
+            let size = read_u32(bytes, entry + 8)? as usize;
+            Some((signature, bytes.get(offset..offset.checked_add(size)?)?))
+        })
+        .collect()
+}
+
+fn read_xyz(data: &[u8]) -> Option<[f64; 3]> {
+    if data.get(..4)? != b"XYZ " {
+        return None;
+    }
+    Some([
+        read_s15_fixed16(data, 8)?,
+        read_s15_fixed16(data, 12)?,
+        read_s15_fixed16(data, 16)?,
+    ])
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_s15_fixed16(bytes: &[u8], at: usize) -> Option<f64> {
+    let raw = i32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?);
+    Some(raw as f64 / 65536.0)
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn multiply(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    std::array::from_fn(|row| {
+        std::array::from_fn(|column| (0..3).map(|i| a[row][i] * b[i][column]).sum())
+    })
+}
+
+fn invert(m: &[[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let cofactor = |row: usize, column: usize| {
+        let (r0, r1) = ((row + 1) % 3, (row + 2) % 3);
+        let (c0, c1) = ((column + 1) % 3, (column + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let determinant: f64 = (0..3)
+        .map(|column| m[0][column] * cofactor(0, column))
+        .sum();
+    if determinant.abs() < 1e-9 {
+        return None;
+    }
+    Some(std::array::from_fn(|row| {
+        std::array::from_fn(|column| cofactor(column, row) / determinant)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A profile with sRGB's primaries and a `para` tone curve of `function`
+    /// with `params`.
+    fn build_profile(function: u16, params: &[f64]) -> Vec<u8> {
+        let s15 = |value: f64| ((value * 65536.0).round() as i32).to_be_bytes();
+
+        let mut tags: Vec<(&[u8; 4], Vec<u8>)> = Vec::new();
+        for (column, signature) in [b"rXYZ", b"gXYZ", b"bXYZ"].into_iter().enumerate() {
+            let mut data = b"XYZ \0\0\0\0".to_vec();
+            for row in SRGB_TO_XYZ_D50 {
+                data.extend_from_slice(&s15(row[column]));
+            }
+            tags.push((signature, data));
+        }
+        let mut curve = b"para\0\0\0\0".to_vec();
+        curve.extend_from_slice(&function.to_be_bytes());
+        curve.extend_from_slice(&[0, 0]);
+        for &param in params {
+            curve.extend_from_slice(&s15(param));
+        }
+        for signature in [b"rTRC", b"gTRC", b"bTRC"] {
+            tags.push((signature, curve.clone()));
+        }
+
+        let mut bytes = vec![0u8; 128];
+        bytes[16..20].copy_from_slice(b"RGB ");
+        bytes[20..24].copy_from_slice(b"XYZ ");
+        bytes[36..40].copy_from_slice(b"acsp");
+        bytes.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+
+        let mut offset = bytes.len() + tags.len() * 12;
+        let mut data = Vec::new();
+        for (signature, tag) in &tags {
+            bytes.extend_from_slice(*signature);
+            bytes.extend_from_slice(&(offset as u32).to_be_bytes());
+            bytes.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+            offset += tag.len();
+            data.extend_from_slice(tag);
+        }
+        bytes.extend_from_slice(&data);
+        bytes
+    }
+
+    // --- DisplayProfile::bake_lut() ---
+
+    #[test]
+    fn test_srgb_profile_is_identity() {
+        let srgb = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+        let profile = DisplayProfile::parse(&build_profile(3, &srgb)).unwrap();
+        let lut = profile.bake_lut();
+        assert_eq!(lut.len(), LUT_SIZE.pow(3) * 4);
+
+        for (i, entry) in lut.chunks_exact(4).enumerate() {
+            let expected = [
+                i % LUT_SIZE,
+                i / LUT_SIZE % LUT_SIZE,
+                i / LUT_SIZE / LUT_SIZE,
+            ]
+            .map(|step| (step as f64 / (LUT_SIZE - 1) as f64 * 255.0).round() as i32);
+            for channel in 0..3 {
+                assert!((entry[channel] as i32 - expected[channel]).abs() <= 1);
+            }
+            assert_eq!(entry[3], 255);
+        }
+    }
+
+    #[test]
+    fn test_gamma_profile() {
+        // Gray on a gamma 1.8 monitor needs a lower value than on sRGB to come
+        // out as bright.
+        let profile = DisplayProfile::parse(&build_profile(0, &[1.8])).unwrap();
+        let lut = profile.bake_lut();
+        let middle = LUT_SIZE / 2;
+        let gray = (middle * LUT_SIZE * LUT_SIZE + middle * LUT_SIZE + middle) * 4;
+        let expected = srgb_to_linear(0.5).powf(1.0 / 1.8) * 255.0;
+        assert!((lut[gray] as f64 - expected).abs() <= 1.0);
+    }
+
+    // --- DisplayProfile::parse() ---
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(DisplayProfile::parse(b"not a profile").is_err());
+
+        let mut cmyk = build_profile(0, &[2.2]);
+        cmyk[16..20].copy_from_slice(b"CMYK");
+        assert!(DisplayProfile::parse(&cmyk).is_err());
+
+        // Cut off in the middle of its tags.
+        let profile = build_profile(0, &[2.2]);
+        assert!(DisplayProfile::parse(&profile[..profile.len() - 10]).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Contains [ExportPreset]s: video export settings saved under a name, so
+//! they can be picked in one click in the export dialog or with `--preset`
+//! when rendering from the command line. Some are [built_in] for common
+//! platforms, and the rest are the user's, kept in their
+//! [AppSettings](crate::app_settings::AppSettings).
+
+use engine::export::{
+    DEFAULT_LOUDNESS_TARGET, EncodingOptions, EncodingSpeed, RateControl, VideoCodec,
+    VideoContainer,
+};
+use serde::{Deserialize, Serialize};
+
+/// Video export settings saved under a name. Hardware encoding isn't part of
+/// a preset, since it depends on the machine and not on where the video goes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportPreset {
+    /// What the preset is picked by (e.g. `youtube-1080p`).
+    pub name: String,
+    /// The resolution to export at, or [None] to keep the project's.
+    #[serde(default)]
+    pub resolution: Option<(u32, u32)>,
+    /// The frame rate to export at as a `(numerator, denominator)` fraction,
+    /// or [None] to keep the project's.
+    #[serde(default)]
+    pub fps: Option<(u32, u32)>,
+    /// The container's file extension (see [VideoContainer::extension]).
+    pub container: String,
+    /// See [codec_id].
+    pub codec: String,
+    /// The [RateControl::Quality] to encode at.
+    #[serde(default)]
+    pub quality: Option<u8>,
+    /// The [RateControl::TwoPass] bit rate, in bits per second. Takes the
+    /// place of `quality` when it's set.
+    #[serde(default)]
+    pub bit_rate: Option<u64>,
+    #[serde(default)]
+    pub keyframe_interval: Option<u32>,
+    /// The [EncodingSpeed]'s name, in lowercase.
+    #[serde(default)]
+    pub speed: Option<String>,
+    /// Whether the video gets the sound of the files the graph plays.
+    #[serde(default = "default_audio")]
+    pub audio: bool,
+    /// The loudness in LUFS to normalize the audio to, if any.
+    #[serde(default)]
+    pub loudness_target: Option<f64>,
+}
+
+fn default_audio() -> bool {
+    true
+}
+
+impl ExportPreset {
+    pub fn new(
+        name: String,
+        resolution: Option<(u32, u32)>,
+        fps: Option<(u32, u32)>,
+        container: VideoContainer,
+        codec: VideoCodec,
+        encoding: &EncodingOptions,
+        audio: bool,
+    ) -> Self {
+        Self {
+            name,
+            resolution,
+            fps,
+            container: container.extension().to_string(),
+            codec: codec_id(codec).to_string(),
+            quality: match encoding.rate_control {
+                Some(RateControl::Quality(quality)) => Some(quality),
+                _ => None,
+            },
+            bit_rate: match encoding.rate_control {
+                Some(RateControl::TwoPass { bit_rate }) => Some(bit_rate),
+                _ => None,
+            },
+            keyframe_interval: encoding.keyframe_interval,
+            speed: encoding.speed.map(|speed| speed.name().to_lowercase()),
+            audio,
+            loudness_target: None,
+        }
+    }
+
+    pub fn container(&self) -> Result<VideoContainer, String> {
+        VideoContainer::ALL
+            .into_iter()
+            .find(|container| container.extension() == self.container)
+            .ok_or_else(|| format!("unknown container \"{}\"", self.container))
+    }
+
+    /// The codec, which fails if the container can't hold it.
+    pub fn codec(&self) -> Result<VideoCodec, String> {
+        let codec = VideoCodec::ALL
+            .into_iter()
+            .find(|&codec| codec_id(codec) == self.codec)
+            .ok_or_else(|| format!("unknown codec \"{}\"", self.codec))?;
+        let container = self.container()?;
+        if !container.supports(codec) {
+            return Err(format!(
+                "{} files can't hold {} video",
+                container.name(),
+                codec.name()
+            ));
+        }
+        Ok(codec)
+    }
+
+    /// How the video is encoded, in software. Fails if the codec doesn't have
+    /// one of the settings (see [EncodingOptions::validate]).
+    pub fn encoding(&self) -> Result<EncodingOptions, String> {
+        let speed = match &self.speed {
+            Some(name) => Some(
+                EncodingSpeed::ALL
+                    .into_iter()
+                    .find(|speed| speed.name().eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("unknown encoding speed \"{name}\""))?,
+            ),
+            None => None,
+        };
+        let encoding = EncodingOptions {
+            rate_control: match (self.bit_rate, self.quality) {
+                (Some(bit_rate), _) => Some(RateControl::TwoPass { bit_rate }),
+                (None, Some(quality)) => Some(RateControl::Quality(quality)),
+                (None, None) => None,
+            },
+            keyframe_interval: self.keyframe_interval,
+            speed,
+            hardware: false,
+        };
+        encoding
+            .validate(self.codec()?)
+            .map_err(|e| e.to_string())?;
+        Ok(encoding)
+    }
+
+    /// Whether this is one of the [built_in] presets, whose names the user's
+    /// can't take.
+    pub fn is_built_in(&self) -> bool {
+        built_in().iter().any(|preset| preset.name == self.name)
+    }
+}
+
+/// The presets every user has, for common places videos go.
+pub fn built_in() -> Vec<ExportPreset> {
+    // Platforms turn everything to about the same loudness, so their presets
+    // get there first.
+    let h264 = |name: &str, resolution, fps, keyframe_interval| ExportPreset {
+        loudness_target: Some(DEFAULT_LOUDNESS_TARGET),
+        ..ExportPreset::new(
+            name.to_string(),
+            Some(resolution),
+            fps,
+            VideoContainer::Mp4,
+            VideoCodec::H264,
+            &EncodingOptions {
+                rate_control: Some(RateControl::Quality(18)),
+                keyframe_interval,
+                speed: Some(EncodingSpeed::Slow),
+                hardware: false,
+            },
+            true,
+        )
+    };
+    vec![
+        h264("youtube-1080p", (1920, 1080), None, None),
+        h264("youtube-4k", (3840, 2160), None, None),
+        h264("instagram-9x16", (1080, 1920), Some((30, 1)), Some(60)),
+        ExportPreset::new(
+            "web-vp9".to_string(),
+            None,
+            None,
+            VideoContainer::WebM,
+            VideoCodec::Vp9,
+            &EncodingOptions {
+                rate_control: Some(RateControl::Quality(31)),
+                keyframe_interval: None,
+                speed: Some(EncodingSpeed::Medium),
+                hardware: false,
+            },
+            true,
+        ),
+        // Full quality at the project's own resolution and frame rate, with
+        // the alpha channel kept.
+        ExportPreset::new(
+            "archive".to_string(),
+            None,
+            None,
+            VideoContainer::Mov,
+            VideoCodec::ProRes4444,
+            &EncodingOptions::default(),
+            true,
+        ),
+    ]
+}
+
+/// The preset named `name`, out of the [built_in] ones and then the user's.
+pub fn find(name: &str, user_presets: &[ExportPreset]) -> Option<ExportPreset> {
+    built_in()
+        .into_iter()
+        .chain(user_presets.iter().cloned())
+        .find(|preset| preset.name == name)
+}
+
+/// The name presets save `codec` under.
+pub fn codec_id(codec: VideoCodec) -> &'static str {
+    match codec {
+        VideoCodec::H264 => "h264",
+        VideoCodec::ProRes4444 => "prores4444",
+        VideoCodec::Vp9 => "vp9",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- built_in() ---
+
+    #[test]
+    fn built_in_presets_are_valid() {
+        for preset in built_in() {
+            assert!(preset.encoding().is_ok(), "{}", preset.name);
+        }
+    }
+
+    // --- ExportPreset::encoding() ---
+
+    #[test]
+    fn presets_keep_their_settings() {
+        let encoding = EncodingOptions {
+            rate_control: Some(RateControl::TwoPass {
+                bit_rate: 8_000_000,
+            }),
+            keyframe_interval: Some(48),
+            speed: Some(EncodingSpeed::Fastest),
+            hardware: true,
+        };
+        let preset = ExportPreset::new(
+            "mine".to_string(),
+            None,
+            Some((24, 1)),
+            VideoContainer::Mkv,
+            VideoCodec::Vp9,
+            &encoding,
+            false,
+        );
+        assert_eq!(preset.container(), Ok(VideoContainer::Mkv));
+        assert_eq!(preset.codec(), Ok(VideoCodec::Vp9));
+        assert!(!preset.audio);
+        assert_eq!(
+            preset.encoding(),
+            Ok(EncodingOptions {
+                hardware: false,
+                ..encoding
+            })
+        );
+    }
+
+    #[test]
+    fn presets_need_a_codec_their_container_holds() {
+        let mut preset = built_in().remove(0);
+        preset.codec = codec_id(VideoCodec::ProRes4444).to_string();
+        assert!(preset.codec().is_err());
+    }
+}
@@ -0,0 +1,27 @@
+//! Contains [wgpu_configuration], the GPU setup the editor normally starts
+//! with (safe mode uses [crate::safe_mode::wgpu_configuration] instead).
+
+use std::sync::Arc;
+
+use egui_wgpu::{WgpuConfiguration, WgpuSetup, WgpuSetupCreateNew};
+
+/// egui's default wgpu setup, with the device also asking for the features
+/// the engine's [pipeline cache](engine::pipeline_cache) needs where the
+/// adapter has them.
+pub fn wgpu_configuration() -> WgpuConfiguration {
+    let default_setup = WgpuSetupCreateNew::default();
+    let default_device_descriptor = default_setup.device_descriptor.clone();
+    let setup = WgpuSetupCreateNew {
+        device_descriptor: Arc::new(move |adapter| {
+            let mut descriptor = default_device_descriptor(adapter);
+            descriptor.required_features |= engine::pipeline_cache::required_features(adapter);
+            descriptor
+        }),
+        ..default_setup
+    };
+
+    WgpuConfiguration {
+        wgpu_setup: WgpuSetup::CreateNew(setup),
+        ..Default::default()
+    }
+}
@@ -1,17 +1,26 @@
 //! Exports [editor] which runs the editor portion of the app.
 
 mod app_area;
+mod app_settings;
 mod args;
 mod components;
+mod display_profile;
+mod export_presets;
+mod gpu_setup;
 mod launcher_comm;
+mod project_windows;
+mod render_cli;
+mod render_worker;
+mod safe_mode;
+mod usage_stats;
 mod windows_resize;
 
 use std::process::ExitCode;
 
 use util::version;
 
-use app_area::AppArea;
 use args::Args;
+use project_windows::{EditorInstance, ProjectWindows};
 
 /// Runs the editor portion of the app.
 pub fn editor() -> ExitCode {
@@ -29,7 +38,11 @@ pub fn editor() -> ExitCode {
         }
     }
 
-    // TODO: Enable stop signal polling and handle stop signals gracefully.
+    // Stop signals are polled by the app so the project can be saved and
+    // closed before exiting.
+    if let Err(e) = util::stop_signals::polling::enable() {
+        util::debug_log_warning!("Failed to enable stop signal polling: {e}");
+    }
 
     if let Some(version_outfile) = args.version {
         return match version::print(version_outfile) {
@@ -41,24 +54,43 @@ pub fn editor() -> ExitCode {
         };
     }
 
-    // Configure the native window with custom title bar
-    let viewport = egui::ViewportBuilder::default()
-        .with_icon(util::ui::load_app_icon())
-        .with_title(version::APP_NAME)
-        .with_decorations(false)
-        .with_resizable(true)
-        .with_inner_size([1280.0, 720.0])
-        .with_min_inner_size([800.0, 600.0])
-        .with_fullscreen(true);
+    if let Some(address) = &args.render_worker {
+        return render_worker::run(address, !args.safe_mode);
+    }
+
+    if let Some(output) = &args.render {
+        return render_cli::run(
+            &args.open_project,
+            output,
+            args.preset.as_deref(),
+            args.duration,
+            args.verify,
+            &args.globals,
+            !args.safe_mode,
+        );
+    }
+
+    let instance = project_windows::claim_instance(&args);
+    if let EditorInstance::Forwarded = instance {
+        util::debug_log_info!("Project sent to the open editor, exiting");
+        return ExitCode::SUCCESS;
+    }
 
-    let native_options = eframe::NativeOptions {
-        viewport,
+    let viewport = viewport_builder(args.safe_mode);
+    let mut native_options = eframe::NativeOptions {
+        viewport: viewport.clone(),
         // Native window persistence can restore stale minimized/tiny sizes on
         // some platforms; keep this off so startup min-size constraints win.
         persist_window: false,
         centered: true,
         ..Default::default()
     };
+    if args.safe_mode {
+        util::debug_log_info!("Starting in safe mode");
+        native_options.wgpu_options = safe_mode::wgpu_configuration();
+    } else {
+        native_options.wgpu_options = gpu_setup::wgpu_configuration();
+    }
 
     eframe::run_native(
         version::APP_NAME,
@@ -66,7 +98,13 @@ pub fn editor() -> ExitCode {
         Box::new(|cc| {
             // Setup Windows-specific borderless resize after window creation
             windows_resize::setup_borderless_resize(cc);
-            Ok(Box::new(AppArea::new(cc, args.clone())))
+            display_profile::remember_window(cc);
+            Ok(Box::new(ProjectWindows::new(
+                cc,
+                args.clone(),
+                viewport,
+                instance,
+            )))
         }),
     )
     .map_or_else(
@@ -77,3 +115,20 @@ pub fn editor() -> ExitCode {
         |_| ExitCode::SUCCESS,
     )
 }
+
+/// The native window for a project, with our custom title bar.
+fn viewport_builder(safe_mode: bool) -> egui::ViewportBuilder {
+    let title = if safe_mode {
+        format!("{} (Safe Mode)", version::APP_NAME)
+    } else {
+        version::APP_NAME.to_string()
+    };
+    egui::ViewportBuilder::default()
+        .with_icon(util::ui::load_app_icon())
+        .with_title(title)
+        .with_decorations(false)
+        .with_resizable(true)
+        .with_inner_size([1280.0, 720.0])
+        .with_min_inner_size([800.0, 600.0])
+        .with_fullscreen(true)
+}
@@ -0,0 +1,267 @@
+//! Contains [ProjectWindows], which lets one editor have several projects open
+//! at once, each in its own window.
+
+use std::time::Instant;
+
+use egui::{ViewportBuilder, ViewportCommand, ViewportId};
+
+use util::instance_lock::{
+    self, EDITOR_LOCK, HEARTBEAT_INTERVAL, InstanceLock, InstanceLockError, RequestReceiver,
+};
+use util::local_data::project::ProjectId;
+use util::stop_signals;
+
+use super::app_area::AppArea;
+use super::args::Args;
+
+/// What [claim_instance] decided this editor should do.
+pub enum EditorInstance {
+    /// This is the editor other instances send projects to.
+    Main(InstanceLock<()>, RequestReceiver),
+    /// This editor only shows the project it was started with.
+    Standalone,
+    /// The project was sent to the editor that's already open, so this one
+    /// should exit.
+    Forwarded,
+}
+
+/// Become the editor other instances send projects to, or send the project
+/// from `args` to the one that already is. Editors started in safe mode or with
+/// the CPU backend keep to themselves, since the projects sent to them
+/// wouldn't expect that.
+pub fn claim_instance(args: &Args) -> EditorInstance {
+    if args.safe_mode || args.cpu_backend {
+        return EditorInstance::Standalone;
+    }
+
+    match InstanceLock::from_default(EDITOR_LOCK) {
+        Ok(instance_lock) => match RequestReceiver::new(EDITOR_LOCK) {
+            Ok(requests) => EditorInstance::Main(instance_lock, requests),
+            Err(e) => {
+                util::debug_log_error!("Failed to create editor request receiver: {e}");
+                EditorInstance::Standalone
+            }
+        },
+
+        Err(InstanceLockError::Locked) => {
+            if args.open_project.is_empty() || !instance_lock::holder_is_responsive(EDITOR_LOCK) {
+                return EditorInstance::Standalone;
+            }
+            match instance_lock::send_request(EDITOR_LOCK, &args.open_project) {
+                Ok(()) => EditorInstance::Forwarded,
+                Err(e) => {
+                    util::debug_log_error!("Failed to send project to the open editor: {e}");
+                    EditorInstance::Standalone
+                }
+            }
+        }
+
+        Err(e) => {
+            util::debug_log_error!("Failed to try acquiring the editor instance lock: {e}");
+            EditorInstance::Standalone
+        }
+    }
+}
+
+/// A project window other than the root one.
+struct ExtraWindow {
+    viewport_id: ViewportId,
+    app_area: AppArea,
+    /// Whether the root window asked this window to close and it hasn't seen
+    /// the request yet.
+    close_pending: bool,
+}
+
+/// The editor's windows. The root window shows the project the editor was
+/// started with. Projects sent by other instances (see [claim_instance]) open
+/// in extra windows, or focus the window they're already open in.
+///
+/// Closing the root window exits the editor, so every extra window is closed
+/// first (each asking about its own unsaved changes). If one of them is
+/// cancelled, the editor stays open.
+pub struct ProjectWindows {
+    args: Args,
+    viewport_builder: ViewportBuilder,
+    root: AppArea,
+    extra_windows: Vec<ExtraWindow>,
+    /// Whether the root window is waiting for the extra windows to close.
+    closing_extra_windows: bool,
+    /// Used to give every extra window its own viewport.
+    windows_opened: u64,
+    /// The instance lock and the requests sent to it, if this is the main
+    /// editor.
+    instance: Option<(InstanceLock<()>, RequestReceiver)>,
+    last_heartbeat: Instant,
+}
+
+impl ProjectWindows {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        args: Args,
+        viewport_builder: ViewportBuilder,
+        instance: EditorInstance,
+    ) -> Self {
+        AppArea::setup_context(&cc.egui_ctx);
+
+        Self {
+            root: AppArea::new(args.clone()),
+            args,
+            viewport_builder,
+            extra_windows: Vec::new(),
+            closing_extra_windows: false,
+            windows_opened: 0,
+            instance: match instance {
+                EditorInstance::Main(instance_lock, requests) => Some((instance_lock, requests)),
+                EditorInstance::Standalone | EditorInstance::Forwarded => None,
+            },
+            last_heartbeat: Instant::now(),
+        }
+    }
+
+    /// Beat the instance lock's heartbeat and open the projects other
+    /// instances have sent.
+    fn handle_requests(&mut self, ctx: &egui::Context) {
+        let Some((instance_lock, requests)) = &mut self.instance else {
+            return;
+        };
+
+        if self.last_heartbeat.elapsed() >= HEARTBEAT_INTERVAL {
+            _ = instance_lock.heartbeat().inspect_err(|e| {
+                util::debug_log_error!("Failed to write editor heartbeat (ignoring): {e}");
+            });
+            self.last_heartbeat = Instant::now();
+        }
+        // Keep beating (and checking for requests) while nothing else repaints.
+        ctx.request_repaint_after(HEARTBEAT_INTERVAL);
+
+        let requests = match requests.receive() {
+            Ok(requests) => requests,
+            Err(e) => {
+                util::debug_log_error!("Failed to receive editor requests (ignoring): {e}");
+                return;
+            }
+        };
+        for project in requests {
+            util::debug_log_info!("Project `{project}` sent by another instance.");
+            self.open_project(ctx, project);
+        }
+    }
+
+    /// Focus the window `project` is open in, opening a new one if there isn't
+    /// one.
+    fn open_project(&mut self, ctx: &egui::Context, project: String) {
+        let Ok(project_id) = ProjectId::try_from(project.clone()) else {
+            util::debug_log_error!("Invalid project ID `{project}` sent (ignoring).");
+            return;
+        };
+
+        let open_in = if self.root.project_id().as_ref() == Some(&project_id) {
+            Some(ViewportId::ROOT)
+        } else {
+            self.extra_windows
+                .iter_mut()
+                .find(|window| window.app_area.project_id().as_ref() == Some(&project_id))
+                .map(|window| window.viewport_id)
+        };
+        if let Some(viewport_id) = open_in {
+            ctx.send_viewport_cmd_to(viewport_id, ViewportCommand::Focus);
+            return;
+        }
+
+        self.windows_opened += 1;
+        self.extra_windows.push(ExtraWindow {
+            viewport_id: ViewportId::from_hash_of(("project window", self.windows_opened)),
+            app_area: AppArea::new(Args {
+                open_project: project,
+                ..self.args.clone()
+            }),
+            close_pending: false,
+        });
+        ctx.request_repaint();
+    }
+
+    /// Close the extra windows before the root window, so each one asks about
+    /// its own unsaved changes instead of them being saved on exit.
+    fn close_extra_windows_first(&mut self, ctx: &egui::Context) {
+        let close_requested = ctx.input(|i| i.viewport().close_requested());
+        self.root
+            .set_close_deferred(close_requested && !self.extra_windows.is_empty());
+        if !close_requested || self.extra_windows.is_empty() {
+            return;
+        }
+
+        ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+        self.closing_extra_windows = true;
+        for window in &mut self.extra_windows {
+            window.close_pending = true;
+            ctx.send_viewport_cmd_to(window.viewport_id, ViewportCommand::Close);
+        }
+    }
+
+    /// Close the root window once the extra windows are closed, or give up if
+    /// one of them was kept open.
+    fn finish_closing_extra_windows(&mut self, ctx: &egui::Context) {
+        if !self.closing_extra_windows {
+            return;
+        }
+
+        if self.extra_windows.is_empty() {
+            self.closing_extra_windows = false;
+            ctx.send_viewport_cmd(ViewportCommand::Close);
+        } else if self
+            .extra_windows
+            .iter()
+            .any(|window| !window.close_pending && !window.app_area.is_confirming_exit())
+        {
+            util::debug_log_info!("A project window was kept open, so the editor stays open.");
+            self.closing_extra_windows = false;
+        }
+    }
+}
+
+impl eframe::App for ProjectWindows {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // Every window has to hear about a stop signal, so it's only consumed
+        // once here.
+        let stop_requested = stop_signals::polling::consume();
+
+        self.handle_requests(ctx);
+        self.close_extra_windows_first(ctx);
+        self.root.update(ctx, frame, stop_requested);
+
+        let viewport_builder = &self.viewport_builder;
+        self.extra_windows.retain_mut(|window| {
+            let closed = ctx.show_viewport_immediate(
+                window.viewport_id,
+                viewport_builder.clone(),
+                |ctx, _class| {
+                    window.app_area.update(ctx, frame, stop_requested);
+                    let close_requested = ctx.input(|i| i.viewport().close_requested());
+                    if close_requested {
+                        window.close_pending = false;
+                    }
+                    close_requested && !window.app_area.is_confirming_exit()
+                },
+            );
+            if closed {
+                window.app_area.shut_down();
+            }
+            !closed
+        });
+
+        self.finish_closing_extra_windows(ctx);
+    }
+
+    fn persist_egui_memory(&self) -> bool {
+        true
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Only left if the editor is exiting some other way (e.g. the OS is
+        // shutting down), so they're saved like any unexpected exit.
+        for window in &mut self.extra_windows {
+            window.app_area.shut_down();
+        }
+        self.root.shut_down();
+    }
+}
@@ -0,0 +1,217 @@
+//! Contains [run], which renders a project to a video from the command line
+//! instead of opening the editor (see `--render`).
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use engine::export::{
+    self, AlphaMode, AudioSource, EncodingOptions, ExportJob, VideoContainer, VideoSettings,
+    VideoSink,
+};
+use engine::node::NodeLibrary;
+use media::frame::Dimensions;
+use util::local_data::project::{Project, ProjectId};
+
+use crate::app_area::editor::{
+    GraphSyncResult, NodeGraphState, OutputSettings, apply_global_overrides, export_fps,
+    frame_count, normalize_node_inputs, sync_graph,
+};
+use crate::app_settings::AppSettings;
+use crate::export_presets;
+
+/// Render `duration_secs` of the project `project_id` to the video `output`,
+/// with the export preset named `preset` if there is one, then print its
+/// summary. The video is decoded back to check it if `verify` is set.
+/// `globals` are `NAME=VALUE` overrides of the project's globals, which aren't
+/// saved to it. Nodes from the users nodes folder are only loaded if
+/// `include_user_nodes` is set.
+pub fn run(
+    project_id: &str,
+    output: &Path,
+    preset: Option<&str>,
+    duration_secs: u32,
+    verify: bool,
+    globals: &[String],
+    include_user_nodes: bool,
+) -> ExitCode {
+    match render(
+        project_id,
+        output,
+        preset,
+        duration_secs,
+        verify,
+        globals,
+        include_user_nodes,
+    ) {
+        Ok((written, path)) => {
+            println!("Rendered {written} frames to {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns how many frames were rendered, and the file they were written to.
+fn render(
+    project_id: &str,
+    output: &Path,
+    preset: Option<&str>,
+    duration_secs: u32,
+    verify: bool,
+    globals: &[String],
+    include_user_nodes: bool,
+) -> Result<(u64, PathBuf), String> {
+    let node_library = if include_user_nodes {
+        NodeLibrary::load_all()
+    } else {
+        NodeLibrary::load_built_in()
+    }
+    .map_err(|err| format!("Failed to load node library: {err:?}"))?;
+
+    let mut project = ProjectId::try_from(project_id.to_string())
+        .and_then(Project::try_from)
+        .and_then(|project| project.open::<NodeGraphState>())
+        .map_err(|err| format!("Failed to open project '{project_id}': {err}"))?;
+    let state = project.data_mut();
+    normalize_node_inputs(state, &node_library);
+    apply_global_overrides(&mut state.globals, globals)?;
+    let (graph, output_node) = match sync_graph(state, &node_library) {
+        GraphSyncResult::Valid {
+            graph,
+            output_node,
+            node_ids,
+        } => {
+            // Render passes are found by their nodes' engine IDs.
+            state.set_engine_ids(node_ids);
+            (graph, output_node)
+        }
+        GraphSyncResult::NoOutput => return Err("The graph has no output to render.".to_string()),
+        GraphSyncResult::Invalid(errors) => {
+            return Err(format!("The graph has errors:\n{}", errors.join("\n")));
+        }
+    };
+
+    let mut output_settings = state.output_settings;
+    let mut audio = true;
+    let mut loudness_target = None;
+    let mut settings = match preset {
+        Some(name) => {
+            let user_presets = AppSettings::load().export.presets;
+            let Some(preset) = export_presets::find(name, &user_presets) else {
+                let names: Vec<String> = export_presets::built_in()
+                    .into_iter()
+                    .chain(user_presets)
+                    .map(|preset| preset.name)
+                    .collect();
+                return Err(format!(
+                    "There's no preset named \"{name}\". The presets are: {}",
+                    names.join(", ")
+                ));
+            };
+            let invalid = |err: String| format!("The \"{name}\" preset can't be used: {err}");
+            output_settings = OutputSettings {
+                resolution: preset.resolution.or(output_settings.resolution),
+                fps: preset.fps.or(output_settings.fps),
+            };
+            audio = preset.audio;
+            loudness_target = preset.loudness_target;
+            VideoSettings {
+                path: output.to_path_buf(),
+                container: preset.container().map_err(invalid)?,
+                codec: preset.codec().map_err(invalid)?,
+                encoding: preset.encoding().map_err(invalid)?,
+                audio: Vec::new(),
+                loudness_target: None,
+            }
+        }
+        None => {
+            let container = output
+                .extension()
+                .and_then(|extension| {
+                    VideoContainer::ALL
+                        .into_iter()
+                        .find(|container| extension.eq_ignore_ascii_case(container.extension()))
+                })
+                .unwrap_or(VideoContainer::Mp4);
+            VideoSettings {
+                path: output.to_path_buf(),
+                container,
+                codec: container.default_codec(),
+                encoding: EncodingOptions::default(),
+                audio: Vec::new(),
+                loudness_target: None,
+            }
+        }
+    };
+    if audio {
+        settings.audio = export::audio_source_paths(&graph, &node_library)
+            .into_iter()
+            .map(|path| AudioSource { path, gain: 1.0 })
+            .collect();
+        settings.loudness_target = loudness_target;
+    }
+
+    let fps = export_fps(output_settings);
+    let job = ExportJob {
+        graph,
+        output_node_id: output_node,
+        resolution: output_settings
+            .resolution
+            .and_then(|(width, height)| Dimensions::new(width, height)),
+        fps,
+        frame_count: frame_count(duration_secs as f64, fps),
+        alpha: if settings.codec.supports_alpha() {
+            AlphaMode::default()
+        } else {
+            AlphaMode::Opaque
+        },
+        passes: state.render_passes(),
+        watermark: None,
+        segment_frames: None,
+    };
+    let path = settings.file_path();
+    let encoding_passes = if settings.encoding.is_two_pass() {
+        2
+    } else {
+        1
+    };
+    let total = job.frame_count * encoding_passes;
+
+    println!("Rendering {} frames to {}", job.frame_count, path.display());
+    let mut sink = VideoSink::new(settings);
+    let mut last_percent = None;
+    let written = export::render_headless(&job, &node_library, &mut sink, |written| {
+        let percent = written * 100 / total;
+        if last_percent != Some(percent) {
+            last_percent = Some(percent);
+            print!("\r{percent}%");
+            let _ = std::io::stdout().flush();
+        }
+        true
+    })
+    .map_err(|err| format!("\nThe render failed: {err}"))?;
+    println!();
+    if let Some(loudness) = sink.loudness() {
+        println!("{loudness}");
+    }
+    if verify {
+        println!("Verifying {}", path.display());
+        sink.verify()
+            .map_err(|err| format!("The verification failed: {err}"))?;
+    }
+    if let Some(summary) = sink.summary() {
+        println!("{summary}");
+        if summary
+            .verification
+            .as_ref()
+            .is_some_and(|verification| !verification.passed())
+        {
+            return Err(format!("{} is corrupt.", path.display()));
+        }
+    }
+    Ok((written, path))
+}
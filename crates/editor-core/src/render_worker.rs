@@ -0,0 +1,39 @@
+//! Contains [run], which runs the editor as a render farm worker instead of
+//! opening its UI (see [engine::export::render_distributed]).
+
+use std::process::ExitCode;
+
+use engine::export;
+use engine::node::NodeLibrary;
+
+/// Render export chunks for the coordinator at `address` until it's done.
+/// Nodes from the users nodes folder are only loaded if `include_user_nodes`
+/// is set, and they have to match the coordinator's.
+pub fn run(address: &str, include_user_nodes: bool) -> ExitCode {
+    let node_library = if include_user_nodes {
+        NodeLibrary::load_all()
+    } else {
+        NodeLibrary::load_built_in()
+    };
+    let node_library = match node_library {
+        Ok(lib) => lib,
+        Err(err) => {
+            eprintln!("Failed to load node library: {err:?}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("Rendering for the coordinator at {address}");
+    match export::run_worker(address, &node_library, |chunk| {
+        println!("Sent chunk {}", chunk + 1);
+    }) {
+        Ok(chunks) => {
+            println!("Done, rendered {chunks} chunk(s)");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Contains [wgpu_configuration], the GPU setup used when the editor is started
+//! with `--safe-mode` (see [crate::args::Args::safe_mode]).
+
+use std::sync::Arc;
+
+use egui_wgpu::{WgpuConfiguration, WgpuSetup, WgpuSetupCreateNew, wgpu};
+
+/// A wgpu setup that picks the least demanding adapter available: a software
+/// (CPU) adapter if there is one, then an integrated GPU, then anything that
+/// can draw to the window.
+pub fn wgpu_configuration() -> WgpuConfiguration {
+    let setup = WgpuSetupCreateNew {
+        power_preference: wgpu::PowerPreference::LowPower,
+        native_adapter_selector: Some(Arc::new(
+            |adapters: &[wgpu::Adapter], surface: Option<&wgpu::Surface<'_>>| {
+                let usable: Vec<&wgpu::Adapter> = adapters
+                    .iter()
+                    .filter(|adapter| {
+                        surface.is_none_or(|surface| adapter.is_surface_supported(surface))
+                    })
+                    .collect();
+
+                let by_type = |device_type| {
+                    usable
+                        .iter()
+                        .find(|adapter| adapter.get_info().device_type == device_type)
+                };
+                let adapter = by_type(wgpu::DeviceType::Cpu)
+                    .or_else(|| by_type(wgpu::DeviceType::IntegratedGpu))
+                    .or_else(|| usable.first())
+                    .ok_or_else(|| "No adapter can draw to the window".to_string())?;
+
+                util::debug_log_info!("Safe mode is using adapter: {:?}", adapter.get_info());
+                Ok((*adapter).clone())
+            },
+        )),
+        ..Default::default()
+    };
+
+    WgpuConfiguration {
+        wgpu_setup: WgpuSetup::CreateNew(setup),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,217 @@
+//! Usage statistics kept for the user: which nodes they add most, how long
+//! their sessions are, and how much they export. They're saved in the user's
+//! local data (see [local_data::usage_stats_file_path]) and never leave the
+//! machine.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use util::local_data;
+use util::saved_file::{self, SavedFile, SavedFileError};
+
+/// How often a session's stats are added to the saved ones.
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageStats {
+    /// How many times each node was added to a graph, by definition name.
+    #[serde(default)]
+    pub node_uses: HashMap<String, u64>,
+    /// How many times the editor was started.
+    #[serde(default)]
+    pub sessions: u64,
+    /// How long the editor has been open, over every session.
+    #[serde(default)]
+    pub session_time: Duration,
+    /// How many exports finished, and how many frames they wrote.
+    #[serde(default)]
+    pub exports: u64,
+    #[serde(default)]
+    pub exported_frames: u64,
+}
+
+impl UsageStats {
+    /// Read the saved stats, or empty ones if there aren't any (or they can't
+    /// be read).
+    fn load() -> Self {
+        match Self::read_from_file_path_default(local_data::usage_stats_file_path()) {
+            Ok((stats, _)) => stats,
+            Err(e) => {
+                util::debug_log_warning!("Failed to read usage stats (starting over): {e}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Save the stats, logging any error.
+    fn save(&self) {
+        let result = saved_file::open_file_with_create_info(local_data::usage_stats_file_path())
+            .map_err(SavedFileError::from)
+            .and_then(|(file, _)| self.save_to_file(&file));
+        if let Err(e) = result {
+            util::debug_log_warning!("Failed to save usage stats: {e}");
+        }
+    }
+
+    /// Add `other`'s counts to these.
+    fn add(&mut self, other: &Self) {
+        for (name, uses) in &other.node_uses {
+            *self.node_uses.entry(name.clone()).or_default() += uses;
+        }
+        self.sessions += other.sessions;
+        self.session_time += other.session_time;
+        self.exports += other.exports;
+        self.exported_frames += other.exported_frames;
+    }
+
+    /// How long a session lasts on average, or [None] before the first.
+    pub fn average_session(&self) -> Option<Duration> {
+        (self.sessions > 0).then(|| self.session_time / self.sessions as u32)
+    }
+
+    /// The definition names of the `count` nodes added most, with how many
+    /// times they were, most first.
+    pub fn most_used_nodes(&self, count: usize) -> Vec<(&str, u64)> {
+        let mut nodes: Vec<(&str, u64)> = self
+            .node_uses
+            .iter()
+            .map(|(name, uses)| (name.as_str(), *uses))
+            .collect();
+        nodes.sort_by(|(a_name, a_uses), (b_name, b_uses)| {
+            b_uses.cmp(a_uses).then_with(|| a_name.cmp(b_name))
+        });
+        nodes.truncate(count);
+        nodes
+    }
+}
+
+/// Records this session's usage, adding it to the saved [UsageStats] every
+/// so often and when the editor closes. Each save re-reads the file first, so
+/// other editor windows' sessions aren't lost.
+pub struct UsageSession {
+    /// The saved stats with this session's so far.
+    totals: UsageStats,
+    /// What's been recorded since the last save.
+    unsaved: UsageStats,
+    last_save: Instant,
+}
+
+impl UsageSession {
+    /// Start a session, counting it.
+    pub fn start() -> Self {
+        let started = UsageStats {
+            sessions: 1,
+            ..Default::default()
+        };
+        let mut totals = UsageStats::load();
+        totals.add(&started);
+        Self {
+            totals,
+            unsaved: started,
+            last_save: Instant::now(),
+        }
+    }
+
+    /// The saved stats with this session's so far.
+    pub fn stats(&self) -> &UsageStats {
+        &self.totals
+    }
+
+    /// Count `definition_name` being added to a graph.
+    pub fn record_node_added(&mut self, definition_name: &str) {
+        self.record(UsageStats {
+            node_uses: HashMap::from([(definition_name.to_string(), 1)]),
+            ..Default::default()
+        });
+    }
+
+    /// Count an export that wrote `frames` frames.
+    pub fn record_export(&mut self, frames: u64) {
+        self.record(UsageStats {
+            exports: 1,
+            exported_frames: frames,
+            ..Default::default()
+        });
+    }
+
+    fn record(&mut self, usage: UsageStats) {
+        self.totals.add(&usage);
+        self.unsaved.add(&usage);
+    }
+
+    /// Save the session's usage if it's been a while since it last was.
+    pub fn tick(&mut self) {
+        if self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.save();
+        }
+    }
+
+    /// Add what's been recorded since the last save (and the time since) to
+    /// the saved stats.
+    pub fn save(&mut self) {
+        let now = Instant::now();
+        self.unsaved.session_time += now - self.last_save;
+        self.last_save = now;
+
+        let mut saved = UsageStats::load();
+        saved.add(&std::mem::take(&mut self.unsaved));
+        saved.save();
+        self.totals = saved;
+    }
+
+    /// Forget every saved stat, starting over from this session.
+    pub fn clear(&mut self) {
+        self.totals = UsageStats::default();
+        self.unsaved = UsageStats::default();
+        self.last_save = Instant::now();
+        self.totals.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- UsageStats::add() ---
+
+    #[test]
+    fn add_sums_every_count() {
+        let mut stats = UsageStats {
+            node_uses: HashMap::from([("blur".to_string(), 2)]),
+            sessions: 1,
+            session_time: Duration::from_secs(60),
+            exports: 1,
+            exported_frames: 100,
+        };
+        stats.add(&UsageStats {
+            node_uses: HashMap::from([("blur".to_string(), 1), ("add".to_string(), 4)]),
+            sessions: 2,
+            session_time: Duration::from_secs(120),
+            exports: 0,
+            exported_frames: 0,
+        });
+
+        assert_eq!(stats.node_uses["blur"], 3);
+        assert_eq!(stats.node_uses["add"], 4);
+        assert_eq!(stats.sessions, 3);
+        assert_eq!(stats.average_session(), Some(Duration::from_secs(60)));
+        assert_eq!(stats.exported_frames, 100);
+    }
+
+    // --- UsageStats::most_used_nodes() ---
+
+    #[test]
+    fn most_used_nodes_breaks_ties_by_name() {
+        let stats = UsageStats {
+            node_uses: HashMap::from([
+                ("b".to_string(), 2),
+                ("a".to_string(), 2),
+                ("c".to_string(), 5),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(stats.most_used_nodes(2), [("c", 5), ("a", 2)]);
+        assert_eq!(UsageStats::default().average_session(), None);
+    }
+}
@@ -0,0 +1,117 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use serde_json::Value;
+
+fn main() {
+    // The stock nodes are embedded into the engine so the app still works
+    // when the nodes folder next to it is missing or damaged (see
+    // `node::embedded_nodes`).
+    let nodes_dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("../../nodes");
+    let nodes_dir = nodes_dir
+        .canonicalize()
+        .unwrap_or_else(|e| panic!("Failed to find the stock nodes folder at {nodes_dir:?}: {e}"));
+    println!("cargo:rerun-if-changed={}", nodes_dir.display());
+
+    let mut files = Vec::new();
+    collect_files(&nodes_dir, &mut files);
+    files.sort();
+
+    for file in &files {
+        if file.file_name().is_some_and(|name| name == "node.json") {
+            validate_node(file);
+        }
+    }
+
+    let mut code = String::from("pub(super) static EMBEDDED_NODE_FILES: &[(&str, &[u8])] = &[\n");
+    for file in &files {
+        let relative = file
+            .strip_prefix(&nodes_dir)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        writeln!(
+            code,
+            "    ({relative:?}, include_bytes!({:?})),",
+            file.display()
+        )
+        .unwrap();
+    }
+    code.push_str("];\n");
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("embedded_nodes.rs");
+    fs::write(out_path, code).unwrap();
+}
+
+/// Add every file in `dir` (and the folders in it) to `files`.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        println!("cargo:rerun-if-changed={}", path.display());
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// Make sure the node.json at `path` parses and every shader it runs compiles,
+/// so a broken stock node fails the build instead of being embedded.
+fn validate_node(path: &Path) {
+    let folder = path.parent().unwrap();
+    let node: Value = serde_json::from_str(&fs::read_to_string(path).unwrap())
+        .unwrap_or_else(|e| panic!("Failed to parse {path:?}: {e}"));
+
+    let relative_paths = |value: Option<&Value>| -> Vec<String> {
+        value
+            .and_then(Value::as_array)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| {
+                        value
+                            .as_str()
+                            .or_else(|| value.get("source").and_then(Value::as_str))
+                            .map(str::to_string)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let executor = &node["executor"];
+    if let Some(shader) = executor.get("Shader") {
+        let mut prelude = String::new();
+        for include in relative_paths(shader.get("includes")) {
+            prelude.push_str(&read(&folder.join(include)));
+            prelude.push('\n');
+        }
+        let mut sources = relative_paths(shader.get("passes"));
+        sources.extend(shader["source"].as_str().map(str::to_string));
+        for source in sources {
+            let source = folder.join(source);
+            validate_wgsl(&source, &format!("{prelude}{}", read(&source)));
+        }
+    } else if let Some(algorithm) = executor.get("Algorithm") {
+        for source in relative_paths(algorithm.get("stages")) {
+            let source = folder.join(source);
+            validate_wgsl(&source, &read(&source));
+        }
+    }
+}
+
+fn validate_wgsl(path: &Path, code: &str) {
+    let module = naga::front::wgsl::parse_str(code)
+        .unwrap_or_else(|e| panic!("Failed to parse {path:?}:\n{}", e.emit_to_string(code)));
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .unwrap_or_else(|e| panic!("{path:?} isn't valid:\n{}", e.emit_to_string(code)));
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| panic!("Failed to read {path:?}: {e}"))
+}
@@ -0,0 +1,292 @@
+//! Exports [find_blobs], which labels the connected regions of a mask, and
+//! [BlobTracker], which follows those regions from frame to frame so each keeps
+//! the same ID (see the Blob Track node).
+
+use media::frame::{Frame, Pixel};
+use serde::Serialize;
+
+/// A connected region of bright pixels in a mask. Positions and sizes are
+/// fractions of the frame's width and height, from its top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Blob {
+    /// The center of the region's pixels.
+    pub center: [f32; 2],
+    /// The fraction of the frame's pixels the region covers.
+    pub area: f32,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// A [Blob] with an ID that stays the same for as long as it's tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TrackedBlob {
+    pub id: u32,
+    #[serde(flatten)]
+    pub blob: Blob,
+    /// How far the blob's center moved since it was last seen.
+    pub velocity: [f32; 2],
+}
+
+/// The regions of 8-connected bright pixels (brighter than half) in `mask`
+/// that cover at least `min_area` of it, largest first.
+pub fn find_blobs(mask: &Frame, min_area: f32) -> Vec<Blob> {
+    let width = mask.dimensions().width() as usize;
+    let height = mask.dimensions().height() as usize;
+    let total = (width * height) as f32;
+    let bright: Vec<bool> = mask.pixels().iter().map(is_bright).collect();
+
+    let mut visited = vec![false; bright.len()];
+    let mut stack = Vec::new();
+    let mut blobs = Vec::new();
+    for start in 0..bright.len() {
+        if !bright[start] || visited[start] {
+            continue;
+        }
+
+        // Flood fill the region, tracking its bounds and center.
+        visited[start] = true;
+        stack.push(start);
+        let (mut area, mut sum_x, mut sum_y) = (0usize, 0usize, 0usize);
+        let (mut left, mut top, mut right, mut bottom) = (width, height, 0, 0);
+        while let Some(index) = stack.pop() {
+            let (x, y) = (index % width, index / width);
+            area += 1;
+            sum_x += x;
+            sum_y += y;
+            left = left.min(x);
+            right = right.max(x);
+            top = top.min(y);
+            bottom = bottom.max(y);
+
+            for neighbor_y in y.saturating_sub(1)..(y + 2).min(height) {
+                for neighbor_x in x.saturating_sub(1)..(x + 2).min(width) {
+                    let neighbor = neighbor_y * width + neighbor_x;
+                    if bright[neighbor] && !visited[neighbor] {
+                        visited[neighbor] = true;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+        if (area as f32) < min_area * total {
+            continue;
+        }
+
+        blobs.push(Blob {
+            center: [
+                (sum_x as f32 / area as f32 + 0.5) / width as f32,
+                (sum_y as f32 / area as f32 + 0.5) / height as f32,
+            ],
+            area: area as f32 / total,
+            x: left as f32 / width as f32,
+            y: top as f32 / height as f32,
+            width: (right - left + 1) as f32 / width as f32,
+            height: (bottom - top + 1) as f32 / height as f32,
+        });
+    }
+
+    blobs.sort_by(|a, b| b.area.total_cmp(&a.area));
+    blobs
+}
+
+fn is_bright(pixel: &Pixel) -> bool {
+    let luma =
+        0.2126 * pixel.red() as f32 + 0.7152 * pixel.green() as f32 + 0.0722 * pixel.blue() as f32;
+    luma > 127.5
+}
+
+struct Track {
+    blob: TrackedBlob,
+    /// How many updates in a row the blob hasn't been found in.
+    missed: u32,
+}
+
+/// Gives blobs IDs that follow them from frame to frame.
+///
+/// Each update, blobs are matched to the tracked blobs nearest them (closest
+/// pairs first) within a maximum distance. Blobs with no match get new IDs, and
+/// tracked blobs that go unmatched are kept for a few updates in case they
+/// reappear (e.g. a cell briefly dropping below the threshold).
+pub struct BlobTracker {
+    tracks: Vec<Track>,
+    next_id: u32,
+    /// How many updates a blob can go missing for before its ID is forgotten.
+    max_missed: u32,
+}
+
+impl BlobTracker {
+    /// Create a tracker that forgets blobs missing for more than `max_missed`
+    /// updates.
+    pub fn new(max_missed: u32) -> Self {
+        Self {
+            tracks: Vec::new(),
+            next_id: 1,
+            max_missed,
+        }
+    }
+
+    /// Match `blobs` to the blobs tracked so far, where a blob can have moved
+    /// at most `max_distance` (as a fraction of the frame) since it was last
+    /// seen. Returns `blobs` with their IDs, oldest ID first.
+    pub fn update(&mut self, blobs: &[Blob], max_distance: f32) -> Vec<TrackedBlob> {
+        let mut pairs = Vec::new();
+        for (track_index, track) in self.tracks.iter().enumerate() {
+            for (blob_index, blob) in blobs.iter().enumerate() {
+                let distance = distance(track.blob.blob.center, blob.center);
+                if distance <= max_distance {
+                    pairs.push((distance, track_index, blob_index));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut track_matched = vec![false; self.tracks.len()];
+        let mut blob_matched = vec![false; blobs.len()];
+        for (_, track_index, blob_index) in pairs {
+            if track_matched[track_index] || blob_matched[blob_index] {
+                continue;
+            }
+            track_matched[track_index] = true;
+            blob_matched[blob_index] = true;
+
+            let track = &mut self.tracks[track_index];
+            let blob = blobs[blob_index];
+            let previous = track.blob.blob.center;
+            track.blob.velocity = [blob.center[0] - previous[0], blob.center[1] - previous[1]];
+            track.blob.blob = blob;
+            track.missed = 0;
+        }
+
+        for (track, matched) in self.tracks.iter_mut().zip(track_matched) {
+            if !matched {
+                track.missed += 1;
+            }
+        }
+        let max_missed = self.max_missed;
+        self.tracks.retain(|track| track.missed <= max_missed);
+
+        for (blob, matched) in blobs.iter().zip(blob_matched) {
+            if !matched {
+                self.tracks.push(Track {
+                    blob: TrackedBlob {
+                        id: self.next_id,
+                        blob: *blob,
+                        velocity: [0.0; 2],
+                    },
+                    missed: 0,
+                });
+                self.next_id += 1;
+            }
+        }
+
+        self.tracks
+            .iter()
+            .filter(|track| track.missed == 0)
+            .map(|track| track.blob)
+            .collect()
+    }
+}
+
+/// Number `blobs` from 1 without tracking them, keeping their order.
+pub fn untracked(blobs: &[Blob]) -> Vec<TrackedBlob> {
+    blobs
+        .iter()
+        .zip(1..)
+        .map(|(blob, id)| TrackedBlob {
+            id,
+            blob: *blob,
+            velocity: [0.0; 2],
+        })
+        .collect()
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use media::frame::Dimensions;
+
+    /// A 100x100 mask with white squares 10 pixels wide at `corners` (row,
+    /// column).
+    fn mask(corners: &[(usize, usize)]) -> Frame {
+        Frame::from_fill_with_coords(Dimensions::new(100, 100).unwrap(), |row, col| {
+            let inside = corners.iter().any(|&(top, left)| {
+                (top..top + 10).contains(&row) && (left..left + 10).contains(&col)
+            });
+            if inside {
+                Pixel::from_rgb(255, 255, 255)
+            } else {
+                Pixel::from_rgb(0, 0, 0)
+            }
+        })
+    }
+
+    // --- find_blobs() ---
+
+    #[test]
+    fn test_find_blobs() {
+        // Two squares touching at a corner are one blob; a lone pixel is too
+        // small to count.
+        let squares = mask(&[(10, 10), (20, 20), (60, 70)]);
+        let frame = Frame::from_fill_with_coords(squares.dimensions(), |row, col| {
+            if row == 90 && col == 5 {
+                Pixel::from_rgb(255, 255, 255)
+            } else {
+                squares.pixels()[row * 100 + col]
+            }
+        });
+
+        let blobs = find_blobs(&frame, 0.001);
+        assert_eq!(blobs.len(), 2, "{blobs:?}");
+        assert!((blobs[0].area - 0.02).abs() < 1e-6);
+        assert!((blobs[0].center[0] - 0.2).abs() < 1e-6);
+        assert!((blobs[0].width - 0.2).abs() < 1e-6);
+        assert!((blobs[1].area - 0.01).abs() < 1e-6);
+        assert!((blobs[1].center[0] - 0.75).abs() < 1e-6);
+        assert!((blobs[1].center[1] - 0.65).abs() < 1e-6);
+
+        assert_eq!(find_blobs(&frame, 0.0).len(), 3);
+    }
+
+    // --- BlobTracker::update() ---
+
+    #[test]
+    fn test_blob_tracker_update() {
+        let mut tracker = BlobTracker::new(1);
+
+        let first = tracker.update(&find_blobs(&mask(&[(10, 10), (60, 60)]), 0.0), 0.1);
+        let ids: Vec<u32> = first.iter().map(|blob| blob.id).collect();
+        assert_eq!(ids, [1, 2]);
+
+        // Both move a little, and their IDs follow them.
+        let second = tracker.update(&find_blobs(&mask(&[(62, 60), (10, 13)]), 0.0), 0.1);
+        assert_eq!(second.len(), 2);
+        assert_eq!(second[0].id, 1);
+        assert!((second[0].blob.center[0] - 0.18).abs() < 1e-6);
+        assert!((second[0].velocity[0] - 0.03).abs() < 1e-6);
+        assert!((second[1].velocity[1] - 0.02).abs() < 1e-6);
+
+        // Blob 2 goes missing for one update, comes back, and keeps its ID. A
+        // blob too far from any tracked one gets a new ID.
+        tracker.update(&find_blobs(&mask(&[(10, 13)]), 0.0), 0.1);
+        let fourth = tracker.update(&find_blobs(&mask(&[(10, 13), (62, 60), (85, 0)]), 0.0), 0.1);
+        let ids: Vec<u32> = fourth.iter().map(|blob| blob.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+
+        // After going missing for longer, it's forgotten.
+        tracker.update(&[], 0.1);
+        tracker.update(&[], 0.1);
+        let ids: Vec<u32> = tracker
+            .update(&find_blobs(&mask(&[(10, 13)]), 0.0), 0.1)
+            .iter()
+            .map(|blob| blob.id)
+            .collect();
+        assert_eq!(ids, [4]);
+    }
+}
@@ -0,0 +1,391 @@
+//! Exports [ContrastEqualizer], which spreads out the brightness of frames with
+//! histogram equalization, either over the whole frame or per tile with CLAHE
+//! (contrast-limited adaptive histogram equalization; see the Equalize node).
+//!
+//! Both run on the GPU. A compute pass gives each tile one workgroup, which
+//! counts the tile's brightness histogram, clips it to the clip limit (giving
+//! the clipped counts back evenly to every bin), and writes its running total
+//! as that tile's lookup table. A render pass then looks each pixel up in the
+//! tables of the four nearest tiles and blends them by distance, so tiles don't
+//! show as blocks. Global equalization is the same with a single tile and no
+//! clipping.
+
+/// The most tiles across or down a frame can be split into.
+pub const MAX_TILES: u32 = 16;
+/// The number of brightness levels in each tile's histogram and table.
+const BINS: u64 = 256;
+
+const HISTOGRAM_SHADER: &str = r#"
+struct Params {
+    tiles_x: u32,
+    tiles_y: u32,
+    clip_limit: f32,
+    amount: f32,
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> luts: array<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+const BINS: u32 = 256u;
+const THREADS: u32 = 256u;
+
+var<workgroup> histogram: array<atomic<u32>, 256>;
+
+fn luma(c: vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@compute @workgroup_size(256)
+fn cs_main(
+    @builtin(workgroup_id) tile: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+) {
+    atomicStore(&histogram[index], 0u);
+    workgroupBarrier();
+
+    let size = textureDimensions(source_texture);
+    let tiles = vec2<u32>(params.tiles_x, params.tiles_y);
+    let start = tile.xy * size / tiles;
+    let end = (tile.xy + 1u) * size / tiles;
+    let tile_size = end - start;
+    let count = tile_size.x * tile_size.y;
+
+    for (var i = index; i < count; i += THREADS) {
+        let coord = start + vec2<u32>(i % tile_size.x, i / tile_size.x);
+        let color = textureLoad(source_texture, vec2<i32>(coord), 0).rgb;
+        let bin = min(u32(luma(color) * f32(BINS)), BINS - 1u);
+        atomicAdd(&histogram[bin], 1u);
+    }
+    workgroupBarrier();
+    if index != 0u {
+        return;
+    }
+
+    // A clip limit of 0 means no limit. Otherwise it's a multiple of the
+    // count every bin would have if brightness were spread evenly.
+    var limit = f32(count);
+    if params.clip_limit > 0.0 {
+        limit = max(params.clip_limit * f32(count) / f32(BINS), 1.0);
+    }
+    var excess = 0.0;
+    for (var bin = 0u; bin < BINS; bin++) {
+        excess += max(f32(atomicLoad(&histogram[bin])) - limit, 0.0);
+    }
+    let share = excess / f32(BINS);
+
+    let offset = (tile.y * params.tiles_x + tile.x) * BINS;
+    let total = max(f32(count), 1.0);
+    var running = 0.0;
+    for (var bin = 0u; bin < BINS; bin++) {
+        running += min(f32(atomicLoad(&histogram[bin])), limit) + share;
+        luts[offset + bin] = min(running / total, 1.0);
+    }
+}
+"#;
+
+const APPLY_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    tiles_x: u32,
+    tiles_y: u32,
+    clip_limit: f32,
+    amount: f32,
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read> luts: array<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+const BINS: u32 = 256u;
+
+fn luma(c: vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn lookup(tile: vec2<u32>, bin: u32) -> f32 {
+    return luts[(tile.y * params.tiles_x + tile.x) * BINS + bin];
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureLoad(source_texture, vec2<i32>(in.position.xy), 0);
+    let y = luma(color.rgb);
+    let bin = min(u32(y * f32(BINS)), BINS - 1u);
+
+    // Where the pixel is relative to the centers of the tiles around it.
+    let size = vec2<f32>(textureDimensions(source_texture));
+    let tiles = vec2<u32>(params.tiles_x, params.tiles_y);
+    let position = clamp(
+        in.position.xy / size * vec2<f32>(tiles) - 0.5,
+        vec2<f32>(0.0),
+        vec2<f32>(tiles - 1u),
+    );
+    let t0 = vec2<u32>(floor(position));
+    let t1 = min(t0 + 1u, tiles - 1u);
+    let f = fract(position);
+
+    let top = mix(lookup(t0, bin), lookup(vec2<u32>(t1.x, t0.y), bin), f.x);
+    let bottom = mix(lookup(vec2<u32>(t0.x, t1.y), bin), lookup(t1, bin), f.x);
+    let mapped = mix(top, bottom, f.y);
+
+    // Shift every channel by the change in brightness to keep the hue.
+    let equalized = clamp(color.rgb + (mapped - y), vec3<f32>(0.0), vec3<f32>(1.0));
+    return vec4<f32>(mix(color.rgb, equalized, params.amount), color.a);
+}
+"#;
+
+/// How [ContrastEqualizer::equalize] spreads out brightness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Equalization {
+    /// One histogram for the whole frame, with no clip limit.
+    Global,
+    /// A histogram per tile of a `tiles` by `tiles` grid (at most
+    /// [MAX_TILES]), each clipped at `clip_limit` times the average bin so
+    /// noise in flat areas isn't blown up. Higher limits add more contrast.
+    Adaptive { tiles: u32, clip_limit: f32 },
+}
+
+/// The buffers one stream of frames is equalized with.
+pub struct EqualizeState {
+    luts_buf: wgpu::Buffer,
+    params_buf: wgpu::Buffer,
+}
+
+impl EqualizeState {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            luts_buf: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("equalize_luts"),
+                size: (MAX_TILES * MAX_TILES) as u64 * BINS * 4,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            }),
+            params_buf: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("equalize_params"),
+                size: 16,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+}
+
+/// Equalizes textures into render targets of one format.
+pub struct ContrastEqualizer {
+    histogram_pipeline: wgpu::ComputePipeline,
+    histogram_bgl: wgpu::BindGroupLayout,
+    apply_pipeline: wgpu::RenderPipeline,
+    apply_bgl: wgpu::BindGroupLayout,
+}
+
+impl ContrastEqualizer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let histogram_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/equalize_histogram"),
+            entries: &bind_group_layout_entries(wgpu::ShaderStages::COMPUTE, false),
+        });
+        let histogram_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/equalize_histogram"),
+            bind_group_layouts: &[&histogram_bgl],
+            ..Default::default()
+        });
+        let histogram_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/equalize_histogram"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(HISTOGRAM_SHADER)),
+        });
+        let histogram_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("pipeline/equalize_histogram"),
+            layout: Some(&histogram_layout),
+            module: &histogram_shader,
+            entry_point: Some("cs_main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let apply_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/equalize_apply"),
+            entries: &bind_group_layout_entries(wgpu::ShaderStages::FRAGMENT, true),
+        });
+        let apply_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/equalize_apply"),
+            bind_group_layouts: &[&apply_bgl],
+            ..Default::default()
+        });
+        let apply_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/equalize_apply"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(APPLY_SHADER)),
+        });
+        let apply_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/equalize_apply"),
+            layout: Some(&apply_layout),
+            vertex: wgpu::VertexState {
+                module: &apply_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &apply_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        Self {
+            histogram_pipeline,
+            histogram_bgl,
+            apply_pipeline,
+            apply_bgl,
+        }
+    }
+
+    /// Record passes into `encoder` that draw `source` equalized with
+    /// `equalization` into `target`, which must be the same size as `source`.
+    /// `amount` (`0.0` to `1.0`) blends between the source and the equalized
+    /// frame.
+    ///
+    /// The parameters are written with `queue` into `state`, so `encoder` must
+    /// be submitted before this is called again with the same state.
+    #[allow(clippy::too_many_arguments)]
+    pub fn equalize(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        state: &EqualizeState,
+        equalization: Equalization,
+        amount: f32,
+        target: &wgpu::TextureView,
+    ) {
+        let (tiles, clip_limit) = match equalization {
+            Equalization::Global => (1, 0.0),
+            Equalization::Adaptive { tiles, clip_limit } => {
+                (tiles.clamp(1, MAX_TILES), clip_limit.max(1.0))
+            }
+        };
+        let mut params = [0u8; 16];
+        params[0..4].copy_from_slice(&tiles.to_le_bytes());
+        params[4..8].copy_from_slice(&tiles.to_le_bytes());
+        params[8..12].copy_from_slice(&clip_limit.to_le_bytes());
+        params[12..16].copy_from_slice(&amount.clamp(0.0, 1.0).to_le_bytes());
+        queue.write_buffer(&state.params_buf, 0, &params);
+
+        let bind_group = |layout, label| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: state.luts_buf.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: state.params_buf.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+
+        {
+            let histogram_group = bind_group(&self.histogram_bgl, "bg/equalize_histogram");
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("equalize_histogram"),
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(&self.histogram_pipeline);
+            cpass.set_bind_group(0, &histogram_group, &[]);
+            cpass.dispatch_workgroups(tiles, tiles, 1);
+        }
+
+        let apply_group = bind_group(&self.apply_bgl, "bg/equalize_apply");
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("equalize_apply"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.apply_pipeline);
+        rpass.set_bind_group(0, &apply_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+/// The source texture, the lookup tables (read only when `read_only` is set),
+/// and the parameters, all visible to `visibility`.
+fn bind_group_layout_entries(
+    visibility: wgpu::ShaderStages,
+    read_only: bool,
+) -> [wgpu::BindGroupLayoutEntry; 3] {
+    [
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+    ]
+}
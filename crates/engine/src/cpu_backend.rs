@@ -0,0 +1,448 @@
+//! CPU fallback implementations for a core subset of nodes, for machines with
+//! GPUs too weak to run every node as a shader.
+//!
+//! Kernels operate on [media::frame::Frame]s directly and are looked up by the
+//! node definition's name, reading inputs by the same names the node.json
+//! declares. This means graphs don't change between backends; nodes without a
+//! CPU kernel (and nodes whose frame inputs aren't available on the CPU) simply
+//! run on the GPU as usual. See [ExecutionBackend].
+//!
+//! Frames are only sampled with [SamplingQuality::Nearest] or
+//! [SamplingQuality::Bilinear] on the CPU, both from the full size frame since
+//! there are no mipmaps. Nodes set to a higher quality run on the GPU.
+
+use std::collections::HashMap;
+
+use media::frame::{Frame, Pixel};
+use thiserror::Error;
+
+use crate::graph_executor::NodeValue;
+use crate::node::NodeDefinition;
+use crate::node_pipelines::{SAMPLING_INPUT_NAME, SamplingQuality};
+
+/// Which backend the [crate::graph_executor::GraphExecutor] runs supported
+/// nodes on. Chosen once per session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ExecutionBackend {
+    /// Run every node on the GPU.
+    #[default]
+    Gpu,
+    /// Run nodes with a CPU kernel on the CPU, falling back to the GPU for the
+    /// rest.
+    Cpu,
+}
+
+/// Errors from running a CPU kernel.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CpuBackendError {
+    #[error("Node '{0}' has no CPU implementation")]
+    Unsupported(String),
+
+    #[error("Node '{node}' can't sample frames with {sampling:?} sampling on the CPU")]
+    UnsupportedSampling {
+        node: String,
+        sampling: SamplingQuality,
+    },
+
+    #[error("Node '{node}' needs {expected} frame input(s) but got {actual}")]
+    MissingFrameInput {
+        node: String,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+/// The nodes with CPU implementations. Names match the node.json `name`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CpuKernel {
+    Invert,
+    Brightness,
+    Overlay,
+    Rotate,
+}
+
+impl CpuKernel {
+    fn for_definition(definition: &NodeDefinition) -> Option<Self> {
+        match definition.node.name.as_str() {
+            "Invert" => Some(Self::Invert),
+            "Brightness" => Some(Self::Brightness),
+            "Overlay" => Some(Self::Overlay),
+            "Rotate" => Some(Self::Rotate),
+            _ => None,
+        }
+    }
+
+    const fn frame_inputs(self) -> usize {
+        match self {
+            Self::Overlay => 2,
+            Self::Invert | Self::Brightness | Self::Rotate => 1,
+        }
+    }
+
+    /// Whether the kernel samples its frames at other coordinates than the
+    /// output pixel's (and so depends on the `Sampling` input).
+    const fn samples(self) -> bool {
+        matches!(self, Self::Overlay | Self::Rotate)
+    }
+}
+
+/// Whether `definition` has a CPU implementation that can run with `inputs`.
+pub fn supports(definition: &NodeDefinition, inputs: &HashMap<String, NodeValue>) -> bool {
+    CpuKernel::for_definition(definition)
+        .is_some_and(|kernel| !kernel.samples() || cpu_sampling(inputs).is_some())
+}
+
+/// Run the CPU implementation of `definition`.
+///
+/// `frames` are the node's frame inputs in declaration order and `inputs` are
+/// its resolved (non-frame) input values, keyed by input name. Missing inputs
+/// use the same defaults as the node's shader.
+pub fn execute(
+    definition: &NodeDefinition,
+    frames: &[&Frame],
+    inputs: &HashMap<String, NodeValue>,
+) -> Result<Frame, CpuBackendError> {
+    let kernel = CpuKernel::for_definition(definition)
+        .ok_or_else(|| CpuBackendError::Unsupported(definition.node.name.clone()))?;
+
+    if frames.len() < kernel.frame_inputs() {
+        return Err(CpuBackendError::MissingFrameInput {
+            node: definition.node.name.clone(),
+            expected: kernel.frame_inputs(),
+            actual: frames.len(),
+        });
+    }
+
+    let sampling = match cpu_sampling(inputs) {
+        Some(sampling) => sampling,
+        None if !kernel.samples() => CpuSampling::Nearest,
+        None => {
+            return Err(CpuBackendError::UnsupportedSampling {
+                node: definition.node.name.clone(),
+                sampling: sampling_quality(inputs),
+            });
+        }
+    };
+
+    let frame = match kernel {
+        CpuKernel::Invert => invert(frames[0]),
+        CpuKernel::Brightness => brightness(frames[0], float_input(inputs, "Brightness", 1.0)),
+        CpuKernel::Overlay => overlay(
+            frames[0],
+            frames[1],
+            float_input(inputs, "Opacity", 0.5),
+            sampling,
+        ),
+        CpuKernel::Rotate => rotate(
+            frames[0],
+            RotateParams {
+                border_mode: enum_input(inputs, "Border Mode", 2),
+                sampling,
+                angle: float_input(inputs, "Angle", 0.0),
+                center_x: float_input(inputs, "Center X", 0.5),
+                center_y: float_input(inputs, "Center Y", 0.5),
+            },
+        ),
+    };
+
+    Ok(frame)
+}
+
+fn float_input(inputs: &HashMap<String, NodeValue>, name: &str, default: f32) -> f32 {
+    match inputs.get(name) {
+        Some(NodeValue::Float(f)) => *f,
+        Some(NodeValue::Int(i)) => *i as f32,
+        _ => default,
+    }
+}
+
+fn enum_input(inputs: &HashMap<String, NodeValue>, name: &str, default: usize) -> usize {
+    match inputs.get(name) {
+        Some(NodeValue::Enum(idx)) => *idx,
+        _ => default,
+    }
+}
+
+/// The `Sampling` input's quality, defaulting like the shader pipelines do.
+fn sampling_quality(inputs: &HashMap<String, NodeValue>) -> SamplingQuality {
+    match inputs.get(SAMPLING_INPUT_NAME) {
+        Some(NodeValue::Enum(index)) => SamplingQuality::from_choice_index(*index),
+        _ => SamplingQuality::default(),
+    }
+}
+
+/// The CPU sampling matching the `Sampling` input, or [None] if only the GPU
+/// can sample that way.
+fn cpu_sampling(inputs: &HashMap<String, NodeValue>) -> Option<CpuSampling> {
+    match sampling_quality(inputs) {
+        SamplingQuality::Nearest => Some(CpuSampling::Nearest),
+        SamplingQuality::Bilinear => Some(CpuSampling::Bilinear),
+        SamplingQuality::Trilinear | SamplingQuality::Anisotropic => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CpuSampling {
+    Nearest,
+    Bilinear,
+}
+
+fn map_pixels(frame: &Frame, mut f: impl FnMut(Pixel) -> Pixel) -> Frame {
+    let pixels = frame.pixels();
+    let width = frame.dimensions().width() as usize;
+    Frame::from_fill_with_coords(frame.dimensions(), |row, col| f(pixels[row * width + col]))
+}
+
+fn invert(frame: &Frame) -> Frame {
+    map_pixels(frame, |p| {
+        Pixel::from_rgba(255 - p.red(), 255 - p.green(), 255 - p.blue(), p.alpha())
+    })
+}
+
+fn brightness(frame: &Frame, factor: f32) -> Frame {
+    let scale = |channel: u8| (channel as f32 * factor).round().clamp(0.0, 255.0) as u8;
+    map_pixels(frame, |p| {
+        Pixel::from_rgba(scale(p.red()), scale(p.green()), scale(p.blue()), p.alpha())
+    })
+}
+
+/// Mixes `foreground` over `background`. Like the shader, the foreground is
+/// sampled at the same normalized coordinates so the frames don't need to be
+/// the same size; the output has the background's dimensions.
+fn overlay(background: &Frame, foreground: &Frame, opacity: f32, sampling: CpuSampling) -> Frame {
+    let opacity = opacity.clamp(0.0, 1.0);
+    let width = background.dimensions().width() as usize;
+    let height = background.dimensions().height() as usize;
+    let bg_pixels = background.pixels();
+
+    Frame::from_fill_with_coords(background.dimensions(), |row, col| {
+        let bg = bg_pixels[row * width + col];
+        let fg = sample(
+            foreground,
+            (col as f32 + 0.5) / width as f32,
+            (row as f32 + 0.5) / height as f32,
+            sampling,
+        );
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * opacity).round() as u8;
+        Pixel::from_rgba(
+            mix(bg.red(), fg.red()),
+            mix(bg.green(), fg.green()),
+            mix(bg.blue(), fg.blue()),
+            mix(bg.alpha(), fg.alpha()),
+        )
+    })
+}
+
+struct RotateParams {
+    /// 0 = clamp, 1 = wrap, 2 = transparent (same as the shader).
+    border_mode: usize,
+    sampling: CpuSampling,
+    angle: f32,
+    center_x: f32,
+    center_y: f32,
+}
+
+fn rotate(frame: &Frame, params: RotateParams) -> Frame {
+    let width = frame.dimensions().width() as f32;
+    let height = frame.dimensions().height() as f32;
+    let (sin, cos) = params.angle.sin_cos();
+
+    Frame::from_fill_with_coords(frame.dimensions(), |row, col| {
+        let x = (col as f32 + 0.5) / width - params.center_x;
+        let y = (row as f32 + 0.5) / height - params.center_y;
+        let u = x * cos - y * sin + params.center_x;
+        let v = x * sin + y * cos + params.center_y;
+
+        match params.border_mode {
+            0 => sample(frame, u.clamp(0.0, 1.0), v.clamp(0.0, 1.0), params.sampling),
+            1 => sample(frame, u.rem_euclid(1.0), v.rem_euclid(1.0), params.sampling),
+            _ if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) => {
+                Pixel::from_rgba(0, 0, 0, 0)
+            }
+            _ => sample(frame, u, v, params.sampling),
+        }
+    })
+}
+
+/// Sample at normalized coordinates (clamped to the edges).
+fn sample(frame: &Frame, u: f32, v: f32, sampling: CpuSampling) -> Pixel {
+    match sampling {
+        CpuSampling::Nearest => sample_nearest(frame, u, v),
+        CpuSampling::Bilinear => sample_bilinear(frame, u, v),
+    }
+}
+
+/// Nearest-neighbor sample at normalized coordinates (clamped to the edges).
+fn sample_nearest(frame: &Frame, u: f32, v: f32) -> Pixel {
+    let width = frame.dimensions().width() as usize;
+    let height = frame.dimensions().height() as usize;
+    let col = ((u * width as f32) as usize).min(width - 1);
+    let row = ((v * height as f32) as usize).min(height - 1);
+    frame.pixels()[row * width + col]
+}
+
+/// Bilinear sample at normalized coordinates (clamped to the edges), weighting
+/// the four pixels around the point like a GPU's linear filter.
+fn sample_bilinear(frame: &Frame, u: f32, v: f32) -> Pixel {
+    let width = frame.dimensions().width() as usize;
+    let height = frame.dimensions().height() as usize;
+    let pixels = frame.pixels();
+
+    let x = u * width as f32 - 0.5;
+    let y = v * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let col = |offset: f32| ((x0 + offset).max(0.0) as usize).min(width - 1);
+    let row = |offset: f32| ((y0 + offset).max(0.0) as usize).min(height - 1);
+
+    let top_left = pixels[row(0.0) * width + col(0.0)];
+    let top_right = pixels[row(0.0) * width + col(1.0)];
+    let bottom_left = pixels[row(1.0) * width + col(0.0)];
+    let bottom_right = pixels[row(1.0) * width + col(1.0)];
+
+    let mix = |channel: fn(&Pixel) -> u8| {
+        let lerp = |a: u8, b: u8, t: f32| a as f32 + (b as f32 - a as f32) * t;
+        let top = lerp(channel(&top_left), channel(&top_right), fx);
+        let bottom = lerp(channel(&bottom_left), channel(&bottom_right), fx);
+        (top + (bottom - top) * fy).round().clamp(0.0, 255.0) as u8
+    };
+    Pixel::from_rgba(
+        mix(Pixel::red),
+        mix(Pixel::green),
+        mix(Pixel::blue),
+        mix(Pixel::alpha),
+    )
+}
+
+#[cfg(test)]
+mod decision_coverage_tests {
+    use super::*;
+    use media::frame::Dimensions;
+
+    fn frame(width: u32, height: u32, pixel: Pixel) -> Frame {
+        Frame::from_fill(Dimensions::new(width, height).unwrap(), pixel)
+    }
+
+    // --- invert ---
+
+    #[test]
+    fn invert_keeps_alpha() {
+        let out = invert(&frame(2, 2, Pixel::from_rgba(10, 20, 30, 40)));
+        assert!(
+            out.pixels()
+                .iter()
+                .all(|p| *p == Pixel::from_rgba(245, 235, 225, 40))
+        );
+    }
+
+    // --- brightness ---
+
+    #[test]
+    fn brightness_scales_and_clamps() {
+        let out = brightness(&frame(1, 1, Pixel::from_rgba(100, 200, 0, 255)), 2.0);
+        assert_eq!(out.pixels()[0], Pixel::from_rgba(200, 255, 0, 255));
+    }
+
+    // --- overlay ---
+
+    #[test]
+    fn overlay_mixes_by_opacity() {
+        let bg = frame(2, 2, Pixel::from_rgba(0, 0, 0, 255));
+        let fg = frame(4, 4, Pixel::from_rgba(200, 100, 50, 255));
+        let out = overlay(&bg, &fg, 0.5, CpuSampling::Nearest);
+        assert_eq!(out.dimensions(), bg.dimensions());
+        assert_eq!(out.pixels()[0], Pixel::from_rgba(100, 50, 25, 255));
+    }
+
+    // --- rotate ---
+
+    #[test]
+    fn rotate_zero_angle_is_identity() {
+        let src = Frame::from_fill_with_coords(Dimensions::new(3, 2).unwrap(), |row, col| {
+            Pixel::from_rgba(row as u8, col as u8, 0, 255)
+        });
+        let out = rotate(
+            &src,
+            RotateParams {
+                border_mode: 2,
+                sampling: CpuSampling::Nearest,
+                angle: 0.0,
+                center_x: 0.5,
+                center_y: 0.5,
+            },
+        );
+        assert_eq!(out.pixels(), src.pixels());
+    }
+
+    #[test]
+    fn rotate_transparent_border_outside_frame() {
+        let src = frame(4, 4, Pixel::from_rgba(255, 255, 255, 255));
+        let out = rotate(
+            &src,
+            RotateParams {
+                border_mode: 2,
+                sampling: CpuSampling::Nearest,
+                angle: 0.0,
+                center_x: 0.0,
+                center_y: 0.0,
+            },
+        );
+        assert_eq!(out.pixels(), src.pixels());
+
+        let shifted = rotate(
+            &src,
+            RotateParams {
+                border_mode: 2,
+                sampling: CpuSampling::Nearest,
+                angle: std::f32::consts::FRAC_PI_4,
+                center_x: 0.0,
+                center_y: 0.0,
+            },
+        );
+        // The bottom-left corner rotates out of the frame.
+        assert_eq!(shifted.pixels()[12], Pixel::from_rgba(0, 0, 0, 0));
+    }
+
+    // --- sample_bilinear ---
+
+    #[test]
+    fn bilinear_blends_neighbors_and_clamps_at_edges() {
+        let src = Frame::from_fill_with_coords(Dimensions::new(2, 1).unwrap(), |_, col| {
+            let value = if col == 0 { 0 } else { 200 };
+            Pixel::from_rgba(value, value, value, 255)
+        });
+        assert_eq!(
+            sample_bilinear(&src, 0.5, 0.5),
+            Pixel::from_rgba(100, 100, 100, 255)
+        );
+        assert_eq!(sample_bilinear(&src, 0.0, 0.5), src.pixels()[0]);
+        assert_eq!(sample_bilinear(&src, 1.0, 0.5), src.pixels()[1]);
+    }
+
+    // --- cpu_sampling ---
+
+    #[test]
+    fn only_nearest_and_bilinear_run_on_cpu() {
+        let mut inputs = HashMap::new();
+        assert_eq!(cpu_sampling(&inputs), None, "the default is trilinear");
+
+        inputs.insert(SAMPLING_INPUT_NAME.to_string(), NodeValue::Enum(0));
+        assert_eq!(cpu_sampling(&inputs), Some(CpuSampling::Nearest));
+        inputs.insert(SAMPLING_INPUT_NAME.to_string(), NodeValue::Enum(1));
+        assert_eq!(cpu_sampling(&inputs), Some(CpuSampling::Bilinear));
+        inputs.insert(SAMPLING_INPUT_NAME.to_string(), NodeValue::Enum(3));
+        assert_eq!(cpu_sampling(&inputs), None);
+    }
+
+    // --- float_input / enum_input ---
+
+    #[test]
+    fn inputs_fall_back_to_defaults() {
+        let mut inputs = HashMap::new();
+        inputs.insert("Angle".to_string(), NodeValue::Int(2));
+        inputs.insert("Border Mode".to_string(), NodeValue::Float(1.0));
+        assert_eq!(float_input(&inputs, "Angle", 0.0), 2.0);
+        assert_eq!(float_input(&inputs, "Missing", 0.5), 0.5);
+        assert_eq!(enum_input(&inputs, "Border Mode", 2), 2);
+    }
+}
@@ -0,0 +1,272 @@
+//! Exports [DepthEstimator] and its backends, which guess how far away each
+//! part of a frame is from a single image (see the Depth Estimate node).
+//!
+//! [HeuristicDepthEstimator] needs no model: it assumes the bottom of the frame
+//! is nearer than the top and that near things are more detailed and saturated
+//! than far ones. That's only a rough stand-in, good enough for gentle fog or
+//! parallax. When the crate is built with the `onnx` feature, [load_estimator]
+//! can instead load a monocular depth model (MiDaS, Depth Anything, and other
+//! models that output relative inverse depth) and run it with ONNX Runtime.
+
+#[cfg(feature = "onnx")]
+mod onnx_depth;
+
+use std::path::{Path, PathBuf};
+
+use media::frame::{Dimensions, Frame, Pixel};
+
+/// How the [HeuristicDepthEstimator]'s cues are weighted.
+const HEIGHT_WEIGHT: f32 = 0.55;
+const DETAIL_WEIGHT: f32 = 0.3;
+const SATURATION_WEIGHT: f32 = 0.15;
+
+/// How far [HeuristicDepthEstimator] spreads its cues, as a fraction of the
+/// frame's longer side.
+const HEURISTIC_BLUR: f32 = 1.0 / 24.0;
+
+/// How near each pixel of a frame is, from `0.0` (the farthest point) to `1.0`
+/// (the nearest).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthMap {
+    dimensions: Dimensions,
+    values: Vec<f32>,
+}
+
+impl DepthMap {
+    /// A map from a model's raw output, where larger values are nearer but
+    /// have no particular scale (as with inverse depth). The values are
+    /// stretched to fill `0.0` to `1.0`.
+    ///
+    /// Returns [None] if there isn't one value per pixel.
+    pub fn from_relative(dimensions: Dimensions, mut values: Vec<f32>) -> Option<Self> {
+        if values.len() != dimensions.area() as usize {
+            return None;
+        }
+
+        let (min, max) = values
+            .iter()
+            .filter(|value| value.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &value| {
+                (min.min(value), max.max(value))
+            });
+        let range = max - min;
+        for value in &mut values {
+            *value = if range > f32::EPSILON && value.is_finite() {
+                (*value - min) / range
+            } else {
+                0.0
+            };
+        }
+        Some(Self { dimensions, values })
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Move this map `amount` (`0.0` to `1.0`) of the way back towards
+    /// `previous`, to steady depth that flickers between frames. Does nothing
+    /// if the maps are different sizes.
+    pub fn smooth_towards(&mut self, previous: &DepthMap, amount: f32) {
+        if previous.dimensions != self.dimensions {
+            return;
+        }
+        let amount = amount.clamp(0.0, 1.0);
+        for (value, previous) in self.values.iter_mut().zip(&previous.values) {
+            *value += (previous - *value) * amount;
+        }
+    }
+
+    /// The map as a grayscale frame, white where it's nearest (or farthest if
+    /// `invert` is set).
+    pub fn to_frame(&self, invert: bool) -> Frame {
+        let pixels = self
+            .values
+            .iter()
+            .map(|&value| {
+                let value = if invert { 1.0 - value } else { value };
+                let level = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                Pixel::from_rgb(level, level, level)
+            })
+            .collect();
+        Frame::from_pixels(pixels, self.dimensions).expect("one pixel per value")
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DepthEstimatorError {
+    #[error("depth estimation models need a build with the 'onnx' feature")]
+    ModelsUnsupported,
+    #[error("failed to load the depth estimation model '{path}': {message}")]
+    Load { path: PathBuf, message: String },
+    #[error("depth estimation failed: {0}")]
+    Inference(String),
+}
+
+/// Estimates depth from single frames.
+pub trait DepthEstimator: Send {
+    /// The depth of `frame`. The map may be a different size than the frame
+    /// (e.g. a model's fixed output size), but covers all of it.
+    fn estimate(&mut self, frame: &Frame) -> Result<DepthMap, DepthEstimatorError>;
+}
+
+/// Load the model at `model_path`, or create a [HeuristicDepthEstimator] if
+/// it's empty.
+pub fn load_estimator(model_path: &Path) -> Result<Box<dyn DepthEstimator>, DepthEstimatorError> {
+    if model_path.as_os_str().is_empty() {
+        return Ok(Box::new(HeuristicDepthEstimator));
+    }
+    load_model(model_path)
+}
+
+#[cfg(feature = "onnx")]
+fn load_model(model_path: &Path) -> Result<Box<dyn DepthEstimator>, DepthEstimatorError> {
+    Ok(Box::new(onnx_depth::OnnxDepthEstimator::load(model_path)?))
+}
+
+#[cfg(not(feature = "onnx"))]
+fn load_model(_model_path: &Path) -> Result<Box<dyn DepthEstimator>, DepthEstimatorError> {
+    Err(DepthEstimatorError::ModelsUnsupported)
+}
+
+/// Guesses depth from where things are in the frame and how they look: lower,
+/// more detailed, and more saturated parts are treated as nearer. It knows
+/// nothing about objects, so e.g. a plain wall at the bottom of the frame comes
+/// out near however far away it is, and busy clouds come out too close.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicDepthEstimator;
+
+impl DepthEstimator for HeuristicDepthEstimator {
+    fn estimate(&mut self, frame: &Frame) -> Result<DepthMap, DepthEstimatorError> {
+        let dimensions = frame.dimensions();
+        let width = dimensions.width() as usize;
+        let height = dimensions.height() as usize;
+        let radius = ((width.max(height) as f32 * HEURISTIC_BLUR).round() as usize).max(1);
+
+        let luma: Vec<f32> = frame
+            .pixels()
+            .iter()
+            .map(|pixel| {
+                0.2126 * pixel.red_normalized() as f32
+                    + 0.7152 * pixel.green_normalized() as f32
+                    + 0.0722 * pixel.blue_normalized() as f32
+            })
+            .collect();
+
+        // Local contrast: how much each pixel differs from its right and lower
+        // neighbors, spread out so it covers whole objects rather than edges.
+        let mut detail = vec![0.0; luma.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                let right = luma[if x + 1 < width { index + 1 } else { index }];
+                let below = luma[if y + 1 < height { index + width } else { index }];
+                detail[index] = (luma[index] - right).abs() + (luma[index] - below).abs();
+            }
+        }
+        box_blur(&mut detail, width, height, radius);
+        let max_detail = detail.iter().copied().fold(0.0f32, f32::max);
+
+        let mut nearness: Vec<f32> = frame
+            .pixels()
+            .iter()
+            .zip(&detail)
+            .enumerate()
+            .map(|(index, (pixel, &detail))| {
+                let channels = [pixel.red(), pixel.green(), pixel.blue()];
+                let max = *channels.iter().max().expect("three channels") as f32;
+                let min = *channels.iter().min().expect("three channels") as f32;
+                let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+                let detail = if max_detail > 0.0 {
+                    detail / max_detail
+                } else {
+                    0.0
+                };
+                let row = (index / width) as f32 / (height.max(2) - 1) as f32;
+
+                HEIGHT_WEIGHT * row + DETAIL_WEIGHT * detail + SATURATION_WEIGHT * saturation
+            })
+            .collect();
+        box_blur(&mut nearness, width, height, radius);
+
+        Ok(DepthMap::from_relative(dimensions, nearness).expect("one value per pixel"))
+    }
+}
+
+/// Blur `values` (`width` by `height`, row by row) in place with a box
+/// `radius` pixels out from its center, shrinking the box at the edges.
+fn box_blur(values: &mut [f32], width: usize, height: usize, radius: usize) {
+    let mut line = Vec::new();
+    let mut sums = Vec::new();
+    for y in 0..height {
+        let row = y * width..(y + 1) * width;
+        line.clear();
+        line.extend_from_slice(&values[row.clone()]);
+        blur_line(&mut line, &mut sums, radius);
+        values[row].copy_from_slice(&line);
+    }
+    for x in 0..width {
+        line.clear();
+        line.extend((0..height).map(|y| values[y * width + x]));
+        blur_line(&mut line, &mut sums, radius);
+        for (y, value) in line.iter().enumerate() {
+            values[y * width + x] = *value;
+        }
+    }
+}
+
+/// Box blur one line in place, using `sums` for scratch space.
+fn blur_line(line: &mut [f32], sums: &mut Vec<f32>, radius: usize) {
+    sums.clear();
+    sums.push(0.0);
+    let mut total = 0.0;
+    for value in line.iter() {
+        total += value;
+        sums.push(total);
+    }
+
+    let len = line.len();
+    for (i, value) in line.iter_mut().enumerate() {
+        let start = i.saturating_sub(radius);
+        let end = (i + radius + 1).min(len);
+        *value = (sums[end] - sums[start]) / (end - start) as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- DepthMap::from_relative() ---
+
+    #[test]
+    fn test_from_relative() {
+        let dimensions = Dimensions::new(2, 2).unwrap();
+        let map = DepthMap::from_relative(dimensions, vec![10.0, 20.0, 30.0, f32::NAN]).unwrap();
+        assert_eq!(map.values(), &[0.0, 0.5, 1.0, 0.0]);
+
+        let flat = DepthMap::from_relative(dimensions, vec![3.0; 4]).unwrap();
+        assert_eq!(flat.values(), &[0.0; 4]);
+
+        assert!(DepthMap::from_relative(dimensions, vec![0.0; 3]).is_none());
+    }
+
+    // --- HeuristicDepthEstimator::estimate() ---
+
+    #[test]
+    fn test_heuristic_depth_bottom_is_nearer() {
+        let frame = Frame::from_fill(
+            Dimensions::new(32, 24).unwrap(),
+            Pixel::from_rgb(90, 120, 160),
+        );
+        let map = HeuristicDepthEstimator.estimate(&frame).unwrap();
+        let values = map.values();
+        assert_eq!(values.len(), 32 * 24);
+        assert!(values[23 * 32 + 16] > values[12 * 32 + 16]);
+        assert!(values[12 * 32 + 16] > values[16]);
+    }
+}
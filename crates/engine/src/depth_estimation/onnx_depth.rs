@@ -0,0 +1,136 @@
+//! Runs monocular depth models with ONNX Runtime.
+//!
+//! Models are expected to take one RGB image normalized with ImageNet's mean
+//! and standard deviation (as MiDaS and Depth Anything do), NCHW or NHWC, and
+//! output one map of relative inverse depth (larger is nearer). Models with a
+//! fixed input size get frames stretched to it; others get frames at their own
+//! size, rounded to a multiple of [SIZE_MULTIPLE].
+
+use std::path::Path;
+
+use media::frame::{Dimensions, Frame, RescaleMethod};
+use ort::session::Session;
+use ort::value::Tensor;
+
+use super::{DepthEstimator, DepthEstimatorError, DepthMap};
+
+const IMAGENET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const IMAGENET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+/// What models that take any input size need each side to be a multiple of
+/// (the total stride of their encoders).
+const SIZE_MULTIPLE: u32 = 32;
+
+pub struct OnnxDepthEstimator {
+    session: Session,
+    /// Whether the model takes NCHW input rather than NHWC.
+    channels_first: bool,
+    /// The input size the model needs, if it's fixed.
+    input_dimensions: Option<Dimensions>,
+}
+
+impl OnnxDepthEstimator {
+    pub fn load(path: &Path) -> Result<Self, DepthEstimatorError> {
+        let load_error = |message: String| DepthEstimatorError::Load {
+            path: path.to_path_buf(),
+            message,
+        };
+        let session = Session::builder()
+            .and_then(|mut builder| builder.commit_from_file(path))
+            .map_err(|e| load_error(e.to_string()))?;
+        let input_shape = session
+            .inputs()
+            .first()
+            .and_then(|input| input.dtype().tensor_shape())
+            .ok_or_else(|| load_error("the model has no tensor input".to_string()))?;
+        let (channels_first, height, width) = match **input_shape {
+            [_, 3, height, width] => (true, height, width),
+            [_, height, width, 3] => (false, height, width),
+            _ => {
+                return Err(load_error(format!(
+                    "the model takes {:?} input, not an RGB image",
+                    &**input_shape
+                )));
+            }
+        };
+        let input_dimensions = u32::try_from(width)
+            .ok()
+            .zip(u32::try_from(height).ok())
+            .and_then(|(width, height)| Dimensions::new(width, height));
+
+        Ok(Self {
+            session,
+            channels_first,
+            input_dimensions,
+        })
+    }
+}
+
+impl DepthEstimator for OnnxDepthEstimator {
+    fn estimate(&mut self, frame: &Frame) -> Result<DepthMap, DepthEstimatorError> {
+        let inference_error = |e: ort::Error| DepthEstimatorError::Inference(e.to_string());
+
+        let dimensions = self.input_dimensions.unwrap_or_else(|| {
+            let round =
+                |side: u32| (side.div_ceil(SIZE_MULTIPLE) * SIZE_MULTIPLE).max(SIZE_MULTIPLE);
+            Dimensions::new(
+                round(frame.dimensions().width()),
+                round(frame.dimensions().height()),
+            )
+            .expect("non-zero size")
+        });
+        let input_frame = if frame.dimensions() == dimensions {
+            frame.clone()
+        } else {
+            frame.rescale(dimensions, RescaleMethod::Bilinear)
+        };
+
+        let area = dimensions.area() as usize;
+        let mut data = vec![0.0f32; area * 3];
+        for (i, pixel) in input_frame.pixels().iter().enumerate() {
+            for (channel, value) in [pixel.red(), pixel.green(), pixel.blue()]
+                .into_iter()
+                .enumerate()
+            {
+                let index = if self.channels_first {
+                    channel * area + i
+                } else {
+                    i * 3 + channel
+                };
+                data[index] =
+                    (value as f32 / 255.0 - IMAGENET_MEAN[channel]) / IMAGENET_STD[channel];
+            }
+        }
+        let (width, height) = (dimensions.width() as usize, dimensions.height() as usize);
+        let shape = if self.channels_first {
+            [1, 3, height, width]
+        } else {
+            [1, height, width, 3]
+        };
+        let input = Tensor::from_array((shape, data)).map_err(inference_error)?;
+        let outputs = self
+            .session
+            .run(ort::inputs![input])
+            .map_err(inference_error)?;
+
+        let (shape, values) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(inference_error)?;
+        // Maps come out as [1, height, width] or [1, 1, height, width].
+        let output_dimensions = match **shape {
+            [.., height, width] => u32::try_from(width)
+                .ok()
+                .zip(u32::try_from(height).ok())
+                .and_then(|(width, height)| Dimensions::new(width, height)),
+            _ => None,
+        };
+        output_dimensions
+            .and_then(|dimensions| DepthMap::from_relative(dimensions, values.to_vec()))
+            .ok_or_else(|| {
+                DepthEstimatorError::Inference(format!(
+                    "the model output a {:?} tensor, not a depth map",
+                    &**shape
+                ))
+            })
+    }
+}
@@ -0,0 +1,64 @@
+//! Adds GPU and node library information to a [DiagnosticsReport].
+
+use util::diagnostics::DiagnosticsReport;
+use util::local_data;
+
+use crate::node::NodeLibrary;
+
+/// Add what's known about the GPU adapter the engine runs on: what it is, the
+/// driver, and the features and limits of `device`.
+pub fn add_gpu_info(
+    report: &mut DiagnosticsReport,
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+) {
+    let info = adapter.get_info();
+    report
+        .add("GPU", "Name", &info.name)
+        .add("GPU", "Type", format!("{:?}", info.device_type))
+        .add("GPU", "Backend", info.backend)
+        .add("GPU", "Vendor ID", format!("{:#06x}", info.vendor))
+        .add("GPU", "Device ID", format!("{:#06x}", info.device))
+        .add(
+            "GPU",
+            "Driver",
+            format!("{} ({})", info.driver, info.driver_info),
+        );
+
+    let limits = device.limits();
+    report
+        .add("GPU", "Max Texture Size", limits.max_texture_dimension_2d)
+        .add("GPU", "Max Buffer Size", limits.max_buffer_size)
+        .add("GPU", "Max Bind Groups", limits.max_bind_groups)
+        .add(
+            "GPU",
+            "Max Storage Buffer Binding",
+            limits.max_storage_buffer_binding_size,
+        )
+        .add(
+            "GPU",
+            "Max Workgroup Invocations",
+            limits.max_compute_invocations_per_workgroup,
+        )
+        .add("GPU", "Features", format!("{:?}", device.features()));
+}
+
+/// Add how many nodes `library` has (and how many are the user's), plus its
+/// [content hash](NodeLibrary::content_hash) so reports can tell whether two
+/// installs have the same nodes.
+pub fn add_node_library_info(report: &mut DiagnosticsReport, library: &NodeLibrary) {
+    let user_nodes_path = local_data::nodes_path();
+    let user_nodes = library
+        .definitions()
+        .values()
+        .filter(|definition| definition.folder_path.starts_with(user_nodes_path))
+        .count();
+    report
+        .add("Node Library", "Nodes", library.definitions().len())
+        .add("Node Library", "User Nodes", user_nodes)
+        .add(
+            "Node Library",
+            "Hash",
+            format!("{:016x}", library.content_hash()),
+        );
+}
@@ -20,6 +20,9 @@ pub enum EngineError {
     #[error("Upload failed: data size mismatch (expected {expected} bytes, got {actual} bytes)")]
     DataSizeMismatch { expected: usize, actual: usize },
 
+    #[error("GPU readback failed: {0}")]
+    ReadbackFailed(String),
+
     // Surface errors
     #[error("Failed to acquire swap chain texture: {0}")]
     SwapChainAcquireFailed(String),
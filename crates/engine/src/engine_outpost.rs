@@ -7,33 +7,91 @@
 //!
 //! Graph changes refresh execution state, but frame cadence stays driven by the
 //! engine timer so parameter edits do not speed up playback.
+//!
+//! The engine thread checks in with the [global](Watchdog::global)
+//! [`Watchdog`] every loop iteration, and so do media stream workers for every
+//! frame they decode. If one stops checking in (e.g. a video decoder is stuck
+//! inside ffmpeg) an [`EngineOutpostEvent::WorkerStalled`] event is broadcast
+//! so the UI can tell the user which subsystem is stuck. A stalled stream is
+//! abandoned and recreated the next time its node runs. Worker threads that panic (the engine
+//! thread itself, media stream workers, stream loaders) are caught with
+//! [util::panic_capture] and reported with an
+//! [`EngineOutpostEvent::WorkerPanicked`] event.
+//!
+//! Playback can also be driven with the versioned [protocol], whose requests
+//! are acknowledged once the engine thread has handled them.
+//!
+//! Node outputs flagged with `publish` are sent on a separate
+//! [`AnalysisBus`] after every frame. Unlike events, these values are
+//! throttled and coalesced per subscriber so the UI can plot them live without
+//! falling behind. Recorders can also receive every frame's values, tagged
+//! with the playback position, to export them.
+//!
+//! While the user is idle the app can have the engine render the output's
+//! frames in the background (see [`EngineCommand::SetBackgroundRendering`]).
+//! They're kept in a [render_cache::RenderCache], and seeking to one of them
+//! shows it without running the graph.
 
+pub mod analysis;
 pub mod broadcast;
 pub mod command_sender;
 pub mod message;
+pub mod protocol;
+mod render_cache;
 
-use std::sync::Arc;
-use std::thread;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
+use image::{ImageBuffer, ImageFormat, Rgba};
 use media::fps::Fps;
-use media::fps::SwitchTimer;
 use media::fps::consts::FPS_60;
+use media::fps::{PreviewRate, SwitchTimer};
 use util::channels::ChannelResult;
 use util::channels::message_channel::{self, Inbox, Outbox};
+use util::panic_capture::{self, PanicSubscription};
+use util::watchdog::{Watchdog, WatchdogHandle, WatchdogMonitor};
 
 use super::graph_executor::{ExecutionError, GraphExecutor, NodeValue};
-use crate::node::NodeLibrary;
-use crate::node_graph::NodeGraph;
+use crate::execution_trace::{ExecutionTrace, TraceFrame, TraceNode};
+use crate::gpu_frame::GpuFrame;
+use crate::node::{NodeInputKind, NodeLibrary};
+use crate::node_graph::{EngineNodeId, GraphEdit, GraphError, InputValue, NodeGraph};
+use crate::pipeline_cache::DiskPipelineCache;
 
+pub use analysis::{
+    AnalysisBus, AnalysisFrame, AnalysisKey, AnalysisReceiver, AnalysisRecordReceiver,
+    AnalysisSample, AnalysisValue,
+};
 pub use broadcast::{EngineEventReceiver, EventBroadcaster, EventFilter, EventKind};
 pub use command_sender::EngineCommandSender;
 pub use message::{EngineCommand, EngineOutpostEvent};
+pub use protocol::{
+    PROTOCOL_VERSION, PendingPlayback, PlaybackAck, PlaybackClient, PlaybackError, PlaybackRequest,
+    PlaybackResult,
+};
+
+use protocol::{PlaybackServer, VersionedRequest};
+use render_cache::RenderCache;
 
 /// How long the engine thread blocks waiting for commands while paused.
 /// Long enough to not burn CPU, short enough to stay responsive to play/unpause.
 const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
+/// How long the engine thread can go without finishing a loop iteration before
+/// it's reported as stalled. Generous enough to cover the slowest frame rates
+/// and first-frame decoder startup.
+const STALL_DEADLINE: Duration = Duration::from_secs(5);
+
+/// How often the watchdog monitor checks for stalled workers.
+const WATCHDOG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The subsystem name reported when the engine thread stalls or panics.
+const ENGINE_WORKER_NAME: &str = "Render engine";
+
 /// A cheaply cloneable handle to the engine thread.
 ///
 /// Cloning produces another handle that shares the same underlying channels,
@@ -41,7 +99,12 @@ const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(50);
 #[derive(Clone)]
 pub struct EngineOutpostHandle {
     command_tx: Arc<Outbox<EngineCommand>>,
+    playback_client: PlaybackClient,
     broadcaster: Arc<EventBroadcaster>,
+    analysis_bus: Arc<AnalysisBus>,
+    thread: Arc<Mutex<Option<JoinHandle<()>>>>,
+    _watchdog_monitor: Arc<WatchdogMonitor>,
+    _panic_subscription: Arc<PanicSubscription>,
 }
 
 impl EngineOutpostHandle {
@@ -49,15 +112,54 @@ impl EngineOutpostHandle {
         EngineCommandSender::new(self.command_tx.clone())
     }
 
+    /// A handle for sending acknowledged [PlaybackRequest]s to the engine
+    /// thread (see [protocol]).
+    pub fn playback_client(&self) -> PlaybackClient {
+        self.playback_client.clone()
+    }
+
     pub fn subscribe(&self, filter: EventFilter) -> EngineEventReceiver {
         self.broadcaster.subscribe(filter)
     }
 
+    /// Subscribe to the values of node outputs flagged with `publish`. The
+    /// receiver picks up values at most once every `min_interval`, only
+    /// keeping the newest value of each output in between.
+    pub fn subscribe_analysis(&self, min_interval: Duration) -> AnalysisReceiver {
+        self.analysis_bus.subscribe(min_interval)
+    }
+
+    /// Receive the values of node outputs flagged with `publish` for every
+    /// frame, along with the playback position. Used to export values.
+    pub fn record_analysis(&self) -> AnalysisRecordReceiver {
+        self.analysis_bus.record()
+    }
+
     // send_command can now just delegate, or you can remove it
     // and require callers to go through command_sender() explicitly
     pub fn send_command(&self, command: EngineCommand) -> ChannelResult<usize, EngineCommand> {
         self.command_tx.send(command)
     }
+
+    /// Ask the engine thread to stop and block until it has exited, dropping
+    /// the graph executor (and with it every media stream worker).
+    ///
+    /// Only the first call waits; later calls (from any clone) return
+    /// immediately.
+    pub fn shutdown(&self) {
+        _ = self.command_tx.send(EngineCommand::Shutdown);
+
+        let thread = self
+            .thread
+            .lock()
+            .expect("engine thread lock poisoned")
+            .take();
+        if let Some(thread) = thread
+            && thread.join().is_err()
+        {
+            util::debug_log_warning!("Engine thread panicked before shutting down.");
+        }
+    }
 }
 
 /// Spawn the engine thread and return a handle to it.
@@ -71,20 +173,56 @@ pub fn spawn(
     format: wgpu::TextureFormat,
 ) -> EngineOutpostHandle {
     let (command_rx, command_tx) = message_channel::new();
+    let (playback_rx, playback_client) = protocol::new();
     let broadcaster = Arc::new(EventBroadcaster::new());
 
+    let watchdog = Watchdog::global().clone();
+    let broadcaster_monitor = broadcaster.clone();
+    let watchdog_monitor = watchdog.spawn_monitor(WATCHDOG_POLL_INTERVAL, move |stalled| {
+        broadcaster_monitor.broadcast(EngineOutpostEvent::WorkerStalled(stalled.name));
+    });
+
+    let broadcaster_panics = broadcaster.clone();
+    let panic_subscription = panic_capture::subscribe(move |worker_panic| {
+        broadcaster_panics.broadcast(EngineOutpostEvent::WorkerPanicked {
+            subsystem: worker_panic.subsystem.clone(),
+            message: worker_panic.message.clone(),
+            backtrace: worker_panic.backtrace.clone(),
+        });
+    });
+
+    let analysis_bus = Arc::new(AnalysisBus::new());
+
     let broadcaster_inner = broadcaster.clone();
-    thread::Builder::new()
+    let analysis_bus_inner = analysis_bus.clone();
+    let thread = thread::Builder::new()
         .name("engine-outpost".into())
         .spawn(move || {
-            EngineOutpostInner::new(device, queue, library, broadcaster_inner, format)
-                .run(command_rx);
+            let watchdog_handle = watchdog.register(ENGINE_WORKER_NAME, STALL_DEADLINE);
+            // The engine can't be restarted, but at least the UI hears why it
+            // stopped.
+            _ = panic_capture::catch(ENGINE_WORKER_NAME, || {
+                EngineOutpostInner::new(
+                    device,
+                    queue,
+                    library,
+                    broadcaster_inner,
+                    analysis_bus_inner,
+                    format,
+                )
+                .run(command_rx, playback_rx, watchdog_handle);
+            });
         })
         .expect("failed to spawn engine-outpost thread");
 
     EngineOutpostHandle {
         command_tx: Arc::new(command_tx),
+        playback_client,
         broadcaster,
+        analysis_bus,
+        thread: Arc::new(Mutex::new(Some(thread))),
+        _watchdog_monitor: Arc::new(watchdog_monitor),
+        _panic_subscription: Arc::new(panic_subscription),
     }
 }
 
@@ -95,12 +233,40 @@ struct EngineOutpostInner {
     device: Arc<wgpu::Device>,
     queue: Arc<wgpu::Queue>,
     broadcaster: Arc<EventBroadcaster>,
+    analysis_bus: Arc<AnalysisBus>,
     timer: SwitchTimer,
     paused: bool,
     output_node_id: Option<crate::node_graph::EngineNodeId>,
     /// When true, `try_apply_output_node_fps` is skipped and the timer runs at
     /// the manually-set rate from `SetGlobalStreamTargetFps`.
     manual_fps_locked: bool,
+    /// Set by `EngineCommand::Shutdown`; the run loop exits once it's seen.
+    shutdown_requested: bool,
+    /// The last position broadcast with `EngineOutpostEvent::PlaybackPosition`.
+    last_playback_position: Option<(usize, Fps)>,
+    /// The trace being recorded, if any (see `EngineCommand::RecordTrace`).
+    trace: Option<TraceRecording>,
+    /// The last peer count broadcast with `EngineOutpostEvent::LinkStatus`.
+    last_link_peers: Option<usize>,
+    /// The nodes last broadcast with `EngineOutpostEvent::ShadersCompiling`.
+    last_compiling_nodes: Vec<EngineNodeId>,
+    /// Set by `EngineCommand::SetExecutionOrderReporting`.
+    report_execution_order: bool,
+    /// Output frames rendered in the background, by frame index.
+    render_cache: RenderCache,
+    /// Set by `EngineCommand::SetBackgroundRendering`.
+    background_rendering: bool,
+    /// The frame the playhead was on before background rendering moved it, to
+    /// put it back once it's done (or interrupted).
+    background_home: Option<usize>,
+}
+
+/// A trace that's recorded until it has `frames` frames, then saved to
+/// `path`.
+struct TraceRecording {
+    trace: ExecutionTrace,
+    frames: usize,
+    path: PathBuf,
 }
 
 impl EngineOutpostInner {
@@ -109,25 +275,50 @@ impl EngineOutpostInner {
         queue: Arc<wgpu::Queue>,
         library: Arc<NodeLibrary>,
         broadcaster: Arc<EventBroadcaster>,
+        analysis_bus: Arc<AnalysisBus>,
         format: wgpu::TextureFormat,
     ) -> Self {
+        // Adding a heavy node shouldn't freeze the preview while it compiles.
+        let mut graph_executor = GraphExecutor::new(format);
+        graph_executor.set_async_pipeline_compilation(true);
+
         Self {
-            graph_executor: GraphExecutor::new(format),
+            graph_executor,
             graph: NodeGraph::default(),
             library,
             device,
             queue,
             broadcaster,
+            analysis_bus,
             timer: SwitchTimer::new(FPS_60),
             paused: false,
             output_node_id: None,
             manual_fps_locked: false,
+            shutdown_requested: false,
+            last_playback_position: None,
+            trace: None,
+            last_link_peers: None,
+            last_compiling_nodes: Vec::new(),
+            report_execution_order: false,
+            render_cache: RenderCache::new(),
+            background_rendering: false,
+            background_home: None,
         }
     }
 
-    fn run(mut self, command_rx: Inbox<EngineCommand>) {
+    fn run(
+        mut self,
+        command_rx: Inbox<EngineCommand>,
+        playback_rx: PlaybackServer,
+        watchdog_handle: WatchdogHandle,
+    ) {
         loop {
-            let timeout = if self.paused {
+            watchdog_handle.ping();
+
+            let timeout = if self.wants_background_render() {
+                // Render the next frame unless something needs handling.
+                Duration::ZERO
+            } else if self.paused {
                 PAUSED_POLL_INTERVAL
             } else {
                 self.timer.time_until_next_switch()
@@ -141,16 +332,57 @@ impl EngineOutpostInner {
                     }
                 }
                 Err(err) if err.is_wait_timeout_error() => {}
-                Err(_) => return,
+                Err(_) => break,
             }
 
-            if !self.paused && self.timer.is_switch_time() {
+            while let Ok(Some((request, response))) = playback_rx.check_non_blocking() {
+                let result = self.handle_playback_request(request);
+                if let Some(response) = response {
+                    _ = response.respond(result);
+                } else if let Err(e) = result {
+                    util::debug_log_warning!("Playback request failed: {e}");
+                }
+            }
+
+            if self.shutdown_requested {
+                break;
+            }
+
+            // Peers come and go while paused too.
+            self.broadcast_link_status();
+
+            // Show nodes as soon as their shaders are compiled, even while
+            // paused.
+            let compiling_while_paused =
+                self.paused && !self.graph_executor.compiling_nodes().is_empty();
+            let switch = if self.paused {
+                None
+            } else {
+                self.timer.next_switch()
+            };
+            if let Some(frames) = switch {
+                // Previewing below the output's FPS skips frames, so sources
+                // keep playing in real time.
+                if frames > 1 {
+                    self.graph_executor.skip_frames(frames - 1);
+                }
                 self.tick();
+            } else if compiling_while_paused {
+                self.tick();
+            } else if self.wants_background_render() {
+                self.render_in_background();
             }
         }
+
+        self.save_pipeline_cache();
     }
 
     fn handle_command(&mut self, command: EngineCommand) {
+        // Panels poll for info while the user is idle, which shouldn't restart
+        // background rendering.
+        if !matches!(command, EngineCommand::RequestInfo(_)) {
+            self.stop_background_render();
+        }
         match command {
             EngineCommand::PauseStreams => {
                 self.graph_executor.pause_streams();
@@ -166,12 +398,16 @@ impl EngineOutpostInner {
                     .broadcast(EngineOutpostEvent::StreamsPlaying);
             }
             EngineCommand::SetGlobalStreamTargetFps(fps) => {
+                self.clear_render_cache();
                 self.manual_fps_locked = true;
                 self.graph_executor.set_global_stream_target_fps(fps);
                 self.timer.set_target_fps(fps);
                 self.broadcaster
                     .broadcast(EngineOutpostEvent::GlobalStreamTargetFpsChanged(fps));
             }
+            EngineCommand::SetPreviewRate(preview_rate) => {
+                self.timer.set_preview_rate(preview_rate);
+            }
             EngineCommand::ClearManualFps => {
                 self.manual_fps_locked = false;
                 if let Some(node_id) = self.output_node_id {
@@ -179,6 +415,7 @@ impl EngineOutpostInner {
                 }
             }
             EngineCommand::SetOutputNode(node_id) => {
+                self.clear_render_cache();
                 self.output_node_id = node_id;
                 // Always tell the app what FPS the engine is running at when an
                 // output is set, even if the rate hasn't changed from the default.
@@ -197,11 +434,363 @@ impl EngineOutpostInner {
                         ));
                     }
                 }
+                message::InfoRequest::NodeTimings => {
+                    self.broadcaster.broadcast(EngineOutpostEvent::InfoResponse(
+                        message::InfoResponse::NodeTimings(
+                            self.graph_executor.last_node_timings().clone(),
+                        ),
+                    ));
+                }
+                message::InfoRequest::BufferingStats => {
+                    self.broadcaster.broadcast(EngineOutpostEvent::InfoResponse(
+                        message::InfoResponse::BufferingStats(
+                            self.graph_executor.buffering_stats(),
+                        ),
+                    ));
+                }
             },
             EngineCommand::UpdateGraph(new_graph) => {
+                // The last graph's pipelines have been compiled by now.
+                self.save_pipeline_cache();
                 self.graph_executor.invalidate_execution_order();
                 self.graph = new_graph;
+                self.clear_render_cache();
+            }
+            EngineCommand::EditGraph(edits) => {
+                if edits.iter().any(GraphEdit::changes_structure) {
+                    self.save_pipeline_cache();
+                    self.graph_executor.invalidate_execution_order();
+                }
+                if let Err(err) = self.graph.apply_edits(edits) {
+                    util::debug_log_warning!("Failed to edit the graph: {err}");
+                    self.broadcaster
+                        .broadcast(EngineOutpostEvent::GraphEditFailed(err.to_string()));
+                }
+                self.clear_render_cache();
+            }
+            EngineCommand::StepFrames(delta) => {
+                self.pause_for_seek();
+                self.graph_executor.step_frames(delta);
+                // The run loop doesn't tick while paused.
+                self.show_frame();
+            }
+            EngineCommand::SetLoopMode(loop_mode) => {
+                self.graph_executor.set_loop_mode(loop_mode);
+            }
+            EngineCommand::SetBufferingPolicy(buffering_policy) => {
+                self.graph_executor.set_buffering_policy(buffering_policy);
+            }
+            EngineCommand::SetLoopRegion(region) => {
+                self.graph_executor.set_loop_region(region);
+                if self.paused {
+                    // Show where the playhead was clamped to.
+                    self.tick();
+                }
+            }
+            EngineCommand::SetOutputFormat(output_format) => {
+                self.clear_render_cache();
+                self.graph_executor.set_output_format(output_format);
+                if !self.manual_fps_locked
+                    && let Some(node_id) = self.output_node_id
+                {
+                    self.try_apply_output_node_fps(node_id);
+                }
+                if self.paused {
+                    self.tick();
+                }
+            }
+            EngineCommand::SetRenderRegion(region) => {
+                self.clear_render_cache();
+                self.graph_executor.set_render_region(region);
+                if self.paused {
+                    self.tick();
+                }
+            }
+            EngineCommand::SetExecutionBackend(backend) => {
+                util::debug_log_info!("Using the {backend:?} execution backend.");
+                self.graph_executor.set_backend(backend);
+                self.clear_render_cache();
+            }
+            EngineCommand::LoadPipelineCache(adapter_info) => {
+                let cache = DiskPipelineCache::load(&self.device, &adapter_info, &self.library);
+                if cache.is_none() {
+                    util::debug_log_info!(
+                        "Pipelines won't be cached on {} ({:?}).",
+                        adapter_info.name,
+                        adapter_info.backend
+                    );
+                }
+                self.graph_executor.set_disk_pipeline_cache(cache);
+            }
+            EngineCommand::RecordTrace { frames, path } => {
+                self.trace = Some(TraceRecording {
+                    trace: ExecutionTrace::new(
+                        &self.graph,
+                        &self.library,
+                        self.output_node_id,
+                        self.graph_executor.output_format(),
+                    ),
+                    frames,
+                    path,
+                });
+                if frames == 0 {
+                    self.finish_trace();
+                } else if self.paused {
+                    for _ in 0..frames {
+                        self.tick();
+                    }
+                }
+            }
+            EngineCommand::SaveNodeOutput { node_id, path } => {
+                let result = self.save_node_output(node_id, &path).map(|()| path);
+                self.broadcaster
+                    .broadcast(EngineOutpostEvent::NodeOutputSaved(result));
+            }
+            EngineCommand::SetForcedNodes(node_ids) => {
+                self.graph_executor.set_captured_nodes(node_ids);
+                if self.paused {
+                    self.tick();
+                }
+            }
+            EngineCommand::SetLinkEnabled(enabled) => {
+                if let Err(e) = self.graph_executor.set_link_enabled(enabled) {
+                    self.broadcaster
+                        .broadcast(EngineOutpostEvent::ExecutionError(format!(
+                            "Failed to enable Ableton Link: {e}"
+                        )));
+                }
+                // Always answer, so a failed attempt still shows as disabled.
+                self.last_link_peers = self.graph_executor.link_peers();
+                self.broadcaster
+                    .broadcast(EngineOutpostEvent::LinkStatus(self.last_link_peers));
+            }
+            EngineCommand::SetExecutionOrderReporting(enabled) => {
+                self.report_execution_order = enabled;
+            }
+            EngineCommand::SetBackgroundRendering(enabled) => {
+                self.background_rendering = enabled;
+            }
+            EngineCommand::Shutdown => {
+                self.shutdown_requested = true;
+            }
+        }
+    }
+
+    fn handle_playback_request(&mut self, request: VersionedRequest) -> PlaybackResult {
+        protocol::check_version(&request)?;
+        self.stop_background_render();
+
+        match request.request {
+            PlaybackRequest::LoadSource { node_id, path } => {
+                let input = self.source_input(node_id)?;
+                self.set_input(node_id, input, InputValue::File(path))?;
+            }
+            PlaybackRequest::SetParameter {
+                node_id,
+                input,
+                value,
+            } => self.set_input(node_id, input, value)?,
+            PlaybackRequest::Seek { frame } => {
+                self.pause_for_seek();
+                self.graph_executor.seek_frame(frame);
+                self.show_frame();
             }
+            PlaybackRequest::Play => self.handle_command(EngineCommand::PlayStreams),
+            PlaybackRequest::Pause => self.handle_command(EngineCommand::PauseStreams),
+            PlaybackRequest::RenderRange { frames, directory } => {
+                let frames = self.render_range(frames, &directory)?;
+                return Ok(PlaybackAck::Rendered { frames });
+            }
+        }
+
+        Ok(PlaybackAck::Done)
+    }
+
+    /// The name of the first file input of `node_id`'s definition.
+    fn source_input(&self, node_id: EngineNodeId) -> Result<String, PlaybackError> {
+        let instance = self
+            .graph
+            .get_instance(node_id)
+            .ok_or(PlaybackError::NodeNotFound(node_id))?;
+        self.library
+            .get_definition(&instance.definition_name)
+            .and_then(|definition| {
+                definition
+                    .node
+                    .inputs
+                    .iter()
+                    .find(|input| matches!(input.kind, NodeInputKind::File { .. }))
+            })
+            .map(|input| input.name.clone())
+            .ok_or(PlaybackError::NotASource(node_id))
+    }
+
+    /// Set an input of the engine's copy of the graph. The change lasts until
+    /// the app sends its next `EngineCommand::UpdateGraph`.
+    fn set_input(
+        &mut self,
+        node_id: EngineNodeId,
+        input: String,
+        value: InputValue,
+    ) -> Result<(), PlaybackError> {
+        self.graph
+            .set_input_value(node_id, input.clone(), value)
+            .map_err(|e| match e {
+                GraphError::NodeNotFound(node_id) => PlaybackError::NodeNotFound(node_id),
+                e => PlaybackError::InvalidParameter {
+                    input,
+                    reason: e.to_string(),
+                },
+            })?;
+        self.clear_render_cache();
+        if self.paused {
+            self.tick();
+        }
+        Ok(())
+    }
+
+    /// Render the output node at each of `frames`, saving them as numbered
+    /// PNGs in `directory`. Returns how many frames were written.
+    fn render_range(
+        &mut self,
+        frames: RangeInclusive<usize>,
+        directory: &Path,
+    ) -> Result<usize, PlaybackError> {
+        let output_node_id = self.output_node_id.ok_or(PlaybackError::NoOutputNode)?;
+        fs::create_dir_all(directory).map_err(|e| PlaybackError::RenderFailed {
+            frame: *frames.start(),
+            reason: e.to_string(),
+        })?;
+
+        self.pause_for_seek();
+        let mut rendered = 0;
+        for frame in frames {
+            self.graph_executor.seek_frame(frame);
+            self.tick();
+            let path = directory.join(format!("frame_{frame:06}.png"));
+            self.save_node_output(output_node_id, &path)
+                .map_err(|reason| PlaybackError::RenderFailed { frame, reason })?;
+            rendered += 1;
+        }
+        Ok(rendered)
+    }
+
+    /// Pause playback (if it isn't already) before moving the playhead.
+    fn pause_for_seek(&mut self) {
+        if !self.paused {
+            self.paused = true;
+            self.broadcaster
+                .broadcast(EngineOutpostEvent::StreamsPaused);
+        }
+    }
+
+    /// Show the frame the playhead is on, from the render cache if it's there.
+    fn show_frame(&mut self) {
+        let cached = self
+            .output_node_id
+            .and_then(|node_id| {
+                self.graph_executor.get_playback_position_for_node(
+                    &self.graph,
+                    &self.library,
+                    node_id,
+                )
+            })
+            .and_then(|(frame, _)| self.render_cache.get(frame).cloned());
+
+        match cached {
+            Some(frame) => {
+                self.broadcaster
+                    .broadcast(EngineOutpostEvent::FrameReady(frame));
+                self.broadcast_playback_position();
+            }
+            None => self.tick(),
+        }
+    }
+
+    /// Whether there's a frame to render in the background (see
+    /// `EngineCommand::SetBackgroundRendering`). Placeholders shown while
+    /// shaders compile aren't worth caching.
+    fn wants_background_render(&self) -> bool {
+        self.background_rendering
+            && self.paused
+            && self.output_node_id.is_some()
+            && !self.render_cache.is_full()
+            && self.graph_executor.compiling_nodes().is_empty()
+    }
+
+    /// Render and cache the next frame after the playhead that isn't cached,
+    /// without showing it. Once every frame is cached the playhead is put back.
+    fn render_in_background(&mut self) {
+        let clip = self.output_node_id.and_then(|node_id| {
+            self.graph_executor
+                .get_clip_for_node(&self.graph, &self.library, node_id)
+        });
+        let home = self
+            .background_home
+            .or(self.last_playback_position.map(|(frame, _)| frame));
+        // Without a video source there's no timeline to walk.
+        let (Some(clip), Some(home)) = (clip, home) else {
+            self.background_rendering = false;
+            return;
+        };
+        let Some(frame) = self.render_cache.next_uncached(&clip, home) else {
+            self.stop_background_render();
+            self.background_rendering = false;
+            return;
+        };
+
+        self.background_home = Some(home);
+        self.graph_executor.seek_frame(frame);
+        match self.execute_output() {
+            Ok(Some(output)) => {
+                let format = self.graph_executor.target_format;
+                self.render_cache
+                    .insert(&self.device, &self.queue, format, frame, &output);
+                self.broadcaster
+                    .broadcast(EngineOutpostEvent::CachedFrames {
+                        clip,
+                        cached: self.render_cache.ranges(),
+                    });
+                if self.render_cache.is_full() {
+                    self.stop_background_render();
+                }
+            }
+            // Don't retry a frame that won't render until asked to again.
+            Ok(None) | Err(_) => {
+                self.stop_background_render();
+                self.background_rendering = false;
+            }
+        }
+    }
+
+    /// Put the playhead back where it was before background rendering moved
+    /// it, if it did.
+    fn stop_background_render(&mut self) {
+        if let Some(home) = self.background_home.take() {
+            self.graph_executor.seek_frame(home);
+        }
+    }
+
+    /// Drop every cached frame, since the output they were rendered from has
+    /// changed.
+    fn clear_render_cache(&mut self) {
+        if self.render_cache.is_empty() {
+            return;
+        }
+        self.render_cache.clear();
+        self.broadcaster
+            .broadcast(EngineOutpostEvent::CachedFrames {
+                clip: 0..=0,
+                cached: Vec::new(),
+            });
+    }
+
+    /// Write the compiled pipelines to disk, if they're being cached.
+    fn save_pipeline_cache(&self) {
+        if let Some(cache) = self.graph_executor.disk_pipeline_cache()
+            && let Err(e) = cache.save()
+        {
+            util::debug_log_warning!("Failed to save pipeline cache: {e}");
         }
     }
 
@@ -222,26 +811,36 @@ impl EngineOutpostInner {
         Some(fps)
     }
 
-    fn tick(&mut self) {
-        let result = self.graph_executor.execute(
+    /// Run the graph once, returning the output node's frame if it made one.
+    fn execute_output(&mut self) -> Result<Option<GpuFrame>, ExecutionError> {
+        let execution_result = self.graph_executor.execute(
             &self.graph,
             &self.library,
             &self.device,
             &self.queue,
             self.output_node_id,
             |event| self.broadcaster.broadcast(event),
-        );
+        )?;
+        Ok(execution_result
+            .outputs
+            .values()
+            .find_map(|value| match value {
+                NodeValue::Frame(frame) => Some(frame.clone()),
+                _ => None,
+            }))
+    }
+
+    fn tick(&mut self) {
+        if self.trace.is_some() || self.report_execution_order {
+            self.graph_executor.trace_next_execution();
+        }
+
+        let result = self.execute_output();
 
+        let executed = result.is_ok();
+        let error = result.as_ref().err().map(ToString::to_string);
         let frame = match result {
-            Ok(execution_result) => {
-                execution_result
-                    .outputs
-                    .values()
-                    .find_map(|value| match value {
-                        NodeValue::Frame(frame) => Some(frame.clone()),
-                        _ => None,
-                    })
-            }
+            Ok(frame) => frame,
             Err(ExecutionError::NoOutputNode) | Err(ExecutionError::NoOutputProduced) => None,
             Err(err) => {
                 self.broadcaster
@@ -260,5 +859,154 @@ impl EngineOutpostInner {
             self.broadcaster
                 .broadcast(EngineOutpostEvent::FrameReady(frame));
         }
+
+        self.broadcast_playback_position();
+        self.broadcast_compiling_nodes();
+
+        if executed {
+            self.publish_analysis();
+        }
+
+        let traced_nodes = self.graph_executor.take_trace().unwrap_or_default();
+        if self.report_execution_order {
+            self.broadcaster
+                .broadcast(EngineOutpostEvent::ExecutionOrder(
+                    traced_nodes.iter().map(|node| node.node_id).collect(),
+                ));
+        }
+        self.record_trace_frame(traced_nodes, error);
+    }
+
+    /// Add the last execution (the `nodes` it ran) to the trace being
+    /// recorded, saving it if it's now long enough.
+    fn record_trace_frame(&mut self, nodes: Vec<TraceNode>, error: Option<String>) {
+        let Some(recording) = &mut self.trace else {
+            return;
+        };
+        recording.trace.frames.push(TraceFrame { nodes, error });
+        if recording.trace.frames.len() >= recording.frames {
+            self.finish_trace();
+        }
+    }
+
+    /// Save the trace being recorded, however many frames it has.
+    fn finish_trace(&mut self) {
+        let Some(recording) = self.trace.take() else {
+            return;
+        };
+        match recording.trace.save(&recording.path) {
+            Ok(()) => self
+                .broadcaster
+                .broadcast(EngineOutpostEvent::TraceSaved(recording.path)),
+            Err(e) => self
+                .broadcaster
+                .broadcast(EngineOutpostEvent::ExecutionError(format!(
+                    "Failed to save the execution trace: {e}"
+                ))),
+        }
+    }
+
+    /// Write the frame `node_id` output last execution to a PNG at `path`.
+    fn save_node_output(
+        &self,
+        node_id: crate::node_graph::EngineNodeId,
+        path: &Path,
+    ) -> Result<(), String> {
+        let frame = self
+            .graph_executor
+            .capture_node_output(node_id, &self.device, &self.queue)
+            .map_err(|e| e.to_string())?;
+        let dimensions = frame.dimensions();
+        ImageBuffer::<Rgba<u8>, Vec<_>>::from_raw(
+            dimensions.width(),
+            dimensions.height(),
+            frame.raw_data().to_vec(),
+        )
+        .expect("one pixel per texel")
+        .save_with_format(path, ImageFormat::Png)
+        .map_err(|e| e.to_string())
+    }
+
+    /// Publish the current value of every output flagged with `publish`.
+    fn publish_analysis(&self) {
+        if !self.analysis_bus.has_subscribers() {
+            return;
+        }
+
+        let mut values = Vec::new();
+        for (&node_id, instance) in self.graph.instances() {
+            let Some(definition) = self.library.get_definition(&instance.definition_name) else {
+                continue;
+            };
+            let Some(outputs) = self.graph_executor.get_node_outputs(node_id) else {
+                continue;
+            };
+
+            for output in definition
+                .node
+                .outputs
+                .iter()
+                .filter(|output| output.publish)
+            {
+                if let Some(value) = outputs
+                    .get(&output.name)
+                    .and_then(AnalysisValue::from_node_value)
+                {
+                    let key = AnalysisKey {
+                        node_id,
+                        output: output.name.clone(),
+                    };
+                    values.push((key, value));
+                }
+            }
+        }
+
+        self.analysis_bus.publish(AnalysisFrame {
+            position: self.last_playback_position,
+            values,
+        });
+    }
+
+    fn broadcast_playback_position(&mut self) {
+        let position = self.output_node_id.and_then(|node_id| {
+            self.graph_executor
+                .get_playback_position_for_node(&self.graph, &self.library, node_id)
+        });
+
+        if position == self.last_playback_position {
+            return;
+        }
+        self.last_playback_position = position;
+
+        if let Some((frame, fps)) = position {
+            self.broadcaster
+                .broadcast(EngineOutpostEvent::PlaybackPosition { frame, fps });
+        }
+    }
+
+    fn broadcast_compiling_nodes(&mut self) {
+        let mut nodes: Vec<EngineNodeId> = self
+            .graph_executor
+            .compiling_nodes()
+            .iter()
+            .copied()
+            .collect();
+        nodes.sort();
+        if nodes == self.last_compiling_nodes {
+            return;
+        }
+        self.last_compiling_nodes = nodes.clone();
+        self.broadcaster
+            .broadcast(EngineOutpostEvent::ShadersCompiling(nodes));
+    }
+
+    fn broadcast_link_status(&mut self) {
+        let peers = self.graph_executor.link_peers();
+        if peers == self.last_link_peers {
+            return;
+        }
+        self.last_link_peers = peers;
+        self.broadcaster
+            .broadcast(EngineOutpostEvent::LinkStatus(peers));
     }
 }
@@ -0,0 +1,148 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use media::fps::Fps;
+use util::channels::message_channel::{self, Inbox, Outbox};
+use util::channels::sample_channel::{self, Publisher, Reader, Sample};
+
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+
+/// Identifies a published value: one output of one node.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnalysisKey {
+    pub node_id: EngineNodeId,
+    pub output: String,
+}
+
+/// A per-frame analysis value (e.g. a motion score, a beat, or a histogram)
+/// published from a node output flagged with `publish`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalysisValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    /// A series of values, e.g. histogram bins, the channels of a pixel, or a
+    /// float array.
+    Series(Vec<f32>),
+}
+
+impl AnalysisValue {
+    /// Convert a node output into something that can be plotted. Frames, MIDI,
+    /// and other non-numeric values can't be published.
+    pub fn from_node_value(value: &NodeValue) -> Option<Self> {
+        match value {
+            NodeValue::Bool(b) => Some(Self::Bool(*b)),
+            NodeValue::Int(i) => Some(Self::Int(*i)),
+            NodeValue::Float(f) => Some(Self::Float(*f)),
+            NodeValue::Pixel(pixel) => Some(Self::Series(pixel.to_vec())),
+            NodeValue::FloatArray(values) => Some(Self::Series(values.clone())),
+            NodeValue::Dimensions(width, height) => {
+                Some(Self::Series(vec![*width as f32, *height as f32]))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value as a single number to plot. Series are averaged.
+    pub fn as_f32(&self) -> f32 {
+        match self {
+            Self::Bool(b) => *b as u8 as f32,
+            Self::Int(i) => *i as f32,
+            Self::Float(f) => *f,
+            Self::Series(values) if values.is_empty() => 0.0,
+            Self::Series(values) => values.iter().sum::<f32>() / values.len() as f32,
+        }
+    }
+}
+
+/// A value taken from an [AnalysisReceiver].
+pub type AnalysisSample = Sample<AnalysisKey, AnalysisValue>;
+
+/// Receives the latest published analysis values. Values published faster than
+/// the receiver's interval are coalesced, so only the newest value per node
+/// output is kept. Call [Reader::take] whenever the UI wants fresh values
+/// (e.g. once per UI frame).
+pub type AnalysisReceiver = Reader<AnalysisKey, AnalysisValue>;
+
+/// Every value published for one engine frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisFrame {
+    /// The output's playback position (frame index and FPS) when the values
+    /// were computed, if the output has a video source.
+    pub position: Option<(usize, Fps)>,
+    pub values: Vec<(AnalysisKey, AnalysisValue)>,
+}
+
+/// Receives every [AnalysisFrame] in order, without throttling or coalescing.
+/// Meant for recording values to disk, not for live display (see
+/// [AnalysisReceiver]).
+pub type AnalysisRecordReceiver = Inbox<AnalysisFrame>;
+
+/// Sits between the engine thread and every analysis subscriber, like
+/// [EventBroadcaster](super::EventBroadcaster) but for high-frequency values
+/// that don't need to be queued.
+#[derive(Default)]
+pub struct AnalysisBus {
+    publishers: Mutex<Vec<Publisher<AnalysisKey, AnalysisValue>>>,
+    recorders: Mutex<Vec<Outbox<AnalysisFrame>>>,
+}
+
+impl AnalysisBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new subscriber that takes values at most once every
+    /// `min_interval`. Dropped receivers are pruned on the next publish.
+    pub fn subscribe(&self, min_interval: Duration) -> AnalysisReceiver {
+        let (reader, publisher) = sample_channel::throttled(min_interval);
+        self.publishers.lock().unwrap().push(publisher);
+        reader
+    }
+
+    /// Register a new recorder that receives every frame's values. Dropped
+    /// receivers are pruned on the next publish.
+    pub fn record(&self) -> AnalysisRecordReceiver {
+        let (inbox, outbox) = message_channel::new();
+        self.recorders.lock().unwrap().push(outbox);
+        inbox
+    }
+
+    /// Whether anyone is listening. Lets the engine skip collecting values.
+    pub fn has_subscribers(&self) -> bool {
+        let mut publishers = self.publishers.lock().unwrap();
+        publishers.retain(|publisher| publisher.is_connected());
+        let mut recorders = self.recorders.lock().unwrap();
+        recorders.retain(|recorder| recorder.connection_open());
+        !publishers.is_empty() || !recorders.is_empty()
+    }
+
+    /// Publish one frame's values to every subscriber and recorder.
+    pub fn publish(&self, frame: AnalysisFrame) {
+        let mut publishers = self.publishers.lock().unwrap();
+        for (key, value) in &frame.values {
+            publishers.retain(|publisher| publisher.publish(key.clone(), value.clone()).is_ok());
+        }
+        drop(publishers);
+
+        let mut recorders = self.recorders.lock().unwrap();
+        recorders.retain(|recorder| recorder.send(frame.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- as_f32() ---
+
+    #[test]
+    fn test_as_f32() {
+        assert_eq!(AnalysisValue::Bool(true).as_f32(), 1.0);
+        assert_eq!(AnalysisValue::Int(-3).as_f32(), -3.0);
+        assert_eq!(AnalysisValue::Float(0.5).as_f32(), 0.5);
+        assert_eq!(AnalysisValue::Series(vec![1.0, 2.0, 3.0]).as_f32(), 2.0);
+        assert_eq!(AnalysisValue::Series(vec![]).as_f32(), 0.0);
+    }
+}
@@ -19,6 +19,17 @@ pub enum EventKind {
     FpsChanged,  // GlobalStreamTargetFpsChanged
     InfoResponse,
     ExecutionError,
+    WorkerStalled,
+    WorkerPanicked,
+    PlaybackPosition,
+    TraceSaved,
+    NodeOutputSaved,
+    LinkStatus,
+    ShadersCompiling,
+    ExecutionOrder,
+    StreamRecovered,
+    CachedFrames,
+    GraphEditFailed,
 }
 
 impl EventFilter {
@@ -43,6 +54,17 @@ impl From<&EngineOutpostEvent> for EventKind {
             EngineOutpostEvent::GlobalStreamTargetFpsChanged(_) => EventKind::FpsChanged,
             EngineOutpostEvent::InfoResponse(_) => EventKind::InfoResponse,
             EngineOutpostEvent::ExecutionError(_) => EventKind::ExecutionError,
+            EngineOutpostEvent::WorkerStalled(_) => EventKind::WorkerStalled,
+            EngineOutpostEvent::WorkerPanicked { .. } => EventKind::WorkerPanicked,
+            EngineOutpostEvent::PlaybackPosition { .. } => EventKind::PlaybackPosition,
+            EngineOutpostEvent::TraceSaved(_) => EventKind::TraceSaved,
+            EngineOutpostEvent::NodeOutputSaved(_) => EventKind::NodeOutputSaved,
+            EngineOutpostEvent::LinkStatus(_) => EventKind::LinkStatus,
+            EngineOutpostEvent::ShadersCompiling(_) => EventKind::ShadersCompiling,
+            EngineOutpostEvent::ExecutionOrder(_) => EventKind::ExecutionOrder,
+            EngineOutpostEvent::StreamRecovered { .. } => EventKind::StreamRecovered,
+            EngineOutpostEvent::CachedFrames { .. } => EventKind::CachedFrames,
+            EngineOutpostEvent::GraphEditFailed(_) => EventKind::GraphEditFailed,
         }
     }
 }
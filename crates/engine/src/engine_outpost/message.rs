@@ -1,8 +1,16 @@
 //! Shared engine outpost message types.
 
+use crate::cpu_backend::ExecutionBackend;
 use crate::gpu_frame::GpuFrame;
-use crate::node_graph::{EngineNodeId, NodeGraph};
-use media::fps::Fps;
+use crate::graph_executor::{OutputFormat, RenderRegion};
+use crate::node::handler::LoopMode;
+use crate::node_graph::{EngineNodeId, GraphEdit, NodeGraph};
+use media::fps::{Fps, PreviewRate};
+use media::playback_stream::{BufferingPolicy, BufferingStats};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// Commands that can be sent into the engine outpost.
 #[derive(Debug, Clone)]
@@ -16,15 +24,87 @@ pub enum EngineCommand {
     /// Release the manual FPS override and resume auto-adjusting from the
     /// output node's recommended rate.
     ClearManualFps,
+    /// Run the graph at a different rate than the engine's FPS (see
+    /// [PreviewRate]) without changing how fast sources play: frames in
+    /// between are skipped. Positions and loop regions stay in frames at the
+    /// engine's FPS.
+    SetPreviewRate(PreviewRate),
     /// Tell the engine which node should be treated as the active output.
     SetOutputNode(Option<EngineNodeId>),
     /// Push an updated graph to the engine. The engine will execute
     /// immediately on the next loop iteration rather than waiting for
     /// the next scheduled tick.
     UpdateGraph(NodeGraph),
+    /// Change the current graph in place (see [NodeGraph::diff]), which keeps
+    /// what the engine has worked out about it when only input values change.
+    /// The edits are made all at once: if one fails none are made, and
+    /// `EngineOutpostEvent::GraphEditFailed` is emitted so the app can send
+    /// its whole graph with `UpdateGraph` instead.
+    EditGraph(Vec<GraphEdit>),
     /// Request information from the engine outpost. The engine should
     /// respond by emitting an `EngineOutpostEvent::InfoResponse`.
     RequestInfo(InfoRequest),
+    /// Pause playback and move video sources by this many frames (negative
+    /// values step backwards), then render the new frame. Used for inspecting
+    /// output frame by frame.
+    StepFrames(isize),
+    /// Choose what video sources do when they reach the end of their loop
+    /// region.
+    SetLoopMode(LoopMode),
+    /// Restrict video sources to an in/out range of frames (inclusive, at the
+    /// output's FPS), or clear the range with `None` to play whole videos.
+    SetLoopRegion(Option<RangeInclusive<usize>>),
+    /// Set the project-level output resolution and frame rate that sources
+    /// are conformed to.
+    SetOutputFormat(OutputFormat),
+    /// Only render this rectangle of the output, leaving the rest black, or
+    /// the whole output with `None` (see `GraphExecutor::set_render_region`).
+    SetRenderRegion(Option<RenderRegion>),
+    /// Choose which backend supported nodes run on. Meant to be sent once
+    /// after spawning (it drops all cached node outputs).
+    SetExecutionBackend(ExecutionBackend),
+    /// Load the compiled pipelines saved for the adapter the engine's device
+    /// was created from (see [crate::pipeline_cache]), and save them back as
+    /// more are compiled. Meant to be sent once after spawning.
+    LoadPipelineCache(wgpu::AdapterInfo),
+    /// Record the next `frames` executions to a JSON trace at `path` (see
+    /// [crate::execution_trace]). If playback is paused, the frames are run
+    /// right away. An `EngineOutpostEvent::TraceSaved` is emitted once the
+    /// trace has been written.
+    RecordTrace {
+        frames: usize,
+        path: PathBuf,
+    },
+    /// Save the frame `node_id` output last execution to a PNG at `path`, at
+    /// its full resolution. An `EngineOutpostEvent::NodeOutputSaved` is
+    /// emitted once it's been written (or failed to be).
+    SaveNodeOutput {
+        node_id: EngineNodeId,
+        path: PathBuf,
+    },
+    /// Keep running these nodes (and what they depend on) every execution,
+    /// even when they don't lead to the output. Replaces the previous set.
+    SetForcedNodes(Vec<EngineNodeId>),
+    /// Join or leave an Ableton Link session, so the tempo clock follows (and
+    /// sets) the tempo and beat of other apps on the network. An
+    /// `EngineOutpostEvent::LinkStatus` is emitted whenever the session
+    /// changes. Link support is experimental (see [util::link]).
+    SetLinkEnabled(bool),
+    /// Start or stop reporting the order nodes run in. While it's on, every
+    /// execution is traced (see [crate::execution_trace]) and an
+    /// `EngineOutpostEvent::ExecutionOrder` is emitted after it.
+    SetExecutionOrderReporting(bool),
+    /// Choose how far ahead video sources decode. Applies to sources that are
+    /// already playing too.
+    SetBufferingPolicy(BufferingPolicy),
+    /// Start or stop rendering the output's frames in the background while
+    /// paused, caching them so seeking to them is instant. Meant to be turned
+    /// on while the user is idle, and off again as soon as they aren't.
+    /// `EngineOutpostEvent::CachedFrames` is emitted as frames are cached.
+    SetBackgroundRendering(bool),
+    /// Stop the engine thread after the current loop iteration. See
+    /// `EngineOutpostHandle::shutdown`.
+    Shutdown,
 }
 
 /// Events emitted by the engine outpost and observed by the app.
@@ -41,6 +121,59 @@ pub enum EngineOutpostEvent {
     ExecutionError(String),
     /// Response to an information request made via `EngineCommand::RequestInfo`.
     InfoResponse(InfoResponse),
+    /// A worker missed its watchdog deadline. Contains the name of the stalled
+    /// subsystem (e.g. the render engine blocked on a video decoder).
+    WorkerStalled(String),
+    /// A worker thread panicked (see [util::panic_capture]). Media stream
+    /// workers are recreated the next time their node runs.
+    WorkerPanicked {
+        subsystem: String,
+        message: String,
+        backtrace: String,
+    },
+    /// The frame the output's video source is showing changed. `frame` is the
+    /// index into the video at `fps`.
+    PlaybackPosition {
+        frame: usize,
+        fps: Fps,
+    },
+    /// A trace requested with `EngineCommand::RecordTrace` was written to this
+    /// path.
+    TraceSaved(PathBuf),
+    /// The path a frame requested with `EngineCommand::SaveNodeOutput` was
+    /// written to, or why it couldn't be.
+    NodeOutputSaved(Result<PathBuf, String>),
+    /// How many other peers are in the Ableton Link session, or [None] if Link
+    /// isn't enabled.
+    LinkStatus(Option<usize>),
+    /// The nodes showing placeholders while their shaders compile in the
+    /// background, sent whenever they change (empty once they're all done).
+    ShadersCompiling(Vec<EngineNodeId>),
+    /// The nodes the last execution ran, in the order they ran, while
+    /// reporting is on (see `EngineCommand::SetExecutionOrderReporting`).
+    /// Nodes after one that failed are missing.
+    ExecutionOrder(Vec<EngineNodeId>),
+    /// A node's video source hit a decode error at `frame` and was reopened
+    /// there. If that didn't work (`recovered` is `false`), the source will
+    /// keep failing until it's reloaded.
+    StreamRecovered {
+        node_id: EngineNodeId,
+        frame: usize,
+        error: String,
+        recovered: bool,
+    },
+    /// An `EngineCommand::EditGraph` couldn't be applied (none of its edits
+    /// were made), so the engine's graph isn't the one the app thinks it is.
+    /// The app should send its graph with `EngineCommand::UpdateGraph`.
+    GraphEditFailed(String),
+    /// The output frames that are cached (see
+    /// `EngineCommand::SetBackgroundRendering`), as runs of frame indices in
+    /// `clip`, the frames the output's video source plays. Sent with no
+    /// frames when the cache is cleared.
+    CachedFrames {
+        clip: RangeInclusive<usize>,
+        cached: Vec<RangeInclusive<usize>>,
+    },
 }
 
 /// Dynamic information request types the app can ask the engine for.
@@ -48,6 +181,10 @@ pub enum EngineOutpostEvent {
 pub enum InfoRequest {
     /// Ask for a recommended FPS for the given node id (typically a video source).
     RecommendedFpsForNode(EngineNodeId),
+    /// Ask how long each node took in the last execution.
+    NodeTimings,
+    /// Ask how full each video source's decode buffer is.
+    BufferingStats,
 }
 
 /// Responses the engine can emit for InfoRequest messages.
@@ -55,6 +192,12 @@ pub enum InfoRequest {
 pub enum InfoResponse {
     /// Recommended FPS for a node (node id, fps)
     RecommendedFpsForNode(EngineNodeId, Fps),
+    /// How long each node that ran took on the CPU in the last execution (see
+    /// [crate::graph_executor::GraphExecutor::last_node_timings]).
+    NodeTimings(HashMap<EngineNodeId, Duration>),
+    /// How each video source's decode buffer is doing (see
+    /// [crate::graph_executor::GraphExecutor::buffering_stats]).
+    BufferingStats(HashMap<EngineNodeId, BufferingStats>),
     /// Generic error
     Error(String),
 }
@@ -0,0 +1,262 @@
+//! The playback protocol, a versioned set of requests the app sends the engine
+//! thread to drive playback, each of which is acknowledged once the engine has
+//! handled it (unlike [EngineCommand](super::EngineCommand)s, which are fire
+//! and forget).
+//!
+//! Requests are sent with a [PlaybackClient] (see
+//! [EngineOutpostHandle::playback_client](super::EngineOutpostHandle::playback_client))
+//! over a [request_channel]. Every request carries the [PROTOCOL_VERSION] the
+//! client was built with, and the engine refuses requests from a different
+//! version instead of guessing at what they mean.
+
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use thiserror::Error;
+use util::channels::ChannelResult;
+use util::channels::request_channel::{self, Client, Request, Server};
+
+use crate::node_graph::{EngineNodeId, InputValue};
+
+/// The version of the playback protocol. Bump this whenever a request or
+/// acknowledgement changes meaning.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Requests the app can make of the engine thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaybackRequest {
+    /// Play the file at `path` in a source node (its first file input).
+    LoadSource {
+        node_id: EngineNodeId,
+        path: PathBuf,
+    },
+    /// Set a node's input to a value. Connections can't be made this way.
+    SetParameter {
+        node_id: EngineNodeId,
+        input: String,
+        value: InputValue,
+    },
+    /// Pause playback and move video sources to this frame (clamped to their
+    /// clips), then render it.
+    Seek { frame: usize },
+    /// Resume playback.
+    Play,
+    /// Pause playback. The graph keeps executing, but sources don't advance.
+    Pause,
+    /// Pause playback and render the output node at each of these frames,
+    /// saving them as numbered PNGs in `directory`.
+    RenderRange {
+        frames: RangeInclusive<usize>,
+        directory: PathBuf,
+    },
+}
+
+/// What the engine did with a [PlaybackRequest].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlaybackAck {
+    /// The request was applied.
+    Done,
+    /// A [PlaybackRequest::RenderRange] finished, writing this many frames.
+    Rendered { frames: usize },
+}
+
+/// Why the engine couldn't handle a [PlaybackRequest].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PlaybackError {
+    #[error("The app speaks playback protocol v{client} but the engine speaks v{engine}.")]
+    VersionMismatch { client: u32, engine: u32 },
+    #[error("Node {0} not found.")]
+    NodeNotFound(EngineNodeId),
+    #[error("Node {0} doesn't have a file input.")]
+    NotASource(EngineNodeId),
+    #[error("Can't set input `{input}`: {reason}")]
+    InvalidParameter { input: String, reason: String },
+    #[error("There's no output node to render.")]
+    NoOutputNode,
+    #[error("Failed to render frame {frame}: {reason}")]
+    RenderFailed { frame: usize, reason: String },
+}
+
+/// The result of a [PlaybackRequest], sent back to the [PlaybackClient].
+pub type PlaybackResult = Result<PlaybackAck, PlaybackError>;
+
+/// A [PlaybackRequest] as it's sent to the engine, tagged with the protocol
+/// version of the client that sent it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionedRequest {
+    pub version: u32,
+    pub request: PlaybackRequest,
+}
+
+/// The engine's end of the playback protocol.
+pub(super) type PlaybackServer = Server<VersionedRequest, PlaybackResult>;
+
+/// A cheaply cloneable handle for sending [PlaybackRequest]s to the engine
+/// thread.
+#[derive(Clone)]
+pub struct PlaybackClient {
+    client: Arc<Client<VersionedRequest, PlaybackResult>>,
+}
+
+impl PlaybackClient {
+    /// Send a request. The returned [Request] receives the engine's answer
+    /// once the request has been handled.
+    pub fn request(
+        &self,
+        request: PlaybackRequest,
+    ) -> ChannelResult<Request<PlaybackResult>, PlaybackRequest> {
+        self.client
+            .request(Self::wrap(request))
+            .map_err(|err| err.map_msg(|versioned| versioned.request))
+    }
+
+    /// Send a request without waiting on (or being told) whether it worked.
+    pub fn alert(&self, request: PlaybackRequest) -> ChannelResult<(), PlaybackRequest> {
+        self.client
+            .alert(Self::wrap(request))
+            .map_err(|err| err.map_msg(|versioned| versioned.request))
+    }
+
+    fn wrap(request: PlaybackRequest) -> VersionedRequest {
+        VersionedRequest {
+            version: PROTOCOL_VERSION,
+            request,
+        }
+    }
+}
+
+/// [PlaybackRequest]s that have been sent but not answered yet.
+#[derive(Default)]
+pub struct PendingPlayback {
+    requests: Vec<Request<PlaybackResult>>,
+}
+
+impl PendingPlayback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send a request with `client`, keeping track of it until it's answered.
+    pub fn send(
+        &mut self,
+        client: &PlaybackClient,
+        request: PlaybackRequest,
+    ) -> ChannelResult<(), PlaybackRequest> {
+        self.requests.push(client.request(request)?);
+        Ok(())
+    }
+
+    /// Whether every request has been answered.
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Why the requests answered since this was last called failed, if any
+    /// did. Requests the engine dropped without answering (e.g. because it
+    /// shut down) are forgotten.
+    pub fn take_errors(&mut self) -> Vec<PlaybackError> {
+        let mut errors = Vec::new();
+        self.requests
+            .retain_mut(|request| match request.check_non_blocking() {
+                Ok(None) => true,
+                Ok(Some(Err(error))) => {
+                    errors.push(error);
+                    false
+                }
+                Ok(Some(Ok(_))) | Err(_) => false,
+            });
+        errors
+    }
+}
+
+/// Create both ends of the playback protocol.
+pub(super) fn new() -> (PlaybackServer, PlaybackClient) {
+    let (server, client) = request_channel::new();
+    (
+        server,
+        PlaybackClient {
+            client: Arc::new(client),
+        },
+    )
+}
+
+/// Check that a request was sent by a client speaking this protocol version.
+pub(super) fn check_version(request: &VersionedRequest) -> Result<(), PlaybackError> {
+    if request.version == PROTOCOL_VERSION {
+        Ok(())
+    } else {
+        Err(PlaybackError::VersionMismatch {
+            client: request.version,
+            engine: PROTOCOL_VERSION,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- check_version() ---
+
+    #[test]
+    fn test_check_version() {
+        let request = VersionedRequest {
+            version: PROTOCOL_VERSION,
+            request: PlaybackRequest::Play,
+        };
+        assert_eq!(check_version(&request), Ok(()));
+
+        let request = VersionedRequest {
+            version: PROTOCOL_VERSION + 1,
+            request: PlaybackRequest::Play,
+        };
+        assert_eq!(
+            check_version(&request),
+            Err(PlaybackError::VersionMismatch {
+                client: PROTOCOL_VERSION + 1,
+                engine: PROTOCOL_VERSION,
+            })
+        );
+    }
+
+    // --- PlaybackClient ---
+
+    #[test]
+    fn requests_are_answered() {
+        let (server, client) = new();
+        let mut request = client.request(PlaybackRequest::Pause).unwrap();
+
+        let (versioned, response) = server.wait().unwrap();
+        assert_eq!(versioned.request, PlaybackRequest::Pause);
+        assert_eq!(versioned.version, PROTOCOL_VERSION);
+        response.unwrap().respond(Ok(PlaybackAck::Done)).unwrap();
+
+        assert_eq!(request.wait(), Ok(Ok(PlaybackAck::Done)));
+    }
+
+    // --- PendingPlayback::take_errors() ---
+
+    #[test]
+    fn test_take_errors() {
+        let (server, client) = new();
+        let mut pending = PendingPlayback::new();
+        pending.send(&client, PlaybackRequest::Play).unwrap();
+        pending
+            .send(&client, PlaybackRequest::Seek { frame: 3 })
+            .unwrap();
+        assert!(pending.take_errors().is_empty());
+        assert!(!pending.is_empty());
+
+        let (_, response) = server.wait().unwrap();
+        response.unwrap().respond(Ok(PlaybackAck::Done)).unwrap();
+        let (_, response) = server.wait().unwrap();
+        response
+            .unwrap()
+            .respond(Err(PlaybackError::NoOutputNode))
+            .unwrap();
+
+        assert_eq!(pending.take_errors(), vec![PlaybackError::NoOutputNode]);
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+//! Exports [RenderCache], which keeps copies of output frames rendered in the
+//! background while the user is idle, so seeking to them shows them right away
+//! instead of running the graph again.
+
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use crate::gpu_frame::GpuFrame;
+use crate::texture_blitter::TextureBlitter;
+
+/// How much GPU memory cached frames can take up before the cache stops
+/// taking more.
+const CAPACITY_BYTES: u64 = 1 << 30;
+
+/// Output frames by the index of the frame they were rendered at.
+pub struct RenderCache {
+    blitter: Option<TextureBlitter>,
+    frames: BTreeMap<usize, GpuFrame>,
+    bytes: u64,
+}
+
+impl RenderCache {
+    pub fn new() -> Self {
+        Self {
+            blitter: None,
+            frames: BTreeMap::new(),
+            bytes: 0,
+        }
+    }
+
+    pub fn get(&self, frame: usize) -> Option<&GpuFrame> {
+        self.frames.get(&frame)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Whether the cache has run out of room.
+    pub fn is_full(&self) -> bool {
+        self.bytes >= CAPACITY_BYTES
+    }
+
+    /// Copy `output` into a texture of its own (its texture is reused by the
+    /// next execution) and keep it as the output at `frame`.
+    pub fn insert(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        frame: usize,
+        output: &GpuFrame,
+    ) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("render_cache_frame"),
+            size: output.size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        if self
+            .blitter
+            .as_ref()
+            .is_none_or(|blitter| blitter.format() != format)
+        {
+            self.blitter = Some(TextureBlitter::new(device, format));
+        }
+        let blitter = self.blitter.as_ref().expect("just set");
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("render_cache"),
+        });
+        blitter.blit(device, &mut encoder, output.view(), &view);
+        queue.submit(Some(encoder.finish()));
+
+        let bytes_per_pixel = format.block_copy_size(None).unwrap_or(4) as u64;
+        self.bytes += output.size.width as u64 * output.size.height as u64 * bytes_per_pixel;
+        self.frames
+            .insert(frame, GpuFrame::new(view, output.size, output.frame_id));
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.bytes = 0;
+    }
+
+    /// The first frame of `clip` that isn't cached, looking from `from` to the
+    /// end of the clip, then from its start up to `from`.
+    pub fn next_uncached(&self, clip: &RangeInclusive<usize>, from: usize) -> Option<usize> {
+        next_uncached(|frame| self.frames.contains_key(&frame), clip, from)
+    }
+
+    /// The cached frames, as runs of consecutive frames.
+    pub fn ranges(&self) -> Vec<RangeInclusive<usize>> {
+        ranges(self.frames.keys().copied())
+    }
+}
+
+fn next_uncached(
+    is_cached: impl Fn(usize) -> bool,
+    clip: &RangeInclusive<usize>,
+    from: usize,
+) -> Option<usize> {
+    let from = from.clamp(*clip.start(), *clip.end());
+    (from..=*clip.end())
+        .chain(*clip.start()..from)
+        .find(|&frame| !is_cached(frame))
+}
+
+/// Group sorted `frames` into runs of consecutive frames.
+fn ranges(frames: impl Iterator<Item = usize>) -> Vec<RangeInclusive<usize>> {
+    let mut ranges: Vec<RangeInclusive<usize>> = Vec::new();
+    for frame in frames {
+        match ranges.last_mut() {
+            Some(range) if *range.end() + 1 == frame => *range = *range.start()..=frame,
+            _ => ranges.push(frame..=frame),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- next_uncached() ---
+
+    #[test]
+    fn next_uncached_looks_ahead_then_wraps() {
+        let cached = [3, 4, 5, 9];
+        let is_cached = |frame| cached.contains(&frame);
+
+        assert_eq!(next_uncached(is_cached, &(0..=9), 3), Some(6));
+        assert_eq!(next_uncached(is_cached, &(0..=9), 9), Some(0));
+        assert_eq!(next_uncached(is_cached, &(3..=5), 0), None);
+    }
+
+    // --- ranges() ---
+
+    #[test]
+    fn ranges_merges_consecutive_frames() {
+        assert_eq!(
+            ranges([1, 2, 3, 7, 9, 10].into_iter()),
+            [1..=3, 7..=7, 9..=10]
+        );
+        assert!(ranges(std::iter::empty()).is_empty());
+    }
+}
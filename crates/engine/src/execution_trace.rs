@@ -0,0 +1,310 @@
+//! Recording a few frames of graph execution to a JSON file, and replaying a
+//! recording against a node library.
+//!
+//! A trace has the graph that was running plus, for every frame, the order
+//! nodes ran in, the inputs they were given, what they output (frames are
+//! recorded by size), whether the output came from the cache, and how long
+//! each node took. It's meant for bug reports like "my graph renders black":
+//! the trace shows where a frame went wrong, and [replay] runs the same graph
+//! again somewhere else so the two can be compared.
+//!
+//! Traces are recorded by the engine with
+//! [EngineCommand::RecordTrace](crate::engine_outpost::EngineCommand::RecordTrace).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use media::fps::Fps;
+use media::frame::Dimensions;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::graph_executor::{GraphExecutor, NodeValue, OutputFormat};
+use crate::node::NodeLibrary;
+use crate::node_graph::{EngineNodeId, NodeGraph};
+
+#[derive(Error, Debug)]
+pub enum TraceError {
+    #[error("Failed to read or write the trace: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("The trace isn't valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Failed to create a GPU device to replay on: {0}")]
+    NoDevice(String),
+}
+
+/// A recording of a few frames of graph execution. See the
+/// [module docs](self).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    /// The version of the app that recorded the trace.
+    pub app_version: String,
+    /// The [content hash](NodeLibrary::content_hash) of the node library the
+    /// trace was recorded with.
+    pub node_library_hash: u64,
+    /// The graph when recording started.
+    pub graph: NodeGraph,
+    /// The node the engine was rendering, or [None] for the graph's output.
+    pub output_node_id: Option<EngineNodeId>,
+    /// The project's output resolution (see [OutputFormat]).
+    pub output_resolution: Option<(u32, u32)>,
+    /// The project's output frame rate as a fraction (see [OutputFormat]).
+    pub output_fps: Option<(u32, u32)>,
+    /// Every recorded frame, in order.
+    pub frames: Vec<TraceFrame>,
+}
+
+/// One execution of the graph.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TraceFrame {
+    /// The nodes that ran, in the order they ran.
+    pub nodes: Vec<TraceNode>,
+    /// Why the execution failed, if it did. Nodes after the one that failed
+    /// aren't in [Self::nodes].
+    pub error: Option<String>,
+}
+
+/// One node's part in a [TraceFrame].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceNode {
+    pub node_id: EngineNodeId,
+    pub definition_name: String,
+    pub inputs: BTreeMap<String, TraceValue>,
+    pub outputs: BTreeMap<String, TraceValue>,
+    /// Whether the node's last outputs were reused because its inputs didn't
+    /// change.
+    pub cached: bool,
+    /// How long the node took on the CPU, in microseconds. GPU work it
+    /// submitted may finish later.
+    pub duration_micros: u64,
+}
+
+/// A [NodeValue] as recorded in a trace. Frames are recorded by size since
+/// their contents are on the GPU.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TraceValue {
+    Frame { width: u32, height: u32 },
+    Midi(String),
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Dimensions(u32, u32),
+    Pixel([f32; 4]),
+    Text(String),
+    Enum(usize),
+    File(PathBuf),
+    FloatArray(Vec<f32>),
+}
+
+impl From<&NodeValue> for TraceValue {
+    fn from(value: &NodeValue) -> Self {
+        match value {
+            NodeValue::Frame(frame) => TraceValue::Frame {
+                width: frame.size.width,
+                height: frame.size.height,
+            },
+            NodeValue::Midi(packet) => TraceValue::Midi(format!("{packet:?}")),
+            NodeValue::Bool(value) => TraceValue::Bool(*value),
+            NodeValue::Int(value) => TraceValue::Int(*value),
+            NodeValue::Float(value) => TraceValue::Float(*value),
+            NodeValue::Dimensions(width, height) => TraceValue::Dimensions(*width, *height),
+            NodeValue::Pixel(value) => TraceValue::Pixel(*value),
+            NodeValue::Text(text) => TraceValue::Text(text.clone()),
+            NodeValue::Enum(index) => TraceValue::Enum(*index),
+            NodeValue::File(path) => TraceValue::File(path.clone()),
+            NodeValue::FloatArray(values) => TraceValue::FloatArray(values.clone()),
+        }
+    }
+}
+
+/// Record every value in `values`.
+pub(crate) fn trace_values(values: &HashMap<String, NodeValue>) -> BTreeMap<String, TraceValue> {
+    values
+        .iter()
+        .map(|(name, value)| (name.clone(), TraceValue::from(value)))
+        .collect()
+}
+
+impl ExecutionTrace {
+    /// Start a trace of `graph` with no frames.
+    pub fn new(
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+        output_node_id: Option<EngineNodeId>,
+        output_format: OutputFormat,
+    ) -> Self {
+        Self {
+            app_version: util::version::APP_VERSION.to_string(),
+            node_library_hash: library.content_hash(),
+            graph: graph.clone(),
+            output_node_id,
+            output_resolution: output_format
+                .resolution
+                .map(|resolution| (resolution.width(), resolution.height())),
+            output_fps: output_format.fps.map(|fps| fps.as_frac()),
+            frames: Vec::new(),
+        }
+    }
+
+    /// The output format the trace was recorded with. Values that aren't
+    /// valid are left unset.
+    pub fn output_format(&self) -> OutputFormat {
+        OutputFormat {
+            resolution: self
+                .output_resolution
+                .and_then(|(width, height)| Dimensions::new(width, height)),
+            fps: self
+                .output_fps
+                .and_then(|(num, den)| Fps::from_frac(num, den).ok()),
+        }
+    }
+
+    /// Write the trace to `path` as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TraceError> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Read a trace written by [Self::save].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TraceError> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+}
+
+/// Run the graph in `trace` for as many frames as it has, with a new
+/// [GraphExecutor] rendering `format` textures, and record a new trace of it.
+///
+/// Replaying with a different node library than the trace was recorded with
+/// works, but the results may differ because of it.
+pub fn replay(
+    trace: &ExecutionTrace,
+    library: &NodeLibrary,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: wgpu::TextureFormat,
+) -> ExecutionTrace {
+    if library.content_hash() != trace.node_library_hash {
+        util::debug_log_warning!("Replaying a trace recorded with a different node library.");
+    }
+
+    let mut executor = GraphExecutor::new(format);
+    executor.set_output_format(trace.output_format());
+
+    let mut replayed = ExecutionTrace::new(
+        &trace.graph,
+        library,
+        trace.output_node_id,
+        trace.output_format(),
+    );
+    for _ in 0..trace.frames.len() {
+        executor.trace_next_execution();
+        let error = executor
+            .execute(
+                &trace.graph,
+                library,
+                device,
+                queue,
+                trace.output_node_id,
+                |_| {},
+            )
+            .err()
+            .map(|err| err.to_string());
+        replayed.frames.push(TraceFrame {
+            nodes: executor.take_trace().unwrap_or_default(),
+            error,
+        });
+    }
+    replayed
+}
+
+/// [replay] on a GPU device of its own, without a window.
+pub fn replay_headless(
+    trace: &ExecutionTrace,
+    library: &NodeLibrary,
+) -> Result<ExecutionTrace, TraceError> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }))
+    .map_err(|e| TraceError::NoDevice(e.to_string()))?;
+    let (device, queue) =
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+            .map_err(|e| TraceError::NoDevice(e.to_string()))?;
+
+    Ok(replay(
+        trace,
+        library,
+        &device,
+        &queue,
+        wgpu::TextureFormat::Rgba8Unorm,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- ExecutionTrace::save() ---
+
+    #[test]
+    fn test_save_and_load() {
+        let output_format = OutputFormat {
+            resolution: Dimensions::new(1920, 1080),
+            fps: Fps::from_frac(30000, 1001).ok(),
+        };
+        let mut trace = ExecutionTrace::new(
+            &NodeGraph::default(),
+            &NodeLibrary::default(),
+            None,
+            output_format,
+        );
+        trace.frames.push(TraceFrame {
+            nodes: vec![TraceNode {
+                node_id: EngineNodeId::default(),
+                definition_name: "Brightness".to_string(),
+                inputs: BTreeMap::from([
+                    ("Amount".to_string(), TraceValue::Float(0.5)),
+                    (
+                        "Input".to_string(),
+                        TraceValue::Frame {
+                            width: 1920,
+                            height: 1080,
+                        },
+                    ),
+                ]),
+                outputs: BTreeMap::from([(
+                    "Output".to_string(),
+                    TraceValue::Frame {
+                        width: 1920,
+                        height: 1080,
+                    },
+                )]),
+                cached: false,
+                duration_micros: 120,
+            }],
+            error: None,
+        });
+        trace.frames.push(TraceFrame {
+            nodes: Vec::new(),
+            error: Some("No output node".to_string()),
+        });
+
+        let path =
+            std::env::temp_dir().join(format!("execution_trace_test_{}.json", std::process::id()));
+        trace.save(&path).unwrap();
+        let loaded = ExecutionTrace::load(&path).unwrap();
+        _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.frames, trace.frames);
+        assert_eq!(loaded.node_library_hash, trace.node_library_hash);
+        assert_eq!(loaded.output_format(), output_format);
+    }
+}
@@ -0,0 +1,578 @@
+//! Rendering a graph to files. Unlike the live engine, an export runs the
+//! graph as fast as the GPU allows instead of in real time, and every frame
+//! is read back from the GPU and written out, so nothing is dropped.
+//!
+//! [render] runs an [ExportJob] with a new [GraphExecutor] and hands each
+//! output frame to a [FrameSink], which decides what's written:
+//!
+//! - [ImageSequenceSink] writes every frame as a numbered PNG or EXR file.
+//! - [VideoSink] encodes frames into a video file, on the GPU's encoder if
+//!   it has one and [VideoSettings::hardware] is set.
+//!
+//! Besides the output node's frames, a job can export [RenderPass]es: other
+//! nodes' frame outputs, rendered with the same frame and handed to the sink
+//! separately (e.g. a "mask" or "glow-only" layer for compositing).
+//!
+//! A job can be written in segments of [ExportJob::segment_frames] frames.
+//! Each finished segment is recorded in a manifest next to the export's
+//! files, so running a job that failed again picks up after the last segment
+//! that was finished intact, and the segments are joined when it's done.
+//!
+//! [render_distributed] splits a video export into chunks rendered by worker
+//! instances on other computers (see [run_worker]).
+//!
+//! A video can carry the sound of the files the graph plays, passed through or
+//! mixed down (see [VideoSettings::audio] and [audio_source_paths]), and
+//! normalized to a loudness first.
+//!
+//! [VideoSink] saves an [ExportSummary] next to each video it finishes, and
+//! can decode the video back to check that it isn't corrupt.
+//!
+//! [check_consistency] renders frames the way the live preview does and the
+//! way an export does side by side, to find the node that makes an export
+//! differ from the preview.
+//!
+//! A job's [Watermark] is burned into the output node's frames by a Watermark
+//! node added after it for the export only.
+//!
+//! Frames are rendered at 8 bits per channel like the live preview, since
+//! that's the format node shaders write. Their alpha channel is kept,
+//! premultiplied, or dropped as the job's [AlphaMode] says before they reach
+//! the sink.
+
+mod alpha;
+mod consistency;
+mod farm;
+mod image_sequence;
+mod segments;
+mod summary;
+mod video;
+mod watermark;
+
+pub use crate::watermark_compositor::WatermarkPosition;
+pub use alpha::AlphaMode;
+pub use consistency::{
+    ConsistencyCheck, ConsistencyReport, FrameComparison, NodeDivergence, PixelDelta,
+    check_consistency, check_consistency_headless, sample_frames,
+};
+pub use farm::{FarmProgress, render_distributed, run_worker};
+pub use image_sequence::{ImageSequenceFormat, ImageSequenceSettings, ImageSequenceSink};
+pub use media::encoding::{
+    AudioSource, DEFAULT_LOUDNESS_TARGET, EncodingOptions, EncodingSpeed, HardwareEncoder,
+    Loudness, RateControl, VideoCodec, VideoContainer, bit_rate_for_file_size, hardware_encoders,
+};
+pub use summary::{CodecSummary, ExportSummary, Verification};
+pub use video::{LoudnessReport, VideoSettings, VideoSink};
+pub use watermark::{Watermark, WatermarkMark};
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hasher};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use media::fps::Fps;
+use media::frame::{Dimensions, Frame};
+use thiserror::Error;
+
+use crate::frame_reader::FrameReader;
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::{ExecutionError, GraphExecutor, NodeValue, OutputFormat};
+use crate::node::NodeLibrary;
+use crate::node::engine_node::{BuiltInHandler, NodeExecutionPlan};
+use crate::node_graph::{EngineNodeId, InputValue, NodeGraph};
+use segments::Segments;
+
+/// The format the graph is rendered in, matching headless trace replays.
+const RENDER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// How long the graph may go without outputting a frame. Image and video
+/// sources load in the background, so it may have nothing to output at first.
+const FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait between tries while the graph has nothing to output.
+const FRAME_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("No GPU is available to export with: {0}")]
+    NoDevice(String),
+    #[error("Frame {frame} failed to render: {source}")]
+    Render {
+        frame: u64,
+        #[source]
+        source: ExecutionError,
+    },
+    #[error("The output node didn't output a frame for frame {0}")]
+    NoFrame(u64),
+    #[error("Can't export with these settings: {0}")]
+    Unsupported(String),
+    #[error("More than one render pass is named '{0}'")]
+    DuplicatePass(String),
+    #[error("Render pass '{pass}' has no frame output named '{output}'")]
+    NoPassFrame { pass: String, output: String },
+    #[error("Can't add the watermark: {0}")]
+    Watermark(String),
+    #[error("Distributed render failed: {0}")]
+    Farm(String),
+    #[error("Failed to read frame {frame} back from the GPU: {message}")]
+    Readback { frame: u64, message: String },
+    #[error("Failed to measure the audio's loudness: {0}")]
+    Loudness(String),
+    #[error("Failed to write '{path}': {message}")]
+    Write { path: PathBuf, message: String },
+}
+
+/// What to render.
+#[derive(Debug, Clone)]
+pub struct ExportJob {
+    pub graph: NodeGraph,
+    /// The node whose output frames are exported.
+    pub output_node_id: EngineNodeId,
+    /// The resolution sources are conformed to (see [OutputFormat]), or
+    /// [None] to render at whatever size the graph outputs.
+    pub resolution: Option<Dimensions>,
+    pub fps: Fps,
+    pub frame_count: u64,
+    pub alpha: AlphaMode,
+    /// Other node outputs to export with each frame. Names must be unique.
+    pub passes: Vec<RenderPass>,
+    /// A mark burned into the output node's frames, if any.
+    pub watermark: Option<Watermark>,
+    /// Write the export in segments of this many frames, so it can be
+    /// resumed if it fails (see [render]). [None] writes it in one go.
+    pub segment_frames: Option<u64>,
+}
+
+/// A node's frame output exported alongside the output node's, sometimes
+/// called an AOV.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderPass {
+    /// What the pass is called, e.g. "mask". Sinks use it to tell passes
+    /// apart (like in file names).
+    pub name: String,
+    pub node_id: EngineNodeId,
+    /// The name of the node's frame output to export.
+    pub output: String,
+}
+
+/// Where rendered frames go.
+pub trait FrameSink {
+    /// Called once before anything is rendered, to check that the sink can
+    /// write what `job` renders.
+    fn start(&mut self, _job: &ExportJob) -> Result<(), ExportError> {
+        Ok(())
+    }
+
+    /// Write the frame at `index` (counting from 0). Frames are written in
+    /// order.
+    fn write_frame(&mut self, index: u64, frame: &Frame) -> Result<(), ExportError>;
+
+    /// Write the frame at `index` of the render pass named `pass`. Called
+    /// after [FrameSink::write_frame] for every pass of the job, in order.
+    fn write_pass(&mut self, pass: &str, index: u64, frame: &Frame) -> Result<(), ExportError>;
+
+    /// Called once after the last frame was written.
+    fn finish(&mut self) -> Result<(), ExportError> {
+        Ok(())
+    }
+
+    /// How many times every frame is rendered and written, for sinks that
+    /// go over the frames more than once (like two-pass video encodes).
+    fn encoding_passes(&self) -> u32 {
+        1
+    }
+
+    /// Called before the first frame of each encoding pass is written,
+    /// counting from 0.
+    fn start_encoding_pass(&mut self, _pass: u32) -> Result<(), ExportError> {
+        Ok(())
+    }
+
+    /// Where the manifest of a segmented export is kept, or [None] if the
+    /// sink can't write in segments.
+    fn manifest_path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// A description of the sink's settings. Segments written with different
+    /// settings aren't reused.
+    fn settings_description(&self) -> String {
+        String::new()
+    }
+
+    /// Called before the first frame of `segment` is written.
+    fn start_segment(&mut self, _segment: u64) -> Result<(), ExportError> {
+        Ok(())
+    }
+
+    /// Called after the last frame of a segment was written (or when an
+    /// export is stopped partway through one). Returns the files the segment
+    /// wrote, which mustn't change afterwards.
+    fn finish_segment(&mut self) -> Result<Vec<PathBuf>, ExportError> {
+        Ok(Vec::new())
+    }
+
+    /// Called instead of [FrameSink::finish] once all `count` segments of a
+    /// segmented export were written, whether by this export or an earlier
+    /// one.
+    fn finish_segments(&mut self, _count: u64) -> Result<(), ExportError> {
+        self.finish()
+    }
+}
+
+/// Render `job` into `sink`. `on_frame` is called with how many frames have
+/// been rendered after each one, and stops the export early by returning
+/// `false`. Returns how many frames were written.
+///
+/// Every frame is rendered once per [encoding pass](FrameSink::encoding_passes)
+/// of the sink, and `on_frame` counts them all. An export stopped before its
+/// last pass wrote nothing.
+///
+/// If the job has [ExportJob::segment_frames] set, segments an earlier run
+/// of it wrote intact are kept and rendering picks up after them. The frames
+/// before that still run through the graph (sources and nodes like Time
+/// Remap depend on what came before) but aren't read back or written. An
+/// export stopped early keeps its finished segments for the next run.
+pub fn render(
+    job: &ExportJob,
+    library: &NodeLibrary,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    sink: &mut dyn FrameSink,
+    mut on_frame: impl FnMut(u64) -> bool,
+) -> Result<u64, ExportError> {
+    let encoding_passes = sink.encoding_passes();
+    if encoding_passes > 1 && job.segment_frames.is_some() {
+        return Err(ExportError::Unsupported(
+            "exports encoded in more than one pass can't be written in segments".to_string(),
+        ));
+    }
+    let mut segments = match job.segment_frames {
+        Some(0) => {
+            return Err(ExportError::Unsupported(
+                "segments must be at least 1 frame long".to_string(),
+            ));
+        }
+        Some(segment_frames) => {
+            let manifest_path = sink.manifest_path().ok_or_else(|| {
+                ExportError::Unsupported("this sink can't write in segments".to_string())
+            })?;
+            // Fingerprinted before the watermark is added, since the node
+            // it's drawn by gets a new ID every time.
+            Some(Segments::resume(
+                manifest_path,
+                job,
+                segment_frames,
+                &sink.settings_description(),
+            ))
+        }
+        None => None,
+    };
+    let resume_from = segments
+        .as_ref()
+        .map_or(0, |segments| segments.resume_from().min(job.frame_count));
+
+    let job = prepare(job, library)?;
+    sink.start(&job)?;
+    let mut written = 0;
+    for pass in 0..encoding_passes {
+        sink.start_encoding_pass(pass)?;
+        let rendered_before = pass as u64 * job.frame_count;
+        written = render_frames(
+            &job,
+            library,
+            device,
+            queue,
+            sink,
+            resume_from..job.frame_count,
+            segments.as_mut(),
+            |rendered| on_frame(rendered_before + rendered),
+        )?;
+        if written < job.frame_count {
+            if pass + 1 < encoding_passes {
+                written = 0;
+            }
+            break;
+        }
+    }
+
+    match segments {
+        // The unfinished segment is closed but not recorded, so it's written
+        // again next time.
+        Some(segments) if written < job.frame_count => {
+            if segments.is_open() {
+                sink.finish_segment()?;
+            }
+        }
+        Some(segments) => {
+            sink.finish_segments(segments.count())?;
+            segments.complete()?;
+        }
+        None => sink.finish()?,
+    }
+    Ok(written)
+}
+
+/// [render] on a GPU device of its own, so exports can run on a thread of
+/// their own while the live engine keeps going.
+pub fn render_headless(
+    job: &ExportJob,
+    library: &NodeLibrary,
+    sink: &mut dyn FrameSink,
+    on_frame: impl FnMut(u64) -> bool,
+) -> Result<u64, ExportError> {
+    let (device, queue) = headless_device()?;
+    render(job, library, &device, &queue, sink, on_frame)
+}
+
+/// The files `graph`'s Video Source and Audio Meter nodes read, which are
+/// where an export's [AudioSource]s come from. Each file is listed once, in
+/// the order the nodes run.
+pub fn audio_source_paths(graph: &NodeGraph, library: &NodeLibrary) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for node_id in graph.execution_order().unwrap_or_default() {
+        let Some(instance) = graph.get_instance(node_id) else {
+            continue;
+        };
+        let has_sound = library
+            .get_definition(&instance.definition_name)
+            .is_some_and(|definition| {
+                matches!(
+                    definition.node.executor,
+                    NodeExecutionPlan::BuiltIn(
+                        BuiltInHandler::VideoSource | BuiltInHandler::AudioMeter
+                    )
+                )
+            });
+        if !has_sound {
+            continue;
+        }
+        for value in instance.input_values.values() {
+            if let InputValue::File(path) = value
+                && !path.as_os_str().is_empty()
+                && !paths.contains(path)
+            {
+                paths.push(path.clone());
+            }
+        }
+    }
+    paths
+}
+
+/// A GPU device of its own to export with.
+fn headless_device() -> Result<(wgpu::Device, wgpu::Queue), ExportError> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }))
+    .map_err(|e| ExportError::NoDevice(e.to_string()))?;
+    pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+        .map_err(|e| ExportError::NoDevice(e.to_string()))
+}
+
+/// Check `job` can be rendered, and add its watermark's node to its graph.
+fn prepare<'a>(
+    job: &'a ExportJob,
+    library: &NodeLibrary,
+) -> Result<Cow<'a, ExportJob>, ExportError> {
+    let mut names = HashSet::new();
+    if let Some(pass) = job.passes.iter().find(|pass| !names.insert(&pass.name)) {
+        return Err(ExportError::DuplicatePass(pass.name.clone()));
+    }
+    match &job.watermark {
+        Some(watermark) => Ok(Cow::Owned(watermark.apply_to(job, library)?)),
+        None => Ok(Cow::Borrowed(job)),
+    }
+}
+
+/// Render the frames in `frames` of a [prepared](prepare) `job` into `sink`,
+/// starting and finishing `segments` as they go, and return how many of the
+/// job's frames have been rendered once it's done or `on_frame` stopped it.
+/// The frames before `frames` still run through the graph (sources and nodes
+/// like Time Remap depend on what came before) but aren't read back or
+/// written. `on_frame` is called after those too.
+#[allow(clippy::too_many_arguments)]
+fn render_frames(
+    job: &ExportJob,
+    library: &NodeLibrary,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    sink: &mut dyn FrameSink,
+    frames: Range<u64>,
+    mut segments: Option<&mut Segments>,
+    mut on_frame: impl FnMut(u64) -> bool,
+) -> Result<u64, ExportError> {
+    // Without anything to write there's nothing for the frames before to
+    // lead up to.
+    if frames.is_empty() {
+        return Ok(frames.end);
+    }
+
+    let mut executor = GraphExecutor::new(RENDER_FORMAT);
+    executor.set_output_format(OutputFormat {
+        resolution: job.resolution,
+        fps: Some(job.fps),
+    });
+    executor.set_captured_nodes(job.passes.iter().map(|pass| pass.node_id).collect());
+    let mut reader = FrameReader::new();
+    let mut pass_readers: Vec<FrameReader> =
+        job.passes.iter().map(|_| FrameReader::new()).collect();
+
+    let mut waiting_since = Instant::now();
+    let mut written = 0;
+    while written < frames.end {
+        let (frame, pass_frames) =
+            match render_frame(&mut executor, job, library, device, queue, written)? {
+                Some(frames) => frames,
+                None if waiting_since.elapsed() < FRAME_TIMEOUT => {
+                    thread::sleep(FRAME_POLL_INTERVAL);
+                    continue;
+                }
+                None => return Err(ExportError::NoFrame(written)),
+            };
+        waiting_since = Instant::now();
+        if written < frames.start {
+            written += 1;
+            if !on_frame(written) {
+                break;
+            }
+            continue;
+        }
+        if let Some(segment) = segments
+            .as_mut()
+            .and_then(|segments| segments.start(written))
+        {
+            sink.start_segment(segment)?;
+        }
+
+        let mut frame = read_frame(&mut reader, device, queue, &frame, written)?;
+        job.alpha.apply(&mut frame);
+        sink.write_frame(written, &frame)?;
+        for ((pass, pass_frame), pass_reader) in
+            job.passes.iter().zip(&pass_frames).zip(&mut pass_readers)
+        {
+            let mut pass_frame = read_frame(pass_reader, device, queue, pass_frame, written)?;
+            job.alpha.apply(&mut pass_frame);
+            sink.write_pass(&pass.name, written, &pass_frame)?;
+        }
+
+        written += 1;
+        if let Some(segments) = &mut segments
+            && segments.ends(written, job.frame_count)
+        {
+            segments.finish(sink.finish_segment()?)?;
+        }
+        if !on_frame(written) {
+            break;
+        }
+    }
+    Ok(written)
+}
+
+/// Run the graph once and return its output frame and the frame of each of
+/// the job's render passes, or [None] if it had nothing to output yet.
+fn render_frame(
+    executor: &mut GraphExecutor,
+    job: &ExportJob,
+    library: &NodeLibrary,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    index: u64,
+) -> Result<Option<(GpuFrame, Vec<GpuFrame>)>, ExportError> {
+    let result = executor.execute(
+        &job.graph,
+        library,
+        device,
+        queue,
+        Some(job.output_node_id),
+        |_| {},
+    );
+    let frame = match result {
+        Ok(result) => result.outputs.values().find_map(|value| match value {
+            NodeValue::Frame(frame) => Some(frame.clone()),
+            _ => None,
+        }),
+        Err(ExecutionError::NoOutputProduced | ExecutionError::GpuReadbackNotReady) => None,
+        Err(source) => {
+            return Err(ExportError::Render {
+                frame: index,
+                source,
+            });
+        }
+    };
+    let Some(frame) = frame else {
+        return Ok(None);
+    };
+
+    let mut pass_frames = Vec::with_capacity(job.passes.len());
+    for pass in &job.passes {
+        let Some(outputs) = executor.get_node_outputs(pass.node_id) else {
+            return Ok(None);
+        };
+        match outputs.get(&pass.output) {
+            Some(NodeValue::Frame(frame)) => pass_frames.push(frame.clone()),
+            Some(_) => {
+                return Err(ExportError::NoPassFrame {
+                    pass: pass.name.clone(),
+                    output: pass.output.clone(),
+                });
+            }
+            None => return Ok(None),
+        }
+    }
+    Ok(Some((frame, pass_frames)))
+}
+
+/// Read `frame` back from the GPU at its full size.
+fn read_frame(
+    reader: &mut FrameReader,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    frame: &GpuFrame,
+    index: u64,
+) -> Result<Frame, ExportError> {
+    let dimensions =
+        Dimensions::new(frame.size.width, frame.size.height).ok_or(ExportError::NoFrame(index))?;
+    reader
+        .read_blocking(device, queue, frame, dimensions)
+        .map_err(|e| ExportError::Readback {
+            frame: index,
+            message: e.to_string(),
+        })
+}
+
+/// A hash of `frame`'s pixels. Frames compare by identity, so this is how two
+/// frames are told to be the same.
+fn frame_hash(frame: &Frame) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for row in frame.raw_data_rows() {
+        hasher.write(row);
+    }
+    hasher.finish()
+}
+
+/// `pass` as part of a file name: characters that aren't safe in file names
+/// are replaced with underscores.
+fn pass_file_name(pass: &str) -> String {
+    let name: String = pass
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | ' ') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "_".to_string()
+    } else {
+        name
+    }
+}
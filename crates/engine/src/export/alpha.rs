@@ -0,0 +1,95 @@
+use media::frame::Frame;
+
+/// What's done with the alpha channel of exported frames. The graph outputs
+/// straight (unassociated) alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AlphaMode {
+    /// Drop transparency, as if frames were laid over black.
+    Opaque,
+    /// Keep transparency with colors as they are, like PNG and most video
+    /// expects.
+    #[default]
+    Straight,
+    /// Keep transparency with colors multiplied by alpha, like EXR and most
+    /// compositing tools expect.
+    Premultiplied,
+}
+
+impl AlphaMode {
+    pub const ALL: [Self; 3] = [Self::Opaque, Self::Straight, Self::Premultiplied];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Opaque => "None (opaque)",
+            Self::Straight => "Straight",
+            Self::Premultiplied => "Premultiplied",
+        }
+    }
+
+    /// Whether frames keep their alpha channel.
+    pub fn has_alpha(self) -> bool {
+        self != Self::Opaque
+    }
+
+    /// Convert `frame` from straight alpha to this mode.
+    pub fn apply(self, frame: &mut Frame) {
+        match self {
+            Self::Straight => {}
+            Self::Premultiplied => {
+                for pixel in frame.pixels_mut() {
+                    let [red, green, blue, alpha] = pixel.channels_mut();
+                    premultiply(red, *alpha);
+                    premultiply(green, *alpha);
+                    premultiply(blue, *alpha);
+                }
+            }
+            Self::Opaque => {
+                for pixel in frame.pixels_mut() {
+                    let [red, green, blue, alpha] = pixel.channels_mut();
+                    premultiply(red, *alpha);
+                    premultiply(green, *alpha);
+                    premultiply(blue, *alpha);
+                    *alpha = u8::MAX;
+                }
+            }
+        }
+    }
+}
+
+/// Multiply `channel` by `alpha`, rounding to the nearest value.
+fn premultiply(channel: &mut u8, alpha: u8) {
+    *channel = ((*channel as u32 * alpha as u32 + 127) / 255) as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use media::frame::{Dimensions, Pixel};
+
+    fn apply(mode: AlphaMode, pixel: Pixel) -> [u8; 4] {
+        let mut frame = Frame::from_fill(Dimensions::new(1, 1).unwrap(), pixel);
+        mode.apply(&mut frame);
+        frame.pixels()[0].channels()
+    }
+
+    // --- AlphaMode::apply() ---
+
+    #[test]
+    fn apply_converts_alpha() {
+        let pixel = Pixel::from_rgba(200, 100, 255, 128);
+
+        assert_eq!(apply(AlphaMode::Straight, pixel), [200, 100, 255, 128]);
+        assert_eq!(apply(AlphaMode::Premultiplied, pixel), [100, 50, 128, 128]);
+        assert_eq!(apply(AlphaMode::Opaque, pixel), [100, 50, 128, 255]);
+    }
+
+    #[test]
+    fn apply_keeps_opaque_pixels() {
+        let pixel = Pixel::from_rgba(12, 34, 56, 255);
+
+        for mode in AlphaMode::ALL {
+            assert_eq!(apply(mode, pixel), [12, 34, 56, 255]);
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::thread;
+use std::time::Instant;
+
+use media::frame::Frame;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ExportError, ExportJob, FRAME_POLL_INTERVAL, FRAME_TIMEOUT, RENDER_FORMAT, frame_hash,
+    headless_device, read_frame,
+};
+use crate::execution_trace::TraceValue;
+use crate::frame_reader::FrameReader;
+use crate::graph_executor::{ExecutionError, GraphExecutor, NodeValue, OutputFormat};
+use crate::node::NodeLibrary;
+use crate::node_graph::EngineNodeId;
+
+/// What [check_consistency] compares: the frames of a job rendered the way
+/// the live preview renders them, and the way [render](super::render) does.
+///
+/// The preview runs the graph once per frame interval and takes whatever
+/// sources have ready, while an export waits for each frame and runs the graph
+/// as fast as it can. Graphs that depend on timing (like video sources that
+/// can't decode in real time, or nodes that read the clock) render differently
+/// in the two, which is what this finds.
+#[derive(Debug, Clone)]
+pub struct ConsistencyCheck {
+    /// What's rendered. Render passes and the watermark are left out, and
+    /// [ExportJob::frame_count] is ignored in favor of
+    /// [ConsistencyCheck::samples].
+    pub job: ExportJob,
+    /// The output format the live engine runs with, which may leave the frame
+    /// rate to the sources. The preview is still paced at the job's.
+    pub preview_format: OutputFormat,
+    /// The frames to compare, counting from 0 (see [sample_frames]). Every
+    /// frame up to the last one is rendered.
+    pub samples: Vec<u64>,
+}
+
+/// What a [check_consistency] found.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsistencyReport {
+    /// One comparison per sampled frame, in order.
+    pub frames: Vec<FrameComparison>,
+}
+
+/// How one sampled frame rendered in the preview and the export.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameComparison {
+    pub frame: u64,
+    /// The hash of the output node's frame in each, or [None] if the preview
+    /// hadn't output anything yet.
+    pub preview_hash: Option<u64>,
+    pub export_hash: Option<u64>,
+    /// How far apart the two output frames are, or [None] if they can't be
+    /// compared pixel by pixel (one is missing or their sizes differ).
+    pub delta: Option<PixelDelta>,
+    /// The first node, in the order nodes run, whose outputs differ.
+    pub divergence: Option<NodeDivergence>,
+}
+
+/// How different two frames of the same size are.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PixelDelta {
+    /// The largest difference of any channel of any pixel (out of 255).
+    pub max: u8,
+    /// The mean difference over every channel of every pixel.
+    pub mean: f64,
+    /// How many pixels differ in any channel.
+    pub differing_pixels: u64,
+}
+
+/// The first node whose outputs differ between the preview and the export.
+/// Nodes after it may only differ because of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeDivergence {
+    pub node_id: EngineNodeId,
+    pub definition_name: String,
+    /// The output that differs, or [None] if the node only ran in one of them.
+    pub output: Option<String>,
+    /// How it differs, e.g. "Float(0.5) in the preview, Float(0.53) in the
+    /// export".
+    pub difference: String,
+}
+
+impl FrameComparison {
+    /// Whether the frame rendered the same in the preview and the export.
+    pub fn matches(&self) -> bool {
+        self.preview_hash == self.export_hash && self.divergence.is_none()
+    }
+}
+
+impl ConsistencyReport {
+    /// The first sampled frame that differs, if any do.
+    pub fn first_mismatch(&self) -> Option<&FrameComparison> {
+        self.frames.iter().find(|frame| !frame.matches())
+    }
+}
+
+impl Display for ConsistencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mismatches = self.frames.iter().filter(|frame| !frame.matches()).count();
+        if mismatches == 0 {
+            return write!(
+                f,
+                "All {} sampled frames match between the preview and the export",
+                self.frames.len()
+            );
+        }
+        write!(
+            f,
+            "{mismatches} of {} sampled frames differ between the preview and the export",
+            self.frames.len()
+        )?;
+        for frame in self.frames.iter().filter(|frame| !frame.matches()) {
+            write!(f, "\nFrame {}: ", frame.frame)?;
+            match (frame.preview_hash, frame.delta) {
+                (None, _) => write!(f, "the preview had no frame yet")?,
+                (Some(_), Some(delta)) => write!(
+                    f,
+                    "{} pixels differ, by up to {} ({:.2} on average)",
+                    delta.differing_pixels, delta.max, delta.mean
+                )?,
+                (Some(_), None) => write!(f, "the frames are different sizes")?,
+            }
+            if let Some(divergence) = &frame.divergence {
+                write!(
+                    f,
+                    "; it starts at {} ({})",
+                    divergence.definition_name, divergence.node_id
+                )?;
+                if let Some(output) = &divergence.output {
+                    write!(f, " output \"{output}\"")?;
+                }
+                write!(f, ": {}", divergence.difference)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `count` frames spread evenly over `frame_count`, starting with the first
+/// and ending with the last.
+pub fn sample_frames(frame_count: u64, count: u64) -> Vec<u64> {
+    match count.min(frame_count) {
+        0 => Vec::new(),
+        1 => vec![0],
+        count => (0..count)
+            .map(|i| i * (frame_count - 1) / (count - 1))
+            .collect(),
+    }
+}
+
+/// Render `check`'s job the way the live preview does and the way an export
+/// does side by side, and compare the sampled frames: the output node's
+/// frames by hash and pixel, and every node's outputs in the order they run
+/// to find where they start to differ. `on_frame` is called with how many
+/// frames have been rendered after each one, and stops the check early by
+/// returning `false`.
+///
+/// Frames are rendered in real time, since that's how the preview renders
+/// them, so this takes at least as long as the last sample is from the start.
+pub fn check_consistency(
+    check: &ConsistencyCheck,
+    library: &NodeLibrary,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mut on_frame: impl FnMut(u64) -> bool,
+) -> Result<ConsistencyReport, ExportError> {
+    let job = &check.job;
+    let mut samples = check.samples.clone();
+    samples.sort_unstable();
+    samples.dedup();
+    let mut report = ConsistencyReport { frames: Vec::new() };
+    let Some(&last) = samples.last() else {
+        return Ok(report);
+    };
+    let order = job.graph.execution_order().unwrap_or_default();
+
+    let mut preview = Renderer::new(check.preview_format);
+    preview.executor.set_global_stream_target_fps(job.fps);
+    let mut export = Renderer::new(OutputFormat {
+        resolution: job.resolution,
+        fps: Some(job.fps),
+    });
+
+    let interval = job.fps.interval();
+    let mut waiting_since = Instant::now();
+    let mut next_tick = Instant::now();
+    for frame in 0..=last {
+        while !export.execute(job, library, device, queue, frame)? {
+            if waiting_since.elapsed() >= FRAME_TIMEOUT {
+                return Err(ExportError::NoFrame(frame));
+            }
+            thread::sleep(FRAME_POLL_INTERVAL);
+        }
+        waiting_since = Instant::now();
+
+        // Like the live engine, the preview runs once per tick whether its
+        // sources are ready or not, and falls behind if the export took
+        // longer than a tick.
+        if let Some(wait) = next_tick.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+        preview.execute(job, library, device, queue, frame)?;
+        next_tick = next_tick.max(Instant::now() - interval) + interval;
+
+        if samples.binary_search(&frame).is_ok() {
+            report.frames.push(compare_frame(
+                frame,
+                job,
+                &order,
+                &mut preview,
+                &mut export,
+                device,
+                queue,
+            )?);
+        }
+        if !on_frame(frame + 1) {
+            break;
+        }
+    }
+    Ok(report)
+}
+
+/// [check_consistency] on a GPU device of its own, so it can run on a thread
+/// of its own while the live engine keeps going.
+pub fn check_consistency_headless(
+    check: &ConsistencyCheck,
+    library: &NodeLibrary,
+    on_frame: impl FnMut(u64) -> bool,
+) -> Result<ConsistencyReport, ExportError> {
+    let (device, queue) = headless_device()?;
+    check_consistency(check, library, &device, &queue, on_frame)
+}
+
+/// One of the two ways [check_consistency] renders a graph.
+struct Renderer {
+    executor: GraphExecutor,
+    /// A reader for each frame output that's been read back.
+    readers: HashMap<(EngineNodeId, String), FrameReader>,
+}
+
+impl Renderer {
+    fn new(output_format: OutputFormat) -> Self {
+        let mut executor = GraphExecutor::new(RENDER_FORMAT);
+        executor.set_output_format(output_format);
+        Self {
+            executor,
+            readers: HashMap::new(),
+        }
+    }
+
+    /// Run the graph once and return whether the output node output a frame.
+    fn execute(
+        &mut self,
+        job: &ExportJob,
+        library: &NodeLibrary,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        frame: u64,
+    ) -> Result<bool, ExportError> {
+        let result = self.executor.execute(
+            &job.graph,
+            library,
+            device,
+            queue,
+            Some(job.output_node_id),
+            |_| {},
+        );
+        match result {
+            Ok(result) => Ok(result
+                .outputs
+                .values()
+                .any(|value| matches!(value, NodeValue::Frame(_)))),
+            Err(ExecutionError::NoOutputProduced | ExecutionError::GpuReadbackNotReady) => {
+                Ok(false)
+            }
+            Err(source) => Err(ExportError::Render { frame, source }),
+        }
+    }
+
+    /// Read the frame output `output` of `node_id` back from the GPU, or
+    /// return [None] if it isn't a frame.
+    fn read_output(
+        &mut self,
+        node_id: EngineNodeId,
+        output: &str,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        frame: u64,
+    ) -> Result<Option<Frame>, ExportError> {
+        let Some(NodeValue::Frame(gpu_frame)) = self
+            .executor
+            .get_node_outputs(node_id)
+            .and_then(|outputs| outputs.get(output))
+        else {
+            return Ok(None);
+        };
+        let reader = self
+            .readers
+            .entry((node_id, output.to_string()))
+            .or_default();
+        read_frame(reader, device, queue, gpu_frame, frame).map(Some)
+    }
+
+    /// The names of `node_id`'s outputs, sorted, or [None] if it hasn't run.
+    fn output_names(&self, node_id: EngineNodeId) -> Option<Vec<String>> {
+        let outputs = self.executor.get_node_outputs(node_id)?;
+        let mut names: Vec<String> = outputs.keys().cloned().collect();
+        names.sort();
+        Some(names)
+    }
+
+    /// `node_id`'s output `output` as a trace records it.
+    fn trace_value(&self, node_id: EngineNodeId, output: &str) -> Option<TraceValue> {
+        self.executor
+            .get_node_outputs(node_id)?
+            .get(output)
+            .map(TraceValue::from)
+    }
+}
+
+/// Compare the preview's and the export's outputs for `frame`, which both
+/// just rendered.
+fn compare_frame(
+    frame: u64,
+    job: &ExportJob,
+    order: &[EngineNodeId],
+    preview: &mut Renderer,
+    export: &mut Renderer,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Result<FrameComparison, ExportError> {
+    let output_frame = |renderer: &mut Renderer| -> Result<Option<Frame>, ExportError> {
+        let Some(name) = renderer
+            .output_names(job.output_node_id)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|name| {
+                matches!(
+                    renderer.trace_value(job.output_node_id, name),
+                    Some(TraceValue::Frame { .. })
+                )
+            })
+        else {
+            return Ok(None);
+        };
+        renderer.read_output(job.output_node_id, &name, device, queue, frame)
+    };
+    let preview_frame = output_frame(preview)?;
+    let export_frame = output_frame(export)?;
+
+    let mut divergence = None;
+    for &node_id in order {
+        divergence = compare_node(node_id, job, preview, export, device, queue, frame)?;
+        if divergence.is_some() {
+            break;
+        }
+    }
+
+    Ok(FrameComparison {
+        frame,
+        preview_hash: preview_frame.as_ref().map(frame_hash),
+        export_hash: export_frame.as_ref().map(frame_hash),
+        delta: preview_frame
+            .as_ref()
+            .zip(export_frame.as_ref())
+            .and_then(|(preview, export)| pixel_delta(preview, export)),
+        divergence,
+    })
+}
+
+/// How `node_id`'s outputs differ between the preview and the export, if they
+/// do.
+fn compare_node(
+    node_id: EngineNodeId,
+    job: &ExportJob,
+    preview: &mut Renderer,
+    export: &mut Renderer,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    frame: u64,
+) -> Result<Option<NodeDivergence>, ExportError> {
+    let divergence = |output: Option<&str>, difference: String| NodeDivergence {
+        node_id,
+        definition_name: job
+            .graph
+            .get_instance(node_id)
+            .map(|instance| instance.definition_name.clone())
+            .unwrap_or_default(),
+        output: output.map(str::to_string),
+        difference,
+    };
+
+    let names = match (preview.output_names(node_id), export.output_names(node_id)) {
+        (None, None) => return Ok(None),
+        (Some(_), None) => {
+            return Ok(Some(divergence(
+                None,
+                "it only ran in the preview".to_string(),
+            )));
+        }
+        (None, Some(_)) => {
+            return Ok(Some(divergence(
+                None,
+                "it only ran in the export".to_string(),
+            )));
+        }
+        (Some(mut preview_names), Some(export_names)) => {
+            preview_names.extend(export_names);
+            preview_names.sort();
+            preview_names.dedup();
+            preview_names
+        }
+    };
+
+    for name in &names {
+        let preview_value = preview.trace_value(node_id, name);
+        let export_value = export.trace_value(node_id, name);
+        let both_frames = matches!(
+            (&preview_value, &export_value),
+            (
+                Some(TraceValue::Frame { .. }),
+                Some(TraceValue::Frame { .. })
+            )
+        );
+        let difference = if both_frames && preview_value == export_value {
+            let preview_frame = preview.read_output(node_id, name, device, queue, frame)?;
+            let export_frame = export.read_output(node_id, name, device, queue, frame)?;
+            preview_frame
+                .zip(export_frame)
+                .and_then(|(preview, export)| pixel_delta(&preview, &export))
+                .filter(|delta| delta.differing_pixels > 0)
+                .map(|delta| {
+                    format!(
+                        "{} pixels differ, by up to {}",
+                        delta.differing_pixels, delta.max
+                    )
+                })
+        } else {
+            (preview_value != export_value).then(|| {
+                format!("{preview_value:?} in the preview, {export_value:?} in the export")
+            })
+        };
+        if let Some(difference) = difference {
+            return Ok(Some(divergence(Some(name), difference)));
+        }
+    }
+    Ok(None)
+}
+
+/// How different `a` and `b` are, or [None] if they aren't the same size.
+fn pixel_delta(a: &Frame, b: &Frame) -> Option<PixelDelta> {
+    if a.dimensions() != b.dimensions() {
+        return None;
+    }
+    let mut max = 0;
+    let mut sum = 0u64;
+    let mut channels = 0u64;
+    let mut differing_pixels = 0;
+    for (row_a, row_b) in a.raw_data_rows().zip(b.raw_data_rows()) {
+        for (pixel_a, pixel_b) in row_a.chunks_exact(4).zip(row_b.chunks_exact(4)) {
+            let mut differs = false;
+            for (channel_a, channel_b) in pixel_a.iter().zip(pixel_b) {
+                let delta = channel_a.abs_diff(*channel_b);
+                max = max.max(delta);
+                sum += delta as u64;
+                differs |= delta > 0;
+            }
+            channels += 4;
+            differing_pixels += differs as u64;
+        }
+    }
+    Some(PixelDelta {
+        max,
+        mean: if channels > 0 {
+            sum as f64 / channels as f64
+        } else {
+            0.0
+        },
+        differing_pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use media::frame::{Dimensions, Pixel};
+
+    // --- sample_frames() ---
+
+    #[test]
+    fn samples_are_spread_over_the_frames() {
+        assert_eq!(sample_frames(100, 5), vec![0, 24, 49, 74, 99]);
+        assert_eq!(sample_frames(3, 10), vec![0, 1, 2]);
+        assert_eq!(sample_frames(100, 1), vec![0]);
+        assert!(sample_frames(0, 5).is_empty());
+    }
+
+    // --- pixel_delta() ---
+
+    #[test]
+    fn pixel_delta_counts_differing_pixels() {
+        let dimensions = Dimensions::new(2, 2).unwrap();
+        let black = Frame::from_fill(dimensions, Pixel::from([0, 0, 0, 255]));
+        let mut grey = black.clone();
+        grey[0][0] = Pixel::from([40, 40, 40, 255]);
+
+        let delta = pixel_delta(&black, &grey).unwrap();
+        assert_eq!(delta.max, 40);
+        assert_eq!(delta.differing_pixels, 1);
+        assert_eq!(delta.mean, 120.0 / 16.0);
+        assert_eq!(pixel_delta(&black, &black).unwrap().differing_pixels, 0);
+
+        let small = Frame::from_fill(Dimensions::new(1, 1).unwrap(), Pixel::default());
+        assert_eq!(pixel_delta(&black, &small), None);
+    }
+}
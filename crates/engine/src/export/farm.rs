@@ -0,0 +1,717 @@
+//! Rendering a video export across several computers. A coordinator splits
+//! the job's frames into chunks and hands them out over TCP to workers
+//! ([run_worker]), which render and encode each chunk on their own GPU and
+//! send its files back. Once every chunk is back they're joined into the
+//! video without encoding them again (see [render_distributed]).
+//!
+//! Chunks whose worker fails, disconnects, or goes quiet are handed to
+//! another worker. Workers must run the same app version with the same nodes
+//! as the coordinator, and files the graph uses (videos, images, ...) must be
+//! at the same paths on every computer, like on a shared drive.
+
+mod protocol;
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use util::version::APP_VERSION;
+
+use super::video::part_path;
+use super::{ExportError, ExportJob, FrameSink, VideoSettings, VideoSink};
+use crate::node::NodeLibrary;
+use protocol::{ChunkFile, CoordinatorMessage, FarmJob, WorkerMessage};
+
+/// How long a worker may go without sending anything before it's considered
+/// gone and its chunk is handed to another one. Workers report their
+/// progress while they render.
+const WORKER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often workers report their progress.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a worker waits before asking again when no chunk is free.
+const WAIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the coordinator checks for new workers and finished chunks.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How many times a chunk may fail (or lose its worker) before the whole
+/// export fails, so a chunk that crashes every worker doesn't go around
+/// forever.
+const MAX_CHUNK_ATTEMPTS: u32 = 3;
+
+/// How a distributed export is going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FarmProgress {
+    /// How many workers are connected.
+    pub workers: usize,
+    /// How many frames have been rendered, including those of chunks that
+    /// are still being rendered.
+    pub frames: u64,
+    pub chunks_done: u64,
+    pub chunk_count: u64,
+}
+
+/// Render `job` into the video `settings` describe on the workers that
+/// connect to `listener`, in chunks of `chunk_frames` frames. `on_progress`
+/// is called every so often, and stops the export early by returning
+/// `false`; the chunks done by then that start the video are joined. Returns
+/// how many frames the video has.
+///
+/// Workers can connect (and leave) at any time while the export runs.
+pub fn render_distributed(
+    job: &ExportJob,
+    library: &NodeLibrary,
+    settings: VideoSettings,
+    chunk_frames: u64,
+    listener: TcpListener,
+    mut on_progress: impl FnMut(&FarmProgress) -> bool,
+) -> Result<u64, ExportError> {
+    if chunk_frames == 0 {
+        return Err(ExportError::Unsupported(
+            "chunks must be at least 1 frame long".to_string(),
+        ));
+    }
+    if settings.encoding.is_two_pass() {
+        return Err(ExportError::Unsupported(
+            "two-pass encodes can't be rendered in chunks".to_string(),
+        ));
+    }
+    // Each chunk's sound would start over from the beginning.
+    if !settings.audio.is_empty() {
+        return Err(ExportError::Unsupported(
+            "audio can't be rendered in chunks".to_string(),
+        ));
+    }
+    let job = super::prepare(job, library)?;
+    let mut sink = VideoSink::new(settings.clone());
+    // The chunks are the sink's segments.
+    sink.start(&ExportJob {
+        segment_frames: Some(chunk_frames),
+        ..(*job).clone()
+    })?;
+    listener.set_nonblocking(true).map_err(farm_error)?;
+
+    let coordinator = Arc::new(Coordinator {
+        farm: Mutex::new(Farm::new(job.frame_count, chunk_frames)),
+        job: FarmJob::new(&job, &settings),
+        passes: job.passes.iter().map(|pass| pass.name.clone()).collect(),
+        settings,
+        library_hash: library.content_hash(),
+    });
+
+    let mut next_worker = 0;
+    let stopped = loop {
+        loop {
+            match listener.accept() {
+                Ok((stream, address)) => {
+                    util::debug_log_info!("Render worker {address} connected.");
+                    let worker = next_worker;
+                    next_worker += 1;
+                    coordinator.lock().workers += 1;
+                    let coordinator = Arc::clone(&coordinator);
+                    thread::spawn(move || {
+                        let result = coordinator.serve(stream, worker);
+                        let mut farm = coordinator.lock();
+                        farm.workers -= 1;
+                        let reason = match result {
+                            Ok(()) => "it left".to_string(),
+                            Err(e) => e.to_string(),
+                        };
+                        farm.release_worker(worker, &reason);
+                        util::debug_log_info!("Render worker {address} disconnected: {reason}");
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(farm_error(e)),
+            }
+        }
+
+        let progress = {
+            let mut farm = coordinator.lock();
+            if let Some(error) = farm.error.clone() {
+                farm.finish();
+                return Err(ExportError::Farm(error));
+            }
+            farm.progress()
+        };
+        if progress.chunks_done == progress.chunk_count {
+            on_progress(&progress);
+            break false;
+        }
+        if !on_progress(&progress) {
+            break true;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let (chunks, leftover) = {
+        let mut farm = coordinator.lock();
+        farm.finish();
+        let leading = farm.leading_done();
+        let leftover: Vec<u64> = (leading..farm.chunk_count())
+            .filter(|&chunk| farm.done[chunk as usize])
+            .collect();
+        (leading, leftover)
+    };
+    // Chunks after a gap can't be joined onto the video.
+    for chunk in leftover {
+        for path in coordinator.chunk_paths(chunk) {
+            _ = fs::remove_file(path);
+        }
+    }
+    if chunks == 0 {
+        return Ok(0);
+    }
+    sink.finish_segments(chunks)?;
+    Ok(if stopped {
+        (chunks * chunk_frames).min(job.frame_count)
+    } else {
+        job.frame_count
+    })
+}
+
+/// Render chunks for the coordinator at `address` (see [render_distributed])
+/// until it has none left. `on_chunk` is called with each chunk once its
+/// files were sent. Returns how many chunks this worker rendered.
+///
+/// Chunks are rendered into a temporary folder and removed once sent.
+pub fn run_worker(
+    address: impl ToSocketAddrs,
+    library: &NodeLibrary,
+    mut on_chunk: impl FnMut(u64),
+) -> Result<u64, ExportError> {
+    let mut stream = TcpStream::connect(address).map_err(farm_error)?;
+    stream
+        .set_read_timeout(Some(WORKER_TIMEOUT))
+        .map_err(farm_error)?;
+    protocol::send(
+        &mut stream,
+        &WorkerMessage::Hello {
+            version: APP_VERSION.to_string(),
+            library_hash: library.content_hash(),
+        },
+    )
+    .map_err(farm_error)?;
+
+    let directory = std::env::temp_dir().join(format!(
+        "bio-visualizer-render-worker-{}",
+        std::process::id()
+    ));
+    let (job, settings) = match protocol::receive(&mut stream).map_err(farm_error)? {
+        CoordinatorMessage::Job(job) => job
+            .into_job(directory.join("chunk"))
+            .map_err(ExportError::Farm)?,
+        CoordinatorMessage::Rejected(reason) => {
+            return Err(ExportError::Farm(format!(
+                "the coordinator turned this worker away: {reason}"
+            )));
+        }
+        _ => return Err(unexpected_message()),
+    };
+    fs::create_dir_all(&directory).map_err(|e| ExportError::Write {
+        path: directory.clone(),
+        message: e.to_string(),
+    })?;
+    let (device, queue) = super::headless_device()?;
+
+    let mut rendered = 0;
+    let result = loop {
+        if let Err(e) = protocol::send(&mut stream, &WorkerMessage::Ready) {
+            break Err(farm_error(e));
+        }
+        let (chunk, frames) = match protocol::receive(&mut stream) {
+            Ok(CoordinatorMessage::Chunk { chunk, frames }) => (chunk, frames),
+            Ok(CoordinatorMessage::Wait) => {
+                thread::sleep(WAIT_INTERVAL);
+                continue;
+            }
+            Ok(CoordinatorMessage::Done) => break Ok(rendered),
+            Ok(_) => break Err(unexpected_message()),
+            Err(e) => break Err(farm_error(e)),
+        };
+
+        let rendered_chunk = render_chunk(
+            &job,
+            library,
+            &device,
+            &queue,
+            &settings,
+            chunk,
+            frames,
+            &mut stream,
+        );
+        let sent = match &rendered_chunk {
+            Ok(files) => send_chunk(&mut stream, chunk, files),
+            Err(e) => protocol::send(
+                &mut stream,
+                &WorkerMessage::ChunkFailed {
+                    chunk,
+                    message: e.to_string(),
+                },
+            ),
+        };
+        if let Ok(files) = rendered_chunk {
+            for (_, path) in files {
+                _ = fs::remove_file(path);
+            }
+        }
+        match sent {
+            Ok(()) => {
+                rendered += 1;
+                on_chunk(chunk);
+            }
+            Err(e) => break Err(farm_error(e)),
+        }
+    };
+
+    _ = fs::remove_dir_all(&directory);
+    result
+}
+
+/// Render `frames` of `job` as chunk `chunk` into part files next to the
+/// video `settings` describe, reporting progress on `stream`. Returns the
+/// render pass (or [None] for the video) and path of every file written.
+#[allow(clippy::too_many_arguments)]
+fn render_chunk(
+    job: &ExportJob,
+    library: &NodeLibrary,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    settings: &VideoSettings,
+    chunk: u64,
+    frames: Range<u64>,
+    stream: &mut TcpStream,
+) -> Result<Vec<(Option<String>, PathBuf)>, ExportError> {
+    let mut sink = VideoSink::new(settings.clone());
+    sink.start(job)?;
+    sink.start_segment(chunk)?;
+
+    let start = frames.start;
+    let end = frames.end;
+    let mut last_progress = Instant::now();
+    let rendered = super::render_frames(
+        job,
+        library,
+        device,
+        queue,
+        &mut sink,
+        frames,
+        None,
+        |rendered| {
+            if last_progress.elapsed() < PROGRESS_INTERVAL {
+                return true;
+            }
+            last_progress = Instant::now();
+            let progress = WorkerMessage::Progress {
+                chunk,
+                frames: rendered.saturating_sub(start),
+            };
+            protocol::send(stream, &progress).is_ok()
+        },
+    )?;
+    sink.finish_segment()?;
+    if rendered < end {
+        return Err(ExportError::Farm(
+            "lost the connection to the coordinator".to_string(),
+        ));
+    }
+
+    let mut files = vec![(None, part_path(&settings.file_path(), chunk))];
+    for pass in &job.passes {
+        files.push((
+            Some(pass.name.clone()),
+            part_path(&settings.pass_file_path(&pass.name), chunk),
+        ));
+    }
+    Ok(files)
+}
+
+/// Send the files of `chunk` to the coordinator.
+fn send_chunk(
+    stream: &mut TcpStream,
+    chunk: u64,
+    files: &[(Option<String>, PathBuf)],
+) -> io::Result<()> {
+    let sizes = files
+        .iter()
+        .map(|(pass, path)| {
+            Ok(ChunkFile {
+                pass: pass.clone(),
+                size: fs::metadata(path)?.len(),
+            })
+        })
+        .collect::<io::Result<_>>()?;
+    protocol::send(
+        stream,
+        &WorkerMessage::ChunkDone {
+            chunk,
+            files: sizes,
+        },
+    )?;
+    for (_, path) in files {
+        io::copy(&mut File::open(path)?, stream)?;
+    }
+    Ok(())
+}
+
+/// What the coordinator and the threads serving its workers share.
+struct Coordinator {
+    farm: Mutex<Farm>,
+    job: FarmJob,
+    /// The names of the job's render passes.
+    passes: Vec<String>,
+    settings: VideoSettings,
+    library_hash: u64,
+}
+
+impl Coordinator {
+    fn lock(&self) -> MutexGuard<'_, Farm> {
+        self.farm.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Talk to the worker on `stream` until it leaves or the export is done.
+    fn serve(&self, mut stream: TcpStream, worker: u64) -> io::Result<()> {
+        // Accepted sockets may inherit the listener's non-blocking mode.
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(WORKER_TIMEOUT))?;
+
+        let WorkerMessage::Hello {
+            version,
+            library_hash,
+        } = protocol::receive(&mut stream)?
+        else {
+            return Err(invalid_data("expected a hello"));
+        };
+        let rejection = if version != APP_VERSION {
+            Some(format!(
+                "it runs version {version} but the coordinator runs {APP_VERSION}"
+            ))
+        } else if library_hash != self.library_hash {
+            Some("its nodes don't match the coordinator's".to_string())
+        } else {
+            None
+        };
+        if let Some(rejection) = rejection {
+            protocol::send(
+                &mut stream,
+                &CoordinatorMessage::Rejected(rejection.clone()),
+            )?;
+            return Err(invalid_data(&rejection));
+        }
+        protocol::send(&mut stream, &CoordinatorMessage::Job(self.job.clone()))?;
+
+        loop {
+            match protocol::receive(&mut stream)? {
+                WorkerMessage::Ready => {
+                    let reply = self.lock().assign(worker);
+                    protocol::send(&mut stream, &reply)?;
+                    if matches!(reply, CoordinatorMessage::Done) {
+                        return Ok(());
+                    }
+                }
+                WorkerMessage::Progress { chunk, frames } => {
+                    self.lock().set_progress(worker, chunk, frames);
+                }
+                WorkerMessage::ChunkDone { chunk, files } => {
+                    self.receive_chunk(&mut stream, worker, chunk, &files)?;
+                }
+                WorkerMessage::ChunkFailed { chunk, message } => {
+                    util::debug_log_warning!("Render worker failed chunk {chunk}: {message}");
+                    self.lock().release(worker, chunk, &message);
+                }
+                WorkerMessage::Hello { .. } => return Err(invalid_data("unexpected hello")),
+            }
+        }
+    }
+
+    /// Receive the `files` of `chunk` the worker sends. They're kept if the
+    /// chunk is still the worker's to render, and thrown away otherwise (it
+    /// was handed to another worker, or the export ended).
+    fn receive_chunk(
+        &self,
+        stream: &mut TcpStream,
+        worker: u64,
+        chunk: u64,
+        files: &[ChunkFile],
+    ) -> io::Result<()> {
+        let mut received = Vec::with_capacity(files.len());
+        for file in files {
+            let path = match &file.pass {
+                Some(pass) if self.passes.contains(pass) => self.settings.pass_file_path(pass),
+                Some(pass) => return Err(invalid_data(&format!("unknown render pass '{pass}'"))),
+                None => self.settings.file_path(),
+            };
+            let path = part_path(&path, chunk);
+            let upload_path = path.with_extension("upload");
+            let copied = io::copy(
+                &mut Read::by_ref(stream).take(file.size),
+                &mut File::create(&upload_path)?,
+            )?;
+            if copied != file.size {
+                _ = fs::remove_file(&upload_path);
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            received.push((upload_path, path));
+        }
+
+        let complete = files.iter().any(|file| file.pass.is_none())
+            && self
+                .passes
+                .iter()
+                .all(|pass| files.iter().any(|file| file.pass.as_ref() == Some(pass)));
+        let mut farm = self.lock();
+        if !complete {
+            farm.release(worker, chunk, "the worker didn't send every file");
+        } else if farm.is_rendering(worker, chunk) {
+            for (upload_path, path) in &received {
+                fs::rename(upload_path, path)?;
+            }
+            farm.complete(worker, chunk);
+            return Ok(());
+        }
+        for (upload_path, _) in received {
+            _ = fs::remove_file(upload_path);
+        }
+        Ok(())
+    }
+
+    /// The files of `chunk`: its video and its render passes'.
+    fn chunk_paths(&self, chunk: u64) -> Vec<PathBuf> {
+        std::iter::once(self.settings.file_path())
+            .chain(
+                self.passes
+                    .iter()
+                    .map(|pass| self.settings.pass_file_path(pass)),
+            )
+            .map(|path| part_path(&path, chunk))
+            .collect()
+    }
+}
+
+/// Which chunks are done, which are being rendered and by whom, and which
+/// are waiting for a worker.
+#[derive(Debug)]
+struct Farm {
+    frame_count: u64,
+    chunk_frames: u64,
+    /// Chunks waiting for a worker, in the order they're handed out.
+    pending: VecDeque<u64>,
+    /// The worker rendering each chunk being rendered, and how many of its
+    /// frames it has rendered.
+    rendering: HashMap<u64, (u64, u64)>,
+    done: Vec<bool>,
+    /// How many times each chunk failed or lost its worker.
+    attempts: Vec<u32>,
+    workers: usize,
+    /// Why the export failed, if it did.
+    error: Option<String>,
+    /// Set once the export is done or stopped, after which workers are sent
+    /// away and chunks still coming in are thrown away.
+    finished: bool,
+}
+
+impl Farm {
+    fn new(frame_count: u64, chunk_frames: u64) -> Self {
+        let chunk_count = frame_count.div_ceil(chunk_frames);
+        Self {
+            frame_count,
+            chunk_frames,
+            pending: (0..chunk_count).collect(),
+            rendering: HashMap::new(),
+            done: vec![false; chunk_count as usize],
+            attempts: vec![0; chunk_count as usize],
+            workers: 0,
+            error: None,
+            finished: false,
+        }
+    }
+
+    fn chunk_count(&self) -> u64 {
+        self.done.len() as u64
+    }
+
+    fn frames_of(&self, chunk: u64) -> Range<u64> {
+        let start = chunk * self.chunk_frames;
+        start..(start + self.chunk_frames).min(self.frame_count)
+    }
+
+    /// What to tell `worker` when it asks for a chunk.
+    fn assign(&mut self, worker: u64) -> CoordinatorMessage {
+        if self.finished {
+            return CoordinatorMessage::Done;
+        }
+        match self.pending.pop_front() {
+            Some(chunk) => {
+                self.rendering.insert(chunk, (worker, 0));
+                CoordinatorMessage::Chunk {
+                    chunk,
+                    frames: self.frames_of(chunk),
+                }
+            }
+            None if self.rendering.is_empty() => CoordinatorMessage::Done,
+            None => CoordinatorMessage::Wait,
+        }
+    }
+
+    fn is_rendering(&self, worker: u64, chunk: u64) -> bool {
+        self.rendering
+            .get(&chunk)
+            .is_some_and(|&(rendering_worker, _)| rendering_worker == worker)
+    }
+
+    fn set_progress(&mut self, worker: u64, chunk: u64, frames: u64) {
+        if let Some((rendering_worker, rendered)) = self.rendering.get_mut(&chunk)
+            && *rendering_worker == worker
+        {
+            *rendered = frames;
+        }
+    }
+
+    fn complete(&mut self, worker: u64, chunk: u64) {
+        if self.is_rendering(worker, chunk) {
+            self.rendering.remove(&chunk);
+            self.done[chunk as usize] = true;
+        }
+    }
+
+    /// Hand `chunk` back out since `worker` couldn't finish it, or fail the
+    /// export if it's failed too many times.
+    fn release(&mut self, worker: u64, chunk: u64, reason: &str) {
+        if !self.is_rendering(worker, chunk) {
+            return;
+        }
+        self.rendering.remove(&chunk);
+        let attempts = &mut self.attempts[chunk as usize];
+        *attempts += 1;
+        if *attempts >= MAX_CHUNK_ATTEMPTS {
+            self.error.get_or_insert_with(|| {
+                format!("chunk {chunk} failed {attempts} times, last because {reason}")
+            });
+        } else {
+            self.pending.push_front(chunk);
+        }
+    }
+
+    /// [Farm::release] every chunk `worker` was rendering.
+    fn release_worker(&mut self, worker: u64, reason: &str) {
+        let chunks: Vec<u64> = self
+            .rendering
+            .iter()
+            .filter(|(_, (rendering_worker, _))| *rendering_worker == worker)
+            .map(|(&chunk, _)| chunk)
+            .collect();
+        for chunk in chunks {
+            self.release(worker, chunk, reason);
+        }
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        self.pending.clear();
+        self.rendering.clear();
+    }
+
+    /// How many chunks in a row from the first one are done.
+    fn leading_done(&self) -> u64 {
+        self.done.iter().take_while(|&&done| done).count() as u64
+    }
+
+    fn progress(&self) -> FarmProgress {
+        let done_frames: u64 = (0..self.chunk_count())
+            .filter(|&chunk| self.done[chunk as usize])
+            .map(|chunk| self.frames_of(chunk).count() as u64)
+            .sum();
+        let rendering_frames: u64 = self.rendering.values().map(|&(_, frames)| frames).sum();
+        FarmProgress {
+            workers: self.workers,
+            frames: done_frames + rendering_frames,
+            chunks_done: self.done.iter().filter(|&&done| done).count() as u64,
+            chunk_count: self.chunk_count(),
+        }
+    }
+}
+
+fn farm_error(e: io::Error) -> ExportError {
+    ExportError::Farm(e.to_string())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn unexpected_message() -> ExportError {
+    ExportError::Farm("the coordinator sent an unexpected message".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assigned(message: CoordinatorMessage) -> (u64, Range<u64>) {
+        match message {
+            CoordinatorMessage::Chunk { chunk, frames } => (chunk, frames),
+            other => panic!("expected a chunk, got {other:?}"),
+        }
+    }
+
+    // --- Farm::assign() ---
+
+    #[test]
+    fn assign_hands_out_chunks_in_order() {
+        let mut farm = Farm::new(250, 100);
+        assert_eq!(farm.chunk_count(), 3);
+
+        assert_eq!(assigned(farm.assign(0)), (0, 0..100));
+        assert_eq!(assigned(farm.assign(1)), (1, 100..200));
+        assert_eq!(assigned(farm.assign(0)), (2, 200..250));
+        assert!(matches!(farm.assign(2), CoordinatorMessage::Wait));
+
+        for (worker, chunk) in [(0, 0), (1, 1), (0, 2)] {
+            farm.complete(worker, chunk);
+        }
+        assert!(matches!(farm.assign(2), CoordinatorMessage::Done));
+        assert_eq!(farm.leading_done(), 3);
+        assert_eq!(farm.progress().frames, 250);
+    }
+
+    // --- Farm::release_worker() ---
+
+    #[test]
+    fn lost_chunks_are_reassigned() {
+        let mut farm = Farm::new(200, 100);
+        assert_eq!(assigned(farm.assign(0)).0, 0);
+        assert_eq!(assigned(farm.assign(1)).0, 1);
+        farm.set_progress(0, 0, 40);
+        assert_eq!(farm.progress().frames, 40);
+
+        farm.release_worker(0, "it left");
+        assert_eq!(farm.progress().frames, 0);
+        assert_eq!(assigned(farm.assign(1)).0, 0);
+
+        // The first worker's late upload isn't its to finish anymore.
+        farm.complete(0, 0);
+        assert!(!farm.done[0]);
+        farm.complete(1, 0);
+        assert!(farm.done[0]);
+        assert_eq!(farm.leading_done(), 1);
+    }
+
+    #[test]
+    fn chunks_that_keep_failing_fail_the_export() {
+        let mut farm = Farm::new(100, 100);
+        for attempt in 1..=MAX_CHUNK_ATTEMPTS {
+            assert_eq!(assigned(farm.assign(attempt as u64)).0, 0);
+            farm.release(attempt as u64, 0, "the GPU crashed");
+        }
+        assert!(farm.error.as_ref().is_some_and(|e| e.contains("GPU")));
+        assert!(matches!(farm.assign(9), CoordinatorMessage::Done));
+    }
+}
@@ -0,0 +1,269 @@
+//! How coordinators and workers talk over TCP. Every message is a big-endian
+//! `u32` length followed by that many bytes of JSON. The files of a finished
+//! chunk follow its [WorkerMessage::ChunkDone] as raw bytes, one after
+//! another.
+
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use media::encoding::{EncodingOptions, EncodingSpeed, RateControl, VideoCodec, VideoContainer};
+use media::fps::Fps;
+use media::frame::Dimensions;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::export::{AlphaMode, ExportJob, RenderPass, VideoSettings};
+use crate::node_graph::{EngineNodeId, NodeGraph};
+
+/// Messages longer than this are treated as garbage rather than allocated.
+const MAX_MESSAGE_LEN: u32 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) enum WorkerMessage {
+    /// The first message a worker sends. Workers must run the same app
+    /// version with the same nodes as the coordinator to render the same
+    /// frames.
+    Hello {
+        version: String,
+        library_hash: u64,
+    },
+    /// The worker is ready for a chunk.
+    Ready,
+    /// How many of its chunk's frames the worker has rendered. Also tells the
+    /// coordinator the worker is still there.
+    Progress {
+        chunk: u64,
+        frames: u64,
+    },
+    /// The chunk's files follow, in this order.
+    ChunkDone {
+        chunk: u64,
+        files: Vec<ChunkFile>,
+    },
+    ChunkFailed {
+        chunk: u64,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) enum CoordinatorMessage {
+    /// What to render, in answer to [WorkerMessage::Hello].
+    Job(FarmJob),
+    /// The worker can't render for this coordinator, and why.
+    Rejected(String),
+    /// Render `frames` as chunk `chunk`.
+    Chunk { chunk: u64, frames: Range<u64> },
+    /// No chunk is free right now, but one being rendered elsewhere might
+    /// fail. Ask again in a bit.
+    Wait,
+    /// Every chunk is done (or the export was stopped).
+    Done,
+}
+
+/// One of the files a chunk wrote: its video, or one of its render passes'.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct ChunkFile {
+    /// The render pass the file is of, or [None] for the video.
+    pub pass: Option<String>,
+    pub size: u64,
+}
+
+/// An [ExportJob] and how its video is encoded, as sent to workers. The job's
+/// watermark has already been added to its graph.
+///
+/// Workers always encode in software: their chunks are joined without
+/// encoding them again, which needs them all encoded the same way, and the
+/// workers' GPUs may differ. Jobs are never two-pass encodes, so
+/// [RateControl::Quality] is the only rate control sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct FarmJob {
+    graph: NodeGraph,
+    output_node_id: EngineNodeId,
+    resolution: Option<(u32, u32)>,
+    fps: (u32, u32),
+    frame_count: u64,
+    /// Index into [AlphaMode::ALL].
+    alpha: usize,
+    /// Each pass's name, node, and output.
+    passes: Vec<(String, EngineNodeId, String)>,
+    /// Index into [VideoContainer::ALL].
+    container: usize,
+    /// Index into [VideoCodec::ALL].
+    codec: usize,
+    /// The [RateControl::Quality], if it's set.
+    quality: Option<u8>,
+    keyframe_interval: Option<u32>,
+    /// Index into [EncodingSpeed::ALL].
+    speed: Option<usize>,
+}
+
+impl FarmJob {
+    pub fn new(job: &ExportJob, settings: &VideoSettings) -> Self {
+        Self {
+            graph: job.graph.clone(),
+            output_node_id: job.output_node_id,
+            resolution: job
+                .resolution
+                .map(|resolution| (resolution.width(), resolution.height())),
+            fps: job.fps.as_frac(),
+            frame_count: job.frame_count,
+            alpha: index_of(&AlphaMode::ALL, job.alpha),
+            passes: job
+                .passes
+                .iter()
+                .map(|pass| (pass.name.clone(), pass.node_id, pass.output.clone()))
+                .collect(),
+            container: index_of(&VideoContainer::ALL, settings.container),
+            codec: index_of(&VideoCodec::ALL, settings.codec),
+            quality: match settings.encoding.rate_control {
+                Some(RateControl::Quality(quality)) => Some(quality),
+                _ => None,
+            },
+            keyframe_interval: settings.encoding.keyframe_interval,
+            speed: settings
+                .encoding
+                .speed
+                .map(|speed| index_of(&EncodingSpeed::ALL, speed)),
+        }
+    }
+
+    /// The job, and settings that encode its video into `path`.
+    pub fn into_job(self, path: PathBuf) -> Result<(ExportJob, VideoSettings), String> {
+        let (num, den) = self.fps;
+        let job = ExportJob {
+            graph: self.graph,
+            output_node_id: self.output_node_id,
+            resolution: match self.resolution {
+                Some((width, height)) => {
+                    Some(Dimensions::new(width, height).ok_or("invalid resolution".to_string())?)
+                }
+                None => None,
+            },
+            fps: Fps::from_frac(num, den).map_err(|e| format!("invalid frame rate: {e}"))?,
+            frame_count: self.frame_count,
+            alpha: *AlphaMode::ALL
+                .get(self.alpha)
+                .ok_or("unknown alpha mode".to_string())?,
+            passes: self
+                .passes
+                .into_iter()
+                .map(|(name, node_id, output)| RenderPass {
+                    name,
+                    node_id,
+                    output,
+                })
+                .collect(),
+            watermark: None,
+            segment_frames: None,
+        };
+        let settings = VideoSettings {
+            path,
+            container: *VideoContainer::ALL
+                .get(self.container)
+                .ok_or("unknown container".to_string())?,
+            codec: *VideoCodec::ALL
+                .get(self.codec)
+                .ok_or("unknown codec".to_string())?,
+            encoding: EncodingOptions {
+                rate_control: self.quality.map(RateControl::Quality),
+                keyframe_interval: self.keyframe_interval,
+                speed: match self.speed {
+                    Some(speed) => Some(
+                        *EncodingSpeed::ALL
+                            .get(speed)
+                            .ok_or("unknown encoding speed".to_string())?,
+                    ),
+                    None => None,
+                },
+                hardware: false,
+            },
+            audio: Vec::new(),
+            loudness_target: None,
+        };
+        Ok((job, settings))
+    }
+}
+
+fn index_of<T: PartialEq>(all: &[T], value: T) -> usize {
+    all.iter()
+        .position(|item| *item == value)
+        .expect("`all` has every value")
+}
+
+/// Send `message` as a length-prefixed JSON message.
+pub(super) fn send<T: Serialize>(stream: &mut impl Write, message: &T) -> io::Result<()> {
+    let data = serde_json::to_vec(message).map_err(io::Error::other)?;
+    let len = u32::try_from(data.len())
+        .ok()
+        .filter(|&len| len <= MAX_MESSAGE_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "message too long"))?;
+    stream.write_all(&len.to_be_bytes())?;
+    stream.write_all(&data)?;
+    stream.flush()
+}
+
+/// Receive a message sent with [send].
+pub(super) fn receive<T: DeserializeOwned>(stream: &mut impl Read) -> io::Result<T> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message too long",
+        ));
+    }
+
+    let mut data = vec![0; len as usize];
+    stream.read_exact(&mut data)?;
+    serde_json::from_slice(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- send() / receive() ---
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            WorkerMessage::Hello {
+                version: "1.0".to_string(),
+                library_hash: 42,
+            },
+            WorkerMessage::ChunkDone {
+                chunk: 3,
+                files: vec![
+                    ChunkFile {
+                        pass: None,
+                        size: 1024,
+                    },
+                    ChunkFile {
+                        pass: Some("mask".to_string()),
+                        size: 512,
+                    },
+                ],
+            },
+        ];
+        let mut data = Vec::new();
+        for message in &messages {
+            send(&mut data, message).unwrap();
+        }
+
+        let mut reader = data.as_slice();
+        for message in &messages {
+            assert_eq!(&receive::<WorkerMessage>(&mut reader).unwrap(), message);
+        }
+        assert!(receive::<WorkerMessage>(&mut reader).is_err());
+    }
+
+    #[test]
+    fn receive_rejects_huge_messages() {
+        let data = u32::MAX.to_be_bytes();
+        let error = receive::<WorkerMessage>(&mut data.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::{ImageBuffer, ImageFormat, Rgba};
+use media::frame::Frame;
+
+use super::{AlphaMode, ExportError, ExportJob, FrameSink, pass_file_name};
+
+/// How many digits frame numbers are padded to when the pattern doesn't say.
+const DEFAULT_DIGITS: usize = 4;
+
+/// The manifest of a segmented export, in the sequence's folder.
+const MANIFEST_FILE_NAME: &str = "export_manifest.json";
+
+/// The file format of an image sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageSequenceFormat {
+    /// 8 bits per channel PNG.
+    Png,
+    /// 16 bits per channel PNG, for tools that expect it.
+    Png16,
+    /// 32-bit float OpenEXR, in linear light as compositing tools expect.
+    Exr,
+}
+
+impl ImageSequenceFormat {
+    pub const ALL: [Self; 3] = [Self::Png, Self::Png16, Self::Exr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png => "PNG (8-bit)",
+            Self::Png16 => "PNG (16-bit)",
+            Self::Exr => "OpenEXR (32-bit float)",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png | Self::Png16 => "png",
+            Self::Exr => "exr",
+        }
+    }
+}
+
+/// Where an image sequence goes and what its files are called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageSequenceSettings {
+    pub directory: PathBuf,
+    /// The file name without its extension. Each run of `#`s is replaced with
+    /// the frame number, zero-padded to as many digits as there are `#`s
+    /// (e.g. "shot_####" makes "shot_0001"). A pattern without any `#`s gets
+    /// the number added to its end.
+    pub pattern: String,
+    /// The number of the first frame's file.
+    pub start_number: u64,
+    pub format: ImageSequenceFormat,
+}
+
+impl ImageSequenceSettings {
+    /// The name of the file the frame at `index` (counting from 0) is
+    /// written to.
+    pub fn file_name(&self, index: u64) -> String {
+        let number = self.start_number + index;
+        let mut name = String::with_capacity(self.pattern.len() + DEFAULT_DIGITS);
+        let mut digits = 0;
+        for character in self.pattern.chars() {
+            if character == '#' {
+                digits += 1;
+            } else {
+                push_number(&mut name, number, digits);
+                digits = 0;
+                name.push(character);
+            }
+        }
+        push_number(&mut name, number, digits);
+
+        if !self.pattern.contains('#') {
+            if !name.is_empty() {
+                name.push('_');
+            }
+            push_number(&mut name, number, DEFAULT_DIGITS);
+        }
+        format!("{name}.{}", self.format.extension())
+    }
+
+    pub fn file_path(&self, index: u64) -> PathBuf {
+        self.directory.join(self.file_name(index))
+    }
+
+    /// The folder render pass `pass` is written to: a folder named after it
+    /// in [ImageSequenceSettings::directory], so every pass is a sequence of
+    /// its own with the same file names.
+    pub fn pass_directory(&self, pass: &str) -> PathBuf {
+        self.directory.join(pass_file_name(pass))
+    }
+}
+
+/// Writes every frame to its own file (see [ImageSequenceSettings]), and
+/// render passes to folders of their own.
+pub struct ImageSequenceSink {
+    settings: ImageSequenceSettings,
+    /// The render passes whose folders have been created.
+    pass_directories: HashSet<String>,
+    /// Whether frames come premultiplied (see [AlphaMode]).
+    premultiplied: bool,
+    /// The files written since the open segment started, if there is one.
+    segment_files: Option<Vec<PathBuf>>,
+}
+
+impl ImageSequenceSink {
+    /// A sink writing into `settings.directory`, which is created if it
+    /// doesn't exist.
+    pub fn new(settings: ImageSequenceSettings) -> Result<Self, ExportError> {
+        create_directory(&settings.directory)?;
+        Ok(Self {
+            settings,
+            pass_directories: HashSet::new(),
+            premultiplied: false,
+            segment_files: None,
+        })
+    }
+
+    /// Write `frame` to `path` in the sequence's format.
+    fn write_image(&mut self, path: PathBuf, frame: &Frame) -> Result<(), ExportError> {
+        let (width, height) = (frame.dimensions().width(), frame.dimensions().height());
+        let data = frame.raw_data();
+
+        let result = match self.settings.format {
+            ImageSequenceFormat::Png => {
+                ImageBuffer::<Rgba<u8>, Vec<_>>::from_raw(width, height, data.to_vec())
+                    .expect("one pixel per texel")
+                    .save_with_format(&path, ImageFormat::Png)
+            }
+            ImageSequenceFormat::Png16 => ImageBuffer::<Rgba<u16>, Vec<_>>::from_raw(
+                width,
+                height,
+                data.iter().map(|&channel| channel as u16 * 257).collect(),
+            )
+            .expect("one pixel per texel")
+            .save_with_format(&path, ImageFormat::Png),
+            ImageSequenceFormat::Exr => ImageBuffer::<Rgba<f32>, Vec<_>>::from_raw(
+                width,
+                height,
+                data.chunks_exact(4)
+                    .flat_map(|pixel| {
+                        let alpha = pixel[3] as f32 / 255.0;
+                        // Colors are linearized straight and premultiplied
+                        // again after, since the sRGB curve isn't linear.
+                        let linear = |channel: u8| {
+                            if !self.premultiplied {
+                                srgb_to_linear(channel as f32 / 255.0)
+                            } else if alpha > 0.0 {
+                                srgb_to_linear((channel as f32 / 255.0 / alpha).min(1.0)) * alpha
+                            } else {
+                                0.0
+                            }
+                        };
+                        [linear(pixel[0]), linear(pixel[1]), linear(pixel[2]), alpha]
+                    })
+                    .collect(),
+            )
+            .expect("one pixel per texel")
+            .save_with_format(&path, ImageFormat::OpenExr),
+        };
+
+        if let Err(e) = result {
+            return Err(ExportError::Write {
+                path,
+                message: e.to_string(),
+            });
+        }
+        if let Some(segment_files) = &mut self.segment_files {
+            segment_files.push(path);
+        }
+        Ok(())
+    }
+}
+
+impl FrameSink for ImageSequenceSink {
+    fn start(&mut self, job: &ExportJob) -> Result<(), ExportError> {
+        self.premultiplied = job.alpha == AlphaMode::Premultiplied;
+        Ok(())
+    }
+
+    fn write_frame(&mut self, index: u64, frame: &Frame) -> Result<(), ExportError> {
+        self.write_image(self.settings.file_path(index), frame)
+    }
+
+    fn write_pass(&mut self, pass: &str, index: u64, frame: &Frame) -> Result<(), ExportError> {
+        let directory = self.settings.pass_directory(pass);
+        if !self.pass_directories.contains(pass) {
+            create_directory(&directory)?;
+            self.pass_directories.insert(pass.to_string());
+        }
+        self.write_image(directory.join(self.settings.file_name(index)), frame)
+    }
+
+    fn manifest_path(&self) -> Option<PathBuf> {
+        Some(self.settings.directory.join(MANIFEST_FILE_NAME))
+    }
+
+    fn settings_description(&self) -> String {
+        format!("{:?}", self.settings)
+    }
+
+    fn start_segment(&mut self, _segment: u64) -> Result<(), ExportError> {
+        self.segment_files = Some(Vec::new());
+        Ok(())
+    }
+
+    fn finish_segment(&mut self) -> Result<Vec<PathBuf>, ExportError> {
+        Ok(self.segment_files.take().unwrap_or_default())
+    }
+}
+
+fn create_directory(directory: &Path) -> Result<(), ExportError> {
+    fs::create_dir_all(directory).map_err(|e| ExportError::Write {
+        path: directory.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+/// Add `number` zero-padded to `digits` digits to `name`, unless `digits` is 0.
+fn push_number(name: &mut String, number: u64, digits: usize) {
+    if digits > 0 {
+        *name += &format!("{number:0digits$}");
+    }
+}
+
+/// An sRGB channel (from 0 to 1) in linear light.
+fn srgb_to_linear(channel: f32) -> f32 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(pattern: &str, start_number: u64) -> ImageSequenceSettings {
+        ImageSequenceSettings {
+            directory: PathBuf::new(),
+            pattern: pattern.to_string(),
+            start_number,
+            format: ImageSequenceFormat::Png,
+        }
+    }
+
+    // --- ImageSequenceSettings::file_name() ---
+
+    #[test]
+    fn file_name_pads_to_the_pattern() {
+        assert_eq!(settings("shot_####", 1).file_name(0), "shot_0001.png");
+        assert_eq!(settings("shot_####", 1).file_name(41), "shot_0042.png");
+        assert_eq!(settings("#.v2", 1001).file_name(0), "1001.v2.png");
+        assert_eq!(settings("a##_b###", 7).file_name(0), "a07_b007.png");
+        assert_eq!(settings("f_##", 99).file_name(1), "f_100.png");
+    }
+
+    #[test]
+    fn file_name_adds_a_number_without_hashes() {
+        assert_eq!(settings("render", 0).file_name(3), "render_0003.png");
+        assert_eq!(settings("", 0).file_name(3), "0003.png");
+
+        let mut exr = settings("render", 0);
+        exr.format = ImageSequenceFormat::Exr;
+        assert_eq!(exr.file_name(0), "render_0000.exr");
+    }
+
+    // --- ImageSequenceSettings::pass_directory() ---
+
+    #[test]
+    fn pass_directory_is_a_safe_folder_name() {
+        let mut settings = settings("f_####", 1);
+        settings.directory = PathBuf::from("out");
+
+        assert_eq!(settings.pass_directory("mask"), Path::new("out/mask"));
+        assert_eq!(
+            settings.pass_directory(" glow-only "),
+            Path::new("out/glow-only")
+        );
+        assert_eq!(settings.pass_directory("../a/b"), Path::new("out/___a_b"));
+        assert_eq!(settings.pass_directory(""), Path::new("out/_"));
+    }
+}
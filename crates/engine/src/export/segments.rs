@@ -0,0 +1,329 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{ExportError, ExportJob};
+
+/// What a segmented export has written so far. It's kept next to the export's
+/// files while it runs and removed once it's done, so one left behind means
+/// the export didn't finish.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+    /// The [job_fingerprint] of what's being exported.
+    fingerprint: u64,
+    segment_frames: u64,
+    /// The files of each finished segment, in order.
+    segments: Vec<Vec<SegmentFile>>,
+}
+
+/// A file a segment wrote, and what it looked like when the segment finished.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SegmentFile {
+    /// Relative to the manifest's folder when the file is inside it.
+    path: PathBuf,
+    size: u64,
+    checksum: u64,
+}
+
+impl SegmentFile {
+    fn new(directory: &Path, path: &Path) -> Result<Self, ExportError> {
+        let write_error = |e: io::Error| ExportError::Write {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        };
+        Ok(Self {
+            path: path.strip_prefix(directory).unwrap_or(path).to_path_buf(),
+            size: fs::metadata(path).map_err(write_error)?.len(),
+            checksum: file_checksum(path).map_err(write_error)?,
+        })
+    }
+
+    /// Whether the file is still there, unchanged.
+    fn is_intact(&self, directory: &Path) -> bool {
+        let path = directory.join(&self.path);
+        fs::metadata(&path).is_ok_and(|metadata| metadata.len() == self.size)
+            && file_checksum(&path).is_ok_and(|checksum| checksum == self.checksum)
+    }
+}
+
+/// Keeps track of a segmented export's segments (see
+/// [ExportJob::segment_frames]) in a manifest, so a failed export can pick up
+/// after the last segment it finished.
+pub(super) struct Segments {
+    manifest_path: PathBuf,
+    manifest: Manifest,
+    /// Whether a segment was started and hasn't finished.
+    open: bool,
+}
+
+impl Segments {
+    /// Pick up the segments an earlier export of `job` left at
+    /// `manifest_path`, up to the first one whose files are missing or were
+    /// changed. Starts over if there's no manifest or it's for something else,
+    /// like a changed graph or different `sink_settings`.
+    pub(super) fn resume(
+        manifest_path: PathBuf,
+        job: &ExportJob,
+        segment_frames: u64,
+        sink_settings: &str,
+    ) -> Self {
+        let fingerprint = job_fingerprint(job, sink_settings);
+        let mut manifest = fs::read(&manifest_path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<Manifest>(&data).ok())
+            .filter(|manifest| {
+                manifest.fingerprint == fingerprint && manifest.segment_frames == segment_frames
+            })
+            .unwrap_or(Manifest {
+                fingerprint,
+                segment_frames,
+                segments: Vec::new(),
+            });
+
+        let directory = manifest_directory(&manifest_path);
+        let intact = manifest
+            .segments
+            .iter()
+            .take_while(|files| files.iter().all(|file| file.is_intact(directory)))
+            .count();
+        manifest.segments.truncate(intact);
+
+        Self {
+            manifest_path,
+            manifest,
+            open: false,
+        }
+    }
+
+    /// The first frame of the first segment that hasn't been written.
+    pub(super) fn resume_from(&self) -> u64 {
+        self.manifest.segments.len() as u64 * self.manifest.segment_frames
+    }
+
+    /// How many segments have been written.
+    pub(super) fn count(&self) -> u64 {
+        self.manifest.segments.len() as u64
+    }
+
+    /// Whether a segment was started and hasn't finished.
+    pub(super) fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// The segment that starts at frame `index`, if one does.
+    pub(super) fn start(&mut self, index: u64) -> Option<u64> {
+        let segment_frames = self.manifest.segment_frames;
+        if !index.is_multiple_of(segment_frames) {
+            return None;
+        }
+        self.open = true;
+        Some(index / segment_frames)
+    }
+
+    /// Whether the open segment ends once `written` frames of `frame_count`
+    /// have been written.
+    pub(super) fn ends(&self, written: u64, frame_count: u64) -> bool {
+        self.open
+            && (written.is_multiple_of(self.manifest.segment_frames) || written == frame_count)
+    }
+
+    /// Record that the open segment finished with `files` written, and save
+    /// the manifest.
+    pub(super) fn finish(&mut self, files: Vec<PathBuf>) -> Result<(), ExportError> {
+        let directory = manifest_directory(&self.manifest_path);
+        let files = files
+            .iter()
+            .map(|path| SegmentFile::new(directory, path))
+            .collect::<Result<_, _>>()?;
+        self.manifest.segments.push(files);
+        self.open = false;
+        self.save()
+    }
+
+    /// Remove the manifest once everything is written.
+    pub(super) fn complete(self) -> Result<(), ExportError> {
+        match fs::remove_file(&self.manifest_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(ExportError::Write {
+                path: self.manifest_path,
+                message: e.to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Write the manifest. It's written next to where it goes and moved over
+    /// the old one, so a crash while writing it can't leave half of one.
+    fn save(&self) -> Result<(), ExportError> {
+        let write_error = |e: io::Error| ExportError::Write {
+            path: self.manifest_path.clone(),
+            message: e.to_string(),
+        };
+        let data = serde_json::to_vec_pretty(&self.manifest)
+            .map_err(|e| write_error(io::Error::other(e)))?;
+        let temporary_path = self.manifest_path.with_extension("tmp");
+        fs::write(&temporary_path, data).map_err(write_error)?;
+        fs::rename(&temporary_path, &self.manifest_path).map_err(write_error)
+    }
+}
+
+fn manifest_directory(manifest_path: &Path) -> &Path {
+    manifest_path.parent().unwrap_or(Path::new(""))
+}
+
+/// A hash of everything about `job` and the sink's settings that changes what
+/// gets written, so segments are only reused by the export that wrote them.
+fn job_fingerprint(job: &ExportJob, sink_settings: &str) -> u64 {
+    // The graph's instances are in a `HashMap`, whose order changes from run
+    // to run, but JSON objects are sorted by key.
+    let graph = serde_json::to_value(&job.graph)
+        .map(|graph| graph.to_string())
+        .unwrap_or_default();
+    let settings = format!(
+        "{:?} {:?} {:?} {} {:?} {:?} {:?}",
+        job.output_node_id,
+        job.resolution,
+        job.fps,
+        job.frame_count,
+        job.alpha,
+        job.passes,
+        job.watermark,
+    );
+
+    let mut hash = Fnv1a::new();
+    hash.write(graph.as_bytes());
+    hash.write(settings.as_bytes());
+    hash.write(sink_settings.as_bytes());
+    hash.finish()
+}
+
+/// A checksum of the file at `path`.
+pub(super) fn file_checksum(path: &Path) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 1 << 16];
+    let mut hash = Fnv1a::new();
+    loop {
+        match file.read(&mut buffer)? {
+            0 => return Ok(hash.finish()),
+            read => hash.write(&buffer[..read]),
+        }
+    }
+}
+
+/// The FNV-1a hash. It's used instead of [std::hash::DefaultHasher] so that
+/// it's stable across Rust versions, since manifests outlive the process.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(segment_frames: u64) -> Segments {
+        Segments {
+            manifest_path: PathBuf::from("export.json"),
+            manifest: Manifest {
+                fingerprint: 0,
+                segment_frames,
+                segments: Vec::new(),
+            },
+            open: false,
+        }
+    }
+
+    // --- Segments::start() / Segments::ends() ---
+
+    #[test]
+    fn segments_split_at_segment_frames() {
+        let mut segments = segments(500);
+        assert!(!segments.ends(0, 1200));
+
+        assert_eq!(segments.start(0), Some(0));
+        assert_eq!(segments.start(1), None);
+        assert!(!segments.ends(499, 1200));
+        assert!(segments.ends(500, 1200));
+
+        segments.open = false;
+        assert_eq!(segments.start(1000), Some(2));
+        assert!(!segments.ends(1100, 1200));
+        assert!(segments.ends(1200, 1200));
+    }
+
+    // --- Segments::resume() ---
+
+    #[test]
+    fn resume_keeps_intact_segments() {
+        let directory =
+            std::env::temp_dir().join(format!("bio-visualizer-segments-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let files: Vec<PathBuf> = (0..3)
+            .map(|segment| {
+                let path = directory.join(format!("part{segment}"));
+                fs::write(&path, format!("segment {segment}")).unwrap();
+                path
+            })
+            .collect();
+        let manifest_path = directory.join("export.json");
+        let job = ExportJob {
+            graph: Default::default(),
+            output_node_id: Default::default(),
+            resolution: None,
+            fps: media::fps::consts::FPS_30,
+            frame_count: 30,
+            alpha: Default::default(),
+            passes: Vec::new(),
+            watermark: None,
+            segment_frames: Some(10),
+        };
+
+        let mut segments = Segments::resume(manifest_path.clone(), &job, 10, "sink");
+        for (segment, file) in files.iter().enumerate() {
+            segments.start(segment as u64 * 10);
+            segments.finish(vec![file.clone()]).unwrap();
+        }
+        assert_eq!(
+            Segments::resume(manifest_path.clone(), &job, 10, "sink").resume_from(),
+            30
+        );
+
+        fs::write(&files[1], "changed").unwrap();
+        assert_eq!(
+            Segments::resume(manifest_path.clone(), &job, 10, "sink").resume_from(),
+            10
+        );
+        assert_eq!(
+            Segments::resume(manifest_path.clone(), &job, 10, "other sink").resume_from(),
+            0
+        );
+        assert_eq!(
+            Segments::resume(manifest_path.clone(), &job, 5, "sink").resume_from(),
+            0
+        );
+
+        Segments::resume(manifest_path.clone(), &job, 10, "sink")
+            .complete()
+            .unwrap();
+        assert!(!manifest_path.exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
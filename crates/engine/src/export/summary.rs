@@ -0,0 +1,201 @@
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use media::encoding::VideoProbe;
+use serde::{Deserialize, Serialize};
+
+use super::ExportError;
+
+/// What a video export wrote and how it went, saved next to the video (see
+/// [VideoSettings::summary_path](super::VideoSettings::summary_path)).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub path: PathBuf,
+    /// How many frames the video has.
+    pub frames: u64,
+    pub duration_secs: f64,
+    pub fps: f64,
+    /// How many frames the job asked for that aren't in the video, because
+    /// the export was stopped before they were rendered.
+    pub dropped_frames: u64,
+    /// How many frames were exactly the same as the one before them. A
+    /// still shot is all duplicates, but in moving footage they usually mean
+    /// a source couldn't keep up and repeated itself.
+    pub duplicated_frames: u64,
+    /// How many frames this export rendered, counting every encoding pass.
+    /// Frames a segmented export kept from an earlier run aren't counted.
+    pub rendered_frames: u64,
+    /// How long rendering and encoding took, in seconds.
+    pub render_secs: f64,
+    /// [ExportSummary::rendered_frames] per second of
+    /// [ExportSummary::render_secs].
+    pub average_fps: f64,
+    /// In bytes.
+    pub file_size: u64,
+    /// The file's FNV-1a hash, in hex, to check copies of it against.
+    pub checksum: String,
+    pub codec: CodecSummary,
+    /// The result of decoding the whole file back, if it was
+    /// [verified](super::VideoSink::verify).
+    pub verification: Option<Verification>,
+}
+
+/// How an [ExportSummary]'s video was encoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodecSummary {
+    pub container: String,
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    /// The hardware encoder it was encoded with, or [None] for software.
+    pub hardware: Option<String>,
+    /// E.g. "quality 23" or "two-pass at 8000 kbit/s", or [None] if it was
+    /// left to the codec.
+    pub rate_control: Option<String>,
+    pub keyframe_interval: Option<u32>,
+    pub speed: Option<String>,
+    pub alpha: bool,
+    pub audio: bool,
+}
+
+/// What decoding an export's video back found.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Verification {
+    pub decoded_frames: u64,
+    /// What's wrong with the file. There's nothing here if it's intact.
+    pub problems: Vec<String>,
+}
+
+impl Verification {
+    /// Compare what probing a video found with what was written to it.
+    pub(super) fn new(probe: &VideoProbe, frames: u64, audio: bool) -> Self {
+        let mut problems = probe.errors.clone();
+        if probe.frames != frames {
+            problems.push(format!(
+                "{} frames decoded, but {frames} were written",
+                probe.frames
+            ));
+        }
+        if audio && !probe.has_audio {
+            problems.push("the audio track is missing".to_string());
+        }
+        Self {
+            decoded_frames: probe.frames,
+            problems,
+        }
+    }
+
+    /// Whether the file decoded without any problems.
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl ExportSummary {
+    /// Write the summary to `path` as JSON.
+    pub(super) fn save(&self, path: &Path) -> Result<(), ExportError> {
+        let write_error = |message: String| ExportError::Write {
+            path: path.to_path_buf(),
+            message,
+        };
+        let json = serde_json::to_string_pretty(self).map_err(|e| write_error(e.to_string()))?;
+        fs::write(path, json).map_err(|e| write_error(e.to_string()))
+    }
+}
+
+impl Display for ExportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} frames ({:.2} s at {:.2} fps), rendered in {:.1} s ({:.1} fps on average)",
+            self.frames, self.duration_secs, self.fps, self.render_secs, self.average_fps
+        )?;
+        if self.dropped_frames > 0 {
+            writeln!(
+                f,
+                "{} frame(s) were dropped when the export was stopped",
+                self.dropped_frames
+            )?;
+        }
+        if self.duplicated_frames > 0 {
+            writeln!(
+                f,
+                "{} frame(s) were the same as the one before them",
+                self.duplicated_frames
+            )?;
+        }
+
+        let codec = &self.codec;
+        write!(
+            f,
+            "{} in {}, {}x{}",
+            codec.codec, codec.container, codec.width, codec.height
+        )?;
+        for setting in [&codec.rate_control, &codec.speed].into_iter().flatten() {
+            write!(f, ", {setting}")?;
+        }
+        if let Some(interval) = codec.keyframe_interval {
+            write!(f, ", a keyframe at least every {interval} frames")?;
+        }
+        if codec.alpha {
+            write!(f, ", with alpha")?;
+        }
+        if codec.audio {
+            write!(f, ", with audio")?;
+        }
+        match &codec.hardware {
+            Some(hardware) => writeln!(f, ", encoded with {hardware}")?,
+            None => writeln!(f, ", encoded in software")?,
+        }
+
+        write!(
+            f,
+            "{:.1} MB, checksum {}",
+            self.file_size as f64 / 1_000_000.0,
+            self.checksum
+        )?;
+        match &self.verification {
+            Some(verification) if verification.passed() => write!(
+                f,
+                "\nVerified: all {} frames decode without errors",
+                verification.decoded_frames
+            ),
+            Some(verification) => write!(
+                f,
+                "\nVerification failed: {}",
+                verification.problems.join("; ")
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- Verification::new() ---
+
+    #[test]
+    fn verification_finds_missing_frames_and_audio() {
+        let probe = VideoProbe {
+            frames: 90,
+            duration_secs: 3.0,
+            has_audio: false,
+            errors: vec!["frame 91 failed to decode: Invalid data".to_string()],
+        };
+        let verification = Verification::new(&probe, 100, true);
+        assert!(!verification.passed());
+        assert_eq!(verification.decoded_frames, 90);
+        assert_eq!(verification.problems.len(), 3);
+
+        let probe = VideoProbe {
+            frames: 100,
+            has_audio: true,
+            errors: Vec::new(),
+            ..probe
+        };
+        assert!(Verification::new(&probe, 100, true).passed());
+    }
+}
@@ -0,0 +1,691 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use media::encoding::{
+    self, AudioSource, EncodingOptions, EncodingPass, HardwareEncoder, Loudness, RateControl,
+    VideoCodec, VideoContainer, VideoEncoder,
+};
+use media::fps::Fps;
+use media::fps::consts::FPS_30;
+use media::frame::{Dimensions, Frame};
+
+use super::segments::file_checksum;
+use super::summary::{CodecSummary, ExportSummary, Verification};
+use super::{ExportError, ExportJob, FrameSink, frame_hash, pass_file_name};
+
+/// Where a video goes and how it's encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoSettings {
+    /// The video file. Its extension is replaced with the container's if
+    /// it's that of any container, and added otherwise.
+    pub path: PathBuf,
+    pub container: VideoContainer,
+    pub codec: VideoCodec,
+    /// Rate control, speed, and whether the GPU's encoder is used (see
+    /// [VideoEncoder::create]).
+    pub encoding: EncodingOptions,
+    /// The sound the video gets, mixed together if there's more than one
+    /// source. Render passes' videos never have any.
+    pub audio: Vec<AudioSource>,
+    /// The loudness in LUFS to turn the audio up or down to, if any (see
+    /// [LoudnessReport]).
+    pub loudness_target: Option<f64>,
+}
+
+impl VideoSettings {
+    /// The file the video is written to.
+    pub fn file_path(&self) -> PathBuf {
+        let has_video_extension = self.path.extension().is_some_and(|extension| {
+            VideoContainer::ALL
+                .iter()
+                .any(|container| extension.eq_ignore_ascii_case(container.extension()))
+        });
+        if has_video_extension {
+            return self.path.with_extension(self.container.extension());
+        }
+
+        let mut path = self.path.clone().into_os_string();
+        path.push(".");
+        path.push(self.container.extension());
+        path.into()
+    }
+
+    /// The file render pass `pass` is written to, next to the video with the
+    /// pass's name added to its own (e.g. "shot_mask.mov").
+    pub fn pass_file_path(&self, pass: &str) -> PathBuf {
+        let path = self.file_path();
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        path.with_file_name(format!(
+            "{stem}_{}.{}",
+            pass_file_name(pass),
+            self.container.extension()
+        ))
+    }
+
+    /// The manifest of a segmented export, next to the video (e.g.
+    /// "shot.export.json").
+    pub fn manifest_path(&self) -> PathBuf {
+        let path = self.file_path();
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        path.with_file_name(format!("{stem}.export.json"))
+    }
+
+    /// The [ExportSummary] of the video, next to it (e.g.
+    /// "shot.summary.json").
+    pub fn summary_path(&self) -> PathBuf {
+        let path = self.file_path();
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        path.with_file_name(format!("{stem}.summary.json"))
+    }
+
+    /// Check that the container can hold the codec, the codec can keep an
+    /// alpha channel if `alpha` is set, and it has the encoding options that
+    /// are set.
+    pub fn validate(&self, alpha: bool) -> Result<(), ExportError> {
+        encoding::check_video_settings(self.container, self.codec, alpha)
+            .and_then(|()| self.encoding.validate(self.codec))
+            .map_err(|e| ExportError::Unsupported(e.to_string()))
+    }
+}
+
+/// How a video's audio was turned to its [VideoSettings::loudness_target].
+/// The audio is measured before anything is encoded, and one gain is applied
+/// to all of it, so its dynamics are kept.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessReport {
+    pub measured: Loudness,
+    pub target: f64,
+    /// The gain applied, in dB.
+    pub gain: f64,
+    /// Whether the gain was held back to keep the peaks from clipping, which
+    /// leaves the audio quieter than the target.
+    pub peak_limited: bool,
+}
+
+impl Display for LoudnessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(integrated) = self.measured.integrated else {
+            return write!(f, "The audio is silent, so its loudness was left alone");
+        };
+        write!(
+            f,
+            "The audio measured {integrated:.1} LUFS (peaking at {:.1} dBFS) and was turned \
+             {:+.1} dB",
+            self.measured.peak, self.gain
+        )?;
+        if self.peak_limited {
+            write!(
+                f,
+                ", short of {:.1} LUFS to keep it from clipping",
+                self.target
+            )?;
+        } else {
+            write!(f, " to {:.1} LUFS", self.target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encodes frames into a video file, and render passes into video files of
+/// their own (see [VideoSettings::pass_file_path]).
+///
+/// Segmented exports write each segment to part files next to the videos
+/// (e.g. "shot.part0003.mov"), which are joined without encoding them again
+/// once every segment is written.
+///
+/// Two-pass encodes take two [encoding passes](FrameSink::encoding_passes).
+/// The first only analyzes the frames, and the second writes the videos.
+///
+/// Once the video is finished, an [ExportSummary] of it is saved next to it
+/// (see [VideoSettings::summary_path]).
+pub struct VideoSink {
+    settings: VideoSettings,
+    fps: Fps,
+    alpha: bool,
+    /// The job's frame count, resolution, and segment length.
+    frame_count: u64,
+    resolution: Option<Dimensions>,
+    segment_frames: Option<u64>,
+    started: Option<Instant>,
+    /// The job's render passes.
+    pass_names: Vec<String>,
+    /// The open segment, if there is one.
+    segment: Option<u64>,
+    /// The encoding pass being written, counting from 0.
+    encoding_pass: u32,
+    /// Why a video asked to be encoded in hardware was encoded in software.
+    software_fallback: Option<String>,
+    loudness: Option<LoudnessReport>,
+    /// The hardware encoder and size of the output node's video.
+    hardware: Option<HardwareEncoder>,
+    dimensions: Option<Dimensions>,
+    /// How many frames were written to the output node's video in this
+    /// encoding pass, and in every pass.
+    written: u64,
+    rendered: u64,
+    /// How many of this pass's frames were the same as the one before them,
+    /// which is recognized by its hash.
+    duplicated: u64,
+    last_frame_hash: Option<u64>,
+    summary: Option<ExportSummary>,
+    /// Created with the first frame, once its size is known.
+    video: Option<VideoEncoder>,
+    passes: HashMap<String, VideoEncoder>,
+}
+
+impl VideoSink {
+    pub fn new(settings: VideoSettings) -> Self {
+        Self {
+            settings,
+            fps: FPS_30,
+            alpha: false,
+            frame_count: 0,
+            resolution: None,
+            segment_frames: None,
+            started: None,
+            pass_names: Vec::new(),
+            segment: None,
+            encoding_pass: 0,
+            software_fallback: None,
+            loudness: None,
+            hardware: None,
+            dimensions: None,
+            written: 0,
+            rendered: 0,
+            duplicated: 0,
+            last_frame_hash: None,
+            summary: None,
+            video: None,
+            passes: HashMap::new(),
+        }
+    }
+
+    /// Why the videos were encoded in software even though
+    /// [VideoSettings::hardware] is set, if any of them were.
+    pub fn software_fallback(&self) -> Option<&str> {
+        self.software_fallback.as_deref()
+    }
+
+    /// How the audio was normalized, once the export has started, if
+    /// [VideoSettings::loudness_target] is set.
+    pub fn loudness(&self) -> Option<LoudnessReport> {
+        self.loudness
+    }
+
+    /// What the export wrote, once it has finished (and written at least one
+    /// frame).
+    pub fn summary(&self) -> Option<&ExportSummary> {
+        self.summary.as_ref()
+    }
+
+    /// Decode the finished video back to check that it isn't corrupt, and
+    /// add what was found to its [summary](VideoSink::summary), which is
+    /// saved again. Does nothing if there's no summary.
+    pub fn verify(&mut self) -> Result<(), ExportError> {
+        let Some(summary) = &mut self.summary else {
+            return Ok(());
+        };
+        let verification = match encoding::probe_video(&summary.path) {
+            Ok(probe) => Verification::new(&probe, summary.frames, summary.codec.audio),
+            Err(e) => Verification {
+                decoded_frames: 0,
+                problems: vec![format!("it can't be read: {e}")],
+            },
+        };
+        summary.verification = Some(verification);
+        summary.save(&self.settings.summary_path())
+    }
+
+    /// Summarize the finished video, which has `frames` frames, and save the
+    /// summary next to it. It's skipped when the video's size isn't known,
+    /// which only happens when a segmented export had nothing left to render.
+    fn write_summary(&mut self, frames: u64) -> Result<(), ExportError> {
+        let Some(dimensions) = self.dimensions.or(self.resolution) else {
+            return Ok(());
+        };
+        let path = self.settings.file_path();
+        let write_error = |e: std::io::Error| ExportError::Write {
+            path: path.clone(),
+            message: e.to_string(),
+        };
+        let file_size = fs::metadata(&path).map_err(write_error)?.len();
+        let checksum = file_checksum(&path).map_err(write_error)?;
+        let render_secs = self
+            .started
+            .map_or(0.0, |started| started.elapsed().as_secs_f64());
+        let fps = self.fps.as_float();
+        let encoding = &self.settings.encoding;
+
+        let summary = ExportSummary {
+            path: path.clone(),
+            frames,
+            duration_secs: frames as f64 / fps,
+            fps,
+            dropped_frames: self.frame_count.saturating_sub(frames),
+            duplicated_frames: self.duplicated,
+            rendered_frames: self.rendered,
+            render_secs,
+            average_fps: if render_secs > 0.0 {
+                self.rendered as f64 / render_secs
+            } else {
+                0.0
+            },
+            file_size,
+            checksum: format!("{checksum:016x}"),
+            codec: CodecSummary {
+                container: self.settings.container.name().to_string(),
+                codec: self.settings.codec.name().to_string(),
+                width: dimensions.width(),
+                height: dimensions.height(),
+                hardware: self.hardware.map(|hardware| hardware.name().to_string()),
+                rate_control: encoding
+                    .rate_control
+                    .map(|rate_control| match rate_control {
+                        RateControl::Quality(quality) => format!("quality {quality}"),
+                        RateControl::TwoPass { bit_rate } => {
+                            format!("two-pass at {} kbit/s", bit_rate / 1000)
+                        }
+                    }),
+                keyframe_interval: encoding.keyframe_interval,
+                speed: encoding.speed.map(|speed| speed.name().to_string()),
+                alpha: self.alpha,
+                audio: !self.settings.audio.is_empty(),
+            },
+            verification: None,
+        };
+        summary.save(&self.settings.summary_path())?;
+        self.summary = Some(summary);
+        Ok(())
+    }
+
+    /// Count `frame` as a duplicate if it's the same as the last frame.
+    fn check_duplicate(&mut self, frame: &Frame) {
+        let hash = frame_hash(frame);
+        if self.last_frame_hash == Some(hash) {
+            self.duplicated += 1;
+        }
+        self.last_frame_hash = Some(hash);
+    }
+
+    /// Measure the audio over the job's `duration_secs` and scale its sources'
+    /// gains to reach [VideoSettings::loudness_target].
+    fn normalize_loudness(&mut self, duration_secs: f64) -> Result<(), ExportError> {
+        let Some(target) = self.settings.loudness_target else {
+            return Ok(());
+        };
+        let measured = encoding::measure_loudness(&self.settings.audio, duration_secs)
+            .map_err(|e| ExportError::Loudness(e.to_string()))?;
+        let Some(measured) = measured else {
+            return Ok(());
+        };
+
+        let (gain, peak_limited) = measured.normalizing_gain(target);
+        let factor = 10f64.powf(gain / 20.0) as f32;
+        for source in &mut self.settings.audio {
+            source.gain *= factor;
+        }
+        self.loudness = Some(LoudnessReport {
+            measured,
+            target,
+            gain,
+            peak_limited,
+        });
+        Ok(())
+    }
+
+    /// The file the video is being written to: a part file while a segment
+    /// is open.
+    fn video_path(&self) -> PathBuf {
+        match self.segment {
+            Some(segment) => part_path(&self.settings.file_path(), segment),
+            None => self.settings.file_path(),
+        }
+    }
+
+    /// The file render pass `pass` is being written to, like
+    /// [VideoSink::video_path].
+    fn pass_path(&self, pass: &str) -> PathBuf {
+        match self.segment {
+            Some(segment) => part_path(&self.settings.pass_file_path(pass), segment),
+            None => self.settings.pass_file_path(pass),
+        }
+    }
+
+    /// Which pass the videos are being encoded in.
+    fn pass(&self) -> EncodingPass {
+        match (self.settings.encoding.is_two_pass(), self.encoding_pass) {
+            (false, _) => EncodingPass::Only,
+            (true, 0) => EncodingPass::First,
+            (true, _) => EncodingPass::Second,
+        }
+    }
+
+    /// Finish every open video and return the files they were written to.
+    fn finish_encoders(&mut self) -> Result<Vec<PathBuf>, ExportError> {
+        let mut paths = Vec::with_capacity(1 + self.passes.len());
+        if let Some(video) = self.video.take() {
+            let path = self.video_path();
+            video.finish().map_err(|e| ExportError::Write {
+                path: path.clone(),
+                message: e.to_string(),
+            })?;
+            paths.push(path);
+        }
+        for (pass, video) in std::mem::take(&mut self.passes) {
+            let path = self.pass_path(&pass);
+            video.finish().map_err(|e| ExportError::Write {
+                path: path.clone(),
+                message: e.to_string(),
+            })?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Open a video for `frame` at `path`, with `audio`'s sound.
+    fn create_encoder(
+        &mut self,
+        path: PathBuf,
+        frame: &Frame,
+        audio: &[AudioSource],
+    ) -> Result<VideoEncoder, ExportError> {
+        let video = VideoEncoder::create(
+            &path,
+            self.settings.container,
+            self.settings.codec,
+            frame.dimensions(),
+            self.fps,
+            self.alpha,
+            &self.settings.encoding,
+            self.pass(),
+            audio,
+        )
+        .map_err(|e| ExportError::Write {
+            path,
+            message: e.to_string(),
+        })?;
+
+        if let Some(reason) = video.software_fallback()
+            && self.software_fallback.is_none()
+        {
+            util::debug_log_warning!("Encoding in software: {reason}");
+            self.software_fallback = Some(reason.to_string());
+        }
+        Ok(video)
+    }
+}
+
+impl FrameSink for VideoSink {
+    fn start(&mut self, job: &ExportJob) -> Result<(), ExportError> {
+        self.settings.validate(job.alpha.has_alpha())?;
+        self.started = Some(Instant::now());
+        self.fps = job.fps;
+        self.alpha = job.alpha.has_alpha();
+        self.frame_count = job.frame_count;
+        self.resolution = job.resolution;
+        self.segment_frames = job.segment_frames;
+        self.pass_names = job.passes.iter().map(|pass| pass.name.clone()).collect();
+        if !self.settings.audio.is_empty() {
+            self.normalize_loudness(job.frame_count as f64 / job.fps.as_float())?;
+        }
+        Ok(())
+    }
+
+    fn write_frame(&mut self, _index: u64, frame: &Frame) -> Result<(), ExportError> {
+        let video = match &mut self.video {
+            Some(video) => video,
+            None => {
+                let audio = self.settings.audio.clone();
+                let video = self.create_encoder(self.video_path(), frame, &audio)?;
+                self.hardware = video.hardware_encoder();
+                self.dimensions = Some(video.dimensions());
+                self.video.insert(video)
+            }
+        };
+        let result = video.write_frame(frame);
+        result.map_err(|e| ExportError::Write {
+            path: self.video_path(),
+            message: e.to_string(),
+        })?;
+        self.written += 1;
+        self.rendered += 1;
+        self.check_duplicate(frame);
+        Ok(())
+    }
+
+    fn write_pass(&mut self, pass: &str, _index: u64, frame: &Frame) -> Result<(), ExportError> {
+        if !self.passes.contains_key(pass) {
+            let video = self.create_encoder(self.pass_path(pass), frame, &[])?;
+            self.passes.insert(pass.to_string(), video);
+        }
+        let result = self
+            .passes
+            .get_mut(pass)
+            .expect("just inserted")
+            .write_frame(frame);
+        result.map_err(|e| ExportError::Write {
+            path: self.pass_path(pass),
+            message: e.to_string(),
+        })
+    }
+
+    fn finish(&mut self) -> Result<(), ExportError> {
+        let paths = self.finish_encoders()?;
+        // An export stopped in its first pass won't have a second one.
+        if self.pass() == EncodingPass::First {
+            for path in paths {
+                encoding::remove_two_pass_stats(path);
+            }
+        } else if self.written > 0 {
+            self.write_summary(self.written)?;
+        }
+        Ok(())
+    }
+
+    fn encoding_passes(&self) -> u32 {
+        if self.settings.encoding.is_two_pass() {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn start_encoding_pass(&mut self, pass: u32) -> Result<(), ExportError> {
+        self.finish_encoders()?;
+        self.encoding_pass = pass;
+        self.written = 0;
+        self.duplicated = 0;
+        self.last_frame_hash = None;
+        Ok(())
+    }
+
+    fn manifest_path(&self) -> Option<PathBuf> {
+        Some(self.settings.manifest_path())
+    }
+
+    fn settings_description(&self) -> String {
+        format!("{:?}", self.settings)
+    }
+
+    fn start_segment(&mut self, segment: u64) -> Result<(), ExportError> {
+        // Each segment's sound would start over from the beginning.
+        if !self.settings.audio.is_empty() {
+            return Err(ExportError::Unsupported(
+                "audio can't be written in segments".to_string(),
+            ));
+        }
+        self.segment = Some(segment);
+        Ok(())
+    }
+
+    fn finish_segment(&mut self) -> Result<Vec<PathBuf>, ExportError> {
+        let paths = self.finish_encoders()?;
+        self.segment = None;
+        Ok(paths)
+    }
+
+    fn finish_segments(&mut self, count: u64) -> Result<(), ExportError> {
+        let paths = std::iter::once(self.settings.file_path()).chain(
+            self.pass_names
+                .iter()
+                .map(|pass| self.settings.pass_file_path(pass)),
+        );
+        for path in paths {
+            let parts: Vec<PathBuf> = (0..count)
+                .map(|segment| part_path(&path, segment))
+                .collect();
+            encoding::concatenate_videos(&parts, &path, self.settings.container).map_err(|e| {
+                ExportError::Write {
+                    path: path.clone(),
+                    message: e.to_string(),
+                }
+            })?;
+            for part in parts {
+                fs::remove_file(&part).map_err(|e| ExportError::Write {
+                    path: part,
+                    message: e.to_string(),
+                })?;
+            }
+        }
+        // Only the first segments are joined when an export is stopped.
+        let frames = self
+            .segment_frames
+            .map_or(self.frame_count, |segment_frames| {
+                (count * segment_frames).min(self.frame_count)
+            });
+        self.write_summary(frames)
+    }
+}
+
+/// The file segment `segment` of the video at `path` is written to, next to
+/// it (e.g. "shot.part0003.mov").
+pub(super) fn part_path(path: &Path, segment: u64) -> PathBuf {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_extension(format!("part{segment:04}.{extension}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(container: VideoContainer, codec: VideoCodec) -> VideoSettings {
+        VideoSettings {
+            path: PathBuf::from("out/shot.mp4"),
+            container,
+            codec,
+            encoding: EncodingOptions::default(),
+            audio: Vec::new(),
+            loudness_target: None,
+        }
+    }
+
+    // --- VideoSettings::validate() ---
+
+    #[test]
+    fn validate_checks_codec_and_alpha() {
+        assert!(
+            settings(VideoContainer::Mov, VideoCodec::ProRes4444)
+                .validate(true)
+                .is_ok()
+        );
+        assert!(
+            settings(VideoContainer::WebM, VideoCodec::Vp9)
+                .validate(true)
+                .is_ok()
+        );
+        assert!(
+            settings(VideoContainer::Mp4, VideoCodec::H264)
+                .validate(false)
+                .is_ok()
+        );
+        assert!(matches!(
+            settings(VideoContainer::Mp4, VideoCodec::H264).validate(true),
+            Err(ExportError::Unsupported(_))
+        ));
+        assert!(matches!(
+            settings(VideoContainer::Mp4, VideoCodec::ProRes4444).validate(false),
+            Err(ExportError::Unsupported(_))
+        ));
+    }
+
+    // --- VideoSettings::pass_file_path() ---
+
+    #[test]
+    fn pass_file_path_is_next_to_the_video() {
+        let settings = settings(VideoContainer::Mov, VideoCodec::ProRes4444);
+
+        assert_eq!(settings.file_path(), PathBuf::from("out/shot.mov"));
+        assert_eq!(
+            settings.pass_file_path("glow only"),
+            PathBuf::from("out/shot_glow only.mov")
+        );
+
+        let mut dotted = settings.clone();
+        dotted.path = PathBuf::from("out/shot.v2");
+        assert_eq!(dotted.file_path(), PathBuf::from("out/shot.v2.mov"));
+        assert_eq!(
+            dotted.pass_file_path("mask"),
+            PathBuf::from("out/shot.v2_mask.mov")
+        );
+    }
+
+    // --- LoudnessReport ---
+
+    #[test]
+    fn loudness_reports_say_when_peaks_held_the_gain_back() {
+        let report = |peak_limited| LoudnessReport {
+            measured: Loudness {
+                integrated: Some(-20.04),
+                peak: -4.0,
+            },
+            target: -14.0,
+            gain: 3.0,
+            peak_limited,
+        };
+        assert_eq!(
+            report(true).to_string(),
+            "The audio measured -20.0 LUFS (peaking at -4.0 dBFS) and was turned +3.0 dB, \
+             short of -14.0 LUFS to keep it from clipping"
+        );
+        assert!(report(false).to_string().ends_with("+3.0 dB to -14.0 LUFS"));
+    }
+
+    // --- part_path() ---
+
+    #[test]
+    fn part_path_numbers_the_segment() {
+        let settings = settings(VideoContainer::Mov, VideoCodec::ProRes4444);
+
+        assert_eq!(
+            part_path(&settings.file_path(), 3),
+            PathBuf::from("out/shot.part0003.mov")
+        );
+        assert_eq!(
+            part_path(&settings.pass_file_path("mask"), 12),
+            PathBuf::from("out/shot_mask.part0012.mov")
+        );
+        assert_eq!(
+            settings.manifest_path(),
+            PathBuf::from("out/shot.export.json")
+        );
+        assert_eq!(
+            settings.summary_path(),
+            PathBuf::from("out/shot.summary.json")
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::PathBuf;
+
+use crate::node::NodeLibrary;
+use crate::node::engine_node::NodeOutputKind;
+use crate::node_graph::InputValue;
+
+use super::{ExportError, ExportJob, WatermarkPosition};
+
+/// The node that draws watermarks (see `nodes/watermark/`).
+const WATERMARK_NODE: &str = "Watermark";
+
+/// A mark burned into every exported frame, like a logo or "PREVIEW". It's
+/// drawn by a Watermark node the export adds after the output node, so the
+/// project's graph (and live output) doesn't have to change. Render passes
+/// aren't marked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watermark {
+    pub mark: WatermarkMark,
+    pub position: WatermarkPosition,
+    /// From 0 (hidden) to 1 (opaque).
+    pub opacity: f32,
+    /// How far the mark is kept from the frame's edges, in pixels.
+    pub margin: f32,
+    /// How many times its own size the mark is drawn.
+    pub scale: f32,
+}
+
+/// What a [Watermark] draws.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatermarkMark {
+    /// An image file, drawn at its own size.
+    Image(PathBuf),
+    /// Text `size` pixels tall in `color`, one line per line of `text`.
+    Text {
+        text: String,
+        size: f32,
+        color: [f32; 4],
+    },
+}
+
+impl Watermark {
+    /// A copy of `job` with a Watermark node drawing this after its output
+    /// node, which becomes the job's output node.
+    pub(super) fn apply_to(
+        &self,
+        job: &ExportJob,
+        library: &NodeLibrary,
+    ) -> Result<ExportJob, ExportError> {
+        if library.get_definition(WATERMARK_NODE).is_none() {
+            return Err(ExportError::Watermark(
+                "the Watermark node isn't installed".to_string(),
+            ));
+        }
+        let output_name = job
+            .graph
+            .get_instance(job.output_node_id)
+            .and_then(|instance| library.get_definition(&instance.definition_name))
+            .and_then(|definition| {
+                definition
+                    .node
+                    .outputs
+                    .iter()
+                    .find(|output| output.kind == NodeOutputKind::Frame)
+            })
+            .map(|output| output.name.clone())
+            .ok_or_else(|| {
+                ExportError::Watermark("the output node has no frame output".to_string())
+            })?;
+
+        let mut job = job.clone();
+        let watermark_node = job.graph.add_instance(WATERMARK_NODE.to_string());
+        job.graph
+            .connect(
+                job.output_node_id,
+                output_name,
+                watermark_node,
+                "Input".to_string(),
+            )
+            .map_err(|e| ExportError::Watermark(e.to_string()))?;
+        for (input_name, value) in self.input_values() {
+            job.graph
+                .set_input_value(watermark_node, input_name.to_string(), value)
+                .map_err(|e| ExportError::Watermark(e.to_string()))?;
+        }
+        job.output_node_id = watermark_node;
+        job.watermark = None;
+        Ok(job)
+    }
+
+    /// The Watermark node's input values that draw this.
+    fn input_values(&self) -> Vec<(&'static str, InputValue)> {
+        let position = WatermarkPosition::ALL
+            .iter()
+            .position(|&position| position == self.position)
+            .expect("every position is in ALL");
+        let mut values = vec![
+            ("Position", InputValue::Enum(position)),
+            ("Opacity", InputValue::Float(self.opacity)),
+            ("Margin", InputValue::Float(self.margin)),
+            ("Scale", InputValue::Float(self.scale)),
+        ];
+        match &self.mark {
+            WatermarkMark::Image(path) => {
+                values.push(("Mark", InputValue::Enum(0)));
+                values.push(("Image", InputValue::File(path.clone())));
+            }
+            WatermarkMark::Text { text, size, color } => {
+                let [r, g, b, a] = *color;
+                values.push(("Mark", InputValue::Enum(1)));
+                values.push(("Text", InputValue::Text(text.clone())));
+                values.push(("Text Size", InputValue::Float(*size)));
+                values.push(("Text Color", InputValue::Pixel { r, g, b, a }));
+            }
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- Watermark::input_values() ---
+
+    #[test]
+    fn input_values_describe_the_mark() {
+        let mut watermark = Watermark {
+            mark: WatermarkMark::Text {
+                text: "PREVIEW".to_string(),
+                size: 48.0,
+                color: [1.0, 0.0, 0.0, 1.0],
+            },
+            position: WatermarkPosition::TopRight,
+            opacity: 0.25,
+            margin: 10.0,
+            scale: 1.0,
+        };
+
+        let values = watermark.input_values();
+        assert!(values.contains(&("Mark", InputValue::Enum(1))));
+        assert!(values.contains(&("Position", InputValue::Enum(1))));
+        assert!(values.contains(&("Text", InputValue::Text("PREVIEW".to_string()))));
+        assert!(values.contains(&("Opacity", InputValue::Float(0.25))));
+
+        watermark.mark = WatermarkMark::Image(PathBuf::from("logo.png"));
+        watermark.position = WatermarkPosition::Center;
+        let values = watermark.input_values();
+        assert!(values.contains(&("Mark", InputValue::Enum(0))));
+        assert!(values.contains(&("Position", InputValue::Enum(4))));
+        assert!(values.contains(&("Image", InputValue::File(PathBuf::from("logo.png")))));
+        assert!(!values.iter().any(|(name, _)| *name == "Text"));
+    }
+}
@@ -0,0 +1,253 @@
+//! Exports [FaceDetector] and its backends, which find faces and their
+//! landmarks in frames (see the Face Detect node).
+//!
+//! [SkinToneDetector] needs no model: it finds face-sized patches of
+//! skin-colored pixels and places landmarks where they usually sit on a face.
+//! It's only a rough stand-in for a real detector. When the crate is built with
+//! the `onnx` feature, [load_detector] can instead load a BlazeFace model (the
+//! short-range one from MediaPipe, exported to ONNX) and run it with ONNX
+//! Runtime.
+
+#[cfg(feature = "onnx")]
+mod blaze_face;
+
+use std::path::{Path, PathBuf};
+
+use media::frame::{Frame, Pixel};
+use serde::Serialize;
+
+/// The fewest pixels, as a fraction of the frame, a patch of skin must cover
+/// for [SkinToneDetector] to call it a face.
+const MIN_SKIN_FACE_AREA: f64 = 0.005;
+
+/// How much taller than wide [SkinToneDetector] lets a face be. Skin below
+/// this (usually the neck) is cut off.
+const MAX_SKIN_FACE_HEIGHT_RATIO: f64 = 1.35;
+
+/// A face found in a frame. Positions are fractions of the frame's width and
+/// height, from its top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FaceDetection {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// How confident the detector is that this is a face, from 0 to 1.
+    pub score: f32,
+    pub landmarks: FaceLandmarks,
+}
+
+impl FaceDetection {
+    /// The center of the face's bounding box.
+    pub fn center(&self) -> [f32; 2] {
+        [self.x + self.width / 2.0, self.y + self.height / 2.0]
+    }
+}
+
+/// Points on a face. Eyes are named from the subject's point of view, so the
+/// right eye is usually on the left of the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FaceLandmarks {
+    pub right_eye: [f32; 2],
+    pub left_eye: [f32; 2],
+    pub nose: [f32; 2],
+    pub mouth: [f32; 2],
+}
+
+impl FaceLandmarks {
+    /// A point in the middle of the forehead: past the eyes on the line from
+    /// the mouth through them, so it follows the head as it tilts.
+    pub fn forehead(&self) -> [f32; 2] {
+        let eyes = [
+            (self.right_eye[0] + self.left_eye[0]) / 2.0,
+            (self.right_eye[1] + self.left_eye[1]) / 2.0,
+        ];
+        [
+            eyes[0] + (eyes[0] - self.mouth[0]) * 0.8,
+            eyes[1] + (eyes[1] - self.mouth[1]) * 0.8,
+        ]
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FaceDetectorError {
+    #[error("face detection models need a build with the 'onnx' feature")]
+    ModelsUnsupported,
+    #[error("failed to load the face detection model '{path}': {message}")]
+    Load { path: PathBuf, message: String },
+    #[error("face detection failed: {0}")]
+    Inference(String),
+}
+
+/// Finds faces in frames.
+pub trait FaceDetector: Send {
+    /// The faces in `frame` scoring at least `min_score`, in no particular
+    /// order.
+    fn detect(
+        &mut self,
+        frame: &Frame,
+        min_score: f32,
+    ) -> Result<Vec<FaceDetection>, FaceDetectorError>;
+}
+
+/// Load the model at `model_path`, or create a [SkinToneDetector] if it's
+/// empty.
+pub fn load_detector(model_path: &Path) -> Result<Box<dyn FaceDetector>, FaceDetectorError> {
+    if model_path.as_os_str().is_empty() {
+        return Ok(Box::new(SkinToneDetector));
+    }
+    load_model(model_path)
+}
+
+#[cfg(feature = "onnx")]
+fn load_model(model_path: &Path) -> Result<Box<dyn FaceDetector>, FaceDetectorError> {
+    Ok(Box::new(blaze_face::BlazeFaceDetector::load(model_path)?))
+}
+
+#[cfg(not(feature = "onnx"))]
+fn load_model(_model_path: &Path) -> Result<Box<dyn FaceDetector>, FaceDetectorError> {
+    Err(FaceDetectorError::ModelsUnsupported)
+}
+
+/// Treats each large enough connected patch of skin-colored pixels as a face.
+/// Hands, arms, and wooden furniture fool it easily, and it can't tell where a
+/// face's features are, so its landmarks are guesses from the patch's shape.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SkinToneDetector;
+
+impl FaceDetector for SkinToneDetector {
+    fn detect(
+        &mut self,
+        frame: &Frame,
+        min_score: f32,
+    ) -> Result<Vec<FaceDetection>, FaceDetectorError> {
+        let width = frame.dimensions().width() as usize;
+        let height = frame.dimensions().height() as usize;
+        let skin: Vec<bool> = frame.pixels().iter().map(is_skin).collect();
+        let min_area = (MIN_SKIN_FACE_AREA * (width * height) as f64).max(1.0) as usize;
+
+        let mut visited = vec![false; skin.len()];
+        let mut stack = Vec::new();
+        let mut faces = Vec::new();
+        for start in 0..skin.len() {
+            if !skin[start] || visited[start] {
+                continue;
+            }
+
+            // Flood fill the patch, tracking its bounds.
+            visited[start] = true;
+            stack.push(start);
+            let (mut area, mut left, mut top, mut right, mut bottom) = (0, width, height, 0, 0);
+            while let Some(index) = stack.pop() {
+                let (x, y) = (index % width, index / width);
+                area += 1;
+                left = left.min(x);
+                right = right.max(x);
+                top = top.min(y);
+                bottom = bottom.max(y);
+
+                let neighbors = [
+                    (x > 0).then(|| index - 1),
+                    (x + 1 < width).then(|| index + 1),
+                    (y > 0).then(|| index - width),
+                    (y + 1 < height).then(|| index + width),
+                ];
+                for neighbor in neighbors.into_iter().flatten() {
+                    if skin[neighbor] && !visited[neighbor] {
+                        visited[neighbor] = true;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            if area < min_area {
+                continue;
+            }
+
+            let box_width = (right - left + 1) as f64;
+            let box_height =
+                ((bottom - top + 1) as f64).min(box_width * MAX_SKIN_FACE_HEIGHT_RATIO);
+            // Faces are roughly elliptical, and an ellipse fills about 79% of
+            // its bounding box; patches that fill much less are probably not
+            // faces.
+            let fill = area as f64 / ((right - left + 1) * (bottom - top + 1)) as f64;
+            let score = (fill / std::f64::consts::FRAC_PI_4).min(1.0) as f32;
+            if score < min_score {
+                continue;
+            }
+
+            let x = left as f32 / width as f32;
+            let y = top as f32 / height as f32;
+            let face_width = box_width as f32 / width as f32;
+            let face_height = box_height as f32 / height as f32;
+            let at = |across: f32, down: f32| [x + face_width * across, y + face_height * down];
+            faces.push(FaceDetection {
+                x,
+                y,
+                width: face_width,
+                height: face_height,
+                score,
+                landmarks: FaceLandmarks {
+                    right_eye: at(0.3, 0.4),
+                    left_eye: at(0.7, 0.4),
+                    nose: at(0.5, 0.6),
+                    mouth: at(0.5, 0.78),
+                },
+            });
+        }
+
+        Ok(faces)
+    }
+}
+
+/// Whether `pixel` is in the range of skin tones in YCbCr space given by Chai
+/// and Ngan, which holds across skin colors since it ignores brightness.
+fn is_skin(pixel: &Pixel) -> bool {
+    let (red, green, blue) = (
+        pixel.red() as f32,
+        pixel.green() as f32,
+        pixel.blue() as f32,
+    );
+    let cb = 128.0 - 0.168736 * red - 0.331264 * green + 0.5 * blue;
+    let cr = 128.0 + 0.5 * red - 0.418688 * green - 0.081312 * blue;
+    (77.0..=127.0).contains(&cb) && (133.0..=173.0).contains(&cr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use media::frame::Dimensions;
+
+    // --- SkinToneDetector::detect() ---
+
+    #[test]
+    fn test_skin_tone_detector() {
+        let skin = Pixel::from_rgb(200, 150, 120);
+        let background = Pixel::from_rgb(30, 60, 200);
+        let frame = Frame::from_fill_with_coords(Dimensions::new(100, 80).unwrap(), |row, col| {
+            if (20..60).contains(&row) && (30..60).contains(&col) || row >= 78 && col < 2 {
+                skin
+            } else {
+                background
+            }
+        });
+
+        let faces = SkinToneDetector.detect(&frame, 0.5).unwrap();
+        assert_eq!(faces.len(), 1, "{faces:?}");
+        let face = faces[0];
+        assert!((face.x - 0.3).abs() < 1e-5);
+        assert!((face.y - 0.25).abs() < 1e-5);
+        assert!((face.width - 0.3).abs() < 1e-5);
+        assert!((face.height - 0.5).abs() < 1e-5);
+        assert_eq!(face.score, 1.0);
+        assert!(face.landmarks.right_eye[0] < face.landmarks.left_eye[0]);
+        assert!(face.landmarks.forehead()[1] < face.landmarks.right_eye[1]);
+
+        assert!(
+            SkinToneDetector
+                .detect(&Frame::from_fill(frame.dimensions(), background), 0.0)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
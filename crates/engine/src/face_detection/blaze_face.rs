@@ -0,0 +1,215 @@
+//! Runs BlazeFace models with ONNX Runtime.
+//!
+//! BlazeFace predicts a box and six keypoints (eyes, nose, mouth, and ears)
+//! relative to each of 896 fixed anchors spread over its 128x128 input, plus a
+//! score for each. Models converted from MediaPipe's TFLite file take NHWC
+//! input and ones exported from PyTorch ports take NCHW; both are supported.
+
+use std::path::Path;
+
+use media::frame::{Dimensions, Frame, RescaleMethod};
+use ort::session::Session;
+use ort::value::Tensor;
+
+use super::{FaceDetection, FaceDetector, FaceDetectorError, FaceLandmarks};
+
+const INPUT_SIZE: u32 = 128;
+
+/// Each anchor's predictions: the box's center and size, then the keypoints.
+const VALUES_PER_ANCHOR: usize = 16;
+
+/// The anchor grids: their strides in input pixels and anchors per cell.
+const ANCHOR_LAYERS: [(u32, usize); 2] = [(8, 2), (16, 6)];
+
+/// Detections overlapping a higher scoring one by more than this (intersection
+/// over union) are the same face.
+const MAX_OVERLAP: f32 = 0.3;
+
+pub struct BlazeFaceDetector {
+    session: Session,
+    /// Whether the model takes NCHW input rather than NHWC.
+    channels_first: bool,
+    /// Anchor centers as uvs in the input.
+    anchors: Vec<[f32; 2]>,
+}
+
+impl BlazeFaceDetector {
+    pub fn load(path: &Path) -> Result<Self, FaceDetectorError> {
+        let load_error = |message: String| FaceDetectorError::Load {
+            path: path.to_path_buf(),
+            message,
+        };
+        let session = Session::builder()
+            .and_then(|mut builder| builder.commit_from_file(path))
+            .map_err(|e| load_error(e.to_string()))?;
+        let input_shape = session
+            .inputs()
+            .first()
+            .and_then(|input| input.dtype().tensor_shape())
+            .ok_or_else(|| load_error("the model has no tensor input".to_string()))?;
+        let channels_first = input_shape.get(1) == Some(&3);
+
+        Ok(Self {
+            session,
+            channels_first,
+            anchors: anchors(),
+        })
+    }
+}
+
+impl FaceDetector for BlazeFaceDetector {
+    fn detect(
+        &mut self,
+        frame: &Frame,
+        min_score: f32,
+    ) -> Result<Vec<FaceDetection>, FaceDetectorError> {
+        let inference_error = |e: ort::Error| FaceDetectorError::Inference(e.to_string());
+
+        // Letterbox the frame into the input so faces aren't squashed.
+        let input_dimensions = Dimensions::new(INPUT_SIZE, INPUT_SIZE).expect("non-zero size");
+        let fitted = frame.dimensions().fit_within(input_dimensions);
+        let left = (INPUT_SIZE - fitted.width()) / 2;
+        let top = (INPUT_SIZE - fitted.height()) / 2;
+        let input_frame = frame.letterbox(input_dimensions, RescaleMethod::Bilinear);
+
+        let area = (INPUT_SIZE * INPUT_SIZE) as usize;
+        let mut data = vec![0.0f32; area * 3];
+        for (i, pixel) in input_frame.pixels().iter().enumerate() {
+            for (channel, value) in [pixel.red(), pixel.green(), pixel.blue()]
+                .into_iter()
+                .enumerate()
+            {
+                let index = if self.channels_first {
+                    channel * area + i
+                } else {
+                    i * 3 + channel
+                };
+                data[index] = value as f32 / 127.5 - 1.0;
+            }
+        }
+        let size = INPUT_SIZE as usize;
+        let shape = if self.channels_first {
+            [1, 3, size, size]
+        } else {
+            [1, size, size, 3]
+        };
+        let input = Tensor::from_array((shape, data)).map_err(inference_error)?;
+        let outputs = self
+            .session
+            .run(ort::inputs![input])
+            .map_err(inference_error)?;
+
+        // The outputs' order differs between conversions, so tell them apart
+        // by shape.
+        let values: Vec<_> = outputs.values().collect();
+        let (mut predictions, mut scores) = (None, None);
+        for value in &values {
+            let (shape, values) = value.try_extract_tensor::<f32>().map_err(inference_error)?;
+            match shape.last() {
+                Some(16) => predictions = Some(values),
+                Some(1) => scores = Some(values),
+                _ => {}
+            }
+        }
+        let (Some(predictions), Some(scores)) = (predictions, scores) else {
+            return Err(FaceDetectorError::Inference(
+                "the model's outputs don't look like BlazeFace's".to_string(),
+            ));
+        };
+        if predictions.len() != self.anchors.len() * VALUES_PER_ANCHOR
+            || scores.len() != self.anchors.len()
+        {
+            return Err(FaceDetectorError::Inference(format!(
+                "the model predicts for {} anchors, not {}",
+                scores.len(),
+                self.anchors.len()
+            )));
+        }
+
+        // Input uvs back to frame uvs, undoing the letterboxing.
+        let to_frame = |[u, v]: [f32; 2]| {
+            [
+                (u * INPUT_SIZE as f32 - left as f32) / fitted.width() as f32,
+                (v * INPUT_SIZE as f32 - top as f32) / fitted.height() as f32,
+            ]
+        };
+        let mut candidates = Vec::new();
+        for ((anchor, prediction), &score) in self
+            .anchors
+            .iter()
+            .zip(predictions.chunks_exact(VALUES_PER_ANCHOR))
+            .zip(scores)
+        {
+            let score = 1.0 / (1.0 + (-score.clamp(-100.0, 100.0)).exp());
+            if score < min_score {
+                continue;
+            }
+
+            let point = |i: usize| {
+                to_frame([
+                    anchor[0] + prediction[i] / INPUT_SIZE as f32,
+                    anchor[1] + prediction[i + 1] / INPUT_SIZE as f32,
+                ])
+            };
+            let center = point(0);
+            let width = prediction[2] / fitted.width() as f32;
+            let height = prediction[3] / fitted.height() as f32;
+            candidates.push(FaceDetection {
+                x: center[0] - width / 2.0,
+                y: center[1] - height / 2.0,
+                width,
+                height,
+                score,
+                landmarks: FaceLandmarks {
+                    right_eye: point(4),
+                    left_eye: point(6),
+                    nose: point(8),
+                    mouth: point(10),
+                },
+            });
+        }
+
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let mut faces: Vec<FaceDetection> = Vec::new();
+        for candidate in candidates {
+            if faces
+                .iter()
+                .all(|face| overlap(face, &candidate) <= MAX_OVERLAP)
+            {
+                faces.push(candidate);
+            }
+        }
+        Ok(faces)
+    }
+}
+
+/// The centers of BlazeFace's anchors, as uvs in its input.
+fn anchors() -> Vec<[f32; 2]> {
+    let mut anchors = Vec::new();
+    for (stride, per_cell) in ANCHOR_LAYERS {
+        let cells = INPUT_SIZE / stride;
+        for y in 0..cells {
+            for x in 0..cells {
+                let center = [
+                    (x as f32 + 0.5) / cells as f32,
+                    (y as f32 + 0.5) / cells as f32,
+                ];
+                anchors.extend(std::iter::repeat_n(center, per_cell));
+            }
+        }
+    }
+    anchors
+}
+
+/// The intersection over union of two detections' boxes.
+fn overlap(a: &FaceDetection, b: &FaceDetection) -> f32 {
+    let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+    let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+    let intersection = width.max(0.0) * height.max(0.0);
+    let union = a.width * a.height + b.width * b.height - intersection;
+    if union > 0.0 {
+        intersection / union
+    } else {
+        0.0
+    }
+}
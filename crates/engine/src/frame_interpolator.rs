@@ -0,0 +1,479 @@
+//! Exports [FrameInterpolator], which synthesizes a frame between two others
+//! with optical flow, so slowed down video moves smoothly instead of
+//! stuttering or cross-fading.
+//!
+//! Interpolation is two passes. The first estimates how each block of the
+//! first frame moved by searching the second frame for the best matching
+//! patch (block matching on luma, at a fraction of the frame's resolution).
+//! The second warps both frames part of the way along that motion and blends
+//! them. Motion larger than the search range or through occlusions falls back
+//! towards a plain blend.
+
+use std::sync::Arc;
+
+/// Estimates motion between the frames. Each fragment is one block of the flow
+/// texture and outputs the UV offset from the first frame to the second.
+const FLOW_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+struct Params {
+    step: vec2<f32>,
+    radius: i32,
+    position: f32,
+    lod: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+@group(0) @binding(0) var frame_sampler: sampler;
+@group(0) @binding(1) var from_texture: texture_2d<f32>;
+@group(0) @binding(2) var to_texture: texture_2d<f32>;
+@group(0) @binding(3) var<uniform> params: Params;
+
+fn luma(color: vec4<f32>) -> f32 {
+    return dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn patch_cost(from_uv: vec2<f32>, to_uv: vec2<f32>) -> f32 {
+    var cost = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * params.step;
+            let a = luma(textureSampleLevel(from_texture, frame_sampler, from_uv + offset, params.lod));
+            let b = luma(textureSampleLevel(to_texture, frame_sampler, to_uv + offset, params.lod));
+            cost += abs(a - b);
+        }
+    }
+    return cost;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Prefer no motion when matches are about as good, so flat areas and
+    // noise don't swim.
+    var best = vec2<f32>(0.0);
+    var best_cost = patch_cost(in.uv, in.uv) - 0.02;
+
+    for (var y = -params.radius; y <= params.radius; y++) {
+        for (var x = -params.radius; x <= params.radius; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * params.step;
+            let cost = patch_cost(in.uv, in.uv + offset);
+            if (cost < best_cost) {
+                best_cost = cost;
+                best = offset;
+            }
+        }
+    }
+
+    return vec4<f32>(best, best_cost, 1.0);
+}
+"#;
+
+/// Warps both frames along the estimated motion and blends them.
+const WARP_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+struct Params {
+    step: vec2<f32>,
+    radius: i32,
+    position: f32,
+    lod: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+@group(0) @binding(0) var frame_sampler: sampler;
+@group(0) @binding(1) var from_texture: texture_2d<f32>;
+@group(0) @binding(2) var to_texture: texture_2d<f32>;
+@group(0) @binding(3) var<uniform> params: Params;
+@group(0) @binding(4) var flow_texture: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let flow = textureSampleLevel(flow_texture, frame_sampler, in.uv, 0.0).xy;
+    let t = params.position;
+    let a = textureSampleLevel(from_texture, frame_sampler, in.uv - flow * t, 0.0);
+    let b = textureSampleLevel(to_texture, frame_sampler, in.uv + flow * (1.0 - t), 0.0);
+    return mix(a, b, t);
+}
+"#;
+
+const FLOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// How much work goes into estimating motion. Every preset searches about the
+/// same distance; better presets use smaller blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FlowQuality {
+    /// 8x8 pixel blocks, searching 4 blocks each way.
+    Fast,
+    /// 4x4 pixel blocks, searching 8 blocks each way.
+    #[default]
+    Balanced,
+    /// 2x2 pixel blocks, searching 12 blocks each way.
+    Quality,
+}
+
+impl FlowQuality {
+    /// Every preset, in the order they're listed in node `Enum` inputs.
+    pub const ALL: [Self; 3] = [Self::Fast, Self::Balanced, Self::Quality];
+
+    /// The preset for a node `Enum` input's choice index.
+    pub fn from_choice_index(index: usize) -> Self {
+        Self::ALL.get(index).copied().unwrap_or_default()
+    }
+
+    /// How many pixels across (and down) one block of the flow texture is.
+    const fn block_size(self) -> u32 {
+        match self {
+            Self::Fast => 8,
+            Self::Balanced => 4,
+            Self::Quality => 2,
+        }
+    }
+
+    /// How many blocks each way motion is searched for.
+    const fn search_radius(self) -> i32 {
+        match self {
+            Self::Fast => 4,
+            Self::Balanced => 8,
+            Self::Quality => 12,
+        }
+    }
+}
+
+/// The size of the flow texture for frames of `size`, at least 1x1.
+fn flow_extent(size: wgpu::Extent3d, quality: FlowQuality) -> wgpu::Extent3d {
+    let block_size = quality.block_size();
+    wgpu::Extent3d {
+        width: size.width.div_ceil(block_size).max(1),
+        height: size.height.div_ceil(block_size).max(1),
+        depth_or_array_layers: 1,
+    }
+}
+
+/// Interpolates between frames, drawing into render targets of one format.
+pub struct FrameInterpolator {
+    flow_pipeline: wgpu::RenderPipeline,
+    warp_pipeline: wgpu::RenderPipeline,
+    flow_bgl: wgpu::BindGroupLayout,
+    warp_bgl: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    params_buf: wgpu::Buffer,
+    /// The motion estimated by the last interpolation, reused while the size
+    /// stays the same.
+    flow: Option<(wgpu::Extent3d, Arc<wgpu::TextureView>)>,
+    format: wgpu::TextureFormat,
+}
+
+impl FrameInterpolator {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let common_entries = [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            texture_entry(1),
+            texture_entry(2),
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ];
+
+        let flow_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/interpolate_flow"),
+            entries: &common_entries,
+        });
+        let warp_entries = [
+            common_entries[0],
+            common_entries[1],
+            common_entries[2],
+            common_entries[3],
+            texture_entry(4),
+        ];
+        let warp_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/interpolate_warp"),
+            entries: &warp_entries,
+        });
+
+        let create_pipeline = |label: &str,
+                               source: &'static str,
+                               bgl: &wgpu::BindGroupLayout,
+                               target_format: wgpu::TextureFormat| {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[bgl],
+                ..Default::default()
+            });
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: target_format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                cache: None,
+                multiview: None,
+            })
+        };
+        let flow_pipeline = create_pipeline(
+            "pipeline/interpolate_flow",
+            FLOW_SHADER,
+            &flow_bgl,
+            FLOW_FORMAT,
+        );
+        let warp_pipeline =
+            create_pipeline("pipeline/interpolate_warp", WARP_SHADER, &warp_bgl, format);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sampler/interpolate"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let params_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("params/interpolate"),
+            size: 32,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            flow_pipeline,
+            warp_pipeline,
+            flow_bgl,
+            warp_bgl,
+            sampler,
+            params_buf,
+            flow: None,
+            format,
+        }
+    }
+
+    /// The format of the targets this can draw into.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Record passes into `encoder` that draw the frame `position` of the way
+    /// from `from` to `to` (`0.0` is `from`, `1.0` is `to`) into `target`.
+    /// Both frames are treated as `size`; `target` must be a [Self::format]
+    /// texture with [wgpu::TextureUsages::RENDER_ATTACHMENT] usage.
+    ///
+    /// The parameters are written with `queue`, so `encoder` must be submitted
+    /// before this is called again.
+    #[allow(clippy::too_many_arguments)]
+    pub fn interpolate(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        from: &wgpu::TextureView,
+        to: &wgpu::TextureView,
+        size: wgpu::Extent3d,
+        position: f32,
+        quality: FlowQuality,
+        target: &wgpu::TextureView,
+    ) {
+        let flow_size = flow_extent(size, quality);
+        let flow_view = match &self.flow {
+            Some((cached_size, view)) if *cached_size == flow_size => view.clone(),
+            _ => {
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("interpolate_flow"),
+                    size: flow_size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: FLOW_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                });
+                let view = Arc::new(texture.create_view(&wgpu::TextureViewDescriptor::default()));
+                self.flow.insert((flow_size, view)).1.clone()
+            }
+        };
+
+        // Blocks are compared at the mip level closest to the block size, so
+        // each sample covers a whole block.
+        let block_size = quality.block_size();
+        let mut params = [0u8; 32];
+        params[0..4].copy_from_slice(&(block_size as f32 / size.width.max(1) as f32).to_le_bytes());
+        params[4..8]
+            .copy_from_slice(&(block_size as f32 / size.height.max(1) as f32).to_le_bytes());
+        params[8..12].copy_from_slice(&quality.search_radius().to_le_bytes());
+        params[12..16].copy_from_slice(&position.clamp(0.0, 1.0).to_le_bytes());
+        params[16..20].copy_from_slice(&(block_size as f32).log2().to_le_bytes());
+        queue.write_buffer(&self.params_buf, 0, &params);
+
+        let common_entries = [
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(from),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(to),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: self.params_buf.as_entire_binding(),
+            },
+        ];
+        let flow_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/interpolate_flow"),
+            layout: &self.flow_bgl,
+            entries: &common_entries,
+        });
+        let warp_entries = [
+            common_entries[0].clone(),
+            common_entries[1].clone(),
+            common_entries[2].clone(),
+            common_entries[3].clone(),
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&flow_view),
+            },
+        ];
+        let warp_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/interpolate_warp"),
+            layout: &self.warp_bgl,
+            entries: &warp_entries,
+        });
+
+        for (label, pipeline, bind_group, view) in [
+            (
+                "interpolate_flow_pass",
+                &self.flow_pipeline,
+                &flow_bind_group,
+                flow_view.as_ref(),
+            ),
+            (
+                "interpolate_warp_pass",
+                &self.warp_pipeline,
+                &warp_bind_group,
+                target,
+            ),
+        ] {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- flow_extent() ---
+
+    #[test]
+    fn test_flow_extent() {
+        let size = wgpu::Extent3d {
+            width: 1920,
+            height: 1081,
+            depth_or_array_layers: 1,
+        };
+        let flow = flow_extent(size, FlowQuality::Balanced);
+        assert_eq!((flow.width, flow.height), (480, 271));
+
+        let tiny = wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        let flow = flow_extent(tiny, FlowQuality::Fast);
+        assert_eq!((flow.width, flow.height), (1, 1));
+    }
+}
@@ -0,0 +1,253 @@
+//! Exports [FrameReader], which copies frames from the GPU back to the CPU,
+//! shrunk to a small size, for nodes that analyze frames with CPU code (e.g.
+//! face detection).
+//!
+//! [FrameReader::read] never waits on the GPU. It starts copying the frame
+//! it's given and returns the last frame whose copy has finished, so what it
+//! returns lags a frame or two behind the graph. Exports, which need every
+//! frame, use [FrameReader::read_blocking] instead.
+
+use std::sync::mpsc;
+
+use media::frame::{Dimensions, Frame, Pixel};
+
+use crate::engine_errors::EngineError;
+use crate::gpu_frame::GpuFrame;
+use crate::texture_blitter::TextureBlitter;
+
+const READ_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// WebGPU requires rows copied into buffers to be aligned to this many bytes.
+const ROW_ALIGNMENT: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+/// The size a frame of `size` is read at: shrunk (keeping its shape) so
+/// neither side is longer than `max_side`, but never enlarged.
+pub fn read_dimensions(size: wgpu::Extent3d, max_side: u32) -> Dimensions {
+    let longest = size.width.max(size.height).max(1);
+    let scale = (max_side as f64 / longest as f64).min(1.0);
+    Dimensions::new(
+        ((size.width as f64 * scale).round() as u32).max(1),
+        ((size.height as f64 * scale).round() as u32).max(1),
+    )
+    .expect("non-zero dimensions")
+}
+
+struct PendingRead {
+    buffer: wgpu::Buffer,
+    dimensions: Dimensions,
+    bytes_per_row: u32,
+    mapped: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+/// Reads frames back from the GPU. Each reader has at most one copy in flight.
+pub struct FrameReader {
+    blitter: Option<TextureBlitter>,
+    target: Option<(Dimensions, wgpu::Texture)>,
+    pending: Option<PendingRead>,
+}
+
+impl Default for FrameReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameReader {
+    pub fn new() -> Self {
+        Self {
+            blitter: None,
+            target: None,
+            pending: None,
+        }
+    }
+
+    /// Start reading `source` back at `dimensions` (unless a read is already
+    /// in flight) and return the frame from the last read if it has finished
+    /// since this was last called.
+    pub fn read(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &GpuFrame,
+        dimensions: Dimensions,
+    ) -> Result<Option<Frame>, EngineError> {
+        let _ = device.poll(wgpu::PollType::Poll);
+        let finished = match &self.pending {
+            Some(pending) => match pending.mapped.try_recv() {
+                Ok(result) => {
+                    result.map_err(|e| EngineError::ReadbackFailed(format!("{e:?}")))?;
+                    Some(self.pending.take().expect("just checked"))
+                }
+                Err(mpsc::TryRecvError::Empty) => None,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.pending = None;
+                    return Err(EngineError::ReadbackFailed(
+                        "the buffer was dropped before it was mapped".to_string(),
+                    ));
+                }
+            },
+            None => None,
+        };
+        let frame = finished.map(|pending| {
+            let frame = pending.to_frame();
+            pending.buffer.unmap();
+            frame
+        });
+
+        if self.pending.is_none() {
+            self.pending = Some(self.start_read(device, queue, source, dimensions));
+        }
+        Ok(frame)
+    }
+
+    /// Read `source` back at `dimensions`, waiting for the GPU to finish the
+    /// copy. A read [Self::read] has in flight is dropped.
+    pub fn read_blocking(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &GpuFrame,
+        dimensions: Dimensions,
+    ) -> Result<Frame, EngineError> {
+        self.pending = None;
+        let pending = self.start_read(device, queue, source, dimensions);
+        device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|e| EngineError::ReadbackFailed(e.to_string()))?;
+        pending
+            .mapped
+            .recv()
+            .map_err(|_| {
+                EngineError::ReadbackFailed(
+                    "the buffer was dropped before it was mapped".to_string(),
+                )
+            })?
+            .map_err(|e| EngineError::ReadbackFailed(format!("{e:?}")))?;
+
+        let frame = pending.to_frame();
+        pending.buffer.unmap();
+        Ok(frame)
+    }
+
+    fn start_read(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &GpuFrame,
+        dimensions: Dimensions,
+    ) -> PendingRead {
+        if self
+            .target
+            .as_ref()
+            .is_none_or(|(target_dimensions, _)| *target_dimensions != dimensions)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("frame_reader_target"),
+                size: wgpu::Extent3d {
+                    width: dimensions.width(),
+                    height: dimensions.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: READ_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+            self.target = Some((dimensions, texture));
+        }
+        let (_, texture) = self.target.as_ref().expect("just set");
+
+        let bytes_per_row = (dimensions.width() * 4).div_ceil(ROW_ALIGNMENT) * ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame_reader_buffer"),
+            size: bytes_per_row as u64 * dimensions.height() as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let blitter = self
+            .blitter
+            .get_or_insert_with(|| TextureBlitter::new(device, READ_FORMAT));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frame_reader"),
+        });
+        let target_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        blitter.blit(device, &mut encoder, source.view(), &target_view);
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(dimensions.height()),
+                },
+            },
+            texture.size(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let (tx, rx) = mpsc::channel();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = tx.send(result);
+            });
+
+        PendingRead {
+            buffer,
+            dimensions,
+            bytes_per_row,
+            mapped: rx,
+        }
+    }
+}
+
+impl PendingRead {
+    /// The mapped buffer's pixels, without the padding at the end of each row.
+    fn to_frame(&self) -> Frame {
+        let data = self.buffer.slice(..).get_mapped_range();
+        let row_bytes = self.dimensions.width() as usize * 4;
+        let pixels = data
+            .chunks_exact(self.bytes_per_row as usize)
+            .flat_map(|row| row[..row_bytes].chunks_exact(4))
+            .map(|pixel| Pixel::from_rgba(pixel[0], pixel[1], pixel[2], pixel[3]))
+            .collect();
+        Frame::from_pixels(pixels, self.dimensions).expect("one pixel per texel")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- read_dimensions() ---
+
+    #[test]
+    fn test_read_dimensions() {
+        let size = |width, height| wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        assert_eq!(
+            read_dimensions(size(1920, 1080), 256),
+            Dimensions::new(256, 144).unwrap()
+        );
+        assert_eq!(
+            read_dimensions(size(720, 1280), 256),
+            Dimensions::new(144, 256).unwrap()
+        );
+        assert_eq!(
+            read_dimensions(size(100, 50), 256),
+            Dimensions::new(100, 50).unwrap()
+        );
+    }
+}
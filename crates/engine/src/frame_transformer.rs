@@ -0,0 +1,258 @@
+//! Exports [FrameTransformer], which draws a texture moved, rotated, and
+//! scaled by an affine [UvTransform], filling whatever falls outside the source
+//! with a solid color.
+
+/// For each target pixel, maps its uv through the transform and samples the
+/// source there (or returns the outside color if that's off the source).
+const TRANSFORM_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    column_x: vec2<f32>,
+    column_y: vec2<f32>,
+    offset: vec2<f32>,
+    _pad: vec2<f32>,
+    outside: vec4<f32>,
+}
+
+@group(0) @binding(0) var source_sampler: sampler;
+@group(0) @binding(1) var source_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = mat2x2<f32>(params.column_x, params.column_y) * in.uv + params.offset;
+    let color = textureSampleLevel(source_texture, source_sampler, uv, 0.0);
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+        return params.outside;
+    }
+    return color;
+}
+"#;
+
+/// An affine map from a uv in the target (`0.0` to `1.0`, from the top left)
+/// to the uv in the source that's drawn there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvTransform {
+    /// The columns of the linear part of the map.
+    pub matrix: [[f32; 2]; 2],
+    pub offset: [f32; 2],
+}
+
+impl UvTransform {
+    /// Draws the source unchanged.
+    pub const IDENTITY: Self = Self {
+        matrix: [[1.0, 0.0], [0.0, 1.0]],
+        offset: [0.0, 0.0],
+    };
+
+    /// The source uv drawn at the target uv `uv`.
+    pub fn apply(&self, uv: [f32; 2]) -> [f32; 2] {
+        [
+            self.matrix[0][0] * uv[0] + self.matrix[1][0] * uv[1] + self.offset[0],
+            self.matrix[0][1] * uv[0] + self.matrix[1][1] * uv[1] + self.offset[1],
+        ]
+    }
+}
+
+/// Draws transformed textures into render targets of one format.
+pub struct FrameTransformer {
+    pipeline: wgpu::RenderPipeline,
+    bgl: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    params_buf: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+}
+
+impl FrameTransformer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/transform"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/transform"),
+            bind_group_layouts: &[&bgl],
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/transform"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(TRANSFORM_SHADER)),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/transform"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sampler/transform"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let params_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("params/transform"),
+            size: 48,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            bgl,
+            sampler,
+            params_buf,
+            format,
+        }
+    }
+
+    /// The format of the targets this can draw into.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Record a pass into `encoder` that draws `source` into all of `target`
+    /// through `transform`, with `outside` wherever the transform maps off the
+    /// source. `target` must be a [Self::format] texture with
+    /// [wgpu::TextureUsages::RENDER_ATTACHMENT] usage.
+    ///
+    /// The parameters are written with `queue`, so `encoder` must be submitted
+    /// before this is called again.
+    #[allow(clippy::too_many_arguments)]
+    pub fn transform(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        transform: UvTransform,
+        outside: wgpu::Color,
+        target: &wgpu::TextureView,
+    ) {
+        let values = [
+            transform.matrix[0][0],
+            transform.matrix[0][1],
+            transform.matrix[1][0],
+            transform.matrix[1][1],
+            transform.offset[0],
+            transform.offset[1],
+            0.0,
+            0.0,
+            outside.r as f32,
+            outside.g as f32,
+            outside.b as f32,
+            outside.a as f32,
+        ];
+        let mut params = [0u8; 48];
+        for (bytes, value) in params.chunks_exact_mut(4).zip(values) {
+            bytes.copy_from_slice(&value.to_le_bytes());
+        }
+        queue.write_buffer(&self.params_buf, 0, &params);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/transform"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("transform_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(outside),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -1,28 +1,85 @@
 //! Executes a [NodeGraph] and returns node outputs. Public types re-exported
 //! at [crate::graph_executor]: [NodeValue], [NodeValue], [ExecutionError].
+mod cost;
 mod enums;
 mod errors;
+mod input_mapping;
+mod param_smoothing;
+pub(crate) mod pipeline_compiler;
+mod stats;
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
 
+use crate::cpu_backend::{self, ExecutionBackend};
 use crate::engine_outpost::EngineOutpostEvent;
+use crate::execution_trace::{TraceNode, trace_values};
+use crate::frame_interpolator::{FlowQuality, FrameInterpolator};
+use crate::frame_reader::FrameReader;
+use crate::gpu_frame::GpuFrame;
 use crate::graph_executor_effects::EffectStage;
 use crate::node::NodeDefinition;
 use crate::node::NodeLibrary;
-use crate::node::engine_node::{AlgorithmStageBackend, BuiltInHandler, NodeExecutionPlan};
+use crate::node::engine_node::{
+    AlgorithmStageBackend, BuiltInHandler, NodeExecutionPlan, NodeInputKind, NodeOutputKind,
+};
 use crate::node::handler::{
-    FrameStreamHandler, FrameStreamHandlerError, MidiStreamHandler, NodeFrameStreamRequest,
-    NodeMidiStreamRequest, NodeNoiseStreamRequest, NodeSignalEnvelopeRequest, NoiseStreamHandler,
-    SignalEnvelopeHandler, StreamKind,
+    AudioMeterHandler, BlobTrackHandler, DepthEstimateHandler, EqualizeHandler, FaceDetectHandler,
+    FeedbackHandler, FrameInterpolation, FrameStreamHandler, FrameStreamHandlerError,
+    LayoutHandler, LevelsCurvesHandler, LoopMode, MatchColorHandler, MidiStreamHandler,
+    NodeAudioMeterRequest, NodeBlobTrackRequest, NodeDepthEstimateRequest, NodeEqualizeRequest,
+    NodeFaceDetectRequest, NodeFeedbackRequest, NodeFrameDelayRequest, NodeFrameStreamRequest,
+    NodeLayoutRequest, NodeLevelsCurvesRequest, NodeLfoRequest, NodeMatchColorRequest,
+    NodeMathRequest, NodeMidiStreamRequest, NodeNoiseStreamRequest, NodeSignalEnvelopeRequest,
+    NodeSlitScanRequest, NodeSpriteSheetRequest, NodeStabilizeRequest, NodeSwitcherRequest,
+    NodeTempoRequest, NodeThresholdRequest, NodeTimeRemapRequest, NodeTrailsRequest,
+    NodeWasmRequest, NodeWatermarkRequest, NodeWhiteBalanceRequest, NoiseStreamHandler,
+    SignalEnvelopeHandler, SlitScanHandler, SpriteSheetHandler, StabilizeHandler, StreamKind,
+    SwitcherHandler, TempoHandler, ThresholdHandler, TimeRemapHandler, TrailsHandler, VideoTrim,
+    WasmHandler, WatermarkHandler, WhiteBalanceHandler, execute_constant, execute_math,
+    execute_text_template,
 };
 use crate::node_graph::EngineNodeId;
 use crate::node_graph::{InputValue, NodeGraph, NodeInstance};
 use crate::node_pipelines::{ComputePipeline, RenderPipeline};
+use crate::pipeline_cache::DiskPipelineCache;
 use crate::upload_stager::UploadStager;
+use input_mapping::InputMapper;
 use media::fps::Fps;
-
+use media::frame::color::ToneMapOperator;
+use media::frame::{ConformPolicy, Dimensions, Frame, Rotation, Uid};
+use media::playback_stream::{BufferingPolicy, BufferingStats};
+use param_smoothing::ParamSmoother;
+use pipeline_compiler::PipelineCompiler;
+use util::link::LinkError;
+
+pub use cost::*;
 pub use enums::*;
 pub use errors::*;
+pub use stats::*;
+
+/// The input image and video source nodes choose their [ConformPolicy] with.
+const CONFORM_INPUT_NAME: &str = "Fit Mode";
+
+/// The input video source nodes override their rotation metadata with.
+const ROTATION_INPUT_NAME: &str = "Rotation";
+
+/// The input video source nodes choose their HDR [ToneMapOperator] with.
+const TONE_MAP_INPUT_NAME: &str = "HDR Tone Mapping";
+
+/// The inputs video source nodes trim their video with (see [VideoTrim]), in
+/// seconds. An out-point of 0 plays to the end.
+const TRIM_IN_INPUT_NAME: &str = "Trim In";
+const TRIM_OUT_INPUT_NAME: &str = "Trim Out";
+const TIMELINE_OFFSET_INPUT_NAME: &str = "Timeline Offset";
+
+/// The input Time Remap nodes choose their [FrameInterpolation] with.
+const INTERPOLATION_INPUT_NAME: &str = "Interpolation";
+
+/// The input nodes that interpolate with optical flow choose their
+/// [FlowQuality] with.
+const FLOW_QUALITY_INPUT_NAME: &str = "Flow Quality";
 
 /// The executor that runs a node graph and produces results.
 ///
@@ -47,6 +104,18 @@ pub struct GraphExecutor {
     /// Cache of compiled compute pipelines for algorithm stages
     pub(crate) compute_pipeline_cache: HashMap<String, ComputePipeline>,
 
+    /// Compiled pipeline code kept on disk between runs, when the device
+    /// supports it (see [GraphExecutor::set_disk_pipeline_cache]).
+    pub(crate) disk_pipeline_cache: Option<DiskPipelineCache>,
+
+    /// Builds pipelines in the background when set (see
+    /// [GraphExecutor::set_async_pipeline_compilation]).
+    pub(crate) pipeline_compiler: Option<PipelineCompiler>,
+
+    /// Nodes that output placeholders last execution because their pipelines
+    /// were still being compiled.
+    pub(crate) compiling_nodes: HashSet<EngineNodeId>,
+
     /// Cache of shader output targets reused per node instance.
     render_target_cache: HashMap<EngineNodeId, CachedRenderTarget>,
 
@@ -70,15 +139,112 @@ pub struct GraphExecutor {
 
     /// Handles built-in scalar smoothing nodes
     signal_envelope_handler: SignalEnvelopeHandler,
+    audio_meter_handler: AudioMeterHandler,
+
+    /// Handles built-in sprite sheet source nodes
+    sprite_sheet_handler: SpriteSheetHandler,
+
+    /// Handles built-in time remap nodes' output clocks
+    time_remap_handler: TimeRemapHandler,
+
+    /// Handles built-in Frame Delay and Feedback node pairs
+    feedback_handler: FeedbackHandler,
+
+    /// Handles built-in Stabilize nodes' video analysis
+    stabilize_handler: StabilizeHandler,
+
+    /// Handles built-in Face Detect nodes' background detection
+    face_detect_handler: FaceDetectHandler,
+
+    /// Handles built-in Depth Estimate nodes' background estimation
+    depth_estimate_handler: DepthEstimateHandler,
+
+    /// Handles built-in Levels and Curves nodes' lookup tables
+    levels_curves_handler: LevelsCurvesHandler,
+
+    /// Handles built-in White Balance nodes' automatic correction
+    white_balance_handler: WhiteBalanceHandler,
+
+    /// Handles built-in Equalize nodes' histogram buffers
+    equalize_handler: EqualizeHandler,
+
+    /// Handles built-in Threshold nodes' histogram buffers
+    threshold_handler: ThresholdHandler,
+
+    /// Handles built-in Blob Track nodes' readback and tracked blobs
+    blob_track_handler: BlobTrackHandler,
+
+    /// Handles built-in Trails nodes' accumulation textures
+    trails_handler: TrailsHandler,
+
+    /// Handles built-in Slit Scan and Time Displace nodes' frame histories
+    slit_scan_handler: SlitScanHandler,
+
+    /// Handles built-in Match Color nodes' histogram buffers
+    match_color_handler: MatchColorHandler,
+
+    /// Tracks built-in Switcher nodes' selections and transitions
+    switcher_handler: SwitcherHandler,
+
+    /// Handles built-in Layout nodes' output textures
+    layout_handler: LayoutHandler,
+
+    /// Handles built-in Watermark nodes' marks and output textures
+    watermark_handler: WatermarkHandler,
+
+    /// Runs the BPM clock built-in Tempo nodes set and LFO nodes follow
+    tempo_handler: TempoHandler,
+
+    /// Runs WASM nodes' modules
+    wasm_handler: WasmHandler,
+
+    /// Runs built-in Frame Interpolate nodes. Created when first needed.
+    frame_interpolator: Option<FrameInterpolator>,
+
+    /// Soft takeover state of connected Float inputs with a mapping set.
+    input_mapper: InputMapper,
+
+    /// Smoothed values of connected Float inputs with smoothing set.
+    param_smoother: ParamSmoother,
 
     /// Last globally requested target FPS for stream handlers.
     global_stream_target_fps: Option<Fps>,
 
+    /// Project-level output resolution and frame rate.
+    output_format: OutputFormat,
+
+    /// The only part of the output shader nodes render, if set (see
+    /// [GraphExecutor::set_render_region]).
+    pub(crate) render_region: Option<RenderRegion>,
+
     /// Cached execution order to avoid recomputing topology every frame
     cached_execution_order: Option<Vec<EngineNodeId>>,
 
     /// The ID of the current output node (last execution)
     output_node_id: EngineNodeId,
+
+    /// Nodes run every execution along with the target, so their outputs can
+    /// be read afterwards (see [GraphExecutor::set_captured_nodes]).
+    captured_node_ids: Vec<EngineNodeId>,
+
+    /// Which backend supported nodes run on.
+    backend: ExecutionBackend,
+
+    /// CPU copies of frame outputs, kept while running on the CPU backend so
+    /// chains of CPU nodes don't need to read frames back from the GPU.
+    cpu_frame_cache: HashMap<EngineNodeId, Frame>,
+
+    /// Upload textures for CPU node outputs (one per node, since the shared
+    /// [UploadStager] texture is overwritten by every upload).
+    cpu_upload_stagers: HashMap<EngineNodeId, UploadStager>,
+
+    /// The nodes run by the current execution, when it's being traced (see
+    /// [GraphExecutor::trace_next_execution]).
+    trace: Option<Vec<TraceNode>>,
+
+    /// How long each node took on the CPU in the last execution (see
+    /// [GraphExecutor::last_node_timings]).
+    node_timings: HashMap<EngineNodeId, Duration>,
 }
 
 /// The result of executing a node graph.
@@ -138,12 +304,140 @@ impl GraphExecutor {
         required
     }
 
+    /// The nodes an execution runs, in execution `order`: the nodes `target`
+    /// depends on (or, without a target, the graph's
+    /// [outputs](Self::output_nodes) do), captured nodes, and the Frame Delay
+    /// nodes that feed back into them. Everything else is a dead branch, and
+    /// isn't run at all.
+    fn active_subgraph(
+        &self,
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+        order: &[EngineNodeId],
+        target: Option<EngineNodeId>,
+    ) -> Vec<EngineNodeId> {
+        let mut required = HashSet::new();
+        match target {
+            Some(target) => {
+                required = Self::collect_required_nodes_for_target(graph, target);
+            }
+            None => {
+                for output in Self::output_nodes(graph, library, order) {
+                    required.extend(Self::collect_required_nodes_for_target(graph, output));
+                }
+            }
+        }
+        self.collect_captured_nodes(graph, &mut required);
+        Self::collect_feedback_writers(graph, library, &mut required);
+        order
+            .iter()
+            .copied()
+            .filter(|node_id| required.contains(node_id))
+            .collect()
+    }
+
+    /// The nodes treated as the graph's outputs when there's no target, in
+    /// execution `order`: nodes that output a frame and aren't connected to
+    /// anything. A dangling node without a frame output (like a forgotten LFO)
+    /// can't be shown, so it's a dead branch rather than an output.
+    fn output_nodes(
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+        order: &[EngineNodeId],
+    ) -> Vec<EngineNodeId> {
+        let leaves: HashSet<EngineNodeId> = graph.find_output_nodes().into_iter().collect();
+        order
+            .iter()
+            .copied()
+            .filter(|node_id| leaves.contains(node_id))
+            .filter(|node_id| {
+                graph
+                    .get_instance(*node_id)
+                    .and_then(|instance| library.get_definition(&instance.definition_name))
+                    .is_some_and(|definition| {
+                        definition
+                            .node
+                            .outputs
+                            .iter()
+                            .any(|output| matches!(output.kind, NodeOutputKind::Frame))
+                    })
+            })
+            .collect()
+    }
+
+    /// Add the captured nodes still in `graph` (and the nodes they need) to
+    /// `required`.
+    fn collect_captured_nodes(&self, graph: &NodeGraph, required: &mut HashSet<EngineNodeId>) {
+        for &node_id in &self.captured_node_ids {
+            if graph.get_instance(node_id).is_some() {
+                required.extend(Self::collect_required_nodes_for_target(graph, node_id));
+            }
+        }
+    }
+
+    /// Add the Frame Delay nodes (and the nodes they need) that feed back into
+    /// any Feedback node in `required`. Nothing connects a Frame Delay node to
+    /// its Feedback node, so it would otherwise never run.
+    fn collect_feedback_writers(
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+        required: &mut HashSet<EngineNodeId>,
+    ) {
+        loop {
+            let channels: HashSet<i32> = required
+                .iter()
+                .filter_map(|node_id| graph.get_instance(*node_id))
+                .filter_map(|instance| {
+                    Self::feedback_channel(library, instance, BuiltInHandler::Feedback)
+                })
+                .collect();
+
+            let writers: Vec<EngineNodeId> = graph
+                .instances()
+                .iter()
+                .filter(|(node_id, instance)| {
+                    !required.contains(*node_id)
+                        && Self::feedback_channel(library, instance, BuiltInHandler::FrameDelay)
+                            .is_some_and(|channel| channels.contains(&channel))
+                })
+                .map(|(node_id, _)| *node_id)
+                .collect();
+            if writers.is_empty() {
+                return;
+            }
+
+            for writer in writers {
+                required.extend(Self::collect_required_nodes_for_target(graph, writer));
+            }
+        }
+    }
+
+    /// The `Channel` of a `handler` (Frame Delay or Feedback) node, or [None]
+    /// if `instance` isn't one or its channel is connected.
+    fn feedback_channel(
+        library: &NodeLibrary,
+        instance: &NodeInstance,
+        handler: BuiltInHandler,
+    ) -> Option<i32> {
+        let definition = library.get_definition(&instance.definition_name)?;
+        if definition.node.executor != NodeExecutionPlan::BuiltIn(handler) {
+            return None;
+        }
+        match instance.input_values.get("Channel") {
+            Some(InputValue::Int(channel)) => Some(*channel),
+            _ => None,
+        }
+    }
+
     pub fn new(format: wgpu::TextureFormat) -> Self {
         Self {
             upload_stager: UploadStager::new(),
             output_cache: HashMap::new(),
             pipeline_cache: HashMap::new(),
             compute_pipeline_cache: HashMap::new(),
+            disk_pipeline_cache: None,
+            pipeline_compiler: None,
+            compiling_nodes: HashSet::new(),
             render_target_cache: HashMap::new(),
             render_stage_target_cache: HashMap::new(),
             compute_stage_target_cache: HashMap::new(),
@@ -151,10 +445,41 @@ impl GraphExecutor {
             noise_stream_handler: NoiseStreamHandler::new(),
             midi_stream_handler: MidiStreamHandler::new(),
             signal_envelope_handler: SignalEnvelopeHandler::new(),
+            audio_meter_handler: AudioMeterHandler::new(),
+            sprite_sheet_handler: SpriteSheetHandler::new(),
+            time_remap_handler: TimeRemapHandler::new(),
+            feedback_handler: FeedbackHandler::new(format),
+            stabilize_handler: StabilizeHandler::new(format),
+            face_detect_handler: FaceDetectHandler::new(),
+            depth_estimate_handler: DepthEstimateHandler::new(format),
+            levels_curves_handler: LevelsCurvesHandler::new(format),
+            white_balance_handler: WhiteBalanceHandler::new(format),
+            equalize_handler: EqualizeHandler::new(format),
+            threshold_handler: ThresholdHandler::new(format),
+            blob_track_handler: BlobTrackHandler::new(),
+            trails_handler: TrailsHandler::new(format),
+            slit_scan_handler: SlitScanHandler::new(format),
+            match_color_handler: MatchColorHandler::new(format),
+            switcher_handler: SwitcherHandler::new(format),
+            layout_handler: LayoutHandler::new(format),
+            watermark_handler: WatermarkHandler::new(format),
+            tempo_handler: TempoHandler::new(),
+            wasm_handler: WasmHandler::new(),
+            frame_interpolator: None,
+            input_mapper: InputMapper::new(),
+            param_smoother: ParamSmoother::new(),
             global_stream_target_fps: None,
+            output_format: OutputFormat::default(),
+            render_region: None,
             target_format: format,
             cached_execution_order: None,
             output_node_id: EngineNodeId::default(),
+            captured_node_ids: Vec::new(),
+            backend: ExecutionBackend::default(),
+            cpu_frame_cache: HashMap::new(),
+            cpu_upload_stagers: HashMap::new(),
+            trace: None,
+            node_timings: HashMap::new(),
         }
     }
 
@@ -170,11 +495,34 @@ impl GraphExecutor {
         self.frame_stream_handler.clear_cache();
         self.midi_stream_handler.clear_cache();
         self.signal_envelope_handler.clear_cache();
+        self.audio_meter_handler.clear_cache();
+        self.sprite_sheet_handler.clear_cache();
+        self.time_remap_handler.clear_cache();
+        self.feedback_handler.clear_cache();
+        self.stabilize_handler.clear_cache();
+        self.face_detect_handler.clear_cache();
+        self.depth_estimate_handler.clear_cache();
+        self.levels_curves_handler.clear_cache();
+        self.white_balance_handler.clear_cache();
+        self.equalize_handler.clear_cache();
+        self.threshold_handler.clear_cache();
+        self.blob_track_handler.clear_cache();
+        self.trails_handler.clear_cache();
+        self.slit_scan_handler.clear_cache();
+        self.match_color_handler.clear_cache();
+        self.switcher_handler.clear_cache();
+        self.layout_handler.clear_cache();
+        self.watermark_handler.clear_cache();
+        self.tempo_handler.clear_cache();
+        self.wasm_handler.clear_cache();
+        self.input_mapper.clear();
+        self.param_smoother.clear();
     }
 
     /// Clear image cache to release textures.
     pub fn clear_image_cache(&mut self) {
         self.frame_stream_handler.clear_cache();
+        self.sprite_sheet_handler.clear_cache();
     }
 
     /// Invalidate cached execution order (call when graph structure changes)
@@ -182,18 +530,162 @@ impl GraphExecutor {
         self.cached_execution_order = None;
     }
 
+    /// The backend supported nodes currently run on.
+    pub fn backend(&self) -> ExecutionBackend {
+        self.backend
+    }
+
+    /// Create pipelines with `cache` from now on, so they can be saved to disk
+    /// and reused on the next run.
+    pub fn set_disk_pipeline_cache(&mut self, cache: Option<DiskPipelineCache>) {
+        self.disk_pipeline_cache = cache;
+    }
+
+    /// The cache set with [GraphExecutor::set_disk_pipeline_cache], if any.
+    pub fn disk_pipeline_cache(&self) -> Option<&DiskPipelineCache> {
+        self.disk_pipeline_cache.as_ref()
+    }
+
+    /// Compile node pipelines on a background thread instead of while the
+    /// node runs. Until a node's pipelines are ready it outputs a placeholder
+    /// (see [GraphExecutor::compiling_nodes]), so this is for live previews,
+    /// not exports.
+    pub fn set_async_pipeline_compilation(&mut self, enabled: bool) {
+        if enabled != self.pipeline_compiler.is_some() {
+            self.pipeline_compiler = enabled.then(PipelineCompiler::new);
+        }
+    }
+
+    /// The nodes that output placeholders last execution because their
+    /// pipelines were still being compiled.
+    pub fn compiling_nodes(&self) -> &HashSet<EngineNodeId> {
+        &self.compiling_nodes
+    }
+
+    /// Switch the backend supported nodes run on. Cached outputs are dropped so
+    /// every node re-executes on the new backend.
+    pub fn set_backend(&mut self, backend: ExecutionBackend) {
+        if self.backend == backend {
+            return;
+        }
+
+        self.backend = backend;
+        self.output_cache.clear();
+        self.cpu_frame_cache.clear();
+        self.cpu_upload_stagers.clear();
+    }
+
+    /// Join or leave an Ableton Link session (see
+    /// [TempoHandler::set_link_enabled]).
+    pub fn set_link_enabled(&mut self, enabled: bool) -> Result<(), LinkError> {
+        self.tempo_handler.set_link_enabled(enabled)
+    }
+
+    /// How many other peers are in the Ableton Link session, or [None] if Link
+    /// isn't enabled.
+    pub fn link_peers(&self) -> Option<usize> {
+        self.tempo_handler.link_peers()
+    }
+
+    /// The project-level output resolution and frame rate.
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    /// Conform sources to `output_format`. Cached outputs are dropped since
+    /// they may hold frames at the old resolution.
+    pub fn set_output_format(&mut self, output_format: OutputFormat) {
+        if self.output_format == output_format {
+            return;
+        }
+
+        self.output_format = output_format;
+        self.frame_stream_handler
+            .set_output_resolution(output_format.resolution);
+        if let Some(fps) = output_format.fps {
+            self.frame_stream_handler.set_target_fps_all(fps);
+            self.noise_stream_handler.set_target_fps_all(fps);
+            self.midi_stream_handler.set_target_fps_all(fps);
+        }
+        self.output_cache.clear();
+        self.cpu_frame_cache.clear();
+    }
+
+    /// Only render `region` of each shader node's output ([None] to render all
+    /// of it), leaving the rest black, to make iterating on a small part of an
+    /// expensive graph faster. Compute stages and built-in nodes still process
+    /// whole frames. Cached outputs are dropped since they may have been
+    /// rendered with a different region.
+    pub fn set_render_region(&mut self, region: Option<RenderRegion>) {
+        if self.render_region == region {
+            return;
+        }
+
+        self.render_region = region;
+        self.output_cache.clear();
+        self.cpu_frame_cache.clear();
+    }
+
+    /// The region set with [Self::set_render_region].
+    pub fn render_region(&self) -> Option<RenderRegion> {
+        self.render_region
+    }
+
     /// Get the cached outputs for a specific node, if available.
     /// Returns None if the node hasn't been executed yet.
     pub fn get_node_outputs(&self, node_id: EngineNodeId) -> Option<&HashMap<String, NodeValue>> {
         self.output_cache.get(&node_id).map(|entry| &entry.outputs)
     }
 
+    /// Copy the frame `node_id` output last execution back from the GPU at its
+    /// full resolution. If it has more than one frame output, the one whose
+    /// name comes first is captured.
+    pub fn capture_node_output(
+        &self,
+        node_id: EngineNodeId,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Frame, ExecutionError> {
+        // Nodes on the CPU backend already have a copy.
+        if let Some(frame) = self.cpu_frame_cache.get(&node_id) {
+            return Ok(frame.clone());
+        }
+
+        let outputs = self
+            .get_node_outputs(node_id)
+            .ok_or(ExecutionError::NodeNotExecuted(node_id))?;
+        let gpu_frame = outputs
+            .iter()
+            .filter_map(|(name, value)| match value {
+                NodeValue::Frame(frame) => Some((name, frame)),
+                _ => None,
+            })
+            .min_by_key(|(name, _)| *name)
+            .map(|(_, frame)| frame)
+            .ok_or(ExecutionError::NoFrameOutput(node_id))?;
+        let dimensions = Dimensions::new(gpu_frame.size.width, gpu_frame.size.height)
+            .ok_or(ExecutionError::NoFrameOutput(node_id))?;
+        FrameReader::new()
+            .read_blocking(device, queue, gpu_frame, dimensions)
+            .map_err(|e| ExecutionError::GpuReadbackError(e.to_string()))
+    }
+
+    /// Also run `node_ids` (and what they depend on) every execution, even
+    /// when they don't lead to the target, so [GraphExecutor::get_node_outputs]
+    /// has their outputs for the same frame. Used to export render passes, and
+    /// to keep nodes the editor forces to evaluate running when they're dead
+    /// branches.
+    pub fn set_captured_nodes(&mut self, node_ids: Vec<EngineNodeId>) {
+        self.captured_node_ids = node_ids;
+    }
+
     /// Get the ID of the current output node (from the last execution)
     pub fn get_output_node_id(&self) -> EngineNodeId {
         self.output_node_id
     }
 
-    /// Return the measured target FPS for a specific node when it is a video source.
+    /// Return the measured target FPS for a specific node when it is a video
+    /// source. The project frame rate always wins when one is set.
     ///
     /// This intentionally avoids relying on runtime output-name matching.
     /// Instead, it inspects the node definition and queries the video handler
@@ -204,6 +696,45 @@ impl GraphExecutor {
         library: &NodeLibrary,
         node_id: EngineNodeId,
     ) -> Option<media::fps::Fps> {
+        if let Some(fps) = self.output_format.fps {
+            return Some(fps);
+        }
+
+        let request = Self::video_source_request_for_node(graph, library, node_id)?;
+        self.frame_stream_handler.get_recommended_fps(&request).ok()
+    }
+
+    /// Return the index of the frame the video source feeding `node_id` is
+    /// showing, along with the FPS it's being played at.
+    pub fn get_playback_position_for_node(
+        &mut self,
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+        node_id: EngineNodeId,
+    ) -> Option<(usize, Fps)> {
+        let request = Self::video_source_request_for_node(graph, library, node_id)?;
+        self.frame_stream_handler.video_position(&request)
+    }
+
+    /// Return the frames the video source feeding `node_id` plays (see
+    /// [GraphExecutor::set_loop_region]).
+    pub fn get_clip_for_node(
+        &mut self,
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+        node_id: EngineNodeId,
+    ) -> Option<RangeInclusive<usize>> {
+        let request = Self::video_source_request_for_node(graph, library, node_id)?;
+        self.frame_stream_handler.video_clip(&request)
+    }
+
+    /// Find the video source `node_id` is (or the first one it depends on) and
+    /// build a stream request for it.
+    fn video_source_request_for_node(
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+        node_id: EngineNodeId,
+    ) -> Option<NodeFrameStreamRequest> {
         let candidate_node_id = {
             let instance = graph.get_instance(node_id)?;
             let definition = library.get_definition(&instance.definition_name)?;
@@ -245,13 +776,18 @@ impl GraphExecutor {
             }
         })?;
 
-        let request = NodeFrameStreamRequest {
+        Some(NodeFrameStreamRequest {
             node_id: candidate_node_id,
             file_path: path.clone(),
             stream_kind: StreamKind::Video,
-        };
-
-        self.frame_stream_handler.get_recommended_fps(&request).ok()
+            // Only used to look the stream up, never to fetch from it.
+            conform_policy: ConformPolicy::default(),
+            rotation: None,
+            tone_map: ToneMapOperator::default(),
+            source_time: None,
+            interpolation: FrameInterpolation::default(),
+            trim: VideoTrim::default(),
+        })
     }
 
     /// Execute the node graph with the provided parameters.
@@ -280,30 +816,12 @@ impl GraphExecutor {
             .execution_order()
             .map_err(ExecutionError::GraphError)?;
 
-        // Determine which nodes should be executed
-        let execution_node_ids: Vec<EngineNodeId> = if let Some(target) = target_node_id {
-            if !order.contains(&target) {
-                return Err(ExecutionError::TargetNodeNotInExecutionOrder(target));
-            }
-            let required = Self::collect_required_nodes_for_target(graph, target);
-            order
-                .iter()
-                .copied()
-                .filter(|node_id| required.contains(node_id))
-                .collect()
-        } else {
-            // No specific target: only execute nodes connected to any output node
-            let output_nodes = graph.find_output_nodes();
-            let mut required = std::collections::HashSet::new();
-            for output in &output_nodes {
-                required.extend(Self::collect_required_nodes_for_target(graph, *output));
-            }
-            order
-                .iter()
-                .copied()
-                .filter(|node_id| required.contains(node_id))
-                .collect()
-        };
+        if let Some(target) = target_node_id
+            && !order.contains(&target)
+        {
+            return Err(ExecutionError::TargetNodeNotInExecutionOrder(target));
+        }
+        let execution_node_ids = self.active_subgraph(graph, library, &order, target_node_id);
 
         let active_nodes: HashSet<EngineNodeId> = execution_node_ids.iter().copied().collect();
         self.frame_stream_handler
@@ -323,6 +841,12 @@ impl GraphExecutor {
                 .set_target_fps_for_nodes(target_fps, &active_nodes);
         }
 
+        // Video sources play at the project frame rate rather than their own.
+        if let Some(fps) = self.output_format.fps {
+            self.frame_stream_handler
+                .set_target_fps_for_nodes(fps, &active_nodes);
+        }
+
         // Execute each node in order
         let live_node_ids: HashSet<EngineNodeId> = order.iter().copied().collect();
         self.render_target_cache
@@ -333,8 +857,21 @@ impl GraphExecutor {
             .retain(|(node_id, _, _), _| live_node_ids.contains(node_id));
         self.output_cache
             .retain(|node_id, _| live_node_ids.contains(node_id));
+        self.cpu_frame_cache
+            .retain(|node_id, _| live_node_ids.contains(node_id));
+        self.cpu_upload_stagers
+            .retain(|node_id, _| live_node_ids.contains(node_id));
+        self.feedback_handler
+            .retain_nodes(|node_id| live_node_ids.contains(&node_id));
+        self.input_mapper.retain_mapped(graph);
+        self.param_smoother.retain_smoothed(graph);
+        self.tempo_handler.tick();
+        let frame_secs = self.frame_secs();
+        self.node_timings.clear();
+        self.compiling_nodes.clear();
 
         for &node_id in &execution_node_ids {
+            let started = Instant::now();
             let instance = graph
                 .get_instance(node_id)
                 .ok_or(ExecutionError::NodeNotFound(node_id))?;
@@ -347,40 +884,79 @@ impl GraphExecutor {
                 })?;
 
             // Resolve all inputs for this node
-            let resolved_inputs = self.resolve_inputs(instance)?;
+            let mut resolved_inputs = self.resolve_inputs(instance, definition)?;
+            self.input_mapper
+                .apply(graph, node_id, definition, &mut resolved_inputs);
+            self.param_smoother
+                .apply(graph, node_id, &mut resolved_inputs, frame_secs);
 
             let input_signature = Self::hash_node_inputs(&resolved_inputs);
             if Self::is_cacheable_node(definition)
                 && let Some(cached) = self.output_cache.get(&node_id)
                 && cached.input_signature == input_signature
             {
-                if Some(node_id) == target_node_id {
-                    break;
-                }
+                self.record_node(node_id, instance, &resolved_inputs, true, started);
                 continue;
             }
 
-            // Execute the node based on its type
-            let outputs = match &definition.node.executor {
-                NodeExecutionPlan::Shader { .. } => {
-                    self.execute_shader_node(node_id, device, queue, definition, &resolved_inputs)?
-                }
-                NodeExecutionPlan::Algorithm { .. } => self.execute_algorithm_node(
+            // CPU kernels get first pick; anything they can't run (missing CPU
+            // frames, unsupported nodes or sampling) goes through the normal
+            // GPU path.
+            let cpu_outputs = if self.backend == ExecutionBackend::Cpu {
+                self.execute_cpu_node(
                     node_id,
+                    instance,
                     device,
                     queue,
                     definition,
                     &resolved_inputs,
-                )?,
-                NodeExecutionPlan::BuiltIn(handler) => self.execute_builtin_node(
-                    node_id,
-                    handler,
-                    &resolved_inputs,
-                    device,
-                    queue,
-                    definition,
-                    &mut on_event,
-                )?,
+                )?
+            } else {
+                None
+            };
+
+            // Execute the node based on its type
+            let outputs = if let Some(outputs) = cpu_outputs {
+                outputs
+            } else {
+                // Stale CPU frames would otherwise be picked up downstream.
+                self.cpu_frame_cache.remove(&node_id);
+                match &definition.node.executor {
+                    NodeExecutionPlan::Shader { .. } => self.execute_shader_node(
+                        node_id,
+                        device,
+                        queue,
+                        definition,
+                        &resolved_inputs,
+                    )?,
+                    NodeExecutionPlan::Algorithm { .. } => self.execute_algorithm_node(
+                        node_id,
+                        device,
+                        queue,
+                        definition,
+                        &resolved_inputs,
+                    )?,
+                    NodeExecutionPlan::BuiltIn(handler) => self.execute_builtin_node(
+                        node_id,
+                        handler,
+                        &resolved_inputs,
+                        device,
+                        queue,
+                        definition,
+                        &mut on_event,
+                    )?,
+                    NodeExecutionPlan::Wasm { .. } => {
+                        let request = NodeWasmRequest {
+                            node_id,
+                            definition,
+                            inputs: &resolved_inputs,
+                            delta_secs: frame_secs,
+                        };
+                        self.wasm_handler
+                            .execute_handler(&request)
+                            .map_err(|error| ExecutionError::WasmError(error.to_string()))?
+                    }
+                }
             };
 
             // Cache the outputs
@@ -391,24 +967,17 @@ impl GraphExecutor {
                     outputs,
                 },
             );
-
-            if Some(node_id) == target_node_id {
-                break;
-            }
+            self.record_node(node_id, instance, &resolved_inputs, false, started);
         }
+        self.feedback_handler.finish_execution();
 
         // Determine output node id
-        let output_node_id = if let Some(target) = target_node_id {
-            target
-        } else {
-            let output_nodes = graph.find_output_nodes();
-
-            if output_nodes.is_empty() {
-                return Err(ExecutionError::NoOutputNode);
-            }
-
+        let output_node_id = match target_node_id {
+            Some(target) => target,
             // For now, return the first output node's result
-            output_nodes[0]
+            None => *Self::output_nodes(graph, library, &order)
+                .first()
+                .ok_or(ExecutionError::NoOutputNode)?,
         };
         self.output_node_id = output_node_id;
         let outputs = self
@@ -423,6 +992,70 @@ impl GraphExecutor {
         })
     }
 
+    /// Make inputs with soft takeover hold their values until their controls
+    /// are moved to them again. Call this after changing those inputs some
+    /// other way so the controls don't make them jump.
+    pub fn release_soft_takeovers(&mut self) {
+        self.input_mapper.release_takeovers();
+    }
+
+    /// How long one execution lasts in the project's time, for anything that
+    /// changes per frame. Defaults to 30 FPS when no frame rate is set.
+    fn frame_secs(&self) -> f32 {
+        self.output_format
+            .fps
+            .or(self.global_stream_target_fps)
+            .map_or(1.0 / 30.0, |fps| fps.interval_float() as f32)
+    }
+
+    /// Record what each node does during the next [Self::execute] call, to be
+    /// picked up with [Self::take_trace].
+    pub fn trace_next_execution(&mut self) {
+        self.trace = Some(Vec::new());
+    }
+
+    /// The nodes run by the last traced execution, in order, or [None] if
+    /// nothing was traced. Nodes after one that failed are missing.
+    pub fn take_trace(&mut self) -> Option<Vec<TraceNode>> {
+        self.trace.take()
+    }
+
+    /// How long each node that ran in the last execution took on the CPU. GPU
+    /// work it submitted may finish later. Nodes reused from the cache took
+    /// next to no time.
+    pub fn last_node_timings(&self) -> &HashMap<EngineNodeId, Duration> {
+        &self.node_timings
+    }
+
+    /// Note how long a node that just ran (or was reused from the cache)
+    /// took, and add it to the trace if there is one.
+    fn record_node(
+        &mut self,
+        node_id: EngineNodeId,
+        instance: &NodeInstance,
+        inputs: &HashMap<String, NodeValue>,
+        cached: bool,
+        started: Instant,
+    ) {
+        let elapsed = started.elapsed();
+        self.node_timings.insert(node_id, elapsed);
+        let Some(trace) = &mut self.trace else {
+            return;
+        };
+        trace.push(TraceNode {
+            node_id,
+            definition_name: instance.definition_name.clone(),
+            inputs: trace_values(inputs),
+            outputs: self
+                .output_cache
+                .get(&node_id)
+                .map(|entry| trace_values(&entry.outputs))
+                .unwrap_or_default(),
+            cached,
+            duration_micros: elapsed.as_micros() as u64,
+        });
+    }
+
     /// Tell the executor to pause all video streams
     /// Will be called if the user want to stop on a frame.
     /// This is different from stopping graph execution.
@@ -431,12 +1064,65 @@ impl GraphExecutor {
         self.frame_stream_handler.pause_all_streams();
         self.noise_stream_handler.pause_all_streams();
         self.midi_stream_handler.pause_all_streams();
+        self.audio_meter_handler.pause_all_streams();
+        self.tempo_handler.pause_all_streams();
+        self.sprite_sheet_handler.pause_all_streams();
+        self.time_remap_handler.pause_all_streams();
+    }
+
+    /// Pause all streams and move video sources `delta` frames (negative
+    /// values step backwards). The next execution shows the new frame.
+    pub fn step_frames(&mut self, delta: isize) {
+        self.pause_streams();
+        self.frame_stream_handler.step_video_streams(delta);
+    }
+
+    /// Move playing video and noise sources `frames` frames further along than
+    /// the next execution alone would, as if the graph had run in between.
+    /// Used to run the graph at a lower rate than sources play at.
+    pub fn skip_frames(&mut self, frames: usize) {
+        self.frame_stream_handler.skip_frames(frames);
+        self.noise_stream_handler.skip_frames(frames);
+    }
+
+    /// Pause all streams and move video sources to `frame` (clamped to their
+    /// clips). The next execution shows the new frame.
+    pub fn seek_frame(&mut self, frame: usize) {
+        self.pause_streams();
+        self.frame_stream_handler.seek_video_streams(frame);
+    }
+
+    /// Change what video sources do when they reach the end of their loop
+    /// region.
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode) {
+        self.frame_stream_handler.set_loop_mode(loop_mode);
+    }
+
+    /// Restrict video sources to the `in..=out` frames of `region`, or play
+    /// whole videos if `region` is [None].
+    pub fn set_loop_region(&mut self, region: Option<RangeInclusive<usize>>) {
+        self.frame_stream_handler.set_loop_region(region);
+    }
+
+    /// Change how far ahead video sources decode.
+    pub fn set_buffering_policy(&mut self, buffering_policy: BufferingPolicy) {
+        self.frame_stream_handler
+            .set_buffering_policy(buffering_policy);
+    }
+
+    /// How each video source's decode buffer is doing.
+    pub fn buffering_stats(&self) -> HashMap<EngineNodeId, BufferingStats> {
+        self.frame_stream_handler.buffering_stats()
     }
 
     pub fn play_streams(&mut self) {
         self.frame_stream_handler.play_all_streams();
         self.noise_stream_handler.play_all_streams();
         self.midi_stream_handler.play_all_streams();
+        self.audio_meter_handler.play_all_streams();
+        self.tempo_handler.play_all_streams();
+        self.sprite_sheet_handler.play_all_streams();
+        self.time_remap_handler.play_all_streams();
     }
 
     pub fn set_global_stream_target_fps(&mut self, target_fps: Fps) {
@@ -453,9 +1139,11 @@ impl GraphExecutor {
 
     /// Resolve all inputs for a node instance
     /// Converts InputValue::Connection references into actual NodeValues
+    /// Unconnected optional Frame inputs are left out
     fn resolve_inputs(
         &self,
         instance: &NodeInstance,
+        definition: &NodeDefinition,
     ) -> Result<HashMap<String, NodeValue>, ExecutionError> {
         let mut resolved = HashMap::new();
 
@@ -485,8 +1173,16 @@ impl GraphExecutor {
                 InputValue::Text(t) => NodeValue::Text(t.clone()),
                 InputValue::Enum(idx) => NodeValue::Enum(*idx),
                 InputValue::File(path) => NodeValue::File(path.clone()),
+                InputValue::FloatArray(values) => NodeValue::FloatArray(values.clone()),
                 InputValue::Frame => {
-                    // Default empty frame
+                    let optional = definition
+                        .node
+                        .inputs
+                        .iter()
+                        .any(|input| &input.name == input_name && input.optional);
+                    if optional {
+                        continue;
+                    }
                     return Err(ExecutionError::UnconnectedFrameInput(
                         instance.id,
                         input_name.clone(),
@@ -509,7 +1205,7 @@ impl GraphExecutor {
         definition: &NodeDefinition,
         inputs: &HashMap<String, NodeValue>,
     ) -> Result<HashMap<String, NodeValue>, ExecutionError> {
-        let NodeExecutionPlan::Shader { source, passes } = &definition.node.executor else {
+        let NodeExecutionPlan::Shader { source, passes, .. } = &definition.node.executor else {
             return Err(ExecutionError::PipelineCreationError(format!(
                 "{} is not a shader node",
                 definition.node.name
@@ -524,6 +1220,7 @@ impl GraphExecutor {
                     source: pass.source.as_path(),
                     extra_frame_inputs: index,
                     dispatch: None,
+                    downscale: pass.downscale,
                 });
             }
             stages.push(EffectStage {
@@ -531,6 +1228,7 @@ impl GraphExecutor {
                 source: source.as_path(),
                 extra_frame_inputs: passes.len(),
                 dispatch: None,
+                downscale: 1,
             });
 
             return self.execute_effect_stages(node_id, device, queue, definition, inputs, &stages);
@@ -541,11 +1239,109 @@ impl GraphExecutor {
             source: source.as_path(),
             extra_frame_inputs: 0,
             dispatch: None,
+            downscale: 1,
         }];
 
         self.execute_effect_stages(node_id, device, queue, definition, inputs, &stages)
     }
 
+    /// Execute a node with the CPU backend. Returns [None] if the node has no
+    /// CPU kernel (for its inputs) or any of its frame inputs isn't available
+    /// on the CPU, in which case it should run on the GPU instead.
+    fn execute_cpu_node(
+        &mut self,
+        node_id: EngineNodeId,
+        instance: &NodeInstance,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        definition: &NodeDefinition,
+        inputs: &HashMap<String, NodeValue>,
+    ) -> Result<Option<HashMap<String, NodeValue>>, ExecutionError> {
+        if !cpu_backend::supports(definition, inputs) {
+            return Ok(None);
+        }
+
+        let mut frames = Vec::new();
+        for input in &definition.node.inputs {
+            if !matches!(input.kind, NodeInputKind::Frame) {
+                continue;
+            }
+            let Some(InputValue::Connection { from_node, .. }) =
+                instance.input_values.get(&input.name)
+            else {
+                return Ok(None);
+            };
+            let Some(frame) = self.cpu_frame_cache.get(from_node) else {
+                return Ok(None);
+            };
+            frames.push(frame);
+        }
+
+        let frame = cpu_backend::execute(definition, &frames, inputs)
+            .map_err(|e| ExecutionError::CpuBackendError(e.to_string()))?;
+        let frame_id = frames.first().map_or_else(|| frame.uid(), |f| f.uid());
+
+        let width = frame.dimensions().width();
+        let height = frame.dimensions().height();
+        let view = self
+            .cpu_upload_stagers
+            .entry(node_id)
+            .or_default()
+            .cpu_to_gpu_rgba(device, queue, width, height, frame.raw_data())
+            .map_err(|e| ExecutionError::TextureUploadError(format!("{e:?}")))?;
+        let gpu_frame = GpuFrame::new(
+            view,
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            frame_id,
+        );
+        self.cpu_frame_cache.insert(node_id, frame);
+
+        let outputs = definition
+            .node
+            .outputs
+            .iter()
+            .map(|output| (output.name.clone(), NodeValue::Frame(gpu_frame.clone())))
+            .collect();
+        Ok(Some(outputs))
+    }
+
+    /// Fetch a frame for an image/video source. On the CPU backend the CPU
+    /// frame is kept too so downstream CPU nodes can use it.
+    fn execute_frame_source<F>(
+        &mut self,
+        request: &NodeFrameStreamRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        emit_event: &mut F,
+    ) -> Result<Vec<NodeValue>, FrameStreamHandlerError>
+    where
+        F: FnMut(EngineOutpostEvent),
+    {
+        if self.backend != ExecutionBackend::Cpu {
+            return self.frame_stream_handler.execute_handler(
+                request,
+                device,
+                queue,
+                &mut self.upload_stager,
+                emit_event,
+            );
+        }
+
+        let (outputs, frame) = self.frame_stream_handler.execute_handler_keep_cpu_frame(
+            request,
+            device,
+            queue,
+            &mut self.upload_stager,
+            emit_event,
+        )?;
+        self.cpu_frame_cache.insert(request.node_id, frame);
+        Ok(outputs)
+    }
+
     /// Execute a built-in node
     ///
     #[allow(clippy::too_many_arguments)]
@@ -576,10 +1372,15 @@ impl GraphExecutor {
                     node_id,
                     file_path: path.clone(),
                     stream_kind: StreamKind::Image,
+                    conform_policy: conform_policy_input(inputs),
+                    rotation: None,
+                    tone_map: ToneMapOperator::default(),
+                    source_time: None,
+                    interpolation: FrameInterpolation::default(),
+                    trim: VideoTrim::default(),
                 };
 
-                self.frame_stream_handler
-                    .execute_handler(&request, device, queue, &mut self.upload_stager, emit_event)
+                self.execute_frame_source(&request, device, queue, emit_event)
                     .map_err(|error| match error {
                         FrameStreamHandlerError::Loading { path } => {
                             ExecutionError::FrameStreamNotReady(path)
@@ -603,10 +1404,15 @@ impl GraphExecutor {
                     node_id,
                     file_path: path.clone(),
                     stream_kind: StreamKind::Video,
+                    conform_policy: conform_policy_input(inputs),
+                    rotation: rotation_input(inputs),
+                    tone_map: tone_map_input(inputs),
+                    source_time: None,
+                    interpolation: FrameInterpolation::default(),
+                    trim: trim_input(inputs),
                 };
 
-                self.frame_stream_handler
-                    .execute_handler(&request, device, queue, &mut self.upload_stager, emit_event)
+                self.execute_frame_source(&request, device, queue, emit_event)
                     .map_err(|error| match error {
                         FrameStreamHandlerError::Loading { path } => {
                             ExecutionError::FrameStreamNotReady(path)
@@ -617,6 +1423,157 @@ impl GraphExecutor {
                         ),
                     })?
             }
+            BuiltInHandler::TimeRemap => {
+                let path = inputs
+                    .values()
+                    .find_map(|v| match v {
+                        NodeValue::File(p) => Some(p),
+                        _ => None,
+                    })
+                    .ok_or(ExecutionError::InvalidInputType)?;
+
+                let source_time = self
+                    .time_remap_handler
+                    .source_time(&NodeTimeRemapRequest { node_id, inputs })
+                    .map_err(|error| ExecutionError::TimeRemapError(error.to_string()))?;
+
+                let request = NodeFrameStreamRequest {
+                    node_id,
+                    file_path: path.clone(),
+                    stream_kind: StreamKind::Video,
+                    conform_policy: conform_policy_input(inputs),
+                    rotation: rotation_input(inputs),
+                    tone_map: tone_map_input(inputs),
+                    source_time: Some(source_time),
+                    interpolation: interpolation_input(inputs),
+                    trim: VideoTrim::default(),
+                };
+
+                let mut outputs = self
+                    .execute_frame_source(&request, device, queue, emit_event)
+                    .map_err(|error| match error {
+                        FrameStreamHandlerError::Loading { path } => {
+                            ExecutionError::FrameStreamNotReady(path)
+                        }
+                        other => ExecutionError::VideoStreamError(
+                            path.clone(),
+                            format!("Time remap stream execution failed: {:?}", other),
+                        ),
+                    })?;
+                outputs.push(NodeValue::Float(source_time as f32));
+                outputs
+            }
+            BuiltInHandler::FrameInterpolate => {
+                let (Some(NodeValue::Frame(from)), Some(NodeValue::Frame(to))) =
+                    (inputs.get("From"), inputs.get("To"))
+                else {
+                    return Err(ExecutionError::MissingInterpolationFrames);
+                };
+                let position = match inputs.get("Position") {
+                    Some(NodeValue::Float(position)) => *position,
+                    _ => 0.5,
+                };
+
+                let target = self.get_or_create_render_target(device, node_id, from.size);
+                let target_format = self.target_format;
+                let interpolator = self
+                    .frame_interpolator
+                    .get_or_insert_with(|| FrameInterpolator::new(device, target_format));
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("frame_interpolate"),
+                });
+                interpolator.interpolate(
+                    device,
+                    queue,
+                    &mut encoder,
+                    from.view(),
+                    to.view(),
+                    from.size,
+                    position,
+                    flow_quality_input(inputs),
+                    &target,
+                );
+                queue.submit(Some(encoder.finish()));
+
+                vec![NodeValue::Frame(GpuFrame {
+                    view: target,
+                    size: from.size,
+                    frame_id: Uid::generate_new(),
+                })]
+            }
+            BuiltInHandler::Stabilize => {
+                let path = inputs
+                    .values()
+                    .find_map(|v| match v {
+                        NodeValue::File(p) => Some(p),
+                        _ => None,
+                    })
+                    .ok_or(ExecutionError::InvalidInputType)?;
+
+                let request = NodeFrameStreamRequest {
+                    node_id,
+                    file_path: path.clone(),
+                    stream_kind: StreamKind::Video,
+                    conform_policy: conform_policy_input(inputs),
+                    rotation: rotation_input(inputs),
+                    tone_map: tone_map_input(inputs),
+                    source_time: None,
+                    interpolation: FrameInterpolation::default(),
+                    trim: VideoTrim::default(),
+                };
+
+                let outputs = self
+                    .execute_frame_source(&request, device, queue, emit_event)
+                    .map_err(|error| match error {
+                        FrameStreamHandlerError::Loading { path } => {
+                            ExecutionError::FrameStreamNotReady(path)
+                        }
+                        other => ExecutionError::VideoStreamError(
+                            path.clone(),
+                            format!("Stabilize stream execution failed: {:?}", other),
+                        ),
+                    })?;
+                let Some(NodeValue::Frame(source)) = outputs.into_iter().next() else {
+                    return Err(ExecutionError::StabilizeError(
+                        "the video produced no frame".to_string(),
+                    ));
+                };
+
+                // The frame on screen, as a time so it can be looked up in an
+                // analysis made at the video's own frame rate.
+                let source_time = self
+                    .frame_stream_handler
+                    .video_position(&request)
+                    .map(|(frame, fps)| frame as f64 / fps.as_float());
+
+                // The CPU copy is of the unstabilized frame.
+                self.cpu_frame_cache.remove(&node_id);
+
+                let target = self.get_or_create_render_target(device, node_id, source.size);
+                let progress = self
+                    .stabilize_handler
+                    .execute_handler(
+                        &NodeStabilizeRequest {
+                            inputs,
+                            file_path: path,
+                            source_time,
+                        },
+                        device,
+                        queue,
+                        &source,
+                        &target,
+                    )
+                    .map_err(|error| ExecutionError::StabilizeError(error.to_string()))?;
+
+                vec![
+                    NodeValue::Frame(GpuFrame {
+                        view: target,
+                        size: source.size,
+                        frame_id: Uid::generate_new(),
+                    }),
+                    NodeValue::Float(progress),
+                ]
+            }
             BuiltInHandler::Noise(noise_kind) => {
                 let request = NodeNoiseStreamRequest {
                     node_id,
@@ -647,6 +1604,167 @@ impl GraphExecutor {
                     .execute_handler(&request)
                     .map_err(|error| ExecutionError::SignalEnvelopeError(error.to_string()))?
             }
+            BuiltInHandler::AudioMeter => {
+                let request = NodeAudioMeterRequest { node_id, inputs };
+
+                self.audio_meter_handler
+                    .execute_handler(&request)
+                    .map_err(|error| ExecutionError::AudioMeterError(error.to_string()))?
+            }
+            BuiltInHandler::Tempo => {
+                let request = NodeTempoRequest { node_id, inputs };
+
+                self.tempo_handler
+                    .execute_tempo(&request)
+                    .map_err(|error| ExecutionError::TempoError(error.to_string()))?
+            }
+            BuiltInHandler::Math(op) => {
+                let request = NodeMathRequest { op, inputs };
+
+                execute_math(&request)
+                    .map_err(|error| ExecutionError::MathError(error.to_string()))?
+            }
+            BuiltInHandler::Constant => execute_constant(inputs)
+                .map_err(|error| ExecutionError::MathError(error.to_string()))?,
+            BuiltInHandler::TextTemplate => execute_text_template(inputs)
+                .map_err(|error| ExecutionError::TextTemplateError(error.to_string()))?,
+            BuiltInHandler::Lfo => {
+                let request = NodeLfoRequest { node_id, inputs };
+
+                self.tempo_handler
+                    .execute_lfo(&request)
+                    .map_err(|error| ExecutionError::TempoError(error.to_string()))?
+            }
+            BuiltInHandler::FrameDelay => {
+                let request = NodeFrameDelayRequest { node_id, inputs };
+
+                self.feedback_handler
+                    .execute_frame_delay(&request, device, queue)
+                    .map_err(|error| ExecutionError::FeedbackError(error.to_string()))?
+            }
+            BuiltInHandler::Feedback => {
+                let (width, height) = self.output_format.resolution.map_or((1, 1), |resolution| {
+                    (resolution.width(), resolution.height())
+                });
+                let request = NodeFeedbackRequest {
+                    inputs,
+                    fallback_size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                };
+
+                self.feedback_handler
+                    .execute_feedback(&request, device, queue)
+                    .map_err(|error| ExecutionError::FeedbackError(error.to_string()))?
+            }
+            BuiltInHandler::FaceDetect => {
+                let request = NodeFaceDetectRequest { node_id, inputs };
+
+                self.face_detect_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::FaceDetectError(error.to_string()))?
+            }
+            BuiltInHandler::DepthEstimate => {
+                let request = NodeDepthEstimateRequest { node_id, inputs };
+
+                self.depth_estimate_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::DepthEstimateError(error.to_string()))?
+            }
+            BuiltInHandler::LevelsCurves => {
+                let request = NodeLevelsCurvesRequest { node_id, inputs };
+
+                self.levels_curves_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::LevelsCurvesError(error.to_string()))?
+            }
+            BuiltInHandler::WhiteBalance => {
+                let request = NodeWhiteBalanceRequest { node_id, inputs };
+
+                self.white_balance_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::WhiteBalanceError(error.to_string()))?
+            }
+            BuiltInHandler::Equalize => {
+                let request = NodeEqualizeRequest { node_id, inputs };
+
+                self.equalize_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::EqualizeError(error.to_string()))?
+            }
+            BuiltInHandler::Threshold => {
+                let request = NodeThresholdRequest { node_id, inputs };
+
+                self.threshold_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::ThresholdError(error.to_string()))?
+            }
+            BuiltInHandler::BlobTrack => {
+                let request = NodeBlobTrackRequest { node_id, inputs };
+
+                self.blob_track_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::BlobTrackError(error.to_string()))?
+            }
+            BuiltInHandler::Trails => {
+                let request = NodeTrailsRequest { node_id, inputs };
+
+                self.trails_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::TrailsError(error.to_string()))?
+            }
+            BuiltInHandler::SlitScan => {
+                let request = NodeSlitScanRequest { node_id, inputs };
+
+                self.slit_scan_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::SlitScanError(error.to_string()))?
+            }
+            BuiltInHandler::MatchColor => {
+                let request = NodeMatchColorRequest { node_id, inputs };
+
+                self.match_color_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::MatchColorError(error.to_string()))?
+            }
+            BuiltInHandler::Switcher => {
+                let request = NodeSwitcherRequest { node_id, inputs };
+
+                self.switcher_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::SwitcherError(error.to_string()))?
+            }
+            BuiltInHandler::Layout => {
+                let request = NodeLayoutRequest { node_id, inputs };
+
+                self.layout_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::LayoutError(error.to_string()))?
+            }
+            BuiltInHandler::Watermark => {
+                let request = NodeWatermarkRequest { node_id, inputs };
+
+                self.watermark_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::WatermarkError(error.to_string()))?
+            }
+            BuiltInHandler::SpriteSheet => {
+                let request = NodeSpriteSheetRequest { node_id, inputs };
+
+                let outputs = self
+                    .sprite_sheet_handler
+                    .execute_handler(&request, device, queue)
+                    .map_err(|error| ExecutionError::SpriteSheetError(error.to_string()))?;
+
+                if self.backend == ExecutionBackend::Cpu
+                    && let Some(cell) = self.sprite_sheet_handler.current_cell(node_id)
+                {
+                    self.cpu_frame_cache.insert(node_id, cell.clone());
+                }
+                outputs
+            }
         };
 
         let mut outputs = HashMap::new();
@@ -811,6 +1929,22 @@ impl GraphExecutor {
                 | NodeExecutionPlan::BuiltIn(BuiltInHandler::MidiSource)
                 | NodeExecutionPlan::BuiltIn(BuiltInHandler::Noise(_))
                 | NodeExecutionPlan::BuiltIn(BuiltInHandler::SignalEnvelope)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::AudioMeter)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::Tempo)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::Lfo)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::SpriteSheet)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::TimeRemap)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::Stabilize)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::FrameDelay)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::Feedback)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::FaceDetect)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::BlobTrack)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::Trails)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::SlitScan)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::DepthEstimate)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::WhiteBalance)
+                | NodeExecutionPlan::BuiltIn(BuiltInHandler::Switcher)
+                | NodeExecutionPlan::Wasm { .. }
         )
     }
 
@@ -860,7 +1994,76 @@ impl GraphExecutor {
             NodeValue::Text(value) => value.hash(hasher),
             NodeValue::Enum(value) => value.hash(hasher),
             NodeValue::File(path) => path.hash(hasher),
+            NodeValue::FloatArray(values) => {
+                values.len().hash(hasher);
+                for value in values {
+                    value.to_bits().hash(hasher);
+                }
+            }
+        }
+    }
+}
+
+/// Read a frame source's [ConformPolicy] from its [CONFORM_INPUT_NAME] input,
+/// whose choices are in [ConformPolicy::ALL] order.
+fn conform_policy_input(inputs: &HashMap<String, NodeValue>) -> ConformPolicy {
+    match inputs.get(CONFORM_INPUT_NAME) {
+        Some(NodeValue::Enum(idx)) => ConformPolicy::ALL.get(*idx).copied().unwrap_or_default(),
+        _ => ConformPolicy::default(),
+    }
+}
+
+/// Read a video source's rotation override from its [ROTATION_INPUT_NAME]
+/// input. The first choice follows the video's metadata ([None]), the rest are
+/// in [Rotation::ALL] order.
+fn rotation_input(inputs: &HashMap<String, NodeValue>) -> Option<Rotation> {
+    match inputs.get(ROTATION_INPUT_NAME) {
+        Some(NodeValue::Enum(idx @ 1..)) => Rotation::ALL.get(idx - 1).copied(),
+        _ => None,
+    }
+}
+
+/// Read a video source's [ToneMapOperator] from its [TONE_MAP_INPUT_NAME]
+/// input, whose choices are in [ToneMapOperator::ALL] order.
+fn tone_map_input(inputs: &HashMap<String, NodeValue>) -> ToneMapOperator {
+    match inputs.get(TONE_MAP_INPUT_NAME) {
+        Some(NodeValue::Enum(idx)) => ToneMapOperator::ALL.get(*idx).copied().unwrap_or_default(),
+        _ => ToneMapOperator::default(),
+    }
+}
+
+/// Read a video source's [VideoTrim] from its [TRIM_IN_INPUT_NAME],
+/// [TRIM_OUT_INPUT_NAME], and [TIMELINE_OFFSET_INPUT_NAME] inputs.
+fn trim_input(inputs: &HashMap<String, NodeValue>) -> VideoTrim {
+    let secs = |name: &str| match inputs.get(name) {
+        Some(NodeValue::Float(secs)) => *secs as f64,
+        _ => 0.0,
+    };
+    let end_secs = secs(TRIM_OUT_INPUT_NAME);
+    VideoTrim {
+        start_secs: secs(TRIM_IN_INPUT_NAME),
+        end_secs: (end_secs > 0.0).then_some(end_secs),
+        offset_secs: secs(TIMELINE_OFFSET_INPUT_NAME),
+    }
+}
+
+/// Read a node's [FlowQuality] from its [FLOW_QUALITY_INPUT_NAME] input, whose
+/// choices are in [FlowQuality::ALL] order.
+fn flow_quality_input(inputs: &HashMap<String, NodeValue>) -> FlowQuality {
+    match inputs.get(FLOW_QUALITY_INPUT_NAME) {
+        Some(NodeValue::Enum(idx)) => FlowQuality::from_choice_index(*idx),
+        _ => FlowQuality::default(),
+    }
+}
+
+/// Read a Time Remap node's [FrameInterpolation] from its
+/// [INTERPOLATION_INPUT_NAME] and [FLOW_QUALITY_INPUT_NAME] inputs.
+fn interpolation_input(inputs: &HashMap<String, NodeValue>) -> FrameInterpolation {
+    match inputs.get(INTERPOLATION_INPUT_NAME) {
+        Some(NodeValue::Enum(idx)) => {
+            FrameInterpolation::from_choice_index(*idx, flow_quality_input(inputs))
         }
+        _ => FrameInterpolation::default(),
     }
 }
 
@@ -0,0 +1,427 @@
+//! Dry-run cost estimation for a [NodeGraph]. See [GraphExecutor::estimate].
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::graph_executor::{ExecutionError, GraphExecutor};
+use crate::node::NodeDefinition;
+use crate::node::NodeLibrary;
+use crate::node::engine_node::{
+    AlgorithmStageBackend, AlgorithmStageDispatchMode, BuiltInHandler, NodeExecutionPlan,
+    NodeOutputKind,
+};
+use crate::node_graph::{EngineNodeId, InputValue, NodeGraph, NodeInstance};
+
+/// Rough number of texture samples per second a modest integrated GPU can
+/// sustain. This is deliberately pessimistic: the estimate is used to warn
+/// before playback stutters, not to promise a frame rate.
+const ESTIMATED_SAMPLES_PER_SECOND: f64 = 2.0e9;
+
+/// Cost multiplier for compute stages dispatched one invocation per row or
+/// column (e.g. pixel sorting), which serialize work along that axis.
+const SERIAL_DISPATCH_COST: f64 = 8.0;
+
+/// Bytes per pixel of render targets (the engine renders to 8-bit RGBA).
+const RENDER_TARGET_BYTES_PER_PIXEL: u64 = 4;
+
+/// Bytes per pixel of intermediate compute targets (`Rgba16Float`).
+const COMPUTE_TARGET_BYTES_PER_PIXEL: u64 = 8;
+
+/// Input names (compared case-insensitively) that are treated as a kernel
+/// radius or sample count when estimating how many samples a pass takes.
+const KERNEL_INPUT_NAMES: &[&str] = &["radius", "kernel size", "samples", "taps"];
+
+/// The estimated cost of a single node. Costs are per pixel so the same
+/// report can be evaluated at any resolution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeCost {
+    pub node_id: EngineNodeId,
+    pub definition_name: String,
+
+    /// Number of GPU passes (render or compute) the node runs.
+    pub passes: usize,
+
+    /// Number of output-sized textures the node allocates.
+    pub texture_allocations: usize,
+
+    /// Bytes of texture memory the node allocates per output pixel.
+    pub texture_bytes_per_pixel: u64,
+
+    /// Approximate texture samples per output pixel across all passes.
+    pub samples_per_pixel: f64,
+
+    /// Whether the node re-executes every frame (it depends on a video, noise,
+    /// MIDI, or signal-envelope source). Static nodes are cached after their
+    /// first execution and don't count towards the per-frame cost.
+    pub dynamic: bool,
+}
+
+/// The result of [GraphExecutor::estimate].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostReport {
+    /// Every node that would be executed, in execution order.
+    pub nodes: Vec<NodeCost>,
+}
+
+impl CostReport {
+    /// Total number of GPU passes across all nodes.
+    pub fn total_passes(&self) -> usize {
+        self.nodes.iter().map(|node| node.passes).sum()
+    }
+
+    /// Total number of output-sized textures allocated across all nodes.
+    pub fn total_texture_allocations(&self) -> usize {
+        self.nodes.iter().map(|node| node.texture_allocations).sum()
+    }
+
+    /// Approximate texture memory used at `width`x`height`, in bytes.
+    pub fn texture_memory_bytes(&self, width: u32, height: u32) -> u64 {
+        let pixels = width as u64 * height as u64;
+        self.nodes
+            .iter()
+            .map(|node| node.texture_bytes_per_pixel * pixels)
+            .sum()
+    }
+
+    /// Approximate GPU time to produce one frame at `width`x`height`, counting
+    /// only nodes that re-execute every frame.
+    pub fn estimated_frame_time(&self, width: u32, height: u32) -> Duration {
+        let pixels = width as f64 * height as f64;
+        let samples: f64 = self
+            .nodes
+            .iter()
+            .filter(|node| node.dynamic)
+            .map(|node| node.samples_per_pixel * pixels)
+            .sum();
+
+        Duration::from_secs_f64(samples / ESTIMATED_SAMPLES_PER_SECOND)
+    }
+
+    /// Whether the graph can likely keep up with `fps` at `width`x`height`.
+    pub fn likely_sustains(&self, fps: f64, width: u32, height: u32) -> bool {
+        self.estimated_frame_time(width, height).as_secs_f64() * fps <= 1.0
+    }
+
+    /// The most expensive per-frame nodes first.
+    pub fn most_expensive(&self) -> Vec<&NodeCost> {
+        let mut nodes: Vec<&NodeCost> = self.nodes.iter().filter(|node| node.dynamic).collect();
+        nodes.sort_by(|a, b| b.samples_per_pixel.total_cmp(&a.samples_per_pixel));
+        nodes
+    }
+}
+
+impl GraphExecutor {
+    /// Estimate the cost of executing `graph` without touching the GPU.
+    ///
+    /// Only nodes connected to an output node are included, matching what
+    /// [GraphExecutor::execute] would run. The estimate is based on the
+    /// node definitions (pass/stage counts and dispatch modes) and any kernel
+    /// size inputs, so it's only a rough guide.
+    pub fn estimate(
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+    ) -> Result<CostReport, ExecutionError> {
+        let order = graph
+            .execution_order()
+            .map_err(ExecutionError::GraphError)?;
+
+        let mut required = HashSet::new();
+        for output in Self::output_nodes(graph, library, &order) {
+            required.extend(Self::collect_required_nodes_for_target(graph, output));
+        }
+        Self::estimate_nodes(graph, library, order, &required)
+    }
+
+    /// Estimate the cost of the nodes in `required`, which come in execution
+    /// `order`.
+    pub(super) fn estimate_nodes(
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+        order: Vec<EngineNodeId>,
+        required: &HashSet<EngineNodeId>,
+    ) -> Result<CostReport, ExecutionError> {
+        let mut dynamic_nodes = HashSet::new();
+        let mut nodes = Vec::new();
+
+        for node_id in order.into_iter().filter(|id| required.contains(id)) {
+            let instance = graph
+                .get_instance(node_id)
+                .ok_or(ExecutionError::NodeNotFound(node_id))?;
+            let definition = library
+                .get_definition(&instance.definition_name)
+                .ok_or_else(|| {
+                    ExecutionError::DefinitionNotFound(instance.definition_name.clone())
+                })?;
+
+            // Execution order is topological, so every upstream node has
+            // already been classified.
+            let dynamic = !Self::is_cacheable_node(definition)
+                || instance.input_values.values().any(|input| {
+                    matches!(input, InputValue::Connection { from_node, .. }
+                        if dynamic_nodes.contains(from_node))
+                });
+            if dynamic {
+                dynamic_nodes.insert(node_id);
+            }
+
+            nodes.push(Self::estimate_node(instance, definition, dynamic));
+        }
+
+        Ok(CostReport { nodes })
+    }
+
+    fn estimate_node(
+        instance: &NodeInstance,
+        definition: &NodeDefinition,
+        dynamic: bool,
+    ) -> NodeCost {
+        let kernel = kernel_samples(instance);
+        let has_frame_output = definition
+            .node
+            .outputs
+            .iter()
+            .any(|output| matches!(output.kind, NodeOutputKind::Frame));
+
+        let (passes, texture_allocations, texture_bytes_per_pixel, samples_per_pixel) =
+            match &definition.node.executor {
+                NodeExecutionPlan::Shader { passes, .. } => {
+                    // Every pre-pass renders into its own intermediate target,
+                    // which covers fewer pixels when the pass is downscaled.
+                    let pass_count = passes.len() + 1;
+                    let mut bytes = RENDER_TARGET_BYTES_PER_PIXEL;
+                    let mut samples = kernel;
+                    for pass in passes {
+                        let area = u64::from(pass.downscale.max(1)).pow(2);
+                        bytes += RENDER_TARGET_BYTES_PER_PIXEL.div_ceil(area);
+                        samples += kernel / area as f64;
+                    }
+                    (pass_count, pass_count, bytes, samples)
+                }
+                NodeExecutionPlan::Algorithm { stages, .. } => {
+                    let mut textures = 0;
+                    let mut bytes = 0;
+                    let mut samples = 0.0;
+
+                    for stage in stages {
+                        textures += 1;
+                        match stage.backend {
+                            AlgorithmStageBackend::Render => {
+                                bytes += RENDER_TARGET_BYTES_PER_PIXEL;
+                                samples += kernel;
+                            }
+                            AlgorithmStageBackend::Compute => {
+                                bytes += COMPUTE_TARGET_BYTES_PER_PIXEL;
+                                let serial = stage.dispatch.as_ref().is_some_and(|dispatch| {
+                                    dispatch.mode != AlgorithmStageDispatchMode::Auto
+                                });
+                                samples += if serial {
+                                    kernel * SERIAL_DISPATCH_COST
+                                } else {
+                                    kernel
+                                };
+                            }
+                        }
+                    }
+
+                    // A final compute stage is blitted into a render target.
+                    let ends_in_compute = stages
+                        .last()
+                        .is_some_and(|stage| stage.backend == AlgorithmStageBackend::Compute);
+                    if ends_in_compute && has_frame_output {
+                        textures += 1;
+                        bytes += RENDER_TARGET_BYTES_PER_PIXEL;
+                        samples += 1.0;
+                    }
+
+                    (stages.len(), textures, bytes, samples)
+                }
+                NodeExecutionPlan::BuiltIn(handler) => match handler {
+                    // Sources upload one frame; the upload is roughly one
+                    // sample per pixel.
+                    BuiltInHandler::ImageSource
+                    | BuiltInHandler::VideoSource
+                    | BuiltInHandler::SpriteSheet
+                    | BuiltInHandler::Noise(_) => (0, 1, RENDER_TARGET_BYTES_PER_PIXEL, 1.0),
+                    // Remapped video may blend two frames before uploading.
+                    BuiltInHandler::TimeRemap => (0, 1, RENDER_TARGET_BYTES_PER_PIXEL, 2.0),
+                    // A motion search at a quarter of the resolution (about
+                    // 300 samples per output pixel with the default quality),
+                    // then a warp.
+                    BuiltInHandler::FrameInterpolate => {
+                        (2, 2, RENDER_TARGET_BYTES_PER_PIXEL, 330.0)
+                    }
+                    // A video upload, then one transformed copy of it.
+                    BuiltInHandler::Stabilize => (1, 2, RENDER_TARGET_BYTES_PER_PIXEL, 2.0),
+                    // A Frame Delay copies its input into its history; a
+                    // Feedback only hands that history back out.
+                    BuiltInHandler::FrameDelay => (1, 1, RENDER_TARGET_BYTES_PER_PIXEL, 1.0),
+                    BuiltInHandler::Feedback => (0, 0, 0, 0.0),
+                    // Only a small copy is read back; detection runs on the
+                    // CPU.
+                    BuiltInHandler::FaceDetect => (1, 0, 0, 0.0),
+                    // A small copy read back (as for Face Detect), plus the
+                    // depth map stretched to the input's size when it updates.
+                    BuiltInHandler::DepthEstimate => (2, 1, RENDER_TARGET_BYTES_PER_PIXEL, 1.0),
+                    // One lookup per channel; the table itself is tiny.
+                    BuiltInHandler::LevelsCurves => (1, 1, RENDER_TARGET_BYTES_PER_PIXEL, 1.0),
+                    // The correction, plus shrinking the input into a small
+                    // analysis texture when correcting automatically.
+                    BuiltInHandler::WhiteBalance => (2, 1, RENDER_TARGET_BYTES_PER_PIXEL, 1.0),
+                    // Every pixel is read once for the histograms and then
+                    // looked up in four tiles' tables.
+                    BuiltInHandler::Equalize => (2, 1, RENDER_TARGET_BYTES_PER_PIXEL, 2.0),
+                    // The mask, plus either the histogram passes (Otsu) or a
+                    // mipmapped copy of the input (adaptive).
+                    BuiltInHandler::Threshold => (3, 2, RENDER_TARGET_BYTES_PER_PIXEL, 2.0),
+                    // A small copy read back and labeled on the CPU, as for
+                    // Face Detect.
+                    BuiltInHandler::BlobTrack => (1, 0, 0, 0.0),
+                    // The blend into a half-float accumulation (read and
+                    // written), then a copy out of it.
+                    BuiltInHandler::Trails => (2, 3, 2 * 8 + RENDER_TARGET_BYTES_PER_PIXEL, 3.0),
+                    // A copy into the history, then one frame drawn from it
+                    // (two layers read per pixel when smoothing).
+                    BuiltInHandler::SlitScan => (2, 2, RENDER_TARGET_BYTES_PER_PIXEL, 3.0),
+                    // Both frames are read once for their histograms, then the
+                    // input is drawn through the lookup tables.
+                    BuiltInHandler::MatchColor => (2, 1, RENDER_TARGET_BYTES_PER_PIXEL, 3.0),
+                    // Both inputs read for one draw while transitioning;
+                    // otherwise the selected input is passed straight through.
+                    BuiltInHandler::Switcher => (1, 1, RENDER_TARGET_BYTES_PER_PIXEL, 1.0),
+                    // Every pixel is cleared, and each input is read once into
+                    // its cell.
+                    BuiltInHandler::Layout => (1, 1, RENDER_TARGET_BYTES_PER_PIXEL, 1.0),
+                    // A copy of the input, then the mark drawn over part of
+                    // it.
+                    BuiltInHandler::Watermark => (2, 1, RENDER_TARGET_BYTES_PER_PIXEL, 2.0),
+                    BuiltInHandler::MidiSource
+                    | BuiltInHandler::MidiProperties
+                    | BuiltInHandler::SignalEnvelope
+                    | BuiltInHandler::AudioMeter
+                    | BuiltInHandler::Tempo
+                    | BuiltInHandler::Lfo
+                    | BuiltInHandler::Math(_)
+                    | BuiltInHandler::Constant
+                    | BuiltInHandler::TextTemplate => (0, 0, 0, 0.0),
+                },
+                // Modules run on the CPU and only output plain values.
+                NodeExecutionPlan::Wasm { .. } => (0, 0, 0, 0.0),
+            };
+
+        // Scalar-output nodes render into a single pixel, so their cost
+        // doesn't scale with resolution.
+        let (texture_bytes_per_pixel, samples_per_pixel) = if has_frame_output {
+            (texture_bytes_per_pixel, samples_per_pixel)
+        } else {
+            (0, 0.0)
+        };
+
+        NodeCost {
+            node_id: instance.id,
+            definition_name: instance.definition_name.clone(),
+            passes,
+            texture_allocations,
+            texture_bytes_per_pixel,
+            samples_per_pixel,
+            dynamic,
+        }
+    }
+}
+
+/// Samples per pixel a single pass of this node takes, based on any input that
+/// looks like a kernel radius or sample count. Defaults to one.
+fn kernel_samples(instance: &NodeInstance) -> f64 {
+    instance
+        .input_values
+        .iter()
+        .filter(|(name, _)| {
+            let name = name.to_lowercase();
+            KERNEL_INPUT_NAMES.contains(&name.as_str())
+        })
+        .filter_map(|(name, value)| {
+            let amount = match value {
+                InputValue::Int(i) => *i as f64,
+                InputValue::Float(f) => *f as f64,
+                _ => return None,
+            };
+            // A radius covers both sides of the pixel (assume a separable
+            // kernel); a sample count is taken as-is.
+            Some(if name.eq_ignore_ascii_case("radius") {
+                2.0 * amount.max(0.0) + 1.0
+            } else {
+                amount.max(1.0)
+            })
+        })
+        .fold(1.0, f64::max)
+}
+
+#[cfg(test)]
+mod decision_coverage_tests {
+    use super::*;
+
+    fn node(samples_per_pixel: f64, dynamic: bool) -> NodeCost {
+        NodeCost {
+            node_id: EngineNodeId::default(),
+            definition_name: String::new(),
+            passes: 1,
+            texture_allocations: 1,
+            texture_bytes_per_pixel: RENDER_TARGET_BYTES_PER_PIXEL,
+            samples_per_pixel,
+            dynamic,
+        }
+    }
+
+    // --- CostReport::estimated_frame_time ---
+
+    #[test]
+    fn estimated_frame_time_ignores_static_nodes() {
+        let report = CostReport {
+            nodes: vec![node(1.0, true), node(1000.0, false)],
+        };
+        let expected = Duration::from_secs_f64(1920.0 * 1080.0 / ESTIMATED_SAMPLES_PER_SECOND);
+        assert_eq!(report.estimated_frame_time(1920, 1080), expected);
+    }
+
+    // --- CostReport::likely_sustains ---
+
+    #[test]
+    fn likely_sustains_depends_on_resolution() {
+        let report = CostReport {
+            nodes: vec![node(100.0, true)],
+        };
+        assert!(report.likely_sustains(60.0, 640, 360));
+        assert!(!report.likely_sustains(60.0, 3840, 2160));
+    }
+
+    #[test]
+    fn likely_sustains_empty_report() {
+        assert!(CostReport::default().likely_sustains(60.0, 3840, 2160));
+    }
+
+    // --- CostReport::most_expensive ---
+
+    #[test]
+    fn most_expensive_sorts_dynamic_nodes() {
+        let report = CostReport {
+            nodes: vec![node(2.0, true), node(50.0, false), node(9.0, true)],
+        };
+        let samples: Vec<f64> = report
+            .most_expensive()
+            .iter()
+            .map(|node| node.samples_per_pixel)
+            .collect();
+        assert_eq!(samples, [9.0, 2.0]);
+    }
+
+    // --- CostReport totals ---
+
+    #[test]
+    fn totals_sum_every_node() {
+        let report = CostReport {
+            nodes: vec![node(1.0, true), node(1.0, false)],
+        };
+        assert_eq!(report.total_passes(), 2);
+        assert_eq!(report.total_texture_allocations(), 2);
+        assert_eq!(report.texture_memory_bytes(10, 10), 800);
+    }
+}
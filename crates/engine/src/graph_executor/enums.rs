@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
 use crate::gpu_frame::GpuFrame;
+use media::fps::Fps;
+use media::frame::Dimensions;
 use media::midi::MidiPacket;
 
 /// A value in the node graph execution system.
@@ -22,6 +24,7 @@ pub enum NodeValue {
     Enum(usize),
     /// File path (inputs only)
     File(PathBuf),
+    FloatArray(Vec<f32>),
 }
 
 impl Default for NodeValue {
@@ -29,3 +32,78 @@ impl Default for NodeValue {
         NodeValue::Float(0.0)
     }
 }
+
+/// A rectangle of the output, in fractions of its width and height (`0.0` to
+/// `1.0`, from the top left corner). See
+/// [GraphExecutor::set_render_region](super::GraphExecutor::set_render_region).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl RenderRegion {
+    /// The pixels the region covers in a `width` by `height` frame, as `(x, y,
+    /// width, height)`. Always at least one pixel, and never past the frame's
+    /// edges.
+    pub fn pixels(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let to_pixels =
+            |fraction: f32, size: u32| (fraction.clamp(0.0, 1.0) * size as f32).round() as u32;
+        let x = to_pixels(self.x, width).min(width.saturating_sub(1));
+        let y = to_pixels(self.y, height).min(height.saturating_sub(1));
+        let right = to_pixels(self.x + self.width, width);
+        let bottom = to_pixels(self.y + self.height, height);
+        (
+            x,
+            y,
+            right.saturating_sub(x).max(1),
+            bottom.saturating_sub(y).max(1),
+        )
+    }
+}
+
+/// Project-level output settings. Image and video sources are conformed
+/// (letterboxed and scaled) to `resolution` and played at `fps` so sources of
+/// different sizes and frame rates mix predictably. [None] fields follow the
+/// sources instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputFormat {
+    pub resolution: Option<Dimensions>,
+    pub fps: Option<Fps>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- RenderRegion::pixels() ---
+
+    #[test]
+    fn test_render_region_pixels() {
+        let region = RenderRegion {
+            x: 0.25,
+            y: 0.5,
+            width: 0.5,
+            height: 0.25,
+        };
+        assert_eq!(region.pixels(1920, 1080), (480, 540, 960, 270));
+
+        // Regions are clamped to the frame and never empty.
+        let region = RenderRegion {
+            x: 0.9,
+            y: -1.0,
+            width: 0.5,
+            height: 0.0,
+        };
+        assert_eq!(region.pixels(100, 100), (90, 0, 10, 1));
+        let region = RenderRegion {
+            x: 1.0,
+            y: 1.0,
+            width: 0.5,
+            height: 0.5,
+        };
+        assert_eq!(region.pixels(100, 100), (99, 99, 1, 1));
+    }
+}
@@ -29,6 +29,9 @@ pub enum ExecutionError {
     #[error("Output '{1}' not found on node {0}")]
     OutputNotFound(EngineNodeId, String),
 
+    #[error("Node {0} didn't output a frame")]
+    NoFrameOutput(EngineNodeId),
+
     #[error("No output node in graph")]
     NoOutputNode,
 
@@ -53,6 +56,72 @@ pub enum ExecutionError {
     #[error("Signal envelope error: {0}")]
     SignalEnvelopeError(String),
 
+    #[error("Audio meter error: {0}")]
+    AudioMeterError(String),
+
+    #[error("Math error: {0}")]
+    MathError(String),
+
+    #[error("Text template error: {0}")]
+    TextTemplateError(String),
+
+    #[error("Tempo error: {0}")]
+    TempoError(String),
+
+    #[error("Sprite sheet error: {0}")]
+    SpriteSheetError(String),
+
+    #[error("Time remap error: {0}")]
+    TimeRemapError(String),
+
+    #[error("Frame interpolate node needs both a 'From' and a 'To' frame")]
+    MissingInterpolationFrames,
+
+    #[error("Stabilize error: {0}")]
+    StabilizeError(String),
+
+    #[error("Feedback error: {0}")]
+    FeedbackError(String),
+
+    #[error("Face detect error: {0}")]
+    FaceDetectError(String),
+
+    #[error("Depth estimate error: {0}")]
+    DepthEstimateError(String),
+
+    #[error("Levels and curves error: {0}")]
+    LevelsCurvesError(String),
+
+    #[error("White balance error: {0}")]
+    WhiteBalanceError(String),
+
+    #[error("Equalize error: {0}")]
+    EqualizeError(String),
+
+    #[error("Threshold error: {0}")]
+    ThresholdError(String),
+
+    #[error("Blob track error: {0}")]
+    BlobTrackError(String),
+
+    #[error("Trails error: {0}")]
+    TrailsError(String),
+
+    #[error("Slit scan error: {0}")]
+    SlitScanError(String),
+
+    #[error("Match color error: {0}")]
+    MatchColorError(String),
+
+    #[error("Switcher error: {0}")]
+    SwitcherError(String),
+
+    #[error("Layout error: {0}")]
+    LayoutError(String),
+
+    #[error("Watermark error: {0}")]
+    WatermarkError(String),
+
     #[error("Render error: {0:?}")]
     RenderError(crate::engine_errors::EngineError),
 
@@ -97,4 +166,10 @@ pub enum ExecutionError {
 
     #[error("Texture upload error: {0}")]
     TextureUploadError(String),
+
+    #[error("WASM node error: {0}")]
+    WasmError(String),
+
+    #[error("CPU backend error: {0}")]
+    CpuBackendError(String),
 }
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use super::NodeValue;
+use crate::node::{NodeDefinition, NodeInputKind};
+use crate::node_graph::{EngineNodeId, InputMapping, NodeGraph};
+
+/// How close (as a fraction of the output range) a control has to come to the
+/// held value for [InputMapping::soft_takeover] to let go of it.
+const TAKEOVER_DISTANCE: f32 = 0.02;
+
+/// Applies the [InputMapping]s of connected Float inputs, keeping the state
+/// soft takeover needs, keyed by node and input name.
+#[derive(Debug, Default)]
+pub(crate) struct InputMapper {
+    takeovers: HashMap<(EngineNodeId, String), Takeover>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Takeover {
+    /// The value the input has.
+    value: f32,
+    /// The last mapped value from the control, while it's not engaged yet.
+    last_mapped: Option<f32>,
+    /// Whether the control has caught up with `value` and now drives it.
+    engaged: bool,
+}
+
+impl InputMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget inputs that no longer use soft takeover.
+    pub fn retain_mapped(&mut self, graph: &NodeGraph) {
+        self.takeovers.retain(|(node_id, input_name), _| {
+            graph
+                .input_mapping(*node_id, input_name)
+                .is_some_and(|mapping| mapping.soft_takeover)
+        });
+    }
+
+    /// Make every soft takeover input hold its value until its control is
+    /// moved to it again (e.g. after the inputs were changed some other way).
+    pub fn release_takeovers(&mut self) {
+        for takeover in self.takeovers.values_mut() {
+            takeover.engaged = false;
+            takeover.last_mapped = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.takeovers.clear();
+    }
+
+    /// Replace each mapped input of `node_id` in `inputs` with its mapped
+    /// value.
+    pub fn apply(
+        &mut self,
+        graph: &NodeGraph,
+        node_id: EngineNodeId,
+        definition: &NodeDefinition,
+        inputs: &mut HashMap<String, NodeValue>,
+    ) {
+        for connection in graph.incoming_connections(node_id) {
+            let Some(mapping) = &connection.mapping else {
+                continue;
+            };
+            let Some(value) = inputs.get_mut(&connection.to_input) else {
+                continue;
+            };
+            let raw = match *value {
+                NodeValue::Float(raw) => raw,
+                NodeValue::Int(raw) => raw as f32,
+                _ => continue,
+            };
+
+            let mapped = mapping.map(raw);
+            let mapped = if mapping.soft_takeover {
+                let takeover = self
+                    .takeovers
+                    .entry((node_id, connection.to_input.clone()))
+                    .or_insert_with(|| Takeover {
+                        value: float_default(definition, &connection.to_input).unwrap_or(mapped),
+                        last_mapped: None,
+                        engaged: false,
+                    });
+                takeover.update(mapped, mapping)
+            } else {
+                mapped
+            };
+            *value = NodeValue::Float(mapped);
+        }
+    }
+}
+
+impl Takeover {
+    /// Take the control's latest `mapped` value, returning the input's value.
+    fn update(&mut self, mapped: f32, mapping: &InputMapping) -> f32 {
+        if !self.engaged {
+            let (out_min, out_max) = mapping.output_range;
+            let close =
+                (mapped - self.value).abs() <= TAKEOVER_DISTANCE * (out_max - out_min).abs();
+            let crossed = self
+                .last_mapped
+                .is_some_and(|last| (last <= self.value) != (mapped <= self.value));
+            self.engaged = close || crossed;
+            self.last_mapped = Some(mapped);
+        }
+
+        if self.engaged {
+            self.value = mapped;
+        }
+        self.value
+    }
+}
+
+/// The default value of the Float input `input_name` in `definition`.
+fn float_default(definition: &NodeDefinition, input_name: &str) -> Option<f32> {
+    let input = definition
+        .node
+        .inputs
+        .iter()
+        .find(|input| input.name == input_name)?;
+    match input.kind {
+        NodeInputKind::Float { default, .. } => Some(default),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- Takeover::update() ---
+
+    #[test]
+    fn test_soft_takeover() {
+        let mapping = InputMapping {
+            soft_takeover: true,
+            ..Default::default()
+        };
+        let mut takeover = Takeover {
+            value: 0.5,
+            last_mapped: None,
+            engaged: false,
+        };
+
+        // The knob starts below the value and is held until it passes it.
+        assert_eq!(takeover.update(0.1, &mapping), 0.5);
+        assert_eq!(takeover.update(0.3, &mapping), 0.5);
+        assert_eq!(takeover.update(0.6, &mapping), 0.6);
+        assert_eq!(takeover.update(0.2, &mapping), 0.2);
+
+        // Coming close enough also takes over.
+        let mut takeover = Takeover {
+            value: 0.5,
+            last_mapped: None,
+            engaged: false,
+        };
+        assert_eq!(takeover.update(0.51, &mapping), 0.51);
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use super::NodeValue;
+use crate::node_graph::{EngineNodeId, InputSmoothing, NodeGraph, SmoothingMode};
+
+/// The smoothed values of connected Float inputs with [InputSmoothing] set,
+/// keyed by node and input name.
+#[derive(Debug, Default)]
+pub(crate) struct ParamSmoother {
+    values: HashMap<(EngineNodeId, String), f32>,
+}
+
+impl ParamSmoother {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget inputs that are no longer smoothed, so smoothing that's turned
+    /// back on starts from the connected value.
+    pub fn retain_smoothed(&mut self, graph: &NodeGraph) {
+        self.values.retain(|(node_id, input_name), _| {
+            graph.input_smoothing(*node_id, input_name).is_some()
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    /// Replace each smoothed input of `node_id` in `inputs` with its value one
+    /// frame (`frame_secs` long) closer to the connected value.
+    pub fn apply(
+        &mut self,
+        graph: &NodeGraph,
+        node_id: EngineNodeId,
+        inputs: &mut HashMap<String, NodeValue>,
+        frame_secs: f32,
+    ) {
+        for connection in graph.incoming_connections(node_id) {
+            let Some(smoothing) = connection.smoothing else {
+                continue;
+            };
+            let Some(value) = inputs.get_mut(&connection.to_input) else {
+                continue;
+            };
+            let target = match *value {
+                NodeValue::Float(target) => target,
+                NodeValue::Int(target) => target as f32,
+                _ => continue,
+            };
+
+            let smoothed = self
+                .values
+                .entry((node_id, connection.to_input.clone()))
+                .and_modify(|current| *current = step(*current, target, smoothing, frame_secs))
+                .or_insert(target);
+            *value = NodeValue::Float(*smoothed);
+        }
+    }
+}
+
+/// Move `current` toward `target` by one frame of `smoothing`.
+fn step(current: f32, target: f32, smoothing: InputSmoothing, frame_secs: f32) -> f32 {
+    if !current.is_finite() || !target.is_finite() {
+        return target;
+    }
+
+    let secs = if target > current {
+        smoothing.attack_secs
+    } else {
+        smoothing.release_secs
+    };
+    if secs <= 0.0 || frame_secs <= 0.0 {
+        return target;
+    }
+
+    match smoothing.mode {
+        SmoothingMode::Exponential => {
+            let amount = 1.0 - (-frame_secs / secs).exp();
+            current + (target - current) * amount
+        }
+        SmoothingMode::SlewLimit => {
+            let max_change = frame_secs / secs;
+            current + (target - current).clamp(-max_change, max_change)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smoothing(mode: SmoothingMode, attack_secs: f32, release_secs: f32) -> InputSmoothing {
+        InputSmoothing {
+            mode,
+            attack_secs,
+            release_secs,
+        }
+    }
+
+    // --- step() ---
+
+    #[test]
+    fn test_step_exponential() {
+        let smoothing = smoothing(SmoothingMode::Exponential, 1.0, 0.0);
+
+        // After the attack time, about 63% of the way there.
+        let mut value = 0.0;
+        for _ in 0..30 {
+            value = step(value, 1.0, smoothing, 1.0 / 30.0);
+        }
+        assert!((value - (1.0 - (-1.0f32).exp())).abs() < 1e-4);
+
+        // No release time jumps straight down.
+        assert_eq!(step(value, 0.0, smoothing, 1.0 / 30.0), 0.0);
+    }
+
+    #[test]
+    fn test_step_slew_limit() {
+        let smoothing = smoothing(SmoothingMode::SlewLimit, 0.5, 2.0);
+
+        assert_eq!(step(0.0, 1.0, smoothing, 0.1), 0.2);
+        assert!((step(1.0, 0.0, smoothing, 0.1) - 0.95).abs() < 1e-6);
+        assert_eq!(step(0.9, 1.0, smoothing, 0.1), 1.0);
+    }
+}
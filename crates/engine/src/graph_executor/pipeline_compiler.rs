@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+use std::thread;
+
+use util::channels::mailbox::{self, Mailbox};
+
+use crate::node::NodeDefinition;
+use crate::node_pipelines::{ComputePipeline, RenderPipeline};
+
+/// A pipeline for one stage of a shader or algorithm node.
+pub(crate) enum PipelineRequest {
+    Render { target_format: wgpu::TextureFormat },
+    Compute { storage_format: wgpu::TextureFormat },
+}
+
+/// A pipeline built by [PipelineCompiler].
+pub(crate) enum CompiledPipeline {
+    Render(RenderPipeline),
+    Compute(ComputePipeline),
+}
+
+type CompileResult = (String, Result<CompiledPipeline, String>);
+
+struct CompileJob {
+    cache_key: String,
+    device: wgpu::Device,
+    shader_code: String,
+    definition: NodeDefinition,
+    request: PipelineRequest,
+    cache: Option<wgpu::PipelineCache>,
+}
+
+/// Builds node pipelines on a background thread, so a node with a heavy shader
+/// doesn't stall the engine (and everything waiting on its frames) while the
+/// driver compiles it.
+pub(crate) struct PipelineCompiler {
+    /// Sends jobs and receives their results, by cache key.
+    mailbox: Mailbox<CompileJob, CompileResult>,
+    /// The cache keys of pipelines that have been asked for but aren't built
+    /// yet.
+    pending: HashSet<String>,
+    /// Why pipelines that couldn't be built failed, by cache key.
+    failed: HashMap<String, String>,
+}
+
+impl PipelineCompiler {
+    pub fn new() -> Self {
+        let (mailbox, worker_mailbox) = mailbox::new::<CompileJob, CompileResult>();
+
+        thread::Builder::new()
+            .name("pipeline-compiler".into())
+            .spawn(move || {
+                while let Ok(job) = worker_mailbox.wait() {
+                    let result = compile(&job);
+                    if worker_mailbox.send((job.cache_key, result)).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn pipeline-compiler thread");
+
+        Self {
+            mailbox,
+            pending: HashSet::new(),
+            failed: HashMap::new(),
+        }
+    }
+
+    /// Start building the pipeline for `cache_key`, unless it's already being
+    /// built.
+    pub fn request(
+        &mut self,
+        cache_key: &str,
+        device: &wgpu::Device,
+        shader_code: &str,
+        definition: &NodeDefinition,
+        request: PipelineRequest,
+        cache: Option<&wgpu::PipelineCache>,
+    ) -> Result<(), String> {
+        if self.pending.contains(cache_key) {
+            return Ok(());
+        }
+        self.mailbox
+            .send(CompileJob {
+                cache_key: cache_key.to_string(),
+                device: device.clone(),
+                shader_code: shader_code.to_string(),
+                definition: definition.clone(),
+                request,
+                cache: cache.cloned(),
+            })
+            .map_err(|_| "the pipeline compiler thread stopped".to_string())?;
+        self.pending.insert(cache_key.to_string());
+        Ok(())
+    }
+
+    /// The pipelines that were built since the last call, by cache key.
+    /// Failures are kept for [PipelineCompiler::take_failure].
+    pub fn finished(&mut self) -> Vec<(String, CompiledPipeline)> {
+        let mut finished = Vec::new();
+        while let Ok(Some((cache_key, result))) = self.mailbox.check_non_blocking() {
+            self.pending.remove(&cache_key);
+            match result {
+                Ok(pipeline) => finished.push((cache_key, pipeline)),
+                Err(error) => {
+                    self.failed.insert(cache_key, error);
+                }
+            }
+        }
+        finished
+    }
+
+    /// Why the pipeline for `cache_key` couldn't be built, if it couldn't.
+    /// Asking for it again afterwards tries building it again.
+    pub fn take_failure(&mut self, cache_key: &str) -> Option<String> {
+        self.failed.remove(cache_key)
+    }
+}
+
+fn compile(job: &CompileJob) -> Result<CompiledPipeline, String> {
+    match job.request {
+        PipelineRequest::Render { target_format } => RenderPipeline::from_shader(
+            &job.device,
+            &job.shader_code,
+            &job.definition,
+            target_format,
+            job.cache.as_ref(),
+        )
+        .map(CompiledPipeline::Render),
+        PipelineRequest::Compute { storage_format } => ComputePipeline::from_shader(
+            &job.device,
+            &job.shader_code,
+            &job.definition,
+            storage_format,
+            job.cache.as_ref(),
+        )
+        .map(CompiledPipeline::Compute)
+        .map_err(|e| e.to_string()),
+    }
+}
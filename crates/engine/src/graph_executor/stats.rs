@@ -0,0 +1,131 @@
+//! A summary of a [NodeGraph]'s shape. See [GraphExecutor::stats].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::graph_executor::{CostReport, ExecutionError, GraphExecutor};
+use crate::node::NodeLibrary;
+use crate::node_graph::{EngineNodeId, InputValue, NodeGraph};
+
+/// The result of [GraphExecutor::stats].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphStats {
+    /// How many nodes there are in each category, including ones that don't
+    /// contribute to the output.
+    pub nodes_by_category: BTreeMap<String, usize>,
+    /// The estimated cost of the nodes that contribute to the output.
+    pub cost: CostReport,
+    /// The most nodes on any one path into the output, counting the output.
+    pub longest_chain: usize,
+    /// Nodes that don't contribute to the output, in execution order. They
+    /// aren't run.
+    pub dead_nodes: Vec<EngineNodeId>,
+}
+
+impl GraphStats {
+    /// How many nodes there are.
+    pub fn node_count(&self) -> usize {
+        self.nodes_by_category.values().sum()
+    }
+}
+
+impl GraphExecutor {
+    /// Summarize `graph` as it would run with `output` as its output, without
+    /// touching the GPU. Nodes only count towards the output if it depends on
+    /// them, or they feed back into a node it depends on (like
+    /// [GraphExecutor::execute] runs them). With no output, every node is dead.
+    pub fn stats(
+        graph: &NodeGraph,
+        library: &NodeLibrary,
+        output: Option<EngineNodeId>,
+    ) -> Result<GraphStats, ExecutionError> {
+        let order = graph
+            .execution_order()
+            .map_err(ExecutionError::GraphError)?;
+
+        let mut nodes_by_category = BTreeMap::new();
+        for instance in graph.instances().values() {
+            let category = library
+                .get_definition(&instance.definition_name)
+                .map_or("Unknown", |definition| definition.node.category.as_str());
+            *nodes_by_category.entry(category.to_string()).or_insert(0) += 1;
+        }
+
+        let mut required = HashSet::new();
+        if let Some(output) = output {
+            required = Self::collect_required_nodes_for_target(graph, output);
+            Self::collect_feedback_writers(graph, library, &mut required);
+        }
+
+        let dead_nodes = order
+            .iter()
+            .filter(|node_id| !required.contains(node_id))
+            .copied()
+            .collect();
+        let longest_chain = output.map_or(0, |output| longest_chain(graph, &order, output));
+        let cost = Self::estimate_nodes(graph, library, order, &required)?;
+
+        Ok(GraphStats {
+            nodes_by_category,
+            cost,
+            longest_chain,
+            dead_nodes,
+        })
+    }
+}
+
+/// The most nodes on any one path into `output`. `order` is `graph`'s
+/// execution order.
+fn longest_chain(graph: &NodeGraph, order: &[EngineNodeId], output: EngineNodeId) -> usize {
+    // Execution order is topological, so every upstream node's chain is known
+    // by the time it's needed.
+    let mut chains: HashMap<EngineNodeId, usize> = HashMap::new();
+    for &node_id in order {
+        let Some(instance) = graph.get_instance(node_id) else {
+            continue;
+        };
+        let upstream = instance
+            .input_values
+            .values()
+            .filter_map(|input| match input {
+                InputValue::Connection { from_node, .. } => chains.get(from_node).copied(),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        chains.insert(node_id, upstream + 1);
+    }
+    chains.get(&output).copied().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- longest_chain() ---
+
+    #[test]
+    fn longest_chain_follows_the_deepest_input() {
+        let mut graph = NodeGraph::new();
+        let source = graph.add_instance("Source".to_string());
+        let blur = graph.add_instance("Blur".to_string());
+        let mix = graph.add_instance("Mix".to_string());
+        let noise = graph.add_instance("Noise".to_string());
+        graph
+            .connect(source, "Output".to_string(), blur, "Input".to_string())
+            .unwrap();
+        graph
+            .connect(blur, "Output".to_string(), mix, "A".to_string())
+            .unwrap();
+        graph
+            .connect(source, "Output".to_string(), mix, "B".to_string())
+            .unwrap();
+        graph
+            .connect(noise, "Output".to_string(), source, "Input".to_string())
+            .unwrap();
+
+        let order = graph.execution_order().unwrap();
+        assert_eq!(longest_chain(&graph, &order, mix), 4);
+        assert_eq!(longest_chain(&graph, &order, blur), 3);
+        assert_eq!(longest_chain(&graph, &order, noise), 1);
+    }
+}
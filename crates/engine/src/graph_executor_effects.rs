@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::sync::mpsc;
 
 use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::pipeline_compiler::{CompiledPipeline, PipelineRequest};
 use crate::graph_executor::{ExecutionError, GraphExecutor, NodeValue};
 use crate::node::NodeDefinition;
 use crate::node::engine_node::{
@@ -10,14 +11,26 @@ use crate::node::engine_node::{
 };
 use crate::node_graph::EngineNodeId;
 use crate::node_pipelines::{ComputePipeline, RenderPipeline};
+use crate::pipeline_cache::DiskPipelineCache;
 use std::path::PathBuf;
 
+/// What nodes without frame inputs show while their pipelines are compiled.
+const PLACEHOLDER_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.12,
+    g: 0.14,
+    b: 0.15,
+    a: 1.0,
+};
+
 #[derive(Clone, Copy)]
 pub(crate) struct EffectStage<'a> {
     pub backend: AlgorithmStageBackend,
     pub source: &'a std::path::Path,
     pub extra_frame_inputs: usize,
     pub dispatch: Option<&'a AlgorithmStageDispatch>,
+    /// How many times smaller than the output an intermediate render stage's
+    /// target is. Final and compute stages always use the output size.
+    pub downscale: u32,
 }
 
 impl GraphExecutor {
@@ -47,6 +60,7 @@ impl GraphExecutor {
                 source: stage.source.as_path(),
                 extra_frame_inputs: stage.extra_frame_inputs,
                 dispatch: stage.dispatch.as_ref(),
+                downscale: 1,
             })
             .collect();
 
@@ -63,9 +77,15 @@ impl GraphExecutor {
         inputs: &HashMap<String, NodeValue>,
         stages: &[EffectStage<'_>],
     ) -> Result<HashMap<String, NodeValue>, ExecutionError> {
+        if !self.stage_pipelines_ready(device, definition, stages)? {
+            self.compiling_nodes.insert(node_id);
+            return Ok(self.placeholder_outputs(node_id, device, queue, definition, inputs));
+        }
+
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("effect_stages"),
         });
+        let render_region = self.render_region;
 
         let frame_inputs = self.collect_frame_inputs(definition, inputs);
         let has_scalar_output = definition
@@ -179,9 +199,8 @@ impl GraphExecutor {
                         stage.source,
                         &format!("{stage_name} shader"),
                     )?;
-                    let cache_key = format!("{}::{}", stage_name, stage.source.display());
-
                     let is_final_stage = stage_index + 1 == stages.len();
+                    let cache_key = stage_cache_key(&stage_name, stage, is_final_stage);
                     let stage_output_view = if is_final_stage {
                         actual_output_view.clone()
                     } else {
@@ -189,7 +208,7 @@ impl GraphExecutor {
                             device,
                             node_id,
                             stage_index,
-                            output_size,
+                            downscaled_size(output_size, stage.downscale),
                         )
                     };
 
@@ -209,6 +228,7 @@ impl GraphExecutor {
                             &stage_additional_inputs,
                             &stage_output_view,
                             inputs,
+                            render_region,
                         )
                         .map_err(ExecutionError::RenderError)?;
 
@@ -223,19 +243,8 @@ impl GraphExecutor {
                         &format!("{stage_name} shader"),
                     )?;
                     let is_final_stage = stage_index + 1 == stages.len();
-
-                    let storage_format = if is_final_stage {
-                        wgpu::TextureFormat::Rgba8Unorm
-                    } else {
-                        wgpu::TextureFormat::Rgba16Float
-                    };
-
-                    let cache_key = format!(
-                        "{}::{}::{:?}",
-                        stage_name,
-                        stage.source.display(),
-                        storage_format
-                    );
+                    let storage_format = compute_storage_format(is_final_stage);
+                    let cache_key = stage_cache_key(&stage_name, stage, is_final_stage);
 
                     // Get or create compute pipeline
                     if !self.compute_pipeline_cache.contains_key(&cache_key) {
@@ -244,6 +253,9 @@ impl GraphExecutor {
                             &shader_code,
                             &stage_definition,
                             storage_format,
+                            self.disk_pipeline_cache
+                                .as_ref()
+                                .map(DiskPipelineCache::cache),
                         )
                         .map_err(|e| ExecutionError::PipelineCreationError(e.to_string()))?;
                         self.compute_pipeline_cache
@@ -341,25 +353,32 @@ impl GraphExecutor {
                                         name: "input".to_string(),
                                         kind: crate::node::engine_node::NodeInputKind::Frame,
                                         show_pin: true,
+                                        help: String::new(),
+                                        optional: false,
                                     }],
                                     outputs: vec![crate::node::engine_node::NodeOutput {
                                         name: "output".to_string(),
                                         kind: crate::node::engine_node::NodeOutputKind::Frame,
                                         show_pin: true,
+                                        help: String::new(),
+                                        publish: false,
                                     }],
                                     executor: crate::node::engine_node::NodeExecutionPlan::Shader {
                                         source: PathBuf::from("internal_blit.wgsl"),
                                         passes: vec![],
+                                        includes: vec![],
                                     },
                                     short_description: String::new(),
                                     long_description: String::new(),
                                     category: String::new(),
                                     subcategories: vec![],
                                     search_keywords: vec![],
+                                    example_images: vec![],
                                 },
                                 shader_path: None,
                                 folder_path: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
                                     .join("shaders"),
+                                example_image_paths: vec![],
                             };
 
                             let blit_cache_key = format!(
@@ -393,6 +412,7 @@ impl GraphExecutor {
                                         String,
                                         crate::graph_executor::NodeValue,
                                     >::new(),
+                                    render_region,
                                 )
                                 .map_err(ExecutionError::RenderError)?;
 
@@ -542,6 +562,164 @@ impl GraphExecutor {
         Ok(outputs)
     }
 
+    /// Whether every stage's pipeline has been built. When pipelines are
+    /// compiled in the background (see
+    /// [GraphExecutor::set_async_pipeline_compilation]), the ones that
+    /// haven't been are asked for and this returns false until they're done;
+    /// otherwise pipelines are built as the stages run, so this is always
+    /// true.
+    fn stage_pipelines_ready(
+        &mut self,
+        device: &wgpu::Device,
+        definition: &NodeDefinition,
+        stages: &[EffectStage<'_>],
+    ) -> Result<bool, ExecutionError> {
+        if self.pipeline_compiler.is_none() {
+            return Ok(true);
+        }
+        self.receive_compiled_pipelines();
+
+        let mut ready = true;
+        for (stage_index, stage) in stages.iter().enumerate() {
+            let stage_name = format!("{}::stage{stage_index}", definition.node.name);
+            let is_final_stage = stage_index + 1 == stages.len();
+            let cache_key = stage_cache_key(&stage_name, stage, is_final_stage);
+            let request = match stage.backend {
+                AlgorithmStageBackend::Render => {
+                    if self.pipeline_cache.contains_key(&cache_key) {
+                        continue;
+                    }
+                    PipelineRequest::Render {
+                        target_format: self.target_format,
+                    }
+                }
+                AlgorithmStageBackend::Compute => {
+                    if self.compute_pipeline_cache.contains_key(&cache_key) {
+                        continue;
+                    }
+                    PipelineRequest::Compute {
+                        storage_format: compute_storage_format(is_final_stage),
+                    }
+                }
+            };
+            ready = false;
+
+            let shader_code =
+                self.load_shader_source(definition, stage.source, &format!("{stage_name} shader"))?;
+            let stage_definition = self.build_shader_stage_definition(
+                definition,
+                &stage_name,
+                stage.extra_frame_inputs,
+            );
+            let cache = self
+                .disk_pipeline_cache
+                .as_ref()
+                .map(DiskPipelineCache::cache);
+            let compiler = self
+                .pipeline_compiler
+                .as_mut()
+                .expect("checked for a compiler above");
+            if let Some(error) = compiler.take_failure(&cache_key) {
+                return Err(ExecutionError::PipelineCreationError(error));
+            }
+            compiler
+                .request(
+                    &cache_key,
+                    device,
+                    &shader_code,
+                    &stage_definition,
+                    request,
+                    cache,
+                )
+                .map_err(ExecutionError::PipelineCreationError)?;
+        }
+        Ok(ready)
+    }
+
+    /// Move pipelines the background compiler has finished into the caches.
+    fn receive_compiled_pipelines(&mut self) {
+        let Some(compiler) = &mut self.pipeline_compiler else {
+            return;
+        };
+        for (cache_key, pipeline) in compiler.finished() {
+            match pipeline {
+                CompiledPipeline::Render(pipeline) => {
+                    self.pipeline_cache.insert(cache_key, pipeline);
+                }
+                CompiledPipeline::Compute(pipeline) => {
+                    self.compute_pipeline_cache.insert(cache_key, pipeline);
+                }
+            }
+        }
+    }
+
+    /// What a node outputs while its pipelines are being compiled: its first
+    /// frame input passed through (or [PLACEHOLDER_COLOR] if it has none), and
+    /// zero for scalar outputs.
+    fn placeholder_outputs(
+        &mut self,
+        node_id: EngineNodeId,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        definition: &NodeDefinition,
+        inputs: &HashMap<String, NodeValue>,
+    ) -> HashMap<String, NodeValue> {
+        let passthrough = self
+            .collect_frame_inputs(definition, inputs)
+            .first()
+            .copied()
+            .cloned();
+        let frame = passthrough.unwrap_or_else(|| {
+            let size = wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            };
+            let view = self.get_or_create_render_target(device, node_id, size);
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("pipeline_placeholder"),
+            });
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("pipeline_placeholder_clear"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(PLACEHOLDER_COLOR),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            queue.submit(Some(encoder.finish()));
+            GpuFrame {
+                view,
+                size,
+                frame_id: media::frame::Uid::generate_new(),
+            }
+        });
+
+        definition
+            .node
+            .outputs
+            .iter()
+            .filter_map(|output| {
+                let value = match output.kind {
+                    NodeOutputKind::Frame => NodeValue::Frame(frame.clone()),
+                    NodeOutputKind::Float => NodeValue::Float(0.0),
+                    NodeOutputKind::Int => NodeValue::Int(0),
+                    NodeOutputKind::Bool => NodeValue::Bool(false),
+                    NodeOutputKind::Pixel => NodeValue::Pixel([0.0; 4]),
+                    _ => return None,
+                };
+                Some((output.name.clone(), value))
+            })
+            .collect()
+    }
+
     fn collect_frame_inputs<'a>(
         &self,
         definition: &'a NodeDefinition,
@@ -574,6 +752,8 @@ impl GraphExecutor {
                 name: format!("Pass Input {}", index + 1),
                 kind: NodeInputKind::Frame,
                 show_pin: false,
+                help: String::new(),
+                optional: false,
             });
         }
 
@@ -586,9 +766,21 @@ impl GraphExecutor {
         source: &std::path::Path,
         context: &str,
     ) -> Result<String, ExecutionError> {
-        let shader_path = definition.folder_path.join(source);
-        std::fs::read_to_string(&shader_path)
-            .map_err(|e| ExecutionError::ShaderLoadError(shader_path, format!("{context}: {e}")))
+        let read = |path: &std::path::Path| {
+            let path = definition.folder_path.join(path);
+            std::fs::read_to_string(&path)
+                .map_err(|e| ExecutionError::ShaderLoadError(path, format!("{context}: {e}")))
+        };
+
+        let mut code = String::new();
+        if let NodeExecutionPlan::Shader { includes, .. } = &definition.node.executor {
+            for include in includes {
+                code.push_str(&read(include.as_path())?);
+                code.push('\n');
+            }
+        }
+        code.push_str(&read(source)?);
+        Ok(code)
     }
 
     fn get_or_create_cached_shader_pipeline<'a>(
@@ -615,8 +807,39 @@ impl GraphExecutor {
         shader_code: &str,
         definition: &NodeDefinition,
     ) -> Result<RenderPipeline, ExecutionError> {
-        RenderPipeline::from_shader(device, shader_code, definition, self.target_format)
-            .map_err(ExecutionError::PipelineCreationError)
+        RenderPipeline::from_shader(
+            device,
+            shader_code,
+            definition,
+            self.target_format,
+            self.disk_pipeline_cache
+                .as_ref()
+                .map(DiskPipelineCache::cache),
+        )
+        .map_err(ExecutionError::PipelineCreationError)
+    }
+}
+
+/// The pipeline cache key for the stage named `stage_name`.
+fn stage_cache_key(stage_name: &str, stage: &EffectStage<'_>, is_final_stage: bool) -> String {
+    match stage.backend {
+        AlgorithmStageBackend::Render => format!("{}::{}", stage_name, stage.source.display()),
+        AlgorithmStageBackend::Compute => format!(
+            "{}::{}::{:?}",
+            stage_name,
+            stage.source.display(),
+            compute_storage_format(is_final_stage)
+        ),
+    }
+}
+
+/// The format a compute stage writes. Intermediate stages keep extra
+/// precision for the stages after them.
+fn compute_storage_format(is_final_stage: bool) -> wgpu::TextureFormat {
+    if is_final_stage {
+        wgpu::TextureFormat::Rgba8Unorm
+    } else {
+        wgpu::TextureFormat::Rgba16Float
     }
 }
 
@@ -640,3 +863,13 @@ fn decode_rgba8_like(format: wgpu::TextureFormat, pixel: &[u8]) -> [f32; 4] {
         ],
     }
 }
+
+/// `size` divided by `downscale`, rounding up so no side reaches zero.
+fn downscaled_size(size: wgpu::Extent3d, downscale: u32) -> wgpu::Extent3d {
+    let downscale = downscale.max(1);
+    wgpu::Extent3d {
+        width: size.width.div_ceil(downscale),
+        height: size.height.div_ceil(downscale),
+        depth_or_array_layers: size.depth_or_array_layers,
+    }
+}
@@ -0,0 +1,521 @@
+//! Exports [HistogramMatcher], which recolors frames so each color channel's
+//! distribution matches a reference frame's (see the Match Color node), and
+//! [MatchState], the buffers it keeps per stream of frames.
+//!
+//! Everything runs on the GPU in one submission: a compute pass counts a
+//! histogram per channel of both frames, a second turns the pair into a lookup
+//! table per channel, and a fragment pass draws the frame through the tables.
+
+/// Histogram bins (and lookup table entries) per channel. Bin `i` counts
+/// values that round to `i / 255`.
+const BINS: u64 = 256;
+const CHANNELS: u64 = 3;
+
+const HISTOGRAM_SHADER: &str = r#"
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> histogram: array<atomic<u32>, 768>;
+
+var<workgroup> local_histogram: array<atomic<u32>, 768>;
+
+fn bin(value: f32) -> u32 {
+    return u32(round(clamp(value, 0.0, 1.0) * 255.0));
+}
+
+// Each workgroup counts its 16 by 16 block locally and adds that to the
+// frame's histograms, so there are far fewer global atomics than pixels.
+@compute @workgroup_size(16, 16)
+fn cs_main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+) {
+    for (var channel = 0u; channel < 3u; channel++) {
+        atomicStore(&local_histogram[channel * 256u + index], 0u);
+    }
+    workgroupBarrier();
+
+    let size = textureDimensions(source_texture);
+    if all(id.xy < size) {
+        let color = textureLoad(source_texture, vec2<i32>(id.xy), 0).rgb;
+        atomicAdd(&local_histogram[bin(color.r)], 1u);
+        atomicAdd(&local_histogram[256u + bin(color.g)], 1u);
+        atomicAdd(&local_histogram[512u + bin(color.b)], 1u);
+    }
+    workgroupBarrier();
+
+    for (var channel = 0u; channel < 3u; channel++) {
+        let slot = channel * 256u + index;
+        let count = atomicLoad(&local_histogram[slot]);
+        if count > 0u {
+            atomicAdd(&histogram[slot], count);
+        }
+    }
+}
+"#;
+
+const LUT_SHADER: &str = r#"
+struct Params {
+    method: u32,  // 0=histogram, 1=mean and standard deviation
+    amount: f32,
+    _pad0: u32,
+    _pad1: u32,
+}
+
+@group(0) @binding(0) var<storage, read> source_histogram: array<u32, 768>;
+@group(0) @binding(1) var<storage, read> reference_histogram: array<u32, 768>;
+@group(0) @binding(2) var<storage, read_write> lut: array<f32, 768>;
+@group(0) @binding(3) var<uniform> params: Params;
+
+// One invocation per channel.
+@compute @workgroup_size(3)
+fn cs_main(@builtin(local_invocation_index) channel: u32) {
+    let base = channel * 256u;
+
+    var source_total = 0.0;
+    var source_sum = 0.0;
+    var source_squares = 0.0;
+    var reference_total = 0.0;
+    var reference_sum = 0.0;
+    var reference_squares = 0.0;
+    for (var i = 0u; i < 256u; i++) {
+        let value = f32(i) / 255.0;
+        let source_count = f32(source_histogram[base + i]);
+        let reference_count = f32(reference_histogram[base + i]);
+        source_total += source_count;
+        source_sum += value * source_count;
+        source_squares += value * value * source_count;
+        reference_total += reference_count;
+        reference_sum += value * reference_count;
+        reference_squares += value * value * reference_count;
+    }
+
+    if source_total == 0.0 || reference_total == 0.0 {
+        for (var i = 0u; i < 256u; i++) {
+            lut[base + i] = f32(i) / 255.0;
+        }
+        return;
+    }
+
+    // Shift and scale the channel so its mean and standard deviation match.
+    if params.method == 1u {
+        let source_mean = source_sum / source_total;
+        let reference_mean = reference_sum / reference_total;
+        let source_deviation = sqrt(max(source_squares / source_total - source_mean * source_mean, 0.0));
+        let reference_deviation = sqrt(max(reference_squares / reference_total - reference_mean * reference_mean, 0.0));
+        let scale = reference_deviation / max(source_deviation, 1e-3);
+        for (var i = 0u; i < 256u; i++) {
+            let value = f32(i) / 255.0;
+            lut[base + i] = clamp((value - source_mean) * scale + reference_mean, 0.0, 1.0);
+        }
+        return;
+    }
+
+    // Map each value to the reference value at the same point of the
+    // cumulative distribution. Both only grow, so one sweep through the
+    // reference bins is enough.
+    var source_below = 0.0;
+    var reference_bin = 0u;
+    var reference_below = 0.0;
+    for (var i = 0u; i < 256u; i++) {
+        let count = f32(source_histogram[base + i]);
+        let rank = (source_below + count * 0.5) / source_total * reference_total;
+        source_below += count;
+
+        loop {
+            let reference_count = f32(reference_histogram[base + reference_bin]);
+            if reference_bin == 255u || reference_below + reference_count >= rank {
+                break;
+            }
+            reference_below += reference_count;
+            reference_bin += 1u;
+        }
+
+        // Place the value within its reference bin by how far into the bin's
+        // share of the distribution it falls.
+        let reference_count = f32(reference_histogram[base + reference_bin]);
+        var within = 0.5;
+        if reference_count > 0.0 {
+            within = clamp((rank - reference_below) / reference_count, 0.0, 1.0);
+        }
+        lut[base + i] = clamp((f32(reference_bin) - 0.5 + within) / 255.0, 0.0, 1.0);
+    }
+}
+"#;
+
+const APPLY_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    method: u32,
+    amount: f32,
+    _pad0: u32,
+    _pad1: u32,
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read> lut: array<f32, 768>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+fn lookup(channel: u32, value: f32) -> f32 {
+    let x = clamp(value, 0.0, 1.0) * 255.0;
+    let i = min(u32(x), 254u);
+    let base = channel * 256u;
+    return mix(lut[base + i], lut[base + i + 1u], x - f32(i));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureLoad(source_texture, vec2<i32>(in.position.xy), 0);
+    let matched = vec3<f32>(lookup(0u, color.r), lookup(1u, color.g), lookup(2u, color.b));
+    return vec4<f32>(mix(color.rgb, matched, params.amount), color.a);
+}
+"#;
+
+/// How [HistogramMatcher::match_colors] matches a frame to its reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchMethod {
+    /// Match each channel's whole distribution, so the frame takes on the
+    /// reference's contrast and color balance as closely as possible.
+    Histogram,
+    /// Only match each channel's mean and standard deviation, a gentler
+    /// transfer that keeps more of the frame's own character.
+    MeanDeviation,
+}
+
+/// The buffers one stream of frames is matched with.
+pub struct MatchState {
+    source_histogram: wgpu::Buffer,
+    reference_histogram: wgpu::Buffer,
+    lut: wgpu::Buffer,
+    params_buf: wgpu::Buffer,
+}
+
+impl MatchState {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = |label, size, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let histogram_usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST;
+
+        Self {
+            source_histogram: buffer(
+                "match_source_histogram",
+                CHANNELS * BINS * 4,
+                histogram_usage,
+            ),
+            reference_histogram: buffer(
+                "match_reference_histogram",
+                CHANNELS * BINS * 4,
+                histogram_usage,
+            ),
+            lut: buffer(
+                "match_lut",
+                CHANNELS * BINS * 4,
+                wgpu::BufferUsages::STORAGE,
+            ),
+            params_buf: buffer(
+                "match_params",
+                16,
+                wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            ),
+        }
+    }
+}
+
+/// Matches textures to references into render targets of one format.
+pub struct HistogramMatcher {
+    histogram_pipeline: wgpu::ComputePipeline,
+    histogram_bgl: wgpu::BindGroupLayout,
+    lut_pipeline: wgpu::ComputePipeline,
+    lut_bgl: wgpu::BindGroupLayout,
+    apply_pipeline: wgpu::RenderPipeline,
+    apply_bgl: wgpu::BindGroupLayout,
+}
+
+impl HistogramMatcher {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let compute = wgpu::ShaderStages::COMPUTE;
+        let fragment = wgpu::ShaderStages::FRAGMENT;
+
+        let histogram_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/match_histogram"),
+            entries: &[texture_entry(0, compute), storage_entry(1, compute, false)],
+        });
+        let histogram_pipeline =
+            compute_pipeline(device, "match_histogram", HISTOGRAM_SHADER, &histogram_bgl);
+
+        let lut_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/match_lut"),
+            entries: &[
+                storage_entry(0, compute, true),
+                storage_entry(1, compute, true),
+                storage_entry(2, compute, false),
+                uniform_entry(3, compute),
+            ],
+        });
+        let lut_pipeline = compute_pipeline(device, "match_lut", LUT_SHADER, &lut_bgl);
+
+        let apply_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/match_apply"),
+            entries: &[
+                texture_entry(0, fragment),
+                storage_entry(1, fragment, true),
+                uniform_entry(2, fragment),
+            ],
+        });
+        let apply_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/match_apply"),
+            bind_group_layouts: &[&apply_bgl],
+            ..Default::default()
+        });
+        let apply_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/match_apply"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(APPLY_SHADER)),
+        });
+        let apply_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/match_apply"),
+            layout: Some(&apply_layout),
+            vertex: wgpu::VertexState {
+                module: &apply_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &apply_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        Self {
+            histogram_pipeline,
+            histogram_bgl,
+            lut_pipeline,
+            lut_bgl,
+            apply_pipeline,
+            apply_bgl,
+        }
+    }
+
+    /// Record passes into `encoder` that draw `source` (`source_size` big)
+    /// into `target`, which must be the same size, with its colors matched to
+    /// `reference` (`reference_size` big, any size) by `method`. `amount` mixes
+    /// between the original (`0.0`) and matched (`1.0`) colors.
+    ///
+    /// The parameters are written with `queue` into `state`, so `encoder` must
+    /// be submitted before this is called again with the same state.
+    #[allow(clippy::too_many_arguments)]
+    pub fn match_colors(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        source: (&wgpu::TextureView, wgpu::Extent3d),
+        reference: (&wgpu::TextureView, wgpu::Extent3d),
+        state: &MatchState,
+        method: MatchMethod,
+        amount: f32,
+        target: &wgpu::TextureView,
+    ) {
+        let method_id: u32 = match method {
+            MatchMethod::Histogram => 0,
+            MatchMethod::MeanDeviation => 1,
+        };
+        let mut params = [0u8; 16];
+        params[0..4].copy_from_slice(&method_id.to_le_bytes());
+        params[4..8].copy_from_slice(&amount.clamp(0.0, 1.0).to_le_bytes());
+        queue.write_buffer(&state.params_buf, 0, &params);
+
+        encoder.clear_buffer(&state.source_histogram, 0, None);
+        encoder.clear_buffer(&state.reference_histogram, 0, None);
+
+        let histogram_group = |texture: &wgpu::TextureView, histogram: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("bg/match_histogram"),
+                layout: &self.histogram_bgl,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(texture),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: histogram.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let source_group = histogram_group(source.0, &state.source_histogram);
+        let reference_group = histogram_group(reference.0, &state.reference_histogram);
+        let lut_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/match_lut"),
+            layout: &self.lut_bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: state.source_histogram.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: state.reference_histogram.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: state.lut.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: state.params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("match_lut"),
+                timestamp_writes: None,
+            });
+            cpass.set_pipeline(&self.histogram_pipeline);
+            for (group, size) in [(&source_group, source.1), (&reference_group, reference.1)] {
+                cpass.set_bind_group(0, group, &[]);
+                cpass.dispatch_workgroups(size.width.div_ceil(16), size.height.div_ceil(16), 1);
+            }
+            cpass.set_pipeline(&self.lut_pipeline);
+            cpass.set_bind_group(0, &lut_group, &[]);
+            cpass.dispatch_workgroups(1, 1, 1);
+        }
+
+        let apply_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/match_apply"),
+            layout: &self.apply_bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source.0),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: state.lut.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: state.params_buf.as_entire_binding(),
+                },
+            ],
+        });
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("match_apply"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.apply_pipeline);
+        rpass.set_bind_group(0, &apply_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+fn compute_pipeline(
+    device: &wgpu::Device,
+    name: &str,
+    source: &str,
+    bgl: &wgpu::BindGroupLayout,
+) -> wgpu::ComputePipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(&format!("layout/{name}")),
+        bind_group_layouts: &[bgl],
+        ..Default::default()
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(&format!("shader/{name}")),
+        source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(&format!("pipeline/{name}")),
+        layout: Some(&layout),
+        module: &shader,
+        entry_point: Some("cs_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    })
+}
+
+fn texture_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn storage_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    read_only: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn uniform_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
@@ -0,0 +1,66 @@
+//! Exports [InferenceWorker], which runs slow per-frame analysis (e.g. a
+//! neural network) on its own thread so the graph keeps running at full speed
+//! while it works, using whichever result finished last.
+
+use std::thread;
+
+use util::channels::mailbox::{self, Mailbox};
+
+/// Runs a function on inputs on a background thread. When inputs arrive faster
+/// than they're processed, only the newest waiting input is used.
+pub struct InferenceWorker<I, O> {
+    /// Sends inputs and receives outputs, with how many inputs each output
+    /// answers (including the ones skipped for it).
+    mailbox: Mailbox<I, (usize, O)>,
+    in_flight: usize,
+}
+
+impl<I: Send + 'static, O: Send + 'static> InferenceWorker<I, O> {
+    /// Spawn a thread that calls `infer` on each input. The thread exits when
+    /// the worker is dropped.
+    pub fn spawn(mut infer: impl FnMut(I) -> O + Send + 'static) -> Self {
+        let (mailbox, worker_mailbox) = mailbox::new::<I, (usize, O)>();
+
+        thread::spawn(move || {
+            while let Ok(mut inputs) = worker_mailbox.wait_all() {
+                let answered = inputs.len();
+                let Some(input) = inputs.pop_back() else {
+                    continue;
+                };
+                if worker_mailbox.send((answered, infer(input))).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            mailbox,
+            in_flight: 0,
+        }
+    }
+
+    /// Whether every input has been answered.
+    pub fn is_idle(&self) -> bool {
+        self.in_flight == 0
+    }
+
+    /// Queue an input. Returns `false` if the worker thread has stopped.
+    pub fn submit(&mut self, input: I) -> bool {
+        let sent = self.mailbox.send(input).is_ok();
+        if sent {
+            self.in_flight += 1;
+        }
+        sent
+    }
+
+    /// The newest output finished since this was last called, if any.
+    pub fn latest(&mut self) -> Option<O> {
+        let outputs = self.mailbox.check_non_blocking_all().ok().flatten()?;
+        let mut latest = None;
+        for (answered, output) in outputs {
+            self.in_flight = self.in_flight.saturating_sub(answered);
+            latest = Some(output);
+        }
+        latest
+    }
+}
@@ -0,0 +1,451 @@
+//! Exports [LayoutCompositor], which draws several frames side by side into
+//! one (see the Layout node), and [layout_cells], which works out where each
+//! frame goes.
+
+/// The most frames one [LayoutCompositor::draw] can arrange.
+pub const MAX_LAYOUT_FRAMES: usize = 9;
+
+/// Draws one frame into the viewport it's given, framed by a border. The
+/// fullscreen triangle matches the one used by node shaders.
+const LAYOUT_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    // The part of the frame shown, for cropping when filling a cell.
+    uv_offset: vec2<f32>,
+    uv_scale: vec2<f32>,
+    // The viewport's size in pixels.
+    size: vec2<f32>,
+    border_width: f32,
+    _pad: f32,
+    border_color: vec4<f32>,
+}
+
+@group(0) @binding(0) var frame_sampler: sampler;
+@group(0) @binding(1) var frame_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = in.uv * params.size;
+    let edge_distance = min(min(pixel.x, pixel.y), min(params.size.x - pixel.x, params.size.y - pixel.y));
+    if edge_distance < params.border_width {
+        return params.border_color;
+    }
+    return textureSample(frame_texture, frame_sampler, params.uv_offset + in.uv * params.uv_scale);
+}
+"#;
+
+/// A rectangle as fractions of the output's width and height, from its top
+/// left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// A corner of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// How [layout_cells] arranges frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// Rows of equal cells, filled left to right. With `columns` of `0` the
+    /// grid is as square as possible. A last row that isn't full is centered.
+    Grid { columns: u32 },
+    /// The first frame over the whole output, with the rest in insets `size`
+    /// (a fraction of the output) big stacked from `corner`.
+    PictureInPicture { size: f32, corner: Corner },
+}
+
+/// How a frame is fitted into a cell of a different shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    /// Stretch the frame to the cell.
+    Stretch,
+    /// Shrink the frame to fit inside the cell, leaving the background around
+    /// it.
+    Contain,
+    /// Crop the frame to fill the cell.
+    Cover,
+}
+
+/// The cells `count` frames go in with `layout`, in order. `gap` is the space
+/// left between cells and around the edges, as a fraction of the output.
+pub fn layout_cells(layout: Layout, count: usize, gap: f32) -> Vec<Cell> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let gap = gap.clamp(0.0, 0.25);
+
+    match layout {
+        Layout::Grid { columns } => {
+            let columns = if columns == 0 {
+                (count as f32).sqrt().ceil() as usize
+            } else {
+                (columns as usize).min(count)
+            };
+            let rows = count.div_ceil(columns);
+            let width = (1.0 - gap * (columns + 1) as f32) / columns as f32;
+            let height = (1.0 - gap * (rows + 1) as f32) / rows as f32;
+
+            (0..count)
+                .map(|index| {
+                    let (row, column) = (index / columns, index % columns);
+                    let in_row = (count - row * columns).min(columns);
+                    let indent = (columns - in_row) as f32 * (width + gap) / 2.0;
+                    Cell {
+                        x: indent + gap + column as f32 * (width + gap),
+                        y: gap + row as f32 * (height + gap),
+                        width,
+                        height,
+                    }
+                })
+                .collect()
+        }
+        Layout::PictureInPicture { size, corner } => {
+            let size = size.clamp(0.05, 1.0);
+            let per_column = (((1.0 - gap) / (size + gap)) as usize).max(1);
+            let mut cells = vec![Cell {
+                x: 0.0,
+                y: 0.0,
+                width: 1.0,
+                height: 1.0,
+            }];
+            cells.extend((0..count - 1).map(|index| {
+                // Stack down (or up) from the corner, then start another
+                // column further in.
+                let along = gap + (index % per_column) as f32 * (size + gap);
+                let across = gap + (index / per_column) as f32 * (size + gap);
+                let (x, y) = match corner {
+                    Corner::TopLeft => (across, along),
+                    Corner::TopRight => (1.0 - across - size, along),
+                    Corner::BottomLeft => (across, 1.0 - along - size),
+                    Corner::BottomRight => (1.0 - across - size, 1.0 - along - size),
+                };
+                Cell {
+                    x,
+                    y,
+                    width: size,
+                    height: size,
+                }
+            }));
+            cells
+        }
+    }
+}
+
+/// The look shared by every frame in a [LayoutCompositor::draw].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutStyle {
+    pub fit: Fit,
+    /// The border drawn inside each frame's cell, in pixels.
+    pub border_width: f32,
+    pub border_color: [f32; 4],
+    pub background: [f32; 4],
+}
+
+/// Arranges frames into one.
+pub struct LayoutCompositor {
+    pipeline: wgpu::RenderPipeline,
+    bgl: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// One per frame, since every frame is drawn in the same submission.
+    params_bufs: Vec<wgpu::Buffer>,
+}
+
+impl LayoutCompositor {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/layout"),
+            bind_group_layouts: &[&bgl],
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/layout"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(LAYOUT_SHADER)),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/layout"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sampler/layout"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let params_bufs = (0..MAX_LAYOUT_FRAMES)
+            .map(|_| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("layout_params"),
+                    size: 48,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+
+        Self {
+            pipeline,
+            bgl,
+            sampler,
+            params_bufs,
+        }
+    }
+
+    /// Record a pass into `encoder` that fills `target` (`target_size` big)
+    /// with the background and draws each frame into its cell, in order. Only
+    /// the first [MAX_LAYOUT_FRAMES] frames are drawn.
+    ///
+    /// The settings are written with `queue`, so `encoder` must be submitted
+    /// before this is called again.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        frames: &[(&wgpu::TextureView, wgpu::Extent3d)],
+        cells: &[Cell],
+        style: LayoutStyle,
+        target: &wgpu::TextureView,
+        target_size: wgpu::Extent3d,
+    ) {
+        let (target_width, target_height) = (target_size.width as f32, target_size.height as f32);
+
+        let mut draws = Vec::new();
+        for (index, ((view, size), cell)) in frames.iter().zip(cells).enumerate() {
+            if index == MAX_LAYOUT_FRAMES {
+                break;
+            }
+
+            let mut rect = [
+                cell.x * target_width,
+                cell.y * target_height,
+                cell.width * target_width,
+                cell.height * target_height,
+            ];
+            let mut uv_offset = [0.0f32; 2];
+            let mut uv_scale = [1.0f32; 2];
+            let cell_aspect = rect[2] / rect[3];
+            let frame_aspect = size.width as f32 / size.height as f32;
+            match style.fit {
+                Fit::Stretch => {}
+                Fit::Contain if frame_aspect > cell_aspect => {
+                    let height = rect[2] / frame_aspect;
+                    rect[1] += (rect[3] - height) / 2.0;
+                    rect[3] = height;
+                }
+                Fit::Contain => {
+                    let width = rect[3] * frame_aspect;
+                    rect[0] += (rect[2] - width) / 2.0;
+                    rect[2] = width;
+                }
+                Fit::Cover if frame_aspect > cell_aspect => {
+                    uv_scale[0] = cell_aspect / frame_aspect;
+                    uv_offset[0] = (1.0 - uv_scale[0]) / 2.0;
+                }
+                Fit::Cover => {
+                    uv_scale[1] = frame_aspect / cell_aspect;
+                    uv_offset[1] = (1.0 - uv_scale[1]) / 2.0;
+                }
+            }
+
+            // Viewports have to be inside the target.
+            let left = rect[0].clamp(0.0, target_width);
+            let top = rect[1].clamp(0.0, target_height);
+            let width = (rect[0] + rect[2]).clamp(0.0, target_width) - left;
+            let height = (rect[1] + rect[3]).clamp(0.0, target_height) - top;
+            if !(width >= 1.0 && height >= 1.0) {
+                continue;
+            }
+
+            let mut params = [0u8; 48];
+            let values = [
+                uv_offset[0],
+                uv_offset[1],
+                uv_scale[0],
+                uv_scale[1],
+                width,
+                height,
+                style.border_width.max(0.0),
+                0.0,
+            ];
+            for (i, value) in values.iter().chain(&style.border_color).enumerate() {
+                params[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            queue.write_buffer(&self.params_bufs[index], 0, &params);
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("bg/layout"),
+                layout: &self.bgl,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.params_bufs[index].as_entire_binding(),
+                    },
+                ],
+            });
+            draws.push((bind_group, [left, top, width, height]));
+        }
+
+        let [red, green, blue, alpha] = style.background.map(f64::from);
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("layout"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: red,
+                        g: green,
+                        b: blue,
+                        a: alpha,
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        for (bind_group, [left, top, width, height]) in &draws {
+            rpass.set_viewport(*left, *top, *width, *height, 0.0, 1.0);
+            rpass.set_bind_group(0, bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_cell(cell: Cell, expected: [f32; 4]) {
+        let actual = [cell.x, cell.y, cell.width, cell.height];
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{actual:?} != {expected:?}");
+        }
+    }
+
+    // --- layout_cells() ---
+
+    #[test]
+    fn test_layout_cells() {
+        // Three frames make a 2x2 grid with the last one centered.
+        let cells = layout_cells(Layout::Grid { columns: 0 }, 3, 0.0);
+        assert_eq!(cells.len(), 3);
+        assert_cell(cells[0], [0.0, 0.0, 0.5, 0.5]);
+        assert_cell(cells[1], [0.5, 0.0, 0.5, 0.5]);
+        assert_cell(cells[2], [0.25, 0.5, 0.5, 0.5]);
+
+        // Gaps go between cells and around the edges.
+        let cells = layout_cells(Layout::Grid { columns: 2 }, 2, 0.1);
+        assert_cell(cells[0], [0.1, 0.1, 0.35, 0.8]);
+        assert_cell(cells[1], [0.55, 0.1, 0.35, 0.8]);
+
+        // Insets stack up from the bottom right corner.
+        let pip = Layout::PictureInPicture {
+            size: 0.25,
+            corner: Corner::BottomRight,
+        };
+        let cells = layout_cells(pip, 3, 0.05);
+        assert_cell(cells[0], [0.0, 0.0, 1.0, 1.0]);
+        assert_cell(cells[1], [0.7, 0.7, 0.25, 0.25]);
+        assert_cell(cells[2], [0.7, 0.4, 0.25, 0.25]);
+
+        assert!(layout_cells(pip, 0, 0.05).is_empty());
+    }
+}
@@ -9,6 +9,13 @@
 //! - [`engine_outpost`] — thread management and the public API surface. [`spawn`] starts the
 //!   engine thread and returns an [`EngineOutpostHandle`] for sending commands and subscribing
 //!   to events.
+//! - [`cpu_backend`] — CPU implementations of a core subset of nodes for machines with weak
+//!   GPUs, selected per session with [`cpu_backend::ExecutionBackend`].
+//! - [`diagnostics`] — GPU and node library sections for bug report diagnostics.
+//! - [`execution_trace`] — records a few frames of graph execution to a JSON file and replays
+//!   recordings headlessly.
+//! - [`export`] — renders a graph frame by frame, faster than real time, into files such as
+//!   PNG or EXR image sequences.
 //! - [`graph_executor`][`crate::graph_executor`] — resolves node inputs, runs shader-based nodes
 //!   and built-in handlers (image/video sources, noise, MIDI), and caches intermediate GPU
 //!   outputs and compiled render pipelines. Internal to the outpost; not called directly by
@@ -17,8 +24,14 @@
 //!   MIDI input, and signal envelope processing.
 //! - [`node_graph`][`crate::node_graph`] — the [`node_graph::NodeGraph`] data model shared
 //!   between the app and engine, containing node instances and their wired input connections.
+//! - [`tempo`] — the BPM clock Tempo nodes set and LFO nodes follow, with tap tempo and beat
+//!   detection.
 //! - `node_pipelines` — dynamic creation of GPU render and compute pipelines from WGSL shaders.
+//! - [`pipeline_cache`] — keeps compiled node pipelines on disk between runs, where the driver
+//!   supports it, so cold starts don't stall compiling shaders.
 //! - `upload_stager` — utilities for staging CPU image data into GPU textures ([`UploadStager`]).
+//! - [`wasm_nodes`] — runs nodes whose logic is a sandboxed WebAssembly module (with the `wasm`
+//!   feature), and describes the ABI those modules implement.
 //!
 //! Usage
 //! -----
@@ -62,6 +75,15 @@
 //! command_tx.send(EngineCommand::ClearManualFps)?;
 //! ```
 //!
+//! Or send acknowledged, versioned requests with the playback protocol (see
+//! [`engine_outpost::protocol`]):
+//!
+//! ```ignore
+//! let playback = handle.playback_client();
+//! let mut seek = playback.request(PlaybackRequest::Seek { frame: 120 })?;
+//! assert_eq!(seek.wait()?, Ok(PlaybackAck::Done));
+//! ```
+//!
 //! The engine shuts down automatically when all [`EngineOutpostHandle`] clones are dropped.
 //!
 //! Errors
@@ -76,28 +98,60 @@
 //!
 //! - Entry points: the shader must provide `vs_main` (vertex) and `fs_main` (fragment).
 //! - Bind group layout used by the runtime:
-//!   - binding 0: a `sampler` (linear filtering)
+//!   - binding 0: a `sampler` (trilinear filtering by default; a node can add an `Enum` input
+//!     named `Sampling` with the choices `Nearest`, `Bilinear`, `Trilinear` and `Anisotropic`
+//!     to pick another, see [`node_pipelines::SamplingQuality`])
 //!   - bindings 1..N: `texture_2d` views for each `Frame` input (primary input is binding 1)
 //!   - binding (N+1): a uniform buffer containing non-texture parameters (bool/int/float/pixel/dimensions/enum)
 //!
 //! Parameters are packed into the uniform buffer by name using simple std140-like alignment.
-//! Text and file inputs are not passed to shaders; `Frame` inputs are provided as texture views
-//! in the order they are declared in the node definition.
+//! Text, file, and curve inputs are not passed to shaders, and neither is the `Sampling` input; `Frame`
+//! inputs are provided as texture views in the order they are declared in the node definition.
+//!
+//! A shader node can list WGSL files in its `includes`, which are prepended to each of its shaders
+//! so a family of nodes can share helper functions (see `nodes/geometry_warp/`).
 //!
 //! Examples
 //! --------
 //! See the `nodes/` folder at the repository root for example `shader.wgsl` files demonstrating
 //! bindings and entry points.
+pub mod cpu_backend;
+pub mod diagnostics;
 pub mod engine_errors;
 pub mod engine_outpost;
+pub mod execution_trace;
+pub mod export;
 pub mod graph_executor;
 pub mod node;
 pub mod node_graph;
 pub mod node_pipelines;
+pub mod pipeline_cache;
+pub mod tempo;
+pub mod tone_curve;
+pub mod wasm_nodes;
 
+mod blob_tracking;
+mod contrast_equalizer;
+mod depth_estimation;
+mod face_detection;
+mod frame_interpolator;
+mod frame_reader;
+mod frame_transformer;
 mod gpu_frame;
 mod graph_executor_effects;
+mod histogram_matcher;
+mod inference_worker;
+mod layout_compositor;
+mod lut_renderer;
+mod mipmap_generator;
+mod slit_scanner;
+mod source_switcher;
+mod texture_blitter;
+mod thresholder;
+mod trail_accumulator;
 mod upload_stager;
+mod watermark_compositor;
+mod white_balancer;
 
 pub use engine_errors::EngineError;
 pub use engine_outpost::{EngineOutpostHandle, spawn};
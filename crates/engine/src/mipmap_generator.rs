@@ -0,0 +1,75 @@
+//! Exports [MipmapGenerator], which fills in the mip levels of a texture so it
+//! can be sampled smaller than its size without aliasing (see
+//! [crate::node_pipelines::SamplingQuality]).
+//!
+//! Each level is rendered from the level above it with a linear filter, so
+//! every level is a 2x2 box filtered copy of the previous one.
+
+use crate::texture_blitter::TextureBlitter;
+
+/// The number of mip levels in a full chain for a texture of the given size,
+/// down to a single pixel.
+pub const fn mip_level_count(width: u32, height: u32) -> u32 {
+    let largest = if width > height { width } else { height };
+    if largest == 0 {
+        1
+    } else {
+        u32::BITS - largest.leading_zeros()
+    }
+}
+
+/// Generates mip levels for textures of one format. Textures passed to
+/// [Self::generate] need [wgpu::TextureUsages::TEXTURE_BINDING] and
+/// [wgpu::TextureUsages::RENDER_ATTACHMENT] usage.
+pub struct MipmapGenerator {
+    blitter: TextureBlitter,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        Self {
+            blitter: TextureBlitter::new(device, format),
+        }
+    }
+
+    /// Record passes into `encoder` that render every mip level of `texture`
+    /// from level 0. Does nothing if the texture has a single level.
+    pub fn generate(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) {
+        debug_assert_eq!(texture.format(), self.blitter.format());
+
+        let level_view = |level| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("mipmap_level"),
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        };
+
+        for level in 1..texture.mip_level_count() {
+            self.blitter
+                .blit(device, encoder, &level_view(level - 1), &level_view(level));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- mip_level_count() ---
+
+    #[test]
+    fn test_mip_level_count() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(2, 1), 2);
+        assert_eq!(mip_level_count(1920, 1080), 11);
+        assert_eq!(mip_level_count(1080, 4096), 13);
+        assert_eq!(mip_level_count(0, 0), 1);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod conversions;
+mod embedded_nodes;
 pub mod engine_node;
 pub mod errors;
 pub mod handler;
@@ -2,6 +2,7 @@
 
 use crate::graph_executor::NodeValue;
 use crate::node::engine_node::{NodeInputKind, NodeOutputKind};
+use crate::tone_curve::ToneCurve;
 
 /// Convert a NodeInputKind to its corresponding NodeOutputKind for connection typing
 pub fn input_kind_to_output_kind(input_kind: &NodeInputKind) -> NodeOutputKind {
@@ -17,6 +18,8 @@ pub fn input_kind_to_output_kind(input_kind: &NodeInputKind) -> NodeOutputKind {
         NodeInputKind::Enum { .. } => NodeOutputKind::Int,
         NodeInputKind::File { .. } => NodeOutputKind::Text,
         NodeInputKind::PortSelection => NodeOutputKind::Text,
+        NodeInputKind::Curve => NodeOutputKind::Text,
+        NodeInputKind::FloatArray { .. } => NodeOutputKind::FloatArray,
     }
 }
 
@@ -34,5 +37,7 @@ pub fn default_value_for_input_kind(input_kind: &NodeInputKind) -> NodeValue {
         NodeInputKind::Enum { default_idx, .. } => NodeValue::Enum(default_idx.unwrap_or(0)),
         NodeInputKind::File { default, .. } => NodeValue::File(default.clone().unwrap_or_default()),
         NodeInputKind::PortSelection => NodeValue::Text(String::new()),
+        NodeInputKind::Curve => NodeValue::Text(ToneCurve::identity().to_string()),
+        NodeInputKind::FloatArray { default } => NodeValue::FloatArray(default.clone()),
     }
 }
@@ -0,0 +1,219 @@
+//! The stock nodes folder, embedded into the binary at build time (see the
+//! engine's `build.rs`, which also checks every stock shader compiles).
+//!
+//! [NodeLibrary](super::NodeLibrary) falls back on these when the nodes folder
+//! next to the app is missing nodes or some of them fail to load. Node
+//! definitions point at the folder they were loaded from, so the embedded
+//! files are unpacked to [local_data::embedded_nodes_path] first.
+//!
+//! Every build unpacks to its own folder, and several apps (e.g. the launcher
+//! and an editor, possibly from different builds) can use them at once. A
+//! process holds a shared lock on `<folder>.lock` for as long as it runs, and
+//! a folder is only removed by another build while nobody holds its lock.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::OnceLock;
+
+use util::local_data;
+
+use super::engine_node::EngineNode;
+use super::node_definition::NodeDefinition;
+
+include!(concat!(env!("OUT_DIR"), "/embedded_nodes.rs"));
+
+/// The names of the embedded nodes.
+pub(super) fn node_names() -> Vec<String> {
+    EMBEDDED_NODE_FILES
+        .iter()
+        .filter(|(path, _)| Path::new(path).ends_with("node.json"))
+        .filter_map(|(_, contents)| serde_json::from_slice::<EngineNode>(contents).ok())
+        .map(|node| node.name)
+        .collect()
+}
+
+/// The names of the embedded nodes in `definitions` whose folder doesn't have
+/// the same files (e.g. shaders) as the embedded copy. Stock nodes on disk can
+/// be left over from another install or be corrupted.
+pub(super) fn mismatched_nodes(definitions: &HashMap<String, NodeDefinition>) -> Vec<String> {
+    EMBEDDED_NODE_FILES
+        .iter()
+        .filter(|(path, _)| Path::new(path).ends_with("node.json"))
+        .filter_map(|(path, contents)| {
+            let node = serde_json::from_slice::<EngineNode>(contents).ok()?;
+            let definition = definitions.get(&node.name)?;
+            let node_dir = Path::new(path).parent()?;
+
+            let matches = EMBEDDED_NODE_FILES
+                .iter()
+                .filter_map(|(file, contents)| {
+                    Some((Path::new(file).strip_prefix(node_dir).ok()?, *contents))
+                })
+                .all(|(relative, contents)| {
+                    fs::read(definition.folder_path.join(relative))
+                        .is_ok_and(|on_disk| on_disk == contents)
+                });
+            (!matches).then_some(node.name)
+        })
+        .collect()
+}
+
+/// Unpack the embedded nodes, returning the folder they're in. They're only
+/// written the first time a build unpacks them; copies from other builds are
+/// removed once no running app uses them.
+pub(super) fn unpack() -> io::Result<PathBuf> {
+    static IN_USE: OnceLock<File> = OnceLock::new();
+
+    let mut hasher = DefaultHasher::new();
+    EMBEDDED_NODE_FILES.hash(&mut hasher);
+    let dir_name = format!("{:016x}", hasher.finish());
+
+    let root = local_data::embedded_nodes_path();
+    remove_unused(root, &dir_name);
+
+    // Waits if another build is removing this build's folder right now.
+    if IN_USE.get().is_none() {
+        let lock_file = open_lock_file(root, &dir_name)?;
+        lock_file.lock_shared()?;
+        _ = IN_USE.set(lock_file);
+    }
+
+    let dir = root.join(&dir_name);
+    if dir.is_dir() {
+        return Ok(dir);
+    }
+
+    // Unpack next to the final folder and move it into place, so a folder
+    // that's there is always complete. The temporary folder is unique to this
+    // process in case another instance of this build is unpacking too.
+    let temp_dir = root.join(format!("{dir_name}.{}.tmp", process::id()));
+    _ = fs::remove_dir_all(&temp_dir);
+    for (path, contents) in EMBEDDED_NODE_FILES {
+        let path = temp_dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+    }
+    if let Err(e) = fs::rename(&temp_dir, &dir) {
+        _ = fs::remove_dir_all(&temp_dir);
+        // Another instance of this build moved its copy into place first.
+        if !dir.is_dir() {
+            return Err(e);
+        }
+    }
+    Ok(dir)
+}
+
+/// Remove the folders (and leftover temporary folders) of builds other than
+/// `dir_name` that no running app holds the lock of. Lock files are kept, since
+/// removing one could let two apps lock different files for the same folder.
+fn remove_unused(root: &Path, dir_name: &str) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        let build = file_name.split('.').next().unwrap_or_default();
+        if build == dir_name {
+            continue;
+        }
+
+        let lock_file = match open_lock_file(root, build) {
+            Ok(lock_file) => lock_file,
+            Err(e) => {
+                util::debug_log_warning!("Failed to open embedded nodes lock: {e}");
+                continue;
+            }
+        };
+        match lock_file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => continue,
+            Err(TryLockError::Error(e)) => {
+                util::debug_log_warning!("Failed to lock old embedded nodes: {e}");
+                continue;
+            }
+        }
+
+        util::debug_log_info!("Removing embedded nodes from another build: {path:?}");
+        _ = fs::remove_dir_all(&path).inspect_err(|e| {
+            util::debug_log_warning!("Failed to remove old embedded nodes: {e}");
+        });
+    }
+}
+
+/// Open (or create) the lock file of a build's folder. It's never truncated,
+/// since another app may have it locked.
+fn open_lock_file(root: &Path, dir_name: &str) -> io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(root.join(format!("{dir_name}.lock")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- node_names() ---
+
+    #[test]
+    fn every_embedded_node_parses() {
+        let node_files = EMBEDDED_NODE_FILES
+            .iter()
+            .filter(|(path, _)| Path::new(path).ends_with("node.json"))
+            .count();
+        assert!(node_files > 0);
+        assert_eq!(node_names().len(), node_files);
+    }
+
+    // --- mismatched_nodes() ---
+
+    #[test]
+    fn mismatched_nodes_finds_changed_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "bio-visualizer-embedded-nodes-{}",
+            std::process::id()
+        ));
+        for (path, contents) in EMBEDDED_NODE_FILES {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        let (node_json, contents) = EMBEDDED_NODE_FILES
+            .iter()
+            .find(|(path, _)| Path::new(path).ends_with("node.json"))
+            .unwrap();
+        let node: EngineNode = serde_json::from_slice(contents).unwrap();
+        let folder_path = dir.join(Path::new(node_json).parent().unwrap());
+        let definitions = HashMap::from([(
+            node.name.clone(),
+            NodeDefinition {
+                node: node.clone(),
+                shader_path: None,
+                folder_path: folder_path.clone(),
+                example_image_paths: Vec::new(),
+            },
+        )]);
+
+        assert!(mismatched_nodes(&definitions).is_empty());
+
+        fs::write(folder_path.join("node.json"), b"{}").unwrap();
+        assert_eq!(mismatched_nodes(&definitions), vec![node.name]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -36,6 +36,10 @@ pub struct EngineNode {
     /// Keywords used to help find this node when searching
     #[serde(default)]
     pub search_keywords: Vec<String>,
+
+    /// Example images shown in the node's help, relative to the node.json file
+    #[serde(default)]
+    pub example_images: Vec<PathBuf>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -50,6 +54,15 @@ pub struct NodeInput {
     /// Default to true because that is the most common case
     #[serde(default = "default_show_pin")]
     pub show_pin: bool,
+
+    /// Help text shown when hovering this input
+    #[serde(default)]
+    pub help: String,
+
+    /// Whether a Frame input can be left unconnected, in which case it's left
+    /// out of the inputs the node is executed with
+    #[serde(default)]
+    pub optional: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -63,6 +76,15 @@ pub struct NodeOutput {
     /// Show Pin
     #[serde(default = "default_show_pin")]
     pub show_pin: bool,
+
+    /// Help text shown when hovering this output
+    #[serde(default)]
+    pub help: String,
+
+    /// Publish this output's value every frame on the analysis bus so the UI
+    /// can plot it (see `EngineOutpostHandle::subscribe_analysis`)
+    #[serde(default)]
+    pub publish: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
@@ -75,6 +97,25 @@ pub enum NodeOutputKind {
     Dimensions,
     Pixel,
     Text,
+    /// A list of floats, e.g. spectrum bins or blob positions
+    FloatArray,
+}
+
+impl NodeOutputKind {
+    /// The kind's name, as it's written in node.json.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Frame => "Frame",
+            Self::MidiPacket => "MidiPacket",
+            Self::Bool => "Bool",
+            Self::Int => "Int",
+            Self::Float => "Float",
+            Self::Dimensions => "Dimensions",
+            Self::Pixel => "Pixel",
+            Self::Text => "Text",
+            Self::FloatArray => "FloatArray",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -145,12 +186,47 @@ pub enum NodeInputKind {
         default: Option<PathBuf>,
     },
     PortSelection,
+    /// A list of floats, edited as comma separated numbers when not connected
+    FloatArray {
+        #[serde(default)]
+        default: Vec<f32>,
+    },
+    /// An editable [ToneCurve](crate::tone_curve::ToneCurve), passed as
+    /// [NodeValue::Text](crate::graph_executor::NodeValue::Text) in the
+    /// curve's text format. Defaults to the identity curve.
+    Curve,
+}
+
+impl NodeInputKind {
+    /// The kind's name, as it's written in node.json.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Frame => "Frame",
+            Self::MidiPacket => "MidiPacket",
+            Self::Bool { .. } => "Bool",
+            Self::Int { .. } => "Int",
+            Self::Float { .. } => "Float",
+            Self::Dimensions { .. } => "Dimensions",
+            Self::Pixel { .. } => "Pixel",
+            Self::Enum { .. } => "Enum",
+            Self::Text { .. } => "Text",
+            Self::File { .. } => "File",
+            Self::PortSelection => "PortSelection",
+            Self::FloatArray { .. } => "FloatArray",
+            Self::Curve => "Curve",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ShaderPass {
     /// Path of a shader file relative to the node.json file.
     pub source: PathBuf,
+    /// How many times smaller than the output this pass renders (e.g. 2 for
+    /// half the width and height), for blurs and other multi-scale effects
+    /// that don't need every pixel.
+    #[serde(default = "default_downscale")]
+    pub downscale: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -161,6 +237,11 @@ pub enum NodeExecutionPlan {
         /// Optional pre-passes that render into temporary textures before the final shader.
         #[serde(default)]
         passes: Vec<ShaderPass>,
+        /// Paths of WGSL files relative to the node.json file that are
+        /// prepended to each of the node's shaders, so related nodes can share
+        /// helper functions (and the vertex shader).
+        #[serde(default)]
+        includes: Vec<PathBuf>,
     },
     Algorithm {
         /// Effect family or algorithm identifier (for example: PixelSort, OpticalFlow, Datamosh).
@@ -170,6 +251,20 @@ pub enum NodeExecutionPlan {
         stages: Vec<AlgorithmStage>,
     },
     BuiltIn(BuiltInHandler),
+    /// Logic run by a WebAssembly module instead of a shader, for nodes
+    /// without frame outputs (see [crate::wasm_nodes] for the module's ABI).
+    Wasm {
+        /// Path of a .wasm file relative to the node.json file
+        module: PathBuf,
+        /// How long one run of the module may take, in milliseconds (capped
+        /// at [crate::wasm_nodes::MAX_TIME_LIMIT_MS])
+        #[serde(default = "default_wasm_time_limit_ms")]
+        time_limit_ms: u32,
+        /// How much memory the module may use, in MiB (capped at
+        /// [crate::wasm_nodes::MAX_MEMORY_LIMIT_MIB])
+        #[serde(default = "default_wasm_memory_limit_mib")]
+        memory_limit_mib: u32,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -179,6 +274,25 @@ pub enum NoiseKind {
     Sin,
 }
 
+/// The stock math and logic nodes, which all work on plain values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MathOp {
+    Add,
+    Multiply,
+    Min,
+    Max,
+    Clamp,
+    Lerp,
+    Remap,
+    Compare,
+    Logic,
+    Not,
+    Select,
+    ArrayIndex,
+    ArraySlice,
+    ArrayReduce,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BuiltInHandler {
     ImageSource,
@@ -186,7 +300,33 @@ pub enum BuiltInHandler {
     MidiSource,
     MidiProperties,
     SignalEnvelope,
+    AudioMeter,
+    SpriteSheet,
+    TimeRemap,
+    FrameInterpolate,
+    Stabilize,
+    FrameDelay,
+    Feedback,
+    FaceDetect,
+    DepthEstimate,
+    LevelsCurves,
+    WhiteBalance,
+    Equalize,
+    Threshold,
+    BlobTrack,
+    Trails,
+    SlitScan,
+    MatchColor,
+    Switcher,
+    Layout,
+    Watermark,
+    Tempo,
+    Lfo,
     Noise(NoiseKind),
+    Math(MathOp),
+    /// Outputs its "Value" input, for constant nodes of any type
+    Constant,
+    TextTemplate,
 }
 
 impl Serialize for BuiltInHandler {
@@ -200,9 +340,47 @@ impl Serialize for BuiltInHandler {
             BuiltInHandler::MidiSource => "MidiSource",
             BuiltInHandler::MidiProperties => "MidiProperties",
             BuiltInHandler::SignalEnvelope => "SignalEnvelope",
+            BuiltInHandler::AudioMeter => "AudioMeter",
+            BuiltInHandler::SpriteSheet => "SpriteSheet",
+            BuiltInHandler::TimeRemap => "TimeRemap",
+            BuiltInHandler::FrameInterpolate => "FrameInterpolate",
+            BuiltInHandler::Stabilize => "Stabilize",
+            BuiltInHandler::FrameDelay => "FrameDelay",
+            BuiltInHandler::Feedback => "Feedback",
+            BuiltInHandler::FaceDetect => "FaceDetect",
+            BuiltInHandler::DepthEstimate => "DepthEstimate",
+            BuiltInHandler::LevelsCurves => "LevelsCurves",
+            BuiltInHandler::WhiteBalance => "WhiteBalance",
+            BuiltInHandler::Equalize => "Equalize",
+            BuiltInHandler::Threshold => "Threshold",
+            BuiltInHandler::BlobTrack => "BlobTrack",
+            BuiltInHandler::Trails => "Trails",
+            BuiltInHandler::SlitScan => "SlitScan",
+            BuiltInHandler::MatchColor => "MatchColor",
+            BuiltInHandler::Switcher => "Switcher",
+            BuiltInHandler::Layout => "Layout",
+            BuiltInHandler::Watermark => "Watermark",
+            BuiltInHandler::Tempo => "Tempo",
+            BuiltInHandler::Lfo => "Lfo",
             BuiltInHandler::Noise(NoiseKind::Perlin) => "PerlinNoise",
             BuiltInHandler::Noise(NoiseKind::Random) => "RandomNoise",
             BuiltInHandler::Noise(NoiseKind::Sin) => "SinNoise",
+            BuiltInHandler::Math(MathOp::Add) => "Add",
+            BuiltInHandler::Math(MathOp::Multiply) => "Multiply",
+            BuiltInHandler::Math(MathOp::Min) => "Min",
+            BuiltInHandler::Math(MathOp::Max) => "Max",
+            BuiltInHandler::Math(MathOp::Clamp) => "Clamp",
+            BuiltInHandler::Math(MathOp::Lerp) => "Lerp",
+            BuiltInHandler::Math(MathOp::Remap) => "Remap",
+            BuiltInHandler::Math(MathOp::Compare) => "Compare",
+            BuiltInHandler::Math(MathOp::Logic) => "Logic",
+            BuiltInHandler::Math(MathOp::Not) => "Not",
+            BuiltInHandler::Math(MathOp::Select) => "Select",
+            BuiltInHandler::Math(MathOp::ArrayIndex) => "ArrayIndex",
+            BuiltInHandler::Math(MathOp::ArraySlice) => "ArraySlice",
+            BuiltInHandler::Math(MathOp::ArrayReduce) => "ArrayReduce",
+            BuiltInHandler::Constant => "Constant",
+            BuiltInHandler::TextTemplate => "TextTemplate",
         };
 
         serializer.serialize_str(name)
@@ -222,9 +400,47 @@ impl<'de> Deserialize<'de> for BuiltInHandler {
             "MidiSource" => Ok(BuiltInHandler::MidiSource),
             "MidiProperties" => Ok(BuiltInHandler::MidiProperties),
             "SignalEnvelope" => Ok(BuiltInHandler::SignalEnvelope),
+            "AudioMeter" => Ok(BuiltInHandler::AudioMeter),
+            "SpriteSheet" => Ok(BuiltInHandler::SpriteSheet),
+            "TimeRemap" => Ok(BuiltInHandler::TimeRemap),
+            "FrameInterpolate" => Ok(BuiltInHandler::FrameInterpolate),
+            "Stabilize" => Ok(BuiltInHandler::Stabilize),
+            "FrameDelay" => Ok(BuiltInHandler::FrameDelay),
+            "Feedback" => Ok(BuiltInHandler::Feedback),
+            "FaceDetect" => Ok(BuiltInHandler::FaceDetect),
+            "DepthEstimate" => Ok(BuiltInHandler::DepthEstimate),
+            "LevelsCurves" => Ok(BuiltInHandler::LevelsCurves),
+            "WhiteBalance" => Ok(BuiltInHandler::WhiteBalance),
+            "Equalize" => Ok(BuiltInHandler::Equalize),
+            "Threshold" => Ok(BuiltInHandler::Threshold),
+            "BlobTrack" => Ok(BuiltInHandler::BlobTrack),
+            "Trails" => Ok(BuiltInHandler::Trails),
+            "SlitScan" => Ok(BuiltInHandler::SlitScan),
+            "MatchColor" => Ok(BuiltInHandler::MatchColor),
+            "Switcher" => Ok(BuiltInHandler::Switcher),
+            "Layout" => Ok(BuiltInHandler::Layout),
+            "Watermark" => Ok(BuiltInHandler::Watermark),
+            "Tempo" => Ok(BuiltInHandler::Tempo),
+            "Lfo" => Ok(BuiltInHandler::Lfo),
             "PerlinNoise" | "Perlin" => Ok(BuiltInHandler::Noise(NoiseKind::Perlin)),
             "RandomNoise" | "Random" => Ok(BuiltInHandler::Noise(NoiseKind::Random)),
             "SinNoise" | "Sin" => Ok(BuiltInHandler::Noise(NoiseKind::Sin)),
+            "Add" => Ok(BuiltInHandler::Math(MathOp::Add)),
+            "Multiply" => Ok(BuiltInHandler::Math(MathOp::Multiply)),
+            "Min" => Ok(BuiltInHandler::Math(MathOp::Min)),
+            "Max" => Ok(BuiltInHandler::Math(MathOp::Max)),
+            "Clamp" => Ok(BuiltInHandler::Math(MathOp::Clamp)),
+            "Lerp" => Ok(BuiltInHandler::Math(MathOp::Lerp)),
+            "Remap" => Ok(BuiltInHandler::Math(MathOp::Remap)),
+            "Compare" => Ok(BuiltInHandler::Math(MathOp::Compare)),
+            "Logic" => Ok(BuiltInHandler::Math(MathOp::Logic)),
+            "Not" => Ok(BuiltInHandler::Math(MathOp::Not)),
+            "Select" => Ok(BuiltInHandler::Math(MathOp::Select)),
+            "ArrayIndex" => Ok(BuiltInHandler::Math(MathOp::ArrayIndex)),
+            "ArraySlice" => Ok(BuiltInHandler::Math(MathOp::ArraySlice)),
+            "ArrayReduce" => Ok(BuiltInHandler::Math(MathOp::ArrayReduce)),
+            "Constant" => Ok(BuiltInHandler::Constant),
+            "TextTemplate" => Ok(BuiltInHandler::TextTemplate),
             other => Err(serde::de::Error::unknown_variant(
                 other,
                 &[
@@ -233,10 +449,48 @@ impl<'de> Deserialize<'de> for BuiltInHandler {
                     "MidiSource",
                     "MidiProperties",
                     "SignalEnvelope",
+                    "AudioMeter",
+                    "SpriteSheet",
+                    "TimeRemap",
+                    "FrameInterpolate",
+                    "Stabilize",
+                    "FrameDelay",
+                    "Feedback",
+                    "FaceDetect",
+                    "DepthEstimate",
+                    "LevelsCurves",
+                    "WhiteBalance",
+                    "Equalize",
+                    "Threshold",
+                    "BlobTrack",
+                    "Trails",
+                    "SlitScan",
+                    "MatchColor",
+                    "Switcher",
+                    "Layout",
+                    "Watermark",
+                    "Tempo",
+                    "Lfo",
                     "RippleEvents",
                     "PerlinNoise",
                     "RandomNoise",
                     "SinNoise",
+                    "Add",
+                    "Multiply",
+                    "Min",
+                    "Max",
+                    "Clamp",
+                    "Lerp",
+                    "Remap",
+                    "Compare",
+                    "Logic",
+                    "Not",
+                    "Select",
+                    "ArrayIndex",
+                    "ArraySlice",
+                    "ArrayReduce",
+                    "Constant",
+                    "TextTemplate",
                 ],
             )),
         }
@@ -333,3 +587,15 @@ fn default_ui_lines() -> u64 {
 fn default_show_pin() -> bool {
     true
 }
+
+fn default_downscale() -> u32 {
+    1
+}
+
+fn default_wasm_time_limit_ms() -> u32 {
+    2
+}
+
+fn default_wasm_memory_limit_mib() -> u32 {
+    16
+}
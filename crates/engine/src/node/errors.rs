@@ -1,4 +1,4 @@
-use super::engine_node::NumberInputUiMode;
+use super::engine_node::{NodeOutputKind, NumberInputUiMode};
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -16,6 +16,12 @@ pub enum LibraryError {
     #[error("Shader file not found: {0:?}")]
     ShaderNotFound(PathBuf),
 
+    #[error("WASM module not found: {0:?}")]
+    WasmModuleNotFound(PathBuf),
+
+    #[error("WASM node '{0}' can't have a {1:?} output")]
+    UnsupportedWasmOutput(String, NodeOutputKind),
+
     #[error("Node '{0}' is not a shader node")]
     NotAShaderNode(String),
 
@@ -1,12 +1,59 @@
+mod audio_meter_handler;
+mod blob_track_handler;
+mod depth_estimate_handler;
+mod equalize_handler;
+mod face_detect_handler;
+mod feedback_handler;
 mod frame_stream_handler;
+mod layout_handler;
+mod levels_curves_handler;
+mod match_color_handler;
+mod math_handler;
 mod midi_stream_handler;
 mod noise_stream_handler;
 mod signal_envelope_handler;
+mod slit_scan_handler;
+mod sprite_sheet_handler;
+mod stabilize_handler;
+mod switcher_handler;
+mod tempo_handler;
+mod text_template_handler;
+mod threshold_handler;
+mod time_remap_handler;
 pub mod timed_stream_handler;
+mod trails_handler;
+mod wasm_handler;
+mod watermark_handler;
+mod white_balance_handler;
 
+pub use audio_meter_handler::{AudioMeterHandler, NodeAudioMeterRequest};
+pub use blob_track_handler::{BlobTrackHandler, NodeBlobTrackRequest};
+pub use depth_estimate_handler::{DepthEstimateHandler, NodeDepthEstimateRequest};
+pub use equalize_handler::{EqualizeHandler, NodeEqualizeRequest};
+pub use face_detect_handler::{FaceDetectHandler, NodeFaceDetectRequest};
+pub use feedback_handler::{
+    FeedbackHandler, MAX_DELAY_FRAMES, NodeFeedbackRequest, NodeFrameDelayRequest,
+};
 pub use frame_stream_handler::{
-    FrameStreamHandler, FrameStreamHandlerError, NodeFrameStreamRequest, StreamKind,
+    FrameInterpolation, FrameStreamHandler, FrameStreamHandlerError, LoopMode,
+    NodeFrameStreamRequest, StreamKind, VideoTrim,
 };
+pub use layout_handler::{LayoutHandler, NodeLayoutRequest};
+pub use levels_curves_handler::{LevelsCurvesHandler, NodeLevelsCurvesRequest};
+pub use match_color_handler::{MatchColorHandler, NodeMatchColorRequest};
+pub use math_handler::{NodeMathRequest, execute_constant, execute_math};
 pub use midi_stream_handler::{MidiStreamHandler, NodeMidiStreamRequest};
 pub use noise_stream_handler::{NodeNoiseStreamRequest, NoiseStreamHandler};
 pub use signal_envelope_handler::{NodeSignalEnvelopeRequest, SignalEnvelopeHandler};
+pub use slit_scan_handler::{NodeSlitScanRequest, SlitScanHandler};
+pub use sprite_sheet_handler::{NodeSpriteSheetRequest, SpriteSheetHandler};
+pub use stabilize_handler::{NodeStabilizeRequest, StabilizeHandler};
+pub use switcher_handler::{NodeSwitcherRequest, SwitcherHandler};
+pub use tempo_handler::{NodeLfoRequest, NodeTempoRequest, TempoHandler};
+pub use text_template_handler::execute_text_template;
+pub use threshold_handler::{NodeThresholdRequest, ThresholdHandler};
+pub use time_remap_handler::{NodeTimeRemapRequest, TimeRemapHandler};
+pub use trails_handler::{NodeTrailsRequest, TrailsHandler};
+pub use wasm_handler::{NodeWasmRequest, WasmHandler};
+pub use watermark_handler::{NodeWatermarkRequest, WatermarkHandler};
+pub use white_balance_handler::{NodeWhiteBalanceRequest, WhiteBalanceHandler};
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use media::audio::{AudioError, AudioFileReader, Ballistics, ChannelLevel, LevelMeter};
+
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+
+/// The most audio read in one tick. Longer gaps (e.g. the engine stalled) skip
+/// ahead instead of catching up.
+const MAX_TICK_ADVANCE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, thiserror::Error)]
+pub enum AudioMeterHandlerError {
+    #[error("audio meter input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("audio meter input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+    #[error("couldn't open audio in '{path}'")]
+    Unreadable { path: PathBuf },
+    #[error("failed to read audio from '{path}': {source}")]
+    Audio {
+        path: PathBuf,
+        #[source]
+        source: AudioError,
+    },
+}
+
+pub struct NodeAudioMeterRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+struct MeterState {
+    path: PathBuf,
+    reader: AudioFileReader,
+    meter: LevelMeter,
+    /// How far into the audio playback is.
+    playhead: Duration,
+    last_tick: Option<Instant>,
+}
+
+/// Plays the audio of a file in real time (looping at the end) and measures
+/// the RMS and peak level of each channel.
+pub struct AudioMeterHandler {
+    state_cache: HashMap<EngineNodeId, MeterState>,
+    /// Files that failed to open, so they aren't reopened every frame.
+    failed_paths: HashMap<EngineNodeId, PathBuf>,
+    paused: bool,
+}
+
+impl Default for AudioMeterHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioMeterHandler {
+    pub fn new() -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            failed_paths: HashMap::new(),
+            paused: false,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+        self.failed_paths.clear();
+    }
+
+    pub fn pause_all_streams(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn play_all_streams(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeAudioMeterRequest,
+    ) -> Result<Vec<NodeValue>, AudioMeterHandlerError> {
+        let path = read_file_input(request.inputs, "Path")?;
+        let ballistics = Ballistics {
+            attack: read_millis_input(request.inputs, "Attack (ms)")?,
+            release: read_millis_input(request.inputs, "Release (ms)")?,
+            peak_hold: read_millis_input(request.inputs, "Peak Hold (ms)")?,
+        };
+
+        let paused = self.paused;
+
+        if path.as_os_str().is_empty() {
+            self.state_cache.remove(&request.node_id);
+            return Ok(level_outputs(&[]));
+        }
+
+        let state = self.meter_state(request.node_id, path, ballistics)?;
+        state.meter.set_ballistics(ballistics);
+
+        let now = Instant::now();
+        let last_tick = state.last_tick.replace(now);
+        if paused {
+            state.last_tick = None;
+            return Ok(level_outputs(state.meter.levels()));
+        }
+        if let Some(last_tick) = last_tick {
+            state.playhead += now.duration_since(last_tick).min(MAX_TICK_ADVANCE);
+        }
+
+        let audio_error = |source| AudioMeterHandlerError::Audio {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        if state.playhead.saturating_sub(state.reader.position()) > MAX_TICK_ADVANCE {
+            state
+                .reader
+                .seek(state.playhead - MAX_TICK_ADVANCE)
+                .map_err(audio_error)?;
+        }
+
+        let meter = &mut state.meter;
+        let reached_playhead = state
+            .reader
+            .read_until(state.playhead, |planes| meter.process(planes))
+            .map_err(audio_error)?;
+        if !reached_playhead {
+            // Loop back to the start.
+            state.reader.seek(Duration::ZERO).map_err(audio_error)?;
+            state.playhead = Duration::ZERO;
+        }
+
+        Ok(level_outputs(state.meter.levels()))
+    }
+
+    /// The meter for `node_id`, (re)opening the file if the path changed.
+    fn meter_state(
+        &mut self,
+        node_id: EngineNodeId,
+        path: &Path,
+        ballistics: Ballistics,
+    ) -> Result<&mut MeterState, AudioMeterHandlerError> {
+        if self
+            .state_cache
+            .get(&node_id)
+            .is_some_and(|state| state.path == path)
+        {
+            return Ok(self.state_cache.get_mut(&node_id).expect("just checked"));
+        }
+
+        if self
+            .failed_paths
+            .get(&node_id)
+            .is_some_and(|failed| failed == path)
+        {
+            return Err(AudioMeterHandlerError::Unreadable {
+                path: path.to_path_buf(),
+            });
+        }
+
+        let reader = match AudioFileReader::open(path) {
+            Ok(reader) => reader,
+            Err(source) => {
+                self.failed_paths.insert(node_id, path.to_path_buf());
+                return Err(AudioMeterHandlerError::Audio {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        };
+        self.failed_paths.remove(&node_id);
+
+        let meter = LevelMeter::new(reader.channels(), reader.sample_rate(), ballistics);
+        let state = MeterState {
+            path: path.to_path_buf(),
+            reader,
+            meter,
+            playhead: Duration::ZERO,
+            last_tick: None,
+        };
+        Ok(self
+            .state_cache
+            .entry(node_id)
+            .insert_entry(state)
+            .into_mut())
+    }
+}
+
+/// The outputs for the given channel levels: left RMS and peak, right RMS and
+/// peak (the same as the left for mono audio), and whether any channel clipped.
+fn level_outputs(levels: &[ChannelLevel]) -> Vec<NodeValue> {
+    let left = levels.first().copied().unwrap_or_default();
+    let right = levels.get(1).copied().unwrap_or(left);
+    let clipped = levels.iter().any(|level| level.clipped);
+
+    vec![
+        NodeValue::Float(left.rms),
+        NodeValue::Float(left.peak),
+        NodeValue::Float(right.rms),
+        NodeValue::Float(right.peak),
+        NodeValue::Bool(clipped),
+    ]
+}
+
+fn read_file_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a Path, AudioMeterHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::File(path)) => Ok(path.as_path()),
+        Some(_) => Err(AudioMeterHandlerError::InvalidInput {
+            input_name,
+            expected: "File",
+        }),
+        None => Err(AudioMeterHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_millis_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<Duration, AudioMeterHandlerError> {
+    let millis = match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => *value,
+        Some(NodeValue::Int(value)) => *value as f32,
+        Some(_) => {
+            return Err(AudioMeterHandlerError::InvalidInput {
+                input_name,
+                expected: "Float",
+            });
+        }
+        None => return Err(AudioMeterHandlerError::MissingInput { input_name }),
+    };
+    Ok(Duration::from_secs_f32(millis.max(0.0) / 1000.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- level_outputs() ---
+
+    #[test]
+    fn test_mono_levels_fill_both_sides() {
+        let level = ChannelLevel {
+            rms: 0.25,
+            peak: 0.5,
+            clipped: false,
+        };
+        assert_eq!(
+            level_outputs(&[level]),
+            vec![
+                NodeValue::Float(0.25),
+                NodeValue::Float(0.5),
+                NodeValue::Float(0.25),
+                NodeValue::Float(0.5),
+                NodeValue::Bool(false),
+            ]
+        );
+    }
+}
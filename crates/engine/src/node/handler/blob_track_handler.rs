@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use crate::blob_tracking::{self, BlobTracker, TrackedBlob};
+use crate::frame_reader::{self, FrameReader};
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+
+/// The longest side masks are shrunk to before blobs are found. Plenty to
+/// count and follow cells, and small enough to label every frame.
+const READ_SIZE: u32 = 256;
+
+/// How many analyzed frames a blob can go missing for before its ID is
+/// forgotten.
+const MAX_MISSED_FRAMES: u32 = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlobTrackHandlerError {
+    #[error("blob track input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("blob track input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+    #[error("failed to read the frame back from the GPU: {0}")]
+    Readback(String),
+}
+
+pub struct NodeBlobTrackRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+struct TrackerState {
+    reader: FrameReader,
+    tracker: BlobTracker,
+    /// The blobs from the last mask read back, oldest ID first when tracking
+    /// and largest first otherwise.
+    blobs: Vec<TrackedBlob>,
+}
+
+/// Runs Blob Track nodes, which find the connected regions of a mask (e.g.
+/// cells from a Threshold node) and follow them from frame to frame.
+///
+/// Masks are read back from the GPU at a small size (see [FrameReader]) and
+/// labeled on the CPU, so the node's outputs lag the frame by a frame or two.
+pub struct BlobTrackHandler {
+    state_cache: HashMap<EngineNodeId, TrackerState>,
+}
+
+impl Default for BlobTrackHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlobTrackHandler {
+    pub fn new() -> Self {
+        Self {
+            state_cache: HashMap::new(),
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeBlobTrackRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, BlobTrackHandlerError> {
+        let input = read_frame_input(request.inputs, "Mask")?;
+        let min_area = read_float_input(request.inputs, "Min Area")?.max(0.0);
+        let track = read_bool_input(request.inputs, "Track")?;
+        let max_distance = read_float_input(request.inputs, "Max Distance")?.max(0.0);
+        let blob_index = read_int_input(request.inputs, "Blob Index")?.max(0) as usize;
+
+        let state = self
+            .state_cache
+            .entry(request.node_id)
+            .or_insert_with(|| TrackerState {
+                reader: FrameReader::new(),
+                tracker: BlobTracker::new(MAX_MISSED_FRAMES),
+                blobs: Vec::new(),
+            });
+
+        let dimensions = frame_reader::read_dimensions(input.size, READ_SIZE);
+        if let Some(mask) = state
+            .reader
+            .read(device, queue, input, dimensions)
+            .map_err(|error| BlobTrackHandlerError::Readback(error.to_string()))?
+        {
+            let blobs = blob_tracking::find_blobs(&mask, min_area);
+            state.blobs = if track {
+                state.tracker.update(&blobs, max_distance)
+            } else {
+                // Start fresh IDs when tracking is turned back on.
+                state.tracker = BlobTracker::new(MAX_MISSED_FRAMES);
+                blob_tracking::untracked(&blobs)
+            };
+        }
+
+        Ok(blob_outputs(&state.blobs, blob_index))
+    }
+}
+
+/// The node's outputs for the blob at `blob_index` of `blobs`. Its values are
+/// all `0` if there's no such blob.
+fn blob_outputs(blobs: &[TrackedBlob], blob_index: usize) -> Vec<NodeValue> {
+    let blob = blobs.get(blob_index);
+    let value = |get: fn(&TrackedBlob) -> f32| NodeValue::Float(blob.map_or(0.0, get));
+
+    vec![
+        NodeValue::Float(blobs.len() as f32),
+        NodeValue::Float(blobs.iter().map(|blob| blob.blob.area).sum()),
+        NodeValue::Bool(blob.is_some()),
+        NodeValue::Int(blob.map_or(0, |blob| blob.id as i32)),
+        value(|blob| blob.blob.center[0]),
+        value(|blob| blob.blob.center[1]),
+        value(|blob| blob.blob.area),
+        value(|blob| blob.velocity[0]),
+        value(|blob| blob.velocity[1]),
+        NodeValue::Text(serde_json::to_string(blobs).unwrap_or_default()),
+    ]
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, BlobTrackHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(BlobTrackHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(BlobTrackHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, BlobTrackHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        Some(NodeValue::Float(value)) => Ok(*value as i32),
+        Some(_) => Err(BlobTrackHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(BlobTrackHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, BlobTrackHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(BlobTrackHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(BlobTrackHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, BlobTrackHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(BlobTrackHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(BlobTrackHandlerError::MissingInput { input_name }),
+    }
+}
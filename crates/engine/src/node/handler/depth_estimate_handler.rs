@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use media::frame::{Frame, Uid};
+
+use crate::depth_estimation::{self, DepthMap};
+use crate::frame_reader::{self, FrameReader};
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::inference_worker::InferenceWorker;
+use crate::node_graph::EngineNodeId;
+use crate::texture_blitter::TextureBlitter;
+use crate::upload_stager::UploadStager;
+
+/// The longest side frames are read back at for each choice of the
+/// Resolution input, in order.
+const RESOLUTIONS: [u32; 4] = [128, 256, 384, 512];
+const DEFAULT_RESOLUTION: u32 = 256;
+
+/// The most a new depth map can be blended with the last one. Any more and
+/// depth would barely follow the video.
+const MAX_SMOOTHING: f32 = 0.95;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DepthEstimateHandlerError {
+    #[error("depth estimate input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("depth estimate input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+    #[error("failed to read the frame back from the GPU: {0}")]
+    Readback(String),
+    #[error("failed to upload the depth map: {0}")]
+    Upload(String),
+    #[error("depth estimation stopped: {0}")]
+    Estimation(String),
+}
+
+pub struct NodeDepthEstimateRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+type EstimateResult = Result<DepthMap, String>;
+
+struct EstimatorState {
+    model_path: PathBuf,
+    reader: FrameReader,
+    worker: InferenceWorker<Frame, EstimateResult>,
+    /// The last depth map, blended with the ones before it.
+    depth: Option<DepthMap>,
+    error: Option<String>,
+    stager: UploadStager,
+    /// The depth map stretched to the input's size.
+    output: Option<GpuFrame>,
+    /// Whether `output` is out of date.
+    redraw: bool,
+    invert: bool,
+}
+
+/// Runs Depth Estimate nodes, which turn frames into grayscale depth maps.
+///
+/// Like Face Detect nodes, depth is estimated on a background thread per node
+/// from a small copy of the frame read back from the GPU, so a slow model only
+/// makes the depth map update less often instead of slowing the graph down.
+/// The map is stretched back to the input's size on the GPU each time it
+/// updates. With no model a rough built-in estimator is used.
+pub struct DepthEstimateHandler {
+    state_cache: HashMap<EngineNodeId, EstimatorState>,
+    /// Created when first needed.
+    blitter: Option<TextureBlitter>,
+    format: wgpu::TextureFormat,
+}
+
+impl DepthEstimateHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            blitter: None,
+            format,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeDepthEstimateRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, DepthEstimateHandlerError> {
+        let input = read_frame_input(request.inputs, "Input")?;
+        let model_path = read_file_input(request.inputs, "Model Path")?;
+        let resolution = resolution_input(request.inputs);
+        let smoothing = read_float_input(request.inputs, "Smoothing")?.clamp(0.0, MAX_SMOOTHING);
+        let invert = read_bool_input(request.inputs, "Invert")?;
+
+        if self
+            .state_cache
+            .get(&request.node_id)
+            .is_none_or(|state| state.model_path != model_path)
+        {
+            self.state_cache
+                .insert(request.node_id, EstimatorState::new(model_path));
+        }
+        let state = self
+            .state_cache
+            .get_mut(&request.node_id)
+            .expect("just inserted");
+
+        if let Some(result) = state.worker.latest() {
+            match result {
+                Ok(mut depth) => {
+                    if let Some(previous) = &state.depth {
+                        depth.smooth_towards(previous, smoothing);
+                    }
+                    state.depth = Some(depth);
+                    state.redraw = true;
+                }
+                Err(message) => state.error = Some(message),
+            }
+        }
+        if let Some(message) = &state.error {
+            return Err(DepthEstimateHandlerError::Estimation(message.clone()));
+        }
+
+        let dimensions = frame_reader::read_dimensions(input.size, resolution);
+        if let Some(frame) = state
+            .reader
+            .read(device, queue, input, dimensions)
+            .map_err(|error| DepthEstimateHandlerError::Readback(error.to_string()))?
+            && !state.worker.submit(frame)
+        {
+            state.error = Some("the estimation thread stopped".to_string());
+        }
+
+        if state
+            .output
+            .as_ref()
+            .is_none_or(|output| output.size != input.size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("depth_estimate_output"),
+                size: input.size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            state.output = Some(GpuFrame::new(
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                input.size,
+                Uid::generate_new(),
+            ));
+            state.redraw = true;
+        }
+        if state.invert != invert {
+            state.invert = invert;
+            state.redraw = true;
+        }
+
+        let output = state.output.as_mut().expect("just created");
+        if state.redraw {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("depth_estimate"),
+            });
+            match &state.depth {
+                Some(depth) => {
+                    let frame = depth.to_frame(invert);
+                    let view = state
+                        .stager
+                        .cpu_to_gpu_rgba(
+                            device,
+                            queue,
+                            frame.dimensions().width(),
+                            frame.dimensions().height(),
+                            frame.raw_data(),
+                        )
+                        .map_err(|error| DepthEstimateHandlerError::Upload(format!("{error:?}")))?;
+                    let format = self.format;
+                    self.blitter
+                        .get_or_insert_with(|| TextureBlitter::new(device, format))
+                        .blit(device, &mut encoder, &view, output.view());
+                }
+                // Everything is far away until the first map is ready.
+                None => TextureBlitter::clear(&mut encoder, output.view(), wgpu::Color::BLACK),
+            }
+            queue.submit(Some(encoder.finish()));
+            output.frame_id = Uid::generate_new();
+            state.redraw = false;
+        }
+
+        Ok(vec![
+            NodeValue::Frame(output.clone()),
+            NodeValue::Bool(state.depth.is_some()),
+        ])
+    }
+}
+
+impl EstimatorState {
+    fn new(model_path: &Path) -> Self {
+        let path = model_path.to_path_buf();
+        let mut estimator = None;
+        let worker = InferenceWorker::spawn(move |frame: Frame| {
+            let estimator = estimator
+                .get_or_insert_with(|| depth_estimation::load_estimator(&path))
+                .as_mut()
+                .map_err(|error| error.to_string())?;
+            estimator
+                .estimate(&frame)
+                .map_err(|error| error.to_string())
+        });
+
+        Self {
+            model_path: model_path.to_path_buf(),
+            reader: FrameReader::new(),
+            worker,
+            depth: None,
+            error: None,
+            stager: UploadStager::new(),
+            output: None,
+            redraw: true,
+            invert: false,
+        }
+    }
+}
+
+/// Read the size frames are read back at from the Resolution input, whose
+/// choices are in [RESOLUTIONS] order.
+fn resolution_input(inputs: &HashMap<String, NodeValue>) -> u32 {
+    match inputs.get("Resolution") {
+        Some(NodeValue::Enum(idx)) => RESOLUTIONS.get(*idx).copied().unwrap_or(DEFAULT_RESOLUTION),
+        _ => DEFAULT_RESOLUTION,
+    }
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, DepthEstimateHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(DepthEstimateHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(DepthEstimateHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_file_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a Path, DepthEstimateHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::File(path)) => Ok(path.as_path()),
+        Some(_) => Err(DepthEstimateHandlerError::InvalidInput {
+            input_name,
+            expected: "File",
+        }),
+        None => Err(DepthEstimateHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, DepthEstimateHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(DepthEstimateHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(DepthEstimateHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, DepthEstimateHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(DepthEstimateHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(DepthEstimateHandlerError::MissingInput { input_name }),
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use media::frame::Uid;
+
+use crate::contrast_equalizer::{ContrastEqualizer, Equalization, EqualizeState};
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EqualizeHandlerError {
+    #[error("equalize input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("equalize input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeEqualizeRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+struct NodeState {
+    buffers: EqualizeState,
+    output: Option<GpuFrame>,
+}
+
+/// Runs Equalize nodes, which spread out the brightness of frames with global
+/// histogram equalization or CLAHE (see [ContrastEqualizer]).
+pub struct EqualizeHandler {
+    state_cache: HashMap<EngineNodeId, NodeState>,
+    /// Created when first needed.
+    equalizer: Option<ContrastEqualizer>,
+    format: wgpu::TextureFormat,
+}
+
+impl EqualizeHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            equalizer: None,
+            format,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeEqualizeRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, EqualizeHandlerError> {
+        let input = read_frame_input(request.inputs, "Input")?;
+        let amount = read_float_input(request.inputs, "Amount")?;
+        let equalization = match request.inputs.get("Mode") {
+            Some(NodeValue::Enum(1)) => Equalization::Global,
+            _ => Equalization::Adaptive {
+                tiles: read_float_input(request.inputs, "Tiles")?.round().max(1.0) as u32,
+                clip_limit: read_float_input(request.inputs, "Clip Limit")?,
+            },
+        };
+
+        let state = self
+            .state_cache
+            .entry(request.node_id)
+            .or_insert_with(|| NodeState {
+                buffers: EqualizeState::new(device),
+                output: None,
+            });
+
+        if state
+            .output
+            .as_ref()
+            .is_none_or(|output| output.size != input.size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("equalize_output"),
+                size: input.size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            state.output = Some(GpuFrame::new(
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                input.size,
+                Uid::generate_new(),
+            ));
+        }
+        let output = state.output.as_mut().expect("just created");
+
+        let format = self.format;
+        let equalizer = self
+            .equalizer
+            .get_or_insert_with(|| ContrastEqualizer::new(device, format));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("equalize"),
+        });
+        equalizer.equalize(
+            device,
+            queue,
+            &mut encoder,
+            input.view(),
+            &state.buffers,
+            equalization,
+            amount,
+            output.view(),
+        );
+        queue.submit(Some(encoder.finish()));
+        output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(output.clone())])
+    }
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, EqualizeHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(EqualizeHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(EqualizeHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, EqualizeHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(EqualizeHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(EqualizeHandlerError::MissingInput { input_name }),
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use media::frame::Frame;
+
+use crate::face_detection::{self, FaceDetection};
+use crate::frame_reader::{self, FrameReader};
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::inference_worker::InferenceWorker;
+use crate::node_graph::EngineNodeId;
+
+/// The longest side frames are shrunk to before detection. Detectors work on
+/// small images anyway (BlazeFace takes 128x128), so reading more is wasted.
+const READ_SIZE: u32 = 256;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FaceDetectHandlerError {
+    #[error("face detect input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("face detect input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+    #[error("failed to read the frame back from the GPU: {0}")]
+    Readback(String),
+    #[error("face detection stopped: {0}")]
+    Detection(String),
+}
+
+pub struct NodeFaceDetectRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+type DetectionResult = Result<Vec<FaceDetection>, String>;
+
+struct DetectorState {
+    model_path: PathBuf,
+    reader: FrameReader,
+    worker: InferenceWorker<(Frame, f32), DetectionResult>,
+    /// The faces from the last finished detection, largest first.
+    faces: Vec<FaceDetection>,
+    error: Option<String>,
+}
+
+/// Runs Face Detect nodes, which find faces (and points on them) in frames so
+/// effects can follow a subject.
+///
+/// Detection runs on a background thread per node on a small copy of the frame
+/// read back from the GPU, so the node's outputs lag the frame a little and
+/// update as fast as the detector can keep up rather than every frame. The
+/// model at the Model Path is loaded on that thread; with no model a rough
+/// built-in skin tone detector is used.
+pub struct FaceDetectHandler {
+    state_cache: HashMap<EngineNodeId, DetectorState>,
+}
+
+impl Default for FaceDetectHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FaceDetectHandler {
+    pub fn new() -> Self {
+        Self {
+            state_cache: HashMap::new(),
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeFaceDetectRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, FaceDetectHandlerError> {
+        let input = read_frame_input(request.inputs, "Input")?;
+        let model_path = read_file_input(request.inputs, "Model Path")?;
+        let face_index = read_int_input(request.inputs, "Face Index")?.max(0) as usize;
+        let min_confidence = read_float_input(request.inputs, "Min Confidence")?.clamp(0.0, 1.0);
+
+        if self
+            .state_cache
+            .get(&request.node_id)
+            .is_none_or(|state| state.model_path != model_path)
+        {
+            self.state_cache
+                .insert(request.node_id, DetectorState::new(model_path));
+        }
+        let state = self
+            .state_cache
+            .get_mut(&request.node_id)
+            .expect("just inserted");
+
+        if let Some(result) = state.worker.latest() {
+            match result {
+                Ok(mut faces) => {
+                    faces.sort_by(|a, b| (b.width * b.height).total_cmp(&(a.width * a.height)));
+                    state.faces = faces;
+                }
+                Err(message) => state.error = Some(message),
+            }
+        }
+        if let Some(message) = &state.error {
+            return Err(FaceDetectHandlerError::Detection(message.clone()));
+        }
+
+        let dimensions = frame_reader::read_dimensions(input.size, READ_SIZE);
+        if let Some(frame) = state
+            .reader
+            .read(device, queue, input, dimensions)
+            .map_err(|error| FaceDetectHandlerError::Readback(error.to_string()))?
+            && !state.worker.submit((frame, min_confidence))
+        {
+            state.error = Some("the detection thread stopped".to_string());
+        }
+
+        Ok(face_outputs(&state.faces, face_index))
+    }
+}
+
+impl DetectorState {
+    fn new(model_path: &Path) -> Self {
+        let path = model_path.to_path_buf();
+        let mut detector = None;
+        let worker = InferenceWorker::spawn(move |(frame, min_score): (Frame, f32)| {
+            let detector = detector
+                .get_or_insert_with(|| face_detection::load_detector(&path))
+                .as_mut()
+                .map_err(|error| error.to_string())?;
+            detector
+                .detect(&frame, min_score)
+                .map_err(|error| error.to_string())
+        });
+
+        Self {
+            model_path: model_path.to_path_buf(),
+            reader: FrameReader::new(),
+            worker,
+            faces: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+/// The node's outputs for the face at `face_index` of `faces`. Positions are
+/// all `0.0` if there's no such face.
+fn face_outputs(faces: &[FaceDetection], face_index: usize) -> Vec<NodeValue> {
+    let face = faces.get(face_index);
+    let point = |get: fn(&FaceDetection) -> [f32; 2]| {
+        let [x, y] = face.map_or([0.0; 2], get);
+        [NodeValue::Float(x), NodeValue::Float(y)]
+    };
+
+    let mut outputs = vec![
+        NodeValue::Int(faces.len() as i32),
+        NodeValue::Bool(face.is_some()),
+    ];
+    outputs.extend(point(FaceDetection::center));
+    outputs.extend([
+        NodeValue::Float(face.map_or(0.0, |face| face.width)),
+        NodeValue::Float(face.map_or(0.0, |face| face.height)),
+        NodeValue::Float(face.map_or(0.0, |face| face.score)),
+    ]);
+    outputs.extend(point(|face| face.landmarks.forehead()));
+    outputs.extend(point(|face| face.landmarks.left_eye));
+    outputs.extend(point(|face| face.landmarks.right_eye));
+    outputs.extend(point(|face| face.landmarks.nose));
+    outputs.extend(point(|face| face.landmarks.mouth));
+    outputs.push(NodeValue::Text(
+        serde_json::to_string(faces).unwrap_or_default(),
+    ));
+    outputs
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, FaceDetectHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(FaceDetectHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(FaceDetectHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_file_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a Path, FaceDetectHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::File(path)) => Ok(path.as_path()),
+        Some(_) => Err(FaceDetectHandlerError::InvalidInput {
+            input_name,
+            expected: "File",
+        }),
+        None => Err(FaceDetectHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, FaceDetectHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        Some(NodeValue::Float(value)) => Ok(*value as i32),
+        Some(_) => Err(FaceDetectHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(FaceDetectHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, FaceDetectHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(FaceDetectHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(FaceDetectHandlerError::MissingInput { input_name }),
+    }
+}
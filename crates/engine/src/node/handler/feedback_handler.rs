@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+use crate::texture_blitter::TextureBlitter;
+
+/// The longest delay a Frame Delay node can have. Every frame of delay keeps a
+/// full size texture alive.
+pub const MAX_DELAY_FRAMES: u32 = 120;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FeedbackHandlerError {
+    #[error("feedback input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("feedback input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeFrameDelayRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+pub struct NodeFeedbackRequest<'a> {
+    pub inputs: &'a HashMap<String, NodeValue>,
+    /// The size of the black frame output before anything is fed back.
+    pub fallback_size: wgpu::Extent3d,
+}
+
+struct DelaySlot {
+    view: Arc<wgpu::TextureView>,
+    frame_id: Uid,
+}
+
+/// The frames a Frame Delay node has seen, oldest overwritten first.
+struct DelayLine {
+    size: wgpu::Extent3d,
+    /// Two more slots than the delay, so neither the slot being output nor
+    /// the one fed back from the last execution is overwritten while in use.
+    slots: Vec<DelaySlot>,
+    /// How many frames have been written since the line was (re)created.
+    written: usize,
+    black: GpuFrame,
+}
+
+impl DelayLine {
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        size: wgpu::Extent3d,
+        delay: u32,
+    ) -> Self {
+        let create_view = |label| {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            Arc::new(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+        };
+
+        let slots = (0..delay + 2)
+            .map(|_| DelaySlot {
+                view: create_view("frame_delay_slot"),
+                frame_id: Uid::generate_new(),
+            })
+            .collect();
+
+        let black = GpuFrame {
+            view: create_view("frame_delay_black"),
+            size,
+            frame_id: Uid::generate_new(),
+        };
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frame_delay_clear"),
+        });
+        TextureBlitter::clear(&mut encoder, &black.view, wgpu::Color::BLACK);
+        queue.submit(Some(encoder.finish()));
+
+        Self {
+            size,
+            slots,
+            written: 0,
+            black,
+        }
+    }
+
+    fn delay(&self) -> usize {
+        self.slots.len() - 2
+    }
+}
+
+/// Runs Frame Delay and Feedback node pairs, which feed a frame back into the
+/// graph on the next execution without creating a cycle.
+///
+/// A Frame Delay node copies its input into a history of frames and outputs
+/// the one from `delay` frames ago. A Feedback node outputs what the Frame
+/// Delay node on the same channel output during the previous execution (or a
+/// black frame before there is one), so it can be wired upstream of it.
+pub struct FeedbackHandler {
+    delay_lines: HashMap<EngineNodeId, DelayLine>,
+    /// What each channel's Frame Delay node output in the last execution.
+    fed_back: HashMap<i32, GpuFrame>,
+    /// What each channel's Frame Delay node output in this execution, moved to
+    /// `fed_back` by [Self::finish_execution].
+    pending: HashMap<i32, GpuFrame>,
+    /// Black frames for Feedback nodes with nothing to feed back yet.
+    fallback: Option<GpuFrame>,
+    blitter: Option<TextureBlitter>,
+    format: wgpu::TextureFormat,
+}
+
+impl FeedbackHandler {
+    /// Create a handler whose frame history is stored in `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            delay_lines: HashMap::new(),
+            fed_back: HashMap::new(),
+            pending: HashMap::new(),
+            fallback: None,
+            blitter: None,
+            format,
+        }
+    }
+
+    /// Drop every frame history, so feedback starts again from black.
+    pub fn clear_cache(&mut self) {
+        self.delay_lines.clear();
+        self.fed_back.clear();
+        self.pending.clear();
+    }
+
+    /// Make the frames output by Frame Delay nodes this execution visible to
+    /// Feedback nodes in the next one.
+    pub fn finish_execution(&mut self) {
+        self.fed_back.extend(self.pending.drain());
+    }
+
+    /// Forget the history of Frame Delay nodes that aren't in the graph.
+    pub fn retain_nodes(&mut self, mut keep: impl FnMut(EngineNodeId) -> bool) {
+        self.delay_lines.retain(|node_id, _| keep(*node_id));
+    }
+
+    pub fn execute_frame_delay(
+        &mut self,
+        request: &NodeFrameDelayRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, FeedbackHandlerError> {
+        let input = match request.inputs.get("Input") {
+            Some(NodeValue::Frame(frame)) => frame,
+            Some(_) => {
+                return Err(FeedbackHandlerError::InvalidInput {
+                    input_name: "Input",
+                    expected: "Frame",
+                });
+            }
+            None => {
+                return Err(FeedbackHandlerError::MissingInput {
+                    input_name: "Input",
+                });
+            }
+        };
+        let channel = read_int_input(request.inputs, "Channel")?;
+        let delay = read_int_input(request.inputs, "Delay (frames)")?
+            .clamp(0, MAX_DELAY_FRAMES as i32) as u32;
+        let clear = read_bool_input(request.inputs, "Clear")?;
+
+        let format = self.format;
+        let reusable = self
+            .delay_lines
+            .get(&request.node_id)
+            .is_some_and(|line| line.size == input.size && line.delay() == delay as usize);
+        if !reusable {
+            let line = DelayLine::new(device, queue, format, input.size, delay);
+            self.delay_lines.insert(request.node_id, line);
+        }
+        let line = self
+            .delay_lines
+            .get_mut(&request.node_id)
+            .expect("just inserted");
+
+        let output = if clear {
+            line.written = 0;
+            line.black.clone()
+        } else {
+            let blitter = self
+                .blitter
+                .get_or_insert_with(|| TextureBlitter::new(device, format));
+
+            let slot_count = line.slots.len();
+            let slot = &mut line.slots[line.written % slot_count];
+            slot.frame_id = Uid::generate_new();
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("frame_delay_copy"),
+            });
+            blitter.blit(device, &mut encoder, input.view(), &slot.view);
+            queue.submit(Some(encoder.finish()));
+            line.written += 1;
+
+            delayed_slot(line.written, delay as usize, slot_count)
+                .map(|index| {
+                    let slot = &line.slots[index];
+                    GpuFrame {
+                        view: slot.view.clone(),
+                        size: line.size,
+                        frame_id: slot.frame_id,
+                    }
+                })
+                .unwrap_or_else(|| line.black.clone())
+        };
+
+        self.pending.insert(channel, output.clone());
+        Ok(vec![NodeValue::Frame(output)])
+    }
+
+    pub fn execute_feedback(
+        &mut self,
+        request: &NodeFeedbackRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, FeedbackHandlerError> {
+        let channel = read_int_input(request.inputs, "Channel")?;
+        if let Some(frame) = self.fed_back.get(&channel) {
+            return Ok(vec![NodeValue::Frame(frame.clone())]);
+        }
+
+        let fallback = match &self.fallback {
+            Some(fallback) if fallback.size == request.fallback_size => fallback.clone(),
+            _ => {
+                let line = DelayLine::new(device, queue, self.format, request.fallback_size, 0);
+                self.fallback.insert(line.black).clone()
+            }
+        };
+        Ok(vec![NodeValue::Frame(fallback)])
+    }
+}
+
+/// The slot holding the frame from `delay` frames before the latest one, after
+/// `written` frames were written to `slot_count` slots, or [None] if that
+/// frame hasn't been written.
+fn delayed_slot(written: usize, delay: usize, slot_count: usize) -> Option<usize> {
+    let latest = written.checked_sub(1)?;
+    let index = latest.checked_sub(delay)?;
+    Some(index % slot_count)
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, FeedbackHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        Some(NodeValue::Float(value)) => Ok(*value as i32),
+        Some(_) => Err(FeedbackHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(FeedbackHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, FeedbackHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(FeedbackHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(FeedbackHandlerError::MissingInput { input_name }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- delayed_slot() ---
+
+    #[test]
+    fn test_delayed_slot() {
+        assert_eq!(delayed_slot(0, 0, 2), None);
+        assert_eq!(delayed_slot(1, 0, 2), Some(0));
+        assert_eq!(delayed_slot(2, 3, 5), None);
+        assert_eq!(delayed_slot(4, 3, 5), Some(0));
+        assert_eq!(delayed_slot(9, 3, 5), Some(0));
+    }
+}
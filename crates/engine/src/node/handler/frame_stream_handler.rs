@@ -1,21 +1,42 @@
+use crate::engine_errors::EngineError;
 use crate::engine_outpost::EngineOutpostEvent;
+use crate::frame_interpolator::{FlowQuality, FrameInterpolator};
 use crate::node_graph::EngineNodeId;
 use crate::{gpu_frame::GpuFrame, graph_executor::NodeValue, upload_stager::UploadStager};
 use media::fps::{Fps, consts::FPS_30};
-use media::frame::streams::{FrameStream, FrameStreamError, StillFrameStream, VideoFrameStream};
-use media::frame::{Frame, FromImgFileError};
+use media::frame::color::ToneMapOperator;
+use media::frame::streams::{
+    CachedClip, FrameStream, FrameStreamError, StillFrameStream, VideoFrameStream,
+};
+use media::frame::{
+    ConformPolicy, Dimensions, Frame, FromImgFileError, RescaleMethod, Rotation, Uid,
+};
+use media::playback_stream::{BufferingPolicy, BufferingStats};
 use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::thread;
 use util::channels::ChannelError;
-use util::channels::message_channel::{self, Inbox, Outbox};
+use util::channels::mailbox::{self, Mailbox};
+use util::panic_capture;
 
 use super::timed_stream_handler::TimedStreamHandler;
 
-type LoadResultInbox = Inbox<(
-    NodeFrameStreamKey,
-    Result<Box<dyn FrameStream + Send>, FrameStreamHandlerError>,
-)>;
+type LoadMailbox = Mailbox<
+    (NodeFrameStreamKey, NodeFrameStreamRequest),
+    (
+        NodeFrameStreamKey,
+        Result<Box<dyn FrameStream + Send>, FrameStreamHandlerError>,
+    ),
+>;
+
+/// How far between two frames a remapped source time must fall before the
+/// frames are blended instead of showing the earlier one.
+const REMAP_BLEND_EPSILON: f64 = 1.0 / 64.0;
+
+/// The format of frames interpolated with optical flow, matching uploads.
+const REMAP_OUTPUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
 
 #[derive(Debug, thiserror::Error)]
 pub enum FrameStreamHandlerError {
@@ -47,6 +68,67 @@ pub enum FrameStreamHandlerError {
     TextureUpload { path: PathBuf, error: String },
     #[error("Frame stream is still loading for '{path}'")]
     Loading { path: PathBuf },
+    #[error("Frame stream loader panicked while loading '{path}': {message}")]
+    LoaderPanicked { path: PathBuf, message: String },
+}
+
+/// What video sources do when they reach the end of their loop region.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Default)]
+pub enum LoopMode {
+    /// Jump back to the start of the region.
+    #[default]
+    Loop,
+    /// Play the region backwards, then forwards again.
+    PingPong,
+    /// Stop and hold the last frame of the region.
+    PlayOnce,
+}
+
+/// The part of a video a source node plays, and where it sits on the
+/// project's timeline. The default plays the whole video from the start of the
+/// timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct VideoTrim {
+    /// Seconds into the video the segment starts at (its in-point).
+    pub start_secs: f64,
+    /// Seconds into the video the segment ends at (its out-point), or [None]
+    /// to play to the end.
+    pub end_secs: Option<f64>,
+    /// Seconds into the timeline the segment starts at. The project's loop
+    /// region is in timeline frames, so this is what lines the segment up
+    /// with it.
+    pub offset_secs: f64,
+}
+
+impl VideoTrim {
+    /// The frames (at `fps`) of a `duration` frame video that are played,
+    /// limited to the timeline's `loop_region` if there is one. A loop region
+    /// that misses the segment holds its nearest end.
+    fn clip(
+        &self,
+        fps: f64,
+        duration: usize,
+        loop_region: Option<RangeInclusive<usize>>,
+    ) -> RangeInclusive<usize> {
+        let last = duration.saturating_sub(1);
+        let to_frame = |secs: f64| (secs.max(0.0) * fps).round() as usize;
+
+        let start = to_frame(self.start_secs).min(last);
+        let end = self
+            .end_secs
+            .map_or(last, |end_secs| to_frame(end_secs).saturating_sub(1))
+            .clamp(start, last);
+        let Some(loop_region) = loop_region else {
+            return start..=end;
+        };
+
+        // Timeline frame `offset` shows the segment's first frame.
+        let offset = (self.offset_secs * fps).round() as i64;
+        let to_source = |timeline_frame: usize| {
+            (timeline_frame as i64 - offset + start as i64).clamp(start as i64, end as i64) as usize
+        };
+        to_source(*loop_region.start())..=to_source(*loop_region.end())
+    }
 }
 
 #[derive(Clone, Copy, Hash, Eq, PartialEq)]
@@ -59,6 +141,130 @@ pub struct NodeFrameStreamRequest {
     pub node_id: EngineNodeId,
     pub file_path: PathBuf,
     pub stream_kind: StreamKind,
+    /// How frames are conformed to the project output resolution.
+    pub conform_policy: ConformPolicy,
+    /// How frames are rotated before they're conformed, or [None] to follow
+    /// the stream's rotation metadata.
+    pub rotation: Option<Rotation>,
+    /// How HDR video is tone mapped. Ignored for SDR sources.
+    pub tone_map: ToneMapOperator,
+    /// Show the frame this many seconds into the video instead of playing it.
+    /// Ignored for images.
+    pub source_time: Option<f64>,
+    /// How a [Self::source_time] between two frames is shown.
+    pub interpolation: FrameInterpolation,
+    /// The part of the video that's played. Ignored for images and when
+    /// [Self::source_time] is set.
+    pub trim: VideoTrim,
+}
+
+/// How a remapped video shows a time that falls between two of its frames.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Default)]
+pub enum FrameInterpolation {
+    /// Show the closest frame.
+    Nearest,
+    /// Cross-fade the two frames.
+    #[default]
+    Blend,
+    /// Move the content of both frames part of the way along the motion
+    /// between them (see [FrameInterpolator]), so slow motion stays sharp.
+    OpticalFlow(FlowQuality),
+}
+
+impl FrameInterpolation {
+    /// The mode for the choices of a Time Remap node's `Interpolation` input
+    /// ("Nearest", "Blend", "Optical Flow") and its flow `quality`.
+    pub fn from_choice_index(index: usize, quality: FlowQuality) -> Self {
+        match index {
+            0 => Self::Nearest,
+            2 => Self::OpticalFlow(quality),
+            _ => Self::Blend,
+        }
+    }
+}
+
+/// The GPU resources for remapped videos interpolated with optical flow.
+#[derive(Default)]
+struct FlowTargets {
+    /// Created with the first interpolation.
+    interpolator: Option<FrameInterpolator>,
+    nodes: HashMap<EngineNodeId, FlowNodeTargets>,
+}
+
+#[derive(Default)]
+struct FlowNodeTargets {
+    /// The upload of the later frame (the earlier one uses the shared stager).
+    next_stager: UploadStager,
+    output: Option<(wgpu::Extent3d, Arc<wgpu::TextureView>)>,
+}
+
+impl FlowTargets {
+    /// Upload `next` and draw the frame `amount` of the way from `from` to it
+    /// into the node's output texture.
+    #[allow(clippy::too_many_arguments)]
+    fn interpolate(
+        &mut self,
+        node_id: EngineNodeId,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        from: &wgpu::TextureView,
+        next: &Frame,
+        amount: f64,
+        quality: FlowQuality,
+    ) -> Result<Arc<wgpu::TextureView>, EngineError> {
+        let size = wgpu::Extent3d {
+            width: next.dimensions().width(),
+            height: next.dimensions().height(),
+            depth_or_array_layers: 1,
+        };
+        let targets = self.nodes.entry(node_id).or_default();
+        let to = targets.next_stager.cpu_to_gpu_rgba(
+            device,
+            queue,
+            size.width,
+            size.height,
+            next.raw_data(),
+        )?;
+
+        let output = match &targets.output {
+            Some((output_size, view)) if *output_size == size => view.clone(),
+            _ => {
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("remap_interpolated"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: REMAP_OUTPUT_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                });
+                let view = Arc::new(texture.create_view(&wgpu::TextureViewDescriptor::default()));
+                targets.output.insert((size, view)).1.clone()
+            }
+        };
+
+        let interpolator = self
+            .interpolator
+            .get_or_insert_with(|| FrameInterpolator::new(device, REMAP_OUTPUT_FORMAT));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("remap_interpolate"),
+        });
+        interpolator.interpolate(
+            device,
+            queue,
+            &mut encoder,
+            from,
+            &to,
+            size,
+            amount as f32,
+            quality,
+            &output,
+        );
+        queue.submit(Some(encoder.finish()));
+        Ok(output)
+    }
 }
 
 #[derive(Clone, Hash, Eq, PartialEq)]
@@ -73,9 +279,23 @@ pub struct FrameStreamHandler {
     stream_cache: HashMap<NodeFrameStreamKey, Box<dyn FrameStream + Send>>,
     pending_streams: HashSet<NodeFrameStreamKey>,
     loading_announced: HashSet<NodeFrameStreamKey>,
-    load_request_tx: Outbox<(NodeFrameStreamKey, NodeFrameStreamRequest)>,
-    load_result_rx: LoadResultInbox,
+    /// Sends streams to load to the loader thread and receives them back.
+    load_mailbox: LoadMailbox,
     paused: bool,
+    loop_mode: LoopMode,
+    /// The in/out frames video streams are clipped to, or [None] to play
+    /// whole videos.
+    loop_region: Option<RangeInclusive<usize>>,
+    /// Video streams currently playing backwards in [LoopMode::PingPong].
+    reversed_streams: HashSet<NodeFrameStreamKey>,
+    /// The trim each video stream was last clipped to, and the FPS its clip
+    /// was worked out at.
+    stream_trims: HashMap<NodeFrameStreamKey, (VideoTrim, Fps)>,
+    /// The project resolution every fetched frame is conformed to, if set.
+    output_resolution: Option<Dimensions>,
+    /// How far ahead video streams decode.
+    buffering_policy: BufferingPolicy,
+    flow_targets: FlowTargets,
 }
 
 impl Default for FrameStreamHandler {
@@ -86,13 +306,20 @@ impl Default for FrameStreamHandler {
 
 impl FrameStreamHandler {
     pub fn new() -> Self {
-        let (load_request_rx, load_request_tx) = message_channel::new();
-        let (load_result_rx, load_result_tx) = message_channel::new();
+        let (load_mailbox, loader_mailbox) = mailbox::new();
 
         thread::spawn(move || {
-            while let Ok((key, request)) = load_request_rx.wait() {
-                let result = Self::build_stream(&request);
-                if load_result_tx.send((key, result)).is_err() {
+            while let Ok((key, request)) = loader_mailbox.wait() {
+                // A panic (e.g. from a broken file) only fails this load.
+                let result =
+                    panic_capture::catch("Frame stream loader", || Self::build_stream(&request))
+                        .unwrap_or_else(|worker_panic| {
+                            Err(FrameStreamHandlerError::LoaderPanicked {
+                                path: request.file_path.clone(),
+                                message: worker_panic.message,
+                            })
+                        });
+                if loader_mailbox.send((key, result)).is_err() {
                     break;
                 }
             }
@@ -102,9 +329,15 @@ impl FrameStreamHandler {
             stream_cache: HashMap::new(),
             pending_streams: HashSet::new(),
             loading_announced: HashSet::new(),
-            load_request_tx,
-            load_result_rx,
+            load_mailbox,
             paused: false,
+            loop_mode: LoopMode::default(),
+            loop_region: None,
+            reversed_streams: HashSet::new(),
+            stream_trims: HashMap::new(),
+            output_resolution: None,
+            buffering_policy: BufferingPolicy::default(),
+            flow_targets: FlowTargets::default(),
         }
     }
 
@@ -180,13 +413,331 @@ impl FrameStreamHandler {
         let stream = self.create_stream(request, None)?;
         let any = stream.as_ref() as &dyn std::any::Any;
 
-        if let Some(video_stream) = any.downcast_ref::<VideoFrameStream>() {
-            return Ok(video_stream.native_fps());
+        if let Some(video_stream) = any.downcast_ref::<CachedClip<VideoFrameStream>>() {
+            return Ok(video_stream.inner().native_fps());
         }
 
         Ok(stream.target_fps())
     }
 
+    /// Conform every fetched frame to `resolution` (see [Frame::conform]), or
+    /// pass frames through at their own size if [None].
+    pub fn set_output_resolution(&mut self, resolution: Option<Dimensions>) {
+        self.output_resolution = resolution;
+    }
+
+    /// Have the stream do the scaling part of conforming itself (video
+    /// streams rescale on their decoder thread) so at most rotating, cropping,
+    /// or black bars are left to do after fetching.
+    fn apply_output_resolution(
+        stream: &mut Box<dyn FrameStream + Send>,
+        resolution: Option<Dimensions>,
+        conform_policy: ConformPolicy,
+        rotation: Rotation,
+    ) {
+        let native_dimensions = stream.native_dimensions();
+        let dimensions = match resolution {
+            Some(resolution) => {
+                // Scale as if already rotated, then un-rotate since streams
+                // produce unrotated frames.
+                let rotated = rotation.rotate_dimensions(native_dimensions);
+                let scaled = conform_policy.scaled_dimensions(rotated, resolution);
+                rotation.rotate_dimensions(scaled)
+            }
+            None => native_dimensions,
+        };
+        if stream.dimensions() != dimensions {
+            stream.set_dimensions(dimensions, RescaleMethod::default());
+        }
+    }
+
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    /// Change what video streams do at the end of their loop region.
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode) {
+        if loop_mode == self.loop_mode {
+            return;
+        }
+        self.loop_mode = loop_mode;
+        self.reversed_streams.clear();
+        self.apply_loop_settings_all();
+    }
+
+    pub fn loop_region(&self) -> Option<RangeInclusive<usize>> {
+        self.loop_region.clone()
+    }
+
+    /// Clip every video stream to the `in..=out` frames of `region` (at the
+    /// stream's target FPS), or play whole videos if `region` is [None].
+    pub fn set_loop_region(&mut self, region: Option<RangeInclusive<usize>>) {
+        if region == self.loop_region {
+            return;
+        }
+        self.loop_region = region;
+        self.apply_loop_settings_all();
+    }
+
+    fn apply_loop_settings_all(&mut self) {
+        for (key, stream) in self.stream_cache.iter_mut() {
+            if key.stream_kind == StreamKind::Video {
+                let trim = self
+                    .stream_trims
+                    .get(key)
+                    .map(|(trim, _)| *trim)
+                    .unwrap_or_default();
+                Self::apply_loop_settings(stream, self.loop_mode, self.loop_region.clone(), trim);
+            }
+        }
+    }
+
+    fn apply_loop_settings(
+        stream: &mut Box<dyn FrameStream + Send>,
+        loop_mode: LoopMode,
+        loop_region: Option<RangeInclusive<usize>>,
+        trim: VideoTrim,
+    ) {
+        let fps = stream.target_fps().as_float();
+        let Some(seek_controls) = stream.seek_controls() else {
+            return;
+        };
+
+        // Ping-pong turns around manually (see `advance_ping_pong`), so only
+        // plain looping lets the stream wrap on its own.
+        seek_controls.set_loop(loop_mode == LoopMode::Loop);
+
+        let clip = trim.clip(fps, seek_controls.unclipped_stream_duration(), loop_region);
+        seek_controls.set_clip(clip);
+    }
+
+    /// Clip the video stream for `key` to `trim` if it isn't already (or its
+    /// FPS changed since), starting it over from the segment's in-point.
+    fn apply_trim(&mut self, key: &NodeFrameStreamKey, trim: VideoTrim) {
+        let Some(stream) = self.stream_cache.get_mut(key) else {
+            return;
+        };
+        let fps = stream.target_fps();
+        if self.stream_trims.get(key) == Some(&(trim, fps)) {
+            return;
+        }
+        self.stream_trims.insert(key.clone(), (trim, fps));
+        self.reversed_streams.remove(key);
+
+        Self::apply_loop_settings(stream, self.loop_mode, self.loop_region.clone(), trim);
+        if let Some(seek_controls) = stream.seek_controls() {
+            let start = *seek_controls.clip().start();
+            if let Err(err) = seek_controls.seek_playhead(start) {
+                util::debug_log_warning!(
+                    "Failed to seek video '{}' to its in-point: {err}",
+                    key.file_path.display()
+                );
+            }
+        }
+    }
+
+    /// Drive a [LoopMode::PingPong] video stream backwards. Streams can't play
+    /// in reverse, so while reversed the stream is kept paused and its playhead
+    /// is moved back one frame per fetch (a paused stream fetches the frame at
+    /// its playhead). Called before fetching; see [Self::finish_ping_pong].
+    fn advance_ping_pong(
+        key: &NodeFrameStreamKey,
+        stream: &mut Box<dyn FrameStream + Send>,
+        reversed_streams: &HashSet<NodeFrameStreamKey>,
+    ) -> Result<(), FrameStreamError> {
+        if !reversed_streams.contains(key) {
+            return Ok(());
+        }
+
+        // Playback was resumed for the node this tick; keep it held.
+        stream.pause();
+        let Some(seek_controls) = stream.seek_controls() else {
+            return Ok(());
+        };
+        let playhead = seek_controls.playhead();
+        if playhead > *seek_controls.clip().start() {
+            seek_controls.seek_playhead(playhead - 1)?;
+        }
+        Ok(())
+    }
+
+    /// Turn a [LoopMode::PingPong] video stream around if the frame that was
+    /// just fetched was the first or last frame of its clip.
+    fn finish_ping_pong(
+        key: &NodeFrameStreamKey,
+        stream: &mut Box<dyn FrameStream + Send>,
+        reversed_streams: &mut HashSet<NodeFrameStreamKey>,
+    ) -> Result<(), FrameStreamError> {
+        let paused = stream.is_paused();
+        let Some(seek_controls) = stream.seek_controls() else {
+            return Ok(());
+        };
+        let playhead = seek_controls.playhead();
+        let clip = seek_controls.clip();
+
+        if reversed_streams.contains(key) {
+            if playhead == *clip.start() {
+                reversed_streams.remove(key);
+                // Don't show the first frame twice.
+                seek_controls.seek_playhead(playhead + 1)?;
+                stream.play();
+            }
+        } else if paused && playhead == *clip.end() && clip.start() != clip.end() {
+            // The stream paused itself on its last frame.
+            reversed_streams.insert(key.clone());
+        }
+        Ok(())
+    }
+
+    /// Move every playing stream `frames` frames along (see
+    /// [TimedStreamHandler::skip_frames]). Reversed [LoopMode::PingPong]
+    /// streams move back instead.
+    pub fn skip_frames(&mut self, frames: usize) {
+        if self.paused {
+            return;
+        }
+        for (key, stream) in self.stream_cache.iter_mut() {
+            if !self.reversed_streams.contains(key) {
+                Self::stream_skip(stream, frames);
+                continue;
+            }
+            let Some(seek_controls) = stream.seek_controls() else {
+                continue;
+            };
+            let playhead = seek_controls
+                .playhead()
+                .saturating_sub(frames)
+                .max(*seek_controls.clip().start());
+            if let Err(err) = seek_controls.seek_playhead(playhead) {
+                util::debug_log_warning!(
+                    "Failed to skip video '{}': {err}",
+                    key.file_path.display()
+                );
+            }
+        }
+    }
+
+    /// Move every video stream `delta` frames from its playhead (clamped to
+    /// its clip). Meant to be used while paused, so the next fetch returns the
+    /// new frame.
+    pub fn step_video_streams(&mut self, delta: isize) {
+        for (key, stream) in self.stream_cache.iter_mut() {
+            if key.stream_kind != StreamKind::Video {
+                continue;
+            }
+            let Some(seek_controls) = stream.seek_controls() else {
+                continue;
+            };
+
+            let playhead = seek_controls.playhead().saturating_add_signed(delta);
+            if let Err(err) = seek_controls.seek_playhead(playhead) {
+                util::debug_log_warning!(
+                    "Failed to step video '{}': {err}",
+                    key.file_path.display()
+                );
+            }
+        }
+    }
+
+    /// Move every video stream to `frame` (clamped to its clip). Meant to be
+    /// used while paused, so the next fetch returns that frame.
+    pub fn seek_video_streams(&mut self, frame: usize) {
+        for (key, stream) in self.stream_cache.iter_mut() {
+            if key.stream_kind != StreamKind::Video {
+                continue;
+            }
+            let Some(seek_controls) = stream.seek_controls() else {
+                continue;
+            };
+
+            let clip = seek_controls.clip();
+            let playhead = frame.clamp(*clip.start(), *clip.end());
+            if let Err(err) = seek_controls.seek_playhead(playhead) {
+                util::debug_log_warning!(
+                    "Failed to seek video '{}': {err}",
+                    key.file_path.display()
+                );
+            }
+        }
+    }
+
+    /// Change how far ahead every video stream decodes.
+    pub fn set_buffering_policy(&mut self, buffering_policy: BufferingPolicy) {
+        self.buffering_policy = buffering_policy;
+        for stream in self.stream_cache.values_mut() {
+            stream.set_buffering_policy(buffering_policy);
+        }
+    }
+
+    /// How each node's stream buffer is doing, for streams that buffer.
+    pub fn buffering_stats(&self) -> HashMap<EngineNodeId, BufferingStats> {
+        self.stream_cache
+            .iter()
+            .filter_map(|(key, stream)| Some((key.node_id, stream.buffering_stats()?)))
+            .collect()
+    }
+
+    /// The index of the last frame fetched from the video stream for
+    /// `request`, and the FPS it's played at. [None] if the stream hasn't been
+    /// created yet.
+    pub fn video_position(&mut self, request: &NodeFrameStreamRequest) -> Option<(usize, Fps)> {
+        let key = NodeFrameStreamKey {
+            node_id: request.node_id,
+            file_path: request.file_path.clone(),
+            stream_kind: request.stream_kind,
+        };
+        let stream = self.stream_cache.get_mut(&key)?;
+        let fps = stream.target_fps();
+        let paused = stream.is_paused();
+        let seek_controls = stream.seek_controls()?;
+
+        // The playhead is the *next* frame to fetch, which is only the frame
+        // on screen while paused.
+        let playhead = seek_controls.playhead();
+        let clip = seek_controls.clip();
+        let frame = if paused {
+            playhead
+        } else if playhead == *clip.start() {
+            *clip.end()
+        } else {
+            playhead - 1
+        };
+
+        Some((frame, fps))
+    }
+
+    /// The frames the video stream for `request` plays (its loop region,
+    /// clamped to the video). [None] if the stream hasn't been created yet.
+    pub fn video_clip(
+        &mut self,
+        request: &NodeFrameStreamRequest,
+    ) -> Option<RangeInclusive<usize>> {
+        let key = NodeFrameStreamKey {
+            node_id: request.node_id,
+            file_path: request.file_path.clone(),
+            stream_kind: request.stream_kind,
+        };
+        Some(self.stream_cache.get_mut(&key)?.seek_controls()?.clip())
+    }
+
+    /// Drop the streams whose worker panicked (and was reported). Any call
+    /// made on a stream after that does nothing, so a stream can stop from
+    /// any call site, not just a fetch. A new stream is created the next time
+    /// its node runs.
+    fn remove_gone_streams(&mut self) {
+        let gone: Vec<NodeFrameStreamKey> = self
+            .stream_cache
+            .iter()
+            .filter(|(_, stream)| stream.is_worker_gone())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in gone {
+            self.stream_cache.remove(&key);
+            self.stream_trims.remove(&key);
+            self.reversed_streams.remove(&key);
+        }
+    }
+
     /// Single API for both image and video stream creation with explicit stream kind.
     fn create_stream(
         &mut self,
@@ -194,6 +745,7 @@ impl FrameStreamHandler {
         mut emit_event: Option<&mut dyn FnMut(EngineOutpostEvent)>,
     ) -> Result<&mut Box<dyn FrameStream + Send>, FrameStreamHandlerError> {
         self.poll_completed_streams();
+        self.remove_gone_streams();
 
         let key = NodeFrameStreamKey {
             node_id: request.node_id,
@@ -211,6 +763,7 @@ impl FrameStreamHandler {
             .collect();
         for stale_key in stale_keys {
             self.stream_cache.remove(&stale_key);
+            self.stream_trims.remove(&stale_key);
         }
 
         self.pending_streams
@@ -242,9 +795,15 @@ impl FrameStreamHandler {
                 node_id: request.node_id,
                 file_path: request.file_path.clone(),
                 stream_kind: request.stream_kind,
+                conform_policy: request.conform_policy,
+                rotation: request.rotation,
+                tone_map: request.tone_map,
+                source_time: request.source_time,
+                interpolation: request.interpolation,
+                trim: request.trim,
             };
 
-            let _ = self.load_request_tx.send((key, request));
+            let _ = self.load_mailbox.send((key, request));
 
             return Err(FrameStreamHandlerError::Loading { path: loading_path });
         }
@@ -257,12 +816,23 @@ impl FrameStreamHandler {
 
     fn poll_completed_streams(&mut self) {
         loop {
-            match self.load_result_rx.check_non_blocking() {
+            match self.load_mailbox.check_non_blocking() {
                 Ok(Some((key, result))) => {
                     self.pending_streams.remove(&key);
                     self.loading_announced.remove(&key);
 
                     if let Ok(mut stream) = result {
+                        stream.set_buffering_policy(self.buffering_policy);
+                        if key.stream_kind == StreamKind::Video {
+                            // Trimmed on its first fetch (see `apply_trim`).
+                            self.stream_trims.remove(&key);
+                            Self::apply_loop_settings(
+                                &mut stream,
+                                self.loop_mode,
+                                self.loop_region.clone(),
+                                VideoTrim::default(),
+                            );
+                        }
                         if self.paused {
                             stream.pause();
                         } else {
@@ -292,14 +862,130 @@ impl FrameStreamHandler {
         upload_stager: &mut UploadStager,
         emit_event: &mut dyn FnMut(EngineOutpostEvent),
     ) -> Result<Vec<NodeValue>, FrameStreamHandlerError> {
-        let stream = self.create_stream(request, Some(emit_event))?;
+        self.fetch_and_upload(request, device, queue, upload_stager, emit_event, false)
+            .map(|(outputs, _)| outputs)
+    }
 
-        let frame = stream
-            .fetch()
-            .map_err(|source| FrameStreamHandlerError::FetchStream {
-                path: request.file_path.clone(),
-                source,
-            })?;
+    /// Same as [Self::execute_handler], but also returns a copy of the CPU
+    /// frame so it can be used by the CPU backend.
+    pub fn execute_handler_keep_cpu_frame(
+        &mut self,
+        request: &NodeFrameStreamRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        upload_stager: &mut UploadStager,
+        emit_event: &mut dyn FnMut(EngineOutpostEvent),
+    ) -> Result<(Vec<NodeValue>, Frame), FrameStreamHandlerError> {
+        let (outputs, frame) =
+            self.fetch_and_upload(request, device, queue, upload_stager, emit_event, true)?;
+        Ok((
+            outputs,
+            frame.expect("The CPU frame is kept when requested."),
+        ))
+    }
+
+    fn fetch_and_upload(
+        &mut self,
+        request: &NodeFrameStreamRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        upload_stager: &mut UploadStager,
+        emit_event: &mut dyn FnMut(EngineOutpostEvent),
+        keep_cpu_frame: bool,
+    ) -> Result<(Vec<NodeValue>, Option<Frame>), FrameStreamHandlerError> {
+        let ping_pong = self.loop_mode == LoopMode::PingPong
+            && !self.paused
+            && request.stream_kind == StreamKind::Video
+            && request.source_time.is_none();
+        let key = NodeFrameStreamKey {
+            node_id: request.node_id,
+            file_path: request.file_path.clone(),
+            stream_kind: request.stream_kind,
+        };
+        let fetch_error = |source| FrameStreamHandlerError::FetchStream {
+            path: request.file_path.clone(),
+            source,
+        };
+
+        self.create_stream(request, Some(&mut *emit_event))?;
+        if request.stream_kind == StreamKind::Video && request.source_time.is_none() {
+            self.apply_trim(&key, request.trim);
+        }
+        let stream = self
+            .stream_cache
+            .get_mut(&key)
+            .expect("stream created above");
+
+        stream.set_tone_map_operator(request.tone_map);
+
+        let rotation = request.rotation.unwrap_or_else(|| stream.native_rotation());
+        Self::apply_output_resolution(
+            stream,
+            self.output_resolution,
+            request.conform_policy,
+            rotation,
+        );
+
+        if ping_pong {
+            Self::advance_ping_pong(&key, stream, &self.reversed_streams).map_err(fetch_error)?;
+        }
+
+        let fetched = match request.source_time {
+            Some(source_time) => Self::fetch_remapped(stream, source_time, request.interpolation),
+            None => stream.fetch().map(|frame| (frame, None)),
+        };
+        // Reported even if the fetch failed, since that's when recovering
+        // didn't work.
+        for recovery in stream.take_recoveries() {
+            emit_event(EngineOutpostEvent::StreamRecovered {
+                node_id: request.node_id,
+                frame: recovery.playhead,
+                error: recovery.error.to_string(),
+                recovered: recovery.recovered,
+            });
+        }
+        let (fetched, next) = match fetched {
+            Ok(fetched) => fetched,
+            Err(source) => {
+                if source.is_worker_gone() {
+                    self.remove_gone_streams();
+                }
+                return Err(fetch_error(source));
+            }
+        };
+
+        if ping_pong {
+            Self::finish_ping_pong(&key, stream, &mut self.reversed_streams)
+                .map_err(fetch_error)?;
+        }
+
+        let conform = |frame: &Frame| {
+            Self::conform_frame(
+                frame,
+                rotation,
+                self.output_resolution,
+                request.conform_policy,
+            )
+        };
+        let processed = conform(&fetched);
+        let frame = processed.as_ref().unwrap_or(&fetched);
+
+        // A remapped time between two frames: either blend them here, or
+        // upload both and interpolate on the GPU below.
+        let next_processed = next.as_ref().map(|(next, _)| conform(next));
+        let between = next
+            .as_ref()
+            .zip(next_processed.as_ref())
+            .map(|((next, amount), processed)| (processed.as_ref().unwrap_or(next), *amount));
+        let flow_quality = match request.interpolation {
+            FrameInterpolation::OpticalFlow(quality) => Some(quality),
+            _ => None,
+        };
+        let blended = match between {
+            Some((next, amount)) if flow_quality.is_none() => Some(frame.mix(next, amount)),
+            _ => None,
+        };
+        let frame = blended.as_ref().unwrap_or(frame);
 
         let width = frame.dimensions().width();
         let height = frame.dimensions().height();
@@ -311,19 +997,115 @@ impl FrameStreamHandler {
                 error: format!("{error:?}"),
             })?;
 
-        let gpu_frame = GpuFrame::new(
-            texture_view,
-            wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1,
-            },
-            frame.uid(),
-        );
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let (gpu_frame, cpu_frame) = match (between, flow_quality) {
+            (Some((next, amount)), Some(quality)) => {
+                let view = self
+                    .flow_targets
+                    .interpolate(
+                        request.node_id,
+                        device,
+                        queue,
+                        &texture_view,
+                        next,
+                        amount,
+                        quality,
+                    )
+                    .map_err(|error| FrameStreamHandlerError::TextureUpload {
+                        path: request.file_path.clone(),
+                        error: format!("{error:?}"),
+                    })?;
+                let gpu_frame = GpuFrame {
+                    view,
+                    size,
+                    frame_id: Uid::generate_new(),
+                };
+                // The CPU backend can't run the flow, so its copy is blended.
+                (gpu_frame, keep_cpu_frame.then(|| frame.mix(next, amount)))
+            }
+            _ => (
+                GpuFrame::new(texture_view, size, frame.uid()),
+                keep_cpu_frame.then(|| frame.clone()),
+            ),
+        };
 
-        stream.recycle(frame);
+        stream.recycle(fetched);
+        if let Some((next, _)) = next {
+            stream.recycle(next);
+        }
 
-        Ok(vec![NodeValue::Frame(gpu_frame)])
+        Ok((vec![NodeValue::Frame(gpu_frame)], cpu_frame))
+    }
+
+    /// Rotate and conform a fetched frame to the output resolution, or [None]
+    /// if it's already right. Streams already do the scaling, so this usually
+    /// only rotates, crops, or adds black bars.
+    fn conform_frame(
+        frame: &Frame,
+        rotation: Rotation,
+        output_resolution: Option<Dimensions>,
+        conform_policy: ConformPolicy,
+    ) -> Option<Frame> {
+        let mut processed = None;
+        if rotation != Rotation::None {
+            processed = Some(frame.rotate(rotation));
+        }
+        if let Some(resolution) = output_resolution {
+            let current = processed.as_ref().unwrap_or(frame);
+            if current.dimensions() != resolution {
+                let conformed =
+                    current.conform(resolution, conform_policy, RescaleMethod::default());
+                processed = Some(conformed);
+            }
+        }
+        processed
+    }
+
+    /// Fetch the frame `source_time` seconds into `stream` without letting it
+    /// play. When the time falls between two frames (and `interpolation`
+    /// isn't [FrameInterpolation::Nearest]) the next frame is fetched too,
+    /// along with how far towards it the time is.
+    fn fetch_remapped(
+        stream: &mut Box<dyn FrameStream + Send>,
+        source_time: f64,
+        interpolation: FrameInterpolation,
+    ) -> Result<(Frame, Option<(Frame, f64)>), FrameStreamError> {
+        if !stream.is_paused() {
+            stream.pause();
+        }
+        let fps = stream.target_fps().as_float();
+
+        let Some(seek_controls) = stream.seek_controls() else {
+            return Ok((stream.fetch()?, None));
+        };
+        let clip = seek_controls.clip();
+        let mut position =
+            (source_time.max(0.0) * fps).clamp(*clip.start() as f64, *clip.end() as f64);
+        if interpolation == FrameInterpolation::Nearest {
+            position = position.round();
+        }
+        let index = position.floor() as usize;
+        let blend = position - index as f64;
+
+        if seek_controls.playhead() != index {
+            seek_controls.seek_playhead(index)?;
+        }
+        let frame = stream.fetch()?;
+        if blend < REMAP_BLEND_EPSILON || index >= *clip.end() {
+            return Ok((frame, None));
+        }
+
+        stream
+            .seek_controls()
+            .expect("the stream is seekable")
+            .seek_playhead(index + 1)?;
+        let next = stream.fetch()?;
+        Ok((frame, Some((next, blend))))
     }
 
     fn build_stream(
@@ -333,6 +1115,7 @@ impl FrameStreamHandler {
             StreamKind::Video => {
                 let mut video_request = VideoFrameStream::builder()
                     .set_loop(true)
+                    .tone_map(request.tone_map)
                     .build(&request.file_path);
 
                 let stream = video_request
@@ -346,7 +1129,21 @@ impl FrameStreamHandler {
                         source,
                     })?;
 
-                Ok(Box::new(stream))
+                let color = stream.native_color();
+                if color.is_hdr() {
+                    util::debug_log_info!(
+                        "Tone mapping HDR video ({:?}, {:?}) with {}: {}",
+                        color.transfer,
+                        color.primaries,
+                        request.tone_map.name(),
+                        request.file_path.display()
+                    );
+                }
+
+                // Short clips are decoded once and played back from a frame
+                // cache after that (shared by every node playing the file).
+                let cache_key = (&request.file_path, request.tone_map);
+                Ok(Box::new(CachedClip::new(stream, cache_key)))
             }
             StreamKind::Image => {
                 let frame = Frame::from_img_file(&request.file_path).map_err(|source| {
@@ -408,6 +1205,9 @@ impl TimedStreamHandler for FrameStreamHandler {
 
     fn clear_stream_cache(&mut self) {
         self.stream_cache.clear();
+        self.reversed_streams.clear();
+        self.stream_trims.clear();
+        self.flow_targets = FlowTargets::default();
         self.pending_streams.clear();
         self.loading_announced.clear();
     }
@@ -423,4 +1223,45 @@ impl TimedStreamHandler for FrameStreamHandler {
     fn stream_set_target_fps(stream: &mut Self::Stream, target_fps: Fps) {
         stream.set_target_fps(target_fps);
     }
+
+    fn stream_skip(stream: &mut Self::Stream, frames: usize) {
+        for _ in 0..frames {
+            if stream.is_paused() {
+                break;
+            }
+            // Errors are reported by the next real fetch.
+            _ = stream.fetch();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- VideoTrim::clip() ---
+
+    #[test]
+    fn test_clip() {
+        assert_eq!(VideoTrim::default().clip(10.0, 100, None), 0..=99);
+        assert_eq!(VideoTrim::default().clip(10.0, 100, Some(5..=20)), 5..=20);
+
+        let trim = VideoTrim {
+            start_secs: 2.0,
+            end_secs: Some(5.0),
+            offset_secs: 1.0,
+        };
+        assert_eq!(trim.clip(10.0, 100, None), 20..=49);
+        assert_eq!(trim.clip(10.0, 100, Some(15..=30)), 25..=40);
+        assert_eq!(trim.clip(10.0, 100, Some(30..=60)), 40..=49);
+        assert_eq!(trim.clip(10.0, 100, Some(0..=5)), 20..=20);
+
+        // Out-points past the end of the video are clamped to it.
+        let trim = VideoTrim {
+            start_secs: 8.0,
+            end_secs: Some(20.0),
+            offset_secs: 0.0,
+        };
+        assert_eq!(trim.clip(10.0, 100, None), 80..=99);
+    }
 }
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::layout_compositor::{
+    Corner, Fit, Layout, LayoutCompositor, LayoutStyle, MAX_LAYOUT_FRAMES, layout_cells,
+};
+use crate::node_graph::EngineNodeId;
+
+/// The Layout node's frame inputs, in order. Only the first two have to be
+/// connected.
+const FRAME_INPUTS: [&str; MAX_LAYOUT_FRAMES] = [
+    "Input 1", "Input 2", "Input 3", "Input 4", "Input 5", "Input 6", "Input 7", "Input 8",
+    "Input 9",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum LayoutHandlerError {
+    #[error("layout input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("layout input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeLayoutRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+/// Runs Layout nodes, which arrange several frames into a grid or a
+/// picture-in-picture layout the size of the first frame.
+///
+/// Unconnected inputs are skipped, so the connected ones fill the layout in
+/// order.
+pub struct LayoutHandler {
+    output_cache: HashMap<EngineNodeId, GpuFrame>,
+    /// Created when first needed.
+    compositor: Option<LayoutCompositor>,
+    format: wgpu::TextureFormat,
+}
+
+impl LayoutHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            output_cache: HashMap::new(),
+            compositor: None,
+            format,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.output_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeLayoutRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, LayoutHandlerError> {
+        let mut frames = Vec::with_capacity(MAX_LAYOUT_FRAMES);
+        for (index, input_name) in FRAME_INPUTS.into_iter().enumerate() {
+            match request.inputs.get(input_name) {
+                Some(NodeValue::Frame(frame)) => frames.push(frame),
+                Some(_) => {
+                    return Err(LayoutHandlerError::InvalidInput {
+                        input_name,
+                        expected: "Frame",
+                    });
+                }
+                None if index < 2 => return Err(LayoutHandlerError::MissingInput { input_name }),
+                None => {}
+            }
+        }
+
+        let layout = match request.inputs.get("Layout") {
+            Some(NodeValue::Enum(1)) => Layout::PictureInPicture {
+                size: read_float_input(request.inputs, "Inset Size")?,
+                corner: match request.inputs.get("Inset Corner") {
+                    Some(NodeValue::Enum(0)) => Corner::TopLeft,
+                    Some(NodeValue::Enum(1)) => Corner::TopRight,
+                    Some(NodeValue::Enum(2)) => Corner::BottomLeft,
+                    _ => Corner::BottomRight,
+                },
+            },
+            _ => Layout::Grid {
+                columns: read_int_input(request.inputs, "Columns")?.max(0) as u32,
+            },
+        };
+        let style = LayoutStyle {
+            fit: match request.inputs.get("Fit") {
+                Some(NodeValue::Enum(1)) => Fit::Contain,
+                Some(NodeValue::Enum(2)) => Fit::Cover,
+                _ => Fit::Stretch,
+            },
+            border_width: read_float_input(request.inputs, "Border Width")?,
+            border_color: read_pixel_input(request.inputs, "Border Color")?,
+            background: read_pixel_input(request.inputs, "Background")?,
+        };
+        let cells = layout_cells(
+            layout,
+            frames.len(),
+            read_float_input(request.inputs, "Gap")?,
+        );
+
+        let size = frames[0].size;
+        let format = self.format;
+        if self
+            .output_cache
+            .get(&request.node_id)
+            .is_none_or(|output| output.size != size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("layout_output"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let output = GpuFrame::new(
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                size,
+                Uid::generate_new(),
+            );
+            self.output_cache.insert(request.node_id, output);
+        }
+        let output = self
+            .output_cache
+            .get_mut(&request.node_id)
+            .expect("just inserted");
+
+        let compositor = self
+            .compositor
+            .get_or_insert_with(|| LayoutCompositor::new(device, format));
+        let frames: Vec<_> = frames
+            .iter()
+            .map(|frame| (frame.view(), frame.size))
+            .collect();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("layout"),
+        });
+        compositor.draw(
+            device,
+            queue,
+            &mut encoder,
+            &frames,
+            &cells,
+            style,
+            output.view(),
+            size,
+        );
+        queue.submit(Some(encoder.finish()));
+        output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(output.clone())])
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, LayoutHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(LayoutHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(LayoutHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, LayoutHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        Some(_) => Err(LayoutHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(LayoutHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_pixel_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<[f32; 4], LayoutHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Pixel(value)) => Ok(*value),
+        Some(_) => Err(LayoutHandlerError::InvalidInput {
+            input_name,
+            expected: "Pixel",
+        }),
+        None => Err(LayoutHandlerError::MissingInput { input_name }),
+    }
+}
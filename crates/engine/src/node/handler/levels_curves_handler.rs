@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::lut_renderer::{Lut, LutRenderer};
+use crate::node_graph::EngineNodeId;
+use crate::tone_curve::{ColorAdjustment, Levels, ToneCurve, ToneCurveError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LevelsCurvesHandlerError {
+    #[error("levels and curves input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("levels and curves input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+    #[error("levels and curves input '{input_name}' isn't a valid curve: {source}")]
+    InvalidCurve {
+        input_name: &'static str,
+        #[source]
+        source: ToneCurveError,
+    },
+}
+
+pub struct NodeLevelsCurvesRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+struct LutState {
+    lut: Lut,
+    /// What `lut` was last baked from.
+    adjustment: Option<ColorAdjustment>,
+    output: Option<GpuFrame>,
+}
+
+/// Runs Levels and Curves nodes, which remap each color channel through
+/// either a levels adjustment or a set of curves.
+///
+/// Either way the adjustment is baked into a 1D lookup table on the CPU (only
+/// when it changes), so drawing it is a single lookup per channel no matter
+/// how many curve points there are.
+pub struct LevelsCurvesHandler {
+    state_cache: HashMap<EngineNodeId, LutState>,
+    /// Created when first needed.
+    renderer: Option<LutRenderer>,
+    format: wgpu::TextureFormat,
+}
+
+impl LevelsCurvesHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            renderer: None,
+            format,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeLevelsCurvesRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, LevelsCurvesHandlerError> {
+        let input = read_frame_input(request.inputs, "Input")?;
+        let adjustment = read_adjustment(request.inputs)?;
+
+        let state = self
+            .state_cache
+            .entry(request.node_id)
+            .or_insert_with(|| LutState {
+                lut: Lut::new(device),
+                adjustment: None,
+                output: None,
+            });
+
+        if state.adjustment.as_ref() != Some(&adjustment) {
+            state.lut.write(queue, &adjustment.bake_lut());
+            state.adjustment = Some(adjustment);
+        }
+
+        if state
+            .output
+            .as_ref()
+            .is_none_or(|output| output.size != input.size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("levels_curves_output"),
+                size: input.size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            state.output = Some(GpuFrame::new(
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                input.size,
+                Uid::generate_new(),
+            ));
+        }
+        let output = state.output.as_mut().expect("just created");
+
+        let format = self.format;
+        let renderer = self
+            .renderer
+            .get_or_insert_with(|| LutRenderer::new(device, format));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("levels_curves"),
+        });
+        renderer.apply(
+            device,
+            &mut encoder,
+            input.view(),
+            &state.lut,
+            output.view(),
+        );
+        queue.submit(Some(encoder.finish()));
+        output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(output.clone())])
+    }
+}
+
+/// Read the adjustment the node's inputs describe. The Mode input picks
+/// between levels (`0`) and curves (`1`).
+fn read_adjustment(
+    inputs: &HashMap<String, NodeValue>,
+) -> Result<ColorAdjustment, LevelsCurvesHandlerError> {
+    let curves = matches!(inputs.get("Mode"), Some(NodeValue::Enum(1)));
+    if curves {
+        return Ok(ColorAdjustment::Curves {
+            master: read_curve_input(inputs, "Curve")?,
+            red: read_curve_input(inputs, "Red Curve")?,
+            green: read_curve_input(inputs, "Green Curve")?,
+            blue: read_curve_input(inputs, "Blue Curve")?,
+        });
+    }
+
+    let gamma = read_float_input(inputs, "Gamma")?;
+    Ok(ColorAdjustment::Levels(Levels {
+        input_black: read_float_input(inputs, "Input Black")?,
+        input_white: read_float_input(inputs, "Input White")?,
+        gamma: [
+            gamma * read_float_input(inputs, "Red Gamma")?,
+            gamma * read_float_input(inputs, "Green Gamma")?,
+            gamma * read_float_input(inputs, "Blue Gamma")?,
+        ],
+        output_black: read_float_input(inputs, "Output Black")?,
+        output_white: read_float_input(inputs, "Output White")?,
+    }))
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, LevelsCurvesHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(LevelsCurvesHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(LevelsCurvesHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, LevelsCurvesHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(LevelsCurvesHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(LevelsCurvesHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_curve_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<ToneCurve, LevelsCurvesHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Text(text)) => text
+            .parse()
+            .map_err(|source| LevelsCurvesHandlerError::InvalidCurve { input_name, source }),
+        Some(_) => Err(LevelsCurvesHandlerError::InvalidInput {
+            input_name,
+            expected: "Curve",
+        }),
+        None => Err(LevelsCurvesHandlerError::MissingInput { input_name }),
+    }
+}
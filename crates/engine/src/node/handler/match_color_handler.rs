@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::histogram_matcher::{HistogramMatcher, MatchMethod, MatchState};
+use crate::node_graph::EngineNodeId;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MatchColorHandlerError {
+    #[error("match color input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("match color input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeMatchColorRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+struct ColorMatchState {
+    buffers: MatchState,
+    output: Option<GpuFrame>,
+}
+
+/// Runs Match Color nodes, which recolor frames so their color distribution
+/// matches a reference frame's.
+///
+/// The histograms of both frames and the lookup tables between them are built
+/// on the GPU in the same submission as the frame they're for (see
+/// [HistogramMatcher]), so the match follows both inputs as they change.
+pub struct MatchColorHandler {
+    state_cache: HashMap<EngineNodeId, ColorMatchState>,
+    /// Created when first needed.
+    matcher: Option<HistogramMatcher>,
+    format: wgpu::TextureFormat,
+}
+
+impl MatchColorHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            matcher: None,
+            format,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeMatchColorRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, MatchColorHandlerError> {
+        let input = read_frame_input(request.inputs, "Input")?;
+        let reference = read_frame_input(request.inputs, "Reference")?;
+        let method = match request.inputs.get("Method") {
+            Some(NodeValue::Enum(1)) => MatchMethod::MeanDeviation,
+            _ => MatchMethod::Histogram,
+        };
+        let amount = read_float_input(request.inputs, "Amount")?;
+
+        let state = self
+            .state_cache
+            .entry(request.node_id)
+            .or_insert_with(|| ColorMatchState {
+                buffers: MatchState::new(device),
+                output: None,
+            });
+
+        if state
+            .output
+            .as_ref()
+            .is_none_or(|output| output.size != input.size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("match_color_output"),
+                size: input.size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            state.output = Some(GpuFrame::new(
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                input.size,
+                Uid::generate_new(),
+            ));
+        }
+        let output = state.output.as_mut().expect("just created");
+
+        let format = self.format;
+        let matcher = self
+            .matcher
+            .get_or_insert_with(|| HistogramMatcher::new(device, format));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("match_color"),
+        });
+        matcher.match_colors(
+            device,
+            queue,
+            &mut encoder,
+            (input.view(), input.size),
+            (reference.view(), reference.size),
+            &state.buffers,
+            method,
+            amount,
+            output.view(),
+        );
+        queue.submit(Some(encoder.finish()));
+        output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(output.clone())])
+    }
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, MatchColorHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(MatchColorHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(MatchColorHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, MatchColorHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(MatchColorHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(MatchColorHandlerError::MissingInput { input_name }),
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use crate::graph_executor::NodeValue;
+use crate::node::engine_node::MathOp;
+
+/// How close two floats have to be for Compare nodes to call them equal, so
+/// values that went through smoothing or mapping still match.
+const EQUALITY_TOLERANCE: f32 = 1e-5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MathHandlerError {
+    #[error("math input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("math input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeMathRequest<'a> {
+    pub op: MathOp,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+/// Run one of the stock math and logic nodes. They keep no state, so their
+/// outputs only change when their inputs do.
+pub fn execute_math(request: &NodeMathRequest) -> Result<Vec<NodeValue>, MathHandlerError> {
+    let inputs = request.inputs;
+    let output = match request.op {
+        MathOp::Add => {
+            NodeValue::Float(read_float_input(inputs, "A")? + read_float_input(inputs, "B")?)
+        }
+        MathOp::Multiply => {
+            NodeValue::Float(read_float_input(inputs, "A")? * read_float_input(inputs, "B")?)
+        }
+        MathOp::Min => {
+            NodeValue::Float(read_float_input(inputs, "A")?.min(read_float_input(inputs, "B")?))
+        }
+        MathOp::Max => {
+            NodeValue::Float(read_float_input(inputs, "A")?.max(read_float_input(inputs, "B")?))
+        }
+        MathOp::Clamp => NodeValue::Float(clamp(
+            read_float_input(inputs, "Value")?,
+            read_float_input(inputs, "Min")?,
+            read_float_input(inputs, "Max")?,
+        )),
+        MathOp::Lerp => {
+            let a = read_float_input(inputs, "A")?;
+            let b = read_float_input(inputs, "B")?;
+            let t = read_float_input(inputs, "T")?;
+            NodeValue::Float(a + (b - a) * t)
+        }
+        MathOp::Remap => NodeValue::Float(remap(
+            read_float_input(inputs, "Value")?,
+            (
+                read_float_input(inputs, "From Min")?,
+                read_float_input(inputs, "From Max")?,
+            ),
+            (
+                read_float_input(inputs, "To Min")?,
+                read_float_input(inputs, "To Max")?,
+            ),
+            read_bool_input(inputs, "Clamp")?,
+        )),
+        MathOp::Compare => NodeValue::Bool(compare(
+            read_float_input(inputs, "A")?,
+            read_float_input(inputs, "B")?,
+            read_enum_input(inputs, "Operator")?,
+        )),
+        MathOp::Logic => NodeValue::Bool(logic(
+            read_bool_input(inputs, "A")?,
+            read_bool_input(inputs, "B")?,
+            read_enum_input(inputs, "Operator")?,
+        )),
+        MathOp::Not => NodeValue::Bool(!read_bool_input(inputs, "A")?),
+        MathOp::Select => {
+            let input_name = if read_bool_input(inputs, "Condition")? {
+                "If True"
+            } else {
+                "If False"
+            };
+            NodeValue::Float(read_float_input(inputs, input_name)?)
+        }
+        MathOp::ArrayIndex => {
+            let values = read_array_input(inputs, "Array")?;
+            let index = read_int_input(inputs, "Index")?;
+            NodeValue::Float(array_index(values, index))
+        }
+        MathOp::ArraySlice => NodeValue::FloatArray(
+            array_slice(
+                read_array_input(inputs, "Array")?,
+                read_int_input(inputs, "Start")?,
+                read_int_input(inputs, "Count")?,
+            )
+            .to_vec(),
+        ),
+        MathOp::ArrayReduce => NodeValue::Float(array_reduce(
+            read_array_input(inputs, "Array")?,
+            read_enum_input(inputs, "Operation")?,
+        )),
+    };
+    Ok(vec![output])
+}
+
+/// Run a constant node, which outputs its "Value" input as-is.
+pub fn execute_constant(
+    inputs: &HashMap<String, NodeValue>,
+) -> Result<Vec<NodeValue>, MathHandlerError> {
+    inputs
+        .get("Value")
+        .cloned()
+        .map(|value| vec![value])
+        .ok_or(MathHandlerError::MissingInput {
+            input_name: "Value",
+        })
+}
+
+/// [f32::clamp], except a minimum above the maximum wins instead of panicking.
+fn clamp(value: f32, min: f32, max: f32) -> f32 {
+    value.min(max).max(min)
+}
+
+/// Map `value` from the range `from` to the range `to`. Either range can be
+/// reversed. A range of zero width maps everything to `to`'s start.
+fn remap(value: f32, from: (f32, f32), to: (f32, f32), clamped: bool) -> f32 {
+    let width = from.1 - from.0;
+    if width.abs() <= f32::EPSILON {
+        return to.0;
+    }
+
+    let mut t = (value - from.0) / width;
+    if clamped {
+        t = t.clamp(0.0, 1.0);
+    }
+    to.0 + (to.1 - to.0) * t
+}
+
+/// Compare `a` and `b` with the Compare node's `operator` (the index of its
+/// choice: <, <=, =, !=, >=, >).
+fn compare(a: f32, b: f32, operator: usize) -> bool {
+    let equal = (a - b).abs() <= EQUALITY_TOLERANCE;
+    match operator {
+        0 => a < b && !equal,
+        1 => a < b || equal,
+        2 => equal,
+        3 => !equal,
+        4 => a > b || equal,
+        _ => a > b && !equal,
+    }
+}
+
+/// Combine `a` and `b` with the Logic node's `operator` (the index of its
+/// choice: And, Or, Xor, Nand, Nor).
+fn logic(a: bool, b: bool, operator: usize) -> bool {
+    match operator {
+        0 => a && b,
+        1 => a || b,
+        2 => a != b,
+        3 => !(a && b),
+        _ => !(a || b),
+    }
+}
+
+/// The value at `index`, clamped to the array's bounds so an out of range
+/// index reads the first or last value. 0 for an empty array.
+fn array_index(values: &[f32], index: i32) -> f32 {
+    let index = usize::try_from(index).unwrap_or(0);
+    values
+        .get(index.min(values.len().saturating_sub(1)))
+        .copied()
+        .unwrap_or(0.0)
+}
+
+/// Up to `count` values starting at `start`. The slice is cut short at the end
+/// of the array, and is empty if it starts past it.
+fn array_slice(values: &[f32], start: i32, count: i32) -> &[f32] {
+    let start = usize::try_from(start).unwrap_or(0).min(values.len());
+    let count = usize::try_from(count).unwrap_or(0);
+    &values[start..values.len().min(start.saturating_add(count))]
+}
+
+/// Reduce `values` to one with the Array Reduce node's `operation` (the index
+/// of its choice: Sum, Average, Min, Max, Count). An empty array reduces to 0.
+fn array_reduce(values: &[f32], operation: usize) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    match operation {
+        0 => values.iter().sum(),
+        1 => values.iter().sum::<f32>() / values.len() as f32,
+        2 => values.iter().copied().fold(f32::INFINITY, f32::min),
+        3 => values.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+        _ => values.len() as f32,
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, MathHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(MathHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(MathHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, MathHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        Some(_) => Err(MathHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(MathHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_array_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a [f32], MathHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::FloatArray(values)) => Ok(values),
+        Some(_) => Err(MathHandlerError::InvalidInput {
+            input_name,
+            expected: "Float Array",
+        }),
+        None => Err(MathHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, MathHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(MathHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(MathHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_enum_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<usize, MathHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Enum(value)) => Ok(*value),
+        Some(_) => Err(MathHandlerError::InvalidInput {
+            input_name,
+            expected: "Enum",
+        }),
+        None => Err(MathHandlerError::MissingInput { input_name }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- remap() ---
+
+    #[test]
+    fn remap_maps_between_ranges() {
+        assert_eq!(remap(0.5, (0.0, 1.0), (10.0, 20.0), false), 15.0);
+        assert_eq!(remap(0.25, (0.0, 1.0), (1.0, 0.0), false), 0.75);
+        assert_eq!(remap(2.0, (0.0, 1.0), (0.0, 10.0), false), 20.0);
+        assert_eq!(remap(2.0, (0.0, 1.0), (0.0, 10.0), true), 10.0);
+        assert_eq!(remap(3.0, (1.0, 1.0), (5.0, 10.0), false), 5.0);
+    }
+
+    // --- compare() ---
+
+    #[test]
+    fn compare_treats_nearly_equal_floats_as_equal() {
+        let nearly_one = 1.0 + EQUALITY_TOLERANCE / 2.0;
+
+        assert!(compare(1.0, nearly_one, 2));
+        assert!(!compare(1.0, nearly_one, 0));
+        assert!(compare(1.0, nearly_one, 1));
+        assert!(compare(1.0, 2.0, 0));
+        assert!(compare(2.0, 1.0, 5));
+        assert!(compare(2.0, 1.0, 3));
+    }
+
+    // --- array_index() ---
+
+    #[test]
+    fn array_index_clamps_to_bounds() {
+        let values = [1.0, 2.0, 3.0];
+
+        assert_eq!(array_index(&values, 1), 2.0);
+        assert_eq!(array_index(&values, -4), 1.0);
+        assert_eq!(array_index(&values, 7), 3.0);
+        assert_eq!(array_index(&[], 0), 0.0);
+    }
+
+    // --- array_slice() ---
+
+    #[test]
+    fn array_slice_stays_in_bounds() {
+        let values = [1.0, 2.0, 3.0, 4.0];
+
+        assert_eq!(array_slice(&values, 1, 2), &[2.0, 3.0]);
+        assert_eq!(array_slice(&values, 2, 10), &[3.0, 4.0]);
+        assert_eq!(array_slice(&values, -1, 1), &[1.0]);
+        assert!(array_slice(&values, 9, 1).is_empty());
+        assert!(array_slice(&values, 0, -1).is_empty());
+    }
+
+    // --- array_reduce() ---
+
+    #[test]
+    fn array_reduce_handles_every_operation() {
+        let values = [2.0, -1.0, 5.0];
+
+        assert_eq!(array_reduce(&values, 0), 6.0);
+        assert_eq!(array_reduce(&values, 1), 2.0);
+        assert_eq!(array_reduce(&values, 2), -1.0);
+        assert_eq!(array_reduce(&values, 3), 5.0);
+        assert_eq!(array_reduce(&values, 4), 3.0);
+        assert_eq!(array_reduce(&[], 2), 0.0);
+    }
+
+    // --- execute_math() ---
+
+    #[test]
+    fn execute_math_selects_by_condition() {
+        let inputs = HashMap::from([
+            ("Condition".to_string(), NodeValue::Bool(false)),
+            ("If True".to_string(), NodeValue::Float(1.0)),
+            ("If False".to_string(), NodeValue::Int(2)),
+        ]);
+
+        let outputs = execute_math(&NodeMathRequest {
+            op: MathOp::Select,
+            inputs: &inputs,
+        })
+        .unwrap();
+
+        assert!(matches!(outputs[..], [NodeValue::Float(2.0)]));
+    }
+}
@@ -227,6 +227,11 @@ impl TimedStreamHandler for MidiStreamHandler {
     fn stream_set_target_fps(stream: &mut Self::Stream, target_fps: Fps) {
         stream.set_target_fps(target_fps);
     }
+
+    fn stream_skip(_stream: &mut Self::Stream, frames: usize) {
+        // Live input isn't played back at a rate, so there's nothing to skip
+        // (and skipping would drop notes).
+    }
 }
 
 fn resolve_port_query(
@@ -87,6 +87,10 @@ impl NoiseStreamHandler {
         <Self as TimedStreamHandler>::set_target_fps_all(self, target_fps);
     }
 
+    pub fn skip_frames(&mut self, frames: usize) {
+        <Self as TimedStreamHandler>::skip_frames(self, frames);
+    }
+
     pub fn set_target_fps_for_nodes(
         &mut self,
         target_fps: Fps,
@@ -181,6 +185,12 @@ impl TimedStreamHandler for NoiseStreamHandler {
     fn stream_set_target_fps(stream: &mut Self::Stream, target_fps: Fps) {
         stream.set_target_fps(target_fps);
     }
+
+    fn stream_skip(stream: &mut Self::Stream, frames: usize) {
+        for _ in 0..frames {
+            _ = stream.fetch();
+        }
+    }
 }
 
 fn build_config_key(
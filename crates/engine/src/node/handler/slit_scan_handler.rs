@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+use crate::slit_scanner::{
+    FrameHistory, MAX_HISTORY_FRAMES, ScanDirection, ScanSettings, SlitScanner,
+};
+use crate::texture_blitter::TextureBlitter;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SlitScanHandlerError {
+    #[error("slit scan input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("slit scan input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeSlitScanRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+struct ScanState {
+    history: FrameHistory,
+    output: GpuFrame,
+}
+
+/// Runs Slit Scan and Time Displace nodes, which draw each part of a frame
+/// from a different one of the last few frames.
+///
+/// Slit Scan nodes pick how far back to reach with a gradient across the
+/// frame; Time Displace nodes have a Map input whose brightness picks it
+/// instead. Each node keeps its recent frames on the GPU between executions
+/// (see [FrameHistory]), and starts them over when the input's size or the
+/// number of frames changes.
+pub struct SlitScanHandler {
+    state_cache: HashMap<EngineNodeId, ScanState>,
+    /// Created when first needed.
+    scanner: Option<SlitScanner>,
+    blitter: Option<TextureBlitter>,
+    format: wgpu::TextureFormat,
+}
+
+impl SlitScanHandler {
+    /// Create a handler that keeps and outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            scanner: None,
+            blitter: None,
+            format,
+        }
+    }
+
+    /// Drop every node's recent frames.
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeSlitScanRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, SlitScanHandlerError> {
+        let input = read_frame_input(request.inputs, "Input")?;
+        let map = match request.inputs.get("Map") {
+            Some(_) => Some(read_frame_input(request.inputs, "Map")?),
+            None => None,
+        };
+        let frames =
+            read_int_input(request.inputs, "Frames")?.clamp(1, MAX_HISTORY_FRAMES as i32) as u32;
+        let settings = ScanSettings {
+            direction: if map.is_some() {
+                ScanDirection::Map
+            } else {
+                direction_input(request.inputs)
+            },
+            smooth: read_bool_input(request.inputs, "Smooth")?,
+            invert: read_bool_input(request.inputs, "Invert")?,
+        };
+
+        let format = self.format;
+        if self.state_cache.get(&request.node_id).is_none_or(|state| {
+            state.history.size() != input.size || state.history.capacity() != frames
+        }) {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("slit_scan_output"),
+                size: input.size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let state = ScanState {
+                history: FrameHistory::new(device, format, input.size, frames),
+                output: GpuFrame::new(
+                    texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    input.size,
+                    Uid::generate_new(),
+                ),
+            };
+            self.state_cache.insert(request.node_id, state);
+        }
+        let state = self
+            .state_cache
+            .get_mut(&request.node_id)
+            .expect("just inserted");
+
+        let scanner = self
+            .scanner
+            .get_or_insert_with(|| SlitScanner::new(device, format));
+        let blitter = self
+            .blitter
+            .get_or_insert_with(|| TextureBlitter::new(device, format));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("slit_scan"),
+        });
+        blitter.blit(
+            device,
+            &mut encoder,
+            input.view(),
+            state.history.next_layer(),
+        );
+        state.history.advance();
+        scanner.draw(
+            device,
+            queue,
+            &mut encoder,
+            &state.history,
+            map.unwrap_or(input).view(),
+            settings,
+            state.output.view(),
+        );
+        queue.submit(Some(encoder.finish()));
+        state.output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(state.output.clone())])
+    }
+}
+
+/// Read the Direction input, whose choices are in [ScanDirection] order.
+fn direction_input(inputs: &HashMap<String, NodeValue>) -> ScanDirection {
+    match inputs.get("Direction") {
+        Some(NodeValue::Enum(1)) => ScanDirection::BottomToTop,
+        Some(NodeValue::Enum(2)) => ScanDirection::LeftToRight,
+        Some(NodeValue::Enum(3)) => ScanDirection::RightToLeft,
+        Some(NodeValue::Enum(4)) => ScanDirection::CenterOut,
+        Some(NodeValue::Enum(5)) => ScanDirection::EdgesIn,
+        _ => ScanDirection::TopToBottom,
+    }
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, SlitScanHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(SlitScanHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(SlitScanHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, SlitScanHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        Some(NodeValue::Float(value)) => Ok(*value as i32),
+        Some(_) => Err(SlitScanHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(SlitScanHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, SlitScanHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(SlitScanHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(SlitScanHandlerError::MissingInput { input_name }),
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use media::frame::{Dimensions, Frame, FromImgFileError};
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+use crate::upload_stager::UploadStager;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpriteSheetHandlerError {
+    #[error("sprite sheet input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("sprite sheet input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+    #[error("no sprite sheet image is selected")]
+    NoImage,
+    #[error("couldn't load the sprite sheet '{path}'")]
+    Unreadable { path: PathBuf },
+    #[error("failed to load the sprite sheet '{path}': {source}")]
+    Load {
+        path: PathBuf,
+        #[source]
+        source: FromImgFileError,
+    },
+    #[error("the sprite sheet '{path}' is smaller than its {columns}x{rows} grid")]
+    GridTooFine {
+        path: PathBuf,
+        columns: u32,
+        rows: u32,
+    },
+    #[error("failed to upload a sprite from '{path}': {error}")]
+    TextureUpload { path: PathBuf, error: String },
+}
+
+pub struct NodeSpriteSheetRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+/// How a sprite sheet is cut into cells, and which cell to show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CellSelection {
+    columns: u32,
+    rows: u32,
+    index: u32,
+}
+
+struct SheetState {
+    path: PathBuf,
+    sheet: Frame,
+    /// The cell's own upload texture (the shared one is overwritten by every
+    /// source).
+    stager: UploadStager,
+    /// The cell that's uploaded, with its CPU copy.
+    current: Option<(CellSelection, GpuFrame, Frame)>,
+    /// How long the animation has played for.
+    elapsed: Duration,
+    last_tick: Option<Instant>,
+}
+
+/// Loads sprite sheet images (a grid of equally sized cells) and outputs one
+/// cell as a frame, optionally stepping through the cells over time.
+///
+/// Cells are numbered left to right, then top to bottom. Animation runs on
+/// the handler's own clock, which follows play and pause like other streams.
+pub struct SpriteSheetHandler {
+    state_cache: HashMap<EngineNodeId, SheetState>,
+    /// Files that failed to load, so they aren't reloaded every frame.
+    failed_paths: HashMap<EngineNodeId, PathBuf>,
+    paused: bool,
+}
+
+impl Default for SpriteSheetHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpriteSheetHandler {
+    pub fn new() -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            failed_paths: HashMap::new(),
+            paused: false,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+        self.failed_paths.clear();
+    }
+
+    pub fn pause_all_streams(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn play_all_streams(&mut self) {
+        self.paused = false;
+    }
+
+    /// The CPU copy of the cell `node_id` last output.
+    pub fn current_cell(&self, node_id: EngineNodeId) -> Option<&Frame> {
+        self.state_cache
+            .get(&node_id)
+            .and_then(|state| state.current.as_ref())
+            .map(|(_, _, cell)| cell)
+    }
+
+    /// Returns the selected cell as a frame, followed by the index of the cell.
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeSpriteSheetRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, SpriteSheetHandlerError> {
+        let inputs = request.inputs;
+        let path = read_file_input(inputs, "Path")?;
+        let columns = read_int_input(inputs, "Columns")?.max(1) as u32;
+        let rows = read_int_input(inputs, "Rows")?.max(1) as u32;
+        let cell_count = match read_int_input(inputs, "Frame Count")? {
+            count if count <= 0 => columns * rows,
+            count => (count as u32).min(columns * rows),
+        };
+        let start_index = read_int_input(inputs, "Frame Index")?.max(0) as u32;
+        let animate = read_bool_input(inputs, "Animate")?;
+        let fps = read_float_input(inputs, "FPS")?;
+
+        if path.as_os_str().is_empty() {
+            self.state_cache.remove(&request.node_id);
+            return Err(SpriteSheetHandlerError::NoImage);
+        }
+
+        let paused = self.paused;
+        let state = self.sheet_state(request.node_id, path)?;
+
+        let now = Instant::now();
+        let last_tick = state.last_tick.replace(now);
+        if paused || !animate {
+            state.last_tick = None;
+        } else if let Some(last_tick) = last_tick {
+            state.elapsed += now.duration_since(last_tick);
+        }
+
+        let index = if animate {
+            animated_index(start_index, cell_count, fps, state.elapsed)
+        } else {
+            start_index % cell_count
+        };
+        let selection = CellSelection {
+            columns,
+            rows,
+            index,
+        };
+
+        if let Some((current, gpu_frame, _)) = &state.current
+            && *current == selection
+        {
+            return Ok(vec![
+                NodeValue::Frame(gpu_frame.clone()),
+                NodeValue::Int(index as i32),
+            ]);
+        }
+
+        let (left, top, dimensions) =
+            cell_rect(state.sheet.dimensions(), selection).ok_or_else(|| {
+                SpriteSheetHandlerError::GridTooFine {
+                    path: path.to_path_buf(),
+                    columns,
+                    rows,
+                }
+            })?;
+        let cell = state.sheet.crop(left, top, dimensions);
+
+        let view = state
+            .stager
+            .cpu_to_gpu_rgba(
+                device,
+                queue,
+                dimensions.width(),
+                dimensions.height(),
+                cell.raw_data(),
+            )
+            .map_err(|error| SpriteSheetHandlerError::TextureUpload {
+                path: path.to_path_buf(),
+                error: format!("{error:?}"),
+            })?;
+        let gpu_frame = GpuFrame::new(
+            view,
+            wgpu::Extent3d {
+                width: dimensions.width(),
+                height: dimensions.height(),
+                depth_or_array_layers: 1,
+            },
+            cell.uid(),
+        );
+
+        state.current = Some((selection, gpu_frame.clone(), cell));
+        Ok(vec![
+            NodeValue::Frame(gpu_frame),
+            NodeValue::Int(index as i32),
+        ])
+    }
+
+    /// The sheet for `node_id`, (re)loading the file if the path changed.
+    fn sheet_state(
+        &mut self,
+        node_id: EngineNodeId,
+        path: &Path,
+    ) -> Result<&mut SheetState, SpriteSheetHandlerError> {
+        if self
+            .state_cache
+            .get(&node_id)
+            .is_some_and(|state| state.path == path)
+        {
+            return Ok(self.state_cache.get_mut(&node_id).expect("just checked"));
+        }
+
+        if self
+            .failed_paths
+            .get(&node_id)
+            .is_some_and(|failed| failed == path)
+        {
+            return Err(SpriteSheetHandlerError::Unreadable {
+                path: path.to_path_buf(),
+            });
+        }
+
+        let sheet = match Frame::from_img_file(path) {
+            Ok(sheet) => sheet,
+            Err(source) => {
+                self.failed_paths.insert(node_id, path.to_path_buf());
+                return Err(SpriteSheetHandlerError::Load {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+        };
+        self.failed_paths.remove(&node_id);
+
+        let state = SheetState {
+            path: path.to_path_buf(),
+            sheet,
+            stager: UploadStager::new(),
+            current: None,
+            elapsed: Duration::ZERO,
+            last_tick: None,
+        };
+        Ok(self
+            .state_cache
+            .entry(node_id)
+            .insert_entry(state)
+            .into_mut())
+    }
+}
+
+/// The cell shown after playing `elapsed` of an animation through
+/// `cell_count` cells at `fps`, starting from `start_index` and looping.
+fn animated_index(start_index: u32, cell_count: u32, fps: f32, elapsed: Duration) -> u32 {
+    let cell_count = cell_count.max(1);
+    let advanced = (elapsed.as_secs_f64() * fps.max(0.0) as f64) as u64;
+    ((start_index as u64 + advanced) % cell_count as u64) as u32
+}
+
+/// The left and top edges and the size of the selected cell, or [None] if the
+/// sheet is too small to have a pixel per cell.
+fn cell_rect(sheet: Dimensions, selection: CellSelection) -> Option<(u32, u32, Dimensions)> {
+    let cell_dimensions = Dimensions::new(
+        sheet.width() / selection.columns,
+        sheet.height() / selection.rows,
+    )?;
+
+    let column = selection.index % selection.columns;
+    let row = (selection.index / selection.columns) % selection.rows;
+    Some((
+        column * cell_dimensions.width(),
+        row * cell_dimensions.height(),
+        cell_dimensions,
+    ))
+}
+
+fn read_file_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a Path, SpriteSheetHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::File(path)) => Ok(path.as_path()),
+        Some(_) => Err(SpriteSheetHandlerError::InvalidInput {
+            input_name,
+            expected: "File",
+        }),
+        None => Err(SpriteSheetHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, SpriteSheetHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        Some(NodeValue::Float(value)) => Ok(*value as i32),
+        Some(_) => Err(SpriteSheetHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(SpriteSheetHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, SpriteSheetHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(SpriteSheetHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(SpriteSheetHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, SpriteSheetHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(SpriteSheetHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(SpriteSheetHandlerError::MissingInput { input_name }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- cell_rect() ---
+
+    #[test]
+    fn test_cell_rect() {
+        let sheet = Dimensions::new(400, 300).unwrap();
+        let selection = CellSelection {
+            columns: 4,
+            rows: 3,
+            index: 6,
+        };
+        assert_eq!(
+            cell_rect(sheet, selection),
+            Some((200, 100, Dimensions::new(100, 100).unwrap()))
+        );
+
+        let too_fine = CellSelection {
+            columns: 500,
+            ..selection
+        };
+        assert_eq!(cell_rect(sheet, too_fine), None);
+    }
+
+    // --- animated_index() ---
+
+    #[test]
+    fn test_animated_index_loops() {
+        assert_eq!(animated_index(0, 8, 12.0, Duration::ZERO), 0);
+        assert_eq!(animated_index(0, 8, 12.0, Duration::from_millis(500)), 6);
+        assert_eq!(animated_index(2, 8, 12.0, Duration::from_millis(500)), 0);
+        assert_eq!(animated_index(0, 8, 0.0, Duration::from_secs(10)), 0);
+    }
+}
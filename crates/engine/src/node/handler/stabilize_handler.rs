@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use media::frame::stabilization::{self, FrameMotion, StabilizationTrack};
+use util::channels::mailbox::{self, Mailbox};
+
+use crate::frame_transformer::{FrameTransformer, UvTransform};
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+
+/// The most frames a Stabilize node's camera path is smoothed over on either
+/// side.
+const MAX_SMOOTHING_FRAMES: u32 = 300;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StabilizeHandlerError {
+    #[error("stabilize input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("stabilize input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+    #[error("failed to analyze '{path}' for stabilization: {message}")]
+    Analysis { path: PathBuf, message: String },
+}
+
+pub struct NodeStabilizeRequest<'a> {
+    pub inputs: &'a HashMap<String, NodeValue>,
+    pub file_path: &'a Path,
+    /// The time of the frame being stabilized in seconds, or [None] if the
+    /// video's position isn't known yet.
+    pub source_time: Option<f64>,
+}
+
+enum AnalysisUpdate {
+    Progress(f32),
+    Finished(Result<StabilizationTrack, String>),
+}
+
+enum Analysis {
+    /// Analysis is running, with the fraction of the video done so far.
+    Pending(f32),
+    Ready {
+        track: StabilizationTrack,
+        /// The corrections for the last smoothing radius used.
+        corrections: Option<(usize, Vec<FrameMotion>)>,
+    },
+    Failed(String),
+}
+
+/// Runs Stabilize nodes, which steady shaky video in two stages.
+///
+/// The first time a video is stabilized it's analyzed on a background thread
+/// (see [stabilization::analyze_video]), which measures how the camera moved
+/// between frames; the frames pass through unchanged until it finishes, and
+/// the result is cached so the video isn't analyzed again. After that each
+/// frame is moved against the shake (the difference between the camera's path
+/// and a smoothed copy of it) and zoomed in to hide the edges this uncovers.
+pub struct StabilizeHandler {
+    analyses: HashMap<PathBuf, Analysis>,
+    /// Sends videos to analyze and receives updates on their analyses.
+    analysis_mailbox: Mailbox<PathBuf, (PathBuf, AnalysisUpdate)>,
+    transformer: Option<FrameTransformer>,
+    format: wgpu::TextureFormat,
+}
+
+impl StabilizeHandler {
+    /// Create a handler that draws stabilized frames into `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        let (analysis_mailbox, worker_mailbox) = mailbox::new();
+
+        thread::spawn(move || {
+            while let Ok(path) = worker_mailbox.wait() {
+                let result = stabilization::analyze_video(&path, |progress| {
+                    worker_mailbox
+                        .send((path.clone(), AnalysisUpdate::Progress(progress)))
+                        .is_ok()
+                })
+                .map_err(|error| error.to_string());
+                if worker_mailbox
+                    .send((path, AnalysisUpdate::Finished(result)))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            analyses: HashMap::new(),
+            analysis_mailbox,
+            transformer: None,
+            format,
+        }
+    }
+
+    /// Forget failed analyses so they're tried again. Finished analyses are
+    /// kept, since they only depend on the video.
+    pub fn clear_cache(&mut self) {
+        self.analyses
+            .retain(|_, analysis| !matches!(analysis, Analysis::Failed(_)));
+    }
+
+    /// Draw `source` (the frame of the request's video at its source time)
+    /// stabilized into `target`, which must be the same size. Returns how much
+    /// of the video has been analyzed, from 0 to 1; until it's all analyzed
+    /// `source` is only zoomed.
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeStabilizeRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &GpuFrame,
+        target: &wgpu::TextureView,
+    ) -> Result<f32, StabilizeHandlerError> {
+        let smoothing = read_int_input(request.inputs, "Smoothing (frames)")?
+            .clamp(0, MAX_SMOOTHING_FRAMES as i32) as usize;
+        let zoom = read_float_input(request.inputs, "Zoom")?.max(1.0);
+        let strength = read_float_input(request.inputs, "Strength")?.clamp(0.0, 1.0);
+
+        self.receive_analysis_updates();
+        let (progress, correction) = match self.analyses.get_mut(request.file_path) {
+            None => {
+                let path = request.file_path.to_path_buf();
+                self.analysis_mailbox.send(path.clone()).map_err(|_| {
+                    StabilizeHandlerError::Analysis {
+                        path: path.clone(),
+                        message: "the analysis thread stopped".to_string(),
+                    }
+                })?;
+                self.analyses.insert(path, Analysis::Pending(0.0));
+                (0.0, FrameMotion::default())
+            }
+            Some(Analysis::Pending(progress)) => (*progress, FrameMotion::default()),
+            Some(Analysis::Failed(message)) => {
+                return Err(StabilizeHandlerError::Analysis {
+                    path: request.file_path.to_path_buf(),
+                    message: message.clone(),
+                });
+            }
+            Some(Analysis::Ready { track, corrections }) => {
+                if corrections
+                    .as_ref()
+                    .is_none_or(|(radius, _)| *radius != smoothing)
+                {
+                    *corrections = Some((smoothing, track.corrections(smoothing)));
+                }
+                let (_, corrections) = corrections.as_ref().expect("just set");
+                let correction = request
+                    .source_time
+                    .and_then(|time| {
+                        let index = (time * track.fps()).round().max(0.0) as usize;
+                        corrections.get(index.min(corrections.len().saturating_sub(1)))
+                    })
+                    .copied()
+                    .unwrap_or_default();
+                (1.0, correction)
+            }
+        };
+
+        let transform = stabilize_transform(
+            correction,
+            strength as f64,
+            zoom as f64,
+            source.size.width as f64 / source.size.height.max(1) as f64,
+        );
+        let format = self.format;
+        let transformer = self
+            .transformer
+            .get_or_insert_with(|| FrameTransformer::new(device, format));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("stabilize"),
+        });
+        transformer.transform(
+            device,
+            queue,
+            &mut encoder,
+            source.view(),
+            transform,
+            wgpu::Color::BLACK,
+            target,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        Ok(progress)
+    }
+
+    fn receive_analysis_updates(&mut self) {
+        let Ok(Some(updates)) = self.analysis_mailbox.check_non_blocking_all() else {
+            return;
+        };
+        for (path, update) in updates {
+            let analysis = match update {
+                AnalysisUpdate::Progress(progress) => Analysis::Pending(progress),
+                AnalysisUpdate::Finished(Ok(track)) => Analysis::Ready {
+                    track,
+                    corrections: None,
+                },
+                AnalysisUpdate::Finished(Err(message)) => Analysis::Failed(message),
+            };
+            self.analyses.insert(path, analysis);
+        }
+    }
+}
+
+/// The transform that moves a frame by `strength` of `correction` (scaled
+/// around its center by `zoom`), for a frame `aspect` times wider than it is
+/// tall.
+fn stabilize_transform(
+    correction: FrameMotion,
+    strength: f64,
+    zoom: f64,
+    aspect: f64,
+) -> UvTransform {
+    // A target uv is drawn from the source uv it came from: undo the zoom and
+    // the correction's translation, then its rotation around the center (in
+    // pixel space, so it isn't squashed by the aspect ratio).
+    let (sin, cos) = (-correction.angle * strength).sin_cos();
+    let unrotate = |(x, y): (f64, f64)| (cos * x - sin / aspect * y, sin * aspect * x + cos * y);
+
+    let (center_x, center_y) = unrotate((0.5, 0.5));
+    let (shift_x, shift_y) = unrotate((correction.dx * strength, correction.dy * strength));
+    let matrix = [
+        [cos / zoom, sin * aspect / zoom],
+        [-sin / aspect / zoom, cos / zoom],
+    ];
+
+    UvTransform {
+        matrix: matrix.map(|column| column.map(|value| value as f32)),
+        offset: [
+            (0.5 - center_x / zoom - shift_x) as f32,
+            (0.5 - center_y / zoom - shift_y) as f32,
+        ],
+    }
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, StabilizeHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        Some(NodeValue::Float(value)) => Ok(*value as i32),
+        Some(_) => Err(StabilizeHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(StabilizeHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, StabilizeHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(StabilizeHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(StabilizeHandlerError::MissingInput { input_name }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_uv_eq(actual: [f32; 2], expected: [f32; 2]) {
+        assert!(
+            (actual[0] - expected[0]).abs() < 1e-5 && (actual[1] - expected[1]).abs() < 1e-5,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    // --- stabilize_transform() ---
+
+    #[test]
+    fn test_stabilize_transform() {
+        let none = stabilize_transform(FrameMotion::default(), 1.0, 1.0, 16.0 / 9.0);
+        assert_uv_eq(none.apply([0.25, 0.75]), [0.25, 0.75]);
+
+        // Moving the frame right draws the source from further left.
+        let shift = FrameMotion {
+            dx: 0.1,
+            dy: -0.05,
+            angle: 0.0,
+        };
+        let shifted = stabilize_transform(shift, 1.0, 1.0, 16.0 / 9.0);
+        assert_uv_eq(shifted.apply([0.5, 0.5]), [0.4, 0.55]);
+        let halved = stabilize_transform(shift, 0.5, 1.0, 16.0 / 9.0);
+        assert_uv_eq(halved.apply([0.5, 0.5]), [0.45, 0.525]);
+
+        // Zooming in draws the middle of the source over the whole target.
+        let zoomed = stabilize_transform(FrameMotion::default(), 1.0, 2.0, 1.0);
+        assert_uv_eq(zoomed.apply([0.0, 0.0]), [0.25, 0.25]);
+        assert_uv_eq(zoomed.apply([0.5, 0.5]), [0.5, 0.5]);
+
+        // Turning clockwise draws what was right of the center below it (in
+        // pixels, so a quarter of the height is an eighth of the width).
+        let turn = FrameMotion {
+            angle: std::f64::consts::FRAC_PI_2,
+            ..Default::default()
+        };
+        let turned = stabilize_transform(turn, 1.0, 1.0, 2.0);
+        assert_uv_eq(turned.apply([0.5, 0.5]), [0.5, 0.5]);
+        assert_uv_eq(turned.apply([0.5, 1.0]), [0.75, 0.5]);
+    }
+}
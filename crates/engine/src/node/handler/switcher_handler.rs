@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+use crate::source_switcher::{
+    SourceMix, SourceSelection, SourceSwitcher, Transition, WipeDirection,
+};
+
+/// The Switcher node's frame inputs, in order. Only the first has to be
+/// connected.
+const SOURCE_INPUTS: [&str; 4] = ["Input 1", "Input 2", "Input 3", "Input 4"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SwitcherHandlerError {
+    #[error("switcher input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("switcher input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeSwitcherRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+#[derive(Default)]
+struct SwitcherState {
+    selection: SourceSelection,
+    /// Where transitions are drawn. Created by the first transition.
+    output: Option<GpuFrame>,
+}
+
+/// Runs Switcher nodes, which show one of several frame inputs and animate
+/// between them when the selected input changes.
+///
+/// Each node remembers the input it's showing and how far along a transition
+/// it is (see [SourceSelection]), so it has to run every frame. Selecting an
+/// input that isn't connected keeps showing the current one.
+pub struct SwitcherHandler {
+    state_cache: HashMap<EngineNodeId, SwitcherState>,
+    /// Created when first needed.
+    switcher: Option<SourceSwitcher>,
+    format: wgpu::TextureFormat,
+}
+
+impl SwitcherHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            switcher: None,
+            format,
+        }
+    }
+
+    /// Forget what every node is showing, so each starts again on its selected
+    /// input with no transition.
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeSwitcherRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, SwitcherHandlerError> {
+        let mut sources = Vec::with_capacity(SOURCE_INPUTS.len());
+        for input_name in SOURCE_INPUTS {
+            sources.push(read_optional_frame_input(request.inputs, input_name)?);
+        }
+        if sources[0].is_none() {
+            return Err(SwitcherHandlerError::MissingInput {
+                input_name: SOURCE_INPUTS[0],
+            });
+        }
+
+        let transition = transition_input(request.inputs);
+        let duration = match transition {
+            Transition::Cut => 0,
+            _ => read_int_input(request.inputs, "Duration")?.max(0) as u32,
+        };
+
+        let state = self.state_cache.entry(request.node_id).or_default();
+        let selected = (read_int_input(request.inputs, "Selected")? - 1)
+            .clamp(0, SOURCE_INPUTS.len() as i32 - 1) as usize;
+        let selected = if sources[selected].is_some() {
+            selected
+        } else {
+            // Keep showing what's shown now, as long as it's still connected.
+            state
+                .selection
+                .current()
+                .filter(|&index| sources[index].is_some())
+                .unwrap_or(0)
+        };
+
+        let (from, to, progress) = match state.selection.update(selected, duration) {
+            SourceMix::Source(index) => {
+                let frame = sources[index].expect("only connected inputs are selected");
+                return Ok(vec![NodeValue::Frame(frame.clone())]);
+            }
+            SourceMix::Transitioning { from, to, progress } => (from, to, progress),
+        };
+        let to = sources[to].expect("only connected inputs are selected");
+        let Some(from) = sources[from] else {
+            // The input being switched away from was disconnected.
+            return Ok(vec![NodeValue::Frame(to.clone())]);
+        };
+
+        let format = self.format;
+        if state
+            .output
+            .as_ref()
+            .is_none_or(|output| output.size != to.size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("switcher_output"),
+                size: to.size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            state.output = Some(GpuFrame::new(
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                to.size,
+                Uid::generate_new(),
+            ));
+        }
+        let output = state.output.as_mut().expect("just created");
+
+        let switcher = self
+            .switcher
+            .get_or_insert_with(|| SourceSwitcher::new(device, format));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("switcher"),
+        });
+        switcher.draw(
+            device,
+            queue,
+            &mut encoder,
+            from.view(),
+            to.view(),
+            transition,
+            progress,
+            output.view(),
+        );
+        queue.submit(Some(encoder.finish()));
+        output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(output.clone())])
+    }
+}
+
+/// Read the Transition and Wipe Direction inputs, whose choices are in
+/// [Transition] and [WipeDirection] order.
+fn transition_input(inputs: &HashMap<String, NodeValue>) -> Transition {
+    match inputs.get("Transition") {
+        Some(NodeValue::Enum(1)) => Transition::Crossfade,
+        Some(NodeValue::Enum(2)) => Transition::Wipe(match inputs.get("Wipe Direction") {
+            Some(NodeValue::Enum(1)) => WipeDirection::RightToLeft,
+            Some(NodeValue::Enum(2)) => WipeDirection::TopToBottom,
+            Some(NodeValue::Enum(3)) => WipeDirection::BottomToTop,
+            _ => WipeDirection::LeftToRight,
+        }),
+        _ => Transition::Cut,
+    }
+}
+
+fn read_optional_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<Option<&'a GpuFrame>, SwitcherHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(Some(frame)),
+        Some(_) => Err(SwitcherHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Ok(None),
+    }
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, SwitcherHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        // So Selected can be driven by MIDI and other signals.
+        Some(NodeValue::Float(value)) => Ok(value.round() as i32),
+        Some(_) => Err(SwitcherHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(SwitcherHandlerError::MissingInput { input_name }),
+    }
+}
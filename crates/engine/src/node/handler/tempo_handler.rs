@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+use crate::tempo::{BeatDetector, LfoShape, TempoClock};
+use util::link::{LinkError, LinkSession};
+
+/// The most the clock moves in one tick. Longer gaps (e.g. the engine stalled)
+/// are skipped instead of jumping ahead.
+const MAX_TICK_ADVANCE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TempoHandlerError {
+    #[error("tempo input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("tempo input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeTempoRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+pub struct NodeLfoRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+/// Where a Tempo node gets the tempo from (the order of its "Source" choices).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TempoSource {
+    Manual,
+    Tap,
+    Audio,
+}
+
+/// Runs the graph's [TempoClock], which Tempo nodes set and LFO nodes follow.
+/// There's one clock per graph, so every LFO is in time with every other.
+///
+/// With Ableton Link enabled the clock follows the Link session instead, and
+/// tempo changes made by Tempo nodes are sent to the session.
+pub struct TempoHandler {
+    clock: TempoClock,
+    link: Option<LinkSession>,
+    detector: BeatDetector,
+    last_tick: Option<Instant>,
+    /// Whether each Tempo node's Tap input was on last frame, so holding it
+    /// only taps once.
+    tap_held: HashMap<EngineNodeId, bool>,
+    /// The beat each Tempo node last output, for its On Beat output.
+    last_beat: HashMap<EngineNodeId, i64>,
+    /// Each Tempo node's BPM input last frame. While other Link peers are
+    /// around only changes are sent to the session, so they can change it too.
+    last_manual_bpm: HashMap<EngineNodeId, f32>,
+    paused: bool,
+}
+
+impl Default for TempoHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TempoHandler {
+    pub fn new() -> Self {
+        Self {
+            clock: TempoClock::new(),
+            link: None,
+            detector: BeatDetector::new(),
+            last_tick: None,
+            tap_held: HashMap::new(),
+            last_beat: HashMap::new(),
+            last_manual_bpm: HashMap::new(),
+            paused: false,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.detector = BeatDetector::new();
+        self.tap_held.clear();
+        self.last_beat.clear();
+        self.last_manual_bpm.clear();
+    }
+
+    /// Join an Ableton Link session (at the clock's tempo) or leave it.
+    pub fn set_link_enabled(&mut self, enabled: bool) -> Result<(), LinkError> {
+        if !enabled {
+            self.link = None;
+        } else if self.link.is_none() {
+            self.link = Some(LinkSession::join(self.clock.bpm())?);
+        }
+        Ok(())
+    }
+
+    /// How many other peers are in the Link session, or [None] if Link isn't
+    /// enabled.
+    pub fn link_peers(&self) -> Option<usize> {
+        self.link.as_ref().map(LinkSession::peer_count)
+    }
+
+    pub fn pause_all_streams(&mut self) {
+        self.paused = true;
+        self.last_tick = None;
+    }
+
+    pub fn play_all_streams(&mut self) {
+        self.paused = false;
+    }
+
+    /// Move the clock forward by the time since the last tick. Called once
+    /// before each execution of the graph.
+    pub fn tick(&mut self) {
+        if self.paused {
+            return;
+        }
+
+        let now = Instant::now();
+        if let Some(last_tick) = self.last_tick.replace(now) {
+            let elapsed = now.duration_since(last_tick).min(MAX_TICK_ADVANCE);
+            self.clock.advance(elapsed.as_secs_f64());
+        }
+
+        if let Some(link) = &self.link {
+            self.clock.follow(link.tempo(), link.beat_at(now));
+        }
+    }
+
+    /// Change the tempo, and the Link session's tempo if Link is enabled.
+    fn set_bpm(&mut self, bpm: f64) {
+        self.clock.set_bpm(bpm);
+        if let Some(link) = &self.link {
+            link.set_tempo(self.clock.bpm(), Instant::now());
+        }
+    }
+
+    /// Set the clock from a Tempo node's inputs, and output its BPM, beat
+    /// count, beat and bar phase, and whether a new beat just started.
+    pub fn execute_tempo(
+        &mut self,
+        request: &NodeTempoRequest,
+    ) -> Result<Vec<NodeValue>, TempoHandlerError> {
+        let source = match read_enum_input(request.inputs, "Source")? {
+            1 => TempoSource::Tap,
+            2 => TempoSource::Audio,
+            _ => TempoSource::Manual,
+        };
+        let beats_per_bar = read_int_input(request.inputs, "Beats per Bar")?.max(1);
+
+        let tap = read_bool_input(request.inputs, "Tap")?;
+        let tap_held = self.tap_held.insert(request.node_id, tap).unwrap_or(false);
+
+        match source {
+            TempoSource::Manual => {
+                let bpm = read_float_input(request.inputs, "BPM")?;
+                let last_bpm = self.last_manual_bpm.insert(request.node_id, bpm);
+                let alone = self.link.as_ref().is_none_or(|link| link.peer_count() == 0);
+                if alone || last_bpm.is_some_and(|last_bpm| last_bpm != bpm) {
+                    self.set_bpm(bpm as f64);
+                }
+            }
+            TempoSource::Tap => {
+                if tap && !tap_held {
+                    self.clock.tap();
+                    self.set_bpm(self.clock.bpm());
+                }
+            }
+            TempoSource::Audio => {
+                let level = read_float_input(request.inputs, "Audio Level")?;
+                let sensitivity = read_float_input(request.inputs, "Sensitivity")?;
+                if self.detector.process(level, self.clock.time(), sensitivity) {
+                    if let Some(bpm) = self.detector.bpm() {
+                        self.set_bpm(bpm);
+                    }
+                    self.clock.nudge_to_beat();
+                }
+            }
+        }
+
+        let beat = self.clock.beat();
+        let whole_beat = beat.floor() as i64;
+        let on_beat = self
+            .last_beat
+            .insert(request.node_id, whole_beat)
+            .is_some_and(|last_beat| last_beat != whole_beat);
+        let bar_phase = (beat / beats_per_bar as f64).rem_euclid(1.0);
+
+        Ok(vec![
+            NodeValue::Float(self.clock.bpm() as f32),
+            NodeValue::Float(beat as f32),
+            NodeValue::Float(self.clock.beat_phase() as f32),
+            NodeValue::Float(bar_phase as f32),
+            NodeValue::Bool(on_beat),
+        ])
+    }
+
+    /// Output an LFO node's waveform at the clock's current beat.
+    pub fn execute_lfo(
+        &self,
+        request: &NodeLfoRequest,
+    ) -> Result<Vec<NodeValue>, TempoHandlerError> {
+        let shape = LfoShape::ALL
+            .get(read_enum_input(request.inputs, "Shape")?)
+            .copied()
+            .unwrap_or(LfoShape::Sine);
+        let rate_beats = read_float_input(request.inputs, "Rate (beats)")?.max(1.0 / 64.0);
+        let phase = read_float_input(request.inputs, "Phase")?;
+
+        // Nodes get their own random values.
+        let mut hasher = DefaultHasher::new();
+        request.node_id.hash(&mut hasher);
+        let seed = hasher.finish();
+
+        let cycles = self.clock.beat() / rate_beats as f64 + phase as f64;
+        Ok(vec![NodeValue::Float(shape.value(cycles, seed))])
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, TempoHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(TempoHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(TempoHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_int_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<i32, TempoHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Int(value)) => Ok(*value),
+        Some(NodeValue::Float(value)) => Ok(*value as i32),
+        Some(_) => Err(TempoHandlerError::InvalidInput {
+            input_name,
+            expected: "Int",
+        }),
+        None => Err(TempoHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, TempoHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(TempoHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(TempoHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_enum_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<usize, TempoHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Enum(index)) => Ok(*index),
+        Some(_) => Err(TempoHandlerError::InvalidInput {
+            input_name,
+            expected: "Enum",
+        }),
+        None => Err(TempoHandlerError::MissingInput { input_name }),
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use crate::graph_executor::NodeValue;
+
+/// The numeric inputs a Text Template node can refer to, in placeholder order
+/// (`{0}` is the first).
+const VALUE_INPUT_NAMES: [&str; 4] = ["Value 0", "Value 1", "Value 2", "Value 3"];
+
+/// The most decimal places a placeholder can ask for.
+const MAX_PRECISION: usize = 9;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TextTemplateHandlerError {
+    #[error("text template input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("text template input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+/// Run a Text Template node, which fills the placeholders in its "Template"
+/// input with its value inputs.
+pub fn execute_text_template(
+    inputs: &HashMap<String, NodeValue>,
+) -> Result<Vec<NodeValue>, TextTemplateHandlerError> {
+    let template = match inputs.get("Template") {
+        Some(NodeValue::Text(template)) => template,
+        Some(_) => {
+            return Err(TextTemplateHandlerError::InvalidInput {
+                input_name: "Template",
+                expected: "Text",
+            });
+        }
+        None => {
+            return Err(TextTemplateHandlerError::MissingInput {
+                input_name: "Template",
+            });
+        }
+    };
+
+    let values = VALUE_INPUT_NAMES
+        .iter()
+        .map(|input_name| match inputs.get(*input_name) {
+            Some(NodeValue::Float(value)) => Ok(*value),
+            Some(NodeValue::Int(value)) => Ok(*value as f32),
+            Some(_) => Err(TextTemplateHandlerError::InvalidInput {
+                input_name,
+                expected: "Float",
+            }),
+            None => Err(TextTemplateHandlerError::MissingInput { input_name }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(vec![NodeValue::Text(format_template(template, &values))])
+}
+
+/// Replace each `{i}` in `template` with `values[i]`, or `{i:.p}` for the value
+/// rounded to `p` decimal places (e.g. "HR: {0:.0} bpm"). `{{` and `}}` are
+/// literal braces. Placeholders that don't parse or refer to a value that
+/// doesn't exist are left as they are, so mistakes show up in the text.
+fn format_template(template: &str, values: &[f32]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            output.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+
+        let placeholder = rest
+            .find('}')
+            .filter(|_| rest.starts_with('{'))
+            .and_then(|end| Some((end, format_placeholder(&rest[1..end], values)?)));
+        match placeholder {
+            Some((end, text)) => {
+                output.push_str(&text);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// The text for a placeholder's contents (what's between its braces), or
+/// [None] if it isn't a valid placeholder.
+fn format_placeholder(placeholder: &str, values: &[f32]) -> Option<String> {
+    let (index, precision) = match placeholder.split_once(":.") {
+        Some((index, precision)) => (index, Some(precision.parse::<usize>().ok()?)),
+        None => (placeholder, None),
+    };
+    let value = *values.get(index.trim().parse::<usize>().ok()?)?;
+
+    Some(match precision {
+        Some(precision) => format!("{value:.0$}", precision.min(MAX_PRECISION)),
+        None => value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- format_template() ---
+
+    #[test]
+    fn format_template_fills_placeholders() {
+        let values = [72.4, 0.126];
+
+        assert_eq!(format_template("HR: {0:.0} bpm", &values), "HR: 72 bpm");
+        assert_eq!(format_template("{1} / {1:.2}", &values), "0.126 / 0.13");
+        assert_eq!(format_template("{0:.1}{1:.1}", &values), "72.40.1");
+        assert_eq!(
+            format_template("no placeholders", &values),
+            "no placeholders"
+        );
+    }
+
+    #[test]
+    fn format_template_keeps_invalid_placeholders() {
+        let values = [1.0];
+
+        assert_eq!(format_template("{{0}} is {0}", &values), "{0} is 1");
+        assert_eq!(format_template("{5} {x} {0:.y}", &values), "{5} {x} {0:.y}");
+        assert_eq!(format_template("{0 and } {", &values), "{0 and } {");
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+use crate::thresholder::{ThresholdMethod, ThresholdState, Thresholder};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ThresholdHandlerError {
+    #[error("threshold input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("threshold input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeThresholdRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+struct MaskState {
+    threshold: ThresholdState,
+    output: Option<GpuFrame>,
+}
+
+/// Runs Threshold nodes, which turn frames into black and white masks using a
+/// fixed, Otsu, or adaptive threshold.
+///
+/// Otsu thresholds are picked on the GPU in the same submission as the frame
+/// they're for (see [Thresholder]), so nothing is read back to the CPU.
+pub struct ThresholdHandler {
+    state_cache: HashMap<EngineNodeId, MaskState>,
+    /// Created when first needed.
+    thresholder: Option<Thresholder>,
+    format: wgpu::TextureFormat,
+}
+
+impl ThresholdHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            thresholder: None,
+            format,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeThresholdRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, ThresholdHandlerError> {
+        let input = read_frame_input(request.inputs, "Input")?;
+        let method = read_method(request.inputs)?;
+        let invert = read_bool_input(request.inputs, "Invert")?;
+
+        let state = self
+            .state_cache
+            .entry(request.node_id)
+            .or_insert_with(|| MaskState {
+                threshold: ThresholdState::new(device),
+                output: None,
+            });
+
+        if state
+            .output
+            .as_ref()
+            .is_none_or(|output| output.size != input.size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("threshold_output"),
+                size: input.size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            state.output = Some(GpuFrame::new(
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                input.size,
+                Uid::generate_new(),
+            ));
+        }
+        let output = state.output.as_mut().expect("just created");
+
+        let format = self.format;
+        let thresholder = self
+            .thresholder
+            .get_or_insert_with(|| Thresholder::new(device, format));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("threshold"),
+        });
+        thresholder.threshold(
+            device,
+            queue,
+            &mut encoder,
+            input.view(),
+            input.size,
+            &mut state.threshold,
+            method,
+            invert,
+            output.view(),
+        );
+        queue.submit(Some(encoder.finish()));
+        output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(output.clone())])
+    }
+}
+
+/// Read the threshold method the node's inputs describe. The Method input's
+/// choices are in [ThresholdMethod] order.
+fn read_method(
+    inputs: &HashMap<String, NodeValue>,
+) -> Result<ThresholdMethod, ThresholdHandlerError> {
+    Ok(match inputs.get("Method") {
+        Some(NodeValue::Enum(1)) => ThresholdMethod::Otsu,
+        Some(NodeValue::Enum(2)) => ThresholdMethod::Adaptive {
+            block_size: read_float_input(inputs, "Block Size")?.max(1.0) as u32,
+            offset: read_float_input(inputs, "Offset")?,
+        },
+        _ => ThresholdMethod::Fixed(read_float_input(inputs, "Threshold")?),
+    })
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, ThresholdHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(ThresholdHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(ThresholdHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, ThresholdHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(ThresholdHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(ThresholdHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, ThresholdHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(ThresholdHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(ThresholdHandlerError::MissingInput { input_name }),
+    }
+}
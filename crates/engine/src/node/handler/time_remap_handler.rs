@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+
+/// The most output time added in one tick. Longer gaps (e.g. the engine
+/// stalled) don't jump the remapped video ahead.
+const MAX_TICK_ADVANCE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TimeRemapHandlerError {
+    #[error("time remap input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("time remap input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+    #[error("invalid time remap curve: {0}")]
+    InvalidCurve(#[from] CurveParseError),
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum CurveParseError {
+    #[error("'{0}' isn't an 'output=source' pair")]
+    NotAPair(String),
+    #[error("'{0}' isn't a time in seconds")]
+    NotATime(String),
+    #[error("'{0}' is negative")]
+    Negative(String),
+    #[error("output times must increase, but {later}s comes after {earlier}s")]
+    OutOfOrder { earlier: f64, later: f64 },
+}
+
+pub struct NodeTimeRemapRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+/// A key on a time remap curve: at `output` seconds the video shows the frame
+/// `source` seconds into it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RemapKey {
+    output: f64,
+    source: f64,
+}
+
+#[derive(Default)]
+struct RemapClock {
+    /// How much output time has played, in seconds.
+    elapsed: f64,
+    last_tick: Option<Instant>,
+}
+
+/// Works out which moment of a video a Time Remap node shows.
+///
+/// Each node has its own output clock (which follows play and pause like other
+/// streams, scaled by the node's speed). The clock is mapped to a time in the
+/// video through a curve of `output=source` keys in seconds, e.g.
+/// `0=0, 4=2, 6=2, 8=0` plays 2 seconds at half speed, freezes for 2 seconds,
+/// then plays back to the start in reverse. Between keys the source time is
+/// interpolated linearly; after the last key it holds, or the curve repeats if
+/// the node loops. An empty curve plays the video normally.
+pub struct TimeRemapHandler {
+    clocks: HashMap<EngineNodeId, RemapClock>,
+    paused: bool,
+}
+
+impl Default for TimeRemapHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeRemapHandler {
+    pub fn new() -> Self {
+        Self {
+            clocks: HashMap::new(),
+            paused: false,
+        }
+    }
+
+    /// Restart every node's output clock.
+    pub fn clear_cache(&mut self) {
+        self.clocks.clear();
+    }
+
+    pub fn pause_all_streams(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn play_all_streams(&mut self) {
+        self.paused = false;
+    }
+
+    /// Advance the node's output clock and return the time in its video (in
+    /// seconds) that should be shown.
+    pub fn source_time(
+        &mut self,
+        request: &NodeTimeRemapRequest,
+    ) -> Result<f64, TimeRemapHandlerError> {
+        let speed = read_float_input(request.inputs, "Speed")?.max(0.0) as f64;
+        let keys = parse_curve(read_text_input(request.inputs, "Curve")?)?;
+        let looped = read_bool_input(request.inputs, "Loop")?;
+        let restart = read_bool_input(request.inputs, "Restart")?;
+
+        let clock = self.clocks.entry(request.node_id).or_default();
+        let now = Instant::now();
+        let last_tick = clock.last_tick.replace(now);
+        if restart {
+            clock.elapsed = 0.0;
+        }
+        if self.paused || restart {
+            clock.last_tick = None;
+        } else if let Some(last_tick) = last_tick {
+            clock.elapsed += now
+                .duration_since(last_tick)
+                .min(MAX_TICK_ADVANCE)
+                .as_secs_f64()
+                * speed;
+        }
+
+        Ok(evaluate_curve(&keys, clock.elapsed, looped))
+    }
+}
+
+/// Parse a curve written as comma, semicolon, or newline separated
+/// `output=source` pairs of times in seconds. Output times must increase.
+fn parse_curve(text: &str) -> Result<Vec<RemapKey>, CurveParseError> {
+    let parse_time = |text: &str| {
+        let time: f64 = text
+            .trim()
+            .trim_end_matches('s')
+            .parse()
+            .map_err(|_| CurveParseError::NotATime(text.trim().to_string()))?;
+        if !time.is_finite() {
+            return Err(CurveParseError::NotATime(text.trim().to_string()));
+        }
+        if time < 0.0 {
+            return Err(CurveParseError::Negative(text.trim().to_string()));
+        }
+        Ok(time)
+    };
+
+    let mut keys: Vec<RemapKey> = Vec::new();
+    for pair in text
+        .split([',', ';', '\n'])
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+    {
+        let (output, source) = pair
+            .split_once('=')
+            .ok_or_else(|| CurveParseError::NotAPair(pair.to_string()))?;
+        let key = RemapKey {
+            output: parse_time(output)?,
+            source: parse_time(source)?,
+        };
+
+        if let Some(previous) = keys.last()
+            && key.output <= previous.output
+        {
+            return Err(CurveParseError::OutOfOrder {
+                earlier: previous.output,
+                later: key.output,
+            });
+        }
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+/// The source time shown at `output` seconds. Before the first key the curve
+/// holds its first source time. With no keys the output time is used as is.
+fn evaluate_curve(keys: &[RemapKey], output: f64, looped: bool) -> f64 {
+    let (Some(first), Some(last)) = (keys.first(), keys.last()) else {
+        return output;
+    };
+
+    let output = if looped && last.output > 0.0 {
+        output % last.output
+    } else {
+        output
+    };
+
+    if output <= first.output {
+        return first.source;
+    }
+    keys.windows(2)
+        .find(|pair| output <= pair[1].output)
+        .map_or(last.source, |pair| {
+            let t = (output - pair[0].output) / (pair[1].output - pair[0].output);
+            pair[0].source + (pair[1].source - pair[0].source) * t
+        })
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, TimeRemapHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(TimeRemapHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(TimeRemapHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_text_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a str, TimeRemapHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Text(value)) => Ok(value),
+        Some(_) => Err(TimeRemapHandlerError::InvalidInput {
+            input_name,
+            expected: "Text",
+        }),
+        None => Err(TimeRemapHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, TimeRemapHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(TimeRemapHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(TimeRemapHandlerError::MissingInput { input_name }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- parse_curve() ---
+
+    #[test]
+    fn test_parse_curve() {
+        assert_eq!(parse_curve(" \n"), Ok(vec![]));
+        assert_eq!(
+            parse_curve("0=1, 2s=0.5s\n3=0.5;"),
+            Ok(vec![
+                RemapKey {
+                    output: 0.0,
+                    source: 1.0
+                },
+                RemapKey {
+                    output: 2.0,
+                    source: 0.5
+                },
+                RemapKey {
+                    output: 3.0,
+                    source: 0.5
+                },
+            ])
+        );
+        assert_eq!(
+            parse_curve("0=0, 1"),
+            Err(CurveParseError::NotAPair("1".to_string()))
+        );
+        assert_eq!(
+            parse_curve("2=0, 1=0"),
+            Err(CurveParseError::OutOfOrder {
+                earlier: 2.0,
+                later: 1.0
+            })
+        );
+        assert_eq!(
+            parse_curve("0=-1"),
+            Err(CurveParseError::Negative("-1".to_string()))
+        );
+    }
+
+    // --- evaluate_curve() ---
+
+    #[test]
+    fn test_evaluate_curve() {
+        let keys = parse_curve("1=0, 5=2, 7=2, 9=0").unwrap();
+        assert_eq!(evaluate_curve(&keys, 0.0, false), 0.0);
+        assert_eq!(evaluate_curve(&keys, 3.0, false), 1.0);
+        assert_eq!(evaluate_curve(&keys, 6.0, false), 2.0);
+        assert_eq!(evaluate_curve(&keys, 8.5, false), 0.5);
+        assert_eq!(evaluate_curve(&keys, 12.0, false), 0.0);
+        assert_eq!(evaluate_curve(&keys, 12.0, true), 1.0);
+        assert_eq!(evaluate_curve(&[], 4.5, true), 4.5);
+    }
+}
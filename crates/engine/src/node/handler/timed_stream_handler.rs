@@ -18,6 +18,7 @@ pub trait TimedStreamHandler {
     fn stream_pause(stream: &mut Self::Stream);
     fn stream_play(stream: &mut Self::Stream);
     fn stream_set_target_fps(stream: &mut Self::Stream, target_fps: Fps);
+    fn stream_skip(stream: &mut Self::Stream, frames: usize);
 
     fn pause_all_streams(&mut self) {
         self.set_paused_state(true);
@@ -48,6 +49,18 @@ pub trait TimedStreamHandler {
         });
     }
 
+    /// Move every playing stream `frames` frames along, as if it had been
+    /// fetched that many more times. Used when the graph runs less often than
+    /// the streams' target FPS.
+    fn skip_frames(&mut self, frames: usize) {
+        if self.is_paused_state() {
+            return;
+        }
+        self.for_each_stream_mut(|_, stream| {
+            Self::stream_skip(stream, frames);
+        });
+    }
+
     fn set_playback_for_nodes(&mut self, active_nodes: &HashSet<EngineNodeId>) {
         let paused = self.is_paused_state();
 
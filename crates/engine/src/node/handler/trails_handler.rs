@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+use crate::texture_blitter::TextureBlitter;
+use crate::trail_accumulator::{Trail, TrailAccumulator, TrailBlend, TrailSettings};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrailsHandlerError {
+    #[error("trails input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("trails input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeTrailsRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+struct TrailState {
+    trail: Trail,
+    output: GpuFrame,
+}
+
+/// Runs Trails nodes, which blend each frame over a fading accumulation of the
+/// frames before it so moving things leave trails behind them.
+///
+/// Each node keeps its accumulation on the GPU between executions (see
+/// [Trail]). It's restarted when the input changes size, when the node's Clear
+/// input is set, and when the cache is cleared.
+pub struct TrailsHandler {
+    state_cache: HashMap<EngineNodeId, TrailState>,
+    /// Created when first needed.
+    accumulator: Option<TrailAccumulator>,
+    blitter: Option<TextureBlitter>,
+    format: wgpu::TextureFormat,
+}
+
+impl TrailsHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            accumulator: None,
+            blitter: None,
+            format,
+        }
+    }
+
+    /// Drop every accumulation, so trails start again from nothing.
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeTrailsRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, TrailsHandlerError> {
+        let input = read_frame_input(request.inputs, "Input")?;
+        let [red, green, blue, _] = read_pixel_input(request.inputs, "Tint")?;
+        let settings = TrailSettings {
+            decay: read_float_input(request.inputs, "Decay")?,
+            blend: blend_input(request.inputs),
+            tint: [red, green, blue],
+        };
+        let clear = read_bool_input(request.inputs, "Clear")?;
+
+        let format = self.format;
+        if clear
+            || self
+                .state_cache
+                .get(&request.node_id)
+                .is_none_or(|state| state.trail.size() != input.size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("trails_output"),
+                size: input.size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let state = TrailState {
+                trail: Trail::new(device, input.size),
+                output: GpuFrame::new(
+                    texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    input.size,
+                    Uid::generate_new(),
+                ),
+            };
+            self.state_cache.insert(request.node_id, state);
+        }
+        let state = self
+            .state_cache
+            .get_mut(&request.node_id)
+            .expect("just inserted");
+
+        let accumulator = self
+            .accumulator
+            .get_or_insert_with(|| TrailAccumulator::new(device));
+        let blitter = self
+            .blitter
+            .get_or_insert_with(|| TextureBlitter::new(device, format));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("trails"),
+        });
+        accumulator.accumulate(
+            device,
+            queue,
+            &mut encoder,
+            input.view(),
+            &mut state.trail,
+            settings,
+        );
+        blitter.blit(
+            device,
+            &mut encoder,
+            state.trail.view(),
+            state.output.view(),
+        );
+        queue.submit(Some(encoder.finish()));
+        state.output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(state.output.clone())])
+    }
+}
+
+/// Read the Blend input, whose choices are in [TrailBlend] order.
+fn blend_input(inputs: &HashMap<String, NodeValue>) -> TrailBlend {
+    match inputs.get("Blend") {
+        Some(NodeValue::Enum(1)) => TrailBlend::Add,
+        Some(NodeValue::Enum(2)) => TrailBlend::Screen,
+        Some(NodeValue::Enum(3)) => TrailBlend::Average,
+        _ => TrailBlend::Lighten,
+    }
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, TrailsHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(TrailsHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(TrailsHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, TrailsHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(TrailsHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(TrailsHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_pixel_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<[f32; 4], TrailsHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Pixel(value)) => Ok(*value),
+        Some(_) => Err(TrailsHandlerError::InvalidInput {
+            input_name,
+            expected: "Pixel",
+        }),
+        None => Err(TrailsHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, TrailsHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(TrailsHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(TrailsHandlerError::MissingInput { input_name }),
+    }
+}
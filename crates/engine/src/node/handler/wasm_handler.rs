@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::graph_executor::NodeValue;
+use crate::node::NodeDefinition;
+use crate::node::engine_node::NodeExecutionPlan;
+use crate::node_graph::EngineNodeId;
+use crate::wasm_nodes::{self, WasmLimits, WasmModule, WasmNodeError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WasmHandlerError {
+    #[error("node '{0}' isn't a WASM node")]
+    NotAWasmNode(String),
+    #[error(transparent)]
+    Module(#[from] WasmNodeError),
+}
+
+pub struct NodeWasmRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub definition: &'a NodeDefinition,
+    pub inputs: &'a HashMap<String, NodeValue>,
+    /// How long since the last execution, in the project's time.
+    pub delta_secs: f32,
+}
+
+struct ModuleState {
+    path: PathBuf,
+    /// The module, or why it couldn't be loaded (kept so a broken module
+    /// isn't loaded again every frame).
+    module: Result<Box<dyn WasmModule>, WasmNodeError>,
+}
+
+/// Runs WASM nodes (see [crate::wasm_nodes]). Each node gets its own
+/// instance of its module, kept until the cache is cleared.
+pub struct WasmHandler {
+    state_cache: HashMap<EngineNodeId, ModuleState>,
+}
+
+impl Default for WasmHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WasmHandler {
+    pub fn new() -> Self {
+        Self {
+            state_cache: HashMap::new(),
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeWasmRequest,
+    ) -> Result<HashMap<String, NodeValue>, WasmHandlerError> {
+        let definition = request.definition;
+        let (
+            NodeExecutionPlan::Wasm {
+                time_limit_ms,
+                memory_limit_mib,
+                ..
+            },
+            Some(path),
+        ) = (&definition.node.executor, definition.wasm_module_path())
+        else {
+            return Err(WasmHandlerError::NotAWasmNode(definition.node.name.clone()));
+        };
+
+        if self
+            .state_cache
+            .get(&request.node_id)
+            .is_none_or(|state| state.path != path)
+        {
+            let limits = WasmLimits::new(*time_limit_ms, *memory_limit_mib);
+            let module = wasm_nodes::load_module(&path, limits);
+            self.state_cache
+                .insert(request.node_id, ModuleState { path, module });
+        }
+        let state = self
+            .state_cache
+            .get_mut(&request.node_id)
+            .expect("just inserted");
+        let module = state.module.as_mut().map_err(|error| error.clone())?;
+
+        let output = module.run(&wasm_nodes::encode_inputs(
+            request.inputs,
+            request.delta_secs,
+        ))?;
+        Ok(wasm_nodes::decode_outputs(definition, &output)?)
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use image::RgbaImage;
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+use crate::watermark_compositor::{
+    WatermarkCompositor, WatermarkPosition, rasterize_text, watermark_rect,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WatermarkHandlerError {
+    #[error("watermark input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("watermark input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+    #[error("failed to load watermark image '{path}': {message}")]
+    Image { path: PathBuf, message: String },
+}
+
+pub struct NodeWatermarkRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+/// What a mark was made from, to tell when it has to be made again.
+#[derive(Debug, Clone, PartialEq)]
+enum MarkSource {
+    Image(PathBuf),
+    Text {
+        text: String,
+        size: f32,
+        color: [f32; 4],
+    },
+}
+
+struct Mark {
+    source: MarkSource,
+    view: wgpu::TextureView,
+    size: (u32, u32),
+}
+
+#[derive(Default)]
+struct WatermarkState {
+    /// [None] while there's nothing to draw (no image or text).
+    mark: Option<Mark>,
+    output: Option<GpuFrame>,
+}
+
+/// Runs Watermark nodes, which draw an image or a line of text over a frame
+/// at one of its corners or its center (e.g. a logo or "PREVIEW").
+///
+/// The mark is only loaded (or its text drawn) again when its image or text
+/// settings change. Without an image or text the input is passed through.
+pub struct WatermarkHandler {
+    state_cache: HashMap<EngineNodeId, WatermarkState>,
+    /// Created when first needed.
+    compositor: Option<WatermarkCompositor>,
+    format: wgpu::TextureFormat,
+}
+
+impl WatermarkHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            compositor: None,
+            format,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeWatermarkRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, WatermarkHandlerError> {
+        let input = match request.inputs.get("Input") {
+            Some(NodeValue::Frame(frame)) => frame,
+            Some(_) => {
+                return Err(WatermarkHandlerError::InvalidInput {
+                    input_name: "Input",
+                    expected: "Frame",
+                });
+            }
+            None => {
+                return Err(WatermarkHandlerError::MissingInput {
+                    input_name: "Input",
+                });
+            }
+        };
+        let source = read_mark_source(request.inputs)?;
+        let position = match request.inputs.get("Position") {
+            Some(NodeValue::Enum(index)) => WatermarkPosition::ALL
+                .get(*index)
+                .copied()
+                .unwrap_or_default(),
+            _ => WatermarkPosition::default(),
+        };
+        let opacity = read_float_input(request.inputs, "Opacity")?;
+        let margin = read_float_input(request.inputs, "Margin")?;
+        let scale = read_float_input(request.inputs, "Scale")?;
+
+        let state = self.state_cache.entry(request.node_id).or_default();
+        if state.mark.as_ref().map(|mark| &mark.source) != source.as_ref() {
+            state.mark = match source {
+                Some(source) => load_mark(device, queue, source)?,
+                None => None,
+            };
+        }
+        let Some(mark) = &state.mark else {
+            return Ok(vec![NodeValue::Frame(input.clone())]);
+        };
+
+        let size = input.size;
+        let format = self.format;
+        if state
+            .output
+            .as_ref()
+            .is_none_or(|output| output.size != size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("watermark_output"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            state.output = Some(GpuFrame::new(
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                size,
+                Uid::generate_new(),
+            ));
+        }
+        let output = state.output.as_mut().expect("just created");
+
+        let compositor = self
+            .compositor
+            .get_or_insert_with(|| WatermarkCompositor::new(device, format));
+        let rect = watermark_rect(
+            (size.width, size.height),
+            mark.size,
+            position,
+            margin,
+            scale,
+        );
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("watermark"),
+        });
+        compositor.draw(
+            device,
+            queue,
+            &mut encoder,
+            input.view(),
+            &mark.view,
+            rect,
+            opacity,
+            output.view(),
+            size,
+        );
+        queue.submit(Some(encoder.finish()));
+        output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(output.clone())])
+    }
+}
+
+/// Read what the mark is made from, or [None] if the chosen image or text is
+/// empty. The Mark input's choices are Image then Text.
+fn read_mark_source(
+    inputs: &HashMap<String, NodeValue>,
+) -> Result<Option<MarkSource>, WatermarkHandlerError> {
+    match inputs.get("Mark") {
+        Some(NodeValue::Enum(1)) => {
+            let text = match inputs.get("Text") {
+                Some(NodeValue::Text(text)) => text,
+                Some(_) => {
+                    return Err(WatermarkHandlerError::InvalidInput {
+                        input_name: "Text",
+                        expected: "Text",
+                    });
+                }
+                None => return Err(WatermarkHandlerError::MissingInput { input_name: "Text" }),
+            };
+            if text.trim().is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(MarkSource::Text {
+                text: text.clone(),
+                size: read_float_input(inputs, "Text Size")?,
+                color: match inputs.get("Text Color") {
+                    Some(NodeValue::Pixel(color)) => *color,
+                    Some(_) => {
+                        return Err(WatermarkHandlerError::InvalidInput {
+                            input_name: "Text Color",
+                            expected: "Pixel",
+                        });
+                    }
+                    None => {
+                        return Err(WatermarkHandlerError::MissingInput {
+                            input_name: "Text Color",
+                        });
+                    }
+                },
+            }))
+        }
+        _ => match inputs.get("Image") {
+            Some(NodeValue::File(path)) if path.as_os_str().is_empty() => Ok(None),
+            Some(NodeValue::File(path)) => Ok(Some(MarkSource::Image(path.clone()))),
+            Some(_) => Err(WatermarkHandlerError::InvalidInput {
+                input_name: "Image",
+                expected: "File",
+            }),
+            None => Err(WatermarkHandlerError::MissingInput {
+                input_name: "Image",
+            }),
+        },
+    }
+}
+
+/// Load or draw the mark `source` describes and upload it. Returns [None] if
+/// there's nothing to draw. Images are loaded on the spot rather than in the
+/// background, since they're usually small logos.
+fn load_mark(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    source: MarkSource,
+) -> Result<Option<Mark>, WatermarkHandlerError> {
+    let image: RgbaImage = match &source {
+        MarkSource::Image(path) => image::open(path)
+            .map_err(|e| WatermarkHandlerError::Image {
+                path: path.clone(),
+                message: e.to_string(),
+            })?
+            .to_rgba8(),
+        MarkSource::Text { text, size, color } => match rasterize_text(text, *size, *color) {
+            Some(image) => image,
+            None => return Ok(None),
+        },
+    };
+
+    let size = wgpu::Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("watermark_mark"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        image.as_raw(),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(image.width() * 4),
+            rows_per_image: Some(image.height()),
+        },
+        size,
+    );
+
+    Ok(Some(Mark {
+        source,
+        view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        size: (image.width(), image.height()),
+    }))
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, WatermarkHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(WatermarkHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(WatermarkHandlerError::MissingInput { input_name }),
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use media::frame::Uid;
+
+use crate::gpu_frame::GpuFrame;
+use crate::graph_executor::NodeValue;
+use crate::node_graph::EngineNodeId;
+use crate::white_balancer::{
+    AutoBalance, AutoMethod, AutoSettings, BalanceSettings, WhiteBalancer,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WhiteBalanceHandlerError {
+    #[error("white balance input '{input_name}' is missing")]
+    MissingInput { input_name: &'static str },
+    #[error("white balance input '{input_name}' must be a {expected}")]
+    InvalidInput {
+        input_name: &'static str,
+        expected: &'static str,
+    },
+}
+
+pub struct NodeWhiteBalanceRequest<'a> {
+    pub node_id: EngineNodeId,
+    pub inputs: &'a HashMap<String, NodeValue>,
+}
+
+struct BalanceState {
+    auto_balance: AutoBalance,
+    /// Whether automatic correction ran last frame. Its gains are forgotten
+    /// whenever it's turned back on.
+    was_auto: bool,
+    output: Option<GpuFrame>,
+}
+
+/// Runs White Balance nodes, which adjust exposure, temperature, and tint, and
+/// can also correct white balance and exposure automatically each frame.
+///
+/// Automatic correction is measured and applied on the GPU in the same
+/// submission as the frame it's for (see [WhiteBalancer]), and smoothed over
+/// time per node so it doesn't flicker.
+pub struct WhiteBalanceHandler {
+    state_cache: HashMap<EngineNodeId, BalanceState>,
+    /// Created when first needed.
+    balancer: Option<WhiteBalancer>,
+    format: wgpu::TextureFormat,
+}
+
+impl WhiteBalanceHandler {
+    /// Create a handler that outputs `format` textures.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            state_cache: HashMap::new(),
+            balancer: None,
+            format,
+        }
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.state_cache.clear();
+    }
+
+    pub fn execute_handler(
+        &mut self,
+        request: &NodeWhiteBalanceRequest,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<NodeValue>, WhiteBalanceHandlerError> {
+        let input = read_frame_input(request.inputs, "Input")?;
+        let auto = read_bool_input(request.inputs, "Auto")?;
+        let settings = BalanceSettings {
+            exposure: read_float_input(request.inputs, "Exposure")?,
+            temperature: read_float_input(request.inputs, "Temperature")?,
+            tint: read_float_input(request.inputs, "Tint")?,
+            auto_balance: auto,
+        };
+
+        let state = self
+            .state_cache
+            .entry(request.node_id)
+            .or_insert_with(|| BalanceState {
+                auto_balance: AutoBalance::new(device),
+                was_auto: false,
+                output: None,
+            });
+        if auto && !state.was_auto {
+            state.auto_balance.reset(queue);
+        }
+        state.was_auto = auto;
+
+        if state
+            .output
+            .as_ref()
+            .is_none_or(|output| output.size != input.size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("white_balance_output"),
+                size: input.size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            state.output = Some(GpuFrame::new(
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                input.size,
+                Uid::generate_new(),
+            ));
+        }
+        let output = state.output.as_mut().expect("just created");
+
+        let format = self.format;
+        let balancer = self
+            .balancer
+            .get_or_insert_with(|| WhiteBalancer::new(device, format));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("white_balance"),
+        });
+        if auto {
+            let auto_settings = AutoSettings {
+                method: auto_method_input(request.inputs),
+                exposure: read_bool_input(request.inputs, "Auto Exposure")?,
+                smoothing: read_float_input(request.inputs, "Smoothing")?,
+            };
+            balancer.analyze(
+                device,
+                queue,
+                &mut encoder,
+                input.view(),
+                &state.auto_balance,
+                auto_settings,
+            );
+        }
+        balancer.apply(
+            device,
+            queue,
+            &mut encoder,
+            input.view(),
+            &state.auto_balance,
+            settings,
+            output.view(),
+        );
+        queue.submit(Some(encoder.finish()));
+        output.frame_id = Uid::generate_new();
+
+        Ok(vec![NodeValue::Frame(output.clone())])
+    }
+}
+
+/// Read the Auto Method input, whose choices are in [AutoMethod] order.
+fn auto_method_input(inputs: &HashMap<String, NodeValue>) -> AutoMethod {
+    match inputs.get("Auto Method") {
+        Some(NodeValue::Enum(1)) => AutoMethod::WhitePatch,
+        _ => AutoMethod::GrayWorld,
+    }
+}
+
+fn read_frame_input<'a>(
+    inputs: &'a HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<&'a GpuFrame, WhiteBalanceHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Frame(frame)) => Ok(frame),
+        Some(_) => Err(WhiteBalanceHandlerError::InvalidInput {
+            input_name,
+            expected: "Frame",
+        }),
+        None => Err(WhiteBalanceHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_float_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<f32, WhiteBalanceHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Float(value)) => Ok(*value),
+        Some(NodeValue::Int(value)) => Ok(*value as f32),
+        Some(_) => Err(WhiteBalanceHandlerError::InvalidInput {
+            input_name,
+            expected: "Float",
+        }),
+        None => Err(WhiteBalanceHandlerError::MissingInput { input_name }),
+    }
+}
+
+fn read_bool_input(
+    inputs: &HashMap<String, NodeValue>,
+    input_name: &'static str,
+) -> Result<bool, WhiteBalanceHandlerError> {
+    match inputs.get(input_name) {
+        Some(NodeValue::Bool(value)) => Ok(*value),
+        Some(_) => Err(WhiteBalanceHandlerError::InvalidInput {
+            input_name,
+            expected: "Bool",
+        }),
+        None => Err(WhiteBalanceHandlerError::MissingInput { input_name }),
+    }
+}
@@ -1,7 +1,8 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use super::errors::LibraryError;
 use crate::node::EngineNode;
+use crate::node::engine_node::NodeExecutionPlan;
 
 /// A loaded node definition with resolved paths
 #[derive(Debug, Clone)]
@@ -14,17 +15,54 @@ pub struct NodeDefinition {
 
     /// Absolute path to the node's folder
     pub folder_path: PathBuf,
+
+    /// Absolute paths to the node's example images (see
+    /// [`EngineNode::example_images`]) that existed when it was loaded, so the
+    /// help UI doesn't have to check the disk every frame it's shown.
+    pub example_image_paths: Vec<PathBuf>,
 }
 
 impl NodeDefinition {
-    /// Load the shader code for this node
+    /// Load the shader code for this node, after the files it includes
     pub fn load_shader_code(&self) -> Result<String, LibraryError> {
         let shader_path = self
             .shader_path
             .as_ref()
             .ok_or_else(|| LibraryError::NotAShaderNode(self.node.name.clone()))?;
 
-        std::fs::read_to_string(shader_path)
-            .map_err(|e| LibraryError::IoError(shader_path.clone(), e))
+        let mut code = String::new();
+        if let NodeExecutionPlan::Shader { includes, .. } = &self.node.executor {
+            for include in includes {
+                let include_path = self.folder_path.join(include);
+                let include_code = std::fs::read_to_string(&include_path)
+                    .map_err(|e| LibraryError::IoError(include_path, e))?;
+                code.push_str(&include_code);
+                code.push('\n');
+            }
+        }
+        code.push_str(
+            &std::fs::read_to_string(shader_path)
+                .map_err(|e| LibraryError::IoError(shader_path.clone(), e))?,
+        );
+        Ok(code)
+    }
+
+    /// Absolute path to the node's WebAssembly module (if this is a WASM node)
+    pub fn wasm_module_path(&self) -> Option<PathBuf> {
+        match &self.node.executor {
+            NodeExecutionPlan::Wasm { module, .. } => Some(self.folder_path.join(module)),
+            _ => None,
+        }
+    }
+
+    /// Absolute paths to this node's example images (see
+    /// [`EngineNode::example_images`]). Images that don't exist are skipped.
+    /// This checks the disk; use [Self::example_image_paths] instead.
+    pub(super) fn find_example_images(node: &EngineNode, folder_path: &Path) -> Vec<PathBuf> {
+        node.example_images
+            .iter()
+            .map(|image| folder_path.join(image))
+            .filter(|path| path.is_file())
+            .collect()
     }
 }
@@ -1,11 +1,13 @@
 use std::collections::HashMap;
-use std::collections::hash_map::Entry;
+use std::collections::hash_map::{DefaultHasher, Entry};
 use std::env;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 
 use serde_json;
 
-use super::engine_node::{EngineNode, NodeExecutionPlan};
+use super::embedded_nodes;
+use super::engine_node::{EngineNode, NodeExecutionPlan, NodeOutputKind};
 use super::errors::LibraryError;
 use super::node_definition::NodeDefinition;
 
@@ -140,11 +142,34 @@ impl NodeLibrary {
         Ok(library)
     }
 
+    /// Load only the prebuilt nodes, skipping the users nodes folder (e.g. when
+    /// the editor is started in safe mode).
+    pub fn load_built_in() -> Result<Self, LibraryError> {
+        Self::load_from_disk()
+    }
+
     /// Get all node definitions
     pub fn definitions(&self) -> &HashMap<String, NodeDefinition> {
         &self.definitions
     }
 
+    /// A hash of every definition's name and node.json contents, for telling
+    /// whether two libraries have the same nodes. It's only comparable between
+    /// builds of the same app version.
+    pub fn content_hash(&self) -> u64 {
+        let mut names: Vec<&String> = self.definitions.keys().collect();
+        names.sort();
+
+        let mut hasher = DefaultHasher::new();
+        for name in names {
+            name.hash(&mut hasher);
+            serde_json::to_string(&self.definitions[name].node)
+                .unwrap_or_default()
+                .hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
     /// Get all node names
     pub fn node_names(&self) -> Vec<String> {
         self.definitions.keys().cloned().collect()
@@ -201,29 +226,80 @@ impl NodeLibrary {
         subcategories
     }
 
-    /// Load all node definitions from the nodes/ folder
+    /// Load all node definitions from the nodes/ folder. Stock nodes it's
+    /// missing (or that fail to load, or whose files don't match the app's) are
+    /// loaded from the copies embedded in the app instead.
     fn load_from_disk() -> Result<Self, LibraryError> {
-        let nodes_folder = Self::resolve_nodes_path()?;
-
         let mut definitions = HashMap::new();
 
-        // Recursively scan for node.json files
-        Self::scan_directory(&nodes_folder, &nodes_folder, &mut definitions)?;
+        let nodes_folder = Self::resolve_nodes_path();
+        match &nodes_folder {
+            Ok(nodes_folder) => {
+                // Recursively scan for node.json files
+                if let Err(e) = Self::scan_directory(nodes_folder, nodes_folder, &mut definitions) {
+                    util::debug_log_error!("Failed to scan {nodes_folder:?}: {e}");
+                }
 
-        if cfg!(debug_assertions) {
-            util::debug_log_info!(
-                "Loaded {} node definitions from {:?}",
-                definitions.len(),
-                nodes_folder
-            );
+                // A shader that doesn't match could fail to compile (or do
+                // something else) mid-session.
+                for name in embedded_nodes::mismatched_nodes(&definitions) {
+                    util::debug_log_warning!(
+                        "Stock node '{name}' doesn't match the app's copy, using the embedded one."
+                    );
+                    definitions.remove(&name);
+                }
+
+                if cfg!(debug_assertions) {
+                    util::debug_log_info!(
+                        "Loaded {} node definitions from {:?}",
+                        definitions.len(),
+                        nodes_folder
+                    );
+                }
+            }
+            Err(e) => util::debug_log_error!("Only embedded nodes will be available: {e}"),
+        }
+
+        let embedded_folder = Self::add_embedded_nodes(&mut definitions);
+        if definitions.is_empty()
+            && let Err(e) = nodes_folder
+        {
+            return Err(e);
         }
 
         Ok(Self {
             definitions,
-            _nodes_folder: nodes_folder,
+            _nodes_folder: nodes_folder.ok().or(embedded_folder).unwrap_or_default(),
         })
     }
 
+    /// Add the [embedded](embedded_nodes) stock nodes that aren't in
+    /// `definitions`, returning the folder they were unpacked to if any were
+    /// needed.
+    fn add_embedded_nodes(definitions: &mut HashMap<String, NodeDefinition>) -> Option<PathBuf> {
+        let missing = embedded_nodes::node_names()
+            .into_iter()
+            .filter(|name| !definitions.contains_key(name))
+            .count();
+        if missing == 0 {
+            return None;
+        }
+
+        let embedded_folder = embedded_nodes::unpack()
+            .inspect_err(|e| util::debug_log_error!("Failed to unpack embedded nodes: {e}"))
+            .ok()?;
+        let mut embedded = HashMap::new();
+        if let Err(e) = Self::scan_directory(&embedded_folder, &embedded_folder, &mut embedded) {
+            util::debug_log_error!("Failed to scan embedded nodes: {e}");
+        }
+
+        util::debug_log_warning!("Using the embedded copies of {missing} stock node(s).");
+        for (name, definition) in embedded {
+            definitions.entry(name).or_insert(definition);
+        }
+        Some(embedded_folder)
+    }
+
     fn load_from_users_folder() -> Result<Self, LibraryError> {
         use util::local_data;
 
@@ -304,20 +380,49 @@ impl NodeLibrary {
             .map_err(|e| LibraryError::ParseError(node_json.clone(), e.to_string()))?;
 
         // Resolve shader file path if this is a shader node
-        let shader_path = if let NodeExecutionPlan::Shader { source, .. } = &node.executor {
+        let shader_path = if let NodeExecutionPlan::Shader {
+            source, includes, ..
+        } = &node.executor
+        {
             let absolute_path = node_folder.join(source);
 
-            // Verify shader file exists
+            // Verify shader file and the files it includes exist
             if !absolute_path.exists() {
                 return Err(LibraryError::ShaderNotFound(absolute_path));
             }
+            for include in includes {
+                let include_path = node_folder.join(include);
+                if !include_path.exists() {
+                    return Err(LibraryError::ShaderNotFound(include_path));
+                }
+            }
 
             Some(absolute_path)
         } else {
             None
         };
 
+        // WASM nodes only exchange plain values with their module
+        if let NodeExecutionPlan::Wasm { module, .. } = &node.executor {
+            let module_path = node_folder.join(module);
+            if !module_path.exists() {
+                return Err(LibraryError::WasmModuleNotFound(module_path));
+            }
+            if let Some(output) = node.outputs.iter().find(|output| {
+                matches!(
+                    output.kind,
+                    NodeOutputKind::Frame | NodeOutputKind::MidiPacket
+                )
+            }) {
+                return Err(LibraryError::UnsupportedWasmOutput(
+                    node.name.clone(),
+                    output.kind,
+                ));
+            }
+        }
+
         Ok(NodeDefinition {
+            example_image_paths: NodeDefinition::find_example_images(&node, node_folder),
             node,
             shader_path,
             folder_path: node_folder.to_path_buf(),
@@ -2,7 +2,8 @@
 //!
 //! Provides [NodeInstance], [Connection], and [NodeGraph] for building and
 //! mutating node graphs, plus utilities such as topological sorting to compute
-//! execution order.
+//! execution order. Graphs can be changed in place with [GraphEdit]s (see
+//! [NodeGraph::diff]).
 use std::collections::HashMap;
 use std::path::PathBuf;
 
@@ -10,6 +11,12 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use util::uid::Uid;
 
+mod graph_edit;
+
+pub use graph_edit::GraphEdit;
+
+use crate::tone_curve::ToneCurve;
+
 /// Unique identifier for a node instance in the graph
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, PartialOrd, Ord, Default,
@@ -51,6 +58,134 @@ pub struct Connection {
 
     /// Name of the input on the destination node
     pub to_input: String,
+
+    /// How a Float input follows the output it's connected to. [None] follows
+    /// it exactly.
+    #[serde(default)]
+    pub smoothing: Option<InputSmoothing>,
+
+    /// How a Float input's connected value is mapped before it's used (and
+    /// before it's smoothed). [None] uses it as is.
+    #[serde(default)]
+    pub mapping: Option<InputMapping>,
+}
+
+/// Maps the value of a connected Float input from one range to another, for
+/// controls (MIDI knobs, OSC, audio levels) whose values don't match the range
+/// the input expects.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputMapping {
+    /// The range of connected values, as `(min, max)`. Values outside it are
+    /// clamped.
+    pub input_range: (f32, f32),
+    /// The range the input gets, as `(min, max)`.
+    pub output_range: (f32, f32),
+    pub curve: MappingCurve,
+    /// Map the top of the input range to the bottom of the output range.
+    pub invert: bool,
+    /// Hold the input's value until the control is moved to (or past) it, so
+    /// a knob that's out of sync with the input doesn't make it jump.
+    pub soft_takeover: bool,
+}
+
+impl Default for InputMapping {
+    fn default() -> Self {
+        Self {
+            input_range: (0.0, 1.0),
+            output_range: (0.0, 1.0),
+            curve: MappingCurve::default(),
+            invert: false,
+            soft_takeover: false,
+        }
+    }
+}
+
+impl InputMapping {
+    /// The value `value` is mapped to.
+    pub fn map(&self, value: f32) -> f32 {
+        let (in_min, in_max) = self.input_range;
+        let mut t = if in_max == in_min {
+            0.0
+        } else {
+            ((value - in_min) / (in_max - in_min)).clamp(0.0, 1.0)
+        };
+        if !t.is_finite() {
+            t = 0.0;
+        }
+        if self.invert {
+            t = 1.0 - t;
+        }
+
+        let (out_min, out_max) = self.output_range;
+        out_min + (out_max - out_min) * self.curve.shape(t)
+    }
+}
+
+/// The shape of an [InputMapping] between the ends of its ranges.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum MappingCurve {
+    #[default]
+    Linear,
+    /// Changes slowly at the bottom of the range and quickly at the top, which
+    /// suits things like zoom and speed.
+    Exponential,
+    /// The opposite of [MappingCurve::Exponential]: quick at the bottom, slow at
+    /// the top.
+    Logarithmic,
+    /// A spline through `[x, y]` points from `0.0` to `1.0` (see [ToneCurve]).
+    Custom(Vec<[f32; 2]>),
+}
+
+impl MappingCurve {
+    /// How steep [MappingCurve::Exponential] and [MappingCurve::Logarithmic]
+    /// are.
+    const STEEPNESS: f32 = 4.0;
+
+    /// The value `t` (from `0.0` to `1.0`) is shaped to.
+    pub fn shape(&self, t: f32) -> f32 {
+        match self {
+            Self::Linear => t,
+            Self::Exponential => (Self::STEEPNESS * t).exp_m1() / Self::STEEPNESS.exp_m1(),
+            Self::Logarithmic => (Self::STEEPNESS.exp_m1() * t).ln_1p() / Self::STEEPNESS,
+            Self::Custom(points) => ToneCurve::new(points.iter().copied())
+                .map(|curve| curve.evaluate(t))
+                .unwrap_or(t),
+        }
+    }
+}
+
+/// Smoothing for a connected Float input, so values from live sources (MIDI,
+/// audio) that jump from frame to frame ease toward each new value instead.
+/// Applied once per frame before the input's node runs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InputSmoothing {
+    pub mode: SmoothingMode,
+    /// Seconds to follow a rising value (see [SmoothingMode]).
+    pub attack_secs: f32,
+    /// Seconds to follow a falling value (see [SmoothingMode]).
+    pub release_secs: f32,
+}
+
+impl Default for InputSmoothing {
+    fn default() -> Self {
+        Self {
+            mode: SmoothingMode::default(),
+            attack_secs: 0.1,
+            release_secs: 0.3,
+        }
+    }
+}
+
+/// How an [InputSmoothing] moves toward the connected value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SmoothingMode {
+    /// Cover a fixed fraction of the remaining distance each frame, so about
+    /// 63% of a jump is covered in the attack or release time.
+    #[default]
+    Exponential,
+    /// Move at a constant rate, so a change of 1.0 takes the attack or release
+    /// time.
+    SlewLimit,
 }
 
 /// In-memory graph used by the executor; supports mutations and topological sort.
@@ -143,6 +278,8 @@ impl NodeGraph {
             from_output: output_name.clone(),
             to_node,
             to_input: input_name.clone(),
+            smoothing: None,
+            mapping: None,
         });
 
         if let Some(instance) = self.instances.get_mut(&to_node) {
@@ -173,6 +310,195 @@ impl NodeGraph {
         removed
     }
 
+    /// Insert `node` into the connection going into the input `to_input` of
+    /// `to_node`: the connection's source is connected to `node_input`, and
+    /// `node_output` is connected to where the connection went. The downstream
+    /// connection keeps the original connection's smoothing and mapping.
+    ///
+    /// Nothing changes if this fails.
+    pub fn splice(
+        &mut self,
+        to_node: EngineNodeId,
+        to_input: &str,
+        node: EngineNodeId,
+        node_input: String,
+        node_output: String,
+    ) -> Result<(), GraphError> {
+        if !self.instances.contains_key(&node) {
+            return Err(GraphError::NodeNotFound(node));
+        }
+        let index = self
+            .connections
+            .iter()
+            .position(|c| c.to_node == to_node && c.to_input == to_input)
+            .ok_or_else(|| GraphError::InvalidInput(format!("{to_input} isn't connected")))?;
+
+        let original = &self.connections[index];
+        if original.from_node == node || original.to_node == node {
+            return Err(GraphError::SelfConnection);
+        }
+        if self
+            .connections
+            .iter()
+            .any(|c| c.to_node == node && c.to_input == node_input)
+        {
+            return Err(GraphError::InputAlreadyConnected);
+        }
+
+        let original = self.connections.remove(index);
+        self.connections.push(Connection {
+            from_node: original.from_node,
+            from_output: original.from_output.clone(),
+            to_node: node,
+            to_input: node_input.clone(),
+            smoothing: None,
+            mapping: None,
+        });
+        self.connections.push(Connection {
+            from_node: node,
+            from_output: node_output.clone(),
+            to_node: original.to_node,
+            to_input: original.to_input.clone(),
+            smoothing: original.smoothing,
+            mapping: original.mapping,
+        });
+
+        if let Some(instance) = self.instances.get_mut(&node) {
+            instance.input_values.insert(
+                node_input,
+                InputValue::Connection {
+                    from_node: original.from_node,
+                    output_name: original.from_output,
+                },
+            );
+        }
+        if let Some(instance) = self.instances.get_mut(&original.to_node) {
+            instance.input_values.insert(
+                original.to_input,
+                InputValue::Connection {
+                    from_node: node,
+                    output_name: node_output,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Remove a node instance like [Self::remove_instance], but first connect
+    /// what fed it to what it fed. `passthroughs` pairs the node's inputs with
+    /// its outputs: whatever was connected to the input is connected to
+    /// everything the output was connected to, keeping those connections'
+    /// smoothing and mapping.
+    pub fn remove_and_heal(
+        &mut self,
+        id: EngineNodeId,
+        passthroughs: &[(&str, &str)],
+    ) -> Option<NodeInstance> {
+        if !self.instances.contains_key(&id) {
+            return None;
+        }
+
+        let mut healed = Vec::new();
+        for (input, output) in passthroughs {
+            let Some(upstream) = self.get_input_connection(id, input) else {
+                continue;
+            };
+            for downstream in self
+                .connections
+                .iter()
+                .filter(|c| c.from_node == id && c.from_output == *output)
+            {
+                if downstream.to_node == upstream.from_node {
+                    continue;
+                }
+                healed.push(Connection {
+                    from_node: upstream.from_node,
+                    from_output: upstream.from_output.clone(),
+                    to_node: downstream.to_node,
+                    to_input: downstream.to_input.clone(),
+                    smoothing: downstream.smoothing,
+                    mapping: downstream.mapping.clone(),
+                });
+            }
+        }
+
+        let instance = self.remove_instance(id);
+        for connection in healed {
+            // Each downstream input was only connected to `id`, so it's only
+            // taken already if two passthroughs share an output.
+            let (to_node, to_input) = (connection.to_node, connection.to_input.clone());
+            if self.get_input_connection(to_node, &to_input).is_some() {
+                continue;
+            }
+            if let Some(downstream) = self.instances.get_mut(&to_node) {
+                downstream.input_values.insert(
+                    to_input,
+                    InputValue::Connection {
+                        from_node: connection.from_node,
+                        output_name: connection.from_output.clone(),
+                    },
+                );
+            }
+            self.connections.push(connection);
+        }
+        instance
+    }
+
+    /// Set how the connected input `input_name` of `to_node` follows the
+    /// output it's connected to ([None] to follow it exactly). Only Float
+    /// inputs are smoothed; it's ignored for anything else.
+    pub fn set_input_smoothing(
+        &mut self,
+        to_node: EngineNodeId,
+        input_name: &str,
+        smoothing: Option<InputSmoothing>,
+    ) -> Result<(), GraphError> {
+        let connection = self
+            .connections
+            .iter_mut()
+            .find(|c| c.to_node == to_node && c.to_input == input_name)
+            .ok_or_else(|| GraphError::InvalidInput(format!("{input_name} isn't connected")))?;
+        connection.smoothing = smoothing;
+        Ok(())
+    }
+
+    /// The smoothing set with [Self::set_input_smoothing] for the connected
+    /// input `input_name` of `to_node`.
+    pub fn input_smoothing(
+        &self,
+        to_node: EngineNodeId,
+        input_name: &str,
+    ) -> Option<InputSmoothing> {
+        self.get_input_connection(to_node, input_name)
+            .and_then(|c| c.smoothing)
+    }
+
+    /// Set how the connected input `input_name` of `to_node` maps the value
+    /// it's connected to ([None] to use it as is). Only Float inputs are
+    /// mapped; it's ignored for anything else.
+    pub fn set_input_mapping(
+        &mut self,
+        to_node: EngineNodeId,
+        input_name: &str,
+        mapping: Option<InputMapping>,
+    ) -> Result<(), GraphError> {
+        let connection = self
+            .connections
+            .iter_mut()
+            .find(|c| c.to_node == to_node && c.to_input == input_name)
+            .ok_or_else(|| GraphError::InvalidInput(format!("{input_name} isn't connected")))?;
+        connection.mapping = mapping;
+        Ok(())
+    }
+
+    /// The mapping set with [Self::set_input_mapping] for the connected input
+    /// `input_name` of `to_node`.
+    pub fn input_mapping(&self, to_node: EngineNodeId, input_name: &str) -> Option<&InputMapping> {
+        self.get_input_connection(to_node, input_name)
+            .and_then(|c| c.mapping.as_ref())
+    }
+
     pub fn set_input_value(
         &mut self,
         node_id: EngineNodeId,
@@ -362,6 +688,7 @@ pub enum InputValue {
     Text(String),
     Enum(usize),
     File(PathBuf),
+    FloatArray(Vec<f32>),
 }
 
 /// Errors that can occur when working with the node graph
@@ -482,4 +809,122 @@ mod tests {
         assert_eq!(graph.connections().len(), 0);
         assert_eq!(graph.instances().len(), 2);
     }
+
+    #[test]
+    fn test_splice() {
+        let mut graph = NodeGraph::new();
+
+        let node_a = graph.add_instance("A".to_string());
+        let node_b = graph.add_instance("B".to_string());
+        let node_c = graph.add_instance("C".to_string());
+
+        // A -> C, then splice B in: A -> B -> C
+        graph
+            .connect(node_a, "out".to_string(), node_c, "in".to_string())
+            .unwrap();
+        let smoothing = InputSmoothing {
+            attack_secs: 0.5,
+            ..Default::default()
+        };
+        graph
+            .set_input_smoothing(node_c, "in", Some(smoothing))
+            .unwrap();
+        graph
+            .splice(node_c, "in", node_b, "in".to_string(), "out".to_string())
+            .unwrap();
+
+        assert_eq!(graph.connections().len(), 2);
+        assert_eq!(
+            graph.get_input_connection(node_b, "in").unwrap().from_node,
+            node_a
+        );
+        assert_eq!(
+            graph.get_input_connection(node_c, "in").unwrap().from_node,
+            node_b
+        );
+        assert_eq!(graph.input_smoothing(node_b, "in"), None);
+        assert_eq!(graph.input_smoothing(node_c, "in"), Some(smoothing));
+        assert_eq!(
+            graph.execution_order().unwrap(),
+            vec![node_a, node_b, node_c]
+        );
+
+        // Splicing into an unconnected input or a node's own connection fails
+        assert!(
+            graph
+                .splice(node_a, "in", node_b, "in".to_string(), "out".to_string())
+                .is_err()
+        );
+        assert!(
+            graph
+                .splice(node_c, "in", node_b, "in".to_string(), "out".to_string())
+                .is_err()
+        );
+        assert_eq!(graph.connections().len(), 2);
+    }
+
+    #[test]
+    fn test_remove_and_heal() {
+        let mut graph = NodeGraph::new();
+
+        let node_a = graph.add_instance("A".to_string());
+        let node_b = graph.add_instance("B".to_string());
+        let node_c = graph.add_instance("C".to_string());
+        let node_d = graph.add_instance("D".to_string());
+
+        // A -> B -> C, B -> D
+        graph
+            .connect(node_a, "out".to_string(), node_b, "in".to_string())
+            .unwrap();
+        graph
+            .connect(node_b, "out".to_string(), node_c, "in".to_string())
+            .unwrap();
+        graph
+            .connect(node_b, "out".to_string(), node_d, "in".to_string())
+            .unwrap();
+
+        // Remove B, healing to A -> C, A -> D
+        assert!(graph.remove_and_heal(node_b, &[("in", "out")]).is_some());
+
+        assert_eq!(graph.instances().len(), 3);
+        assert_eq!(graph.connections().len(), 2);
+        for node in [node_c, node_d] {
+            assert_eq!(
+                graph.get_input_connection(node, "in").unwrap().from_node,
+                node_a
+            );
+            assert_eq!(
+                graph.get_instance(node).unwrap().input_values["in"],
+                InputValue::Connection {
+                    from_node: node_a,
+                    output_name: "out".to_string(),
+                }
+            );
+        }
+
+        // Nodes that aren't in the graph aren't removed
+        assert!(graph.remove_and_heal(node_b, &[("in", "out")]).is_none());
+    }
+
+    #[test]
+    fn test_input_mapping() {
+        let mut mapping = InputMapping {
+            input_range: (0.0, 127.0),
+            output_range: (-1.0, 1.0),
+            ..Default::default()
+        };
+        assert_eq!(mapping.map(0.0), -1.0);
+        assert_eq!(mapping.map(127.0), 1.0);
+        assert_eq!(mapping.map(500.0), 1.0);
+
+        mapping.invert = true;
+        assert_eq!(mapping.map(127.0), -1.0);
+
+        mapping.invert = false;
+        mapping.curve = MappingCurve::Exponential;
+        assert!(mapping.map(63.5) < 0.0);
+        mapping.curve = MappingCurve::Logarithmic;
+        assert!(mapping.map(63.5) > 0.0);
+        assert!((mapping.map(127.0) - 1.0).abs() < 1e-6);
+    }
 }
@@ -0,0 +1,438 @@
+//! Granular edits to a [NodeGraph], so a graph that changed a little can be
+//! updated in place instead of being replaced.
+//!
+//! [NodeGraph::diff] finds the edits that turn one graph into another, and
+//! [NodeGraph::apply_edit] (or [NodeGraph::apply_edits]) makes them.
+
+use std::collections::HashSet;
+
+use super::{
+    Connection, EngineNodeId, GraphError, InputMapping, InputSmoothing, InputValue, NodeGraph,
+};
+
+/// One change to a [NodeGraph].
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphEdit {
+    /// Add a node with no inputs set.
+    AddNode {
+        id: EngineNodeId,
+        definition_name: String,
+    },
+    /// Remove a node and any connections to or from it.
+    RemoveNode(EngineNodeId),
+    /// Set an input to a value (not a connection, see [Self::Connect]).
+    SetInput {
+        node_id: EngineNodeId,
+        input: String,
+        value: InputValue,
+    },
+    /// Unset an input that isn't connected, so its default is used.
+    ClearInput {
+        node_id: EngineNodeId,
+        input: String,
+    },
+    Connect {
+        from_node: EngineNodeId,
+        from_output: String,
+        to_node: EngineNodeId,
+        to_input: String,
+    },
+    Disconnect {
+        to_node: EngineNodeId,
+        to_input: String,
+    },
+    /// See [NodeGraph::set_input_smoothing].
+    SetInputSmoothing {
+        to_node: EngineNodeId,
+        to_input: String,
+        smoothing: Option<InputSmoothing>,
+    },
+    /// See [NodeGraph::set_input_mapping].
+    SetInputMapping {
+        to_node: EngineNodeId,
+        to_input: String,
+        mapping: Option<InputMapping>,
+    },
+}
+
+impl GraphEdit {
+    /// Whether the edit adds, removes or rewires nodes, which can change the
+    /// order they execute in. Other edits only change values.
+    pub fn changes_structure(&self) -> bool {
+        matches!(
+            self,
+            Self::AddNode { .. }
+                | Self::RemoveNode(_)
+                | Self::Connect { .. }
+                | Self::Disconnect { .. }
+        )
+    }
+}
+
+impl NodeGraph {
+    /// Make every edit in `edits` to the graph, in order. If any of them fails
+    /// nothing changes, so the graph is never left half edited.
+    pub fn apply_edits(&mut self, edits: Vec<GraphEdit>) -> Result<(), GraphError> {
+        let mut edited = self.clone();
+        for edit in edits {
+            edited.apply_edit(edit)?;
+        }
+        *self = edited;
+        Ok(())
+    }
+
+    /// Make one edit to the graph. Nothing changes if this fails.
+    pub fn apply_edit(&mut self, edit: GraphEdit) -> Result<(), GraphError> {
+        match edit {
+            GraphEdit::AddNode {
+                id,
+                definition_name,
+            } => {
+                self.add_instance_with_id(id, definition_name);
+                Ok(())
+            }
+            GraphEdit::RemoveNode(id) => self
+                .remove_instance(id)
+                .map(|_| ())
+                .ok_or(GraphError::NodeNotFound(id)),
+            GraphEdit::SetInput {
+                node_id,
+                input,
+                value,
+            } => self.set_input_value(node_id, input, value),
+            GraphEdit::ClearInput { node_id, input } => {
+                if self.get_input_connection(node_id, &input).is_some() {
+                    return Err(GraphError::UseConnectMethod);
+                }
+                self.instances
+                    .get_mut(&node_id)
+                    .ok_or(GraphError::NodeNotFound(node_id))?
+                    .input_values
+                    .remove(&input);
+                Ok(())
+            }
+            GraphEdit::Connect {
+                from_node,
+                from_output,
+                to_node,
+                to_input,
+            } => self.connect(from_node, from_output, to_node, to_input),
+            GraphEdit::Disconnect { to_node, to_input } => {
+                if self.disconnect(to_node, &to_input) {
+                    Ok(())
+                } else {
+                    Err(GraphError::InvalidInput(format!(
+                        "{to_input} isn't connected"
+                    )))
+                }
+            }
+            GraphEdit::SetInputSmoothing {
+                to_node,
+                to_input,
+                smoothing,
+            } => self.set_input_smoothing(to_node, &to_input, smoothing),
+            GraphEdit::SetInputMapping {
+                to_node,
+                to_input,
+                mapping,
+            } => self.set_input_mapping(to_node, &to_input, mapping),
+        }
+    }
+
+    /// The edits that turn this graph into `new`, in the order they should be
+    /// applied (see [Self::apply_edit]). Nodes that changed definition are
+    /// removed and added again.
+    pub fn diff(&self, new: &NodeGraph) -> Vec<GraphEdit> {
+        let kept: HashSet<EngineNodeId> = new
+            .instances
+            .values()
+            .filter(|instance| {
+                self.instances
+                    .get(&instance.id)
+                    .is_some_and(|old| old.definition_name == instance.definition_name)
+            })
+            .map(|instance| instance.id)
+            .collect();
+        let is_kept = |connection: &Connection| {
+            kept.contains(&connection.from_node) && kept.contains(&connection.to_node)
+        };
+        let find_same = |connections: &[Connection], connection: &Connection| {
+            connections
+                .iter()
+                .find(|c| {
+                    c.from_node == connection.from_node
+                        && c.from_output == connection.from_output
+                        && c.to_node == connection.to_node
+                        && c.to_input == connection.to_input
+                })
+                .cloned()
+        };
+
+        let mut edits = Vec::new();
+
+        // Connections into nodes that stay, that go away or change source
+        // (including sources that are removed, so the inputs don't keep
+        // pointing at them). The rest go away with their nodes.
+        for connection in &self.connections {
+            let unchanged =
+                is_kept(connection) && find_same(&new.connections, connection).is_some();
+            if kept.contains(&connection.to_node) && !unchanged {
+                edits.push(GraphEdit::Disconnect {
+                    to_node: connection.to_node,
+                    to_input: connection.to_input.clone(),
+                });
+            }
+        }
+
+        let mut removed: Vec<EngineNodeId> = self
+            .instances
+            .keys()
+            .filter(|id| !kept.contains(id))
+            .copied()
+            .collect();
+        removed.sort();
+        edits.extend(removed.into_iter().map(GraphEdit::RemoveNode));
+
+        let mut ids: Vec<EngineNodeId> = new.instances.keys().copied().collect();
+        ids.sort();
+        for &id in &ids {
+            if !kept.contains(&id) {
+                edits.push(GraphEdit::AddNode {
+                    id,
+                    definition_name: new.instances[&id].definition_name.clone(),
+                });
+            }
+        }
+
+        // Input values. Connected inputs are set by connecting them.
+        for &id in &ids {
+            let instance = &new.instances[&id];
+            let old_values = kept
+                .contains(&id)
+                .then(|| &self.instances[&id].input_values);
+
+            let mut inputs: Vec<(&String, &InputValue)> = instance
+                .input_values
+                .iter()
+                .filter(|(_, value)| !matches!(value, InputValue::Connection { .. }))
+                .collect();
+            inputs.sort_by_key(|(input, _)| input.as_str());
+            for (input, value) in inputs {
+                if old_values.and_then(|values| values.get(input)) != Some(value) {
+                    edits.push(GraphEdit::SetInput {
+                        node_id: id,
+                        input: input.clone(),
+                        value: value.clone(),
+                    });
+                }
+            }
+
+            if let Some(old_values) = old_values {
+                let mut cleared: Vec<&String> = old_values
+                    .iter()
+                    .filter(|(input, value)| {
+                        !matches!(value, InputValue::Connection { .. })
+                            && !instance.input_values.contains_key(*input)
+                    })
+                    .map(|(input, _)| input)
+                    .collect();
+                cleared.sort();
+                edits.extend(cleared.into_iter().map(|input| GraphEdit::ClearInput {
+                    node_id: id,
+                    input: input.clone(),
+                }));
+            }
+        }
+
+        for connection in &new.connections {
+            let old = if is_kept(connection) {
+                find_same(&self.connections, connection)
+            } else {
+                None
+            };
+            if old.is_none() {
+                edits.push(GraphEdit::Connect {
+                    from_node: connection.from_node,
+                    from_output: connection.from_output.clone(),
+                    to_node: connection.to_node,
+                    to_input: connection.to_input.clone(),
+                });
+            }
+
+            if old.as_ref().and_then(|c| c.smoothing) != connection.smoothing {
+                edits.push(GraphEdit::SetInputSmoothing {
+                    to_node: connection.to_node,
+                    to_input: connection.to_input.clone(),
+                    smoothing: connection.smoothing,
+                });
+            }
+            if old.as_ref().and_then(|c| c.mapping.as_ref()) != connection.mapping.as_ref() {
+                edits.push(GraphEdit::SetInputMapping {
+                    to_node: connection.to_node,
+                    to_input: connection.to_input.clone(),
+                    mapping: connection.mapping.clone(),
+                });
+            }
+        }
+
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether two graphs have the same nodes, inputs and connections.
+    fn same_graph(a: &NodeGraph, b: &NodeGraph) -> bool {
+        let same_nodes = a.instances.len() == b.instances.len()
+            && a.instances.values().all(|instance| {
+                b.instances.get(&instance.id).is_some_and(|other| {
+                    other.definition_name == instance.definition_name
+                        && other.input_values == instance.input_values
+                })
+            });
+        let same_connections = a.connections.len() == b.connections.len()
+            && a.connections.iter().all(|connection| {
+                b.connections.iter().any(|other| {
+                    other.from_node == connection.from_node
+                        && other.from_output == connection.from_output
+                        && other.to_node == connection.to_node
+                        && other.to_input == connection.to_input
+                        && other.smoothing == connection.smoothing
+                        && other.mapping == connection.mapping
+                })
+            });
+        same_nodes && same_connections
+    }
+
+    // --- NodeGraph::diff() ---
+
+    #[test]
+    fn test_diff_of_same_graph_is_empty() {
+        let mut graph = NodeGraph::new();
+        let a = graph.add_instance("Source".to_string());
+        let b = graph.add_instance("Blur".to_string());
+        graph
+            .connect(a, "out".to_string(), b, "in".to_string())
+            .unwrap();
+        graph
+            .set_input_value(b, "radius".to_string(), InputValue::Float(2.0))
+            .unwrap();
+
+        assert!(graph.diff(&graph.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_value_changes_dont_change_structure() {
+        let mut old = NodeGraph::new();
+        let a = old.add_instance("Source".to_string());
+        let b = old.add_instance("Blur".to_string());
+        old.connect(a, "out".to_string(), b, "in".to_string())
+            .unwrap();
+        old.set_input_value(b, "radius".to_string(), InputValue::Float(2.0))
+            .unwrap();
+
+        let mut new = old.clone();
+        new.set_input_value(b, "radius".to_string(), InputValue::Float(4.0))
+            .unwrap();
+        new.set_input_smoothing(b, "in", Some(InputSmoothing::default()))
+            .unwrap();
+
+        let edits = old.diff(&new);
+        assert_eq!(edits.len(), 2);
+        assert!(!edits.iter().any(GraphEdit::changes_structure));
+
+        for edit in edits {
+            old.apply_edit(edit).unwrap();
+        }
+        assert!(same_graph(&old, &new));
+    }
+
+    #[test]
+    fn test_diff_applies_to_new_graph() {
+        let mut old = NodeGraph::new();
+        let a = old.add_instance("Source".to_string());
+        let b = old.add_instance("Blur".to_string());
+        let c = old.add_instance("Invert".to_string());
+        old.connect(a, "out".to_string(), b, "in".to_string())
+            .unwrap();
+        old.connect(b, "out".to_string(), c, "in".to_string())
+            .unwrap();
+        old.set_input_value(c, "amount".to_string(), InputValue::Float(1.0))
+            .unwrap();
+        let e = old.add_instance("Output".to_string());
+        old.connect(b, "out".to_string(), e, "in".to_string())
+            .unwrap();
+
+        // Swap Blur for Sharpen under the same ID, drop Invert's value, add a
+        // node between Source and Sharpen, and leave a node that was fed by
+        // Blur unconnected.
+        let mut new = NodeGraph::new();
+        new.add_instance_with_id(a, "Source".to_string());
+        new.add_instance_with_id(b, "Sharpen".to_string());
+        new.add_instance_with_id(c, "Invert".to_string());
+        new.add_instance_with_id(e, "Output".to_string());
+        let d = new.add_instance("Mirror".to_string());
+        new.connect(a, "out".to_string(), d, "in".to_string())
+            .unwrap();
+        new.connect(d, "out".to_string(), b, "in".to_string())
+            .unwrap();
+        new.connect(b, "out".to_string(), c, "in".to_string())
+            .unwrap();
+
+        let edits = old.diff(&new);
+        assert!(edits.contains(&GraphEdit::RemoveNode(b)));
+        assert!(edits.contains(&GraphEdit::ClearInput {
+            node_id: c,
+            input: "amount".to_string(),
+        }));
+
+        for edit in edits {
+            old.apply_edit(edit).unwrap();
+        }
+        assert!(same_graph(&old, &new));
+    }
+
+    // --- NodeGraph::apply_edit() ---
+
+    #[test]
+    fn test_apply_edit_failures() {
+        let mut graph = NodeGraph::new();
+        let a = graph.add_instance("Source".to_string());
+
+        assert!(
+            graph
+                .apply_edit(GraphEdit::RemoveNode(EngineNodeId::default()))
+                .is_err()
+        );
+        assert!(
+            graph
+                .apply_edit(GraphEdit::Disconnect {
+                    to_node: a,
+                    to_input: "in".to_string(),
+                })
+                .is_err()
+        );
+        assert_eq!(graph.instances().len(), 1);
+    }
+
+    // --- NodeGraph::apply_edits() ---
+
+    #[test]
+    fn test_apply_edits_is_all_or_nothing() {
+        let mut graph = NodeGraph::new();
+        let a = graph.add_instance("Source".to_string());
+        let before = graph.clone();
+
+        let result = graph.apply_edits(vec![
+            GraphEdit::RemoveNode(a),
+            GraphEdit::RemoveNode(EngineNodeId::default()),
+        ]);
+        assert!(result.is_err());
+        assert!(same_graph(&graph, &before));
+
+        assert!(graph.apply_edits(vec![GraphEdit::RemoveNode(a)]).is_ok());
+        assert!(graph.instances().is_empty());
+    }
+}
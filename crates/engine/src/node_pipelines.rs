@@ -3,4 +3,5 @@ mod helpers;
 mod render_pipeline;
 
 pub use compute_pipeline::ComputePipeline;
+pub use helpers::{SAMPLING_INPUT_NAME, SamplingQuality};
 pub use render_pipeline::RenderPipeline;
@@ -56,12 +56,14 @@ struct ComputeShaderParam {
 }
 
 impl ComputePipeline {
-    /// Create a compute pipeline from shader source and node definition.
+    /// Create a compute pipeline from shader source and node definition,
+    /// reusing compiled code from `cache` if there is any.
     pub fn from_shader(
         device: &wgpu::Device,
         shader_code: &str,
         definition: &NodeDefinition,
         storage_format: wgpu::TextureFormat,
+        cache: Option<&wgpu::PipelineCache>,
     ) -> Result<Self, EngineError> {
         // Compile shader module
         let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -111,7 +113,7 @@ impl ComputePipeline {
             ),
             module: &shader_module,
             entry_point: Some("cs_main"),
-            cache: None,
+            cache,
             compilation_options: Default::default(),
         });
 
@@ -1,10 +1,85 @@
 use crate::node::engine_node::NodeInputKind;
 
-pub fn create_linear_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+/// The name of the optional [NodeInputKind::Enum] input that picks how a shader
+/// node samples its input frames. It's handled by the pipeline and isn't passed
+/// to the shader's parameters.
+pub const SAMPLING_INPUT_NAME: &str = "Sampling";
+
+/// How input frames are filtered when a shader samples them. Higher qualities
+/// reduce aliasing when a frame is drawn smaller than its size (e.g. a large
+/// video scaled down in a composite), at some GPU cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SamplingQuality {
+    /// Blocky, with no filtering at all.
+    Nearest,
+    /// Filters within the closest mip level.
+    Bilinear,
+    /// Filters within and between mip levels.
+    #[default]
+    Trilinear,
+    /// Trilinear, but also keeps detail when a frame is squashed more along
+    /// one axis than the other (e.g. when rotated or skewed).
+    Anisotropic,
+}
+
+impl SamplingQuality {
+    /// Every quality, in declaration order (which is also the order of the
+    /// `Sampling` input's choices).
+    pub const ALL: [Self; 4] = [
+        Self::Nearest,
+        Self::Bilinear,
+        Self::Trilinear,
+        Self::Anisotropic,
+    ];
+
+    /// The quality for an index into the `Sampling` input's choices, or the
+    /// default if it's out of range.
+    pub fn from_choice_index(index: usize) -> Self {
+        Self::ALL.get(index).copied().unwrap_or_default()
+    }
+}
+
+pub fn create_sampler(device: &wgpu::Device, quality: SamplingQuality) -> wgpu::Sampler {
+    let (label, filter, mipmap_filter, anisotropy_clamp) = match quality {
+        SamplingQuality::Nearest => (
+            "sampler/nearest",
+            wgpu::FilterMode::Nearest,
+            wgpu::FilterMode::Nearest,
+            1,
+        ),
+        SamplingQuality::Bilinear => (
+            "sampler/bilinear",
+            wgpu::FilterMode::Linear,
+            wgpu::FilterMode::Nearest,
+            1,
+        ),
+        SamplingQuality::Trilinear => (
+            "sampler/trilinear",
+            wgpu::FilterMode::Linear,
+            wgpu::FilterMode::Linear,
+            1,
+        ),
+        SamplingQuality::Anisotropic => (
+            "sampler/anisotropic",
+            wgpu::FilterMode::Linear,
+            wgpu::FilterMode::Linear,
+            16,
+        ),
+    };
+
     device.create_sampler(&wgpu::SamplerDescriptor {
-        label: Some("sampler/linear"),
-        mag_filter: wgpu::FilterMode::Linear,
-        min_filter: wgpu::FilterMode::Linear,
+        label: Some(label),
+        mag_filter: filter,
+        min_filter: filter,
+        mipmap_filter,
+        anisotropy_clamp,
+        // Nearest sampling is meant to look blocky, so it stays on the full
+        // size level.
+        lod_max_clamp: if quality == SamplingQuality::Nearest {
+            0.0
+        } else {
+            32.0
+        },
         address_mode_u: wgpu::AddressMode::ClampToEdge,
         address_mode_v: wgpu::AddressMode::ClampToEdge,
         address_mode_w: wgpu::AddressMode::ClampToEdge,
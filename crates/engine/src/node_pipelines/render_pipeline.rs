@@ -21,9 +21,9 @@
 use std::any::Any;
 use std::collections::HashMap;
 
-use super::helpers::create_linear_sampler;
+use super::helpers::{SAMPLING_INPUT_NAME, SamplingQuality, create_sampler};
 use crate::engine_errors::EngineError;
-use crate::graph_executor::NodeValue;
+use crate::graph_executor::{NodeValue, RenderRegion};
 use crate::node::NodeDefinition;
 use crate::node::engine_node::NodeInputKind;
 
@@ -41,7 +41,8 @@ pub struct RenderPipeline {
     // GPU resources
     pipeline: wgpu::RenderPipeline,
     bgl: wgpu::BindGroupLayout,
-    sampler: wgpu::Sampler,
+    /// One sampler per [SamplingQuality], in [SamplingQuality::ALL] order.
+    samplers: [wgpu::Sampler; 4],
     params_buf: wgpu::Buffer,
 
     // Pipeline metadata
@@ -72,6 +73,7 @@ impl RenderPipeline {
         additional_inputs: &[&wgpu::TextureView],
         output: &wgpu::TextureView,
         params: &dyn Any,
+        region: Option<RenderRegion>,
     ) -> Result<(), EngineError> {
         // Execute the pipeline.
         //
@@ -79,7 +81,8 @@ impl RenderPipeline {
         //   declared [NodeInputKind::Frame] inputs (primary input + additional inputs).
         // - Writes the [params] (expected to be [HashMap<String, NodeValue>]) into
         //   the uniform buffer using [param_layout] rules.
-        // - Builds a bind group and issues the render pass that draws into [output].
+        // - Builds a bind group and issues the render pass that draws into [output],
+        //   only inside [region] if it's set (the rest is cleared).
         // Validate input count. For pipelines with zero frame inputs, no textures are expected.
         let expected = self.texture_input_count.saturating_sub(1);
         if self.texture_input_count > 0 && additional_inputs.len() != expected {
@@ -90,7 +93,8 @@ impl RenderPipeline {
         }
 
         // Update uniform buffer
-        if let Some(param_map) = params.downcast_ref::<HashMap<String, NodeValue>>() {
+        let sampling = if let Some(param_map) = params.downcast_ref::<HashMap<String, NodeValue>>()
+        {
             let params_size = Self::calculate_params_size(&self.param_layout);
             let mut buffer = vec![0u8; params_size];
             for param in &self.param_layout {
@@ -99,18 +103,24 @@ impl RenderPipeline {
                 }
             }
             queue.write_buffer(&self.params_buf, 0, &buffer);
+
+            match param_map.get(SAMPLING_INPUT_NAME) {
+                Some(NodeValue::Enum(index)) => SamplingQuality::from_choice_index(*index),
+                _ => SamplingQuality::default(),
+            }
         } else {
             return Err(EngineError::InvalidParamType {
                 pipeline: self.name.clone(),
                 expected: "HashMap<String, NodeValue>".to_string(),
                 actual: std::any::type_name_of_val(params).to_string(),
             });
-        }
+        };
+        let sampler = &self.samplers[sampling as usize];
 
         // Build bind group
         let mut entries = vec![wgpu::BindGroupEntry {
             binding: 0,
-            resource: wgpu::BindingResource::Sampler(&self.sampler),
+            resource: wgpu::BindingResource::Sampler(sampler),
         }];
 
         if self.texture_input_count > 0 {
@@ -156,6 +166,11 @@ impl RenderPipeline {
         });
         rpass.set_pipeline(&self.pipeline);
         rpass.set_bind_group(0, &bind_group, &[]);
+        if let Some(region) = region {
+            let size = output.texture().size();
+            let (x, y, width, height) = region.pixels(size.width, size.height);
+            rpass.set_scissor_rect(x, y, width, height);
+        }
         rpass.draw(0..3, 0..1);
 
         Ok(())
@@ -163,19 +178,21 @@ impl RenderPipeline {
 }
 
 impl RenderPipeline {
-    /// Create pipeline from WGSL shader code and node definition
+    /// Create pipeline from WGSL shader code and node definition, reusing
+    /// compiled code from `cache` if there is any.
     pub fn from_shader(
         device: &wgpu::Device,
         shader_code: &str,
         definition: &NodeDefinition,
         target_format: wgpu::TextureFormat,
+        cache: Option<&wgpu::PipelineCache>,
     ) -> Result<Self, String> {
         // Construct a [RenderPipeline] from raw WGSL shader code and a
         // [NodeDefinition] describing inputs/outputs.
         //
         // This will create the pipeline, a matching bind-group-layout and a
         // uniform buffer sized to hold the node's non-texture parameters.
-        let sampler = create_linear_sampler(device);
+        let samplers = SamplingQuality::ALL.map(|quality| create_sampler(device, quality));
 
         // Count how many Frame inputs this node has
         let texture_input_count = definition
@@ -221,7 +238,7 @@ impl RenderPipeline {
             primitive: wgpu::PrimitiveState::default(),
             depth_stencil: None,
             multisample: wgpu::MultisampleState::default(),
-            cache: None,
+            cache,
             multiview: None,
         });
 
@@ -238,7 +255,7 @@ impl RenderPipeline {
         });
 
         Ok(Self {
-            sampler,
+            samplers,
             bgl,
             pipeline,
             params_buf,
@@ -348,7 +365,8 @@ impl RenderPipeline {
     fn build_param_layout(inputs: &[crate::node::engine_node::NodeInput]) -> Vec<ShaderParam> {
         // Convert node [inputs] into a list of [ShaderParam] describing the
         // order and byte offsets of parameters placed in the uniform buffer.
-        // Frame and Midi inputs are skipped (they are bound as textures or handled elsewhere).
+        // Frame and Midi inputs are skipped (they are bound as textures or handled elsewhere),
+        // as is the `Sampling` input (it picks the sampler).
         let mut params = Vec::new();
         let mut offset = 0usize;
 
@@ -357,6 +375,10 @@ impl RenderPipeline {
             if matches!(input.kind, NodeInputKind::Frame | NodeInputKind::MidiPacket) {
                 continue;
             }
+            if input.name == SAMPLING_INPUT_NAME && matches!(input.kind, NodeInputKind::Enum { .. })
+            {
+                continue;
+            }
 
             params.push(ShaderParam {
                 name: input.name.clone(),
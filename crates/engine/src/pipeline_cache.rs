@@ -0,0 +1,140 @@
+//! Keeps compiled GPU pipelines on disk between runs with [DiskPipelineCache],
+//! so node shaders don't all have to be compiled again on every cold start.
+//!
+//! Only drivers wgpu can read pipeline caches back from support it (currently
+//! Vulkan), and only on devices created with the features from
+//! [required_features]. Elsewhere there's simply no cache.
+//!
+//! Cache files are named after the adapter, plus a hash of the driver version
+//! and the node library's shaders. When either changes, the old file is
+//! deleted instead of being loaded.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use util::local_data;
+
+use crate::node::NodeLibrary;
+use crate::node::engine_node::NodeExecutionPlan;
+
+/// The device features [DiskPipelineCache] needs that `adapter` supports. Add
+/// these to the features a device is requested with.
+pub fn required_features(adapter: &wgpu::Adapter) -> wgpu::Features {
+    adapter.features() & wgpu::Features::PIPELINE_CACHE
+}
+
+/// A [wgpu::PipelineCache] loaded from (and saved back to) the
+/// [pipeline cache folder](local_data::pipeline_cache_path).
+pub struct DiskPipelineCache {
+    cache: wgpu::PipelineCache,
+    path: PathBuf,
+}
+
+impl DiskPipelineCache {
+    /// Load the cache for the adapter described by `adapter_info` and
+    /// `library`, or start an empty one if there isn't one yet. Returns [None]
+    /// if `device` can't use pipeline caches.
+    pub fn load(
+        device: &wgpu::Device,
+        adapter_info: &wgpu::AdapterInfo,
+        library: &NodeLibrary,
+    ) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::PIPELINE_CACHE) {
+            return None;
+        }
+        let key = wgpu::util::pipeline_cache_key(adapter_info)?;
+
+        let mut hasher = DefaultHasher::new();
+        adapter_info.driver.hash(&mut hasher);
+        adapter_info.driver_info.hash(&mut hasher);
+        library_shader_hash(library).hash(&mut hasher);
+        let file_name = format!("{key}_{:016x}.bin", hasher.finish());
+
+        let dir = local_data::pipeline_cache_path();
+        remove_stale_files(dir, &key, &file_name);
+
+        let path = dir.join(file_name);
+        let data = fs::read(&path).ok();
+        util::debug_log_info!(
+            "Loading pipeline cache from {path:?} ({} bytes).",
+            data.as_ref().map_or(0, Vec::len)
+        );
+
+        // SAFETY: `data` was written by `save` from a cache for an adapter with
+        // the same `pipeline_cache_key` (it's part of the file name), and with
+        // `fallback` set, data the driver won't use is ignored.
+        let cache = unsafe {
+            device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                label: Some("pipeline_cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        };
+        Some(Self { cache, path })
+    }
+
+    /// The cache to create pipelines with.
+    pub fn cache(&self) -> &wgpu::PipelineCache {
+        &self.cache
+    }
+
+    /// Write everything the cache holds to disk. The file is replaced in one
+    /// step, so a crash while saving can't leave half a cache behind.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(data) = self.cache.get_data() else {
+            return Ok(());
+        };
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, data)?;
+        fs::rename(&temp_path, &self.path)
+    }
+}
+
+/// A hash of `library`'s [content hash](NodeLibrary::content_hash) and the
+/// code of every shader and algorithm stage its nodes run.
+fn library_shader_hash(library: &NodeLibrary) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    library.content_hash().hash(&mut hasher);
+
+    let mut names: Vec<&String> = library.definitions().keys().collect();
+    names.sort();
+    for name in names {
+        let definition = &library.definitions()[name];
+        match &definition.node.executor {
+            NodeExecutionPlan::Shader { .. } => {
+                definition.load_shader_code().ok().hash(&mut hasher);
+            }
+            NodeExecutionPlan::Algorithm { stages, .. } => {
+                for stage in stages {
+                    fs::read(definition.folder_path.join(&stage.source))
+                        .ok()
+                        .hash(&mut hasher);
+                }
+            }
+            _ => {}
+        }
+    }
+    hasher.finish()
+}
+
+/// Delete cache files in `dir` for the adapter `key` other than `keep` (left
+/// from an older driver or node library).
+fn remove_stale_files(dir: &Path, key: &str, keep: &str) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let prefix = format!("{key}_");
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if file_name.starts_with(&prefix) && file_name != keep {
+            util::debug_log_info!("Removing stale pipeline cache {file_name}.");
+            if let Err(e) = fs::remove_file(entry.path()) {
+                util::debug_log_warning!("Failed to remove stale pipeline cache: {e}");
+            }
+        }
+    }
+}
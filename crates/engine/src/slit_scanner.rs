@@ -0,0 +1,404 @@
+//! Exports [SlitScanner], which draws frames whose parts come from different
+//! moments in the past (see the Slit Scan and Time Displace nodes), and
+//! [FrameHistory], the recent frames it draws them from.
+//!
+//! The history is a texture array used as a ring buffer, so drawing is a
+//! single pass that picks a layer per pixel no matter how many frames are
+//! kept.
+
+/// The most frames a [FrameHistory] can keep. Every frame keeps a full size
+/// texture layer alive.
+pub const MAX_HISTORY_FRAMES: u32 = 120;
+
+const SLIT_SCAN_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    latest: u32,
+    available: u32,
+    capacity: u32,
+    // 0=top to bottom, 1=bottom to top, 2=left to right, 3=right to left,
+    // 4=center out, 5=edges in, 6=map
+    direction: u32,
+    smooth_blend: u32,
+    invert: u32,
+    _pad0: u32,
+    _pad1: u32,
+}
+
+@group(0) @binding(0) var map_sampler: sampler;
+@group(0) @binding(1) var history: texture_2d_array<f32>;
+@group(0) @binding(2) var map_texture: texture_2d<f32>;
+@group(0) @binding(3) var<uniform> params: Params;
+
+// How far back in time to draw `uv` from, from 0.0 (the latest frame) to 1.0
+// (the oldest).
+fn age(uv: vec2<f32>) -> f32 {
+    var t: f32;
+    switch params.direction {
+        case 0u: {
+            t = uv.y;
+        }
+        case 1u: {
+            t = 1.0 - uv.y;
+        }
+        case 2u: {
+            t = uv.x;
+        }
+        case 3u: {
+            t = 1.0 - uv.x;
+        }
+        case 4u: {
+            t = length(uv - 0.5) / length(vec2<f32>(0.5));
+        }
+        case 5u: {
+            t = 1.0 - length(uv - 0.5) / length(vec2<f32>(0.5));
+        }
+        default: {
+            let color = textureSampleLevel(map_texture, map_sampler, uv, 0.0).rgb;
+            t = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+        }
+    }
+    if params.invert != 0u {
+        t = 1.0 - t;
+    }
+    return clamp(t, 0.0, 1.0);
+}
+
+fn frame_ago(position: vec2<i32>, ago: u32) -> vec4<f32> {
+    let layer = (params.latest + params.capacity - ago) % params.capacity;
+    return textureLoad(history, position, layer, 0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let position = vec2<i32>(in.position.xy);
+    let ago = age(in.uv) * f32(params.available - 1u);
+    let older = min(u32(ago) + 1u, params.available - 1u);
+    let newer = frame_ago(position, u32(ago));
+    if params.smooth_blend == 0u {
+        return newer;
+    }
+    return mix(newer, frame_ago(position, older), fract(ago));
+}
+"#;
+
+/// Which way [SlitScanner::draw] reaches back in time across the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanDirection {
+    /// The top of the frame is the latest and the bottom the oldest.
+    TopToBottom,
+    BottomToTop,
+    LeftToRight,
+    RightToLeft,
+    /// The center is the latest and the corners the oldest.
+    CenterOut,
+    EdgesIn,
+    /// Brighter parts of a map are older.
+    Map,
+}
+
+/// The settings [SlitScanner::draw] draws with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanSettings {
+    pub direction: ScanDirection,
+    /// Blend between the two frames nearest each pixel's age instead of using
+    /// the nearest one, so there are no visible bands.
+    pub smooth: bool,
+    /// Swap the latest and oldest ends.
+    pub invert: bool,
+}
+
+/// The last few frames of a stream, in the layers of a texture array.
+pub struct FrameHistory {
+    size: wgpu::Extent3d,
+    /// One view per layer, for copying frames in.
+    layer_views: Vec<wgpu::TextureView>,
+    array_view: wgpu::TextureView,
+    /// How many frames have been added since the history was created.
+    written: usize,
+    params_buf: wgpu::Buffer,
+}
+
+impl FrameHistory {
+    /// Create an empty history of up to `capacity` (at most
+    /// [MAX_HISTORY_FRAMES]) `format` frames `size` big.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: wgpu::Extent3d,
+        capacity: u32,
+    ) -> Self {
+        let capacity = capacity.clamp(1, MAX_HISTORY_FRAMES);
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("frame_history"),
+            size: wgpu::Extent3d {
+                depth_or_array_layers: capacity,
+                ..size
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let layer_views = (0..capacity)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("frame_history_layer"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("frame_history_array"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        Self {
+            size,
+            layer_views,
+            array_view,
+            written: 0,
+            params_buf: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("slit_scan_params"),
+                size: 32,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    pub fn size(&self) -> wgpu::Extent3d {
+        self.size
+    }
+
+    /// How many frames the history can keep.
+    pub fn capacity(&self) -> u32 {
+        self.layer_views.len() as u32
+    }
+
+    /// The layer the next frame is added to. Add it by drawing into this, then
+    /// call [Self::advance].
+    pub fn next_layer(&self) -> &wgpu::TextureView {
+        &self.layer_views[self.written % self.layer_views.len()]
+    }
+
+    /// Record that a frame was drawn into [Self::next_layer].
+    pub fn advance(&mut self) {
+        self.written += 1;
+    }
+}
+
+/// Draws frames from [FrameHistory]s into render targets of one format.
+pub struct SlitScanner {
+    pipeline: wgpu::RenderPipeline,
+    bgl: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl SlitScanner {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/slit_scan"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/slit_scan"),
+            bind_group_layouts: &[&bgl],
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/slit_scan"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(SLIT_SCAN_SHADER)),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/slit_scan"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sampler/slit_scan"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            bgl,
+            sampler,
+        }
+    }
+
+    /// Record a pass into `encoder` that draws each pixel of `target` (which
+    /// must be the size of `history`) from the frame of `history` its age
+    /// picks. `map` is only read for [ScanDirection::Map], and can be any
+    /// size. The history must have at least one frame.
+    ///
+    /// The settings are written with `queue` into `history`, so `encoder`
+    /// must be submitted before this is called again with the same history.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        history: &FrameHistory,
+        map: &wgpu::TextureView,
+        settings: ScanSettings,
+        target: &wgpu::TextureView,
+    ) {
+        let capacity = history.capacity();
+        let available = (history.written as u32).clamp(1, capacity);
+        let latest = (history.written.max(1) - 1) as u32 % capacity;
+        let direction: u32 = match settings.direction {
+            ScanDirection::TopToBottom => 0,
+            ScanDirection::BottomToTop => 1,
+            ScanDirection::LeftToRight => 2,
+            ScanDirection::RightToLeft => 3,
+            ScanDirection::CenterOut => 4,
+            ScanDirection::EdgesIn => 5,
+            ScanDirection::Map => 6,
+        };
+        let params = [
+            latest,
+            available,
+            capacity,
+            direction,
+            u32::from(settings.smooth),
+            u32::from(settings.invert),
+            0,
+            0,
+        ];
+        let bytes: Vec<u8> = params
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        queue.write_buffer(&history.params_buf, 0, &bytes);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/slit_scan"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&history.array_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(map),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: history.params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("slit_scan"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
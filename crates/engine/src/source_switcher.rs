@@ -0,0 +1,395 @@
+//! Exports [SourceSwitcher], which draws the transition between two frames
+//! (see the Switcher node), and [SourceSelection], which keeps track of which
+//! source is shown and how far along a transition between sources is.
+
+const SWITCH_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    progress: f32,
+    transition: u32,  // 0=crossfade, 1=wipe
+    direction: u32,   // 0=left to right, 1=right to left, 2=top to bottom, 3=bottom to top
+    softness: f32,
+}
+
+@group(0) @binding(0) var frame_sampler: sampler;
+@group(0) @binding(1) var from_texture: texture_2d<f32>;
+@group(0) @binding(2) var to_texture: texture_2d<f32>;
+@group(0) @binding(3) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let from_color = textureSample(from_texture, frame_sampler, in.uv);
+    let to_color = textureSample(to_texture, frame_sampler, in.uv);
+    if params.transition == 0u {
+        return mix(from_color, to_color, params.progress);
+    }
+
+    // How far along the wipe's path this pixel is.
+    var along: f32;
+    switch params.direction {
+        case 1u: {
+            along = 1.0 - in.uv.x;
+        }
+        case 2u: {
+            along = in.uv.y;
+        }
+        case 3u: {
+            along = 1.0 - in.uv.y;
+        }
+        default: {
+            along = in.uv.x;
+        }
+    }
+
+    // The edge runs past the end by the softness so the wipe finishes with
+    // none of the old frame left.
+    let edge = params.progress * (1.0 + params.softness);
+    let amount = 1.0 - smoothstep(edge - params.softness, edge, along);
+    return mix(from_color, to_color, amount);
+}
+"#;
+
+/// How the Switcher node changes from one source to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Change immediately.
+    Cut,
+    /// Fade from the old source to the new one.
+    Crossfade,
+    /// Slide an edge across the frame, showing the new source behind it.
+    Wipe(WipeDirection),
+}
+
+/// The direction a [Transition::Wipe] moves in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipeDirection {
+    LeftToRight,
+    RightToLeft,
+    TopToBottom,
+    BottomToTop,
+}
+
+/// What a [SourceSelection] should show for the current frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceMix {
+    /// Just this source.
+    Source(usize),
+    /// Part way (`progress`, from `0.0` to `1.0`) from one source to another.
+    Transitioning {
+        from: usize,
+        to: usize,
+        progress: f32,
+    },
+}
+
+/// The source a switcher shows, and the transition it's part way through, if
+/// any. Transitions are counted in frames (calls to [Self::update]) so they
+/// play out the same when exporting as they do live.
+#[derive(Debug, Clone, Default)]
+pub struct SourceSelection {
+    /// The source shown (or being transitioned to).
+    current: Option<usize>,
+    /// The source being transitioned from, and how many frames ago the
+    /// transition started.
+    previous: Option<(usize, u32)>,
+}
+
+impl SourceSelection {
+    /// The source shown (or being transitioned to), if [Self::update] has been
+    /// called.
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Advance one frame with `selected` as the chosen source, starting a
+    /// transition `duration` frames long if it's changed.
+    ///
+    /// Changing the selection part way through a transition starts a new one
+    /// from the source being transitioned to, so the switch is never undone.
+    pub fn update(&mut self, selected: usize, duration: u32) -> SourceMix {
+        let Some(current) = self.current else {
+            self.current = Some(selected);
+            return SourceMix::Source(selected);
+        };
+
+        if selected != current {
+            self.current = Some(selected);
+            self.previous = (duration > 0).then_some((current, 0));
+        }
+
+        let Some((from, elapsed)) = self.previous else {
+            return SourceMix::Source(selected);
+        };
+        let elapsed = elapsed + 1;
+        if elapsed >= duration {
+            self.previous = None;
+            return SourceMix::Source(selected);
+        }
+        self.previous = Some((from, elapsed));
+        SourceMix::Transitioning {
+            from,
+            to: selected,
+            progress: elapsed as f32 / duration as f32,
+        }
+    }
+}
+
+/// Draws transitions between pairs of frames.
+pub struct SourceSwitcher {
+    pipeline: wgpu::RenderPipeline,
+    bgl: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    params_buf: wgpu::Buffer,
+}
+
+impl SourceSwitcher {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/switch"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/switch"),
+            bind_group_layouts: &[&bgl],
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/switch"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(SWITCH_SHADER)),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/switch"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sampler/switch"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let params_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("switch_params"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            bgl,
+            sampler,
+            params_buf,
+        }
+    }
+
+    /// Record a pass into `encoder` that draws `progress` (from `0.0` to
+    /// `1.0`) of the way through `transition` from `from` to `to` into
+    /// `target`. The frames are stretched to fit `target` if their sizes
+    /// differ.
+    ///
+    /// The settings are written with `queue`, so `encoder` must be submitted
+    /// before this is called again.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        from: &wgpu::TextureView,
+        to: &wgpu::TextureView,
+        transition: Transition,
+        progress: f32,
+        target: &wgpu::TextureView,
+    ) {
+        // A cut is a crossfade that's already finished.
+        let progress = if transition == Transition::Cut {
+            1.0
+        } else {
+            progress.clamp(0.0, 1.0)
+        };
+        let (transition, direction): (u32, u32) = match transition {
+            Transition::Cut | Transition::Crossfade => (0, 0),
+            Transition::Wipe(direction) => (
+                1,
+                match direction {
+                    WipeDirection::LeftToRight => 0,
+                    WipeDirection::RightToLeft => 1,
+                    WipeDirection::TopToBottom => 2,
+                    WipeDirection::BottomToTop => 3,
+                },
+            ),
+        };
+        let mut params = [0u8; 16];
+        params[0..4].copy_from_slice(&progress.to_le_bytes());
+        params[4..8].copy_from_slice(&transition.to_le_bytes());
+        params[8..12].copy_from_slice(&direction.to_le_bytes());
+        params[12..16].copy_from_slice(&0.02f32.to_le_bytes());
+        queue.write_buffer(&self.params_buf, 0, &params);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/switch"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(from),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(to),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("switch"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- SourceSelection::update() ---
+
+    #[test]
+    fn test_source_selection_update() {
+        let mut selection = SourceSelection::default();
+        assert_eq!(selection.update(0, 4), SourceMix::Source(0));
+        assert_eq!(selection.update(0, 4), SourceMix::Source(0));
+
+        // Switching plays out over the duration, then settles.
+        let progress: Vec<SourceMix> = (0..4).map(|_| selection.update(2, 4)).collect();
+        assert_eq!(
+            progress,
+            [
+                SourceMix::Transitioning {
+                    from: 0,
+                    to: 2,
+                    progress: 0.25
+                },
+                SourceMix::Transitioning {
+                    from: 0,
+                    to: 2,
+                    progress: 0.5
+                },
+                SourceMix::Transitioning {
+                    from: 0,
+                    to: 2,
+                    progress: 0.75
+                },
+                SourceMix::Source(2),
+            ]
+        );
+
+        // Switching part way through starts again from the new source.
+        selection.update(1, 4);
+        assert_eq!(
+            selection.update(3, 4),
+            SourceMix::Transitioning {
+                from: 1,
+                to: 3,
+                progress: 0.25
+            }
+        );
+
+        // With no duration it's a cut.
+        assert_eq!(selection.update(0, 0), SourceMix::Source(0));
+    }
+}
@@ -0,0 +1,332 @@
+//! Exports [TempoClock], the musical clock the Tempo and LFO nodes share,
+//! [BeatDetector], which follows the beat of an audio level signal, and
+//! [LfoShape], the waveforms LFO nodes output.
+//!
+//! Time is counted in seconds the clock has been advanced by (not wall clock
+//! time) so the clock stops while playback is paused.
+
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// The slowest and fastest tempos the clock runs at.
+pub const MIN_BPM: f64 = 20.0;
+pub const MAX_BPM: f64 = 300.0;
+
+/// The tempo the clock starts at.
+pub const DEFAULT_BPM: f64 = 120.0;
+
+/// Taps further apart than this start a new tempo instead of refining the
+/// current one.
+const TAP_TIMEOUT_SECS: f64 = 2.0;
+
+/// How many of the latest taps (or detected beats) the tempo is averaged over.
+const TEMPO_HISTORY: usize = 8;
+
+/// Detected tempos are doubled or halved into this range, since a beat
+/// detector can't tell half time from double time.
+const DETECTED_BPM_RANGE: (f64, f64) = (70.0, 180.0);
+
+/// How long the running average a level has to rise above to be a beat takes
+/// to follow the level.
+const AVERAGE_SECS: f64 = 1.0;
+
+/// Levels below this are never beats.
+const MIN_BEAT_LEVEL: f32 = 0.01;
+
+/// How much of the distance to the nearest beat the clock's phase is moved by
+/// when a beat is detected.
+const PHASE_CORRECTION: f64 = 0.5;
+
+/// A clock that counts beats at a tempo. See the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct TempoClock {
+    bpm: f64,
+    /// Beats since the clock started.
+    beat: f64,
+    /// Seconds the clock has been advanced by.
+    time: f64,
+    /// The times of recent taps, oldest first.
+    taps: VecDeque<f64>,
+}
+
+impl Default for TempoClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TempoClock {
+    /// Create a clock at [DEFAULT_BPM].
+    pub fn new() -> Self {
+        Self {
+            bpm: DEFAULT_BPM,
+            beat: 0.0,
+            time: 0.0,
+            taps: VecDeque::new(),
+        }
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// Change the tempo, clamped to [MIN_BPM] and [MAX_BPM]. The beat count
+    /// carries on from where it is.
+    pub fn set_bpm(&mut self, bpm: f64) {
+        if bpm.is_finite() {
+            self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        }
+    }
+
+    /// Beats since the clock started.
+    pub fn beat(&self) -> f64 {
+        self.beat
+    }
+
+    /// How far through the current beat the clock is, from 0 to 1.
+    pub fn beat_phase(&self) -> f64 {
+        self.beat.rem_euclid(1.0)
+    }
+
+    /// Move the clock `secs` forward.
+    pub fn advance(&mut self, secs: f64) {
+        let secs = secs.max(0.0);
+        self.time += secs;
+        self.beat += secs * self.bpm / 60.0;
+    }
+
+    /// Tap the tempo: once two or more taps are close enough together, the
+    /// tempo becomes their average interval. Every tap lands on a beat.
+    pub fn tap(&mut self) {
+        if self
+            .taps
+            .back()
+            .is_some_and(|last| self.time - last > TAP_TIMEOUT_SECS)
+        {
+            self.taps.clear();
+        }
+        self.taps.push_back(self.time);
+        if self.taps.len() > TEMPO_HISTORY {
+            self.taps.pop_front();
+        }
+
+        if let (Some(first), Some(last)) = (self.taps.front(), self.taps.back())
+            && last > first
+        {
+            self.set_bpm(60.0 * (self.taps.len() - 1) as f64 / (last - first));
+        }
+        self.beat = self.beat.round();
+    }
+
+    /// Move the clock's phase part of the way toward the nearest beat, for
+    /// following detected beats without jumping.
+    pub fn nudge_to_beat(&mut self) {
+        self.beat += (self.beat.round() - self.beat) * PHASE_CORRECTION;
+    }
+
+    /// Follow another clock (e.g. an Ableton Link session): jump straight to
+    /// its tempo and beat.
+    pub fn follow(&mut self, bpm: f64, beat: f64) {
+        self.set_bpm(bpm);
+        if beat.is_finite() {
+            self.beat = beat;
+        }
+    }
+
+    /// Seconds the clock has been advanced by.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+}
+
+/// Finds beats in an audio level (e.g. an Audio Meter's RMS), sampled once a
+/// frame, and estimates the tempo from the time between them.
+///
+/// A beat is the level jumping above its running average by an amount that
+/// depends on the sensitivity.
+#[derive(Debug, Clone, Default)]
+pub struct BeatDetector {
+    average: f32,
+    /// Whether the level has fallen back since the last beat, so one loud
+    /// stretch is only one beat.
+    armed: bool,
+    last_time: Option<f64>,
+    last_beat: Option<f64>,
+    /// The tempos implied by recent intervals between beats, oldest first.
+    tempos: VecDeque<f64>,
+}
+
+impl BeatDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the `level` at `time` (in seconds). `sensitivity` (0 to 1) is how
+    /// small a jump counts as a beat. Returns whether this is a beat.
+    pub fn process(&mut self, level: f32, time: f64, sensitivity: f32) -> bool {
+        let Some(last_time) = self.last_time.replace(time) else {
+            self.average = level;
+            return false;
+        };
+        let dt = (time - last_time).max(0.0);
+
+        let threshold = self.average * (2.0 - sensitivity.clamp(0.0, 1.0));
+        let min_interval = 60.0 / MAX_BPM;
+        let is_beat = self.armed
+            && level >= MIN_BEAT_LEVEL
+            && level > threshold
+            && self
+                .last_beat
+                .is_none_or(|last_beat| time - last_beat >= min_interval);
+
+        if is_beat {
+            self.armed = false;
+            if let Some(last_beat) = self.last_beat.replace(time) {
+                self.record_interval(time - last_beat);
+            }
+        } else if level <= self.average {
+            self.armed = true;
+        }
+
+        let amount = 1.0 - (-dt / AVERAGE_SECS).exp();
+        self.average += (level - self.average) * amount as f32;
+        is_beat
+    }
+
+    /// The tempo of recent beats, once there have been enough of them.
+    pub fn bpm(&self) -> Option<f64> {
+        if self.tempos.len() < 3 {
+            return None;
+        }
+
+        let mut tempos: Vec<f64> = self.tempos.iter().copied().collect();
+        tempos.sort_by(f64::total_cmp);
+        Some(tempos[tempos.len() / 2])
+    }
+
+    fn record_interval(&mut self, secs: f64) {
+        if secs <= 0.0 || secs > TAP_TIMEOUT_SECS {
+            self.tempos.clear();
+            return;
+        }
+
+        let (min, max) = DETECTED_BPM_RANGE;
+        let mut bpm = 60.0 / secs;
+        while bpm < min {
+            bpm *= 2.0;
+        }
+        while bpm > max {
+            bpm /= 2.0;
+        }
+
+        self.tempos.push_back(bpm);
+        if self.tempos.len() > TEMPO_HISTORY {
+            self.tempos.pop_front();
+        }
+    }
+}
+
+/// The waveforms of an LFO, each going from 0 to 1 once a cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LfoShape {
+    Sine,
+    Saw,
+    Square,
+    /// A new random value every cycle.
+    Random,
+}
+
+impl LfoShape {
+    /// The shapes in the order LFO nodes list them.
+    pub const ALL: [LfoShape; 4] = [
+        LfoShape::Sine,
+        LfoShape::Saw,
+        LfoShape::Square,
+        LfoShape::Random,
+    ];
+
+    /// The value (0 to 1) `cycles` cycles in. Every cycle starts at 0 except
+    /// for [LfoShape::Random], whose values depend on `seed`.
+    pub fn value(self, cycles: f64, seed: u64) -> f32 {
+        let phase = cycles.rem_euclid(1.0);
+        let value = match self {
+            LfoShape::Sine => 0.5 - 0.5 * (TAU * phase).cos(),
+            LfoShape::Saw => phase,
+            LfoShape::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            LfoShape::Random => {
+                let mut hasher = DefaultHasher::new();
+                (seed, cycles.floor() as i64).hash(&mut hasher);
+                (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+            }
+        };
+        value as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- TempoClock::tap() ---
+
+    #[test]
+    fn test_tap_tempo() {
+        let mut clock = TempoClock::new();
+        for _ in 0..4 {
+            clock.tap();
+            clock.advance(0.5);
+        }
+        assert!((clock.bpm() - 120.0).abs() < 1e-9);
+
+        // Taps at 100 BPM after a pause start over.
+        clock.advance(3.0);
+        for _ in 0..3 {
+            clock.tap();
+            assert_eq!(clock.beat_phase(), 0.0);
+            clock.advance(0.6);
+        }
+        assert!((clock.bpm() - 100.0).abs() < 1e-9);
+    }
+
+    // --- BeatDetector::process() ---
+
+    #[test]
+    fn test_beat_detection() {
+        let mut detector = BeatDetector::new();
+        let frame_secs = 1.0 / 60.0;
+        let mut beats = 0;
+
+        // A kick every 30 frames (120 BPM) over a quiet bed.
+        for frame in 0..600 {
+            let level = if frame % 30 == 0 { 0.8 } else { 0.1 };
+            if detector.process(level, frame as f64 * frame_secs, 0.5) {
+                beats += 1;
+            }
+        }
+
+        assert!(beats >= 18);
+        assert!((detector.bpm().unwrap() - 120.0).abs() < 1.0);
+    }
+
+    // --- LfoShape::value() ---
+
+    #[test]
+    fn test_lfo_shapes() {
+        assert!(LfoShape::Sine.value(0.0, 0).abs() < 1e-6);
+        assert!((LfoShape::Sine.value(0.5, 0) - 1.0).abs() < 1e-6);
+        assert_eq!(LfoShape::Saw.value(2.25, 0), 0.25);
+        assert_eq!(LfoShape::Square.value(0.25, 0), 1.0);
+        assert_eq!(LfoShape::Square.value(0.75, 0), 0.0);
+
+        let random = LfoShape::Random.value(3.1, 7);
+        assert_eq!(random, LfoShape::Random.value(3.9, 7));
+        assert!((0.0..1.0).contains(&random));
+    }
+}
@@ -0,0 +1,563 @@
+//! Exports [Thresholder], which turns frames into black and white masks by
+//! comparing each pixel's brightness with a threshold (see the Threshold node),
+//! and [ThresholdState], the buffers it keeps per stream of frames.
+//!
+//! The threshold can be fixed, picked per frame with Otsu's method, or
+//! adaptive (compared with the average brightness around each pixel). Otsu's
+//! method runs on the GPU: a compute pass counts a brightness histogram and a
+//! second picks the threshold that best separates it into two classes, which
+//! the mask pass reads straight from a storage buffer. The adaptive average is
+//! read from a mip level of a copy of the frame, which box filters it for free.
+
+use crate::mipmap_generator::{self, MipmapGenerator};
+use crate::texture_blitter::TextureBlitter;
+
+const BINS: u64 = 256;
+const MEAN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+const HISTOGRAM_SHADER: &str = r#"
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> histogram: array<atomic<u32>, 256>;
+
+var<workgroup> local_histogram: array<atomic<u32>, 256>;
+
+fn luma(c: vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// Each workgroup counts its 16 by 16 block locally and adds that to the
+// frame's histogram, so there are far fewer global atomics than pixels.
+@compute @workgroup_size(16, 16)
+fn cs_main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+) {
+    atomicStore(&local_histogram[index], 0u);
+    workgroupBarrier();
+
+    let size = textureDimensions(source_texture);
+    if all(id.xy < size) {
+        let color = textureLoad(source_texture, vec2<i32>(id.xy), 0).rgb;
+        let bin = min(u32(luma(color) * 256.0), 255u);
+        atomicAdd(&local_histogram[bin], 1u);
+    }
+    workgroupBarrier();
+
+    let count = atomicLoad(&local_histogram[index]);
+    if count > 0u {
+        atomicAdd(&histogram[index], count);
+    }
+}
+"#;
+
+const OTSU_SHADER: &str = r#"
+@group(0) @binding(0) var<storage, read> histogram: array<u32, 256>;
+@group(0) @binding(1) var<storage, read_write> threshold: vec4<f32>;
+
+// Otsu's method: the threshold that maximizes the variance between the
+// brightness of the pixels below it and of those above it.
+@compute @workgroup_size(1)
+fn cs_main() {
+    var total = 0.0;
+    var weighted_total = 0.0;
+    for (var bin = 0u; bin < 256u; bin++) {
+        let count = f32(histogram[bin]);
+        total += count;
+        weighted_total += f32(bin) * count;
+    }
+
+    var best_bin = 127u;
+    var best_variance = -1.0;
+    var below = 0.0;
+    var weighted_below = 0.0;
+    for (var bin = 0u; bin < 256u; bin++) {
+        let count = f32(histogram[bin]);
+        below += count;
+        weighted_below += f32(bin) * count;
+        let above = total - below;
+        if below == 0.0 {
+            continue;
+        }
+        if above == 0.0 {
+            break;
+        }
+        let mean_below = weighted_below / below;
+        let mean_above = (weighted_total - weighted_below) / above;
+        let difference = mean_below - mean_above;
+        let variance = below * above * difference * difference;
+        if variance > best_variance {
+            best_variance = variance;
+            best_bin = bin;
+        }
+    }
+
+    // Everything in the best bin and below is background.
+    threshold = vec4<f32>(f32(best_bin + 1u) / 256.0, 0.0, 0.0, 1.0);
+}
+"#;
+
+const MASK_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    method: u32,  // 0=fixed, 1=otsu, 2=adaptive
+    mean_level: f32,
+    threshold: f32,
+    offset: f32,
+    invert: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+}
+
+@group(0) @binding(0) var mean_sampler: sampler;
+@group(0) @binding(1) var source_texture: texture_2d<f32>;
+@group(0) @binding(2) var mean_texture: texture_2d<f32>;
+@group(0) @binding(3) var<storage, read> otsu: vec4<f32>;
+@group(0) @binding(4) var<uniform> params: Params;
+
+fn luma(c: vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let value = luma(textureLoad(source_texture, vec2<i32>(in.position.xy), 0).rgb);
+
+    var threshold = params.threshold;
+    if params.method == 1u {
+        threshold = otsu.x;
+    } else if params.method == 2u {
+        let mean = textureSampleLevel(mean_texture, mean_sampler, in.uv, params.mean_level).rgb;
+        threshold = luma(mean) + params.offset;
+    }
+
+    var inside = value >= threshold;
+    if params.invert != 0u {
+        inside = !inside;
+    }
+    return vec4<f32>(vec3<f32>(select(0.0, 1.0, inside)), 1.0);
+}
+"#;
+
+/// How [Thresholder::threshold] picks the threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdMethod {
+    /// The same threshold (`0.0` to `1.0`) everywhere.
+    Fixed(f32),
+    /// The threshold that best splits each frame's brightness into a dark and
+    /// a bright class (Otsu's method).
+    Otsu,
+    /// The average brightness of the `block_size` pixel square around each
+    /// pixel, plus `offset`. Copes with uneven lighting.
+    Adaptive { block_size: u32, offset: f32 },
+}
+
+/// The buffers and textures one stream of frames is thresholded with.
+pub struct ThresholdState {
+    histogram_buf: wgpu::Buffer,
+    otsu_buf: wgpu::Buffer,
+    params_buf: wgpu::Buffer,
+    /// A mipmapped copy of the frame for adaptive thresholds, and its size.
+    mean: Option<(wgpu::Extent3d, wgpu::Texture, wgpu::TextureView)>,
+}
+
+impl ThresholdState {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = |label, size, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+
+        Self {
+            histogram_buf: buffer(
+                "threshold_histogram",
+                BINS * 4,
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            ),
+            otsu_buf: buffer("threshold_otsu", 16, wgpu::BufferUsages::STORAGE),
+            params_buf: buffer(
+                "threshold_params",
+                32,
+                wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            ),
+            mean: None,
+        }
+    }
+
+    /// The mipmapped copy of frames `size` big, created when first needed or
+    /// when the size changes.
+    fn mean_texture(&mut self, device: &wgpu::Device, size: wgpu::Extent3d) -> &wgpu::Texture {
+        if self
+            .mean
+            .as_ref()
+            .is_none_or(|(mean_size, _, _)| *mean_size != size)
+        {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("threshold_mean"),
+                size,
+                mip_level_count: mipmap_generator::mip_level_count(size.width, size.height),
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: MEAN_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.mean = Some((size, texture, view));
+        }
+        &self.mean.as_ref().expect("just created").1
+    }
+}
+
+/// Thresholds textures into render targets of one format.
+pub struct Thresholder {
+    histogram_pipeline: wgpu::ComputePipeline,
+    histogram_bgl: wgpu::BindGroupLayout,
+    otsu_pipeline: wgpu::ComputePipeline,
+    otsu_bgl: wgpu::BindGroupLayout,
+    mask_pipeline: wgpu::RenderPipeline,
+    mask_bgl: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    blitter: TextureBlitter,
+    mipmaps: MipmapGenerator,
+}
+
+impl Thresholder {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let compute = wgpu::ShaderStages::COMPUTE;
+        let fragment = wgpu::ShaderStages::FRAGMENT;
+
+        let histogram_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/threshold_histogram"),
+            entries: &[texture_entry(0, compute), storage_entry(1, compute, false)],
+        });
+        let histogram_pipeline = compute_pipeline(
+            device,
+            "threshold_histogram",
+            HISTOGRAM_SHADER,
+            &histogram_bgl,
+        );
+
+        let otsu_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/threshold_otsu"),
+            entries: &[
+                storage_entry(0, compute, true),
+                storage_entry(1, compute, false),
+            ],
+        });
+        let otsu_pipeline = compute_pipeline(device, "threshold_otsu", OTSU_SHADER, &otsu_bgl);
+
+        let mask_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/threshold_mask"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: fragment,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(1, fragment),
+                texture_entry(2, fragment),
+                storage_entry(3, fragment, true),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: fragment,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let mask_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/threshold_mask"),
+            bind_group_layouts: &[&mask_bgl],
+            ..Default::default()
+        });
+        let mask_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/threshold_mask"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(MASK_SHADER)),
+        });
+        let mask_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/threshold_mask"),
+            layout: Some(&mask_layout),
+            vertex: wgpu::VertexState {
+                module: &mask_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &mask_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sampler/threshold_mean"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        Self {
+            histogram_pipeline,
+            histogram_bgl,
+            otsu_pipeline,
+            otsu_bgl,
+            mask_pipeline,
+            mask_bgl,
+            sampler,
+            blitter: TextureBlitter::new(device, MEAN_FORMAT),
+            mipmaps: MipmapGenerator::new(device, MEAN_FORMAT),
+        }
+    }
+
+    /// Record passes into `encoder` that draw the mask of `source` (`size`
+    /// big) into `target`, which must be the same size: white where a pixel
+    /// is at least as bright as the threshold, or below it if `invert` is set,
+    /// and black elsewhere.
+    ///
+    /// The parameters are written with `queue` into `state`, so `encoder` must
+    /// be submitted before this is called again with the same state.
+    #[allow(clippy::too_many_arguments)]
+    pub fn threshold(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        size: wgpu::Extent3d,
+        state: &mut ThresholdState,
+        method: ThresholdMethod,
+        invert: bool,
+        target: &wgpu::TextureView,
+    ) {
+        let (method_id, mean_level, threshold, offset) = match method {
+            ThresholdMethod::Fixed(threshold) => (0u32, 0.0, threshold, 0.0),
+            ThresholdMethod::Otsu => (1, 0.0, 0.0, 0.0),
+            ThresholdMethod::Adaptive { block_size, offset } => {
+                (2, (block_size.max(1) as f32).log2(), 0.0, offset)
+            }
+        };
+        let mut params = [0u8; 32];
+        params[0..4].copy_from_slice(&method_id.to_le_bytes());
+        params[4..8].copy_from_slice(&f32::to_le_bytes(mean_level));
+        params[8..12].copy_from_slice(&f32::to_le_bytes(threshold));
+        params[12..16].copy_from_slice(&f32::to_le_bytes(offset));
+        params[16..20].copy_from_slice(&u32::from(invert).to_le_bytes());
+        queue.write_buffer(&state.params_buf, 0, &params);
+
+        if method == ThresholdMethod::Otsu {
+            self.pick_otsu_threshold(device, encoder, source, size, state);
+        }
+
+        // The mask pass always binds a mean texture, but only reads it for
+        // adaptive thresholds.
+        let mean_view = match method {
+            ThresholdMethod::Adaptive { .. } => {
+                let mean = state.mean_texture(device, size);
+                let top_level = mean.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("threshold_mean_top"),
+                    base_mip_level: 0,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                });
+                self.blitter.blit(device, encoder, source, &top_level);
+                self.mipmaps.generate(device, encoder, mean);
+                &state.mean.as_ref().expect("just created").2
+            }
+            _ => source,
+        };
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/threshold_mask"),
+            layout: &self.mask_bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(mean_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: state.otsu_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: state.params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("threshold_mask"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.mask_pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+
+    /// Record passes that count `source`'s histogram and store its Otsu
+    /// threshold in `state`.
+    fn pick_otsu_threshold(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        size: wgpu::Extent3d,
+        state: &ThresholdState,
+    ) {
+        encoder.clear_buffer(&state.histogram_buf, 0, None);
+
+        let histogram_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/threshold_histogram"),
+            layout: &self.histogram_bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: state.histogram_buf.as_entire_binding(),
+                },
+            ],
+        });
+        let otsu_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/threshold_otsu"),
+            layout: &self.otsu_bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: state.histogram_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: state.otsu_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("threshold_otsu"),
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&self.histogram_pipeline);
+        cpass.set_bind_group(0, &histogram_group, &[]);
+        cpass.dispatch_workgroups(size.width.div_ceil(16), size.height.div_ceil(16), 1);
+        cpass.set_pipeline(&self.otsu_pipeline);
+        cpass.set_bind_group(0, &otsu_group, &[]);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+}
+
+fn compute_pipeline(
+    device: &wgpu::Device,
+    name: &str,
+    source: &str,
+    bgl: &wgpu::BindGroupLayout,
+) -> wgpu::ComputePipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(&format!("layout/{name}")),
+        bind_group_layouts: &[bgl],
+        ..Default::default()
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(&format!("shader/{name}")),
+        source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(&format!("pipeline/{name}")),
+        layout: Some(&layout),
+        module: &shader,
+        entry_point: Some("cs_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    })
+}
+
+fn texture_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn storage_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    read_only: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
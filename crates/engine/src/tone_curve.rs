@@ -0,0 +1,333 @@
+//! Exports [ToneCurve] and [ColorAdjustment], which remap the values of color
+//! channels (see the Levels and Curves node), and [ColorAdjustment::bake_lut],
+//! which turns an adjustment into the lookup table the GPU samples.
+//!
+//! Curves are stored in node inputs as text, as `x,y` points separated by
+//! spaces (e.g. `0,0 0.25,0.2 1,1`), which is what [ToneCurve]'s [FromStr] and
+//! [Display] implementations read and write.
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// How many entries a baked lookup table has per channel.
+pub const LUT_SIZE: usize = 256;
+
+/// A smooth curve through control points that maps channel values from `0.0`
+/// to `1.0` to new ones. Between points it's a monotone cubic spline, so it
+/// never overshoots: a curve whose points rise never dips anywhere. Before the
+/// first point and after the last it's flat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToneCurve {
+    points: Vec<[f32; 2]>,
+    /// The slope of the curve at each point.
+    tangents: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ToneCurveError {
+    #[error("'{0}' isn't a curve point (expected 'x,y')")]
+    InvalidPoint(String),
+    #[error("a curve needs points at two or more places")]
+    TooFewPoints,
+}
+
+impl ToneCurve {
+    /// The curve that leaves values unchanged.
+    pub fn identity() -> Self {
+        Self::new([[0.0, 0.0], [1.0, 1.0]]).expect("two points")
+    }
+
+    /// A curve through `points` (`[x, y]`). Points are clamped to `0.0` to
+    /// `1.0` and sorted by x, and any point at the same x as an earlier one is
+    /// dropped.
+    pub fn new(points: impl IntoIterator<Item = [f32; 2]>) -> Result<Self, ToneCurveError> {
+        let mut points: Vec<[f32; 2]> = points
+            .into_iter()
+            .filter(|[x, y]| x.is_finite() && y.is_finite())
+            .map(|[x, y]| [x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)])
+            .collect();
+        points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        points.dedup_by(|later, earlier| later[0] == earlier[0]);
+        if points.len() < 2 {
+            return Err(ToneCurveError::TooFewPoints);
+        }
+
+        let tangents = monotone_tangents(&points);
+        Ok(Self { points, tangents })
+    }
+
+    /// The control points, sorted by x.
+    pub fn points(&self) -> &[[f32; 2]] {
+        &self.points
+    }
+
+    /// Whether the curve leaves every value unchanged.
+    pub fn is_identity(&self) -> bool {
+        self.points.iter().all(|[x, y]| x == y)
+            && self.points.first().is_some_and(|[x, _]| *x == 0.0)
+            && self.points.last().is_some_and(|[x, _]| *x == 1.0)
+    }
+
+    /// The value `x` is mapped to.
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        if x <= first[0] {
+            return first[1];
+        }
+        if x >= last[0] {
+            return last[1];
+        }
+
+        let segment = self.points.partition_point(|point| point[0] <= x) - 1;
+        let [x0, y0] = self.points[segment];
+        let [x1, y1] = self.points[segment + 1];
+        let width = x1 - x0;
+        let t = (x - x0) / width;
+        let (t2, t3) = (t * t, t * t * t);
+
+        // Cubic Hermite basis.
+        let value = (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * width * self.tangents[segment]
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * width * self.tangents[segment + 1];
+        value.clamp(0.0, 1.0)
+    }
+}
+
+impl Default for ToneCurve {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl FromStr for ToneCurve {
+    type Err = ToneCurveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let points = s
+            .split_whitespace()
+            .map(|point| {
+                let invalid = || ToneCurveError::InvalidPoint(point.to_string());
+                let (x, y) = point.split_once(',').ok_or_else(invalid)?;
+                Ok([
+                    x.parse().map_err(|_| invalid())?,
+                    y.parse().map_err(|_| invalid())?,
+                ])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(points)
+    }
+}
+
+impl Display for ToneCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, [x, y]) in self.points.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{x},{y}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Tangents for a monotone cubic spline through `points` (Fritsch and
+/// Carlson's method): the average of the neighboring slopes, flattened at
+/// peaks and valleys and scaled down wherever they would overshoot.
+fn monotone_tangents(points: &[[f32; 2]]) -> Vec<f32> {
+    let slopes: Vec<f32> = points
+        .windows(2)
+        .map(|pair| (pair[1][1] - pair[0][1]) / (pair[1][0] - pair[0][0]))
+        .collect();
+
+    let mut tangents = Vec::with_capacity(points.len());
+    tangents.push(slopes[0]);
+    for pair in slopes.windows(2) {
+        tangents.push(if pair[0] * pair[1] <= 0.0 {
+            0.0
+        } else {
+            (pair[0] + pair[1]) / 2.0
+        });
+    }
+    tangents.push(slopes[slopes.len() - 1]);
+
+    for (i, &slope) in slopes.iter().enumerate() {
+        if slope == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+            continue;
+        }
+        let a = tangents[i] / slope;
+        let b = tangents[i + 1] / slope;
+        let length = (a * a + b * b).sqrt();
+        if length > 3.0 {
+            tangents[i] = 3.0 / length * a * slope;
+            tangents[i + 1] = 3.0 / length * b * slope;
+        }
+    }
+    tangents
+}
+
+/// A classic levels adjustment: values between the input black and white
+/// points are stretched to fill the range between the output ones, with a
+/// gamma bending the midtones in between.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Levels {
+    pub input_black: f32,
+    pub input_white: f32,
+    /// The gamma for red, green, and blue. Above `1.0` brightens midtones and
+    /// below darkens them.
+    pub gamma: [f32; 3],
+    pub output_black: f32,
+    pub output_white: f32,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self {
+            input_black: 0.0,
+            input_white: 1.0,
+            gamma: [1.0; 3],
+            output_black: 0.0,
+            output_white: 1.0,
+        }
+    }
+}
+
+impl Levels {
+    /// The value `value` of `channel` (`0` to `2` for red, green, and blue)
+    /// is mapped to.
+    pub fn apply(&self, channel: usize, value: f32) -> f32 {
+        let range = self.input_white - self.input_black;
+        let normalized = if range.abs() > f32::EPSILON {
+            ((value - self.input_black) / range).clamp(0.0, 1.0)
+        } else if value >= self.input_white {
+            1.0
+        } else {
+            0.0
+        };
+        let curved = normalized.powf(1.0 / self.gamma[channel].max(0.01));
+        (self.output_black + curved * (self.output_white - self.output_black)).clamp(0.0, 1.0)
+    }
+}
+
+/// A per-channel color adjustment that can be baked into a lookup table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorAdjustment {
+    Levels(Levels),
+    /// A curve for each of red, green, and blue, followed by `master` for all
+    /// three.
+    Curves {
+        master: ToneCurve,
+        red: ToneCurve,
+        green: ToneCurve,
+        blue: ToneCurve,
+    },
+}
+
+impl ColorAdjustment {
+    /// The value `value` of `channel` (`0` to `2` for red, green, and blue)
+    /// is mapped to.
+    pub fn apply(&self, channel: usize, value: f32) -> f32 {
+        match self {
+            Self::Levels(levels) => levels.apply(channel, value),
+            Self::Curves {
+                master,
+                red,
+                green,
+                blue,
+            } => {
+                let curve = [red, green, blue][channel];
+                master.evaluate(curve.evaluate(value))
+            }
+        }
+    }
+
+    /// The adjustment as an RGBA8 lookup table [LUT_SIZE] entries long: entry
+    /// `i`'s red, green, and blue are what those channels map `i / 255` to.
+    /// Alpha is always opaque.
+    pub fn bake_lut(&self) -> Vec<u8> {
+        let mut lut = Vec::with_capacity(LUT_SIZE * 4);
+        for i in 0..LUT_SIZE {
+            let value = i as f32 / (LUT_SIZE - 1) as f32;
+            for channel in 0..3 {
+                lut.push((self.apply(channel, value) * 255.0).round() as u8);
+            }
+            lut.push(u8::MAX);
+        }
+        lut
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- ToneCurve::from_str() ---
+
+    #[test]
+    fn test_curve_parse_and_display() {
+        let curve: ToneCurve = "1,1 0,0.1  0.5,0.25".parse().unwrap();
+        assert_eq!(curve.points(), &[[0.0, 0.1], [0.5, 0.25], [1.0, 1.0]]);
+        assert_eq!(curve.to_string(), "0,0.1 0.5,0.25 1,1");
+        assert_eq!(curve.to_string().parse::<ToneCurve>().unwrap(), curve);
+
+        assert_eq!(
+            "0,0 1".parse::<ToneCurve>(),
+            Err(ToneCurveError::InvalidPoint("1".to_string()))
+        );
+        assert_eq!(
+            "0.5,0 0.5,1".parse::<ToneCurve>(),
+            Err(ToneCurveError::TooFewPoints)
+        );
+    }
+
+    // --- ToneCurve::evaluate() ---
+
+    #[test]
+    fn test_curve_evaluate() {
+        let identity = ToneCurve::identity();
+        assert!(identity.is_identity());
+        for i in 0..=10 {
+            let x = i as f32 / 10.0;
+            assert!((identity.evaluate(x) - x).abs() < 1e-6);
+        }
+
+        // An S curve stays monotone and passes through its points.
+        let s_curve = ToneCurve::new([[0.0, 0.0], [0.25, 0.1], [0.75, 0.9], [1.0, 1.0]]).unwrap();
+        assert!(!s_curve.is_identity());
+        assert!((s_curve.evaluate(0.25) - 0.1).abs() < 1e-6);
+        let mut previous = 0.0;
+        for i in 0..=100 {
+            let y = s_curve.evaluate(i as f32 / 100.0);
+            assert!(y >= previous);
+            previous = y;
+        }
+
+        let clipped = ToneCurve::new([[0.2, 0.3], [0.8, 0.6]]).unwrap();
+        assert_eq!(clipped.evaluate(0.0), 0.3);
+        assert_eq!(clipped.evaluate(1.0), 0.6);
+    }
+
+    // --- ColorAdjustment::bake_lut() ---
+
+    #[test]
+    fn test_bake_lut() {
+        let identity = ColorAdjustment::Levels(Levels::default()).bake_lut();
+        assert_eq!(identity.len(), LUT_SIZE * 4);
+        assert_eq!(&identity[128 * 4..129 * 4], &[128, 128, 128, 255]);
+
+        let levels = ColorAdjustment::Levels(Levels {
+            input_black: 0.2,
+            input_white: 0.6,
+            gamma: [1.0, 2.0, 1.0],
+            output_black: 0.0,
+            output_white: 0.5,
+        });
+        assert_eq!(levels.apply(0, 0.1), 0.0);
+        assert!((levels.apply(0, 0.4) - 0.25).abs() < 1e-6);
+        assert!(levels.apply(1, 0.4) > levels.apply(0, 0.4));
+        assert!((levels.apply(2, 1.0) - 0.5).abs() < 1e-6);
+    }
+}
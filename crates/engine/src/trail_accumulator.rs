@@ -0,0 +1,293 @@
+//! Exports [TrailAccumulator], which blends frames into a fading accumulation
+//! of the frames before them (see the Trails node), and [Trail], the
+//! accumulation it keeps for one stream of frames.
+//!
+//! The accumulation is kept in half-float textures so slow fades don't stall
+//! at a faint ghost: at 8 bits per channel, fading the last step of brightness
+//! by a few percent rounds straight back to it.
+
+const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+const TRAIL_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    decay: f32,
+    blend: u32,  // 0=lighten, 1=add, 2=screen, 3=average
+    _pad0: u32,
+    _pad1: u32,
+    tint: vec4<f32>,
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var previous_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let position = vec2<i32>(in.position.xy);
+    let source = textureLoad(source_texture, position, 0);
+    let previous = textureLoad(previous_texture, position, 0);
+
+    // Average blends towards each new frame instead of fading, so it leaves
+    // the trail's color alone.
+    if params.blend == 3u {
+        return mix(source, previous, params.decay);
+    }
+
+    let trail = vec4<f32>(previous.rgb * params.tint.rgb, previous.a) * params.decay;
+    var rgb: vec3<f32>;
+    switch params.blend {
+        case 1u: {
+            rgb = min(source.rgb + trail.rgb, vec3<f32>(1.0));
+        }
+        case 2u: {
+            rgb = 1.0 - (1.0 - source.rgb) * (1.0 - clamp(trail.rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+        }
+        default: {
+            rgb = max(source.rgb, trail.rgb);
+        }
+    }
+    return vec4<f32>(rgb, max(source.a, trail.a));
+}
+"#;
+
+/// How [TrailAccumulator::accumulate] combines a frame with its trail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailBlend {
+    /// The brighter of the frame and the faded trail.
+    Lighten,
+    /// The frame plus the faded trail.
+    Add,
+    /// The frame screened over the faded trail.
+    Screen,
+    /// A running average of frames, where the decay is how much of the
+    /// average is kept each frame. The tint isn't used.
+    Average,
+}
+
+/// The settings a frame is added to a [Trail] with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrailSettings {
+    /// How much of the trail is kept each frame, from `0.0` (none) to just
+    /// under `1.0`.
+    pub decay: f32,
+    pub blend: TrailBlend,
+    /// Multiplies the trail each frame, so older frames shift towards it.
+    pub tint: [f32; 3],
+}
+
+/// The accumulated frames of one stream, in two textures that take turns
+/// being read and written.
+pub struct Trail {
+    size: wgpu::Extent3d,
+    views: [wgpu::TextureView; 2],
+    /// Which of `views` holds the latest accumulation.
+    latest: usize,
+    params_buf: wgpu::Buffer,
+}
+
+impl Trail {
+    /// Create an empty (transparent) trail for frames `size` big.
+    pub fn new(device: &wgpu::Device, size: wgpu::Extent3d) -> Self {
+        let create_view = || {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("trail_accumulation"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: ACCUMULATION_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            texture.create_view(&wgpu::TextureViewDescriptor::default())
+        };
+
+        // New textures are zeroed, so the trail starts out empty.
+        Self {
+            size,
+            views: [create_view(), create_view()],
+            latest: 0,
+            params_buf: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("trail_params"),
+                size: 32,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    pub fn size(&self) -> wgpu::Extent3d {
+        self.size
+    }
+
+    /// The latest accumulation.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.views[self.latest]
+    }
+}
+
+/// Adds frames to [Trail]s.
+pub struct TrailAccumulator {
+    pipeline: wgpu::RenderPipeline,
+    bgl: wgpu::BindGroupLayout,
+}
+
+impl TrailAccumulator {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/trail"),
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/trail"),
+            bind_group_layouts: &[&bgl],
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/trail"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(TRAIL_SHADER)),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/trail"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: ACCUMULATION_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        Self { pipeline, bgl }
+    }
+
+    /// Record a pass into `encoder` that blends `source`, which must be the
+    /// same size as `trail`, with `trail` faded by one frame. The result
+    /// becomes the trail's latest accumulation.
+    ///
+    /// The settings are written with `queue` into `trail`, so `encoder` must
+    /// be submitted before this is called again with the same trail.
+    pub fn accumulate(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        trail: &mut Trail,
+        settings: TrailSettings,
+    ) {
+        let blend: u32 = match settings.blend {
+            TrailBlend::Lighten => 0,
+            TrailBlend::Add => 1,
+            TrailBlend::Screen => 2,
+            TrailBlend::Average => 3,
+        };
+        let mut params = [0u8; 32];
+        params[0..4].copy_from_slice(&settings.decay.clamp(0.0, 0.999).to_le_bytes());
+        params[4..8].copy_from_slice(&blend.to_le_bytes());
+        for (i, channel) in settings.tint.iter().chain(&[1.0]).enumerate() {
+            params[16 + i * 4..20 + i * 4].copy_from_slice(&channel.to_le_bytes());
+        }
+        queue.write_buffer(&trail.params_buf, 0, &params);
+
+        let previous = trail.latest;
+        let next = 1 - previous;
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/trail"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&trail.views[previous]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: trail.params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("trail"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &trail.views[next],
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+        drop(rpass);
+
+        trail.latest = next;
+    }
+}
@@ -5,7 +5,14 @@
 //!
 //! This abstraction avoids allocating a new GPU texture every frame when
 //! feeding CPU-decoded frames into the pipeline.
+//!
+//! The texture has a full mip chain which is regenerated on every upload, so
+//! sources that are drawn smaller than their size can be sampled without
+//! aliasing.
 use crate::engine_errors::EngineError;
+use crate::mipmap_generator::{MipmapGenerator, mip_level_count};
+
+const UPLOAD_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
 
 /// Stages CPU RGBA data into a GPU texture and returns a [wgpu::TextureView].
 ///
@@ -15,6 +22,8 @@ use crate::engine_errors::EngineError;
 pub struct UploadStager {
     tex: Option<wgpu::Texture>,
     extent: wgpu::Extent3d,
+    /// Created along with the first texture.
+    mipmaps: Option<MipmapGenerator>,
 }
 
 impl Default for UploadStager {
@@ -33,6 +42,7 @@ impl UploadStager {
                 height: 0,
                 depth_or_array_layers: 1,
             },
+            mipmaps: None,
         }
     }
 
@@ -51,19 +61,25 @@ impl UploadStager {
         let tex = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("upload_texture"),
             size: self.extent,
-            mip_level_count: 1,
+            mip_level_count: mip_level_count(width, height),
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format: UPLOAD_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
             view_formats: &[],
         });
 
+        self.mipmaps
+            .get_or_insert_with(|| MipmapGenerator::new(device, UPLOAD_FORMAT));
+
         self.tex = Some(tex);
     }
 
-    /// Blit RGBA pixel data from CPU memory into the staging texture and
-    /// return a [wgpu::TextureView] that can be used for sampling.
+    /// Blit RGBA pixel data from CPU memory into the staging texture,
+    /// regenerate its mip levels, and return a [wgpu::TextureView] (of every
+    /// level) that can be used for sampling.
     pub fn cpu_to_gpu_rgba(
         &mut self,
         device: &wgpu::Device,
@@ -103,6 +119,18 @@ impl UploadStager {
             self.extent,
         );
 
+        // Downsample the new data into the smaller levels. `write_texture` is
+        // applied before the next submission, so this sees the new data.
+        if let (Some(tex), Some(mipmaps)) = (&self.tex, &self.mipmaps)
+            && tex.mip_level_count() > 1
+        {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("upload_mipmaps"),
+            });
+            mipmaps.generate(device, &mut encoder, tex);
+            queue.submit(Some(encoder.finish()));
+        }
+
         // Create and return the texture view; unwrap is safe here because we
         // checked [tex] above when writing.
         Ok(self
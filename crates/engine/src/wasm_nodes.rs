@@ -0,0 +1,323 @@
+//! Runs nodes whose logic is a WebAssembly module instead of a shader, for
+//! things WGSL can't express (state machines, string handling, math over many
+//! values). Modules are sandboxed: they get no imports, so they can't reach
+//! the file system, the network, or anything else outside their own memory,
+//! and each run is limited in time and memory.
+//!
+//! Running modules needs a build with the `wasm` feature; without it
+//! [load_module] always fails with [WasmNodeError::Unsupported].
+//!
+//! ABI
+//! ---
+//! A module exports:
+//!
+//! - `memory`: its linear memory.
+//! - `alloc(len: i32) -> i32`: returns a pointer to `len` free bytes, which
+//!   the engine writes the run's input into.
+//! - `run(ptr: i32, len: i32) -> i64`: runs the node on the input at `ptr`,
+//!   returning where its output is as `(output_ptr << 32) | output_len`.
+//!
+//! Input and output are UTF-8 JSON. The input is an object with the time
+//! since the last run in `delta_secs` and the node's values in `inputs`, keyed
+//! by input name:
+//!
+//! ```json
+//! { "delta_secs": 0.0333, "inputs": { "Count": 4, "Color": [1.0, 0.5, 0.0, 1.0] } }
+//! ```
+//!
+//! The output is an object with a value for every output the node.json
+//! declares, keyed by output name. Bools, ints, floats, and text are JSON
+//! values of the same kind, dimensions are `[width, height]`, pixels are
+//! `[r, g, b, a]`, and float arrays are arrays of numbers. Enum inputs are their choice's index and file inputs are
+//! their path. Frame and MIDI inputs aren't passed to the module, and WASM
+//! nodes can't have frame or MIDI outputs.
+//!
+//! The module instance lives as long as the node does, so modules can keep
+//! state between runs. A module that fails a run (traps, runs out of time, or
+//! returns bad output) is started over from scratch for the next one.
+
+#[cfg(feature = "wasm")]
+mod wasmi_module;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::graph_executor::NodeValue;
+use crate::node::NodeDefinition;
+use crate::node::engine_node::NodeOutputKind;
+
+/// The most a node.json can set its `time_limit_ms` to.
+pub const MAX_TIME_LIMIT_MS: u32 = 50;
+
+/// The most a node.json can set its `memory_limit_mib` to.
+pub const MAX_MEMORY_LIMIT_MIB: u32 = 256;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum WasmNodeError {
+    #[error("WASM nodes need a build with the 'wasm' feature")]
+    Unsupported,
+    #[error("failed to load the WASM module '{path}': {message}")]
+    Load { path: PathBuf, message: String },
+    #[error("the WASM module doesn't export '{0}'")]
+    MissingExport(&'static str),
+    #[error("the WASM module took longer than {0:?}")]
+    TimeLimit(Duration),
+    #[error("the WASM module stopped: {0}")]
+    Trap(String),
+    #[error("the WASM module's memory doesn't fit {0}")]
+    OutOfBounds(&'static str),
+    #[error("the WASM module's output isn't valid JSON: {0}")]
+    InvalidJson(String),
+    #[error("the WASM module didn't output '{0}'")]
+    MissingOutput(String),
+    #[error("the WASM module's output '{output}' must be a {expected}")]
+    InvalidOutput {
+        output: String,
+        expected: &'static str,
+    },
+}
+
+/// How long a module may take and how much memory it may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    pub time: Duration,
+    pub memory_bytes: usize,
+}
+
+impl WasmLimits {
+    /// Limits from a node.json's `time_limit_ms` and `memory_limit_mib`,
+    /// capped at [MAX_TIME_LIMIT_MS] and [MAX_MEMORY_LIMIT_MIB].
+    pub fn new(time_limit_ms: u32, memory_limit_mib: u32) -> Self {
+        Self {
+            time: Duration::from_millis(time_limit_ms.clamp(1, MAX_TIME_LIMIT_MS).into()),
+            memory_bytes: memory_limit_mib.clamp(1, MAX_MEMORY_LIMIT_MIB) as usize * 1024 * 1024,
+        }
+    }
+}
+
+/// A loaded module, ready to run.
+pub trait WasmModule: Send {
+    /// Run the module on `input` (see the [ABI](self#abi)) and return its
+    /// output.
+    fn run(&mut self, input: &[u8]) -> Result<Vec<u8>, WasmNodeError>;
+}
+
+/// Load the module at `path`, checking that it has the exports the ABI needs.
+#[cfg(feature = "wasm")]
+pub fn load_module(path: &Path, limits: WasmLimits) -> Result<Box<dyn WasmModule>, WasmNodeError> {
+    Ok(Box::new(wasmi_module::WasmiModule::load(path, limits)?))
+}
+
+/// Load the module at `path`, checking that it has the exports the ABI needs.
+#[cfg(not(feature = "wasm"))]
+pub fn load_module(
+    _path: &Path,
+    _limits: WasmLimits,
+) -> Result<Box<dyn WasmModule>, WasmNodeError> {
+    Err(WasmNodeError::Unsupported)
+}
+
+/// The JSON a module is run on for a node with `inputs`.
+pub fn encode_inputs(inputs: &HashMap<String, NodeValue>, delta_secs: f32) -> Vec<u8> {
+    let inputs: Map<String, Value> = inputs
+        .iter()
+        .filter_map(|(name, value)| Some((name.clone(), input_to_json(value)?)))
+        .collect();
+
+    let mut input = Map::new();
+    input.insert("delta_secs".to_string(), delta_secs.into());
+    input.insert("inputs".to_string(), Value::Object(inputs));
+    Value::Object(input).to_string().into_bytes()
+}
+
+fn input_to_json(value: &NodeValue) -> Option<Value> {
+    Some(match value {
+        NodeValue::Frame(_) | NodeValue::Midi(_) => return None,
+        NodeValue::Bool(value) => (*value).into(),
+        NodeValue::Int(value) => (*value).into(),
+        NodeValue::Float(value) => (*value).into(),
+        NodeValue::Dimensions(width, height) => Value::from(vec![*width, *height]),
+        NodeValue::Pixel(pixel) => Value::from(pixel.to_vec()),
+        NodeValue::Text(text) => text.clone().into(),
+        NodeValue::Enum(index) => (*index).into(),
+        NodeValue::File(path) => path.to_string_lossy().into_owned().into(),
+        NodeValue::FloatArray(values) => Value::from(values.clone()),
+    })
+}
+
+/// The node's output values from a module's `output`, which must have every
+/// output `definition` declares.
+pub fn decode_outputs(
+    definition: &NodeDefinition,
+    output: &[u8],
+) -> Result<HashMap<String, NodeValue>, WasmNodeError> {
+    let json: Value = serde_json::from_slice(output)
+        .map_err(|error| WasmNodeError::InvalidJson(error.to_string()))?;
+    let Value::Object(mut values) = json else {
+        return Err(WasmNodeError::InvalidJson(
+            "expected an object of output values".to_string(),
+        ));
+    };
+
+    definition
+        .node
+        .outputs
+        .iter()
+        .map(|output| {
+            let value = values
+                .remove(&output.name)
+                .ok_or_else(|| WasmNodeError::MissingOutput(output.name.clone()))?;
+            let value = output_from_json(output.kind, &value).ok_or_else(|| {
+                WasmNodeError::InvalidOutput {
+                    output: output.name.clone(),
+                    expected: expected_json(output.kind),
+                }
+            })?;
+            Ok((output.name.clone(), value))
+        })
+        .collect()
+}
+
+fn output_from_json(kind: NodeOutputKind, value: &Value) -> Option<NodeValue> {
+    Some(match kind {
+        NodeOutputKind::Frame | NodeOutputKind::MidiPacket => return None,
+        NodeOutputKind::Bool => NodeValue::Bool(value.as_bool()?),
+        NodeOutputKind::Int => NodeValue::Int(i32::try_from(value.as_i64()?).ok()?),
+        NodeOutputKind::Float => NodeValue::Float(value.as_f64()? as f32),
+        NodeOutputKind::Dimensions => {
+            let [width, height] = json_array(value)?;
+            NodeValue::Dimensions(
+                u32::try_from(width.as_u64()?).ok()?,
+                u32::try_from(height.as_u64()?).ok()?,
+            )
+        }
+        NodeOutputKind::Pixel => {
+            let channels: [&Value; 4] = json_array(value)?;
+            let mut pixel = [0.0; 4];
+            for (channel, value) in pixel.iter_mut().zip(channels) {
+                *channel = value.as_f64()? as f32;
+            }
+            NodeValue::Pixel(pixel)
+        }
+        NodeOutputKind::Text => NodeValue::Text(value.as_str()?.to_string()),
+        NodeOutputKind::FloatArray => NodeValue::FloatArray(
+            value
+                .as_array()?
+                .iter()
+                .map(|value| Some(value.as_f64()? as f32))
+                .collect::<Option<_>>()?,
+        ),
+    })
+}
+
+fn json_array<const N: usize>(value: &Value) -> Option<[&Value; N]> {
+    let values: Vec<&Value> = value.as_array()?.iter().collect();
+    values.try_into().ok()
+}
+
+fn expected_json(kind: NodeOutputKind) -> &'static str {
+    match kind {
+        NodeOutputKind::Frame | NodeOutputKind::MidiPacket => "value WASM nodes can output",
+        NodeOutputKind::Bool => "bool",
+        NodeOutputKind::Int => "32-bit integer",
+        NodeOutputKind::Float => "number",
+        NodeOutputKind::Dimensions => "[width, height] array",
+        NodeOutputKind::Pixel => "[r, g, b, a] array",
+        NodeOutputKind::Text => "string",
+        NodeOutputKind::FloatArray => "array of numbers",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::node::EngineNode;
+
+    fn definition(outputs: &str) -> NodeDefinition {
+        let node: EngineNode = serde_json::from_str(&format!(
+            r#"{{
+                "name": "Test",
+                "inputs": [],
+                "outputs": {outputs},
+                "executor": {{ "Wasm": {{ "module": "node.wasm" }} }}
+            }}"#
+        ))
+        .unwrap();
+        NodeDefinition {
+            node,
+            shader_path: None,
+            folder_path: PathBuf::new(),
+            example_image_paths: Vec::new(),
+        }
+    }
+
+    // --- encode_inputs() ---
+
+    #[test]
+    fn encode_inputs_passes_values_by_name() {
+        let inputs = HashMap::from([
+            ("Count".to_string(), NodeValue::Int(4)),
+            ("Size".to_string(), NodeValue::Dimensions(640, 480)),
+            ("Mode".to_string(), NodeValue::Enum(2)),
+        ]);
+
+        let input: Value = serde_json::from_slice(&encode_inputs(&inputs, 0.5)).unwrap();
+
+        assert_eq!(
+            input,
+            serde_json::json!({
+                "delta_secs": 0.5,
+                "inputs": { "Count": 4, "Size": [640, 480], "Mode": 2 },
+            })
+        );
+    }
+
+    // --- decode_outputs() ---
+
+    #[test]
+    fn decode_outputs_reads_declared_outputs() {
+        let definition = definition(
+            r#"[
+                { "name": "On", "kind": "Bool" },
+                { "name": "Color", "kind": "Pixel" },
+                { "name": "Label", "kind": "Text" }
+            ]"#,
+        );
+
+        let outputs = decode_outputs(
+            &definition,
+            br#"{ "On": true, "Color": [1, 0.5, 0, 1], "Label": "hi", "Extra": 3 }"#,
+        )
+        .unwrap();
+
+        assert_eq!(outputs.len(), 3);
+        assert!(matches!(outputs["On"], NodeValue::Bool(true)));
+        assert!(matches!(
+            outputs["Color"],
+            NodeValue::Pixel([1.0, 0.5, 0.0, 1.0])
+        ));
+        assert!(matches!(&outputs["Label"], NodeValue::Text(text) if text == "hi"));
+    }
+
+    #[test]
+    fn decode_outputs_rejects_missing_and_mistyped_outputs() {
+        let definition = definition(r#"[{ "name": "Count", "kind": "Int" }]"#);
+
+        assert!(matches!(
+            decode_outputs(&definition, b"{}"),
+            Err(WasmNodeError::MissingOutput(name)) if name == "Count"
+        ));
+        assert!(matches!(
+            decode_outputs(&definition, br#"{ "Count": 1.5 }"#),
+            Err(WasmNodeError::InvalidOutput { .. })
+        ));
+        assert!(matches!(
+            decode_outputs(&definition, b"[1]"),
+            Err(WasmNodeError::InvalidJson(_))
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+//! Runs WASM node modules with the wasmi interpreter.
+//!
+//! wasmi can't stop a module after a set amount of time, so the time limit is
+//! enforced with fuel: each run gets about as many instructions as the limit
+//! allows ([FUEL_PER_MILLISECOND]), and runs that use them all up (or still
+//! take longer than the limit) fail with [WasmNodeError::TimeLimit].
+
+use std::path::Path;
+use std::time::Instant;
+
+use wasmi::{
+    Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use super::{WasmLimits, WasmModule, WasmNodeError};
+
+/// Roughly how many instructions wasmi runs in a millisecond, on the slower
+/// machines the app supports.
+const FUEL_PER_MILLISECOND: u64 = 100_000;
+
+pub struct WasmiModule {
+    engine: Engine,
+    module: Module,
+    limits: WasmLimits,
+    /// The running instance. [None] after a failed run, so the next run
+    /// starts over.
+    instance: Option<Instance>,
+}
+
+struct Instance {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    run: TypedFunc<(i32, i32), i64>,
+}
+
+impl WasmiModule {
+    pub fn load(path: &Path, limits: WasmLimits) -> Result<Self, WasmNodeError> {
+        let load_error = |message: String| WasmNodeError::Load {
+            path: path.to_path_buf(),
+            message,
+        };
+        let bytes = std::fs::read(path).map_err(|e| load_error(e.to_string()))?;
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &bytes).map_err(|e| load_error(e.to_string()))?;
+
+        let mut this = Self {
+            engine,
+            module,
+            limits,
+            instance: None,
+        };
+        // Start it now so a module missing exports fails when it's loaded.
+        this.instance = Some(this.instantiate()?);
+        Ok(this)
+    }
+
+    fn instantiate(&self) -> Result<Instance, WasmNodeError> {
+        let store_limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, store_limits);
+        store.limiter(|limits| limits);
+        self.refuel(&mut store)?;
+
+        // No imports, so modules can only touch their own memory.
+        let linker = Linker::<StoreLimits>::new(&self.engine);
+        let instance = linker
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| WasmNodeError::Trap(e.to_string()))?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or(WasmNodeError::MissingExport("memory"))?;
+        let alloc = instance
+            .get_typed_func(&store, "alloc")
+            .map_err(|_| WasmNodeError::MissingExport("alloc"))?;
+        let run = instance
+            .get_typed_func(&store, "run")
+            .map_err(|_| WasmNodeError::MissingExport("run"))?;
+
+        Ok(Instance {
+            store,
+            memory,
+            alloc,
+            run,
+        })
+    }
+
+    fn refuel(&self, store: &mut Store<StoreLimits>) -> Result<(), WasmNodeError> {
+        let millis = self.limits.time.as_millis() as u64;
+        store
+            .set_fuel(millis * FUEL_PER_MILLISECOND)
+            .map_err(|e| WasmNodeError::Trap(e.to_string()))
+    }
+
+    fn run_instance(
+        &self,
+        instance: &mut Instance,
+        input: &[u8],
+    ) -> Result<Vec<u8>, WasmNodeError> {
+        let started = Instant::now();
+        self.refuel(&mut instance.store)?;
+        let call_error = |store: &Store<StoreLimits>, error: wasmi::Error| {
+            if store.get_fuel().is_ok_and(|fuel| fuel == 0) {
+                WasmNodeError::TimeLimit(self.limits.time)
+            } else {
+                WasmNodeError::Trap(error.to_string())
+            }
+        };
+
+        let input_len =
+            i32::try_from(input.len()).map_err(|_| WasmNodeError::OutOfBounds("the input"))?;
+        let input_ptr = instance
+            .alloc
+            .call(&mut instance.store, input_len)
+            .map_err(|e| call_error(&instance.store, e))?;
+        let input_start = input_ptr as u32 as usize;
+        instance
+            .memory
+            .data_mut(&mut instance.store)
+            .get_mut(input_start..input_start + input.len())
+            .ok_or(WasmNodeError::OutOfBounds("the input"))?
+            .copy_from_slice(input);
+
+        let packed = instance
+            .run
+            .call(&mut instance.store, (input_ptr, input_len))
+            .map_err(|e| call_error(&instance.store, e))?;
+        if started.elapsed() > self.limits.time {
+            return Err(WasmNodeError::TimeLimit(self.limits.time));
+        }
+
+        let output_start = (packed as u64 >> 32) as usize;
+        let output_len = (packed as u64 & 0xFFFF_FFFF) as usize;
+        instance
+            .memory
+            .data(&instance.store)
+            .get(output_start..output_start + output_len)
+            .map(<[u8]>::to_vec)
+            .ok_or(WasmNodeError::OutOfBounds("the output"))
+    }
+}
+
+impl WasmModule for WasmiModule {
+    fn run(&mut self, input: &[u8]) -> Result<Vec<u8>, WasmNodeError> {
+        let mut instance = match self.instance.take() {
+            Some(instance) => instance,
+            None => self.instantiate()?,
+        };
+        let output = self.run_instance(&mut instance, input)?;
+        self.instance = Some(instance);
+        Ok(output)
+    }
+}
@@ -0,0 +1,416 @@
+//! Exports [WatermarkCompositor], which draws a mark (a logo or a line of
+//! text) over a frame (see the Watermark node), [watermark_rect], which works
+//! out where the mark goes, and [rasterize_text], which turns text into a mark.
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use image::{Rgba, RgbaImage};
+
+use crate::texture_blitter::TextureBlitter;
+
+/// Draws the mark over the viewport it's given, faded by the opacity. The
+/// fullscreen triangle matches the one used by node shaders.
+const WATERMARK_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    opacity: f32,
+    _pad: vec3<f32>,
+}
+
+@group(0) @binding(0) var mark_sampler: sampler;
+@group(0) @binding(1) var mark_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(mark_texture, mark_sampler, in.uv);
+    return vec4<f32>(color.rgb, color.a * params.opacity);
+}
+"#;
+
+/// The font text marks are drawn with.
+const FONT: &[u8] = epaint_default_fonts::UBUNTU_LIGHT;
+
+/// Where a mark is placed on the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl WatermarkPosition {
+    /// Every position, in the order of the Watermark node's Position choices.
+    pub const ALL: [Self; 5] = [
+        Self::TopLeft,
+        Self::TopRight,
+        Self::BottomLeft,
+        Self::BottomRight,
+        Self::Center,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TopLeft => "Top Left",
+            Self::TopRight => "Top Right",
+            Self::BottomLeft => "Bottom Left",
+            Self::BottomRight => "Bottom Right",
+            Self::Center => "Center",
+        }
+    }
+}
+
+/// Where a `mark_size` mark drawn `scale` times its size goes on a
+/// `frame_size` frame, `margin` pixels in from the edges at `position`, as
+/// `[left, top, width, height]` in pixels. Marks too big for the frame are
+/// shrunk (keeping their shape) to fit inside the margins.
+pub fn watermark_rect(
+    frame_size: (u32, u32),
+    mark_size: (u32, u32),
+    position: WatermarkPosition,
+    margin: f32,
+    scale: f32,
+) -> [f32; 4] {
+    let (frame_width, frame_height) = (frame_size.0 as f32, frame_size.1 as f32);
+    let margin = margin.clamp(0.0, frame_width.min(frame_height) / 2.0);
+    let mut width = mark_size.0 as f32 * scale.max(0.0);
+    let mut height = mark_size.1 as f32 * scale.max(0.0);
+
+    let fit = ((frame_width - 2.0 * margin) / width)
+        .min((frame_height - 2.0 * margin) / height)
+        .min(1.0);
+    if fit.is_finite() {
+        width *= fit;
+        height *= fit;
+    }
+
+    let right = frame_width - margin - width;
+    let bottom = frame_height - margin - height;
+    let (left, top) = match position {
+        WatermarkPosition::TopLeft => (margin, margin),
+        WatermarkPosition::TopRight => (right, margin),
+        WatermarkPosition::BottomLeft => (margin, bottom),
+        WatermarkPosition::BottomRight => (right, bottom),
+        WatermarkPosition::Center => ((frame_width - width) / 2.0, (frame_height - height) / 2.0),
+    };
+    [left, top, width, height]
+}
+
+/// Draw `text` `size` pixels tall in `color` (straight alpha) on a
+/// transparent image just big enough for it, one line per line of `text`.
+/// Returns [None] if there's nothing to draw.
+pub fn rasterize_text(text: &str, size: f32, color: [f32; 4]) -> Option<RgbaImage> {
+    let font = FontRef::try_from_slice(FONT).expect("the built-in font is valid");
+    let font = font.as_scaled(PxScale::from(size.max(1.0)));
+    let line_height = font.height() + font.line_gap();
+
+    let lines: Vec<&str> = text.lines().collect();
+    let line_width = |line: &str| {
+        let mut width = 0.0;
+        let mut previous = None;
+        for glyph_id in line.chars().map(|c| font.glyph_id(c)) {
+            if let Some(previous) = previous {
+                width += font.kern(previous, glyph_id);
+            }
+            width += font.h_advance(glyph_id);
+            previous = Some(glyph_id);
+        }
+        width
+    };
+    let width = lines
+        .iter()
+        .map(|line| line_width(line))
+        .fold(0.0, f32::max);
+    let height = line_height * lines.len() as f32;
+    if width < 1.0 || height < 1.0 {
+        return None;
+    }
+
+    let (width, height) = (width.ceil() as u32, height.ceil() as u32);
+    let mut coverage = vec![0.0f32; (width * height) as usize];
+    for (index, line) in lines.iter().enumerate() {
+        let baseline = index as f32 * line_height + font.ascent();
+        let mut caret = 0.0;
+        let mut previous = None;
+        for c in line.chars() {
+            let glyph_id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += font.kern(previous, glyph_id);
+            }
+            let glyph = glyph_id.with_scale_and_position(font.scale(), point(caret, baseline));
+            caret += font.h_advance(glyph_id);
+            previous = Some(glyph_id);
+
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|x, y, value| {
+                let x = bounds.min.x as i64 + x as i64;
+                let y = bounds.min.y as i64 + y as i64;
+                if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                    let pixel = &mut coverage[(y as u32 * width + x as u32) as usize];
+                    *pixel = pixel.max(value);
+                }
+            });
+        }
+    }
+
+    let [red, green, blue, alpha] = color.map(|channel| channel.clamp(0.0, 1.0));
+    let to_u8 = |channel: f32| (channel * 255.0).round() as u8;
+    Some(RgbaImage::from_fn(width, height, |x, y| {
+        let value = coverage[(y * width + x) as usize].min(1.0);
+        Rgba([to_u8(red), to_u8(green), to_u8(blue), to_u8(alpha * value)])
+    }))
+}
+
+/// Draws marks over frames into render targets of one format.
+pub struct WatermarkCompositor {
+    pipeline: wgpu::RenderPipeline,
+    bgl: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    params_buf: wgpu::Buffer,
+    blitter: TextureBlitter,
+}
+
+impl WatermarkCompositor {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/watermark"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/watermark"),
+            bind_group_layouts: &[&bgl],
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/watermark"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(WATERMARK_SHADER)),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/watermark"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sampler/watermark"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let params_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("watermark_params"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            bgl,
+            sampler,
+            params_buf,
+            blitter: TextureBlitter::new(device, format),
+        }
+    }
+
+    /// Record passes into `encoder` that copy `source` into `target` (which
+    /// must be the same size) and draw `mark` over it in `rect` (see
+    /// [watermark_rect]), faded by `opacity`.
+    ///
+    /// The opacity is written with `queue`, so `encoder` must be submitted
+    /// before this is called again.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        mark: &wgpu::TextureView,
+        rect: [f32; 4],
+        opacity: f32,
+        target: &wgpu::TextureView,
+        target_size: wgpu::Extent3d,
+    ) {
+        self.blitter.blit(device, encoder, source, target);
+
+        // Viewports have to be inside the target.
+        let (target_width, target_height) = (target_size.width as f32, target_size.height as f32);
+        let left = rect[0].clamp(0.0, target_width);
+        let top = rect[1].clamp(0.0, target_height);
+        let width = (rect[0] + rect[2]).clamp(0.0, target_width) - left;
+        let height = (rect[1] + rect[3]).clamp(0.0, target_height) - top;
+        if !(width >= 1.0 && height >= 1.0) || opacity <= 0.0 {
+            return;
+        }
+
+        let mut params = [0u8; 16];
+        params[..4].copy_from_slice(&opacity.min(1.0).to_le_bytes());
+        queue.write_buffer(&self.params_buf, 0, &params);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/watermark"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(mark),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("watermark"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_viewport(left, top, width, height, 0.0, 1.0);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- watermark_rect() ---
+
+    #[test]
+    fn test_watermark_rect() {
+        let frame = (1920, 1080);
+        let mark = (200, 100);
+
+        assert_eq!(
+            watermark_rect(frame, mark, WatermarkPosition::TopLeft, 20.0, 1.0),
+            [20.0, 20.0, 200.0, 100.0]
+        );
+        assert_eq!(
+            watermark_rect(frame, mark, WatermarkPosition::BottomRight, 20.0, 0.5),
+            [1800.0, 1010.0, 100.0, 50.0]
+        );
+        assert_eq!(
+            watermark_rect(frame, mark, WatermarkPosition::Center, 20.0, 1.0),
+            [860.0, 490.0, 200.0, 100.0]
+        );
+
+        // Marks bigger than the frame are shrunk to fit inside the margins.
+        assert_eq!(
+            watermark_rect(
+                (400, 300),
+                (800, 100),
+                WatermarkPosition::TopRight,
+                10.0,
+                1.0
+            ),
+            [10.0, 10.0, 380.0, 47.5]
+        );
+    }
+
+    // --- rasterize_text() ---
+
+    #[test]
+    fn test_rasterize_text() {
+        let one_line = rasterize_text("Preview", 32.0, [1.0; 4]).unwrap();
+        let two_lines = rasterize_text("Preview\nDraft", 32.0, [1.0; 4]).unwrap();
+        assert!(one_line.width() > one_line.height());
+        assert_eq!(two_lines.width(), one_line.width());
+        assert!(two_lines.height() > one_line.height() * 3 / 2);
+        assert!(one_line.pixels().any(|pixel| pixel[3] == 255));
+        assert!(one_line.pixels().any(|pixel| pixel[3] == 0));
+
+        assert!(rasterize_text("", 32.0, [1.0; 4]).is_none());
+    }
+}
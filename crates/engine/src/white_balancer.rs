@@ -0,0 +1,541 @@
+//! Exports [WhiteBalancer], which adjusts exposure and white balance and can
+//! correct white balance and exposure automatically (see the White Balance node),
+//! and [AutoBalance], the per-node state automatic correction keeps between
+//! frames.
+//!
+//! Automatic correction runs entirely on the GPU. Each frame is shrunk into a
+//! small analysis texture whose mip chain box filters it down, a single compute
+//! workgroup measures the smallest useful level and turns that into a gain per
+//! channel, and the gains are blended with the previous frame's in a storage
+//! buffer before the correction is drawn. Nothing is read back, so the gains
+//! are always for the frame being drawn.
+
+use crate::mipmap_generator::MipmapGenerator;
+use crate::texture_blitter::TextureBlitter;
+
+/// The size frames are shrunk to before their mip chain is generated.
+const ANALYSIS_SIZE: u32 = 256;
+/// The mip level of the analysis texture that gets measured (64 by 64).
+const ANALYSIS_LEVEL: u32 = 2;
+const ANALYSIS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// The most of the previous frame's gains that can be kept. Any more and the
+/// correction would never catch up with a change in lighting.
+pub const MAX_SMOOTHING: f32 = 0.99;
+
+const ANALYZE_SHADER: &str = r#"
+struct AnalyzeParams {
+    method: u32,
+    smoothing: f32,
+    auto_exposure: u32,
+    _pad: f32,
+}
+
+@group(0) @binding(0) var analysis_texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> gains: vec4<f32>;
+@group(0) @binding(2) var<uniform> params: AnalyzeParams;
+
+const THREADS: u32 = 256u;
+const BINS: u32 = 64u;
+const ANALYSIS_LEVEL: i32 = 2;
+// The fraction of the brightest pixels in each channel that are ignored as
+// highlights (specular glints, clipped lights) when finding its white point.
+const WHITE_PERCENTILE: f32 = 0.01;
+// The average brightness (in linear light) auto exposure aims for.
+const MIDDLE_GRAY: f32 = 0.18;
+const LUMA: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
+
+var<workgroup> sums: array<vec3<f32>, 256>;
+var<workgroup> histogram: array<atomic<u32>, 192>;
+
+fn to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+@compute @workgroup_size(256)
+fn cs_main(@builtin(local_invocation_index) index: u32) {
+    let size = textureDimensions(analysis_texture, ANALYSIS_LEVEL);
+    let count = size.x * size.y;
+
+    for (var bin = index; bin < BINS * 3u; bin += THREADS) {
+        atomicStore(&histogram[bin], 0u);
+    }
+    workgroupBarrier();
+
+    var sum = vec3<f32>(0.0);
+    for (var i = index; i < count; i += THREADS) {
+        let coord = vec2<i32>(i32(i % size.x), i32(i / size.x));
+        let color = to_linear(textureLoad(analysis_texture, coord, ANALYSIS_LEVEL).rgb);
+        sum += color;
+        let bins = min(vec3<u32>(color * f32(BINS)), vec3<u32>(BINS - 1u));
+        atomicAdd(&histogram[bins.r], 1u);
+        atomicAdd(&histogram[BINS + bins.g], 1u);
+        atomicAdd(&histogram[BINS * 2u + bins.b], 1u);
+    }
+    sums[index] = sum;
+    workgroupBarrier();
+
+    for (var stride = THREADS / 2u; stride > 0u; stride /= 2u) {
+        if index < stride {
+            sums[index] += sums[index + stride];
+        }
+        workgroupBarrier();
+    }
+    if index != 0u {
+        return;
+    }
+
+    // Gray world: the average color of the scene should be neutral.
+    let average = sums[0] / f32(max(count, 1u));
+    var reference = average;
+    if params.method == 1u {
+        // White patch: the brightest parts of the scene should be neutral.
+        let skip = u32(f32(count) * WHITE_PERCENTILE);
+        for (var channel = 0u; channel < 3u; channel++) {
+            var seen = 0u;
+            var bin = BINS;
+            loop {
+                if bin == 0u {
+                    break;
+                }
+                bin -= 1u;
+                seen += atomicLoad(&histogram[channel * BINS + bin]);
+                if seen > skip {
+                    break;
+                }
+            }
+            reference[channel] = (f32(bin) + 0.5) / f32(BINS);
+        }
+    }
+
+    // Scale each channel to the reference's brightness, keeping the overall
+    // brightness the same. Near-black frames are left alone.
+    let brightness = dot(reference, LUMA);
+    var target_gains = vec3<f32>(1.0);
+    if brightness > 0.001 {
+        target_gains = clamp(vec3<f32>(brightness) / max(reference, vec3<f32>(0.0001)), vec3<f32>(0.25), vec3<f32>(4.0));
+        target_gains /= dot(target_gains * reference, LUMA) / brightness;
+    }
+    let average_brightness = dot(average, LUMA);
+    if params.auto_exposure != 0u && average_brightness > 0.001 {
+        target_gains *= clamp(MIDDLE_GRAY / average_brightness, 0.25, 4.0);
+    }
+
+    // The previous gains are only valid once w has been set.
+    let previous = gains;
+    if previous.w > 0.0 {
+        gains = vec4<f32>(mix(target_gains, previous.rgb, params.smoothing), 1.0);
+    } else {
+        gains = vec4<f32>(target_gains, 1.0);
+    }
+}
+"#;
+
+const APPLY_SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct ApplyParams {
+    exposure: f32,
+    temperature: f32,
+    tint: f32,
+    auto_balance: u32,
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read> gains: vec4<f32>;
+@group(0) @binding(2) var<uniform> params: ApplyParams;
+
+const LUMA: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
+
+fn to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3<f32>(2.4)), c / 12.92, c <= vec3<f32>(0.04045));
+}
+
+fn to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureLoad(source_texture, vec2<i32>(in.position.xy), 0);
+    var linear = to_linear(color.rgb);
+
+    if params.auto_balance != 0u && gains.w > 0.0 {
+        linear *= gains.rgb;
+    }
+
+    // Warmer moves red up and blue down, and a magenta tint moves green down.
+    // The shift is scaled so it doesn't change overall brightness.
+    var balance = vec3<f32>(
+        1.0 + 0.3 * params.temperature,
+        1.0 - 0.3 * params.tint,
+        1.0 - 0.3 * params.temperature,
+    );
+    balance /= dot(balance, LUMA);
+    linear *= balance * exp2(params.exposure);
+
+    return vec4<f32>(to_srgb(clamp(linear, vec3<f32>(0.0), vec3<f32>(1.0))), color.a);
+}
+"#;
+
+/// How [WhiteBalancer::analyze] decides what's neutral.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutoMethod {
+    /// Assume the scene averages out to gray.
+    #[default]
+    GrayWorld,
+    /// Assume the brightest parts of the scene (ignoring the top percent of
+    /// highlights) are white.
+    WhitePatch,
+}
+
+/// What [WhiteBalancer::analyze] corrects for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoSettings {
+    pub method: AutoMethod,
+    /// Whether to also brighten or darken frames to an average brightness.
+    pub exposure: bool,
+    /// How much of the previous frame's gains are kept, from `0.0` to
+    /// [MAX_SMOOTHING].
+    pub smoothing: f32,
+}
+
+/// Manual exposure and white balance settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BalanceSettings {
+    /// In stops: `1.0` doubles brightness and `-1.0` halves it.
+    pub exposure: f32,
+    /// From `-1.0` (cooler) to `1.0` (warmer).
+    pub temperature: f32,
+    /// From `-1.0` (greener) to `1.0` (more magenta).
+    pub tint: f32,
+    /// Whether to apply the gains found by [WhiteBalancer::analyze], before
+    /// the manual settings.
+    pub auto_balance: bool,
+}
+
+/// The state automatic white balance keeps for one stream of frames: its
+/// analysis texture and the smoothed gains.
+pub struct AutoBalance {
+    analysis: wgpu::Texture,
+    analysis_view: wgpu::TextureView,
+    gains_buf: wgpu::Buffer,
+    analyze_params_buf: wgpu::Buffer,
+    apply_params_buf: wgpu::Buffer,
+}
+
+impl AutoBalance {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let analysis = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("white_balance_analysis"),
+            size: wgpu::Extent3d {
+                width: ANALYSIS_SIZE,
+                height: ANALYSIS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: ANALYSIS_LEVEL + 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ANALYSIS_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let analysis_view = analysis.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let buffer = |label, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: 16,
+                usage: usage | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+
+        Self {
+            analysis,
+            analysis_view,
+            gains_buf: buffer("white_balance_gains", wgpu::BufferUsages::STORAGE),
+            analyze_params_buf: buffer("white_balance_analyze", wgpu::BufferUsages::UNIFORM),
+            apply_params_buf: buffer("white_balance_apply", wgpu::BufferUsages::UNIFORM),
+        }
+    }
+
+    /// Forget the smoothed gains, so the next analysis starts fresh instead of
+    /// easing in from old lighting.
+    pub fn reset(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.gains_buf, 0, &[0; 16]);
+    }
+}
+
+/// Draws exposure and white balance corrections into render targets of one
+/// format.
+pub struct WhiteBalancer {
+    analyze_pipeline: wgpu::ComputePipeline,
+    analyze_bgl: wgpu::BindGroupLayout,
+    apply_pipeline: wgpu::RenderPipeline,
+    apply_bgl: wgpu::BindGroupLayout,
+    blitter: TextureBlitter,
+    mipmaps: MipmapGenerator,
+}
+
+impl WhiteBalancer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let analyze_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/white_balance_analyze"),
+            entries: &[
+                texture_entry(0, wgpu::ShaderStages::COMPUTE),
+                buffer_entry(
+                    1,
+                    wgpu::ShaderStages::COMPUTE,
+                    wgpu::BufferBindingType::Storage { read_only: false },
+                ),
+                buffer_entry(
+                    2,
+                    wgpu::ShaderStages::COMPUTE,
+                    wgpu::BufferBindingType::Uniform,
+                ),
+            ],
+        });
+        let analyze_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/white_balance_analyze"),
+            bind_group_layouts: &[&analyze_bgl],
+            ..Default::default()
+        });
+        let analyze_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/white_balance_analyze"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(ANALYZE_SHADER)),
+        });
+        let analyze_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("pipeline/white_balance_analyze"),
+            layout: Some(&analyze_layout),
+            module: &analyze_shader,
+            entry_point: Some("cs_main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let apply_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bgl/white_balance_apply"),
+            entries: &[
+                texture_entry(0, wgpu::ShaderStages::FRAGMENT),
+                buffer_entry(
+                    1,
+                    wgpu::ShaderStages::FRAGMENT,
+                    wgpu::BufferBindingType::Storage { read_only: true },
+                ),
+                buffer_entry(
+                    2,
+                    wgpu::ShaderStages::FRAGMENT,
+                    wgpu::BufferBindingType::Uniform,
+                ),
+            ],
+        });
+        let apply_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("layout/white_balance_apply"),
+            bind_group_layouts: &[&apply_bgl],
+            ..Default::default()
+        });
+        let apply_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader/white_balance_apply"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(APPLY_SHADER)),
+        });
+        let apply_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("pipeline/white_balance_apply"),
+            layout: Some(&apply_layout),
+            vertex: wgpu::VertexState {
+                module: &apply_shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &apply_shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview: None,
+        });
+
+        Self {
+            analyze_pipeline,
+            analyze_bgl,
+            apply_pipeline,
+            apply_bgl,
+            blitter: TextureBlitter::new(device, ANALYSIS_FORMAT),
+            mipmaps: MipmapGenerator::new(device, ANALYSIS_FORMAT),
+        }
+    }
+
+    /// Record passes into `encoder` that measure `source` and blend the gains
+    /// that would correct it into `state`'s.
+    pub fn analyze(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        state: &AutoBalance,
+        settings: AutoSettings,
+    ) {
+        let method: u32 = match settings.method {
+            AutoMethod::GrayWorld => 0,
+            AutoMethod::WhitePatch => 1,
+        };
+        let mut params = [0u8; 16];
+        params[0..4].copy_from_slice(&method.to_le_bytes());
+        params[4..8].copy_from_slice(&settings.smoothing.clamp(0.0, MAX_SMOOTHING).to_le_bytes());
+        params[8..12].copy_from_slice(&u32::from(settings.exposure).to_le_bytes());
+        queue.write_buffer(&state.analyze_params_buf, 0, &params);
+
+        let top_level = state.analysis.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("white_balance_analysis_top"),
+            base_mip_level: 0,
+            mip_level_count: Some(1),
+            ..Default::default()
+        });
+        self.blitter.blit(device, encoder, source, &top_level);
+        self.mipmaps.generate(device, encoder, &state.analysis);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/white_balance_analyze"),
+            layout: &self.analyze_bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&state.analysis_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: state.gains_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: state.analyze_params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("white_balance_analyze"),
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&self.analyze_pipeline);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+
+    /// Record a pass into `encoder` that draws `source` with `settings` (and
+    /// `state`'s gains, if enabled) into `target`, which must be the same size
+    /// as `source`.
+    ///
+    /// The parameters are written with `queue` into `state`, so `encoder` must
+    /// be submitted before this is called again with the same state.
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        state: &AutoBalance,
+        settings: BalanceSettings,
+        target: &wgpu::TextureView,
+    ) {
+        let mut params = [0u8; 16];
+        params[0..4].copy_from_slice(&settings.exposure.to_le_bytes());
+        params[4..8].copy_from_slice(&settings.temperature.clamp(-1.0, 1.0).to_le_bytes());
+        params[8..12].copy_from_slice(&settings.tint.clamp(-1.0, 1.0).to_le_bytes());
+        params[12..16].copy_from_slice(&u32::from(settings.auto_balance).to_le_bytes());
+        queue.write_buffer(&state.apply_params_buf, 0, &params);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bg/white_balance_apply"),
+            layout: &self.apply_bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: state.gains_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: state.apply_params_buf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("white_balance_apply"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.apply_pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+fn texture_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn buffer_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    ty: wgpu::BufferBindingType,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
@@ -14,6 +14,12 @@ pub struct Args {
     #[arg(long, num_args(1..), trailing_var_arg = true, allow_hyphen_values = true)]
     pub editor_cmd: Vec<String>,
 
+    /// Open projects with the editor in safe mode (see the editor's
+    /// `--safe-mode`), for when the GPU or its driver crashes the editor. Has
+    /// no effect with `--editor-cmd`.
+    #[arg(long)]
+    pub safe_mode: bool,
+
     /// Require this instance to be a sender.
     #[arg(long, num_args(0..=1), default_missing_value = "true")]
     pub send_only: Option<ForcibleFlag>,
@@ -53,6 +59,18 @@ pub struct Args {
     /// want to print to a file.
     #[arg(long, value_name = "OUTPUT_FILE")]
     pub version: Option<Option<PathBuf>>,
+
+    /// A project archive (`.bvproj`) or project link
+    /// (`substrate://open/<PROJECT_ID>`) to open in the editor. This is how the
+    /// OS opens them once `--register-file-types` has been run. Archives are
+    /// imported first if they haven't been already.
+    #[arg(value_name = "ARCHIVE_OR_LINK")]
+    pub open: Option<String>,
+
+    /// Register the launcher with the OS as the app that opens project
+    /// archives and project links, then exit.
+    #[arg(long)]
+    pub register_file_types: bool,
 }
 
 impl Default for Args {
@@ -1,12 +1,14 @@
 //! Exports [launcher] which runs the launcher portion of the app.
 
 mod args;
+mod open_target;
 mod other_instances;
 mod receiver;
 mod sender;
 
 use std::process::ExitCode;
 
+use util::instance_lock::LAUNCHER_LOCK;
 use util::stop_signals;
 use util::version;
 
@@ -47,9 +49,22 @@ pub fn launcher() -> ExitCode {
         };
     }
 
-    match InstanceLock::<PersistedData>::from_default() {
-        Ok(instance_lock) => receiver::receiver(args, instance_lock),
-        Err(InstanceLockError::Locked) => sender::sender(args),
+    if args.register_file_types {
+        return match open_target::register() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                util::debug_log_error!("Failed to register file types: {e}");
+                eprintln!("Failed to register file types: {e}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let open_project = args.open.as_deref().map(open_target::resolve);
+
+    match InstanceLock::<PersistedData>::from_default(LAUNCHER_LOCK) {
+        Ok(instance_lock) => receiver::receiver(args, instance_lock, open_project),
+        Err(InstanceLockError::Locked) => sender::sender(args, open_project),
 
         Err(e) => {
             util::debug_log_error!("Failed to try acquiring instance lock: {e}");
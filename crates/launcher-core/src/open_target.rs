@@ -0,0 +1,209 @@
+//! Contains [resolve], which finds the project for something the OS asked the
+//! launcher to open (a project archive or a link), and [register], which sets
+//! the launcher up as what the OS asks.
+
+use std::path::Path;
+#[cfg(any(windows, target_os = "linux"))]
+use std::process::Command;
+use std::{env, io};
+
+use util::local_data::project::{self, ProjectId};
+
+/// The URL scheme of links to projects, e.g. `substrate://open/<project id>`.
+pub const URL_SCHEME: &str = "substrate";
+
+/// Find the project to open for `target`, a project archive path or a link to a
+/// project. Archives are imported first if they haven't been already.
+///
+/// The error is a message for the user.
+pub fn resolve(target: &str) -> Result<ProjectId, String> {
+    if let Some(link) = target
+        .strip_prefix(URL_SCHEME)
+        .and_then(|t| t.strip_prefix("://"))
+    {
+        let project_id = link
+            .strip_prefix("open/")
+            .map(|id| id.trim_end_matches('/'))
+            .ok_or_else(|| format!("Unsupported link `{target}`."))?;
+        return ProjectId::try_from(project_id.to_string()).map_err(|e| {
+            util::debug_log_warning!("Invalid project ID in link: {e}");
+            format!("The link `{target}` doesn't point to a project.")
+        });
+    }
+
+    let path = Path::new(target);
+    if path
+        .extension()
+        .is_none_or(|extension| extension != project::ARCHIVE_EXTENSION)
+    {
+        return Err(format!(
+            "`{target}` isn't a project archive (`.{}`).",
+            project::ARCHIVE_EXTENSION
+        ));
+    }
+    project::import_archive(path).map_err(|e| {
+        util::debug_log_error!("Failed to import project archive: {e}");
+        format!("Failed to import the project archive `{target}`.")
+    })
+}
+
+/// Register this launcher with the OS as the app that opens project archives
+/// and project links.
+pub fn register() -> Result<(), io::Error> {
+    let exe = env::current_exe()?.canonicalize()?;
+    register_impl(&exe)
+}
+
+#[cfg(windows)]
+fn register_impl(exe: &Path) -> Result<(), io::Error> {
+    use util::version;
+
+    const CLASSES: &str = r"HKCU\Software\Classes";
+    let prog_id = format!("{}.Project", version::APP_NAME);
+    let open_command = format!("\"{}\" \"%1\"", exe.display());
+
+    let reg_add = |key: String, value: Option<&str>, data: &str| -> Result<(), io::Error> {
+        let mut command = Command::new("reg");
+        command.args(["add", &key, "/f", "/d", data]);
+        match value {
+            Some(value) => command.args(["/v", value]),
+            None => command.arg("/ve"),
+        };
+        run(&mut command)
+    };
+
+    reg_add(
+        format!(r"{CLASSES}\.{}", project::ARCHIVE_EXTENSION),
+        None,
+        &prog_id,
+    )?;
+    reg_add(
+        format!(r"{CLASSES}\{prog_id}"),
+        None,
+        &format!("{} Project", version::APP_NAME),
+    )?;
+    reg_add(
+        format!(r"{CLASSES}\{prog_id}\shell\open\command"),
+        None,
+        &open_command,
+    )?;
+
+    reg_add(
+        format!(r"{CLASSES}\{URL_SCHEME}"),
+        None,
+        &format!("URL:{} Project Link", version::APP_NAME),
+    )?;
+    reg_add(format!(r"{CLASSES}\{URL_SCHEME}"), Some("URL Protocol"), "")?;
+    reg_add(
+        format!(r"{CLASSES}\{URL_SCHEME}\shell\open\command"),
+        None,
+        &open_command,
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn register_impl(exe: &Path) -> Result<(), io::Error> {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use util::version;
+
+    const MIME_TYPE: &str = "application/x-substrate-project";
+    const DESKTOP_FILE_NAME: &str = "substrate-launcher.desktop";
+
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No home directory"))?;
+
+    let applications_dir = data_home.join("applications");
+    fs::create_dir_all(&applications_dir)?;
+    fs::write(
+        applications_dir.join(DESKTOP_FILE_NAME),
+        format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name={}\n\
+             Exec=\"{}\" %u\n\
+             MimeType={MIME_TYPE};x-scheme-handler/{URL_SCHEME};\n\
+             NoDisplay=true\n",
+            version::APP_NAME,
+            exe.display(),
+        ),
+    )?;
+
+    let mime_dir = data_home.join("mime");
+    let mime_packages_dir = mime_dir.join("packages");
+    fs::create_dir_all(&mime_packages_dir)?;
+    fs::write(
+        mime_packages_dir.join("substrate-project.xml"),
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n\
+             \x20 <mime-type type=\"{MIME_TYPE}\">\n\
+             \x20   <comment>{} Project</comment>\n\
+             \x20   <glob pattern=\"*.{}\"/>\n\
+             \x20 </mime-type>\n\
+             </mime-info>\n",
+            version::APP_NAME,
+            project::ARCHIVE_EXTENSION,
+        ),
+    )?;
+
+    run(Command::new("update-mime-database").arg(&mime_dir))?;
+    run(Command::new("xdg-mime").args([
+        "default",
+        DESKTOP_FILE_NAME,
+        MIME_TYPE,
+        &format!("x-scheme-handler/{URL_SCHEME}"),
+    ]))
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn register_impl(_exe: &Path) -> Result<(), io::Error> {
+    // macOS hands files and links to apps with Apple events instead of
+    // command-line arguments, which the launcher doesn't handle.
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Run `command`, failing if it doesn't exit successfully.
+#[cfg(any(windows, target_os = "linux"))]
+fn run(command: &mut Command) -> Result<(), io::Error> {
+    let status = command.status().inspect_err(|e| {
+        util::debug_log_error!("Failed to run `{command:?}`: {e}");
+    })?;
+    if !status.success() {
+        util::debug_log_error!("`{command:?}` failed ({status}).");
+        return Err(io::Error::other(format!("`{command:?}` failed ({status})")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- resolve() ---
+
+    #[test]
+    fn links_resolve_to_their_project() {
+        let project_id = ProjectId::default();
+        let id_str = String::from(project_id.clone());
+
+        assert_eq!(
+            resolve(&format!("{URL_SCHEME}://open/{id_str}")),
+            Ok(project_id.clone())
+        );
+        assert_eq!(
+            resolve(&format!("{URL_SCHEME}://open/{id_str}/")),
+            Ok(project_id)
+        );
+    }
+
+    #[test]
+    fn bad_targets_are_rejected() {
+        assert!(resolve(&format!("{URL_SCHEME}://delete/abc")).is_err());
+        assert!(resolve(&format!("{URL_SCHEME}://open/not a project")).is_err());
+        assert!(resolve("project.txt").is_err());
+    }
+}
@@ -3,8 +3,7 @@
 //!
 //! "OI" is short for "Other Instance".
 
-mod instance_lock;
 mod oi_messager;
 
-pub use instance_lock::*;
 pub use oi_messager::*;
+pub use util::instance_lock::{InstanceLock, InstanceLockError};
@@ -12,62 +12,89 @@ use std::path::PathBuf;
 use thiserror::Error;
 
 use util::local_data;
+use util::local_data::project::ProjectId;
 
 /// A message from one instance to another.
 ///
+/// Messages are written as a single byte, followed by a newline-terminated
+/// argument for the messages that have one.
+///
 /// "OI" is short for "Other Instance".
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[repr(u8)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum OIMsg {
     /// Another instance was launched and is now exiting. Focus the current
     /// instance to indicate this.
-    Focus = b'F',
+    Focus,
 
     /// Another instance was launched by the editor to tell the current instance
     /// that a project was saved.
-    ProjectUpdated = b'U',
+    ProjectUpdated,
 
     /// Another instance was launched by the editor to tell the current instance
     /// that a project couldn't be opened.
-    ProjectOpenFailed = b'O',
+    ProjectOpenFailed,
 
     /// Another instance was launched by the editor to tell the current instance
     /// to close.
-    Close = b'C',
+    Close,
+
+    /// Another instance was launched by the OS (for a project archive or link)
+    /// to tell the current instance to open a project.
+    OpenProject(ProjectId),
 }
-// IMPORTANT: When adding/changing these variants, make sure to update the
-// `TryFrom<u8>` implementation (you won't automatically get an error telling
-// you to fix it).
+// IMPORTANT: When adding/changing these variants, make sure to update
+// `OIMsg::decode` (you won't automatically get an error telling you to fix it).
+
+impl OIMsg {
+    /// The byte that starts the message.
+    fn tag(&self) -> u8 {
+        match self {
+            Self::Focus => b'F',
+            Self::ProjectUpdated => b'U',
+            Self::ProjectOpenFailed => b'O',
+            Self::Close => b'C',
+            Self::OpenProject(_) => b'P',
+        }
+    }
 
-impl TryFrom<u8> for OIMsg {
-    type Error = InvalidOIMsgByte;
+    /// The message as it's written to the IPC file.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![self.tag()];
+        if let Self::OpenProject(project_id) = self {
+            bytes.extend_from_slice(project_id.as_ref().as_encoded_bytes());
+            bytes.push(b'\n');
+        }
+        bytes
+    }
 
-    fn try_from(char: u8) -> Result<Self, Self::Error> {
-        match char {
+    /// Decode the message starting with `tag`, taking its argument (if it has
+    /// one) from `rest`.
+    fn decode(tag: u8, rest: &mut impl Iterator<Item = u8>) -> Result<Self, InvalidOIMsgByte> {
+        match tag {
             b'F' => Ok(Self::Focus),
             b'U' => Ok(Self::ProjectUpdated),
             b'O' => Ok(Self::ProjectOpenFailed),
             b'C' => Ok(Self::Close),
+            b'P' => {
+                let argument = rest.take_while(|&byte| byte != b'\n').collect();
+                String::from_utf8(argument)
+                    .ok()
+                    .and_then(|argument| ProjectId::try_from(argument).ok())
+                    .map(Self::OpenProject)
+                    .ok_or(InvalidOIMsgByte(tag))
+            }
             byte => Err(InvalidOIMsgByte(byte)),
         }
     }
 }
 
-impl From<OIMsg> for u8 {
-    fn from(msg: OIMsg) -> Self {
-        msg as u8
-    }
-}
-
-impl From<OIMsg> for char {
-    fn from(msg: OIMsg) -> Self {
-        msg as u8 as char
-    }
-}
-
 impl Display for OIMsg {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.write_char((*self).into())
+        f.write_char(self.tag().into())?;
+        if let Self::OpenProject(project_id) = self {
+            write!(f, "{}", project_id.as_ref().display())?;
+        }
+        Ok(())
     }
 }
 
@@ -127,9 +154,9 @@ impl OIMsgReceiver {
             util::debug_log_error!("Failed to read from IPC file: {e}");
         })?;
 
-        self.msg_queue.reserve(buf.len());
-        for byte in buf.drain(..) {
-            let msg = match OIMsg::try_from(byte) {
+        let mut bytes = buf.drain(..);
+        while let Some(tag) = bytes.next() {
+            let msg = match OIMsg::decode(tag, &mut bytes) {
                 Ok(msg) => msg,
                 Err(e) => {
                     util::debug_log_warning!("{e}");
@@ -177,8 +204,8 @@ impl OIMsgSender {
     }
 
     /// Send a message.
-    pub fn send(&mut self, msg: OIMsg) -> Result<(), io::Error> {
-        self.file.write_all(&[msg.into()]).inspect_err(|e| {
+    pub fn send(&mut self, msg: &OIMsg) -> Result<(), io::Error> {
+        self.file.write_all(&msg.encode()).inspect_err(|e| {
             util::debug_log_error!("Failed to append to IPC file: {e}");
         })
     }
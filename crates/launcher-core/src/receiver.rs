@@ -12,6 +12,7 @@ use std::{env, io};
 
 use serde::{Deserialize, Serialize};
 
+use util::local_data::project::ProjectId;
 use util::stop_signals;
 
 use crate::args::{Args, ForcibleFlag};
@@ -37,8 +38,13 @@ impl PersistedData {
     }
 }
 
-/// The main instance code path.
-pub fn receiver(args: Args, mut instance_lock: InstanceLock<PersistedData>) -> ExitCode {
+/// The main instance code path. `open_project` is opened in the editor once the
+/// UI is up (see [Args::open]).
+pub fn receiver(
+    args: Args,
+    mut instance_lock: InstanceLock<PersistedData>,
+    open_project: Option<Result<ProjectId, String>>,
+) -> ExitCode {
     if let Some(required) = args.send_only {
         return match required {
             ForcibleFlag::Force => {
@@ -49,6 +55,10 @@ pub fn receiver(args: Args, mut instance_lock: InstanceLock<PersistedData>) -> E
         };
     }
 
+    // Projects can only be handed to an editor that's already open if it's the
+    // same editor we'd run (see `WorkerData::route_to_open_editor`).
+    let route_to_open_editor = args.editor_cmd.is_empty() && !args.safe_mode;
+
     let editor_cmd = if !args.editor_cmd.is_empty() {
         args.editor_cmd
     } else {
@@ -60,7 +70,12 @@ pub fn receiver(args: Args, mut instance_lock: InstanceLock<PersistedData>) -> E
             Ok(editor_path) => {
                 let path_str = editor_path.to_string_lossy().to_string();
                 util::debug_log_info!("Using editor executable at: {}", path_str);
-                vec![path_str, "--open-project".into()]
+                let mut editor_cmd = vec![path_str];
+                if args.safe_mode {
+                    editor_cmd.push("--safe-mode".into());
+                }
+                editor_cmd.push("--open-project".into());
+                editor_cmd
             }
             Err(e) => {
                 util::debug_log_error!("Couldn't find editor executable: {e}");
@@ -70,8 +85,8 @@ pub fn receiver(args: Args, mut instance_lock: InstanceLock<PersistedData>) -> E
         }
     };
 
-    let worker = Worker::new(editor_cmd);
-    let exit_plan = ui::run_ui(&mut instance_lock, &worker);
+    let worker = Worker::new(editor_cmd, route_to_open_editor);
+    let exit_plan = ui::run_ui(&mut instance_lock, &worker, open_project);
 
     // Another instance shouldn't be blocked while we're shutting down (waiting
     // for an editor to close may take a while).
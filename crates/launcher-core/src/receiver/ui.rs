@@ -12,6 +12,7 @@ use serde::{Deserialize, Serialize};
 use eframe::NativeOptions;
 use egui::{Vec2, ViewportBuilder};
 
+use util::local_data::project::ProjectId;
 use util::version;
 
 use super::PersistedData;
@@ -62,11 +63,22 @@ pub struct ExitPlan {
 }
 
 /// Starts up the launcher UI, normally exiting when the UI is closed.
+/// `open_project` is opened in the editor (or why it can't be is shown) once
+/// the UI is up.
 ///
 /// This function can only be run from the main thread.
-pub fn run_ui(instance_lock: &mut InstanceLock<PersistedData>, worker: &Worker) -> ExitPlan {
+pub fn run_ui(
+    instance_lock: &mut InstanceLock<PersistedData>,
+    worker: &Worker,
+    open_project: Option<Result<ProjectId, String>>,
+) -> ExitPlan {
     let mut close_editors_on_exit = false;
-    let ui_manager = UiManager::new(worker, instance_lock, &mut close_editors_on_exit);
+    let ui_manager = UiManager::new(
+        worker,
+        instance_lock,
+        &mut close_editors_on_exit,
+        open_project,
+    );
 
     let window_title = String::from(version::APP_NAME) + " - Launcher";
 
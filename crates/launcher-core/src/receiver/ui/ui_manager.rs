@@ -45,6 +45,8 @@ pub struct UiManager<'a> {
     is_1st_update: bool,
     new_project_name_buffer: Option<String>,
     close_editors_on_exit: &'a mut bool,
+    /// The project the launcher was started to open, or why it couldn't be.
+    open_on_startup: Option<Result<ProjectId, String>>,
 }
 
 impl<'a> UiManager<'a> {
@@ -55,6 +57,7 @@ impl<'a> UiManager<'a> {
         worker: &'a Worker,
         instance_lock: &'a mut InstanceLock<PersistedData>,
         close_editors_on_exit: &'a mut bool,
+        open_on_startup: Option<Result<ProjectId, String>>,
     ) -> Self {
         let unsaved_ui_data = instance_lock.data().ui_data().clone();
 
@@ -75,6 +78,7 @@ impl<'a> UiManager<'a> {
             is_1st_update: true,
             new_project_name_buffer: None,
             close_editors_on_exit,
+            open_on_startup,
         }
     }
 
@@ -199,6 +203,10 @@ impl<'a> UiManager<'a> {
                     self.projects.remove_project(&project_id)
                 }
 
+                WorkerMsg::OpenProject(project_id) => {
+                    ui_action_queue.push_back(UiAction::OpenProjectEditor(project_id));
+                }
+
                 WorkerMsg::ProjectOpenFailed => {
                     util::debug_log_error!("Failed to open project.");
                     ui_action_queue
@@ -345,6 +353,18 @@ impl<'a> App for UiManager<'a> {
 
         let mut ui_action_queue = VecDeque::default();
 
+        // This is the same as double-clicking the project.
+        match self.open_on_startup.take() {
+            Some(Ok(project_id)) => {
+                ui_action_queue.push_back(UiAction::OpenProjectEditor(project_id));
+                if !self.unsaved_ui_data.stay_open {
+                    ui_action_queue.push_back(UiAction::Close);
+                }
+            }
+            Some(Err(e)) => ui_action_queue.push_back(UiAction::ShowError(e)),
+            None => {}
+        }
+
         self.handle_worker_msgs(&mut ui_action_queue);
         self.handle_worker_task_responses(&mut ui_action_queue);
 
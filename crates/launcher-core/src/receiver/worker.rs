@@ -38,15 +38,22 @@ pub struct Worker {
 }
 
 impl Worker {
-    /// Create a new worker.
-    pub fn new(editor_cmd: Vec<String>) -> Self {
+    /// Create a new worker. If `route_to_open_editor` is set, projects are
+    /// opened in a new window of an editor that's already running when there
+    /// is one.
+    pub fn new(editor_cmd: Vec<String>, route_to_open_editor: bool) -> Self {
         let (frontend_inbox, worker_outbox) = message_channel::new::<WorkerMsg>();
 
         let (worker_server, frontend_client) =
             request_channel::new::<WorkerTask, WorkerTaskResult>();
 
-        let thread = drop_join_thread::spawn(|| {
-            worker(editor_cmd, worker_outbox, worker_server);
+        let thread = drop_join_thread::spawn(move || {
+            worker(
+                editor_cmd,
+                route_to_open_editor,
+                worker_outbox,
+                worker_server,
+            );
         });
 
         Self {
@@ -99,6 +106,9 @@ pub enum WorkerMsg {
 
     /// An editor treied to open a project but failed.
     ProjectOpenFailed,
+
+    /// Another instance was asked to open a project, open it in the editor.
+    OpenProject(ProjectId),
 }
 
 impl TryFrom<OIMsg> for WorkerMsg {
@@ -110,6 +120,7 @@ impl TryFrom<OIMsg> for WorkerMsg {
             OIMsg::Focus => Ok(Self::Focus),
             OIMsg::ProjectOpenFailed => Ok(Self::ProjectOpenFailed),
             OIMsg::Close => Ok(Self::Close),
+            OIMsg::OpenProject(project_id) => Ok(Self::OpenProject(project_id)),
 
             // Don't relay these messages:
             OIMsg::ProjectUpdated => Err(()),
@@ -161,6 +172,9 @@ struct WorkerData<'a> {
     oi_msg_receiver: OIMsgReceiver,
     known_projects: HashMap<ProjectHashedById, ProjectKnownState>,
     editor_cmd: Vec<String>,
+    /// Whether projects should be sent to an editor that's already running
+    /// (which opens them in a new window) instead of starting a new editor.
+    route_to_open_editor: bool,
     ui_context: Option<Context>,
 }
 
@@ -187,10 +201,16 @@ type Impossible = Infallible;
 
 fn worker(
     editor_cmd: Vec<String>,
+    route_to_open_editor: bool,
     worker_outbox: Outbox<WorkerMsg>,
     worker_server: Server<WorkerTask, WorkerTaskResult>,
 ) {
-    let stop_work_reason = worker_inner(editor_cmd, &worker_outbox, &worker_server);
+    let stop_work_reason = worker_inner(
+        editor_cmd,
+        route_to_open_editor,
+        &worker_outbox,
+        &worker_server,
+    );
 
     match stop_work_reason.expect_err("Ok return value is impossible here.") {
         StopWorkReason::ConnectionDropped => {}
@@ -202,6 +222,7 @@ fn worker(
 
 fn worker_inner(
     editor_cmd: Vec<String>,
+    route_to_open_editor: bool,
     worker_outbox: &Outbox<WorkerMsg>,
     worker_server: &Server<WorkerTask, WorkerTaskResult>,
 ) -> Result<Impossible, StopWorkReason> {
@@ -221,6 +242,7 @@ fn worker_inner(
         oi_msg_receiver,
         known_projects: HashMap::default(),
         editor_cmd,
+        route_to_open_editor,
         ui_context: None,
     };
 
@@ -15,6 +15,7 @@ use thiserror::Error;
 
 use util::channels::ChannelError;
 use util::channels::request_channel::ReqRes;
+use util::instance_lock::{self, EDITOR_LOCK};
 use util::local_data::project::{self, Project, ProjectHeader, ProjectId, ProjectInfo};
 
 use super::{WorkerData, WorkerMsg, WorkerTask, WorkerTaskDone, WorkerTaskResult};
@@ -102,7 +103,7 @@ pub fn handle_oi_msgs(worker_data: &mut WorkerData) -> Result<bool, StopWorkReas
 
         // If it can be converted to a message for the frontend, we should send
         // it to the frontend.
-        if let Ok(msg) = msg.try_into() {
+        if let Ok(msg) = msg.clone().try_into() {
             worker_data
                 .send_outbox_msg(msg)
                 .map_err(|_| StopWorkReason::ConnectionDropped)?;
@@ -112,6 +113,7 @@ pub fn handle_oi_msgs(worker_data: &mut WorkerData) -> Result<bool, StopWorkReas
             && match msg {
                 OIMsg::Focus => false,
                 OIMsg::Close => false,
+                OIMsg::OpenProject(_) => false,
 
                 OIMsg::ProjectUpdated => true,
                 OIMsg::ProjectOpenFailed => true,
@@ -194,6 +196,20 @@ fn respond_to_worker_server_requests(
 }
 
 fn open_project_editor(worker_data: &WorkerData, project_id: ProjectId) -> WorkerTaskResult {
+    if worker_data.route_to_open_editor && instance_lock::holder_is_responsive(EDITOR_LOCK) {
+        let request = project_id.as_ref().to_string_lossy();
+        match instance_lock::send_request(EDITOR_LOCK, &request) {
+            Ok(()) => {
+                util::debug_log_info!("Sent project `{request}` to the open editor.");
+                return Ok(WorkerTaskDone::NoInfo);
+            }
+            Err(e) => {
+                util::debug_log_error!("Failed to send project to the open editor: {e}");
+                util::debug_log_warning!("Starting a new editor instead.");
+            }
+        }
+    }
+
     if util::debug_log::enabled() {
         let mut cmd_str = worker_data.editor_cmd.join(" ");
         cmd_str.push(' ');
@@ -3,11 +3,14 @@
 
 use std::process::ExitCode;
 
+use util::local_data::project::ProjectId;
+
 use crate::args::{Args, ForcibleFlag};
 use crate::other_instances::{OIMsg, OIMsgSender};
 
-/// The code path for when this isn't the main instance.
-pub fn sender(args: Args) -> ExitCode {
+/// The code path for when this isn't the main instance. `open_project` is sent
+/// to the main instance to be opened (see [Args::open]).
+pub fn sender(args: Args, open_project: Option<Result<ProjectId, String>>) -> ExitCode {
     if let Some(required) = args.receive_only {
         return match required {
             ForcibleFlag::Force => {
@@ -35,7 +38,7 @@ pub fn sender(args: Args) -> ExitCode {
 
     let mut exit_code = ExitCode::SUCCESS;
 
-    let mut send = |msg| match msg_sender.send(msg) {
+    let mut send = |msg| match msg_sender.send(&msg) {
         Ok(_) => {
             util::debug_log_info!("Other instance message sent: `{msg}`.");
         }
@@ -49,14 +52,21 @@ pub fn sender(args: Args) -> ExitCode {
         }
     };
 
-    if !args.no_focus {
+    // The editor window that opens is what gets focused instead.
+    if !args.no_focus && !matches!(open_project, Some(Ok(_))) {
         send(OIMsg::Focus);
     }
     if args.rescan_projects {
         send(OIMsg::ProjectUpdated);
     }
-    if args.project_open_failed {
-        send(OIMsg::ProjectOpenFailed);
+    match open_project {
+        Some(Ok(project_id)) => send(OIMsg::OpenProject(project_id)),
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            send(OIMsg::ProjectOpenFailed);
+        }
+        None if args.project_open_failed => send(OIMsg::ProjectOpenFailed),
+        None => {}
     }
 
     exit_code
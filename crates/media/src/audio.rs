@@ -0,0 +1,72 @@
+//! This module exports everything that has to do with audio: reading it from
+//! media files and measuring its [levels](level_meter) and
+//! [loudness](loudness_meter).
+
+pub mod level_meter;
+pub mod loudness_meter;
+
+pub use level_meter::{Ballistics, ChannelLevel, LevelMeter};
+pub use loudness_meter::LoudnessMeter;
+
+use std::path::Path;
+use std::time::Duration;
+
+use ffmpeg_next as ffmpeg;
+
+use crate::ffmpeg_tools::ffmpeg_audio::FFmpegAudio;
+
+/// Reads the audio of a media file (an audio file or the sound of a video) in
+/// order, as blocks of planar `f32` samples.
+pub struct AudioFileReader {
+    audio: FFmpegAudio,
+}
+
+impl AudioFileReader {
+    /// Open the best audio stream in the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AudioError> {
+        Ok(Self {
+            audio: FFmpegAudio::new(path.as_ref())?,
+        })
+    }
+
+    pub fn channels(&self) -> usize {
+        self.audio.channels()
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.audio.sample_rate()
+    }
+
+    /// The time of the next sample that will be read.
+    pub fn position(&self) -> Duration {
+        self.audio.position()
+    }
+
+    /// Jump to `time`. Reading may resume a little before `time`.
+    pub fn seek(&mut self, time: Duration) -> Result<(), AudioError> {
+        Ok(self.audio.seek(time)?)
+    }
+
+    /// Read blocks of samples until [Self::position] reaches `until`, passing
+    /// each one to `f` as one slice per channel.
+    ///
+    /// Returns `false` if the audio ended before reaching `until`.
+    pub fn read_until<F>(&mut self, until: Duration, mut f: F) -> Result<bool, AudioError>
+    where
+        F: FnMut(&[&[f32]]),
+    {
+        while self.audio.position() < until {
+            match self.audio.next_block(&mut f) {
+                Ok(()) => {}
+                Err(ffmpeg::Error::Eof) => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Indicates something went wrong reading audio with [AudioFileReader].
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("Audio Error: {0}")]
+pub struct AudioError(#[from] ffmpeg::Error);
@@ -0,0 +1,215 @@
+//! Exports [LevelMeter].
+
+use std::time::Duration;
+
+/// The level that counts as clipping (full scale).
+pub const CLIP_LEVEL: f32 = 1.0;
+
+/// How fast a [LevelMeter] reacts to changes in level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ballistics {
+    /// How long the RMS level takes to rise most of the way (~63%) to a louder
+    /// level.
+    pub attack: Duration,
+    /// How long the RMS and peak levels take to fall most of the way (~63%) to
+    /// a quieter level.
+    pub release: Duration,
+    /// How long the peak level is held before it starts falling.
+    pub peak_hold: Duration,
+}
+
+impl Default for Ballistics {
+    /// Roughly VU-like RMS with a PPM-like peak hold.
+    fn default() -> Self {
+        Self {
+            attack: Duration::from_millis(10),
+            release: Duration::from_millis(300),
+            peak_hold: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// The levels of a single channel. Levels are linear (`1.0` is full scale).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChannelLevel {
+    pub rms: f32,
+    pub peak: f32,
+    /// Whether any sample reached [CLIP_LEVEL] since the peak was last reset
+    /// (i.e. within the peak hold time).
+    pub clipped: bool,
+}
+
+impl ChannelLevel {
+    /// Convert a linear level to decibels relative to full scale. Silence is
+    /// negative infinity.
+    pub fn to_dbfs(level: f32) -> f32 {
+        20.0 * level.log10()
+    }
+}
+
+/// Measures the RMS and peak levels of each channel of an audio signal.
+#[derive(Debug, Clone)]
+pub struct LevelMeter {
+    sample_rate: u32,
+    ballistics: Ballistics,
+    levels: Vec<ChannelLevel>,
+    /// The smoothed mean square of each channel.
+    mean_squares: Vec<f32>,
+    /// Samples left before each channel's peak starts falling.
+    hold_remaining: Vec<u64>,
+}
+
+impl LevelMeter {
+    /// Create a meter for `channels` channels of audio at `sample_rate` Hz.
+    pub fn new(channels: usize, sample_rate: u32, ballistics: Ballistics) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            ballistics,
+            levels: vec![ChannelLevel::default(); channels],
+            mean_squares: vec![0.0; channels],
+            hold_remaining: vec![0; channels],
+        }
+    }
+
+    pub fn ballistics(&self) -> Ballistics {
+        self.ballistics
+    }
+
+    /// Change the ballistics without resetting the levels.
+    pub fn set_ballistics(&mut self, ballistics: Ballistics) {
+        self.ballistics = ballistics;
+    }
+
+    /// The current level of each channel.
+    pub fn levels(&self) -> &[ChannelLevel] {
+        &self.levels
+    }
+
+    /// Drop every level back to silence.
+    pub fn reset(&mut self) {
+        self.levels.fill(ChannelLevel::default());
+        self.mean_squares.fill(0.0);
+        self.hold_remaining.fill(0);
+    }
+
+    /// Measure a block of planar samples (one slice per channel). Extra
+    /// channels are ignored.
+    pub fn process(&mut self, planes: &[&[f32]]) {
+        let attack = self.smoothing_coefficient(self.ballistics.attack);
+        let release = self.smoothing_coefficient(self.ballistics.release);
+        let hold_samples =
+            (self.ballistics.peak_hold.as_secs_f64() * self.sample_rate as f64) as u64;
+
+        for (channel, samples) in planes.iter().enumerate().take(self.levels.len()) {
+            let level = &mut self.levels[channel];
+            let mean_square = &mut self.mean_squares[channel];
+            let hold_remaining = &mut self.hold_remaining[channel];
+
+            for &sample in *samples {
+                let magnitude = sample.abs();
+                let square = sample * sample;
+
+                let coefficient = if square > *mean_square {
+                    attack
+                } else {
+                    release
+                };
+                *mean_square = square + (*mean_square - square) * coefficient;
+
+                if magnitude >= level.peak {
+                    level.peak = magnitude;
+                    *hold_remaining = hold_samples;
+                    if magnitude >= CLIP_LEVEL {
+                        level.clipped = true;
+                    }
+                } else if *hold_remaining > 0 {
+                    *hold_remaining -= 1;
+                } else {
+                    level.peak = magnitude + (level.peak - magnitude) * release;
+                    level.clipped = false;
+                }
+            }
+
+            level.rms = mean_square.sqrt();
+        }
+    }
+
+    /// The per-sample coefficient for a one-pole filter with time constant
+    /// `time`. Zero means the filter follows its input instantly.
+    fn smoothing_coefficient(&self, time: Duration) -> f32 {
+        let samples = time.as_secs_f64() * self.sample_rate as f64;
+        if samples <= 0.0 {
+            0.0
+        } else {
+            (-1.0 / samples).exp() as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    fn instant_ballistics() -> Ballistics {
+        Ballistics {
+            attack: Duration::ZERO,
+            release: Duration::ZERO,
+            peak_hold: Duration::ZERO,
+        }
+    }
+
+    // --- process() ---
+
+    #[test]
+    fn test_square_wave_levels() {
+        let mut meter = LevelMeter::new(2, SAMPLE_RATE, Ballistics::default());
+        let left: Vec<f32> = (0..SAMPLE_RATE)
+            .map(|i| if i % 2 == 0 { 0.5 } else { -0.5 })
+            .collect();
+        let right = vec![0.0; SAMPLE_RATE as usize];
+        meter.process(&[&left, &right]);
+
+        let levels = meter.levels();
+        assert!((levels[0].rms - 0.5).abs() < 1e-3);
+        assert_eq!(levels[0].peak, 0.5);
+        assert!(!levels[0].clipped);
+        assert_eq!(levels[1], ChannelLevel::default());
+    }
+
+    #[test]
+    fn test_clipping_is_held_with_the_peak() {
+        let mut meter = LevelMeter::new(1, SAMPLE_RATE, instant_ballistics());
+        meter.process(&[&[0.2, 1.0]]);
+        assert!(meter.levels()[0].clipped);
+
+        meter.process(&[&[0.1]]);
+        assert!(!meter.levels()[0].clipped);
+        assert_eq!(meter.levels()[0].peak, 0.1);
+    }
+
+    #[test]
+    fn test_peak_hold() {
+        let ballistics = Ballistics {
+            peak_hold: Duration::from_secs(1),
+            ..instant_ballistics()
+        };
+        let mut meter = LevelMeter::new(1, 10, ballistics);
+        meter.process(&[&[0.8]]);
+        meter.process(&[&[0.0; 10]]);
+        assert_eq!(meter.levels()[0].peak, 0.8);
+
+        meter.process(&[&[0.0]]);
+        assert_eq!(meter.levels()[0].peak, 0.0);
+    }
+
+    // --- to_dbfs() ---
+
+    #[test]
+    fn test_to_dbfs() {
+        assert_eq!(ChannelLevel::to_dbfs(1.0), 0.0);
+        assert!((ChannelLevel::to_dbfs(0.5) + 6.0206).abs() < 1e-3);
+        assert_eq!(ChannelLevel::to_dbfs(0.0), f32::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,232 @@
+//! Exports [LoudnessMeter].
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+/// Blocks quieter than this (in LUFS) are left out of the integrated loudness
+/// entirely.
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Blocks this much quieter (in LU) than the loudness of the blocks past the
+/// [ABSOLUTE_GATE] are left out too.
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Gating blocks are 400 ms long and start every 100 ms, so each is made of
+/// this many 100 ms sub-blocks.
+const SUB_BLOCKS_PER_BLOCK: usize = 4;
+const SUB_BLOCKS_PER_SEC: u32 = 10;
+
+/// Measures the integrated loudness of an audio signal, in LUFS, as described
+/// by ITU-R BS.1770 and EBU R128: samples are K-weighted, and the mean square
+/// of overlapping 400 ms blocks is averaged, leaving out silence and quiet
+/// passages. Every channel is weighted the same, as left and right are.
+///
+/// Also keeps the highest sample peak, since turning the audio up to a
+/// loudness may make it clip.
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    /// Each channel's K-weighting filter: a high shelf, then a high pass.
+    filters: Vec<[Biquad; 2]>,
+    sub_block_len: usize,
+    /// How many samples of the current sub-block have been measured.
+    sub_block_pos: usize,
+    /// The sum of the current sub-block's squared, K-weighted samples, over
+    /// every channel.
+    sub_block_sum: f64,
+    /// The mean squares of the last few sub-blocks.
+    sub_blocks: VecDeque<f64>,
+    /// The mean square of every gating block so far.
+    blocks: Vec<f64>,
+    peak: f32,
+}
+
+impl LoudnessMeter {
+    /// Create a meter for `channels` channels of audio at `sample_rate` Hz.
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(SUB_BLOCKS_PER_SEC);
+        Self {
+            filters: vec![
+                [
+                    Biquad::high_shelf(sample_rate),
+                    Biquad::high_pass(sample_rate)
+                ];
+                channels
+            ],
+            sub_block_len: (sample_rate / SUB_BLOCKS_PER_SEC) as usize,
+            sub_block_pos: 0,
+            sub_block_sum: 0.0,
+            sub_blocks: VecDeque::with_capacity(SUB_BLOCKS_PER_BLOCK),
+            blocks: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// Measure a block of planar samples (one slice per channel). Extra
+    /// channels are ignored.
+    pub fn process(&mut self, planes: &[&[f32]]) {
+        let planes = &planes[..planes.len().min(self.filters.len())];
+        let len = planes.iter().map(|plane| plane.len()).min().unwrap_or(0);
+
+        for i in 0..len {
+            for (plane, filters) in planes.iter().zip(&mut self.filters) {
+                let sample = plane[i];
+                self.peak = self.peak.max(sample.abs());
+                let weighted = filters
+                    .iter_mut()
+                    .fold(sample as f64, |sample, filter| filter.process(sample));
+                self.sub_block_sum += weighted * weighted;
+            }
+
+            self.sub_block_pos += 1;
+            if self.sub_block_pos == self.sub_block_len {
+                self.finish_sub_block();
+            }
+        }
+    }
+
+    /// The integrated loudness of everything measured so far, in LUFS, or
+    /// [None] if it was all too quiet to count (or shorter than a block).
+    pub fn integrated(&self) -> Option<f64> {
+        let above = |gate: f64| {
+            let (sum, count) = self
+                .blocks
+                .iter()
+                .filter(|&&block| loudness(block) > gate)
+                .fold((0.0, 0), |(sum, count), block| (sum + block, count + 1));
+            (count > 0).then(|| sum / count as f64)
+        };
+        let relative_gate = loudness(above(ABSOLUTE_GATE)?) + RELATIVE_GATE;
+        above(relative_gate.max(ABSOLUTE_GATE)).map(loudness)
+    }
+
+    /// The highest absolute sample value measured so far (`1.0` is full
+    /// scale).
+    pub fn sample_peak(&self) -> f32 {
+        self.peak
+    }
+
+    fn finish_sub_block(&mut self) {
+        if self.sub_blocks.len() == SUB_BLOCKS_PER_BLOCK {
+            self.sub_blocks.pop_front();
+        }
+        self.sub_blocks
+            .push_back(self.sub_block_sum / self.sub_block_len as f64);
+        if self.sub_blocks.len() == SUB_BLOCKS_PER_BLOCK {
+            self.blocks
+                .push(self.sub_blocks.iter().sum::<f64>() / SUB_BLOCKS_PER_BLOCK as f64);
+        }
+        self.sub_block_pos = 0;
+        self.sub_block_sum = 0.0;
+    }
+}
+
+/// The loudness of a block with a (channel summed) mean square of `power`.
+fn loudness(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// A second order IIR filter (transposed direct form II).
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    /// The first stage of the K-weighting filter, which models the head's
+    /// effect on high frequencies. BS.1770 only gives coefficients at 48 kHz,
+    /// so they're derived from the filter's analog parameters.
+    fn high_shelf(sample_rate: u32) -> Self {
+        const FREQUENCY: f64 = 1681.974450955533;
+        const GAIN_DB: f64 = 3.999843853973347;
+        const Q: f64 = 0.7071752369554196;
+
+        let k = (PI * FREQUENCY / sample_rate as f64).tan();
+        let vh = 10f64.powf(GAIN_DB / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / Q + k * k;
+        Self {
+            b: [
+                (vh + vb * k / Q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / Q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / Q + k * k) / a0],
+            state: [0.0; 2],
+        }
+    }
+
+    /// The second stage of the K-weighting filter (the "RLB" curve).
+    fn high_pass(sample_rate: u32) -> Self {
+        const FREQUENCY: f64 = 38.13547087602444;
+        const Q: f64 = 0.5003270373238773;
+
+        let k = (PI * FREQUENCY / sample_rate as f64).tan();
+        let a0 = 1.0 + k / Q + k * k;
+        Self {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / Q + k * k) / a0],
+            state: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.state[0];
+        self.state[0] = self.b[1] * input - self.a[0] * output + self.state[1];
+        self.state[1] = self.b[2] * input - self.a[1] * output;
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    /// `secs` seconds of a 1 kHz sine wave peaking at `dbfs`.
+    fn sine(dbfs: f64, secs: f64) -> Vec<f32> {
+        let amplitude = 10f64.powf(dbfs / 20.0);
+        (0..(secs * SAMPLE_RATE as f64) as usize)
+            .map(|i| {
+                let time = i as f64 / SAMPLE_RATE as f64;
+                (amplitude * (2.0 * PI * 1000.0 * time).sin()) as f32
+            })
+            .collect()
+    }
+
+    // --- integrated() ---
+
+    #[test]
+    fn stereo_sine_matches_its_level() {
+        // EBU Tech 3341's first test: a -23 dBFS stereo sine is -23 LUFS.
+        let samples = sine(-23.0, 20.0);
+        let mut meter = LoudnessMeter::new(2, SAMPLE_RATE);
+        meter.process(&[&samples, &samples]);
+        let loudness = meter.integrated().unwrap();
+        assert!((loudness + 23.0).abs() < 0.1, "{loudness}");
+    }
+
+    #[test]
+    fn quiet_passages_are_gated() {
+        // EBU Tech 3341's third test: -36 dBFS between -23 dBFS is gated
+        // out.
+        let mut meter = LoudnessMeter::new(2, SAMPLE_RATE);
+        for (dbfs, secs) in [(-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0)] {
+            let samples = sine(dbfs, secs);
+            meter.process(&[&samples, &samples]);
+        }
+        let loudness = meter.integrated().unwrap();
+        assert!((loudness + 23.0).abs() < 0.1, "{loudness}");
+    }
+
+    #[test]
+    fn silence_has_no_loudness() {
+        let mut meter = LoudnessMeter::new(2, SAMPLE_RATE);
+        let silence = vec![0.0; SAMPLE_RATE as usize * 2];
+        meter.process(&[&silence, &silence]);
+        assert_eq!(meter.integrated(), None);
+        assert_eq!(meter.sample_peak(), 0.0);
+    }
+}
@@ -0,0 +1,19 @@
+//! Adds media information to a [DiagnosticsReport].
+
+use ffmpeg_next as ffmpeg;
+use util::diagnostics::DiagnosticsReport;
+
+/// Add the versions and license of the FFmpeg libraries in use.
+pub fn add_media_info(report: &mut DiagnosticsReport) {
+    let libraries = [
+        ("libavutil", ffmpeg::util::version()),
+        ("libavcodec", ffmpeg::codec::version()),
+        ("libavformat", ffmpeg::format::version()),
+    ];
+    for (name, version) in libraries {
+        // FFmpeg packs versions as `major << 16 | minor << 8 | micro`.
+        let (major, minor, micro) = (version >> 16, (version >> 8) & 0xff, version & 0xff);
+        report.add("Media", name, format!("{major}.{minor}.{micro}"));
+    }
+    report.add("Media", "FFmpeg License", ffmpeg::util::license());
+}
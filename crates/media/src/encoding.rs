@@ -0,0 +1,710 @@
+//! This module exports everything that has to do with writing frames into
+//! video files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use ffmpeg_next as ffmpeg;
+
+use crate::ffmpeg_tools::ffmpeg_audio_track;
+use crate::ffmpeg_tools::ffmpeg_video_encoder::{self, FFmpegVideoEncoder};
+use crate::fps::Fps;
+use crate::frame::{Dimensions, Frame};
+
+/// A codec video can be encoded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VideoCodec {
+    /// H.264, for playing back and sharing anywhere. No alpha channel.
+    H264,
+    /// Apple ProRes 4444 (10-bit 4:4:4), for editing and compositing. Keeps
+    /// the alpha channel.
+    ProRes4444,
+    /// VP9, for the web. Keeps the alpha channel in WebM and Matroska files.
+    Vp9,
+}
+
+impl VideoCodec {
+    pub const ALL: [Self; 3] = [Self::H264, Self::ProRes4444, Self::Vp9];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::H264 => "H.264",
+            Self::ProRes4444 => "ProRes 4444",
+            Self::Vp9 => "VP9",
+        }
+    }
+
+    /// Whether videos with this codec can have an alpha channel.
+    pub fn supports_alpha(self) -> bool {
+        match self {
+            Self::H264 => false,
+            Self::ProRes4444 | Self::Vp9 => true,
+        }
+    }
+
+    /// The [RateControl::Quality] values this codec takes, from best to
+    /// worst, or [None] if it has no rate control. ProRes has none: its bit
+    /// rate is set by its profile, and every frame is a keyframe.
+    pub fn quality_range(self) -> Option<RangeInclusive<u8>> {
+        match self {
+            Self::H264 => Some(0..=51),
+            Self::ProRes4444 => None,
+            Self::Vp9 => Some(0..=63),
+        }
+    }
+
+    /// The [RateControl::Quality] to start from, which looks good without
+    /// making huge files.
+    pub fn default_quality(self) -> Option<u8> {
+        match self {
+            Self::H264 => Some(23),
+            Self::ProRes4444 => None,
+            Self::Vp9 => Some(31),
+        }
+    }
+}
+
+/// How an encoder decides how many bits each frame gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateControl {
+    /// Keep the quality constant (CRF), at this value of the codec's
+    /// [quality_range](VideoCodec::quality_range). The file is as big as
+    /// that takes.
+    Quality(u8),
+    /// Average `bit_rate` bits per second, so the file's size is known up
+    /// front (see [bit_rate_for_file_size]). Frames are encoded twice (see
+    /// [EncodingPass]) so the bits go where they're needed most.
+    TwoPass { bit_rate: u64 },
+}
+
+/// The [RateControl::TwoPass] bit rate that makes a video `duration_secs`
+/// long about `file_size` bytes. The container adds a little on top.
+pub fn bit_rate_for_file_size(file_size: u64, duration_secs: f64) -> u64 {
+    if duration_secs <= 0.0 {
+        return 0;
+    }
+    (file_size as f64 * 8.0 / duration_secs) as u64
+}
+
+/// How long an encoder spends on each frame. Slower encoders make smaller
+/// files at the same quality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EncodingSpeed {
+    Fastest,
+    Fast,
+    #[default]
+    Medium,
+    Slow,
+    Slowest,
+}
+
+impl EncodingSpeed {
+    pub const ALL: [Self; 5] = [
+        Self::Fastest,
+        Self::Fast,
+        Self::Medium,
+        Self::Slow,
+        Self::Slowest,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fastest => "Fastest",
+            Self::Fast => "Fast",
+            Self::Medium => "Medium",
+            Self::Slow => "Slow",
+            Self::Slowest => "Slowest",
+        }
+    }
+}
+
+/// How a video is encoded, besides its codec. [None]s are left to the
+/// codec, so the default encodes like the codec does on its own, in
+/// software.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EncodingOptions {
+    pub rate_control: Option<RateControl>,
+    /// The most frames between keyframes. More keyframes make seeking faster
+    /// and files bigger.
+    pub keyframe_interval: Option<u32>,
+    pub speed: Option<EncodingSpeed>,
+    /// Encode with one of the machine's [hardware_encoders] if it has one
+    /// (see [VideoEncoder::create]).
+    pub hardware: bool,
+}
+
+impl EncodingOptions {
+    /// Whether the frames are encoded twice (see [RateControl::TwoPass]).
+    pub fn is_two_pass(&self) -> bool {
+        matches!(self.rate_control, Some(RateControl::TwoPass { .. }))
+    }
+
+    /// Check that `codec` has every setting that's set, and that they're in
+    /// range. Fails with [EncodingError::Unsupported] otherwise.
+    pub fn validate(&self, codec: VideoCodec) -> Result<(), EncodingError> {
+        let unsupported = |message: String| Err(EncodingError::Unsupported(message));
+        let quality_range = codec.quality_range();
+
+        match (self.rate_control, &quality_range) {
+            (None, _) => {}
+            (Some(_), None) => {
+                return unsupported(format!("{} video has no rate control", codec.name()));
+            }
+            (Some(RateControl::Quality(quality)), Some(range)) if !range.contains(&quality) => {
+                return unsupported(format!(
+                    "{} quality goes from {} to {}",
+                    codec.name(),
+                    range.start(),
+                    range.end()
+                ));
+            }
+            (Some(RateControl::TwoPass { bit_rate: 0 }), _) => {
+                return unsupported("the bit rate must be above 0".to_string());
+            }
+            (Some(_), Some(_)) => {}
+        }
+
+        match self.keyframe_interval {
+            Some(0) => {
+                return unsupported("keyframes must be at least 1 frame apart".to_string());
+            }
+            Some(_) if quality_range.is_none() => {
+                return unsupported(format!("every {} frame is a keyframe", codec.name()));
+            }
+            _ => {}
+        }
+        if self.speed.is_some() && quality_range.is_none() {
+            return unsupported(format!("{} has no speed settings", codec.name()));
+        }
+        Ok(())
+    }
+}
+
+/// Which pass over the frames a [VideoEncoder] makes. Two-pass encodes (see
+/// [RateControl::TwoPass]) are made by encoding the same frames with a
+/// [First](EncodingPass::First) and then a [Second](EncodingPass::Second)
+/// encoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EncodingPass {
+    /// The one pass of anything but a two-pass encode.
+    Only,
+    /// Analyze the frames without writing the video, and keep what was
+    /// learned in a file next to it.
+    First,
+    /// Encode the video with what the first pass learned, then remove its
+    /// file.
+    Second,
+}
+
+/// Where the first pass of a two-pass encode into `path` keeps what it
+/// learned (e.g. "shot.mp4.2pass.log"). Some encoders write more files next
+/// to it, named after it.
+pub(crate) fn two_pass_stats_path(path: &Path) -> PathBuf {
+    let mut stats_path = path.as_os_str().to_owned();
+    stats_path.push(".2pass.log");
+    stats_path.into()
+}
+
+/// Remove what the first pass of a two-pass encode into `path` left behind,
+/// for when the second pass won't run (like when an export is stopped).
+/// Files that aren't there are skipped.
+pub fn remove_two_pass_stats(path: impl AsRef<Path>) {
+    let stats_path = two_pass_stats_path(path.as_ref());
+    let mut mbtree_path = stats_path.clone().into_os_string();
+    mbtree_path.push(".mbtree");
+    for path in [stats_path, mbtree_path.into()] {
+        _ = fs::remove_file(path);
+    }
+}
+
+/// An encoder built into a GPU. These encode much faster than the CPU, but
+/// make somewhat bigger files for the same quality and can't keep an alpha
+/// channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HardwareEncoder {
+    /// NVIDIA's encoder.
+    Nvenc,
+    /// AMD's encoder.
+    Amf,
+    /// Intel's encoder.
+    QuickSync,
+    /// Apple's encoder.
+    VideoToolbox,
+}
+
+impl HardwareEncoder {
+    /// Every hardware encoder, in the order they're tried.
+    pub const ALL: [Self; 4] = [Self::Nvenc, Self::Amf, Self::QuickSync, Self::VideoToolbox];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Nvenc => "NVIDIA NVENC",
+            Self::Amf => "AMD AMF",
+            Self::QuickSync => "Intel Quick Sync",
+            Self::VideoToolbox => "Apple VideoToolbox",
+        }
+    }
+
+    /// Whether this encoder can encode `codec` (on machines that have it).
+    pub fn supports(self, codec: VideoCodec) -> bool {
+        self.ffmpeg_encoder_name(codec).is_some()
+    }
+
+    pub(crate) fn ffmpeg_encoder_name(self, codec: VideoCodec) -> Option<&'static str> {
+        match (self, codec) {
+            (Self::Nvenc, VideoCodec::H264) => Some("h264_nvenc"),
+            (Self::Amf, VideoCodec::H264) => Some("h264_amf"),
+            (Self::QuickSync, VideoCodec::H264) => Some("h264_qsv"),
+            (Self::QuickSync, VideoCodec::Vp9) => Some("vp9_qsv"),
+            (Self::VideoToolbox, VideoCodec::H264) => Some("h264_videotoolbox"),
+            _ => None,
+        }
+    }
+}
+
+/// The hardware encoders this machine has for `codec`, in the order they're
+/// tried. Every encoder is checked (by opening it) the first time this is
+/// called, which can take a moment.
+pub fn hardware_encoders(codec: VideoCodec) -> &'static [HardwareEncoder] {
+    static AVAILABLE: LazyLock<HashMap<VideoCodec, Vec<HardwareEncoder>>> = LazyLock::new(|| {
+        VideoCodec::ALL
+            .into_iter()
+            .map(|codec| {
+                let encoders = HardwareEncoder::ALL
+                    .into_iter()
+                    .filter(|&hardware| {
+                        ffmpeg_video_encoder::hardware_encoder_works(hardware, codec)
+                    })
+                    .collect();
+                (codec, encoders)
+            })
+            .collect()
+    });
+    AVAILABLE.get(&codec).map(Vec::as_slice).unwrap_or_default()
+}
+
+/// A file format video can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VideoContainer {
+    Mp4,
+    Mov,
+    WebM,
+    Mkv,
+}
+
+impl VideoContainer {
+    pub const ALL: [Self; 4] = [Self::Mp4, Self::Mov, Self::WebM, Self::Mkv];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mp4 => "MP4",
+            Self::Mov => "QuickTime (MOV)",
+            Self::WebM => "WebM",
+            Self::Mkv => "Matroska (MKV)",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Mov => "mov",
+            Self::WebM => "webm",
+            Self::Mkv => "mkv",
+        }
+    }
+
+    /// Whether `codec` can be written in this container.
+    pub fn supports(self, codec: VideoCodec) -> bool {
+        match self {
+            Self::Mp4 => codec == VideoCodec::H264,
+            Self::Mov => matches!(codec, VideoCodec::H264 | VideoCodec::ProRes4444),
+            Self::WebM => codec == VideoCodec::Vp9,
+            Self::Mkv => matches!(codec, VideoCodec::H264 | VideoCodec::Vp9),
+        }
+    }
+
+    /// The codec to pick when this container is chosen and doesn't support
+    /// the current one.
+    pub fn default_codec(self) -> VideoCodec {
+        match self {
+            Self::Mp4 | Self::Mkv => VideoCodec::H264,
+            Self::Mov => VideoCodec::ProRes4444,
+            Self::WebM => VideoCodec::Vp9,
+        }
+    }
+
+    pub(crate) fn ffmpeg_format_name(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Mov => "mov",
+            Self::WebM => "webm",
+            Self::Mkv => "matroska",
+        }
+    }
+}
+
+/// The sample rate of mixed audio tracks (see [AudioSource]).
+pub const AUDIO_SAMPLE_RATE: u32 = 48_000;
+
+/// A media file (an audio file or a video with sound) whose audio goes into a
+/// video's audio track.
+///
+/// A lone source at a gain of 1 is passed through: its packets are copied
+/// into the video as they are, when the container can hold its codec.
+/// Anything else is decoded, resampled to [AUDIO_SAMPLE_RATE] stereo, mixed,
+/// and encoded again (AAC, or Opus in WebM files).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioSource {
+    pub path: PathBuf,
+    /// What the samples are multiplied by. 1 keeps them as they are.
+    pub gain: f32,
+}
+
+/// The loudness most streaming platforms turn audio to, in LUFS.
+pub const DEFAULT_LOUDNESS_TARGET: f64 = -14.0;
+
+/// The highest sample peak normalized audio may reach, in dBFS. Encoding
+/// moves peaks a little, so full scale isn't safe.
+pub const LOUDNESS_PEAK_CEILING: f64 = -1.0;
+
+/// How loud a mix of [AudioSource]s is (see [measure_loudness]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// The integrated loudness (EBU R128), in LUFS, or [None] if the mix is
+    /// silent.
+    pub integrated: Option<f64>,
+    /// The highest sample peak, in dBFS.
+    pub peak: f64,
+}
+
+impl Loudness {
+    /// The gain in dB that brings the mix to `target` LUFS without its peaks
+    /// going over [LOUDNESS_PEAK_CEILING], and whether the ceiling held it
+    /// back. Silence is left alone.
+    pub fn normalizing_gain(&self, target: f64) -> (f64, bool) {
+        let Some(integrated) = self.integrated else {
+            return (0.0, false);
+        };
+        let gain = target - integrated;
+        let headroom = LOUDNESS_PEAK_CEILING - self.peak;
+        if gain > headroom {
+            (headroom, true)
+        } else {
+            (gain, false)
+        }
+    }
+}
+
+/// Measure how loud the first `duration_secs` seconds of a video's audio
+/// would be with `sources`, or return [None] if none of them have sound. Every
+/// source is decoded, so this takes a while for long ones.
+pub fn measure_loudness(
+    sources: &[AudioSource],
+    duration_secs: f64,
+) -> Result<Option<Loudness>, EncodingError> {
+    let Some(meter) = ffmpeg_audio_track::measure_mix(sources, duration_secs)? else {
+        return Ok(None);
+    };
+    Ok(Some(Loudness {
+        integrated: meter.integrated(),
+        peak: 20.0 * (meter.sample_peak() as f64).log10(),
+    }))
+}
+
+/// Encodes frames into a video file, in order.
+pub struct VideoEncoder {
+    video: FFmpegVideoEncoder,
+    hardware: Option<HardwareEncoder>,
+    software_fallback: Option<String>,
+}
+
+impl VideoEncoder {
+    /// Create the file at `path` and get ready to encode frames of
+    /// `dimensions` at `fps` into it. `alpha` keeps the frames' alpha channel
+    /// instead of dropping it.
+    ///
+    /// If [EncodingOptions::hardware] is set, the first of the machine's
+    /// [hardware_encoders] that starts is used. When none does (or `alpha`
+    /// is set, which they can't keep, or it's a two-pass encode) the video is
+    /// encoded in software instead, and [VideoEncoder::software_fallback]
+    /// says why.
+    ///
+    /// `audio` is mixed into the video's audio track as frames are written,
+    /// and cut off where the video ends. Sources without audio are skipped,
+    /// and the first pass of a two-pass encode writes no audio.
+    ///
+    /// `pass` must be [EncodingPass::Only] unless `options` are for a
+    /// two-pass encode. Fails if the settings don't pass
+    /// [check_video_settings] and [EncodingOptions::validate].
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        path: impl AsRef<Path>,
+        container: VideoContainer,
+        codec: VideoCodec,
+        dimensions: Dimensions,
+        fps: Fps,
+        alpha: bool,
+        options: &EncodingOptions,
+        pass: EncodingPass,
+        audio: &[AudioSource],
+    ) -> Result<Self, EncodingError> {
+        check_video_settings(container, codec, alpha)?;
+        options.validate(codec)?;
+        if options.is_two_pass() == (pass == EncodingPass::Only) {
+            return Err(EncodingError::Unsupported(
+                "two-pass encodes need a first and a second pass, and nothing else does"
+                    .to_string(),
+            ));
+        }
+        let path = path.as_ref();
+        let new_video = |hardware| {
+            FFmpegVideoEncoder::new(
+                path, container, codec, dimensions, fps, alpha, hardware, options, pass, audio,
+            )
+        };
+
+        let software_fallback = if !options.hardware {
+            None
+        } else if alpha {
+            Some("hardware encoders can't keep an alpha channel".to_string())
+        } else if options.is_two_pass() {
+            Some("two-pass encodes are made in software".to_string())
+        } else {
+            let mut failures = Vec::new();
+            for &hardware in hardware_encoders(codec) {
+                match new_video(Some(hardware)) {
+                    Ok(video) => {
+                        return Ok(Self {
+                            video,
+                            hardware: Some(hardware),
+                            software_fallback: None,
+                        });
+                    }
+                    Err(e) => failures.push(format!("{} failed to start ({e})", hardware.name())),
+                }
+            }
+            if failures.is_empty() {
+                Some(format!(
+                    "no hardware encoder for {} was found",
+                    codec.name()
+                ))
+            } else {
+                Some(failures.join(", "))
+            }
+        };
+
+        Ok(Self {
+            video: new_video(None)?,
+            hardware: None,
+            software_fallback,
+        })
+    }
+
+    /// The hardware encoder encoding the video, or [None] if it's encoded in
+    /// software.
+    pub fn hardware_encoder(&self) -> Option<HardwareEncoder> {
+        self.hardware
+    }
+
+    /// Why the video is encoded in software even though a hardware encoder
+    /// was asked for, if it is.
+    pub fn software_fallback(&self) -> Option<&str> {
+        self.software_fallback.as_deref()
+    }
+
+    /// The dimensions every frame must have.
+    pub fn dimensions(&self) -> Dimensions {
+        self.video.dimensions()
+    }
+
+    /// Encode `frame` as the next frame of the video. It must have the
+    /// encoder's [dimensions](Self::dimensions).
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), EncodingError> {
+        Ok(self.video.write_frame(frame)?)
+    }
+
+    /// Finish the file. The file isn't playable until this is called.
+    pub fn finish(self) -> Result<(), EncodingError> {
+        Ok(self.video.finish()?)
+    }
+}
+
+/// Join `parts`, videos written by [VideoEncoder]s with the same settings and
+/// `container`, into one video at `path`, one after another. They're copied
+/// rather than encoded again, so this is about as fast as copying the files.
+pub fn concatenate_videos(
+    parts: &[PathBuf],
+    path: impl AsRef<Path>,
+    container: VideoContainer,
+) -> Result<(), EncodingError> {
+    if parts.is_empty() {
+        return Err(EncodingError::Unsupported(
+            "there are no videos to join".to_string(),
+        ));
+    }
+    let parts: Vec<&Path> = parts.iter().map(PathBuf::as_path).collect();
+    Ok(ffmpeg_video_encoder::concatenate_videos(
+        &parts,
+        path.as_ref(),
+        container,
+    )?)
+}
+
+/// What decoding a whole video found (see [probe_video]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoProbe {
+    /// How many frames of the video stream decoded.
+    pub frames: u64,
+    /// How long the container says the video is, in seconds.
+    pub duration_secs: f64,
+    pub has_audio: bool,
+    /// Packets that were flagged corrupt or failed to decode, in the order
+    /// they were found. A video that's intact has none.
+    pub errors: Vec<String>,
+}
+
+/// Decode every frame of the video at `path` to check that it isn't corrupt,
+/// like after writing it. This takes a while for long videos, though frames
+/// aren't converted or scaled.
+pub fn probe_video(path: impl AsRef<Path>) -> Result<VideoProbe, EncodingError> {
+    Ok(ffmpeg_video_encoder::probe_video(path.as_ref())?)
+}
+
+/// Check that `container` [supports](VideoContainer::supports) `codec`, and
+/// that `codec` [supports](VideoCodec::supports_alpha) an alpha channel if
+/// `alpha` is set. Fails with [EncodingError::Unsupported] otherwise.
+pub fn check_video_settings(
+    container: VideoContainer,
+    codec: VideoCodec,
+    alpha: bool,
+) -> Result<(), EncodingError> {
+    if !container.supports(codec) {
+        return Err(EncodingError::Unsupported(format!(
+            "{} files can't hold {} video",
+            container.name(),
+            codec.name()
+        )));
+    }
+    if alpha && !codec.supports_alpha() {
+        return Err(EncodingError::Unsupported(format!(
+            "{} video can't have an alpha channel",
+            codec.name()
+        )));
+    }
+    Ok(())
+}
+
+/// Indicates something went wrong encoding video with [VideoEncoder].
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum EncodingError {
+    #[error("Unsupported video settings: {0}")]
+    Unsupported(String),
+    #[error("Encoding Error: {0}")]
+    FFmpeg(#[from] ffmpeg::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- VideoContainer::supports() ---
+
+    #[test]
+    fn every_container_supports_its_default_codec() {
+        for container in VideoContainer::ALL {
+            assert!(container.supports(container.default_codec()));
+        }
+    }
+
+    #[test]
+    fn alpha_codecs_have_a_container() {
+        assert!(VideoContainer::Mov.supports(VideoCodec::ProRes4444));
+        assert!(VideoContainer::WebM.supports(VideoCodec::Vp9));
+        assert!(!VideoContainer::Mp4.supports(VideoCodec::ProRes4444));
+        assert!(!VideoContainer::WebM.supports(VideoCodec::H264));
+    }
+
+    // --- EncodingOptions::validate() ---
+
+    #[test]
+    fn validate_checks_the_codec_has_the_setting() {
+        let quality = |quality| EncodingOptions {
+            rate_control: Some(RateControl::Quality(quality)),
+            ..Default::default()
+        };
+        assert!(quality(23).validate(VideoCodec::H264).is_ok());
+        assert!(quality(63).validate(VideoCodec::Vp9).is_ok());
+        assert!(quality(52).validate(VideoCodec::H264).is_err());
+        assert!(quality(23).validate(VideoCodec::ProRes4444).is_err());
+
+        let two_pass = |bit_rate| EncodingOptions {
+            rate_control: Some(RateControl::TwoPass { bit_rate }),
+            ..Default::default()
+        };
+        assert!(two_pass(8_000_000).validate(VideoCodec::Vp9).is_ok());
+        assert!(two_pass(0).validate(VideoCodec::H264).is_err());
+
+        let keyframes = |interval| EncodingOptions {
+            keyframe_interval: Some(interval),
+            ..Default::default()
+        };
+        assert!(keyframes(60).validate(VideoCodec::H264).is_ok());
+        assert!(keyframes(0).validate(VideoCodec::H264).is_err());
+        assert!(keyframes(60).validate(VideoCodec::ProRes4444).is_err());
+
+        assert!(
+            EncodingOptions::default()
+                .validate(VideoCodec::ProRes4444)
+                .is_ok()
+        );
+    }
+
+    // --- bit_rate_for_file_size() ---
+
+    #[test]
+    fn bit_rate_fills_the_file_size() {
+        // 75 MB over 60 seconds is 10 Mbit/s.
+        assert_eq!(bit_rate_for_file_size(75_000_000, 60.0), 10_000_000);
+        assert_eq!(bit_rate_for_file_size(75_000_000, 0.0), 0);
+    }
+
+    // --- Loudness::normalizing_gain() ---
+
+    #[test]
+    fn normalizing_gain_reaches_the_target() {
+        let loudness = Loudness {
+            integrated: Some(-20.0),
+            peak: -12.0,
+        };
+        assert_eq!(loudness.normalizing_gain(-14.0), (6.0, false));
+        assert_eq!(loudness.normalizing_gain(-23.0), (-3.0, false));
+    }
+
+    #[test]
+    fn normalizing_gain_keeps_peaks_under_the_ceiling() {
+        let loudness = Loudness {
+            integrated: Some(-20.0),
+            peak: -4.0,
+        };
+        assert_eq!(loudness.normalizing_gain(-14.0), (3.0, true));
+
+        let silence = Loudness {
+            integrated: None,
+            peak: f64::NEG_INFINITY,
+        };
+        assert_eq!(silence.normalizing_gain(-14.0), (0.0, false));
+    }
+
+    // --- HardwareEncoder::supports() ---
+
+    #[test]
+    fn every_hardware_encoder_supports_h264() {
+        for hardware in HardwareEncoder::ALL {
+            assert!(hardware.supports(VideoCodec::H264));
+            assert!(!hardware.supports(VideoCodec::ProRes4444));
+        }
+    }
+}
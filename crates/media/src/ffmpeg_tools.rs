@@ -1,6 +1,9 @@
 //! Tools for dealing with FFmpeg.
 
+pub mod ffmpeg_audio;
+pub mod ffmpeg_audio_track;
 pub mod ffmpeg_video;
+pub mod ffmpeg_video_encoder;
 
 mod impls;
 
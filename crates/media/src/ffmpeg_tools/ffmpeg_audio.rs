@@ -0,0 +1,194 @@
+//! Exports [FFmpegAudio].
+
+use std::path::Path;
+use std::time::Duration;
+
+use ffmpeg::ChannelLayout;
+use ffmpeg::codec::Context as FFmpegCodecContext;
+use ffmpeg::codec::decoder::Audio as FFmpegAudioDecoder;
+use ffmpeg::format::Sample as FFmpegSampleFormat;
+use ffmpeg::format::context::Input as FFmpegInputFormatContext;
+use ffmpeg::format::sample::Type as FFmpegSampleType;
+use ffmpeg::media::Type as FFmpegMediaType;
+use ffmpeg::software::resampling::Context as FFmpegResamplingContext;
+use ffmpeg_next as ffmpeg;
+
+use super::FFmpegResult;
+
+pub type FFmpegAudioFrame = ffmpeg::frame::Audio;
+
+/// The sample format every decoded block is converted to.
+const TARGET_SAMPLE_FORMAT: FFmpegSampleFormat = FFmpegSampleFormat::F32(FFmpegSampleType::Planar);
+
+/// The units of [FFmpegInputFormatContext::seek] timestamps when seeking
+/// without a stream (`AV_TIME_BASE`).
+const SEEK_UNITS_PER_SEC: f64 = 1_000_000.0;
+
+/// Decodes the best audio stream of a media file in order, as blocks of
+/// planar `f32` samples (one slice per channel).
+pub struct FFmpegAudio {
+    input_context: FFmpegInputFormatContext,
+    decoder: FFmpegAudioDecoder,
+    /// Converts decoded frames to [TARGET_SAMPLE_FORMAT] (and the output's
+    /// rate and channels), or [None] if the decoder already outputs it.
+    resampler: Option<FFmpegResamplingContext>,
+    /// The sample rate blocks come out at.
+    output_rate: u32,
+    output_channels: usize,
+    decoded_frame: FFmpegAudioFrame,
+    draining: bool,
+
+    target_stream_index: usize,
+    /// Seconds per stream timestamp.
+    time_base: f64,
+    /// The time of the next sample to be decoded.
+    position: Duration,
+}
+
+impl FFmpegAudio {
+    /// Open the best audio stream in the file at `path`.
+    pub fn new(path: &Path) -> FFmpegResult<Self> {
+        Self::with_output(path, None)
+    }
+
+    /// Open the best audio stream in the file at `path`, resampled to
+    /// `output`'s sample rate and channel layout if it's set.
+    pub fn with_output(path: &Path, output: Option<(u32, ChannelLayout)>) -> FFmpegResult<Self> {
+        let input_context = ffmpeg::format::input(path)?;
+
+        let best_audio_stream = input_context
+            .streams()
+            .best(FFmpegMediaType::Audio)
+            .ok_or(ffmpeg::Error::StreamNotFound)?;
+        let target_stream_index = best_audio_stream.index();
+        let time_base = f64::from(best_audio_stream.time_base());
+
+        let decoder_context = FFmpegCodecContext::from_parameters(best_audio_stream.parameters())?;
+        let decoder = decoder_context.decoder().audio()?;
+
+        // Some files (e.g. WAV) don't say which channel is which.
+        let channel_layout = if decoder.channel_layout().is_empty() {
+            ChannelLayout::default(decoder.channels() as i32)
+        } else {
+            decoder.channel_layout()
+        };
+        let (output_rate, output_layout) = output.unwrap_or((decoder.rate(), channel_layout));
+        let resampler = (decoder.format() != TARGET_SAMPLE_FORMAT || output.is_some())
+            .then(|| {
+                FFmpegResamplingContext::get(
+                    decoder.format(),
+                    channel_layout,
+                    decoder.rate(),
+                    TARGET_SAMPLE_FORMAT,
+                    output_layout,
+                    output_rate,
+                )
+            })
+            .transpose()?;
+
+        Ok(Self {
+            input_context,
+            decoder,
+            resampler,
+            output_rate,
+            output_channels: output_layout.channels() as usize,
+            decoded_frame: FFmpegAudioFrame::empty(),
+            draining: false,
+            target_stream_index,
+            time_base,
+            position: Duration::ZERO,
+        })
+    }
+
+    pub fn channels(&self) -> usize {
+        self.output_channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /// The time of the next sample [Self::next_block] will decode.
+    pub const fn position(&self) -> Duration {
+        self.position
+    }
+
+    /// Move to the keyframe at or before `time`. The next block may start a
+    /// little before `time` (see [Self::position]).
+    pub fn seek(&mut self, time: Duration) -> FFmpegResult<()> {
+        let ts = (time.as_secs_f64() * SEEK_UNITS_PER_SEC) as i64;
+        self.input_context.seek(ts, ..ts + 1)?;
+        self.decoder.flush();
+        self.draining = false;
+        self.position = time;
+        Ok(())
+    }
+
+    /// Decode the next block of samples and pass it to `f` as one slice per
+    /// channel.
+    ///
+    /// An [Eof](ffmpeg::Error::Eof) error is returned once the stream runs out.
+    pub fn next_block<F, R>(&mut self, f: F) -> FFmpegResult<R>
+    where
+        F: FnOnce(&[&[f32]]) -> R,
+    {
+        self.decode_next_frame()?;
+
+        if let Some(timestamp) = self.decoded_frame.timestamp() {
+            self.position = Duration::from_secs_f64((timestamp as f64 * self.time_base).max(0.0));
+        }
+
+        let converted;
+        let frame = match &mut self.resampler {
+            Some(resampler) => {
+                let mut output = FFmpegAudioFrame::empty();
+                resampler.run(&self.decoded_frame, &mut output)?;
+                converted = output;
+                &converted
+            }
+            None => &self.decoded_frame,
+        };
+
+        let planes: Vec<&[f32]> = (0..frame.planes()).map(|i| frame.plane(i)).collect();
+        let ret = f(&planes);
+
+        let rate = self.output_rate.max(1);
+        self.position += Duration::from_secs_f64(frame.samples() as f64 / rate as f64);
+        Ok(ret)
+    }
+
+    fn decode_next_frame(&mut self) -> FFmpegResult<()> {
+        loop {
+            let decode_err = match self.decoder.receive_frame(&mut self.decoded_frame) {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            // `EAGAIN` means we haven't sent enough packets for a frame yet.
+            if decode_err != EAGAIN {
+                return Err(decode_err);
+            }
+            if self.draining {
+                return Err(ffmpeg::Error::Eof);
+            }
+
+            let mut packets = self
+                .input_context
+                .packets()
+                .filter_map(|(packet_stream, packet)| {
+                    (packet_stream.index() == self.target_stream_index).then_some(packet)
+                });
+
+            if let Some(packet) = packets.next() {
+                self.decoder.send_packet(&packet)?;
+            } else {
+                self.decoder.send_eof()?;
+                self.draining = true;
+            }
+        }
+    }
+}
+
+const EAGAIN: ffmpeg::Error = ffmpeg::Error::Other {
+    errno: ffmpeg::error::EAGAIN,
+};
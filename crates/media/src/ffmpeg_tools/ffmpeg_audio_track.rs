@@ -0,0 +1,506 @@
+//! Exports [FFmpegAudioTrack].
+
+use std::path::Path;
+
+use ffmpeg::ChannelLayout;
+use ffmpeg::Packet as FFmpegPacket;
+use ffmpeg::Rational;
+use ffmpeg::codec::Context as FFmpegCodecContext;
+use ffmpeg::codec::encoder::audio::Encoder as FFmpegOpenAudioEncoder;
+use ffmpeg::format::Sample as FFmpegSampleFormat;
+use ffmpeg::format::context::Input as FFmpegInputFormatContext;
+use ffmpeg::format::context::Output as FFmpegOutputFormatContext;
+use ffmpeg::format::sample::Type as FFmpegSampleType;
+use ffmpeg::media::Type as FFmpegMediaType;
+use ffmpeg::software::resampling::Context as FFmpegResamplingContext;
+use ffmpeg_next as ffmpeg;
+
+use super::FFmpegResult;
+use super::ffmpeg_audio::{FFmpegAudio, FFmpegAudioFrame};
+use crate::audio::LoudnessMeter;
+use crate::encoding::{AUDIO_SAMPLE_RATE, AudioSource, VideoContainer};
+
+/// The format sources are mixed in, one plane per channel.
+const MIX_SAMPLE_FORMAT: FFmpegSampleFormat = FFmpegSampleFormat::F32(FFmpegSampleType::Planar);
+
+const MIX_CHANNEL_LAYOUT: ChannelLayout = ChannelLayout::STEREO;
+const MIX_CHANNELS: usize = 2;
+
+/// The bit rate mixes are encoded at, in bits per second.
+const MIX_BIT_RATE: usize = 192_000;
+
+/// How many samples go in each frame sent to encoders that take any number.
+const DEFAULT_FRAME_SIZE: usize = 1024;
+
+/// `FF_COMPLIANCE_NORMAL`, for [container_holds].
+const STANDARD_COMPLIANCE: i32 = 0;
+
+/// The audio stream of a video an
+/// [FFmpegVideoEncoder](super::ffmpeg_video_encoder::FFmpegVideoEncoder) is
+/// writing. The encoder writes the audio up to each frame's time as it
+/// writes the frame, so the muxer can interleave the two.
+pub struct FFmpegAudioTrack {
+    source: TrackSource,
+    stream_index: usize,
+    /// The time base packets are written in, which is known once the file's
+    /// header is written (see [FFmpegAudioTrack::header_written]).
+    stream_time_base: Rational,
+}
+
+enum TrackSource {
+    Passthrough(Passthrough),
+    Mix(Box<Mix>),
+}
+
+/// A source's audio stream, copied as it is.
+struct Passthrough {
+    input_context: FFmpegInputFormatContext,
+    input_index: usize,
+    input_time_base: Rational,
+    /// The stream's first timestamp, which moves to the start of the video.
+    start: i64,
+    /// A packet that was read but comes after what's been written so far.
+    held: Option<FFmpegPacket>,
+}
+
+/// Sources decoded, mixed, and encoded again.
+struct Mix {
+    mixer: Mixer,
+    encoder: FFmpegOpenAudioEncoder,
+    encoder_time_base: Rational,
+    /// Converts mixed frames to the encoder's format, or [None] if it takes
+    /// [MIX_SAMPLE_FORMAT].
+    converter: Option<FFmpegResamplingContext>,
+    frame_size: usize,
+    /// How many samples have been sent to the encoder.
+    written: u64,
+}
+
+/// Decodes sources and adds them together.
+struct Mixer {
+    sources: Vec<MixSource>,
+    /// The last block mixed, per channel.
+    mixed: [Vec<f32>; MIX_CHANNELS],
+}
+
+struct MixSource {
+    audio: FFmpegAudio,
+    gain: f32,
+    /// Samples that were decoded but haven't been mixed, per channel.
+    pending: [Vec<f32>; MIX_CHANNELS],
+    ended: bool,
+}
+
+impl FFmpegAudioTrack {
+    /// Add a stream for `sources` to `output_context`, whose header mustn't
+    /// have been written yet. `global_header` must be set if the container
+    /// wants codec headers up front.
+    ///
+    /// Returns [None] if none of the sources have audio.
+    pub fn new(
+        output_context: &mut FFmpegOutputFormatContext,
+        container: VideoContainer,
+        sources: &[AudioSource],
+        global_header: bool,
+    ) -> FFmpegResult<Option<Self>> {
+        if let [source] = sources
+            && source.gain == 1.0
+            && let Some(passthrough) = Passthrough::open(&source.path, output_context)?
+        {
+            let stream_time_base = passthrough.input_time_base;
+            let stream_index = {
+                let input_stream = passthrough
+                    .input_context
+                    .stream(passthrough.input_index)
+                    .ok_or(ffmpeg::Error::StreamNotFound)?;
+                let mut stream =
+                    output_context.add_stream(ffmpeg::encoder::find(ffmpeg::codec::Id::None))?;
+                stream.set_parameters(input_stream.parameters());
+                stream.set_time_base(passthrough.input_time_base);
+                // The tag is specific to the source's container, and the muxer
+                // picks one itself when it's unset. There's no safe API for
+                // this.
+                unsafe {
+                    (*stream.parameters().as_mut_ptr()).codec_tag = 0;
+                }
+                stream.index()
+            };
+            return Ok(Some(Self {
+                source: TrackSource::Passthrough(passthrough),
+                stream_index,
+                stream_time_base,
+            }));
+        }
+
+        let Some(mixer) = Mixer::open(sources)? else {
+            return Ok(None);
+        };
+        let mix = Mix::new(mixer, container, global_header)?;
+        let stream_time_base = mix.encoder_time_base;
+        let stream_index = {
+            let mut stream = output_context.add_stream(mix.encoder.codec())?;
+            stream.set_parameters(&mix.encoder);
+            stream.set_time_base(mix.encoder_time_base);
+            stream.index()
+        };
+        Ok(Some(Self {
+            source: TrackSource::Mix(Box::new(mix)),
+            stream_index,
+            stream_time_base,
+        }))
+    }
+
+    /// Read the stream's time base, which muxers may change when writing the
+    /// file's header.
+    pub fn header_written(
+        &mut self,
+        output_context: &FFmpegOutputFormatContext,
+    ) -> FFmpegResult<()> {
+        self.stream_time_base = output_context
+            .stream(self.stream_index)
+            .ok_or(ffmpeg::Error::StreamNotFound)?
+            .time_base();
+        Ok(())
+    }
+
+    /// Write the audio up to `until` seconds into the video.
+    pub fn write_until(
+        &mut self,
+        output_context: &mut FFmpegOutputFormatContext,
+        until: f64,
+    ) -> FFmpegResult<()> {
+        let (stream_index, stream_time_base) = (self.stream_index, self.stream_time_base);
+        match &mut self.source {
+            TrackSource::Passthrough(passthrough) => {
+                passthrough.write_until(output_context, until, stream_index, stream_time_base)
+            }
+            TrackSource::Mix(mix) => {
+                let until = (until * AUDIO_SAMPLE_RATE as f64) as u64;
+                while mix.written + mix.frame_size as u64 <= until {
+                    if !mix.encode_frame()? {
+                        break;
+                    }
+                    mix.write_packets(output_context, stream_index, stream_time_base)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Write the rest of the audio, up to the end of the video (`until`
+    /// seconds in). Must be called before the file's trailer is written.
+    pub fn finish(
+        mut self,
+        output_context: &mut FFmpegOutputFormatContext,
+        until: f64,
+    ) -> FFmpegResult<()> {
+        self.write_until(output_context, until)?;
+        let TrackSource::Mix(mix) = &mut self.source else {
+            return Ok(());
+        };
+
+        // The video ends partway into a frame. Not every encoder takes short
+        // frames, so it's padded with silence.
+        let until = (until * AUDIO_SAMPLE_RATE as f64) as u64;
+        if until > mix.written {
+            mix.encode_frame()?;
+        }
+        mix.encoder.send_eof()?;
+        mix.write_packets(output_context, self.stream_index, self.stream_time_base)
+    }
+}
+
+impl Passthrough {
+    /// Open the best audio stream in the file at `path`, or return [None] if
+    /// it has none or `output_context`'s container can't hold its codec.
+    fn open(path: &Path, output_context: &FFmpegOutputFormatContext) -> FFmpegResult<Option<Self>> {
+        let input_context = ffmpeg::format::input(path)?;
+        let Some(stream) = input_context.streams().best(FFmpegMediaType::Audio) else {
+            return Ok(None);
+        };
+        if !container_holds(output_context, stream.parameters().id()) {
+            return Ok(None);
+        }
+        let (input_index, input_time_base) = (stream.index(), stream.time_base());
+        let start = match stream.start_time() {
+            ffmpeg::ffi::AV_NOPTS_VALUE => 0,
+            start => start,
+        };
+        Ok(Some(Self {
+            input_context,
+            input_index,
+            input_time_base,
+            start,
+            held: None,
+        }))
+    }
+
+    fn write_until(
+        &mut self,
+        output_context: &mut FFmpegOutputFormatContext,
+        until: f64,
+        stream_index: usize,
+        stream_time_base: Rational,
+    ) -> FFmpegResult<()> {
+        loop {
+            let packet = match self.held.take() {
+                Some(packet) => packet,
+                None => {
+                    let input_index = self.input_index;
+                    let next = self.input_context.packets().find_map(|(stream, packet)| {
+                        (stream.index() == input_index).then_some(packet)
+                    });
+                    match next {
+                        Some(packet) => packet,
+                        None => return Ok(()),
+                    }
+                }
+            };
+
+            let Some(pts) = packet.pts().or(packet.dts()) else {
+                continue;
+            };
+            if (pts - self.start) as f64 * f64::from(self.input_time_base) >= until {
+                self.held = Some(packet);
+                return Ok(());
+            }
+
+            let mut packet = packet;
+            packet.set_pts(packet.pts().map(|pts| pts - self.start));
+            packet.set_dts(packet.dts().map(|dts| dts - self.start));
+            packet.rescale_ts(self.input_time_base, stream_time_base);
+            packet.set_position(-1);
+            packet.set_stream(stream_index);
+            packet.write_interleaved(output_context)?;
+        }
+    }
+}
+
+impl Mix {
+    fn new(mixer: Mixer, container: VideoContainer, global_header: bool) -> FFmpegResult<Self> {
+        let codec = match container {
+            VideoContainer::WebM => ffmpeg::encoder::find_by_name("libopus")
+                .or_else(|| ffmpeg::encoder::find(ffmpeg::codec::Id::OPUS)),
+            VideoContainer::Mp4 | VideoContainer::Mov | VideoContainer::Mkv => {
+                ffmpeg::encoder::find(ffmpeg::codec::Id::AAC)
+            }
+        }
+        .ok_or(ffmpeg::Error::EncoderNotFound)?;
+        let formats: Vec<FFmpegSampleFormat> = codec
+            .audio()?
+            .formats()
+            .map(|formats| formats.collect())
+            .unwrap_or_default();
+        let format = if formats.is_empty() || formats.contains(&MIX_SAMPLE_FORMAT) {
+            MIX_SAMPLE_FORMAT
+        } else {
+            formats[0]
+        };
+
+        let encoder_time_base = Rational::new(1, AUDIO_SAMPLE_RATE as i32);
+        let mut encoder = FFmpegCodecContext::new_with_codec(codec)
+            .encoder()
+            .audio()?;
+        encoder.set_rate(AUDIO_SAMPLE_RATE as i32);
+        encoder.set_channel_layout(MIX_CHANNEL_LAYOUT);
+        encoder.set_format(format);
+        encoder.set_time_base(encoder_time_base);
+        encoder.set_bit_rate(MIX_BIT_RATE);
+        if global_header {
+            encoder.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
+        }
+        let encoder = encoder.open_as(codec)?;
+
+        let converter = (format != MIX_SAMPLE_FORMAT)
+            .then(|| {
+                FFmpegResamplingContext::get(
+                    MIX_SAMPLE_FORMAT,
+                    MIX_CHANNEL_LAYOUT,
+                    AUDIO_SAMPLE_RATE,
+                    format,
+                    MIX_CHANNEL_LAYOUT,
+                    AUDIO_SAMPLE_RATE,
+                )
+            })
+            .transpose()?;
+        let frame_size = match encoder.frame_size() {
+            0 => DEFAULT_FRAME_SIZE,
+            frame_size => frame_size as usize,
+        };
+
+        Ok(Self {
+            mixer,
+            encoder,
+            encoder_time_base,
+            converter,
+            frame_size,
+            written: 0,
+        })
+    }
+
+    /// Mix the next frame's worth of every source and send it to the encoder.
+    /// Returns `false` (sending nothing) once every source has run out.
+    fn encode_frame(&mut self) -> FFmpegResult<bool> {
+        let samples = self.frame_size;
+        let Some(mixed) = self.mixer.mix(samples)? else {
+            return Ok(false);
+        };
+
+        let mut frame = FFmpegAudioFrame::new(MIX_SAMPLE_FORMAT, samples, MIX_CHANNEL_LAYOUT);
+        frame.set_rate(AUDIO_SAMPLE_RATE);
+        for (channel, mixed) in mixed.iter().enumerate() {
+            let plane = &mut frame.plane_mut::<f32>(channel)[..samples];
+            for (sample, mixed) in plane.iter_mut().zip(mixed) {
+                *sample = mixed.clamp(-1.0, 1.0);
+            }
+        }
+
+        let mut frame = match &mut self.converter {
+            Some(converter) => {
+                let mut converted = FFmpegAudioFrame::empty();
+                converter.run(&frame, &mut converted)?;
+                converted
+            }
+            None => frame,
+        };
+        frame.set_pts(Some(self.written as i64));
+        self.written += samples as u64;
+        self.encoder.send_frame(&frame)?;
+        Ok(true)
+    }
+
+    /// Write every packet the encoder has ready to the file.
+    fn write_packets(
+        &mut self,
+        output_context: &mut FFmpegOutputFormatContext,
+        stream_index: usize,
+        stream_time_base: Rational,
+    ) -> FFmpegResult<()> {
+        let mut packet = FFmpegPacket::empty();
+        loop {
+            match self.encoder.receive_packet(&mut packet) {
+                Ok(()) => {}
+                // `EAGAIN` means the encoder needs more samples for a packet.
+                Err(e) if e == EAGAIN || e == ffmpeg::Error::Eof => return Ok(()),
+                Err(e) => return Err(e),
+            }
+            packet.set_stream(stream_index);
+            packet.rescale_ts(self.encoder_time_base, stream_time_base);
+            packet.write_interleaved(output_context)?;
+        }
+    }
+}
+
+impl Mixer {
+    /// Open every source that has sound, or return [None] if none do.
+    fn open(sources: &[AudioSource]) -> FFmpegResult<Option<Self>> {
+        let mut mix_sources = Vec::with_capacity(sources.len());
+        for source in sources {
+            match FFmpegAudio::with_output(
+                &source.path,
+                Some((AUDIO_SAMPLE_RATE, MIX_CHANNEL_LAYOUT)),
+            ) {
+                Ok(audio) => mix_sources.push(MixSource {
+                    audio,
+                    gain: source.gain,
+                    pending: Default::default(),
+                    ended: false,
+                }),
+                // Files without sound are left out.
+                Err(ffmpeg::Error::StreamNotFound) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((!mix_sources.is_empty()).then(|| Self {
+            sources: mix_sources,
+            mixed: Default::default(),
+        }))
+    }
+
+    /// Add up the next `samples` samples of every source, times its gain.
+    /// Sources that run out before the others are silent, and [None] is
+    /// returned once every source has. The mix may go past full scale.
+    fn mix(&mut self, samples: usize) -> FFmpegResult<Option<&[Vec<f32>; MIX_CHANNELS]>> {
+        for source in &mut self.sources {
+            source.fill(samples)?;
+        }
+        if self
+            .sources
+            .iter()
+            .all(|source| source.ended && source.pending[0].is_empty())
+        {
+            return Ok(None);
+        }
+
+        for (channel, mixed) in self.mixed.iter_mut().enumerate() {
+            mixed.clear();
+            mixed.resize(samples, 0.0);
+            for source in &self.sources {
+                for (mixed, sample) in mixed.iter_mut().zip(&source.pending[channel]) {
+                    *mixed += sample * source.gain;
+                }
+            }
+        }
+        for source in &mut self.sources {
+            for pending in &mut source.pending {
+                pending.drain(..samples.min(pending.len()));
+            }
+        }
+        Ok(Some(&self.mixed))
+    }
+}
+
+impl MixSource {
+    /// Decode until at least `samples` samples are pending, or the audio ends.
+    fn fill(&mut self, samples: usize) -> FFmpegResult<()> {
+        while !self.ended && self.pending[0].len() < samples {
+            let pending = &mut self.pending;
+            match self.audio.next_block(|planes| {
+                for (pending, plane) in pending.iter_mut().zip(planes) {
+                    pending.extend_from_slice(plane);
+                }
+            }) {
+                Ok(()) => {}
+                Err(ffmpeg::Error::Eof) => self.ended = true,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Measure the first `secs` seconds of the mix a track of `sources` would
+/// have, or return [None] if none of the sources have sound.
+pub fn measure_mix(sources: &[AudioSource], secs: f64) -> FFmpegResult<Option<LoudnessMeter>> {
+    let Some(mut mixer) = Mixer::open(sources)? else {
+        return Ok(None);
+    };
+    let mut meter = LoudnessMeter::new(MIX_CHANNELS, AUDIO_SAMPLE_RATE);
+    let mut remaining = (secs * AUDIO_SAMPLE_RATE as f64) as usize;
+    while remaining > 0 {
+        let samples = remaining.min(DEFAULT_FRAME_SIZE);
+        let Some(mixed) = mixer.mix(samples)? else {
+            break;
+        };
+        let planes: Vec<&[f32]> = mixed.iter().map(Vec::as_slice).collect();
+        meter.process(&planes);
+        remaining -= samples;
+    }
+    Ok(Some(meter))
+}
+
+/// Whether `output_context`'s container can hold audio encoded with `codec`.
+fn container_holds(output_context: &FFmpegOutputFormatContext, codec: ffmpeg::codec::Id) -> bool {
+    // There's no safe API for this.
+    unsafe {
+        ffmpeg::ffi::avformat_query_codec(
+            output_context.format().as_ptr(),
+            codec.into(),
+            STANDARD_COMPLIANCE,
+        ) == 1
+    }
+}
+
+const EAGAIN: ffmpeg::Error = ffmpeg::Error::Other {
+    errno: ffmpeg::error::EAGAIN,
+};
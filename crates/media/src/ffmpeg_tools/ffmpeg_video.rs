@@ -2,10 +2,14 @@
 
 mod seek_info;
 use seek_info::SeekInfo;
+pub(crate) use seek_info::hashed_path;
 
 mod inner;
 use inner::*;
 
+mod shared;
+pub use shared::SharedFFmpegVideo;
+
 use std::convert::Infallible;
 use std::fmt::{self, Debug};
 use std::num::NonZeroUsize;
@@ -21,7 +25,8 @@ use util::channels::request_channel::Request;
 
 use super::FFmpegResult;
 use crate::fps::Fps;
-use crate::frame::{Dimensions, FrameBuffer, Pixel, RescaleMethod};
+use crate::frame::color::{ColorInfo, ToneMapOperator};
+use crate::frame::{Dimensions, FrameBuffer, Pixel, RescaleMethod, Rotation};
 
 pub type FFmpegVideoFrame = ffmpeg::frame::Video;
 
@@ -56,6 +61,10 @@ impl FrameBuffer for FFmpegVideoFrame {
 /// [FFmpeg video frames](FFmpegVideoFrame) should be using.
 const TARGET_PIXEL_FORMAT: FFmpegPixelFormat = FFmpegPixelFormat::RGBA;
 
+/// The 16 bits per channel format HDR frames are reformatted to so that they
+/// can be tone mapped down to [TARGET_PIXEL_FORMAT] without banding.
+const WIDE_PIXEL_FORMAT: FFmpegPixelFormat = FFmpegPixelFormat::RGBA64LE;
+
 /// A video (courtesy for FFmpeg).
 ///
 /// If any method returns an error, the object should be discarded. Its behavior
@@ -82,9 +91,12 @@ impl FFmpegVideo {
     ///
     /// Before the request resolves, the `f` is called on the [FFmpegVideo] so
     /// that you can get a request that resolves to something else.
+    ///
+    /// `tone_map` is only used if the video is HDR (see [Self::src_color]).
     pub fn new_mapped<F, R>(
         path: &Path,
         rescale: Option<(Dimensions, RescaleMethod)>,
+        tone_map: ToneMapOperator,
         paused: bool,
         f: F,
     ) -> Request<R>
@@ -92,7 +104,7 @@ impl FFmpegVideo {
         F: Send + FnOnce(FFmpegResult<Self>) -> R + 'static,
         R: Send + 'static,
     {
-        let mut inner = match FFmpegVideoInner::new(path, rescale) {
+        let mut inner = match FFmpegVideoInner::new(path, rescale, tone_map) {
             Ok(inner) => inner,
             Err(e) => return f(Err(e)).into(),
         };
@@ -269,6 +281,36 @@ impl FFmpegVideo {
         self.inner.src_dimensions()
     }
 
+    /// How the frames in this video should be rotated to be displayed upright
+    /// (from its rotation metadata). Frames are *not* rotated for you.
+    #[inline(always)]
+    pub const fn src_rotation(&self) -> Rotation {
+        self.inner.src_rotation()
+    }
+
+    /// The color primaries and transfer function of this video. If it's HDR,
+    /// frames are tone mapped to SDR for you.
+    #[inline(always)]
+    pub const fn src_color(&self) -> ColorInfo {
+        self.inner.src_color()
+    }
+
+    /// Set how HDR frames are tone mapped to SDR. Does nothing if the video
+    /// isn't HDR.
+    pub fn set_tone_map_operator(&mut self, operator: ToneMapOperator) -> FFmpegResult<()> {
+        if !self.inner.set_tone_map_operator(operator) {
+            return Ok(());
+        }
+
+        // The frame we're holding onto (e.g. while paused) was mapped with the
+        // old operator so we need to decode it again.
+        if self.last_frame.take().is_some() {
+            self.last_keyframe_array_idx =
+                self.inner.seek_playhead(self.playhead, &self.seek_info)?;
+        }
+        Ok(())
+    }
+
     /// The dimensions of the frames that will be produced.
     #[inline(always)]
     pub const fn dest_dimensions(&self) -> Dimensions {
@@ -377,6 +419,7 @@ struct FrameScaler {
     #[cfg(debug_assertions)]
     src_dimensions_debug: Dimensions,
 
+    dest_format: FFmpegPixelFormat,
     dest_dimensions: Dimensions,
     rescale_method: RescaleMethod,
 }
@@ -388,6 +431,24 @@ impl FrameScaler {
         src_dimensions: Dimensions,
         dest_dimensions: Dimensions,
         rescale_method: RescaleMethod,
+    ) -> FFmpegResult<Self> {
+        Self::new_with_dest_format(
+            src_format,
+            src_dimensions,
+            TARGET_PIXEL_FORMAT,
+            dest_dimensions,
+            rescale_method,
+        )
+    }
+
+    /// Like [Self::new] but frames are reformatted to `dest_format` instead of
+    /// [TARGET_PIXEL_FORMAT].
+    pub fn new_with_dest_format(
+        src_format: FFmpegPixelFormat,
+        src_dimensions: Dimensions,
+        dest_format: FFmpegPixelFormat,
+        dest_dimensions: Dimensions,
+        rescale_method: RescaleMethod,
     ) -> FFmpegResult<Self> {
         let scaling_flags = if src_dimensions == dest_dimensions {
             FFmpegScalingFlags::empty()
@@ -405,7 +466,7 @@ impl FrameScaler {
             src_dimensions.width(),
             src_dimensions.height(),
             // Dest:
-            TARGET_PIXEL_FORMAT,
+            dest_format,
             dest_dimensions.width(),
             dest_dimensions.height(),
             // Rescale method:
@@ -420,6 +481,7 @@ impl FrameScaler {
             #[cfg(debug_assertions)]
             src_dimensions_debug: src_dimensions,
 
+            dest_format,
             dest_dimensions,
             rescale_method,
         })
@@ -454,7 +516,7 @@ impl FrameScaler {
             );
         }
 
-        debug_assert_eq!(dest.format(), TARGET_PIXEL_FORMAT);
+        debug_assert_eq!(dest.format(), self.dest_format);
         debug_assert_eq!(
             Dimensions::new(dest.width(), dest.height()),
             Some(self.dest_dimensions)
@@ -463,6 +525,11 @@ impl FrameScaler {
         self.scaler.run(src, dest)
     }
 
+    /// The pixel format this rescaler writes.
+    pub const fn dest_format(&self) -> FFmpegPixelFormat {
+        self.dest_format
+    }
+
     /// This rescaler's [Dimensions].
     pub const fn dest_dimensions(&self) -> Dimensions {
         self.dest_dimensions
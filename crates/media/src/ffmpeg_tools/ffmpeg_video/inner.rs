@@ -7,14 +7,21 @@ use std::path::Path;
 
 use ffmpeg::codec::Context as FFmpegCodecContext;
 use ffmpeg::codec::decoder::Video as FFmpegVideoDecoder;
+use ffmpeg::codec::packet::side_data::Type as FFmpegSideDataType;
 use ffmpeg::format::context::Input as FFmpegInputFormatContext;
+use ffmpeg::format::stream::Stream as FFmpegStream;
 use ffmpeg::media::Type as FFmpegMediaType;
+use ffmpeg::util::color::Primaries as FFmpegColorPrimaries;
+use ffmpeg::util::color::TransferCharacteristic as FFmpegTransferCharacteristic;
 use ffmpeg_next as ffmpeg;
 
-use super::{FFmpegResult, FFmpegVideoFrame, FrameScaler, TARGET_PIXEL_FORMAT};
+use super::{FFmpegResult, FFmpegVideoFrame, FrameScaler, TARGET_PIXEL_FORMAT, WIDE_PIXEL_FORMAT};
 use crate::ffmpeg_tools::ffmpeg_video::seek_info::SeekInfo;
 use crate::fps::Fps;
-use crate::frame::{Dimensions, RescaleMethod};
+use crate::frame::color::{
+    ColorInfo, ColorPrimaries, ToneMapOperator, ToneMapper, TransferFunction,
+};
+use crate::frame::{Dimensions, FrameBuffer, RescaleMethod, Rotation};
 
 /// A basic FFmpeg video stream that can write formatted and resized frames from
 /// a stream in order and can be seeked. See [FFmpegVideo](super::FFmpegVideo).
@@ -26,6 +33,10 @@ pub struct FFmpegVideoInner {
     src_frame_buffer: Option<FFmpegVideoFrame>,
     draining: bool,
 
+    // Tone Mapping (HDR only):
+    tone_mapper: Option<ToneMapper>,
+    wide_frame_buffer: Option<FFmpegVideoFrame>,
+
     // Seeking:
     frames_until_target: usize,
 
@@ -33,12 +44,18 @@ pub struct FFmpegVideoInner {
     target_stream_index: usize,
     src_fps: Fps,
     src_dimensions: Dimensions,
+    src_rotation: Rotation,
+    src_color: ColorInfo,
 }
 
 impl FFmpegVideoInner {
     /// Create an [FFmpegVideo](super::FFmpegVideo) with everything but the
     /// duration.
-    pub fn new(path: &Path, rescale: Option<(Dimensions, RescaleMethod)>) -> FFmpegResult<Self> {
+    pub fn new(
+        path: &Path,
+        rescale: Option<(Dimensions, RescaleMethod)>,
+        tone_map: ToneMapOperator,
+    ) -> FFmpegResult<Self> {
         // This object is a handle to the file we opened. Right now, this is
         // just the kind of container (e.g. MP4, MKV) and FFmpeg has none of the
         // actual video/audio data yet (only the file's metadata).
@@ -79,14 +96,35 @@ impl FFmpegVideoInner {
         let src_dimensions =
             Dimensions::new(decoder.width(), decoder.height()).ok_or(UNSUPPORTED_FORMAT)?;
 
+        // Phones record sideways and store how the video should be turned to
+        // be displayed upright instead of rotating the pixels.
+        let src_rotation = stream_rotation(&best_video_stream);
+
+        // HDR frames are always reformatted to a 16-bit intermediate so they
+        // can be tone mapped down to 8-bit.
+        let src_color = decoder_color(&decoder);
+        let tone_mapper = src_color
+            .is_hdr()
+            .then(|| ToneMapper::new(src_color, tone_map));
+
         let (dest_dimensions, rescale_method) =
             rescale.unwrap_or((src_dimensions, RescaleMethod::default()));
-        let scaler = FrameScaler::new_if_needed(
-            decoder.format(),
-            src_dimensions,
-            dest_dimensions,
-            rescale_method,
-        )?;
+        let scaler = if tone_mapper.is_some() {
+            Some(FrameScaler::new_with_dest_format(
+                decoder.format(),
+                src_dimensions,
+                WIDE_PIXEL_FORMAT,
+                dest_dimensions,
+                rescale_method,
+            )?)
+        } else {
+            FrameScaler::new_if_needed(
+                decoder.format(),
+                src_dimensions,
+                dest_dimensions,
+                rescale_method,
+            )?
+        };
 
         Ok(Self {
             // Frame Generation:
@@ -96,6 +134,10 @@ impl FFmpegVideoInner {
             src_frame_buffer: None,
             draining: false,
 
+            // Tone Mapping (HDR only):
+            tone_mapper,
+            wide_frame_buffer: None,
+
             // Seeking:
             frames_until_target: 0,
 
@@ -103,6 +145,8 @@ impl FFmpegVideoInner {
             target_stream_index,
             src_fps,
             src_dimensions,
+            src_rotation,
+            src_color,
         })
     }
 
@@ -200,15 +244,28 @@ impl FFmpegVideoInner {
             return Ok(());
         }
 
-        self.scaler = Some(FrameScaler::new(
+        self.scaler = Some(FrameScaler::new_with_dest_format(
             self.decoder.format(),
             self.src_dimensions,
+            self.scaler_dest_format(),
             new_dest_dimensions,
             new_rescale_method,
         )?);
         Ok(())
     }
 
+    /// See [FFmpegVideo::set_tone_map_operator](super::FFmpegVideo::set_tone_map_operator).
+    /// Returns whether frames will be mapped any differently.
+    pub fn set_tone_map_operator(&mut self, operator: ToneMapOperator) -> bool {
+        match &mut self.tone_mapper {
+            Some(tone_mapper) if tone_mapper.operator() != operator => {
+                *tone_mapper = ToneMapper::new(self.src_color, operator);
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Skips the next `n` frames.
     pub fn skip_frames(&mut self, n: usize) -> FFmpegResult<()> {
         for _ in 0..n {
@@ -237,6 +294,28 @@ impl FFmpegVideoInner {
         self.src_fps
     }
 
+    /// How this video's frames should be rotated to be displayed upright.
+    #[inline(always)]
+    pub const fn src_rotation(&self) -> Rotation {
+        self.src_rotation
+    }
+
+    /// The color primaries and transfer function of this video.
+    #[inline(always)]
+    pub const fn src_color(&self) -> ColorInfo {
+        self.src_color
+    }
+
+    /// The pixel format the scaler should write (HDR frames need to be tone
+    /// mapped after).
+    const fn scaler_dest_format(&self) -> ffmpeg::format::Pixel {
+        if self.tone_mapper.is_some() {
+            WIDE_PIXEL_FORMAT
+        } else {
+            TARGET_PIXEL_FORMAT
+        }
+    }
+
     fn skip_frame_with_stats(&mut self) -> FFmpegResult<FrameStats> {
         self.write_next_frame_in_stream(None, true)
             .map(|(frame, is_keyframe)| {
@@ -318,9 +397,30 @@ impl FFmpegVideoInner {
 
                         // We're going to return this frame. Reformat the
                         // intermediate frame onto the return frame (if we
-                        // didn't already write directly to it).
-                        if let Some(scaler) = &mut self.scaler {
-                            scaler.rescale(src_frame, ret_frame)?;
+                        // didn't already write directly to it). HDR frames
+                        // take a detour through a 16-bit frame first.
+                        match (&mut self.scaler, &self.tone_mapper) {
+                            (Some(scaler), Some(tone_mapper)) => {
+                                let dest_dimensions = scaler.dest_dimensions();
+                                if !self.wide_frame_buffer.as_ref().is_some_and(|wide_frame| {
+                                    wide_frame.dimensions() == dest_dimensions
+                                }) {
+                                    self.wide_frame_buffer = Some(FFmpegVideoFrame::new(
+                                        WIDE_PIXEL_FORMAT,
+                                        dest_dimensions.width(),
+                                        dest_dimensions.height(),
+                                    ));
+                                }
+                                let wide_frame = self
+                                    .wide_frame_buffer
+                                    .as_mut()
+                                    .expect("just created if missing");
+
+                                scaler.rescale(src_frame, wide_frame)?;
+                                tone_map_frame(tone_mapper, wide_frame, ret_frame);
+                            }
+                            (Some(scaler), None) => scaler.rescale(src_frame, ret_frame)?,
+                            (None, _) => {}
                         }
                     }
                     return Ok((ret_frame, frame_stats));
@@ -486,6 +586,97 @@ impl FFmpegVideoInner {
     }
 }
 
+/// Read the color primaries and transfer function from a decoder. Unspecified
+/// values are assumed to be SDR BT.709.
+fn decoder_color(decoder: &FFmpegVideoDecoder) -> ColorInfo {
+    let primaries = match decoder.color_primaries() {
+        FFmpegColorPrimaries::BT709 | FFmpegColorPrimaries::Unspecified => ColorPrimaries::Bt709,
+        FFmpegColorPrimaries::BT2020 => ColorPrimaries::Bt2020,
+        _ => ColorPrimaries::Other,
+    };
+
+    let transfer = match decoder.color_transfer_characteristic() {
+        FFmpegTransferCharacteristic::SMPTE2084 => TransferFunction::Pq,
+        FFmpegTransferCharacteristic::ARIB_STD_B67 => TransferFunction::Hlg,
+        _ => TransferFunction::Sdr,
+    };
+
+    ColorInfo {
+        primaries,
+        transfer,
+    }
+}
+
+/// Tone map `wide_frame` ([WIDE_PIXEL_FORMAT]) onto `dest_frame`
+/// ([TARGET_PIXEL_FORMAT]). Both frames must have the same dimensions.
+fn tone_map_frame(
+    tone_mapper: &ToneMapper,
+    wide_frame: &FFmpegVideoFrame,
+    dest_frame: &mut FFmpegVideoFrame,
+) {
+    debug_assert_eq!(wide_frame.format(), WIDE_PIXEL_FORMAT);
+    debug_assert_eq!(wide_frame.dimensions(), dest_frame.dimensions());
+
+    // Rows in the wide frame can be padded so we can't map it all at once.
+    let width = wide_frame.width() as usize;
+    let row_len = width * 4 * size_of::<u16>();
+    let stride = wide_frame.stride(0);
+    let src = wide_frame.data(0);
+
+    for (row, dest_row) in dest_frame.pixels_mut().chunks_exact_mut(width).enumerate() {
+        let row_start = row * stride;
+        tone_mapper.map_row(&src[row_start..row_start + row_len], dest_row);
+    }
+}
+
+/// Read the clockwise rotation a stream should be displayed with from its
+/// display matrix (or the older `rotate` metadata tag). Mirroring in the
+/// display matrix is ignored.
+fn stream_rotation(stream: &FFmpegStream) -> Rotation {
+    let display_matrix_degrees = stream
+        .side_data()
+        .find(|side_data| side_data.kind() == FFmpegSideDataType::DisplayMatrix)
+        .and_then(|side_data| display_matrix_rotation(side_data.data()));
+
+    let degrees = display_matrix_degrees.or_else(|| {
+        stream
+            .metadata()
+            .get("rotate")
+            .and_then(|rotate| rotate.trim().parse::<f64>().ok())
+    });
+
+    degrees.and_then(Rotation::from_degrees).unwrap_or_default()
+}
+
+/// The clockwise rotation (in degrees) stored in a display matrix, a 3x3
+/// matrix of native endian `i32`s. This mirrors FFmpeg's
+/// `av_display_rotation_get` (which returns the counter-clockwise angle).
+fn display_matrix_rotation(data: &[u8]) -> Option<f64> {
+    const MATRIX_LEN: usize = 9;
+
+    if data.len() < MATRIX_LEN * size_of::<i32>() {
+        return None;
+    }
+
+    let matrix: Vec<f64> = data
+        .chunks_exact(size_of::<i32>())
+        .take(MATRIX_LEN)
+        .map(|bytes| i32::from_ne_bytes(bytes.try_into().expect("chunks are 4 bytes")) as f64)
+        .collect();
+
+    let scale_x = matrix[0].hypot(matrix[3]);
+    let scale_y = matrix[1].hypot(matrix[4]);
+    if scale_x == 0.0 || scale_y == 0.0 {
+        return None;
+    }
+
+    Some(
+        (matrix[1] / scale_y)
+            .atan2(matrix[0] / scale_x)
+            .to_degrees(),
+    )
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct FrameStats {
     is_keyframe: bool,
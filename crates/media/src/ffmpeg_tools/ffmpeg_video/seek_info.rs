@@ -590,7 +590,7 @@ enum Lookup<H, M = ()> {
 
 /// Hashes a path into a 16 character ASCII string. See [hashed_bytes].
 #[inline]
-fn hashed_path(path: &Path) -> StrN<16> {
+pub(crate) fn hashed_path(path: &Path) -> StrN<16> {
     // NOTE: If the Rust version changes, the internal representation may
     // change here, invalidating all cache entries.
     let bytes = path.as_os_str().as_encoded_bytes();
@@ -0,0 +1,533 @@
+//! Exports [SharedFFmpegVideo].
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Debug};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+
+use ffmpeg_next as ffmpeg;
+
+use util::channels::request_channel::Request;
+
+use super::{
+    FFmpegResult, FFmpegVideo, FFmpegVideoFrame, FFmpegVideoInner, FrameScaler, TARGET_PIXEL_FORMAT,
+};
+use crate::fps::Fps;
+use crate::frame::color::{ColorInfo, ToneMapOperator};
+use crate::frame::{Dimensions, RescaleMethod, Rotation};
+
+/// How many frames a [SharedFFmpegVideo] decodes at once while it shares its
+/// decoder. Decoding a few frames at a time means consumers at different
+/// offsets make the decoder seek once every few frames instead of every frame.
+const READ_AHEAD: usize = 4;
+
+/// How many of the most recently decoded frames a shared decoder holds onto so
+/// consumers at (or near) the same offset don't decode them again.
+const DECODER_CACHE_LEN: usize = 8;
+
+/// How many more times a frame that failed to decode is read (seeking to it
+/// again first) before the decoder is considered broken.
+const READ_RETRIES: usize = 3;
+
+const LOCK_NOT_POISONED: &str = "The lock isn't poisoned.";
+
+/// The decoders that are open, so a video that's opened more than once can
+/// share one.
+static DECODERS: LazyLock<Mutex<HashMap<DecoderKey, Weak<Mutex<Decoder>>>>> =
+    LazyLock::new(Default::default);
+
+/// What decoders are shared by. HDR frames are tone mapped as they're decoded
+/// so consumers with different [ToneMapOperator]s can't share.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DecoderKey {
+    path: PathBuf,
+    tone_map: ToneMapOperator,
+}
+
+/// One [FFmpegVideo] decoding native frames for every [SharedFFmpegVideo] of
+/// the same video.
+struct Decoder {
+    key: DecoderKey,
+    /// Never paused and never rescaled.
+    video: FFmpegVideo,
+    /// The most recently decoded frames (and their indices), oldest first.
+    /// Only kept while the decoder is shared.
+    recent: VecDeque<(usize, FFmpegVideoFrame)>,
+    /// The error that broke the decoder, if one did. The [FFmpegVideo] can't be
+    /// used after it errors.
+    error: Option<ffmpeg::Error>,
+}
+
+impl Decoder {
+    /// The decoder for `key`, if one is open (and hasn't broken).
+    ///
+    /// [DECODERS] must never be locked while a decoder is.
+    fn find(key: &DecoderKey) -> Option<Arc<Mutex<Self>>> {
+        let decoders = DECODERS.lock().expect(LOCK_NOT_POISONED);
+        Self::find_locked(&decoders, key)
+    }
+
+    fn find_locked(
+        decoders: &HashMap<DecoderKey, Weak<Mutex<Self>>>,
+        key: &DecoderKey,
+    ) -> Option<Arc<Mutex<Self>>> {
+        let decoder = decoders.get(key).and_then(Weak::upgrade)?;
+        let broken = decoder.lock().expect(LOCK_NOT_POISONED).error.is_some();
+        (!broken).then_some(decoder)
+    }
+
+    /// Register a decoder for `key` (or return the one that was registered
+    /// first if there's a race).
+    fn register(key: DecoderKey, video: FFmpegVideo) -> Arc<Mutex<Self>> {
+        let mut decoders = DECODERS.lock().expect(LOCK_NOT_POISONED);
+        decoders.retain(|_, decoder| decoder.strong_count() > 0);
+
+        if let Some(decoder) = Self::find_locked(&decoders, &key) {
+            return decoder;
+        }
+
+        let decoder = Arc::new(Mutex::new(Self {
+            key: key.clone(),
+            video,
+            recent: VecDeque::with_capacity(DECODER_CACHE_LEN),
+            error: None,
+        }));
+        decoders.insert(key, Arc::downgrade(&decoder));
+        decoder
+    }
+
+    /// Decode the frames in `range`. Decoded frames are only remembered if
+    /// `shared`.
+    fn decode(
+        &mut self,
+        range: Range<usize>,
+        shared: bool,
+    ) -> FFmpegResult<Vec<(usize, FFmpegVideoFrame)>> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if !shared {
+            self.recent.clear();
+        }
+
+        let mut frames = Vec::with_capacity(range.len());
+        for frame_idx in range {
+            if let Some((_, frame)) = self.recent.iter().find(|(idx, _)| *idx == frame_idx) {
+                frames.push((frame_idx, frame.clone()));
+                continue;
+            }
+
+            let frame = self
+                .read_frame(frame_idx)
+                .inspect_err(|e| self.error = Some(*e))?;
+
+            if shared {
+                if self.recent.len() == DECODER_CACHE_LEN {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back((frame_idx, frame.clone()));
+            }
+            frames.push((frame_idx, frame));
+        }
+        Ok(frames)
+    }
+
+    /// Read the frame at `frame_idx`, trying again (up to [READ_RETRIES]
+    /// times) if it fails.
+    fn read_frame(&mut self, frame_idx: usize) -> FFmpegResult<FFmpegVideoFrame> {
+        let mut retries = 0;
+        loop {
+            let result = self
+                .video
+                .seek_playhead(frame_idx)
+                .and_then(|_| self.video.write_next(None));
+            match result {
+                Err(e) if retries < READ_RETRIES => {
+                    retries += 1;
+                    util::debug_log_warning!(
+                        "Failed to decode frame {frame_idx} of '{}' ({e}), retrying ({retries}/{READ_RETRIES})",
+                        self.key.path.display()
+                    );
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A video that shares its decoder with every other [SharedFFmpegVideo] of the
+/// same file, so a video that's played several times (e.g. at different
+/// offsets) is only decoded once. Each one has its own playhead and can be
+/// rescaled on its own. See [FFmpegVideo], which this mirrors.
+///
+/// If any method returns an error, the object should be discarded. Its behavior
+/// becomes undefined.
+pub struct SharedFFmpegVideo {
+    decoder: Arc<Mutex<Decoder>>,
+    tone_map: ToneMapOperator,
+    scaler: Option<FrameScaler>,
+    playhead: usize,
+    paused: bool,
+    /// The frame at the playhead while paused, already rescaled.
+    last_frame: Option<FFmpegVideoFrame>,
+    /// Frames decoded ahead of the playhead (and their indices) in order.
+    read_ahead: VecDeque<(usize, FFmpegVideoFrame)>,
+
+    // Src Info (Final):
+    duration: NonZeroUsize,
+    src_fps: Fps,
+    src_dimensions: Dimensions,
+    src_rotation: Rotation,
+    src_color: ColorInfo,
+}
+
+impl SharedFFmpegVideo {
+    /// Open a video file, sharing the decoder of another [SharedFFmpegVideo]
+    /// of it if there is one. See [FFmpegVideo::new_mapped].
+    pub fn new_mapped<F, R>(
+        path: &Path,
+        rescale: Option<(Dimensions, RescaleMethod)>,
+        tone_map: ToneMapOperator,
+        paused: bool,
+        f: F,
+    ) -> Request<R>
+    where
+        F: Send + FnOnce(FFmpegResult<Self>) -> R + 'static,
+        R: Send + 'static,
+    {
+        let key = DecoderKey {
+            path: path.to_path_buf(),
+            tone_map,
+        };
+        if let Some(decoder) = Decoder::find(&key) {
+            util::debug_log_info!("Sharing video decoder: {}", path.display());
+            return f(Self::from_decoder(decoder, rescale, paused)).into();
+        }
+
+        FFmpegVideo::new_mapped(path, None, tone_map, false, move |video| {
+            let video = video.and_then(|video| {
+                let decoder = Decoder::register(key, video);
+                Self::from_decoder(decoder, rescale, paused)
+            });
+            f(video)
+        })
+    }
+
+    /// Write the next frame. See [FFmpegVideo::write_next].
+    ///
+    /// Calling this function when [Self::playhead] is more than or equal to
+    /// [Self::duration] will result in the function panicking.
+    pub fn write_next(
+        &mut self,
+        recycled_frame: Option<FFmpegVideoFrame>,
+    ) -> FFmpegResult<FFmpegVideoFrame> {
+        assert!(
+            self.playhead < self.duration(),
+            "Can't play past video duration."
+        );
+
+        if !self.paused {
+            let frame = match self.last_frame.take() {
+                Some(last_frame) => last_frame,
+                None => self.frame_at_playhead(recycled_frame)?,
+            };
+            self.playhead += 1;
+            return Ok(frame);
+        }
+
+        if let Some(ref last_frame) = self.last_frame {
+            return Ok(last_frame.clone());
+        }
+        let new_frame = self.frame_at_playhead(recycled_frame)?;
+        self.last_frame = Some(new_frame.clone());
+        Ok(new_frame)
+    }
+
+    /// Seek to a frame index so that the next frame that will be written is
+    /// `new_playhead`. See [FFmpegVideo::seek_playhead].
+    ///
+    /// Calling this function with a `new_playhead` value greater than
+    /// [Self::duration] will result in the function panicking.
+    pub fn seek_playhead(&mut self, new_playhead: usize) -> FFmpegResult<()> {
+        assert!(
+            new_playhead <= self.duration(),
+            "Can't seek past video duration."
+        );
+
+        if new_playhead != self.playhead {
+            self.last_frame = None;
+            self.playhead = new_playhead;
+        }
+        Ok(())
+    }
+
+    /// The index of the next frame that will be written.
+    ///
+    /// The returned value will never be more than [Self::duration], but it can
+    /// equal it (in this case [Self::write_next] must not be called).
+    #[inline(always)]
+    pub const fn playhead(&self) -> usize {
+        self.playhead
+    }
+
+    /// The number of frames this video has.
+    ///
+    /// This value will never be 0. Also see [Self::duration_non_zero].
+    #[inline(always)]
+    pub const fn duration(&self) -> usize {
+        self.duration.get()
+    }
+
+    /// The number of frames this video has.
+    #[inline(always)]
+    pub const fn duration_non_zero(&self) -> NonZeroUsize {
+        self.duration
+    }
+
+    /// The intended (native) [Fps] playback speed of this video.
+    #[inline(always)]
+    pub const fn src_fps(&self) -> Fps {
+        self.src_fps
+    }
+
+    /// The intended (native) dimensions of the frames in this video.
+    #[inline(always)]
+    pub const fn src_dimensions(&self) -> Dimensions {
+        self.src_dimensions
+    }
+
+    /// How the frames in this video should be rotated to be displayed upright
+    /// (from its rotation metadata). Frames are *not* rotated for you.
+    #[inline(always)]
+    pub const fn src_rotation(&self) -> Rotation {
+        self.src_rotation
+    }
+
+    /// The color primaries and transfer function of this video. If it's HDR,
+    /// frames are tone mapped to SDR for you.
+    #[inline(always)]
+    pub const fn src_color(&self) -> ColorInfo {
+        self.src_color
+    }
+
+    /// Set how HDR frames are tone mapped to SDR. Does nothing if the video
+    /// isn't HDR.
+    ///
+    /// Frames are tone mapped as they're decoded, so this switches to a
+    /// decoder that tone maps with `operator` (opening one if needed).
+    pub fn set_tone_map_operator(&mut self, operator: ToneMapOperator) -> FFmpegResult<()> {
+        if operator == self.tone_map || !self.src_color.is_hdr() {
+            return Ok(());
+        }
+
+        let (path, seek_info) = {
+            let decoder = self.decoder.lock().expect(LOCK_NOT_POISONED);
+            (decoder.key.path.clone(), decoder.video.seek_info.clone())
+        };
+        let key = DecoderKey {
+            path,
+            tone_map: operator,
+        };
+        let decoder = match Decoder::find(&key) {
+            Some(decoder) => decoder,
+            None => {
+                // The seek info is the same no matter how frames are tone
+                // mapped, so it doesn't need to be figured out again.
+                let inner = FFmpegVideoInner::new(&key.path, None, operator)?;
+                Decoder::register(key, FFmpegVideo::from_parts(inner, false, seek_info))
+            }
+        };
+
+        self.decoder = decoder;
+        self.tone_map = operator;
+        self.last_frame = None;
+        self.read_ahead.clear();
+        Ok(())
+    }
+
+    /// Switch to a decoder that hasn't broken (opening a new one if needed),
+    /// keeping the playhead where it is. Meant for recovering after an error.
+    pub fn reopen(&mut self) -> FFmpegResult<()> {
+        let (key, seek_info) = {
+            let decoder = self.decoder.lock().expect(LOCK_NOT_POISONED);
+            (decoder.key.clone(), decoder.video.seek_info.clone())
+        };
+        // Broken decoders are never found, so this is either one another
+        // consumer already reopened or a fresh one.
+        let decoder = match Decoder::find(&key) {
+            Some(decoder) => decoder,
+            None => {
+                let inner = FFmpegVideoInner::new(&key.path, None, key.tone_map)?;
+                Decoder::register(key, FFmpegVideo::from_parts(inner, false, seek_info))
+            }
+        };
+
+        self.decoder = decoder;
+        self.last_frame = None;
+        self.read_ahead.clear();
+        Ok(())
+    }
+
+    /// The dimensions of the frames that will be produced.
+    #[inline(always)]
+    pub const fn dest_dimensions(&self) -> Dimensions {
+        match &self.scaler {
+            Some(scaler) => scaler.dest_dimensions(),
+            None => self.src_dimensions,
+        }
+    }
+
+    /// Sets whether or not the stream will be paused.
+    #[inline(always)]
+    pub const fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Whether or not the stream is paused.
+    #[inline(always)]
+    pub const fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Set how frames should be rescaled if needed.
+    ///
+    /// If `dest_dimensions` is the same as [Self::src_dimensions],
+    /// [Self::rescale_method] will return [None].
+    pub fn set_rescale(
+        &mut self,
+        dest_dimensions: Dimensions,
+        rescale_method: RescaleMethod,
+    ) -> FFmpegResult<()> {
+        self.scaler = Self::scaler(self.src_dimensions, Some((dest_dimensions, rescale_method)))?;
+        self.last_frame = None;
+        Ok(())
+    }
+
+    /// The [RescaleMethod] being used if [Self::src_dimensions] don't match
+    /// [Self::dest_dimensions].
+    #[inline(always)]
+    pub const fn rescale_method(&self) -> Option<RescaleMethod> {
+        match &self.scaler {
+            Some(scaler) => Some(scaler.rescale_method()),
+            None => None,
+        }
+    }
+
+    fn from_decoder(
+        decoder: Arc<Mutex<Decoder>>,
+        rescale: Option<(Dimensions, RescaleMethod)>,
+        paused: bool,
+    ) -> FFmpegResult<Self> {
+        let locked = decoder.lock().expect(LOCK_NOT_POISONED);
+        let tone_map = locked.key.tone_map;
+        let duration = locked.video.duration_non_zero();
+        let src_fps = locked.video.src_fps();
+        let src_dimensions = locked.video.src_dimensions();
+        let src_rotation = locked.video.src_rotation();
+        let src_color = locked.video.src_color();
+        drop(locked);
+
+        Ok(Self {
+            decoder,
+            tone_map,
+            scaler: Self::scaler(src_dimensions, rescale)?,
+            playhead: 0,
+            paused,
+            last_frame: None,
+            read_ahead: VecDeque::with_capacity(READ_AHEAD),
+            duration,
+            src_fps,
+            src_dimensions,
+            src_rotation,
+            src_color,
+        })
+    }
+
+    fn scaler(
+        src_dimensions: Dimensions,
+        rescale: Option<(Dimensions, RescaleMethod)>,
+    ) -> FFmpegResult<Option<FrameScaler>> {
+        let Some((dest_dimensions, rescale_method)) = rescale else {
+            return Ok(None);
+        };
+        if dest_dimensions == src_dimensions {
+            return Ok(None);
+        }
+        FrameScaler::new(
+            TARGET_PIXEL_FORMAT,
+            src_dimensions,
+            dest_dimensions,
+            rescale_method,
+        )
+        .map(Some)
+    }
+
+    /// The (rescaled) frame at the playhead, decoding it (and the next few
+    /// frames) if it hasn't been already.
+    fn frame_at_playhead(
+        &mut self,
+        recycled_frame: Option<FFmpegVideoFrame>,
+    ) -> FFmpegResult<FFmpegVideoFrame> {
+        while self
+            .read_ahead
+            .front()
+            .is_some_and(|(idx, _)| *idx < self.playhead)
+        {
+            self.read_ahead.pop_front();
+        }
+
+        if self
+            .read_ahead
+            .front()
+            .is_none_or(|(idx, _)| *idx != self.playhead)
+        {
+            // Only read ahead when other consumers might move the decoder's
+            // playhead out from under us.
+            let shared = Arc::strong_count(&self.decoder) > 1;
+            let batch_len = if shared { READ_AHEAD } else { 1 };
+            let range = self.playhead..(self.playhead + batch_len).min(self.duration());
+
+            let mut decoder = self.decoder.lock().expect(LOCK_NOT_POISONED);
+            self.read_ahead = decoder.decode(range, shared)?.into();
+        }
+
+        let (_, frame) = self
+            .read_ahead
+            .pop_front()
+            .expect("the frame at the playhead was just decoded");
+
+        let Some(scaler) = &mut self.scaler else {
+            return Ok(frame);
+        };
+        let dest_dimensions = scaler.dest_dimensions();
+        let mut dest_frame = match recycled_frame {
+            Some(recycled_frame)
+                if recycled_frame.format() == TARGET_PIXEL_FORMAT
+                    && recycled_frame.width() == dest_dimensions.width()
+                    && recycled_frame.height() == dest_dimensions.height() =>
+            {
+                recycled_frame
+            }
+            _ => FFmpegVideoFrame::new(
+                TARGET_PIXEL_FORMAT,
+                dest_dimensions.width(),
+                dest_dimensions.height(),
+            ),
+        };
+        scaler.rescale(&frame, &mut dest_frame)?;
+        Ok(dest_frame)
+    }
+}
+
+// The FFmpeg types don't implement `Debug` so we're doing it by hand.
+impl Debug for SharedFFmpegVideo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedFFmpegVideo")
+            .field("playhead", &self.playhead)
+            .field("paused", &self.paused)
+            .field("duration", &self.duration)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,676 @@
+//! Exports [FFmpegVideoEncoder].
+
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ffmpeg::Dictionary;
+use ffmpeg::Packet as FFmpegPacket;
+use ffmpeg::Rational;
+use ffmpeg::codec::Context as FFmpegCodecContext;
+use ffmpeg::codec::decoder::Video as FFmpegVideoDecoder;
+use ffmpeg::codec::encoder::video::Encoder as FFmpegOpenVideoEncoder;
+use ffmpeg::codec::encoder::video::Video as FFmpegVideoEncoderSetup;
+use ffmpeg::format::Pixel as FFmpegPixelFormat;
+use ffmpeg::format::context::Output as FFmpegOutputFormatContext;
+use ffmpeg::software::scaling::Context as FFmpegScalingContext;
+use ffmpeg::software::scaling::flag::Flags as FFmpegScalingFlags;
+use ffmpeg_next as ffmpeg;
+
+use super::FFmpegResult;
+use super::ffmpeg_audio_track::FFmpegAudioTrack;
+use super::ffmpeg_video::FFmpegVideoFrame;
+use crate::encoding::{
+    self, AudioSource, EncodingOptions, EncodingPass, EncodingSpeed, HardwareEncoder, RateControl,
+    VideoCodec, VideoContainer, VideoProbe,
+};
+use crate::fps::Fps;
+use crate::frame::{Dimensions, Frame};
+
+/// The format frames are handed to the encoder's scaler in.
+const SRC_PIXEL_FORMAT: FFmpegPixelFormat = FFmpegPixelFormat::RGBA;
+
+/// The format frames are handed to hardware encoders in, which every one of
+/// them takes.
+const HARDWARE_PIXEL_FORMAT: FFmpegPixelFormat = FFmpegPixelFormat::NV12;
+
+/// The highest (worst) value of x264's CRF scale, which hardware encoders'
+/// quality settings roughly follow.
+const MAX_H264_CRF: u32 = 51;
+
+/// The size of the video hardware encoders are opened for to check whether
+/// they work. Some refuse very small videos.
+const PROBE_SIZE: u32 = 256;
+
+/// Encodes frames into a video file with FFmpeg. See
+/// [VideoEncoder](crate::encoding::VideoEncoder).
+///
+/// If any method returns an error, the object should be discarded. The file
+/// it was writing is likely unplayable.
+pub struct FFmpegVideoEncoder {
+    /// [None] for the first pass of a two-pass encode, which writes no file.
+    output_context: Option<FFmpegOutputFormatContext>,
+    encoder: FFmpegOpenVideoEncoder,
+    scaler: FFmpegScalingContext,
+    src_frame: FFmpegVideoFrame,
+    /// The video's sound, if it has any. Always [None] without a file.
+    audio: Option<FFmpegAudioTrack>,
+
+    stream_index: usize,
+    encoder_time_base: Rational,
+    stream_time_base: Rational,
+    dimensions: Dimensions,
+    next_pts: i64,
+    pass: EncodingPass,
+    /// Where the passes of a two-pass encode keep the first's stats.
+    stats_path: PathBuf,
+}
+
+impl FFmpegVideoEncoder {
+    /// Create the file at `path` and get ready to encode `dimensions` frames
+    /// at `fps` into it. `alpha` keeps the frames' alpha channel, which
+    /// `codec` must [support](VideoCodec::supports_alpha). The video is
+    /// encoded with `hardware` if it's set, which must support `codec` and
+    /// can't be combined with `alpha` or a two-pass encode.
+    ///
+    /// `options` must be [valid](EncodingOptions::validate) for `codec`, and
+    /// `pass` must match them.
+    ///
+    /// The `audio` sources' sound is written alongside the frames (see
+    /// [AudioSource]). The first pass of a two-pass encode ignores it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        path: &Path,
+        container: VideoContainer,
+        codec: VideoCodec,
+        dimensions: Dimensions,
+        fps: Fps,
+        alpha: bool,
+        hardware: Option<HardwareEncoder>,
+        options: &EncodingOptions,
+        pass: EncodingPass,
+        audio: &[AudioSource],
+    ) -> FFmpegResult<Self> {
+        if hardware.is_some() && (alpha || pass != EncodingPass::Only) {
+            return Err(ffmpeg::Error::InvalidData);
+        }
+        let ffmpeg_codec = match hardware {
+            Some(hardware) => hardware
+                .ffmpeg_encoder_name(codec)
+                .and_then(ffmpeg::encoder::find_by_name),
+            None => match codec {
+                VideoCodec::H264 => ffmpeg::encoder::find(ffmpeg::codec::Id::H264),
+                VideoCodec::ProRes4444 => ffmpeg::encoder::find_by_name("prores_ks"),
+                VideoCodec::Vp9 => ffmpeg::encoder::find_by_name("libvpx-vp9"),
+            },
+        }
+        .ok_or(ffmpeg::Error::EncoderNotFound)?;
+
+        let dest_pixel_format = match (codec, alpha) {
+            _ if hardware.is_some() => HARDWARE_PIXEL_FORMAT,
+            (VideoCodec::H264, _) => FFmpegPixelFormat::YUV420P,
+            (VideoCodec::ProRes4444, true) => FFmpegPixelFormat::YUVA444P10LE,
+            (VideoCodec::ProRes4444, false) => FFmpegPixelFormat::YUV444P10LE,
+            (VideoCodec::Vp9, true) => FFmpegPixelFormat::YUVA420P,
+            (VideoCodec::Vp9, false) => FFmpegPixelFormat::YUV420P,
+        };
+
+        let frame_rate: Rational = fps.try_into().or(Err(ffmpeg::Error::InvalidData))?;
+        let encoder_time_base = frame_rate.invert();
+
+        // This is a handle to the file we're writing. Like with decoding, it's
+        // just the kind of container (e.g. MP4, MOV) until streams are added.
+        // The first pass of a two-pass encode only analyzes the frames, so it
+        // has no file.
+        let mut output_context = match pass {
+            EncodingPass::First => None,
+            EncodingPass::Only | EncodingPass::Second => Some(ffmpeg::format::output_as(
+                path,
+                container.ffmpeg_format_name(),
+            )?),
+        };
+        let global_header = output_context.as_ref().is_some_and(|output_context| {
+            output_context
+                .format()
+                .flags()
+                .contains(ffmpeg::format::Flags::GLOBAL_HEADER)
+        });
+
+        let mut encoder = FFmpegCodecContext::new_with_codec(ffmpeg_codec)
+            .encoder()
+            .video()?;
+        encoder.set_width(dimensions.width());
+        encoder.set_height(dimensions.height());
+        encoder.set_format(dest_pixel_format);
+        encoder.set_time_base(encoder_time_base);
+        encoder.set_frame_rate(Some(frame_rate));
+        let mut flags = ffmpeg::codec::Flags::empty();
+        if global_header {
+            flags |= ffmpeg::codec::Flags::GLOBAL_HEADER;
+        }
+        match pass {
+            EncodingPass::Only => {}
+            EncodingPass::First => flags |= ffmpeg::codec::Flags::PASS1,
+            EncodingPass::Second => flags |= ffmpeg::codec::Flags::PASS2,
+        }
+        encoder.set_flags(flags);
+        if let Some(keyframe_interval) = options.keyframe_interval {
+            encoder.set_gop(keyframe_interval);
+        }
+
+        let mut dictionary = Dictionary::new();
+        match hardware {
+            Some(hardware) => {
+                set_hardware_options(hardware, codec, options, &mut encoder, &mut dictionary)
+            }
+            None => set_software_options(codec, alpha, options, &mut encoder, &mut dictionary),
+        }
+        let stats_path = encoding::two_pass_stats_path(path);
+        // x264 reads and writes its stats file itself, and libvpx takes
+        // them from the codec context (see `take_stats` and `set_stats`).
+        if pass != EncodingPass::Only {
+            dictionary.set("stats", &stats_path.to_string_lossy());
+        }
+        if pass == EncodingPass::Second && codec == VideoCodec::Vp9 {
+            let stats = fs::read(&stats_path).map_err(|_| ffmpeg::Error::InvalidData)?;
+            set_stats(&mut encoder, &stats)?;
+        }
+        let mut encoder = encoder.open_as_with(ffmpeg_codec, dictionary);
+        if let Ok(encoder) = &mut encoder {
+            // The encoder copies the stats when it's opened.
+            clear_stats(encoder);
+        }
+        let encoder = encoder?;
+
+        let mut audio_track = None;
+        let (stream_index, stream_time_base) = match &mut output_context {
+            Some(output_context) => {
+                let stream_index = {
+                    let mut stream = output_context.add_stream(ffmpeg_codec)?;
+                    stream.set_parameters(&encoder);
+                    stream.set_time_base(encoder_time_base);
+                    stream.set_avg_frame_rate(frame_rate);
+                    stream.index()
+                };
+                audio_track =
+                    FFmpegAudioTrack::new(output_context, container, audio, global_header)?;
+
+                // Muxers may change the stream's time base when writing the
+                // header, so it has to be read after.
+                output_context.write_header()?;
+                if let Some(audio_track) = &mut audio_track {
+                    audio_track.header_written(output_context)?;
+                }
+                let stream_time_base = output_context
+                    .stream(stream_index)
+                    .ok_or(ffmpeg::Error::StreamNotFound)?
+                    .time_base();
+                (stream_index, stream_time_base)
+            }
+            None => (0, encoder_time_base),
+        };
+
+        let scaler = FFmpegScalingContext::get(
+            // Src:
+            SRC_PIXEL_FORMAT,
+            dimensions.width(),
+            dimensions.height(),
+            // Dest:
+            dest_pixel_format,
+            dimensions.width(),
+            dimensions.height(),
+            // Only the format changes, so this is just for chroma subsampling.
+            FFmpegScalingFlags::BILINEAR,
+        )?;
+
+        Ok(Self {
+            output_context,
+            encoder,
+            scaler,
+            src_frame: FFmpegVideoFrame::new(
+                SRC_PIXEL_FORMAT,
+                dimensions.width(),
+                dimensions.height(),
+            ),
+            audio: audio_track,
+
+            stream_index,
+            encoder_time_base,
+            stream_time_base,
+            dimensions,
+            next_pts: 0,
+            pass,
+            stats_path,
+        })
+    }
+
+    /// The dimensions every frame must have.
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    /// Encode `frame` as the next frame of the video.
+    pub fn write_frame(&mut self, frame: &Frame) -> FFmpegResult<()> {
+        if frame.dimensions() != self.dimensions {
+            return Err(ffmpeg::Error::InvalidData);
+        }
+
+        // FFmpeg frame rows may be padded, so they're copied one at a time.
+        let stride = self.src_frame.stride(0);
+        for (dest_row, src_row) in self
+            .src_frame
+            .data_mut(0)
+            .chunks_mut(stride)
+            .zip(frame.raw_data_rows())
+        {
+            dest_row[..src_row.len()].copy_from_slice(src_row);
+        }
+
+        // The encoder may hold onto the frames it's sent, so every frame gets
+        // a buffer of its own.
+        let mut dest_frame = FFmpegVideoFrame::empty();
+        self.scaler.run(&self.src_frame, &mut dest_frame)?;
+        dest_frame.set_pts(Some(self.next_pts));
+        self.next_pts += 1;
+
+        self.encoder.send_frame(&dest_frame)?;
+        self.write_packets()?;
+
+        // The sound is kept up with the frames so the muxer can interleave
+        // them.
+        let duration = self.duration();
+        if let (Some(audio), Some(output_context)) = (&mut self.audio, &mut self.output_context) {
+            audio.write_until(output_context, duration)?;
+        }
+        Ok(())
+    }
+
+    /// Encode any frames the encoder is still holding onto and finish the
+    /// file. The file isn't playable until this is called.
+    ///
+    /// The first pass of a two-pass encode saves its stats instead, and the
+    /// second removes them.
+    pub fn finish(mut self) -> FFmpegResult<()> {
+        self.encoder.send_eof()?;
+        self.write_packets()?;
+        match self.pass {
+            EncodingPass::Only => {}
+            EncodingPass::First => {
+                if let Some(stats) = take_stats(&self.encoder) {
+                    fs::write(&self.stats_path, stats).map_err(|_| ffmpeg::Error::InvalidData)?;
+                }
+            }
+            EncodingPass::Second => encoding::remove_two_pass_stats(&self.stats_path),
+        }
+        let duration = self.duration();
+        match &mut self.output_context {
+            Some(output_context) => {
+                if let Some(audio) = self.audio.take() {
+                    audio.finish(output_context, duration)?;
+                }
+                output_context.write_trailer()
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// How long the frames written so far last, in seconds.
+    fn duration(&self) -> f64 {
+        self.next_pts as f64 * f64::from(self.encoder_time_base)
+    }
+
+    /// Write every packet the encoder has ready to the file.
+    fn write_packets(&mut self) -> FFmpegResult<()> {
+        let mut packet = FFmpegPacket::empty();
+        loop {
+            match self.encoder.receive_packet(&mut packet) {
+                Ok(()) => {}
+                // `EAGAIN` means the encoder needs more frames for a packet.
+                Err(e) if e == EAGAIN || e == ffmpeg::Error::Eof => return Ok(()),
+                Err(e) => return Err(e),
+            }
+
+            let Some(output_context) = &mut self.output_context else {
+                continue;
+            };
+            packet.set_stream(self.stream_index);
+            packet.rescale_ts(self.encoder_time_base, self.stream_time_base);
+            packet.write_interleaved(output_context)?;
+        }
+    }
+}
+
+/// Set the options `codec`'s software encoder takes for `options`.
+fn set_software_options(
+    codec: VideoCodec,
+    alpha: bool,
+    options: &EncodingOptions,
+    encoder: &mut FFmpegVideoEncoderSetup,
+    dictionary: &mut Dictionary,
+) {
+    match codec {
+        VideoCodec::H264 => {
+            let preset = match options.speed.unwrap_or_default() {
+                EncodingSpeed::Fastest => "veryfast",
+                EncodingSpeed::Fast => "fast",
+                EncodingSpeed::Medium => "medium",
+                EncodingSpeed::Slow => "slow",
+                EncodingSpeed::Slowest => "veryslow",
+            };
+            dictionary.set("preset", preset);
+        }
+        VideoCodec::ProRes4444 => dictionary.set("profile", "4444"),
+        VideoCodec::Vp9 => {
+            dictionary.set("row-mt", "1");
+            // Alt-ref frames aren't supported with an alpha channel.
+            if alpha {
+                dictionary.set("auto-alt-ref", "0");
+            }
+            if let Some(speed) = options.speed {
+                let (deadline, cpu_used) = match speed {
+                    EncodingSpeed::Fastest => ("realtime", "8"),
+                    EncodingSpeed::Fast => ("good", "4"),
+                    EncodingSpeed::Medium => ("good", "2"),
+                    EncodingSpeed::Slow => ("good", "1"),
+                    EncodingSpeed::Slowest => ("good", "0"),
+                };
+                dictionary.set("deadline", deadline);
+                dictionary.set("cpu-used", cpu_used);
+            }
+        }
+    }
+
+    match options.rate_control {
+        Some(RateControl::Quality(quality)) => {
+            // libvpx only keeps the quality constant without a bit rate.
+            encoder.set_bit_rate(0);
+            dictionary.set("crf", &quality.to_string());
+        }
+        Some(RateControl::TwoPass { bit_rate }) => encoder.set_bit_rate(bit_rate as usize),
+        None => {}
+    }
+}
+
+/// Set the options `hardware` takes for `options` when it encodes `codec`.
+/// Every vendor has scales of its own, so quality and speed only roughly
+/// match the software encoders'.
+fn set_hardware_options(
+    hardware: HardwareEncoder,
+    codec: VideoCodec,
+    options: &EncodingOptions,
+    encoder: &mut FFmpegVideoEncoderSetup,
+    dictionary: &mut Dictionary,
+) {
+    // Hardware encoders' quality settings go about like x264's CRF, so the
+    // codec's quality is moved onto that scale.
+    let quality = match options.rate_control {
+        Some(RateControl::Quality(quality)) => Some(quality),
+        _ => codec.default_quality(),
+    };
+    let crf = match (quality, codec.quality_range()) {
+        (Some(quality), Some(range)) => quality as u32 * MAX_H264_CRF / *range.end() as u32,
+        _ => 23,
+    };
+    let speed = options.speed.unwrap_or_default();
+
+    match hardware {
+        HardwareEncoder::Nvenc => {
+            // Constant quality, with the bit rate only limited by that.
+            encoder.set_bit_rate(0);
+            dictionary.set("rc", "vbr");
+            dictionary.set("cq", &crf.to_string());
+            let preset = match speed {
+                EncodingSpeed::Fastest => "p1",
+                EncodingSpeed::Fast => "p3",
+                EncodingSpeed::Medium => "p4",
+                EncodingSpeed::Slow => "p5",
+                EncodingSpeed::Slowest => "p7",
+            };
+            dictionary.set("preset", preset);
+        }
+        HardwareEncoder::Amf => {
+            dictionary.set("rc", "cqp");
+            for frame_type in ["qp_i", "qp_p", "qp_b"] {
+                dictionary.set(frame_type, &crf.to_string());
+            }
+            let quality = match speed {
+                EncodingSpeed::Fastest | EncodingSpeed::Fast => "speed",
+                EncodingSpeed::Medium => "balanced",
+                EncodingSpeed::Slow | EncodingSpeed::Slowest => "quality",
+            };
+            dictionary.set("quality", quality);
+        }
+        HardwareEncoder::QuickSync => {
+            // Intelligent constant quality, which uses the same scale as
+            // x264.
+            encoder.set_global_quality(crf as i32);
+            let preset = match speed {
+                EncodingSpeed::Fastest => "veryfast",
+                EncodingSpeed::Fast => "faster",
+                EncodingSpeed::Medium => "medium",
+                EncodingSpeed::Slow => "slow",
+                EncodingSpeed::Slowest => "veryslow",
+            };
+            dictionary.set("preset", preset);
+        }
+        HardwareEncoder::VideoToolbox => {
+            // Quality goes from 0 to 100 (best) here, in "lambda" units.
+            // There's no speed setting.
+            const QP2LAMBDA: i32 = 118;
+            let quality = 100 - (crf * 100 / MAX_H264_CRF) as i32;
+            encoder.set_global_quality(quality * QP2LAMBDA);
+        }
+    }
+}
+
+/// Hand the `stats` a first pass saved to a second pass's `encoder`, for
+/// encoders that take them from the codec context (libvpx).
+fn set_stats(encoder: &mut FFmpegVideoEncoderSetup, stats: &[u8]) -> FFmpegResult<()> {
+    let stats = CString::new(stats).map_err(|_| ffmpeg::Error::InvalidData)?;
+    // FFmpeg may free the stats itself, so they're copied into memory it
+    // allocated. There's no safe API for this.
+    unsafe {
+        (*encoder.as_mut_ptr()).stats_in = ffmpeg::ffi::av_strdup(stats.as_ptr());
+    }
+    Ok(())
+}
+
+/// Free the stats [set_stats] handed to `encoder`, if it has any.
+fn clear_stats(encoder: &mut FFmpegOpenVideoEncoder) {
+    unsafe {
+        let context = encoder.as_mut_ptr();
+        if !(*context).stats_in.is_null() {
+            ffmpeg::ffi::av_freep(&raw mut (*context).stats_in as *mut std::ffi::c_void);
+        }
+    }
+}
+
+/// The stats a finished first pass left in its codec context, for encoders
+/// that don't write a stats file themselves (libvpx).
+fn take_stats(encoder: &FFmpegOpenVideoEncoder) -> Option<Vec<u8>> {
+    unsafe {
+        let stats = (*encoder.as_ptr()).stats_out;
+        (!stats.is_null()).then(|| CStr::from_ptr(stats).to_bytes().to_vec())
+    }
+}
+
+/// Whether `hardware` can encode `codec` on this machine: FFmpeg was built
+/// with its encoder, and the encoder opens (which fails without the GPU or
+/// driver it needs).
+pub fn hardware_encoder_works(hardware: HardwareEncoder, codec: VideoCodec) -> bool {
+    let Some(ffmpeg_codec) = hardware
+        .ffmpeg_encoder_name(codec)
+        .and_then(ffmpeg::encoder::find_by_name)
+    else {
+        return false;
+    };
+    let Ok(mut encoder) = FFmpegCodecContext::new_with_codec(ffmpeg_codec)
+        .encoder()
+        .video()
+    else {
+        return false;
+    };
+    encoder.set_width(PROBE_SIZE);
+    encoder.set_height(PROBE_SIZE);
+    encoder.set_format(HARDWARE_PIXEL_FORMAT);
+    encoder.set_time_base(Rational::new(1, 30));
+    encoder.set_frame_rate(Some(Rational::new(30, 1)));
+
+    let mut dictionary = Dictionary::new();
+    set_hardware_options(
+        hardware,
+        codec,
+        &EncodingOptions::default(),
+        &mut encoder,
+        &mut dictionary,
+    );
+    encoder.open_as_with(ffmpeg_codec, dictionary).is_ok()
+}
+
+/// Join `parts` (videos written by [FFmpegVideoEncoder] with the same
+/// settings) into one video at `path`, one after another. Packets are copied
+/// as they are, so nothing is encoded again.
+pub fn concatenate_videos(
+    parts: &[&Path],
+    path: &Path,
+    container: VideoContainer,
+) -> FFmpegResult<()> {
+    let mut output_context = ffmpeg::format::output_as(path, container.ffmpeg_format_name())?;
+    let mut output_time_base = None;
+    // Where the next part starts, in the output's time base.
+    let mut offset = 0;
+
+    for part in parts {
+        let mut input_context = ffmpeg::format::input(part)?;
+        let (input_index, input_time_base) = {
+            let stream = input_context
+                .streams()
+                .best(ffmpeg::media::Type::Video)
+                .ok_or(ffmpeg::Error::StreamNotFound)?;
+
+            if output_time_base.is_none() {
+                let mut output_stream =
+                    output_context.add_stream(ffmpeg::encoder::find(ffmpeg::codec::Id::None))?;
+                output_stream.set_parameters(stream.parameters());
+                // The tag is specific to the part's container, and the muxer
+                // picks one itself when it's unset. There's no safe API for
+                // this.
+                unsafe {
+                    (*output_stream.parameters().as_mut_ptr()).codec_tag = 0;
+                }
+            }
+            (stream.index(), stream.time_base())
+        };
+        let time_base = match output_time_base {
+            Some(time_base) => time_base,
+            None => {
+                output_context.write_header()?;
+                let time_base = output_context
+                    .stream(0)
+                    .ok_or(ffmpeg::Error::StreamNotFound)?
+                    .time_base();
+                output_time_base = Some(time_base);
+                time_base
+            }
+        };
+
+        // Each part starts from 0, so it's moved to where the last one ended.
+        let mut end = offset;
+        for (stream, mut packet) in input_context.packets() {
+            if stream.index() != input_index {
+                continue;
+            }
+            packet.rescale_ts(input_time_base, time_base);
+            packet.set_pts(packet.pts().map(|pts| pts + offset));
+            packet.set_dts(packet.dts().map(|dts| dts + offset));
+            if let Some(pts) = packet.pts() {
+                end = end.max(pts + packet.duration());
+            }
+            packet.set_position(-1);
+            packet.set_stream(0);
+            packet.write_interleaved(&mut output_context)?;
+        }
+        offset = end;
+    }
+
+    if output_time_base.is_none() {
+        return Err(ffmpeg::Error::InvalidData);
+    }
+    output_context.write_trailer()
+}
+
+/// Decode every frame of the video at `path`, noting what goes wrong. See
+/// [probe_video](crate::encoding::probe_video).
+pub fn probe_video(path: &Path) -> FFmpegResult<VideoProbe> {
+    let mut input_context = ffmpeg::format::input(path)?;
+    let (stream_index, parameters) = {
+        let stream = input_context
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or(ffmpeg::Error::StreamNotFound)?;
+        (stream.index(), stream.parameters())
+    };
+    let mut decoder = FFmpegCodecContext::from_parameters(parameters)?
+        .decoder()
+        .video()?;
+    let mut probe = VideoProbe {
+        frames: 0,
+        duration_secs: match input_context.duration() {
+            ffmpeg::ffi::AV_NOPTS_VALUE => 0.0,
+            duration => duration.max(0) as f64 / ffmpeg::ffi::AV_TIME_BASE as f64,
+        },
+        has_audio: input_context
+            .streams()
+            .best(ffmpeg::media::Type::Audio)
+            .is_some(),
+        errors: Vec::new(),
+    };
+
+    let mut frame = FFmpegVideoFrame::empty();
+    for (stream, packet) in input_context.packets() {
+        if stream.index() != stream_index {
+            continue;
+        }
+        if packet.is_corrupt() {
+            probe.errors.push(format!(
+                "the packet after frame {} is corrupt",
+                probe.frames
+            ));
+        }
+        if let Err(e) = decoder.send_packet(&packet) {
+            probe.errors.push(format!(
+                "the packet after frame {} failed to decode: {e}",
+                probe.frames
+            ));
+        }
+        receive_probed_frames(&mut decoder, &mut frame, &mut probe);
+    }
+    decoder.send_eof()?;
+    receive_probed_frames(&mut decoder, &mut frame, &mut probe);
+    Ok(probe)
+}
+
+/// Count the frames `decoder` has ready for [probe_video].
+fn receive_probed_frames(
+    decoder: &mut FFmpegVideoDecoder,
+    frame: &mut FFmpegVideoFrame,
+    probe: &mut VideoProbe,
+) {
+    loop {
+        match decoder.receive_frame(frame) {
+            Ok(()) => probe.frames += 1,
+            // `EAGAIN` means the decoder needs another packet for a frame.
+            Err(e) if e == EAGAIN || e == ffmpeg::Error::Eof => return,
+            Err(e) => {
+                probe
+                    .errors
+                    .push(format!("frame {} failed to decode: {e}", probe.frames + 1));
+                return;
+            }
+        }
+    }
+}
+
+const EAGAIN: ffmpeg::Error = ffmpeg::Error::Other {
+    errno: ffmpeg::error::EAGAIN,
+};
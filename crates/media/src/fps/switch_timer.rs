@@ -4,13 +4,33 @@ use std::time::{Duration, Instant};
 
 use super::Fps;
 
+/// How often a [SwitchTimer] switches frames when asked with
+/// [SwitchTimer::next_switch], independent of its
+/// [target FPS](SwitchTimer::target_fps).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PreviewRate {
+    /// Switch at the target FPS.
+    #[default]
+    Target,
+    /// Switch at this rate, skipping target frames to keep up. Rates at or
+    /// above the target FPS switch at the target FPS.
+    Limited(Fps),
+    /// Switch every time, moving one target frame each time no matter how
+    /// much time has passed (e.g. to see how fast frames can be made).
+    Unlimited,
+}
+
 /// A clock for tracking when it's time to switch to the next frame (given a
-/// target [Fps]). See [Self::is_switch_time].
+/// target [Fps]). See [Self::is_switch_time] and [Self::next_switch].
 #[derive(Debug)]
 pub struct SwitchTimer {
     target_fps: Fps,
+    preview_rate: PreviewRate,
     start_time: Option<Instant>,
     frame_idx: usize,
+    /// How many [PreviewRate::Limited] intervals had passed at the last
+    /// switch.
+    preview_idx: usize,
 }
 
 impl SwitchTimer {
@@ -18,8 +38,10 @@ impl SwitchTimer {
     pub fn new(target_fps: Fps) -> Self {
         Self {
             target_fps,
+            preview_rate: PreviewRate::Target,
             start_time: None,
             frame_idx: 0,
+            preview_idx: 0,
         }
     }
 
@@ -42,10 +64,8 @@ impl SwitchTimer {
             return true;
         };
 
-        let elapsed_nanos = now.duration_since(start_time).as_nanos();
-        let frames_elapsed = elapsed_nanos.saturating_mul(self.target_fps.num() as u128)
-            / ((self.target_fps.den() as u128) * 1_000_000_000u128);
-        let frame_intervals_since_start = frames_elapsed.min(usize::MAX as u128) as usize;
+        let frame_intervals_since_start =
+            intervals_elapsed(now.duration_since(start_time), self.target_fps);
 
         if frame_intervals_since_start > self.frame_idx {
             self.frame_idx += 1;
@@ -55,6 +75,43 @@ impl SwitchTimer {
         }
     }
 
+    /// Like [Self::is_switch_time], but switches at the
+    /// [preview rate](Self::preview_rate). Returns how many target frames the
+    /// timeline moved since the last switch (at least 1), or [None] if it isn't
+    /// time to switch yet.
+    ///
+    /// Unlike [Self::is_switch_time], a timer that gets behind at a
+    /// [PreviewRate::Limited] rate skips the switches it missed (adding their
+    /// frames to the next switch) so the timeline keeps moving in real time.
+    pub fn next_switch(&mut self) -> Option<usize> {
+        let preview_fps = match self.preview_rate {
+            PreviewRate::Limited(preview_fps) if preview_fps < self.target_fps => preview_fps,
+            PreviewRate::Target | PreviewRate::Limited(_) => {
+                return self.is_switch_time().then_some(1);
+            }
+            PreviewRate::Unlimited => return Some(1),
+        };
+
+        let now = Instant::now();
+        let Some(start_time) = self.start_time else {
+            self.start_time = Some(now);
+            return Some(1);
+        };
+
+        let elapsed = now.duration_since(start_time);
+        let preview_intervals = intervals_elapsed(elapsed, preview_fps);
+        if preview_intervals <= self.preview_idx {
+            return None;
+        }
+        self.preview_idx = preview_intervals;
+
+        let frames = intervals_elapsed(elapsed, self.target_fps)
+            .saturating_sub(self.frame_idx)
+            .max(1);
+        self.frame_idx += frames;
+        Some(frames)
+    }
+
     /// Returns how long until the next switch should happen.
     ///
     /// Returns [Duration::ZERO] if the timer has not started yet or if the
@@ -64,13 +121,21 @@ impl SwitchTimer {
             return Duration::ZERO;
         };
 
+        let (next_idx, fps) = match self.preview_rate {
+            PreviewRate::Limited(preview_fps) if preview_fps < self.target_fps => {
+                (self.preview_idx, preview_fps)
+            }
+            PreviewRate::Target | PreviewRate::Limited(_) => (self.frame_idx, self.target_fps),
+            PreviewRate::Unlimited => return Duration::ZERO,
+        };
+
         let now = Instant::now();
         let elapsed_nanos = now.duration_since(start_time).as_nanos();
-        let next_frame_idx = self.frame_idx.saturating_add(1) as u128;
-        let next_switch_nanos = next_frame_idx
-            .saturating_mul(self.target_fps.den() as u128)
+        let next_idx = next_idx.saturating_add(1) as u128;
+        let next_switch_nanos = next_idx
+            .saturating_mul(fps.den() as u128)
             .saturating_mul(1_000_000_000u128)
-            / (self.target_fps.num() as u128);
+            / (fps.num() as u128);
         let remaining_nanos = next_switch_nanos.saturating_sub(elapsed_nanos);
 
         let secs = (remaining_nanos / 1_000_000_000u128).min(u64::MAX as u128) as u64;
@@ -82,7 +147,10 @@ impl SwitchTimer {
     /// will *always* return `true` (as if the object had just been
     /// constructed).
     pub fn reset(&mut self) {
-        *self = Self::new(self.target_fps);
+        *self = Self {
+            preview_rate: self.preview_rate,
+            ..Self::new(self.target_fps)
+        };
     }
 
     /// The [Fps] this timer is targeting.
@@ -101,6 +169,30 @@ impl SwitchTimer {
             self.reset();
         }
     }
+
+    /// How often [Self::next_switch] switches.
+    ///
+    /// See [Self::set_preview_rate].
+    pub fn preview_rate(&self) -> PreviewRate {
+        self.preview_rate
+    }
+
+    /// Change the [preview rate](Self::preview_rate). If `new_preview_rate` is
+    /// *different* from the original one, the object will be reset (see
+    /// [Self::reset]).
+    pub fn set_preview_rate(&mut self, new_preview_rate: PreviewRate) {
+        if new_preview_rate != self.preview_rate {
+            self.preview_rate = new_preview_rate;
+            self.reset();
+        }
+    }
+}
+
+/// How many whole intervals of `fps` fit in `elapsed`.
+fn intervals_elapsed(elapsed: Duration, fps: Fps) -> usize {
+    let intervals = elapsed.as_nanos().saturating_mul(fps.num() as u128)
+        / ((fps.den() as u128) * 1_000_000_000u128);
+    intervals.min(usize::MAX as u128) as usize
 }
 
 #[cfg(test)]
@@ -215,6 +307,51 @@ mod tests {
         assert!(timer.is_switch_time());
     }
 
+    // --- next_switch decisions ---
+
+    #[test]
+    fn next_switch_at_limited_rate_skips_target_frames() {
+        let mut timer = SwitchTimer::new(consts::FPS_60);
+        timer.set_preview_rate(PreviewRate::Limited(consts::FPS_30));
+
+        assert_eq!(timer.next_switch(), Some(1));
+        assert_eq!(timer.next_switch(), None);
+
+        // at least one 30 FPS interval (two 60 FPS frames)
+        sleep(std::time::Duration::from_millis(40));
+
+        assert!(timer.next_switch().is_some_and(|frames| frames >= 2));
+    }
+
+    #[test]
+    fn next_switch_above_target_rate_switches_at_target_rate() {
+        let mut timer = SwitchTimer::new(consts::FPS_30);
+        timer.set_preview_rate(PreviewRate::Limited(consts::FPS_120));
+
+        assert_eq!(timer.next_switch(), Some(1));
+        assert_eq!(timer.next_switch(), None);
+    }
+
+    #[test]
+    fn next_switch_unlimited_always_switches() {
+        let mut timer = SwitchTimer::new(consts::FPS_60);
+        timer.set_preview_rate(PreviewRate::Unlimited);
+
+        assert_eq!(timer.next_switch(), Some(1));
+        assert_eq!(timer.next_switch(), Some(1));
+        assert_eq!(timer.time_until_next_switch(), Duration::ZERO);
+    }
+
+    #[test]
+    fn reset_keeps_preview_rate() {
+        let mut timer = SwitchTimer::new(consts::FPS_60);
+        timer.set_preview_rate(PreviewRate::Unlimited);
+
+        timer.reset();
+
+        assert_eq!(timer.preview_rate(), PreviewRate::Unlimited);
+    }
+
     // --- target_fps getter ---
 
     #[test]
@@ -1,6 +1,8 @@
 //! This module exports everything that has to do with image/video [Frame]s and
 //! [streams] of them.
 
+pub mod color;
+pub mod stabilization;
 pub mod streams;
 
 mod buffer;
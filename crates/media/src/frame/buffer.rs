@@ -9,8 +9,10 @@
 //! comments explaining things). If you're going to modify this module (or its
 //! sub-modules), be *extremely* careful.
 
+mod conform;
 mod dimensions;
 mod pixel;
+mod rotation;
 mod uid;
 
 use std::any::Any;
@@ -29,8 +31,10 @@ use thiserror::Error;
 
 use util::cast_slice;
 
+pub use conform::*;
 pub use dimensions::*;
 pub use pixel::*;
+pub use rotation::*;
 pub use uid::*;
 
 /// A buffer of data representing all of the [Pixel]s in a frame, along with the
@@ -412,6 +416,131 @@ impl Frame {
         }
     }
 
+    /// Make this frame have `new_dimensions` following `conform_policy`
+    /// (fitting, filling, stretching, or centering it). The frame is always
+    /// centered, and any area it doesn't cover is filled with [Pixel::BLACK].
+    ///
+    /// If this frame already has `new_dimensions` this is the same as
+    /// [Self::clone].
+    pub fn conform(
+        &self,
+        new_dimensions: Dimensions,
+        conform_policy: ConformPolicy,
+        rescale_method: RescaleMethod,
+    ) -> Self {
+        if self.dimensions() == new_dimensions {
+            return self.clone();
+        }
+
+        let scaled_dimensions = conform_policy.scaled_dimensions(self.dimensions(), new_dimensions);
+        let rescaled;
+        let scaled = if scaled_dimensions == self.dimensions() {
+            self
+        } else {
+            rescaled = self.rescale(scaled_dimensions, rescale_method);
+            &rescaled
+        };
+        if scaled_dimensions == new_dimensions {
+            return scaled.clone();
+        }
+
+        // Negative offsets crop, positive offsets pad.
+        let left = (new_dimensions.width() as isize - scaled_dimensions.width() as isize) / 2;
+        let top = (new_dimensions.height() as isize - scaled_dimensions.height() as isize) / 2;
+        let scaled_width = scaled_dimensions.width() as isize;
+        let scaled_height = scaled_dimensions.height() as isize;
+        let scaled_pixels = scaled.pixels();
+
+        Self::from_fill_with_coords(new_dimensions, |row, col| {
+            let row = row as isize - top;
+            let col = col as isize - left;
+            if (0..scaled_height).contains(&row) && (0..scaled_width).contains(&col) {
+                scaled_pixels[(row * scaled_width + col) as usize]
+            } else {
+                Pixel::BLACK
+            }
+        })
+    }
+
+    /// Rescale this frame to fit inside of `new_dimensions` without changing
+    /// its aspect ratio, filling the rest of the frame with black bars
+    /// (letterboxing or pillarboxing). Shorthand for [Self::conform] with
+    /// [ConformPolicy::Fit].
+    pub fn letterbox(&self, new_dimensions: Dimensions, rescale_method: RescaleMethod) -> Self {
+        self.conform(new_dimensions, ConformPolicy::Fit, rescale_method)
+    }
+
+    /// Rotate this frame clockwise by a multiple of 90 degrees. The width and
+    /// height are swapped for quarter turns. No resampling is needed so this
+    /// is lossless.
+    ///
+    /// For [Rotation::None] this is the same as [Self::clone].
+    pub fn rotate(&self, rotation: Rotation) -> Self {
+        if rotation == Rotation::None {
+            return self.clone();
+        }
+
+        let src_width = self.dimensions().width() as usize;
+        let src_height = self.dimensions().height() as usize;
+        let src_pixels = self.pixels();
+
+        Self::from_fill_with_coords(rotation.rotate_dimensions(self.dimensions()), |row, col| {
+            let (src_row, src_col) = match rotation {
+                Rotation::None => (row, col),
+                Rotation::Clockwise90 => (src_height - 1 - col, row),
+                Rotation::Clockwise180 => (src_height - 1 - row, src_width - 1 - col),
+                Rotation::Clockwise270 => (col, src_width - 1 - row),
+            };
+            src_pixels[src_row * src_width + src_col]
+        })
+    }
+
+    /// Copy the part of this frame that's `dimensions` in size with its top
+    /// left corner at (`left`, `top`). Any of it that's outside of this frame
+    /// is filled with [Pixel::BLACK].
+    pub fn crop(&self, left: u32, top: u32, dimensions: Dimensions) -> Self {
+        let src_width = self.dimensions().width() as usize;
+        let src_height = self.dimensions().height() as usize;
+        let src_pixels = self.pixels();
+
+        Self::from_fill_with_coords(dimensions, |row, col| {
+            let src_row = row + top as usize;
+            let src_col = col + left as usize;
+            if src_row < src_height && src_col < src_width {
+                src_pixels[src_row * src_width + src_col]
+            } else {
+                Pixel::BLACK
+            }
+        })
+    }
+
+    /// Blend this frame with `other`, `amount` of the way towards `other`
+    /// (`0.0` is this frame and `1.0` is `other`). `other` is sampled at the
+    /// same normalized coordinates, so the frames don't need to be the same
+    /// size; the result has this frame's dimensions.
+    pub fn mix(&self, other: &Frame, amount: f64) -> Self {
+        let amount = amount.clamp(0.0, 1.0);
+        let width = self.dimensions().width() as usize;
+        let height = self.dimensions().height() as usize;
+        let other_width = other.dimensions().width() as usize;
+        let other_height = other.dimensions().height() as usize;
+        let pixels = self.pixels();
+        let other_pixels = other.pixels();
+
+        Self::from_fill_with_coords(self.dimensions(), |row, col| {
+            let a = pixels[row * width + col];
+            let b = other_pixels
+                [(row * other_height / height) * other_width + col * other_width / width];
+            let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * amount).round() as u8;
+            Pixel::from_rgba(
+                mix(a.red(), b.red()),
+                mix(a.green(), b.green()),
+                mix(a.blue(), b.blue()),
+                mix(a.alpha(), b.alpha()),
+            )
+        })
+    }
+
     /// Rescale this [Frame] to have new [Dimensions] using the
     /// [nearest neighbor](RescaleMethod::NearestNeighbor) rescaling algorithm.
     ///
@@ -971,4 +1100,117 @@ mod tests {
         let good_length_pixels = vec![Pixel::WHITE; 4].into_boxed_slice();
         assert!(Frame::from_pixels(good_length_pixels, Dimensions::new(2, 2).unwrap()).is_ok());
     }
+
+    #[test]
+    fn letterbox_centers_with_black_bars() {
+        let frame = Frame::from_fill(Dimensions::new(2, 1).unwrap(), Pixel::WHITE);
+        let boxed = frame.letterbox(Dimensions::new(2, 3).unwrap(), RescaleMethod::Bilinear);
+
+        assert_eq!(boxed.dimensions(), Dimensions::new(2, 3).unwrap());
+        assert_eq!(boxed[0], [Pixel::BLACK, Pixel::BLACK]);
+        assert_eq!(boxed[1], [Pixel::WHITE, Pixel::WHITE]);
+        assert_eq!(boxed[2], [Pixel::BLACK, Pixel::BLACK]);
+    }
+
+    #[test]
+    fn letterbox_same_aspect_ratio_only_rescales() {
+        let frame = Frame::from_fill(Dimensions::new(4, 2).unwrap(), Pixel::WHITE);
+        let boxed = frame.letterbox(Dimensions::new(2, 1).unwrap(), RescaleMethod::Bilinear);
+
+        assert_eq!(boxed.dimensions(), Dimensions::new(2, 1).unwrap());
+        assert!(boxed.pixels().iter().all(|p| *p == Pixel::WHITE));
+    }
+
+    #[test]
+    fn conform_fill_crops_overhang() {
+        // 3 wide (black, white, black) into 1x1 → only the center column stays
+        let frame = Frame::from_fill_with_coords(Dimensions::new(3, 1).unwrap(), |_, col| {
+            if col == 1 { Pixel::WHITE } else { Pixel::BLACK }
+        });
+        let filled = frame.conform(
+            Dimensions::new(1, 1).unwrap(),
+            ConformPolicy::Fill,
+            RescaleMethod::NearestNeighbor,
+        );
+
+        assert_eq!(filled.dimensions(), Dimensions::new(1, 1).unwrap());
+        assert_eq!(filled[0], [Pixel::WHITE]);
+    }
+
+    #[test]
+    fn conform_stretch_ignores_aspect_ratio() {
+        let frame = Frame::from_fill(Dimensions::new(4, 1).unwrap(), Pixel::WHITE);
+        let stretched = frame.conform(
+            Dimensions::new(2, 2).unwrap(),
+            ConformPolicy::Stretch,
+            RescaleMethod::Bilinear,
+        );
+
+        assert_eq!(stretched.dimensions(), Dimensions::new(2, 2).unwrap());
+        assert!(stretched.pixels().iter().all(|p| *p == Pixel::WHITE));
+    }
+
+    #[test]
+    fn conform_center_pads_without_scaling() {
+        let frame = Frame::from_fill(Dimensions::new(1, 1).unwrap(), Pixel::WHITE);
+        let centered = frame.conform(
+            Dimensions::new(3, 3).unwrap(),
+            ConformPolicy::Center,
+            RescaleMethod::Bilinear,
+        );
+
+        assert_eq!(centered[0], [Pixel::BLACK; 3]);
+        assert_eq!(centered[1], [Pixel::BLACK, Pixel::WHITE, Pixel::BLACK]);
+        assert_eq!(centered[2], [Pixel::BLACK; 3]);
+    }
+
+    #[test]
+    fn rotate_quarter_turns() {
+        // 2x1: red on the left, blue on the right
+        let red = Pixel::from_rgba(255, 0, 0, 255);
+        let blue = Pixel::from_rgba(0, 0, 255, 255);
+        let frame = Frame::from_fill_with_coords(Dimensions::new(2, 1).unwrap(), |_, col| {
+            if col == 0 { red } else { blue }
+        });
+
+        let clockwise = frame.rotate(Rotation::Clockwise90);
+        assert_eq!(clockwise.dimensions(), Dimensions::new(1, 2).unwrap());
+        assert_eq!(clockwise[0], [red]);
+        assert_eq!(clockwise[1], [blue]);
+
+        let upside_down = frame.rotate(Rotation::Clockwise180);
+        assert_eq!(upside_down[0], [blue, red]);
+
+        let counter_clockwise = frame.rotate(Rotation::Clockwise270);
+        assert_eq!(counter_clockwise[0], [blue]);
+        assert_eq!(counter_clockwise[1], [red]);
+    }
+
+    #[test]
+    fn crop_pads_outside_the_frame() {
+        let frame = Frame::from_fill_with_coords(Dimensions::new(3, 2).unwrap(), |row, col| {
+            Pixel::from_rgba((row * 3 + col) as u8, 0, 0, 255)
+        });
+
+        let cropped = frame.crop(1, 1, Dimensions::new(3, 1).unwrap());
+        assert_eq!(
+            cropped[0],
+            [
+                Pixel::from_rgba(4, 0, 0, 255),
+                Pixel::from_rgba(5, 0, 0, 255),
+                Pixel::BLACK
+            ]
+        );
+    }
+
+    #[test]
+    fn mix_blends_towards_the_other_frame() {
+        let black = Frame::from_fill(Dimensions::new(2, 2).unwrap(), Pixel::BLACK);
+        let white = Frame::from_fill(Dimensions::new(1, 1).unwrap(), Pixel::BRIGHT_WHITE);
+
+        let mixed = black.mix(&white, 0.5);
+        assert_eq!(mixed.dimensions(), black.dimensions());
+        assert_eq!(mixed[1][1], Pixel::from_rgba(128, 128, 128, 255));
+        assert_eq!(black.mix(&white, 0.0)[0][0], Pixel::BLACK);
+    }
 }
@@ -0,0 +1,97 @@
+//! Declares the [ConformPolicy] type, used by [super::Frame::conform].
+
+use super::Dimensions;
+
+/// How a frame is made to fit dimensions with a different aspect ratio (or
+/// size) than its own. Also see [Frame::conform](super::Frame::conform).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConformPolicy {
+    /// Scale to fit entirely inside the new dimensions, filling the rest with
+    /// black bars (letterboxing or pillarboxing).
+    #[default]
+    Fit,
+    /// Scale to cover the new dimensions, cropping whatever hangs over.
+    Fill,
+    /// Scale each side independently to match exactly, distorting the frame
+    /// if the aspect ratio differs.
+    Stretch,
+    /// Don't scale at all (1:1 pixels). Centered, then cropped or padded with
+    /// black bars.
+    Center,
+}
+
+impl ConformPolicy {
+    /// Every policy, in declaration order.
+    pub const ALL: [Self; 4] = [Self::Fit, Self::Fill, Self::Stretch, Self::Center];
+
+    /// A short, printable name for this policy.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Fit => "Fit",
+            Self::Fill => "Fill",
+            Self::Stretch => "Stretch",
+            Self::Center => "Center 1:1",
+        }
+    }
+
+    /// The dimensions a frame with `source` dimensions is rescaled to before
+    /// it's centered inside of (and cropped or padded to) `target`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use media::frame::{ConformPolicy, Dimensions};
+    ///
+    /// let source: Dimensions = (3840, 2160).into();
+    /// let target: Dimensions = (1080, 1080).into();
+    /// assert_eq!(ConformPolicy::Fit.scaled_dimensions(source, target), (1080, 608).into());
+    /// assert_eq!(ConformPolicy::Fill.scaled_dimensions(source, target), (1920, 1080).into());
+    /// ```
+    pub fn scaled_dimensions(&self, source: Dimensions, target: Dimensions) -> Dimensions {
+        match self {
+            Self::Fit => source.fit_within(target),
+            Self::Fill => source.cover(target),
+            Self::Stretch => target,
+            Self::Center => source,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- scaled_dimensions() ---
+
+    #[test]
+    fn test_scaled_dimensions_stretch_matches_target() {
+        let source: Dimensions = (1280, 720).into();
+        let target: Dimensions = (1080, 1920).into();
+        assert_eq!(
+            ConformPolicy::Stretch.scaled_dimensions(source, target),
+            target
+        );
+    }
+
+    #[test]
+    fn test_scaled_dimensions_center_keeps_source() {
+        let source: Dimensions = (1280, 720).into();
+        let target: Dimensions = (1920, 1080).into();
+        assert_eq!(
+            ConformPolicy::Center.scaled_dimensions(source, target),
+            source
+        );
+    }
+
+    #[test]
+    fn test_scaled_dimensions_same_aspect_ratio() {
+        // Fit and Fill agree when there's nothing to crop or pad
+        let source: Dimensions = (1280, 720).into();
+        let target: Dimensions = (1920, 1080).into();
+        assert_eq!(ConformPolicy::Fit.scaled_dimensions(source, target), target);
+        assert_eq!(
+            ConformPolicy::Fill.scaled_dimensions(source, target),
+            target
+        );
+    }
+}
@@ -207,6 +207,50 @@ impl Dimensions {
                 .max(1);
         Self::new(new_width, new_height)
     }
+
+    /// The largest dimensions with (roughly) the same aspect ratio as `self`
+    /// that fit inside of `bounds`. Both sides are at least `1`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use media::frame::Dimensions;
+    ///
+    /// let d: Dimensions = (3840, 2160).into();
+    /// assert_eq!(d.fit_within((1080, 1080).into()), (1080, 608).into());
+    /// ```
+    pub fn fit_within(&self, bounds: Dimensions) -> Self {
+        let scale = (bounds.width() as f64 / self.width() as f64)
+            .min(bounds.height() as f64 / self.height() as f64);
+
+        let width = ((self.width() as f64 * scale).round() as u32).clamp(1, bounds.width());
+        let height = ((self.height() as f64 * scale).round() as u32).clamp(1, bounds.height());
+
+        // SAFETY: Both sides were clamped to be at least 1.
+        unsafe { Self::new_unchecked(width, height) }
+    }
+
+    /// The smallest dimensions with (roughly) the same aspect ratio as `self`
+    /// that completely cover `bounds`. Also see [Self::fit_within].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use media::frame::Dimensions;
+    ///
+    /// let d: Dimensions = (3840, 2160).into();
+    /// assert_eq!(d.cover((1080, 1080).into()), (1920, 1080).into());
+    /// ```
+    pub fn cover(&self, bounds: Dimensions) -> Self {
+        let scale = (bounds.width() as f64 / self.width() as f64)
+            .max(bounds.height() as f64 / self.height() as f64);
+
+        let width = ((self.width() as f64 * scale).round() as u32).max(bounds.width());
+        let height = ((self.height() as f64 * scale).round() as u32).max(bounds.height());
+
+        // SAFETY: `bounds` has non-zero sides so both sides are at least 1.
+        unsafe { Self::new_unchecked(width, height) }
+    }
 }
 
 /// Ordering depends on [area](Self::area).
@@ -286,6 +330,34 @@ mod tests {
         assert!(Dimensions::new(1920, 0).is_none());
     }
 
+    // --- fit_within() ---
+
+    #[test]
+    fn test_fit_within_wider_source() {
+        // Width is the limiting side → letterboxed top and bottom
+        let d: Dimensions = (3840, 2160).into();
+        assert_eq!(d.fit_within((1920, 1920).into()), (1920, 1080).into());
+    }
+
+    #[test]
+    fn test_fit_within_taller_source() {
+        // Height is the limiting side → pillarboxed left and right
+        let d: Dimensions = (720, 1280).into();
+        assert_eq!(d.fit_within((1920, 1080).into()), (608, 1080).into());
+    }
+
+    #[test]
+    fn test_fit_within_upscales() {
+        let d: Dimensions = (1280, 720).into();
+        assert_eq!(d.fit_within((1920, 1080).into()), (1920, 1080).into());
+    }
+
+    #[test]
+    fn test_fit_within_never_zero() {
+        let d: Dimensions = (10000, 1).into();
+        assert_eq!(d.fit_within((10, 10).into()), (10, 1).into());
+    }
+
     // --- rescale_height() ---
 
     #[test]
@@ -367,4 +439,26 @@ mod tests {
         let d: Dimensions = (1920, 1080).into();
         assert!(d.rescale_width_rounded(0).is_none());
     }
+
+    // --- cover() ---
+
+    #[test]
+    fn test_cover_wider_source() {
+        // Height is the limiting side → the sides get cropped
+        let d: Dimensions = (3840, 2160).into();
+        assert_eq!(d.cover((1080, 1080).into()), (1920, 1080).into());
+    }
+
+    #[test]
+    fn test_cover_taller_source() {
+        // Width is the limiting side → the top and bottom get cropped
+        let d: Dimensions = (720, 1280).into();
+        assert_eq!(d.cover((1920, 1080).into()), (1920, 3413).into());
+    }
+
+    #[test]
+    fn test_cover_same_aspect_ratio() {
+        let d: Dimensions = (1280, 720).into();
+        assert_eq!(d.cover((1920, 1080).into()), (1920, 1080).into());
+    }
 }
@@ -0,0 +1,115 @@
+//! Declares the [Rotation] type, used by [super::Frame::rotate].
+
+use super::Dimensions;
+
+/// A clockwise rotation by a multiple of 90 degrees. Also see
+/// [Frame::rotate](super::Frame::rotate).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl Rotation {
+    /// Every rotation, in declaration order.
+    pub const ALL: [Self; 4] = [
+        Self::None,
+        Self::Clockwise90,
+        Self::Clockwise180,
+        Self::Clockwise270,
+    ];
+
+    /// The rotation closest to `degrees` clockwise. Any angle is accepted
+    /// (e.g. `-90.0` is the same as `270.0`). [None] is returned if `degrees`
+    /// isn't finite.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use media::frame::Rotation;
+    ///
+    /// assert_eq!(Rotation::from_degrees(-90.0), Some(Rotation::Clockwise270));
+    /// assert_eq!(Rotation::from_degrees(179.9), Some(Rotation::Clockwise180));
+    /// ```
+    pub fn from_degrees(degrees: f64) -> Option<Self> {
+        if !degrees.is_finite() {
+            return None;
+        }
+
+        let quarter_turns = (degrees / 90.0).round().rem_euclid(4.0) as usize;
+        Some(Self::ALL[quarter_turns])
+    }
+
+    /// The clockwise angle of this rotation in degrees (`0`, `90`, `180`, or
+    /// `270`).
+    pub const fn degrees(&self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Clockwise90 => 90,
+            Self::Clockwise180 => 180,
+            Self::Clockwise270 => 270,
+        }
+    }
+
+    /// Whether this rotation swaps a frame's width and height.
+    pub const fn swaps_sides(&self) -> bool {
+        matches!(self, Self::Clockwise90 | Self::Clockwise270)
+    }
+
+    /// The dimensions of a frame with `dimensions` after this rotation.
+    pub const fn rotate_dimensions(&self, dimensions: Dimensions) -> Dimensions {
+        if self.swaps_sides() {
+            Dimensions::from_non_zero(dimensions.height_non_zero(), dimensions.width_non_zero())
+        } else {
+            dimensions
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- from_degrees() ---
+
+    #[test]
+    fn test_from_degrees_exact() {
+        assert_eq!(Rotation::from_degrees(0.0), Some(Rotation::None));
+        assert_eq!(Rotation::from_degrees(90.0), Some(Rotation::Clockwise90));
+        assert_eq!(Rotation::from_degrees(180.0), Some(Rotation::Clockwise180));
+        assert_eq!(Rotation::from_degrees(270.0), Some(Rotation::Clockwise270));
+    }
+
+    #[test]
+    fn test_from_degrees_wraps() {
+        assert_eq!(Rotation::from_degrees(360.0), Some(Rotation::None));
+        assert_eq!(Rotation::from_degrees(-90.0), Some(Rotation::Clockwise270));
+        assert_eq!(Rotation::from_degrees(-180.0), Some(Rotation::Clockwise180));
+    }
+
+    #[test]
+    fn test_from_degrees_not_finite() {
+        assert_eq!(Rotation::from_degrees(f64::NAN), None);
+        assert_eq!(Rotation::from_degrees(f64::INFINITY), None);
+    }
+
+    // --- rotate_dimensions() ---
+
+    #[test]
+    fn test_rotate_dimensions() {
+        let d: Dimensions = (1920, 1080).into();
+        assert_eq!(Rotation::None.rotate_dimensions(d), d);
+        assert_eq!(
+            Rotation::Clockwise90.rotate_dimensions(d),
+            (1080, 1920).into()
+        );
+        assert_eq!(Rotation::Clockwise180.rotate_dimensions(d), d);
+        assert_eq!(
+            Rotation::Clockwise270.rotate_dimensions(d),
+            (1080, 1920).into()
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! Color metadata for [Frame](super::Frame) sources, and tone mapping for HDR
+//! (PQ and HLG) video so it can be shown in the SDR (BT.709/sRGB) working
+//! space everything else uses.
+
+use super::Pixel;
+
+/// The color primaries (gamut) a source was mastered in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorPrimaries {
+    /// BT.709 (the same primaries as sRGB).
+    #[default]
+    Bt709,
+    /// BT.2020, the wide gamut used by HDR video.
+    Bt2020,
+    /// Anything else. Treated like [ColorPrimaries::Bt709].
+    Other,
+}
+
+/// The transfer function (how stored values map to light) of a source.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferFunction {
+    /// Standard dynamic range (BT.709, sRGB, ect.).
+    #[default]
+    Sdr,
+    /// SMPTE ST 2084 "perceptual quantizer" HDR (HDR10, Dolby Vision).
+    Pq,
+    /// ARIB STD-B67 "hybrid log-gamma" HDR (broadcast, most phones).
+    Hlg,
+}
+
+/// Describes how the colors in a source should be interpreted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColorInfo {
+    pub primaries: ColorPrimaries,
+    pub transfer: TransferFunction,
+}
+
+impl ColorInfo {
+    /// Standard dynamic range BT.709, what every [Frame](super::Frame) holds.
+    pub const SDR: Self = Self {
+        primaries: ColorPrimaries::Bt709,
+        transfer: TransferFunction::Sdr,
+    };
+
+    /// Whether this is a high dynamic range source that needs to be
+    /// [tone mapped](ToneMapper).
+    pub const fn is_hdr(&self) -> bool {
+        !matches!(self.transfer, TransferFunction::Sdr)
+    }
+}
+
+/// How HDR highlights are compressed into the SDR range.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToneMapOperator {
+    /// Smoothly rolls off highlights up to the source's peak brightness.
+    #[default]
+    Reinhard,
+    /// The "Uncharted 2" filmic curve. More contrast than
+    /// [ToneMapOperator::Reinhard] with a slight toe in the shadows.
+    Hable,
+    /// Clips everything brighter than SDR white.
+    Clip,
+}
+
+impl ToneMapOperator {
+    /// Every operator, in declaration order.
+    pub const ALL: [Self; 3] = [Self::Reinhard, Self::Hable, Self::Clip];
+
+    /// A short, printable name for this operator.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Reinhard => "Reinhard",
+            Self::Hable => "Hable",
+            Self::Clip => "Clip",
+        }
+    }
+
+    /// Map linear light `x` (where `1.0` is SDR white and `peak` is the
+    /// brightest value the source can have) into `0.0..=1.0`.
+    fn apply(&self, x: f32, peak: f32) -> f32 {
+        let mapped = match self {
+            Self::Clip => x,
+            // Extended Reinhard, so `peak` maps to exactly `1.0`.
+            Self::Reinhard => x * (1.0 + x / (peak * peak)) / (1.0 + x),
+            Self::Hable => {
+                const EXPOSURE_BIAS: f32 = 2.0;
+                hable_curve(x * EXPOSURE_BIAS) / hable_curve(peak.max(1.0) * EXPOSURE_BIAS)
+            }
+        };
+        mapped.clamp(0.0, 1.0)
+    }
+}
+
+fn hable_curve(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+/// The brightness (in nits) of SDR white when placed in an HDR signal
+/// (ITU-R BT.2408).
+const SDR_WHITE_NITS: f32 = 203.0;
+
+/// The peak brightness (in nits) HLG is displayed at.
+const HLG_PEAK_NITS: f32 = 1000.0;
+
+/// The brightest a PQ signal can be (in nits).
+const PQ_PEAK_NITS: f32 = 10000.0;
+
+/// Linear BT.2020 to linear BT.709 RGB.
+const BT2020_TO_BT709: [[f32; 3]; 3] = [
+    [1.660_491, -0.587_641, -0.072_850],
+    [-0.124_550, 1.132_900, -0.008_349],
+    [-0.018_151, -0.100_579, 1.118_730],
+];
+
+/// BT.2020 luma coefficients, used for the HLG OOTF.
+const BT2020_LUMA: [f32; 3] = [0.2627, 0.6780, 0.0593];
+
+/// How many entries the linear to sRGB lookup table has.
+const ENCODE_LUT_LEN: usize = 4096;
+
+/// Converts 16-bit HDR pixels to SDR [Pixel]s for a specific [ColorInfo] and
+/// [ToneMapOperator]. Lookup tables are built up front so creating one of these
+/// isn't free, but mapping pixels is cheap.
+#[derive(Clone)]
+pub struct ToneMapper {
+    color_info: ColorInfo,
+    operator: ToneMapOperator,
+    /// 16-bit signal value to linear light (`1.0` is SDR white). For HLG this
+    /// is scene light, the OOTF is applied per pixel.
+    to_linear: Box<[f32]>,
+    /// Linear light in `0.0..=1.0` to an 8-bit sRGB value.
+    encode: Box<[u8]>,
+    peak: f32,
+}
+
+impl ToneMapper {
+    /// Create a [ToneMapper] for sources described by `color_info`.
+    pub fn new(color_info: ColorInfo, operator: ToneMapOperator) -> Self {
+        let to_linear = (0..=u16::MAX)
+            .map(|value| {
+                let signal = value as f32 / u16::MAX as f32;
+                match color_info.transfer {
+                    TransferFunction::Sdr => signal.powf(2.4),
+                    TransferFunction::Pq => pq_eotf(signal) / SDR_WHITE_NITS,
+                    TransferFunction::Hlg => hlg_inverse_oetf(signal),
+                }
+            })
+            .collect();
+
+        let encode = (0..ENCODE_LUT_LEN)
+            .map(|i| {
+                let linear = i as f32 / (ENCODE_LUT_LEN - 1) as f32;
+                (srgb_oetf(linear) * 255.0).round() as u8
+            })
+            .collect();
+
+        let peak = match color_info.transfer {
+            TransferFunction::Sdr => 1.0,
+            TransferFunction::Pq => PQ_PEAK_NITS / SDR_WHITE_NITS,
+            TransferFunction::Hlg => HLG_PEAK_NITS / SDR_WHITE_NITS,
+        };
+
+        Self {
+            color_info,
+            operator,
+            to_linear,
+            encode,
+            peak,
+        }
+    }
+
+    /// The sources this mapper is for.
+    pub fn color_info(&self) -> ColorInfo {
+        self.color_info
+    }
+
+    /// The operator used to compress highlights.
+    pub fn operator(&self) -> ToneMapOperator {
+        self.operator
+    }
+
+    /// Tone map one 16-bit RGBA pixel.
+    pub fn map(&self, [r, g, b, a]: [u16; 4]) -> Pixel {
+        let mut rgb = [r, g, b].map(|channel| self.to_linear[channel as usize]);
+
+        if self.color_info.transfer == TransferFunction::Hlg {
+            // HLG's OOTF: scene light to display light, relative to SDR white.
+            let luma: f32 = rgb.iter().zip(BT2020_LUMA).map(|(c, k)| c * k).sum();
+            let gain = HLG_PEAK_NITS / SDR_WHITE_NITS * luma.max(0.0).powf(0.2);
+            rgb = rgb.map(|channel| channel * gain);
+        }
+
+        if self.color_info.primaries == ColorPrimaries::Bt2020 {
+            rgb = BT2020_TO_BT709
+                .map(|row| (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]).max(0.0));
+        }
+
+        let [r, g, b] = rgb.map(|channel| {
+            let mapped = self.operator.apply(channel, self.peak);
+            self.encode[(mapped * (ENCODE_LUT_LEN - 1) as f32).round() as usize]
+        });
+        Pixel::from_rgba(r, g, b, (a >> 8) as u8)
+    }
+
+    /// Tone map a row of little endian 16-bit RGBA pixels (FFmpeg's `RGBA64LE`
+    /// format) into `dest`. Extra bytes at the end of `src` are ignored.
+    pub fn map_row(&self, src: &[u8], dest: &mut [Pixel]) {
+        const PIXEL_BYTES: usize = 4 * size_of::<u16>();
+
+        for (src, dest) in src.chunks_exact(PIXEL_BYTES).zip(dest) {
+            let channel = |i: usize| u16::from_le_bytes([src[i * 2], src[i * 2 + 1]]);
+            *dest = self.map([channel(0), channel(1), channel(2), channel(3)]);
+        }
+    }
+}
+
+impl std::fmt::Debug for ToneMapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToneMapper")
+            .field("color_info", &self.color_info)
+            .field("operator", &self.operator)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The SMPTE ST 2084 (PQ) EOTF. Takes a signal in `0.0..=1.0` and returns
+/// light in nits.
+fn pq_eotf(signal: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    const C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+    let p = signal.powf(1.0 / M2);
+    let linear = ((p - C1).max(0.0) / (C2 - C3 * p)).powf(1.0 / M1);
+    linear * PQ_PEAK_NITS
+}
+
+/// The inverse of the HLG OETF. Takes a signal in `0.0..=1.0` and returns
+/// scene light in `0.0..=1.0`.
+fn hlg_inverse_oetf(signal: f32) -> f32 {
+    const A: f32 = 0.178_832_77;
+    const B: f32 = 0.284_668_92;
+    const C: f32 = 0.559_910_7;
+
+    if signal <= 0.5 {
+        signal * signal / 3.0
+    } else {
+        (((signal - C) / A).exp() + B) / 12.0
+    }
+}
+
+/// The sRGB OETF. Takes linear light in `0.0..=1.0`.
+fn srgb_oetf(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HDR10: ColorInfo = ColorInfo {
+        primaries: ColorPrimaries::Bt2020,
+        transfer: TransferFunction::Pq,
+    };
+
+    const HLG: ColorInfo = ColorInfo {
+        primaries: ColorPrimaries::Bt2020,
+        transfer: TransferFunction::Hlg,
+    };
+
+    fn signal(value: f32) -> u16 {
+        (value * u16::MAX as f32).round() as u16
+    }
+
+    // --- pq_eotf() ---
+
+    #[test]
+    fn test_pq_eotf_known_values() {
+        assert_eq!(pq_eotf(0.0), 0.0);
+        assert!((pq_eotf(1.0) - PQ_PEAK_NITS).abs() < 1.0);
+        // BT.2408 puts SDR white (203 nits) at a PQ signal of about 58%.
+        assert!((pq_eotf(0.5807) - SDR_WHITE_NITS).abs() < 2.0);
+    }
+
+    // --- hlg_inverse_oetf() ---
+
+    #[test]
+    fn test_hlg_inverse_oetf_is_continuous() {
+        let below = hlg_inverse_oetf(0.5);
+        let above = hlg_inverse_oetf(0.500_01);
+        assert!((below - above).abs() < 1e-3);
+        assert!((hlg_inverse_oetf(1.0) - 1.0).abs() < 1e-3);
+    }
+
+    // --- ToneMapOperator::apply() ---
+
+    #[test]
+    fn test_operators_stay_in_range_and_increase() {
+        for operator in ToneMapOperator::ALL {
+            let mut last = 0.0;
+            for i in 0..=100 {
+                let x = i as f32 * 0.5;
+                let mapped = operator.apply(x, 49.0);
+                assert!(
+                    (0.0..=1.0).contains(&mapped),
+                    "{operator:?}({x}) = {mapped}"
+                );
+                assert!(mapped >= last, "{operator:?} isn't monotonic at {x}");
+                last = mapped;
+            }
+        }
+    }
+
+    #[test]
+    fn test_reinhard_maps_peak_to_white() {
+        assert!((ToneMapOperator::Reinhard.apply(49.0, 49.0) - 1.0).abs() < 1e-5);
+    }
+
+    // --- ToneMapper::map() ---
+
+    #[test]
+    fn test_map_black_stays_black() {
+        for color_info in [HDR10, HLG] {
+            let pixel =
+                ToneMapper::new(color_info, ToneMapOperator::Reinhard).map([0, 0, 0, 0xFFFF]);
+            assert_eq!(pixel, Pixel::from_rgba(0, 0, 0, 255));
+        }
+    }
+
+    #[test]
+    fn test_map_pq_reference_white_is_white_when_clipped() {
+        let white = signal(0.5807);
+        let pixel =
+            ToneMapper::new(HDR10, ToneMapOperator::Clip).map([white, white, white, 0xFFFF]);
+        let [r, g, b, a] = pixel.channels();
+        assert!(r >= 253 && g >= 253 && b >= 253, "{pixel:?}");
+        assert_eq!(a, 255);
+    }
+
+    #[test]
+    fn test_map_brighter_signal_is_brighter() {
+        let mapper = ToneMapper::new(HLG, ToneMapOperator::Hable);
+        let dim = mapper.map([signal(0.3), signal(0.3), signal(0.3), 0xFFFF]);
+        let bright = mapper.map([signal(0.9), signal(0.9), signal(0.9), 0xFFFF]);
+        assert!(bright.red() > dim.red());
+    }
+
+    // --- ToneMapper::map_row() ---
+
+    #[test]
+    fn test_map_row_reads_little_endian() {
+        let mapper = ToneMapper::new(HDR10, ToneMapOperator::Clip);
+        let white = signal(0.5807);
+        let white_bytes = white.to_le_bytes();
+        let mut src = Vec::new();
+        for _ in 0..2 {
+            src.extend_from_slice(&[white_bytes[0], white_bytes[1]].repeat(3));
+            src.extend_from_slice(&[0xFF, 0xFF]);
+        }
+        src.extend_from_slice(&[0; 4]); // row padding
+
+        let mut dest = [Pixel::BLACK; 2];
+        mapper.map_row(&src, &mut dest);
+        assert_eq!(dest[0], dest[1]);
+        assert_eq!(dest[0], mapper.map([white, white, white, 0xFFFF]));
+    }
+}
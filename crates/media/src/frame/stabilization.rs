@@ -0,0 +1,602 @@
+//! Exports [analyze_video], which measures how a video's camera shakes so it
+//! can be stabilized, and the [StabilizationTrack] it produces.
+//!
+//! Analysis tracks a grid of small, high contrast blocks from each frame to the
+//! next (on a downscaled copy of the video) and fits a rotation and translation
+//! to where they moved. Smoothing the camera path those motions add up to and
+//! comparing it with the real path gives the correction that steadies each
+//! frame (see [StabilizationTrack::corrections]).
+//!
+//! # Disk Caching
+//!
+//! Analyzing a video means decoding all of it, so finished tracks are saved in
+//! `local_data::stabilization_cache_path()`. Each entry is a text file named
+//! with the hash of the video's path, holding the path, the video file's size
+//! and modification time (so edited videos are analyzed again), its frame rate,
+//! and one `dx dy angle` line per frame.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use thiserror::Error;
+
+use util::channels::ChannelError;
+use util::local_data;
+
+use super::streams::{FrameStream, FrameStreamError, VideoFrameStream};
+use super::{Dimensions, Frame, RescaleMethod};
+use crate::ffmpeg_tools::ffmpeg_video::hashed_path;
+use crate::playback_stream::{PlaybackStream, SeekablePlaybackStream};
+
+/// The width videos are downscaled to for analysis.
+pub const ANALYSIS_WIDTH: u32 = 320;
+
+/// The grid of cells a feature is picked from in each frame.
+const GRID_COLUMNS: usize = 8;
+const GRID_ROWS: usize = 6;
+
+/// The width and height of a tracked block in pixels.
+const BLOCK_SIZE: usize = 8;
+
+/// How far a block is searched for, as a fraction of the frame's width.
+const SEARCH_FRACTION: f64 = 0.05;
+
+/// Blocks with less contrast than this (the smaller of the summed horizontal
+/// and vertical differences) are too flat to track reliably.
+const MIN_FEATURE_STRENGTH: f32 = 4.0 * (BLOCK_SIZE * BLOCK_SIZE) as f32;
+
+const CACHE_FILE_MAGIC: &str = "stabilization 1";
+
+/// How a frame moved relative to the frame before it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameMotion {
+    /// Horizontal movement as a fraction of the frame's width.
+    pub dx: f64,
+    /// Vertical movement as a fraction of the frame's height.
+    pub dy: f64,
+    /// Rotation around the frame's center, in radians (clockwise on screen).
+    pub angle: f64,
+}
+
+impl FrameMotion {
+    fn add(self, other: Self) -> Self {
+        Self {
+            dx: self.dx + other.dx,
+            dy: self.dy + other.dy,
+            angle: self.angle + other.angle,
+        }
+    }
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            dx: self.dx - other.dx,
+            dy: self.dy - other.dy,
+            angle: self.angle - other.angle,
+        }
+    }
+
+    fn scale(self, factor: f64) -> Self {
+        Self {
+            dx: self.dx * factor,
+            dy: self.dy * factor,
+            angle: self.angle * factor,
+        }
+    }
+}
+
+/// The measured motion of every frame of a video. See [analyze_video].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StabilizationTrack {
+    fps: f64,
+    /// The motion of each frame from the one before it (the first is always
+    /// zero).
+    motions: Vec<FrameMotion>,
+}
+
+impl StabilizationTrack {
+    /// The frame rate the video was analyzed at.
+    pub const fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    /// The number of analyzed frames.
+    pub const fn frame_count(&self) -> usize {
+        self.motions.len()
+    }
+
+    /// The motion of each frame from the one before it.
+    pub fn motions(&self) -> &[FrameMotion] {
+        &self.motions
+    }
+
+    /// The movement that steadies each frame: how far the camera path,
+    /// averaged over `smoothing_radius` frames either side, is from where the
+    /// camera really was. Larger radii give a smoother (but more cropped)
+    /// result.
+    pub fn corrections(&self, smoothing_radius: usize) -> Vec<FrameMotion> {
+        let trajectory: Vec<FrameMotion> = self
+            .motions
+            .iter()
+            .scan(FrameMotion::default(), |position, motion| {
+                *position = position.add(*motion);
+                Some(*position)
+            })
+            .collect();
+
+        (0..trajectory.len())
+            .map(|index| {
+                let window = &trajectory[index.saturating_sub(smoothing_radius)
+                    ..(index + smoothing_radius + 1).min(trajectory.len())];
+                let smoothed = window
+                    .iter()
+                    .fold(FrameMotion::default(), |sum, position| sum.add(*position))
+                    .scale(1.0 / window.len() as f64);
+                smoothed.sub(trajectory[index])
+            })
+            .collect()
+    }
+
+    /// Load the cached track for a video, if it was analyzed since it last
+    /// changed.
+    pub fn load_cached(video_path: &Path) -> Option<Self> {
+        let stamp = FileStamp::of(video_path).ok()?;
+        let text = fs::read_to_string(cache_file_path(video_path)).ok()?;
+        let (cached_path, cached_stamp, track) = Self::from_cache_text(&text)?;
+        (cached_path == video_path && cached_stamp == stamp).then_some(track)
+    }
+
+    /// Cache the track for a video, replacing any older entry.
+    pub fn save_cached(&self, video_path: &Path) -> io::Result<()> {
+        let stamp = FileStamp::of(video_path)?;
+        let text = self.to_cache_text(video_path, stamp);
+
+        let path = cache_file_path(video_path);
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, text)?;
+        fs::rename(&temp_path, &path)
+    }
+
+    fn to_cache_text(&self, video_path: &Path, stamp: FileStamp) -> String {
+        let mut text = format!(
+            "{CACHE_FILE_MAGIC}\n{}\n{} {}\n{}\n",
+            video_path.display(),
+            stamp.len,
+            stamp.modified_nanos,
+            self.fps
+        );
+        for motion in &self.motions {
+            text.push_str(&format!("{} {} {}\n", motion.dx, motion.dy, motion.angle));
+        }
+        text
+    }
+
+    fn from_cache_text(text: &str) -> Option<(PathBuf, FileStamp, Self)> {
+        let mut lines = text.lines();
+        if lines.next()? != CACHE_FILE_MAGIC {
+            return None;
+        }
+        let video_path = PathBuf::from(lines.next()?);
+        let (len, modified_nanos) = lines.next()?.split_once(' ')?;
+        let stamp = FileStamp {
+            len: len.parse().ok()?,
+            modified_nanos: modified_nanos.parse().ok()?,
+        };
+        let fps = lines.next()?.parse().ok()?;
+
+        let motions = lines
+            .map(|line| {
+                let mut values = line.split(' ').map(str::parse::<f64>);
+                let motion = FrameMotion {
+                    dx: values.next()?.ok()?,
+                    dy: values.next()?.ok()?,
+                    angle: values.next()?.ok()?,
+                };
+                values.next().is_none().then_some(motion)
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some((video_path, stamp, Self { fps, motions }))
+    }
+}
+
+/// Indicates [analyze_video] failed.
+#[derive(Error, Debug)]
+pub enum StabilizationError {
+    #[error("Failed to open the video: {0}")]
+    Request(#[from] ChannelError),
+    #[error("Failed to decode the video: {0}")]
+    Stream(#[from] FrameStreamError),
+    #[error("The analysis was cancelled.")]
+    Cancelled,
+}
+
+/// Measure the motion of every frame of a video, or load it from the cache if
+/// the video was analyzed before.
+///
+/// `on_progress` is called with the fraction of the video analyzed so far
+/// (from 0 to 1) after every frame. Returning `false` from it cancels the
+/// analysis.
+pub fn analyze_video(
+    video_path: &Path,
+    mut on_progress: impl FnMut(f32) -> bool,
+) -> Result<StabilizationTrack, StabilizationError> {
+    if let Some(track) = StabilizationTrack::load_cached(video_path) {
+        return Ok(track);
+    }
+
+    let mut stream = VideoFrameStream::builder().build(&video_path).wait()??;
+    let native = stream.native_dimensions();
+    let width = ANALYSIS_WIDTH.min(native.width());
+    let height = (native.height() as u64 * width as u64 / native.width() as u64).max(1) as u32;
+    let dimensions = Dimensions::new(width, height).expect("non-zero dimensions");
+    stream.set_dimensions(dimensions, RescaleMethod::Bilinear);
+
+    let fps = stream.native_fps().as_float();
+    let frame_count = stream.clipped_stream_duration_non_zero().get();
+
+    let mut motions = Vec::with_capacity(frame_count);
+    let mut previous: Option<LumaImage> = None;
+    for index in 0..frame_count {
+        let frame = stream.fetch()?;
+        let luma = LumaImage::from_frame(&frame);
+        stream.recycle(frame);
+
+        motions.push(match &previous {
+            Some(previous) => estimate_luma_motion(previous, &luma),
+            None => FrameMotion::default(),
+        });
+        previous = Some(luma);
+
+        if !on_progress((index + 1) as f32 / frame_count as f32) {
+            return Err(StabilizationError::Cancelled);
+        }
+    }
+
+    let track = StabilizationTrack { fps, motions };
+    _ = track.save_cached(video_path).inspect_err(|e| {
+        util::debug_log_error!("Failed to cache stabilization analysis: {e}");
+    });
+    Ok(track)
+}
+
+/// Estimate how `next` moved relative to `prev`. The frames should have the
+/// same dimensions (if they don't, no motion is found).
+pub fn estimate_motion(prev: &Frame, next: &Frame) -> FrameMotion {
+    if prev.dimensions() != next.dimensions() {
+        return FrameMotion::default();
+    }
+    estimate_luma_motion(&LumaImage::from_frame(prev), &LumaImage::from_frame(next))
+}
+
+/// The brightness of each pixel of a frame.
+struct LumaImage {
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+}
+
+impl LumaImage {
+    fn from_frame(frame: &Frame) -> Self {
+        Self {
+            width: frame.dimensions().width() as usize,
+            height: frame.dimensions().height() as usize,
+            values: frame
+                .pixels()
+                .iter()
+                .map(|pixel| {
+                    0.299 * pixel.red() as f32
+                        + 0.587 * pixel.green() as f32
+                        + 0.114 * pixel.blue() as f32
+                })
+                .collect(),
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> f32 {
+        self.values[y * self.width + x]
+    }
+}
+
+fn estimate_luma_motion(prev: &LumaImage, next: &LumaImage) -> FrameMotion {
+    let radius = ((prev.width as f64 * SEARCH_FRACTION) as usize).max(4);
+
+    let matches: Vec<_> = find_features(prev, radius)
+        .into_iter()
+        .filter_map(|(x, y)| {
+            let (dx, dy) = track_feature(prev, next, x, y, radius)?;
+            let center = BLOCK_SIZE as f64 / 2.0;
+            let from = (x as f64 + center, y as f64 + center);
+            Some((from, (from.0 + dx, from.1 + dy)))
+        })
+        .collect();
+
+    let center = (prev.width as f64 / 2.0, prev.height as f64 / 2.0);
+    let (dx, dy, angle) = fit_motion(&reject_outliers(matches), center);
+    FrameMotion {
+        dx: dx / prev.width as f64,
+        dy: dy / prev.height as f64,
+        angle,
+    }
+}
+
+/// The top left corner of the most trackable block in each grid cell, leaving
+/// room around the edges to search `radius` pixels.
+fn find_features(luma: &LumaImage, radius: usize) -> Vec<(usize, usize)> {
+    let min = radius;
+    let (Some(max_x), Some(max_y)) = (
+        luma.width.checked_sub(BLOCK_SIZE + radius + 1),
+        luma.height.checked_sub(BLOCK_SIZE + radius + 1),
+    ) else {
+        return Vec::new();
+    };
+    if max_x <= min || max_y <= min {
+        return Vec::new();
+    }
+
+    let strength = |x: usize, y: usize| {
+        let (mut horizontal, mut vertical) = (0.0, 0.0);
+        for row in y..y + BLOCK_SIZE {
+            for column in x..x + BLOCK_SIZE {
+                let value = luma.get(column, row);
+                horizontal += (luma.get(column + 1, row) - value).abs();
+                vertical += (luma.get(column, row + 1) - value).abs();
+            }
+        }
+        f32::min(horizontal, vertical)
+    };
+
+    let cell_width = (max_x - min) / GRID_COLUMNS;
+    let cell_height = (max_y - min) / GRID_ROWS;
+    let mut features = Vec::with_capacity(GRID_COLUMNS * GRID_ROWS);
+    for row in 0..GRID_ROWS {
+        for column in 0..GRID_COLUMNS {
+            let left = min + column * cell_width;
+            let top = min + row * cell_height;
+            let best = (top..=top + cell_height)
+                .step_by(2)
+                .flat_map(|y| (left..=left + cell_width).step_by(2).map(move |x| (x, y)))
+                .map(|(x, y)| (strength(x, y), x, y))
+                .max_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some((score, x, y)) = best
+                && score >= MIN_FEATURE_STRENGTH
+            {
+                features.push((x, y));
+            }
+        }
+    }
+    features
+}
+
+/// Find where the block at `x`, `y` in `prev` moved to in `next` (to a
+/// fraction of a pixel), searching `radius` pixels in each direction.
+fn track_feature(
+    prev: &LumaImage,
+    next: &LumaImage,
+    x: usize,
+    y: usize,
+    radius: usize,
+) -> Option<(f64, f64)> {
+    let side = 2 * radius + 1;
+    let mut costs = vec![0.0f32; side * side];
+    for v in 0..side {
+        for u in 0..side {
+            let (next_x, next_y) = (x + u - radius, y + v - radius);
+            let mut cost = 0.0;
+            for row in 0..BLOCK_SIZE {
+                for column in 0..BLOCK_SIZE {
+                    cost += (prev.get(x + column, y + row)
+                        - next.get(next_x + column, next_y + row))
+                    .abs();
+                }
+            }
+            costs[v * side + u] = cost;
+        }
+    }
+
+    let best = (0..costs.len()).min_by(|a, b| costs[*a].total_cmp(&costs[*b]))?;
+    let (u, v) = (best % side, best / side);
+
+    // Fit a parabola through the best cost and its neighbors on each axis.
+    let refine = |before: Option<f32>, at: f32, after: Option<f32>| match (before, after) {
+        (Some(before), Some(after)) => {
+            let curvature = before - 2.0 * at + after;
+            if curvature > f32::EPSILON {
+                (0.5 * (before - after) / curvature).clamp(-0.5, 0.5) as f64
+            } else {
+                0.0
+            }
+        }
+        _ => 0.0,
+    };
+    let cost = |u: usize, v: usize| costs[v * side + u];
+    let refine_u = refine(
+        u.checked_sub(1).map(|u| cost(u, v)),
+        cost(u, v),
+        (u + 1 < side).then(|| cost(u + 1, v)),
+    );
+    let refine_v = refine(
+        v.checked_sub(1).map(|v| cost(u, v)),
+        cost(u, v),
+        (v + 1 < side).then(|| cost(u, v + 1)),
+    );
+
+    Some((
+        u as f64 - radius as f64 + refine_u,
+        v as f64 - radius as f64 + refine_v,
+    ))
+}
+
+type PointMatch = ((f64, f64), (f64, f64));
+
+/// Drop matches that moved very differently from the rest (e.g. features on
+/// something moving through the frame rather than on the background).
+fn reject_outliers(matches: Vec<PointMatch>) -> Vec<PointMatch> {
+    fn median(mut values: Vec<f64>) -> f64 {
+        values.sort_by(f64::total_cmp);
+        values.get(values.len() / 2).copied().unwrap_or(0.0)
+    }
+
+    let movement = |((x0, y0), (x1, y1)): &PointMatch| (x1 - x0, y1 - y0);
+    let median_x = median(matches.iter().map(|m| movement(m).0).collect());
+    let median_y = median(matches.iter().map(|m| movement(m).1).collect());
+    let deviation = |m: &PointMatch| {
+        let (x, y) = movement(m);
+        (x - median_x).hypot(y - median_y)
+    };
+    let threshold = (3.0 * median(matches.iter().map(deviation).collect())).max(1.0);
+
+    matches
+        .into_iter()
+        .filter(|m| deviation(m) <= threshold)
+        .collect()
+}
+
+/// Fit the rotation around `center` and the translation (in pixels) that best
+/// move each match's first point onto its second.
+fn fit_motion(matches: &[PointMatch], center: (f64, f64)) -> (f64, f64, f64) {
+    if matches.is_empty() {
+        return (0.0, 0.0, 0.0);
+    }
+
+    let count = matches.len() as f64;
+    let (from_mean, to_mean) = matches.iter().fold(
+        ((0.0, 0.0), (0.0, 0.0)),
+        |((fx, fy), (tx, ty)), ((x0, y0), (x1, y1))| ((fx + x0, fy + y0), (tx + x1, ty + y1)),
+    );
+    let from_mean = (from_mean.0 / count, from_mean.1 / count);
+    let to_mean = (to_mean.0 / count, to_mean.1 / count);
+
+    // With too few points a rotation can't be told apart from noise.
+    let angle = if matches.len() >= 3 {
+        let (cross, dot) = matches
+            .iter()
+            .fold((0.0, 0.0), |(cross, dot), ((x0, y0), (x1, y1))| {
+                let (fx, fy) = (x0 - from_mean.0, y0 - from_mean.1);
+                let (tx, ty) = (x1 - to_mean.0, y1 - to_mean.1);
+                (cross + fx * ty - fy * tx, dot + fx * tx + fy * ty)
+            });
+        cross.atan2(dot)
+    } else {
+        0.0
+    };
+
+    // to = R(from - center) + center + t
+    let (sin, cos) = angle.sin_cos();
+    let (rx, ry) = (from_mean.0 - center.0, from_mean.1 - center.1);
+    (
+        to_mean.0 - center.0 - (cos * rx - sin * ry),
+        to_mean.1 - center.1 - (sin * rx + cos * ry),
+        angle,
+    )
+}
+
+/// Identifies a version of a file, so cached analyses of a file that changed
+/// aren't used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified_nanos: u128,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> io::Result<Self> {
+        let metadata = path.metadata()?;
+        Ok(Self {
+            len: metadata.len(),
+            modified_nanos: metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos()),
+        })
+    }
+}
+
+fn cache_file_path(video_path: &Path) -> PathBuf {
+    local_data::stabilization_cache_path().join(hashed_path(video_path).as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::Pixel;
+
+    /// A frame of blobby texture, shifted right and down by `offset` pixels.
+    fn textured_frame(offset: (usize, usize)) -> Frame {
+        Frame::from_fill_with_coords(Dimensions::new(160, 120).unwrap(), |row, column| {
+            let (x, y) = (
+                column as f64 - offset.0 as f64,
+                row as f64 - offset.1 as f64,
+            );
+            let value = 128.0
+                + 60.0 * (x * 0.31).sin() * (y * 0.23).cos()
+                + 50.0 * ((x + 2.0 * y) * 0.11).sin();
+            let value = value.clamp(0.0, 255.0) as u8;
+            Pixel::from_rgb(value, value, value)
+        })
+    }
+
+    // --- estimate_motion() ---
+
+    #[test]
+    fn estimate_motion_finds_a_shift() {
+        let motion = estimate_motion(&textured_frame((0, 0)), &textured_frame((3, 2)));
+        assert!((motion.dx * 160.0 - 3.0).abs() < 0.5, "{motion:?}");
+        assert!((motion.dy * 120.0 - 2.0).abs() < 0.5, "{motion:?}");
+        assert!(motion.angle.abs() < 0.01, "{motion:?}");
+    }
+
+    // --- StabilizationTrack::corrections() ---
+
+    #[test]
+    fn corrections_cancel_shake_but_keep_pans() {
+        let shake = |dx| FrameMotion {
+            dx,
+            ..Default::default()
+        };
+
+        let pan = StabilizationTrack {
+            fps: 30.0,
+            motions: vec![shake(0.01); 9],
+        };
+        assert!(pan.corrections(2)[4].dx.abs() < 1e-9);
+
+        let shaky = StabilizationTrack {
+            fps: 30.0,
+            motions: [0.0, 0.02, -0.02, 0.02, -0.02, 0.02, -0.02]
+                .into_iter()
+                .map(shake)
+                .collect(),
+        };
+        let corrections = shaky.corrections(3);
+        assert!(corrections[3].dx < 0.0 && corrections[4].dx > 0.0);
+    }
+
+    // --- StabilizationTrack::from_cache_text() ---
+
+    #[test]
+    fn cache_text_round_trips() {
+        let track = StabilizationTrack {
+            fps: 29.97,
+            motions: vec![
+                FrameMotion::default(),
+                FrameMotion {
+                    dx: 0.25,
+                    dy: -0.125,
+                    angle: 0.001,
+                },
+            ],
+        };
+        let stamp = FileStamp {
+            len: 1234,
+            modified_nanos: 5678,
+        };
+        let text = track.to_cache_text(Path::new("/videos/shaky.mp4"), stamp);
+        assert_eq!(
+            StabilizationTrack::from_cache_text(&text),
+            Some((PathBuf::from("/videos/shaky.mp4"), stamp, track))
+        );
+        assert_eq!(StabilizationTrack::from_cache_text("other 1\n"), None);
+    }
+}
@@ -1,15 +1,16 @@
 //! Exports all kinds of [FrameStream]s ([PlaybackStream]s of [Frame]s).
 
 mod stream_generator;
-use stream_generator::StreamGenerator;
+use stream_generator::{GeneratorThread, StreamGenerator};
 
 use ffmpeg_next as ffmpeg;
 
 use util::channels::ChannelError;
 
-use super::{Dimensions, RescaleMethod};
+use super::color::{ColorInfo, ToneMapOperator};
+use super::{Dimensions, RescaleMethod, Rotation};
 use crate::frame::Frame;
-use crate::playback_stream::PlaybackStream;
+use crate::playback_stream::{BufferingPolicy, BufferingStats, PlaybackStream};
 
 mod still_frame_stream;
 pub use still_frame_stream::*;
@@ -17,6 +18,9 @@ pub use still_frame_stream::*;
 mod video_frame_stream;
 pub use video_frame_stream::*;
 
+mod cached_clip;
+pub use cached_clip::*;
+
 /// A [PlaybackStream] of [Frame]s.
 pub trait FrameStream: PlaybackStream<Frame, FrameStreamError> + Send {
     /// Whether or not the last frame that was fetched is the same as the frame
@@ -41,6 +45,31 @@ pub trait FrameStream: PlaybackStream<Frame, FrameStreamError> + Send {
     /// to this value, no rescaling is required.
     fn native_dimensions(&self) -> Dimensions;
 
+    /// How fetched [Frame]s should be [rotated](Frame::rotate) to be displayed
+    /// upright, from the source's metadata (e.g. phone videos are often stored
+    /// sideways). Frames are *not* rotated by the stream, and
+    /// [Self::dimensions] are the unrotated dimensions.
+    ///
+    /// The default implementation returns [Rotation::None].
+    fn native_rotation(&self) -> Rotation {
+        Rotation::None
+    }
+
+    /// The color primaries and transfer function of the source. HDR sources
+    /// are tone mapped (see [Self::set_tone_map_operator]) so fetched [Frame]s
+    /// are always SDR.
+    ///
+    /// The default implementation returns [ColorInfo::SDR].
+    fn native_color(&self) -> ColorInfo {
+        ColorInfo::SDR
+    }
+
+    /// Change how HDR sources are tone mapped. Nothing happens if the source
+    /// isn't HDR (see [Self::native_color]).
+    ///
+    /// The default implementation does nothing.
+    fn set_tone_map_operator(&mut self, _tone_map_operator: ToneMapOperator) {}
+
     /// The rescale method (if any) that will be used to make fetched frames
     /// into the right dimensions.
     fn rescale_method(&self) -> Option<RescaleMethod>;
@@ -66,6 +95,63 @@ pub trait FrameStream: PlaybackStream<Frame, FrameStreamError> + Send {
     fn last_frame_is_distinct_from_previous(&self) -> bool {
         true
     }
+
+    /// How many frames are prepared ahead of time, or [None] if the stream
+    /// doesn't buffer frames in a configurable way.
+    ///
+    /// The default implementation returns [None].
+    fn buffering_policy(&self) -> Option<BufferingPolicy> {
+        None
+    }
+
+    /// Change how many frames are prepared ahead of time. Nothing happens if
+    /// the stream doesn't buffer frames in a configurable way (see
+    /// [Self::buffering_policy]).
+    ///
+    /// The default implementation does nothing.
+    fn set_buffering_policy(&mut self, _buffering_policy: BufferingPolicy) {}
+
+    /// How the stream's frame buffer is doing, or [None] if the stream doesn't
+    /// buffer frames in a configurable way (see [Self::buffering_policy]).
+    ///
+    /// The default implementation returns [None].
+    fn buffering_stats(&self) -> Option<BufferingStats> {
+        None
+    }
+
+    /// Take the errors the stream tried to recover from since this was last
+    /// called, oldest first.
+    ///
+    /// The default implementation returns nothing.
+    fn take_recoveries(&mut self) -> Vec<StreamRecovery> {
+        Vec::new()
+    }
+
+    /// Whether the stream's worker thread has stopped (because it panicked).
+    /// Changing the stream does nothing from then on and
+    /// [PlaybackStream::fetch] fails (see [FrameStreamError::is_worker_gone]),
+    /// so the stream should be recreated.
+    ///
+    /// The default implementation returns `false`.
+    fn is_worker_gone(&self) -> bool {
+        false
+    }
+}
+
+/// An error a [FrameStream] tried to recover from by reopening its source. See
+/// [FrameStream::take_recoveries].
+#[derive(Debug, Clone)]
+pub struct StreamRecovery {
+    /// The index of the frame that couldn't be produced (where the source was
+    /// reopened).
+    pub playhead: usize,
+
+    /// The error that was recovered from.
+    pub error: FrameStreamError,
+
+    /// Whether reopening the source worked. If it didn't, the stream will only
+    /// return errors from now on.
+    pub recovered: bool,
 }
 
 /// Indicates something went wrong with [FrameStream] (a [PlaybackStream] of
@@ -82,6 +168,17 @@ enum FrameStreamErrorInner {
     ChannelError(#[from] ChannelError),
 }
 
+impl FrameStreamError {
+    /// Whether the stream's worker thread has stopped (because it panicked).
+    /// The stream won't produce any more frames and should be recreated.
+    pub fn is_worker_gone(&self) -> bool {
+        matches!(
+            &self.0,
+            FrameStreamErrorInner::ChannelError(e) if e.is_connection_dropped_error()
+        )
+    }
+}
+
 impl From<ffmpeg::Error> for FrameStreamError {
     fn from(e: ffmpeg::Error) -> Self {
         Into::<FrameStreamErrorInner>::into(e).into()
@@ -0,0 +1,686 @@
+//! Exports [CachedClip].
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{self, AtomicU64};
+use std::sync::{Arc, LazyLock, Mutex, Once, RwLock, Weak};
+
+use memmap2::MmapMut;
+
+use util::local_data;
+
+use super::{FrameStream, FrameStreamError, StreamRecovery};
+use crate::fps::Fps;
+use crate::frame::color::{ColorInfo, ToneMapOperator};
+use crate::frame::{Dimensions, Frame, Pixel, RescaleMethod, Rotation};
+use crate::playback_stream::{
+    BufferingPolicy, BufferingStats, PlaybackStream, SeekablePlaybackStream,
+};
+
+/// The most a single clip's cache can take up by default (see
+/// [CachedClip::with_max_bytes]). Clips that need more aren't cached.
+pub const DEFAULT_MAX_CLIP_CACHE_BYTES: u64 = 1 << 30;
+
+/// The most every clip's cache can take up together. Clips that would go over
+/// this aren't cached.
+const MAX_TOTAL_CACHE_BYTES: u64 = 4 << 30;
+
+const LOCK_NOT_POISONED: &str = "The lock isn't poisoned.";
+
+/// How many bytes every open [FrameCache] takes up together.
+static TOTAL_CACHE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// The caches that are open, so [CachedClip]s of the same clip can share one.
+static CACHES: LazyLock<Mutex<HashMap<u64, Weak<FrameCache>>>> = LazyLock::new(Default::default);
+
+/// A [FrameStream] that saves the frames another (seekable) stream produces to
+/// a memory mapped cache file as they're played. Once every frame of the
+/// stream's [clip](SeekablePlaybackStream::clip) is cached, frames are read
+/// from the cache instead and the wrapped stream is paused, so later loops
+/// cost nothing to decode. [CachedClip]s wrapping the same clip (see
+/// [Self::new]) share one cache.
+///
+/// Changing the stream's target FPS, playback speed, dimensions, or tone
+/// mapping changes its frames, so they're cached again from scratch. Streams
+/// that can't seek, and clips too long to cache (see [Self::with_max_bytes]),
+/// are just played through. The cache file is deleted once no [CachedClip]
+/// is using it.
+#[derive(Debug)]
+pub struct CachedClip<S: FrameStream> {
+    inner: S,
+    seekable: bool,
+    key: u64,
+    max_bytes: u64,
+    tone_map: Option<ToneMapOperator>,
+    cache: Option<Arc<FrameCache>>,
+    /// Whether frames are being read from [Self::cache] (in which case `inner`
+    /// is paused).
+    playing_cached: bool,
+
+    // Playback state (kept in sync with `inner` while it's playing):
+    playhead: usize,
+    clip: (usize, usize),
+    duration: NonZeroUsize,
+    will_loop: bool,
+    paused: bool,
+    playback_speed: Fps,
+
+    // Local State (only used while playing from the cache):
+    last_played: Option<usize>,
+    ended: bool,
+    recycled_frames: Vec<Frame>,
+}
+
+impl<S: FrameStream> CachedClip<S> {
+    /// Wrap `inner`, caching up to [DEFAULT_MAX_CLIP_CACHE_BYTES] of it.
+    ///
+    /// `key` should identify the source of `inner`'s frames and anything that
+    /// changes them other than its target FPS, playback speed, and dimensions
+    /// (e.g. a video's file path and tone mapping). [CachedClip]s with the same
+    /// key share their cache.
+    pub fn new(inner: S, key: impl Hash) -> Self {
+        Self::with_max_bytes(inner, key, DEFAULT_MAX_CLIP_CACHE_BYTES)
+    }
+
+    /// Like [Self::new] but clips are only cached if all of their frames take
+    /// up `max_bytes` or less.
+    pub fn with_max_bytes(mut inner: S, key: impl Hash, max_bytes: u64) -> Self {
+        let seekable = inner.seek_controls().is_some();
+        let paused = inner.is_paused();
+
+        let mut ret = Self {
+            inner,
+            seekable,
+            key: hash(key),
+            max_bytes,
+            tone_map: None,
+            cache: None,
+            playing_cached: false,
+            playhead: 0,
+            clip: (0, 0),
+            duration: NonZeroUsize::MIN,
+            will_loop: false,
+            paused,
+            playback_speed: crate::fps::consts::FPS_1,
+            last_played: None,
+            ended: false,
+            recycled_frames: Vec::new(),
+        };
+        ret.sync_from_inner();
+        ret.open_cache();
+        ret
+    }
+
+    /// The wrapped stream.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwrap the stream, handing playback back to it if frames are being
+    /// played from the cache.
+    pub fn into_inner(mut self) -> S {
+        self.stop_playing_cached();
+        self.inner
+    }
+
+    /// Whether frames are being read from the cache instead of the wrapped
+    /// stream.
+    pub fn is_playing_cached(&self) -> bool {
+        self.playing_cached
+    }
+
+    /// Copy the wrapped stream's playback state.
+    fn sync_from_inner(&mut self) {
+        debug_assert!(!self.playing_cached);
+
+        self.paused = self.inner.is_paused();
+        if let Some(seek_controls) = self.inner.seek_controls() {
+            let clip = seek_controls.clip();
+            self.clip = (*clip.start(), *clip.end());
+            self.playhead = seek_controls.playhead();
+            self.duration = seek_controls.unclipped_stream_duration_non_zero();
+            self.will_loop = seek_controls.will_loop();
+            self.playback_speed = seek_controls.playback_speed();
+        }
+    }
+
+    /// Switch to the cache for the wrapped stream's current frames (dropping
+    /// the old one).
+    fn open_cache(&mut self) {
+        debug_assert!(!self.playing_cached);
+
+        self.cache = None;
+        self.recycled_frames.clear();
+        if !self.seekable {
+            return;
+        }
+
+        let dimensions = self.inner.dimensions();
+        let layout_key = hash((
+            self.key,
+            dimensions,
+            self.duration,
+            self.inner.target_fps().as_frac(),
+            self.playback_speed.as_frac(),
+        ));
+        self.cache = FrameCache::open(layout_key, dimensions, self.duration, self.max_bytes);
+    }
+
+    /// Start reading frames from the cache if every frame in the clip is
+    /// cached.
+    fn play_cached_if_ready(&mut self) {
+        if self.playing_cached
+            || !self
+                .cache
+                .as_ref()
+                .is_some_and(|cache| cache.covers(self.clip.0..=self.clip.1))
+        {
+            return;
+        }
+
+        util::debug_log_info!("Playing clip from frame cache.");
+        self.inner.set_paused(true);
+        self.playing_cached = true;
+        self.last_played = None;
+        self.ended = false;
+    }
+
+    /// Hand playback back to the wrapped stream, catching it up to where the
+    /// cached playback is.
+    fn stop_playing_cached(&mut self) {
+        if !self.playing_cached {
+            return;
+        }
+        self.playing_cached = false;
+
+        if let Some(seek_controls) = self.inner.seek_controls() {
+            seek_controls.set_clip(self.clip.0..=self.clip.1);
+            seek_controls.set_loop(self.will_loop);
+            if let Err(e) = seek_controls.seek_playhead(self.playhead) {
+                util::debug_log_warning!("Failed to resume clip after frame cache: {e}");
+            }
+        }
+        self.inner.set_paused(self.paused);
+        self.sync_from_inner();
+    }
+
+    /// Fetch the frame at the playhead from the cache and step the playhead
+    /// like the wrapped stream would.
+    fn fetch_cached(&mut self) -> Frame {
+        let cache = self
+            .cache
+            .as_ref()
+            .expect("only playing cached with a cache");
+
+        // Like the wrapped stream, stop on the last frame if we aren't looping.
+        if self.playhead == self.clip.1 && !self.will_loop {
+            self.ended = !self.paused || self.ended;
+            self.paused = true;
+        }
+
+        let mut frame = self
+            .recycled_frames
+            .pop()
+            .unwrap_or_else(|| Frame::new(cache.dimensions));
+        let read = cache.read(self.playhead, &mut frame);
+        debug_assert!(read, "every frame in the clip should be cached");
+
+        self.last_played = Some(self.playhead);
+        if !self.paused {
+            self.playhead = if self.playhead >= self.clip.1 {
+                self.clip.0
+            } else {
+                self.playhead + 1
+            };
+        }
+        frame
+    }
+
+    /// Stop playing from the cache if it doesn't cover the (new) clip.
+    fn check_cache_covers_clip(&mut self) {
+        let covered = self
+            .cache
+            .as_ref()
+            .is_some_and(|cache| cache.covers(self.clip.0..=self.clip.1));
+        if !covered {
+            self.stop_playing_cached();
+        }
+    }
+}
+
+impl<S: FrameStream> PlaybackStream<Frame, FrameStreamError> for CachedClip<S> {
+    fn fetch(&mut self) -> Result<Frame, FrameStreamError> {
+        if self.playing_cached {
+            return Ok(self.fetch_cached());
+        }
+
+        let frame_idx = self.playhead;
+        let frame = self.inner.fetch()?;
+        self.sync_from_inner();
+
+        if let Some(cache) = &self.cache {
+            cache.write(frame_idx, &frame);
+        }
+        self.play_cached_if_ready();
+        Ok(frame)
+    }
+
+    fn set_target_fps(&mut self, new_target_fps: Fps) {
+        if new_target_fps == self.inner.target_fps() {
+            return;
+        }
+
+        self.stop_playing_cached();
+        self.inner.set_target_fps(new_target_fps);
+        self.sync_from_inner();
+        self.open_cache();
+    }
+
+    fn target_fps(&self) -> Fps {
+        self.inner.target_fps()
+    }
+
+    fn set_paused(&mut self, paused: bool) -> bool {
+        if !self.playing_cached {
+            let paused = self.inner.set_paused(paused);
+            self.sync_from_inner();
+            return paused;
+        }
+
+        // Like the wrapped stream, un-pausing once the clip is over restarts
+        // it.
+        if self.ended && !paused {
+            if self.clip.0 == self.clip.1 {
+                return true;
+            }
+            self.playhead = self.clip.0;
+            self.ended = false;
+        }
+        self.paused = paused;
+        paused
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn seek_controls(
+        &mut self,
+    ) -> Option<&mut dyn SeekablePlaybackStream<Frame, FrameStreamError>> {
+        if self.seekable { Some(self as _) } else { None }
+    }
+
+    fn recycle(&mut self, frame: Frame) {
+        if !self.playing_cached {
+            self.inner.recycle(frame);
+            return;
+        }
+
+        if let Some(cache) = &self.cache
+            && frame.dimensions() == cache.dimensions
+        {
+            self.recycled_frames.push(frame);
+        }
+    }
+}
+
+impl<S: FrameStream> FrameStream for CachedClip<S> {
+    fn fetched_frame_changed(&self) -> bool {
+        if self.playing_cached {
+            self.last_frame_is_distinct_from_previous()
+        } else {
+            self.inner.fetched_frame_changed()
+        }
+    }
+
+    fn dimensions(&self) -> Dimensions {
+        self.inner.dimensions()
+    }
+
+    fn set_dimensions(&mut self, new_dimensions: Dimensions, rescale_method: RescaleMethod) {
+        let old = (self.inner.dimensions(), self.inner.rescale_method());
+
+        self.stop_playing_cached();
+        self.inner.set_dimensions(new_dimensions, rescale_method);
+        self.sync_from_inner();
+
+        if (self.inner.dimensions(), self.inner.rescale_method()) != old {
+            self.open_cache();
+        }
+    }
+
+    fn native_dimensions(&self) -> Dimensions {
+        self.inner.native_dimensions()
+    }
+
+    fn native_rotation(&self) -> Rotation {
+        self.inner.native_rotation()
+    }
+
+    fn native_color(&self) -> ColorInfo {
+        self.inner.native_color()
+    }
+
+    fn set_tone_map_operator(&mut self, tone_map_operator: ToneMapOperator) {
+        if self.tone_map == Some(tone_map_operator) || !self.inner.native_color().is_hdr() {
+            return;
+        }
+
+        self.stop_playing_cached();
+        self.inner.set_tone_map_operator(tone_map_operator);
+        self.sync_from_inner();
+
+        // Frames mapped differently can't share a cache.
+        self.tone_map = Some(tone_map_operator);
+        self.key = hash((self.key, tone_map_operator));
+        self.open_cache();
+    }
+
+    fn rescale_method(&self) -> Option<RescaleMethod> {
+        self.inner.rescale_method()
+    }
+
+    fn last_frame_is_distinct_from_previous(&self) -> bool {
+        if !self.playing_cached {
+            return self.inner.last_frame_is_distinct_from_previous();
+        }
+
+        // `last_played` is the frame that was just fetched, and the playhead
+        // only stays on it if we're paused.
+        !(self.paused && self.last_played == Some(self.playhead))
+    }
+
+    fn buffering_policy(&self) -> Option<BufferingPolicy> {
+        self.inner.buffering_policy()
+    }
+
+    fn set_buffering_policy(&mut self, buffering_policy: BufferingPolicy) {
+        self.inner.set_buffering_policy(buffering_policy);
+    }
+
+    fn buffering_stats(&self) -> Option<BufferingStats> {
+        self.inner.buffering_stats()
+    }
+
+    fn take_recoveries(&mut self) -> Vec<StreamRecovery> {
+        self.inner.take_recoveries()
+    }
+
+    fn is_worker_gone(&self) -> bool {
+        self.inner.is_worker_gone()
+    }
+}
+
+impl<S: FrameStream> SeekablePlaybackStream<Frame, FrameStreamError> for CachedClip<S> {
+    fn clip(&self) -> RangeInclusive<usize> {
+        self.clip.0..=self.clip.1
+    }
+
+    fn set_clip(&mut self, playback_range: RangeInclusive<usize>) -> RangeInclusive<usize> {
+        if !self.playing_cached {
+            let clip = self
+                .inner
+                .seek_controls()
+                .expect("only seekable streams are seekable")
+                .set_clip(playback_range);
+            self.sync_from_inner();
+            self.play_cached_if_ready();
+            return clip;
+        }
+
+        let last = self.duration.get() - 1;
+        let start = (*playback_range.start()).min(last);
+        let end = (*playback_range.end()).clamp(start, last);
+        self.clip = (start, end);
+        self.playhead = self.playhead.clamp(start, end);
+        self.ended = false;
+
+        self.check_cache_covers_clip();
+        start..=end
+    }
+
+    fn unclipped_stream_duration_non_zero(&self) -> NonZeroUsize {
+        self.duration
+    }
+
+    fn playhead(&self) -> usize {
+        self.playhead
+    }
+
+    fn seek_playhead(&mut self, playhead: usize) -> Result<usize, FrameStreamError> {
+        if !self.playing_cached {
+            let playhead = self
+                .inner
+                .seek_controls()
+                .expect("only seekable streams are seekable")
+                .seek_playhead(playhead)?;
+            self.sync_from_inner();
+            return Ok(playhead);
+        }
+
+        self.playhead = playhead.clamp(self.clip.0, self.clip.1);
+        self.ended = false;
+        Ok(self.playhead)
+    }
+
+    fn will_loop(&self) -> bool {
+        self.will_loop
+    }
+
+    fn set_loop(&mut self, do_loop: bool) {
+        if !self.playing_cached {
+            self.inner
+                .seek_controls()
+                .expect("only seekable streams are seekable")
+                .set_loop(do_loop);
+            self.sync_from_inner();
+            return;
+        }
+
+        self.will_loop = do_loop;
+    }
+
+    fn playback_speed(&self) -> Fps {
+        self.playback_speed
+    }
+
+    fn set_playback_speed(&mut self, multipler: Fps) {
+        if multipler == self.playback_speed {
+            return;
+        }
+
+        self.stop_playing_cached();
+        self.inner
+            .seek_controls()
+            .expect("only seekable streams are seekable")
+            .set_playback_speed(multipler);
+        self.sync_from_inner();
+        self.open_cache();
+    }
+}
+
+/// A memory mapped file with a slot for every frame of a stream, all with the
+/// same dimensions. The file is deleted when this is dropped.
+#[derive(Debug)]
+struct FrameCache {
+    path: PathBuf,
+    dimensions: Dimensions,
+    frame_len: usize,
+    len: u64,
+    slots: RwLock<CacheSlots>,
+}
+
+#[derive(Debug)]
+struct CacheSlots {
+    /// Only [None] while being dropped.
+    mmap: Option<MmapMut>,
+    /// Which slots have had their frame written.
+    written: Vec<bool>,
+}
+
+impl FrameCache {
+    /// The cache for `key`, opening it if it isn't already. [None] is returned
+    /// if `frame_count` frames don't fit in `max_bytes` (or the total cache
+    /// budget), or if the cache file can't be made.
+    fn open(
+        key: u64,
+        dimensions: Dimensions,
+        frame_count: NonZeroUsize,
+        max_bytes: u64,
+    ) -> Option<Arc<Self>> {
+        let frame_len = dimensions.area() as usize * size_of::<Pixel>();
+        let len = frame_len as u64 * frame_count.get() as u64;
+        if len > max_bytes {
+            return None;
+        }
+
+        let mut caches = CACHES.lock().expect(LOCK_NOT_POISONED);
+        caches.retain(|_, cache| cache.strong_count() > 0);
+        if let Some(cache) = caches.get(&key).and_then(Weak::upgrade) {
+            return Some(cache);
+        }
+
+        let reserved = TOTAL_CACHE_BYTES.fetch_update(
+            atomic::Ordering::SeqCst,
+            atomic::Ordering::SeqCst,
+            |total| (total + len <= MAX_TOTAL_CACHE_BYTES).then_some(total + len),
+        );
+        if reserved.is_err() {
+            util::debug_log_info!("Frame cache budget is used up, not caching clip.");
+            return None;
+        }
+
+        let path = local_data::frame_cache_path().join(format!("{}-{key:016x}", process::id()));
+        let cache = Self::create(&path, dimensions, frame_len, len, frame_count.get())
+            .inspect_err(|e| {
+                util::debug_log_warning!("Failed to create frame cache (not caching clip): {e}");
+                TOTAL_CACHE_BYTES.fetch_sub(len, atomic::Ordering::SeqCst);
+            })
+            .ok()
+            .map(Arc::new)?;
+
+        caches.insert(key, Arc::downgrade(&cache));
+        Some(cache)
+    }
+
+    fn create(
+        path: &Path,
+        dimensions: Dimensions,
+        frame_len: usize,
+        len: u64,
+        frame_count: usize,
+    ) -> io::Result<Self> {
+        remove_stale_cache_files();
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(len)?;
+
+        // SAFETY: The file was just made (or truncated) for this cache alone
+        // and nothing else writes to it. Other processes only ever delete it.
+        let mmap = unsafe { MmapMut::map_mut(&file) }.inspect_err(|_| {
+            _ = fs::remove_file(path);
+        })?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            dimensions,
+            frame_len,
+            len,
+            slots: RwLock::new(CacheSlots {
+                mmap: Some(mmap),
+                written: vec![false; frame_count],
+            }),
+        })
+    }
+
+    /// Cache `frame` as frame `frame_idx` if it isn't already.
+    fn write(&self, frame_idx: usize, frame: &Frame) {
+        if frame.dimensions() != self.dimensions {
+            return;
+        }
+
+        let mut slots = self.slots.write().expect(LOCK_NOT_POISONED);
+        let CacheSlots { mmap, written } = &mut *slots;
+        let (Some(mmap), Some(false)) = (mmap, written.get(frame_idx)) else {
+            return;
+        };
+
+        let start = frame_idx * self.frame_len;
+        mmap[start..start + self.frame_len].copy_from_slice(frame.raw_data());
+        written[frame_idx] = true;
+    }
+
+    /// Copy frame `frame_idx` into `frame` (resizing it if needed). `false` is
+    /// returned if the frame isn't cached.
+    fn read(&self, frame_idx: usize, frame: &mut Frame) -> bool {
+        let slots = self.slots.read().expect(LOCK_NOT_POISONED);
+        let (Some(mmap), Some(true)) = (&slots.mmap, slots.written.get(frame_idx)) else {
+            return false;
+        };
+
+        if frame.dimensions() != self.dimensions {
+            *frame = Frame::new(self.dimensions);
+        }
+        let start = frame_idx * self.frame_len;
+        frame
+            .raw_data_mut()
+            .copy_from_slice(&mmap[start..start + self.frame_len]);
+        true
+    }
+
+    /// Whether every frame in `range` is cached.
+    fn covers(&self, range: RangeInclusive<usize>) -> bool {
+        let slots = self.slots.read().expect(LOCK_NOT_POISONED);
+        slots
+            .written
+            .get(range)
+            .is_some_and(|written| written.iter().all(|&written| written))
+    }
+}
+
+impl Drop for FrameCache {
+    fn drop(&mut self) {
+        // The file has to be unmapped before it can be deleted on Windows.
+        drop(self.slots.get_mut().expect(LOCK_NOT_POISONED).mmap.take());
+
+        _ = fs::remove_file(&self.path).inspect_err(|e| {
+            util::debug_log_warning!("Failed to delete frame cache file (ignoring): {e}");
+        });
+        TOTAL_CACHE_BYTES.fetch_sub(self.len, atomic::Ordering::SeqCst);
+    }
+}
+
+/// Delete the cache files left behind by processes that didn't exit cleanly.
+/// This only happens once per process.
+fn remove_stale_cache_files() {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let Ok(entries) = fs::read_dir(local_data::frame_cache_path()) else {
+            return;
+        };
+
+        // Files that are still in use can't be deleted on Windows, and
+        // deleting them elsewhere doesn't affect the process using them.
+        for entry in entries.flatten() {
+            _ = fs::remove_file(entry.path());
+        }
+    });
+}
+
+fn hash(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
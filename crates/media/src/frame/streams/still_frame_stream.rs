@@ -5,9 +5,9 @@ use std::collections::VecDeque;
 
 use util::channels::message_channel::{self, Inbox};
 use util::channels::request_channel::{self, Client};
-use util::drop_join_thread::{self, DropJoinHandle};
+use util::channels::{ChannelError, ChannelResult};
 
-use super::{FrameStream, FrameStreamError, StreamGenerator};
+use super::{FrameStream, FrameStreamError, GeneratorThread, StreamGenerator};
 use crate::fps::Fps;
 use crate::frame::{Dimensions, Frame, RescaleMethod};
 use crate::playback_stream::{PlaybackStream, SeekablePlaybackStream};
@@ -62,7 +62,7 @@ pub struct StillFrameStream {
     native_dimensions: Dimensions,
 
     // Keep this field last. Channels must be dropped before joining thread.
-    _worker: DropJoinHandle<()>,
+    worker: GeneratorThread,
 }
 
 impl StillFrameStream {
@@ -78,8 +78,10 @@ impl StillFrameStream {
         let dimensions = frame.dimensions();
         let (frame_inbox, frame_outbox) = message_channel::new::<Frame>();
         let (worker_server, worker_client) = request_channel::new::<WorkerRequest, ()>();
-        let worker = drop_join_thread::spawn(move || {
-            Worker::new(&frame, target_fps).run(frame_outbox, worker_server);
+        // A panic or stall is reported, and the next `fetch` fails (see
+        // [FrameStream::is_worker_gone]).
+        let worker = GeneratorThread::spawn("Still frame stream", move |watchdog| {
+            Worker::new(&frame, target_fps).run(frame_outbox, worker_server, watchdog);
         });
 
         Self {
@@ -91,24 +93,31 @@ impl StillFrameStream {
             rescale_method: RescaleMethod::default(),
             frames_since_change: 0,
             native_dimensions: dimensions,
-            _worker: worker,
+            worker,
         }
     }
 
+    // Only fails if the worker panicked, in which case the stream can't be
+    // changed anymore and the next `fetch` fails (see
+    // [FrameStream::is_worker_gone]).
     fn worker_alert(&self, msg: WorkerRequest) {
-        self.worker_client.alert(msg).expect(EXPECT_WORKER);
+        _ = self.worker_client.alert(msg);
     }
 
-    fn worker_request_and_wait(&self, msg: WorkerRequest) {
-        let mut req = self.worker_client.request(msg).expect(EXPECT_WORKER);
+    // Only fails if the worker panicked (see [Self::worker_alert]).
+    fn worker_request_and_wait(&self, msg: WorkerRequest) -> ChannelResult<()> {
+        let mut req = self
+            .worker_client
+            .request(msg)
+            .map_err(ChannelError::unmap_msg)?;
 
         // Interrupt worker if it's waiting for us to pull from the queue.
-        self.frame_inbox.block_sender().expect(EXPECT_WORKER);
+        self.frame_inbox.block_sender()?;
 
         // Wait for the queue to be fixed.
-        req.wait().expect(EXPECT_WORKER);
+        req.wait()?;
 
-        self.frame_inbox.unblock_sender().expect(EXPECT_WORKER);
+        self.frame_inbox.unblock_sender()
     }
 }
 
@@ -118,7 +127,8 @@ impl PlaybackStream<Frame, FrameStreamError> for StillFrameStream {
 
         self.frames_since_change += 1;
 
-        Ok(self.frame_inbox.wait().expect(EXPECT_WORKER))
+        // Fails if the worker panicked, so the stream can be recreated.
+        Ok(self.frame_inbox.wait()?)
     }
 
     fn set_target_fps(&mut self, new_target_fps: Fps) {
@@ -172,7 +182,9 @@ impl FrameStream for StillFrameStream {
             return;
         }
 
-        self.worker_request_and_wait(WorkerRequest::SetDimensions(new_dimensions, rescale_method));
+        // Fails if the worker is gone, which the next `fetch` reports.
+        _ = self
+            .worker_request_and_wait(WorkerRequest::SetDimensions(new_dimensions, rescale_method));
         self.dimensions = new_dimensions;
 
         self.frames_since_change = 0;
@@ -185,9 +197,11 @@ impl FrameStream for StillFrameStream {
     fn native_dimensions(&self) -> Dimensions {
         self.native_dimensions
     }
-}
 
-const EXPECT_WORKER: &str = "The worker should be connected.";
+    fn is_worker_gone(&self) -> bool {
+        self.worker_client.connection_closed() || self.worker.is_abandoned()
+    }
+}
 
 #[derive(Debug)]
 enum WorkerRequest {
@@ -2,13 +2,19 @@
 
 use std::collections::VecDeque;
 use std::convert::Infallible;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use util::channels::message_channel::Outbox;
-use util::channels::request_channel::{ReqRes, Server};
+use util::channels::request_channel::{Queued, Server};
 use util::channels::{ChannelError, ChannelResult};
+use util::drop_join_thread::{self, DropJoinHandle};
+use util::panic_capture;
+use util::watchdog::{Watchdog, WatchdogHandle};
 
 use crate::fps::Fps;
-use crate::playback_stream::BufferingSuggestor;
+use crate::playback_stream::{BufferingPolicy, BufferingSuggestor};
 
 /// A utility for creating streams that will generate and send data ahead of
 /// time to another thread.
@@ -34,17 +40,34 @@ pub trait StreamGenerator: Sized + Send {
     /// [Inbox](util::channels::message_channel::Inbox) or the
     /// `request_server`'s [Client](util::channels::request_channel::Client) is
     /// dropped.
+    ///
+    /// The worker pings `watchdog` every time it generates data (see
+    /// [GeneratorThread]).
     fn run(
         self,
         data_outbox: Outbox<Self::Data>,
         request_server: Server<Self::Request, Self::Response>,
+        watchdog: WatchdogHandle,
     ) {
-        _ = StreamGeneratorOuter::new(self, data_outbox, request_server).run();
+        _ = StreamGeneratorOuter::new(self, data_outbox, request_server, watchdog).run();
     }
 
     /// The stream's target frame rate.
     fn target_fps(&self) -> Fps;
 
+    /// How many data items should be generated ahead of time.
+    ///
+    /// The default implementation returns [BufferingPolicy::default].
+    fn buffering_policy(&self) -> BufferingPolicy {
+        BufferingPolicy::default()
+    }
+
+    /// Called whenever the number of data items being generated ahead of time
+    /// changes (see [Self::buffering_policy]).
+    ///
+    /// The default implementation does nothing.
+    fn buffering_target_changed(&mut self, _buffering_target: usize) {}
+
     /// Generate a new piece of data to send.
     ///
     /// The number of data items already in flight (sent but not received) is
@@ -77,10 +100,72 @@ pub trait StreamGenerator: Sized + Send {
     fn create_response_for_request(&mut self, req: Self::Request) -> Self::Response;
 }
 
+/// How long a stream worker can take to generate one piece of data before
+/// it's reported as stalled (and abandoned, see [GeneratorThread]).
+const STALL_DEADLINE: Duration = Duration::from_secs(5);
+
+/// The thread a [StreamGenerator] runs on. The worker registers with the
+/// [global watchdog](Watchdog::global), and a panic is reported by
+/// [panic_capture] (the stream's channels are dropped either way).
+///
+/// The thread is joined when this is dropped, unless the watchdog found it
+/// stalled. Then it's [abandoned](Self::is_abandoned) and left to exit on its
+/// own, so the stream can be dropped and recreated without waiting on it.
+#[derive(Debug)]
+pub struct GeneratorThread {
+    thread: Option<DropJoinHandle<()>>,
+    abandoned: Arc<AtomicBool>,
+}
+
+impl GeneratorThread {
+    /// Spawn a worker thread that calls `run` (which should call
+    /// [StreamGenerator::run]) with the worker's [WatchdogHandle].
+    /// `subsystem` is the name used to report stalls and panics.
+    pub fn spawn<F>(subsystem: impl Into<String>, run: F) -> Self
+    where
+        F: FnOnce(WatchdogHandle) + Send + 'static,
+    {
+        let subsystem = subsystem.into();
+        let abandoned = Arc::new(AtomicBool::new(false));
+        let abandoned_inner = Arc::clone(&abandoned);
+
+        let thread = drop_join_thread::spawn(move || {
+            let watchdog = Watchdog::global().register_restartable(
+                subsystem.as_str(),
+                STALL_DEADLINE,
+                move || abandoned_inner.store(true, Ordering::SeqCst),
+            );
+            _ = panic_capture::catch(subsystem, || run(watchdog));
+        });
+
+        Self {
+            thread: Some(thread),
+            abandoned,
+        }
+    }
+
+    /// Whether the watchdog gave up on the worker because it stalled. The
+    /// stream should be recreated.
+    pub fn is_abandoned(&self) -> bool {
+        self.abandoned.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for GeneratorThread {
+    fn drop(&mut self) {
+        if self.is_abandoned() {
+            // Don't join: the worker is stuck. It exits once it notices the
+            // stream's channels were dropped.
+            _ = self.thread.take().map(std::thread::JoinHandle::from);
+        }
+    }
+}
+
 struct StreamGeneratorOuter<T: StreamGenerator> {
     generator: T,
     data_outbox: Outbox<T::Data>,
     request_server: Server<T::Request, T::Response>,
+    watchdog: WatchdogHandle,
     in_flight: usize,
 }
 
@@ -90,11 +175,13 @@ impl<T: StreamGenerator> StreamGeneratorOuter<T> {
         generator: T,
         data_outbox: Outbox<T::Data>,
         request_server: Server<T::Request, T::Response>,
+        watchdog: WatchdogHandle,
     ) -> Self {
         Self {
             generator,
             data_outbox,
             request_server,
+            watchdog,
             in_flight: 0,
         }
     }
@@ -102,18 +189,30 @@ impl<T: StreamGenerator> StreamGeneratorOuter<T> {
     /// Runs the stream generator, sending and handling requests.
     pub fn run(mut self) -> ChannelResult<Infallible> {
         let mut buffering_suggestor = BufferingSuggestor::new(self.generator.target_fps());
+        let mut last_buffering_target = None;
 
         loop {
+            self.watchdog.ping();
+
             let new_data = buffering_suggestor
                 .run_timed_and_sampled(|| self.generator.new_data(self.in_flight));
 
             buffering_suggestor.set_dest_fps(self.generator.target_fps());
-            let buffering_suggestion = buffering_suggestor.buffering_suggestion();
+            let buffering_target = self
+                .generator
+                .buffering_policy()
+                .target(&buffering_suggestor);
+            if last_buffering_target != Some(buffering_target) {
+                last_buffering_target = Some(buffering_target);
+                self.generator.buffering_target_changed(buffering_target);
+            }
 
-            let new_data = match self
-                .data_outbox
-                .send_bounded(new_data, buffering_suggestion)
-            {
+            // Blocks until the client takes data, which can take forever
+            // (e.g. while paused).
+            let sent = self
+                .watchdog
+                .idle_while(|| self.data_outbox.send_bounded(new_data, buffering_target));
+            let new_data = match sent {
                 Ok(in_flight) => {
                     self.in_flight = in_flight;
                     self.handle_requests()?;
@@ -137,8 +236,8 @@ impl<T: StreamGenerator> StreamGeneratorOuter<T> {
     }
 
     fn handle_requests(&mut self) -> ChannelResult<()> {
-        let mut handle_all_requests = |requests: &mut VecDeque<ReqRes<_, _>>| -> () {
-            for (mut request, res_handle) in requests.drain(..) {
+        let mut handle_all_requests = |requests: &mut VecDeque<Queued<_, _>>| -> () {
+            for (_, (mut request, res_handle)) in requests.drain(..) {
                 let queue_invalid_note = self.generator.handle_request(&mut request);
 
                 if let Some(queue_invalid_note) = queue_invalid_note {
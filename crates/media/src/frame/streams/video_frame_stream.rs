@@ -8,18 +8,23 @@ use std::mem;
 use std::num::NonZeroUsize;
 use std::ops::{Bound, RangeBounds, RangeInclusive};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use util::channels::message_channel::{self, Inbox};
 use util::channels::request_channel::{self, Client, Request};
-use util::drop_join_thread::{self, DropJoinHandle};
+use util::channels::{ChannelError, ChannelResult};
 
-use super::{FrameStream, FrameStreamError, StreamGenerator};
+use super::{FrameStream, FrameStreamError, GeneratorThread, StreamGenerator, StreamRecovery};
 use crate::ffmpeg_tools::FFmpegResult;
-use crate::ffmpeg_tools::ffmpeg_video::{FFmpegVideo, FFmpegVideoFrame};
+use crate::ffmpeg_tools::ffmpeg_video::{FFmpegVideoFrame, SharedFFmpegVideo};
 use crate::fps::{self, Fps};
-use crate::frame::{Dimensions, Frame, RescaleMethod};
-use crate::playback_stream::{PlaybackStream, SeekablePlaybackStream};
+use crate::frame::color::{ColorInfo, ToneMapOperator};
+use crate::frame::{Dimensions, Frame, RescaleMethod, Rotation};
+use crate::playback_stream::{
+    BufferingPolicy, BufferingStats, PlaybackStream, SeekablePlaybackStream,
+};
 use resampled_ffmpeg_video::ResampledFFmpegVideo;
 
 /// A builder for creating [VideoFrameStream]s. See [VideoFrameStream::builder].
@@ -32,7 +37,9 @@ pub struct VideoFrameStreamBuilder {
     will_loop: bool,
     playback_speed: Fps,
     rescale: Option<(Dimensions, RescaleMethod)>,
+    tone_map: ToneMapOperator,
     fetch_timeout: Option<Duration>,
+    buffering: BufferingPolicy,
 }
 
 impl VideoFrameStreamBuilder {
@@ -135,6 +142,17 @@ impl VideoFrameStreamBuilder {
         self
     }
 
+    /// Set how the video is tone mapped if it's HDR. If unset
+    /// [ToneMapOperator::default] is used.
+    ///
+    /// See [FrameStream::native_color] and [FrameStream::set_tone_map_operator].
+    #[must_use = "Builder methods take `Self` by value."]
+    #[inline(always)]
+    pub const fn tone_map(mut self, tone_map_operator: ToneMapOperator) -> Self {
+        self.tone_map = tone_map_operator;
+        self
+    }
+
     /// How long to wait before giving up when fetching a frame.
     ///
     /// See [VideoFrameStream::fetch_timeout] and
@@ -146,6 +164,18 @@ impl VideoFrameStreamBuilder {
         self
     }
 
+    /// Set how many frames are decoded ahead of time. If unset
+    /// [BufferingPolicy::default] is used.
+    ///
+    /// See [FrameStream::buffering_policy] and
+    /// [FrameStream::set_buffering_policy].
+    #[must_use = "Builder methods take `Self` by value."]
+    #[inline(always)]
+    pub const fn buffering(mut self, buffering_policy: BufferingPolicy) -> Self {
+        self.buffering = buffering_policy;
+        self
+    }
+
     /// Create a [VideoFrameStream].
     #[inline(always)]
     pub fn build(
@@ -167,7 +197,9 @@ impl VideoFrameStreamBuilder {
             will_loop: false,
             playback_speed: fps::consts::FPS_1,
             rescale: None,
+            tone_map: ToneMapOperator::Reinhard,
             fetch_timeout: None,
+            buffering: BufferingPolicy::Adaptive,
         }
     }
 }
@@ -184,23 +216,30 @@ pub struct VideoFrameStream {
     paused: bool,
     dimensions: Dimensions,
     rescale_method: RescaleMethod,
+    tone_map_operator: ToneMapOperator,
     clip: Clip,
     playhead: usize,
     will_loop: bool,
     playback_speed: Fps,
+    buffering_policy: BufferingPolicy,
+    buffering_target: Arc<AtomicUsize>,
+    recoveries: Arc<Mutex<Vec<StreamRecovery>>>,
 
     // Src Info (Final):
     native_dimensions: Dimensions,
     native_fps: Fps,
+    native_rotation: Rotation,
+    native_color: ColorInfo,
     unclipped_duration: NonZeroUsize,
 
     // Local State:
     fetch_timeout: Option<Duration>,
     last_frame_distinct_from_previous: bool,
     has_fetched_frame: bool,
+    underruns: u64,
 
     // Keep this field last. Channels must be dropped before joining thread.
-    _worker: DropJoinHandle<()>,
+    worker: GeneratorThread,
 }
 
 impl VideoFrameStream {
@@ -239,15 +278,19 @@ impl VideoFrameStream {
         builder: VideoFrameStreamBuilder,
         video_file_path: &Path,
     ) -> Request<Result<Self, FrameStreamError>> {
-        FFmpegVideo::new_mapped(
+        let subsystem = format!("Video decoder ({})", video_file_path.display());
+        SharedFFmpegVideo::new_mapped(
             video_file_path,
             builder.rescale,
+            builder.tone_map,
             builder.paused,
             move |ffmpeg_video| -> Result<Self, FrameStreamError> {
                 let ffmpeg_video = ResampledFFmpegVideo::new(ffmpeg_video?, builder);
 
                 let (frame_inbox, frame_outbox) = message_channel::new();
                 let (worker_server, worker_client) = request_channel::new();
+                let buffering_target = Arc::new(AtomicUsize::new(0));
+                let recoveries = Arc::new(Mutex::new(Vec::new()));
 
                 Ok(Self {
                     frame_inbox,
@@ -256,19 +299,34 @@ impl VideoFrameStream {
                     paused: ffmpeg_video.paused(),
                     dimensions: ffmpeg_video.dest_dimensions(),
                     rescale_method: ffmpeg_video.rescale_method().unwrap_or_default(),
+                    tone_map_operator: builder.tone_map,
                     clip: ffmpeg_video.clip(),
                     playhead: ffmpeg_video.playhead(),
                     will_loop: ffmpeg_video.will_loop(),
                     playback_speed: ffmpeg_video.playback_speed(),
+                    buffering_policy: builder.buffering,
+                    buffering_target: Arc::clone(&buffering_target),
+                    recoveries: Arc::clone(&recoveries),
                     native_dimensions: ffmpeg_video.src_dimensions(),
                     native_fps: ffmpeg_video.src_fps(),
+                    native_rotation: ffmpeg_video.src_rotation(),
+                    native_color: ffmpeg_video.src_color(),
                     unclipped_duration: ffmpeg_video.resampled_duration_non_zero(),
                     fetch_timeout: builder.fetch_timeout,
                     last_frame_distinct_from_previous: true,
                     has_fetched_frame: false,
-
-                    _worker: drop_join_thread::spawn(move || {
-                        Worker::new(ffmpeg_video).run(frame_outbox, worker_server);
+                    underruns: 0,
+
+                    // A panic or stall is reported, and the next `fetch` fails
+                    // (see [FrameStream::is_worker_gone]).
+                    worker: GeneratorThread::spawn(subsystem, move |watchdog| {
+                        Worker::new(
+                            ffmpeg_video,
+                            builder.buffering,
+                            buffering_target,
+                            recoveries,
+                        )
+                        .run(frame_outbox, worker_server, watchdog);
                     }),
                 })
             },
@@ -282,31 +340,51 @@ impl VideoFrameStream {
         self.unclipped_duration = new_state.duration;
     }
 
+    // Only fails if the worker panicked, in which case the stream can't be
+    // changed anymore and the next `fetch` fails (see
+    // [FrameStream::is_worker_gone]).
     fn worker_alert(&self, msg: WorkerRequest) {
         let msg = WorkerRequestAndState {
             msg,
             client_state: self.snapshot(),
         };
-        self.worker_client.alert(msg).expect(EXPECT_WORKER);
+        _ = self.worker_client.alert(msg);
     }
 
-    #[must_use]
-    fn worker_request_and_wait(&self, msg: WorkerRequest) -> PlaybackState {
+    // Only fails if the worker panicked (see [Self::worker_alert]).
+    fn worker_request_and_wait(&self, msg: WorkerRequest) -> ChannelResult<PlaybackState> {
         let msg = WorkerRequestAndState {
             msg,
             client_state: self.snapshot(),
         };
-        let mut req = self.worker_client.request(msg).expect(EXPECT_WORKER);
+        let mut req = self
+            .worker_client
+            .request(msg)
+            .map_err(ChannelError::unmap_msg)?;
 
         // Interrupt worker if it's waiting for us to pull from the queue.
-        self.frame_inbox.block_sender().expect(EXPECT_WORKER);
+        self.frame_inbox.block_sender()?;
 
         // Wait for the queue to be fixed.
-        let ret = req.wait().expect(EXPECT_WORKER);
+        let ret = req.wait()?;
+
+        self.frame_inbox.unblock_sender()?;
+
+        Ok(ret)
+    }
 
-        self.frame_inbox.unblock_sender().expect(EXPECT_WORKER);
+    /// [Self::apply_state] with the worker's response to a request. Nothing
+    /// happens if the worker is gone, which the next `fetch` reports.
+    fn apply_response(&mut self, response: ChannelResult<PlaybackState>) {
+        if let Ok(new_state) = response {
+            self.apply_state(new_state);
+        }
+    }
 
-        ret
+    fn frames_buffered(&self) -> usize {
+        self.frame_inbox
+            .with_queue_in_place(|queue| queue.len())
+            .unwrap_or(0)
     }
 
     fn snapshot(&self) -> PlaybackState {
@@ -321,16 +399,19 @@ impl VideoFrameStream {
 
 impl PlaybackStream<Frame, FrameStreamError> for VideoFrameStream {
     fn fetch(&mut self) -> Result<Frame, FrameStreamError> {
+        // The first fetch will always have to wait on the worker.
+        if self.has_fetched_frame && self.frames_buffered() == 0 {
+            self.underruns += 1;
+        }
+
         let previous_playhead = self.playhead;
+        // Channel errors are timeouts, or the worker having panicked (so the
+        // stream can be recreated).
         let (frame, new_state) = match self.fetch_timeout {
-            Some(timeout) => match self.frame_inbox.wait_timeout(timeout) {
-                Err(e) if e.is_any_timeout_error() => return Err(e.into()),
-                wait_result => wait_result,
-            },
-
+            Some(timeout) => self.frame_inbox.wait_timeout(timeout),
             None => self.frame_inbox.wait(),
         }
-        .expect(EXPECT_WORKER)?;
+        .map_err(FrameStreamError::from)??;
 
         self.last_frame_distinct_from_previous =
             !self.has_fetched_frame || new_state.playhead != previous_playhead;
@@ -347,7 +428,7 @@ impl PlaybackStream<Frame, FrameStreamError> for VideoFrameStream {
 
         let new_state = self.worker_request_and_wait(WorkerRequest::SetTargetFps(new_target_fps));
         self.target_fps = new_target_fps;
-        self.apply_state(new_state);
+        self.apply_response(new_state);
     }
 
     fn target_fps(&self) -> Fps {
@@ -361,7 +442,7 @@ impl PlaybackStream<Frame, FrameStreamError> for VideoFrameStream {
 
         let new_state = self.worker_request_and_wait(WorkerRequest::SetPaused(new_paused));
         self.paused = new_paused;
-        self.apply_state(new_state);
+        self.apply_response(new_state);
 
         self.paused
     }
@@ -403,7 +484,7 @@ impl FrameStream for VideoFrameStream {
             .worker_request_and_wait(WorkerRequest::SetDimensions(new_dimensions, rescale_method));
         self.dimensions = new_dimensions;
 
-        self.apply_state(new_state);
+        self.apply_response(new_state);
     }
 
     fn rescale_method(&self) -> Option<RescaleMethod> {
@@ -414,9 +495,57 @@ impl FrameStream for VideoFrameStream {
         self.native_dimensions
     }
 
+    fn native_rotation(&self) -> Rotation {
+        self.native_rotation
+    }
+
+    fn native_color(&self) -> ColorInfo {
+        self.native_color
+    }
+
+    fn set_tone_map_operator(&mut self, tone_map_operator: ToneMapOperator) {
+        if tone_map_operator == self.tone_map_operator || !self.native_color.is_hdr() {
+            return;
+        }
+
+        let new_state =
+            self.worker_request_and_wait(WorkerRequest::SetToneMapOperator(tone_map_operator));
+        self.tone_map_operator = tone_map_operator;
+
+        self.apply_response(new_state);
+    }
+
     fn last_frame_is_distinct_from_previous(&self) -> bool {
         self.last_frame_distinct_from_previous
     }
+
+    fn buffering_policy(&self) -> Option<BufferingPolicy> {
+        Some(self.buffering_policy)
+    }
+
+    fn set_buffering_policy(&mut self, buffering_policy: BufferingPolicy) {
+        if buffering_policy == self.buffering_policy {
+            return;
+        }
+        self.buffering_policy = buffering_policy;
+        self.worker_alert(WorkerRequest::SetBufferingPolicy(buffering_policy));
+    }
+
+    fn buffering_stats(&self) -> Option<BufferingStats> {
+        Some(BufferingStats {
+            buffered: self.frames_buffered(),
+            target: self.buffering_target.load(AtomicOrdering::Relaxed),
+            underruns: self.underruns,
+        })
+    }
+
+    fn take_recoveries(&mut self) -> Vec<StreamRecovery> {
+        mem::take(&mut *self.recoveries.lock().expect(LOCK_NOT_POISONED))
+    }
+
+    fn is_worker_gone(&self) -> bool {
+        self.worker_client.connection_closed() || self.worker.is_abandoned()
+    }
 }
 
 impl SeekablePlaybackStream<Frame, FrameStreamError> for VideoFrameStream {
@@ -432,7 +561,7 @@ impl SeekablePlaybackStream<Frame, FrameStreamError> for VideoFrameStream {
 
         let new_state = self.worker_request_and_wait(WorkerRequest::SetClip(clip));
         self.clip = clip;
-        self.apply_state(new_state);
+        self.apply_response(new_state);
 
         clip.into()
     }
@@ -454,7 +583,7 @@ impl SeekablePlaybackStream<Frame, FrameStreamError> for VideoFrameStream {
 
         let new_state = self.worker_request_and_wait(WorkerRequest::SeekPlayhead(new_playhead));
         self.playhead = new_playhead;
-        self.apply_state(new_state);
+        self.apply_state(new_state?);
 
         Ok(new_playhead)
     }
@@ -470,7 +599,7 @@ impl SeekablePlaybackStream<Frame, FrameStreamError> for VideoFrameStream {
 
         let new_state = self.worker_request_and_wait(WorkerRequest::SetLoop(will_loop));
         self.will_loop = will_loop;
-        self.apply_state(new_state);
+        self.apply_response(new_state);
     }
 
     fn playback_speed(&self) -> Fps {
@@ -485,11 +614,15 @@ impl SeekablePlaybackStream<Frame, FrameStreamError> for VideoFrameStream {
         let new_state =
             self.worker_request_and_wait(WorkerRequest::SetPlaybackSpeed(new_playback_speed));
         self.playback_speed = new_playback_speed;
-        self.apply_state(new_state);
+        self.apply_response(new_state);
     }
 }
 
-const EXPECT_WORKER: &str = "The worker should be connected.";
+const LOCK_NOT_POISONED: &str = "The lock isn't poisoned.";
+
+/// How many times in a row the worker reopens the video after an error before
+/// giving up on it.
+const MAX_REOPENS: usize = 2;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct PlaybackState {
@@ -597,21 +730,32 @@ enum WorkerRequest {
     SetPaused(bool),
     Recycle(Option<Frame>),
     SetDimensions(Dimensions, RescaleMethod),
+    SetToneMapOperator(ToneMapOperator),
     SetClip(Clip),
     SeekPlayhead(usize),
     SetLoop(bool),
     SetPlaybackSpeed(Fps),
+    SetBufferingPolicy(BufferingPolicy),
 }
 
 struct Worker {
     ffmpeg_video: ResampledFFmpegVideo,
     recycled_frames: Vec<FFmpegVideoFrame>,
     err_state: Option<FrameStreamError>,
+    buffering_policy: BufferingPolicy,
+    buffering_target: Arc<AtomicUsize>,
+    /// Errors recovered from that the client hasn't taken yet.
+    recoveries: Arc<Mutex<Vec<StreamRecovery>>>,
 }
 
 impl Worker {
     /// Create a new [Worker].
-    pub fn new(ffmpeg_video: ResampledFFmpegVideo) -> Self {
+    pub fn new(
+        ffmpeg_video: ResampledFFmpegVideo,
+        buffering_policy: BufferingPolicy,
+        buffering_target: Arc<AtomicUsize>,
+        recoveries: Arc<Mutex<Vec<StreamRecovery>>>,
+    ) -> Self {
         let recycled_frames = Vec::with_capacity(32);
 
         let mut state_history = VecDeque::with_capacity(32);
@@ -621,12 +765,46 @@ impl Worker {
             ffmpeg_video,
             recycled_frames,
             err_state: None,
+            buffering_policy,
+            buffering_target,
+            recoveries,
         }
     }
 
+    /// Write the next frame, reopening the video at the frame that failed (up
+    /// to [MAX_REOPENS] times) if there's an error.
     fn write_next_frame(&mut self) -> FFmpegResult<FFmpegVideoFrame> {
-        let recycled_frame = self.recycled_frames.pop();
-        self.ffmpeg_video.write_next(recycled_frame)
+        let mut reopens = 0;
+        loop {
+            let playhead = self.ffmpeg_video.playhead();
+            let recycled_frame = self.recycled_frames.pop();
+            let e = match self.ffmpeg_video.write_next(recycled_frame) {
+                Err(e) if reopens < MAX_REOPENS => e,
+                result => return result,
+            };
+            reopens += 1;
+
+            // Writing moves the playhead even if it fails.
+            self.ffmpeg_video.seek_playhead(playhead);
+            let reopened = self.ffmpeg_video.reopen();
+            match &reopened {
+                Ok(()) => util::debug_log_warning!(
+                    "Reopened video at frame {playhead} after an error ({reopens}/{MAX_REOPENS}): {e}"
+                ),
+                Err(reopen_error) => util::debug_log_warning!(
+                    "Failed to reopen video at frame {playhead} after an error ({e}): {reopen_error}"
+                ),
+            }
+            self.recoveries
+                .lock()
+                .expect(LOCK_NOT_POISONED)
+                .push(StreamRecovery {
+                    playhead,
+                    error: e.into(),
+                    recovered: reopened.is_ok(),
+                });
+            reopened?;
+        }
     }
 }
 
@@ -640,6 +818,15 @@ impl StreamGenerator for Worker {
         self.ffmpeg_video.target_fps()
     }
 
+    fn buffering_policy(&self) -> BufferingPolicy {
+        self.buffering_policy
+    }
+
+    fn buffering_target_changed(&mut self, buffering_target: usize) {
+        self.buffering_target
+            .store(buffering_target, AtomicOrdering::Relaxed);
+    }
+
     fn new_data(&mut self, _in_flight: usize) -> Self::Data {
         if let Some(e) = &self.err_state {
             return Err(e.clone());
@@ -673,10 +860,16 @@ impl StreamGenerator for Worker {
                 // We don't need to update the queue for this.
                 None
             }
+            WorkerRequest::SetBufferingPolicy(buffering_policy) => {
+                // The new bound takes effect the next time a frame is sent.
+                self.buffering_policy = *buffering_policy;
+                None
+            }
 
             // If we need to update the queue, we'll handle the request in
             // `Self::handle_invalid_queue` when we can actually see the queue.
             WorkerRequest::SetDimensions(_, _) => Some(()),
+            WorkerRequest::SetToneMapOperator(_) => Some(()),
             WorkerRequest::SetTargetFps(_) => Some(()),
             WorkerRequest::SetPaused(_) => Some(()),
             WorkerRequest::SetClip(_) => Some(()),
@@ -701,7 +894,7 @@ impl StreamGenerator for Worker {
 
         match &mut req.msg {
             // We shouldn't be in this function if this was the request.
-            WorkerRequest::Recycle(_) => unreachable!(),
+            WorkerRequest::Recycle(_) | WorkerRequest::SetBufferingPolicy(_) => unreachable!(),
 
             // We can't fix frames with bad dimensions.
             WorkerRequest::SetDimensions(dimensions, rescale_method) => {
@@ -712,6 +905,15 @@ impl StreamGenerator for Worker {
                 return;
             }
 
+            // Frames that were already mapped would need to be decoded again.
+            WorkerRequest::SetToneMapOperator(tone_map_operator) => {
+                if let Err(e) = self.ffmpeg_video.set_tone_map_operator(*tone_map_operator) {
+                    self.err_state = Some(e.into());
+                }
+                queue.clear(); // queue not salvageable
+                return;
+            }
+
             // These completely change the meaning of the playhead. Not worth
             // the effort of fixing.
             WorkerRequest::SetTargetFps(target_fps) => {
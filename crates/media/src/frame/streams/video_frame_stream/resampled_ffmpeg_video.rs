@@ -4,17 +4,18 @@ use std::num::NonZeroUsize;
 
 use super::{Clip, VideoFrameStreamBuilder};
 use crate::ffmpeg_tools::FFmpegResult;
-use crate::ffmpeg_tools::ffmpeg_video::{FFmpegVideo, FFmpegVideoFrame};
+use crate::ffmpeg_tools::ffmpeg_video::{FFmpegVideoFrame, SharedFFmpegVideo};
 use crate::fps::{self, Fps, Resampler};
-use crate::frame::{Dimensions, RescaleMethod};
+use crate::frame::color::{ColorInfo, ToneMapOperator};
+use crate::frame::{Dimensions, RescaleMethod, Rotation};
 
-/// An extended [FFmpegVideo] that supports FPS resampling, custom playback
-/// speeds, looping, and clipping.
+/// An extended [SharedFFmpegVideo] that supports FPS resampling, custom
+/// playback speeds, looping, and clipping.
 ///
 /// If any method returns an error, the state of the object becomes undefined.
 #[derive(Debug)]
 pub struct ResampledFFmpegVideo {
-    ffmpeg_video: FFmpegVideo,
+    ffmpeg_video: SharedFFmpegVideo,
     fps_resampler: Resampler,
     target_fps: Fps,
     playback_speed: Fps,
@@ -32,9 +33,11 @@ pub struct ResampledFFmpegVideo {
 impl ResampledFFmpegVideo {
     /// Create a new [ResampledFFmpegVideo].
     ///
-    /// `builder`'s [rescale](VideoFrameStreamBuilder::rescale) and
-    /// [fetch_timeout](VideoFrameStreamBuilder::fetch_timeout) are ignored.
-    pub fn new(ffmpeg_video: FFmpegVideo, builder: VideoFrameStreamBuilder) -> Self {
+    /// `builder`'s [rescale](VideoFrameStreamBuilder::rescale),
+    /// [tone_map](VideoFrameStreamBuilder::tone_map),
+    /// [fetch_timeout](VideoFrameStreamBuilder::fetch_timeout), and
+    /// [buffering](VideoFrameStreamBuilder::buffering) are ignored.
+    pub fn new(ffmpeg_video: SharedFFmpegVideo, builder: VideoFrameStreamBuilder) -> Self {
         let VideoFrameStreamBuilder {
             target_fps,
             paused,
@@ -43,7 +46,9 @@ impl ResampledFFmpegVideo {
             will_loop,
             playback_speed,
             rescale: _,
+            tone_map: _,
             fetch_timeout: _,
+            buffering: _,
         } = builder;
 
         let src_fps = ffmpeg_video.src_fps();
@@ -76,8 +81,8 @@ impl ResampledFFmpegVideo {
         ret
     }
 
-    /// Like [FFmpegVideo::write_next] except it handles all the extra things
-    /// that the resampled video can handle.
+    /// Like [SharedFFmpegVideo::write_next] except it handles all the extra
+    /// things that the resampled video can handle.
     pub fn write_next(
         &mut self,
         recycled_frame: Option<FFmpegVideoFrame>,
@@ -119,8 +124,8 @@ impl ResampledFFmpegVideo {
     /// that will be written. The actual new playhead (after being clamped to be
     /// within the clip) is returned.
     ///
-    /// The underlying [FFmpegVideo] is not seeked. That doesn't happen until
-    /// the next call to [Self::write_next].
+    /// The underlying [SharedFFmpegVideo] is not seeked. That doesn't happen
+    /// until the next call to [Self::write_next].
     pub fn seek_playhead(&mut self, new_playhead: usize) -> usize {
         self.debug_assert_state_is_valid();
 
@@ -282,6 +287,33 @@ impl ResampledFFmpegVideo {
         self.ffmpeg_video.src_dimensions()
     }
 
+    /// How the frames in this video should be rotated to be displayed upright.
+    pub const fn src_rotation(&self) -> Rotation {
+        self.ffmpeg_video.src_rotation()
+    }
+
+    /// The color primaries and transfer function of this video.
+    pub const fn src_color(&self) -> ColorInfo {
+        self.ffmpeg_video.src_color()
+    }
+
+    /// Set how HDR frames are tone mapped to SDR.
+    pub fn set_tone_map_operator(&mut self, operator: ToneMapOperator) -> FFmpegResult<()> {
+        self.debug_assert_state_is_valid();
+        let result = self.ffmpeg_video.set_tone_map_operator(operator);
+        self.debug_assert_state_is_valid();
+        result
+    }
+
+    /// Reopen the video's decoder after an error (see
+    /// [SharedFFmpegVideo::reopen]). The playhead isn't moved.
+    pub fn reopen(&mut self) -> FFmpegResult<()> {
+        self.debug_assert_state_is_valid();
+        let result = self.ffmpeg_video.reopen();
+        self.debug_assert_state_is_valid();
+        result
+    }
+
     /// The dimensions of the frames that will be produced.
     pub const fn dest_dimensions(&self) -> Dimensions {
         self.ffmpeg_video.dest_dimensions()
@@ -1,5 +1,8 @@
 //! This library contains functionality for managing and playing back media.
 
+pub mod audio;
+pub mod diagnostics;
+pub mod encoding;
 pub mod fps;
 pub mod frame;
 pub mod midi;
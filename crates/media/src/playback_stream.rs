@@ -11,6 +11,9 @@ use crate::fps::{self, Fps, FpsError};
 mod buffering_suggestor;
 pub use buffering_suggestor::BufferingSuggestor;
 
+mod buffering_policy;
+pub use buffering_policy::{BufferingPolicy, BufferingStats};
+
 /// A stream of data where data is intended to be fetched (played back) at a
 /// known frame rate (target [FPS](Fps)).
 pub trait PlaybackStream<T, E>: Any + 'static {
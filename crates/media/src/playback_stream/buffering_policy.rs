@@ -0,0 +1,92 @@
+//! Exports [BufferingPolicy] and [BufferingStats].
+
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use super::BufferingSuggestor;
+
+/// Decides how many items a data producer should keep buffered (queued) ahead
+/// of a data consumer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BufferingPolicy {
+    /// Always keep this many items buffered.
+    Fixed(NonZeroUsize),
+
+    /// Keep enough items buffered to cover this much playback time at the
+    /// consumer's frame rate.
+    Lookahead(Duration),
+
+    /// Adjust the number of buffered items based on how long (and how
+    /// consistently long) it takes to produce them. See [BufferingSuggestor].
+    #[default]
+    Adaptive,
+}
+
+impl BufferingPolicy {
+    /// The number of items that should be buffered, given a `suggestor` that
+    /// has been sampling production times. This is always at least 1.
+    pub fn target(&self, suggestor: &BufferingSuggestor) -> usize {
+        let target = match *self {
+            Self::Fixed(items) => items.get(),
+            Self::Lookahead(lookahead) => {
+                let interval = suggestor.consumer_interval().as_secs_f64();
+                (lookahead.as_secs_f64() / interval).ceil() as usize
+            }
+            Self::Adaptive => suggestor.buffering_suggestion(),
+        };
+
+        target.max(1)
+    }
+}
+
+/// A snapshot of how a stream's buffer is doing, intended to be shown to the
+/// user.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BufferingStats {
+    /// The number of items currently buffered and ready to be fetched.
+    pub buffered: usize,
+
+    /// The number of items the producer is trying to keep buffered (see
+    /// [BufferingPolicy::target]).
+    pub target: usize,
+
+    /// The number of times an item was fetched while the buffer was empty
+    /// (i.e. the consumer had to wait on the producer).
+    pub underruns: u64,
+}
+
+impl BufferingStats {
+    /// How full the buffer is relative to its target, from `0.0` (empty) to
+    /// `1.0` (full or over-full).
+    pub fn fill_level(&self) -> f32 {
+        if self.target == 0 {
+            return 1.0;
+        }
+        (self.buffered as f32 / self.target as f32).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fps::Fps;
+
+    // --- BufferingPolicy::target() ---
+
+    #[test]
+    fn test_target() {
+        let suggestor = BufferingSuggestor::new(Fps::from_int(30).unwrap());
+
+        let fixed = BufferingPolicy::Fixed(NonZeroUsize::new(6).unwrap());
+        assert_eq!(fixed.target(&suggestor), 6);
+
+        let lookahead = BufferingPolicy::Lookahead(Duration::from_millis(490));
+        assert_eq!(lookahead.target(&suggestor), 15);
+
+        let lookahead = BufferingPolicy::Lookahead(Duration::ZERO);
+        assert_eq!(lookahead.target(&suggestor), 1);
+
+        assert!(BufferingPolicy::Adaptive.target(&suggestor) >= 1);
+    }
+}
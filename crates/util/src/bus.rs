@@ -0,0 +1,285 @@
+//! This module contains a typed publish/subscribe bus for loosely coupled
+//! notifications between subsystems (e.g. "the theme changed" or "the timeline
+//! was seeked") that shouldn't need to know about each other.
+//!
+//! A [Topic] carries one message type. Every [Subscription] to it gets its own
+//! [message_channel] inbox with a copy of each message published after it
+//! subscribed. Subscriptions are weak: dropping one is all it takes to
+//! unsubscribe, and the topic forgets about it the next time it publishes.
+//!
+//! Topics for stateful values (like the current theme) can replay the latest
+//! message to new subscribers so they don't have to wait for the next change to
+//! know the current state (see [TopicMessage::REPLAY_LATEST]).
+//!
+//! [Bus] hands out one topic per message type, so publishers and subscribers
+//! only have to agree on a type.
+//!
+//! # Example
+//!
+//! ```ignore
+//! #[derive(Clone)]
+//! struct ThemeChanged(Theme);
+//!
+//! impl TopicMessage for ThemeChanged {
+//!     const REPLAY_LATEST: bool = true;
+//! }
+//!
+//! let themes = Bus::global().subscribe::<ThemeChanged>();
+//! Bus::global().publish(ThemeChanged(Theme::Dark));
+//!
+//! // Somewhere else, once per frame.
+//! if let Some(ThemeChanged(theme)) = themes.latest() {
+//!     apply(theme);
+//! }
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::channels::ChannelError;
+use crate::channels::message_channel::{self, Inbox, Outbox};
+
+const LOCK_PANIC_MSG: &str = "Another thread panicked while holding a bus lock.";
+
+/// A message type that can be published on a [Bus].
+pub trait TopicMessage: Clone + Send + 'static {
+    /// Whether new subscribers are sent the last message published before they
+    /// subscribed. This is for messages that describe state rather than
+    /// events.
+    const REPLAY_LATEST: bool = false;
+}
+
+/// A publish/subscribe topic for messages of type `T`. See the
+/// [module docs](self).
+///
+/// Cloning a topic gives another handle to the same topic.
+pub struct Topic<T> {
+    inner: Arc<Mutex<TopicInner<T>>>,
+}
+
+struct TopicInner<T> {
+    subscribers: Vec<Outbox<T>>,
+    replay_latest: bool,
+    latest: Option<T>,
+}
+
+impl<T: Clone> Topic<T> {
+    /// Create a topic that only sends subscribers messages published after
+    /// they subscribed.
+    pub fn new() -> Self {
+        Self::with_replay(false)
+    }
+
+    /// Create a topic that also sends new subscribers the last message
+    /// published before they subscribed.
+    pub fn replaying_latest() -> Self {
+        Self::with_replay(true)
+    }
+
+    fn with_replay(replay_latest: bool) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(TopicInner {
+                subscribers: Vec::new(),
+                replay_latest,
+                latest: None,
+            })),
+        }
+    }
+
+    /// Send `msg` to every subscriber, forgetting subscriptions that have been
+    /// dropped.
+    pub fn publish(&self, msg: T) {
+        let mut inner = self.inner.lock().expect(LOCK_PANIC_MSG);
+        inner.subscribers.retain(|subscriber| {
+            !matches!(
+                subscriber.send(msg.clone()),
+                Err(ChannelError::ConnectionDropped)
+            )
+        });
+        if inner.replay_latest {
+            inner.latest = Some(msg);
+        }
+    }
+
+    /// Subscribe to messages published from now on (and the latest one, if the
+    /// topic [replays it](Self::replaying_latest)).
+    pub fn subscribe(&self) -> Subscription<T> {
+        let (inbox, outbox) = message_channel::new();
+
+        let mut inner = self.inner.lock().expect(LOCK_PANIC_MSG);
+        if let Some(latest) = &inner.latest {
+            _ = outbox.send(latest.clone());
+        }
+        inner.subscribers.push(outbox);
+
+        Subscription { inbox }
+    }
+
+    /// The last message published, if the topic
+    /// [replays it](Self::replaying_latest).
+    pub fn latest(&self) -> Option<T> {
+        self.inner.lock().expect(LOCK_PANIC_MSG).latest.clone()
+    }
+
+    /// The number of subscriptions that haven't been dropped.
+    pub fn subscriber_count(&self) -> usize {
+        let mut inner = self.inner.lock().expect(LOCK_PANIC_MSG);
+        inner
+            .subscribers
+            .retain(|subscriber| subscriber.connection_open());
+        inner.subscribers.len()
+    }
+}
+
+impl<T: Clone> Default for Topic<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for Topic<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+/// A subscriber's end of a [Topic]. Dropping it unsubscribes.
+pub struct Subscription<T> {
+    inbox: Inbox<T>,
+}
+
+impl<T> Subscription<T> {
+    /// The oldest message that hasn't been received yet, if there is one.
+    pub fn check(&self) -> Option<T> {
+        self.inbox.check().ok().flatten()
+    }
+
+    /// Every message that hasn't been received yet, oldest first.
+    pub fn check_all(&self) -> Vec<T> {
+        self.inbox
+            .check_all()
+            .ok()
+            .flatten()
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+
+    /// The newest message that hasn't been received yet, skipping (and
+    /// discarding) any older ones.
+    pub fn latest(&self) -> Option<T> {
+        self.inbox.check_all().ok().flatten()?.pop_back()
+    }
+
+    /// Wait for a message. [None] is returned once every handle to the topic
+    /// has been dropped and all messages have been received.
+    pub fn wait(&self) -> Option<T> {
+        self.inbox.wait().ok()
+    }
+
+    /// The underlying inbox, for the rest of the [Inbox] API.
+    pub fn inbox(&self) -> &Inbox<T> {
+        &self.inbox
+    }
+}
+
+/// One [Topic] for each [TopicMessage] type. See the [module docs](self).
+#[derive(Default)]
+pub struct Bus {
+    topics: Mutex<HashMap<TypeId, Box<dyn Any + Send>>>,
+}
+
+impl Bus {
+    /// Create a bus with no topics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide bus.
+    pub fn global() -> &'static Bus {
+        static GLOBAL: OnceLock<Bus> = OnceLock::new();
+        GLOBAL.get_or_init(Bus::new)
+    }
+
+    /// The topic for messages of type `T`, which is created the first time
+    /// it's asked for.
+    pub fn topic<T: TopicMessage>(&self) -> Topic<T> {
+        let mut topics = self.topics.lock().expect(LOCK_PANIC_MSG);
+        topics
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Topic::<T>::with_replay(T::REPLAY_LATEST)))
+            .downcast_ref::<Topic<T>>()
+            .expect("Topics are stored under their message's type ID.")
+            .clone()
+    }
+
+    /// Send `msg` to every subscriber of its type's topic.
+    pub fn publish<T: TopicMessage>(&self, msg: T) {
+        self.topic::<T>().publish(msg);
+    }
+
+    /// Subscribe to the topic for messages of type `T`.
+    pub fn subscribe<T: TopicMessage>(&self) -> Subscription<T> {
+        self.topic::<T>().subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Seeked(u32);
+
+    impl TopicMessage for Seeked {}
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Renamed(&'static str);
+
+    impl TopicMessage for Renamed {
+        const REPLAY_LATEST: bool = true;
+    }
+
+    // --- Topic::publish() ---
+
+    #[test]
+    fn every_subscriber_gets_each_message() {
+        let bus = Bus::new();
+        let first = bus.subscribe::<Seeked>();
+        bus.publish(Seeked(1));
+        let second = bus.subscribe::<Seeked>();
+        bus.publish(Seeked(2));
+
+        assert_eq!(first.check_all(), [Seeked(1), Seeked(2)]);
+        assert_eq!(second.check_all(), [Seeked(2)]);
+        assert_eq!(bus.subscribe::<Seeked>().check(), None);
+    }
+
+    #[test]
+    fn dropped_subscriptions_are_forgotten() {
+        let topic = Topic::new();
+        let kept = topic.subscribe();
+        let dropped = topic.subscribe();
+        assert_eq!(topic.subscriber_count(), 2);
+
+        drop(dropped);
+        topic.publish(1);
+        assert_eq!(topic.subscriber_count(), 1);
+        assert_eq!(kept.latest(), Some(1));
+    }
+
+    // --- Topic::subscribe() ---
+
+    #[test]
+    fn stateful_topics_replay_the_latest_message() {
+        let bus = Bus::new();
+        bus.publish(Renamed("old"));
+        bus.publish(Renamed("new"));
+
+        let subscription = bus.subscribe::<Renamed>();
+        assert_eq!(subscription.check_all(), [Renamed("new")]);
+        assert_eq!(bus.topic::<Renamed>().latest(), Some(Renamed("new")));
+    }
+}
@@ -1,14 +1,22 @@
 //! This module contains the submodules [message_channel] and [request_channel],
 //! 2 kinds of single producer single consumer queue-based message passing
-//! systems.
-
+//! systems, [mailbox], a two-way conversation made of 2 message channels, and
+//! [sample_channel], a single producer single consumer channel for live values
+//! that only keeps the latest value per key.
+//!
+//! Messages sent through any of them can be wrapped in [traced::Traced] to
+//! measure how long they take to get from one thread to another.
+
+pub mod mailbox;
 pub mod message_channel;
 pub mod request_channel;
+pub mod sample_channel;
+pub mod traced;
 
 mod conn_n;
 
 use conn_n::ConnN;
-use request_channel::ReqRes;
+use request_channel::Queued;
 
 use std::convert::Infallible;
 use std::time::Duration;
@@ -164,9 +172,9 @@ impl<T> ChannelError<T> {
     }
 }
 
-impl<Q, A> ChannelError<ReqRes<Q, A>> {
+impl<Q, A> ChannelError<Queued<Q, A>> {
     fn map_to_req(self) -> ChannelError<Q> {
-        self.map_msg(|(req, _)| req)
+        self.map_msg(|(_, (req, _))| req)
     }
 }
 
@@ -0,0 +1,124 @@
+//! This module contains [Mailbox], one end of a two-way conversation between
+//! two threads (e.g. a worker and whoever sends it work) made of a pair of
+//! [message_channel]s.
+//!
+//! See [new] to construct.
+
+use std::collections::VecDeque;
+
+use super::ChannelResult;
+use super::message_channel::{self, Inbox, Outbox};
+
+/// One end of a two-way conversation. Messages of type `S` are sent to the
+/// other end, and messages of type `R` are received from it.
+///
+/// The methods here cover the common cases. See [Self::outbox] and
+/// [Self::inbox] for everything else.
+///
+/// See [new] to construct.
+#[derive(Debug)]
+pub struct Mailbox<S, R> {
+    outbox: Outbox<S>,
+    inbox: Inbox<R>,
+}
+
+impl<S, R> Mailbox<S, R> {
+    /// Send a message to the other end. See [Outbox::send].
+    pub fn send(&self, msg: S) -> ChannelResult<usize, S> {
+        self.outbox.send(msg)
+    }
+
+    /// Wait for a message from the other end. See [Inbox::wait].
+    pub fn wait(&self) -> ChannelResult<R> {
+        self.inbox.wait()
+    }
+
+    /// Wait for a message from the other end, returning all messages if
+    /// multiple have built up. See [Inbox::wait_all].
+    pub fn wait_all(&self) -> ChannelResult<VecDeque<R>> {
+        self.inbox.wait_all()
+    }
+
+    /// Receive a message from the other end if one is waiting. See
+    /// [Inbox::check_non_blocking].
+    pub fn check_non_blocking(&self) -> ChannelResult<Option<R>> {
+        self.inbox.check_non_blocking()
+    }
+
+    /// Receive every message from the other end that's waiting. See
+    /// [Inbox::check_non_blocking_all].
+    pub fn check_non_blocking_all(&self) -> ChannelResult<Option<VecDeque<R>>> {
+        self.inbox.check_non_blocking_all()
+    }
+
+    /// The half of the mailbox messages are sent from.
+    pub fn outbox(&self) -> &Outbox<S> {
+        &self.outbox
+    }
+
+    /// The half of the mailbox messages are received in.
+    pub fn inbox(&self) -> &Inbox<R> {
+        &self.inbox
+    }
+
+    /// Split the mailbox into its [Outbox] and [Inbox].
+    pub fn split(self) -> (Outbox<S>, Inbox<R>) {
+        (self.outbox, self.inbox)
+    }
+}
+
+/// Create both ends of a two-way conversation. The first [Mailbox] sends `A`s
+/// and receives `B`s, and the second sends `B`s and receives `A`s.
+///
+/// Each end can send and receive as long as the other end hasn't been dropped
+/// (see [message_channel::new]).
+pub fn new<A, B>() -> (Mailbox<A, B>, Mailbox<B, A>) {
+    let (a_inbox, a_outbox) = message_channel::new::<A>();
+    let (b_inbox, b_outbox) = message_channel::new::<B>();
+    (
+        Mailbox {
+            outbox: a_outbox,
+            inbox: b_inbox,
+        },
+        Mailbox {
+            outbox: b_outbox,
+            inbox: a_inbox,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn messages_go_both_ways() {
+        let (client, worker) = new::<i32, String>();
+
+        let thread = thread::spawn(move || {
+            while let Ok(n) = worker.wait() {
+                assert!(worker.send(n.to_string()).is_ok());
+            }
+        });
+
+        assert!(client.send(1).is_ok());
+        assert!(client.send(2).is_ok());
+        assert_eq!(client.wait(), Ok("1".to_string()));
+        assert_eq!(client.wait(), Ok("2".to_string()));
+
+        drop(client);
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn dropping_one_end_closes_both_directions() {
+        let (client, worker) = new::<i32, i32>();
+        drop(worker);
+
+        assert!(client.send(1).is_err());
+        assert!(client.wait().is_err());
+        assert!(client.outbox().connection_closed());
+    }
+}
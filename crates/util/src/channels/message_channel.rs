@@ -5,7 +5,8 @@
 use std::collections::VecDeque;
 use std::num::NonZeroUsize;
 use std::ops::{Deref, DerefMut};
-use std::sync::{Condvar, Mutex, MutexGuard, TryLockError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError, TryLockError};
 use std::time::{Duration, Instant};
 
 use super::{ChannelError, ChannelResult, ConnN, THREAD_PANIC_MSG};
@@ -379,7 +380,7 @@ impl<T> Inbox<T> {
     /// Also see [Self::block_sender], [Self::unblock_sender], and
     /// [Self::is_send_blocked].
     pub fn set_send_blocked(&self, send_blocked: bool) -> ChannelResult<()> {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         let mut queue = self.channel.queue.lock().expect(THREAD_PANIC_MSG);
 
@@ -406,7 +407,7 @@ impl<T> Inbox<T> {
     /// Also see [Self::block_sender], [Self::unblock_sender], and
     /// [Self::set_send_blocked].
     pub fn is_send_blocked(&self) -> ChannelResult<bool> {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         Ok(self.channel.queue.lock().expect(THREAD_PANIC_MSG).rule == SendRule::Block)
     }
@@ -414,7 +415,7 @@ impl<T> Inbox<T> {
     /// Whether the other party still has their end of the connection alive, the
     /// inverse of [Self::connection_closed].
     pub fn connection_open(&self) -> bool {
-        super::connection_not_dropped(&self.channel)
+        self.channel.connected()
     }
 
     /// Whether the other party has dropped their end of the connection, the
@@ -433,7 +434,7 @@ impl<T> Inbox<T> {
     where
         F: FnOnce(&mut VecDeque<T>) -> R,
     {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
         let mut queue = self.channel.queue.lock().expect(THREAD_PANIC_MSG);
         Ok(self.mutate_queue(&mut queue, |q| f(q)))
     }
@@ -461,7 +462,7 @@ impl<T> Inbox<T> {
 
         // If there are no messages we need to make sure the other end hasn't
         // hung up.
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         loop {
             queue = self.channel.notifier.wait(queue).expect(THREAD_PANIC_MSG);
@@ -473,7 +474,7 @@ impl<T> Inbox<T> {
             // No messages after waking up means one of two things:
             // 1. The other end hung up.
             // 2. This was a spurious (early) wakeup (should go back to sleep).
-            super::ensure_connection_not_dropped(&self.channel)?;
+            self.channel.ensure_connected()?;
         }
     }
 
@@ -491,7 +492,7 @@ impl<T> Inbox<T> {
 
         // If there are no messages we need to make sure the other end hasn't
         // hung up.
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         let deadline = Instant::now() + timeout;
 
@@ -516,7 +517,7 @@ impl<T> Inbox<T> {
             // No messages after waking up means one of two things:
             // 1. The other end hung up.
             // 2. This was a spurious (early) wakeup (should go back to sleep).
-            super::ensure_connection_not_dropped(&self.channel)?;
+            self.channel.ensure_connected()?;
         }
     }
 
@@ -533,7 +534,7 @@ impl<T> Inbox<T> {
 
         // If there are no messages we need to make sure the other end hasn't
         // hung up.
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         Ok(None)
     }
@@ -551,7 +552,7 @@ impl<T> Inbox<T> {
                 } else {
                     // If there are no messages we need to make sure the other
                     // end hasn't hung up.
-                    super::ensure_connection_not_dropped(&self.channel)?;
+                    self.channel.ensure_connected()?;
 
                     Ok(None)
                 }
@@ -597,7 +598,7 @@ impl<T> Inbox<T> {
 // doesn't just wait forever.
 impl<T> Drop for Inbox<T> {
     fn drop(&mut self) {
-        self.channel.notifier.notify_one();
+        self.channel.hang_up();
     }
 }
 
@@ -620,7 +621,7 @@ impl<T> Outbox<T> {
     ///
     /// Also see [Self::send_bounded] and [Self::send_bounded_timeout].
     pub fn send(&self, msg: T) -> ChannelResult<usize, T> {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         let mut queue = self.channel.queue.lock().expect(THREAD_PANIC_MSG);
         if queue.rule == SendRule::Block {
@@ -650,7 +651,7 @@ impl<T> Outbox<T> {
     ///
     /// Also see [Self::send] and [Self::send_bounded_timeout].
     pub fn send_bounded(&self, msg: T, max_in_flight: usize) -> ChannelResult<usize, T> {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         let mut queue = self.channel.queue.lock().expect(THREAD_PANIC_MSG);
         if queue.rule == SendRule::Block {
@@ -673,7 +674,7 @@ impl<T> Outbox<T> {
                     break;
                 }
 
-                super::ensure_connection_not_dropped(&self.channel)?;
+                self.channel.ensure_connected()?;
             }
 
             queue.rule = SendRule::None;
@@ -711,7 +712,7 @@ impl<T> Outbox<T> {
         max_in_flight: usize,
         timeout: Duration,
     ) -> ChannelResult<usize, T> {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         let mut queue = self.channel.queue.lock().expect(THREAD_PANIC_MSG);
         if queue.rule == SendRule::Block {
@@ -751,7 +752,7 @@ impl<T> Outbox<T> {
                 // 1. The other end hung up.
                 // 2. This was a spurious (early) wakeup (should go back to
                 //    sleep).
-                super::ensure_connection_not_dropped(&self.channel)?;
+                self.channel.ensure_connected()?;
             }
 
             queue.rule = SendRule::None;
@@ -778,7 +779,7 @@ impl<T> Outbox<T> {
     where
         F: FnOnce(&mut VecDeque<T>) -> R,
     {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
         let mut queue = self.channel.queue.lock().expect(THREAD_PANIC_MSG);
         if queue.rule == SendRule::Block {
             return Err(ChannelError::SendBlockedNoMsg);
@@ -815,7 +816,7 @@ impl<T> Outbox<T> {
     /// A [ChannelError::ConnectionDropped] error is returned if the other end
     /// of the connection was dropped.
     pub fn messages_in_flight(&self) -> ChannelResult<usize> {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         Ok(self.channel.queue.lock().expect(THREAD_PANIC_MSG).len())
     }
@@ -827,7 +828,7 @@ impl<T> Outbox<T> {
     /// A [ChannelError::ConnectionDropped] error is returned if the other end
     /// of the connection was dropped.
     pub fn is_send_blocked(&self) -> ChannelResult<bool> {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         Ok(self.channel.queue.lock().expect(THREAD_PANIC_MSG).rule == SendRule::Block)
     }
@@ -838,7 +839,7 @@ impl<T> Outbox<T> {
     /// A [ChannelError::ConnectionDropped] error is returned if the other end
     /// of the connection was dropped.
     pub fn wait_for_send_unblocked(&self) -> ChannelResult<()> {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         let mut queue = self.channel.queue.lock().expect(THREAD_PANIC_MSG);
         if queue.rule != SendRule::Block {
@@ -852,7 +853,7 @@ impl<T> Outbox<T> {
                 return Ok(());
             }
 
-            super::ensure_connection_not_dropped(&self.channel)?;
+            self.channel.ensure_connected()?;
         }
     }
 
@@ -866,7 +867,7 @@ impl<T> Outbox<T> {
     /// A [ChannelError::ConnectionDropped] error is returned if the other end
     /// of the connection was dropped.
     pub fn wait_for_send_unblocked_timeout(&self, timeout: Duration) -> ChannelResult<()> {
-        super::ensure_connection_not_dropped(&self.channel)?;
+        self.channel.ensure_connected()?;
 
         let mut queue = self.channel.queue.lock().expect(THREAD_PANIC_MSG);
         if queue.rule != SendRule::Block {
@@ -896,14 +897,14 @@ impl<T> Outbox<T> {
             // No messages after waking up means one of two things:
             // 1. The other end hung up.
             // 2. This was a spurious (early) wakeup (should go back to sleep).
-            super::ensure_connection_not_dropped(&self.channel)?;
+            self.channel.ensure_connected()?;
         }
     }
 
     /// Whether the other party still has their end of the connection alive, the
     /// inverse of [Self::connection_closed].
     pub fn connection_open(&self) -> bool {
-        super::connection_not_dropped(&self.channel)
+        self.channel.connected()
     }
 
     /// Whether the other party has dropped their end of the connection, the
@@ -918,7 +919,7 @@ impl<T> Outbox<T> {
 // forever.
 impl<T> Drop for Outbox<T> {
     fn drop(&mut self) {
-        self.channel.notifier.notify_one();
+        self.channel.hang_up();
     }
 }
 
@@ -932,6 +933,7 @@ pub fn new<T>() -> (Inbox<T>, Outbox<T>) {
     OneWayChannel {
         queue: Mutex::default(),
         notifier: Condvar::default(),
+        hung_up: AtomicBool::new(false),
     }
     .into()
 }
@@ -949,6 +951,7 @@ pub fn with_capacity<T>(capacity: usize) -> (Inbox<T>, Outbox<T>) {
     OneWayChannel {
         queue: Mutex::new(VecDeque::with_capacity(capacity).into()),
         notifier: Condvar::default(),
+        hung_up: AtomicBool::new(false),
     }
     .into()
 }
@@ -964,6 +967,7 @@ pub fn with_starting_messages<T, I: IntoIterator<Item = T>>(msg: I) -> (Inbox<T>
     OneWayChannel {
         queue: Mutex::new(msg.into_iter().collect()),
         notifier: Condvar::default(),
+        hung_up: AtomicBool::new(false),
     }
     .into()
 }
@@ -1042,6 +1046,36 @@ enum SendRule {
 struct OneWayChannel<T> {
     queue: Mutex<QueueAndRule<T>>,
     notifier: Condvar,
+    /// Whether either end was dropped. This is set before the other end is
+    /// notified (unlike the [ConnN] count, which only drops after), so a
+    /// waiting end can't wake up and still see a live connection.
+    hung_up: AtomicBool,
+}
+
+impl<T> OneWayChannel<T> {
+    /// Whether neither end has been dropped.
+    fn connected(&self) -> bool {
+        !self.hung_up.load(Ordering::SeqCst)
+    }
+
+    fn ensure_connected<E>(&self) -> Result<(), ChannelError<E>> {
+        if self.connected() {
+            Ok(())
+        } else {
+            Err(ChannelError::ConnectionDropped)
+        }
+    }
+
+    /// Mark the connection as dropped and wake up the other end if it's
+    /// waiting. The queue is locked while the flag is set so the other end
+    /// either sees it before it waits or is already waiting when notified.
+    fn hang_up(&self) {
+        // A poisoned lock still guards the queue (we don't touch it), and
+        // panicking here could be a panic while unwinding.
+        let _queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        self.hung_up.store(true, Ordering::SeqCst);
+        self.notifier.notify_all();
+    }
 }
 
 impl<T> From<OneWayChannel<T>> for (Inbox<T>, Outbox<T>) {
@@ -2,9 +2,15 @@
 //! [Request] and [ResponseHandle]) for working with a two-way SPSC (single
 //! producer single consumer) requesting system, useful in situations with a
 //! single thread making requests and another single thread responding.
+//!
+//! Requests can be sent with a [Priority] (see [Client::request_with_priority])
+//! so that, for example, requests for something the user is waiting on are
+//! handled before background work that was requested earlier. Requests of the
+//! same priority are always received in the order they were sent.
 
 mod req_res;
 
+use std::cmp::Reverse;
 use std::collections::VecDeque;
 use std::time::Duration;
 
@@ -14,13 +20,33 @@ use super::{connection_not_dropped, ensure_connection_not_dropped};
 
 pub use req_res::*;
 
+/// How urgently a request should be handled. The [Server] always receives
+/// higher priority requests first, and requests of the same priority in the
+/// order they were sent.
+///
+/// Also see [Client::request_with_priority].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Work nobody is waiting on (e.g. generating thumbnails).
+    Background,
+    /// The priority of requests sent without one.
+    #[default]
+    Normal,
+    /// Work the user is waiting on (e.g. a frame for a scrubbed timeline).
+    Interactive,
+}
+
+/// A request as it sits in the channel's queue, with the [Priority] it was
+/// sent with. This is what the `*_in_place` methods give access to.
+pub type Queued<Q, A> = (Priority, ReqRes<Q, A>);
+
 /// The server (request receiver/responder) of a two-way message channel (single
 /// producer single consumer). Also see [Client].
 ///
 /// See [new] and [with_capacity] to construct.
 #[derive(Debug)]
 pub struct Server<Q, A> {
-    channel: message_channel::Inbox<ReqRes<Q, A>>,
+    channel: message_channel::Inbox<Queued<Q, A>>,
 }
 
 impl<Q, A> Server<Q, A> {
@@ -55,7 +81,7 @@ impl<Q, A> Server<Q, A> {
     /// }
     /// ```
     pub fn wait(&self) -> ChannelResult<ReqRes<Q, A>> {
-        self.channel.wait()
+        self.channel.wait_in_place(pop_highest).map(expect_request)
     }
 
     /// Waits for a request from the client for up to `timeout` time.
@@ -80,7 +106,9 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_in_place]
     /// - [Self::check_non_blocking_in_place]
     pub fn wait_timeout(&self, timeout: Duration) -> ChannelResult<ReqRes<Q, A>> {
-        self.channel.wait_timeout(timeout)
+        self.channel
+            .wait_timeout_in_place(pop_highest, timeout)
+            .map(expect_request)
     }
 
     /// Receives a request from the client if a request is waiting, returning
@@ -105,7 +133,9 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_in_place]
     /// - [Self::check_non_blocking_in_place]
     pub fn check(&self) -> ChannelResult<Option<ReqRes<Q, A>>> {
-        self.channel.check()
+        self.channel
+            .check_in_place(pop_highest)
+            .map(Option::flatten)
     }
 
     /// Receives a request from the client if the queue is not locked and a
@@ -132,7 +162,9 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_in_place]
     /// - [Self::check_non_blocking_in_place]
     pub fn check_non_blocking(&self) -> ChannelResult<Option<ReqRes<Q, A>>> {
-        self.channel.check_non_blocking()
+        self.channel
+            .check_non_blocking_in_place(pop_highest)
+            .map(Option::flatten)
     }
 
     /// Waits for a request from the client until one appears, returning all
@@ -156,7 +188,7 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_in_place]
     /// - [Self::check_non_blocking_in_place]
     pub fn wait_all(&self) -> ChannelResult<VecDeque<ReqRes<Q, A>>> {
-        self.channel.wait_all()
+        self.channel.wait_in_place(take_all)
     }
 
     /// Waits for a request from the client for up to `timeout` time, returning
@@ -184,7 +216,7 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_in_place]
     /// - [Self::check_non_blocking_in_place]
     pub fn wait_timeout_all(&self, timeout: Duration) -> ChannelResult<VecDeque<ReqRes<Q, A>>> {
-        self.channel.wait_timeout_all(timeout)
+        self.channel.wait_timeout_in_place(take_all, timeout)
     }
 
     /// Receives all requests from the client if requests are waiting, returning
@@ -211,7 +243,7 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_in_place]
     /// - [Self::check_non_blocking_in_place]
     pub fn check_all(&self) -> ChannelResult<Option<VecDeque<ReqRes<Q, A>>>> {
-        self.channel.check_all()
+        self.channel.check_in_place(take_all)
     }
 
     /// Receives all request from the client if the queue is not locked and a
@@ -240,7 +272,7 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_in_place]
     /// - [Self::check_non_blocking_in_place]
     pub fn check_non_blocking_all(&self) -> ChannelResult<Option<VecDeque<ReqRes<Q, A>>>> {
-        self.channel.check_non_blocking_all()
+        self.channel.check_non_blocking_in_place(take_all)
     }
 
     /// Waits for a request from the outbox until one appears, giving in-place
@@ -248,7 +280,8 @@ impl<Q, A> Server<Q, A> {
     ///
     /// No messages can be sent while `f` is executing.
     ///
-    /// The [VecDeque] is guaranteed to have at least 1 element.
+    /// The [VecDeque] is guaranteed to have at least 1 element. It's sorted
+    /// highest [priority](Priority) first (see [Queued]).
     ///
     /// A [ChannelError::ConnectionDropped] error is returned if the other end
     /// of the connection was dropped and there are no more items in the queue.
@@ -267,9 +300,9 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_non_blocking_in_place]
     pub fn wait_in_place<F, R>(&self, f: F) -> ChannelResult<R>
     where
-        F: FnOnce(&mut VecDeque<ReqRes<Q, A>>) -> R,
+        F: FnOnce(&mut VecDeque<Queued<Q, A>>) -> R,
     {
-        self.channel.wait_in_place(f)
+        self.channel.wait_in_place(|queue| with_sorted(queue, f))
     }
 
     /// Waits for a request from the outbox for up to `timeout` time, giving
@@ -281,7 +314,8 @@ impl<Q, A> Server<Q, A> {
     ///
     /// No messages can be sent while `f` is executing.
     ///
-    /// The [VecDeque] is guaranteed to have at least 1 element.
+    /// The [VecDeque] is guaranteed to have at least 1 element. It's sorted
+    /// highest [priority](Priority) first (see [Queued]).
     ///
     /// A [ChannelError::ConnectionDropped] error is returned if the other end
     /// of the connection was dropped and there are no more items in the queue.
@@ -300,9 +334,10 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_non_blocking_in_place]
     pub fn wait_timeout_in_place<F, R>(&self, f: F, timeout: Duration) -> ChannelResult<R>
     where
-        F: FnOnce(&mut VecDeque<ReqRes<Q, A>>) -> R,
+        F: FnOnce(&mut VecDeque<Queued<Q, A>>) -> R,
     {
-        self.channel.wait_timeout_in_place(f, timeout)
+        self.channel
+            .wait_timeout_in_place(|queue| with_sorted(queue, f), timeout)
     }
 
     /// Gives in-place access to all requests from the outbox if at least one
@@ -314,7 +349,8 @@ impl<Q, A> Server<Q, A> {
     ///
     /// No messages can be sent while `f` is executing.
     ///
-    /// The [VecDeque] is guaranteed to have at least 1 element.
+    /// The [VecDeque] is guaranteed to have at least 1 element. It's sorted
+    /// highest [priority](Priority) first (see [Queued]).
     ///
     /// A [ChannelError::ConnectionDropped] error is returned if the other end
     /// of the connection was dropped and there are no more items in the queue.
@@ -333,9 +369,9 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_non_blocking_in_place]
     pub fn check_in_place<F, R>(&self, f: F) -> ChannelResult<Option<R>>
     where
-        F: FnOnce(&mut VecDeque<ReqRes<Q, A>>) -> R,
+        F: FnOnce(&mut VecDeque<Queued<Q, A>>) -> R,
     {
-        self.channel.check_in_place(f)
+        self.channel.check_in_place(|queue| with_sorted(queue, f))
     }
 
     /// Gives in-place access to all requests from the outbox if the queue is
@@ -348,7 +384,8 @@ impl<Q, A> Server<Q, A> {
     ///
     /// No messages can be sent while `f` is executing.
     ///
-    /// The [VecDeque] is guaranteed to have at least 1 element.
+    /// The [VecDeque] is guaranteed to have at least 1 element. It's sorted
+    /// highest [priority](Priority) first (see [Queued]).
     ///
     /// A [ChannelError::ConnectionDropped] error is returned if the other end
     /// of the connection was dropped and there are no more items in the queue.
@@ -367,9 +404,10 @@ impl<Q, A> Server<Q, A> {
     /// - [Self::check_in_place]
     pub fn check_non_blocking_in_place<F, R>(&self, f: F) -> ChannelResult<Option<R>>
     where
-        F: FnOnce(&mut VecDeque<ReqRes<Q, A>>) -> R,
+        F: FnOnce(&mut VecDeque<Queued<Q, A>>) -> R,
     {
-        self.channel.check_non_blocking_in_place(f)
+        self.channel
+            .check_non_blocking_in_place(|queue| with_sorted(queue, f))
     }
 
     /// Block requests from being sent until [Self::unblock_sender] is called.
@@ -421,7 +459,8 @@ impl<Q, A> Server<Q, A> {
         self.channel.connection_closed()
     }
 
-    /// Direct access to the inner request queue.
+    /// Direct access to the inner request queue, sorted highest
+    /// [priority](Priority) first (see [Queued]).
     ///
     /// No requests can be sent while `f` is executing.
     /// is blocked.
@@ -430,9 +469,10 @@ impl<Q, A> Server<Q, A> {
     /// of the connection was dropped.
     pub fn with_queue_in_place<F, R>(&self, f: F) -> ChannelResult<R>
     where
-        F: FnOnce(&mut VecDeque<ReqRes<Q, A>>) -> R,
+        F: FnOnce(&mut VecDeque<Queued<Q, A>>) -> R,
     {
-        self.channel.with_queue_in_place(f)
+        self.channel
+            .with_queue_in_place(|queue| with_sorted(queue, f))
     }
 
     /// Like [Self::with_queue_in_place], just without the check for if the
@@ -440,9 +480,10 @@ impl<Q, A> Server<Q, A> {
     /// [send-blocked](Server::block_sender).
     pub fn with_queue_in_place_unchecked<F, R>(&self, f: F) -> R
     where
-        F: FnOnce(&mut VecDeque<ReqRes<Q, A>>) -> R,
+        F: FnOnce(&mut VecDeque<Queued<Q, A>>) -> R,
     {
-        self.channel.with_queue_in_place_unchecked(f)
+        self.channel
+            .with_queue_in_place_unchecked(|queue| with_sorted(queue, f))
     }
 }
 
@@ -452,7 +493,7 @@ impl<Q, A> Server<Q, A> {
 /// See [new] and [with_capacity] to construct.
 #[derive(Debug)]
 pub struct Client<Q, A> {
-    channel: message_channel::Outbox<ReqRes<Q, A>>,
+    channel: message_channel::Outbox<Queued<Q, A>>,
 }
 
 impl<Q, A> Client<Q, A> {
@@ -462,9 +503,26 @@ impl<Q, A> Client<Q, A> {
     /// of the connection was dropped. [ChannelError::SendBlocked] is returned
     /// if the channel is [send-blocked](Server::block_sender).
     ///
-    /// Also see [Self::request_bounded] and [Self::request_bounded].
+    /// Also see [Self::request_bounded] and [Self::request_with_priority].
     pub fn request(&self, request: Q) -> ChannelResult<Request<A>, Q> {
-        Self::send_template(self, request, |channel, msg| channel.send(msg))
+        self.request_with_priority(request, Priority::Normal)
+    }
+
+    /// Send a request to the server that will be received before any waiting
+    /// requests with a lower `priority` (but after those with the same or a
+    /// higher one).
+    ///
+    /// A [ChannelError::ConnectionDropped] error is returned if the other end
+    /// of the connection was dropped. [ChannelError::SendBlocked] is returned
+    /// if the channel is [send-blocked](Server::block_sender).
+    ///
+    /// Also see [Self::request] and [Self::alert_with_priority].
+    pub fn request_with_priority(
+        &self,
+        request: Q,
+        priority: Priority,
+    ) -> ChannelResult<Request<A>, Q> {
+        Self::send_template(self, request, priority, |channel, msg| channel.send(msg))
             .map_err(|e| e.map_to_req())
     }
 
@@ -483,7 +541,7 @@ impl<Q, A> Client<Q, A> {
         request: Q,
         max_in_flight: usize,
     ) -> ChannelResult<Request<A>, Q> {
-        Self::send_template(self, request, |channel, msg| {
+        Self::send_template(self, request, Priority::Normal, |channel, msg| {
             channel.send_bounded(msg, max_in_flight)
         })
         .map_err(|e| e.map_to_req())
@@ -510,7 +568,7 @@ impl<Q, A> Client<Q, A> {
         max_in_flight: usize,
         timeout: Duration,
     ) -> ChannelResult<Request<A>, Q> {
-        Self::send_template(self, request, |channel, msg| {
+        Self::send_template(self, request, Priority::Normal, |channel, msg| {
             channel
                 .send_bounded_timeout(msg, max_in_flight, timeout)
                 .map_err(|e| e.map_to_req())
@@ -523,9 +581,22 @@ impl<Q, A> Client<Q, A> {
     /// of the connection was dropped. [ChannelError::SendBlocked] is returned
     /// if the channel is [send-blocked](Server::block_sender).
     ///
-    /// Also see [Self::alert_bounded] and [Self::alert_bounded_timeout].
+    /// Also see [Self::alert_bounded] and [Self::alert_with_priority].
     pub fn alert(&self, request: Q) -> ChannelResult<(), Q> {
-        self.alert_template(request, |channel, msg| channel.send(msg))
+        self.alert_with_priority(request, Priority::Normal)
+    }
+
+    /// Send a message to the server that it does not need to reply to, which
+    /// will be received before any waiting requests with a lower `priority`
+    /// (but after those with the same or a higher one).
+    ///
+    /// A [ChannelError::ConnectionDropped] error is returned if the other end
+    /// of the connection was dropped. [ChannelError::SendBlocked] is returned
+    /// if the channel is [send-blocked](Server::block_sender).
+    ///
+    /// Also see [Self::alert] and [Self::request_with_priority].
+    pub fn alert_with_priority(&self, request: Q, priority: Priority) -> ChannelResult<(), Q> {
+        self.alert_template(request, priority, |channel, msg| channel.send(msg))
             .map_err(|e| e.map_to_req())
     }
 
@@ -541,7 +612,7 @@ impl<Q, A> Client<Q, A> {
     ///
     /// Also see [Self::alert] and [Self::alert_bounded_timeout].
     pub fn alert_bounded(&self, request: Q, max_in_flight: usize) -> ChannelResult<(), Q> {
-        Self::alert_template(self, request, |channel, msg| {
+        Self::alert_template(self, request, Priority::Normal, |channel, msg| {
             channel.send_bounded(msg, max_in_flight)
         })
         .map_err(|e| e.map_to_req())
@@ -569,7 +640,7 @@ impl<Q, A> Client<Q, A> {
         max_in_flight: usize,
         timeout: Duration,
     ) -> ChannelResult<(), Q> {
-        Self::alert_template(self, request, |channel, msg| {
+        Self::alert_template(self, request, Priority::Normal, |channel, msg| {
             channel
                 .send_bounded_timeout(msg, max_in_flight, timeout)
                 .map_err(|e| e.map_to_req())
@@ -629,7 +700,8 @@ impl<Q, A> Client<Q, A> {
         self.channel.connection_closed()
     }
 
-    /// Direct access to the inner request queue.
+    /// Direct access to the inner request queue, sorted highest
+    /// [priority](Priority) first (see [Queued]).
     ///
     /// No requests can be received while `f` is executing.
     ///
@@ -638,9 +710,10 @@ impl<Q, A> Client<Q, A> {
     /// returned if the channel is [send-blocked](Server::block_sender).
     pub fn with_queue_in_place<F, R>(&self, f: F) -> ChannelResult<R>
     where
-        F: FnOnce(&mut VecDeque<ReqRes<Q, A>>) -> R,
+        F: FnOnce(&mut VecDeque<Queued<Q, A>>) -> R,
     {
-        self.channel.with_queue_in_place(f)
+        self.channel
+            .with_queue_in_place(|queue| with_sorted(queue, f))
     }
 
     /// Like [Self::with_queue_in_place], just without the check for if the
@@ -648,30 +721,77 @@ impl<Q, A> Client<Q, A> {
     /// [send-blocked](Server::block_sender).
     pub fn with_queue_in_place_unchecked<F, R>(&self, f: F) -> R
     where
-        F: FnOnce(&mut VecDeque<ReqRes<Q, A>>) -> R,
+        F: FnOnce(&mut VecDeque<Queued<Q, A>>) -> R,
     {
-        self.channel.with_queue_in_place_unchecked(f)
+        self.channel
+            .with_queue_in_place_unchecked(|queue| with_sorted(queue, f))
     }
 
     #[inline]
-    fn send_template<F, R, M>(&self, request: Q, sender: F) -> ChannelResult<Request<A>, M>
+    fn send_template<F, R, M>(
+        &self,
+        request: Q,
+        priority: Priority,
+        sender: F,
+    ) -> ChannelResult<Request<A>, M>
     where
-        F: FnOnce(&message_channel::Outbox<ReqRes<Q, A>>, ReqRes<Q, A>) -> ChannelResult<R, M>,
+        F: FnOnce(&message_channel::Outbox<Queued<Q, A>>, Queued<Q, A>) -> ChannelResult<R, M>,
     {
         let (req, res) = Request::new();
-        sender(&self.channel, (request, Some(res)))?;
+        sender(&self.channel, (priority, (request, Some(res))))?;
         Ok(req)
     }
 
     #[inline]
-    fn alert_template<F, R, M>(&self, request: Q, sender: F) -> ChannelResult<(), M>
+    fn alert_template<F, R, M>(
+        &self,
+        request: Q,
+        priority: Priority,
+        sender: F,
+    ) -> ChannelResult<(), M>
     where
-        F: FnOnce(&message_channel::Outbox<ReqRes<Q, A>>, ReqRes<Q, A>) -> ChannelResult<R, M>,
+        F: FnOnce(&message_channel::Outbox<Queued<Q, A>>, Queued<Q, A>) -> ChannelResult<R, M>,
     {
-        sender(&self.channel, (request, None)).map(|_| ())
+        sender(&self.channel, (priority, (request, None))).map(|_| ())
     }
 }
 
+/// Remove the oldest of the highest priority requests from `queue`.
+fn pop_highest<Q, A>(queue: &mut VecDeque<Queued<Q, A>>) -> Option<ReqRes<Q, A>> {
+    let highest = queue.iter().map(|(priority, _)| *priority).max()?;
+    let index = queue
+        .iter()
+        .position(|(priority, _)| *priority == highest)?;
+    queue.remove(index).map(|(_, req_res)| req_res)
+}
+
+/// For the results of [pop_highest] on queues that can't be empty.
+fn expect_request<Q, A>(req_res: Option<ReqRes<Q, A>>) -> ReqRes<Q, A> {
+    req_res.expect("The queue should have at least 1 request.")
+}
+
+/// Remove every request from `queue`, highest priority first.
+fn take_all<Q, A>(queue: &mut VecDeque<Queued<Q, A>>) -> VecDeque<ReqRes<Q, A>> {
+    let mut requests: Vec<Queued<Q, A>> = queue.drain(..).collect();
+    // The sort is stable, so requests of the same priority stay in order.
+    requests.sort_by_key(|(priority, _)| Reverse(*priority));
+    requests.into_iter().map(|(_, req_res)| req_res).collect()
+}
+
+/// Sort `queue` highest priority first (keeping the order requests of the
+/// same priority were sent in) and give it to `f`. Every request keeps the
+/// priority it's paired with, so whatever `f` leaves in (or adds to) the queue
+/// is received in priority order like any other request.
+fn with_sorted<Q, A, F, R>(queue: &mut VecDeque<Queued<Q, A>>, f: F) -> R
+where
+    F: FnOnce(&mut VecDeque<Queued<Q, A>>) -> R,
+{
+    queue
+        .make_contiguous()
+        .sort_by_key(|(priority, _)| Reverse(*priority));
+    f(queue)
+}
+
 /// Create a two-way message channel's [Server] and [Client].
 ///
 /// `Q` is the request type. `A` is the response type.
@@ -763,6 +883,54 @@ mod tests {
         thread.join().unwrap();
     }
 
+    #[test]
+    fn priorities_are_respected() {
+        let (server, client) = new::<i32, ()>();
+
+        client.alert_with_priority(1, Priority::Background).unwrap();
+        client.alert(2).unwrap();
+        client
+            .alert_with_priority(3, Priority::Interactive)
+            .unwrap();
+        client.alert_with_priority(4, Priority::Background).unwrap();
+        client
+            .alert_with_priority(5, Priority::Interactive)
+            .unwrap();
+        client.alert(6).unwrap();
+
+        assert_eq!(server.wait().unwrap().0, 3);
+        assert_eq!(server.check().unwrap().unwrap().0, 5);
+
+        let rest: Vec<i32> = server
+            .check_all()
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|(req, _)| req)
+            .collect();
+        assert_eq!(rest, [2, 6, 1, 4]);
+    }
+
+    #[test]
+    fn in_place_leftovers_keep_their_priority() {
+        let (server, client) = new::<i32, ()>();
+
+        client.alert_with_priority(1, Priority::Background).unwrap();
+        client
+            .alert_with_priority(2, Priority::Interactive)
+            .unwrap();
+
+        // Only handle the interactive request, leaving the background one.
+        let handled = server
+            .check_in_place(|queue| queue.pop_front().map(|(_, (req, _))| req))
+            .unwrap();
+        assert_eq!(handled, Some(Some(2)));
+
+        client.alert(3).unwrap();
+        assert_eq!(server.wait().unwrap().0, 3);
+        assert_eq!(server.wait().unwrap().0, 1);
+    }
+
     #[test]
     fn early_response_handler_drop_is_ok() {
         let (server, client) = new::<i32, i32>();
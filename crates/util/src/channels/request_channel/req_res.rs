@@ -1,7 +1,8 @@
 //! The request and response types.
 
 use std::mem;
-use std::sync::{Condvar, Mutex, TryLockError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex, PoisonError, TryLockError};
 use std::time::{Duration, Instant};
 
 use super::{ChannelError, ChannelResult, ConnN, THREAD_PANIC_MSG};
@@ -58,7 +59,7 @@ impl<A> ResponseHandle<A> {
 impl<A> Drop for ResponseHandle<A> {
     fn drop(&mut self) {
         // We need to notify the client that no response is coming.
-        self.0.notifier.notify_one();
+        self.0.hang_up();
     }
 }
 
@@ -123,7 +124,7 @@ impl<A> Request<A> {
 
         // If there's no response we need to make sure the other end hasn't hung
         // up.
-        super::ensure_connection_not_dropped(responder)?;
+        responder.ensure_connected()?;
 
         loop {
             response = responder.notifier.wait(response).expect(THREAD_PANIC_MSG);
@@ -135,7 +136,7 @@ impl<A> Request<A> {
             // No response after waking up means one of two things:
             // 1. The other end hung up.
             // 2. This was a spurious (early) wakeup (should go back to sleep).
-            super::ensure_connection_not_dropped(responder)?;
+            responder.ensure_connected()?;
         }
     }
 
@@ -167,7 +168,7 @@ impl<A> Request<A> {
 
         // If there's no response we need to make sure the other end hasn't hung
         // up.
-        super::ensure_connection_not_dropped(responder)?;
+        responder.ensure_connected()?;
 
         let deadline = Instant::now() + timeout;
 
@@ -191,7 +192,7 @@ impl<A> Request<A> {
             // No response after waking up means one of two things:
             // 1. The other end hung up.
             // 2. This was a spurious (early) wakeup (should go back to sleep).
-            super::ensure_connection_not_dropped(responder)?;
+            responder.ensure_connected()?;
         }
     }
 
@@ -219,7 +220,7 @@ impl<A> Request<A> {
         } else {
             // If there's no response we need to make sure the other end hasn't
             // hung up.
-            super::ensure_connection_not_dropped(responder)?;
+            responder.ensure_connected()?;
 
             Ok(None)
         }
@@ -269,7 +270,7 @@ impl<A> Request<A> {
         match &self.0 {
             RequestInner::ResponseReceived => Err(ChannelError::ResponseAlreadyReceived),
             RequestInner::ResponseInline(_) => Ok(true),
-            RequestInner::Awaiting(responder) => Ok(super::connection_not_dropped(responder)),
+            RequestInner::Awaiting(responder) => Ok(responder.connected()),
         }
     }
 
@@ -325,6 +326,37 @@ impl<A> RequestInner<A> {
 struct Responder<A> {
     response: Mutex<Option<A>>,
     notifier: Condvar,
+    /// Whether the [ResponseHandle] was dropped. This is set before the
+    /// [Request] is notified (unlike the [ConnN] count, which only drops
+    /// after), so a waiting request can't wake up and still see a live
+    /// connection.
+    hung_up: AtomicBool,
+}
+
+impl<A> Responder<A> {
+    /// Whether the [ResponseHandle] hasn't been dropped.
+    fn connected(&self) -> bool {
+        !self.hung_up.load(Ordering::SeqCst)
+    }
+
+    fn ensure_connected<M>(&self) -> Result<(), ChannelError<M>> {
+        if self.connected() {
+            Ok(())
+        } else {
+            Err(ChannelError::ConnectionDropped)
+        }
+    }
+
+    /// Mark the [ResponseHandle] as dropped and wake up the [Request] if it's
+    /// waiting. The response is locked while the flag is set so the request
+    /// either sees it before it waits or is already waiting when notified.
+    fn hang_up(&self) {
+        // A poisoned lock still guards the response (we don't touch it), and
+        // panicking here could be a panic while unwinding.
+        let _response = self.response.lock().unwrap_or_else(PoisonError::into_inner);
+        self.hung_up.store(true, Ordering::SeqCst);
+        self.notifier.notify_all();
+    }
 }
 
 impl<A> Default for Responder<A> {
@@ -332,6 +364,7 @@ impl<A> Default for Responder<A> {
         Self {
             response: Mutex::new(None),
             notifier: Condvar::default(),
+            hung_up: AtomicBool::new(false),
         }
     }
 }
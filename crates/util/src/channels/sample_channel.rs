@@ -0,0 +1,287 @@
+//! This module defines the [Reader] and [Publisher] types for working with a
+//! one-way SPSC (single producer single consumer) channel of keyed samples,
+//! useful for streaming live values (e.g. a motion score every frame) from a
+//! fast producer to a slower consumer that only cares about the latest value.
+//!
+//! Unlike a [message channel](super::message_channel), samples aren't queued.
+//! Publishing a sample for a key that already has one waiting replaces it (the
+//! samples are *coalesced*), so the channel never holds more than 1 sample per
+//! key no matter how far behind the reader falls. The reader can also be
+//! *throttled* so it picks up samples at most once every so often.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::{ChannelResult, ConnN, THREAD_PANIC_MSG, ensure_connection_not_dropped};
+
+/// The latest value published for a key. Also see [Reader::take].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample<K, V> {
+    pub key: K,
+    pub value: V,
+    /// When [Self::value] was published.
+    pub published_at: Instant,
+    /// How many older values for [Self::key] were replaced by this one without
+    /// being read.
+    pub coalesced: usize,
+}
+
+/// The reading end of a sample channel. Also see [Publisher].
+///
+/// See [new] and [throttled] to construct.
+#[derive(Debug)]
+pub struct Reader<K, V> {
+    channel: ConnN<SampleChannel<K, V>>,
+}
+
+impl<K, V> Reader<K, V> {
+    /// Takes every waiting sample (at most 1 per key), in the order their keys
+    /// were first published since the last take.
+    ///
+    /// If the channel is [throttled] and the last non-empty take was less than
+    /// the minimum interval ago, nothing is returned and the samples keep
+    /// waiting (and coalescing).
+    ///
+    /// A [ChannelError::ConnectionDropped](super::ChannelError::ConnectionDropped)
+    /// error is returned if the publisher was dropped and there are no more
+    /// waiting samples.
+    pub fn take(&self) -> ChannelResult<Vec<Sample<K, V>>> {
+        let mut state = self.channel.lock();
+
+        if state.samples.is_empty() {
+            ensure_connection_not_dropped(&self.channel)?;
+            return Ok(Vec::new());
+        }
+
+        let now = Instant::now();
+        if let Some(last_take) = state.last_take
+            && now.duration_since(last_take) < self.channel.min_interval
+        {
+            return Ok(Vec::new());
+        }
+
+        state.last_take = Some(now);
+        state.key_indices.clear();
+        Ok(std::mem::take(&mut state.samples))
+    }
+
+    /// The number of samples (keys) waiting to be taken.
+    pub fn len(&self) -> usize {
+        self.channel.lock().samples.len()
+    }
+
+    /// Whether there are no samples waiting to be taken.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The minimum time between takes that return samples. See [throttled].
+    pub fn min_interval(&self) -> Duration {
+        self.channel.min_interval
+    }
+}
+
+/// The publishing end of a sample channel. Also see [Reader].
+///
+/// See [new] and [throttled] to construct.
+#[derive(Debug)]
+pub struct Publisher<K, V> {
+    channel: ConnN<SampleChannel<K, V>>,
+}
+
+impl<K: Eq + Hash + Clone, V> Publisher<K, V> {
+    /// Publishes `value` for `key`, replacing the waiting sample for `key` if
+    /// there is one.
+    ///
+    /// A [ChannelError::ConnectionDropped](super::ChannelError::ConnectionDropped)
+    /// error is returned if the reader was dropped.
+    pub fn publish(&self, key: K, value: V) -> ChannelResult<()> {
+        ensure_connection_not_dropped(&self.channel)?;
+
+        let mut state = self.channel.lock();
+        let published_at = Instant::now();
+
+        if let Some(&idx) = state.key_indices.get(&key) {
+            let sample = &mut state.samples[idx];
+            sample.value = value;
+            sample.published_at = published_at;
+            sample.coalesced += 1;
+        } else {
+            let idx = state.samples.len();
+            state.key_indices.insert(key.clone(), idx);
+            state.samples.push(Sample {
+                key,
+                value,
+                published_at,
+                coalesced: 0,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<K, V> Publisher<K, V> {
+    /// Whether the [Reader] is still connected.
+    pub fn is_connected(&self) -> bool {
+        !self.channel.is_only_handle()
+    }
+}
+
+/// Create a sample channel's [Reader] and [Publisher]. The reader isn't
+/// throttled (see [throttled]).
+///
+/// - The reader will be able to take samples as long as the publisher hasn't
+///   been dropped or while there are still waiting samples.
+/// - The publisher will be able to publish samples as long as the reader hasn't
+///   been dropped.
+pub fn new<K, V>() -> (Reader<K, V>, Publisher<K, V>) {
+    throttled(Duration::ZERO)
+}
+
+/// Create a sample channel's [Reader] and [Publisher] where the reader takes
+/// samples at most once every `min_interval`. Samples published in between
+/// are coalesced.
+///
+/// - The reader will be able to take samples as long as the publisher hasn't
+///   been dropped or while there are still waiting samples.
+/// - The publisher will be able to publish samples as long as the reader hasn't
+///   been dropped.
+pub fn throttled<K, V>(min_interval: Duration) -> (Reader<K, V>, Publisher<K, V>) {
+    let [reader_channel, publisher_channel] = ConnN::new::<2>(SampleChannel {
+        state: Mutex::new(SampleState {
+            samples: Vec::new(),
+            key_indices: HashMap::new(),
+            last_take: None,
+        }),
+        min_interval,
+    });
+    (
+        Reader {
+            channel: reader_channel,
+        },
+        Publisher {
+            channel: publisher_channel,
+        },
+    )
+}
+
+#[derive(Debug)]
+struct SampleChannel<K, V> {
+    state: Mutex<SampleState<K, V>>,
+    min_interval: Duration,
+}
+
+impl<K, V> SampleChannel<K, V> {
+    fn lock(&self) -> MutexGuard<'_, SampleState<K, V>> {
+        self.state.lock().expect(THREAD_PANIC_MSG)
+    }
+}
+
+#[derive(Debug)]
+struct SampleState<K, V> {
+    samples: Vec<Sample<K, V>>,
+    /// Index into `samples` for each key.
+    key_indices: HashMap<K, usize>,
+    last_take: Option<Instant>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::super::ChannelError;
+    use super::*;
+
+    #[test]
+    fn samples_can_be_taken() {
+        let (reader, publisher) = new::<&str, i32>();
+
+        assert!(publisher.publish("a", 1).is_ok());
+        assert!(publisher.publish("b", 2).is_ok());
+
+        let samples = reader.take().unwrap();
+        let values: Vec<_> = samples.iter().map(|s| (s.key, s.value)).collect();
+        assert_eq!(values, [("a", 1), ("b", 2)]);
+        assert!(reader.take().unwrap().is_empty());
+    }
+
+    #[test]
+    fn samples_are_coalesced() {
+        let (reader, publisher) = new::<&str, i32>();
+
+        for value in 0..5 {
+            assert!(publisher.publish("a", value).is_ok());
+        }
+        assert!(publisher.publish("b", 10).is_ok());
+        assert!(publisher.publish("a", 5).is_ok());
+        assert_eq!(reader.len(), 2);
+
+        let samples = reader.take().unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!((samples[0].key, samples[0].value), ("a", 5));
+        assert_eq!(samples[0].coalesced, 5);
+        assert_eq!((samples[1].key, samples[1].value), ("b", 10));
+        assert_eq!(samples[1].coalesced, 0);
+    }
+
+    #[test]
+    fn throttled_reader_waits() {
+        let min_interval = Duration::from_millis(100);
+        let (reader, publisher) = throttled::<&str, i32>(min_interval);
+
+        assert!(publisher.publish("a", 1).is_ok());
+        assert_eq!(reader.take().unwrap().len(), 1);
+
+        // Too soon after the last take, the sample keeps waiting.
+        assert!(publisher.publish("a", 2).is_ok());
+        assert!(reader.take().unwrap().is_empty());
+        assert!(publisher.publish("a", 3).is_ok());
+
+        thread::sleep(min_interval);
+        let samples = reader.take().unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].value, 3);
+        assert_eq!(samples[0].coalesced, 1);
+    }
+
+    #[test]
+    fn connection_dropped() {
+        let (reader, publisher) = new::<&str, i32>();
+
+        assert!(publisher.publish("a", 1).is_ok());
+        drop(publisher);
+
+        // Waiting samples can still be taken.
+        assert_eq!(reader.take().unwrap().len(), 1);
+        assert_eq!(reader.take(), Err(ChannelError::ConnectionDropped));
+
+        let (reader, publisher) = new::<&str, i32>();
+        assert!(publisher.is_connected());
+        drop(reader);
+        assert!(!publisher.is_connected());
+        assert_eq!(
+            publisher.publish("a", 1),
+            Err(ChannelError::ConnectionDropped)
+        );
+    }
+
+    #[test]
+    fn samples_cross_threads() {
+        let (reader, publisher) = new::<usize, usize>();
+
+        let thread = thread::spawn(move || {
+            for i in 0..1000 {
+                assert!(publisher.publish(i % 4, i).is_ok());
+            }
+        });
+        thread.join().unwrap();
+
+        let mut samples = reader.take().unwrap();
+        samples.sort_by_key(|s| s.key);
+        let values: Vec<_> = samples.iter().map(|s| s.value).collect();
+        assert_eq!(values, [996, 997, 998, 999]);
+    }
+}
@@ -0,0 +1,227 @@
+//! This module contains [Traced], a wrapper that gives a message a
+//! [CorrelationId] and reports when it's sent, received, and responded to, so
+//! that latency between threads can be measured without adding logging to
+//! every sender and receiver.
+//!
+//! Tracing is opt-in per channel: send `Traced<T>` instead of `T` through a
+//! [message_channel](super::message_channel) or
+//! [request_channel](super::request_channel). Events go to the hook set with
+//! [set_trace_hook]. When no hook is set, tracing costs an atomic load per
+//! event.
+//!
+//! # Example
+//!
+//! ```ignore
+//! channels::traced::set_trace_hook(|event| {
+//!     eprintln!("{} {:?} after {:?}", event.id, event.kind, event.since_sent);
+//! });
+//!
+//! // Client thread.
+//! let request = client.request(Traced::new(frame_index))?;
+//!
+//! // Server thread.
+//! let (req, res) = server.wait()?;
+//! req.mark_received();
+//! let frame = decode(*req);
+//! res.unwrap().respond(frame)?;
+//! req.mark_responded();
+//! ```
+
+use std::fmt::{self, Display};
+use std::ops::Deref;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use super::THREAD_PANIC_MSG;
+
+/// An ID that's unique to one [Traced] message (and anything sent
+/// [on its behalf](Traced::with_id)) for the life of the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CorrelationId(u64);
+
+impl CorrelationId {
+    /// A new, never before used ID.
+    pub fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// What happened to a [Traced] message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceEventKind {
+    Sent,
+    Received,
+    Responded,
+}
+
+/// One event in a [Traced] message's life, given to the
+/// [trace hook](set_trace_hook).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub id: CorrelationId,
+    pub kind: TraceEventKind,
+    /// When the event happened.
+    pub at: Instant,
+    /// How long after the message was sent the event happened ([Duration::ZERO]
+    /// for [TraceEventKind::Sent]).
+    pub since_sent: Duration,
+    /// The thread the event happened on.
+    pub thread: ThreadId,
+}
+
+type TraceHook = dyn Fn(&TraceEvent) + Send + Sync;
+
+static HOOK_SET: AtomicBool = AtomicBool::new(false);
+static HOOK: RwLock<Option<Box<TraceHook>>> = RwLock::new(None);
+
+/// Call `hook` with every [TraceEvent] from now on, replacing the previous
+/// hook. The hook is called on the thread the event happened on, so it should
+/// be quick.
+pub fn set_trace_hook(hook: impl Fn(&TraceEvent) + Send + Sync + 'static) {
+    *HOOK.write().expect(THREAD_PANIC_MSG) = Some(Box::new(hook));
+    HOOK_SET.store(true, Ordering::Release);
+}
+
+/// Stop reporting [TraceEvent]s.
+pub fn clear_trace_hook() {
+    HOOK_SET.store(false, Ordering::Release);
+    *HOOK.write().expect(THREAD_PANIC_MSG) = None;
+}
+
+fn record(id: CorrelationId, kind: TraceEventKind, sent_at: Instant) {
+    if !HOOK_SET.load(Ordering::Acquire) {
+        return;
+    }
+
+    let at = Instant::now();
+    let event = TraceEvent {
+        id,
+        kind,
+        at,
+        since_sent: at.saturating_duration_since(sent_at),
+        thread: thread::current().id(),
+    };
+    if let Some(hook) = HOOK.read().expect(THREAD_PANIC_MSG).as_ref() {
+        hook(&event);
+    }
+}
+
+/// A message with a [CorrelationId] that reports [TraceEvent]s. See the
+/// [module docs](self).
+///
+/// Dereferences to the message.
+#[derive(Debug, Clone)]
+pub struct Traced<T> {
+    id: CorrelationId,
+    sent_at: Instant,
+    msg: T,
+}
+
+impl<T> Traced<T> {
+    /// Wrap `msg` with a new [CorrelationId], reporting it as sent.
+    pub fn new(msg: T) -> Self {
+        Self::with_id(msg, CorrelationId::next())
+    }
+
+    /// Wrap `msg` with an existing [CorrelationId], reporting it as sent. This
+    /// is for messages sent because of another one (e.g. a reply over a
+    /// separate channel) so their events can be matched up.
+    pub fn with_id(msg: T, id: CorrelationId) -> Self {
+        let sent_at = Instant::now();
+        record(id, TraceEventKind::Sent, sent_at);
+        Self { id, sent_at, msg }
+    }
+
+    pub const fn id(&self) -> CorrelationId {
+        self.id
+    }
+
+    /// How long ago the message was sent.
+    pub fn since_sent(&self) -> Duration {
+        self.sent_at.elapsed()
+    }
+
+    /// Report that the message was received.
+    pub fn mark_received(&self) {
+        record(self.id, TraceEventKind::Received, self.sent_at);
+    }
+
+    /// Report that the message was responded to.
+    pub fn mark_responded(&self) {
+        record(self.id, TraceEventKind::Responded, self.sent_at);
+    }
+
+    /// Report that the message was received and unwrap it.
+    pub fn receive(self) -> T {
+        self.mark_received();
+        self.msg
+    }
+
+    /// Unwrap the message without reporting anything.
+    pub fn into_inner(self) -> T {
+        self.msg
+    }
+}
+
+impl<T> Deref for Traced<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.msg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::super::message_channel;
+    use super::*;
+
+    // --- Traced ---
+
+    #[test]
+    fn events_are_reported_across_threads() {
+        static EVENTS: Mutex<Vec<TraceEvent>> = Mutex::new(Vec::new());
+        set_trace_hook(|event| EVENTS.lock().unwrap().push(event.clone()));
+
+        let (inbox, outbox) = message_channel::new();
+        let sender = thread::spawn(move || {
+            let msg = Traced::new("frame");
+            let id = msg.id();
+            outbox.send(msg).unwrap();
+            id
+        });
+        let id = sender.join().unwrap();
+        assert_eq!(inbox.wait().unwrap().receive(), "frame");
+
+        clear_trace_hook();
+        Traced::new("not reported");
+
+        let events: Vec<TraceEvent> = EVENTS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.id == id)
+            .cloned()
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, TraceEventKind::Sent);
+        assert_eq!(events[1].kind, TraceEventKind::Received);
+        assert_ne!(events[0].thread, events[1].thread);
+        assert!(events[1].at >= events[0].at);
+    }
+}
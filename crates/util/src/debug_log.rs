@@ -5,7 +5,9 @@
 
 pub mod panic_on_errors;
 
+use std::collections::VecDeque;
 use std::panic::Location;
+use std::sync::Mutex;
 #[cfg(debug_assertions)]
 use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -24,9 +26,11 @@ macro_rules! debug_log_info {
 
             let where_and_when = $crate::debug_log::where_and_when(blue, reset_color);
 
+            let message = format!($($arg)*);
+            $crate::debug_log::remember("INFO", &message);
+
             ::std::println!(
-                "{blue}DEBUG LOG{reset_color} [{magenta}INFO{reset_color}]: {}\n{where_and_when}",
-                format!($($arg)*),
+                "{blue}DEBUG LOG{reset_color} [{magenta}INFO{reset_color}]: {message}\n{where_and_when}",
             );
         }
     }};
@@ -45,9 +49,11 @@ macro_rules! debug_log_warning {
 
             let where_and_when = $crate::debug_log::where_and_when(blue, reset_color);
 
+            let message = format!($($arg)*);
+            $crate::debug_log::remember("WARNING", &message);
+
             ::std::eprintln!(
-                "{blue}DEBUG LOG{reset_color} [{yellow}WARNING{reset_color}]: {}\n{where_and_when}",
-                format!($($arg)*),
+                "{blue}DEBUG LOG{reset_color} [{yellow}WARNING{reset_color}]: {message}\n{where_and_when}",
             );
         }
     }};
@@ -66,9 +72,11 @@ macro_rules! debug_log_error {
 
             let where_and_when = $crate::debug_log::where_and_when(blue, reset_color);
 
+            let message = format!($($arg)*);
+            $crate::debug_log::remember("ERROR", &message);
+
             ::std::eprintln!(
-                "{blue}DEBUG LOG{reset_color} [{red}ERROR{reset_color}]: {}\n{where_and_when}",
-                format!($($arg)*),
+                "{blue}DEBUG LOG{reset_color} [{red}ERROR{reset_color}]: {message}\n{where_and_when}",
             );
 
             if $crate::debug_log::panic_on_errors::enabled() {
@@ -146,6 +154,37 @@ pub fn where_and_when(color: &str, reset_color: &str) -> String {
         + format!("\tExec.: {color}{exec}{reset_color}").as_str()
 }
 
+/// The most recent messages logged, oldest first (e.g. `[WARNING] Some
+/// message`). At most [RECENT_MESSAGES_CAPACITY] are kept.
+///
+/// This is empty if logging isn't [enabled], since nothing gets logged.
+pub fn recent_messages() -> Vec<String> {
+    RECENT_MESSAGES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .cloned()
+        .collect()
+}
+
+/// How many messages [recent_messages] keeps.
+pub const RECENT_MESSAGES_CAPACITY: usize = 200;
+
+/// Add a message to [recent_messages].
+///
+/// This function gets called by the debug log macros (e.g. [debug_log_info])
+/// and generally shouldn't be called directly.
+#[doc(hidden)]
+pub fn remember(level: &str, message: &str) {
+    let mut recent = RECENT_MESSAGES.lock().unwrap_or_else(|e| e.into_inner());
+    if recent.len() == RECENT_MESSAGES_CAPACITY {
+        recent.pop_front();
+    }
+    recent.push_back(format!("[{level}] {message}"));
+}
+
+static RECENT_MESSAGES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
 #[cfg(debug_assertions)]
 static ENABLED: AtomicBool = AtomicBool::new(true);
 
@@ -279,6 +318,22 @@ mod decision_coverage_tests {
         assert!(!exec_line.trim_start_matches("\tExec.:").trim().is_empty());
     }
 
+    // --- remember() ---
+
+    #[test]
+    fn remember_keeps_only_the_most_recent_messages() {
+        let _g = StateGuard::acquire();
+        for i in 0..RECENT_MESSAGES_CAPACITY + 5 {
+            remember("INFO", &format!("message {i}"));
+        }
+        let recent = recent_messages();
+        assert_eq!(recent.len(), RECENT_MESSAGES_CAPACITY);
+        assert_eq!(
+            recent.last().map(String::as_str),
+            Some(format!("[INFO] message {}", RECENT_MESSAGES_CAPACITY + 4).as_str())
+        );
+    }
+
     // --- debug_log_info! ---
 
     #[test]
@@ -0,0 +1,217 @@
+//! This module contains [DiagnosticsReport], which gathers what's useful to
+//! know about the machine and the app into one text blob that users can attach
+//! to bug reports.
+//!
+//! A report starts with the app's version and the OS, and other crates add
+//! their own sections (e.g. the GPU adapter from the engine). The text ends
+//! with the most recent debug log messages (see
+//! [debug_log::recent_messages]). The user's home directory and name are
+//! redacted from everything.
+
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::{debug_log, local_data, version};
+
+/// Sections of `key: value` entries describing the app and the machine it's
+/// running on. See the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticsReport {
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Section {
+    title: String,
+    entries: Vec<(String, String)>,
+}
+
+impl DiagnosticsReport {
+    /// Create a report with the app's version and the OS filled in.
+    pub fn new() -> Self {
+        let mut report = Self {
+            sections: Vec::new(),
+        };
+
+        report.add(
+            "App",
+            "Version",
+            format!("{} {}", version::APP_NAME, version::APP_VERSION),
+        );
+        report.add(
+            "App",
+            "Build",
+            if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            },
+        );
+
+        report.add("System", "OS", os_description());
+        report.add("System", "Architecture", env::consts::ARCH);
+        report.add(
+            "System",
+            "Threads",
+            thread::available_parallelism()
+                .map_or_else(|e| format!("unknown ({e})"), |n| n.to_string()),
+        );
+
+        report
+    }
+
+    /// Add an entry to the section titled `section`, which is created (after
+    /// the existing ones) if it doesn't exist yet.
+    pub fn add(&mut self, section: &str, key: &str, value: impl Display) -> &mut Self {
+        let index = match self.sections.iter().position(|s| s.title == section) {
+            Some(index) => index,
+            None => {
+                self.sections.push(Section {
+                    title: section.to_string(),
+                    entries: Vec::new(),
+                });
+                self.sections.len() - 1
+            }
+        };
+        self.sections[index]
+            .entries
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// The report as redacted text, ending with the recent debug log messages.
+    pub fn to_text(&self) -> String {
+        let when = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_else(|e| format!("Unknown time: {e}"));
+
+        let mut text = format!(
+            "{} Diagnostics Report\nGenerated: {when}\n",
+            version::APP_NAME
+        );
+        for section in &self.sections {
+            text += &format!("\n[{}]\n", section.title);
+            for (key, value) in &section.entries {
+                text += &format!("{key}: {value}\n");
+            }
+        }
+
+        text += "\n[Debug Log]\n";
+        let messages = debug_log::recent_messages();
+        if messages.is_empty() {
+            text += "(nothing logged, or debug logging is disabled)\n";
+        }
+        for message in messages {
+            text += &message;
+            text.push('\n');
+        }
+
+        redact(
+            &text,
+            env::var("HOME")
+                .or_else(|_| env::var("USERPROFILE"))
+                .ok()
+                .as_deref(),
+            env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .ok()
+                .as_deref(),
+        )
+    }
+
+    /// Save [Self::to_text] to a new file in the
+    /// [diagnostics folder](local_data::diagnostics_path), returning its path.
+    pub fn save(&self) -> Result<PathBuf, io::Error> {
+        let file_name = OffsetDateTime::now_local()
+            .ok()
+            .and_then(|now| {
+                now.format(time::macros::format_description!(
+                    "[year]-[month]-[day]_[hour]-[minute]-[second]"
+                ))
+                .ok()
+            })
+            .unwrap_or_else(|| String::from("unknown_time"));
+
+        let path = local_data::diagnostics_path().join(format!("diagnostics_{file_name}.txt"));
+        fs::write(&path, self.to_text())?;
+        Ok(path)
+    }
+}
+
+impl Default for DiagnosticsReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Replace the user's `home` directory with `~` and their `user` name with
+/// `<user>` in `text`. User names shorter than 3 characters are left alone
+/// since they'd match too much else.
+fn redact(text: &str, home: Option<&str>, user: Option<&str>) -> String {
+    let mut text = text.to_string();
+    if let Some(home) = home.filter(|home| home.len() > 1) {
+        text = text.replace(home.trim_end_matches(['/', '\\']), "~");
+    }
+    if let Some(user) = user.filter(|user| user.len() >= 3) {
+        text = text.replace(user, "<user>");
+    }
+    text
+}
+
+/// The OS's name, and its release where it's easy to find.
+fn os_description() -> String {
+    #[cfg(target_os = "linux")]
+    if let Ok(os_release) = fs::read_to_string("/etc/os-release")
+        && let Some(name) = os_release
+            .lines()
+            .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+    {
+        return format!("linux ({})", name.trim_matches('"'));
+    }
+
+    env::consts::OS.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- DiagnosticsReport::add() ---
+
+    #[test]
+    fn add_groups_entries_by_section() {
+        let mut report = DiagnosticsReport::new();
+        report.add("GPU", "Name", "Test Adapter");
+        report.add("App", "Safe Mode", true);
+        report.add("GPU", "Backend", "Vulkan");
+
+        let text = report.to_text();
+        let gpu = text.find("[GPU]").expect("GPU section missing");
+        assert!(text.find("Safe Mode: true").expect("entry missing") < gpu);
+        assert!(text[gpu..].contains("Name: Test Adapter\nBackend: Vulkan\n"));
+        assert!(text.contains("[Debug Log]"));
+    }
+
+    // --- redact() ---
+
+    #[test]
+    fn redact_removes_home_and_user() {
+        let text = "Project: /home/sam/Videos/cells.mp4\nLogged in as sam";
+        assert_eq!(
+            redact(text, Some("/home/sam/"), Some("sam")),
+            "Project: ~/Videos/cells.mp4\nLogged in as <user>"
+        );
+    }
+
+    #[test]
+    fn redact_skips_short_user_names() {
+        assert_eq!(redact("a blob", None, Some("a")), "a blob");
+    }
+}
@@ -0,0 +1,284 @@
+//! Defines [InstanceLock], a lock that, when held, indicates that this is the
+//! main instance of some part of the app (e.g. the launcher or the editor).
+//!
+//! The holder of a lock can also beat a heartbeat ([InstanceLock::heartbeat])
+//! and receive requests from other instances ([RequestReceiver]), so other
+//! instances can hand work to it ([send_request]) instead of doing it
+//! themselves, as long as it's still responding ([holder_is_responsive]).
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use thiserror::Error;
+
+use crate::local_data;
+use crate::saved_file::{self, SavedFile, SavedFileError};
+use crate::version;
+
+/// The name of the launcher's instance lock.
+pub const LAUNCHER_LOCK: &str = "launcher";
+
+/// The name of the editor's instance lock.
+pub const EDITOR_LOCK: &str = "editor";
+
+/// How often the holder of a lock should call [InstanceLock::heartbeat].
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long the holder of a lock can go without a heartbeat before
+/// [holder_is_responsive] gives up on it.
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// While held, no other instance can be the main instance. The instance lock
+/// will be unlocked when this is dropped.
+#[derive(Debug)]
+pub struct InstanceLock<T: SavedFile> {
+    name: &'static str,
+    data: InstanceLockData<T>,
+    lock_file: File,
+}
+
+impl<T: SavedFile> InstanceLock<T> {
+    /// Open the instance lock called `name`, calling `f` to generate the file's
+    /// data if it doesn't already exist. [InstanceLockError::Locked] is
+    /// returned if the lock is already being held.
+    pub fn new<F>(name: &'static str, f: F) -> Result<Self, InstanceLockError>
+    where
+        F: FnOnce() -> T,
+    {
+        let (lock_file, lock_file_created) =
+            saved_file::open_file_with_create_info(lock_file_path(name))?;
+
+        if let Err(e) = lock_file.try_lock() {
+            match e {
+                TryLockError::Error(e) => return Err(e.into()),
+                TryLockError::WouldBlock => {
+                    if lock_file_created {
+                        crate::debug_log_error!("Lock file was created but couldn't be locked.");
+                    }
+                    return Err(InstanceLockError::Locked);
+                }
+            }
+        }
+
+        let data = if !lock_file_created {
+            let mut data = InstanceLockData::<T>::read_from_file(&lock_file).inspect_err(|e| {
+                crate::debug_log_error!("Failed to read from file: {e}");
+            })?;
+
+            if data.app_version != version::APP_VERSION {
+                crate::debug_log_warning!("Converting lock file to new version.");
+                data.app_version = version::APP_VERSION.into();
+            }
+
+            data
+        } else {
+            let data = InstanceLockData::new(f());
+            data.save_to_file(&lock_file).inspect_err(|e| {
+                crate::debug_log_error!("Failed to save to file: {e}");
+            })?;
+            data
+        };
+
+        let instance_lock = Self {
+            name,
+            data,
+            lock_file,
+        };
+        _ = instance_lock.heartbeat().inspect_err(|e| {
+            crate::debug_log_error!("Failed to write first heartbeat (ignoring): {e}");
+        });
+        Ok(instance_lock)
+    }
+
+    /// The same as [Self::new] but [T::default](Default::default) is used
+    /// instead of a callback function.
+    pub fn from_default(name: &'static str) -> Result<Self, InstanceLockError>
+    where
+        T: Default,
+    {
+        Self::new(name, T::default)
+    }
+
+    /// Access the saved data.
+    pub fn data(&self) -> &T {
+        &self.data.data
+    }
+
+    /// Access the saved data *mutably*, saving the data after.
+    pub fn with_data<F>(&mut self, f: F) -> Result<(), SavedFileError>
+    where
+        F: FnOnce(&mut T),
+    {
+        f(&mut self.data.data);
+        self.data.save_to_file(&self.lock_file)
+    }
+
+    /// Record that the holder is still responding. This should be called about
+    /// every [HEARTBEAT_INTERVAL] from wherever the holder handles requests, so
+    /// a holder that's hung can be told apart from one that's just busy (the
+    /// lock itself is only released once the holder exits).
+    pub fn heartbeat(&self) -> Result<(), io::Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+        fs::write(heartbeat_file_path(self.name), now.as_secs().to_string())
+    }
+}
+
+impl<T: SavedFile> Drop for InstanceLock<T> {
+    fn drop(&mut self) {
+        _ = fs::remove_file(heartbeat_file_path(self.name)).inspect_err(|e| {
+            crate::debug_log_error!("Failed to remove heartbeat file in `Drop` (ignoring): {e}");
+        });
+        _ = self.lock_file.unlock().inspect_err(|e| {
+            crate::debug_log_error!(
+                "Failed to unlock instance lock file in `Drop` (ignoring): {e}"
+            );
+        });
+    }
+}
+
+/// Indicates that something went wrong trying to acquire an [InstanceLock].
+#[derive(Error, Debug)]
+pub enum InstanceLockError {
+    #[error("The instance lock is already locked.")]
+    Locked,
+    #[error("Something went wrong with the instance lock file: {0}")]
+    SavedFileError(#[from] SavedFileError),
+}
+
+impl From<io::Error> for InstanceLockError {
+    fn from(e: io::Error) -> Self {
+        SavedFileError::from(e).into()
+    }
+}
+
+/// Whether the instance lock called `name` is held by an instance that has
+/// beaten its heartbeat in the last [HEARTBEAT_TIMEOUT].
+pub fn holder_is_responsive(name: &str) -> bool {
+    let Ok(lock_file) = File::open(lock_file_path(name)) else {
+        return false;
+    };
+    match lock_file.try_lock_shared() {
+        Ok(()) => {
+            // Nobody is holding it.
+            _ = lock_file.unlock();
+            return false;
+        }
+        Err(TryLockError::WouldBlock) => {}
+        Err(TryLockError::Error(e)) => {
+            crate::debug_log_error!("Failed to check instance lock `{name}`: {e}");
+            return false;
+        }
+    }
+
+    let Some(last_beat) = fs::read_to_string(heartbeat_file_path(name))
+        .ok()
+        .and_then(|secs| secs.trim().parse().ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    else {
+        return false;
+    };
+    SystemTime::now()
+        .duration_since(last_beat)
+        .is_ok_and(|since| since < HEARTBEAT_TIMEOUT)
+}
+
+/// Send a request to the holder of the instance lock called `name`, which it
+/// gets from its [RequestReceiver]. Requests are single lines of text.
+pub fn send_request(name: &str, request: &str) -> Result<(), io::Error> {
+    debug_assert!(!request.contains('\n'), "Requests can't contain newlines.");
+
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(requests_file_path(name))?;
+    file.lock()?;
+    let ret = writeln!(file, "{request}");
+    file.unlock()?;
+    ret
+}
+
+/// For receiving the requests [send_request] sends. This should *only* exist
+/// on the instance holding the lock it's for.
+#[derive(Debug)]
+pub struct RequestReceiver {
+    file: File,
+    file_path: PathBuf,
+}
+
+impl RequestReceiver {
+    /// Create a receiver for the instance lock called `name`, dropping any
+    /// requests left over from a previous holder.
+    pub fn new(name: &str) -> Result<Self, io::Error> {
+        let file_path = requests_file_path(name);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&file_path)?;
+        Ok(Self { file, file_path })
+    }
+
+    /// Receive every request that's been sent since the last call.
+    pub fn receive(&mut self) -> Result<Vec<String>, io::Error> {
+        self.file.lock()?;
+        let requests = read_and_clear(&mut self.file);
+        self.file.unlock()?;
+
+        Ok(requests?
+            .lines()
+            .filter(|request| !request.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+impl Drop for RequestReceiver {
+    fn drop(&mut self) {
+        _ = fs::remove_file(&self.file_path).inspect_err(|e| {
+            crate::debug_log_error!("Failed to remove requests file in `Drop` (ignoring): {e}");
+        });
+    }
+}
+
+fn read_and_clear(file: &mut File) -> Result<String, io::Error> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    file.set_len(0)?;
+    Ok(contents)
+}
+
+fn lock_file_path(name: &str) -> PathBuf {
+    local_data::root_path().join(format!("{name}.json"))
+}
+
+fn heartbeat_file_path(name: &str) -> PathBuf {
+    local_data::root_path().join(format!("{name}_heartbeat.txt"))
+}
+
+fn requests_file_path(name: &str) -> PathBuf {
+    local_data::root_path().join(format!("{name}_requests.txt"))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "T: Serialize + DeserializeOwned")]
+struct InstanceLockData<T> {
+    app_version: String,
+    data: T,
+}
+
+impl<T> InstanceLockData<T> {
+    /// Create an instance that uses the app's current version.
+    pub fn new(data: T) -> Self {
+        Self {
+            app_version: version::APP_VERSION.into(),
+            data,
+        }
+    }
+}
@@ -1,6 +1,8 @@
 //! This crate contains useful utilities that will be shared between different
 //! parts of the project.
 
+#[cfg(feature = "bus")]
+pub mod bus;
 #[cfg(feature = "cast_slice")]
 pub mod cast_slice;
 #[cfg(feature = "channels")]
@@ -9,29 +11,43 @@ pub mod channels;
 pub mod crash_reporting;
 #[cfg(feature = "debug_log")]
 pub mod debug_log;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
 #[cfg(feature = "drop_join_thread")]
 pub mod drop_join_thread;
 #[cfg(feature = "fuzzy_search")]
 pub mod fuzzy_search;
 #[cfg(feature = "gcd")]
 pub mod gcd;
+#[cfg(feature = "instance_lock")]
+pub mod instance_lock;
+#[cfg(feature = "link")]
+pub mod link;
 #[cfg(feature = "local_data")]
 pub mod local_data;
+#[cfg(feature = "panic_capture")]
+pub mod panic_capture;
 #[cfg(feature = "read_write_at")]
 pub mod read_write_at;
 #[cfg(feature = "rolling_avg")]
 pub mod rolling_avg;
 #[cfg(feature = "saved_file")]
 pub mod saved_file;
+#[cfg(feature = "shutdown")]
+pub mod shutdown;
 #[cfg(feature = "stop_signals")]
 pub mod stop_signals;
 #[cfg(feature = "strn")]
 pub mod strn;
+#[cfg(feature = "timecode")]
+pub mod timecode;
 #[cfg(feature = "ui")]
 pub mod ui;
 #[cfg(feature = "uid")]
 pub mod uid;
 #[cfg(feature = "version")]
 pub mod version;
+#[cfg(feature = "watchdog")]
+pub mod watchdog;
 #[cfg(feature = "windows_build")]
 pub mod windows_build;
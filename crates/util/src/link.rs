@@ -0,0 +1,548 @@
+//! This module contains [LinkSession], a peer in an
+//! [Ableton Link](https://www.ableton.com/link/) session: a tempo and beat
+//! phase shared by every Link app (DAWs, visual tools, ...) on the local
+//! network.
+//!
+//! Peers find each other by multicasting what they know to everyone every
+//! quarter second. Each session has its own shared clock ("ghost time"), and a
+//! peer measures the offset between its own clock and another session's by
+//! pinging one of its peers before joining it. Peers in a session all follow
+//! the same timeline (tempo plus where the beats fall in ghost time), and
+//! changing it on any peer changes it on all of them.
+//!
+//! Transport start/stop isn't shared.
+//!
+//! This is experimental. It's written from Ableton's open source library, but
+//! hasn't been checked against packets from real Link apps (see [wire]), so it
+//! may not find or agree with them.
+
+mod socket;
+mod wire;
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::drop_join_thread::{self, DropJoinHandle};
+use wire::{NodeId, PeerMessage, PeerMessageKind, PeerState, Pong, Timeline};
+
+/// The multicast group and port peers announce themselves on.
+const MULTICAST_GROUP: Ipv4Addr = Ipv4Addr::new(224, 76, 78, 75);
+const MULTICAST_PORT: u16 = 20808;
+
+/// How often peers announce themselves, and how many seconds a peer is
+/// remembered without hearing from it.
+const ANNOUNCE_INTERVAL: Duration = Duration::from_millis(250);
+const PEER_TTL: u8 = 5;
+
+/// How many pings a measurement takes, how far apart they are, and how long a
+/// measurement is given before it's abandoned.
+const MEASUREMENT_PINGS: usize = 5;
+const PING_INTERVAL: Duration = Duration::from_millis(50);
+const MEASUREMENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often the offset to the session's ghost time is measured again, so the
+/// clocks don't drift apart.
+const REMEASURE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Sessions whose ghost times are closer than this (in microseconds) are
+/// considered to have started at the same time.
+const SESSION_EPSILON: i64 = 500_000;
+
+/// How long the network thread waits between checking its sockets.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum LinkError {
+    #[error("Failed to open the Link discovery socket: {0}")]
+    Discovery(io::Error),
+    #[error("Failed to open a Link socket: {0}")]
+    Socket(io::Error),
+}
+
+/// A peer in a Link session. See the [module docs](self).
+///
+/// Starts out alone in a session of its own, and joins other peers' sessions
+/// as they're found. Leaves the session when dropped.
+#[derive(Debug)]
+pub struct LinkSession {
+    state: Arc<Mutex<SessionState>>,
+    stop: Arc<AtomicBool>,
+    _thread: DropJoinHandle<()>,
+}
+
+impl LinkSession {
+    /// Start a session at `bpm` and begin looking for peers.
+    pub fn join(bpm: f64) -> Result<Self, LinkError> {
+        let discovery = socket::bind_multicast(MULTICAST_GROUP, MULTICAST_PORT)
+            .map_err(LinkError::Discovery)?;
+        let unicast = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(LinkError::Socket)?;
+        for socket in [&discovery, &unicast] {
+            socket.set_nonblocking(true).map_err(LinkError::Socket)?;
+        }
+
+        let port = unicast.local_addr().map_err(LinkError::Socket)?.port();
+        let endpoint = SocketAddrV4::new(interface_address(), port);
+
+        let node = NodeId::random();
+        let state = Arc::new(Mutex::new(SessionState {
+            node,
+            session: node,
+            ghost_offset: 0,
+            timeline: timeline_at(bpm, 0.0, 0),
+            peers: HashMap::new(),
+            measurement: None,
+            last_measured: Instant::now(),
+            timeline_changed: false,
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let mut network = Network {
+                state: Arc::clone(&state),
+                stop: Arc::clone(&stop),
+                discovery,
+                unicast,
+                endpoint,
+            };
+            drop_join_thread::spawn(move || network.run())
+        };
+
+        Ok(Self {
+            state,
+            stop,
+            _thread: thread,
+        })
+    }
+
+    /// How many other peers are in the session.
+    pub fn peer_count(&self) -> usize {
+        let state = self.lock();
+        state
+            .peers
+            .values()
+            .filter(|peer| peer.state.session == state.session)
+            .count()
+    }
+
+    /// The session's tempo.
+    pub fn tempo(&self) -> f64 {
+        60_000_000.0 / self.lock().timeline.micros_per_beat as f64
+    }
+
+    /// The session's beat at `at`. Peers only agree on the phase of the beat
+    /// (see [LinkSession::phase_at]), not on the beat count itself.
+    pub fn beat_at(&self, at: Instant) -> f64 {
+        let state = self.lock();
+        beat_at_ghost(&state.timeline, host_micros_at(at) + state.ghost_offset)
+    }
+
+    /// How far through a bar of `quantum` beats the session is at `at`.
+    pub fn phase_at(&self, at: Instant, quantum: f64) -> f64 {
+        if quantum > 0.0 {
+            self.beat_at(at).rem_euclid(quantum)
+        } else {
+            0.0
+        }
+    }
+
+    /// Change the session's tempo for every peer, keeping the beat at `at`
+    /// where it is.
+    pub fn set_tempo(&self, bpm: f64, at: Instant) {
+        if !bpm.is_finite() || bpm <= 0.0 {
+            return;
+        }
+
+        let mut state = self.lock();
+        let ghost = host_micros_at(at) + state.ghost_offset;
+        let beat = beat_at_ghost(&state.timeline, ghost);
+        let timeline = timeline_at(bpm, beat, ghost);
+        if timeline.micros_per_beat != state.timeline.micros_per_beat {
+            state.timeline = timeline;
+            state.timeline_changed = true;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SessionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for LinkSession {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Everything a peer knows, shared between [LinkSession] and its network
+/// thread.
+#[derive(Debug)]
+struct SessionState {
+    node: NodeId,
+    session: NodeId,
+    /// Ghost time minus host time, in microseconds.
+    ghost_offset: i64,
+    timeline: Timeline,
+    peers: HashMap<NodeId, Peer>,
+    measurement: Option<Measurement>,
+    last_measured: Instant,
+    /// Whether the timeline was changed here and the other peers need to hear
+    /// about it now instead of at the next announcement.
+    timeline_changed: bool,
+}
+
+impl SessionState {
+    fn peer_state(&self, endpoint: SocketAddrV4) -> PeerState {
+        PeerState {
+            timeline: self.timeline,
+            session: self.session,
+            endpoint: Some(endpoint),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Peer {
+    state: PeerState,
+    expires: Instant,
+}
+
+/// Pinging a peer to find the offset from host time to its session's ghost
+/// time.
+#[derive(Debug)]
+struct Measurement {
+    session: NodeId,
+    timeline: Timeline,
+    endpoint: SocketAddrV4,
+    started: Instant,
+    pings_sent: usize,
+    last_ping: Option<Instant>,
+    last_ghost_time: Option<i64>,
+    /// Estimates of ghost time minus host time.
+    samples: Vec<i64>,
+}
+
+impl Measurement {
+    fn new(state: &PeerState, endpoint: SocketAddrV4) -> Self {
+        Self {
+            session: state.session,
+            timeline: state.timeline,
+            endpoint,
+            started: Instant::now(),
+            pings_sent: 0,
+            last_ping: None,
+            last_ghost_time: None,
+            samples: Vec::new(),
+        }
+    }
+
+    fn add_pong(&mut self, pong: &Pong, now: i64) {
+        if pong.session != self.session {
+            return;
+        }
+
+        self.samples
+            .push(pong.ghost_time - (pong.host_time + now) / 2);
+        if let Some(prev_ghost_time) = pong.prev_ghost_time {
+            self.samples
+                .push((pong.ghost_time + prev_ghost_time) / 2 - pong.host_time);
+        }
+        self.last_ghost_time = Some(pong.ghost_time);
+    }
+
+    /// The measured offset, once every ping has been answered.
+    fn offset(&self) -> Option<i64> {
+        if self.samples.len() < MEASUREMENT_PINGS {
+            return None;
+        }
+
+        let mut samples = self.samples.clone();
+        samples.sort_unstable();
+        Some(samples[samples.len() / 2])
+    }
+}
+
+/// The network thread's half of a [LinkSession].
+struct Network {
+    state: Arc<Mutex<SessionState>>,
+    stop: Arc<AtomicBool>,
+    discovery: UdpSocket,
+    unicast: UdpSocket,
+    /// Where this peer answers pings (the unicast socket).
+    endpoint: SocketAddrV4,
+}
+
+impl Network {
+    fn run(&mut self) {
+        let mut last_announced: Option<Instant> = None;
+        let mut buffer = [0u8; 512];
+
+        while !self.stop.load(Ordering::Relaxed) {
+            let now = Instant::now();
+            let timeline_changed = std::mem::take(&mut self.lock().timeline_changed);
+            if timeline_changed || last_announced.is_none_or(|last| now - last >= ANNOUNCE_INTERVAL)
+            {
+                last_announced = Some(now);
+                self.send_peer_message(PeerMessageKind::Alive, multicast_address());
+            }
+
+            while let Ok((len, from)) = self.discovery.recv_from(&mut buffer) {
+                self.receive(&buffer[..len], from);
+            }
+            while let Ok((len, from)) = self.unicast.recv_from(&mut buffer) {
+                self.receive(&buffer[..len], from);
+            }
+
+            self.update_measurement();
+            self.lock().peers.retain(|_, peer| peer.expires > now);
+
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        self.send_peer_message(PeerMessageKind::ByeBye, multicast_address());
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SessionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn send_peer_message(&self, kind: PeerMessageKind, to: SocketAddr) {
+        let message = {
+            let state = self.lock();
+            PeerMessage {
+                kind,
+                ttl: if kind == PeerMessageKind::ByeBye {
+                    0
+                } else {
+                    PEER_TTL
+                },
+                node: state.node,
+                state: (kind != PeerMessageKind::ByeBye).then(|| state.peer_state(self.endpoint)),
+            }
+        };
+        if let Err(e) = self.unicast.send_to(&message.encode(), to) {
+            crate::debug_log_warning!("Failed to send Link message (ignoring): {e}");
+        }
+    }
+
+    fn receive(&self, bytes: &[u8], from: SocketAddr) {
+        if wire::is_ping(bytes) {
+            let pong = {
+                let state = self.lock();
+                let ghost_time = host_micros() + state.ghost_offset;
+                wire::encode_pong(bytes, state.session, ghost_time)
+            };
+            if let Some(pong) = pong {
+                _ = self.unicast.send_to(&pong, from);
+            }
+        } else if let Some(pong) = Pong::decode(bytes) {
+            if let Some(measurement) = &mut self.lock().measurement {
+                measurement.add_pong(&pong, host_micros());
+            }
+        } else if let Some(message) = PeerMessage::decode(bytes) {
+            self.receive_peer_message(message, from);
+        }
+    }
+
+    fn receive_peer_message(&self, message: PeerMessage, from: SocketAddr) {
+        let mut state = self.lock();
+        if message.node == state.node {
+            return;
+        }
+
+        let Some(peer_state) = message.state else {
+            state.peers.remove(&message.node);
+            return;
+        };
+
+        let is_new = state
+            .peers
+            .insert(
+                message.node,
+                Peer {
+                    state: peer_state,
+                    expires: Instant::now() + Duration::from_secs(message.ttl.into()),
+                },
+            )
+            .is_none_or(|peer| peer.state != peer_state);
+
+        if peer_state.session == state.session {
+            if peer_state.timeline != state.timeline && is_new {
+                state.timeline = peer_state.timeline;
+            }
+        } else if state.measurement.is_none()
+            && let Some(endpoint) = peer_state.endpoint
+        {
+            state.measurement = Some(Measurement::new(&peer_state, endpoint));
+        }
+        drop(state);
+
+        if message.kind == PeerMessageKind::Alive {
+            self.send_peer_message(PeerMessageKind::Response, from);
+        }
+    }
+
+    /// Send the next ping of the measurement in progress, or finish it. Starts
+    /// measuring the session again every so often.
+    fn update_measurement(&self) {
+        let now = Instant::now();
+        let mut state = self.lock();
+
+        if state.measurement.is_none()
+            && state.session != state.node
+            && now - state.last_measured >= REMEASURE_INTERVAL
+        {
+            let session = state.session;
+            let peer = state
+                .peers
+                .values()
+                .find(|peer| peer.state.session == session)
+                .and_then(|peer| Some((peer.state, peer.state.endpoint?)));
+            if let Some((peer_state, endpoint)) = peer {
+                state.measurement = Some(Measurement::new(&peer_state, endpoint));
+            }
+            state.last_measured = now;
+        }
+
+        let Some(measurement) = &mut state.measurement else {
+            return;
+        };
+
+        if let Some(offset) = measurement.offset() {
+            let session = measurement.session;
+            let timeline = measurement.timeline;
+            state.measurement = None;
+            state.last_measured = now;
+
+            if session == state.session {
+                state.ghost_offset = offset;
+            } else if should_join(state.session, state.ghost_offset, session, offset) {
+                crate::debug_log_info!("Joined Link session with a tempo of {} BPM.", {
+                    60_000_000.0 / timeline.micros_per_beat as f64
+                });
+                state.session = session;
+                state.ghost_offset = offset;
+                state.timeline = timeline;
+            }
+            return;
+        }
+
+        if now - measurement.started >= MEASUREMENT_TIMEOUT {
+            state.measurement = None;
+            return;
+        }
+
+        if measurement.pings_sent < MEASUREMENT_PINGS
+            && measurement
+                .last_ping
+                .is_none_or(|last| now - last >= PING_INTERVAL)
+        {
+            measurement.pings_sent += 1;
+            measurement.last_ping = Some(now);
+            let ping = wire::encode_ping(host_micros(), measurement.last_ghost_time);
+            _ = self.unicast.send_to(&ping, measurement.endpoint);
+        }
+    }
+}
+
+/// Whether to leave `session` for `other`, given the offsets from host time to
+/// each one's ghost time. The session that's been running longer (whose ghost
+/// time is further ahead) wins, and sessions started at about the same time
+/// are decided by ID so every peer picks the same one.
+fn should_join(session: NodeId, offset: i64, other: NodeId, other_offset: i64) -> bool {
+    let ahead_by = other_offset - offset;
+    ahead_by > SESSION_EPSILON || (ahead_by.abs() <= SESSION_EPSILON && other < session)
+}
+
+/// A timeline at `bpm` where the beat at `ghost` is `beat`.
+fn timeline_at(bpm: f64, beat: f64, ghost: i64) -> Timeline {
+    Timeline {
+        micros_per_beat: (60_000_000.0 / bpm).round() as i64,
+        beat_origin: (beat * 1_000_000.0).round() as i64,
+        time_origin: ghost,
+    }
+}
+
+/// The beat of `timeline` at the ghost time `ghost`.
+fn beat_at_ghost(timeline: &Timeline, ghost: i64) -> f64 {
+    let micros_per_beat = timeline.micros_per_beat.max(1) as f64;
+    timeline.beat_origin as f64 / 1_000_000.0
+        + (ghost - timeline.time_origin) as f64 / micros_per_beat
+}
+
+fn multicast_address() -> SocketAddr {
+    SocketAddr::V4(SocketAddrV4::new(MULTICAST_GROUP, MULTICAST_PORT))
+}
+
+/// The address of the interface multicast traffic goes out on, so peers can
+/// ping this one directly.
+fn interface_address() -> Ipv4Addr {
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|probe| {
+            probe.connect(multicast_address())?;
+            probe.local_addr()
+        })
+        .ok()
+        .and_then(|address| match address {
+            SocketAddr::V4(address) if !address.ip().is_unspecified() => Some(*address.ip()),
+            _ => None,
+        })
+        .unwrap_or(Ipv4Addr::LOCALHOST)
+}
+
+/// This process's clock, in microseconds since it was first read.
+fn host_micros() -> i64 {
+    host_micros_at(Instant::now())
+}
+
+fn host_micros_at(at: Instant) -> i64 {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    let epoch = *EPOCH.get_or_init(Instant::now);
+    if at >= epoch {
+        at.duration_since(epoch).as_micros() as i64
+    } else {
+        -(epoch.duration_since(at).as_micros() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- beat_at_ghost() ---
+
+    #[test]
+    fn test_timeline() {
+        let timeline = timeline_at(120.0, 8.0, 1_000_000);
+        assert_eq!(timeline.micros_per_beat, 500_000);
+        assert_eq!(beat_at_ghost(&timeline, 1_000_000), 8.0);
+        assert_eq!(beat_at_ghost(&timeline, 2_000_000), 10.0);
+        assert_eq!(beat_at_ghost(&timeline, 0), 6.0);
+
+        // Changing tempo keeps the beat where it is.
+        let faster = timeline_at(240.0, beat_at_ghost(&timeline, 2_000_000), 2_000_000);
+        assert_eq!(beat_at_ghost(&faster, 2_000_000), 10.0);
+        assert_eq!(beat_at_ghost(&faster, 2_500_000), 12.0);
+    }
+
+    // --- should_join() ---
+
+    #[test]
+    fn test_session_choice() {
+        let a = NodeId([1; 8]);
+        let b = NodeId([2; 8]);
+
+        // Older sessions win.
+        assert!(should_join(a, 0, b, 2_000_000));
+        assert!(!should_join(b, 2_000_000, a, 0));
+
+        // Sessions started together go to the lower ID either way.
+        assert!(should_join(b, 0, a, 100_000));
+        assert!(should_join(b, 100_000, a, 0));
+        assert!(!should_join(a, 0, b, 100_000));
+    }
+}
@@ -0,0 +1,121 @@
+//! The discovery socket. Every Link app on a computer listens on the same
+//! multicast port, which [UdpSocket::bind] can't do (it doesn't set
+//! `SO_REUSEADDR`), so the socket is made by hand and handed to std.
+
+use std::io;
+use std::net::{Ipv4Addr, UdpSocket};
+
+/// Bind a UDP socket to `port` on every interface, sharing the port with any
+/// other sockets bound to it, and join the `group` multicast group.
+pub fn bind_multicast(group: Ipv4Addr, port: u16) -> io::Result<UdpSocket> {
+    let socket = bind_reusable(port)?;
+    socket.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)?;
+    // Our own messages are ignored by ID anyway, but the loopback is how other
+    // Link apps on this computer hear us.
+    socket.set_multicast_loop_v4(true)?;
+    Ok(socket)
+}
+
+#[cfg(unix)]
+fn bind_reusable(port: u16) -> io::Result<UdpSocket> {
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: Plain socket calls. The socket is closed on every error path and
+    // otherwise owned by the returned `UdpSocket`.
+    unsafe {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, libc::IPPROTO_UDP);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let close_with_error = || {
+            let error = io::Error::last_os_error();
+            libc::close(fd);
+            Err(error)
+        };
+
+        let enable: libc::c_int = 1;
+        for option in [libc::SO_REUSEADDR, libc::SO_REUSEPORT] {
+            if libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                option,
+                (&raw const enable).cast(),
+                size_of::<libc::c_int>() as libc::socklen_t,
+            ) != 0
+            {
+                return close_with_error();
+            }
+        }
+
+        let mut address: libc::sockaddr_in = std::mem::zeroed();
+        address.sin_family = libc::AF_INET as libc::sa_family_t;
+        address.sin_port = port.to_be();
+        address.sin_addr.s_addr = u32::from(Ipv4Addr::UNSPECIFIED).to_be();
+        if libc::bind(
+            fd,
+            (&raw const address).cast(),
+            size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        ) != 0
+        {
+            return close_with_error();
+        }
+
+        Ok(UdpSocket::from_raw_fd(fd))
+    }
+}
+
+#[cfg(windows)]
+fn bind_reusable(port: u16) -> io::Result<UdpSocket> {
+    use std::os::windows::io::FromRawSocket;
+    use windows_sys::Win32::Networking::WinSock;
+
+    // Creating any std socket makes sure Winsock has been started up.
+    drop(UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?);
+
+    // SAFETY: Plain socket calls. The socket is closed on every error path and
+    // otherwise owned by the returned `UdpSocket`.
+    unsafe {
+        let socket = WinSock::socket(
+            WinSock::AF_INET as i32,
+            WinSock::SOCK_DGRAM,
+            WinSock::IPPROTO_UDP,
+        );
+        if socket == WinSock::INVALID_SOCKET {
+            return Err(io::Error::last_os_error());
+        }
+
+        let close_with_error = || {
+            let error = io::Error::last_os_error();
+            WinSock::closesocket(socket);
+            Err(error)
+        };
+
+        let enable: i32 = 1;
+        if WinSock::setsockopt(
+            socket,
+            WinSock::SOL_SOCKET,
+            WinSock::SO_REUSEADDR,
+            (&raw const enable).cast(),
+            size_of::<i32>() as i32,
+        ) != 0
+        {
+            return close_with_error();
+        }
+
+        let mut address: WinSock::SOCKADDR_IN = std::mem::zeroed();
+        address.sin_family = WinSock::AF_INET;
+        address.sin_port = port.to_be();
+        address.sin_addr.S_un.S_addr = u32::from(Ipv4Addr::UNSPECIFIED).to_be();
+        if WinSock::bind(
+            socket,
+            (&raw const address).cast(),
+            size_of::<WinSock::SOCKADDR_IN>() as i32,
+        ) != 0
+        {
+            return close_with_error();
+        }
+
+        Ok(UdpSocket::from_raw_socket(socket as _))
+    }
+}
@@ -0,0 +1,403 @@
+//! The message formats of Link's discovery and measurement protocols.
+//!
+//! Every message is a protocol header, a message header, then a payload of
+//! entries. An entry is a four character key and a size (both big endian
+//! `u32`s) followed by that many bytes. Entries with keys that aren't known
+//! are skipped.
+//!
+//! The layouts follow the message definitions in Ableton's open source Link
+//! library (<https://github.com/Ableton/link>), which is the only reference
+//! for the protocol. They haven't been tested against a running Link peer;
+//! the tests below pin the exact bytes instead. All numbers are big endian:
+//!
+//! - Discovery (`include/ableton/discovery/v1/Messages.hpp`):
+//!   `_asdp_v\x01`, then the message type (`u8`, 1 = alive, 2 = response,
+//!   3 = bye bye), TTL in seconds (`u8`), group ID (`u16`, always 0) and the
+//!   sender's node ID (8 bytes).
+//! - Measurement (`include/ableton/link/v1/Messages.hpp`): `_link_v\x01`,
+//!   then the message type (`u8`, 1 = ping, 2 = pong). A pong's payload is
+//!   the session and ghost time followed by the ping's payload, unchanged.
+//!
+//! Payload entries (named after their types in the library):
+//!
+//! - `tmln` (`Timeline`): microseconds per beat, the beat origin in
+//!   millionths of a beat, and the time origin in microseconds (`i64`s).
+//! - `sess` (`SessionMembership`): the session ID (8 bytes).
+//! - `stst` (`StartStopState`): whether the transport is playing (`u8`),
+//!   then beats in millionths and a timestamp in microseconds (`i64`s).
+//! - `mep4` (`MeasurementEndpointV4`): where a peer answers pings, as an
+//!   IPv4 address (4 bytes) and port (`u16`).
+//! - `hst_`, `__gt` and `_pgt` (`HostTime`, `GHostTime` and `PrevGHostTime`):
+//!   host time, ghost time and previous ghost time in microseconds (`i64`s).
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+/// The start of every discovery message.
+pub const DISCOVERY_HEADER: &[u8; 8] = b"_asdp_v\x01";
+
+/// The start of every measurement message.
+pub const MEASUREMENT_HEADER: &[u8; 8] = b"_link_v\x01";
+
+const TIMELINE_KEY: u32 = u32::from_be_bytes(*b"tmln");
+const SESSION_KEY: u32 = u32::from_be_bytes(*b"sess");
+const START_STOP_KEY: u32 = u32::from_be_bytes(*b"stst");
+const ENDPOINT_V4_KEY: u32 = u32::from_be_bytes(*b"mep4");
+const HOST_TIME_KEY: u32 = u32::from_be_bytes(*b"hst_");
+const GHOST_TIME_KEY: u32 = u32::from_be_bytes(*b"__gt");
+const PREV_GHOST_TIME_KEY: u32 = u32::from_be_bytes(*b"_pgt");
+
+/// The ID of one Link peer, or of a session (which takes the ID of the peer
+/// that started it).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub [u8; 8]);
+
+impl NodeId {
+    /// A new random ID.
+    pub fn random() -> Self {
+        use std::collections::hash_map::RandomState;
+        use std::hash::{BuildHasher, Hasher};
+
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(super::host_micros() as u128);
+        Self(hasher.finish().to_be_bytes())
+    }
+}
+
+/// A session's tempo and where its beats line up, in the session's shared
+/// ("ghost") time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeline {
+    pub micros_per_beat: i64,
+    /// The beat at `time_origin`, in millionths of a beat.
+    pub beat_origin: i64,
+    /// Ghost time in microseconds.
+    pub time_origin: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerMessageKind {
+    Alive,
+    Response,
+    ByeBye,
+}
+
+impl PeerMessageKind {
+    const fn as_u8(self) -> u8 {
+        match self {
+            PeerMessageKind::Alive => 1,
+            PeerMessageKind::Response => 2,
+            PeerMessageKind::ByeBye => 3,
+        }
+    }
+
+    const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(PeerMessageKind::Alive),
+            2 => Some(PeerMessageKind::Response),
+            3 => Some(PeerMessageKind::ByeBye),
+            _ => None,
+        }
+    }
+}
+
+/// What a peer tells everyone else about itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerState {
+    pub timeline: Timeline,
+    pub session: NodeId,
+    /// Where the peer answers measurement pings.
+    pub endpoint: Option<SocketAddrV4>,
+}
+
+/// A discovery message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerMessage {
+    pub kind: PeerMessageKind,
+    /// How many seconds the peer should be remembered for.
+    pub ttl: u8,
+    pub node: NodeId,
+    /// Only [None] for [PeerMessageKind::ByeBye].
+    pub state: Option<PeerState>,
+}
+
+impl PeerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = DISCOVERY_HEADER.to_vec();
+        bytes.push(self.kind.as_u8());
+        bytes.push(self.ttl);
+        bytes.extend_from_slice(&0u16.to_be_bytes()); // Group ID.
+        bytes.extend_from_slice(&self.node.0);
+
+        if let Some(state) = &self.state {
+            let timeline = state.timeline;
+            let mut timeline_bytes = Vec::with_capacity(24);
+            timeline_bytes.extend_from_slice(&timeline.micros_per_beat.to_be_bytes());
+            timeline_bytes.extend_from_slice(&timeline.beat_origin.to_be_bytes());
+            timeline_bytes.extend_from_slice(&timeline.time_origin.to_be_bytes());
+            push_entry(&mut bytes, TIMELINE_KEY, &timeline_bytes);
+            push_entry(&mut bytes, SESSION_KEY, &state.session.0);
+
+            // Transport start/stop isn't shared: never playing, at beat 0.
+            push_entry(&mut bytes, START_STOP_KEY, &[0; 17]);
+
+            if let Some(endpoint) = state.endpoint {
+                let mut endpoint_bytes = endpoint.ip().octets().to_vec();
+                endpoint_bytes.extend_from_slice(&endpoint.port().to_be_bytes());
+                push_entry(&mut bytes, ENDPOINT_V4_KEY, &endpoint_bytes);
+            }
+        }
+        bytes
+    }
+
+    /// Read a discovery message, or [None] if `bytes` isn't one.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(DISCOVERY_HEADER)?;
+        let (header, payload) = rest.split_at_checked(12)?;
+        let kind = PeerMessageKind::from_u8(header[0])?;
+        let ttl = header[1];
+        let node = NodeId(header[4..12].try_into().ok()?);
+
+        let mut timeline = None;
+        let mut session = None;
+        let mut endpoint = None;
+        for (key, value) in entries(payload)? {
+            match key {
+                TIMELINE_KEY => {
+                    timeline = Some(Timeline {
+                        micros_per_beat: read_i64(value, 0)?,
+                        beat_origin: read_i64(value, 8)?,
+                        time_origin: read_i64(value, 16)?,
+                    });
+                }
+                SESSION_KEY => session = Some(NodeId(value.get(..8)?.try_into().ok()?)),
+                ENDPOINT_V4_KEY => {
+                    let ip: [u8; 4] = value.get(..4)?.try_into().ok()?;
+                    let port = u16::from_be_bytes(value.get(4..6)?.try_into().ok()?);
+                    endpoint = Some(SocketAddrV4::new(Ipv4Addr::from(ip), port));
+                }
+                _ => {}
+            }
+        }
+
+        let state = match (timeline, session) {
+            (Some(timeline), Some(session)) => Some(PeerState {
+                timeline,
+                session,
+                endpoint,
+            }),
+            _ => None,
+        };
+        if state.is_none() && kind != PeerMessageKind::ByeBye {
+            return None;
+        }
+
+        Some(Self {
+            kind,
+            ttl,
+            node,
+            state,
+        })
+    }
+}
+
+/// A measurement ping, asking a peer for its session's ghost time.
+pub fn encode_ping(host_time: i64, prev_ghost_time: Option<i64>) -> Vec<u8> {
+    let mut bytes = MEASUREMENT_HEADER.to_vec();
+    bytes.push(1);
+    push_entry(&mut bytes, HOST_TIME_KEY, &host_time.to_be_bytes());
+    if let Some(prev_ghost_time) = prev_ghost_time {
+        push_entry(
+            &mut bytes,
+            PREV_GHOST_TIME_KEY,
+            &prev_ghost_time.to_be_bytes(),
+        );
+    }
+    bytes
+}
+
+/// The answer to `ping`: the session and its ghost time now, followed by the
+/// ping's own entries.
+pub fn encode_pong(ping: &[u8], session: NodeId, ghost_time: i64) -> Option<Vec<u8>> {
+    let ping_payload = ping.strip_prefix(MEASUREMENT_HEADER)?.strip_prefix(&[1])?;
+
+    let mut bytes = MEASUREMENT_HEADER.to_vec();
+    bytes.push(2);
+    push_entry(&mut bytes, SESSION_KEY, &session.0);
+    push_entry(&mut bytes, GHOST_TIME_KEY, &ghost_time.to_be_bytes());
+    bytes.extend_from_slice(ping_payload);
+    Some(bytes)
+}
+
+/// Whether `bytes` is a measurement ping.
+pub fn is_ping(bytes: &[u8]) -> bool {
+    bytes
+        .strip_prefix(MEASUREMENT_HEADER)
+        .is_some_and(|rest| rest.first() == Some(&1))
+}
+
+/// A measurement pong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pong {
+    pub session: NodeId,
+    pub ghost_time: i64,
+    pub host_time: i64,
+    pub prev_ghost_time: Option<i64>,
+}
+
+impl Pong {
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let payload = bytes.strip_prefix(MEASUREMENT_HEADER)?.strip_prefix(&[2])?;
+
+        let mut session = None;
+        let mut ghost_time = None;
+        let mut host_time = None;
+        let mut prev_ghost_time = None;
+        for (key, value) in entries(payload)? {
+            match key {
+                SESSION_KEY => session = Some(NodeId(value.get(..8)?.try_into().ok()?)),
+                GHOST_TIME_KEY => ghost_time = Some(read_i64(value, 0)?),
+                HOST_TIME_KEY => host_time = Some(read_i64(value, 0)?),
+                PREV_GHOST_TIME_KEY => prev_ghost_time = Some(read_i64(value, 0)?),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            session: session?,
+            ghost_time: ghost_time?,
+            host_time: host_time?,
+            prev_ghost_time,
+        })
+    }
+}
+
+fn push_entry(bytes: &mut Vec<u8>, key: u32, value: &[u8]) {
+    bytes.extend_from_slice(&key.to_be_bytes());
+    bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(value);
+}
+
+/// The `(key, value)` entries of `payload`, or [None] if it's cut short.
+fn entries(mut payload: &[u8]) -> Option<Vec<(u32, &[u8])>> {
+    let mut entries = Vec::new();
+    while !payload.is_empty() {
+        let key = u32::from_be_bytes(payload.get(..4)?.try_into().ok()?);
+        let size = u32::from_be_bytes(payload.get(4..8)?.try_into().ok()?) as usize;
+        let value = payload.get(8..8 + size)?;
+        entries.push((key, value));
+        payload = &payload[8 + size..];
+    }
+    Some(entries)
+}
+
+fn read_i64(bytes: &[u8], at: usize) -> Option<i64> {
+    Some(i64::from_be_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- PeerMessage::decode() ---
+
+    #[test]
+    fn peer_messages_round_trip() {
+        let message = PeerMessage {
+            kind: PeerMessageKind::Alive,
+            ttl: 5,
+            node: NodeId(*b"peer0001"),
+            state: Some(PeerState {
+                timeline: Timeline {
+                    micros_per_beat: 500_000,
+                    beat_origin: 12_500_000,
+                    time_origin: -42,
+                },
+                session: NodeId(*b"session1"),
+                endpoint: Some(SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 20), 50123)),
+            }),
+        };
+        assert_eq!(PeerMessage::decode(&message.encode()), Some(message));
+
+        let bye = PeerMessage {
+            kind: PeerMessageKind::ByeBye,
+            ttl: 0,
+            node: NodeId(*b"peer0001"),
+            state: None,
+        };
+        assert_eq!(PeerMessage::decode(&bye.encode()), Some(bye));
+
+        // Cut short.
+        let encoded = message.encode();
+        assert_eq!(PeerMessage::decode(&encoded[..encoded.len() - 1]), None);
+    }
+
+    #[test]
+    fn peer_messages_match_link_layout() {
+        let message = PeerMessage {
+            kind: PeerMessageKind::Alive,
+            ttl: 5,
+            node: NodeId(*b"peer0001"),
+            state: Some(PeerState {
+                timeline: Timeline {
+                    micros_per_beat: 500_000,
+                    beat_origin: 1,
+                    time_origin: -2,
+                },
+                session: NodeId(*b"session1"),
+                endpoint: Some(SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 7), 0x1234)),
+            }),
+        };
+
+        let mut expected = b"_asdp_v\x01".to_vec();
+        expected.extend_from_slice(&[1, 5, 0, 0]);
+        expected.extend_from_slice(b"peer0001");
+        expected.extend_from_slice(b"tmln\0\0\0\x18");
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0x07, 0xa1, 0x20]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        expected.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]);
+        expected.extend_from_slice(b"sess\0\0\0\x08session1");
+        expected.extend_from_slice(b"stst\0\0\0\x11");
+        expected.extend_from_slice(&[0; 17]);
+        expected.extend_from_slice(b"mep4\0\0\0\x06");
+        expected.extend_from_slice(&[10, 0, 0, 7, 0x12, 0x34]);
+
+        assert_eq!(message.encode(), expected);
+    }
+
+    // --- Pong::decode() ---
+
+    #[test]
+    fn pongs_echo_the_ping() {
+        let ping = encode_ping(1_000, Some(2_000));
+        assert!(is_ping(&ping));
+
+        let pong = encode_pong(&ping, NodeId(*b"session1"), 5_000).unwrap();
+        assert!(!is_ping(&pong));
+        assert_eq!(
+            Pong::decode(&pong),
+            Some(Pong {
+                session: NodeId(*b"session1"),
+                ghost_time: 5_000,
+                host_time: 1_000,
+                prev_ghost_time: Some(2_000),
+            })
+        );
+    }
+
+    #[test]
+    fn measurements_match_link_layout() {
+        let ping = encode_ping(0x0102, None);
+        let mut expected_ping = b"_link_v\x01\x01".to_vec();
+        expected_ping.extend_from_slice(b"hst_\0\0\0\x08");
+        expected_ping.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0x01, 0x02]);
+        assert_eq!(ping, expected_ping);
+
+        let pong = encode_pong(&ping, NodeId(*b"session1"), 3).unwrap();
+        let mut expected_pong = b"_link_v\x01\x02".to_vec();
+        expected_pong.extend_from_slice(b"sess\0\0\0\x08session1");
+        expected_pong.extend_from_slice(b"__gt\0\0\0\x08");
+        expected_pong.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 3]);
+        expected_pong.extend_from_slice(&expected_ping[9..]);
+        assert_eq!(pong, expected_pong);
+    }
+}
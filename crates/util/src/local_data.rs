@@ -91,6 +91,44 @@ pub fn crash_reports_path() -> &'static Path {
     &PATH
 }
 
+/// The path to the directory where diagnostics reports are saved (see
+/// `util::diagnostics`).
+///
+/// This value will only be computed the first time this function is called.
+/// Once computed, subsequent calls are significantly cheaper.
+///
+/// The directory will be created if it doesn't exist.
+pub fn diagnostics_path() -> &'static Path {
+    static PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+        let path = join_paths(root_path(), DIAGNOSTICS_DIR_NAME);
+        ensure_dirs_exist(&path);
+        path
+    });
+    &PATH
+}
+
+/// The path to the file app-wide settings (those that aren't saved with a
+/// project) are saved in, unique for each user.
+///
+/// This value will only be computed the first time this function is called.
+/// Once computed, subsequent calls are significantly cheaper.
+pub fn settings_file_path() -> &'static Path {
+    static PATH: LazyLock<PathBuf> = LazyLock::new(|| join_paths(root_path(), SETTINGS_FILE_NAME));
+    &PATH
+}
+
+/// The path to the file the user's usage statistics are saved in, unique for
+/// each user. They're kept for the user to look at and never leave the
+/// machine.
+///
+/// This value will only be computed the first time this function is called.
+/// Once computed, subsequent calls are significantly cheaper.
+pub fn usage_stats_file_path() -> &'static Path {
+    static PATH: LazyLock<PathBuf> =
+        LazyLock::new(|| join_paths(root_path(), USAGE_STATS_FILE_NAME));
+    &PATH
+}
+
 /// The path to the directory where cached video information is stored, unique
 /// for each user.
 ///
@@ -107,6 +145,71 @@ pub fn video_cache_path() -> &'static Path {
     &PATH
 }
 
+/// The path to the directory where video stabilization analyses are stored,
+/// unique for each user.
+///
+/// This value will only be computed the first time this function is called.
+/// Once computed, subsequent calls are significantly cheaper.
+///
+/// The directory will be created if it doesn't exist.
+pub fn stabilization_cache_path() -> &'static Path {
+    static PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+        let path = join_paths(root_path(), STABILIZATION_CACHE_NAME);
+        ensure_dirs_exist(&path);
+        path
+    });
+    &PATH
+}
+
+/// The path to the directory where compiled GPU pipelines are cached between
+/// runs, unique for each user.
+///
+/// This value will only be computed the first time this function is called.
+/// Once computed, subsequent calls are significantly cheaper.
+///
+/// The directory will be created if it doesn't exist.
+pub fn pipeline_cache_path() -> &'static Path {
+    static PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+        let path = join_paths(root_path(), PIPELINE_CACHE_NAME);
+        ensure_dirs_exist(&path);
+        path
+    });
+    &PATH
+}
+
+/// The path to the directory where decoded frames are cached while the app is
+/// running, unique for each user. Files in here shouldn't outlive the process
+/// that made them.
+///
+/// This value will only be computed the first time this function is called.
+/// Once computed, subsequent calls are significantly cheaper.
+///
+/// The directory will be created if it doesn't exist.
+pub fn frame_cache_path() -> &'static Path {
+    static PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+        let path = join_paths(root_path(), FRAME_CACHE_NAME);
+        ensure_dirs_exist(&path);
+        path
+    });
+    &PATH
+}
+
+/// The path to the directory the stock nodes embedded in the app are unpacked
+/// to when the nodes folder next to it is missing some, unique for each user.
+///
+/// This value will only be computed the first time this function is called.
+/// Once computed, subsequent calls are significantly cheaper.
+///
+/// The directory will be created if it doesn't exist.
+pub fn embedded_nodes_path() -> &'static Path {
+    static PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+        let path = join_paths(root_path(), EMBEDDED_NODES_DIR_NAME);
+        ensure_dirs_exist(&path);
+        path
+    });
+    &PATH
+}
+
 /// Returns a guard for a shared advisory read-lock on the
 /// [video cache directory](video_cache_path).
 ///
@@ -237,8 +340,15 @@ const ROOT_DIR_NAME: &str = version::APP_NAME;
 const PROJECTS_DIR_NAME: &str = "Projects";
 const NODES_DIR_NAME: &str = "Nodes";
 const CRASH_REPORTS_DIR_NAME: &str = "CrashReports";
+const DIAGNOSTICS_DIR_NAME: &str = "Diagnostics";
 const VIDEO_CACHE_NAME: &str = "VideoCache";
 const VIDEO_CACHE_LOCK_NAME: &str = "VideoCacheLock";
+const STABILIZATION_CACHE_NAME: &str = "StabilizationCache";
+const PIPELINE_CACHE_NAME: &str = "PipelineCache";
+const FRAME_CACHE_NAME: &str = "FrameCache";
+const EMBEDDED_NODES_DIR_NAME: &str = "EmbeddedNodes";
+const SETTINGS_FILE_NAME: &str = "Settings.json";
+const USAGE_STATS_FILE_NAME: &str = "UsageStats.json";
 
 #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
 compile_error!("Unsupported platform.");
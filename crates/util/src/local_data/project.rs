@@ -348,6 +348,7 @@ impl Project {
 
         Ok(OpenProject {
             last_saved_data: data.clone(),
+            last_journaled_data: None,
             data,
             data_file,
             header: Some(self),
@@ -367,6 +368,30 @@ impl Project {
         Ok(())
     }
 
+    /// Write this project to a [project archive](ARCHIVE_EXTENSION) at `path`.
+    /// If the project is open, its last saved data is what's written.
+    pub fn export_archive(&self, path: &Path) -> Result<()> {
+        let data = match fs::read(self.dir_path.join(DATA_FILE_NAME)) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                crate::debug_log_error!("Failed to parse data file for archive: {e}");
+                ProjectError::BadSerializedData
+            })?,
+            // The project has never been opened, so it has no data yet.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => serde_json::Value::Null,
+            Err(e) => {
+                crate::debug_log_error!("Failed to read data file for archive: {e}");
+                return Err(e.into());
+            }
+        };
+
+        let archive = ProjectArchive {
+            info: self.cached_info().clone(),
+            data,
+        };
+        archive.save_to_file(&File::create(path)?)?;
+        Ok(())
+    }
+
     /// Open a new info file, returning the file and a cache that store's its
     /// contents and write timestamp.
     ///
@@ -530,6 +555,8 @@ impl ProjectHeader for Project {
 pub struct OpenProject<T: ProjectData> {
     data: T,
     last_saved_data: T,
+    /// What was last written to the recovery journal, if it's there.
+    last_journaled_data: Option<T>,
     data_file: File,
     header: Option<Project>,
 }
@@ -560,13 +587,106 @@ impl<T: ProjectData> OpenProject<T> {
         })?;
         self.last_saved_data = self.data.clone();
 
+        _ = self.discard_journal().inspect_err(|e| {
+            crate::debug_log_error!(
+                "Failed to remove recovery journal after saving (ignoring): {e}"
+            );
+        });
+
         Ok(true)
     }
 
-    /// Close the project, unlocking the project's non-header data.
+    /// Write the project's unsaved data to its recovery journal, so it can be
+    /// restored if the app exits without saving or closing the project (e.g.
+    /// it crashes). See [OpenProject::recovered_data].
+    ///
+    /// The journal is removed instead when nothing is unsaved, and isn't
+    /// rewritten if nothing changed since the last call. It's written to a
+    /// temporary file first and then moved into place, so a crash while
+    /// writing it leaves the previous journal intact.
+    pub fn write_journal(&mut self) -> Result<()> {
+        if self.data == self.last_saved_data {
+            return self.discard_journal();
+        }
+        if self.last_journaled_data.as_ref() == Some(&self.data) {
+            return Ok(());
+        }
+
+        let temp_path = self.dir_path().join(JOURNAL_TEMP_FILE_NAME);
+        let journal_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp_path)
+            .inspect_err(|e| {
+                crate::debug_log_error!("Failed to create temporary recovery journal: {e}");
+            })?;
+        self.data.save_to_file(&journal_file).inspect_err(|e| {
+            crate::debug_log_error!("Failed to write recovery journal: {e}");
+        })?;
+        journal_file.sync_all().inspect_err(|e| {
+            crate::debug_log_error!("Failed to flush recovery journal: {e}");
+        })?;
+        drop(journal_file);
+        fs::rename(&temp_path, self.journal_path()).inspect_err(|e| {
+            crate::debug_log_error!("Failed to move recovery journal into place: {e}");
+        })?;
+        self.last_journaled_data = Some(self.data.clone());
+
+        Ok(())
+    }
+
+    /// The data in the recovery journal, if it's newer than the last save and
+    /// differs from the saved data.
+    ///
+    /// Only whoever has the project open (locked) writes its journal, and
+    /// saving or closing the project removes it. So when this is called right
+    /// after opening the project, a journal is what's left of a session that
+    /// ended without doing either (e.g. it crashed).
+    pub fn recovered_data(&self) -> Result<Option<T>> {
+        let journal_path = self.journal_path();
+        if !journal_path.exists() {
+            return Ok(None);
+        }
+
+        let journal_file = File::open(&journal_path).inspect_err(|e| {
+            crate::debug_log_error!("Failed to open recovery journal: {e}");
+        })?;
+        let journaled = last_edit_timestamp(&journal_file)?;
+        if let Some(saved) = self.last_edited()?
+            && saved >= journaled
+        {
+            return Ok(None);
+        }
+
+        let data = T::read_from_file(&journal_file)?;
+        Ok((data != self.data).then_some(data))
+    }
+
+    /// Remove the recovery journal, e.g. once its data was restored or the
+    /// user chose not to restore it.
+    pub fn discard_journal(&mut self) -> Result<()> {
+        self.last_journaled_data = None;
+        match fs::remove_file(self.journal_path()) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => {
+                crate::debug_log_error!("Failed to remove recovery journal: {e}");
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Close the project, unlocking the project's non-header data. Unsaved
+    /// changes are dropped along with the recovery journal.
     ///
     /// Can fail if unlocking the info file fails.
     pub fn close(mut self) -> Result<Project> {
+        _ = self.discard_journal().inspect_err(|e| {
+            crate::debug_log_error!("Failed to remove recovery journal on close (ignoring): {e}");
+        });
+
         let header = self.header.take().expect(HEADER_EXPECT_MSG);
         header.info_file.unlock().inspect_err(|e| {
             crate::debug_log_error!("Failed to unlock info file: {e}");
@@ -578,6 +698,10 @@ impl<T: ProjectData> OpenProject<T> {
         self.header.as_ref().expect(HEADER_EXPECT_MSG)
     }
 
+    fn journal_path(&self) -> PathBuf {
+        self.dir_path().join(JOURNAL_FILE_NAME)
+    }
+
     fn header_mut(&mut self) -> &mut Project {
         self.header.as_mut().expect(HEADER_EXPECT_MSG)
     }
@@ -855,13 +979,60 @@ pub fn iter_projects() -> Result<impl Iterator<Item = Result<ProjectId>>>
     Ok(iter)
 }
 
+/// Import the [project archive](ARCHIVE_EXTENSION) at `path`, returning the ID
+/// of the project in it. Nothing is imported if a project with that ID is
+/// already on disk (e.g. the archive was imported before).
+pub fn import_archive(path: &Path) -> Result<ProjectId> {
+    let archive = ProjectArchive::read_from_file(&File::open(path)?).inspect_err(|e| {
+        crate::debug_log_error!("Failed to read project archive: {e}");
+    })?;
+    let project_id = archive.info.id().clone();
+
+    if local_data::projects_path()
+        .join(project_id.as_ref())
+        .exists()
+    {
+        crate::debug_log_info!("Project in archive already exists, not importing.");
+        return Ok(project_id);
+    }
+
+    let project = Project::create(archive.info)?;
+    if !archive.data.is_null() {
+        let data_file_path = project.dir_path().join(DATA_FILE_NAME);
+        if let Err(e) = archive.data.save_to_file(&File::create(data_file_path)?) {
+            crate::debug_log_error!("Failed to write imported project data: {e}");
+            _ = project.delete().inspect_err(|e| {
+                crate::debug_log_error!("Imported project cleanup failed (ignoring): {e}");
+            });
+            return Err(e.into());
+        }
+    }
+
+    Ok(project_id)
+}
+
+/// The file extension of project archives, which hold a whole project in one
+/// file so it can be shared (see [Project::export_archive] and
+/// [import_archive]).
+pub const ARCHIVE_EXTENSION: &str = "bvproj";
+
 const INFO_FILE_NAME: &str = "info.json";
 const DATA_FILE_NAME: &str = "data.json";
+const JOURNAL_FILE_NAME: &str = "recovery.json";
+const JOURNAL_TEMP_FILE_NAME: &str = "recovery.json.tmp";
 
 const HEADER_EXPECT_MSG: &str = "The header should be present.";
 
 type ProjectInfoCache = (ProjectInfo, SystemTime);
 
+/// The contents of a [project archive](ARCHIVE_EXTENSION).
+#[derive(Serialize, Deserialize)]
+struct ProjectArchive {
+    info: ProjectInfo,
+    /// The project's data file, or null if it doesn't have one yet.
+    data: serde_json::Value,
+}
+
 fn read_from_file_with_time<T: SavedFile>(file: &File) -> Result<(T, SystemTime)> {
     let data = T::read_from_file(file)
         .inspect_err(|e| crate::debug_log_error!("Failed to read from saved file: {e}"))?;
@@ -939,3 +1110,35 @@ fn format_datetime(datetime: OffsetDateTime) -> String {
         ))
         .expect("The date shouldn't fail to format.")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- OpenProject::write_journal() ---
+
+    #[test]
+    fn journal_round_trip_survives_interrupted_write() {
+        let project = ProjectInfo::new("journal test".to_string())
+            .create_project()
+            .unwrap();
+        let mut open = project.open::<Vec<u32>>().unwrap();
+        assert_eq!(open.recovered_data().unwrap(), None);
+
+        // Let the journal be newer than the data file.
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        open.data_mut().push(1);
+        open.write_journal().unwrap();
+
+        // A crash while writing the next journal leaves a partial temporary
+        // file, which doesn't affect the last complete journal.
+        fs::write(open.dir_path().join(JOURNAL_TEMP_FILE_NAME), "[1, 2").unwrap();
+        open.data_mut().clear();
+        assert_eq!(open.recovered_data().unwrap(), Some(vec![1]));
+
+        open.discard_journal().unwrap();
+        assert_eq!(open.recovered_data().unwrap(), None);
+
+        open.close().unwrap().delete().unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+//! This module contains [catch], a wrapper for the work a thread does that
+//! turns a panic into a [WorkerPanic] report instead of letting it surface
+//! somewhere else as an unrelated `expect` failure (e.g. a channel to the
+//! thread being dropped).
+//!
+//! Reports are logged and passed to every [subscriber](subscribe), so they can
+//! be forwarded to the UI.
+//!
+//! Caught panics skip the panic hook that was set before this module's was
+//! installed (the first time [catch] is called), so e.g. a crash report isn't
+//! written for a panic that was recovered from.
+
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+
+/// A report of a panic caught by [catch].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerPanic {
+    /// The name of the subsystem that panicked (passed to [catch]).
+    pub subsystem: String,
+    /// The panic message, with where it panicked if that's known.
+    pub message: String,
+    /// A backtrace from where it panicked.
+    pub backtrace: String,
+    /// The name of the thread that panicked.
+    pub thread_name: Option<String>,
+}
+
+/// Run `f`, catching it if it panics. The panic is reported (see [subscribe])
+/// and returned as a [WorkerPanic].
+///
+/// `f` is treated as unwind safe. Callers are expected to throw away (or
+/// rebuild) anything `f` was working on when it panicked.
+pub fn catch<F, T>(subsystem: impl Into<String>, f: F) -> Result<T, WorkerPanic>
+where
+    F: FnOnce() -> T,
+{
+    install_hook();
+
+    CATCHING.set(CATCHING.get() + 1);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.set(CATCHING.get() - 1);
+
+    result.map_err(|payload| {
+        let (location, backtrace) = LAST_PANIC.take().unwrap_or_default();
+        let mut message = payload_message(payload.as_ref());
+        if let Some(location) = location {
+            message = format!("{message} (at {location})");
+        }
+
+        let worker_panic = WorkerPanic {
+            subsystem: subsystem.into(),
+            message,
+            backtrace,
+            thread_name: thread::current().name().map(String::from),
+        };
+        report(&worker_panic);
+        worker_panic
+    })
+}
+
+/// Call `on_panic` with every [WorkerPanic] caught by [catch] (on any thread)
+/// until the returned [PanicSubscription] is dropped.
+pub fn subscribe<F>(on_panic: F) -> PanicSubscription
+where
+    F: Fn(&WorkerPanic) + Send + Sync + 'static,
+{
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+    SUBSCRIBERS
+        .lock()
+        .expect(LOCK_NOT_POISONED)
+        .push((id, Arc::new(on_panic)));
+
+    PanicSubscription { id }
+}
+
+/// Keeps a [subscriber](subscribe) subscribed. It's unsubscribed when this is
+/// dropped.
+#[derive(Debug)]
+pub struct PanicSubscription {
+    id: u64,
+}
+
+impl Drop for PanicSubscription {
+    fn drop(&mut self) {
+        SUBSCRIBERS
+            .lock()
+            .expect(LOCK_NOT_POISONED)
+            .retain(|(id, _)| *id != self.id);
+    }
+}
+
+type Subscriber = Arc<dyn Fn(&WorkerPanic) + Send + Sync>;
+
+static SUBSCRIBERS: Mutex<Vec<(u64, Subscriber)>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// How many [catch] calls are running on this thread.
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+
+    /// Where the last caught panic on this thread happened, and its backtrace.
+    static LAST_PANIC: RefCell<Option<(Option<String>, String)>> =
+        const { RefCell::new(None) };
+}
+
+const LOCK_NOT_POISONED: &str = "The lock isn't poisoned.";
+
+/// Install a panic hook that records where caught panics happened (the
+/// payload doesn't say) and passes every other panic to the previous hook.
+fn install_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCHING.get() == 0 {
+                previous_hook(info);
+                return;
+            }
+
+            let location = info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+            let backtrace = Backtrace::force_capture().to_string();
+            LAST_PANIC.set(Some((location, backtrace)));
+        }));
+    });
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        (*msg).to_owned()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown".to_owned()
+    }
+}
+
+fn report(worker_panic: &WorkerPanic) {
+    crate::debug_log_warning!(
+        "`{}` (thread `{}`) panicked: {}\nBacktrace:\n{}",
+        worker_panic.subsystem,
+        worker_panic.thread_name.as_deref().unwrap_or("anonymous"),
+        worker_panic.message,
+        worker_panic.backtrace,
+    );
+
+    // Cloned so subscribers can (un)subscribe without deadlocking.
+    let subscribers: Vec<Subscriber> = SUBSCRIBERS
+        .lock()
+        .expect(LOCK_NOT_POISONED)
+        .iter()
+        .map(|(_, subscriber)| subscriber.clone())
+        .collect();
+    for subscriber in subscribers {
+        subscriber(worker_panic);
+    }
+}
+
+#[cfg(test)]
+mod decision_coverage_tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    // --- catch ---
+
+    #[test]
+    fn catch_returns_value_when_no_panic() {
+        assert_eq!(catch("no-panic", || 5), Ok(5));
+    }
+
+    #[test]
+    fn catch_reports_str_and_string_payloads() {
+        let err = catch("str-payload", || panic!("boom")).unwrap_err();
+        assert_eq!(err.subsystem, "str-payload");
+        assert!(err.message.starts_with("boom (at "));
+        assert!(err.message.contains("panic_capture.rs"));
+
+        let err = catch("string-payload", || panic!("{}", String::from("bang"))).unwrap_err();
+        assert!(err.message.starts_with("bang"));
+
+        let err = catch("other-payload", || panic::panic_any(7_u8)).unwrap_err();
+        assert!(err.message.starts_with("unknown"));
+    }
+
+    #[test]
+    fn catch_records_thread_name() {
+        let err = thread::Builder::new()
+            .name("panicking-worker".into())
+            .spawn(|| catch("thread-name", || panic!("boom")).unwrap_err())
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(err.thread_name.as_deref(), Some("panicking-worker"));
+    }
+
+    // --- subscribe ---
+
+    #[test]
+    fn subscribers_see_panics_until_dropped() {
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let subscription = subscribe(move |worker_panic| {
+            if worker_panic.subsystem == "subscribed" {
+                _ = tx.lock().unwrap().send(worker_panic.message.clone());
+            }
+        });
+
+        _ = catch("subscribed", || panic!("first"));
+        assert!(rx.try_recv().unwrap().starts_with("first"));
+
+        drop(subscription);
+        _ = catch("subscribed", || panic!("second"));
+        assert!(rx.try_recv().is_err());
+    }
+}
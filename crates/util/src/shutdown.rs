@@ -0,0 +1,454 @@
+//! This module contains [ShutdownCoordinator], which shuts subsystems down in a
+//! deterministic order instead of relying on channel drops and [Drop] impls
+//! joining threads in whatever order they happen to run.
+//!
+//! Subsystems are registered with a shutdown hook and a timeout. Ordering
+//! constraints (e.g. "the engine shuts down before media") are declared with
+//! [ShutdownCoordinator::order]. Each hook runs on its own thread so that a
+//! hook that hangs can't block the rest of the sequence. Hooks that have to
+//! wait for one that hung are skipped, since whatever they'd clean up may
+//! still be in use.
+
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs subsystem shutdown hooks in dependency order, each with a timeout. See
+/// the [module docs](self).
+#[derive(Default)]
+pub struct ShutdownCoordinator {
+    subsystems: Vec<Subsystem>,
+    orderings: Vec<(&'static str, &'static str)>,
+}
+
+impl ShutdownCoordinator {
+    /// Create a coordinator with no registered subsystems.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a subsystem with a shutdown `hook` that's given `timeout` to
+    /// finish. Registering a name twice replaces the previous hook.
+    ///
+    /// Subsystems without ordering constraints shut down in the order they
+    /// were registered.
+    pub fn register<F>(&mut self, name: &'static str, timeout: Duration, hook: F) -> &mut Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let subsystem = Subsystem {
+            name,
+            timeout,
+            hook: Box::new(hook),
+        };
+
+        match self.subsystems.iter_mut().find(|s| s.name == name) {
+            Some(existing) => {
+                crate::debug_log_warning!("Shutdown hook for `{name}` registered twice.");
+                *existing = subsystem;
+            }
+            None => self.subsystems.push(subsystem),
+        }
+
+        self
+    }
+
+    /// Require that subsystem `first` finishes shutting down before subsystem
+    /// `then` starts. If `first` times out (or is skipped itself), `then` is
+    /// [skipped](ShutdownOutcome::Skipped). Constraints naming unregistered
+    /// subsystems are ignored.
+    pub fn order(&mut self, first: &'static str, then: &'static str) -> &mut Self {
+        self.orderings.push((first, then));
+        self
+    }
+
+    /// Whether a subsystem with the name `name` has been registered.
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.subsystems.iter().any(|s| s.name == name)
+    }
+
+    /// The order subsystems will be shut down in.
+    ///
+    /// If the ordering constraints contain a cycle, the subsystems involved are
+    /// shut down in registration order after everything else.
+    pub fn shutdown_order(&self) -> Vec<&'static str> {
+        self.sorted_indices()
+            .into_iter()
+            .map(|i| self.subsystems[i].name)
+            .collect()
+    }
+
+    /// Run every shutdown hook in order, waiting up to each subsystem's timeout
+    /// before moving on to the next one.
+    pub fn shutdown(self) -> ShutdownReport {
+        let order = self.sorted_indices();
+        let prerequisites = self.prerequisites();
+        let mut subsystems: Vec<Option<Subsystem>> =
+            self.subsystems.into_iter().map(Some).collect();
+        let mut unfinished = vec![false; subsystems.len()];
+
+        let outcomes = order
+            .into_iter()
+            .map(|i| {
+                let subsystem = subsystems[i].take().expect("Each index is visited once.");
+                let name = subsystem.name;
+                let outcome = if prerequisites[i].iter().any(|&first| unfinished[first]) {
+                    subsystem.skip()
+                } else {
+                    subsystem.run()
+                };
+                unfinished[i] = matches!(
+                    outcome,
+                    ShutdownOutcome::TimedOut | ShutdownOutcome::Skipped
+                );
+
+                match outcome {
+                    ShutdownOutcome::Completed(elapsed) => {
+                        crate::debug_log_info!("Shut down `{name}` in {elapsed:?}.");
+                    }
+                    ShutdownOutcome::TimedOut => {
+                        crate::debug_log_warning!("Shutting down `{name}` timed out (skipping).");
+                    }
+                    ShutdownOutcome::Panicked => {
+                        crate::debug_log_warning!("Shutting down `{name}` panicked (skipping).");
+                    }
+                    ShutdownOutcome::Skipped => {
+                        crate::debug_log_warning!(
+                            "Not shutting down `{name}` (something it waits for didn't finish)."
+                        );
+                    }
+                }
+
+                (name, outcome)
+            })
+            .collect();
+
+        ShutdownReport { outcomes }
+    }
+
+    /// The indices of the subsystems each subsystem has to wait for.
+    fn prerequisites(&self) -> Vec<Vec<usize>> {
+        let index_of = |name: &str| self.subsystems.iter().position(|s| s.name == name);
+
+        let mut prerequisites = vec![Vec::new(); self.subsystems.len()];
+        for &(first, then) in &self.orderings {
+            if let (Some(first), Some(then)) = (index_of(first), index_of(then))
+                && first != then
+            {
+                prerequisites[then].push(first);
+            }
+        }
+        prerequisites
+    }
+
+    /// Kahn's algorithm, always picking the lowest registration index that's
+    /// ready so the result is deterministic.
+    fn sorted_indices(&self) -> Vec<usize> {
+        let index_of: HashMap<&str, usize> = self
+            .subsystems
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.name, i))
+            .collect();
+
+        let len = self.subsystems.len();
+        let mut blocked_by = vec![0usize; len];
+        let mut unblocks: Vec<Vec<usize>> = vec![Vec::new(); len];
+
+        for &(first, then) in &self.orderings {
+            match (index_of.get(first), index_of.get(then)) {
+                (Some(&first), Some(&then)) if first != then => {
+                    blocked_by[then] += 1;
+                    unblocks[first].push(then);
+                }
+                _ => crate::debug_log_warning!(
+                    "Ignoring shutdown ordering `{first}` -> `{then}` (not registered)."
+                ),
+            }
+        }
+
+        let mut done = vec![false; len];
+        let mut order = Vec::with_capacity(len);
+
+        while let Some(next) = (0..len).find(|&i| !done[i] && blocked_by[i] == 0) {
+            done[next] = true;
+            order.push(next);
+            for &i in &unblocks[next] {
+                blocked_by[i] -= 1;
+            }
+        }
+
+        if order.len() != len {
+            crate::debug_log_warning!("Shutdown orderings contain a cycle (ignoring them).");
+            order.extend((0..len).filter(|&i| !done[i]));
+        }
+
+        order
+    }
+}
+
+/// What happened when a subsystem's shutdown hook was run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownOutcome {
+    /// The hook returned within its timeout.
+    Completed(Duration),
+    /// The hook didn't return within its timeout. Its thread is left running.
+    TimedOut,
+    /// The hook panicked.
+    Panicked,
+    /// The hook wasn't run because a subsystem it's ordered after timed out
+    /// (or was skipped). The hook is leaked instead of dropped, so anything it
+    /// owns (e.g. open files) is left as it is.
+    Skipped,
+}
+
+/// The result of [ShutdownCoordinator::shutdown].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    outcomes: Vec<(&'static str, ShutdownOutcome)>,
+}
+
+impl ShutdownReport {
+    /// Every subsystem paired with its outcome, in the order they were shut
+    /// down.
+    pub fn outcomes(&self) -> &[(&'static str, ShutdownOutcome)] {
+        &self.outcomes
+    }
+
+    /// The outcome for the subsystem named `name`.
+    pub fn outcome(&self, name: &str) -> Option<ShutdownOutcome> {
+        self.outcomes
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, outcome)| *outcome)
+    }
+
+    /// Whether every hook completed within its timeout.
+    pub fn all_completed(&self) -> bool {
+        self.outcomes
+            .iter()
+            .all(|(_, outcome)| matches!(outcome, ShutdownOutcome::Completed(_)))
+    }
+}
+
+struct Subsystem {
+    name: &'static str,
+    timeout: Duration,
+    hook: Box<dyn FnOnce() + Send>,
+}
+
+impl Subsystem {
+    fn skip(self) -> ShutdownOutcome {
+        std::mem::forget(self.hook);
+        ShutdownOutcome::Skipped
+    }
+
+    fn run(self) -> ShutdownOutcome {
+        let (tx, rx) = mpsc::channel();
+        let hook = self.hook;
+        let start = Instant::now();
+
+        let spawn_result = thread::Builder::new()
+            .name(format!("shutdown-{}", self.name))
+            .spawn(move || {
+                hook();
+                _ = tx.send(());
+            });
+
+        if let Err(e) = spawn_result {
+            crate::debug_log_warning!("Failed to spawn shutdown thread for `{}`: {e}", self.name);
+            return ShutdownOutcome::Panicked;
+        }
+
+        match rx.recv_timeout(self.timeout) {
+            Ok(()) => ShutdownOutcome::Completed(start.elapsed()),
+            Err(mpsc::RecvTimeoutError::Timeout) => ShutdownOutcome::TimedOut,
+            // The sender was dropped without sending, so the hook panicked.
+            Err(mpsc::RecvTimeoutError::Disconnected) => ShutdownOutcome::Panicked,
+        }
+    }
+}
+
+#[cfg(test)]
+mod decision_coverage_tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    const LONG: Duration = Duration::from_secs(10);
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    /// Returns a hook that pushes `name` onto `log` when run.
+    fn push_to(log: &Log, name: &'static str) -> impl FnOnce() + Send + 'static {
+        let log = log.clone();
+        move || log.lock().unwrap().push(name)
+    }
+
+    // --- ShutdownCoordinator::register ---
+
+    #[test]
+    fn register_duplicate_replaces_hook() {
+        let log = Log::default();
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator.register("a", LONG, push_to(&log, "first"));
+        coordinator.register("a", LONG, push_to(&log, "second"));
+        assert_eq!(coordinator.shutdown_order(), ["a"]);
+
+        coordinator.shutdown();
+        assert_eq!(*log.lock().unwrap(), ["second"]);
+    }
+
+    #[test]
+    fn is_registered_reports_registration() {
+        let mut coordinator = ShutdownCoordinator::new();
+        assert!(!coordinator.is_registered("a"));
+        coordinator.register("a", LONG, || {});
+        assert!(coordinator.is_registered("a"));
+    }
+
+    // --- ShutdownCoordinator::shutdown_order ---
+
+    #[test]
+    fn shutdown_order_defaults_to_registration_order() {
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator
+            .register("a", LONG, || {})
+            .register("b", LONG, || {})
+            .register("c", LONG, || {});
+        assert_eq!(coordinator.shutdown_order(), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn shutdown_order_respects_orderings() {
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator
+            .register("project", LONG, || {})
+            .register("media", LONG, || {})
+            .register("engine", LONG, || {})
+            .order("engine", "media")
+            .order("media", "project");
+        assert_eq!(coordinator.shutdown_order(), ["engine", "media", "project"]);
+    }
+
+    #[test]
+    fn shutdown_order_ignores_unknown_and_self_orderings() {
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator
+            .register("a", LONG, || {})
+            .register("b", LONG, || {})
+            .order("missing", "a")
+            .order("b", "b");
+        assert_eq!(coordinator.shutdown_order(), ["a", "b"]);
+    }
+
+    #[test]
+    fn shutdown_order_with_cycle_still_includes_everything() {
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator
+            .register("a", LONG, || {})
+            .register("b", LONG, || {})
+            .register("c", LONG, || {})
+            .order("a", "b")
+            .order("b", "a");
+        assert_eq!(coordinator.shutdown_order(), ["c", "a", "b"]);
+    }
+
+    // --- ShutdownCoordinator::shutdown ---
+
+    #[test]
+    fn shutdown_runs_hooks_in_order() {
+        let log = Log::default();
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator
+            .register("b", LONG, push_to(&log, "b"))
+            .register("a", LONG, push_to(&log, "a"))
+            .order("a", "b");
+
+        let report = coordinator.shutdown();
+        assert_eq!(*log.lock().unwrap(), ["a", "b"]);
+        assert!(report.all_completed());
+        assert_eq!(report.outcomes().len(), 2);
+        assert_eq!(report.outcomes()[0].0, "a");
+    }
+
+    #[test]
+    fn shutdown_reports_timeout_and_continues() {
+        let log = Log::default();
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator
+            .register("slow", Duration::from_millis(10), || {
+                thread::sleep(Duration::from_millis(500))
+            })
+            .register("after", LONG, push_to(&log, "after"));
+
+        let report = coordinator.shutdown();
+        assert_eq!(report.outcome("slow"), Some(ShutdownOutcome::TimedOut));
+        assert!(matches!(
+            report.outcome("after"),
+            Some(ShutdownOutcome::Completed(_))
+        ));
+        assert!(!report.all_completed());
+        assert_eq!(*log.lock().unwrap(), ["after"]);
+    }
+
+    #[test]
+    fn shutdown_reports_panic_and_continues() {
+        let log = Log::default();
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator
+            .register("bad", LONG, || panic!("hook panicked"))
+            .register("after", LONG, push_to(&log, "after"));
+
+        let report = coordinator.shutdown();
+        assert_eq!(report.outcome("bad"), Some(ShutdownOutcome::Panicked));
+        assert_eq!(*log.lock().unwrap(), ["after"]);
+    }
+
+    #[test]
+    fn shutdown_skips_hooks_ordered_after_a_timeout() {
+        let log = Log::default();
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator
+            .register("project", LONG, push_to(&log, "project"))
+            .register("engine", Duration::from_millis(10), || {
+                thread::sleep(Duration::from_millis(500))
+            })
+            .register("after_project", LONG, push_to(&log, "after_project"))
+            .register("unrelated", LONG, push_to(&log, "unrelated"))
+            .order("engine", "project")
+            .order("project", "after_project");
+
+        let report = coordinator.shutdown();
+        assert_eq!(report.outcome("engine"), Some(ShutdownOutcome::TimedOut));
+        assert_eq!(report.outcome("project"), Some(ShutdownOutcome::Skipped));
+        assert_eq!(
+            report.outcome("after_project"),
+            Some(ShutdownOutcome::Skipped)
+        );
+        assert_eq!(*log.lock().unwrap(), ["unrelated"]);
+    }
+
+    #[test]
+    fn shutdown_runs_hooks_ordered_after_a_panic() {
+        let log = Log::default();
+        let mut coordinator = ShutdownCoordinator::new();
+        coordinator
+            .register("bad", LONG, || panic!("hook panicked"))
+            .register("after", LONG, push_to(&log, "after"))
+            .order("bad", "after");
+
+        let report = coordinator.shutdown();
+        assert_eq!(report.outcome("bad"), Some(ShutdownOutcome::Panicked));
+        assert_eq!(*log.lock().unwrap(), ["after"]);
+    }
+
+    // --- ShutdownReport::outcome ---
+
+    #[test]
+    fn outcome_missing_subsystem_is_none() {
+        let report = ShutdownCoordinator::new().shutdown();
+        assert_eq!(report.outcome("a"), None);
+        assert!(report.all_completed());
+    }
+}
@@ -0,0 +1,657 @@
+//! Timecode formatting and parsing.
+//!
+//! Supports SMPTE-style `HH:MM:SS:FF` timecodes (including drop-frame
+//! `HH:MM:SS;FF` timecodes for 29.97 and 59.94 FPS), `HH:MM:SS.mmm` clock
+//! times, and converting between frame indices and times at any (including
+//! fractional) [FrameRate].
+
+use std::fmt::{self, Display};
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// A frame rate stored as a fraction of frames per second (e.g. `30000/1001`
+/// for 29.97 FPS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRate {
+    num: NonZeroU32,
+    den: NonZeroU32,
+}
+
+impl FrameRate {
+    /// Create a new [FrameRate] of `num / den` frames per second. Returns
+    /// [None] if either is zero.
+    pub const fn new(num: u32, den: u32) -> Option<Self> {
+        match (NonZeroU32::new(num), NonZeroU32::new(den)) {
+            (Some(num), Some(den)) => Some(Self { num, den }),
+            _ => None,
+        }
+    }
+
+    /// The numerator of the frame rate fraction.
+    pub const fn num(&self) -> u32 {
+        self.num.get()
+    }
+
+    /// The denominator of the frame rate fraction.
+    pub const fn den(&self) -> u32 {
+        self.den.get()
+    }
+
+    /// The frame rate as a floating point number.
+    pub const fn as_float(&self) -> f64 {
+        self.num.get() as f64 / self.den.get() as f64
+    }
+
+    /// The whole number of frames counted per timecode second (e.g. 30 for
+    /// 29.97 FPS). Never zero.
+    pub const fn nominal(&self) -> u64 {
+        let num = self.num.get() as u64;
+        let den = self.den.get() as u64;
+        let rounded = (num + den / 2) / den;
+        if rounded == 0 { 1 } else { rounded }
+    }
+
+    /// Whether drop-frame timecodes can be used at this frame rate (29.97 or
+    /// 59.94 FPS, or any other `N*1000/1001` rate where `N` is a multiple of
+    /// 30).
+    pub const fn supports_drop_frame(&self) -> bool {
+        let num = self.num.get() as u64;
+        let den = self.den.get() as u64;
+        // `num/den == nominal * 1000/1001`, without assuming the fraction is
+        // simplified.
+        let nominal = self.nominal();
+        nominal.is_multiple_of(30) && num * 1001 == nominal * 1000 * den
+    }
+
+    /// The number of frame numbers skipped at the start of each minute (other
+    /// than every tenth minute) in drop-frame timecodes.
+    const fn dropped_per_minute(&self) -> u64 {
+        self.nominal() / 15
+    }
+}
+
+/// Convert a frame index to the time that frame starts at, rounded up to the
+/// nanosecond (so [time_to_frame] maps it back to the same frame).
+pub fn frame_to_time(frame: u64, rate: FrameRate) -> Duration {
+    let num = frame as u128 * rate.den() as u128 * NANOS_PER_SEC;
+    let den = rate.num() as u128;
+    duration_from_nanos(num.div_ceil(den))
+}
+
+/// Convert a time to the index of the frame showing at that time (i.e. the
+/// last frame that starts at or before `time`).
+pub fn time_to_frame(time: Duration, rate: FrameRate) -> u64 {
+    let frame = time.as_nanos() * rate.num() as u128 / (rate.den() as u128 * NANOS_PER_SEC);
+    frame.min(u64::MAX as u128) as u64
+}
+
+/// Convert a time to the index of the frame that starts closest to `time`. Use
+/// this over [time_to_frame] for times that have been rounded (e.g. to the
+/// millisecond) so that they map back to the frame they were created from.
+pub fn time_to_nearest_frame(time: Duration, rate: FrameRate) -> u64 {
+    let num = time.as_nanos() * rate.num() as u128;
+    let den = rate.den() as u128 * NANOS_PER_SEC;
+    ((num + den / 2) / den).min(u64::MAX as u128) as u64
+}
+
+/// Format `time` as `HH:MM:SS.mmm`, rounded to the nearest millisecond.
+/// Hours aren't wrapped, so they can take more than 2 digits.
+pub fn format_clock(time: Duration) -> String {
+    let millis = (time.as_nanos() + 500_000) / 1_000_000;
+    let total_seconds = millis / 1000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        total_seconds / 3600,
+        (total_seconds / 60) % 60,
+        total_seconds % 60,
+        millis % 1000
+    )
+}
+
+/// Format the start of `frame` as `HH:MM:SS.mmm` at `rate`. See
+/// [format_clock].
+pub fn format_frame_clock(frame: u64, rate: FrameRate) -> String {
+    format_clock(frame_to_time(frame, rate))
+}
+
+/// An SMPTE-style timecode.
+///
+/// Non-drop-frame timecodes count [FrameRate::nominal] frames per second, so
+/// at fractional frame rates they drift from the actual (wall clock) time.
+/// Drop-frame timecodes correct for this at 29.97 and 59.94 FPS by skipping
+/// frame numbers 0 and 1 (0-3 at 59.94) at the start of every minute except
+/// every tenth minute. No actual frames are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timecode {
+    pub hours: u64,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u64,
+    pub drop_frame: bool,
+}
+
+impl Timecode {
+    /// The timecode of `frame` at `rate`.
+    ///
+    /// If `drop_frame` is requested but `rate` doesn't
+    /// [support it](FrameRate::supports_drop_frame) a non-drop-frame timecode
+    /// is returned instead.
+    pub fn from_frame(frame: u64, rate: FrameRate, drop_frame: bool) -> Self {
+        let drop_frame = drop_frame && rate.supports_drop_frame();
+        let nominal = rate.nominal();
+
+        let mut frame_number = frame;
+        if drop_frame {
+            let dropped = rate.dropped_per_minute();
+            let frames_per_minute = nominal * 60 - dropped;
+            let frames_per_ten_minutes = nominal * 600 - dropped * 9;
+
+            let tens = frame / frames_per_ten_minutes;
+            let remainder = frame % frames_per_ten_minutes;
+            frame_number += dropped * 9 * tens;
+            if remainder > dropped {
+                frame_number += dropped * ((remainder - dropped) / frames_per_minute);
+            }
+        }
+
+        let total_seconds = frame_number / nominal;
+        Self {
+            hours: total_seconds / 3600,
+            minutes: ((total_seconds / 60) % 60) as u8,
+            seconds: (total_seconds % 60) as u8,
+            frames: frame_number % nominal,
+            drop_frame,
+        }
+    }
+
+    /// The frame index this timecode refers to at `rate`.
+    ///
+    /// Returns an error if a field is out of range for `rate`, if this is a
+    /// drop-frame timecode and `rate` doesn't support it, or if it names a
+    /// skipped drop-frame frame number.
+    pub fn to_frame(&self, rate: FrameRate) -> Result<u64, TimecodeError> {
+        let nominal = rate.nominal();
+
+        check_range("minutes", self.minutes as u64, 59)?;
+        check_range("seconds", self.seconds as u64, 59)?;
+        check_range("frames", self.frames, nominal - 1)?;
+
+        let total_minutes = self
+            .hours
+            .checked_mul(60)
+            .and_then(|minutes| minutes.checked_add(self.minutes as u64))
+            .ok_or(TimecodeError::TooLarge)?;
+        let frame_number = total_minutes
+            .checked_mul(60)
+            .and_then(|seconds| seconds.checked_add(self.seconds as u64))
+            .and_then(|seconds| seconds.checked_mul(nominal))
+            .and_then(|frames| frames.checked_add(self.frames))
+            .ok_or(TimecodeError::TooLarge)?;
+
+        if !self.drop_frame {
+            return Ok(frame_number);
+        }
+
+        if !rate.supports_drop_frame() {
+            return Err(TimecodeError::DropFrameUnsupported);
+        }
+
+        let dropped = rate.dropped_per_minute();
+        if self.seconds == 0 && !self.minutes.is_multiple_of(10) && self.frames < dropped {
+            return Err(TimecodeError::DroppedFrameNumber);
+        }
+
+        Ok(frame_number - dropped * (total_minutes - total_minutes / 10))
+    }
+}
+
+impl Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.drop_frame { ';' } else { ':' };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{separator}{:02}",
+            self.hours, self.minutes, self.seconds, self.frames
+        )
+    }
+}
+
+/// Parse a user-entered position into a frame index at `rate`.
+///
+/// Accepted formats:
+/// - `HH:MM:SS:FF` (SMPTE timecode) or `HH:MM:SS;FF` (drop-frame timecode).
+/// - `HH:MM:SS.mmm`, `MM:SS.mmm` or `SS.mmm` (clock time, the fraction is
+///   optional when there's at least one `:`). Times are rounded to the
+///   nearest frame.
+/// - A bare whole number, which is taken as a frame index.
+///
+/// Surrounding whitespace is ignored.
+pub fn parse(s: &str, rate: FrameRate) -> Result<u64, TimecodeError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(TimecodeError::Empty);
+    }
+
+    if let Some((time, frames)) = s.rsplit_once(';') {
+        return parse_timecode(time, frames, true, rate);
+    }
+
+    let fields: Vec<&str> = s.split(':').collect();
+    match fields.len() {
+        1 if !s.contains('.') => parse_number(s),
+        1..=3 => parse_clock(&fields, rate),
+        4 => parse_timecode(&s[..s.rfind(':').unwrap()], fields[3], false, rate),
+        n => Err(TimecodeError::FieldCount(n)),
+    }
+}
+
+fn parse_timecode(
+    time: &str,
+    frames: &str,
+    drop_frame: bool,
+    rate: FrameRate,
+) -> Result<u64, TimecodeError> {
+    let fields: Vec<&str> = time.split(':').collect();
+    let [hours, minutes, seconds] = fields[..] else {
+        return Err(TimecodeError::FieldCount(fields.len() + 1));
+    };
+
+    Timecode {
+        hours: parse_number(hours)?,
+        minutes: parse_field("minutes", minutes, 59)?,
+        seconds: parse_field("seconds", seconds, 59)?,
+        frames: parse_number(frames)?,
+        drop_frame,
+    }
+    .to_frame(rate)
+}
+
+fn parse_clock(fields: &[&str], rate: FrameRate) -> Result<u64, TimecodeError> {
+    let (last, leading) = fields.split_last().ok_or(TimecodeError::Empty)?;
+    let (whole_seconds, fraction) = match last.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (*last, None),
+    };
+
+    let seconds = parse_number(whole_seconds)?;
+    if !leading.is_empty() {
+        check_range("seconds", seconds, 59)?;
+    }
+    let leading_seconds = match leading {
+        [] => Some(0),
+        [minutes] => parse_number(minutes)?.checked_mul(60),
+        [hours, minutes] => {
+            let minutes = parse_field("minutes", minutes, 59)? as u64 * 60;
+            parse_number(hours)?
+                .checked_mul(3600)
+                .and_then(|hours| hours.checked_add(minutes))
+        }
+        _ => return Err(TimecodeError::FieldCount(fields.len())),
+    };
+    let seconds = leading_seconds
+        .and_then(|leading| leading.checked_add(seconds))
+        .ok_or(TimecodeError::TooLarge)?;
+
+    let nanos = match fraction {
+        Some(fraction) => parse_fraction_nanos(fraction)?,
+        None => 0,
+    };
+
+    Ok(time_to_nearest_frame(Duration::new(seconds, nanos), rate))
+}
+
+/// Parse the digits after a decimal point into nanoseconds. Digits past
+/// nanosecond precision are ignored.
+fn parse_fraction_nanos(fraction: &str) -> Result<u32, TimecodeError> {
+    if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(TimecodeError::InvalidNumber(fraction.to_string()));
+    }
+
+    let digits = &fraction[..fraction.len().min(9)];
+    let nanos: u32 = digits.parse().unwrap_or(0);
+    Ok(nanos * 10u32.pow(9 - digits.len() as u32))
+}
+
+fn parse_number(s: &str) -> Result<u64, TimecodeError> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(TimecodeError::InvalidNumber(s.to_string()));
+    }
+    // Only digits are left, so the only way parsing can fail is overflow.
+    s.parse().map_err(|_| TimecodeError::TooLarge)
+}
+
+fn parse_field(field: &'static str, s: &str, max: u64) -> Result<u8, TimecodeError> {
+    let value = parse_number(s)?;
+    check_range(field, value, max)?;
+    Ok(value as u8)
+}
+
+fn check_range(field: &'static str, value: u64, max: u64) -> Result<(), TimecodeError> {
+    if value > max {
+        return Err(TimecodeError::OutOfRange { field, value, max });
+    }
+    Ok(())
+}
+
+fn duration_from_nanos(nanos: u128) -> Duration {
+    let secs = nanos / NANOS_PER_SEC;
+    if secs > u64::MAX as u128 {
+        return Duration::MAX;
+    }
+    Duration::new(secs as u64, (nanos % NANOS_PER_SEC) as u32)
+}
+
+/// An error from parsing or converting a timecode.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum TimecodeError {
+    #[error("The timecode is empty")]
+    Empty,
+    #[error("'{0}' is not a valid number")]
+    InvalidNumber(String),
+    #[error("Expected 1 to 4 fields (or 4 for a timecode) but got {0}")]
+    FieldCount(usize),
+    #[error("The {field} field is {value} but can be at most {max}")]
+    OutOfRange {
+        field: &'static str,
+        value: u64,
+        max: u64,
+    },
+    #[error("The position is too large")]
+    TooLarge,
+    #[error("Drop-frame timecodes are only supported at 29.97 and 59.94 FPS")]
+    DropFrameUnsupported,
+    #[error("The frame number is skipped in drop-frame timecodes")]
+    DroppedFrameNumber,
+}
+
+#[cfg(test)]
+mod decision_coverage_tests {
+    use super::*;
+
+    const FPS_24: FrameRate = FrameRate::new(24, 1).unwrap();
+    const FPS_25: FrameRate = FrameRate::new(25, 1).unwrap();
+    const FPS_29_97: FrameRate = FrameRate::new(30000, 1001).unwrap();
+    const FPS_59_94: FrameRate = FrameRate::new(60000, 1001).unwrap();
+
+    // --- FrameRate ---
+
+    #[test]
+    fn frame_rate_rejects_zero() {
+        assert!(FrameRate::new(0, 1).is_none());
+        assert!(FrameRate::new(1, 0).is_none());
+    }
+
+    #[test]
+    fn frame_rate_nominal_rounds() {
+        assert_eq!(FPS_29_97.nominal(), 30);
+        assert_eq!(FPS_59_94.nominal(), 60);
+        assert_eq!(FrameRate::new(24000, 1001).unwrap().nominal(), 24);
+        assert_eq!(FrameRate::new(1, 10).unwrap().nominal(), 1);
+    }
+
+    #[test]
+    fn frame_rate_supports_drop_frame() {
+        assert!(FPS_29_97.supports_drop_frame());
+        assert!(FPS_59_94.supports_drop_frame());
+        assert!(FrameRate::new(60000, 2002).unwrap().supports_drop_frame());
+        assert!(!FrameRate::new(30, 1).unwrap().supports_drop_frame());
+        assert!(!FrameRate::new(24000, 1001).unwrap().supports_drop_frame());
+    }
+
+    // --- frame_to_time / time_to_frame ---
+
+    #[test]
+    fn frame_to_time_integer_rate() {
+        assert_eq!(frame_to_time(48, FPS_24), Duration::from_secs(2));
+        assert_eq!(frame_to_time(1, FPS_25), Duration::from_millis(40));
+    }
+
+    #[test]
+    fn frame_to_time_fractional_rate() {
+        // 30 frames at 29.97 take 1.001 seconds.
+        assert_eq!(frame_to_time(30, FPS_29_97), Duration::from_millis(1001));
+    }
+
+    #[test]
+    fn time_to_frame_floors() {
+        assert_eq!(time_to_frame(Duration::from_millis(1000), FPS_29_97), 29);
+        assert_eq!(time_to_frame(Duration::from_millis(1001), FPS_29_97), 30);
+    }
+
+    #[test]
+    fn time_to_nearest_frame_rounds() {
+        assert_eq!(
+            time_to_nearest_frame(Duration::from_millis(1000), FPS_29_97),
+            30
+        );
+        assert_eq!(time_to_nearest_frame(Duration::from_millis(19), FPS_25), 0);
+        assert_eq!(time_to_nearest_frame(Duration::from_millis(21), FPS_25), 1);
+    }
+
+    #[test]
+    fn frame_time_round_trip() {
+        for frame in [0, 1, 29, 1799, 17982, 107892] {
+            let time = frame_to_time(frame, FPS_29_97);
+            assert_eq!(time_to_frame(time, FPS_29_97), frame);
+        }
+    }
+
+    // --- format_clock ---
+
+    #[test]
+    fn format_clock_rounds_to_millis() {
+        assert_eq!(
+            format_clock(Duration::from_nanos(1_999_600_000)),
+            "00:00:02.000"
+        );
+        assert_eq!(format_clock(Duration::from_secs(3723)), "01:02:03.000");
+    }
+
+    #[test]
+    fn format_frame_clock_fractional_rate() {
+        assert_eq!(format_frame_clock(1, FPS_29_97), "00:00:00.033");
+    }
+
+    // --- Timecode::from_frame ---
+
+    #[test]
+    fn from_frame_non_drop() {
+        let tc = Timecode::from_frame(90_061, FPS_25, false);
+        assert_eq!(tc.to_string(), "01:00:02:11");
+    }
+
+    #[test]
+    fn from_frame_drop_frame_skips_numbers() {
+        assert_eq!(
+            Timecode::from_frame(1799, FPS_29_97, true).to_string(),
+            "00:00:59;29"
+        );
+        assert_eq!(
+            Timecode::from_frame(1800, FPS_29_97, true).to_string(),
+            "00:01:00;02"
+        );
+        // Every tenth minute isn't skipped.
+        assert_eq!(
+            Timecode::from_frame(17982, FPS_29_97, true).to_string(),
+            "00:10:00;00"
+        );
+        assert_eq!(
+            Timecode::from_frame(3600, FPS_59_94, true).to_string(),
+            "00:01:00;04"
+        );
+    }
+
+    #[test]
+    fn from_frame_drop_frame_tracks_wall_clock() {
+        // One hour of 29.97 footage is exactly one hour of drop-frame timecode.
+        assert_eq!(
+            Timecode::from_frame(107_892, FPS_29_97, true).to_string(),
+            "01:00:00;00"
+        );
+    }
+
+    #[test]
+    fn from_frame_drop_frame_unsupported_falls_back() {
+        let tc = Timecode::from_frame(30, FPS_25, true);
+        assert!(!tc.drop_frame);
+        assert_eq!(tc.to_string(), "00:00:01:05");
+    }
+
+    // --- Timecode::to_frame ---
+
+    #[test]
+    fn to_frame_round_trip() {
+        for frame in [
+            0, 1, 1799, 1800, 1801, 17981, 17982, 35964, 107_892, 1_000_000,
+        ] {
+            let tc = Timecode::from_frame(frame, FPS_29_97, true);
+            assert_eq!(tc.to_frame(FPS_29_97), Ok(frame));
+            let tc = Timecode::from_frame(frame, FPS_29_97, false);
+            assert_eq!(tc.to_frame(FPS_29_97), Ok(frame));
+        }
+    }
+
+    #[test]
+    fn to_frame_rejects_skipped_numbers() {
+        let tc = Timecode {
+            hours: 0,
+            minutes: 1,
+            seconds: 0,
+            frames: 1,
+            drop_frame: true,
+        };
+        assert_eq!(
+            tc.to_frame(FPS_29_97),
+            Err(TimecodeError::DroppedFrameNumber)
+        );
+    }
+
+    #[test]
+    fn to_frame_rejects_unsupported_drop_frame() {
+        let tc = Timecode::from_frame(0, FPS_29_97, true);
+        assert_eq!(
+            tc.to_frame(FPS_25),
+            Err(TimecodeError::DropFrameUnsupported)
+        );
+    }
+
+    #[test]
+    fn to_frame_rejects_out_of_range_frames() {
+        let tc = Timecode {
+            hours: 0,
+            minutes: 0,
+            seconds: 0,
+            frames: 25,
+            drop_frame: false,
+        };
+        assert!(matches!(
+            tc.to_frame(FPS_25),
+            Err(TimecodeError::OutOfRange {
+                field: "frames",
+                ..
+            })
+        ));
+    }
+
+    // --- parse ---
+
+    #[test]
+    fn parse_timecode() {
+        assert_eq!(parse("00:00:02:11", FPS_25), Ok(61));
+        assert_eq!(parse(" 00:01:00;02 ", FPS_29_97), Ok(1800));
+    }
+
+    #[test]
+    fn parse_clock_times() {
+        assert_eq!(parse("00:00:02.000", FPS_25), Ok(50));
+        assert_eq!(parse("1:02", FPS_25), Ok(62 * 25));
+        assert_eq!(parse("2.5", FPS_24), Ok(60));
+        assert_eq!(parse("00:00:00.033", FPS_29_97), Ok(1));
+    }
+
+    #[test]
+    fn parse_bare_number_is_frame() {
+        assert_eq!(parse("123", FPS_24), Ok(123));
+    }
+
+    #[test]
+    fn parse_round_trips_formatting() {
+        for frame in [0, 1, 59, 1800, 17982, 100_000] {
+            let tc = Timecode::from_frame(frame, FPS_29_97, true).to_string();
+            assert_eq!(parse(&tc, FPS_29_97), Ok(frame));
+            let clock = format_frame_clock(frame, FPS_29_97);
+            assert_eq!(parse(&clock, FPS_29_97), Ok(frame));
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("  ", FPS_25), Err(TimecodeError::Empty));
+        assert_eq!(
+            parse("1:2:3:4:5", FPS_25),
+            Err(TimecodeError::FieldCount(5))
+        );
+        assert_eq!(
+            parse("00:0a:00", FPS_25),
+            Err(TimecodeError::InvalidNumber("0a".to_string()))
+        );
+        assert_eq!(
+            parse("1.", FPS_25),
+            Err(TimecodeError::InvalidNumber(String::new()))
+        );
+        assert!(matches!(
+            parse("00:61:00", FPS_25),
+            Err(TimecodeError::OutOfRange {
+                field: "minutes",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("01:75", FPS_25),
+            Err(TimecodeError::OutOfRange {
+                field: "seconds",
+                ..
+            })
+        ));
+        assert_eq!(
+            parse("00:00;10", FPS_29_97),
+            Err(TimecodeError::FieldCount(3))
+        );
+        assert_eq!(
+            parse("00:01:00;00", FPS_29_97),
+            Err(TimecodeError::DroppedFrameNumber)
+        );
+    }
+
+    #[test]
+    fn parse_rejects_overflow() {
+        assert_eq!(
+            parse("99999999999999999999", FPS_25),
+            Err(TimecodeError::TooLarge)
+        );
+        assert_eq!(
+            parse("18446744073709551615:00:00:00", FPS_25),
+            Err(TimecodeError::TooLarge)
+        );
+        assert_eq!(
+            parse("5124095576030432:00:00:00", FPS_25),
+            Err(TimecodeError::TooLarge)
+        );
+        assert_eq!(
+            parse("18446744073709551615:00.5", FPS_25),
+            Err(TimecodeError::TooLarge)
+        );
+        assert_eq!(
+            parse("5124095576030432:00:00.0", FPS_25),
+            Err(TimecodeError::TooLarge)
+        );
+    }
+
+    #[test]
+    fn parse_fraction_ignores_extra_precision() {
+        assert_eq!(parse_fraction_nanos("5"), Ok(500_000_000));
+        assert_eq!(parse_fraction_nanos("1234567891"), Ok(123_456_789));
+    }
+}
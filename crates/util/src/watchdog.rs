@@ -0,0 +1,468 @@
+//! This module contains the [Watchdog] type, a service that worker threads
+//! check in with every iteration so that a stalled thread (e.g. a decoder stuck
+//! inside ffmpeg) gets noticed and reported instead of silently timing out.
+//!
+//! Each worker gets a [WatchdogHandle] from [Watchdog::register] and calls
+//! [WatchdogHandle::ping] once per loop iteration. A [WatchdogMonitor] (see
+//! [Watchdog::spawn_monitor]) periodically checks for workers that have missed
+//! their deadline. Workers registered with [Watchdog::register_restartable]
+//! are also restarted when they stall.
+//!
+//! Workers that are spawned deep inside other crates (e.g. media decoders)
+//! register with [Watchdog::global], so whoever runs the monitor for it hears
+//! about them without having to pass a [Watchdog] down.
+//!
+//! The standard library can't capture the stack of *another* thread, so when a
+//! stalled worker finally pings again its backtrace is logged from the ping
+//! site. This is usually enough to tell which loop was stuck.
+
+use std::backtrace::Backtrace;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Keeps track of registered workers and when they last checked in. Cloning a
+/// [Watchdog] produces another reference to the same set of workers.
+#[derive(Debug, Clone, Default)]
+pub struct Watchdog {
+    workers: Arc<Mutex<Vec<Arc<WorkerState>>>>,
+}
+
+impl Watchdog {
+    /// Create a watchdog with no registered workers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide watchdog. Nothing checks it unless a monitor is
+    /// [spawned](Self::spawn_monitor) for it.
+    pub fn global() -> &'static Watchdog {
+        static GLOBAL: LazyLock<Watchdog> = LazyLock::new(Watchdog::new);
+        &GLOBAL
+    }
+
+    /// Register a worker that promises to [ping](WatchdogHandle::ping) at least
+    /// once every `deadline`. The worker is unregistered when the returned
+    /// handle is dropped.
+    ///
+    /// This should be called from the worker thread so that the thread's name
+    /// can be included in stall reports.
+    pub fn register(&self, name: impl Into<String>, deadline: Duration) -> WatchdogHandle {
+        self.register_impl(name.into(), deadline, None)
+    }
+
+    /// The same as [Self::register], but `restart` is called (from the thread
+    /// calling [Self::check]) whenever the worker is found to be stalled. The
+    /// stalled thread itself can't be interrupted, so `restart` is expected to
+    /// abandon it and have a replacement spun up.
+    pub fn register_restartable<F>(
+        &self,
+        name: impl Into<String>,
+        deadline: Duration,
+        restart: F,
+    ) -> WatchdogHandle
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.register_impl(name.into(), deadline, Some(Box::new(restart)))
+    }
+
+    /// The number of workers currently registered.
+    pub fn worker_count(&self) -> usize {
+        let mut workers = self.workers.lock().expect(LOCK_NOT_POISONED);
+        prune_dropped(&mut workers);
+        workers.len()
+    }
+
+    /// Check every registered worker, returning the ones that have *newly*
+    /// missed their deadline. A worker is only reported once per stall (it has
+    /// to ping again before it can be reported again).
+    ///
+    /// Restart callbacks (see [Self::register_restartable]) are called for
+    /// every returned worker.
+    pub fn check(&self) -> Vec<StalledWorker> {
+        let now = Instant::now();
+
+        let newly_stalled: Vec<Arc<WorkerState>> = {
+            let mut workers = self.workers.lock().expect(LOCK_NOT_POISONED);
+            prune_dropped(&mut workers);
+            workers
+                .iter()
+                .filter(|worker| {
+                    !worker.idle.load(Ordering::SeqCst)
+                        && worker.silent_for(now) > worker.deadline
+                        && !worker.stalled.swap(true, Ordering::SeqCst)
+                })
+                .cloned()
+                .collect()
+        };
+
+        newly_stalled
+            .into_iter()
+            .map(|worker| {
+                let stalled = StalledWorker {
+                    name: worker.name.clone(),
+                    thread_name: worker.thread_name.clone(),
+                    silent_for: worker.silent_for(now),
+                };
+
+                crate::debug_log_warning!(
+                    "Watchdog: `{}` (thread `{}`) hasn't checked in for {:?} (deadline {:?}).",
+                    stalled.name,
+                    stalled.thread_name.as_deref().unwrap_or("anonymous"),
+                    stalled.silent_for,
+                    worker.deadline,
+                );
+
+                if let Some(restart) = worker.restart.lock().expect(LOCK_NOT_POISONED).as_mut() {
+                    crate::debug_log_info!("Watchdog: restarting `{}`.", stalled.name);
+                    restart();
+                }
+
+                stalled
+            })
+            .collect()
+    }
+
+    /// Spawn a thread that calls [Self::check] every `poll_interval`, passing
+    /// each stalled worker to `on_stall`. The thread stops when the returned
+    /// [WatchdogMonitor] is dropped.
+    pub fn spawn_monitor<F>(&self, poll_interval: Duration, mut on_stall: F) -> WatchdogMonitor
+    where
+        F: FnMut(StalledWorker) + Send + 'static,
+    {
+        let watchdog = self.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_inner = stop.clone();
+
+        let thread = thread::Builder::new()
+            .name("watchdog".into())
+            .spawn(move || {
+                while !stop_inner.load(Ordering::SeqCst) {
+                    thread::park_timeout(poll_interval);
+                    if stop_inner.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    watchdog.check().into_iter().for_each(&mut on_stall);
+                }
+            })
+            .expect("Spawning the watchdog thread shouldn't fail.");
+
+        WatchdogMonitor {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn register_impl(
+        &self,
+        name: String,
+        deadline: Duration,
+        restart: Option<RestartFn>,
+    ) -> WatchdogHandle {
+        let state = Arc::new(WorkerState {
+            name,
+            thread_name: thread::current().name().map(String::from),
+            deadline,
+            last_ping: Mutex::new(Instant::now()),
+            stalled: AtomicBool::new(false),
+            idle: AtomicBool::new(false),
+            restart: Mutex::new(restart),
+        });
+
+        let mut workers = self.workers.lock().expect(LOCK_NOT_POISONED);
+        // The global watchdog may never be checked, so it's pruned here too.
+        prune_dropped(&mut workers);
+        workers.push(state.clone());
+
+        WatchdogHandle { state }
+    }
+}
+
+/// A worker's connection to a [Watchdog]. See [Watchdog::register].
+#[derive(Debug)]
+pub struct WatchdogHandle {
+    state: Arc<WorkerState>,
+}
+
+impl WatchdogHandle {
+    /// Tell the watchdog this worker is still making progress. Call this once
+    /// per loop iteration.
+    ///
+    /// If the worker had been reported as stalled, the recovery is logged along
+    /// with a backtrace of the caller.
+    pub fn ping(&self) {
+        if self.state.stalled.load(Ordering::SeqCst) {
+            crate::debug_log_warning!(
+                "Watchdog: `{}` recovered from a stall. Backtrace:\n{}",
+                self.state.name,
+                Backtrace::force_capture(),
+            );
+        }
+
+        // Only after logging, since capturing a backtrace can take longer than
+        // the deadline.
+        *self.state.last_ping.lock().expect(LOCK_NOT_POISONED) = Instant::now();
+        self.state.stalled.store(false, Ordering::SeqCst);
+    }
+
+    /// Run `f`, which waits for something other than this worker (e.g. for a
+    /// consumer to make room), without the worker being reported as stalled
+    /// while it does. The worker [pings](Self::ping) once `f` returns.
+    pub fn idle_while<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        self.state.idle.store(true, Ordering::SeqCst);
+        let ret = f();
+        self.state.idle.store(false, Ordering::SeqCst);
+        self.ping();
+        ret
+    }
+
+    /// The name the worker was registered with.
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// Whether the watchdog has reported this worker as stalled (and it hasn't
+    /// pinged since).
+    pub fn is_stalled(&self) -> bool {
+        self.state.stalled.load(Ordering::SeqCst)
+    }
+}
+
+/// A report of a worker that missed its deadline. See [Watchdog::check].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StalledWorker {
+    /// The name the worker was registered with.
+    pub name: String,
+    /// The name of the thread the worker was registered from.
+    pub thread_name: Option<String>,
+    /// How long it had been since the worker last pinged when it was checked.
+    pub silent_for: Duration,
+}
+
+/// Owns the thread spawned by [Watchdog::spawn_monitor]. The thread is stopped
+/// and joined when this is dropped.
+#[derive(Debug)]
+pub struct WatchdogMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for WatchdogMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            _ = thread.join();
+        }
+    }
+}
+
+type RestartFn = Box<dyn FnMut() + Send>;
+
+struct WorkerState {
+    name: String,
+    thread_name: Option<String>,
+    deadline: Duration,
+    last_ping: Mutex<Instant>,
+    stalled: AtomicBool,
+    idle: AtomicBool,
+    restart: Mutex<Option<RestartFn>>,
+}
+
+impl WorkerState {
+    fn silent_for(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(*self.last_ping.lock().expect(LOCK_NOT_POISONED))
+    }
+}
+
+impl std::fmt::Debug for WorkerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkerState")
+            .field("name", &self.name)
+            .field("thread_name", &self.thread_name)
+            .field("deadline", &self.deadline)
+            .field("last_ping", &self.last_ping)
+            .field("stalled", &self.stalled)
+            .field("idle", &self.idle)
+            .finish_non_exhaustive()
+    }
+}
+
+const LOCK_NOT_POISONED: &str = "The lock isn't poisoned.";
+
+/// Remove workers whose [WatchdogHandle] has been dropped (the list holds the
+/// only remaining reference).
+fn prune_dropped(workers: &mut Vec<Arc<WorkerState>>) {
+    workers.retain(|worker| Arc::strong_count(worker) > 1);
+}
+
+#[cfg(test)]
+mod decision_coverage_tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::mpsc;
+
+    const LONG: Duration = Duration::from_secs(60);
+    const SHORT: Duration = Duration::from_millis(10);
+
+    // --- Watchdog::register / worker_count ---
+
+    #[test]
+    fn register_adds_worker_and_drop_removes_it() {
+        let watchdog = Watchdog::new();
+        let handle = watchdog.register("a", LONG);
+        assert_eq!(watchdog.worker_count(), 1);
+        assert_eq!(handle.name(), "a");
+        drop(handle);
+        assert_eq!(watchdog.worker_count(), 0);
+    }
+
+    #[test]
+    fn register_records_thread_name() {
+        let watchdog = Watchdog::new();
+        let watchdog_inner = watchdog.clone();
+        let handle = thread::Builder::new()
+            .name("named-worker".into())
+            .spawn(move || watchdog_inner.register("a", Duration::ZERO))
+            .unwrap()
+            .join()
+            .unwrap();
+
+        thread::sleep(SHORT);
+        let stalled = watchdog.check();
+        assert_eq!(stalled.len(), 1);
+        assert_eq!(stalled[0].thread_name.as_deref(), Some("named-worker"));
+        drop(handle);
+    }
+
+    #[test]
+    fn global_is_shared() {
+        assert!(std::ptr::eq(Watchdog::global(), Watchdog::global()));
+    }
+
+    // --- Watchdog::check ---
+
+    #[test]
+    fn check_ignores_workers_within_deadline() {
+        let watchdog = Watchdog::new();
+        let _handle = watchdog.register("a", LONG);
+        assert!(watchdog.check().is_empty());
+    }
+
+    #[test]
+    fn check_reports_stalled_worker_once() {
+        let watchdog = Watchdog::new();
+        let handle = watchdog.register("a", SHORT);
+        thread::sleep(SHORT * 3);
+
+        let stalled = watchdog.check();
+        assert_eq!(stalled.len(), 1);
+        assert_eq!(stalled[0].name, "a");
+        assert!(stalled[0].silent_for > SHORT);
+        assert!(handle.is_stalled());
+
+        // Already reported, so not reported again.
+        assert!(watchdog.check().is_empty());
+    }
+
+    #[test]
+    fn check_reports_again_after_ping_and_new_stall() {
+        let watchdog = Watchdog::new();
+        let handle = watchdog.register("a", SHORT);
+        thread::sleep(SHORT * 3);
+        assert_eq!(watchdog.check().len(), 1);
+
+        handle.ping();
+        assert!(!handle.is_stalled());
+        assert!(watchdog.check().is_empty());
+
+        thread::sleep(SHORT * 3);
+        assert_eq!(watchdog.check().len(), 1);
+    }
+
+    #[test]
+    fn check_skips_dropped_handles() {
+        let watchdog = Watchdog::new();
+        drop(watchdog.register("a", Duration::ZERO));
+        thread::sleep(SHORT);
+        assert!(watchdog.check().is_empty());
+    }
+
+    #[test]
+    fn check_calls_restart_for_stalled_restartable_worker() {
+        let watchdog = Watchdog::new();
+        let restarts = Arc::new(AtomicUsize::new(0));
+        let restarts_inner = restarts.clone();
+        let _handle = watchdog.register_restartable("a", SHORT, move || {
+            restarts_inner.fetch_add(1, Ordering::SeqCst);
+        });
+
+        assert!(watchdog.check().is_empty());
+        assert_eq!(restarts.load(Ordering::SeqCst), 0);
+
+        thread::sleep(SHORT * 3);
+        assert_eq!(watchdog.check().len(), 1);
+        assert_eq!(restarts.load(Ordering::SeqCst), 1);
+    }
+
+    // --- WatchdogHandle::ping ---
+
+    #[test]
+    fn ping_keeps_worker_alive() {
+        let watchdog = Watchdog::new();
+        let handle = watchdog.register("a", Duration::from_millis(200));
+        for _ in 0..5 {
+            thread::sleep(Duration::from_millis(50));
+            handle.ping();
+            assert!(watchdog.check().is_empty());
+        }
+    }
+
+    // --- WatchdogHandle::idle_while ---
+
+    #[test]
+    fn idle_while_isnt_reported_and_pings_after() {
+        let watchdog = Watchdog::new();
+        let handle = watchdog.register("a", SHORT);
+
+        let ret = handle.idle_while(|| {
+            thread::sleep(SHORT * 3);
+            assert!(watchdog.check().is_empty());
+            7
+        });
+        assert_eq!(ret, 7);
+        assert!(watchdog.check().is_empty());
+
+        thread::sleep(SHORT * 3);
+        assert_eq!(watchdog.check().len(), 1);
+    }
+
+    // --- Watchdog::spawn_monitor ---
+
+    #[test]
+    fn monitor_reports_stalled_worker() {
+        let watchdog = Watchdog::new();
+        let _handle = watchdog.register("stuck", SHORT);
+        let (tx, rx) = mpsc::channel();
+        let _monitor = watchdog.spawn_monitor(SHORT, move |stalled| {
+            _ = tx.send(stalled.name);
+        });
+
+        assert_eq!(rx.recv_timeout(LONG).unwrap(), "stuck");
+    }
+
+    #[test]
+    fn monitor_stops_when_dropped() {
+        let watchdog = Watchdog::new();
+        let monitor = watchdog.spawn_monitor(LONG, |_| {});
+        // Should return promptly even with a long poll interval.
+        let start = Instant::now();
+        drop(monitor);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
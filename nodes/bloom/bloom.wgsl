@@ -0,0 +1,74 @@
+// Shared by every pass of the Bloom node (see "includes" in node.json). The
+// passes extract the bright parts of the input at half size, shrink them down
+// to a sixteenth, then grow them back up, adding each size onto the next so
+// the glow has both a tight core and a wide halo.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    threshold: f32,
+    softness: f32,
+    intensity: f32,
+    size: f32,
+    tint_r: f32,
+    tint_g: f32,
+    tint_b: f32,
+    tint_a: f32,
+}
+
+// Shrink `source` to half its size around `uv` with a 13-tap filter (as in
+// Jimenez's "Next Generation Post Processing in Call of Duty"), which avoids
+// the flicker and blockiness of a plain 2x2 average.
+fn downsample(source: texture_2d<f32>, source_sampler: sampler, uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    let a = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-2.0, -2.0), 0.0).rgb;
+    let b = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(0.0, -2.0), 0.0).rgb;
+    let c = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(2.0, -2.0), 0.0).rgb;
+    let d = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-2.0, 0.0), 0.0).rgb;
+    let e = textureSampleLevel(source, source_sampler, uv, 0.0).rgb;
+    let f = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(2.0, 0.0), 0.0).rgb;
+    let g = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-2.0, 2.0), 0.0).rgb;
+    let h = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(0.0, 2.0), 0.0).rgb;
+    let i = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(2.0, 2.0), 0.0).rgb;
+    let j = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-1.0, -1.0), 0.0).rgb;
+    let k = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(1.0, -1.0), 0.0).rgb;
+    let l = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-1.0, 1.0), 0.0).rgb;
+    let m = textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(1.0, 1.0), 0.0).rgb;
+
+    return e * 0.125 + (a + c + g + i) * 0.03125 + (b + d + f + h) * 0.0625 + (j + k + l + m) * 0.125;
+}
+
+// Grow `source` back up with a 3x3 tent filter whose taps are `size` of its
+// texels apart. Larger sizes spread the glow further.
+fn upsample(source: texture_2d<f32>, source_sampler: sampler, uv: vec2<f32>, size: f32) -> vec3<f32> {
+    let texel = max(size, 0.0) / vec2<f32>(textureDimensions(source));
+    var sum = textureSampleLevel(source, source_sampler, uv, 0.0).rgb * 4.0;
+    sum += textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-1.0, 0.0), 0.0).rgb * 2.0;
+    sum += textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(1.0, 0.0), 0.0).rgb * 2.0;
+    sum += textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(0.0, -1.0), 0.0).rgb * 2.0;
+    sum += textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(0.0, 1.0), 0.0).rgb * 2.0;
+    sum += textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-1.0, -1.0), 0.0).rgb;
+    sum += textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(1.0, -1.0), 0.0).rgb;
+    sum += textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(-1.0, 1.0), 0.0).rgb;
+    sum += textureSampleLevel(source, source_sampler, uv + texel * vec2<f32>(1.0, 1.0), 0.0).rgb;
+    return sum / 16.0;
+}
+
+// Add a smaller size's glow onto this one's. Each size weighs half of what's
+// been built up so far, so the result stays in range however many are added.
+fn combine(glow: vec3<f32>, smaller: vec3<f32>) -> vec4<f32> {
+    return vec4<f32>(mix(glow, smaller, 0.5), 1.0);
+}
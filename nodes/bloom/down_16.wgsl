@@ -0,0 +1,10 @@
+// Pass 4 (1/16 size): pass 3 shrunk by half.
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(4) var previous_texture: texture_2d<f32>;
+@group(0) @binding(5) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(previous_texture, input_sampler, in.uv), 1.0);
+}
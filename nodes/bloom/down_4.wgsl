@@ -0,0 +1,10 @@
+// Pass 2 (1/4 size): pass 1 shrunk by half.
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(2) var previous_texture: texture_2d<f32>;
+@group(0) @binding(3) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(previous_texture, input_sampler, in.uv), 1.0);
+}
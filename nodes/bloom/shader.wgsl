@@ -0,0 +1,17 @@
+// Final pass: the glow (pass 7) grown to full size and added onto the input.
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(8) var glow_texture: texture_2d<f32>;
+@group(0) @binding(9) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input_texture, input_sampler, in.uv, 0.0);
+    let glow = upsample(glow_texture, input_sampler, in.uv, params.size);
+    let tint = vec3<f32>(params.tint_r, params.tint_g, params.tint_b);
+    // Each size's glow was averaged in, so scale it back up to roughly the
+    // brightness of the parts it came from.
+    let added = glow * tint * params.intensity * 2.0;
+    return vec4<f32>(color.rgb + added, color.a);
+}
@@ -0,0 +1,21 @@
+// Pass 1 (half size): the parts of the input brighter than the threshold.
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // At half size each pixel's bilinear sample averages four input pixels.
+    let color = textureSampleLevel(input_texture, input_sampler, in.uv, 0.0).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+
+    // A soft knee eases colors in over a range below the threshold rather
+    // than cutting them off, so the glow doesn't pop on and off.
+    let knee = max(params.threshold * params.softness, 0.0001);
+    var soft = clamp(brightness - params.threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee);
+    let contribution = max(soft, brightness - params.threshold) / max(brightness, 0.0001);
+
+    return vec4<f32>(color * contribution, 1.0);
+}
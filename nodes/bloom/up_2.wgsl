@@ -0,0 +1,12 @@
+// Pass 7 (1/2 size): pass 6 grown back up and added onto pass 1.
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(2) var same_size_texture: texture_2d<f32>;
+@group(0) @binding(7) var smaller_texture: texture_2d<f32>;
+@group(0) @binding(8) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let glow = textureSampleLevel(same_size_texture, input_sampler, in.uv, 0.0).rgb;
+    return combine(glow, upsample(smaller_texture, input_sampler, in.uv, params.size));
+}
@@ -15,6 +15,9 @@ fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
 
 struct Params {
     strength: f32,
+    mode: u32,  // 0=horizontal, 1=radial
+    center_x: f32,
+    center_y: f32,
 }
 
 @group(0) @binding(0) var input_sampler: sampler;
@@ -24,13 +27,18 @@ struct Params {
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     // Calculate offset based on strength (convert to screen-space)
-    let offset = params.strength * 0.005;
+    var offset = vec2<f32>(params.strength * 0.005, 0.0);
+    if (params.mode == 1u) {
+        // Radial: red is magnified and blue shrunk around the center, like a
+        // real lens, so the fringes grow towards the edges of the frame.
+        offset = (vec2<f32>(params.center_x, params.center_y) - in.uv) * params.strength * 0.01;
+    }
     
     // Sample each channel at different positions
-    // Red shifts right, Blue shifts left, Green stays centered
-    let r = textureSample(input_texture, input_sampler, in.uv + vec2<f32>(offset, 0.0)).r;
+    // Red and Blue shift in opposite directions, Green stays centered
+    let r = textureSample(input_texture, input_sampler, in.uv + offset).r;
     let g = textureSample(input_texture, input_sampler, in.uv).g;
-    let b = textureSample(input_texture, input_sampler, in.uv - vec2<f32>(offset, 0.0)).b;
+    let b = textureSample(input_texture, input_sampler, in.uv - offset).b;
     
     return vec4<f32>(r, g, b, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,135 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    colormap: u32,  // 0=viridis, 1=magma, 2=inferno, 3=plasma, 4=jet, 5=custom gradient
+    channel: u32,   // 0=luma, 1=red, 2=green, 3=blue
+    low: f32,
+    high: f32,
+    reverse: u32,
+    low_r: f32,
+    low_g: f32,
+    low_b: f32,
+    low_a: f32,
+    mid_r: f32,
+    mid_g: f32,
+    mid_b: f32,
+    mid_a: f32,
+    high_r: f32,
+    high_g: f32,
+    high_b: f32,
+    high_a: f32,
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+// The matplotlib colormaps as degree 6 polynomial fits (by Matt Zucker,
+// public domain), accurate to well under one 8-bit step.
+fn polynomial(t: f32, c0: vec3<f32>, c1: vec3<f32>, c2: vec3<f32>, c3: vec3<f32>, c4: vec3<f32>, c5: vec3<f32>, c6: vec3<f32>) -> vec3<f32> {
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+}
+
+fn viridis(t: f32) -> vec3<f32> {
+    return polynomial(t,
+        vec3<f32>(0.2777273272234177, 0.005407344544966578, 0.3340998053353061),
+        vec3<f32>(0.1050930431085774, 1.404613529898575, 1.384590162594685),
+        vec3<f32>(-0.3308618287255563, 0.214847559468213, 0.09509516302823659),
+        vec3<f32>(-4.634230498983486, -5.799100973351585, -19.33244095627987),
+        vec3<f32>(6.228269936347081, 14.17993336680509, 56.69055260068105),
+        vec3<f32>(4.776384997670288, -13.74514537774601, -65.35303263337234),
+        vec3<f32>(-5.435455855934631, 4.645852612178535, 26.3124352495832));
+}
+
+fn magma(t: f32) -> vec3<f32> {
+    return polynomial(t,
+        vec3<f32>(-0.002136485053939582, -0.000749655052795221, -0.005386127855323933),
+        vec3<f32>(0.2516605407371642, 0.6775232436837668, 2.494026599312351),
+        vec3<f32>(8.353717279216625, -3.577719514958484, 0.3144679030132573),
+        vec3<f32>(-27.66873308576866, 14.26473078096533, -13.64921318813922),
+        vec3<f32>(52.17613981234068, -27.94360607168351, 12.94416944238394),
+        vec3<f32>(-50.76852536473588, 29.04658282127291, 4.23415299384598),
+        vec3<f32>(18.65570506591883, -11.48977351997711, -5.601961508734096));
+}
+
+fn inferno(t: f32) -> vec3<f32> {
+    return polynomial(t,
+        vec3<f32>(0.0002189403691192265, 0.001651004631001012, -0.01948089843709184),
+        vec3<f32>(0.1065134194856116, 0.5639564367884091, 3.932712388889277),
+        vec3<f32>(11.60249308247187, -3.972853965665698, -15.9423941062914),
+        vec3<f32>(-41.70399613139459, 17.43639888205313, 44.35414519872813),
+        vec3<f32>(77.162935699427, -33.40235894210092, -81.80730925738993),
+        vec3<f32>(-71.31942824499214, 32.62606426397723, 73.20951985803202),
+        vec3<f32>(25.13112622477341, -12.24266895238567, -23.07032500287172));
+}
+
+fn plasma(t: f32) -> vec3<f32> {
+    return polynomial(t,
+        vec3<f32>(0.05873234392399702, 0.02333670892565664, 0.5433401826748754),
+        vec3<f32>(2.176514634195958, 0.2383834171260182, 0.7539604599784036),
+        vec3<f32>(-2.689460476458034, -7.455851135738909, 3.110799939717086),
+        vec3<f32>(6.130348345893603, 42.3461881477227, -28.51885465332158),
+        vec3<f32>(-11.10743619062271, -82.66631109428045, 60.13984767418263),
+        vec3<f32>(10.02306557647065, 71.41361770095349, -54.07218655560067),
+        vec3<f32>(-3.658713842777788, -22.93153465461149, 18.19190778539828));
+}
+
+fn jet(t: f32) -> vec3<f32> {
+    return clamp(vec3<f32>(1.5) - abs(4.0 * t - vec3<f32>(3.0, 2.0, 1.0)), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn gradient(t: f32) -> vec3<f32> {
+    let low = vec3<f32>(params.low_r, params.low_g, params.low_b);
+    let mid = vec3<f32>(params.mid_r, params.mid_g, params.mid_b);
+    let high = vec3<f32>(params.high_r, params.high_g, params.high_b);
+    if (t < 0.5) {
+        return mix(low, mid, t * 2.0);
+    }
+    return mix(mid, high, t * 2.0 - 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input_texture, input_sampler, in.uv);
+
+    var value: f32;
+    switch params.channel {
+        case 1u: { value = color.r; }
+        case 2u: { value = color.g; }
+        case 3u: { value = color.b; }
+        default: { value = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722)); }
+    }
+
+    // Stretch the Low to High range over the whole colormap.
+    let range = params.high - params.low;
+    var t = select(step(params.high, value), (value - params.low) / range, abs(range) > 0.0001);
+    t = clamp(t, 0.0, 1.0);
+    if (params.reverse != 0u) {
+        t = 1.0 - t;
+    }
+
+    var mapped: vec3<f32>;
+    switch params.colormap {
+        case 1u: { mapped = magma(t); }
+        case 2u: { mapped = inferno(t); }
+        case 3u: { mapped = plasma(t); }
+        case 4u: { mapped = jet(t); }
+        case 5u: { mapped = gradient(t); }
+        default: { mapped = viridis(t); }
+    }
+
+    return vec4<f32>(clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
@@ -0,0 +1,70 @@
+// First pass: the brightness gradient at each pixel. Red holds its strength
+// and green which of four directions it points in (0.0, 1/3, 2/3, or 1.0 for
+// 0, 45, 90, and 135 degrees), for shader.wgsl to thin and threshold.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    mode: u32,
+    threshold: f32,
+    line_r: f32,
+    line_g: f32,
+    line_b: f32,
+    line_a: f32,
+    background: u32,
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+const PI: f32 = 3.14159265359;
+
+// Brightness around `uv`, lightly blurred (four bilinear taps half a pixel
+// out) so noise doesn't turn into edges.
+fn soft_luma(uv: vec2<f32>, texel: vec2<f32>) -> f32 {
+    let half_texel = texel * 0.5;
+    let color = textureSampleLevel(input_texture, input_sampler, uv + vec2<f32>(-half_texel.x, -half_texel.y), 0.0)
+        + textureSampleLevel(input_texture, input_sampler, uv + vec2<f32>(half_texel.x, -half_texel.y), 0.0)
+        + textureSampleLevel(input_texture, input_sampler, uv + vec2<f32>(-half_texel.x, half_texel.y), 0.0)
+        + textureSampleLevel(input_texture, input_sampler, uv + vec2<f32>(half_texel.x, half_texel.y), 0.0);
+    return dot(color.rgb * 0.25, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(input_texture));
+
+    var luma: array<f32, 9>;
+    for (var i = 0; i < 9; i++) {
+        let offset = vec2<f32>(f32(i % 3 - 1), f32(i / 3 - 1));
+        luma[i] = soft_luma(in.uv + offset * texel, texel);
+    }
+
+    // Sobel kernels, with y pointing down the frame.
+    let gx = (luma[2] + 2.0 * luma[5] + luma[8]) - (luma[0] + 2.0 * luma[3] + luma[6]);
+    let gy = (luma[6] + 2.0 * luma[7] + luma[8]) - (luma[0] + 2.0 * luma[1] + luma[2]);
+    let strength = clamp(length(vec2<f32>(gx, gy)) / 4.0, 0.0, 1.0);
+
+    // Fold the direction into 0 to 180 degrees and round it to 45 degree steps.
+    var angle = atan2(gy, gx);
+    if (angle < 0.0) {
+        angle += PI;
+    }
+    let direction = f32(u32(round(angle / (PI / 4.0))) % 4u) / 3.0;
+
+    return vec4<f32>(strength, direction, 0.0, 1.0);
+}
@@ -0,0 +1,95 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    mode: u32,        // 0=sobel, 1=canny
+    threshold: f32,
+    line_r: f32,
+    line_g: f32,
+    line_b: f32,
+    line_a: f32,
+    background: u32,  // 0=black, 1=transparent, 2=input
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var gradient_texture: texture_2d<f32>;  // from gradient.wgsl
+@group(0) @binding(3) var<uniform> params: Params;
+
+fn gradient_at(pixel: vec2<i32>) -> vec2<f32> {
+    let size = vec2<i32>(textureDimensions(gradient_texture));
+    return textureLoad(gradient_texture, clamp(pixel, vec2<i32>(0), size - 1), 0).rg;
+}
+
+// How strongly `pixel` is an edge: a soft ramp around the threshold for Sobel,
+// or 0.0 or 1.0 for Canny.
+fn edge_at(pixel: vec2<i32>) -> f32 {
+    let gradient = gradient_at(pixel);
+    let strength = gradient.r;
+    let high = max(params.threshold, 0.001);
+    let low = high * 0.5;
+
+    if (params.mode == 0u) {
+        return smoothstep(low, high, strength);
+    }
+
+    // Non-maximum suppression: only keep the peak across the edge, which
+    // thins edges down to one pixel.
+    var step = vec2<i32>(1, 0);
+    let direction = u32(round(gradient.g * 3.0));
+    if (direction == 1u) {
+        step = vec2<i32>(1, 1);
+    } else if (direction == 2u) {
+        step = vec2<i32>(0, 1);
+    } else if (direction == 3u) {
+        step = vec2<i32>(-1, 1);
+    }
+    if (strength < gradient_at(pixel + step).r || strength < gradient_at(pixel - step).r) {
+        return 0.0;
+    }
+
+    // Double threshold: strong edges are kept, and weak ones only when they
+    // touch a strong one (one step of hysteresis).
+    if (strength >= high) {
+        return 1.0;
+    }
+    if (strength < low) {
+        return 0.0;
+    }
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            if (gradient_at(pixel + vec2<i32>(x, y)).r >= high) {
+                return 1.0;
+            }
+        }
+    }
+    return 0.0;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let edge = edge_at(vec2<i32>(in.position.xy));
+    let line = vec4<f32>(params.line_r, params.line_g, params.line_b, params.line_a);
+    let coverage = edge * line.a;
+
+    if (params.background == 1u) {
+        return vec4<f32>(line.rgb, coverage);
+    }
+    var background = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    if (params.background == 2u) {
+        background = textureSample(input_texture, input_sampler, in.uv);
+    }
+    return vec4<f32>(mix(background.rgb, line.rgb, coverage), background.a);
+}
@@ -0,0 +1,75 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    amount: f32,
+    size: f32,
+    seed: f32,
+    colored: u32,
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+// PCG hash (Jarzynski and Olano, "Hash Functions for GPU Rendering").
+fn pcg(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// A random value from -1.0 to 1.0 for each grain cell and seed.
+fn cell_noise(cell: vec2<i32>, seed: u32) -> f32 {
+    let hash = pcg(bitcast<u32>(cell.x) ^ pcg(bitcast<u32>(cell.y) ^ pcg(seed)));
+    return f32(hash) / 2147483647.5 - 1.0;
+}
+
+// Grain `size` pixels across: random values on a grid that far apart,
+// smoothly blended between so larger grains are soft blobs, not squares.
+fn grain(pixel: vec2<f32>, seed: u32) -> f32 {
+    let position = pixel / max(params.size, 1.0);
+    let cell = vec2<i32>(floor(position));
+    let t = fract(position);
+    let blend = t * t * (3.0 - 2.0 * t);
+
+    let top = mix(cell_noise(cell, seed), cell_noise(cell + vec2<i32>(1, 0), seed), blend.x);
+    let bottom = mix(
+        cell_noise(cell + vec2<i32>(0, 1), seed),
+        cell_noise(cell + vec2<i32>(1, 1), seed),
+        blend.x,
+    );
+    return mix(top, bottom, blend.y);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input_texture, input_sampler, in.uv);
+    let seed = bitcast<u32>(params.seed);
+    let pixel = in.position.xy;
+
+    var noise = vec3<f32>(grain(pixel, seed));
+    if (params.colored != 0u) {
+        noise = vec3<f32>(noise.r, grain(pixel, seed ^ 0x9e3779b9u), grain(pixel, seed ^ 0x7f4a7c15u));
+    }
+
+    // Like film, grain shows most in the midtones and least in deep shadows
+    // and bright highlights.
+    let luma = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let response = 0.25 + 3.0 * luma * (1.0 - luma);
+
+    let grained = color.rgb + noise * params.amount * response;
+    return vec4<f32>(clamp(grained, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
@@ -0,0 +1,91 @@
+// Shared by the Mirror, Kaleidoscope, and Tile nodes, which list this file in
+// the "includes" of their node.json. It provides the vertex shader and the
+// functions that fold output coordinates back onto the input. Coordinates are
+// uvs from 0.0 to 1.0 with the origin at the top left.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+const TAU: f32 = 6.28318530718;
+
+// Fold any coordinate into 0.0 to 1.0 by mirroring it back and forth, so going
+// past an edge reflects the frame instead of stretching or wrapping it.
+fn mirror_repeat(value: f32) -> f32 {
+    let t = fract(value * 0.5) * 2.0;
+    return select(t, 2.0 - t, t > 1.0);
+}
+
+fn mirror_repeat2(uv: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(mirror_repeat(uv.x), mirror_repeat(uv.y));
+}
+
+// Reflect `value` across `axis` if it's on the side being replaced. With
+// `keep_low` the side below the axis is kept and shown on both sides.
+fn mirror_across(value: f32, axis: f32, keep_low: bool) -> f32 {
+    let replaced = select(value < axis, value > axis, keep_low);
+    return select(value, 2.0 * axis - value, replaced);
+}
+
+// How many times wider than tall a texture is.
+fn aspect_ratio(texture: texture_2d<f32>) -> f32 {
+    let size = vec2<f32>(textureDimensions(texture));
+    return size.x / max(size.y, 1.0);
+}
+
+// Fold `uv` into one wedge of a kaleidoscope with `segments` mirrored wedges
+// around `center`. `rotation` turns the pattern and `source_angle` picks which
+// wedge of the input is repeated (both in radians). `zoom` above 1.0 shows a
+// smaller part of the input. `aspect` keeps the wedges even on frames that
+// aren't square.
+fn kaleidoscope_uv(
+    uv: vec2<f32>,
+    center: vec2<f32>,
+    segments: f32,
+    rotation: f32,
+    source_angle: f32,
+    zoom: f32,
+    aspect: f32,
+) -> vec2<f32> {
+    let offset = (uv - center) * vec2<f32>(aspect, 1.0);
+    let radius = length(offset) / max(zoom, 0.001);
+    let wedge = TAU / max(segments, 1.0);
+
+    // Angle within the wedge, mirrored every other wedge so edges line up.
+    var angle = atan2(offset.y, offset.x) - rotation;
+    angle = angle - wedge * floor(angle / wedge);
+    angle = min(angle, wedge - angle) + source_angle;
+
+    let folded = vec2<f32>(cos(angle), sin(angle)) * radius / vec2<f32>(aspect, 1.0);
+    return mirror_repeat2(center + folded);
+}
+
+// Where in its tile `uv` lands when the frame is repeated `tiles` times across
+// and down, scrolled by `offset` (in tiles). The result is 0.0 to 1.0 within
+// the tile. With `mirrored` every other tile is flipped so neighbors meet
+// without a seam.
+fn tile_uv(uv: vec2<f32>, tiles: vec2<f32>, offset: vec2<f32>, mirrored: bool) -> vec2<f32> {
+    let position = uv * max(tiles, vec2<f32>(0.001)) - offset;
+    if (mirrored) {
+        return mirror_repeat2(position);
+    }
+    return fract(position);
+}
+
+// How much of the shifted copy to blend in at `t` (0.0 to 1.0 within a tile)
+// to hide the seam: 1.0 at the tile's edges, fading to 0.0 `width` in.
+fn tile_seam_weight(t: f32, width: f32) -> f32 {
+    let edge_distance = min(t, 1.0 - t);
+    return 1.0 - smoothstep(0.0, max(width, 0.0001), edge_distance);
+}
@@ -0,0 +1,28 @@
+// Prepended with ../geometry_warp.wgsl (see node.json).
+
+struct Params {
+    segments: i32,
+    rotation: f32,
+    source_angle: f32,
+    zoom: f32,
+    center_x: f32,
+    center_y: f32,
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = kaleidoscope_uv(
+        in.uv,
+        vec2<f32>(params.center_x, params.center_y),
+        f32(max(params.segments, 1)),
+        params.rotation,
+        params.source_angle,
+        params.zoom,
+        aspect_ratio(input_texture),
+    );
+    return textureSample(input_texture, input_sampler, uv);
+}
@@ -0,0 +1,28 @@
+// Prepended with ../geometry_warp.wgsl (see node.json).
+
+struct Params {
+    mode: u32,  // 0=left to right, 1=right to left, 2=top to bottom, 3=bottom to top, 4=quad
+    axis_x: f32,
+    axis_y: f32,
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var uv = in.uv;
+
+    if (params.mode == 0u || params.mode == 1u) {
+        uv.x = mirror_across(uv.x, params.axis_x, params.mode == 0u);
+    } else if (params.mode == 2u || params.mode == 3u) {
+        uv.y = mirror_across(uv.y, params.axis_y, params.mode == 2u);
+    } else {
+        // Quad: the top left quarter is repeated into the other three.
+        uv.x = mirror_across(uv.x, params.axis_x, true);
+        uv.y = mirror_across(uv.y, params.axis_y, true);
+    }
+
+    return textureSample(input_texture, input_sampler, mirror_repeat2(uv));
+}
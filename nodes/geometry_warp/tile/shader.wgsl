@@ -0,0 +1,42 @@
+// Prepended with ../geometry_warp.wgsl (see node.json).
+
+struct Params {
+    mode: u32,  // 0=repeat, 1=mirror, 2=blend
+    tiles_x: f32,
+    tiles_y: f32,
+    offset_x: f32,
+    offset_y: f32,
+    blend_width: f32,
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let tiles = vec2<f32>(params.tiles_x, params.tiles_y);
+    let offset = vec2<f32>(params.offset_x, params.offset_y);
+    let t = tile_uv(in.uv, tiles, offset, params.mode == 1u);
+    if (params.mode != 2u) {
+        return textureSample(input_texture, input_sampler, t);
+    }
+
+    // Blend: near each tile edge, cross-fade to a copy of the frame shifted by
+    // half a tile. The copy is continuous across the edge, so the seam fades
+    // out.
+    let shifted = fract(t + vec2<f32>(0.5));
+    let weight_x = tile_seam_weight(t.x, params.blend_width);
+    let weight_y = tile_seam_weight(t.y, params.blend_width);
+    let top = mix(
+        textureSample(input_texture, input_sampler, t),
+        textureSample(input_texture, input_sampler, vec2<f32>(shifted.x, t.y)),
+        weight_x,
+    );
+    let bottom = mix(
+        textureSample(input_texture, input_sampler, vec2<f32>(t.x, shifted.y)),
+        textureSample(input_texture, input_sampler, shifted),
+        weight_x,
+    );
+    return mix(top, bottom, weight_y);
+}
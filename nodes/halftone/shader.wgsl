@@ -0,0 +1,95 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    pattern: u32,  // 0=dots, 1=lines, 2=squares
+    colors: u32,   // 0=black and white, 1=cmyk
+    cell_size: f32,
+    angle: f32,
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+const DEGREES: f32 = 0.01745329252;
+
+fn rotate(point: vec2<f32>, angle: f32) -> vec2<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return vec2<f32>(point.x * c - point.y * s, point.x * s + point.y * c);
+}
+
+// How much of one ink covers the pixel at `pixel` (in pixels) for a screen
+// turned by `angle`. Each cell's mark is sized by how much of the ink (see
+// `ink_amount`) the input needs at the cell's center.
+fn screen(pixel: vec2<f32>, size: vec2<f32>, angle: f32, channel: u32) -> f32 {
+    let cell_size = max(params.cell_size, 2.0);
+    let rotated = rotate(pixel, -angle) / cell_size;
+    let cell = floor(rotated);
+    let local = rotated - cell - 0.5;
+
+    let center_uv = rotate((cell + 0.5) * cell_size, angle) / size;
+    let color = textureSampleLevel(input_texture, input_sampler, clamp(center_uv, vec2<f32>(0.0), vec2<f32>(1.0)), 0.0);
+    let amount = ink_amount(color.rgb, channel);
+
+    // Antialias over about one pixel.
+    let edge = 1.0 / cell_size;
+    if (params.pattern == 1u) {
+        return 1.0 - smoothstep(amount * 0.5 - edge, amount * 0.5 + edge, abs(local.y));
+    }
+    if (params.pattern == 2u) {
+        let half_side = sqrt(amount) * 0.5;
+        return 1.0 - smoothstep(half_side - edge, half_side + edge, max(abs(local.x), abs(local.y)));
+    }
+    // Dots touch their neighbors at full darkness and fill the gaps past it.
+    let radius = sqrt(amount) * 0.70710678;
+    return 1.0 - smoothstep(radius - edge, radius + edge, length(local));
+}
+
+// How much of one ink  needs: 0-3 are cyan, magenta, yellow, and black
+// separations, and 4 is the darkness for a single black screen.
+fn ink_amount(color: vec3<f32>, channel: u32) -> f32 {
+    if (channel == 4u) {
+        return 1.0 - dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    }
+    let black = 1.0 - max(color.r, max(color.g, color.b));
+    if (channel == 3u) {
+        return black;
+    }
+    let white = max(1.0 - black, 0.0001);
+    return clamp((1.0 - color[channel] - black) / white, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(input_texture));
+    let pixel = in.uv * size;
+    let alpha = textureSample(input_texture, input_sampler, in.uv).a;
+
+    if (params.colors == 0u) {
+        let ink = screen(pixel, size, params.angle, 4u);
+        return vec4<f32>(vec3<f32>(1.0 - ink), alpha);
+    }
+
+    // Each ink gets its own screen angle, as in print, so their dots don't
+    // line up into moire.
+    let cyan = screen(pixel, size, params.angle + 15.0 * DEGREES, 0u);
+    let magenta = screen(pixel, size, params.angle + 75.0 * DEGREES, 1u);
+    let yellow = screen(pixel, size, params.angle, 2u);
+    let black = screen(pixel, size, params.angle + 45.0 * DEGREES, 3u);
+    let paper = vec3<f32>(1.0 - cyan, 1.0 - magenta, 1.0 - yellow) * (1.0 - black);
+    return vec4<f32>(paper, alpha);
+}
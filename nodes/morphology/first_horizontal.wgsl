@@ -0,0 +1,9 @@
+// Pass 1: the first operation across each row of the input.
+
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return morph(input_texture, in.position.xy, vec2<i32>(1, 0), first_operation());
+}
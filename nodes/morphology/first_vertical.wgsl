@@ -0,0 +1,9 @@
+// Pass 2: the first operation down each column of pass 1.
+
+@group(0) @binding(2) var previous_texture: texture_2d<f32>;
+@group(0) @binding(3) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return morph(previous_texture, in.position.xy, vec2<i32>(0, 1), first_operation());
+}
@@ -0,0 +1,73 @@
+// Shared by every pass of the Morphology node (see "includes" in node.json).
+// A square kernel is separable, so each operation is a horizontal pass then a
+// vertical one. Erode and Dilate run once; Open erodes then dilates and Close
+// dilates then erodes, so the second pair of passes is skipped for the first
+// two.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    operation: u32,  // 0=erode, 1=dilate, 2=open, 3=close
+    kernel_size: i32,
+}
+
+// The operation the first pair of passes does: 0 to erode, 1 to dilate.
+fn first_operation() -> u32 {
+    return select(0u, 1u, params.operation == 1u || params.operation == 3u);
+}
+
+// The operation the second pair of passes does: 0 to erode, 1 to dilate, or 2
+// to pass its input through.
+fn second_operation() -> u32 {
+    switch params.operation {
+        case 2u: {
+            return 1u;
+        }
+        case 3u: {
+            return 0u;
+        }
+        default: {
+            return 2u;
+        }
+    }
+}
+
+// The minimum (erode) or maximum (dilate) of each channel over the kernel's
+// row or column through `position`, in the direction `step`. Pixels past the
+// edges are skipped, so objects touching the edge aren't eaten away.
+fn morph(source: texture_2d<f32>, position: vec2<f32>, step: vec2<i32>, operation: u32) -> vec4<f32> {
+    let center = vec2<i32>(position);
+    if operation == 2u {
+        return textureLoad(source, center, 0);
+    }
+
+    let size = vec2<i32>(textureDimensions(source));
+    let radius = max(params.kernel_size, 1) / 2;
+    var result = textureLoad(source, center, 0);
+    for (var i = -radius; i <= radius; i++) {
+        let pixel = center + step * i;
+        if any(pixel < vec2<i32>(0)) || any(pixel >= size) {
+            continue;
+        }
+        let value = textureLoad(source, pixel, 0);
+        if operation == 0u {
+            result = min(result, value);
+        } else {
+            result = max(result, value);
+        }
+    }
+    return result;
+}
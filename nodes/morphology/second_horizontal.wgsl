@@ -0,0 +1,9 @@
+// Pass 3: the second operation (if any) across each row of pass 2.
+
+@group(0) @binding(3) var previous_texture: texture_2d<f32>;
+@group(0) @binding(4) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return morph(previous_texture, in.position.xy, vec2<i32>(1, 0), second_operation());
+}
@@ -0,0 +1,9 @@
+// Final pass: the second operation (if any) down each column of pass 3.
+
+@group(0) @binding(4) var previous_texture: texture_2d<f32>;
+@group(0) @binding(5) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return morph(previous_texture, in.position.xy, vec2<i32>(0, 1), second_operation());
+}
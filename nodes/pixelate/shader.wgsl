@@ -0,0 +1,62 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    block_size: i32,
+    shape: u32,  // 0=squares, 1=circles
+    smooth_blocks: u32,
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+// Samples per side of the grid Smooth averages over each block.
+const SMOOTH_SAMPLES: i32 = 4;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(input_texture));
+    let block = f32(max(params.block_size, 1));
+    let block_uv = block / size;
+
+    // Blocks are laid out from the center of the frame so they stay put as
+    // the block size changes around it.
+    let position = (in.uv - 0.5) / block_uv;
+    let cell = floor(position);
+    let cell_center = 0.5 + (cell + 0.5) * block_uv;
+
+    var color = vec4<f32>(0.0);
+    if (params.smooth_blocks != 0u) {
+        for (var y = 0; y < SMOOTH_SAMPLES; y++) {
+            for (var x = 0; x < SMOOTH_SAMPLES; x++) {
+                let offset = (vec2<f32>(f32(x), f32(y)) + 0.5) / f32(SMOOTH_SAMPLES) - 0.5;
+                color += textureSampleLevel(input_texture, input_sampler, cell_center + offset * block_uv, 0.0);
+            }
+        }
+        color /= f32(SMOOTH_SAMPLES * SMOOTH_SAMPLES);
+    } else {
+        color = textureSampleLevel(input_texture, input_sampler, cell_center, 0.0);
+    }
+
+    if (params.shape == 1u) {
+        // Circles: one round dot per block on black, anti-aliased over a pixel.
+        let distance = length(position - cell - 0.5) * block;
+        let radius = block * 0.5;
+        let coverage = 1.0 - smoothstep(radius - 1.0, radius, distance);
+        return vec4<f32>(color.rgb * coverage, color.a);
+    }
+    return color;
+}
@@ -0,0 +1,49 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    levels: i32,
+    dither: u32,
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+// A 4x4 Bayer matrix, for ordered dithering.
+const BAYER: array<f32, 16> = array<f32, 16>(
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0,
+);
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input_texture, input_sampler, in.uv);
+    let steps = f32(max(params.levels, 2) - 1);
+
+    // Without dithering each channel rounds to the nearest level. With it,
+    // the rounding point shifts per pixel in a fixed pattern so smooth
+    // gradients turn into a mix of the two nearest levels instead of bands.
+    var bias = 0.5;
+    if (params.dither != 0u) {
+        let pixel = vec2<u32>(in.position.xy) % 4u;
+        bias = (BAYER[pixel.y * 4u + pixel.x] + 0.5) / 16.0;
+    }
+    let posterized = floor(color.rgb * steps + bias) / steps;
+
+    return vec4<f32>(clamp(posterized, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
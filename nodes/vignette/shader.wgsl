@@ -0,0 +1,50 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vid: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32((vid << 1u) & 2u);
+    let y = f32(vid & 2u);
+    out.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    out.uv = vec2<f32>(x, y);
+    return out;
+}
+
+struct Params {
+    size: f32,
+    softness: f32,
+    color_r: f32,
+    color_g: f32,
+    color_b: f32,
+    color_a: f32,
+    shape: u32,  // 0=circle, 1=fit frame
+}
+
+@group(0) @binding(0) var input_sampler: sampler;
+@group(0) @binding(1) var input_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input_texture, input_sampler, in.uv);
+
+    // Distance from the center, where 1.0 is the middle of the nearest edge
+    // (circle) or of every edge (fit frame).
+    var offset = (in.uv - 0.5) * 2.0;
+    if (params.shape == 0u) {
+        let size = vec2<f32>(textureDimensions(input_texture));
+        offset *= size / min(size.x, size.y);
+    }
+    let distance = length(offset);
+
+    // The darkening starts at Size and fades in over Softness.
+    let inner = params.size * (1.0 - params.softness);
+    let outer = max(params.size, inner + 0.0001);
+    let amount = smoothstep(inner, outer, distance) * params.color_a;
+
+    let tint = vec3<f32>(params.color_r, params.color_g, params.color_b);
+    return vec4<f32>(mix(color.rgb, tint, amount), color.a);
+}